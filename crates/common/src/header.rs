@@ -13,6 +13,8 @@ pub struct BlockHeader {
     pub strk_l1_gas_price: GasPrice,
     pub eth_l1_data_gas_price: GasPrice,
     pub strk_l1_data_gas_price: GasPrice,
+    pub eth_l2_gas_price: GasPrice,
+    pub strk_l2_gas_price: GasPrice,
     pub sequencer_address: SequencerAddress,
     pub starknet_version: StarknetVersion,
     pub class_commitment: ClassCommitment,
@@ -119,6 +121,16 @@ impl BlockHeaderBuilder {
         self
     }
 
+    pub fn eth_l2_gas_price(mut self, eth_l2_gas_price: GasPrice) -> Self {
+        self.0.eth_l2_gas_price = eth_l2_gas_price;
+        self
+    }
+
+    pub fn strk_l2_gas_price(mut self, strk_l2_gas_price: GasPrice) -> Self {
+        self.0.strk_l2_gas_price = strk_l2_gas_price;
+        self
+    }
+
     pub fn sequencer_address(mut self, sequencer_address: SequencerAddress) -> Self {
         self.0.sequencer_address = sequencer_address;
         self
@@ -240,7 +240,10 @@ impl TransactionVersion {
 #[cfg_attr(any(test, feature = "full-serde"), derive(Serialize))]
 #[serde(deny_unknown_fields)]
 pub enum BlockId {
-    #[serde(rename = "block_number")]
+    #[serde(
+        rename = "block_number",
+        deserialize_with = "BlockNumber::deserialize_lenient"
+    )]
     Number(BlockNumber),
     #[serde(rename = "block_hash")]
     Hash(BlockHash),
@@ -283,6 +286,45 @@ impl BlockNumber {
     pub fn checked_sub(&self, rhs: u64) -> Option<Self> {
         self.0.checked_sub(rhs).map(Self)
     }
+
+    /// Deserializes a [BlockNumber] from either an integer, a decimal string
+    /// or a `0x`-prefixed hex string.
+    pub fn deserialize_lenient<'de, D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct Visitor;
+
+        impl serde::de::Visitor<'_> for Visitor {
+            type Value = BlockNumber;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                formatter.write_str("a block number as an integer, decimal string or hex string")
+            }
+
+            fn visit_u64<E: serde::de::Error>(self, v: u64) -> Result<Self::Value, E> {
+                BlockNumber::deserialize_value(v)
+            }
+
+            fn visit_i64<E: serde::de::Error>(self, v: i64) -> Result<Self::Value, E> {
+                let v = u64::try_from(v)
+                    .map_err(|_| E::invalid_value(serde::de::Unexpected::Signed(v), &self))?;
+                BlockNumber::deserialize_value(v)
+            }
+
+            fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                let parsed = match v.strip_prefix("0x") {
+                    Some(hex) => u64::from_str_radix(hex, 16),
+                    None => v.parse::<u64>(),
+                };
+                let v =
+                    parsed.map_err(|_| E::invalid_value(serde::de::Unexpected::Str(v), &self))?;
+                BlockNumber::deserialize_value(v)
+            }
+        }
+
+        deserializer.deserialize_any(Visitor)
+    }
 }
 
 impl std::ops::Add<u64> for BlockNumber {
@@ -464,6 +506,7 @@ impl StarknetVersion {
     }
 
     pub const V_0_13_2: Self = Self::new(0, 13, 2, 0);
+    pub const V_0_13_4: Self = Self::new(0, 13, 4, 0);
 }
 
 impl FromStr for StarknetVersion {
@@ -1,8 +1,13 @@
 use primitive_types::H256;
 
-/// An L1 -> L2 message hash with the L1 tx hash where it was sent
-#[derive(Debug, Clone)]
+/// An L1 -> L2 message hash with the L1 tx hash where it was sent, or
+/// cancelled.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct L1ToL2MessageLog {
     pub message_hash: H256,
     pub l1_tx_hash: H256,
+    /// The L1 block containing `l1_tx_hash`.
+    pub l1_block_number: u64,
+    /// Whether the log was removed from the L1 chain by a reorg.
+    pub removed: bool,
 }
@@ -262,6 +262,44 @@ impl StateUpdate {
             })
     }
 
+    /// Combines this update with the update of a later block, resulting in the
+    /// update from this update's parent state to the later update's state.
+    ///
+    /// Changes made by both updates take the later value, even if it restores
+    /// the value this update started from.
+    pub fn squash(mut self, later: StateUpdate) -> Self {
+        for (address, update) in later.contract_updates {
+            let squashed = self.contract_updates.entry(address).or_default();
+            squashed.storage.extend(update.storage);
+            squashed.nonce = update.nonce.or(squashed.nonce);
+            squashed.class = match (squashed.class.take(), update.class) {
+                // The contract did not exist before this update, so replacing its class
+                // later is still a deployment.
+                (Some(ContractClassUpdate::Deploy(_)), Some(class)) => {
+                    Some(ContractClassUpdate::Deploy(class.class_hash()))
+                }
+                (class, later_class) => later_class.or(class),
+            };
+        }
+
+        for (address, update) in later.system_contract_updates {
+            self.system_contract_updates
+                .entry(address)
+                .or_default()
+                .storage
+                .extend(update.storage);
+        }
+
+        self.declared_cairo_classes
+            .extend(later.declared_cairo_classes);
+        self.declared_sierra_classes
+            .extend(later.declared_sierra_classes);
+
+        self.block_hash = later.block_hash;
+        self.state_commitment = later.state_commitment;
+        self
+    }
+
     pub fn compute_state_diff_commitment(&self) -> StateDiffCommitment {
         state_diff_commitment::compute(
             &self.contract_updates,
@@ -659,6 +697,43 @@ mod tests {
             .is_none());
     }
 
+    #[test]
+    fn squash() {
+        let contract = contract_address_bytes!(b"contract");
+        let deployed = contract_address_bytes!(b"deployed");
+        let key = storage_address_bytes!(b"key");
+        let other_key = storage_address_bytes!(b"other key");
+
+        let earlier = StateUpdate::default()
+            .with_parent_state_commitment(state_commitment_bytes!(b"root 0"))
+            .with_state_commitment(state_commitment_bytes!(b"root 1"))
+            .with_storage_update(contract, key, storage_value_bytes!(b"value 1"))
+            .with_storage_update(contract, other_key, storage_value_bytes!(b"other value"))
+            .with_contract_nonce(contract, contract_nonce!("0x1"))
+            .with_deployed_contract(deployed, class_hash_bytes!(b"class 1"))
+            .with_declared_cairo_class(class_hash_bytes!(b"cairo class"));
+        let later = StateUpdate::default()
+            .with_block_hash(block_hash_bytes!(b"block 2"))
+            .with_parent_state_commitment(state_commitment_bytes!(b"root 1"))
+            .with_state_commitment(state_commitment_bytes!(b"root 2"))
+            .with_storage_update(contract, key, storage_value_bytes!(b"value 2"))
+            .with_replaced_class(deployed, class_hash_bytes!(b"class 2"))
+            .with_declared_sierra_class(sierra_hash_bytes!(b"sierra"), casm_hash_bytes!(b"casm"));
+
+        let expected = StateUpdate::default()
+            .with_block_hash(block_hash_bytes!(b"block 2"))
+            .with_parent_state_commitment(state_commitment_bytes!(b"root 0"))
+            .with_state_commitment(state_commitment_bytes!(b"root 2"))
+            .with_storage_update(contract, key, storage_value_bytes!(b"value 2"))
+            .with_storage_update(contract, other_key, storage_value_bytes!(b"other value"))
+            .with_contract_nonce(contract, contract_nonce!("0x1"))
+            .with_deployed_contract(deployed, class_hash_bytes!(b"class 2"))
+            .with_declared_cairo_class(class_hash_bytes!(b"cairo class"))
+            .with_declared_sierra_class(sierra_hash_bytes!(b"sierra"), casm_hash_bytes!(b"casm"));
+
+        assert_eq!(earlier.squash(later), expected);
+    }
+
     /// Source:
     /// https://github.com/starkware-libs/starknet-api/blob/5565e5282f5fead364a41e49c173940fd83dee00/src/block_hash/state_diff_hash_test.rs#L14
     #[test]
@@ -131,6 +131,27 @@ impl TransactionVariant {
         }
     }
 
+    /// The account which sent the transaction. Deploy and L1 handler
+    /// transactions are not sent by an account, and neither are `v0` declare
+    /// transactions whose sender address is the `0x1` placeholder rather than
+    /// an account.
+    pub fn sender_address(&self) -> Option<ContractAddress> {
+        match self {
+            TransactionVariant::DeclareV0(_) => None,
+            TransactionVariant::DeclareV1(tx) => Some(tx.sender_address),
+            TransactionVariant::DeclareV2(tx) => Some(tx.sender_address),
+            TransactionVariant::DeclareV3(tx) => Some(tx.sender_address),
+            TransactionVariant::DeployV0(_) => None,
+            TransactionVariant::DeployV1(_) => None,
+            TransactionVariant::DeployAccountV1(tx) => Some(tx.contract_address),
+            TransactionVariant::DeployAccountV3(tx) => Some(tx.contract_address),
+            TransactionVariant::InvokeV0(tx) => Some(tx.sender_address),
+            TransactionVariant::InvokeV1(tx) => Some(tx.sender_address),
+            TransactionVariant::InvokeV3(tx) => Some(tx.sender_address),
+            TransactionVariant::L1Handler(_) => None,
+        }
+    }
+
     /// Some variants had a different hash calculations for blocks around
     /// Starknet v0.8 and earlier. The hash excluded the transaction version
     /// and nonce.
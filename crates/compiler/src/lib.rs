@@ -68,6 +68,17 @@ fn parse_sierra_version(program: &serde_json::value::RawValue) -> anyhow::Result
     }
 }
 
+/// Returns the Sierra version of a Sierra class definition, formatted as
+/// `major.minor.patch`.
+pub fn sierra_version(sierra_definition: &[u8]) -> anyhow::Result<String> {
+    let definition = serde_json::from_slice::<FeederGatewayContractClass<'_>>(sierra_definition)
+        .context("Parsing Sierra class")?;
+    let SierraVersion(major, minor, patch) =
+        parse_sierra_version(definition.sierra_program).context("Parsing Sierra version")?;
+
+    Ok(format!("{major}.{minor}.{patch}"))
+}
+
 /// Parse CASM class definition and return CASM class hash.
 ///
 /// Uses the _latest_ compiler for the parsing and calculation.
@@ -36,11 +36,17 @@ pub mod core_addr {
         Decoder::Hex.decode(b"4737c0c1B4D5b1A687B42610DdabEE781152359c");
 }
 
+/// The number of Ethereum blocks covered by a single `eth_getLogs` request
+/// when backfilling state updates. Providers commonly limit the block range of
+/// a single request.
+const LOG_BACKFILL_CHUNK_SIZE: u64 = 10_000;
+
 /// Events that can be emitted by the Ethereum client
 #[derive(Debug)]
 pub enum EthereumEvent {
     StateUpdate(EthereumStateUpdate),
     MessageLog(L1ToL2MessageLog),
+    MessageCancelled(L1ToL2MessageLog),
 }
 
 /// State update from Ethereum
@@ -56,6 +62,15 @@ pub struct EthereumStateUpdate {
 pub trait EthereumApi {
     async fn get_starknet_state(&self, address: &H160) -> anyhow::Result<EthereumStateUpdate>;
     async fn get_chain(&self) -> anyhow::Result<EthereumChain>;
+    async fn backfill_state_updates<F, Fut>(
+        &self,
+        address: &H160,
+        stop_at: BlockNumber,
+        callback: F,
+    ) -> anyhow::Result<()>
+    where
+        F: Fn(Vec<EthereumStateUpdate>) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static;
     async fn listen<F, Fut>(
         &mut self,
         address: &H160,
@@ -138,6 +153,12 @@ impl EthereumApi for EthereumClient {
             .await?
             .into_stream();
 
+        // Listen for L1 to L2 message cancellation events
+        let mut cancellations = provider
+            .subscribe_logs(&core_contract.MessageToL2Canceled_filter().filter)
+            .await?
+            .into_stream();
+
         // Listen for state update events
         let mut state_updates = provider
             .subscribe_logs(&core_contract.LogStateUpdate_filter().filter)
@@ -189,10 +210,22 @@ impl EthereumApi for EthereumClient {
                     let msg = L1ToL2MessageLog {
                         message_hash: H256::from(log.inner.message_hash().to_be_bytes()),
                         l1_tx_hash: log.transaction_hash.map(|hash| H256::from(hash.0)).unwrap_or_default(),
+                        l1_block_number: log.block_number.unwrap_or_default(),
+                        removed: log.removed,
                     };
                     // Emit the message log
                     callback(EthereumEvent::MessageLog(msg)).await;
                 }
+                Some(log) = cancellations.next() => {
+                    let log: Log<StarknetCoreContract::MessageToL2Canceled> = log.log_decode()?;
+                    let msg = L1ToL2MessageLog {
+                        message_hash: H256::from(log.inner.message_hash().to_be_bytes()),
+                        l1_tx_hash: log.transaction_hash.map(|hash| H256::from(hash.0)).unwrap_or_default(),
+                        l1_block_number: log.block_number.unwrap_or_default(),
+                        removed: log.removed,
+                    };
+                    callback(EthereumEvent::MessageCancelled(msg)).await;
+                }
                 Some(block_number) = finalized_block_rx.recv() => {
                     // Collect all state updates up to (and including) the finalized block
                     let pending_state_updates: Vec<EthereumStateUpdate> = self.pending_state_updates
@@ -238,6 +271,79 @@ impl EthereumApi for EthereumClient {
         })
     }
 
+    /// Fetches the finalized state updates of the Starknet core contract and
+    /// passes them to the caller using the provided callback.
+    ///
+    /// `LogStateUpdate` events are queried in chunks going backwards from the
+    /// finalized Ethereum block, until the state update for Starknet block
+    /// `stop_at` or older has been found. The callback is invoked once per
+    /// chunk as soon as it has been fetched, so the chunks arrive newest first
+    /// while the updates within a chunk are ordered oldest first.
+    async fn backfill_state_updates<F, Fut>(
+        &self,
+        address: &H160,
+        stop_at: BlockNumber,
+        callback: F,
+    ) -> anyhow::Result<()>
+    where
+        F: Fn(Vec<EthereumStateUpdate>) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        // Create a WebSocket connection
+        let ws = WsConnect::new(self.url.clone());
+        let provider = ProviderBuilder::new().on_ws(ws).await?;
+
+        // Create the StarknetCoreContract instance
+        let address = Address::new((*address).into());
+        let core_contract = StarknetCoreContract::new(address, provider.clone());
+        let filter = core_contract.LogStateUpdate_filter().filter;
+
+        let finalized = provider
+            .get_block_by_number(BlockNumberOrTag::Finalized, false)
+            .await?
+            .context("Failed to fetch finalized block")?
+            .header
+            .number;
+
+        let mut to = finalized;
+        loop {
+            let from = to.saturating_sub(LOG_BACKFILL_CHUNK_SIZE - 1);
+            tracing::debug!(%from, %to, "Fetching L1 state update logs");
+
+            let logs = provider
+                .get_logs(&filter.clone().from_block(from).to_block(to))
+                .await
+                .with_context(|| format!("Fetching state update logs for blocks {from}-{to}"))?;
+
+            let mut done = from == 0;
+            let mut state_updates = BTreeMap::new();
+            for log in logs {
+                let log: Log<StarknetCoreContract::LogStateUpdate> = log.log_decode()?;
+                let block_number = get_block_number(log.inner.blockNumber);
+                done |= block_number <= stop_at;
+                state_updates.insert(
+                    block_number,
+                    EthereumStateUpdate {
+                        block_number,
+                        block_hash: get_block_hash(log.inner.blockHash),
+                        state_root: get_state_root(log.inner.globalRoot),
+                    },
+                );
+            }
+
+            if !state_updates.is_empty() {
+                callback(state_updates.into_values().collect()).await;
+            }
+
+            if done {
+                break;
+            }
+            to = from - 1;
+        }
+
+        Ok(())
+    }
+
     /// Get the Ethereum chain
     async fn get_chain(&self) -> anyhow::Result<EthereumChain> {
         // Create a WebSocket connection
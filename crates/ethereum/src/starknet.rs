@@ -7,23 +7,51 @@ alloy::sol!(
 
 impl StarknetCoreContract::LogMessageToL2 {
     pub fn message_hash(&self) -> alloy::primitives::U256 {
-        let mut hash = alloy::primitives::Keccak256::new();
+        message_hash(
+            self.fromAddress,
+            self.toAddress,
+            self.nonce,
+            self.selector,
+            &self.payload,
+        )
+    }
+}
 
-        // This is an ethereum address: pad the 160 bits to 32 bytes to match a felt.
-        hash.update([0u8; 12]);
-        hash.update(self.fromAddress);
-        hash.update(self.toAddress.to_be_bytes::<32>());
-        hash.update(self.nonce.to_be_bytes::<32>());
-        hash.update(self.selector.to_be_bytes::<32>());
+impl StarknetCoreContract::MessageToL2Canceled {
+    pub fn message_hash(&self) -> alloy::primitives::U256 {
+        message_hash(
+            self.fromAddress,
+            self.toAddress,
+            self.nonce,
+            self.selector,
+            &self.payload,
+        )
+    }
+}
+
+fn message_hash(
+    from_address: alloy::primitives::Address,
+    to_address: alloy::primitives::U256,
+    nonce: alloy::primitives::U256,
+    selector: alloy::primitives::U256,
+    payload: &[alloy::primitives::U256],
+) -> alloy::primitives::U256 {
+    let mut hash = alloy::primitives::Keccak256::new();
 
-        // Pad the u64 to 32 bytes to match a felt.
-        hash.update([0u8; 24]);
-        hash.update((self.payload.len() as u64).to_be_bytes());
+    // This is an ethereum address: pad the 160 bits to 32 bytes to match a felt.
+    hash.update([0u8; 12]);
+    hash.update(from_address);
+    hash.update(to_address.to_be_bytes::<32>());
+    hash.update(nonce.to_be_bytes::<32>());
+    hash.update(selector.to_be_bytes::<32>());
 
-        for elem in &self.payload {
-            hash.update(elem.to_be_bytes::<32>());
-        }
+    // Pad the u64 to 32 bytes to match a felt.
+    hash.update([0u8; 24]);
+    hash.update((payload.len() as u64).to_be_bytes());
 
-        hash.finalize().into()
+    for elem in payload {
+        hash.update(elem.to_be_bytes::<32>());
     }
+
+    hash.finalize().into()
 }
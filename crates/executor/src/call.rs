@@ -1,7 +1,8 @@
 use std::sync::Arc;
 
-use blockifier::context::TransactionContext;
+use blockifier::context::{BlockContext, TransactionContext};
 use blockifier::execution::entry_point::{CallEntryPoint, EntryPointExecutionContext};
+use blockifier::state::cached_state::CachedState;
 use blockifier::state::state_api::StateReader;
 use blockifier::transaction::objects::{DeprecatedTransactionInfo, TransactionInfo};
 use blockifier::versioned_constants::VersionedConstants;
@@ -10,7 +11,7 @@ use pathfinder_common::{CallParam, CallResultValue, ContractAddress, EntryPoint}
 use starknet_api::core::PatriciaKey;
 
 use super::error::CallError;
-use super::execution_state::ExecutionState;
+use super::execution_state::{ExecutionLimits, ExecutionState};
 use super::felt::{IntoFelt, IntoStarkFelt};
 
 pub fn call(
@@ -19,8 +20,57 @@ pub fn call(
     entry_point_selector: EntryPoint,
     calldata: Vec<CallParam>,
 ) -> Result<Vec<CallResultValue>, CallError> {
+    let limits = execution_state.limits;
     let (mut state, block_context) = execution_state.starknet_state()?;
 
+    execute(
+        &mut state,
+        block_context,
+        limits,
+        contract_address,
+        entry_point_selector,
+        calldata,
+    )
+}
+
+/// Executes each of `calls` on top of the same state, returning their results
+/// in order.
+///
+/// The state is only set up once and its caches are shared by the calls, which
+/// makes this much cheaper than a [call] per function. Storage written by a
+/// call is visible to the calls after it.
+pub fn call_many(
+    execution_state: ExecutionState<'_>,
+    calls: Vec<(ContractAddress, EntryPoint, Vec<CallParam>)>,
+) -> Result<Vec<Result<Vec<CallResultValue>, CallError>>, CallError> {
+    let limits = execution_state.limits;
+    let (mut state, block_context) = execution_state.starknet_state()?;
+
+    let results = calls
+        .into_iter()
+        .map(|(contract_address, entry_point_selector, calldata)| {
+            execute(
+                &mut state,
+                block_context.clone(),
+                limits,
+                contract_address,
+                entry_point_selector,
+                calldata,
+            )
+        })
+        .collect();
+
+    Ok(results)
+}
+
+fn execute<S: StateReader>(
+    state: &mut CachedState<S>,
+    block_context: BlockContext,
+    limits: ExecutionLimits,
+    contract_address: ContractAddress,
+    entry_point_selector: EntryPoint,
+    calldata: Vec<CallParam>,
+) -> Result<Vec<CallResultValue>, CallError> {
     let contract_address = starknet_api::core::ContractAddress(PatriciaKey::try_from(
         contract_address.0.into_starkfelt(),
     )?);
@@ -37,7 +87,10 @@ pub fn call(
         entry_point_type: starknet_api::deprecated_contract_class::EntryPointType::External,
         entry_point_selector,
         calldata: starknet_api::transaction::Calldata(Arc::new(calldata)),
-        initial_gas: VersionedConstants::latest_constants().tx_initial_gas(),
+        initial_gas: limits
+            .max_gas
+            .map_or(u64::MAX, |max_gas| max_gas.get())
+            .min(VersionedConstants::latest_constants().tx_initial_gas()),
         call_type: blockifier::execution::entry_point::CallType::Call,
         ..Default::default()
     };
@@ -52,14 +105,23 @@ pub fn call(
     )?;
 
     let call_info = call_entry_point
-        .execute(&mut state, &mut resources, &mut context)
+        .execute(state, &mut resources, &mut context)
         .map_err(|e| {
-            CallError::from_entry_point_execution_error(
+            let error = CallError::from_entry_point_execution_error(
                 e,
                 &contract_address,
                 &class_hash,
                 &entry_point_selector,
-            )
+            );
+            match error {
+                CallError::ContractError(error, error_stack, _)
+                    if limits.exceeded_by(&format!("{error:#}"))
+                        || error_stack.exceeds(&limits) =>
+                {
+                    CallError::ExecutionResourcesExceeded
+                }
+                other => other,
+            }
         })?;
 
     let result = call_info
@@ -0,0 +1,145 @@
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use blockifier::execution::contract_class::ContractClass;
+use cached::{Cached, SizedCache};
+use pathfinder_common::BlockHash;
+use starknet_api::core::ClassHash as StarknetClassHash;
+
+use crate::lru_cache::CacheStats;
+
+/// Classes loaded while executing on top of a block's state, keyed by block
+/// hash.
+///
+/// Consecutive calls on top of the same block reuse the deserialized classes
+/// instead of reading and parsing their definitions from the database again.
+/// Since a block hash identifies the state, cached classes never become stale,
+/// not even on reorgs.
+#[derive(Clone)]
+pub struct ClassCache {
+    blocks: Arc<Mutex<SizedCache<BlockHash, BlockClasses>>>,
+    lookups: Arc<Lookups>,
+}
+
+/// The classes loaded on top of a single block, holding at most
+/// [BlockClasses::SIZE] of the most recently used ones like the global class
+/// cache.
+#[derive(Clone)]
+pub(crate) struct BlockClasses {
+    classes: Arc<Mutex<SizedCache<StarknetClassHash, ContractClass>>>,
+    lookups: Arc<Lookups>,
+}
+
+/// Class lookups across all blocks, including those since evicted.
+#[derive(Default)]
+struct Lookups {
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl ClassCache {
+    /// The number of blocks cached by [ClassCache::default].
+    pub const DEFAULT_SIZE: NonZeroUsize = match NonZeroUsize::new(16) {
+        Some(size) => size,
+        None => unreachable!(),
+    };
+
+    /// Creates a cache holding the classes of up to `size` blocks.
+    pub fn with_size(size: NonZeroUsize) -> Self {
+        Self {
+            blocks: Arc::new(Mutex::new(SizedCache::with_size(size.get()))),
+            lookups: Default::default(),
+        }
+    }
+
+    pub(crate) fn for_block(&self, block_hash: BlockHash) -> BlockClasses {
+        self.blocks
+            .lock()
+            .unwrap()
+            .cache_get_or_set_with(block_hash, || BlockClasses {
+                classes: Arc::new(Mutex::new(SizedCache::with_size(BlockClasses::SIZE))),
+                lookups: self.lookups.clone(),
+            })
+            .clone()
+    }
+
+    /// The number of class lookups which found, or did not find, a class
+    /// loaded on top of the same block.
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.lookups.hits.load(Ordering::Relaxed),
+            misses: self.lookups.misses.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl Default for ClassCache {
+    fn default() -> Self {
+        Self::with_size(Self::DEFAULT_SIZE)
+    }
+}
+
+impl BlockClasses {
+    /// The number of classes cached per block.
+    const SIZE: usize = 128;
+
+    pub fn get(&self, class_hash: &StarknetClassHash) -> Option<ContractClass> {
+        let class = self.classes.lock().unwrap().cache_get(class_hash).cloned();
+
+        let counter = match class {
+            Some(_) => &self.lookups.hits,
+            None => &self.lookups.misses,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+
+        class
+    }
+
+    pub fn insert(&self, class_hash: StarknetClassHash, class: ContractClass) {
+        self.classes.lock().unwrap().cache_set(class_hash, class);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::macro_prelude::*;
+
+    use super::*;
+
+    fn same(a: &BlockClasses, b: &BlockClasses) -> bool {
+        Arc::ptr_eq(&a.classes, &b.classes)
+    }
+
+    #[test]
+    fn classes_are_shared_per_block() {
+        let cache = ClassCache::with_size(NonZeroUsize::new(2).unwrap());
+
+        let first = cache.for_block(block_hash!("0x1"));
+
+        assert!(same(&first, &cache.for_block(block_hash!("0x1"))));
+        assert!(!same(&first, &cache.for_block(block_hash!("0x2"))));
+    }
+
+    #[test]
+    fn least_recently_used_block_is_evicted() {
+        let cache = ClassCache::with_size(NonZeroUsize::new(1).unwrap());
+
+        let first = cache.for_block(block_hash!("0x1"));
+        cache.for_block(block_hash!("0x2"));
+
+        assert!(!same(&first, &cache.for_block(block_hash!("0x1"))));
+    }
+
+    #[test]
+    fn lookups_are_counted_across_blocks() {
+        let cache = ClassCache::with_size(NonZeroUsize::new(1).unwrap());
+        let class_hash = StarknetClassHash(Default::default());
+
+        cache.for_block(block_hash!("0x1")).get(&class_hash);
+        // Evicts the first block.
+        cache.for_block(block_hash!("0x2")).get(&class_hash);
+
+        assert_eq!(cache.stats(), CacheStats { hits: 0, misses: 2 });
+    }
+}
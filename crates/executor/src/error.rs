@@ -1,3 +1,4 @@
+use blockifier::blockifier::stateful_validator::StatefulValidatorError;
 use blockifier::execution::errors::{
     ConstructorEntryPointExecutionError,
     EntryPointExecutionError as BlockifierEntryPointExecutionError,
@@ -5,15 +6,74 @@ use blockifier::execution::errors::{
 };
 use blockifier::execution::stack_trace::gen_transaction_execution_error_trace;
 use blockifier::state::errors::StateError;
-use blockifier::transaction::errors::TransactionExecutionError as BlockifierTransactionExecutionError;
+use blockifier::transaction::errors::{
+    TransactionExecutionError as BlockifierTransactionExecutionError,
+    TransactionFeeError,
+    TransactionPreValidationError,
+};
 
 use crate::error_stack::ErrorStack;
+use crate::ExecutionLimits;
+
+/// What made execution fail, so that clients can tell failures apart without
+/// parsing error messages.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FailureReason {
+    /// The contract reverted or otherwise failed while executing.
+    Revert,
+    /// The called entry point doesn't exist in the contract's class.
+    EntryPointNotFound,
+    /// The account's `__validate__` entry point failed.
+    ValidationFailure,
+}
+
+impl FailureReason {
+    /// `error_stack` must be the stack of `error`, which reveals the errors of
+    /// nested calls.
+    pub(crate) fn of(
+        error: &BlockifierTransactionExecutionError,
+        error_stack: &ErrorStack,
+    ) -> Self {
+        use BlockifierTransactionExecutionError::*;
+
+        match error {
+            ValidateTransactionError { .. } => Self::ValidationFailure,
+            ContractConstructorExecutionFailed(
+                ConstructorEntryPointExecutionError::ExecutionError { error, .. },
+            )
+            | ExecutionError { error, .. }
+                if matches!(
+                    error,
+                    BlockifierEntryPointExecutionError::PreExecutionError(
+                        PreExecutionError::EntryPointNotFound(_)
+                    )
+                ) =>
+            {
+                Self::EntryPointNotFound
+            }
+            _ if error_stack.entry_point_not_found() => Self::EntryPointNotFound,
+            _ => Self::Revert,
+        }
+    }
+}
+
+/// Whether `error` is the message with which a call fails if its entry point
+/// doesn't exist, from the blockifier for Cairo 0 classes and from the Sierra
+/// contract's panic for Sierra classes.
+pub(crate) fn entry_point_not_found(error: &str) -> bool {
+    const NOT_FOUND: &str = "not found in contract";
+    const SIERRA_NOT_FOUND: &str = "ENTRYPOINT_NOT_FOUND";
+
+    error.contains(NOT_FOUND) || error.contains(SIERRA_NOT_FOUND)
+}
 
 #[derive(Debug)]
 pub enum CallError {
     ContractNotFound,
-    InvalidMessageSelector,
-    ContractError(anyhow::Error, ErrorStack),
+    ContractError(anyhow::Error, ErrorStack, FailureReason),
+    /// Execution ran out of the steps or gas allowed by its
+    /// [ExecutionLimits](crate::ExecutionLimits).
+    ExecutionResourcesExceeded,
     Internal(anyhow::Error),
     Custom(anyhow::Error),
 }
@@ -22,39 +82,21 @@ impl From<BlockifierTransactionExecutionError> for CallError {
     fn from(value: BlockifierTransactionExecutionError) -> Self {
         use BlockifierTransactionExecutionError::*;
 
-        let error_stack = gen_transaction_execution_error_trace(&value);
+        let error_stack = ErrorStack::from(gen_transaction_execution_error_trace(&value));
+        let reason = FailureReason::of(&value, &error_stack);
 
         match value {
             ContractConstructorExecutionFailed(
                 ConstructorEntryPointExecutionError::ExecutionError { error, .. },
-            ) => match error {
-                BlockifierEntryPointExecutionError::PreExecutionError(
-                    PreExecutionError::EntryPointNotFound(_),
-                ) => Self::InvalidMessageSelector,
-                BlockifierEntryPointExecutionError::PreExecutionError(
-                    PreExecutionError::UninitializedStorageAddress(_),
-                ) => Self::ContractNotFound,
-                _ => Self::ContractError(error.into(), error_stack.into()),
-            },
-            ExecutionError { error, .. } => match error {
-                BlockifierEntryPointExecutionError::PreExecutionError(
-                    PreExecutionError::EntryPointNotFound(_),
-                ) => Self::InvalidMessageSelector,
-                BlockifierEntryPointExecutionError::PreExecutionError(
-                    PreExecutionError::UninitializedStorageAddress(_),
-                ) => Self::ContractNotFound,
-                _ => Self::ContractError(error.into(), error_stack.into()),
-            },
-            ValidateTransactionError { error, .. } => match error {
-                BlockifierEntryPointExecutionError::PreExecutionError(
-                    PreExecutionError::EntryPointNotFound(_),
-                ) => Self::InvalidMessageSelector,
+            )
+            | ExecutionError { error, .. }
+            | ValidateTransactionError { error, .. } => match error {
                 BlockifierEntryPointExecutionError::PreExecutionError(
                     PreExecutionError::UninitializedStorageAddress(_),
                 ) => Self::ContractNotFound,
-                _ => Self::ContractError(error.into(), error_stack.into()),
+                _ => Self::ContractError(error.into(), error_stack, reason),
             },
-            e => Self::ContractError(e.into(), error_stack.into()),
+            e => Self::ContractError(e.into(), error_stack, reason),
         }
     }
 }
@@ -103,6 +145,12 @@ pub enum TransactionExecutionError {
         transaction_index: usize,
         error: String,
         error_stack: ErrorStack,
+        reason: FailureReason,
+    },
+    /// Execution ran out of the steps or gas allowed by its
+    /// [ExecutionLimits](crate::ExecutionLimits).
+    ExecutionResourcesExceeded {
+        transaction_index: usize,
     },
     Internal(anyhow::Error),
     Custom(anyhow::Error),
@@ -131,20 +179,161 @@ impl From<anyhow::Error> for TransactionExecutionError {
 
 impl TransactionExecutionError {
     pub fn new(transaction_index: usize, error: BlockifierTransactionExecutionError) -> Self {
-        let error_stack = gen_transaction_execution_error_trace(&error);
+        let error_stack = ErrorStack::from(gen_transaction_execution_error_trace(&error));
+        let reason = FailureReason::of(&error, &error_stack);
 
         Self::ExecutionError {
             transaction_index,
             error: error.to_string(),
-            error_stack: error_stack.into(),
+            error_stack,
+            reason,
+        }
+    }
+
+    /// Whether execution failed because it ran out of the resources restricted
+    /// by `limits`.
+    pub(crate) fn exceeds(&self, limits: &ExecutionLimits) -> bool {
+        match self {
+            Self::ExecutionError {
+                error, error_stack, ..
+            } => limits.exceeded_by(error) || error_stack.exceeds(limits),
+            _ => false,
+        }
+    }
+}
+
+/// Reasons for which [validate](crate::validate) rejects a transaction.
+#[derive(Debug)]
+pub enum ValidationError {
+    InvalidNonce,
+    InsufficientMaxFee,
+    InsufficientAccountBalance,
+    /// The account's `__validate__` entry point, or another check, failed.
+    ValidationFailure(String),
+    Internal(anyhow::Error),
+}
+
+impl From<StatefulValidatorError> for ValidationError {
+    fn from(value: StatefulValidatorError) -> Self {
+        match value {
+            StatefulValidatorError::StateError(e) => e.into(),
+            StatefulValidatorError::TransactionPreValidationError(e) => e.into(),
+            StatefulValidatorError::TransactionExecutionError(e) => e.into(),
+            error => Self::ValidationFailure(error.to_string()),
         }
     }
 }
 
+impl From<TransactionPreValidationError> for ValidationError {
+    fn from(value: TransactionPreValidationError) -> Self {
+        use TransactionFeeError::*;
+
+        match value {
+            TransactionPreValidationError::InvalidNonce { .. } => Self::InvalidNonce,
+            TransactionPreValidationError::TransactionFeeError(
+                MaxFeeExceedsBalance { .. } | L1GasBoundsExceedBalance { .. },
+            ) => Self::InsufficientAccountBalance,
+            TransactionPreValidationError::TransactionFeeError(
+                MaxFeeTooLow { .. } | MaxL1GasAmountTooLow { .. } | MaxL1GasPriceTooLow { .. },
+            ) => Self::InsufficientMaxFee,
+            TransactionPreValidationError::StateError(e) => e.into(),
+            error => Self::ValidationFailure(error.to_string()),
+        }
+    }
+}
+
+impl From<BlockifierTransactionExecutionError> for ValidationError {
+    fn from(value: BlockifierTransactionExecutionError) -> Self {
+        match value {
+            BlockifierTransactionExecutionError::TransactionPreValidationError(error) => {
+                error.into()
+            }
+            error => Self::ValidationFailure(error.to_string()),
+        }
+    }
+}
+
+impl From<StateError> for ValidationError {
+    fn from(e: StateError) -> Self {
+        match e {
+            StateError::StateReadError(_) => Self::Internal(e.into()),
+            _ => Self::ValidationFailure(format!("State error: {e}")),
+        }
+    }
+}
+
+impl From<anyhow::Error> for ValidationError {
+    fn from(value: anyhow::Error) -> Self {
+        Self::Internal(value)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn resources_exhausted() {
+        const STEPS: &str =
+            "Could not reach the end of the program. RunResources has no remaining steps.";
+        const GAS: &str =
+            "Execution failed. Failure reason: 0x4f7574206f6620676173 ('Out of gas').";
+        const OTHER: &str = "Execution failed. Failure reason: 0x496e76616c6964 ('Invalid').";
+
+        let steps = ExecutionLimits {
+            max_steps: Some(std::num::NonZeroU32::new(10).unwrap()),
+            max_gas: None,
+        };
+        assert!(steps.exceeded_by(STEPS));
+        assert!(!steps.exceeded_by(GAS));
+        assert!(!steps.exceeded_by(OTHER));
+
+        let gas = ExecutionLimits {
+            max_steps: None,
+            max_gas: Some(std::num::NonZeroU64::new(10).unwrap()),
+        };
+        assert!(!gas.exceeded_by(STEPS));
+        assert!(gas.exceeded_by(GAS));
+
+        // Running out of the resources allowed by the protocol is an ordinary failure.
+        assert!(!ExecutionLimits::default().exceeded_by(STEPS));
+        assert!(!ExecutionLimits::default().exceeded_by(GAS));
+    }
+
+    #[test]
+    fn nested_entry_point_not_found() {
+        use blockifier::execution::errors::EntryPointExecutionError;
+
+        use crate::error_stack::Frame;
+
+        let error = BlockifierTransactionExecutionError::ExecutionError {
+            error: EntryPointExecutionError::RecursionDepthExceeded,
+            class_hash: Default::default(),
+            storage_address: Default::default(),
+            selector: Default::default(),
+        };
+        let stack = |message: &str| ErrorStack(vec![Frame::StringFrame(message.to_owned())]);
+
+        assert_eq!(
+            FailureReason::of(
+                &error,
+                &stack("Entry point EntryPointSelector(0x1) not found in contract.")
+            ),
+            FailureReason::EntryPointNotFound
+        );
+        assert_eq!(
+            FailureReason::of(
+                &error,
+                &stack("Execution failed. Failure reason: 0x454e545259504f494e545f4e4f545f464f554e44 ('ENTRYPOINT_NOT_FOUND').")
+            ),
+            FailureReason::EntryPointNotFound
+        );
+        assert_eq!(
+            FailureReason::of(&error, &stack("Execution failed. Failure reason: 0x1.")),
+            FailureReason::Revert
+        );
+    }
+
     mod transaction_errors_are_mapped_correctly {
         //! Some variants in the blockifier are opaque and omit the inner
         //! error's data. We've patched this manually and this tests
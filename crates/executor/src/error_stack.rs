@@ -10,6 +10,26 @@ use crate::IntoFelt;
 #[derive(Clone, Debug, Default)]
 pub struct ErrorStack(pub Vec<Frame>);
 
+impl ErrorStack {
+    /// Whether a call, possibly a nested one, failed because the entry point
+    /// it called doesn't exist.
+    pub(crate) fn entry_point_not_found(&self) -> bool {
+        self.0.iter().any(|frame| match frame {
+            Frame::StringFrame(string) => crate::error::entry_point_not_found(string),
+            Frame::CallFrame(_) => false,
+        })
+    }
+
+    /// Whether execution failed because it ran out of the resources restricted
+    /// by `limits`.
+    pub(crate) fn exceeds(&self, limits: &crate::ExecutionLimits) -> bool {
+        self.0.iter().any(|frame| match frame {
+            Frame::StringFrame(string) => limits.exceeded_by(string),
+            Frame::CallFrame(_) => false,
+        })
+    }
+}
+
 impl From<BlockifierErrorStack> for ErrorStack {
     fn from(value: BlockifierErrorStack) -> Self {
         Self(value.stack.into_iter().map(Into::into).collect())
@@ -1,8 +1,8 @@
 use blockifier::transaction::transaction_execution::Transaction;
 use blockifier::transaction::transactions::ExecutableTransaction;
 
-use super::error::TransactionExecutionError;
-use super::execution_state::ExecutionState;
+use super::error::{FailureReason, TransactionExecutionError};
+use super::execution_state::{ExecutionLimits, ExecutionState};
 use super::types::FeeEstimate;
 
 pub fn estimate(
@@ -11,6 +11,12 @@ pub fn estimate(
     skip_validate: bool,
 ) -> Result<Vec<FeeEstimate>, TransactionExecutionError> {
     let block_number = execution_state.header.number;
+    // Transactions run out of gas according to their own resource bounds, which
+    // is an ordinary revert.
+    let limits = ExecutionLimits {
+        max_gas: None,
+        ..execution_state.limits
+    };
 
     let (mut state, block_context) = execution_state.starknet_state()?;
 
@@ -39,10 +45,16 @@ pub fn estimate(
                 if let Some(revert_error) = tx_info.revert_error {
                     let revert_string = revert_error.to_string();
                     tracing::debug!(revert_error=%revert_string, "Transaction reverted");
+                    if limits.exceeded_by(&revert_string) {
+                        return Err(TransactionExecutionError::ExecutionResourcesExceeded {
+                            transaction_index: transaction_idx,
+                        });
+                    }
                     return Err(TransactionExecutionError::ExecutionError {
                         transaction_index: transaction_idx,
                         error: revert_string,
                         error_stack: revert_error.into(),
+                        reason: FailureReason::Revert,
                     });
                 }
 
@@ -57,7 +69,13 @@ pub fn estimate(
             }
             Err(error) => {
                 tracing::debug!(%error, %transaction_idx, "Transaction estimation failed");
-                return Err(TransactionExecutionError::new(transaction_idx, error));
+                let error = TransactionExecutionError::new(transaction_idx, error);
+                if error.exceeds(&limits) {
+                    return Err(TransactionExecutionError::ExecutionResourcesExceeded {
+                        transaction_index: transaction_idx,
+                    });
+                }
+                return Err(error);
             }
         }
     }
@@ -1,3 +1,4 @@
+use std::num::{NonZeroU32, NonZeroU64};
 use std::sync::Arc;
 
 use anyhow::Context;
@@ -16,6 +17,7 @@ use pathfinder_common::{
 };
 use starknet_api::core::PatriciaKey;
 
+use super::class_cache::ClassCache;
 use super::pending::PendingStateReader;
 use super::state_reader::PathfinderStateReader;
 use crate::IntoStarkFelt;
@@ -101,15 +103,58 @@ pub struct ExecutionState<'tx> {
     pub header: BlockHeader,
     execute_on_parent_state: bool,
     pending_state: Option<Arc<StateUpdate>>,
+    state_overrides: Option<Arc<StateUpdate>>,
     allow_use_kzg_data: bool,
     custom_versioned_constants: Option<VersionedConstants>,
+    pub(crate) limits: ExecutionLimits,
+    class_cache: Option<ClassCache>,
+    stored_block_hashes: bool,
+}
+
+/// Upper bounds on the resources used by execution, on top of the limits
+/// imposed by the protocol.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ExecutionLimits {
+    /// The maximum number of Cairo steps, for validation and execution each.
+    pub max_steps: Option<NonZeroU32>,
+    /// The maximum amount of Sierra gas available to a call. Only applies to
+    /// [call](crate::call).
+    pub max_gas: Option<NonZeroU64>,
+}
+
+impl ExecutionLimits {
+    /// Returns the stricter of the two limits for each resource.
+    pub fn tightened_by(self, other: Self) -> Self {
+        fn min<T: Ord>(a: Option<T>, b: Option<T>) -> Option<T> {
+            match (a, b) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            }
+        }
+
+        Self {
+            max_steps: min(self.max_steps, other.max_steps),
+            max_gas: min(self.max_gas, other.max_gas),
+        }
+    }
+
+    /// Whether `error` is the message with which the Cairo VM or a Sierra
+    /// contract aborts once it has used up a resource restricted by these
+    /// limits.
+    pub(crate) fn exceeded_by(&self, error: &str) -> bool {
+        const STEPS_EXHAUSTED: &str = "RunResources has no remaining steps";
+        const GAS_EXHAUSTED: &str = "Out of gas";
+
+        (self.max_steps.is_some() && error.contains(STEPS_EXHAUSTED))
+            || (self.max_gas.is_some() && error.contains(GAS_EXHAUSTED))
+    }
 }
 
 impl<'tx> ExecutionState<'tx> {
     pub(super) fn starknet_state(
         self,
     ) -> anyhow::Result<(
-        CachedState<PendingStateReader<PathfinderStateReader<'tx>>>,
+        CachedState<PendingStateReader<PendingStateReader<PathfinderStateReader<'tx>>>>,
         BlockContext,
     )> {
         let block_number = if self.execute_on_parent_state {
@@ -118,13 +163,35 @@ impl<'tx> ExecutionState<'tx> {
             Some(self.header.number)
         };
 
-        let raw_reader = PathfinderStateReader::new(
+        // Fail up front, state read errors don't survive the trip through blockifier.
+        if let Some(block_number) = block_number {
+            self.transaction
+                .ensure_state_not_pruned(block_number.into())?;
+        }
+
+        let mut raw_reader = PathfinderStateReader::new(
             self.transaction,
             block_number,
             self.pending_state.is_some(),
         );
+        // Classes can only be shared between executions on top of the same stored
+        // block, the pending block changes under us.
+        if let (Some(cache), None) = (&self.class_cache, &self.pending_state) {
+            let state_block_hash = if self.execute_on_parent_state {
+                self.header.parent_hash
+            } else {
+                self.header.hash
+            };
+            raw_reader = raw_reader.with_block_classes(cache.for_block(state_block_hash));
+        }
+        if self.stored_block_hashes {
+            raw_reader = raw_reader.with_stored_block_hashes();
+        }
         let pending_state_reader = PendingStateReader::new(raw_reader, self.pending_state.clone());
-        let mut cached_state = CachedState::new(pending_state_reader);
+        // State overrides take precedence over both the pending and the stored state.
+        let overridden_state_reader =
+            PendingStateReader::new(pending_state_reader, self.state_overrides.clone());
+        let mut cached_state = CachedState::new(overridden_state_reader);
 
         let chain_info = self.chain_info()?;
         let block_info = self.block_info()?;
@@ -151,10 +218,20 @@ impl<'tx> ExecutionState<'tx> {
             None
         };
 
-        let versioned_constants = versioned_constants::for_version(
+        let mut versioned_constants = versioned_constants::for_version(
             &self.header.starknet_version,
             self.custom_versioned_constants,
-        );
+        )
+        .into_owned();
+
+        if let Some(max_steps) = self.limits.max_steps {
+            versioned_constants.invoke_tx_max_n_steps = versioned_constants
+                .invoke_tx_max_n_steps
+                .min(max_steps.get());
+            versioned_constants.validate_max_n_steps = versioned_constants
+                .validate_max_n_steps
+                .min(max_steps.get());
+        }
 
         pre_process_block(
             &mut cached_state,
@@ -165,7 +242,7 @@ impl<'tx> ExecutionState<'tx> {
         let block_context = BlockContext::new(
             block_info,
             chain_info,
-            versioned_constants.into_owned(),
+            versioned_constants,
             BouncerConfig::max(),
         );
 
@@ -265,9 +342,13 @@ impl<'tx> ExecutionState<'tx> {
             chain_id,
             header,
             pending_state,
+            state_overrides: None,
             execute_on_parent_state: true,
             allow_use_kzg_data: true,
             custom_versioned_constants,
+            limits: Default::default(),
+            class_cache: None,
+            stored_block_hashes: false,
         }
     }
 
@@ -284,11 +365,46 @@ impl<'tx> ExecutionState<'tx> {
             chain_id,
             header,
             pending_state,
+            state_overrides: None,
             execute_on_parent_state: false,
             allow_use_kzg_data: l1_blob_data_availability == L1BlobDataAvailability::Enabled,
             custom_versioned_constants,
+            limits: Default::default(),
+            class_cache: None,
+            stored_block_hashes: false,
         }
     }
+
+    /// Restricts execution to the given [ExecutionLimits].
+    pub fn with_limits(mut self, limits: ExecutionLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Overrides storage values, nonces and class hashes for the duration of
+    /// the execution, on top of the pending state if there is one.
+    ///
+    /// Only the storage updates, nonces and replaced classes of `overrides` are
+    /// used.
+    pub fn with_state_overrides(mut self, overrides: StateUpdate) -> Self {
+        self.state_overrides = Some(Arc::new(overrides));
+        self
+    }
+
+    /// Reuses the classes loaded by previous executions on top of the same
+    /// block, instead of loading them from the database again.
+    pub fn with_class_cache(mut self, cache: ClassCache) -> Self {
+        self.class_cache = Some(cache);
+        self
+    }
+
+    /// Reads the hashes of blocks from before the block hash contract at 0x1
+    /// was introduced from the database, instead of getting zero. This differs
+    /// from the sequencer, so must not be used to re-execute blocks.
+    pub fn with_stored_block_hashes(mut self) -> Self {
+        self.stored_block_hashes = true;
+        self
+    }
 }
 
 #[derive(Copy, Clone, PartialEq)]
@@ -1,5 +1,6 @@
 pub(crate) mod call;
 pub(crate) mod class;
+pub(crate) mod class_cache;
 pub(crate) mod error;
 pub(crate) mod error_stack;
 pub(crate) mod estimate;
@@ -10,6 +11,7 @@ pub(crate) mod pending;
 pub(crate) mod simulate;
 pub(crate) mod state_reader;
 pub(crate) mod transaction;
+pub(crate) mod validate;
 pub mod types;
 
 // re-export blockifier transaction type since it's exposed on our API
@@ -17,17 +19,21 @@ pub use blockifier::execution::contract_class::ClassInfo;
 pub use blockifier::transaction::account_transaction::AccountTransaction;
 pub use blockifier::transaction::transaction_execution::Transaction;
 pub use blockifier::versioned_constants::VersionedConstants;
-pub use call::call;
+pub use call::{call, call_many};
 pub use class::{parse_casm_definition, parse_deprecated_class_definition};
-pub use error::{CallError, TransactionExecutionError};
+pub use class_cache::ClassCache;
+pub use error::{CallError, FailureReason, TransactionExecutionError, ValidationError};
 pub use error_stack::{CallFrame, ErrorStack, Frame};
 pub use estimate::estimate;
 pub use execution_state::{
+    ExecutionLimits,
     ExecutionState,
     L1BlobDataAvailability,
     ETH_FEE_TOKEN_ADDRESS,
     STRK_FEE_TOKEN_ADDRESS,
 };
 pub use felt::{IntoFelt, IntoStarkFelt};
+pub use lru_cache::{class_cache_stats, CacheStats};
 pub use simulate::{simulate, trace, TraceCache};
 pub use transaction::transaction_hash;
+pub use validate::validate;
@@ -17,6 +17,29 @@ pub struct Entry {
     pub height: BlockNumber,
 }
 
+/// Lookup counters of a cache.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl CacheStats {
+    /// The fraction of lookups which were hits, or zero if there were none.
+    pub fn hit_rate(&self) -> f64 {
+        match self.hits + self.misses {
+            0 => 0.0,
+            lookups => self.hits as f64 / lookups as f64,
+        }
+    }
+}
+
+/// The lookup counters of the process-wide compiled class cache shared by all
+/// executions.
+pub fn class_cache_stats() -> CacheStats {
+    GLOBAL_CACHE.stats().unwrap_or_default()
+}
+
 /// An LRU contract class cache
 pub struct LruContractCache(Mutex<SizedCache<StarknetClassHash, Entry>>);
 
@@ -32,6 +55,16 @@ impl LruContractCache {
         })
     }
 
+    /// The number of lookups which found, or did not find, a class.
+    pub fn stats(&self) -> StateResult<CacheStats> {
+        let cache = self.locked_cache()?;
+
+        Ok(CacheStats {
+            hits: cache.cache_hits().unwrap_or_default(),
+            misses: cache.cache_misses().unwrap_or_default(),
+        })
+    }
+
     pub fn get(&self, class_hash: &StarknetClassHash) -> StateResult<Option<Entry>> {
         Ok(self.locked_cache()?.cache_get(class_hash).cloned())
     }
@@ -1,7 +1,9 @@
 use std::collections::BTreeMap;
+use std::num::NonZeroUsize;
 use std::sync::{Arc, Mutex};
 
 use anyhow::Context;
+use blockifier::execution::stack_trace::gen_transaction_execution_error_trace;
 use blockifier::state::cached_state::{CachedState, CommitmentStateDiff};
 use blockifier::state::errors::StateError;
 use blockifier::transaction::transaction_execution::Transaction;
@@ -19,7 +21,7 @@ use pathfinder_common::{
     TransactionHash,
 };
 
-use super::error::TransactionExecutionError;
+use super::error::{FailureReason, TransactionExecutionError};
 use super::execution_state::ExecutionState;
 use super::types::{FeeEstimate, TransactionSimulation, TransactionTrace};
 use crate::error_stack::ErrorStack;
@@ -53,6 +55,7 @@ struct ExecutionError {
     transaction_index: usize,
     error: String,
     error_stack: ErrorStack,
+    reason: FailureReason,
 }
 
 impl From<ExecutionError> for TransactionExecutionError {
@@ -61,18 +64,34 @@ impl From<ExecutionError> for TransactionExecutionError {
             transaction_index: value.transaction_index,
             error: value.error,
             error_stack: value.error_stack,
+            reason: value.reason,
         }
     }
 }
 
+/// Caches the traces of whole blocks by block hash, so that repeated trace
+/// requests for the same block don't re-execute it.
 #[derive(Debug, Clone)]
 pub struct TraceCache(Arc<Mutex<SizedCache<BlockHash, CacheItem>>>);
 
 type Traces = Vec<(TransactionHash, TransactionTrace)>;
 
+impl TraceCache {
+    /// The number of blocks cached by [TraceCache::default].
+    pub const DEFAULT_SIZE: NonZeroUsize = match NonZeroUsize::new(128) {
+        Some(size) => size,
+        None => unreachable!(),
+    };
+
+    /// Creates a cache holding the traces of up to `size` blocks.
+    pub fn with_size(size: NonZeroUsize) -> Self {
+        Self(Arc::new(Mutex::new(SizedCache::with_size(size.get()))))
+    }
+}
+
 impl Default for TraceCache {
     fn default() -> Self {
-        Self(Arc::new(Mutex::new(SizedCache::with_size(128))))
+        Self::with_size(Self::DEFAULT_SIZE)
     }
 }
 
@@ -193,10 +212,13 @@ pub fn trace(
             .map_err(|e| {
                 // Update the cache with the error. Lock the cache before sending to avoid
                 // race conditions between senders and receivers.
+                let error = e.to_string();
+                let error_stack = ErrorStack::from(gen_transaction_execution_error_trace(&e));
                 let err = ExecutionError {
                     transaction_index: transaction_idx,
-                    error: e.to_string(),
-                    error_stack: e.into(),
+                    error,
+                    reason: FailureReason::of(&e, &error_stack),
+                    error_stack,
                 };
                 let mut cache = cache.0.lock().unwrap();
                 let _ = sender.send(Err(err.clone()));
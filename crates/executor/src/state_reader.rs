@@ -1,11 +1,19 @@
 use blockifier::state::errors::StateError;
 use blockifier::state::state_api::StateReader;
-use pathfinder_common::{BlockNumber, ClassHash, StorageAddress, StorageValue};
+use pathfinder_common::{
+    BlockHash,
+    BlockNumber,
+    ClassHash,
+    ContractAddress,
+    StorageAddress,
+    StorageValue,
+};
 use pathfinder_crypto::Felt;
 use starknet_api::StarknetApiError;
 use starknet_types_core::felt::Felt as CoreFelt;
 
 use super::felt::{IntoFelt, IntoStarkFelt};
+use crate::class_cache::BlockClasses;
 use crate::lru_cache::GLOBAL_CACHE;
 
 pub(super) struct PathfinderStateReader<'tx> {
@@ -15,6 +23,11 @@ pub(super) struct PathfinderStateReader<'tx> {
     // This flag makes it possible to find these classes -- essentially makes the state
     // reader look up classes which are not declared at a canonical block yet.
     ignore_block_number_for_classes: bool,
+    /// Classes already loaded on top of the same block.
+    block_classes: Option<BlockClasses>,
+    /// Whether to fall back to the stored hashes of blocks missing from the
+    /// block hash contract.
+    stored_block_hashes: bool,
 }
 
 impl<'tx> PathfinderStateReader<'tx> {
@@ -27,9 +40,25 @@ impl<'tx> PathfinderStateReader<'tx> {
             transaction,
             block_number,
             ignore_block_number_for_classes,
+            block_classes: None,
+            stored_block_hashes: false,
         }
     }
 
+    /// Falls back to the stored block hashes for blocks missing from the block
+    /// hash contract, see [Self::stored_block_hash].
+    pub fn with_stored_block_hashes(mut self) -> Self {
+        self.stored_block_hashes = true;
+        self
+    }
+
+    /// Looks up classes in, and adds loaded classes to, `block_classes`. These
+    /// must have been loaded on top of the same block.
+    pub fn with_block_classes(mut self, block_classes: BlockClasses) -> Self {
+        self.block_classes = Some(block_classes);
+        self
+    }
+
     fn state_block_id(&self) -> Option<pathfinder_storage::BlockId> {
         self.block_number.map(Into::into)
     }
@@ -120,6 +149,60 @@ impl<'tx> PathfinderStateReader<'tx> {
 
         Err(StateError::UndeclaredClassHash(*class_hash))
     }
+
+    /// The hash of the block whose number is `storage_key`, if that block is
+    /// not after the state's block.
+    ///
+    /// The block hash contract at 0x1 only holds the hashes written since it
+    /// was introduced, so `get_block_hash` returns zero for older blocks unless
+    /// their hashes are read from storage instead.
+    fn stored_block_hash(
+        &self,
+        storage_key: StorageAddress,
+    ) -> blockifier::state::state_api::StateResult<Option<BlockHash>> {
+        let bytes = storage_key.0.as_be_bytes();
+        if bytes[..24] != [0; 24] {
+            return Ok(None);
+        }
+        let number = u64::from_be_bytes(bytes[24..].try_into().expect("8 bytes"));
+        let Some(number) = BlockNumber::new(number) else {
+            return Ok(None);
+        };
+        if self.block_number.map_or(true, |state| number > state) {
+            return Ok(None);
+        }
+
+        self.transaction
+            .block_hash(number.into())
+            .map_err(map_anyhow_to_state_err)
+    }
+
+    fn global_cached_compiled_contract_class(
+        &self,
+        pathfinder_class_hash: ClassHash,
+    ) -> blockifier::state::state_api::StateResult<
+        blockifier::execution::contract_class::ContractClass,
+    > {
+        let class_hash = starknet_api::core::ClassHash(pathfinder_class_hash.0.into_starkfelt());
+
+        if let Some(entry) = GLOBAL_CACHE.get(&class_hash)? {
+            if let Some(reader_block_number) = self.block_number {
+                if entry.height <= reader_block_number {
+                    tracing::trace!("Global class cache hit");
+                    return Ok(entry.definition);
+                }
+            }
+        }
+
+        let (definition_block_number, contract_class) =
+            self.non_cached_compiled_contract_class(pathfinder_class_hash, &class_hash)?;
+
+        if let Some(block_number) = definition_block_number {
+            GLOBAL_CACHE.set(class_hash, contract_class.clone(), block_number)?;
+        }
+
+        Ok(contract_class)
+    }
 }
 
 impl StateReader for PathfinderStateReader<'_> {
@@ -154,6 +237,16 @@ impl StateReader for PathfinderStateReader<'_> {
             .map_err(map_anyhow_to_state_err)?
             .unwrap_or(StorageValue(Felt::ZERO));
 
+        if self.stored_block_hashes
+            && storage_val.0 == Felt::ZERO
+            && pathfinder_contract_address == ContractAddress::ONE
+        {
+            if let Some(block_hash) = self.stored_block_hash(storage_key)? {
+                tracing::trace!(%block_hash, "Got block hash from storage");
+                return Ok(block_hash.0.into_starkfelt());
+            }
+        }
+
         tracing::trace!(storage_value=%storage_val, "Got storage value");
 
         Ok(storage_val.0.into_starkfelt())
@@ -230,20 +323,19 @@ impl StateReader for PathfinderStateReader<'_> {
             tracing::trace_span!("get_compiled_contract_class", class_hash=%pathfinder_class_hash)
                 .entered();
 
-        if let Some(entry) = GLOBAL_CACHE.get(&class_hash)? {
-            if let Some(reader_block_number) = self.block_number {
-                if entry.height <= reader_block_number {
-                    tracing::trace!("Global class cache hit");
-                    return Ok(entry.definition);
-                }
-            }
+        if let Some(definition) = self
+            .block_classes
+            .as_ref()
+            .and_then(|classes| classes.get(&class_hash))
+        {
+            tracing::trace!("Block class cache hit");
+            return Ok(definition);
         }
 
-        let (definition_block_number, contract_class) =
-            self.non_cached_compiled_contract_class(pathfinder_class_hash, &class_hash)?;
+        let contract_class = self.global_cached_compiled_contract_class(pathfinder_class_hash)?;
 
-        if let Some(block_number) = definition_block_number {
-            GLOBAL_CACHE.set(class_hash, contract_class.clone(), block_number)?;
+        if let Some(classes) = &self.block_classes {
+            classes.insert(class_hash, contract_class.clone());
         }
 
         Ok(contract_class)
@@ -283,3 +375,75 @@ fn map_anyhow_to_state_err(error: anyhow::Error) -> StateError {
     tracing::error!(%error, "Internal error in execution state reader");
     StateError::StateReadError(error.to_string())
 }
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::macro_prelude::*;
+    use pathfinder_common::BlockHeader;
+    use pathfinder_storage::StorageBuilder;
+
+    use super::*;
+
+    #[test]
+    fn block_hashes_missing_from_the_block_hash_contract() {
+        let storage = StorageBuilder::in_memory().unwrap();
+        let mut db = storage.connection().unwrap();
+        let tx = db.transaction().unwrap();
+        let header0 = BlockHeader::builder().finalize_with_hash(block_hash!("0xa"));
+        let header1 = header0
+            .child_builder()
+            .finalize_with_hash(block_hash!("0xb"));
+        let header2 = header1
+            .child_builder()
+            .finalize_with_hash(block_hash!("0xc"));
+        for header in [&header0, &header1, &header2] {
+            tx.insert_block_header(header).unwrap();
+        }
+        // Only block 0's hash is stored in the contract, and it differs to show
+        // that the contract takes precedence.
+        tx.insert_state_update(
+            header0.number,
+            &pathfinder_common::StateUpdate::default().with_storage_update(
+                ContractAddress::ONE,
+                storage_address!("0x0"),
+                storage_value!("0x123"),
+            ),
+        )
+        .unwrap();
+
+        fn block_hash(
+            reader: &PathfinderStateReader<'_>,
+            contract: ContractAddress,
+            number: u64,
+        ) -> Felt {
+            reader
+                .get_storage_at(
+                    starknet_api::core::ContractAddress(
+                        starknet_api::core::PatriciaKey::try_from(contract.0.into_starkfelt())
+                            .unwrap(),
+                    ),
+                    starknet_api::state::StorageKey(
+                        starknet_api::core::PatriciaKey::try_from(
+                            Felt::from_u64(number).into_starkfelt(),
+                        )
+                        .unwrap(),
+                    ),
+                )
+                .unwrap()
+                .into_felt()
+        }
+
+        let reader =
+            PathfinderStateReader::new(&tx, Some(header1.number), false).with_stored_block_hashes();
+
+        assert_eq!(block_hash(&reader, ContractAddress::ONE, 0), felt!("0x123"));
+        assert_eq!(block_hash(&reader, ContractAddress::ONE, 1), header1.hash.0);
+        // Blocks after the state's block are not revealed.
+        assert_eq!(block_hash(&reader, ContractAddress::ONE, 2), Felt::ZERO);
+        assert_eq!(block_hash(&reader, contract_address!("0x2"), 1), Felt::ZERO);
+
+        // Only if enabled, as the sequencer doesn't fall back.
+        let reader = PathfinderStateReader::new(&tx, Some(header1.number), false);
+        assert_eq!(block_hash(&reader, ContractAddress::ONE, 1), Felt::ZERO);
+    }
+}
@@ -124,6 +124,15 @@ pub enum TransactionTrace {
 }
 
 impl TransactionTrace {
+    pub fn execution_resources(&self) -> &ExecutionResources {
+        match self {
+            TransactionTrace::Declare(trace) => &trace.execution_resources,
+            TransactionTrace::DeployAccount(trace) => &trace.execution_resources,
+            TransactionTrace::Invoke(trace) => &trace.execution_resources,
+            TransactionTrace::L1Handler(trace) => &trace.execution_resources,
+        }
+    }
+
     fn revert_reason(&self) -> Option<&str> {
         match self {
             TransactionTrace::Invoke(InvokeTransactionTrace {
@@ -201,7 +210,34 @@ pub struct FunctionInvocation {
     pub events: Vec<Event>,
     pub messages: Vec<MsgToL1>,
     pub result: Vec<Felt>,
+    /// Resources used by this call, including those of its internal calls.
     pub computation_resources: ComputationResources,
+    /// Sierra gas consumed by this call, including that of its internal
+    /// calls. Always zero for Cairo 0 classes, and `None` if unknown, as for
+    /// traces fetched from the gateway.
+    pub gas_consumed: Option<u64>,
+}
+
+impl FunctionInvocation {
+    /// Resources used by this call alone, excluding its internal calls.
+    pub fn own_computation_resources(&self) -> ComputationResources {
+        self.internal_calls
+            .iter()
+            .fold(self.computation_resources.clone(), |own, call| {
+                own.saturating_sub(&call.computation_resources)
+            })
+    }
+
+    /// Sierra gas consumed by this call alone, excluding its internal calls.
+    /// `None` if the gas consumed by this call or any of its internal calls
+    /// is unknown.
+    pub fn own_gas_consumed(&self) -> Option<u64> {
+        self.internal_calls
+            .iter()
+            .try_fold(self.gas_consumed?, |own, call| {
+                Some(own.saturating_sub(call.gas_consumed?))
+            })
+    }
 }
 
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -292,6 +328,39 @@ impl std::ops::Add for ComputationResources {
     }
 }
 
+impl ComputationResources {
+    fn saturating_sub(self, rhs: &Self) -> Self {
+        Self {
+            steps: self.steps.saturating_sub(rhs.steps),
+            memory_holes: self.memory_holes.saturating_sub(rhs.memory_holes),
+            range_check_builtin_applications: self
+                .range_check_builtin_applications
+                .saturating_sub(rhs.range_check_builtin_applications),
+            pedersen_builtin_applications: self
+                .pedersen_builtin_applications
+                .saturating_sub(rhs.pedersen_builtin_applications),
+            poseidon_builtin_applications: self
+                .poseidon_builtin_applications
+                .saturating_sub(rhs.poseidon_builtin_applications),
+            ec_op_builtin_applications: self
+                .ec_op_builtin_applications
+                .saturating_sub(rhs.ec_op_builtin_applications),
+            ecdsa_builtin_applications: self
+                .ecdsa_builtin_applications
+                .saturating_sub(rhs.ecdsa_builtin_applications),
+            bitwise_builtin_applications: self
+                .bitwise_builtin_applications
+                .saturating_sub(rhs.bitwise_builtin_applications),
+            keccak_builtin_applications: self
+                .keccak_builtin_applications
+                .saturating_sub(rhs.keccak_builtin_applications),
+            segment_arena_builtin: self
+                .segment_arena_builtin
+                .saturating_sub(rhs.segment_arena_builtin),
+        }
+    }
+}
+
 #[derive(Debug, Default, Clone, Eq, PartialEq)]
 pub struct DataAvailabilityResources {
     pub l1_gas: u128,
@@ -343,6 +412,7 @@ impl From<blockifier::execution::call_info::CallInfo> for FunctionInvocation {
             messages,
             result,
             computation_resources: call_info.resources.into(),
+            gas_consumed: Some(call_info.execution.gas_consumed),
         }
     }
 }
@@ -452,3 +522,58 @@ impl From<cairo_vm::vm::runners::cairo_runner::ExecutionResources> for Computati
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::contract_address;
+
+    use super::*;
+
+    fn invocation(
+        steps: usize,
+        gas_consumed: u64,
+        internal_calls: Vec<FunctionInvocation>,
+    ) -> FunctionInvocation {
+        FunctionInvocation {
+            calldata: vec![],
+            contract_address: contract_address!("0x1"),
+            selector: Felt::ZERO,
+            call_type: CallType::Call,
+            caller_address: Felt::ZERO,
+            internal_calls,
+            class_hash: None,
+            entry_point_type: EntryPointType::External,
+            events: vec![],
+            messages: vec![],
+            result: vec![],
+            computation_resources: ComputationResources {
+                steps,
+                range_check_builtin_applications: steps / 10,
+                ..Default::default()
+            },
+            gas_consumed: Some(gas_consumed),
+        }
+    }
+
+    #[test]
+    fn own_resources_exclude_internal_calls() {
+        let inner = invocation(100, 1_000, vec![invocation(30, 200, vec![])]);
+        let outer = invocation(250, 5_000, vec![inner, invocation(50, 0, vec![])]);
+
+        let expected = ComputationResources {
+            steps: 100,
+            range_check_builtin_applications: 10,
+            ..Default::default()
+        };
+        assert_eq!(outer.own_computation_resources(), expected);
+        assert_eq!(outer.own_gas_consumed(), Some(4_000));
+
+        let inner = &outer.internal_calls[0];
+        assert_eq!(inner.own_computation_resources().steps, 70);
+        assert_eq!(inner.own_gas_consumed(), Some(800));
+
+        let mut unknown = outer.clone();
+        unknown.internal_calls[0].gas_consumed = None;
+        assert_eq!(unknown.own_gas_consumed(), None);
+    }
+}
@@ -0,0 +1,39 @@
+use blockifier::blockifier::stateful_validator::StatefulValidator;
+use blockifier::transaction::transaction_execution::Transaction;
+
+use super::error::ValidationError;
+use super::execution_state::ExecutionState;
+
+/// Checks whether the sequencer would accept `transaction` on top of
+/// `execution_state`.
+///
+/// This runs the same pre-validation as the sequencer (nonce, fee bounds and
+/// account balance) followed by the account's `__validate__` entry point,
+/// within the [limits](crate::ExecutionLimits) of `execution_state`. The
+/// transaction itself is not executed, except for deploy account transactions
+/// whose constructor has to run before they can be validated. No state
+/// changes are persisted.
+pub fn validate(
+    execution_state: ExecutionState<'_>,
+    transaction: Transaction,
+) -> Result<(), ValidationError> {
+    let block_number = execution_state.header.number;
+
+    let (state, block_context) = execution_state.starknet_state()?;
+
+    let _span = tracing::debug_span!("validate", transaction_hash=%super::transaction::transaction_hash(&transaction), %block_number).entered();
+
+    let Transaction::AccountTransaction(transaction) = transaction else {
+        return Err(ValidationError::ValidationFailure(
+            "Only account transactions can be submitted".to_owned(),
+        ));
+    };
+
+    let mut validator = StatefulValidator::create(state, block_context);
+    validator
+        .perform_validations(transaction, false)
+        .map_err(|error| {
+            tracing::debug!(%error, "Transaction validation failed");
+            error.into()
+        })
+}
@@ -16,6 +16,7 @@ use pathfinder_common::{BlockId, ClassHash, TransactionHash};
 use starknet_gateway_types::error::SequencerError;
 
 use crate::metrics::{with_metrics, BlockTag, RequestMetadata};
+use crate::policy::{with_circuit_breaker, CircuitBreaker, RetryPolicy};
 
 const X_THROTTLING_BYPASS: &str = "X-Throttling-Bypass";
 
@@ -25,6 +26,8 @@ pub struct Request<'a, S: RequestState> {
     url: reqwest::Url,
     api_key: Option<String>,
     client: &'a reqwest::Client,
+    retry_policy: RetryPolicy,
+    circuit_breaker: Option<&'a CircuitBreaker>,
 }
 
 pub mod stage {
@@ -62,9 +65,13 @@ pub mod stage {
     /// - [get](super::Request::get)
     /// - [get_as_bytes](super::Request::get_as_bytes)
     /// - [post_with_json](super::Request::post_with_json)
+    ///
+    /// Responses read as bytes can optionally be
+    /// [limited in size](super::Request::max_response_size).
     pub struct Final {
         pub meta: RequestMetadata,
         pub retry: bool,
+        pub max_response_size: Option<usize>,
     }
 
     impl super::RequestState for Init {}
@@ -79,11 +86,15 @@ impl<'a> Request<'a, stage::Init> {
         client: &'a reqwest::Client,
         url: reqwest::Url,
         api_key: Option<String>,
+        retry_policy: RetryPolicy,
+        circuit_breaker: Option<&'a CircuitBreaker>,
     ) -> Request<'a, stage::Method> {
         Request {
             url,
             client,
             api_key,
+            retry_policy,
+            circuit_breaker,
             state: stage::Method,
         }
     }
@@ -162,6 +173,8 @@ impl<'a> Request<'a, stage::Method> {
             url: self.url,
             client: self.client,
             api_key: self.api_key,
+            retry_policy: self.retry_policy,
+            circuit_breaker: self.circuit_breaker,
             state: stage::Params {
                 meta: RequestMetadata::new(method),
             },
@@ -220,15 +233,27 @@ impl<'a> Request<'a, stage::Params> {
             url: self.url,
             client: self.client,
             api_key: self.api_key,
+            retry_policy: self.retry_policy,
+            circuit_breaker: self.circuit_breaker,
             state: stage::Final {
                 meta: self.state.meta,
                 retry,
+                max_response_size: None,
             },
         }
     }
 }
 
 impl<'a> Request<'a, stage::Final> {
+    /// Limits the size of the response body read by
+    /// [get_as_bytes](Self::get_as_bytes). The body is read incrementally and
+    /// the request fails with [SequencerError::ResponseTooLarge] as soon as
+    /// the limit is exceeded, without buffering the remainder.
+    pub fn max_response_size(mut self, limit: usize) -> Self {
+        self.state.max_response_size = Some(limit);
+        self
+    }
+
     /// Sends the Sequencer request as a REST `GET` operation and parses the
     /// response into `T`.
     pub async fn get<T>(self) -> Result<T, SequencerError>
@@ -240,8 +265,9 @@ impl<'a> Request<'a, stage::Final> {
             api_key: Option<String>,
             client: &reqwest::Client,
             meta: RequestMetadata,
+            circuit_breaker: Option<&CircuitBreaker>,
         ) -> Result<T, SequencerError> {
-            with_metrics(meta, async move {
+            let request = with_metrics(meta, async move {
                 tracing::trace!(%url, "Fetching data from feeder gateway");
                 let request = client.get(url);
                 let request = match api_key {
@@ -250,20 +276,37 @@ impl<'a> Request<'a, stage::Final> {
                 };
                 let response = request.send().await?;
                 parse::<T>(response).await
-            })
-            .await
+            });
+            with_circuit_breaker(circuit_breaker, request).await
         }
 
         match self.state.retry {
-            false => send_request(self.url, self.api_key, self.client, self.state.meta).await,
+            false => {
+                send_request(
+                    self.url,
+                    self.api_key,
+                    self.client,
+                    self.state.meta,
+                    self.circuit_breaker,
+                )
+                .await
+            }
             true => {
                 retry0(
                     || async {
                         let url = self.url.clone();
                         let api_key = self.api_key.clone();
-                        send_request(url, api_key, self.client, self.state.meta).await
+                        send_request(
+                            url,
+                            api_key,
+                            self.client,
+                            self.state.meta,
+                            self.circuit_breaker,
+                        )
+                        .await
                     },
-                    retry_condition,
+                    &self.retry_policy,
+                    self.state.meta,
                 )
                 .await
             }
@@ -278,8 +321,10 @@ impl<'a> Request<'a, stage::Final> {
             api_key: Option<String>,
             client: &reqwest::Client,
             meta: RequestMetadata,
+            max_size: Option<usize>,
+            circuit_breaker: Option<&CircuitBreaker>,
         ) -> Result<bytes::Bytes, SequencerError> {
-            with_metrics(meta, async {
+            let request = with_metrics(meta, async {
                 tracing::trace!(%url, "Fetching binary data from feeder gateway");
                 let request = client.get(url);
                 let request = match api_key {
@@ -288,22 +333,44 @@ impl<'a> Request<'a, stage::Final> {
                 };
                 let response = request.send().await?;
                 let response = parse_raw(response).await?;
-                let bytes = response.bytes().await?;
-                Ok(bytes)
-            })
-            .await
+                match max_size {
+                    Some(limit) => read_limited(response, limit).await,
+                    None => Ok(response.bytes().await?),
+                }
+            });
+            with_circuit_breaker(circuit_breaker, request).await
         }
 
+        let max_size = self.state.max_response_size;
         match self.state.retry {
-            false => get_as_bytes_inner(self.url, self.api_key, self.client, self.state.meta).await,
+            false => {
+                get_as_bytes_inner(
+                    self.url,
+                    self.api_key,
+                    self.client,
+                    self.state.meta,
+                    max_size,
+                    self.circuit_breaker,
+                )
+                .await
+            }
             true => {
                 retry0(
                     || async {
                         let url = self.url.clone();
                         let api_key = self.api_key.clone();
-                        get_as_bytes_inner(url, api_key, self.client, self.state.meta).await
+                        get_as_bytes_inner(
+                            url,
+                            api_key,
+                            self.client,
+                            self.state.meta,
+                            max_size,
+                            self.circuit_breaker,
+                        )
+                        .await
                     },
-                    retry_condition,
+                    &self.retry_policy,
+                    self.state.meta,
                 )
                 .await
             }
@@ -331,12 +398,13 @@ impl<'a> Request<'a, stage::Final> {
             meta: RequestMetadata,
             json: &J,
             timeout: Option<std::time::Duration>,
+            circuit_breaker: Option<&CircuitBreaker>,
         ) -> Result<T, SequencerError>
         where
             T: serde::de::DeserializeOwned,
             J: serde::Serialize + ?Sized,
         {
-            with_metrics(meta, async {
+            let request = with_metrics(meta, async {
                 let request = client.post(url);
                 let request = match api_key {
                     Some(api_key) => request.header(X_THROTTLING_BYPASS, api_key),
@@ -348,8 +416,8 @@ impl<'a> Request<'a, stage::Final> {
                 };
                 let response = request.json(json).send().await?;
                 parse::<T>(response).await
-            })
-            .await
+            });
+            with_circuit_breaker(circuit_breaker, request).await
         }
 
         match self.state.retry {
@@ -361,6 +429,7 @@ impl<'a> Request<'a, stage::Final> {
                     self.state.meta,
                     json,
                     timeout,
+                    self.circuit_breaker,
                 )
                 .await
             }
@@ -377,10 +446,12 @@ impl<'a> Request<'a, stage::Final> {
                             self.state.meta,
                             json,
                             timeout,
+                            self.circuit_breaker,
                         )
                         .await
                     },
-                    retry_condition,
+                    &self.retry_policy,
+                    self.state.meta,
                 )
                 .await
             }
@@ -398,6 +469,31 @@ where
     Ok(response)
 }
 
+/// Reads the response body chunk by chunk, failing as soon as it exceeds
+/// `limit` bytes.
+async fn read_limited(
+    mut response: reqwest::Response,
+    limit: usize,
+) -> Result<bytes::Bytes, SequencerError> {
+    let too_large = SequencerError::ResponseTooLarge { limit };
+
+    let capacity = match response.content_length() {
+        Some(length) if length > limit as u64 => return Err(too_large),
+        Some(length) => length as usize,
+        None => 0,
+    };
+
+    let mut body = bytes::BytesMut::with_capacity(capacity);
+    while let Some(chunk) = response.chunk().await? {
+        if body.len() + chunk.len() > limit {
+            return Err(too_large);
+        }
+        body.extend_from_slice(&chunk);
+    }
+
+    Ok(body.freeze())
+}
+
 /// Helper function which allows skipping deserialization when required.
 async fn parse_raw(response: reqwest::Response) -> Result<reqwest::Response, SequencerError> {
     use starknet_gateway_types::error::StarknetError;
@@ -423,24 +519,36 @@ async fn parse_raw(response: reqwest::Response) -> Result<reqwest::Response, Seq
 pub trait RequestState {}
 
 /// Wrapper function to allow retrying sequencer queries in an exponential
-/// manner.
-async fn retry0<T, Fut, FutureFactory, Ret>(
+/// manner, as configured by the [RetryPolicy].
+async fn retry0<T, Fut, FutureFactory>(
     future_factory: FutureFactory,
-    retry_condition: Ret,
+    policy: &RetryPolicy,
+    meta: RequestMetadata,
 ) -> Result<T, SequencerError>
 where
     Fut: futures::Future<Output = Result<T, SequencerError>>,
     FutureFactory: FnMut() -> Fut,
-    Ret: FnMut(&SequencerError) -> bool,
 {
     use std::num::NonZeroU64;
 
     use pathfinder_retry::Retry;
 
-    Retry::exponential(future_factory, NonZeroU64::new(2).unwrap())
+    let retry = Retry::exponential(future_factory, policy.backoff_base_secs)
         .factor(NonZeroU64::new(1).unwrap())
-        .max_delay(std::time::Duration::from_secs(10))
-        .when(retry_condition)
+        .max_delay(policy.max_delay);
+    let retry = match policy.max_retries {
+        Some(max_retries) => retry.max_num_retries(max_retries),
+        None => retry,
+    };
+
+    retry
+        .when(|e| {
+            let retry = retry_condition(e);
+            if retry {
+                crate::metrics::increment_retried(meta);
+            }
+            retry
+        })
         .await
 }
 
@@ -485,6 +593,7 @@ fn retry_condition(e: &SequencerError) -> bool {
             error!(reason=%e, "Request failed, retrying");
             true
         }
+        SequencerError::ResponseTooLarge { .. } => false,
     }
 }
 
@@ -505,7 +614,9 @@ mod tests {
         use warp::http::StatusCode;
         use warp::Filter;
 
-        use crate::builder::{retry0, retry_condition};
+        use crate::builder::retry0;
+        use crate::metrics::RequestMetadata;
+        use crate::policy::RetryPolicy;
 
         // A test helper
         fn status_queue_server(
@@ -564,7 +675,8 @@ mod tests {
                     let response = reqwest::get(url).await?;
                     builder::parse::<String>(response).await
                 },
-                retry_condition,
+                &RetryPolicy::default(),
+                RequestMetadata::new("test"),
             )
             .await
             .unwrap();
@@ -601,7 +713,8 @@ mod tests {
                     let response = reqwest::get(url).await?;
                     builder::parse::<String>(response).await
                 },
-                retry_condition,
+                &RetryPolicy::default(),
+                RequestMetadata::new("test"),
             )
             .await
             .unwrap_err();
@@ -611,6 +724,44 @@ mod tests {
             );
         }
 
+        #[test_log::test(tokio::test)]
+        async fn stop_after_max_retries() {
+            use std::num::NonZeroUsize;
+            use std::sync::atomic::{AtomicUsize, Ordering};
+
+            use crate::builder;
+
+            tokio::time::pause();
+
+            let statuses = VecDeque::from([
+                (StatusCode::TOO_MANY_REQUESTS, ""),
+                (StatusCode::BAD_GATEWAY, ""),
+                (StatusCode::SERVICE_UNAVAILABLE, ""),
+                (StatusCode::OK, r#""Too late""#),
+            ]);
+            let policy = RetryPolicy {
+                max_retries: Some(NonZeroUsize::new(2).unwrap()),
+                ..Default::default()
+            };
+            let attempts = AtomicUsize::new(0);
+
+            let (_jh, addr) = status_queue_server(statuses);
+            retry0(
+                || async {
+                    attempts.fetch_add(1, Ordering::Relaxed);
+                    let mut url = reqwest::Url::parse("http://localhost/").unwrap();
+                    url.set_port(Some(addr.port())).unwrap();
+                    let response = reqwest::get(url).await?;
+                    builder::parse::<String>(response).await
+                },
+                &policy,
+                RequestMetadata::new("test"),
+            )
+            .await
+            .unwrap_err();
+            assert_eq!(attempts.load(Ordering::Relaxed), 3);
+        }
+
         #[tokio::test(flavor = "current_thread")]
         async fn request_timeout() {
             use std::sync::atomic::{AtomicUsize, Ordering};
@@ -639,7 +790,8 @@ mod tests {
                         .await?;
                     builder::parse::<String>(response).await
                 },
-                retry_condition,
+                &RetryPolicy::default(),
+                RequestMetadata::new("test"),
             );
 
             // The retry loops forever, so wrap it in a timeout and check the counter.
@@ -789,4 +941,43 @@ mod tests {
             Ok(())
         }
     }
+
+    mod max_response_size {
+        use gateway_test_utils::GATEWAY_TIMEOUT;
+        use httpmock::prelude::*;
+        use starknet_gateway_types::error::SequencerError;
+
+        use crate::Client;
+
+        #[tokio::test]
+        async fn is_enforced() {
+            let server = MockServer::start_async().await;
+            server.mock(|when, then| {
+                when.any_request();
+                then.status(200).body(vec![0u8; 100]);
+            });
+            let client =
+                Client::with_base_url(server.base_url().parse().unwrap(), GATEWAY_TIMEOUT).unwrap();
+
+            let bytes = client
+                .feeder_gateway_request()
+                .method("")
+                .retry(false)
+                .max_response_size(100)
+                .get_as_bytes()
+                .await
+                .unwrap();
+            assert_eq!(bytes.len(), 100);
+
+            let error = client
+                .feeder_gateway_request()
+                .method("")
+                .retry(false)
+                .max_response_size(99)
+                .get_as_bytes()
+                .await
+                .unwrap_err();
+            assert_matches::assert_matches!(error, SequencerError::ResponseTooLarge { limit: 99 });
+        }
+    }
 }
@@ -1,6 +1,7 @@
 //! Starknet L2 sequencer client.
 use std::fmt::Debug;
 use std::result::Result;
+use std::sync::Arc;
 use std::time::Duration;
 
 use pathfinder_common::{
@@ -20,6 +21,18 @@ use starknet_gateway_types::{reply, request};
 
 mod builder;
 mod metrics;
+mod policy;
+
+pub use policy::{CircuitBreakerConfig, RetryPolicy};
+
+/// Upper bound for a single class or CASM definition downloaded from the feeder
+/// gateway. Downloads are aborted as soon as they exceed this size, so a
+/// runaway response cannot exhaust memory during sync.
+///
+/// Definitions are still buffered in full rather than streamed into storage:
+/// the class hash can only be computed from the complete definition, and
+/// storage compresses each definition as a single blob.
+pub const MAX_CLASS_DEFINITION_SIZE: usize = 64 * 1024 * 1024;
 
 #[allow(unused_variables)]
 #[mockall::automock]
@@ -90,6 +103,13 @@ pub trait GatewayApi: Sync {
         unimplemented!();
     }
 
+    async fn resubmit_transaction(
+        &self,
+        transaction: Box<serde_json::value::RawValue>,
+    ) -> Result<reply::add_transaction::AddTransactionResponse, SequencerError> {
+        unimplemented!();
+    }
+
     /// This is a **temporary** measure to keep the sync logic unchanged
     ///
     /// TODO remove when p2p friendly sync is implemented
@@ -184,6 +204,13 @@ impl<T: GatewayApi + Sync + Send> GatewayApi for std::sync::Arc<T> {
         self.as_ref().add_deploy_account(deploy).await
     }
 
+    async fn resubmit_transaction(
+        &self,
+        transaction: Box<serde_json::value::RawValue>,
+    ) -> Result<reply::add_transaction::AddTransactionResponse, SequencerError> {
+        self.as_ref().resubmit_transaction(transaction).await
+    }
+
     async fn block_traces(&self, block: BlockId) -> Result<BlockTrace, SequencerError> {
         self.as_ref().block_traces(block).await
     }
@@ -209,11 +236,17 @@ impl<T: GatewayApi + Sync + Send> GatewayApi for std::sync::Arc<T> {
 /// Retry is performed on __all__ types of errors __except for__
 /// [Starknet specific errors](starknet_gateway_types::error::StarknetError).
 ///
-/// Initial backoff time is 30 seconds and saturates at 10 minutes:
+/// By default the initial backoff time is 2 seconds and saturates at 10
+/// seconds:
+///
+/// `backoff [secs] = min(2 ^ N, 10) [secs]`
 ///
-/// `backoff [secs] = min((2 ^ N) * 15, 600) [secs]`
+/// where `N` is the consecutive retry iteration number `{1, 2, ...}`. This can
+/// be changed using [with_retry_policy](Client::with_retry_policy).
 ///
-/// where `N` is the consecutive retry iteration number `{1, 2, ...}`.
+/// Optionally a [circuit breaker](Client::with_circuit_breaker) pauses
+/// requests while the gateway is rate limiting or failing. Feeder gateway
+/// requests and transaction submissions are tracked by separate breakers.
 #[derive(Debug, Clone)]
 pub struct Client {
     /// This client is internally refcounted
@@ -230,6 +263,11 @@ pub struct Client {
     /// Api key added to each request as a value for 'X-Throttling-Bypass'
     /// header.
     api_key: Option<String>,
+    retry_policy: RetryPolicy,
+    /// Applied to feeder gateway requests, shared by all clones of this client.
+    circuit_breaker: Option<Arc<policy::CircuitBreaker>>,
+    /// Applied to transaction submissions, shared by all clones of this client.
+    submission_circuit_breaker: Option<Arc<policy::CircuitBreaker>>,
 }
 
 impl Client {
@@ -280,6 +318,9 @@ impl Client {
             feeder_gateway,
             retry: true,
             api_key: None,
+            retry_policy: Default::default(),
+            circuit_breaker: None,
+            submission_circuit_breaker: None,
         })
     }
 
@@ -290,6 +331,30 @@ impl Client {
         self
     }
 
+    /// Sets the backoff and retry limit used for retried requests.
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Pauses requests for a while once the gateway keeps responding with
+    /// rate limiting or server errors. Disabled if `None`.
+    ///
+    /// Feeder gateway requests and transaction submissions use separate
+    /// breakers, so that an overloaded feeder gateway does not hold back
+    /// submissions and vice versa.
+    pub fn with_circuit_breaker(mut self, config: Option<CircuitBreakerConfig>) -> Self {
+        self.circuit_breaker = config.map(|config| Arc::new(policy::CircuitBreaker::new(config)));
+        self.submission_circuit_breaker =
+            config.map(|config| Arc::new(policy::CircuitBreaker::new(config)));
+        self
+    }
+
+    /// The feeder gateway [Url] used by this client.
+    pub fn feeder_gateway_url(&self) -> &Url {
+        &self.feeder_gateway
+    }
+
     /// Use this method to disable retry logic for all __non write__ requests
     /// when testing.
     pub fn disable_retry_for_tests(self) -> Self {
@@ -300,7 +365,13 @@ impl Client {
     }
 
     fn gateway_request(&self) -> builder::Request<'_, builder::stage::Method> {
-        builder::Request::builder(&self.inner, self.gateway.clone(), self.api_key.clone())
+        builder::Request::builder(
+            &self.inner,
+            self.gateway.clone(),
+            self.api_key.clone(),
+            self.retry_policy,
+            self.submission_circuit_breaker.as_deref(),
+        )
     }
 
     fn feeder_gateway_request(&self) -> builder::Request<'_, builder::stage::Method> {
@@ -308,6 +379,8 @@ impl Client {
             &self.inner,
             self.feeder_gateway.clone(),
             self.api_key.clone(),
+            self.retry_policy,
+            self.circuit_breaker.as_deref(),
         )
     }
 }
@@ -368,6 +441,7 @@ impl GatewayApi for Client {
             .class_hash(class_hash)
             .block(BlockId::Pending)
             .retry(self.retry)
+            .max_response_size(MAX_CLASS_DEFINITION_SIZE)
             .get_as_bytes()
             .await
     }
@@ -383,6 +457,7 @@ impl GatewayApi for Client {
             .class_hash(class_hash)
             .block(BlockId::Pending)
             .retry(self.retry)
+            .max_response_size(MAX_CLASS_DEFINITION_SIZE)
             .get_as_bytes()
             .await
     }
@@ -502,6 +577,21 @@ impl GatewayApi for Client {
             .await
     }
 
+    /// Sends a transaction again, given as the JSON of the
+    /// [AddTransaction](request::add_transaction::AddTransaction) it was
+    /// originally submitted with.
+    #[tracing::instrument(skip(self))]
+    async fn resubmit_transaction(
+        &self,
+        transaction: Box<serde_json::value::RawValue>,
+    ) -> Result<reply::add_transaction::AddTransactionResponse, SequencerError> {
+        self.gateway_request()
+            .add_transaction()
+            .retry(false)
+            .post_with_json(&transaction, Some(Duration::MAX))
+            .await
+    }
+
     #[tracing::instrument(skip(self))]
     async fn block_traces(&self, block: BlockId) -> Result<BlockTrace, SequencerError> {
         self.feeder_gateway_request()
@@ -740,6 +830,42 @@ mod tests {
                 });
                 client.add_invoke_transaction(invoke).await.unwrap();
             }
+
+            #[tokio::test]
+            async fn resubmitted() {
+                use request::add_transaction::{
+                    AddTransaction,
+                    InvokeFunction,
+                    InvokeFunctionV0V1,
+                };
+
+                let (_jh, url) = setup([(
+                    "/gateway/add_transaction",
+                    (
+                        r#"{"code":"TRANSACTION_RECEIVED","transaction_hash":"0x0389DD0629F42176CC8B6C43ACEFC0713D0064ECDFC0470E0FC179F53421A38B"}"#,
+                        200,
+                    ),
+                )]);
+                let client = Client::with_base_url(url, GATEWAY_TIMEOUT).unwrap();
+                let (_, fee, sig, nonce, addr, call) = inputs();
+                let invoke = AddTransaction::Invoke(InvokeFunction::V1(InvokeFunctionV0V1 {
+                    max_fee: fee,
+                    signature: sig,
+                    nonce: Some(nonce),
+                    sender_address: addr,
+                    entry_point_selector: None,
+                    calldata: call,
+                }));
+                let invoke = serde_json::value::to_raw_value(&invoke).unwrap();
+
+                let response = client.resubmit_transaction(invoke).await.unwrap();
+                assert_eq!(
+                    response.transaction_hash,
+                    transaction_hash!(
+                        "0x0389DD0629F42176CC8B6C43ACEFC0713D0064ECDFC0470E0FC179F53421A38B"
+                    )
+                );
+            }
         }
 
         mod declare {
@@ -9,6 +9,7 @@ use super::SequencerError;
 const METRIC_REQUESTS: &str = "gateway_requests_total";
 const METRIC_FAILED_REQUESTS: &str = "gateway_requests_failed_total";
 const METRIC_REQUESTS_LATENCY: &str = "gateway_request_duration_seconds";
+const METRIC_RETRIED_REQUESTS: &str = "gateway_requests_retried_total";
 const METRICS: [&str; 2] = [METRIC_REQUESTS, METRIC_FAILED_REQUESTS];
 const TAG_LATEST: &str = "latest";
 const TAG_PENDING: &str = "pending";
@@ -43,9 +44,10 @@ pub fn register() {
         })
     });
 
-    // Request latency for all methods
+    // Request latency and retries for all methods
     Request::<'_, Method>::METHODS.iter().for_each(|&method| {
         metrics::register_histogram!(METRIC_REQUESTS_LATENCY, "method" => method);
+        metrics::register_counter!(METRIC_RETRIED_REQUESTS, "method" => method);
     });
 
     // Failed requests for specific failure reasons
@@ -111,6 +113,11 @@ impl RequestMetadata {
     }
 }
 
+/// Increments the `gateway_requests_retried_total` counter of the method.
+pub fn increment_retried(meta: RequestMetadata) {
+    metrics::increment_counter!(METRIC_RETRIED_REQUESTS, "method" => meta.method);
+}
+
 /// # Usage
 ///
 ///  Awaits future `f` and increments the following counters for a particular
@@ -189,7 +196,7 @@ pub async fn with_metrics<T>(
             SequencerError::ReqwestError(e) if e.is_timeout() => {
                 increment_failed(meta, REASON_TIMEOUT);
             }
-            SequencerError::ReqwestError(_) => {}
+            SequencerError::ReqwestError(_) | SequencerError::ResponseTooLarge { .. } => {}
         }
     })
 }
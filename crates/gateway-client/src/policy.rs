@@ -0,0 +1,384 @@
+//! Retry and circuit breaker policies applied to gateway requests.
+use std::num::{NonZeroU32, NonZeroU64, NonZeroUsize};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use futures::Future;
+use starknet_gateway_types::error::SequencerError;
+use tokio::sync::Notify;
+use tokio::time::Instant;
+
+const METRIC_CIRCUIT_BREAKER_OPENED: &str = "gateway_circuit_breaker_opened_total";
+const METRIC_CIRCUIT_BREAKER_DELAYED: &str = "gateway_circuit_breaker_delayed_requests_total";
+
+/// Controls how retryable requests are retried.
+///
+/// The `N`th retry is delayed by `min(backoff_base_secs ^ N, max_delay)`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub backoff_base_secs: NonZeroU64,
+    pub max_delay: Duration,
+    /// Give up after this many retries, retry indefinitely if `None`.
+    pub max_retries: Option<NonZeroUsize>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            backoff_base_secs: NonZeroU64::new(2).unwrap(),
+            max_delay: Duration::from_secs(10),
+            max_retries: None,
+        }
+    }
+}
+
+/// Configures the [CircuitBreaker].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct CircuitBreakerConfig {
+    /// The number of consecutive rate limited or server error responses after
+    /// which the circuit opens.
+    pub failure_threshold: NonZeroU32,
+    /// How long the circuit stays open.
+    pub cooldown: Duration,
+}
+
+/// Stops sending requests to the gateway for a while once it keeps responding
+/// with `429 Too Many Requests` or `5xx` status codes.
+///
+/// While the circuit is open, requests wait for the cooldown to pass. After
+/// that the circuit is half-open: a single request is sent as a probe while
+/// the others keep waiting for its outcome. If the probe is rate limited or
+/// fails with a server error the circuit opens again for another cooldown,
+/// otherwise it closes and the waiting requests are released.
+#[derive(Debug)]
+pub(crate) struct CircuitBreaker {
+    config: CircuitBreakerConfig,
+    state: Mutex<CircuitState>,
+    /// Notified once a half-open probe completes.
+    probe_done: Notify,
+}
+
+#[derive(Debug, Default)]
+struct CircuitState {
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+    probe_in_flight: bool,
+}
+
+/// Permission to send a request, obtained from [CircuitBreaker::acquire].
+///
+/// Dropping the probe permit without recording an outcome, for example because
+/// the request was cancelled, lets another request probe the gateway.
+struct Permit<'a> {
+    breaker: &'a CircuitBreaker,
+    probe: bool,
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        if self.probe {
+            self.breaker.state.lock().unwrap().probe_in_flight = false;
+            self.breaker.probe_done.notify_waiters();
+        }
+    }
+}
+
+impl CircuitBreaker {
+    pub fn new(config: CircuitBreakerConfig) -> Self {
+        register_metrics();
+
+        Self {
+            config,
+            state: Default::default(),
+            probe_done: Notify::new(),
+        }
+    }
+
+    /// Waits until a request may be sent, which is either when the circuit is
+    /// closed or when this request gets to probe the half-open circuit.
+    async fn acquire(&self) -> Permit<'_> {
+        let mut delayed = false;
+
+        loop {
+            let probe_done = self.probe_done.notified();
+            tokio::pin!(probe_done);
+
+            let open_until = {
+                let mut state = self.state.lock().unwrap();
+                match state.open_until {
+                    None => {
+                        return Permit {
+                            breaker: self,
+                            probe: false,
+                        }
+                    }
+                    Some(open_until) if open_until > Instant::now() => Some(open_until),
+                    Some(_) if !state.probe_in_flight => {
+                        state.probe_in_flight = true;
+                        return Permit {
+                            breaker: self,
+                            probe: true,
+                        };
+                    }
+                    Some(_) => {
+                        // Register before releasing the lock so that the
+                        // probe's notification cannot be missed.
+                        probe_done.as_mut().enable();
+                        None
+                    }
+                }
+            };
+
+            if !delayed {
+                metrics::increment_counter!(METRIC_CIRCUIT_BREAKER_DELAYED);
+                delayed = true;
+            }
+
+            match open_until {
+                Some(open_until) => tokio::time::sleep_until(open_until).await,
+                None => probe_done.await,
+            }
+        }
+    }
+
+    fn record<T>(&self, permit: Permit<'_>, result: &Result<T, SequencerError>) {
+        let mut state = self.state.lock().unwrap();
+        let overloaded = matches!(result, Err(e) if is_overload(e));
+
+        if permit.probe {
+            if overloaded {
+                tracing::warn!(
+                    cooldown=?self.config.cooldown,
+                    "Gateway is still failing, pausing requests"
+                );
+                metrics::increment_counter!(METRIC_CIRCUIT_BREAKER_OPENED);
+                state.open_until = Some(Instant::now() + self.config.cooldown);
+            } else {
+                tracing::info!("Gateway recovered, resuming requests");
+                state.open_until = None;
+            }
+            state.consecutive_failures = 0;
+            // Dropping the permit releases the requests waiting for the probe.
+            drop(state);
+            return;
+        }
+
+        if !overloaded {
+            state.consecutive_failures = 0;
+            return;
+        }
+
+        state.consecutive_failures += 1;
+        if state.open_until.is_none()
+            && state.consecutive_failures >= self.config.failure_threshold.get()
+        {
+            tracing::warn!(
+                failures=%state.consecutive_failures,
+                cooldown=?self.config.cooldown,
+                "Gateway keeps failing, pausing requests"
+            );
+            metrics::increment_counter!(METRIC_CIRCUIT_BREAKER_OPENED);
+            state.consecutive_failures = 0;
+            state.open_until = Some(Instant::now() + self.config.cooldown);
+        }
+    }
+}
+
+fn register_metrics() {
+    metrics::register_counter!(METRIC_CIRCUIT_BREAKER_OPENED);
+    metrics::register_counter!(METRIC_CIRCUIT_BREAKER_DELAYED);
+}
+
+/// Rate limiting and server errors indicate that the gateway is struggling.
+fn is_overload(e: &SequencerError) -> bool {
+    match e {
+        SequencerError::ReqwestError(e) => e.status().is_some_and(|status| {
+            status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+        }),
+        _ => false,
+    }
+}
+
+/// Awaits future `f` once the circuit breaker, if any, lets it through and
+/// records its outcome.
+pub(crate) async fn with_circuit_breaker<T>(
+    breaker: Option<&CircuitBreaker>,
+    f: impl Future<Output = Result<T, SequencerError>>,
+) -> Result<T, SequencerError> {
+    let Some(breaker) = breaker else {
+        return f.await;
+    };
+
+    let permit = breaker.acquire().await;
+    let result = f.await;
+    breaker.record(permit, &result);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use warp::http::response::Builder;
+    use warp::Filter;
+
+    use super::*;
+
+    fn server(status: u16) -> std::net::SocketAddr {
+        let any = warp::any().then(move || async move { Builder::new().status(status).body("") });
+        let (addr, run_srv) = warp::serve(any).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(run_srv);
+        addr
+    }
+
+    async fn request(addr: std::net::SocketAddr) -> Result<(), SequencerError> {
+        let url = format!("http://{addr}/");
+        let response = reqwest::get(url).await?;
+        response.error_for_status()?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn opens_after_consecutive_overload_errors() {
+        let breaker = CircuitBreaker::new(CircuitBreakerConfig {
+            failure_threshold: NonZeroU32::new(2).unwrap(),
+            cooldown: Duration::from_secs(60),
+        });
+        let overloaded = server(503);
+
+        with_circuit_breaker(Some(&breaker), request(overloaded))
+            .await
+            .unwrap_err();
+        assert!(breaker.state.lock().unwrap().open_until.is_none());
+
+        with_circuit_breaker(Some(&breaker), request(overloaded))
+            .await
+            .unwrap_err();
+        assert!(breaker.state.lock().unwrap().open_until.is_some());
+    }
+
+    #[tokio::test]
+    async fn client_errors_and_successes_reset_the_failure_count() {
+        let breaker = CircuitBreaker::new(CircuitBreakerConfig {
+            failure_threshold: NonZeroU32::new(2).unwrap(),
+            cooldown: Duration::from_secs(60),
+        });
+        let overloaded = server(429);
+        let not_found = server(404);
+        let ok = server(200);
+
+        for other in [not_found, ok] {
+            with_circuit_breaker(Some(&breaker), request(overloaded))
+                .await
+                .unwrap_err();
+            let _ = with_circuit_breaker(Some(&breaker), request(other)).await;
+            with_circuit_breaker(Some(&breaker), request(overloaded))
+                .await
+                .unwrap_err();
+            assert!(breaker.state.lock().unwrap().open_until.is_none());
+            let _ = with_circuit_breaker(Some(&breaker), request(other)).await;
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn open_circuit_delays_requests() {
+        let breaker = CircuitBreaker::new(CircuitBreakerConfig {
+            failure_threshold: NonZeroU32::new(1).unwrap(),
+            cooldown: Duration::from_secs(60),
+        });
+        let overloaded = server(500);
+
+        with_circuit_breaker(Some(&breaker), request(overloaded))
+            .await
+            .unwrap_err();
+
+        let started = Instant::now();
+        with_circuit_breaker(Some(&breaker), async { Ok(()) })
+            .await
+            .unwrap();
+        assert!(started.elapsed() >= Duration::from_secs(60));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn half_open_circuit_sends_a_single_probe() {
+        let breaker = CircuitBreaker::new(CircuitBreakerConfig {
+            failure_threshold: NonZeroU32::new(1).unwrap(),
+            cooldown: Duration::from_secs(60),
+        });
+        let overloaded = server(500);
+
+        with_circuit_breaker(Some(&breaker), request(overloaded))
+            .await
+            .unwrap_err();
+        tokio::time::sleep(Duration::from_secs(60)).await;
+
+        let (probe_tx, probe_rx) = tokio::sync::oneshot::channel::<()>();
+        let probe = with_circuit_breaker(Some(&breaker), async move {
+            probe_rx.await.unwrap();
+            Ok(())
+        });
+        let waiting = with_circuit_breaker(Some(&breaker), async { Ok(()) });
+        tokio::pin!(probe, waiting);
+
+        // The probe is in flight, the other request must wait for its outcome.
+        tokio::select! {
+            _ = &mut probe => panic!("Probe completed early"),
+            _ = &mut waiting => panic!("Request sent while probing"),
+            _ = tokio::time::sleep(Duration::from_secs(1)) => {}
+        }
+        assert!(breaker.state.lock().unwrap().probe_in_flight);
+
+        probe_tx.send(()).unwrap();
+        probe.await.unwrap();
+        waiting.await.unwrap();
+        assert!(breaker.state.lock().unwrap().open_until.is_none());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn failed_probe_reopens_the_circuit() {
+        let breaker = CircuitBreaker::new(CircuitBreakerConfig {
+            failure_threshold: NonZeroU32::new(3).unwrap(),
+            cooldown: Duration::from_secs(60),
+        });
+        let overloaded = server(503);
+
+        for _ in 0..3 {
+            with_circuit_breaker(Some(&breaker), request(overloaded))
+                .await
+                .unwrap_err();
+        }
+        tokio::time::sleep(Duration::from_secs(60)).await;
+
+        // A single failing probe is enough to open the circuit again.
+        with_circuit_breaker(Some(&breaker), request(overloaded))
+            .await
+            .unwrap_err();
+        let state = breaker.state.lock().unwrap();
+        assert!(state.open_until.is_some_and(|t| t > Instant::now()));
+        assert!(!state.probe_in_flight);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn cancelled_probe_releases_the_half_open_circuit() {
+        let breaker = CircuitBreaker::new(CircuitBreakerConfig {
+            failure_threshold: NonZeroU32::new(1).unwrap(),
+            cooldown: Duration::from_secs(60),
+        });
+        let overloaded = server(500);
+
+        with_circuit_breaker(Some(&breaker), request(overloaded))
+            .await
+            .unwrap_err();
+        tokio::time::sleep(Duration::from_secs(60)).await;
+
+        let cancelled = tokio::time::timeout(
+            Duration::from_secs(1),
+            with_circuit_breaker(Some(&breaker), std::future::pending::<Result<(), _>>()),
+        )
+        .await;
+        assert!(cancelled.is_err());
+
+        with_circuit_breaker(Some(&breaker), async { Ok(()) })
+            .await
+            .unwrap();
+        assert!(breaker.state.lock().unwrap().open_until.is_none());
+    }
+}
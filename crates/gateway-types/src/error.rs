@@ -14,6 +14,9 @@ pub enum SequencerError {
     /// not informative enough or bloated
     #[error("error decoding response body: invalid error variant")]
     InvalidStarknetErrorVariant,
+    /// The response body exceeded the size limit set for the request.
+    #[error("response body exceeds the size limit of {limit} bytes")]
+    ResponseTooLarge { limit: usize },
 }
 
 /// Used for deserializing specific Starknet sequencer error data.
@@ -65,6 +65,10 @@ pub struct Block {
     pub state_diff_commitment: Option<StateDiffCommitment>,
     #[serde(default)]
     pub state_diff_length: Option<u64>,
+
+    // Introduced in v0.13.4, older blocks don't have this field.
+    #[serde(default)]
+    pub l2_gas_price: Option<GasPrices>,
 }
 
 #[serde_as]
@@ -92,6 +96,10 @@ pub struct PendingBlock {
     pub starknet_version: StarknetVersion,
     // Introduced in v0.13.1
     pub l1_da_mode: L1DataAvailabilityMode,
+
+    // Introduced in v0.13.4, older blocks don't have this field.
+    #[serde(default)]
+    pub l2_gas_price: Option<GasPrices>,
 }
 
 #[derive(Copy, Clone, Debug, Default, Deserialize, PartialEq, Eq, serde::Serialize)]
@@ -2067,6 +2075,87 @@ impl From<StateUpdate> for pathfinder_common::StateUpdate {
     }
 }
 
+impl From<pathfinder_common::StateUpdate> for StateUpdate {
+    fn from(state_update: pathfinder_common::StateUpdate) -> Self {
+        use pathfinder_common::state_update::ContractClassUpdate;
+
+        let mut state_diff = state_update::StateDiff {
+            old_declared_contracts: state_update.declared_cairo_classes,
+            ..Default::default()
+        };
+
+        // System contract updates are embedded in the normal storage diffs, see the
+        // conversion above.
+        for (address, update) in state_update.system_contract_updates {
+            state_diff.storage_diffs.entry(address).or_default().extend(
+                update
+                    .storage
+                    .into_iter()
+                    .map(|(key, value)| state_update::StorageDiff { key, value }),
+            );
+        }
+
+        for (address, update) in state_update.contract_updates {
+            if !update.storage.is_empty() {
+                state_diff.storage_diffs.entry(address).or_default().extend(
+                    update
+                        .storage
+                        .into_iter()
+                        .map(|(key, value)| state_update::StorageDiff { key, value }),
+                );
+            }
+
+            match update.class {
+                Some(ContractClassUpdate::Deploy(class_hash)) => state_diff
+                    .deployed_contracts
+                    .push(state_update::DeployedContract {
+                        address,
+                        class_hash,
+                    }),
+                Some(ContractClassUpdate::Replace(class_hash)) => {
+                    state_diff
+                        .replaced_classes
+                        .push(state_update::ReplacedClass {
+                            address,
+                            class_hash,
+                        })
+                }
+                None => {}
+            }
+
+            if let Some(nonce) = update.nonce {
+                state_diff.nonces.insert(address, nonce);
+            }
+        }
+
+        state_diff.declared_classes = state_update
+            .declared_sierra_classes
+            .into_iter()
+            .map(
+                |(class_hash, compiled_class_hash)| state_update::DeclaredSierraClass {
+                    class_hash,
+                    compiled_class_hash,
+                },
+            )
+            .collect();
+
+        // Keep the output stable, as the updates are collected from hash maps.
+        for storage_diffs in state_diff.storage_diffs.values_mut() {
+            storage_diffs.sort();
+        }
+        state_diff.deployed_contracts.sort();
+        state_diff.replaced_classes.sort();
+        state_diff.declared_classes.sort();
+
+        Self {
+            block_hash: state_update.block_hash,
+            new_root: state_update.state_commitment,
+            old_root: state_update.parent_state_commitment,
+            state_diff,
+        }
+    }
+}
+
 /// Types used when deserializing state update related data.
 pub mod state_update {
     use std::collections::{HashMap, HashSet};
@@ -2175,6 +2264,14 @@ pub mod add_transaction {
         pub transaction_hash: TransactionHash,
     }
 
+    /// API response for a transaction of any type, ignoring the fields specific
+    /// to the type
+    #[derive(Clone, Debug, serde::Deserialize, PartialEq, Eq)]
+    pub struct AddTransactionResponse {
+        pub code: String, // TRANSACTION_RECEIVED
+        pub transaction_hash: TransactionHash,
+    }
+
     #[cfg(test)]
     mod serde_test {
         use pathfinder_common::macro_prelude::*;
@@ -2317,9 +2414,10 @@ mod tests {
             },
         };
 
-        let common = pathfinder_common::StateUpdate::from(gateway);
+        let common = pathfinder_common::StateUpdate::from(gateway.clone());
 
         assert_eq!(common, expected);
+        assert_eq!(super::StateUpdate::from(common), gateway);
     }
 
     mod receipts {
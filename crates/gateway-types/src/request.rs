@@ -175,7 +175,7 @@ pub mod add_transaction {
     }
 
     /// Account deployment transaction details.
-    #[derive(Debug, serde::Serialize)]
+    #[derive(Clone, Debug, serde::Serialize)]
     #[serde(tag = "version")]
     pub enum DeployAccount {
         #[serde(rename = "0x0")]
@@ -187,7 +187,7 @@ pub mod add_transaction {
     }
 
     #[serde_as]
-    #[derive(Debug, serde::Serialize)]
+    #[derive(Clone, Debug, serde::Serialize)]
     pub struct DeployAccountV0V1 {
         pub max_fee: Fee,
         #[serde_as(as = "Vec<TransactionSignatureElemAsDecimalStr>")]
@@ -201,7 +201,7 @@ pub mod add_transaction {
     }
 
     #[serde_as]
-    #[derive(Debug, serde::Serialize)]
+    #[derive(Clone, Debug, serde::Serialize)]
     pub struct DeployAccountV3 {
         pub signature: Vec<TransactionSignatureElem>,
         pub nonce: TransactionNonce,
@@ -218,7 +218,7 @@ pub mod add_transaction {
     }
 
     /// Invoke contract transaction details.
-    #[derive(Debug, serde::Serialize)]
+    #[derive(Clone, Debug, serde::Serialize)]
     #[serde(tag = "version")]
     pub enum InvokeFunction {
         #[serde(rename = "0x0")]
@@ -230,7 +230,7 @@ pub mod add_transaction {
     }
 
     #[serde_as]
-    #[derive(Debug, serde::Serialize)]
+    #[derive(Clone, Debug, serde::Serialize)]
     pub struct InvokeFunctionV0V1 {
         // AccountTransaction properties
         pub max_fee: Fee,
@@ -247,7 +247,7 @@ pub mod add_transaction {
     }
 
     #[serde_as]
-    #[derive(Debug, serde::Serialize)]
+    #[derive(Clone, Debug, serde::Serialize)]
     pub struct InvokeFunctionV3 {
         pub signature: Vec<TransactionSignatureElem>,
         pub nonce: TransactionNonce,
@@ -265,7 +265,7 @@ pub mod add_transaction {
 
     /// Declare transaction details.
     #[allow(clippy::large_enum_variant)]
-    #[derive(Debug, serde::Serialize)]
+    #[derive(Clone, Debug, serde::Serialize)]
     #[serde(tag = "version")]
     pub enum Declare {
         #[serde(rename = "0x0")]
@@ -279,7 +279,7 @@ pub mod add_transaction {
     }
 
     #[serde_as]
-    #[derive(Debug, serde::Serialize)]
+    #[derive(Clone, Debug, serde::Serialize)]
     pub struct DeclareV0V1V2 {
         // Transaction properties
         pub version: TransactionVersion,
@@ -298,7 +298,7 @@ pub mod add_transaction {
     }
 
     #[serde_as]
-    #[derive(Debug, serde::Serialize)]
+    #[derive(Clone, Debug, serde::Serialize)]
     pub struct DeclareV3 {
         pub signature: Vec<TransactionSignatureElem>,
         pub nonce: TransactionNonce,
@@ -319,7 +319,7 @@ pub mod add_transaction {
     ///
     /// This adds the "type" attribute to the JSON request according the type of
     /// the transaction (invoke or deploy).
-    #[derive(Debug, serde::Serialize)]
+    #[derive(Clone, Debug, serde::Serialize)]
     #[serde(tag = "type")]
     pub enum AddTransaction {
         #[serde(rename = "INVOKE_FUNCTION")]
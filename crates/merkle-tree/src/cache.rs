@@ -0,0 +1,227 @@
+//! An LRU cache of decoded trie nodes, shared between readers of the tries.
+
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use anyhow::Context;
+use cached::{Cached, SizedCache};
+use pathfinder_crypto::Felt;
+use pathfinder_storage::{StoredNode, Transaction};
+
+/// Identifies the trie, and therefore the database table, a node belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum Trie {
+    Class,
+    Contract,
+    Storage,
+}
+
+/// The trie generation, the trie and the node's storage index.
+type CacheKey = (u64, Trie, u64);
+
+/// The node and its hash are read separately, so either may be missing.
+#[derive(Debug, Clone, Default)]
+struct Entry {
+    node: Option<StoredNode>,
+    hash: Option<Felt>,
+}
+
+/// The approximate memory used by a single entry. The cache stores each key
+/// twice, and edge paths of up to 32 bytes are stored on the heap.
+const APPROX_ENTRY_SIZE: usize =
+    2 * std::mem::size_of::<CacheKey>() + std::mem::size_of::<Entry>() + 32;
+
+/// Caches decoded trie nodes by their storage index, so that hot paths through
+/// the tries aren't repeatedly read and decoded from the database.
+///
+/// Stored nodes are immutable, but the indices of deleted nodes may be reused.
+/// Entries are therefore keyed by the
+/// [trie generation](Transaction::trie_generation) as well, which changes
+/// whenever that can happen. Nodes are only cached once read, and a miss falls
+/// back to the database.
+#[derive(Debug, Clone)]
+pub struct TrieNodeCache(Arc<Mutex<SizedCache<CacheKey, Entry>>>);
+
+impl TrieNodeCache {
+    /// The memory budget of [TrieNodeCache::default], in bytes.
+    pub const DEFAULT_MEMORY_BUDGET: usize = 64 * 1024 * 1024;
+
+    /// Creates a cache using roughly up to `bytes` of memory.
+    pub fn with_memory_budget(bytes: usize) -> Self {
+        let size = (bytes / APPROX_ENTRY_SIZE).max(1);
+        Self(Arc::new(Mutex::new(SizedCache::with_size(size))))
+    }
+
+    fn locked_cache(&self) -> MutexGuard<'_, SizedCache<CacheKey, Entry>> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// The cache as seen by `tx`.
+    pub(crate) fn for_transaction(&self, tx: &Transaction<'_>) -> anyhow::Result<CachedNodes> {
+        let generation = tx.trie_generation().context("Querying trie generation")?;
+
+        Ok(CachedNodes {
+            cache: self.clone(),
+            generation,
+        })
+    }
+}
+
+impl Default for TrieNodeCache {
+    fn default() -> Self {
+        Self::with_memory_budget(Self::DEFAULT_MEMORY_BUDGET)
+    }
+}
+
+/// A [TrieNodeCache] restricted to the nodes of a single trie generation.
+pub(crate) struct CachedNodes {
+    cache: TrieNodeCache,
+    generation: u64,
+}
+
+impl CachedNodes {
+    /// Returns the node at `index`, using `load` to read it on a cache miss.
+    pub(crate) fn node(
+        &self,
+        trie: Trie,
+        index: u64,
+        load: impl FnOnce() -> anyhow::Result<Option<StoredNode>>,
+    ) -> anyhow::Result<Option<StoredNode>> {
+        let key = (self.generation, trie, index);
+
+        if let Some(node) = self
+            .cache
+            .locked_cache()
+            .cache_get(&key)
+            .and_then(|entry| entry.node.clone())
+        {
+            return Ok(Some(node));
+        }
+
+        let node = load()?;
+        if let Some(node) = &node {
+            let mut cache = self.cache.locked_cache();
+            let entry = cache.cache_get_or_set_with(key, Entry::default);
+            entry.node = Some(node.clone());
+        }
+
+        Ok(node)
+    }
+
+    /// Returns the hash of the node at `index`, using `load` to read it on a
+    /// cache miss.
+    pub(crate) fn hash(
+        &self,
+        trie: Trie,
+        index: u64,
+        load: impl FnOnce() -> anyhow::Result<Option<Felt>>,
+    ) -> anyhow::Result<Option<Felt>> {
+        let key = (self.generation, trie, index);
+
+        if let Some(hash) = self
+            .cache
+            .locked_cache()
+            .cache_get(&key)
+            .and_then(|entry| entry.hash)
+        {
+            return Ok(Some(hash));
+        }
+
+        let hash = load()?;
+        if let Some(hash) = hash {
+            let mut cache = self.cache.locked_cache();
+            let entry = cache.cache_get_or_set_with(key, Entry::default);
+            entry.hash = Some(hash);
+        }
+
+        Ok(hash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at_generation(cache: &TrieNodeCache, generation: u64) -> CachedNodes {
+        CachedNodes {
+            cache: cache.clone(),
+            generation,
+        }
+    }
+
+    #[test]
+    fn node_is_loaded_once() {
+        let cache = at_generation(&TrieNodeCache::default(), 0);
+        let node = StoredNode::Binary { left: 1, right: 2 };
+
+        let loaded = cache
+            .node(Trie::Storage, 7, || Ok(Some(node.clone())))
+            .unwrap();
+        assert_eq!(loaded, Some(node.clone()));
+
+        let cached = cache
+            .node(Trie::Storage, 7, || panic!("Node should be cached"))
+            .unwrap();
+        assert_eq!(cached, Some(node));
+    }
+
+    #[test]
+    fn tries_are_cached_separately() {
+        let cache = at_generation(&TrieNodeCache::default(), 0);
+
+        cache
+            .hash(Trie::Class, 1, || Ok(Some(Felt::from_u64(1))))
+            .unwrap();
+        let hash = cache
+            .hash(Trie::Contract, 1, || Ok(Some(Felt::from_u64(2))))
+            .unwrap();
+
+        assert_eq!(hash, Some(Felt::from_u64(2)));
+    }
+
+    #[test]
+    fn generations_are_cached_separately() {
+        let cache = TrieNodeCache::default();
+
+        at_generation(&cache, 0)
+            .node(Trie::Storage, 1, || Ok(Some(StoredNode::LeafBinary)))
+            .unwrap();
+        let node = at_generation(&cache, 1)
+            .node(Trie::Storage, 1, || {
+                Ok(Some(StoredNode::Binary { left: 2, right: 3 }))
+            })
+            .unwrap();
+
+        assert_eq!(node, Some(StoredNode::Binary { left: 2, right: 3 }));
+    }
+
+    #[test]
+    fn missing_nodes_are_not_cached() {
+        let cache = at_generation(&TrieNodeCache::default(), 0);
+
+        cache.node(Trie::Storage, 1, || Ok(None)).unwrap();
+        let node = cache
+            .node(Trie::Storage, 1, || Ok(Some(StoredNode::LeafBinary)))
+            .unwrap();
+
+        assert_eq!(node, Some(StoredNode::LeafBinary));
+    }
+
+    #[test]
+    fn memory_budget_limits_entries() {
+        let budgeted = TrieNodeCache::with_memory_budget(2 * APPROX_ENTRY_SIZE);
+        let cache = at_generation(&budgeted, 0);
+
+        for index in 0..3 {
+            cache
+                .hash(Trie::Storage, index, || Ok(Some(Felt::from_u64(index))))
+                .unwrap();
+        }
+
+        assert_eq!(budgeted.locked_cache().cache_size(), 2);
+        // The least recently used entry was evicted.
+        let hash = cache
+            .hash(Trie::Storage, 0, || Ok(Some(Felt::ZERO)))
+            .unwrap();
+        assert_eq!(hash, Some(Felt::ZERO));
+    }
+}
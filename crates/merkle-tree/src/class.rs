@@ -11,6 +11,7 @@ use pathfinder_common::{
 use pathfinder_crypto::Felt;
 use pathfinder_storage::{Transaction, TrieUpdate};
 
+use crate::cache::{CachedNodes, Trie, TrieNodeCache};
 use crate::tree::MerkleTree;
 
 /// A [Patricia Merkle tree](MerkleTree) used to calculate commitments to
@@ -26,7 +27,11 @@ pub struct ClassCommitmentTree<'tx> {
 
 impl<'tx> ClassCommitmentTree<'tx> {
     pub fn empty(tx: &'tx Transaction<'tx>) -> Self {
-        let storage = ClassStorage { tx, block: None };
+        let storage = ClassStorage {
+            tx,
+            block: None,
+            cache: None,
+        };
         let tree = MerkleTree::empty();
 
         Self { tree, storage }
@@ -43,6 +48,7 @@ impl<'tx> ClassCommitmentTree<'tx> {
         let storage = ClassStorage {
             tx,
             block: Some(block),
+            cache: None,
         };
         let tree = MerkleTree::new(root);
 
@@ -54,6 +60,12 @@ impl<'tx> ClassCommitmentTree<'tx> {
         self
     }
 
+    /// Reads nodes through the given cache.
+    pub fn with_node_cache(mut self, cache: &TrieNodeCache) -> anyhow::Result<Self> {
+        self.storage.cache = Some(cache.for_transaction(self.storage.tx)?);
+        Ok(self)
+    }
+
     /// Adds a leaf node for a Sierra -> CASM commitment.
     ///
     /// Note that the leaf value is _not_ the Cairo hash, but a hashed value
@@ -79,10 +91,12 @@ impl<'tx> ClassCommitmentTree<'tx> {
         block: BlockNumber,
         class_hash: ClassHash,
         root: u64,
+        cache: &TrieNodeCache,
     ) -> anyhow::Result<Option<Vec<TrieNode>>> {
         let storage = ClassStorage {
             tx,
             block: Some(block),
+            cache: Some(cache.for_transaction(tx)?),
         };
 
         MerkleTree::<PoseidonHash, 251>::get_proof(root, &storage, class_hash.0.view_bits())
@@ -92,15 +106,22 @@ impl<'tx> ClassCommitmentTree<'tx> {
 struct ClassStorage<'tx> {
     tx: &'tx Transaction<'tx>,
     block: Option<BlockNumber>,
+    cache: Option<CachedNodes>,
 }
 
 impl crate::storage::Storage for ClassStorage<'_> {
     fn get(&self, index: u64) -> anyhow::Result<Option<pathfinder_storage::StoredNode>> {
-        self.tx.class_trie_node(index)
+        match &self.cache {
+            Some(cache) => cache.node(Trie::Class, index, || self.tx.class_trie_node(index)),
+            None => self.tx.class_trie_node(index),
+        }
     }
 
     fn hash(&self, index: u64) -> anyhow::Result<Option<Felt>> {
-        self.tx.class_trie_node_hash(index)
+        match &self.cache {
+            Some(cache) => cache.hash(Trie::Class, index, || self.tx.class_trie_node_hash(index)),
+            None => self.tx.class_trie_node_hash(index),
+        }
     }
 
     fn leaf(
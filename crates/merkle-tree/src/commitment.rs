@@ -0,0 +1,368 @@
+//! Leaf hashes of the per-block transaction and receipt commitments, and
+//! proofs of membership in them.
+//!
+//! Each commitment is the root of a [TransactionOrEventTree] whose leaves are
+//! keyed by the index of the transaction within its block.
+
+use std::sync::LazyLock;
+
+use anyhow::Context;
+use pathfinder_common::hash::{FeltHash, PedersenHash, PoseidonHash};
+use pathfinder_common::receipt::{ExecutionStatus, Receipt};
+use pathfinder_common::transaction::{Transaction, TransactionVariant};
+use pathfinder_common::trie::TrieNode;
+use pathfinder_common::{StarknetVersion, TransactionSignatureElem};
+use pathfinder_crypto::hash::{pedersen_hash, poseidon_hash_many, HashChain, PoseidonHasher};
+use pathfinder_crypto::{Felt, MontFelt};
+use sha3::Digest;
+
+use crate::TransactionOrEventTree;
+
+const V_0_11_1: StarknetVersion = StarknetVersion::new(0, 11, 1, 0);
+
+/// A proof that a leaf is part of a transaction or receipt commitment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommitmentProof {
+    /// The root of the commitment tree.
+    pub root: Felt,
+    /// The value of the proven leaf.
+    pub leaf: Felt,
+    /// The nodes from the root down to the leaf, root first.
+    pub nodes: Vec<TrieNode>,
+}
+
+/// Builds the commitment tree from `hashes` and returns its root.
+pub fn commitment_root<H: FeltHash>(hashes: Vec<Felt>) -> anyhow::Result<Felt> {
+    commitment_tree::<H>(&hashes)?.commit()
+}
+
+/// Builds the commitment tree from `hashes` and proves the leaf at `index`.
+pub fn commitment_proof<H: FeltHash>(
+    hashes: &[Felt],
+    index: usize,
+) -> anyhow::Result<CommitmentProof> {
+    let leaf = *hashes
+        .get(index)
+        .context("Leaf index is outside of the commitment tree")?;
+    let index = index
+        .try_into()
+        .expect("too many leaves while calculating commitment proof");
+
+    let (root, nodes) = commitment_tree::<H>(hashes)?.commit_with_proof(index)?;
+
+    Ok(CommitmentProof { root, leaf, nodes })
+}
+
+fn commitment_tree<H: FeltHash>(hashes: &[Felt]) -> anyhow::Result<TransactionOrEventTree<H>> {
+    let mut tree: TransactionOrEventTree<H> = Default::default();
+
+    hashes
+        .iter()
+        .enumerate()
+        .try_for_each(|(idx, final_hash)| {
+            let idx: u64 = idx
+                .try_into()
+                .expect("too many transactions while calculating commitment");
+            tree.set(idx, *final_hash)
+        })
+        .context("Building transaction commitment tree")?;
+
+    Ok(tree)
+}
+
+/// Proves the membership of the transaction at `index` in the transaction
+/// commitment of a block with the given `version`.
+pub fn transaction_commitment_proof(
+    transactions: &[Transaction],
+    version: StarknetVersion,
+    index: usize,
+) -> anyhow::Result<CommitmentProof> {
+    let hashes = transactions
+        .iter()
+        .map(|tx| transaction_hash_with_signature(tx, version))
+        .collect::<Vec<_>>();
+
+    if version < StarknetVersion::V_0_13_2 {
+        commitment_proof::<PedersenHash>(&hashes, index)
+    } else {
+        commitment_proof::<PoseidonHash>(&hashes, index)
+    }
+}
+
+/// Proves the membership of the receipt at `index` in the receipt commitment
+/// of a block.
+///
+/// Receipt commitments only exist for blocks from Starknet 0.13.2 onwards.
+pub fn receipt_commitment_proof(
+    receipts: &[Receipt],
+    index: usize,
+) -> anyhow::Result<CommitmentProof> {
+    let hashes = receipts.iter().map(receipt_hash).collect::<Vec<_>>();
+
+    commitment_proof::<PoseidonHash>(&hashes, index)
+}
+
+/// Compute the transaction commitment leaf of a transaction, which combines
+/// the transaction hash with its signature.
+pub fn transaction_hash_with_signature(tx: &Transaction, version: StarknetVersion) -> Felt {
+    if version < V_0_11_1 {
+        transaction_hash_with_signature_pre_0_11_1(tx)
+    } else if version < StarknetVersion::V_0_13_2 {
+        transaction_hash_with_signature_pre_0_13_2(tx)
+    } else {
+        transaction_hash_with_signature_0_13_2(tx)
+    }
+}
+
+/// Compute the combined hash of the transaction hash and the signature.
+///
+/// Since the transaction hash doesn't take the signature values as its input
+/// computing the transaction commitent uses a hash value that combines
+/// the transaction hash with the array of signature values.
+///
+/// Note that for non-invoke transactions we don't actually have signatures. The
+/// cairo-lang uses an empty list (whose hash is not the ZERO value!) in that
+/// case.
+fn transaction_hash_with_signature_pre_0_11_1(tx: &Transaction) -> Felt {
+    static HASH_OF_EMPTY_LIST: LazyLock<Felt> = LazyLock::new(|| HashChain::default().finalize());
+
+    let signature_hash = match &tx.variant {
+        TransactionVariant::InvokeV0(tx) => calculate_signature_hash(&tx.signature),
+        TransactionVariant::InvokeV1(tx) => calculate_signature_hash(&tx.signature),
+        TransactionVariant::InvokeV3(tx) => calculate_signature_hash(&tx.signature),
+        TransactionVariant::DeclareV0(_)
+        | TransactionVariant::DeclareV1(_)
+        | TransactionVariant::DeclareV2(_)
+        | TransactionVariant::DeclareV3(_)
+        | TransactionVariant::DeployV0(_)
+        | TransactionVariant::DeployV1(_)
+        | TransactionVariant::DeployAccountV1(_)
+        | TransactionVariant::DeployAccountV3(_)
+        | TransactionVariant::L1Handler(_) => *HASH_OF_EMPTY_LIST,
+    };
+
+    pedersen_hash(tx.hash.0, signature_hash)
+}
+
+/// Compute the combined hash of the transaction hash and the signature for
+/// block before v0.13.2.
+///
+/// Since the transaction hash doesn't take the signature values as its input
+/// computing the transaction commitment uses a hash value that combines
+/// the transaction hash with the array of signature values.
+///
+/// Note that for non-invoke transactions we don't actually have signatures. The
+/// cairo-lang uses an empty list (whose hash is not the ZERO value!) in that
+/// case.
+fn transaction_hash_with_signature_pre_0_13_2(tx: &Transaction) -> Felt {
+    static HASH_OF_EMPTY_LIST: LazyLock<Felt> = LazyLock::new(|| HashChain::default().finalize());
+
+    let signature_hash = match &tx.variant {
+        TransactionVariant::InvokeV0(tx) => calculate_signature_hash(&tx.signature),
+        TransactionVariant::DeclareV0(tx) => calculate_signature_hash(&tx.signature),
+        TransactionVariant::DeclareV1(tx) => calculate_signature_hash(&tx.signature),
+        TransactionVariant::DeclareV2(tx) => calculate_signature_hash(&tx.signature),
+        TransactionVariant::DeclareV3(tx) => calculate_signature_hash(&tx.signature),
+        TransactionVariant::DeployAccountV1(tx) => calculate_signature_hash(&tx.signature),
+        TransactionVariant::DeployAccountV3(tx) => calculate_signature_hash(&tx.signature),
+        TransactionVariant::InvokeV1(tx) => calculate_signature_hash(&tx.signature),
+        TransactionVariant::InvokeV3(tx) => calculate_signature_hash(&tx.signature),
+        TransactionVariant::DeployV0(_)
+        | TransactionVariant::DeployV1(_)
+        | TransactionVariant::L1Handler(_) => *HASH_OF_EMPTY_LIST,
+    };
+
+    pedersen_hash(tx.hash.0, signature_hash)
+}
+
+/// Compute the combined hash of the transaction hash and the signature.
+///
+/// [Reference code from StarkWare](https://github.com/starkware-libs/starknet-api/blob/5565e5282f5fead364a41e49c173940fd83dee00/src/block_hash/block_hash_calculator.rs#L95-L98).
+fn transaction_hash_with_signature_0_13_2(tx: &Transaction) -> Felt {
+    let signature = match &tx.variant {
+        TransactionVariant::InvokeV0(tx) => tx.signature.as_slice(),
+        TransactionVariant::DeclareV0(tx) => tx.signature.as_slice(),
+        TransactionVariant::DeclareV1(tx) => tx.signature.as_slice(),
+        TransactionVariant::DeclareV2(tx) => tx.signature.as_slice(),
+        TransactionVariant::DeclareV3(tx) => tx.signature.as_slice(),
+        TransactionVariant::DeployAccountV1(tx) => tx.signature.as_slice(),
+        TransactionVariant::DeployAccountV3(tx) => tx.signature.as_slice(),
+        TransactionVariant::InvokeV1(tx) => tx.signature.as_slice(),
+        TransactionVariant::InvokeV3(tx) => tx.signature.as_slice(),
+        TransactionVariant::DeployV0(_)
+        | TransactionVariant::DeployV1(_)
+        | TransactionVariant::L1Handler(_) => &[],
+    };
+
+    let signature = if signature.is_empty() {
+        &[TransactionSignatureElem::ZERO]
+    } else {
+        signature
+    };
+
+    let mut hasher = PoseidonHasher::new();
+    hasher.write(tx.hash.0.into());
+    for elem in signature {
+        hasher.write(elem.0.into());
+    }
+    hasher.finish().into()
+}
+
+fn calculate_signature_hash(signature: &[TransactionSignatureElem]) -> Felt {
+    let mut hash = HashChain::default();
+    for s in signature {
+        hash.update(s.0);
+    }
+    hash.finalize()
+}
+
+/// Compute the receipt commitment leaf of a receipt.
+pub fn receipt_hash(receipt: &Receipt) -> Felt {
+    poseidon_hash_many(&[
+        receipt.transaction_hash.0.into(),
+        receipt.actual_fee.0.into(),
+        // Calculate hash of messages sent.
+        {
+            let mut hasher = PoseidonHasher::new();
+            hasher.write((receipt.l2_to_l1_messages.len() as u64).into());
+            for msg in &receipt.l2_to_l1_messages {
+                hasher.write(msg.from_address.0.into());
+                hasher.write(msg.to_address.0.into());
+                hasher.write((msg.payload.len() as u64).into());
+                for payload in &msg.payload {
+                    hasher.write(payload.0.into());
+                }
+            }
+            hasher.finish()
+        },
+        // Revert reason.
+        match &receipt.execution_status {
+            ExecutionStatus::Succeeded => MontFelt::ZERO,
+            ExecutionStatus::Reverted { reason } => {
+                let mut keccak = sha3::Keccak256::default();
+                keccak.update(reason.as_bytes());
+                let mut hashed_bytes: [u8; 32] = keccak.finalize().into();
+                hashed_bytes[0] &= 0b00000011_u8; // Discard the six MSBs.
+                MontFelt::from_be_bytes(hashed_bytes)
+            }
+        },
+        // Execution resources:
+        // L2 gas
+        MontFelt::ZERO,
+        // L1 gas consumed
+        receipt.execution_resources.total_gas_consumed.l1_gas.into(),
+        // L1 data gas consumed
+        receipt
+            .execution_resources
+            .total_gas_consumed
+            .l1_data_gas
+            .into(),
+    ])
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use bitvec::prelude::Msb0;
+    use bitvec::view::BitView;
+    use pathfinder_common::felt;
+    use pathfinder_common::transaction::InvokeTransactionV3;
+    use pathfinder_common::TransactionHash;
+
+    use super::*;
+
+    /// Walks the proof from the root down to the leaf, the way an external
+    /// verifier would.
+    fn verify<H: FeltHash>(proof: &CommitmentProof, index: u64) -> bool {
+        let key = index.to_be_bytes().view_bits::<Msb0>().to_owned();
+        let mut expected = proof.root;
+        let mut height = 0;
+
+        for node in &proof.nodes {
+            if node.hash::<H>() != expected {
+                return false;
+            }
+
+            expected = match node {
+                TrieNode::Binary { left, right } => {
+                    let bit = key[height];
+                    height += 1;
+                    if bit {
+                        *right
+                    } else {
+                        *left
+                    }
+                }
+                TrieNode::Edge { child, path } => {
+                    if key[height..height + path.len()] != path[..] {
+                        return false;
+                    }
+                    height += path.len();
+                    *child
+                }
+            };
+        }
+
+        height == 64 && expected == proof.leaf
+    }
+
+    #[test]
+    fn proof_matches_root() {
+        let hashes = [1u64, 2, 3, 4, 5].map(Felt::from_u64);
+
+        for index in 0..hashes.len() {
+            let proof = commitment_proof::<PedersenHash>(&hashes, index).unwrap();
+
+            assert_eq!(
+                proof.root,
+                commitment_root::<PedersenHash>(hashes.to_vec()).unwrap()
+            );
+            assert_eq!(proof.leaf, hashes[index]);
+            assert!(verify::<PedersenHash>(&proof, index as u64));
+        }
+    }
+
+    #[test]
+    fn single_leaf_proof() {
+        let hashes = [Felt::from_u64(7)];
+        let proof = commitment_proof::<PoseidonHash>(&hashes, 0).unwrap();
+
+        assert_eq!(proof.nodes.len(), 1);
+        assert!(verify::<PoseidonHash>(&proof, 0));
+    }
+
+    #[test]
+    fn index_out_of_range() {
+        let hashes = [Felt::from_u64(1)];
+        commitment_proof::<PoseidonHash>(&hashes, 1).unwrap_err();
+    }
+
+    /// Source:
+    /// https://github.com/starkware-libs/starknet-api/blob/5565e5282f5fead364a41e49c173940fd83dee00/src/block_hash/transaction_commitment_test.rs#L32.
+    #[test]
+    fn transaction_proof_0_13_2() {
+        let transaction = Transaction {
+            hash: TransactionHash(Felt::ONE),
+            variant: TransactionVariant::InvokeV3(InvokeTransactionV3 {
+                signature: vec![
+                    TransactionSignatureElem(Felt::from_u64(2)),
+                    TransactionSignatureElem(Felt::from_u64(3)),
+                ],
+                ..Default::default()
+            }),
+        };
+
+        let proof = transaction_commitment_proof(
+            &[transaction.clone(), transaction],
+            StarknetVersion::V_0_13_2,
+            1,
+        )
+        .unwrap();
+
+        assert_eq!(
+            proof.root,
+            felt!("0x0282b635972328bd1cfa86496fe920d20bd9440cd78ee8dc90ae2b383d664dcf")
+        );
+        assert!(verify::<PoseidonHash>(&proof, 1));
+    }
+}
@@ -23,6 +23,7 @@ use pathfinder_common::{
 use pathfinder_crypto::Felt;
 use pathfinder_storage::{Transaction, TrieUpdate};
 
+use crate::cache::{CachedNodes, Trie, TrieNodeCache};
 use crate::merkle_node::InternalNode;
 use crate::tree::{MerkleTree, Visit};
 
@@ -44,6 +45,7 @@ impl<'tx> ContractsStorageTree<'tx> {
             tx,
             block: None,
             contract,
+            cache: None,
         };
         let tree = MerkleTree::empty();
 
@@ -66,6 +68,7 @@ impl<'tx> ContractsStorageTree<'tx> {
             tx,
             block: Some(block),
             contract,
+            cache: None,
         };
         let tree = MerkleTree::new(root);
 
@@ -77,6 +80,12 @@ impl<'tx> ContractsStorageTree<'tx> {
         self
     }
 
+    /// Reads nodes through the given cache.
+    pub fn with_node_cache(mut self, cache: &TrieNodeCache) -> anyhow::Result<Self> {
+        self.storage.cache = Some(cache.for_transaction(self.storage.tx)?);
+        Ok(self)
+    }
+
     /// Generates a proof for `key`. See [`MerkleTree::get_proof`].
     pub fn get_proof(
         tx: &'tx Transaction<'tx>,
@@ -84,11 +93,13 @@ impl<'tx> ContractsStorageTree<'tx> {
         block: BlockNumber,
         key: &BitSlice<u8, Msb0>,
         root: u64,
+        cache: &TrieNodeCache,
     ) -> anyhow::Result<Option<Vec<TrieNode>>> {
         let storage = ContractStorage {
             tx,
             block: Some(block),
             contract,
+            cache: Some(cache.for_transaction(tx)?),
         };
 
         MerkleTree::<PedersenHash, 251>::get_proof(root, &storage, key)
@@ -130,7 +141,11 @@ pub struct StorageCommitmentTree<'tx> {
 
 impl<'tx> StorageCommitmentTree<'tx> {
     pub fn empty(tx: &'tx Transaction<'tx>) -> Self {
-        let storage = StorageTrieStorage { tx, block: None };
+        let storage = StorageTrieStorage {
+            tx,
+            block: None,
+            cache: None,
+        };
         let tree = MerkleTree::empty();
 
         Self { tree, storage }
@@ -147,6 +162,7 @@ impl<'tx> StorageCommitmentTree<'tx> {
         let storage = StorageTrieStorage {
             tx,
             block: Some(block),
+            cache: None,
         };
 
         let tree = MerkleTree::new(root);
@@ -159,6 +175,12 @@ impl<'tx> StorageCommitmentTree<'tx> {
         self
     }
 
+    /// Reads nodes through the given cache.
+    pub fn with_node_cache(mut self, cache: &TrieNodeCache) -> anyhow::Result<Self> {
+        self.storage.cache = Some(cache.for_transaction(self.storage.tx)?);
+        Ok(self)
+    }
+
     pub fn set(
         &mut self,
         address: ContractAddress,
@@ -188,10 +210,12 @@ impl<'tx> StorageCommitmentTree<'tx> {
         block: BlockNumber,
         address: &ContractAddress,
         root: u64,
+        cache: &TrieNodeCache,
     ) -> anyhow::Result<Option<Vec<TrieNode>>> {
         let storage = StorageTrieStorage {
             tx,
             block: Some(block),
+            cache: Some(cache.for_transaction(tx)?),
         };
 
         MerkleTree::<PedersenHash, 251>::get_proof(root, &storage, address.view_bits())
@@ -210,15 +234,24 @@ struct ContractStorage<'tx> {
     tx: &'tx Transaction<'tx>,
     block: Option<BlockNumber>,
     contract: ContractAddress,
+    cache: Option<CachedNodes>,
 }
 
 impl crate::storage::Storage for ContractStorage<'_> {
     fn get(&self, index: u64) -> anyhow::Result<Option<pathfinder_storage::StoredNode>> {
-        self.tx.contract_trie_node(index)
+        match &self.cache {
+            Some(cache) => cache.node(Trie::Contract, index, || self.tx.contract_trie_node(index)),
+            None => self.tx.contract_trie_node(index),
+        }
     }
 
     fn hash(&self, index: u64) -> anyhow::Result<Option<Felt>> {
-        self.tx.contract_trie_node_hash(index)
+        match &self.cache {
+            Some(cache) => cache.hash(Trie::Contract, index, || {
+                self.tx.contract_trie_node_hash(index)
+            }),
+            None => self.tx.contract_trie_node_hash(index),
+        }
     }
 
     fn leaf(&self, path: &BitSlice<u8, Msb0>) -> anyhow::Result<Option<Felt>> {
@@ -243,15 +276,24 @@ impl crate::storage::Storage for ContractStorage<'_> {
 struct StorageTrieStorage<'tx> {
     tx: &'tx Transaction<'tx>,
     block: Option<BlockNumber>,
+    cache: Option<CachedNodes>,
 }
 
 impl crate::storage::Storage for StorageTrieStorage<'_> {
     fn get(&self, index: u64) -> anyhow::Result<Option<pathfinder_storage::StoredNode>> {
-        self.tx.storage_trie_node(index)
+        match &self.cache {
+            Some(cache) => cache.node(Trie::Storage, index, || self.tx.storage_trie_node(index)),
+            None => self.tx.storage_trie_node(index),
+        }
     }
 
     fn hash(&self, index: u64) -> anyhow::Result<Option<Felt>> {
-        self.tx.storage_trie_node_hash(index)
+        match &self.cache {
+            Some(cache) => cache.hash(Trie::Storage, index, || {
+                self.tx.storage_trie_node_hash(index)
+            }),
+            None => self.tx.storage_trie_node_hash(index),
+        }
     }
 
     fn leaf(&self, path: &BitSlice<u8, Msb0>) -> anyhow::Result<Option<Felt>> {
@@ -1,3 +1,5 @@
+pub mod cache;
+pub mod commitment;
 pub mod contract_state;
 pub mod merkle_node;
 pub mod storage;
@@ -7,6 +9,7 @@ pub mod class;
 mod contract;
 mod transaction;
 
+pub use cache::TrieNodeCache;
 pub use class::ClassCommitmentTree;
 pub use contract::{ContractsStorageTree, StorageCommitmentTree};
 pub use transaction::TransactionOrEventTree;
@@ -1,7 +1,12 @@
+use std::collections::HashMap;
+
+use anyhow::Context;
+use bitvec::prelude::{BitSlice, BitVec, Msb0};
 use bitvec::view::BitView;
 use pathfinder_common::hash::FeltHash;
+use pathfinder_common::trie::TrieNode;
 use pathfinder_crypto::Felt;
-use pathfinder_storage::StoredNode;
+use pathfinder_storage::{Node, NodeRef, StoredNode};
 
 use crate::tree::MerkleTree;
 
@@ -38,14 +43,32 @@ impl crate::storage::Storage for NullStorage {
         Ok(None)
     }
 
-    fn leaf(
-        &self,
-        _: &bitvec::slice::BitSlice<u8, bitvec::prelude::Msb0>,
-    ) -> anyhow::Result<Option<Felt>> {
+    fn leaf(&self, _: &BitSlice<u8, Msb0>) -> anyhow::Result<Option<Felt>> {
         Ok(None)
     }
 }
 
+/// [Storage](crate::storage::Storage) type holding a single committed tree in
+/// memory, which is all that is needed to produce proofs.
+struct MemoryStorage {
+    nodes: Vec<(Felt, StoredNode)>,
+    leaves: HashMap<BitVec<u8, Msb0>, Felt>,
+}
+
+impl crate::storage::Storage for MemoryStorage {
+    fn get(&self, index: u64) -> anyhow::Result<Option<StoredNode>> {
+        Ok(self.nodes.get(index as usize).map(|(_, node)| node.clone()))
+    }
+
+    fn hash(&self, index: u64) -> anyhow::Result<Option<Felt>> {
+        Ok(self.nodes.get(index as usize).map(|(hash, _)| *hash))
+    }
+
+    fn leaf(&self, path: &BitSlice<u8, Msb0>) -> anyhow::Result<Option<Felt>> {
+        Ok(self.leaves.get(path).copied())
+    }
+}
+
 impl<H: FeltHash> TransactionOrEventTree<H> {
     pub fn set(&mut self, index: u64, value: Felt) -> anyhow::Result<()> {
         let key = index.to_be_bytes().view_bits().to_owned();
@@ -57,6 +80,51 @@ impl<H: FeltHash> TransactionOrEventTree<H> {
             .commit(&NullStorage {})
             .map(|update| update.root_commitment)
     }
+
+    /// Commits the tree and returns its root along with the proof for the leaf
+    /// at `index`, root first.
+    pub fn commit_with_proof(self, index: u64) -> anyhow::Result<(Felt, Vec<TrieNode>)> {
+        let leaves = self.tree.leaves().clone();
+        let update = self.tree.commit(&NullStorage {})?;
+
+        // The tree starts out empty, so every node is new and refers to its
+        // children by their position in the update.
+        let nodes = update
+            .nodes_added
+            .into_iter()
+            .map(|(hash, node)| {
+                let node = match node {
+                    Node::Binary {
+                        left: NodeRef::Index(left),
+                        right: NodeRef::Index(right),
+                    } => StoredNode::Binary {
+                        left: left as u64,
+                        right: right as u64,
+                    },
+                    Node::Edge {
+                        child: NodeRef::Index(child),
+                        path,
+                    } => StoredNode::Edge {
+                        child: child as u64,
+                        path,
+                    },
+                    Node::LeafBinary => StoredNode::LeafBinary,
+                    Node::LeafEdge { path } => StoredNode::LeafEdge { path },
+                    other => anyhow::bail!("Unexpected reference to storage: {other:?}"),
+                };
+                Ok((hash, node))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let root = nodes.len().checked_sub(1).context("Tree is empty")? as u64;
+        let storage = MemoryStorage { nodes, leaves };
+        let key = index.to_be_bytes().view_bits::<Msb0>().to_owned();
+
+        let proof =
+            MerkleTree::<H, 64>::get_proof(root, &storage, &key)?.context("Proof is missing")?;
+
+        Ok((update.root_commitment, proof))
+    }
 }
 
 #[cfg(test)]
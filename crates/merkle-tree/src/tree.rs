@@ -105,6 +105,11 @@ impl<H: FeltHash, const HEIGHT: usize> MerkleTree<H, HEIGHT> {
         }
     }
 
+    /// The leaves which have been set since the tree was loaded.
+    pub(crate) fn leaves(&self) -> &HashMap<BitVec<u8, Msb0>, Felt> {
+        &self.leaves
+    }
+
     /// Commits all tree mutations and returns the [changes](TrieUpdate) to the
     /// tree.
     pub fn commit(self, storage: &impl Storage) -> anyhow::Result<TrieUpdate> {
@@ -137,6 +137,8 @@ impl ToDto<p2p_proto::header::SignedBlockHeader> for SignedBlockHeader {
                 r: self.signature.r.0,
                 s: self.signature.s.0,
             }],
+            l2_gas_price_fri: Some(self.header.strk_l2_gas_price.0),
+            l2_gas_price_wei: Some(self.header.eth_l2_gas_price.0),
         }
     }
 }
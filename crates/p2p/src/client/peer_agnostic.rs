@@ -292,6 +292,64 @@ impl EventStream for Client {
 }
 
 impl BlockClient for Client {
+    async fn headers_for_blocks(
+        self,
+        start: BlockNumber,
+        stop: BlockNumber,
+    ) -> Option<(PeerId, Vec<SignedBlockHeader>)> {
+        let limit = stop.get().checked_sub(start.get())? + 1;
+        let request = BlockHeadersRequest {
+            iteration: Iteration {
+                start: start.get().into(),
+                direction: Direction::Forward,
+                limit,
+                step: 1.into(),
+            },
+        };
+
+        let peers = self.get_random_peers().await;
+
+        'next_peer: for peer in peers {
+            let Ok(mut stream) = self
+                .inner
+                .send_headers_sync_request(peer, request)
+                .await
+                .inspect_err(|error| tracing::debug!(%peer, %error, "Headers request failed"))
+            else {
+                continue;
+            };
+
+            let mut headers = Vec::new();
+
+            while let Some(resp) = stream.next().await {
+                match resp {
+                    Ok(BlockHeadersResponse::Header(hdr)) => {
+                        match SignedBlockHeader::try_from_dto(*hdr) {
+                            Ok(hdr) => headers.push(hdr),
+                            Err(error) => {
+                                tracing::debug!(%peer, %error, "Header response stream failed");
+                                continue 'next_peer;
+                            }
+                        }
+                    }
+                    Ok(BlockHeadersResponse::Fin) => break,
+                    Err(error) => {
+                        tracing::debug!(%peer, %error, "Header response stream failed");
+                        continue 'next_peer;
+                    }
+                }
+            }
+
+            if headers.len() as u64 == limit {
+                return Some((peer, headers));
+            }
+
+            tracing::debug!(%peer, expected=%limit, actual=%headers.len(), "Incomplete headers response");
+        }
+
+        None
+    }
+
     async fn transactions_for_block(
         self,
         block: BlockNumber,
@@ -76,6 +76,14 @@ pub trait EventStream {
 }
 
 pub trait BlockClient {
+    /// Headers of `start..=stop` from a single peer, the peer is expected to
+    /// have all of them.
+    fn headers_for_blocks(
+        self,
+        start: BlockNumber,
+        stop: BlockNumber,
+    ) -> impl Future<Output = Option<(PeerId, Vec<SignedBlockHeader>)>> + Send;
+
     fn transactions_for_block(
         self,
         block: BlockNumber,
@@ -36,6 +36,10 @@ pub struct SignedBlockHeader {
     pub data_gas_price_wei: u128,
     pub l1_data_availability_mode: L1DataAvailabilityMode,
     pub signatures: Vec<ConsensusSignature>,
+    #[optional]
+    pub l2_gas_price_fri: Option<u128>,
+    #[optional]
+    pub l2_gas_price_wei: Option<u128>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Dummy)]
@@ -80,6 +84,8 @@ impl<T> Dummy<T> for SignedBlockHeader {
             data_gas_price_wei: Faker.fake_with_rng(rng),
             l1_data_availability_mode: Faker.fake_with_rng(rng),
             signatures: Faker.fake_with_rng(rng),
+            l2_gas_price_fri: Faker.fake_with_rng(rng),
+            l2_gas_price_wei: Faker.fake_with_rng(rng),
         }
     }
 }
@@ -470,6 +470,10 @@ fn resolve_block(
         receipt_commitment: Some(receipt_commitment),
         state_diff_commitment: Some(header.state_diff_commitment),
         state_diff_length: Some(header.state_diff_length),
+        l2_gas_price: Some(GasPrices {
+            price_in_wei: header.eth_l2_gas_price,
+            price_in_fri: header.strk_l2_gas_price,
+        }),
     })
 }
 
@@ -0,0 +1,70 @@
+use std::num::NonZeroU32;
+
+use anyhow::Context;
+use pathfinder_common::BlockNumber;
+use pathfinder_storage::{BlockId, StorageBuilder};
+
+/// Verify state commitments in a pathfinder database.
+///
+/// Recomputes the global state commitment of each block in the range from the
+/// nodes and leaves of the storage and class tries and compares it against the
+/// value stored in the block header. Exits with a non-zero status on the first mismatch, so it can
+/// be used from monitoring scripts.
+///
+/// The range defaults to the whole database. Note that for a database with
+/// pruned tries only the most recent blocks can be verified.
+///
+/// Usage:
+/// `cargo run --release -p pathfinder --example verify_state_commitments
+/// ./mainnet.sqlite [from] [to]`
+fn main() -> anyhow::Result<()> {
+    tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .compact()
+        .init();
+
+    let database_path = std::env::args().nth(1).unwrap();
+    let storage = StorageBuilder::file(database_path.into())
+        .migrate()?
+        .create_read_only_pool(NonZeroU32::new(1).unwrap())?;
+
+    let latest_block = {
+        let mut db = storage
+            .connection()
+            .context("Opening database connection")?;
+        let tx = db.transaction()?;
+        tx.block_id(BlockId::Latest)
+            .context("Fetching latest block number")?
+            .context("No latest block number")?
+            .0
+    };
+
+    let from = std::env::args()
+        .nth(2)
+        .map(|s| s.parse::<u64>().context("Parsing start block"))
+        .transpose()?
+        .map(BlockNumber::new_or_panic)
+        .unwrap_or(BlockNumber::GENESIS);
+    let to = std::env::args()
+        .nth(3)
+        .map(|s| s.parse::<u64>().context("Parsing end block"))
+        .transpose()?
+        .map(BlockNumber::new_or_panic)
+        .unwrap_or(latest_block);
+
+    tracing::info!(%from, %to, "Verifying state commitments");
+
+    match pathfinder_storage::verify_state_commitments(&storage, from, to)? {
+        Some(mismatch) => {
+            anyhow::bail!(
+                "State commitment mismatch at block {}: stored {}, derived {}",
+                mismatch.block_number,
+                mismatch.stored,
+                mismatch.derived
+            );
+        }
+        None => tracing::info!(%from, %to, "State commitments match"),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,313 @@
+//! Scheduled backups of the database to S3-compatible object storage.
+//!
+//! Each backup is a database snapshot, see [pathfinder_storage::snapshot],
+//! uploaded as `<prefix><timestamp>.sqlite.zst`. The latest block of the
+//! snapshot is stored in the object's `block-number` and `block-hash` metadata,
+//! which is what `--sync.checkpoint` needs to restore it. Once a backup has
+//! been uploaded, the oldest backups beyond the retention limit are deleted.
+
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::Context;
+use pathfinder_rpc::backup::{Backup, BackupFailure, BackupStatus};
+use tokio::io::AsyncReadExt;
+use tokio::sync::watch;
+
+mod s3;
+
+/// The smallest part of a multipart upload. S3 allows at most
+/// [MAX_PARTS] parts, so larger backups use larger parts.
+const MIN_PART_SIZE: u64 = 64 * 1024 * 1024;
+const MAX_PARTS: u64 = 10_000;
+
+const KEY_SUFFIX: &str = ".sqlite.zst";
+
+#[derive(Clone)]
+pub struct BackupConfig {
+    /// The time between the end of a backup and the start of the next one.
+    pub interval: Duration,
+    /// The number of backups kept in the bucket.
+    pub retention: NonZeroUsize,
+    /// Prepended to the key of every backup.
+    pub prefix: String,
+    pub s3: S3Config,
+}
+
+#[derive(Clone)]
+pub struct S3Config {
+    /// The path-style URL of the bucket, e.g.
+    /// `https://s3.eu-central-1.amazonaws.com/my-bucket`.
+    pub bucket_url: reqwest::Url,
+    pub region: String,
+    pub access_key_id: String,
+    pub secret_access_key: String,
+}
+
+/// Backs up the database at `database` every `config.interval`, publishing the
+/// outcome to `status`. Runs until the task is dropped.
+///
+/// The snapshot is staged next to the database before it is uploaded.
+pub async fn run(config: BackupConfig, database: PathBuf, status: watch::Sender<BackupStatus>) {
+    let client = s3::Client::new(config.s3.clone());
+    let mut staging = database.clone().into_os_string();
+    staging.push(".backup.zst");
+    let staging = PathBuf::from(staging);
+
+    loop {
+        status.send_modify(|status| {
+            status.next_backup_at = Some(unix_now() + config.interval.as_secs());
+        });
+        tokio::time::sleep(config.interval).await;
+        status.send_modify(|status| status.in_progress = true);
+
+        let result = backup(&client, &config, &database, &staging).await;
+        let uploaded = result.is_ok();
+
+        status.send_modify(|status| {
+            status.in_progress = false;
+            match result {
+                Ok(backup) => {
+                    tracing::info!(key=%backup.key, size=%backup.size, "Database backup uploaded");
+                    status.last_backup = Some(backup);
+                    status.last_failure = None;
+                }
+                Err(error) => {
+                    tracing::warn!(?error, "Database backup failed");
+                    status.last_failure = Some(BackupFailure {
+                        error: format!("{error:#}"),
+                        failed_at: unix_now(),
+                    });
+                }
+            }
+        });
+
+        if uploaded {
+            if let Err(error) = prune(&client, &config).await {
+                tracing::warn!(?error, "Deleting old database backups failed");
+            }
+        }
+    }
+}
+
+async fn backup(
+    client: &s3::Client,
+    config: &BackupConfig,
+    database: &Path,
+    staging: &Path,
+) -> anyhow::Result<Backup> {
+    let key = format!(
+        "{}{}{KEY_SUFFIX}",
+        config.prefix,
+        basic_timestamp(time::OffsetDateTime::now_utc())
+    );
+
+    // Left behind if the node stopped during a previous backup.
+    remove_staging(staging)?;
+
+    tracing::info!(%key, "Backing up database");
+    let block = {
+        let database = database.to_owned();
+        let staging = staging.to_owned();
+        tokio::task::spawn_blocking(move || {
+            pathfinder_storage::snapshot::export_snapshot(&database, &staging)
+        })
+        .await
+        .context("Snapshot task panicked")?
+        .context("Exporting database snapshot")?
+    };
+
+    let metadata = match block {
+        Some((number, hash)) => vec![
+            ("block-number", number.to_string()),
+            ("block-hash", hash.to_string()),
+        ],
+        None => vec![],
+    };
+    let result = upload(client, &key, staging, &metadata).await;
+    if let Err(error) = remove_staging(staging) {
+        tracing::warn!(?error, "Removing database snapshot failed");
+    }
+    let size = result.context("Uploading backup")?;
+
+    Ok(Backup {
+        key,
+        block,
+        size,
+        finished_at: unix_now(),
+    })
+}
+
+/// Uploads the file at `path` as `key`, returning its size.
+async fn upload(
+    client: &s3::Client,
+    key: &str,
+    path: &Path,
+    metadata: &[(&str, String)],
+) -> anyhow::Result<u64> {
+    let upload_id = client
+        .create_multipart_upload(key, metadata)
+        .await
+        .context("Starting upload")?;
+
+    let result = upload_parts(client, key, &upload_id, path).await;
+    if result.is_err() {
+        if let Err(error) = client.abort_multipart_upload(key, &upload_id).await {
+            tracing::debug!(?error, "Aborting upload failed");
+        }
+    }
+
+    result
+}
+
+async fn upload_parts(
+    client: &s3::Client,
+    key: &str,
+    upload_id: &str,
+    path: &Path,
+) -> anyhow::Result<u64> {
+    let mut file = tokio::fs::File::open(path)
+        .await
+        .context("Opening snapshot")?;
+    let size = file
+        .metadata()
+        .await
+        .context("Reading snapshot metadata")?
+        .len();
+    let part_size = MIN_PART_SIZE.max(size.div_ceil(MAX_PARTS));
+
+    let mut etags = Vec::new();
+    loop {
+        let mut part = Vec::new();
+        (&mut file)
+            .take(part_size)
+            .read_to_end(&mut part)
+            .await
+            .context("Reading snapshot")?;
+        // An empty snapshot still needs one part.
+        if part.is_empty() && !etags.is_empty() {
+            break;
+        }
+        let last = (part.len() as u64) < part_size;
+
+        let part_number = etags.len() + 1;
+        let etag = client
+            .upload_part(key, upload_id, part_number, part)
+            .await
+            .with_context(|| format!("Uploading part {part_number}"))?;
+        etags.push(etag);
+
+        if last {
+            break;
+        }
+    }
+
+    client
+        .complete_multipart_upload(key, upload_id, &etags)
+        .await
+        .context("Completing upload")?;
+
+    Ok(size)
+}
+
+/// Deletes the oldest backups beyond `config.retention`.
+async fn prune(client: &s3::Client, config: &BackupConfig) -> anyhow::Result<()> {
+    let mut keys = client
+        .list_objects(&config.prefix)
+        .await
+        .context("Listing backups")?
+        .into_iter()
+        .filter(|key| is_backup_key(&config.prefix, key))
+        .collect::<Vec<_>>();
+    // Timestamps sort chronologically.
+    keys.sort_unstable();
+
+    let excess = keys.len().saturating_sub(config.retention.get());
+    for key in &keys[..excess] {
+        client
+            .delete_object(key)
+            .await
+            .with_context(|| format!("Deleting {key}"))?;
+        tracing::info!(%key, "Deleted old database backup");
+    }
+
+    Ok(())
+}
+
+/// Whether `key` is a backup made with `prefix`, as opposed to another object
+/// whose key happens to start with it.
+fn is_backup_key(prefix: &str, key: &str) -> bool {
+    key.strip_prefix(prefix)
+        .and_then(|name| name.strip_suffix(KEY_SUFFIX))
+        .is_some_and(|timestamp| {
+            timestamp.len() == "20240101T000000Z".len()
+                && timestamp.ends_with('Z')
+                && timestamp.bytes().filter(|b| !b.is_ascii_digit()).count() == 2
+        })
+}
+
+fn remove_staging(path: &Path) -> anyhow::Result<()> {
+    match std::fs::remove_file(path) {
+        Ok(()) => Ok(()),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(error) => Err(error).with_context(|| format!("Removing {}", path.display())),
+    }
+}
+
+/// `time` in the ISO 8601 basic format, e.g. `20240101T000000Z`, as used by
+/// AWS request signatures.
+fn basic_timestamp(time: time::OffsetDateTime) -> String {
+    let time = time.to_offset(time::UtcOffset::UTC);
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        time.year(),
+        u8::from(time.month()),
+        time.day(),
+        time.hour(),
+        time.minute(),
+        time.second()
+    )
+}
+
+fn unix_now() -> u64 {
+    time::OffsetDateTime::now_utc()
+        .unix_timestamp()
+        .try_into()
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamp() {
+        let time = time::macros::datetime!(2024-03-05 07:08:09 UTC);
+
+        assert_eq!(basic_timestamp(time), "20240305T070809Z");
+    }
+
+    #[test]
+    fn backup_keys() {
+        assert!(is_backup_key(
+            "backups/",
+            "backups/20240305T070809Z.sqlite.zst"
+        ));
+        assert!(is_backup_key("", "20240305T070809Z.sqlite.zst"));
+
+        assert!(!is_backup_key(
+            "backups/",
+            "other/20240305T070809Z.sqlite.zst"
+        ));
+        assert!(!is_backup_key(
+            "backups/",
+            "backups/keep/20240305T0708Z.sqlite.zst"
+        ));
+        assert!(!is_backup_key(
+            "backups/",
+            "backups/20240305T070809Z.sqlite"
+        ));
+        assert!(!is_backup_key("backups/", "backups/notes.sqlite.zst"));
+    }
+}
@@ -0,0 +1,261 @@
+//! Replays historical blocks through the executor against the local database,
+//! for benchmarking the executor and its caches on real workloads.
+//!
+//! Blocks are executed on top of their parent's state, like `starknet_trace*`
+//! does, and are not compared to their stored receipts.
+
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use pathfinder_common::{BlockNumber, ChainId, TransactionHash};
+use pathfinder_executor::{CacheStats, ClassCache, ExecutionState};
+use pathfinder_storage::{BlockId, Storage};
+
+/// The outcome of a single replayed transaction.
+#[derive(Debug, Clone)]
+pub struct TransactionReport {
+    pub hash: TransactionHash,
+    pub steps: u64,
+    pub reverted: bool,
+}
+
+/// The timings of a single replayed block.
+#[derive(Debug, Clone)]
+pub struct BlockReport {
+    pub number: BlockNumber,
+    /// The time spent executing the block, excluding reading it from the
+    /// database.
+    pub execution_time: Duration,
+    pub transactions: Vec<TransactionReport>,
+}
+
+impl BlockReport {
+    pub fn steps(&self) -> u64 {
+        self.transactions.iter().map(|tx| tx.steps).sum()
+    }
+}
+
+/// The totals of a replayed range of blocks.
+#[derive(Debug, Clone, Default)]
+pub struct Report {
+    pub blocks: u64,
+    /// Blocks whose execution failed, which are not included in the totals.
+    pub failed_blocks: u64,
+    pub transactions: u64,
+    pub steps: u64,
+    /// The sum of the blocks' execution times.
+    pub execution_time: Duration,
+    /// The time the whole replay took, across all workers.
+    pub elapsed: Duration,
+    /// Lookups of the compiled class cache during the replay.
+    pub class_cache: CacheStats,
+    /// Lookups of the classes already loaded on top of the same block, which
+    /// are checked before the compiled class cache.
+    pub block_class_cache: CacheStats,
+}
+
+impl Report {
+    /// The number of Cairo steps executed per second of wall-clock time.
+    pub fn steps_per_second(&self) -> f64 {
+        self.steps as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+/// Replays the blocks `from..=to` using `workers` threads, calling `on_block`
+/// once for each block executed.
+///
+/// Blocks are handed out to the workers in order, so with more than one worker
+/// they complete, and are reported, slightly out of order.
+pub fn bench_execute(
+    storage: &Storage,
+    chain_id: ChainId,
+    from: BlockNumber,
+    to: BlockNumber,
+    workers: NonZeroUsize,
+    on_block: impl Fn(&BlockReport) + Sync,
+) -> anyhow::Result<Report> {
+    let next = AtomicU64::new(from.get());
+    let report = Mutex::new(Report::default());
+    let class_cache_before = pathfinder_executor::class_cache_stats();
+    let block_classes = ClassCache::default();
+    let start = Instant::now();
+
+    std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..workers.get())
+            .map(|_| {
+                scope.spawn(|| -> anyhow::Result<()> {
+                    let mut db = storage
+                        .connection()
+                        .context("Opening database connection")?;
+
+                    loop {
+                        let number = next.fetch_add(1, Ordering::Relaxed);
+                        if number > to.get() {
+                            return Ok(());
+                        }
+                        let number = BlockNumber::new_or_panic(number);
+
+                        let tx = db.transaction().context("Creating database transaction")?;
+                        let block = match execute_block(&tx, chain_id, number, &block_classes)? {
+                            Ok(block) => block,
+                            Err(error) => {
+                                tracing::warn!(block=%number, %error, "Block execution failed");
+                                report.lock().unwrap().failed_blocks += 1;
+                                continue;
+                            }
+                        };
+                        drop(tx);
+
+                        on_block(&block);
+
+                        let mut report = report.lock().unwrap();
+                        report.blocks += 1;
+                        report.transactions += block.transactions.len() as u64;
+                        report.steps += block.steps();
+                        report.execution_time += block.execution_time;
+                    }
+                })
+            })
+            .collect();
+
+        workers
+            .into_iter()
+            .try_for_each(|worker| worker.join().expect("Worker panicked"))
+    })?;
+
+    let class_cache = pathfinder_executor::class_cache_stats();
+    let mut report = report.into_inner().unwrap();
+    report.elapsed = start.elapsed();
+    report.class_cache = CacheStats {
+        hits: class_cache.hits - class_cache_before.hits,
+        misses: class_cache.misses - class_cache_before.misses,
+    };
+    report.block_class_cache = block_classes.stats();
+
+    Ok(report)
+}
+
+/// Executes block `number`. The outer error is for database failures, and the
+/// inner one for failures to execute the block, which don't stop the replay.
+fn execute_block(
+    tx: &pathfinder_storage::Transaction<'_>,
+    chain_id: ChainId,
+    number: BlockNumber,
+    block_classes: &ClassCache,
+) -> anyhow::Result<anyhow::Result<BlockReport>> {
+    let header = tx
+        .block_header(BlockId::Number(number))
+        .context("Fetching block header")?
+        .context("Block header missing")?;
+    let transactions = tx
+        .transactions_for_block(BlockId::Number(number))
+        .context("Fetching transactions")?
+        .context("Block transactions missing")?;
+
+    let hashes: Vec<_> = transactions.iter().map(|tx| tx.hash).collect();
+    let transactions = match transactions
+        .iter()
+        .map(|transaction| pathfinder_rpc::compose_executor_transaction(transaction, tx))
+        .collect::<Result<Vec<_>, _>>()
+    {
+        Ok(transactions) => transactions,
+        Err(error) => return Ok(Err(error.context("Converting transactions"))),
+    };
+
+    let execution_state = ExecutionState::trace(tx, chain_id, header, None, None)
+        .with_class_cache(block_classes.clone());
+    let start = Instant::now();
+    let simulations =
+        match pathfinder_executor::simulate(execution_state, transactions, false, false) {
+            Ok(simulations) => simulations,
+            Err(error) => return Ok(Err(anyhow::anyhow!("{error:?}"))),
+        };
+    let execution_time = start.elapsed();
+
+    let transactions = hashes
+        .into_iter()
+        .zip(simulations)
+        .map(|(hash, simulation)| TransactionReport {
+            hash,
+            steps: simulation
+                .trace
+                .execution_resources()
+                .computation_resources
+                .steps as u64,
+            reverted: simulation.revert_reason().is_some(),
+        })
+        .collect();
+
+    Ok(Ok(BlockReport {
+        number,
+        execution_time,
+        transactions,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::macro_prelude::*;
+    use pathfinder_common::receipt::Receipt;
+    use pathfinder_common::transaction::Transaction;
+    use pathfinder_common::BlockHeader;
+    use pathfinder_storage::StorageBuilder;
+
+    use super::*;
+
+    #[test]
+    fn failed_blocks_are_skipped() {
+        let storage = StorageBuilder::in_memory().unwrap();
+        let mut db = storage.connection().unwrap();
+        let tx = db.transaction().unwrap();
+        let mut header = BlockHeader::builder().finalize_with_hash(block_hash!("0x0"));
+        for i in 0..3 {
+            if i > 0 {
+                header = header
+                    .child_builder()
+                    .finalize_with_hash(block_hash_bytes!(format!("block {i}").as_bytes()));
+            }
+            tx.insert_block_header(&header).unwrap();
+
+            // The class declared by the transaction of block 1 is missing, so it
+            // can't be executed.
+            let transactions = match i {
+                1 => vec![(
+                    Transaction {
+                        hash: transaction_hash!("0x1"),
+                        variant: Default::default(),
+                    },
+                    Receipt::default(),
+                )],
+                _ => vec![],
+            };
+            tx.insert_transaction_data(header.number, &transactions, None)
+                .unwrap();
+        }
+        tx.commit().unwrap();
+
+        let executed = Mutex::new(Vec::new());
+        let report = bench_execute(
+            &storage,
+            ChainId::SEPOLIA_TESTNET,
+            BlockNumber::GENESIS,
+            BlockNumber::new_or_panic(2),
+            NonZeroUsize::new(2).unwrap(),
+            |block| executed.lock().unwrap().push(block.number),
+        )
+        .unwrap();
+
+        let mut executed = executed.into_inner().unwrap();
+        executed.sort();
+        assert_eq!(
+            executed,
+            vec![BlockNumber::GENESIS, BlockNumber::new_or_panic(2)]
+        );
+        assert_eq!(report.blocks, 2);
+        assert_eq!(report.failed_blocks, 1);
+        assert_eq!(report.transactions, 0);
+    }
+}
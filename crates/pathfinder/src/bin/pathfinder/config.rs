@@ -1,7 +1,7 @@
 use std::collections::HashSet;
 use std::fs::File;
 use std::net::SocketAddr;
-use std::num::{NonZeroU32, NonZeroUsize};
+use std::num::{NonZeroU32, NonZeroU64, NonZeroUsize};
 use std::path::PathBuf;
 use std::time::Duration;
 
@@ -11,10 +11,19 @@ use ipnet::IpNet;
 #[cfg(feature = "p2p")]
 use p2p::libp2p::Multiaddr;
 use pathfinder_common::consts::VERGEN_GIT_DESCRIBE;
-use pathfinder_common::AllowedOrigins;
-use pathfinder_executor::VersionedConstants;
+use pathfinder_common::{AllowedOrigins, BlockHash};
+use pathfinder_executor::{ExecutionLimits, VersionedConstants};
+use pathfinder_lib::node::{SnapshotSource, SyncCheckpoint};
+use pathfinder_rpc::middleware::method_filter::{MethodFilter, MethodSet};
+use pathfinder_rpc::middleware::rate_limit::RateLimitConfig;
+use pathfinder_rpc::middleware::request_log::RequestLogConfig;
+use pathfinder_rpc::tls::TlsConfig;
 use pathfinder_storage::JournalMode;
+use primitive_types::H160;
 use reqwest::Url;
+use starknet_gateway_client::{CircuitBreakerConfig, RetryPolicy};
+
+pub mod file;
 
 #[derive(Parser)]
 #[command(name = "Pathfinder")]
@@ -23,7 +32,11 @@ use reqwest::Url;
 #[command(
     about = "A Starknet node implemented by Equilibrium Labs. Submit bug reports and issues at https://github.com/eqlabs/pathfinder."
 )]
+#[command(subcommand_negates_reqs = true)]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<CliCommand>,
+
     #[arg(
         long,
         value_name = "DIR", 
@@ -52,8 +65,9 @@ Examples:
         value_name = "HTTP(s) URL",
         value_hint = clap::ValueHint::Url,
         env = "PATHFINDER_ETHEREUM_API_URL", 
+        required_unless_present_any = ["offline", "fork"]
     )]
-    ethereum_url: Url,
+    ethereum_url: Option<Url>,
 
     #[arg(
         long = "http-rpc",
@@ -80,9 +94,41 @@ Examples:
     )]
     rpc_cors_domains: Vec<String>,
 
+    #[arg(
+        long = "rpc.tls-cert",
+        long_help = "Path to a PEM encoded TLS certificate chain. If set, the RPC server only \
+                     accepts HTTPS and secure websocket connections. The certificate and key are \
+                     reloaded when pathfinder receives SIGHUP.",
+        value_name = "PATH",
+        env = "PATHFINDER_RPC_TLS_CERT",
+        requires = "rpc_tls_key"
+    )]
+    rpc_tls_cert: Option<PathBuf>,
+
+    #[arg(
+        long = "rpc.tls-key",
+        long_help = "Path to the PEM encoded private key of the `--rpc.tls-cert` certificate",
+        value_name = "PATH",
+        env = "PATHFINDER_RPC_TLS_KEY",
+        requires = "rpc_tls_cert"
+    )]
+    rpc_tls_key: Option<PathBuf>,
+
+    #[arg(
+        long = "rpc.ipc-path",
+        long_help = "Path of a Unix domain socket on which to also serve the JSON-RPC API of \
+                     `--rpc.root-version`. Requests and responses are newline delimited. Access \
+                     is limited to the owner and group of the socket file.",
+        value_name = "PATH",
+        env = "PATHFINDER_RPC_IPC_PATH"
+    )]
+    rpc_ipc_path: Option<PathBuf>,
+
     #[arg(
         long = "rpc.root-version",
-        long_help = "Version of the JSON-RPC API to serve on the / (root) path",
+        alias = "rpc.default-version",
+        long_help = "Version of the JSON-RPC API to serve on the / (root) path. All versions are \
+                     always available on their own paths, e.g. /rpc/v0_6.",
         default_value = "v07",
         env = "PATHFINDER_RPC_ROOT_VERSION"
     )]
@@ -104,6 +150,17 @@ Examples:
     )]
     monitor_address: Option<SocketAddr>,
 
+    #[arg(
+        long = "monitor.max-sync-lag",
+        long_help = "The maximum number of blocks the node may lag behind the chain tip and \
+                     still be considered ready. If set, `/ready` reports the node as unavailable \
+                     while it is further behind, or while sync has not reported its progress \
+                     yet. Also replaces the default of 5 blocks used by `/ready/synced`.",
+        value_name = "BLOCKS",
+        env = "PATHFINDER_MONITOR_MAX_SYNC_LAG"
+    )]
+    monitor_max_sync_lag: Option<u64>,
+
     #[clap(flatten)]
     network: NetworkCli,
 
@@ -124,6 +181,197 @@ Examples:
     )]
     max_rpc_connections: std::num::NonZeroUsize,
 
+    #[arg(
+        long = "rpc.shutdown-timeout",
+        long_help = "The maximum time in seconds to wait for in-flight RPC requests to complete \
+                     on shutdown. New requests are rejected while waiting.",
+        default_value = "10",
+        env = "PATHFINDER_RPC_SHUTDOWN_TIMEOUT"
+    )]
+    rpc_shutdown_timeout: u64,
+
+    #[arg(
+        long = "rpc.health-max-block-age",
+        long_help = "The maximum age in seconds of the latest block before `pathfinder_health` \
+                     reports sync as stalled",
+        default_value = "300",
+        env = "PATHFINDER_RPC_HEALTH_MAX_BLOCK_AGE"
+    )]
+    rpc_health_max_block_age: u64,
+
+    #[arg(
+        long = "rpc.trace-cache-size",
+        long_help = "The number of blocks whose transaction traces are cached, so that repeated \
+                     `starknet_traceBlockTransactions` and `starknet_traceTransaction` requests \
+                     for the same block don't re-execute it",
+        default_value = "128",
+        env = "PATHFINDER_RPC_TRACE_CACHE_SIZE"
+    )]
+    rpc_trace_cache_size: NonZeroUsize,
+
+    #[arg(
+        long = "rpc.trie-node-cache-size",
+        long_help = "The memory budget in MiB of the cache of decoded trie nodes, which is \
+                     shared by requests reading the state tries such as `pathfinder_getProof` \
+                     and `starknet_getStorageProof`",
+        default_value = "64",
+        env = "PATHFINDER_RPC_TRIE_NODE_CACHE_SIZE"
+    )]
+    rpc_trie_node_cache_size: usize,
+
+    #[arg(
+        long = "rpc.call-max-steps",
+        long_help = "The maximum number of Cairo steps `starknet_call` and `starknet_estimateFee` \
+                     may execute. Requests exceeding it fail with an \
+                     `ExecutionResourcesExceeded` error. Individual requests may lower this \
+                     using the `X-Pathfinder-Call-Max-Steps` header.",
+        env = "PATHFINDER_RPC_CALL_MAX_STEPS"
+    )]
+    rpc_call_max_steps: Option<NonZeroU32>,
+
+    #[arg(
+        long = "rpc.call-max-gas",
+        long_help = "The maximum amount of Sierra gas available to `starknet_call`. Individual \
+                     requests may lower this using the `X-Pathfinder-Call-Max-Gas` header.",
+        env = "PATHFINDER_RPC_CALL_MAX_GAS"
+    )]
+    rpc_call_max_gas: Option<NonZeroU64>,
+
+    #[arg(
+        long = "rpc.call-timeout",
+        long_help = "The maximum time in seconds `starknet_call` and `starknet_estimateFee` may \
+                     take. Individual requests may lower this using the \
+                     `X-Pathfinder-Call-Timeout-Ms` header, given in milliseconds.",
+        env = "PATHFINDER_RPC_CALL_TIMEOUT"
+    )]
+    rpc_call_timeout: Option<NonZeroU64>,
+
+    #[arg(
+        long = "rpc.validate-transactions",
+        long_help = "Validate transactions submitted using `starknet_addInvokeTransaction`, \
+                     `starknet_addDeclareTransaction` and `starknet_addDeployAccountTransaction` \
+                     against the pending state before forwarding them to the gateway. Invalid \
+                     transactions are rejected immediately with the error the gateway would \
+                     have returned. Validation is subject to the `--rpc.call-max-steps` and \
+                     `--rpc.call-timeout` limits.",
+        action = clap::ArgAction::Set,
+        default_value = "false",
+        env = "PATHFINDER_RPC_VALIDATE_TRANSACTIONS"
+    )]
+    rpc_validate_transactions: bool,
+
+    #[arg(
+        long = "rpc.rebroadcast-window",
+        long_help = "Transactions submitted through this node which haven't made it into a \
+                     block after this many seconds are sent to the gateway again, up to 5 \
+                     times. Set to 0 to disable re-broadcasting. Submitted transactions remain \
+                     queryable using `pathfinder_getSubmittedTransactions` either way.",
+        value_name = "SECONDS",
+        default_value = "300",
+        env = "PATHFINDER_RPC_REBROADCAST_WINDOW"
+    )]
+    rpc_rebroadcast_window: u64,
+
+    #[arg(
+        long = "rpc.rate-limits",
+        long_help = "Path to a TOML file configuring token bucket rate limits for RPC method \
+                     calls. Limits may be set globally, per client IP address and per method \
+                     group (`read`, `trace` or `write`). Calls exceeding a limit fail with error \
+                     code -32097.",
+        value_name = "PATH",
+        env = "PATHFINDER_RPC_RATE_LIMITS"
+    )]
+    rpc_rate_limits: Option<PathBuf>,
+
+    #[arg(
+        long = "rpc.disabled-method-groups",
+        long_help = "Method groups which are not exposed on the HTTP-RPC address: `write` \
+                     (transaction submission), `trace` (tracing, simulation, fee estimation and \
+                     calls) and `pathfinder` (the `pathfinder_*` extension methods). Disabled \
+                     methods fail with error code -32601 as if they didn't exist. The GraphQL, \
+                     gRPC and feeder gateway APIs don't serve data of disabled methods either.",
+        value_name = "GROUP LIST",
+        value_delimiter = ',',
+        env = "PATHFINDER_RPC_DISABLED_METHOD_GROUPS"
+    )]
+    rpc_disabled_method_groups: Vec<MethodSet>,
+
+    #[arg(
+        long = "rpc.disabled-methods",
+        long_help = "Methods which are not exposed on the HTTP-RPC address, e.g. \
+                     `starknet_getEvents,pathfinder_getProof`. The GraphQL, gRPC and feeder \
+                     gateway APIs don't serve data of disabled methods either.",
+        value_name = "METHOD LIST",
+        value_delimiter = ',',
+        env = "PATHFINDER_RPC_DISABLED_METHODS"
+    )]
+    rpc_disabled_methods: Vec<String>,
+
+    #[arg(
+        long = "rpc.enabled-methods",
+        long_help = "If set, the only methods exposed on the HTTP-RPC address. Disabled methods \
+                     and method groups are excluded from these as well.",
+        value_name = "METHOD LIST",
+        value_delimiter = ',',
+        env = "PATHFINDER_RPC_ENABLED_METHODS"
+    )]
+    rpc_enabled_methods: Option<Vec<String>>,
+
+    #[arg(
+        long = "rpc.unrestricted-address",
+        long_help = "An additional HTTP-RPC listening address exposing all methods, regardless \
+                     of the disabled and enabled methods. Intended for a private interface, \
+                     e.g. `127.0.0.1:9546`, while the public one only exposes some methods. \
+                     The admin methods `pathfinder_getSubmittedTransactions` and \
+                     `pathfinder_registerAbi` are only exposed here.",
+        value_name = "IP:PORT",
+        env = "PATHFINDER_RPC_UNRESTRICTED_ADDRESS"
+    )]
+    rpc_unrestricted_address: Option<SocketAddr>,
+
+    #[arg(
+        long = "rpc.record",
+        long_help = "Records every RPC method call and its response, along with the latest \
+                     block at the time, to new files of up to 256 MiB in this directory. Calls \
+                     are dropped if the disk can't keep up. The calls can then be re-run with \
+                     `pathfinder replay`.",
+        value_name = "DIR",
+        value_hint = clap::ValueHint::DirPath,
+        env = "PATHFINDER_RPC_RECORD"
+    )]
+    rpc_record: Option<PathBuf>,
+
+    #[arg(
+        long = "rpc.slow-request-threshold",
+        long_help = "RPC method calls taking at least this many milliseconds are logged at warn \
+                     level along with their full params",
+        env = "PATHFINDER_RPC_SLOW_REQUEST_THRESHOLD"
+    )]
+    rpc_slow_request_threshold: Option<NonZeroU64>,
+
+    #[arg(
+        long = "rpc.request-log-sample-percent",
+        long_help = "The percentage of RPC method calls, other than slow ones, which are logged \
+                     at info level with their method, params digest, duration and result",
+        default_value = "0",
+        value_parser = clap::value_parser!(u8).range(0..=100),
+        env = "PATHFINDER_RPC_REQUEST_LOG_SAMPLE_PERCENT"
+    )]
+    rpc_request_log_sample_percent: u8,
+
+    #[arg(
+        long = "rpc.abi-directory",
+        long_help = "Directory of contract ABIs used to decode events when `decode` is requested. \
+                     ABIs are read from `classes/<class hash>.json` and \
+                     `contracts/<contract address>.json`, and ABIs registered using \
+                     `pathfinder_registerAbi` are stored here as well. Without it, registered \
+                     ABIs are lost on restart. At most 10000 ABIs of up to 1 MiB each are \
+                     accepted.",
+        value_name = "DIR",
+        env = "PATHFINDER_RPC_ABI_DIRECTORY"
+    )]
+    rpc_abi_directory: Option<PathBuf>,
+
     #[arg(
         long = "sync.poll-interval",
         long_help = "New block poll interval in seconds",
@@ -140,6 +388,97 @@ Examples:
     )]
     l1_poll_interval: std::num::NonZeroU64,
 
+    #[arg(
+        long = "sync.pending-poll-interval",
+        long_help = "Pending block poll interval in milliseconds. The pending block is only polled \
+                     while the node is in sync with the chain tip.",
+        default_value = "2000",
+        env = "PATHFINDER_PENDING_POLL_INTERVAL_MILLIS"
+    )]
+    pending_poll_interval: std::num::NonZeroU64,
+
+    #[arg(
+        long = "sync.max-reorg-depth",
+        long_help = "The maximum number of blocks a single reorg may roll back. Deeper reorgs \
+                     halt sync with an error instead, and require operator intervention.",
+        default_value = "10000",
+        env = "PATHFINDER_SYNC_MAX_REORG_DEPTH"
+    )]
+    max_reorg_depth: std::num::NonZeroU64,
+
+    #[arg(
+        long = "sync.mode",
+        long_help = "`light` stores block headers, transactions, receipts and events, but \
+                     neither applies state diffs nor maintains the Merkle tries. This keeps the \
+                     database small, at the cost of all methods which read contract state, \
+                     classes or execute transactions failing with an error. Can only be chosen \
+                     when creating a new database, and defaults to the mode of an existing \
+                     database or `full` for a new one.",
+        env = "PATHFINDER_SYNC_MODE"
+    )]
+    sync_mode: Option<SyncMode>,
+
+    #[arg(
+        long = "sync.verify-execution",
+        long_help = "Re-execute every synced block with the local executor and compare the \
+                     resulting receipts, events and state diff with the gateway's data before \
+                     storing the block. `warn` logs mismatches and stores the block regardless, \
+                     while `halt` stops sync instead. Blocks older than Starknet 0.13.1.1 are not \
+                     re-executed. This slows down sync considerably.",
+        default_value = "disabled",
+        env = "PATHFINDER_SYNC_VERIFY_EXECUTION"
+    )]
+    verify_execution: ExecutionVerification,
+
+    #[arg(
+        long = "sync.verify-concurrency",
+        long_help = "How many downloaded blocks to verify concurrently during bulk sync",
+        default_value = "8",
+        env = "PATHFINDER_SYNC_VERIFY_CONCURRENCY"
+    )]
+    sync_verify_concurrency: std::num::NonZeroUsize,
+
+    #[arg(
+        long = "sync.class-fetch-concurrency",
+        long_help = "How many blocks to fetch newly declared classes for concurrently during \
+                     bulk sync",
+        default_value = "8",
+        env = "PATHFINDER_SYNC_CLASS_FETCH_CONCURRENCY"
+    )]
+    sync_class_fetch_concurrency: std::num::NonZeroUsize,
+
+    #[arg(
+        long = "sync.queue-capacity",
+        long_help = "How many blocks may be queued between each of the download, verification, \
+                     class fetch and database commit stages of bulk sync. Larger values smooth \
+                     out slow gateway responses and database commits at the cost of memory.",
+        default_value = "256",
+        env = "PATHFINDER_SYNC_QUEUE_CAPACITY"
+    )]
+    sync_queue_capacity: std::num::NonZeroUsize,
+
+    #[arg(
+        long = "config",
+        long_help = "A TOML file setting any of these options, named as on the command line with \
+                     tables for their dotted prefixes, e.g. `data-directory = \"/data\"` and \
+                     `[rpc]` `rate-limits = \"limits.toml\"`. Options given on the command line \
+                     or as environment variables take precedence. On SIGHUP, changes to \
+                     `log-filter` and `rpc.rate-limits` are applied without restarting.",
+        value_name = "FILE",
+        value_hint = clap::ValueHint::FilePath,
+        env = "PATHFINDER_CONFIG"
+    )]
+    config: Option<PathBuf>,
+
+    #[arg(
+        long = "log-filter",
+        long_help = "Which logs to output, in the syntax of `RUST_LOG`, e.g. \
+                     `pathfinder=debug,pathfinder_rpc=trace`. Takes precedence over `RUST_LOG`.",
+        value_name = "FILTER",
+        env = "PATHFINDER_LOG_FILTER"
+    )]
+    log_filter: Option<String>,
+
     #[arg(
         long = "color",
         long_help = "This flag controls when to use colors in the output logs.",
@@ -211,6 +550,26 @@ This should only be enabled for debugging purposes as it adds substantial proces
     )]
     rpc_batch_concurrency_limit: NonZeroUsize,
 
+    #[arg(
+        long = "rpc.max-batch-size",
+        long_help = "The maximum number of requests in a single JSON-RPC batch. Larger batches \
+                     are rejected with an invalid request error.",
+        env = "PATHFINDER_RPC_MAX_BATCH_SIZE",
+        default_value = "1000"
+    )]
+    rpc_max_batch_size: NonZeroUsize,
+
+    #[arg(
+        long = "rpc.max-response-size",
+        long_help = "The size in KiB after which paged methods such as `starknet_getEvents` and \
+                     `pathfinder_getClassDefinitions` cut their page short and return a \
+                     continuation token, so that responses stay within the limits of proxies. \
+                     The first item of a page is always returned, even if larger. Unlimited if \
+                     unset.",
+        env = "PATHFINDER_RPC_MAX_RESPONSE_SIZE"
+    )]
+    rpc_max_response_size: Option<NonZeroUsize>,
+
     #[arg(
         long = "sync.enable",
         long_help = "Enable syncing the chain",
@@ -229,6 +588,83 @@ This should only be enabled for debugging purposes as it adds substantial proces
     )]
     is_rpc_enabled: bool,
 
+    #[arg(
+        long = "read-only",
+        long_help = "Serve RPC from an existing database without writing to it. Another \
+                     pathfinder instance must be syncing into the same database file. Syncing is \
+                     disabled and no pending data is available in this mode.",
+        env = "PATHFINDER_READ_ONLY",
+        default_value = "false",
+        action=ArgAction::Set
+    )]
+    read_only: bool,
+
+    #[arg(
+        long = "offline",
+        long_help = "Serve RPC purely from the existing database, without connecting to Ethereum, \
+                     the Starknet gateway or peers. Syncing is disabled, no pending data is \
+                     available and methods which need the gateway, such as submitting \
+                     transactions, fail. The network must be set using --network.",
+        env = "PATHFINDER_OFFLINE",
+        default_value = "false",
+        action=ArgAction::Set
+    )]
+    offline: bool,
+
+    #[arg(
+        long = "fork",
+        long_help = "Execute submitted invoke and deploy account transactions locally, on top of \
+                     the latest block in the database, instead of sending them to the gateway. \
+                     Each transaction is appended to the pending block right away; no blocks \
+                     are mined. Declaring classes is not supported. Implies --offline and the \
+                     database is never written to, so local transactions are discarded on \
+                     restart.",
+        env = "PATHFINDER_FORK",
+        default_value = "false",
+        action=ArgAction::Set
+    )]
+    fork: bool,
+
+    #[cfg(feature = "grpc")]
+    #[arg(
+        long = "grpc.listen",
+        long_help = "Address on which to serve the read-only gRPC API. The gRPC API is disabled \
+                     if not set.",
+        value_name = "IP:PORT",
+        env = "PATHFINDER_GRPC_LISTEN"
+    )]
+    grpc_listen: Option<SocketAddr>,
+
+    #[cfg(not(feature = "grpc"))]
+    #[clap(skip)]
+    grpc_listen: Option<SocketAddr>,
+
+    #[cfg(feature = "graphql")]
+    #[arg(
+        long = "graphql.listen",
+        long_help = "Address on which to serve the read-only GraphQL API, at `/graphql`. The \
+                     GraphQL API is disabled if not set.",
+        value_name = "IP:PORT",
+        env = "PATHFINDER_GRAPHQL_LISTEN"
+    )]
+    graphql_listen: Option<SocketAddr>,
+
+    #[cfg(not(feature = "graphql"))]
+    #[clap(skip)]
+    graphql_listen: Option<SocketAddr>,
+
+    #[arg(
+        long = "feeder-gateway-api.listen",
+        long_help = "Address on which to serve `get_block`, `get_state_update` and \
+                     `get_class_by_hash` of the feeder gateway API from the local database, at \
+                     `/feeder_gateway`. This lets tools written against the feeder gateway use \
+                     this node instead. The pending block is not served. The feeder gateway API \
+                     is disabled if not set.",
+        value_name = "IP:PORT",
+        env = "PATHFINDER_FEEDER_GATEWAY_API_LISTEN"
+    )]
+    feeder_gateway_api_listen: Option<SocketAddr>,
+
     #[arg(
         long = "gateway-api-key",
         value_name = "API_KEY",
@@ -255,6 +691,56 @@ This should only be enabled for debugging purposes as it adds substantial proces
     )]
     feeder_gateway_fetch_concurrency: std::num::NonZeroUsize,
 
+    #[arg(
+        long = "gateway.retry-backoff-base",
+        value_name = "Seconds",
+        long_help = "Failed gateway and feeder-gateway requests are retried with an exponential \
+                     backoff. The Nth retry is delayed by this value to the power of N seconds.",
+        env = "PATHFINDER_GATEWAY_RETRY_BACKOFF_BASE",
+        default_value = "2"
+    )]
+    gateway_retry_backoff_base: std::num::NonZeroU64,
+
+    #[arg(
+        long = "gateway.retry-max-delay",
+        value_name = "Seconds",
+        long_help = "The maximum delay between retries of failed gateway and feeder-gateway \
+                     requests.",
+        env = "PATHFINDER_GATEWAY_RETRY_MAX_DELAY",
+        default_value = "10"
+    )]
+    gateway_retry_max_delay: std::num::NonZeroU64,
+
+    #[arg(
+        long = "gateway.max-retries",
+        long_help = "The number of times a failed gateway or feeder-gateway request is retried \
+                     before giving up. Requests are retried indefinitely if not set.",
+        env = "PATHFINDER_GATEWAY_MAX_RETRIES"
+    )]
+    gateway_max_retries: Option<std::num::NonZeroUsize>,
+
+    #[arg(
+        long = "gateway.circuit-breaker-threshold",
+        long_help = "The number of consecutive rate limited (429) or server error (5xx) responses \
+                     after which requests to the gateway and feeder-gateway are paused. \
+                     Submissions to the gateway and feeder-gateway requests are tracked \
+                     separately. Zero disables the circuit breaker.",
+        env = "PATHFINDER_GATEWAY_CIRCUIT_BREAKER_THRESHOLD",
+        default_value = "10"
+    )]
+    gateway_circuit_breaker_threshold: u32,
+
+    #[arg(
+        long = "gateway.circuit-breaker-cooldown",
+        value_name = "Seconds",
+        long_help = "How long requests to the gateway and feeder-gateway are paused once the \
+                     circuit breaker opens, after which a single request probes whether it \
+                     has recovered.",
+        env = "PATHFINDER_GATEWAY_CIRCUIT_BREAKER_COOLDOWN",
+        default_value = "30"
+    )]
+    gateway_circuit_breaker_cooldown: std::num::NonZeroU64,
+
     #[arg(
         long = "storage.event-bloom-filter-cache-size",
         long_help = "The number of blocks whose event bloom filters are cached in memory. This \
@@ -294,6 +780,100 @@ This should only be enabled for debugging purposes as it adds substantial proces
     )]
     state_tries: Option<StateTries>,
 
+    #[arg(
+        long = "storage.prune-history",
+        long_help = "Only keep the state diffs of the latest N blocks, deleting older history \
+                     incrementally during sync. Block headers and transactions are always kept. \
+                     State queries and execution against older blocks fail with \
+                     `BlockNotFound`, and their state diffs are not served to peers. Enabling \
+                     this on an existing database prunes its history on startup, which can take \
+                     a while. Must be at least `--sync.max-reorg-depth`, and cannot be disabled \
+                     once a database has been pruned.",
+        env = "PATHFINDER_STORAGE_PRUNE_HISTORY",
+        value_name = "N"
+    )]
+    prune_history: Option<std::num::NonZeroU64>,
+
+    #[arg(
+        long = "storage.trie-backend",
+        long_help = "Where Merkle trie nodes are stored. `rocksdb` keeps them in a RocksDB \
+                     database next to the SQLite database, which avoids most of the SQLite \
+                     page churn caused by trie updates. Requires building with the `rocksdb` \
+                     feature. Can only be chosen when creating a new database, and defaults to \
+                     the backend of an existing database or `sqlite` for a new one.",
+        env = "PATHFINDER_STORAGE_TRIE_BACKEND"
+    )]
+    trie_backend: Option<TrieBackend>,
+
+    #[arg(
+        long = "storage.pragma-profile",
+        long_help = "The set of SQLite pragmas applied to every database connection. \
+                     `read-optimized` uses a large page cache, memory mapped I/O and in-memory \
+                     temporary storage to speed up read-heavy workloads at the cost of extra \
+                     memory.",
+        default_value = "default",
+        env = "PATHFINDER_STORAGE_PRAGMA_PROFILE"
+    )]
+    pragma_profile: PragmaProfile,
+
+    #[arg(
+        long = "storage.rpc-pool-size",
+        long_help = "The number of database connections used to serve RPC requests. Requests \
+                     wait for a free connection once all of them are in use. Defaults to an \
+                     eighth of `--rpc.max-connections`, but at least 10.",
+        env = "PATHFINDER_STORAGE_RPC_POOL_SIZE"
+    )]
+    rpc_storage_pool_size: Option<NonZeroU32>,
+
+    #[arg(
+        long = "storage.busy-timeout",
+        long_help = "How long in milliseconds a database connection waits for a lock held by \
+                     another connection before failing",
+        env = "PATHFINDER_STORAGE_BUSY_TIMEOUT"
+    )]
+    storage_busy_timeout: Option<NonZeroU64>,
+
+    #[arg(
+        long = "storage.mmap-size",
+        long_help = "The maximum size in MiB of the database file to memory map, at most 2047. \
+                     Overrides the value set by `--storage.pragma-profile`, and 0 disables \
+                     memory mapped I/O.",
+        value_parser = clap::value_parser!(u64)
+            .range(..=pathfinder_storage::ConnectionSettings::MAX_MMAP_SIZE / (1024 * 1024)),
+        env = "PATHFINDER_STORAGE_MMAP_SIZE"
+    )]
+    storage_mmap_size: Option<u64>,
+
+    #[arg(
+        long = "storage.cache-size",
+        long_help = "The size in MiB of the page cache of each database connection. Overrides \
+                     the value set by `--storage.pragma-profile`.",
+        env = "PATHFINDER_STORAGE_CACHE_SIZE"
+    )]
+    storage_cache_size: Option<NonZeroU64>,
+
+    #[arg(
+        long = "storage.compaction-budget",
+        long_help = "Returns up to this many MiB of free database pages to the file system per \
+                     minute while the node is running. Requires incremental vacuum, which is \
+                     enabled for new databases and by running `pathfinder database compact` \
+                     once. Disabled by default.",
+        env = "PATHFINDER_STORAGE_COMPACTION_BUDGET",
+        value_name = "MiB"
+    )]
+    storage_compaction_budget: Option<NonZeroU64>,
+
+    #[arg(
+        long = "storage.background-migration-rate",
+        long_help = "The number of blocks per second processed by background migrations, which \
+                     backfill data for existing blocks after a database upgrade while the node \
+                     is running. Their progress is reported by `pathfinder_getMigrationStatus`.",
+        env = "PATHFINDER_STORAGE_BACKGROUND_MIGRATION_RATE",
+        value_name = "BLOCKS",
+        default_value = "500"
+    )]
+    storage_background_migration_rate: NonZeroU64,
+
     #[arg(
         long = "rpc.custom-versioned-constants-json-path",
         long_help = "Path to a JSON file containing the versioned constants to use for execution",
@@ -309,6 +889,299 @@ This should only be enabled for debugging purposes as it adds substantial proces
         action=ArgAction::Set
     )]
     fetch_casm_from_fgw: bool,
+
+    #[arg(
+        long = "sync.checkpoint",
+        long_help = "Bootstrap a new node from a snapshot whose latest block has this hash, \
+                     instead of syncing from genesis. The snapshot's state commitment is \
+                     verified against its state tries before syncing continues from the \
+                     checkpoint. Ignored if the database already exists.",
+        env = "PATHFINDER_SYNC_CHECKPOINT",
+        value_name = "BLOCK_HASH",
+        value_parser = parse_block_hash,
+        requires = "checkpoint_snapshot"
+    )]
+    checkpoint: Option<pathfinder_common::BlockHash>,
+
+    #[arg(
+        long = "sync.checkpoint-snapshot",
+        long_help = "The file path or HTTP(S) URL of the snapshot used by `--sync.checkpoint`, \
+                     as produced by `pathfinder database snapshot export`",
+        env = "PATHFINDER_SYNC_CHECKPOINT_SNAPSHOT",
+        value_name = "PATH | URL",
+        value_parser = parse_snapshot_source,
+        requires = "checkpoint"
+    )]
+    checkpoint_snapshot: Option<SnapshotSource>,
+
+    #[arg(
+        long = "backup.s3-url",
+        long_help = "Enables scheduled database backups, which are uploaded to the S3-compatible \
+                     bucket at this path-style URL, e.g. \
+                     `https://s3.eu-central-1.amazonaws.com/my-bucket`. The status of the \
+                     backups is reported by `pathfinder_getBackupStatus`. Not supported for \
+                     databases storing their Merkle tries in RocksDB.",
+        env = "PATHFINDER_BACKUP_S3_URL",
+        value_name = "URL",
+        requires_all = ["backup_s3_access_key_id", "backup_s3_secret_access_key"]
+    )]
+    backup_s3_url: Option<Url>,
+
+    #[arg(
+        long = "backup.s3-region",
+        long_help = "The region of the `--backup.s3-url` bucket",
+        env = "PATHFINDER_BACKUP_S3_REGION",
+        default_value = "us-east-1"
+    )]
+    backup_s3_region: String,
+
+    #[arg(
+        long = "backup.s3-access-key-id",
+        long_help = "The access key id used to upload backups",
+        env = "PATHFINDER_BACKUP_S3_ACCESS_KEY_ID",
+        requires = "backup_s3_url"
+    )]
+    backup_s3_access_key_id: Option<String>,
+
+    #[arg(
+        long = "backup.s3-secret-access-key",
+        long_help = "The secret access key used to upload backups",
+        value_name = None,
+        env = "PATHFINDER_BACKUP_S3_SECRET_ACCESS_KEY",
+        requires = "backup_s3_url"
+    )]
+    backup_s3_secret_access_key: Option<String>,
+
+    #[arg(
+        long = "backup.prefix",
+        long_help = "Prepended to the object key of every backup",
+        env = "PATHFINDER_BACKUP_PREFIX",
+        default_value = "pathfinder/"
+    )]
+    backup_prefix: String,
+
+    #[arg(
+        long = "backup.interval",
+        long_help = "The time between the end of a backup and the start of the next one, in \
+                     seconds",
+        env = "PATHFINDER_BACKUP_INTERVAL_SECONDS",
+        default_value = "86400"
+    )]
+    backup_interval: NonZeroU64,
+
+    #[arg(
+        long = "backup.retention",
+        long_help = "The number of backups kept in the bucket. Older backups are deleted after \
+                     each successful backup.",
+        env = "PATHFINDER_BACKUP_RETENTION",
+        default_value = "7"
+    )]
+    backup_retention: NonZeroUsize,
+
+    #[arg(
+        long = "tracing.otlp-endpoint",
+        long_help = "Exports tracing spans to the OpenTelemetry collector at this gRPC \
+                     endpoint, e.g. `http://localhost:4317`. Spans are subject to the same filter \
+                     as logs, see `--log-filter`. The RPC call spans are at info level, while the \
+                     sync stage and execution spans are at debug level.",
+        env = "PATHFINDER_TRACING_OTLP_ENDPOINT",
+        value_name = "URL"
+    )]
+    tracing_otlp_endpoint: Option<Url>,
+
+    #[arg(
+        long = "tracing.otlp-sample-ratio",
+        long_help = "The fraction of traces which are exported, between 0 and 1. All spans of a \
+                     trace are either exported or dropped together.",
+        env = "PATHFINDER_TRACING_OTLP_SAMPLE_RATIO",
+        default_value = "1",
+        value_parser = parse_sample_ratio
+    )]
+    tracing_otlp_sample_ratio: f64,
+}
+
+#[derive(clap::Subcommand)]
+enum CliCommand {
+    /// Database maintenance commands.
+    #[command(subcommand)]
+    Database(DatabaseCommand),
+    /// Re-computes the block hashes, commitments and state commitments of the
+    /// blocks stored in a database and reports any mismatch.
+    VerifyChain(VerifyChainCommand),
+    /// Replays a range of blocks through the executor and reports execution
+    /// times, steps per second and class cache hit rates.
+    BenchExecute(BenchExecuteCommand),
+    /// Re-runs the RPC calls recorded using `--rpc.record` against a database
+    /// and reports the responses which differ from the recorded ones.
+    Replay(ReplayCommand),
+    /// Lists the contracts, storage slots and classes whose values differ
+    /// between the state tries of two blocks, or of a block and the same block
+    /// on another node.
+    StateDiff(StateDiffCommand),
+}
+
+#[derive(clap::Subcommand)]
+enum DatabaseCommand {
+    /// Export or import compressed database snapshots.
+    #[command(subcommand)]
+    Snapshot(SnapshotCommand),
+    /// Deletes orphaned Merkle trie nodes and returns the database's free
+    /// pages to the file system. The node must be stopped while this runs.
+    Compact(CompactCommand),
+}
+
+#[derive(clap::Subcommand, Debug, Clone, PartialEq)]
+pub enum SnapshotCommand {
+    /// Writes a consistent, compressed snapshot of the database to <PATH>. This
+    /// is safe to run against the database of a running node. Needs free space
+    /// of twice the database size next to <PATH>.
+    Export {
+        #[arg(
+            long,
+            value_name = "FILE",
+            value_hint = clap::ValueHint::FilePath,
+            long_help = "The database file to export, e.g. `<data-directory>/mainnet.sqlite`"
+        )]
+        database: PathBuf,
+        #[arg(value_name = "PATH", value_hint = clap::ValueHint::FilePath)]
+        path: PathBuf,
+    },
+    /// Restores the snapshot at <PATH> into a new database file.
+    Import {
+        #[arg(
+            long,
+            value_name = "FILE",
+            value_hint = clap::ValueHint::FilePath,
+            long_help = "The database file to create, e.g. `<data-directory>/mainnet.sqlite`. \
+                         Must not exist yet."
+        )]
+        database: PathBuf,
+        #[arg(value_name = "PATH", value_hint = clap::ValueHint::FilePath)]
+        path: PathBuf,
+    },
+}
+
+#[derive(clap::Args, Debug, Clone, PartialEq)]
+pub struct CompactCommand {
+    #[arg(
+        long,
+        value_name = "FILE",
+        value_hint = clap::ValueHint::FilePath,
+        long_help = "The database file to compact, e.g. `<data-directory>/mainnet.sqlite`"
+    )]
+    pub database: PathBuf,
+}
+
+#[derive(clap::Args, Debug, Clone, PartialEq)]
+pub struct VerifyChainCommand {
+    #[arg(
+        long,
+        value_name = "FILE",
+        value_hint = clap::ValueHint::FilePath,
+        long_help = "The database file to verify, e.g. `<data-directory>/mainnet.sqlite`"
+    )]
+    pub database: PathBuf,
+    #[arg(
+        long,
+        value_name = "BLOCK",
+        long_help = "The first block to verify. Defaults to the genesis block"
+    )]
+    pub from: Option<u64>,
+    #[arg(
+        long,
+        value_name = "BLOCK",
+        long_help = "The last block to verify. Defaults to the latest block"
+    )]
+    pub to: Option<u64>,
+}
+
+#[derive(clap::Args, Debug, Clone, PartialEq)]
+pub struct BenchExecuteCommand {
+    #[arg(
+        long,
+        value_name = "FILE",
+        value_hint = clap::ValueHint::FilePath,
+        long_help = "The database file to replay blocks from, e.g. `<data-directory>/mainnet.sqlite`"
+    )]
+    pub database: PathBuf,
+    #[arg(long, value_name = "BLOCK", long_help = "The first block to replay")]
+    pub from: u64,
+    #[arg(
+        long,
+        value_name = "BLOCK",
+        long_help = "The last block to replay. Defaults to the latest block"
+    )]
+    pub to: Option<u64>,
+    #[arg(
+        long,
+        value_name = "N",
+        long_help = "The number of blocks replayed in parallel",
+        default_value = "1"
+    )]
+    pub threads: NonZeroUsize,
+    #[arg(
+        long,
+        long_help = "Also report the steps and outcome of every transaction",
+        default_value = "false"
+    )]
+    pub transactions: bool,
+}
+
+#[derive(clap::Args, Debug, Clone, PartialEq)]
+pub struct ReplayCommand {
+    #[arg(
+        long,
+        value_name = "FILE",
+        value_hint = clap::ValueHint::FilePath,
+        long_help = "The database file to replay the calls against, e.g. \
+                     `<data-directory>/mainnet.sqlite`"
+    )]
+    pub database: PathBuf,
+    #[arg(
+        value_name = "DIR",
+        value_hint = clap::ValueHint::DirPath,
+        long_help = "The directory the calls were recorded to"
+    )]
+    pub directory: PathBuf,
+}
+
+#[derive(clap::Args, Debug, Clone, PartialEq)]
+pub struct StateDiffCommand {
+    #[arg(
+        long,
+        value_name = "FILE",
+        value_hint = clap::ValueHint::FilePath,
+        long_help = "The database file to compare, e.g. `<data-directory>/mainnet.sqlite`"
+    )]
+    pub database: PathBuf,
+    #[arg(
+        value_name = "BLOCK_A",
+        long_help = "The block whose state is compared"
+    )]
+    pub block_a: u64,
+    #[arg(
+        value_name = "BLOCK_B",
+        required_unless_present = "remote",
+        conflicts_with = "remote",
+        long_help = "The block of the same database to compare <BLOCK_A> against"
+    )]
+    pub block_b: Option<u64>,
+    #[arg(
+        long,
+        value_name = "URL",
+        long_help = "Compare <BLOCK_A> against the same block on another node instead, \
+                     whose trie nodes are fetched from its `pathfinder_getProof` and \
+                     `pathfinder_getClassProof` methods. This is the node's pathfinder \
+                     JSON-RPC endpoint, e.g. `http://localhost:9545/rpc/pathfinder/v0.1`"
+    )]
+    pub remote: Option<reqwest::Url>,
+    #[arg(
+        long,
+        value_name = "N",
+        long_help = "The maximum number of differences reported per trie",
+        default_value = "100"
+    )]
+    pub limit: NonZeroUsize,
 }
 
 #[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq)]
@@ -336,6 +1209,70 @@ impl Color {
 pub enum RpcVersion {
     V06,
     V07,
+    V08,
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum PragmaProfile {
+    Default,
+    ReadOptimized,
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum ExecutionVerification {
+    Disabled,
+    Warn,
+    Halt,
+}
+
+impl From<ExecutionVerification> for pathfinder_lib::state::ExecutionVerification {
+    fn from(value: ExecutionVerification) -> Self {
+        match value {
+            ExecutionVerification::Disabled => Self::Disabled,
+            ExecutionVerification::Warn => Self::Warn,
+            ExecutionVerification::Halt => Self::Halt,
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum SyncMode {
+    Full,
+    Light,
+}
+
+impl From<SyncMode> for pathfinder_storage::SyncMode {
+    fn from(value: SyncMode) -> Self {
+        match value {
+            SyncMode::Full => Self::Full,
+            SyncMode::Light => Self::Light,
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum TrieBackend {
+    Sqlite,
+    #[value(name = "rocksdb")]
+    RocksDb,
+}
+
+impl From<TrieBackend> for pathfinder_storage::TrieBackend {
+    fn from(value: TrieBackend) -> Self {
+        match value {
+            TrieBackend::Sqlite => Self::Sqlite,
+            TrieBackend::RocksDb => Self::RocksDb,
+        }
+    }
+}
+
+impl From<PragmaProfile> for pathfinder_storage::PragmaProfile {
+    fn from(value: PragmaProfile) -> Self {
+        match value {
+            PragmaProfile::Default => Self::Default,
+            PragmaProfile::ReadOptimized => Self::ReadOptimized,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -356,18 +1293,69 @@ fn parse_state_tries(s: &str) -> Result<StateTries, String> {
     }
 }
 
+fn parse_block_hash(s: &str) -> Result<pathfinder_common::BlockHash, String> {
+    pathfinder_crypto::Felt::from_hex_str(s)
+        .map(pathfinder_common::BlockHash)
+        .map_err(|_| "Expected a hex encoded block hash".to_string())
+}
+
+fn parse_sample_ratio(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(ratio) if (0.0..=1.0).contains(&ratio) => Ok(ratio),
+        _ => Err("Expected a number between 0 and 1".to_string()),
+    }
+}
+
+fn parse_snapshot_source(s: &str) -> Result<SnapshotSource, String> {
+    match Url::parse(s) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") => Ok(SnapshotSource::Url(url)),
+        _ => Ok(SnapshotSource::Path(PathBuf::from(s))),
+    }
+}
+
 #[derive(clap::Args)]
 struct NetworkCli {
     #[arg(
         long = "network",
         long_help = r"Specify the Starknet network for pathfinder to operate on.
 
-Note that 'custom' requires also setting the --gateway-url and --feeder-gateway-url options.",
+Note that 'custom' requires also setting the --gateway-url and --feeder-gateway-url options. Alternatively, use --chain-spec to describe a custom network in a file.",
         value_enum,
         env = "PATHFINDER_NETWORK"
     )]
     network: Option<Network>,
 
+    #[arg(
+        long = "additional-network",
+        long_help = "A second Starknet network to sync and serve from this process, using its \
+                     own database in the data directory. Both networks are then also served \
+                     under a path prefix named after the network, e.g. `/mainnet/rpc/v0_7` and \
+                     `/sepolia-testnet/rpc/v0_7`. Requires --additional-network.ethereum.url.",
+        value_enum,
+        env = "PATHFINDER_ADDITIONAL_NETWORK",
+        requires = "additional_network_ethereum_url",
+        conflicts_with_all = ["offline", "fork", "read_only"]
+    )]
+    additional_network: Option<AdditionalNetwork>,
+
+    #[arg(
+        long = "additional-network.ethereum.url",
+        long_help = "The Ethereum WS RPC endpoint used to sync the additional network, which \
+                     must be on the L1 network of the additional network.",
+        value_name = "URL",
+        value_hint = clap::ValueHint::Url,
+        env = "PATHFINDER_ADDITIONAL_NETWORK_ETHEREUM_API_URL"
+    )]
+    additional_network_ethereum_url: Option<Url>,
+
+    #[arg(
+        long = "additional-network.ethereum.password",
+        long_help = "The optional password to use for the Ethereum API of the additional network",
+        value_name = None,
+        env = "PATHFINDER_ADDITIONAL_NETWORK_ETHEREUM_API_PASSWORD"
+    )]
+    additional_network_ethereum_password: Option<String>,
+
     #[arg(
         long,
         long_help = "Set a custom Starknet chain ID (e.g. SN_SEPOLIA)",
@@ -380,21 +1368,45 @@ Note that 'custom' requires also setting the --gateway-url and --feeder-gateway-
         long = "feeder-gateway-url",
         value_name = "URL",
         value_hint = clap::ValueHint::Url,
-        long_help = "Specify a custom Starknet feeder gateway url. Can be used to run pathfinder on a custom Starknet network, or to use a gateway proxy. Requires '--network custom'.",
+        long_help = "Specify a custom Starknet feeder gateway url. Can be used to run pathfinder on a custom Starknet network, or to use a gateway proxy. Requires '--network custom'.
+
+May be repeated, together with --gateway-url, to specify additional gateways. Sync then sends requests to the healthiest of them and fails over to the others if a request fails. The environment variable takes additional urls separated by spaces.",
         env = "PATHFINDER_FEEDER_GATEWAY_URL", 
+        value_delimiter = ' ',
         required_if_eq("network", Network::Custom),
     )]
-    feeder_gateway: Option<Url>,
+    feeder_gateway: Vec<Url>,
 
     #[arg(
         long = "gateway-url",
         value_name = "URL",
         value_hint = clap::ValueHint::Url,
-        long_help = "Specify a custom Starknet gateway url. Can be used to run pathfinder on a custom Starknet network, or to use a gateway proxy. Requires '--network custom'.",
+        long_help = "Specify a custom Starknet gateway url. Can be used to run pathfinder on a custom Starknet network, or to use a gateway proxy. Requires '--network custom'.
+
+May be repeated to specify additional gateways, each paired with the --feeder-gateway-url given at the same position. Transactions are only submitted to the first gateway. The environment variable takes additional urls separated by spaces.",
         env = "PATHFINDER_GATEWAY_URL",
+        value_delimiter = ' ',
         required_if_eq("network", Network::Custom),
     )]
-    gateway: Option<Url>,
+    gateway: Vec<Url>,
+
+    #[arg(
+        long = "chain-spec",
+        value_name = "PATH",
+        value_hint = clap::ValueHint::FilePath,
+        long_help = r#"Path to a TOML file describing a custom Starknet chain. Can be used to run pathfinder on an appchain or devnet instead of one of the known networks.
+
+The file must contain the following keys:
+
+chain_id = "MY_APPCHAIN"
+core_contract_address = "0x..."
+gateway_url = "https://..."
+feeder_gateway_url = "https://..."
+genesis_hash = "0x...""#,
+        env = "PATHFINDER_CHAIN_SPEC",
+        conflicts_with_all = ["network", "chain_id", "feeder_gateway", "gateway"],
+    )]
+    chain_spec: Option<PathBuf>,
 }
 
 #[cfg(feature = "p2p")]
@@ -408,6 +1420,16 @@ struct P2PCli {
         env = "PATHFINDER_P2P_PROXY"
     )]
     proxy: bool,
+    #[arg(
+        long = "p2p.sync-from-peers",
+        long_help = "When syncing from the feeder gateway, download blocks from peers first and \
+                     only fall back to the feeder gateway if no peer can provide them. Requires \
+                     '--p2p.proxy true'.",
+        default_value = "false",
+        action = clap::ArgAction::Set,
+        env = "PATHFINDER_P2P_SYNC_FROM_PEERS"
+    )]
+    sync_from_peers: bool,
     #[arg(
         long = "p2p.identity-config-file",
         long_help = "Path to file containing the private key of the node. If not provided, a new \
@@ -577,6 +1599,15 @@ enum Network {
     Custom,
 }
 
+/// The networks which can be run alongside the main network, see
+/// [Config::additional_network].
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AdditionalNetwork {
+    Mainnet,
+    SepoliaTestnet,
+    SepoliaIntegration,
+}
+
 impl From<Network> for clap::builder::OsStr {
     fn from(value: Network) -> Self {
         match value {
@@ -645,6 +1676,27 @@ pub fn parse_cors_or_exit(input: Vec<String>) -> Option<AllowedOrigins> {
     }
 }
 
+fn parse_prune_history_or_exit(
+    prune_history: Option<std::num::NonZeroU64>,
+    max_reorg_depth: std::num::NonZeroU64,
+) -> Option<std::num::NonZeroU64> {
+    use clap::error::ErrorKind;
+
+    match prune_history {
+        Some(num_blocks) if num_blocks < max_reorg_depth => Cli::command()
+            .error(
+                ErrorKind::ValueValidation,
+                format!(
+                    "--storage.prune-history ({num_blocks}) must be at least \
+                     --sync.max-reorg-depth ({max_reorg_depth}), otherwise a reorg could roll \
+                     back into pruned state"
+                ),
+            )
+            .exit(),
+        other => other,
+    }
+}
+
 #[derive(Debug, thiserror::Error, PartialEq)]
 enum RpcCorsDomainsParseError {
     #[error("Invalid allowed domain for CORS: {0}.")]
@@ -685,38 +1737,146 @@ enum ParseVersionedConstantsError {
     Parse(#[from] serde_json::Error),
 }
 
+fn parse_chain_spec(path: PathBuf) -> Result<ChainSpec, ParseChainSpecError> {
+    let contents = std::fs::read_to_string(path)?;
+    let chain_spec = toml::from_str(&contents)?;
+
+    Ok(chain_spec)
+}
+
+fn parse_chain_spec_or_exit(path: PathBuf) -> ChainSpec {
+    use clap::error::ErrorKind;
+
+    match parse_chain_spec(path) {
+        Ok(chain_spec) => chain_spec,
+        Err(error) => Cli::command()
+            .error(ErrorKind::ValueValidation, error)
+            .exit(),
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+enum ParseChainSpecError {
+    #[error("IO error while reading chain spec: {0}.")]
+    Io(#[from] std::io::Error),
+    #[error("Parse error while loading chain spec: {0}.")]
+    Parse(#[from] toml::de::Error),
+}
+
+fn parse_rate_limits(path: PathBuf) -> Result<RateLimitConfig, ParseRateLimitsError> {
+    let contents = std::fs::read_to_string(path)?;
+    let rate_limits = toml::from_str(&contents)?;
+
+    Ok(rate_limits)
+}
+
+fn parse_rate_limits_or_exit(path: PathBuf) -> RateLimitConfig {
+    use clap::error::ErrorKind;
+
+    match parse_rate_limits(path) {
+        Ok(rate_limits) => rate_limits,
+        Err(error) => Cli::command()
+            .error(ErrorKind::ValueValidation, error)
+            .exit(),
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+enum ParseRateLimitsError {
+    #[error("IO error while reading RPC rate limits: {0}.")]
+    Io(#[from] std::io::Error),
+    #[error("Parse error while loading RPC rate limits: {0}.")]
+    Parse(#[from] toml::de::Error),
+}
+
 pub struct Config {
     pub data_directory: PathBuf,
-    pub ethereum: Ethereum,
+    /// Only [None] in offline mode.
+    pub ethereum: Option<Ethereum>,
     pub rpc_address: SocketAddr,
     pub rpc_cors_domains: Option<AllowedOrigins>,
+    pub rpc_tls: Option<TlsConfig>,
+    pub rpc_ipc_path: Option<PathBuf>,
     pub rpc_root_version: RpcVersion,
     pub websocket: WebsocketConfig,
     pub monitor_address: Option<SocketAddr>,
+    pub monitor_max_sync_lag: Option<u64>,
     pub network: Option<NetworkConfig>,
+    /// A second network synced and served by the same process.
+    pub additional_network: Option<AdditionalNetworkConfig>,
     pub execution_concurrency: Option<std::num::NonZeroU32>,
     pub sqlite_wal: JournalMode,
     pub max_rpc_connections: std::num::NonZeroUsize,
+    pub rpc_shutdown_timeout: Duration,
+    pub rpc_health_max_block_age: Duration,
+    pub rpc_trace_cache_size: NonZeroUsize,
+    pub rpc_trie_node_cache_size: usize,
+    pub rpc_execution_limits: ExecutionLimits,
+    pub rpc_execution_timeout: Option<Duration>,
+    pub rpc_validate_transactions: bool,
+    pub rpc_rebroadcast_window: Option<Duration>,
+    pub rpc_rate_limits: Option<RateLimitConfig>,
+    pub rpc_method_filter: MethodFilter,
+    pub rpc_unrestricted_address: Option<SocketAddr>,
+    pub rpc_record: Option<PathBuf>,
+    pub rpc_request_log: RequestLogConfig,
+    pub rpc_abi_directory: Option<PathBuf>,
     pub poll_interval: std::time::Duration,
     pub l1_poll_interval: std::time::Duration,
+    pub pending_poll_interval: std::time::Duration,
+    pub max_reorg_depth: std::num::NonZeroU64,
+    pub sync_mode: Option<pathfinder_storage::SyncMode>,
+    pub sync_verify_concurrency: NonZeroUsize,
+    pub sync_class_fetch_concurrency: NonZeroUsize,
+    pub sync_queue_capacity: NonZeroUsize,
+    /// Set if the options were loaded from a config file.
+    pub config_file: Option<file::ConfigFile>,
+    pub log_filter: Option<String>,
     pub color: Color,
     pub log_output_json: bool,
     pub disable_version_update_check: bool,
     pub p2p: P2PConfig,
     pub debug: DebugConfig,
     pub verify_tree_hashes: bool,
+    pub verify_execution: pathfinder_lib::state::ExecutionVerification,
     pub rpc_batch_concurrency_limit: NonZeroUsize,
+    pub rpc_max_batch_size: NonZeroUsize,
+    /// In bytes.
+    pub rpc_max_response_size: Option<NonZeroUsize>,
     pub is_sync_enabled: bool,
     pub is_rpc_enabled: bool,
+    pub read_only: bool,
+    pub offline: bool,
+    pub fork: bool,
+    pub grpc_listen: Option<SocketAddr>,
+    pub graphql_listen: Option<SocketAddr>,
+    pub feeder_gateway_api_listen: Option<SocketAddr>,
     pub gateway_api_key: Option<String>,
     pub gateway_timeout: Duration,
+    pub gateway_retry_policy: RetryPolicy,
+    pub gateway_circuit_breaker: Option<CircuitBreakerConfig>,
     pub event_bloom_filter_cache_size: NonZeroUsize,
     pub get_events_max_blocks_to_scan: NonZeroUsize,
     pub get_events_max_uncached_bloom_filters_to_load: NonZeroUsize,
     pub state_tries: Option<StateTries>,
+    pub prune_history: Option<std::num::NonZeroU64>,
+    pub trie_backend: Option<pathfinder_storage::TrieBackend>,
+    pub pragma_profile: pathfinder_storage::PragmaProfile,
+    pub storage_connection_settings: pathfinder_storage::ConnectionSettings,
+    pub rpc_storage_pool_size: Option<NonZeroU32>,
+    pub storage_compaction_budget: Option<NonZeroU64>,
+    pub storage_background_migration_rate: NonZeroU64,
     pub custom_versioned_constants: Option<VersionedConstants>,
     pub feeder_gateway_fetch_concurrency: NonZeroUsize,
     pub fetch_casm_from_fgw: bool,
+    pub sync_checkpoint: Option<SyncCheckpoint>,
+    pub backup: Option<pathfinder_lib::backup::BackupConfig>,
+    pub otlp: Option<crate::otlp::OtlpConfig>,
+}
+
+pub struct AdditionalNetworkConfig {
+    pub network: AdditionalNetwork,
+    pub ethereum: Ethereum,
 }
 
 pub struct Ethereum {
@@ -733,13 +1893,31 @@ pub enum NetworkConfig {
         gateway: Url,
         feeder_gateway: Url,
         chain_id: String,
+        /// Additional `(gateway, feeder_gateway)` pairs sync can fail over to.
+        failover_gateways: Vec<(Url, Url)>,
     },
+    ChainSpec(ChainSpec),
+}
+
+/// A custom Starknet chain, as loaded from the file given by `--chain-spec`.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ChainSpec {
+    pub chain_id: String,
+    /// The Starknet core contract on L1.
+    pub core_contract_address: H160,
+    pub gateway_url: Url,
+    pub feeder_gateway_url: Url,
+    /// The hash of the chain's genesis block. Used to verify that the gateway
+    /// serves this chain, which is skipped in offline mode.
+    pub genesis_hash: BlockHash,
 }
 
 #[cfg(feature = "p2p")]
 #[derive(Clone)]
 pub struct P2PConfig {
     pub proxy: bool,
+    pub sync_from_peers: bool,
     pub identity_config_file: Option<std::path::PathBuf>,
     pub listen_on: Multiaddr,
     pub bootstrap_addresses: Vec<Multiaddr>,
@@ -768,27 +1946,40 @@ pub struct DebugConfig {
 impl NetworkConfig {
     fn from_components(args: NetworkCli) -> Option<Self> {
         use Network::*;
-        let cfg = match (
-            args.network,
-            args.gateway,
-            args.feeder_gateway,
-            args.chain_id,
-        ) {
-            (None, None, None, None) => return None,
-            (Some(Custom), Some(gateway), Some(feeder_gateway), Some(chain_id)) => {
+
+        if let Some(path) = args.chain_spec {
+            return Some(NetworkConfig::ChainSpec(parse_chain_spec_or_exit(path)));
+        }
+
+        if args.gateway.len() != args.feeder_gateway.len() {
+            use clap::error::ErrorKind;
+
+            Cli::command()
+                .error(
+                    ErrorKind::WrongNumberOfValues,
+                    "--gateway-url and --feeder-gateway-url must be given the same number of times",
+                )
+                .exit()
+        }
+        let mut gateways = args.gateway.into_iter().zip(args.feeder_gateway);
+
+        let cfg = match (args.network, gateways.next(), args.chain_id) {
+            (None, None, None) => return None,
+            (Some(Custom), Some((gateway, feeder_gateway)), Some(chain_id)) => {
                 NetworkConfig::Custom {
                     gateway,
                     feeder_gateway,
                     chain_id,
+                    failover_gateways: gateways.collect(),
                 }
             }
-            (Some(Custom), _, _, _) => {
+            (Some(Custom), _, _) => {
                 unreachable!("`--network custom` requirements are handled by clap derive")
             }
             // Handle non-custom variants in an inner match so that the compiler will force
             // us to handle a new network variants explicitly. Otherwise we end up with a
             // catch-all arm that would swallow new variants silently.
-            (Some(non_custom), None, None, None) => match non_custom {
+            (Some(non_custom), None, None) => match non_custom {
                 Mainnet => NetworkConfig::Mainnet,
                 SepoliaTestnet => NetworkConfig::SepoliaTestnet,
                 SepoliaIntegration => NetworkConfig::SepoliaIntegration,
@@ -871,6 +2062,15 @@ impl P2PConfig {
                 .exit()
         }
 
+        if args.sync_from_peers && !args.proxy {
+            Cli::command()
+                .error(
+                    ErrorKind::ArgumentConflict,
+                    "p2p.sync-from-peers requires p2p.proxy",
+                )
+                .exit()
+        }
+
         if args.kad_name.iter().any(|x| !x.starts_with('/')) {
             Cli::command()
                 .error(
@@ -890,6 +2090,7 @@ impl P2PConfig {
                 .unwrap(),
             max_outbound_connections: args.max_outbound_connections.try_into().unwrap(),
             proxy: args.proxy,
+            sync_from_peers: args.sync_from_peers,
             identity_config_file: args.identity_config_file,
             listen_on: args.listen_on,
             bootstrap_addresses: parse_multiaddr_vec(
@@ -963,54 +2164,230 @@ impl DebugConfig {
     }
 }
 
-impl Config {
-    #[cfg_attr(not(feature = "p2p"), allow(clippy::unit_arg))]
+/// What the binary has been asked to do.
+pub enum Command {
+    /// Run the node.
+    Node(Box<Config>),
+    Snapshot(SnapshotCommand),
+    Compact(CompactCommand),
+    VerifyChain(VerifyChainCommand),
+    BenchExecute(BenchExecuteCommand),
+    Replay(ReplayCommand),
+    StateDiff(StateDiffCommand),
+}
+
+impl Command {
     pub fn parse() -> Self {
-        let cli = Cli::parse();
+        use clap::error::ErrorKind;
+        use clap::FromArgMatches;
+
+        let command = Cli::command();
+        let mut config_file = file::path(std::env::args_os()).map(|path| {
+            file::ConfigFile::load(path, &command).unwrap_or_else(|error| {
+                Cli::command()
+                    .error(ErrorKind::ValueValidation, format!("{error:#}"))
+                    .exit()
+            })
+        });
+
+        let matches = command.clone().get_matches();
+        let mut cli = Cli::from_arg_matches(&matches).unwrap_or_else(|error| error.exit());
+        if let Some(config_file) = &mut config_file {
+            config_file.given_on_command_line(&command, &matches);
+        }
+
+        match cli.command.take() {
+            Some(CliCommand::Database(DatabaseCommand::Snapshot(command))) => {
+                Self::Snapshot(command)
+            }
+            Some(CliCommand::Database(DatabaseCommand::Compact(command))) => Self::Compact(command),
+            Some(CliCommand::VerifyChain(command)) => Self::VerifyChain(command),
+            Some(CliCommand::BenchExecute(command)) => Self::BenchExecute(command),
+            Some(CliCommand::Replay(command)) => Self::Replay(command),
+            Some(CliCommand::StateDiff(command)) => Self::StateDiff(command),
+            None => Self::Node(Box::new(Config {
+                config_file,
+                ..Config::from_cli(cli)
+            })),
+        }
+    }
+}
 
+impl Config {
+    #[cfg_attr(not(feature = "p2p"), allow(clippy::unit_arg))]
+    fn from_cli(cli: Cli) -> Self {
         let network = NetworkConfig::from_components(cli.network);
 
         Config {
             data_directory: cli.data_directory,
-            ethereum: Ethereum {
+            ethereum: cli.ethereum_url.map(|url| Ethereum {
                 password: cli.ethereum_password,
-                url: cli.ethereum_url,
-            },
+                url,
+            }),
             rpc_address: cli.rpc_address,
             rpc_cors_domains: parse_cors_or_exit(cli.rpc_cors_domains),
+            rpc_tls: cli
+                .rpc_tls_cert
+                .zip(cli.rpc_tls_key)
+                .map(|(cert_path, key_path)| TlsConfig {
+                    cert_path,
+                    key_path,
+                }),
+            rpc_ipc_path: cli.rpc_ipc_path,
             rpc_root_version: cli.rpc_root_version,
             websocket: cli.websocket,
             monitor_address: cli.monitor_address,
+            monitor_max_sync_lag: cli.monitor_max_sync_lag,
             network,
+            additional_network: cli
+                .additional_network
+                .zip(cli.additional_network_ethereum_url)
+                .map(|(network, url)| AdditionalNetworkConfig {
+                    network,
+                    ethereum: Ethereum {
+                        url,
+                        password: cli.additional_network_ethereum_password,
+                    },
+                }),
             execution_concurrency: cli.execution_concurrency,
             sqlite_wal: match cli.sqlite_wal {
                 true => JournalMode::WAL,
                 false => JournalMode::Rollback,
             },
             max_rpc_connections: cli.max_rpc_connections,
+            rpc_shutdown_timeout: Duration::from_secs(cli.rpc_shutdown_timeout),
+            rpc_health_max_block_age: Duration::from_secs(cli.rpc_health_max_block_age),
+            rpc_trace_cache_size: cli.rpc_trace_cache_size,
+            rpc_trie_node_cache_size: cli.rpc_trie_node_cache_size.saturating_mul(1024 * 1024),
+            rpc_execution_limits: ExecutionLimits {
+                max_steps: cli.rpc_call_max_steps,
+                max_gas: cli.rpc_call_max_gas,
+            },
+            rpc_execution_timeout: cli
+                .rpc_call_timeout
+                .map(|timeout| Duration::from_secs(timeout.get())),
+            rpc_validate_transactions: cli.rpc_validate_transactions,
+            rpc_rebroadcast_window: (cli.rpc_rebroadcast_window > 0)
+                .then(|| Duration::from_secs(cli.rpc_rebroadcast_window)),
+            rpc_rate_limits: cli.rpc_rate_limits.map(parse_rate_limits_or_exit),
+            rpc_method_filter: MethodFilter {
+                // Admin methods are only served on the unrestricted address.
+                disabled_sets: cli
+                    .rpc_disabled_method_groups
+                    .into_iter()
+                    .chain(std::iter::once(MethodSet::Admin))
+                    .collect(),
+                disabled_methods: cli.rpc_disabled_methods.into_iter().collect(),
+                enabled_methods: cli
+                    .rpc_enabled_methods
+                    .map(|methods| methods.into_iter().collect()),
+            },
+            rpc_unrestricted_address: cli.rpc_unrestricted_address,
+            rpc_record: cli.rpc_record,
+            rpc_request_log: RequestLogConfig {
+                slow_request_threshold: cli
+                    .rpc_slow_request_threshold
+                    .map(|threshold| Duration::from_millis(threshold.get())),
+                sample_percent: cli.rpc_request_log_sample_percent,
+            },
+            rpc_abi_directory: cli.rpc_abi_directory,
             poll_interval: Duration::from_secs(cli.poll_interval.get()),
             l1_poll_interval: Duration::from_secs(cli.l1_poll_interval.get()),
+            pending_poll_interval: Duration::from_millis(cli.pending_poll_interval.get()),
+            max_reorg_depth: cli.max_reorg_depth,
+            sync_mode: cli.sync_mode.map(Into::into),
+            sync_verify_concurrency: cli.sync_verify_concurrency,
+            sync_class_fetch_concurrency: cli.sync_class_fetch_concurrency,
+            sync_queue_capacity: cli.sync_queue_capacity,
+            config_file: None,
+            log_filter: cli.log_filter,
             color: cli.color,
             log_output_json: cli.log_output_json,
             disable_version_update_check: cli.disable_version_update_check,
             p2p: P2PConfig::parse_or_exit(cli.p2p),
             debug: DebugConfig::parse(cli.debug),
             verify_tree_hashes: cli.verify_tree_node_data,
+            verify_execution: cli.verify_execution.into(),
             rpc_batch_concurrency_limit: cli.rpc_batch_concurrency_limit,
+            rpc_max_batch_size: cli.rpc_max_batch_size,
+            rpc_max_response_size: cli
+                .rpc_max_response_size
+                .map(|size| size.saturating_mul(NonZeroUsize::new(1024).unwrap())),
             is_sync_enabled: cli.is_sync_enabled,
             is_rpc_enabled: cli.is_rpc_enabled,
+            read_only: cli.read_only,
+            offline: cli.offline || cli.fork,
+            fork: cli.fork,
+            grpc_listen: cli.grpc_listen,
+            graphql_listen: cli.graphql_listen,
+            feeder_gateway_api_listen: cli.feeder_gateway_api_listen,
             gateway_api_key: cli.gateway_api_key,
             event_bloom_filter_cache_size: cli.event_bloom_filter_cache_size,
             get_events_max_blocks_to_scan: cli.get_events_max_blocks_to_scan,
             get_events_max_uncached_bloom_filters_to_load: cli
                 .get_events_max_uncached_bloom_filters_to_load,
             gateway_timeout: Duration::from_secs(cli.gateway_timeout.get()),
+            gateway_retry_policy: RetryPolicy {
+                backoff_base_secs: cli.gateway_retry_backoff_base,
+                max_delay: Duration::from_secs(cli.gateway_retry_max_delay.get()),
+                max_retries: cli.gateway_max_retries,
+            },
+            gateway_circuit_breaker: NonZeroU32::new(cli.gateway_circuit_breaker_threshold).map(
+                |failure_threshold| CircuitBreakerConfig {
+                    failure_threshold,
+                    cooldown: Duration::from_secs(cli.gateway_circuit_breaker_cooldown.get()),
+                },
+            ),
             feeder_gateway_fetch_concurrency: cli.feeder_gateway_fetch_concurrency,
             state_tries: cli.state_tries,
+            prune_history: parse_prune_history_or_exit(cli.prune_history, cli.max_reorg_depth),
+            trie_backend: cli.trie_backend.map(Into::into),
+            pragma_profile: cli.pragma_profile.into(),
+            storage_connection_settings: pathfinder_storage::ConnectionSettings {
+                busy_timeout: cli
+                    .storage_busy_timeout
+                    .map(|timeout| Duration::from_millis(timeout.get())),
+                mmap_size: cli.storage_mmap_size.map(|mib| mib * 1024 * 1024),
+                cache_size_kib: cli
+                    .storage_cache_size
+                    .map(|mib| mib.get().saturating_mul(1024)),
+            },
+            rpc_storage_pool_size: cli.rpc_storage_pool_size,
+            storage_compaction_budget: cli.storage_compaction_budget,
+            storage_background_migration_rate: cli.storage_background_migration_rate,
             custom_versioned_constants: cli
                 .custom_versioned_constants_path
                 .map(parse_versioned_constants_or_exit),
             fetch_casm_from_fgw: cli.fetch_casm_from_fgw,
+            sync_checkpoint: cli.checkpoint.zip(cli.checkpoint_snapshot).map(
+                |(block_hash, snapshot)| SyncCheckpoint {
+                    block_hash,
+                    snapshot,
+                },
+            ),
+            backup: cli
+                .backup_s3_url
+                .map(|bucket_url| pathfinder_lib::backup::BackupConfig {
+                    interval: Duration::from_secs(cli.backup_interval.get()),
+                    retention: cli.backup_retention,
+                    prefix: cli.backup_prefix,
+                    s3: pathfinder_lib::backup::S3Config {
+                        bucket_url,
+                        region: cli.backup_s3_region,
+                        access_key_id: cli
+                            .backup_s3_access_key_id
+                            .expect("Required by --backup.s3-url"),
+                        secret_access_key: cli
+                            .backup_s3_secret_access_key
+                            .expect("Required by --backup.s3-url"),
+                    },
+                }),
+            otlp: cli
+                .tracing_otlp_endpoint
+                .map(|endpoint| crate::otlp::OtlpConfig {
+                    endpoint,
+                    sample_ratio: cli.tracing_otlp_sample_ratio,
+                }),
         }
     }
 }
@@ -1153,4 +2530,536 @@ mod tests {
         )
         .unwrap();
     }
+
+    #[test]
+    fn parse_chain_spec() {
+        use pathfinder_common::macro_prelude::*;
+
+        use super::ChainSpec;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("chain.toml");
+        std::fs::write(
+            &path,
+            r#"
+chain_id = "MY_APPCHAIN"
+core_contract_address = "0x00000000000000000000000000000000000000ab"
+gateway_url = "http://localhost:5050/gateway"
+feeder_gateway_url = "http://localhost:5050/feeder_gateway"
+genesis_hash = "0x123"
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            super::parse_chain_spec(path).unwrap(),
+            ChainSpec {
+                chain_id: "MY_APPCHAIN".to_owned(),
+                core_contract_address: primitive_types::H160::from_low_u64_be(0xab),
+                gateway_url: "http://localhost:5050/gateway".parse().unwrap(),
+                feeder_gateway_url: "http://localhost:5050/feeder_gateway".parse().unwrap(),
+                genesis_hash: block_hash!("0x123"),
+            }
+        );
+    }
+
+    #[test]
+    fn parse_chain_spec_fails_on_missing_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("chain.toml");
+        std::fs::write(&path, r#"chain_id = "MY_APPCHAIN""#).unwrap();
+
+        assert_matches!(
+            super::parse_chain_spec(path).unwrap_err(),
+            super::ParseChainSpecError::Parse(_)
+        );
+    }
+
+    #[test]
+    fn parse_rate_limits() {
+        use std::collections::HashMap;
+
+        use pathfinder_rpc::middleware::rate_limit::{BucketConfig, MethodGroup, RateLimitConfig};
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rate_limits.toml");
+        std::fs::write(
+            &path,
+            r#"
+[per_ip]
+requests_per_second = 10
+burst = 50
+
+[groups.trace]
+requests_per_second = 2
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            super::parse_rate_limits(path).unwrap(),
+            RateLimitConfig {
+                global: None,
+                per_ip: Some(BucketConfig {
+                    requests_per_second: 10.try_into().unwrap(),
+                    burst: Some(50.try_into().unwrap()),
+                }),
+                groups: HashMap::from([(
+                    MethodGroup::Trace,
+                    BucketConfig {
+                        requests_per_second: 2.try_into().unwrap(),
+                        burst: None,
+                    }
+                )]),
+            }
+        );
+    }
+
+    #[test]
+    fn parse_snapshot_source() {
+        use super::SnapshotSource;
+
+        assert_eq!(
+            super::parse_snapshot_source("https://example.com/mainnet.zst").unwrap(),
+            SnapshotSource::Url("https://example.com/mainnet.zst".parse().unwrap())
+        );
+        assert_eq!(
+            super::parse_snapshot_source("/snapshots/mainnet.zst").unwrap(),
+            SnapshotSource::Path("/snapshots/mainnet.zst".into())
+        );
+    }
+
+    #[test]
+    fn ethereum_url_is_not_required_offline() {
+        use clap::Parser;
+
+        super::Cli::try_parse_from(["pathfinder"]).unwrap_err();
+
+        let cli =
+            super::Cli::try_parse_from(["pathfinder", "--offline", "true", "--network", "mainnet"])
+                .unwrap();
+
+        assert!(cli.offline);
+        assert_eq!(cli.ethereum_url, None);
+    }
+
+    #[test]
+    fn ethereum_url_is_not_required_for_fork() {
+        use clap::Parser;
+
+        let cli =
+            super::Cli::try_parse_from(["pathfinder", "--fork", "true", "--network", "mainnet"])
+                .unwrap();
+
+        assert!(cli.fork);
+        assert_eq!(cli.ethereum_url, None);
+    }
+
+    #[test]
+    fn repeated_gateway_urls_are_failover_gateways() {
+        use clap::Parser;
+
+        let cli = super::Cli::try_parse_from([
+            "pathfinder",
+            "--ethereum.url",
+            "wss://example.com",
+            "--network",
+            "custom",
+            "--chain-id",
+            "SN_MAIN",
+            "--gateway-url",
+            "https://first.com/gateway",
+            "--feeder-gateway-url",
+            "https://first.com/feeder_gateway",
+            "--gateway-url",
+            "https://second.com/gateway",
+            "--feeder-gateway-url",
+            "https://second.com/feeder_gateway",
+        ])
+        .unwrap();
+
+        let network = super::NetworkConfig::from_components(cli.network);
+        assert_matches!(
+            network,
+            Some(super::NetworkConfig::Custom { gateway, feeder_gateway, failover_gateways, .. }) => {
+                assert_eq!(gateway.as_str(), "https://first.com/gateway");
+                assert_eq!(feeder_gateway.as_str(), "https://first.com/feeder_gateway");
+                assert_eq!(
+                    failover_gateways,
+                    vec![(
+                        "https://second.com/gateway".parse().unwrap(),
+                        "https://second.com/feeder_gateway".parse().unwrap()
+                    )]
+                );
+            }
+        );
+    }
+
+    #[test]
+    fn gateway_url_with_comma_is_a_single_gateway() {
+        use clap::Parser;
+
+        let cli = super::Cli::try_parse_from([
+            "pathfinder",
+            "--ethereum.url",
+            "wss://example.com",
+            "--network",
+            "custom",
+            "--chain-id",
+            "SN_MAIN",
+            "--gateway-url",
+            "https://proxy.com/gateway?upstream=a,b",
+            "--feeder-gateway-url",
+            "https://proxy.com/feeder_gateway?upstream=a,b",
+        ])
+        .unwrap();
+
+        let network = super::NetworkConfig::from_components(cli.network);
+        assert_matches!(
+            network,
+            Some(super::NetworkConfig::Custom { gateway, failover_gateways, .. }) => {
+                assert_eq!(gateway.as_str(), "https://proxy.com/gateway?upstream=a,b");
+                assert!(failover_gateways.is_empty());
+            }
+        );
+    }
+
+    #[test]
+    fn additional_network_requires_ethereum_url() {
+        use clap::Parser;
+
+        super::Cli::try_parse_from([
+            "pathfinder",
+            "--ethereum.url",
+            "wss://example.com",
+            "--additional-network",
+            "sepolia-testnet",
+        ])
+        .unwrap_err();
+
+        let cli = super::Cli::try_parse_from([
+            "pathfinder",
+            "--ethereum.url",
+            "wss://example.com",
+            "--additional-network",
+            "sepolia-testnet",
+            "--additional-network.ethereum.url",
+            "wss://sepolia.example.com",
+        ])
+        .unwrap();
+
+        assert_eq!(
+            cli.additional_network,
+            Some(super::AdditionalNetwork::SepoliaTestnet)
+        );
+        assert_eq!(
+            cli.additional_network_ethereum_url,
+            Some("wss://sepolia.example.com".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn rpc_tls_requires_cert_and_key() {
+        use clap::Parser;
+
+        super::Cli::try_parse_from([
+            "pathfinder",
+            "--ethereum.url",
+            "wss://example.com",
+            "--rpc.tls-cert",
+            "cert.pem",
+        ])
+        .unwrap_err();
+
+        let cli = super::Cli::try_parse_from([
+            "pathfinder",
+            "--ethereum.url",
+            "wss://example.com",
+            "--rpc.tls-cert",
+            "cert.pem",
+            "--rpc.tls-key",
+            "key.pem",
+        ])
+        .unwrap();
+
+        assert_eq!(cli.rpc_tls_cert, Some("cert.pem".into()));
+        assert_eq!(cli.rpc_tls_key, Some("key.pem".into()));
+    }
+
+    #[test]
+    fn backup_requires_credentials() {
+        use clap::Parser;
+
+        super::Cli::try_parse_from([
+            "pathfinder",
+            "--ethereum.url",
+            "wss://example.com",
+            "--backup.s3-url",
+            "https://s3.example.com/bucket",
+            "--backup.s3-access-key-id",
+            "id",
+        ])
+        .unwrap_err();
+
+        let cli = super::Cli::try_parse_from([
+            "pathfinder",
+            "--ethereum.url",
+            "wss://example.com",
+            "--backup.s3-url",
+            "https://s3.example.com/bucket",
+            "--backup.s3-access-key-id",
+            "id",
+            "--backup.s3-secret-access-key",
+            "secret",
+        ])
+        .unwrap();
+
+        assert_eq!(
+            cli.backup_s3_url,
+            Some("https://s3.example.com/bucket".parse().unwrap())
+        );
+        assert_eq!(cli.backup_retention.get(), 7);
+    }
+
+    #[test]
+    fn sample_ratio() {
+        assert_eq!(super::parse_sample_ratio("0.25"), Ok(0.25));
+        assert_eq!(super::parse_sample_ratio("1"), Ok(1.0));
+        assert_eq!(super::parse_sample_ratio("0"), Ok(0.0));
+        super::parse_sample_ratio("1.5").unwrap_err();
+        super::parse_sample_ratio("-0.1").unwrap_err();
+        super::parse_sample_ratio("NaN").unwrap_err();
+    }
+
+    #[test]
+    fn rpc_default_version_alias() {
+        use clap::Parser;
+
+        let cli = super::Cli::try_parse_from([
+            "pathfinder",
+            "--ethereum.url",
+            "wss://example.com",
+            "--rpc.default-version",
+            "v08",
+        ])
+        .unwrap();
+
+        assert_eq!(cli.rpc_root_version, super::RpcVersion::V08);
+    }
+
+    #[test]
+    fn rpc_method_filter() {
+        use clap::Parser;
+
+        let cli = super::Cli::try_parse_from([
+            "pathfinder",
+            "--ethereum.url",
+            "wss://example.com",
+            "--rpc.disabled-method-groups",
+            "trace,pathfinder",
+            "--rpc.disabled-methods",
+            "starknet_getEvents",
+            "--rpc.unrestricted-address",
+            "127.0.0.1:9546",
+        ])
+        .unwrap();
+
+        assert_eq!(
+            cli.rpc_disabled_method_groups,
+            vec![super::MethodSet::Trace, super::MethodSet::Pathfinder]
+        );
+        assert_eq!(cli.rpc_disabled_methods, vec!["starknet_getEvents"]);
+        assert_eq!(cli.rpc_enabled_methods, None);
+        assert_eq!(
+            cli.rpc_unrestricted_address,
+            Some("127.0.0.1:9546".parse().unwrap())
+        );
+
+        super::Cli::try_parse_from([
+            "pathfinder",
+            "--ethereum.url",
+            "wss://example.com",
+            "--rpc.disabled-method-groups",
+            "read",
+        ])
+        .unwrap_err();
+    }
+
+    #[test]
+    fn verify_chain_subcommand() {
+        use clap::Parser;
+
+        let cli = super::Cli::try_parse_from([
+            "pathfinder",
+            "verify-chain",
+            "--database",
+            "mainnet.sqlite",
+            "--from",
+            "10",
+        ])
+        .unwrap();
+
+        assert_matches!(
+            cli.command,
+            Some(super::CliCommand::VerifyChain(command)) => {
+                assert_eq!(
+                    command,
+                    super::VerifyChainCommand {
+                        database: "mainnet.sqlite".into(),
+                        from: Some(10),
+                        to: None,
+                    }
+                );
+            }
+        );
+    }
+
+    #[test]
+    fn bench_execute_subcommand() {
+        use clap::Parser;
+
+        let cli = super::Cli::try_parse_from([
+            "pathfinder",
+            "bench-execute",
+            "--database",
+            "mainnet.sqlite",
+            "--from",
+            "10",
+            "--threads",
+            "4",
+        ])
+        .unwrap();
+
+        assert_matches!(
+            cli.command,
+            Some(super::CliCommand::BenchExecute(command)) => {
+                assert_eq!(
+                    command,
+                    super::BenchExecuteCommand {
+                        database: "mainnet.sqlite".into(),
+                        from: 10,
+                        to: None,
+                        threads: std::num::NonZeroUsize::new(4).unwrap(),
+                        transactions: false,
+                    }
+                );
+            }
+        );
+    }
+
+    #[test]
+    fn replay_subcommand() {
+        use clap::Parser;
+
+        let cli = super::Cli::try_parse_from([
+            "pathfinder",
+            "replay",
+            "--database",
+            "mainnet.sqlite",
+            "recorded",
+        ])
+        .unwrap();
+
+        assert_matches!(
+            cli.command,
+            Some(super::CliCommand::Replay(command)) => {
+                assert_eq!(
+                    command,
+                    super::ReplayCommand {
+                        database: "mainnet.sqlite".into(),
+                        directory: "recorded".into(),
+                    }
+                );
+            }
+        );
+    }
+
+    #[test]
+    fn state_diff_subcommand() {
+        use clap::Parser;
+
+        let cli = super::Cli::try_parse_from([
+            "pathfinder",
+            "state-diff",
+            "--database",
+            "mainnet.sqlite",
+            "10",
+            "11",
+        ])
+        .unwrap();
+
+        assert_matches!(
+            cli.command,
+            Some(super::CliCommand::StateDiff(command)) => {
+                assert_eq!(
+                    command,
+                    super::StateDiffCommand {
+                        database: "mainnet.sqlite".into(),
+                        block_a: 10,
+                        block_b: Some(11),
+                        remote: None,
+                        limit: std::num::NonZeroUsize::new(100).unwrap(),
+                    }
+                );
+            }
+        );
+
+        let cli = super::Cli::try_parse_from([
+            "pathfinder",
+            "state-diff",
+            "--database",
+            "mainnet.sqlite",
+            "10",
+            "--remote",
+            "http://localhost:9545/rpc/pathfinder/v0.1",
+        ])
+        .unwrap();
+
+        assert_matches!(
+            cli.command,
+            Some(super::CliCommand::StateDiff(command)) => {
+                assert_eq!(command.block_b, None);
+                assert_eq!(
+                    command.remote.unwrap().as_str(),
+                    "http://localhost:9545/rpc/pathfinder/v0.1"
+                );
+            }
+        );
+
+        // One of the two is required.
+        super::Cli::try_parse_from([
+            "pathfinder",
+            "state-diff",
+            "--database",
+            "mainnet.sqlite",
+            "10",
+        ])
+        .unwrap_err();
+    }
+
+    #[test]
+    fn snapshot_subcommand_does_not_require_node_args() {
+        use clap::Parser;
+
+        let cli = super::Cli::try_parse_from([
+            "pathfinder",
+            "database",
+            "snapshot",
+            "export",
+            "--database",
+            "mainnet.sqlite",
+            "snapshot.zst",
+        ])
+        .unwrap();
+
+        assert_matches!(
+            cli.command,
+            Some(super::CliCommand::Database(super::DatabaseCommand::Snapshot(
+                super::SnapshotCommand::Export { database, path }
+            ))) => {
+                assert_eq!(database, std::path::Path::new("mainnet.sqlite"));
+                assert_eq!(path, std::path::Path::new("snapshot.zst"));
+            }
+        );
+    }
 }
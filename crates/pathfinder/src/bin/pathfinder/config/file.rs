@@ -0,0 +1,347 @@
+//! Node configuration from a TOML file, given using `--config`.
+//!
+//! Keys are the long names of the command line options, with tables standing
+//! in for the dotted prefixes of names:
+//!
+//! ```toml
+//! data-directory = "/var/lib/pathfinder"
+//! log-filter = "pathfinder=debug"
+//!
+//! [ethereum]
+//! url = "wss://eth-mainnet.g.alchemy.com/v2/<PROJECT_ID>"
+//!
+//! [rpc]
+//! rate-limits = "/etc/pathfinder/rate-limits.toml"
+//! disabled-methods = ["pathfinder_getProof", "starknet_getEvents"]
+//! ```
+//!
+//! The file's values are used as if they were given as the options'
+//! environment variables, so options given on the command line or as
+//! environment variables take precedence over the file.
+//!
+//! On SIGHUP the file is read again, and changes to the options in
+//! [RELOADABLE] take effect. For `rpc.rate-limits` this includes changes to the
+//! contents of the rate limits file. Changes to other options are only applied
+//! on restart, and removing an option keeps its current value.
+
+use std::collections::{BTreeMap, HashSet};
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::Context;
+use pathfinder_rpc::middleware::rate_limit::RateLimiter;
+
+/// Options which take effect when the file is reloaded.
+pub const RELOADABLE: [&str; 2] = ["log-filter", "rpc.rate-limits"];
+
+/// Replaces the filter of the logs, in `RUST_LOG` syntax.
+pub type ReloadLogFilter = Box<dyn Fn(&str) -> anyhow::Result<()> + Send + Sync>;
+
+/// The config file the node was started with.
+#[derive(Debug, Clone)]
+pub struct ConfigFile {
+    pub path: PathBuf,
+    /// The options set by the file when it was loaded, by their long names.
+    values: BTreeMap<String, String>,
+    /// Options set by the file which were also given on the command line or
+    /// as environment variables.
+    overridden: HashSet<String>,
+}
+
+/// The path given using `--config`, or its environment variable.
+pub(super) fn path(args: impl IntoIterator<Item = OsString>) -> Option<PathBuf> {
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            break;
+        }
+        if arg == "--config" {
+            return args.next().map(PathBuf::from);
+        }
+        if let Some(path) = arg.to_str().and_then(|arg| arg.strip_prefix("--config=")) {
+            return Some(path.into());
+        }
+    }
+
+    std::env::var_os("PATHFINDER_CONFIG").map(PathBuf::from)
+}
+
+impl ConfigFile {
+    /// Reads the file at `path` and sets the environment variables of the
+    /// options of `command` it sets, unless they are set already.
+    pub(super) fn load(path: PathBuf, command: &clap::Command) -> anyhow::Result<Self> {
+        let values = read(&path, command)?;
+
+        let mut overridden = HashSet::new();
+        for (option, value) in &values {
+            let env = env_name(command, option).expect("Options were validated");
+            if std::env::var_os(env).is_some() {
+                overridden.insert(option.clone());
+            } else {
+                std::env::set_var(env, value);
+            }
+        }
+
+        Ok(Self {
+            path,
+            values,
+            overridden,
+        })
+    }
+
+    /// Records the options set by the file which were also given on the
+    /// command line.
+    pub(super) fn given_on_command_line(
+        &mut self,
+        command: &clap::Command,
+        matches: &clap::ArgMatches,
+    ) {
+        for option in self.values.keys() {
+            let given = command
+                .get_arguments()
+                .find(|arg| arg.get_long() == Some(option))
+                .and_then(|arg| matches.value_source(arg.get_id().as_str()))
+                == Some(clap::parser::ValueSource::CommandLine);
+            if given {
+                self.overridden.insert(option.clone());
+            }
+        }
+    }
+
+    /// Reloads the file on every SIGHUP, applying the changes to the
+    /// [RELOADABLE] options which it sets and which were not overridden.
+    pub fn reload_on_sighup(
+        self,
+        reload_log_filter: ReloadLogFilter,
+        rate_limiter: Arc<RateLimiter>,
+    ) -> anyhow::Result<()> {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut hangup = signal(SignalKind::hangup()).context("Registering SIGHUP handler")?;
+        tokio::spawn(async move {
+            while hangup.recv().await.is_some() {
+                match self.reload(&reload_log_filter, &rate_limiter) {
+                    Ok(()) => tracing::info!(path=%self.path.display(), "Reloaded config file"),
+                    Err(error) => tracing::warn!(
+                        path=%self.path.display(),
+                        error=?error,
+                        "Failed to reload config file, keeping the current configuration"
+                    ),
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    fn reload(
+        &self,
+        reload_log_filter: &ReloadLogFilter,
+        rate_limiter: &RateLimiter,
+    ) -> anyhow::Result<()> {
+        use clap::CommandFactory;
+
+        let values = read(&self.path, &super::Cli::command())?;
+
+        // Validated before applying anything, so that a bad value doesn't leave the
+        // configuration half reloaded.
+        let reloaded = |option: &str| {
+            values
+                .get(option)
+                .filter(|_| !self.overridden.contains(option))
+        };
+        let rate_limits = reloaded("rpc.rate-limits")
+            .map(|path| super::parse_rate_limits(path.into()))
+            .transpose()?;
+
+        if let Some(filter) = reloaded("log-filter") {
+            reload_log_filter(filter).context("Setting log filter")?;
+        }
+        if let Some(rate_limits) = rate_limits {
+            rate_limiter.reconfigure(rate_limits);
+        }
+
+        for (option, value) in &values {
+            if !RELOADABLE.contains(&option.as_str()) && self.values.get(option) != Some(value) {
+                tracing::warn!(%option, "Changing this option requires a restart");
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Reads the file at `path`, returning the values of the options it sets by
+/// their long names.
+fn read(path: &Path, command: &clap::Command) -> anyhow::Result<BTreeMap<String, String>> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Reading config file {}", path.display()))?;
+    let table: toml::Table = toml::from_str(&contents)
+        .with_context(|| format!("Parsing config file {}", path.display()))?;
+
+    let mut values = BTreeMap::new();
+    flatten("", table, &mut values)?;
+
+    for option in values.keys() {
+        anyhow::ensure!(
+            option != "config" && env_name(command, option).is_some(),
+            "Unknown option `{option}` in config file {}",
+            path.display()
+        );
+    }
+
+    Ok(values)
+}
+
+/// Adds the values of `table` to `values`, naming nested values after their
+/// tables, e.g. `rpc.rate-limits`. Arrays are joined into comma-separated
+/// lists.
+fn flatten(
+    prefix: &str,
+    table: toml::Table,
+    values: &mut BTreeMap<String, String>,
+) -> anyhow::Result<()> {
+    for (key, value) in table {
+        let key = if prefix.is_empty() {
+            key
+        } else {
+            format!("{prefix}.{key}")
+        };
+
+        let value = match value {
+            toml::Value::Table(table) => {
+                flatten(&key, table, values)?;
+                continue;
+            }
+            toml::Value::Array(items) => items
+                .into_iter()
+                .map(scalar)
+                .collect::<Option<Vec<_>>>()
+                .with_context(|| format!("`{key}` may only contain plain values"))?
+                .join(","),
+            value => scalar(value).expect("Tables and arrays are handled above"),
+        };
+        values.insert(key, value);
+    }
+
+    Ok(())
+}
+
+fn scalar(value: toml::Value) -> Option<String> {
+    match value {
+        toml::Value::String(value) => Some(value),
+        toml::Value::Integer(value) => Some(value.to_string()),
+        toml::Value::Float(value) => Some(value.to_string()),
+        toml::Value::Boolean(value) => Some(value.to_string()),
+        toml::Value::Datetime(value) => Some(value.to_string()),
+        toml::Value::Array(_) | toml::Value::Table(_) => None,
+    }
+}
+
+/// The environment variable of the option with the long name `option`.
+fn env_name<'a>(command: &'a clap::Command, option: &str) -> Option<&'a OsStr> {
+    command
+        .get_arguments()
+        .find(|arg| arg.get_long() == Some(option))
+        .and_then(|arg| arg.get_env())
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::CommandFactory;
+
+    use super::*;
+
+    fn write(contents: &str) -> tempfile::NamedTempFile {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, contents.as_bytes()).unwrap();
+        file
+    }
+
+    #[test]
+    fn options_are_named_after_tables() {
+        let file = write(
+            r#"
+            data-directory = "/data"
+            rpc.websocket.enabled = true
+
+            [ethereum]
+            url = "wss://example.com"
+
+            [rpc]
+            max-batch-size = 10
+            disabled-methods = ["starknet_getEvents", "pathfinder_getProof"]
+            "#,
+        );
+
+        let values = read(file.path(), &crate::config::Cli::command()).unwrap();
+
+        assert_eq!(
+            values,
+            BTreeMap::from(
+                [
+                    ("data-directory", "/data"),
+                    ("ethereum.url", "wss://example.com"),
+                    ("rpc.max-batch-size", "10"),
+                    (
+                        "rpc.disabled-methods",
+                        "starknet_getEvents,pathfinder_getProof"
+                    ),
+                    ("rpc.websocket.enabled", "true"),
+                ]
+                .map(|(option, value)| (option.to_owned(), value.to_owned()))
+            )
+        );
+    }
+
+    #[test]
+    fn unknown_options_are_rejected() {
+        let command = crate::config::Cli::command();
+
+        let file = write("[rpc]\nunknown = 1");
+        let error = read(file.path(), &command).unwrap_err();
+        assert!(error.to_string().contains("`rpc.unknown`"), "{error}");
+
+        let file = write("config = \"other.toml\"");
+        read(file.path(), &command).unwrap_err();
+    }
+
+    #[test]
+    fn environment_takes_precedence() {
+        let command = clap::Command::new("test")
+            .arg(
+                clap::Arg::new("a")
+                    .long("a")
+                    .env("PATHFINDER_TEST_CONFIG_FILE_A"),
+            )
+            .arg(
+                clap::Arg::new("b")
+                    .long("b")
+                    .env("PATHFINDER_TEST_CONFIG_FILE_B"),
+            );
+        std::env::set_var("PATHFINDER_TEST_CONFIG_FILE_A", "from env");
+        let file = write("a = \"from file\"\nb = \"from file\"");
+
+        let config = ConfigFile::load(file.path().to_owned(), &command).unwrap();
+
+        assert_eq!(config.overridden, HashSet::from(["a".to_owned()]));
+        let matches = command.get_matches_from(["test"]);
+        assert_eq!(matches.get_one::<String>("a").unwrap(), "from env");
+        assert_eq!(matches.get_one::<String>("b").unwrap(), "from file");
+    }
+
+    #[test]
+    fn path_from_args() {
+        let args = |args: &[&str]| args.iter().map(OsString::from).collect::<Vec<_>>();
+
+        assert_eq!(
+            path(args(&["pathfinder", "--config", "a.toml"])),
+            Some("a.toml".into())
+        );
+        assert_eq!(
+            path(args(&["pathfinder", "--config=b.toml"])),
+            Some("b.toml".into())
+        );
+    }
+}
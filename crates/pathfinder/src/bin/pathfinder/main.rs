@@ -1,21 +1,32 @@
 #![deny(rust_2018_idioms)]
 
 use std::net::SocketAddr;
-use std::num::NonZeroU32;
-use std::path::PathBuf;
+use std::num::{NonZeroU32, NonZeroUsize};
+use std::path::{Path, PathBuf};
 use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use anyhow::Context;
-use metrics_exporter_prometheus::PrometheusBuilder;
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder};
 use pathfinder_common::consts::VERGEN_GIT_DESCRIBE;
 use pathfinder_common::{BlockNumber, Chain, ChainId, EthereumChain};
 use pathfinder_ethereum::{EthereumApi, EthereumClient};
 use pathfinder_lib::monitoring::{self};
+use pathfinder_lib::node::{
+    rpc_pool_size,
+    NodeBuilder,
+    NodeConfig,
+    NodeHandle,
+    RpcServerConfig,
+    StorageConfig,
+    SyncConfig,
+    WebsocketConfig,
+};
 use pathfinder_lib::state;
 use pathfinder_lib::state::SyncContext;
-use pathfinder_rpc::context::WebsocketContext;
+use pathfinder_rpc::context::RpcConfig;
+use pathfinder_rpc::middleware::rate_limit::RateLimiter;
 use pathfinder_rpc::{Notifications, SyncState};
 use pathfinder_storage::Storage;
 use primitive_types::H160;
@@ -23,9 +34,10 @@ use starknet_gateway_client::GatewayApi;
 use tokio::signal::unix::{signal, SignalKind};
 use tracing::{info, warn};
 
-use crate::config::{NetworkConfig, StateTries};
+use crate::config::{AdditionalNetwork, NetworkConfig, StateTries};
 
 mod config;
+mod otlp;
 mod update;
 
 // The Cairo VM allocates felts on the stack, so during execution it's making
@@ -49,12 +61,34 @@ async fn async_main() -> anyhow::Result<()> {
         std::env::set_var("RUST_LOG", "pathfinder=info");
     }
 
-    let mut config = config::Config::parse();
+    let mut config = match config::Command::parse() {
+        config::Command::Node(config) => *config,
+        config::Command::Snapshot(command) => return run_snapshot_command(command).await,
+        config::Command::Compact(command) => return run_compact_command(command).await,
+        config::Command::VerifyChain(command) => return run_verify_chain_command(command).await,
+        config::Command::BenchExecute(command) => return run_bench_execute_command(command).await,
+        config::Command::Replay(command) => return run_replay_command(command).await,
+        config::Command::StateDiff(command) => return run_state_diff_command(command).await,
+    };
+
+    if let Some(filter) = &config.log_filter {
+        std::env::set_var("RUST_LOG", filter);
+    }
 
-    setup_tracing(
+    // Declared before anything is traced so that it is dropped, flushing the
+    // remaining spans, after everything else.
+    let otlp = config
+        .otlp
+        .as_ref()
+        .map(otlp::Exporter::new)
+        .transpose()
+        .context("Setting up OTLP exporter")?;
+
+    let reload_log_filter = setup_tracing(
         config.color,
         config.debug.pretty_log,
         config.log_output_json,
+        otlp.as_ref(),
     );
 
     info!(
@@ -83,17 +117,28 @@ async fn async_main() -> anyhow::Result<()> {
     let readiness = Arc::new(AtomicBool::new(false));
 
     let sync_state = Arc::new(SyncState::default());
+    // Created upfront so that monitoring can report the additional network's sync.
+    let additional_sync_state = Arc::new(SyncState::default());
 
-    let ethereum = EthereumContext::setup(config.ethereum.url.clone(), &config.ethereum.password)
-        .await
-        .context("Creating Ethereum context")?;
+    // There is no L1 connection in offline mode.
+    let ethereum = match &config.ethereum {
+        Some(ethereum) if !config.offline => Some(
+            EthereumContext::setup(ethereum.url.clone(), &ethereum.password)
+                .await
+                .context("Creating Ethereum context")?,
+        ),
+        _ => None,
+    };
 
     // Use the default starknet network if none was configured.
-    let network = match config.network {
-        Some(ref network) => network.clone(),
-        None => ethereum
+    let network = match (&config.network, &ethereum) {
+        (Some(network), _) => network.clone(),
+        (None, Some(ethereum)) => ethereum
             .default_network()
             .context("Using default Starknet network based on Ethereum configuration")?,
+        (None, None) => {
+            anyhow::bail!("The Starknet network must be set using --network in offline mode")
+        }
     };
 
     // Spawn monitoring if configured.
@@ -102,199 +147,290 @@ async fn async_main() -> anyhow::Result<()> {
             NetworkConfig::Mainnet => "mainnet",
             NetworkConfig::SepoliaTestnet => "testnet-sepolia",
             NetworkConfig::SepoliaIntegration => "integration-sepolia",
-            NetworkConfig::Custom { .. } => "custom",
+            NetworkConfig::Custom { .. } | NetworkConfig::ChainSpec(_) => "custom",
+        };
+        let mut sync_states = vec![sync_state.clone()];
+        // Metrics aren't broken down per network, so they are labelled with both.
+        // Sync gauges carry their own `chain` label instead.
+        let network_label = match &config.additional_network {
+            Some(additional) => {
+                sync_states.push(additional_sync_state.clone());
+                let additional_label = match additional.network {
+                    AdditionalNetwork::Mainnet => "mainnet",
+                    AdditionalNetwork::SepoliaTestnet => "testnet-sepolia",
+                    AdditionalNetwork::SepoliaIntegration => "integration-sepolia",
+                };
+                format!("{network_label}+{additional_label}")
+            }
+            None => network_label.to_owned(),
         };
         spawn_monitoring(
-            network_label,
+            &network_label,
             address,
             readiness.clone(),
-            sync_state.clone(),
+            sync_states,
+            config.monitor_max_sync_lag,
         )
         .await
         .context("Starting monitoring task")?;
     }
 
-    let pathfinder_context = PathfinderContext::configure_and_proxy_check(
+    let mut pathfinder_context = PathfinderContext::configure_and_proxy_check(
         network,
         &config.data_directory,
         config.gateway_api_key.clone(),
         config.gateway_timeout,
+        config.offline,
     )
     .await
     .context("Configuring pathfinder")?;
-
-    verify_networks(pathfinder_context.network, ethereum.chain)?;
-
-    let gateway_public_key = pathfinder_context
-        .gateway
-        .public_key()
-        .await
-        .context("Fetching Starknet gateway public key")?;
-
-    // Setup and verify database
-
-    let storage_manager =
-        pathfinder_storage::StorageBuilder::file(pathfinder_context.database.clone())
-            .journal_mode(config.sqlite_wal)
-            .bloom_filter_cache_size(config.event_bloom_filter_cache_size.get())
-            .trie_prune_mode(match config.state_tries {
-                Some(StateTries::Pruned(num_blocks_kept)) => {
-                    Some(pathfinder_storage::TriePruneMode::Prune { num_blocks_kept })
-                }
-                Some(StateTries::Archive) => Some(pathfinder_storage::TriePruneMode::Archive),
-                None => None,
-            })
-            .migrate()?;
-    let sync_storage = storage_manager
-        // 5 is enough for normal sync operations, and then `available_parallelism` for
-        // the rayon thread pool workers to use.
-        .create_pool(NonZeroU32::new(5 + available_parallelism.get() as u32).unwrap())
-        .context(
-            r"Creating database connection pool for sync.
-
-Hint: This is usually caused by exceeding the file descriptor limit of your system.
-      Try increasing the file limit to using `ulimit` or similar tooling.",
-        )?;
-
-    // Set the rpc file connection limit to a fraction of the RPC connections.
-    // Having this be too large is counter productive as disk IO will then slow down
-    // all queries.
-    let max_rpc_connections: u32 = config
-        .max_rpc_connections
-        .get()
-        .try_into()
-        .expect("usize should cast to u32");
-    let rpc_storage = std::cmp::max(10, max_rpc_connections / 8);
-    let rpc_storage = NonZeroU32::new(rpc_storage).expect("A non-zero minimum is set");
-    let rpc_storage = storage_manager.create_read_only_pool(rpc_storage).context(
-        r"Creating database connection pool for RPC
-
-Hint: This is usually caused by exceeding the file descriptor limit of your system.
-      Try increasing the file limit to using `ulimit` or similar tooling.",
-    )?;
-
-    let execution_storage_pool_size = config.execution_concurrency.unwrap_or_else(|| {
-        std::num::NonZeroU32::new(available_parallelism.get() as u32)
-            .expect("The number of CPU cores should be non-zero")
+    pathfinder_context.gateway = pathfinder_context.gateway.map(|gateway| {
+        gateway
+            .with_retry_policy(config.gateway_retry_policy)
+            .with_circuit_breaker(config.gateway_circuit_breaker)
     });
-    let execution_storage = storage_manager
-        .create_read_only_pool(execution_storage_pool_size)
-        .context(
-            r"Creating database connection pool for execution
-
-Hint: This is usually caused by exceeding the file descriptor limit of your system.
-      Try increasing the file limit to using `ulimit` or similar tooling.",
-        )?;
-
-    let p2p_storage = storage_manager
-        .create_pool(NonZeroU32::new(1).unwrap())
-        .context(
-            r"Creating database connection pool for p2p
-
-Hint: This is usually caused by exceeding the file descriptor limit of your system.
-      Try increasing the file limit to using `ulimit` or similar tooling.",
-        )?;
-
-    info!(location=?pathfinder_context.database, "Database migrated.");
-    verify_database(
-        &sync_storage,
-        pathfinder_context.network,
-        &pathfinder_context.gateway,
-    )
-    .await
-    .context("Verifying database")?;
-
-    sync_storage
-        .connection()
-        .context("Creating database connection")?
-        .transaction()
-        .context(r"Creating database transaction")?
-        .prune_tries()
-        .context("Pruning tries on startup")?;
-
-    let (tx_pending, rx_pending) = tokio::sync::watch::channel(Default::default());
+    pathfinder_context.failover_gateways = pathfinder_context
+        .failover_gateways
+        .into_iter()
+        .map(|(url, gateway)| {
+            let gateway = gateway
+                .with_retry_policy(config.gateway_retry_policy)
+                .with_circuit_breaker(config.gateway_circuit_breaker);
+            (url, gateway)
+        })
+        .collect();
 
     let rpc_config = pathfinder_rpc::context::RpcConfig {
         batch_concurrency_limit: config.rpc_batch_concurrency_limit,
+        max_batch_size: config.rpc_max_batch_size,
+        max_response_size: config.rpc_max_response_size,
         get_events_max_blocks_to_scan: config.get_events_max_blocks_to_scan,
         get_events_max_uncached_bloom_filters_to_load: config
             .get_events_max_uncached_bloom_filters_to_load,
         custom_versioned_constants: config.custom_versioned_constants.take(),
+        health_max_block_age: config.rpc_health_max_block_age,
+        trace_cache_size: config.rpc_trace_cache_size,
+        trie_node_cache_size: config.rpc_trie_node_cache_size,
+        execution_limits: config.rpc_execution_limits,
+        execution_timeout: config.rpc_execution_timeout,
+        validate_transactions: config.rpc_validate_transactions,
+        request_log: config.rpc_request_log,
+        // Set by the node from the database.
+        sync_mode: Default::default(),
     };
 
-    let notifications = Notifications::default();
+    let rpc_server = config.is_rpc_enabled.then(|| RpcServerConfig {
+        address: config.rpc_address,
+        default_version: match config.rpc_root_version {
+            config::RpcVersion::V06 => pathfinder_rpc::RpcVersion::V06,
+            config::RpcVersion::V07 => pathfinder_rpc::RpcVersion::V07,
+            config::RpcVersion::V08 => pathfinder_rpc::RpcVersion::V08,
+        },
+        max_connections: config.max_rpc_connections,
+        cors: config.rpc_cors_domains.clone(),
+        tls: config.rpc_tls.clone(),
+        method_filter: config.rpc_method_filter.clone(),
+        unrestricted_address: config.rpc_unrestricted_address,
+        ipc_path: config.rpc_ipc_path.clone(),
+    });
 
-    let context = pathfinder_rpc::context::RpcContext::new(
-        rpc_storage,
-        execution_storage,
-        sync_state.clone(),
-        pathfinder_context.network_id,
-        pathfinder_context.gateway.clone(),
-        rx_pending.clone(),
-        notifications.clone(),
-        rpc_config,
+    let sync = match (&ethereum, &pathfinder_context.gateway) {
+        (Some(ethereum), Some(_)) if config.is_sync_enabled && !config.read_only => {
+            Some(sync_config(&config, ethereum.client.clone()))
+        }
+        _ => None,
+    };
+
+    let node = NodeBuilder::from_config(NodeConfig {
+        chain: pathfinder_context.network,
+        chain_id: pathfinder_context.network_id,
+        core_address: pathfinder_context.l1_core_address,
+        gateway: pathfinder_context.gateway.clone(),
+        database: pathfinder_context.database.clone(),
+        read_only: config.read_only,
+        checkpoint: config.sync_checkpoint.clone(),
+        storage: storage_config(&config),
+        rpc: rpc_config.clone(),
+        rpc_server,
+        websocket: config.websocket.enabled.then_some(WebsocketConfig {
+            socket_buffer_capacity: config.websocket.socket_buffer_capacity,
+            topic_sender_capacity: config.websocket.topic_sender_capacity,
+        }),
+        fork: config.fork,
+        sync,
+        sync_state: sync_state.clone(),
+        shutdown_timeout: config.rpc_shutdown_timeout,
+        sync_shutdown_timeout: SYNC_SHUTDOWN_TIMEOUT,
+        // Transactions are only forwarded to the gateway if we're neither offline
+        // nor forking, which implies offline.
+        track_submissions: pathfinder_context.gateway.is_some(),
+        rebroadcast_window: config.rpc_rebroadcast_window,
+    })
+    .open()
+    .await?;
+
+    let sync_mode = node.storage_manager().sync_mode();
+    #[cfg(feature = "p2p")]
+    anyhow::ensure!(
+        sync_mode == pathfinder_storage::SyncMode::Full || config.p2p.proxy,
+        "Light sync mode is only supported when syncing from the feeder gateway"
+    );
+    // The additional network's database is created in the same mode.
+    config.sync_mode = Some(sync_mode);
+    // Backups copy the SQLite database only, which lacks the trie nodes kept in
+    // RocksDB.
+    anyhow::ensure!(
+        config.backup.is_none()
+            || node.storage_manager().trie_backend() == pathfinder_storage::TrieBackend::Sqlite,
+        "Backups are not supported for databases storing their Merkle tries in RocksDB"
     );
 
-    let context = if config.websocket.enabled {
-        context.with_websockets(WebsocketContext::new(
-            config.websocket.socket_buffer_capacity,
-            config.websocket.topic_sender_capacity,
-            rx_pending,
-        ))
+    let p2p_storage = if config.read_only {
+        node.storage_manager()
+            .create_read_only_pool(NonZeroU32::new(1).unwrap())
     } else {
-        context
-    };
+        node.storage_manager()
+            .create_pool(NonZeroU32::new(1).unwrap())
+    }
+    .context(
+        r"Creating database connection pool for p2p
+
+Hint: This is usually caused by exceeding the file descriptor limit of your system.
+      Try increasing the file limit to using `ulimit` or similar tooling.",
+    )?;
 
-    let default_version = match config.rpc_root_version {
-        config::RpcVersion::V06 => pathfinder_rpc::RpcVersion::V06,
-        config::RpcVersion::V07 => pathfinder_rpc::RpcVersion::V07,
+    let mut additional_node = match config.additional_network.take() {
+        Some(additional) => Some(
+            start_additional_network(
+                additional,
+                pathfinder_context.network,
+                &config,
+                rpc_config,
+                additional_sync_state,
+            )
+            .await
+            .context("Starting the additional network")?,
+        ),
+        None => None,
+    };
+    // Both networks are served under their own prefix, in addition to the main
+    // network being served at the root.
+    let node = match &additional_node {
+        Some((chain, additional)) => node.with_network(*chain, additional.rpc_context().clone()),
+        None => node,
     };
 
-    let rpc_server = pathfinder_rpc::RpcServer::new(config.rpc_address, context, default_version);
-    let rpc_server = match config.rpc_cors_domains {
-        Some(ref allowed_origins) => rpc_server.with_cors(allowed_origins.clone()),
-        None => rpc_server,
+    // With a config file, rate limits are always checked so that they can be
+    // added by reloading the file.
+    let rate_limiter = match (config.rpc_rate_limits.take(), &config.config_file) {
+        (Some(rate_limits), _) => Some(Arc::new(RateLimiter::new(rate_limits))),
+        (None, Some(_)) => Some(Arc::new(RateLimiter::new(Default::default()))),
+        (None, None) => None,
     };
+    if let (Some(config_file), Some(rate_limiter)) =
+        (config.config_file.take(), rate_limiter.clone())
+    {
+        config_file
+            .reload_on_sighup(reload_log_filter, rate_limiter)
+            .context("Setting up config file reloading")?;
+    }
 
-    let (p2p_handle, gossiper, p2p_client) = start_p2p(
-        pathfinder_context.network_id,
-        p2p_storage,
-        config.p2p.clone(),
-    )
-    .await?;
+    let abi_registry = config
+        .rpc_abi_directory
+        .take()
+        .map(pathfinder_rpc::abi_registry::AbiRegistry::load)
+        .transpose()
+        .context("Loading ABIs for event decoding")?;
+
+    let recorder = config
+        .rpc_record
+        .take()
+        .map(|directory| pathfinder_rpc::record::Recorder::create(&directory))
+        .transpose()
+        .context("Starting the RPC call recorder")?;
+
+    let backup_status = config.backup.take().map(|backup| {
+        let (status_tx, status_rx) = tokio::sync::watch::channel(Default::default());
+        tokio::spawn(pathfinder_lib::backup::run(
+            backup,
+            pathfinder_context.database.clone(),
+            status_tx,
+        ));
+        status_rx
+    });
 
-    let sync_handle = if config.is_sync_enabled {
-        start_sync(
-            sync_storage,
-            pathfinder_context,
-            ethereum.client,
-            sync_state.clone(),
-            &config,
-            tx_pending,
-            rpc_server.get_topic_broadcasters().cloned(),
-            notifications,
-            gossiper,
-            gateway_public_key,
-            p2p_client,
-            config.verify_tree_hashes,
+    let node = node.map_rpc_context(|context| {
+        let context = match rate_limiter {
+            Some(rate_limiter) => context.with_rate_limiter(rate_limiter),
+            None => context,
+        };
+        let context = match abi_registry {
+            Some(abi_registry) => context.with_abi_registry(abi_registry),
+            None => context,
+        };
+        let context = match recorder {
+            Some(recorder) => context.with_recorder(recorder),
+            None => context,
+        };
+        match backup_status {
+            Some(status) => context.with_backup_status(status),
+            None => context,
+        }
+    });
+
+    let (p2p_handle, gossiper, p2p_client) = if config.offline {
+        (
+            tokio::task::spawn(futures::future::pending()),
+            Default::default(),
+            None,
         )
     } else {
-        tokio::task::spawn(futures::future::pending())
+        start_p2p(
+            pathfinder_context.network_id,
+            p2p_storage,
+            config.p2p.clone(),
+        )
+        .await?
     };
 
-    let rpc_handle = if config.is_rpc_enabled {
-        let (rpc_handle, local_addr) = rpc_server
-            .with_max_connections(config.max_rpc_connections.get())
-            .spawn()
-            .await
-            .context("Starting the RPC server")?;
-        info!("📡 HTTP-RPC server started on: {}", local_addr);
-        rpc_handle
-    } else {
-        tokio::spawn(std::future::pending())
-    };
+    let grpc_handle = start_grpc(
+        config.grpc_listen,
+        node.rpc_context().clone(),
+        config.rpc_method_filter.clone(),
+    )
+    .await?;
+    let graphql_handle = start_graphql(
+        config.graphql_listen,
+        node.rpc_context().clone(),
+        config.rpc_method_filter.clone(),
+        config.max_rpc_connections.get(),
+    )
+    .await?;
+    let feeder_gateway_api_handle = start_feeder_gateway_api(
+        config.feeder_gateway_api_listen,
+        node.rpc_context().clone(),
+        config.rpc_method_filter.clone(),
+        config.max_rpc_connections.get(),
+    )
+    .await?;
 
-    if !config.disable_version_update_check {
+    let mut node = node
+        .with_service("P2P", p2p_handle)
+        .with_service("gRPC server", grpc_handle)
+        .with_service("GraphQL server", graphql_handle)
+        .with_service("Feeder gateway API server", feeder_gateway_api_handle)
+        .start_with(|context| {
+            start_sync(
+                context,
+                &pathfinder_context.failover_gateways,
+                &config,
+                gossiper,
+                p2p_client,
+            )
+        })
+        .await?;
+
+    if !config.disable_version_update_check && !config.offline {
         tokio::spawn(update::poll_github_for_releases());
     }
 
@@ -306,72 +442,562 @@ Hint: This is usually caused by exceeding the file descriptor limit of your syst
 
     // Monitor our critical spawned process tasks.
     tokio::select! {
-        result = sync_handle => {
-            match result {
-                Ok(task_result) => tracing::error!("Sync process ended unexpected with: {:?}", task_result),
-                Err(err) => tracing::error!("Sync process ended unexpected; failed to join task handle: {:?}", err),
-            }
+        error = node.stopped() => {
+            tracing::error!("{error:#}");
             anyhow::bail!("Unexpected shutdown");
         }
-        result = rpc_handle => {
-            match result {
-                Ok(_) => tracing::error!("RPC server process ended unexpectedly"),
-                Err(err) => tracing::error!(error=%err, "RPC server process ended unexpectedly"),
-            }
-            anyhow::bail!("Unexpected shutdown");
-        }
-        result = p2p_handle => {
-            match result {
-                Ok(_) => tracing::error!("P2P process ended unexpectedly"),
-                Err(err) => tracing::error!(error=%err, "P2P process ended unexpectedly"),
-            }
+        error = additional_node_stopped(&mut additional_node) => {
+            tracing::error!(%error, "Additional network ended unexpectedly");
             anyhow::bail!("Unexpected shutdown");
         }
         _ = term_signal.recv() => {
             tracing::info!("TERM signal received, exiting gracefully");
-            Ok(())
         }
         _ = int_signal.recv() => {
             tracing::info!("INT signal received, exiting gracefully");
+        }
+    }
+
+    let stopped = node.shutdown().await;
+
+    if let Some((_, node)) = additional_node {
+        if let Err(error) = node.shutdown().await {
+            tracing::warn!(%error, "Additional network did not shut down cleanly");
+        }
+    }
+
+    stopped
+}
+
+/// Starts a node for the additional network, which syncs into its own database
+/// in the data directory. Its RPC API is served by the main RPC server.
+async fn start_additional_network(
+    additional: config::AdditionalNetworkConfig,
+    main_network: Chain,
+    config: &config::Config,
+    rpc_config: RpcConfig,
+    sync_state: Arc<SyncState>,
+) -> anyhow::Result<(Chain, NodeHandle)> {
+    anyhow::ensure!(
+        main_network != Chain::Custom,
+        "An additional network can only be run alongside a known network"
+    );
+
+    let node = match additional.network {
+        AdditionalNetwork::Mainnet => NodeBuilder::mainnet(&config.data_directory),
+        AdditionalNetwork::SepoliaTestnet => NodeBuilder::sepolia_testnet(&config.data_directory),
+        AdditionalNetwork::SepoliaIntegration => {
+            NodeBuilder::sepolia_integration(&config.data_directory)
+        }
+    };
+    let chain = node.config().chain;
+    anyhow::ensure!(
+        chain != main_network,
+        "The additional network must differ from the main network"
+    );
+
+    let gateway = node.config().gateway.clone().map(|gateway| {
+        gateway
+            .with_api_key(config.gateway_api_key.clone())
+            .with_retry_policy(config.gateway_retry_policy)
+            .with_circuit_breaker(config.gateway_circuit_breaker)
+    });
+    let mut node = node
+        .with_storage(storage_config(config))
+        .with_rpc_config(rpc_config)
+        .without_rpc_server()
+        .with_sync_state(sync_state)
+        .with_shutdown_timeout(config.rpc_shutdown_timeout)
+        .with_sync_shutdown_timeout(SYNC_SHUTDOWN_TIMEOUT)
+        // As for the main network, which is never offline alongside an additional
+        // one.
+        .with_submission_tracking(config.rpc_rebroadcast_window);
+    node.config_mut().gateway = gateway;
+
+    let node = if config.read_only {
+        node.read_only()
+    } else if config.is_sync_enabled {
+        let ethereum =
+            EthereumContext::setup(additional.ethereum.url, &additional.ethereum.password)
+                .await
+                .context("Creating Ethereum context")?;
+        node.with_sync(sync_config(config, ethereum.client))
+    } else {
+        node
+    };
+
+    let node = node.start().await?;
+    info!(network=%chain, "Additional network started");
+
+    Ok((chain, node))
+}
+
+/// The database settings of both networks.
+fn storage_config(config: &config::Config) -> StorageConfig {
+    StorageConfig {
+        journal_mode: config.sqlite_wal,
+        bloom_filter_cache_size: config.event_bloom_filter_cache_size.get(),
+        trie_prune_mode: match config.state_tries {
+            Some(StateTries::Pruned(num_blocks_kept)) => {
+                Some(pathfinder_storage::TriePruneMode::Prune { num_blocks_kept })
+            }
+            Some(StateTries::Archive) => Some(pathfinder_storage::TriePruneMode::Archive),
+            None => None,
+        },
+        history_prune_mode: match config.prune_history {
+            Some(num_blocks_kept) => pathfinder_storage::HistoryPruneMode::Prune {
+                num_blocks_kept: num_blocks_kept.get(),
+            },
+            None => pathfinder_storage::HistoryPruneMode::Archive,
+        },
+        trie_backend: config.trie_backend,
+        sync_mode: config.sync_mode,
+        pragma_profile: config.pragma_profile,
+        connection_settings: config.storage_connection_settings,
+        background_migration_rate: config.storage_background_migration_rate,
+        compaction_budget: config.storage_compaction_budget,
+        rpc_pool_size: Some(
+            config
+                .rpc_storage_pool_size
+                .unwrap_or_else(|| rpc_pool_size(config.max_rpc_connections)),
+        ),
+        execution_pool_size: config.execution_concurrency,
+    }
+}
+
+/// The feeder gateway sync configuration of both networks.
+fn sync_config(config: &config::Config, ethereum: EthereumClient) -> SyncConfig {
+    SyncConfig {
+        head_poll_interval: config.poll_interval,
+        l1_poll_interval: config.l1_poll_interval,
+        pending_poll_interval: config.pending_poll_interval,
+        pipeline: state::l2::PipelineConfig {
+            download_concurrency: config.feeder_gateway_fetch_concurrency,
+            verify_concurrency: config.sync_verify_concurrency,
+            class_fetch_concurrency: config.sync_class_fetch_concurrency,
+            queue_capacity: config.sync_queue_capacity,
+        },
+        fetch_casm_from_fgw: config.fetch_casm_from_fgw,
+        verify_tree_hashes: config.verify_tree_hashes,
+        verify_execution: config.verify_execution,
+        max_reorg_depth: config.max_reorg_depth,
+        restart_delay: config.debug.restart_delay,
+        ..SyncConfig::new(ethereum)
+    }
+}
+
+/// Resolves once the additional network stops unexpectedly, never if there is
+/// none.
+async fn additional_node_stopped(node: &mut Option<(Chain, NodeHandle)>) -> anyhow::Error {
+    match node {
+        Some((_, node)) => node.stopped().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// The maximum time to wait for sync to finish storing the current block on
+/// shutdown.
+const SYNC_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(30);
+
+async fn run_snapshot_command(command: config::SnapshotCommand) -> anyhow::Result<()> {
+    setup_tracing(config::Color::Auto, false, false, None);
+
+    tokio::task::spawn_blocking(move || match command {
+        config::SnapshotCommand::Export { database, path } => {
+            pathfinder_storage::snapshot::export_snapshot(&database, &path)
+                .context("Exporting database snapshot")?;
+            info!(snapshot=%path.display(), "Database snapshot exported");
             Ok(())
         }
+        config::SnapshotCommand::Import { database, path } => {
+            pathfinder_storage::snapshot::import_snapshot(&path, &database)
+                .context("Importing database snapshot")?;
+            info!(database=%database.display(), "Database snapshot imported");
+            Ok(())
+        }
+    })
+    .await
+    .context("Snapshot task panicked")?
+}
+
+async fn run_compact_command(command: config::CompactCommand) -> anyhow::Result<()> {
+    setup_tracing(config::Color::Auto, false, false, None);
+
+    tokio::task::spawn_blocking(move || {
+        let storage = pathfinder_storage::StorageBuilder::file(command.database)
+            .migrate()?
+            .create_pool(NonZeroU32::new(1).unwrap())
+            .context("Opening database")?;
+
+        pathfinder_storage::compaction::compact(&storage).context("Compacting database")
+    })
+    .await
+    .context("Compaction task panicked")?
+}
+
+async fn run_verify_chain_command(command: config::VerifyChainCommand) -> anyhow::Result<()> {
+    use pathfinder_lib::state::verify_chain::{detect_chain, verify_chain};
+
+    setup_tracing(config::Color::Auto, false, false, None);
+
+    tokio::task::spawn_blocking(move || {
+        let storage = pathfinder_storage::StorageBuilder::file(command.database)
+            .open_read_only()?
+            .create_read_only_pool(NonZeroU32::new(1).unwrap())
+            .context("Opening database")?;
+
+        let (chain, chain_id) = detect_chain(&storage)?;
+
+        let from = command
+            .from
+            .map(BlockNumber::new_or_panic)
+            .unwrap_or(BlockNumber::GENESIS);
+        let to = match command.to {
+            Some(to) => BlockNumber::new_or_panic(to),
+            None => {
+                let mut db = storage
+                    .connection()
+                    .context("Opening database connection")?;
+                let tx = db.transaction().context("Creating database transaction")?;
+                tx.block_id(pathfinder_storage::BlockId::Latest)
+                    .context("Fetching latest block number")?
+                    .context("Database is empty")?
+                    .0
+            }
+        };
+
+        info!(%chain, %from, %to, "Verifying chain");
+        let report = verify_chain(&storage, chain, chain_id, from, to)?;
+
+        if report.state_commitments_unavailable > 0 {
+            warn!(
+                blocks = report.state_commitments_unavailable,
+                "State commitments could not be verified as the tries have been pruned"
+            );
+        }
+
+        anyhow::ensure!(
+            report.mismatches.is_empty(),
+            "Found {} mismatches in {} blocks",
+            report.mismatches.len(),
+            report.blocks
+        );
+
+        info!(blocks = report.blocks, "Chain verified");
+        Ok(())
+    })
+    .await
+    .context("Verification task panicked")?
+}
+
+async fn run_state_diff_command(command: config::StateDiffCommand) -> anyhow::Result<()> {
+    use pathfinder_lib::state::state_diff::{state_diff, LocalState, RemoteState, Report};
+
+    setup_tracing(config::Color::Auto, false, false, None);
+
+    let runtime = tokio::runtime::Handle::current();
+    let limit = command.limit;
+
+    let report: Report = tokio::task::spawn_blocking(move || {
+        let storage = pathfinder_storage::StorageBuilder::file(command.database)
+            .migrate()?
+            .create_read_only_pool(NonZeroU32::new(1).unwrap())
+            .context("Opening database")?;
+        let mut db = storage
+            .connection()
+            .context("Opening database connection")?;
+        let tx = db.transaction().context("Creating database transaction")?;
+
+        let block_a = BlockNumber::new_or_panic(command.block_a);
+        let mut a = LocalState::new(&tx, block_a);
+        let limit = limit.get();
+
+        match (command.remote, command.block_b) {
+            (Some(url), _) => {
+                info!(block=%block_a, %url, "Comparing state against remote node");
+                let mut b = RemoteState::new(url, block_a, runtime);
+                state_diff(&mut a, &mut b, limit)
+            }
+            (None, Some(block_b)) => {
+                let block_b = BlockNumber::new_or_panic(block_b);
+                info!(a=%block_a, b=%block_b, "Comparing states");
+                let mut b = LocalState::new(&tx, block_b);
+                state_diff(&mut a, &mut b, limit)
+            }
+            (None, None) => anyhow::bail!("Either <BLOCK_B> or --remote is required"),
+        }
+    })
+    .await
+    .context("State diff task panicked")??;
+
+    for contract in &report.contracts {
+        warn!(
+            address=%contract.address,
+            a=?contract.a,
+            b=?contract.b,
+            "Contract state differs"
+        );
+        for slot in &contract.storage {
+            warn!(
+                address=%contract.address,
+                key=%slot.key,
+                a=?slot.a,
+                b=?slot.b,
+                "Storage value differs"
+            );
+        }
+    }
+    for class in &report.classes {
+        warn!(
+            class_hash=%class.key,
+            a=?class.a,
+            b=?class.b,
+            "Class commitment leaf differs"
+        );
+    }
+    if report.truncated {
+        warn!(
+            %limit,
+            "More differences were found than the limit, only the first ones are reported"
+        );
     }
+
+    anyhow::ensure!(
+        report.is_empty(),
+        "Found {} differing contracts and {} differing classes",
+        report.contracts.len(),
+        report.classes.len()
+    );
+
+    info!("States are identical");
+    Ok(())
+}
+
+async fn run_bench_execute_command(command: config::BenchExecuteCommand) -> anyhow::Result<()> {
+    use pathfinder_lib::bench_execute::bench_execute;
+    use pathfinder_lib::state::verify_chain::detect_chain;
+
+    setup_tracing(config::Color::Auto, false, false, None);
+
+    tokio::task::spawn_blocking(move || {
+        // Each worker holds a connection for the whole replay.
+        let storage = pathfinder_storage::StorageBuilder::file(command.database)
+            .migrate()?
+            .create_read_only_pool(NonZeroU32::new(command.threads.get() as u32).unwrap())
+            .context("Opening database")?;
+
+        let (_, chain_id) = detect_chain(&storage)?;
+
+        let from = BlockNumber::new_or_panic(command.from);
+        let to = match command.to {
+            Some(to) => BlockNumber::new_or_panic(to),
+            None => {
+                let mut db = storage
+                    .connection()
+                    .context("Opening database connection")?;
+                let tx = db.transaction().context("Creating database transaction")?;
+                tx.block_id(pathfinder_storage::BlockId::Latest)
+                    .context("Fetching latest block number")?
+                    .context("Database is empty")?
+                    .0
+            }
+        };
+
+        info!(%from, %to, threads=%command.threads, "Replaying blocks");
+        let report = bench_execute(&storage, chain_id, from, to, command.threads, |block| {
+            let elapsed = block.execution_time.as_secs_f64();
+            info!(
+                block=%block.number,
+                transactions=block.transactions.len(),
+                steps=block.steps(),
+                elapsed_ms=elapsed * 1000.0,
+                steps_per_second=block.steps() as f64 / elapsed.max(f64::EPSILON),
+                "Executed block"
+            );
+            if command.transactions {
+                for transaction in &block.transactions {
+                    info!(
+                        block=%block.number,
+                        transaction=%transaction.hash,
+                        steps=transaction.steps,
+                        reverted=transaction.reverted,
+                        "Executed transaction"
+                    );
+                }
+            }
+        })?;
+
+        if report.failed_blocks > 0 {
+            warn!(
+                blocks = report.failed_blocks,
+                "Some blocks failed to execute"
+            );
+        }
+        info!(
+            blocks = report.blocks,
+            transactions = report.transactions,
+            steps = report.steps,
+            elapsed_s = report.elapsed.as_secs_f64(),
+            execution_s = report.execution_time.as_secs_f64(),
+            steps_per_second = report.steps_per_second(),
+            class_cache_hits = report.class_cache.hits,
+            class_cache_misses = report.class_cache.misses,
+            class_cache_hit_rate = report.class_cache.hit_rate(),
+            block_class_cache_hits = report.block_class_cache.hits,
+            block_class_cache_misses = report.block_class_cache.misses,
+            block_class_cache_hit_rate = report.block_class_cache.hit_rate(),
+            "Replay finished"
+        );
+        Ok(())
+    })
+    .await
+    .context("Replay task panicked")?
+}
+
+async fn run_replay_command(command: config::ReplayCommand) -> anyhow::Result<()> {
+    use pathfinder_lib::state::verify_chain::detect_chain;
+    use pathfinder_rpc::record::{replay, ReplayOutcome};
+
+    setup_tracing(config::Color::Auto, false, false, None);
+
+    let database = command.database;
+    let (storage, chain_id) = tokio::task::spawn_blocking(move || -> anyhow::Result<_> {
+        let storage = pathfinder_storage::StorageBuilder::file(database)
+            .migrate()?
+            .create_read_only_pool(NonZeroU32::new(4).unwrap())
+            .context("Opening database")?;
+        let (_, chain_id) = detect_chain(&storage)?;
+        Ok((storage, chain_id))
+    })
+    .await
+    .context("Opening database task panicked")??;
+
+    // The node's defaults, so that paged responses are cut at the same places.
+    let config = RpcConfig {
+        batch_concurrency_limit: NonZeroUsize::new(1).unwrap(),
+        max_batch_size: NonZeroUsize::new(1000).unwrap(),
+        max_response_size: None,
+        get_events_max_blocks_to_scan: NonZeroUsize::new(500).unwrap(),
+        get_events_max_uncached_bloom_filters_to_load: NonZeroUsize::new(100_000).unwrap(),
+        custom_versioned_constants: None,
+        health_max_block_age: Duration::from_secs(300),
+        trace_cache_size: NonZeroUsize::new(128).unwrap(),
+        trie_node_cache_size: 64 * 1024 * 1024,
+        execution_limits: Default::default(),
+        execution_timeout: None,
+        validate_transactions: false,
+        request_log: Default::default(),
+        sync_mode: Default::default(),
+    };
+    let (_, pending_data) = tokio::sync::watch::channel(Default::default());
+    let context = pathfinder_rpc::context::RpcContext::new(
+        storage.clone(),
+        storage,
+        Arc::new(SyncState::default()),
+        chain_id,
+        // Only transaction submissions, which aren't replayed, use the gateway.
+        None,
+        pending_data,
+        Notifications::default(),
+        config,
+    );
+
+    info!(directory=%command.directory.display(), "Replaying RPC calls");
+    let report = replay(context, &command.directory, |call, outcome| match outcome {
+        ReplayOutcome::Matched => {}
+        ReplayOutcome::Mismatched(differences) => warn!(
+            method=%call.method,
+            version=%call.version,
+            params=%call.params.clone().unwrap_or_default(),
+            differences=?differences,
+            "Response differs"
+        ),
+        ReplayOutcome::Skipped(reason) => {
+            tracing::debug!(method=%call.method, %reason, "Skipped call")
+        }
+    })
+    .await?;
+
+    info!(
+        matched = report.matched,
+        mismatched = report.mismatched,
+        skipped = report.skipped,
+        "Replay finished"
+    );
+    Ok(())
 }
 
 #[cfg(feature = "tokio-console")]
-fn setup_tracing(color: config::Color, pretty_log: bool, json_log: bool) {
+fn setup_tracing(
+    color: config::Color,
+    pretty_log: bool,
+    json_log: bool,
+    otlp: Option<&otlp::Exporter>,
+) -> config::file::ReloadLogFilter {
+    use std::sync::RwLock;
+
     use tracing_subscriber::prelude::*;
 
     // EnvFilter isn't really a Filter, so this we need this ugly workaround for
     // filtering with it. See https://github.com/tokio-rs/tracing/issues/1868 for more details.
-    let env_filter = Arc::new(tracing_subscriber::EnvFilter::from_default_env());
+    let env_filter = Arc::new(RwLock::new(
+        tracing_subscriber::EnvFilter::from_default_env(),
+    ));
     let fmt_layer = tracing_subscriber::fmt::layer()
         .with_ansi(color.is_color_enabled())
         .with_target(pretty_log);
-    let filter =
-        tracing_subscriber::filter::dynamic_filter_fn(move |m, c| env_filter.enabled(m, c.clone()));
+    let filter = {
+        let env_filter = env_filter.clone();
+        tracing_subscriber::filter::dynamic_filter_fn(move |m, c| {
+            env_filter.read().unwrap().enabled(m, c.clone())
+        })
+    };
 
     if json_log {
         tracing_subscriber::registry()
             .with(fmt_layer.json().flatten_event(true).with_filter(filter))
             .with(console_subscriber::spawn())
+            .with(otlp.map(otlp::Exporter::layer))
             .init();
     } else if pretty_log {
         tracing_subscriber::registry()
             .with(fmt_layer.pretty().with_filter(filter))
             .with(console_subscriber::spawn())
+            .with(otlp.map(otlp::Exporter::layer))
             .init();
     } else {
         tracing_subscriber::registry()
             .with(fmt_layer.compact().with_filter(filter))
             .with(console_subscriber::spawn())
+            .with(otlp.map(otlp::Exporter::layer))
             .init();
     }
+
+    Box::new(move |filter| {
+        *env_filter.write().unwrap() = tracing_subscriber::EnvFilter::try_new(filter)?;
+        Ok(())
+    })
 }
 
 #[cfg(not(feature = "tokio-console"))]
-fn setup_tracing(color: config::Color, pretty_log: bool, json_log: bool) {
+fn setup_tracing(
+    color: config::Color,
+    pretty_log: bool,
+    json_log: bool,
+    otlp: Option<&otlp::Exporter>,
+) -> config::file::ReloadLogFilter {
     use time::macros::format_description;
+    use tracing_subscriber::prelude::*;
+    use tracing_subscriber::reload::Handle;
+
+    fn reload<S: 'static>(
+        handle: Handle<tracing_subscriber::EnvFilter, S>,
+    ) -> config::file::ReloadLogFilter {
+        Box::new(move |filter| {
+            handle.reload(tracing_subscriber::EnvFilter::try_new(filter)?)?;
+            Ok(())
+        })
+    }
 
     let time_fmt = format_description!("[year]-[month]-[day]T[hour]:[minute]:[second]");
     let time_fmt = tracing_subscriber::fmt::time::UtcTime::new(time_fmt);
@@ -382,14 +1008,36 @@ fn setup_tracing(color: config::Color, pretty_log: bool, json_log: bool) {
         .with_timer(time_fmt)
         .with_ansi(color.is_color_enabled());
 
+    // The filter is reloaded through the formatted subscriber, so it must be made
+    // reloadable after choosing the format. Spans are exported subject to the same
+    // filter.
     if json_log {
-        subscriber.json().flatten_event(true).init();
+        let subscriber = subscriber
+            .json()
+            .flatten_event(true)
+            .with_filter_reloading();
+        let handle = subscriber.reload_handle();
+        subscriber
+            .finish()
+            .with(otlp.map(otlp::Exporter::layer))
+            .init();
+        reload(handle)
+    } else if pretty_log {
+        let subscriber = subscriber.pretty().with_filter_reloading();
+        let handle = subscriber.reload_handle();
+        subscriber
+            .finish()
+            .with(otlp.map(otlp::Exporter::layer))
+            .init();
+        reload(handle)
     } else {
-        if pretty_log {
-            subscriber.pretty().init();
-        } else {
-            subscriber.compact().init();
-        }
+        let subscriber = subscriber.compact().with_filter_reloading();
+        let handle = subscriber.reload_handle();
+        subscriber
+            .finish()
+            .with(otlp.map(otlp::Exporter::layer))
+            .init();
+        reload(handle)
     }
 }
 
@@ -407,6 +1055,82 @@ fn permission_check(base: &std::path::Path) -> Result<(), anyhow::Error> {
     Ok(())
 }
 
+#[cfg(feature = "grpc")]
+async fn start_grpc(
+    address: Option<SocketAddr>,
+    context: pathfinder_rpc::context::RpcContext,
+    method_filter: pathfinder_rpc::middleware::method_filter::MethodFilter,
+) -> anyhow::Result<tokio::task::JoinHandle<anyhow::Result<()>>> {
+    let Some(address) = address else {
+        return Ok(tokio::task::spawn(futures::future::pending()));
+    };
+
+    let (handle, local_addr) = pathfinder_rpc::grpc::spawn(address, context, method_filter)
+        .await
+        .context("Starting the gRPC server")?;
+    info!("📡 gRPC server started on: {}", local_addr);
+
+    Ok(handle)
+}
+
+#[cfg(not(feature = "grpc"))]
+async fn start_grpc(
+    _: Option<SocketAddr>,
+    _: pathfinder_rpc::context::RpcContext,
+    _: pathfinder_rpc::middleware::method_filter::MethodFilter,
+) -> anyhow::Result<tokio::task::JoinHandle<anyhow::Result<()>>> {
+    Ok(tokio::task::spawn(futures::future::pending()))
+}
+
+#[cfg(feature = "graphql")]
+async fn start_graphql(
+    address: Option<SocketAddr>,
+    context: pathfinder_rpc::context::RpcContext,
+    method_filter: pathfinder_rpc::middleware::method_filter::MethodFilter,
+    max_connections: usize,
+) -> anyhow::Result<tokio::task::JoinHandle<anyhow::Result<()>>> {
+    let Some(address) = address else {
+        return Ok(tokio::task::spawn(futures::future::pending()));
+    };
+
+    let (handle, local_addr) =
+        pathfinder_rpc::graphql::spawn(address, context, method_filter, max_connections)
+            .await
+            .context("Starting the GraphQL server")?;
+    info!("📡 GraphQL server started on: {}", local_addr);
+
+    Ok(handle)
+}
+
+#[cfg(not(feature = "graphql"))]
+async fn start_graphql(
+    _: Option<SocketAddr>,
+    _: pathfinder_rpc::context::RpcContext,
+    _: pathfinder_rpc::middleware::method_filter::MethodFilter,
+    _: usize,
+) -> anyhow::Result<tokio::task::JoinHandle<anyhow::Result<()>>> {
+    Ok(tokio::task::spawn(futures::future::pending()))
+}
+
+async fn start_feeder_gateway_api(
+    address: Option<SocketAddr>,
+    context: pathfinder_rpc::context::RpcContext,
+    method_filter: pathfinder_rpc::middleware::method_filter::MethodFilter,
+    max_connections: usize,
+) -> anyhow::Result<tokio::task::JoinHandle<anyhow::Result<()>>> {
+    let Some(address) = address else {
+        return Ok(tokio::task::spawn(futures::future::pending()));
+    };
+
+    let (handle, local_addr) =
+        pathfinder_rpc::feeder_gateway::spawn(address, context, method_filter, max_connections)
+            .await
+            .context("Starting the feeder gateway API server")?;
+    info!("📡 Feeder gateway API server started on: {}", local_addr);
+
+    Ok(handle)
+}
+
 #[cfg(feature = "p2p")]
 async fn start_p2p(
     chain_id: ChainId,
@@ -507,138 +1231,116 @@ async fn start_p2p(
 }
 
 #[cfg(feature = "p2p")]
-#[allow(clippy::too_many_arguments)]
 fn start_sync(
-    storage: Storage,
-    pathfinder_context: PathfinderContext,
-    ethereum_client: EthereumClient,
-    sync_state: Arc<SyncState>,
+    context: SyncContext<starknet_gateway_client::Client, EthereumClient>,
+    failover_gateways: &[(reqwest::Url, starknet_gateway_client::Client)],
     config: &config::Config,
-    tx_pending: tokio::sync::watch::Sender<pathfinder_rpc::PendingData>,
-    websocket_txs: Option<pathfinder_rpc::TopicBroadcasters>,
-    notifications: Notifications,
     gossiper: state::Gossiper,
-    gateway_public_key: pathfinder_common::PublicKey,
     p2p_client: Option<p2p::client::peer_agnostic::Client>,
-    verify_tree_hashes: bool,
 ) -> tokio::task::JoinHandle<anyhow::Result<()>> {
+    let context = SyncContext {
+        gossiper,
+        ..context
+    };
     if config.p2p.proxy {
-        start_feeder_gateway_sync(
-            storage,
-            pathfinder_context,
-            ethereum_client,
-            sync_state,
-            config,
-            tx_pending,
-            websocket_txs,
-            notifications,
-            gossiper,
-            gateway_public_key,
-        )
+        let gateway = sync_gateway(context.sequencer.clone(), failover_gateways, config);
+        match p2p_client {
+            Some(p2p_client) if config.p2p.sync_from_peers => {
+                let sequencer = state::l2::source::PeerSource::new(
+                    p2p_client,
+                    gateway,
+                    context.chain,
+                    context.chain_id,
+                    context.sequencer_public_key,
+                );
+                start_feeder_gateway_sync(context.with_sequencer(sequencer))
+            }
+            _ => start_feeder_gateway_sync(context.with_sequencer(gateway)),
+        }
     } else {
         let p2p_client = p2p_client.expect("P2P client is expected with the p2p feature enabled");
-        start_p2p_sync(
-            storage,
-            pathfinder_context,
-            ethereum_client,
-            p2p_client,
-            gateway_public_key,
-            config.p2p.l1_checkpoint_override,
-            verify_tree_hashes,
-        )
+        start_p2p_sync(context, p2p_client, config.p2p.l1_checkpoint_override)
     }
 }
 
 #[cfg(not(feature = "p2p"))]
-#[allow(clippy::too_many_arguments)]
 fn start_sync(
-    storage: Storage,
-    pathfinder_context: PathfinderContext,
-    ethereum_client: EthereumClient,
-    sync_state: Arc<SyncState>,
+    context: SyncContext<starknet_gateway_client::Client, EthereumClient>,
+    failover_gateways: &[(reqwest::Url, starknet_gateway_client::Client)],
     config: &config::Config,
-    tx_pending: tokio::sync::watch::Sender<pathfinder_rpc::PendingData>,
-    websocket_txs: Option<pathfinder_rpc::TopicBroadcasters>,
-    notifications: Notifications,
     gossiper: state::Gossiper,
-    gateway_public_key: pathfinder_common::PublicKey,
     _p2p_client: Option<p2p::client::peer_agnostic::Client>,
-    _verify_tree_hashes: bool,
 ) -> tokio::task::JoinHandle<anyhow::Result<()>> {
+    let gateway = sync_gateway(context.sequencer.clone(), failover_gateways, config);
     start_feeder_gateway_sync(
-        storage,
-        pathfinder_context,
-        ethereum_client,
-        sync_state,
-        config,
-        tx_pending,
-        websocket_txs,
-        notifications,
-        gossiper,
-        gateway_public_key,
+        SyncContext {
+            gossiper,
+            ..context
+        }
+        .with_sequencer(gateway),
     )
 }
 
-#[allow(clippy::too_many_arguments)]
-fn start_feeder_gateway_sync(
-    storage: Storage,
-    pathfinder_context: PathfinderContext,
-    ethereum_client: EthereumClient,
-    sync_state: Arc<SyncState>,
+/// The gateway used by the feeder gateway sync, which fails over from
+/// `gateway` to the additional gateways if there are any.
+fn sync_gateway(
+    gateway: starknet_gateway_client::Client,
+    failover_gateways: &[(reqwest::Url, starknet_gateway_client::Client)],
     config: &config::Config,
-    tx_pending: tokio::sync::watch::Sender<pathfinder_rpc::PendingData>,
-    websocket_txs: Option<pathfinder_rpc::TopicBroadcasters>,
-    notifications: Notifications,
-    gossiper: state::Gossiper,
-    gateway_public_key: pathfinder_common::PublicKey,
-) -> tokio::task::JoinHandle<anyhow::Result<()>> {
-    let sync_context = SyncContext {
-        storage,
-        ethereum: ethereum_client,
-        chain: pathfinder_context.network,
-        chain_id: pathfinder_context.network_id,
-        core_address: pathfinder_context.l1_core_address,
-        sequencer: pathfinder_context.gateway,
-        state: sync_state.clone(),
-        head_poll_interval: config.poll_interval,
-        l1_poll_interval: config.l1_poll_interval,
-        pending_data: tx_pending,
-        block_validation_mode: state::l2::BlockValidationMode::Strict,
-        websocket_txs,
-        notifications,
-        block_cache_size: 1_000,
-        restart_delay: config.debug.restart_delay,
-        verify_tree_hashes: config.verify_tree_hashes,
-        gossiper,
-        sequencer_public_key: gateway_public_key,
-        fetch_concurrency: config.feeder_gateway_fetch_concurrency,
-        fetch_casm_from_fgw: config.fetch_casm_from_fgw,
+) -> state::l2::failover::FailoverSource<starknet_gateway_client::Client> {
+    use std::num::NonZeroUsize;
+
+    use starknet_gateway_client::RetryPolicy;
+
+    let primary = (gateway.feeder_gateway_url().to_string(), gateway);
+    if failover_gateways.is_empty() {
+        return state::l2::failover::FailoverSource::new([primary]);
+    }
+
+    // Requests need to give up eventually to fail over to the next gateway.
+    let retry_policy = RetryPolicy {
+        max_retries: config
+            .gateway_retry_policy
+            .max_retries
+            .or(NonZeroUsize::new(1)),
+        ..config.gateway_retry_policy
     };
+    let gateways = std::iter::once(primary)
+        .chain(
+            failover_gateways
+                .iter()
+                .map(|(url, gateway)| (url.to_string(), gateway.clone())),
+        )
+        .map(|(url, gateway)| (url, gateway.with_retry_policy(retry_policy)));
+    state::l2::failover::FailoverSource::new(gateways)
+}
 
-    tokio::spawn(state::sync(sync_context, state::l1::sync, state::l2::sync))
+fn start_feeder_gateway_sync<G>(
+    context: SyncContext<G, EthereumClient>,
+) -> tokio::task::JoinHandle<anyhow::Result<()>>
+where
+    G: GatewayApi + Clone + Send + Sync + 'static,
+{
+    tokio::spawn(state::sync(context, state::l1::sync, state::l2::sync))
 }
 
 #[cfg(feature = "p2p")]
 fn start_p2p_sync(
-    storage: Storage,
-    pathfinder_context: PathfinderContext,
-    ethereum_client: EthereumClient,
+    context: SyncContext<starknet_gateway_client::Client, EthereumClient>,
     p2p_client: p2p::client::peer_agnostic::Client,
-    gateway_public_key: pathfinder_common::PublicKey,
     l1_checkpoint_override: Option<pathfinder_ethereum::EthereumStateUpdate>,
-    verify_tree_hashes: bool,
 ) -> tokio::task::JoinHandle<anyhow::Result<()>> {
     let sync = pathfinder_lib::sync::Sync {
-        storage,
+        storage: context.storage,
         p2p: p2p_client,
-        eth_client: ethereum_client,
-        eth_address: pathfinder_context.l1_core_address,
-        fgw_client: pathfinder_context.gateway,
-        chain_id: pathfinder_context.network_id,
-        chain: pathfinder_context.network,
-        public_key: gateway_public_key,
+        eth_client: context.ethereum,
+        eth_address: context.core_address,
+        fgw_client: context.sequencer,
+        chain_id: context.chain_id,
+        chain: context.chain,
+        public_key: context.sequencer_public_key,
         l1_checkpoint_override,
-        verify_tree_hashes,
+        verify_tree_hashes: context.verify_tree_hashes,
     };
     tokio::spawn(sync.run())
 }
@@ -648,10 +1350,32 @@ async fn spawn_monitoring(
     network: &str,
     address: SocketAddr,
     readiness: Arc<AtomicBool>,
-    sync_state: Arc<SyncState>,
+    sync_states: Vec<Arc<SyncState>>,
+    max_sync_lag: Option<u64>,
 ) -> anyhow::Result<tokio::task::JoinHandle<()>> {
+    // Latencies vary from sub-millisecond reads to multi-second block
+    // processing, so use roughly exponential buckets.
+    const DURATION_BUCKETS: &[f64] = &[
+        0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0,
+    ];
+
     let prometheus_handle = PrometheusBuilder::new()
         .add_global_label("network", network)
+        .set_buckets_for_metric(
+            Matcher::Full("rpc_method_calls_duration_seconds".to_owned()),
+            DURATION_BUCKETS,
+        )
+        .context("Setting RPC latency buckets")?
+        .set_buckets_for_metric(
+            Matcher::Full("sync_stage_duration_seconds".to_owned()),
+            DURATION_BUCKETS,
+        )
+        .context("Setting sync stage duration buckets")?
+        .set_buckets_for_metric(
+            Matcher::Full("storage_connection_wait_seconds".to_owned()),
+            DURATION_BUCKETS,
+        )
+        .context("Setting database connection wait buckets")?
         .install_recorder()
         .context("Creating Prometheus recorder")?;
 
@@ -662,8 +1386,14 @@ async fn spawn_monitoring(
         Err(err) => tracing::error!("Failed to read system time: {:?}", err),
     }
 
-    let (_, handle) =
-        monitoring::spawn_server(address, readiness, sync_state, prometheus_handle).await?;
+    let (_, handle) = monitoring::spawn_server(
+        address,
+        readiness,
+        sync_states,
+        prometheus_handle,
+        max_sync_lag,
+    )
+    .await?;
     Ok(handle)
 }
 
@@ -714,7 +1444,7 @@ Hint: Make sure the provided ethereum.url and ethereum.password are good.",
                 anyhow::bail!(
                     r"Implicit Starknet networks are only available for Ethereum mainnet and Sepolia, but the provided Ethereum network has chain ID = {id}.
 
-If you are trying to connect to a custom Starknet on another Ethereum network, please use '--network custom'"
+If you are trying to connect to a custom Starknet on another Ethereum network, please use '--network custom' or '--chain-spec'"
                 )
             }
         }
@@ -724,7 +1454,11 @@ If you are trying to connect to a custom Starknet on another Ethereum network, p
 struct PathfinderContext {
     network: Chain,
     network_id: ChainId,
-    gateway: starknet_gateway_client::Client,
+    /// [None] in offline mode, where the gateway is never contacted.
+    gateway: Option<starknet_gateway_client::Client>,
+    /// Additional gateways sync can fail over to, along with their feeder
+    /// gateway urls.
+    failover_gateways: Vec<(reqwest::Url, starknet_gateway_client::Client)>,
     database: PathBuf,
     l1_core_address: H160,
 }
@@ -742,7 +1476,7 @@ mod pathfinder_context {
     use starknet_gateway_client::Client as GatewayClient;
 
     use super::PathfinderContext;
-    use crate::config::NetworkConfig;
+    use crate::config::{ChainSpec, NetworkConfig};
 
     impl PathfinderContext {
         pub async fn configure_and_proxy_check(
@@ -750,27 +1484,36 @@ mod pathfinder_context {
             data_directory: &Path,
             api_key: Option<String>,
             gateway_timeout: Duration,
+            offline: bool,
         ) -> anyhow::Result<Self> {
+            if offline {
+                return Self::configure_offline(cfg, data_directory);
+            }
+
             let context = match cfg {
                 NetworkConfig::Mainnet => Self {
                     network: Chain::Mainnet,
                     network_id: ChainId::MAINNET,
-                    gateway: GatewayClient::mainnet(gateway_timeout).with_api_key(api_key),
+                    gateway: Some(GatewayClient::mainnet(gateway_timeout).with_api_key(api_key)),
+                    failover_gateways: Vec::new(),
                     database: data_directory.join("mainnet.sqlite"),
                     l1_core_address: H160::from(core_addr::MAINNET),
                 },
                 NetworkConfig::SepoliaTestnet => Self {
                     network: Chain::SepoliaTestnet,
                     network_id: ChainId::SEPOLIA_TESTNET,
-                    gateway: GatewayClient::sepolia_testnet(gateway_timeout).with_api_key(api_key),
+                    gateway: Some(GatewayClient::sepolia_testnet(gateway_timeout).with_api_key(api_key)),
+                    failover_gateways: Vec::new(),
                     database: data_directory.join("testnet-sepolia.sqlite"),
                     l1_core_address: H160::from(core_addr::SEPOLIA_TESTNET),
                 },
                 NetworkConfig::SepoliaIntegration => Self {
                     network: Chain::SepoliaIntegration,
                     network_id: ChainId::SEPOLIA_INTEGRATION,
-                    gateway: GatewayClient::sepolia_integration(gateway_timeout)
-                        .with_api_key(api_key),
+                    gateway: Some(
+                        GatewayClient::sepolia_integration(gateway_timeout).with_api_key(api_key),
+                    ),
+                    failover_gateways: Vec::new(),
                     database: data_directory.join("integration-sepolia.sqlite"),
                     l1_core_address: H160::from(core_addr::SEPOLIA_INTEGRATION),
                 },
@@ -778,21 +1521,80 @@ mod pathfinder_context {
                     gateway,
                     feeder_gateway,
                     chain_id,
+                    failover_gateways,
                 } => Self::configure_custom(
                     gateway,
                     feeder_gateway,
                     chain_id,
+                    failover_gateways,
                     data_directory,
                     api_key,
                     gateway_timeout,
                 )
                 .await
                 .context("Configuring custom network")?,
+                NetworkConfig::ChainSpec(spec) => {
+                    Self::configure_chain_spec(spec, data_directory, api_key, gateway_timeout)
+                        .await
+                        .context("Configuring network from chain spec")?
+                }
             };
 
             Ok(context)
         }
 
+        /// Creates a [PathfinderContext] without any gateway clients. The L1
+        /// address of a custom network is unknown, which is fine as L1 is not
+        /// synced in offline mode.
+        fn configure_offline(cfg: NetworkConfig, data_directory: &Path) -> anyhow::Result<Self> {
+            use pathfinder_crypto::Felt;
+
+            let (network, network_id, database, l1_core_address) = match cfg {
+                NetworkConfig::Mainnet => (
+                    Chain::Mainnet,
+                    ChainId::MAINNET,
+                    "mainnet.sqlite",
+                    H160::from(core_addr::MAINNET),
+                ),
+                NetworkConfig::SepoliaTestnet => (
+                    Chain::SepoliaTestnet,
+                    ChainId::SEPOLIA_TESTNET,
+                    "testnet-sepolia.sqlite",
+                    H160::from(core_addr::SEPOLIA_TESTNET),
+                ),
+                NetworkConfig::SepoliaIntegration => (
+                    Chain::SepoliaIntegration,
+                    ChainId::SEPOLIA_INTEGRATION,
+                    "integration-sepolia.sqlite",
+                    H160::from(core_addr::SEPOLIA_INTEGRATION),
+                ),
+                NetworkConfig::Custom { chain_id, .. } => (
+                    Chain::Custom,
+                    ChainId(Felt::from_be_slice(chain_id.as_bytes()).context("Parsing chain ID")?),
+                    "custom.sqlite",
+                    H160::zero(),
+                ),
+                NetworkConfig::ChainSpec(spec) => (
+                    detect_proxy(spec.core_contract_address),
+                    ChainId(
+                        Felt::from_be_slice(spec.chain_id.as_bytes())
+                            .context("Parsing chain ID")?,
+                    ),
+                    "custom.sqlite",
+                    spec.core_contract_address,
+                ),
+            };
+
+            Ok(Self {
+                network,
+                network_id,
+                gateway: None,
+                failover_gateways: Vec::new(),
+                database: data_directory.join(database),
+                l1_core_address,
+            })
+        }
+
         /// Creates a [PathfinderContext] for a custom network. Provides
         /// additional verification by checking for a proxy gateway by
         /// comparing against L1 starknet address against of
@@ -801,6 +1603,7 @@ mod pathfinder_context {
             gateway: Url,
             feeder: Url,
             chain_id: String,
+            failover_gateways: Vec<(Url, Url)>,
             data_directory: &Path,
             api_key: Option<String>,
             gateway_timeout: Duration,
@@ -810,7 +1613,17 @@ mod pathfinder_context {
 
             let gateway = GatewayClient::with_urls(gateway, feeder, gateway_timeout)
                 .context("Creating gateway client")?
-                .with_api_key(api_key);
+                .with_api_key(api_key.clone());
+
+            let failover_gateways = failover_gateways
+                .into_iter()
+                .map(|(gateway, feeder)| {
+                    let client = GatewayClient::with_urls(gateway, feeder.clone(), gateway_timeout)
+                        .context("Creating failover gateway client")?
+                        .with_api_key(api_key.clone());
+                    Ok((feeder, client))
+                })
+                .collect::<anyhow::Result<_>>()?;
 
             let network_id =
                 ChainId(Felt::from_be_slice(chain_id.as_bytes()).context("Parsing chain ID")?);
@@ -822,106 +1635,81 @@ mod pathfinder_context {
                 .starknet
                 .0;
 
-            // Check for proxies by comparing the core address against those of the known
-            // networks.
-            let network = match l1_core_address.as_bytes() {
-                x if x == core_addr::MAINNET => Chain::Mainnet,
-                x if x == core_addr::SEPOLIA_TESTNET => Chain::SepoliaTestnet,
-                x if x == core_addr::SEPOLIA_INTEGRATION => Chain::SepoliaIntegration,
-                _ => Chain::Custom,
-            };
-
-            if network != Chain::Custom {
-                tracing::info!(%network, "Proxy gateway detected");
-            }
-
             let context = Self {
-                network,
+                network: detect_proxy(l1_core_address),
                 network_id,
-                gateway,
+                gateway: Some(gateway),
+                failover_gateways,
                 database: data_directory.join("custom.sqlite"),
                 l1_core_address,
             };
 
             Ok(context)
         }
-    }
-}
 
-/// Errors if there is a mismatch between the starknet and ethereum networks.
-fn verify_networks(starknet: Chain, ethereum: EthereumChain) -> anyhow::Result<()> {
-    if starknet != Chain::Custom {
-        let expected = match starknet {
-            Chain::Mainnet => EthereumChain::Mainnet,
-            Chain::SepoliaTestnet | Chain::SepoliaIntegration => EthereumChain::Sepolia,
-            Chain::Custom => unreachable!("Already checked against"),
-        };
+        /// Creates a [PathfinderContext] for the chain described by a chain
+        /// spec file. Unlike [configure_custom](Self::configure_custom) the
+        /// L1 core address is taken from the spec, and the gateway's genesis
+        /// block is checked against the spec's genesis hash.
+        async fn configure_chain_spec(
+            spec: ChainSpec,
+            data_directory: &Path,
+            api_key: Option<String>,
+            gateway_timeout: Duration,
+        ) -> anyhow::Result<Self> {
+            use pathfinder_common::BlockNumber;
+            use pathfinder_crypto::Felt;
+            use starknet_gateway_client::GatewayApi;
 
-        anyhow::ensure!(
-            ethereum == expected,
-            "Incorrect Ethereum network detected. Found {ethereum:?} but expected {expected:?} \
-             for {} Starknet",
-            starknet
-        );
-    }
+            let gateway = GatewayClient::with_urls(
+                spec.gateway_url,
+                spec.feeder_gateway_url,
+                gateway_timeout,
+            )
+            .context("Creating gateway client")?
+            .with_api_key(api_key);
 
-    Ok(())
-}
+            let network_id =
+                ChainId(Felt::from_be_slice(spec.chain_id.as_bytes()).context("Parsing chain ID")?);
 
-async fn verify_database(
-    storage: &Storage,
-    network: Chain,
-    gateway_client: &starknet_gateway_client::Client,
-) -> anyhow::Result<()> {
-    let storage = storage.clone();
-    let db_genesis = tokio::task::spawn_blocking(move || {
-        let mut conn = storage.connection().context("Create database connection")?;
-        let tx = conn.transaction().context("Create database transaction")?;
-
-        tx.block_id(BlockNumber::GENESIS.into())
-    })
-    .await
-    .context("Joining database task")?
-    .context("Fetching genesis hash from database")?
-    .map(|x| x.1);
-
-    if let Some(database_genesis) = db_genesis {
-        use pathfinder_common::consts::{
-            MAINNET_GENESIS_HASH,
-            SEPOLIA_INTEGRATION_GENESIS_HASH,
-            SEPOLIA_TESTNET_GENESIS_HASH,
-        };
+            let (_, gateway_genesis) = gateway
+                .block_header(BlockNumber::GENESIS.into())
+                .await
+                .context("Downloading genesis block from gateway for chain spec verification")?;
+            anyhow::ensure!(
+                gateway_genesis == spec.genesis_hash,
+                "Gateway genesis block does not match chain spec. {} != {}",
+                gateway_genesis,
+                spec.genesis_hash
+            );
 
-        let db_network = match database_genesis {
-            MAINNET_GENESIS_HASH => Chain::Mainnet,
-            SEPOLIA_TESTNET_GENESIS_HASH => Chain::SepoliaTestnet,
-            SEPOLIA_INTEGRATION_GENESIS_HASH => Chain::SepoliaIntegration,
+            let context = Self {
+                network: detect_proxy(spec.core_contract_address),
+                network_id,
+                gateway: Some(gateway),
+                failover_gateways: Vec::new(),
+                database: data_directory.join("custom.sqlite"),
+                l1_core_address: spec.core_contract_address,
+            };
+
+            Ok(context)
+        }
+    }
+
+    /// Checks for proxies by comparing the core address against those of the
+    /// known networks.
+    fn detect_proxy(l1_core_address: H160) -> Chain {
+        let network = match l1_core_address.as_bytes() {
+            x if x == core_addr::MAINNET => Chain::Mainnet,
+            x if x == core_addr::SEPOLIA_TESTNET => Chain::SepoliaTestnet,
+            x if x == core_addr::SEPOLIA_INTEGRATION => Chain::SepoliaIntegration,
             _ => Chain::Custom,
         };
 
-        match (network, db_network) {
-            (Chain::Custom, _) => {
-                // Verify against gateway.
-                let (_, gateway_hash) = gateway_client
-                    .block_header(BlockNumber::GENESIS.into())
-                    .await
-                    .context("Downloading genesis block from gateway for database verification")?;
-
-                anyhow::ensure!(
-                    database_genesis == gateway_hash,
-                    "Database genesis block does not match gateway. {} != {}",
-                    database_genesis,
-                    gateway_hash
-                );
-            }
-            (network, db_network) => anyhow::ensure!(
-                network == db_network,
-                "Database ({}) does not match the expected network ({})",
-                db_network,
-                network
-            ),
+        if network != Chain::Custom {
+            tracing::info!(%network, "Proxy gateway detected");
         }
-    }
 
-    Ok(())
+        network
+    }
 }
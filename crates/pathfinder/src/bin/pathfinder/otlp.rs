@@ -0,0 +1,71 @@
+//! Exports tracing spans to an OpenTelemetry collector, see
+//! `--tracing.otlp-endpoint`.
+
+use anyhow::Context;
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::trace::{Sampler, Tracer, TracerProvider};
+use opentelemetry_sdk::Resource;
+use pathfinder_common::consts::VERGEN_GIT_DESCRIBE;
+use tracing_opentelemetry::OpenTelemetryLayer;
+use tracing_subscriber::registry::LookupSpan;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OtlpConfig {
+    /// The gRPC endpoint of the collector.
+    pub endpoint: reqwest::Url,
+    /// The fraction of traces which are exported, in `[0, 1]`.
+    pub sample_ratio: f64,
+}
+
+/// Batches finished spans and sends them to the collector in the background.
+///
+/// Spans which haven't been sent yet are flushed when this is dropped, so it
+/// must outlive everything which is traced.
+pub struct Exporter(TracerProvider);
+
+impl Exporter {
+    /// Must be called from within the Tokio runtime, which runs the export
+    /// task.
+    pub fn new(config: &OtlpConfig) -> anyhow::Result<Self> {
+        let exporter = opentelemetry_otlp::SpanExporter::builder()
+            .with_tonic()
+            .with_endpoint(config.endpoint.as_str())
+            .build()
+            .context("Creating span exporter")?;
+
+        // The sampling decision is made once per trace, when its root span is
+        // created, so that a trace is exported either in full or not at all.
+        let sampler =
+            Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(config.sample_ratio)));
+
+        let provider = TracerProvider::builder()
+            .with_batch_exporter(exporter, opentelemetry_sdk::runtime::Tokio)
+            .with_sampler(sampler)
+            .with_resource(Resource::new([
+                KeyValue::new("service.name", "pathfinder"),
+                KeyValue::new("service.version", VERGEN_GIT_DESCRIBE),
+            ]))
+            .build();
+
+        Ok(Self(provider))
+    }
+
+    /// A layer which turns the spans of the subscriber into OpenTelemetry
+    /// spans.
+    pub fn layer<S>(&self) -> OpenTelemetryLayer<S, Tracer>
+    where
+        S: tracing::Subscriber + for<'span> LookupSpan<'span>,
+    {
+        tracing_opentelemetry::layer().with_tracer(self.0.tracer("pathfinder"))
+    }
+}
+
+impl Drop for Exporter {
+    fn drop(&mut self) {
+        if let Err(error) = self.0.shutdown() {
+            tracing::warn!(%error, "Failed to flush spans to the OTLP collector");
+        }
+    }
+}
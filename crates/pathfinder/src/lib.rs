@@ -1,6 +1,9 @@
 #![deny(rust_2018_idioms)]
 
+pub mod backup;
+pub mod bench_execute;
 pub mod monitoring;
+pub mod node;
 pub mod p2p_network;
 pub mod state;
 pub mod sync;
@@ -6,19 +6,30 @@ use metrics_exporter_prometheus::PrometheusHandle;
 use pathfinder_rpc::v02::types::syncing::Syncing;
 use pathfinder_rpc::SyncState;
 
+/// The number of blocks `/ready/synced` allows the node to lag behind the
+/// chain tip if no maximum is configured.
+const DEFAULT_MAX_SYNC_LAG: u64 = 5;
+
 #[derive(Clone)]
 struct State {
     readiness: Arc<AtomicBool>,
-    sync: Arc<SyncState>,
+    /// The sync state of every network served by the node.
+    sync: Vec<Arc<SyncState>>,
     prometheus: PrometheusHandle,
+    max_sync_lag: Option<u64>,
 }
 
 /// Spawns a server which hosts a `/health` endpoint.
+///
+/// If `max_sync_lag` is set, `/ready` and `/ready/synced` report the node as
+/// unavailable while the sync of any of `sync_states` is more than
+/// `max_sync_lag` blocks behind the chain tip.
 pub async fn spawn_server(
     addr: impl Into<std::net::SocketAddr> + 'static,
     readiness: Arc<AtomicBool>,
-    sync_state: Arc<SyncState>,
+    sync_states: Vec<Arc<SyncState>>,
     prometheus_handle: PrometheusHandle,
+    max_sync_lag: Option<u64>,
 ) -> anyhow::Result<(SocketAddr, tokio::task::JoinHandle<()>)> {
     let app = axum::Router::new()
         .route("/health", axum::routing::get(health_route))
@@ -27,8 +38,9 @@ pub async fn spawn_server(
         .route("/metrics", axum::routing::get(metrics_route))
         .with_state(State {
             readiness,
-            sync: sync_state,
+            sync: sync_states,
             prometheus: prometheus_handle,
+            max_sync_lag,
         });
     let listener = tokio::net::TcpListener::bind(addr.into()).await?;
     let addr = listener.local_addr()?;
@@ -45,16 +57,23 @@ async fn health_route() -> http::StatusCode {
     http::StatusCode::OK
 }
 
-/// Returns `Ok` if `readiness == true`, or `SERVICE_UNAVAILABLE` otherwise.
+/// Returns `Ok` if `readiness == true` and the node is within the configured
+/// maximum sync lag, if any, or `SERVICE_UNAVAILABLE` otherwise.
 async fn ready_route(axum::extract::State(state): axum::extract::State<State>) -> http::StatusCode {
-    if state.readiness.load(std::sync::atomic::Ordering::Relaxed) {
-        http::StatusCode::OK
-    } else {
-        http::StatusCode::SERVICE_UNAVAILABLE
+    if !state.readiness.load(std::sync::atomic::Ordering::Relaxed) {
+        return http::StatusCode::SERVICE_UNAVAILABLE;
+    }
+
+    match state.max_sync_lag {
+        Some(max_sync_lag) if !all_synced(&state.sync, max_sync_lag).await => {
+            http::StatusCode::SERVICE_UNAVAILABLE
+        }
+        _ => http::StatusCode::OK,
     }
 }
 
-/// Returns `Ok` if `readiness == true`, or `SERVICE_UNAVAILABLE` otherwise.
+/// Returns `Ok` if `readiness == true` and the node is within the maximum sync
+/// lag, or `SERVICE_UNAVAILABLE` otherwise.
 async fn synced_route(
     axum::extract::State(state): axum::extract::State<State>,
 ) -> http::StatusCode {
@@ -62,14 +81,36 @@ async fn synced_route(
         return http::StatusCode::SERVICE_UNAVAILABLE;
     }
 
-    let status = { state.sync.status.read().await.clone() };
-    match status {
-        Syncing::Status(status)
-            if status.highest.number.get() - status.current.number.get() < 6 =>
-        {
-            http::StatusCode::OK
+    let max_sync_lag = state.max_sync_lag.unwrap_or(DEFAULT_MAX_SYNC_LAG);
+    if all_synced(&state.sync, max_sync_lag).await {
+        http::StatusCode::OK
+    } else {
+        http::StatusCode::SERVICE_UNAVAILABLE
+    }
+}
+
+async fn all_synced(syncs: &[Arc<SyncState>], max_sync_lag: u64) -> bool {
+    for sync in syncs {
+        if !is_synced(sync, max_sync_lag).await {
+            return false;
         }
-        _ => http::StatusCode::SERVICE_UNAVAILABLE,
+    }
+    true
+}
+
+/// Whether sync is at most `max_sync_lag` blocks behind the chain tip. This is
+/// `false` until sync has reported its status.
+async fn is_synced(sync: &SyncState, max_sync_lag: u64) -> bool {
+    match &*sync.status.read().await {
+        Syncing::Status(status) => {
+            status
+                .highest
+                .number
+                .get()
+                .saturating_sub(status.current.number.get())
+                <= max_sync_lag
+        }
+        Syncing::False(_) => false,
     }
 }
 
@@ -114,6 +155,7 @@ mod tests {
             readiness.clone(),
             Default::default(),
             handle,
+            None,
         )
         .await
         .unwrap();
@@ -134,6 +176,7 @@ mod tests {
             readiness.clone(),
             Default::default(),
             handle,
+            None,
         )
         .await
         .unwrap();
@@ -163,8 +206,9 @@ mod tests {
         let (addr, _) = super::spawn_server(
             ([127, 0, 0, 1], 0),
             readiness.clone(),
-            sync_state.clone(),
+            vec![sync_state.clone()],
             handle,
+            None,
         )
         .await
         .unwrap();
@@ -218,6 +262,65 @@ mod tests {
         assert_eq!(resp.status(), reqwest::StatusCode::OK);
     }
 
+    #[tokio::test]
+    async fn ready_with_max_sync_lag() {
+        let readiness = Arc::new(AtomicBool::new(true));
+        let handle = PrometheusBuilder::new().build_recorder().handle();
+        let sync_state = Arc::new(SyncState {
+            status: RwLock::new(Syncing::False(false)),
+        });
+        let (addr, _) = super::spawn_server(
+            ([127, 0, 0, 1], 0),
+            readiness.clone(),
+            vec![sync_state.clone()],
+            handle,
+            Some(10),
+        )
+        .await
+        .unwrap();
+        let url = reqwest::Url::parse(&format!("http://{addr}")).unwrap();
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(5))
+            .build()
+            .unwrap();
+        wait_healthy(&client, url.clone()).await;
+
+        let status = |current: u64| {
+            Syncing::Status(Status {
+                starting: NumberedBlock {
+                    hash: Default::default(),
+                    number: BlockNumber::new_or_panic(0),
+                },
+                current: NumberedBlock {
+                    hash: Default::default(),
+                    number: BlockNumber::new_or_panic(current),
+                },
+                highest: NumberedBlock {
+                    hash: Default::default(),
+                    number: BlockNumber::new_or_panic(100),
+                },
+            })
+        };
+
+        // Sync has not reported its status yet.
+        for path in ["ready", "ready/synced"] {
+            let resp = client.get(url.join(path).unwrap()).send().await.unwrap();
+            assert_eq!(resp.status(), reqwest::StatusCode::SERVICE_UNAVAILABLE);
+        }
+
+        *sync_state.status.write().await = status(89);
+        for path in ["ready", "ready/synced"] {
+            let resp = client.get(url.join(path).unwrap()).send().await.unwrap();
+            assert_eq!(resp.status(), reqwest::StatusCode::SERVICE_UNAVAILABLE);
+        }
+
+        *sync_state.status.write().await = status(90);
+        for path in ["ready", "ready/synced"] {
+            let resp = client.get(url.join(path).unwrap()).send().await.unwrap();
+            assert_eq!(resp.status(), reqwest::StatusCode::OK);
+        }
+    }
+
     #[tokio::test]
     async fn metrics() {
         use pathfinder_common::test_utils::metrics::ScopedRecorderGuard;
@@ -239,6 +342,7 @@ mod tests {
             readiness.clone(),
             Default::default(),
             handle,
+            None,
         )
         .await
         .unwrap();
@@ -0,0 +1,1524 @@
+//! Runs a pathfinder node as part of another program.
+//!
+//! [NodeBuilder] covers the core of the `pathfinder` binary: it restores
+//! checkpoints, migrates and maintains the database, syncs it from the feeder
+//! gateway and L1 and serves the JSON-RPC API. The binary starts its node the
+//! same way, adding P2P, gRPC, GraphQL and monitoring as services of the
+//! [OpenedNode].
+
+use std::net::SocketAddr;
+use std::num::{NonZeroU32, NonZeroU64, NonZeroUsize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Context;
+use pathfinder_common::{AllowedOrigins, BlockHash, BlockNumber, Chain, ChainId, EthereumChain};
+use pathfinder_ethereum::{core_addr, EthereumApi, EthereumClient};
+use pathfinder_rpc::context::{RpcConfig, RpcContext, WebsocketContext};
+use pathfinder_rpc::middleware::method_filter::MethodFilter;
+use pathfinder_rpc::tls::TlsConfig;
+use pathfinder_rpc::{Notifications, RpcServer, RpcVersion, SyncState};
+use pathfinder_storage::{
+    ConnectionSettings,
+    HistoryPruneMode,
+    JournalMode,
+    PragmaProfile,
+    Storage,
+    StorageBuilder,
+    StorageManager,
+    SyncMode,
+    TrieBackend,
+    TriePruneMode,
+};
+use primitive_types::H160;
+use reqwest::Url;
+use starknet_gateway_client::{Client as GatewayClient, GatewayApi};
+use tokio::task::JoinHandle;
+
+use crate::state::chain_events::ChainEvents;
+use crate::state::{self, SyncContext};
+
+/// The node's configuration, see [NodeBuilder] for defaults.
+pub struct NodeConfig {
+    pub chain: Chain,
+    pub chain_id: ChainId,
+    /// The Starknet core contract on L1.
+    pub core_address: H160,
+    /// Synced from and forwarded submitted transactions to. The node is
+    /// offline without one.
+    pub gateway: Option<GatewayClient>,
+    /// The database file, created if it does not exist yet.
+    pub database: PathBuf,
+    /// Opens the database without migrating it, which rules out sync and
+    /// anything else writing to it.
+    pub read_only: bool,
+    /// Bootstraps a new database from a snapshot, ignored if the database
+    /// already exists.
+    pub checkpoint: Option<SyncCheckpoint>,
+    pub storage: StorageConfig,
+    pub rpc: RpcConfig,
+    /// Serves the JSON-RPC API, the [RpcContext] is available regardless.
+    pub rpc_server: Option<RpcServerConfig>,
+    pub websocket: Option<WebsocketConfig>,
+    /// Executes submitted transactions locally on top of the latest block,
+    /// instead of forwarding them to the gateway.
+    pub fork: bool,
+    /// Syncs the database, which is otherwise only served as is.
+    pub sync: Option<SyncConfig>,
+    /// Updated by sync, shared with the caller to monitor its progress.
+    pub sync_state: Arc<SyncState>,
+    /// How long shutdown waits for in-flight RPC requests to complete.
+    pub shutdown_timeout: Duration,
+    /// How long shutdown waits for sync to store the current block before
+    /// aborting it.
+    pub sync_shutdown_timeout: Duration,
+    /// Records the transactions submitted through the RPC API, see
+    /// [SubmissionTracker](pathfinder_rpc::submissions::SubmissionTracker).
+    pub track_submissions: bool,
+    /// Re-broadcasts tracked transactions until they are included in a block
+    /// or this much time has passed since their submission.
+    pub rebroadcast_window: Option<Duration>,
+}
+
+/// How the database is opened, see [StorageBuilder] for the settings.
+#[derive(Clone, Copy, Debug)]
+pub struct StorageConfig {
+    pub journal_mode: JournalMode,
+    pub bloom_filter_cache_size: usize,
+    pub trie_prune_mode: Option<TriePruneMode>,
+    pub history_prune_mode: HistoryPruneMode,
+    pub trie_backend: Option<TrieBackend>,
+    pub sync_mode: Option<SyncMode>,
+    pub pragma_profile: PragmaProfile,
+    pub connection_settings: ConnectionSettings,
+    /// The number of blocks per second processed by [background
+    /// migrations](pathfinder_storage::background_migration).
+    pub background_migration_rate: NonZeroU64,
+    /// The MiB of free database pages returned to the file system per minute,
+    /// see [compaction](pathfinder_storage::compaction).
+    pub compaction_budget: Option<NonZeroU64>,
+    /// Defaults to [rpc_pool_size] of the RPC server's connections.
+    pub rpc_pool_size: Option<NonZeroU32>,
+    /// Defaults to [execution_pool_size].
+    pub execution_pool_size: Option<NonZeroU32>,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            journal_mode: JournalMode::WAL,
+            bloom_filter_cache_size: 16,
+            trie_prune_mode: None,
+            history_prune_mode: HistoryPruneMode::Archive,
+            trie_backend: None,
+            sync_mode: None,
+            pragma_profile: Default::default(),
+            connection_settings: Default::default(),
+            background_migration_rate: NonZeroU64::new(500).unwrap(),
+            compaction_budget: None,
+            rpc_pool_size: None,
+            execution_pool_size: None,
+        }
+    }
+}
+
+impl StorageConfig {
+    pub fn builder(&self, database: PathBuf) -> StorageBuilder {
+        StorageBuilder::file(database)
+            .journal_mode(self.journal_mode)
+            .bloom_filter_cache_size(self.bloom_filter_cache_size)
+            .trie_prune_mode(self.trie_prune_mode)
+            .history_prune_mode(self.history_prune_mode)
+            .trie_backend(self.trie_backend)
+            .sync_mode(self.sync_mode)
+            .pragma_profile(self.pragma_profile)
+            .connection_settings(self.connection_settings)
+    }
+}
+
+pub struct RpcServerConfig {
+    /// Use port 0 to listen on a random port, see [NodeHandle::rpc_address].
+    pub address: SocketAddr,
+    /// The version served at the root path.
+    pub default_version: RpcVersion,
+    pub max_connections: NonZeroUsize,
+    pub cors: Option<AllowedOrigins>,
+    pub tls: Option<TlsConfig>,
+    /// The methods served at [RpcServerConfig::address].
+    pub method_filter: MethodFilter,
+    /// Serves all methods, regardless of the method filter.
+    pub unrestricted_address: Option<SocketAddr>,
+    /// Also serves the filtered methods on this Unix socket.
+    pub ipc_path: Option<PathBuf>,
+}
+
+pub struct WebsocketConfig {
+    pub socket_buffer_capacity: NonZeroUsize,
+    pub topic_sender_capacity: NonZeroUsize,
+}
+
+/// A trusted block to bootstrap a new database from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyncCheckpoint {
+    pub block_hash: BlockHash,
+    pub snapshot: SnapshotSource,
+}
+
+/// Where the snapshot for a [SyncCheckpoint] is loaded from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SnapshotSource {
+    Path(PathBuf),
+    Url(Url),
+}
+
+pub struct SyncConfig {
+    /// Must be connected to the L1 network of [NodeConfig::chain].
+    pub ethereum: EthereumClient,
+    pub head_poll_interval: Duration,
+    pub l1_poll_interval: Duration,
+    pub pending_poll_interval: Duration,
+    pub pipeline: state::l2::PipelineConfig,
+    pub fetch_casm_from_fgw: bool,
+    pub verify_tree_hashes: bool,
+    pub verify_execution: state::ExecutionVerification,
+    pub max_reorg_depth: NonZeroU64,
+    /// How long to wait before restarting L2 sync after it failed.
+    pub restart_delay: Duration,
+}
+
+impl SyncConfig {
+    /// The configuration used by the `pathfinder` binary by default.
+    pub fn new(ethereum: EthereumClient) -> Self {
+        Self {
+            ethereum,
+            head_poll_interval: Duration::from_secs(2),
+            l1_poll_interval: Duration::from_secs(120),
+            pending_poll_interval: Duration::from_millis(2000),
+            pipeline: Default::default(),
+            fetch_casm_from_fgw: false,
+            verify_tree_hashes: false,
+            verify_execution: Default::default(),
+            max_reorg_depth: NonZeroU64::new(10_000).unwrap(),
+            restart_delay: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Configures and starts a node.
+///
+/// By default the node serves RPC on `127.0.0.1:9545` and does not sync,
+/// which requires an Ethereum client to be configured using
+/// [NodeBuilder::with_sync].
+pub struct NodeBuilder {
+    config: NodeConfig,
+}
+
+impl NodeBuilder {
+    /// A mainnet node storing its database in `data_directory`.
+    pub fn mainnet(data_directory: &Path) -> Self {
+        Self::new(
+            Chain::Mainnet,
+            ChainId::MAINNET,
+            H160::from(core_addr::MAINNET),
+            GatewayClient::mainnet(GATEWAY_TIMEOUT),
+            data_directory.join("mainnet.sqlite"),
+        )
+    }
+
+    /// A Sepolia testnet node storing its database in `data_directory`.
+    pub fn sepolia_testnet(data_directory: &Path) -> Self {
+        Self::new(
+            Chain::SepoliaTestnet,
+            ChainId::SEPOLIA_TESTNET,
+            H160::from(core_addr::SEPOLIA_TESTNET),
+            GatewayClient::sepolia_testnet(GATEWAY_TIMEOUT),
+            data_directory.join("testnet-sepolia.sqlite"),
+        )
+    }
+
+    /// A Sepolia integration node storing its database in `data_directory`.
+    pub fn sepolia_integration(data_directory: &Path) -> Self {
+        Self::new(
+            Chain::SepoliaIntegration,
+            ChainId::SEPOLIA_INTEGRATION,
+            H160::from(core_addr::SEPOLIA_INTEGRATION),
+            GatewayClient::sepolia_integration(GATEWAY_TIMEOUT),
+            data_directory.join("integration-sepolia.sqlite"),
+        )
+    }
+
+    pub fn new(
+        chain: Chain,
+        chain_id: ChainId,
+        core_address: H160,
+        gateway: GatewayClient,
+        database: PathBuf,
+    ) -> Self {
+        Self {
+            config: NodeConfig {
+                chain,
+                chain_id,
+                core_address,
+                gateway: Some(gateway),
+                database,
+                read_only: false,
+                checkpoint: None,
+                storage: Default::default(),
+                rpc: default_rpc_config(),
+                rpc_server: Some(RpcServerConfig {
+                    address: ([127, 0, 0, 1], 9545).into(),
+                    default_version: RpcVersion::V07,
+                    max_connections: NonZeroUsize::new(1024).unwrap(),
+                    cors: None,
+                    tls: None,
+                    method_filter: Default::default(),
+                    unrestricted_address: None,
+                    ipc_path: None,
+                }),
+                websocket: None,
+                fork: false,
+                sync: None,
+                sync_state: Default::default(),
+                shutdown_timeout: Duration::from_secs(10),
+                sync_shutdown_timeout: Duration::from_secs(30),
+                track_submissions: false,
+                rebroadcast_window: None,
+            },
+        }
+    }
+
+    pub fn from_config(config: NodeConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn config(&self) -> &NodeConfig {
+        &self.config
+    }
+
+    pub fn config_mut(&mut self) -> &mut NodeConfig {
+        &mut self.config
+    }
+
+    pub fn with_gateway(mut self, gateway: GatewayClient) -> Self {
+        self.config.gateway = Some(gateway);
+        self
+    }
+
+    /// Runs the node offline, which rules out sync.
+    pub fn without_gateway(mut self) -> Self {
+        self.config.gateway = None;
+        self
+    }
+
+    pub fn read_only(mut self) -> Self {
+        self.config.read_only = true;
+        self
+    }
+
+    pub fn with_checkpoint(mut self, checkpoint: SyncCheckpoint) -> Self {
+        self.config.checkpoint = Some(checkpoint);
+        self
+    }
+
+    pub fn with_rpc_config(mut self, rpc: RpcConfig) -> Self {
+        self.config.rpc = rpc;
+        self
+    }
+
+    pub fn with_rpc_server(mut self, rpc_server: RpcServerConfig) -> Self {
+        self.config.rpc_server = Some(rpc_server);
+        self
+    }
+
+    pub fn without_rpc_server(mut self) -> Self {
+        self.config.rpc_server = None;
+        self
+    }
+
+    pub fn with_websockets(mut self, websocket: WebsocketConfig) -> Self {
+        self.config.websocket = Some(websocket);
+        self
+    }
+
+    pub fn with_fork(mut self) -> Self {
+        self.config.fork = true;
+        self
+    }
+
+    pub fn with_sync(mut self, sync: SyncConfig) -> Self {
+        self.config.sync = Some(sync);
+        self
+    }
+
+    pub fn with_sync_state(mut self, sync_state: Arc<SyncState>) -> Self {
+        self.config.sync_state = sync_state;
+        self
+    }
+
+    pub fn with_shutdown_timeout(mut self, timeout: Duration) -> Self {
+        self.config.shutdown_timeout = timeout;
+        self
+    }
+
+    pub fn with_sync_shutdown_timeout(mut self, timeout: Duration) -> Self {
+        self.config.sync_shutdown_timeout = timeout;
+        self
+    }
+
+    pub fn with_storage(mut self, storage: StorageConfig) -> Self {
+        self.config.storage = storage;
+        self
+    }
+
+    /// Tracks submitted transactions, re-broadcasting them for
+    /// `rebroadcast_window` if set.
+    pub fn with_submission_tracking(mut self, rebroadcast_window: Option<Duration>) -> Self {
+        self.config.track_submissions = true;
+        self.config.rebroadcast_window = rebroadcast_window;
+        self
+    }
+
+    /// Starts the node as configured, see [NodeBuilder::open] to extend it.
+    pub async fn start(self) -> anyhow::Result<NodeHandle> {
+        self.open().await?.start().await
+    }
+
+    /// Restores the checkpoint, opens and migrates the database and sets up
+    /// the RPC API. Nothing is served or synced until the returned node is
+    /// started.
+    pub async fn open(self) -> anyhow::Result<OpenedNode> {
+        let NodeConfig {
+            chain,
+            chain_id,
+            core_address,
+            gateway,
+            database,
+            read_only,
+            checkpoint,
+            storage,
+            rpc,
+            rpc_server,
+            websocket,
+            fork,
+            sync,
+            sync_state,
+            shutdown_timeout,
+            sync_shutdown_timeout,
+            track_submissions,
+            rebroadcast_window,
+        } = self.config;
+
+        if let Some(sync) = &sync {
+            anyhow::ensure!(!read_only, "Sync requires a writable database");
+            anyhow::ensure!(gateway.is_some(), "Sync requires a gateway");
+            if let HistoryPruneMode::Prune { num_blocks_kept } = storage.history_prune_mode {
+                anyhow::ensure!(
+                    num_blocks_kept >= sync.max_reorg_depth.get(),
+                    "History pruning keeps {num_blocks_kept} blocks, which is less than the \
+                     maximum reorg depth of {}: a reorg could roll back to state that has already \
+                     been pruned",
+                    sync.max_reorg_depth
+                );
+            }
+
+            let ethereum_chain = sync
+                .ethereum
+                .get_chain()
+                .await
+                .context("Determining Ethereum chain")?;
+            verify_networks(chain, ethereum_chain)?;
+        }
+
+        if let Some(directory) = database.parent() {
+            std::fs::create_dir_all(directory).context("Creating database directory")?;
+        }
+
+        let checkpoint = match checkpoint {
+            Some(checkpoint) if !read_only => restore_checkpoint(&checkpoint, &database).await?,
+            _ => None,
+        };
+
+        let storage_manager = storage.builder(database.clone());
+        let storage_manager = if read_only {
+            tracing::info!(
+                "Read-only mode enabled, syncing is disabled and no pending data is available"
+            );
+            storage_manager
+                .open_read_only()
+                .context("Opening database")?
+        } else {
+            storage_manager.migrate().context("Migrating database")?
+        };
+        // Existing databases keep the mode they were created with.
+        let sync_mode = storage_manager.sync_mode();
+        if sync_mode == SyncMode::Light {
+            tracing::info!(
+                "Light sync mode enabled, state diffs are not applied and state is unavailable"
+            );
+        }
+
+        let StoragePools {
+            sync: sync_storage,
+            rpc: rpc_storage,
+            execution: execution_storage,
+        } = StoragePools::create(
+            &storage_manager,
+            read_only,
+            storage.rpc_pool_size.unwrap_or_else(|| {
+                rpc_server
+                    .as_ref()
+                    .map_or(NonZeroU32::new(10).unwrap(), |server| {
+                        rpc_pool_size(server.max_connections)
+                    })
+            }),
+            match storage.execution_pool_size {
+                Some(execution_pool_size) => execution_pool_size,
+                None => execution_pool_size()?,
+            },
+        )?;
+
+        if let Some(block_hash) = checkpoint {
+            match verify_checkpoint(&sync_storage, chain, chain_id, block_hash).await {
+                Ok(block_number) => {
+                    tracing::info!(%block_number, %block_hash, "Syncing from checkpoint")
+                }
+                Err(error) => {
+                    // Don't leave an unverified database behind, it would otherwise be
+                    // picked up as is on the next start.
+                    drop(sync_storage);
+                    drop(rpc_storage);
+                    drop(execution_storage);
+                    drop(storage_manager);
+                    remove_database(&database);
+                    return Err(error.context("Verifying sync checkpoint"));
+                }
+            }
+        }
+
+        tracing::info!(location=?database, "Database migrated.");
+        verify_database(&sync_storage, chain, gateway.as_ref())
+            .await
+            .context("Verifying database")?;
+
+        if !read_only {
+            sync_storage
+                .connection()
+                .context("Creating database connection")?
+                .transaction()
+                .context("Creating database transaction")?
+                .prune_tries()
+                .context("Pruning tries on startup")?;
+        }
+
+        if fork {
+            let (latest, latest_hash) = sync_storage
+                .connection()
+                .context("Creating database connection")?
+                .transaction()
+                .context("Creating database transaction")?
+                .block_id(pathfinder_storage::BlockId::Latest)
+                .context("Querying latest block")?
+                .context("Forking requires a database containing at least one block")?;
+            tracing::info!(block_number=%latest, block_hash=%latest_hash, "Forking, transactions are executed locally");
+        }
+
+        let mut tasks = BackgroundTasks::default();
+        match storage.compaction_budget {
+            Some(_) if read_only => {
+                tracing::warn!("Online compaction is disabled in read-only mode")
+            }
+            Some(budget) => {
+                let compaction_storage = storage_manager
+                    .create_pool(NonZeroU32::new(1).unwrap())
+                    .context("Creating database connection pool for compaction")?
+                    .with_pool_name("compaction");
+                tasks.spawn(compact_online(compaction_storage, budget));
+            }
+            None => {}
+        }
+        if !read_only {
+            let migration_storage = storage_manager
+                .create_pool(NonZeroU32::new(1).unwrap())
+                .context("Creating database connection pool for background migrations")?
+                .with_pool_name("background_migration");
+            tasks.spawn(migrate_in_background(
+                migration_storage,
+                storage.background_migration_rate,
+            ));
+        }
+
+        let notifications = Notifications::default();
+        let chain_events = ChainEvents::new(notifications.clone(), rpc_storage.clone())
+            .context("Initializing chain events")?;
+        let (tx_pending, rx_pending) = tokio::sync::watch::channel(Default::default());
+
+        let rpc_context = RpcContext::new(
+            rpc_storage,
+            execution_storage,
+            sync_state.clone(),
+            chain_id,
+            gateway.clone(),
+            rx_pending.clone(),
+            notifications.clone(),
+            RpcConfig { sync_mode, ..rpc },
+        );
+        let rpc_context = match websocket {
+            Some(websocket) => rpc_context.with_websockets(WebsocketContext::new(
+                websocket.socket_buffer_capacity,
+                websocket.topic_sender_capacity,
+                rx_pending,
+            )),
+            None => rpc_context,
+        };
+        let rpc_context = if fork {
+            rpc_context.with_fork(tx_pending.clone())
+        } else {
+            rpc_context
+        };
+        let rpc_context = if track_submissions && !read_only {
+            let submissions_storage = storage_manager
+                .create_pool(NonZeroU32::new(1).unwrap())
+                .context("Creating database connection pool for submitted transactions")?
+                .with_pool_name("submissions");
+            let tracker = pathfinder_rpc::submissions::SubmissionTracker::new(submissions_storage);
+            if let (Some(gateway), Some(window)) = (&gateway, rebroadcast_window) {
+                tasks.spawn(tracker.clone().rebroadcast(gateway.clone(), window));
+            }
+            rpc_context.with_submission_tracking(tracker)
+        } else {
+            rpc_context
+        };
+
+        Ok(OpenedNode {
+            chain,
+            chain_id,
+            core_address,
+            gateway,
+            read_only,
+            storage_manager,
+            sync_storage,
+            rpc_context,
+            rpc_server,
+            networks: Vec::new(),
+            sync,
+            sync_state,
+            tx_pending,
+            notifications,
+            chain_events,
+            shutdown_timeout,
+            sync_shutdown_timeout,
+            tasks,
+            services: Vec::new(),
+        })
+    }
+}
+
+/// A node whose database is open, which can be extended before it is started,
+/// e.g. by services sharing its [RpcContext].
+pub struct OpenedNode {
+    chain: Chain,
+    chain_id: ChainId,
+    core_address: H160,
+    gateway: Option<GatewayClient>,
+    read_only: bool,
+    storage_manager: StorageManager,
+    sync_storage: Storage,
+    rpc_context: RpcContext,
+    rpc_server: Option<RpcServerConfig>,
+    networks: Vec<(Chain, RpcContext)>,
+    sync: Option<SyncConfig>,
+    sync_state: Arc<SyncState>,
+    tx_pending: tokio::sync::watch::Sender<pathfinder_rpc::PendingData>,
+    notifications: Notifications,
+    chain_events: ChainEvents,
+    shutdown_timeout: Duration,
+    sync_shutdown_timeout: Duration,
+    tasks: BackgroundTasks,
+    services: Vec<Service>,
+}
+
+impl OpenedNode {
+    /// For additional connection pools.
+    pub fn storage_manager(&self) -> &StorageManager {
+        &self.storage_manager
+    }
+
+    pub fn rpc_context(&self) -> &RpcContext {
+        &self.rpc_context
+    }
+
+    /// Replaces the [RpcContext], which is served and shared with sync.
+    pub fn map_rpc_context(mut self, f: impl FnOnce(RpcContext) -> RpcContext) -> Self {
+        self.rpc_context = f(self.rpc_context);
+        self
+    }
+
+    /// Also serves the RPC API of another network's node under its path
+    /// prefix, in which case this node's is served under its own as well as at
+    /// the root.
+    pub fn with_network(mut self, chain: Chain, rpc_context: RpcContext) -> Self {
+        self.networks.push((chain, rpc_context));
+        self
+    }
+
+    /// A task which the node stops with it, and which stops the node if it
+    /// ends, see [NodeHandle::stopped].
+    pub fn with_service<T>(mut self, name: &'static str, handle: JoinHandle<T>) -> Self
+    where
+        T: std::fmt::Debug + Send + 'static,
+    {
+        self.services.push(Service::new(name, handle));
+        self
+    }
+
+    /// Starts the RPC server and sync.
+    pub async fn start(self) -> anyhow::Result<NodeHandle> {
+        self.start_with(|context| {
+            tokio::spawn(state::sync(context, state::l1::sync, state::l2::sync))
+        })
+        .await
+    }
+
+    /// Starts the RPC server and sync, which is started by `start_sync` from
+    /// the context the node would sync with.
+    pub async fn start_with(
+        self,
+        start_sync: impl FnOnce(
+            SyncContext<GatewayClient, EthereumClient>,
+        ) -> JoinHandle<anyhow::Result<()>>,
+    ) -> anyhow::Result<NodeHandle> {
+        let Self {
+            chain,
+            chain_id,
+            core_address,
+            gateway,
+            read_only,
+            storage_manager,
+            sync_storage,
+            rpc_context,
+            rpc_server,
+            networks,
+            sync,
+            sync_state,
+            tx_pending,
+            notifications,
+            chain_events,
+            shutdown_timeout,
+            sync_shutdown_timeout,
+            tasks,
+            mut services,
+        } = self;
+
+        // Fetched upfront, so that nothing has been started if this fails.
+        let sync = match (sync, gateway) {
+            (Some(config), Some(gateway)) => {
+                let sequencer_public_key = gateway
+                    .public_key()
+                    .await
+                    .context("Fetching Starknet gateway public key")?;
+                Some((config, gateway, sequencer_public_key))
+            }
+            _ => None,
+        };
+
+        let (rpc_handle, rpc_address) = match rpc_server {
+            Some(config) => {
+                let scheme = if config.tls.is_some() {
+                    "HTTPS"
+                } else {
+                    "HTTP"
+                };
+                let server =
+                    RpcServer::new(config.address, rpc_context.clone(), config.default_version);
+                let server = if networks.is_empty() {
+                    server
+                } else {
+                    networks.into_iter().fold(
+                        server.with_network(network_path_prefix(chain), rpc_context.clone()),
+                        |server, (chain, context)| {
+                            server.with_network(network_path_prefix(chain), context)
+                        },
+                    )
+                };
+                let server = match config.cors {
+                    Some(allowed_origins) => server.with_cors(allowed_origins),
+                    None => server,
+                };
+                let server = match config.tls {
+                    Some(tls) => server.with_tls(tls),
+                    None => server,
+                };
+                let server = server.with_max_connections(config.max_connections.get());
+                let unrestricted_server = config
+                    .unrestricted_address
+                    .map(|address| server.clone().with_address(address));
+                let server = server.with_method_filter(config.method_filter);
+                let server = match config.ipc_path {
+                    Some(path) => server.with_ipc(path),
+                    None => server,
+                };
+
+                let (handle, address) = server.spawn().await.context("Starting the RPC server")?;
+                tracing::info!("📡 {scheme}-RPC server started on: {}", address);
+                if let Some(server) = unrestricted_server {
+                    let (handle, address) = server
+                        .spawn()
+                        .await
+                        .context("Starting the unrestricted RPC server")?;
+                    tracing::info!(
+                        "📡 Unrestricted {scheme}-RPC server started on: {}",
+                        address
+                    );
+                    services.push(Service::new("Unrestricted RPC server", handle));
+                }
+
+                (Some(handle), Some(address))
+            }
+            None => (None, None),
+        };
+
+        let (stop_sync, sync_shutdown) = tokio::sync::watch::channel(false);
+        let sync_handle = sync.map(|(config, gateway, sequencer_public_key)| {
+            let context = SyncContext {
+                storage: sync_storage.clone(),
+                ethereum: config.ethereum,
+                chain,
+                chain_id,
+                core_address,
+                sequencer: gateway,
+                state: sync_state,
+                head_poll_interval: config.head_poll_interval,
+                l1_poll_interval: config.l1_poll_interval,
+                pending_poll_interval: config.pending_poll_interval,
+                pending_data: tx_pending,
+                block_validation_mode: state::l2::BlockValidationMode::Strict,
+                websocket_txs: rpc_context
+                    .websocket
+                    .as_ref()
+                    .map(|websocket| websocket.broadcasters.clone()),
+                notifications,
+                block_cache_size: 1_000,
+                restart_delay: config.restart_delay,
+                verify_tree_hashes: config.verify_tree_hashes,
+                verify_execution: config.verify_execution,
+                gossiper: Default::default(),
+                sequencer_public_key,
+                pipeline: config.pipeline,
+                fetch_casm_from_fgw: config.fetch_casm_from_fgw,
+                max_reorg_depth: config.max_reorg_depth,
+                sync_mode: storage_manager.sync_mode(),
+                shutdown: sync_shutdown,
+            };
+
+            start_sync(context)
+        });
+
+        Ok(NodeHandle {
+            rpc_context,
+            rpc_address,
+            chain_events,
+            rpc_handle,
+            sync_handle,
+            services,
+            tasks,
+            stop_sync,
+            wal_storage: (!read_only).then_some(sync_storage),
+            shutdown_timeout,
+            sync_shutdown_timeout,
+        })
+    }
+}
+
+/// The database connection pools shared by sync and the RPC API.
+pub struct StoragePools {
+    pub sync: Storage,
+    pub rpc: Storage,
+    pub execution: Storage,
+}
+
+impl StoragePools {
+    /// Creates the pools, where `read_only` also opens the sync pool read-only
+    /// since there is no sync.
+    pub fn create(
+        storage_manager: &StorageManager,
+        read_only: bool,
+        rpc_pool_size: NonZeroU32,
+        execution_pool_size: NonZeroU32,
+    ) -> anyhow::Result<Self> {
+        const HINT: &str = r"
+Hint: This is usually caused by exceeding the file descriptor limit of your system.
+      Try increasing the file limit to using `ulimit` or similar tooling.";
+
+        // 5 is enough for normal sync operations, and then `available_parallelism`
+        // for the rayon thread pool workers to use.
+        let sync_pool_size = NonZeroU32::new(5 + execution_pool_size.get()).unwrap();
+        let sync = if read_only {
+            storage_manager.create_read_only_pool(sync_pool_size)
+        } else {
+            storage_manager.create_pool(sync_pool_size)
+        }
+        .with_context(|| format!("Creating database connection pool for sync.\n{HINT}"))?
+        .with_pool_name("sync");
+        let rpc = storage_manager
+            .create_read_only_pool(rpc_pool_size)
+            .with_context(|| format!("Creating database connection pool for RPC\n{HINT}"))?
+            .with_pool_name("rpc");
+        let execution = storage_manager
+            .create_read_only_pool(execution_pool_size)
+            .with_context(|| format!("Creating database connection pool for execution\n{HINT}"))?
+            .with_pool_name("execution");
+
+        Ok(Self {
+            sync,
+            rpc,
+            execution,
+        })
+    }
+}
+
+/// A fraction of the RPC connections, as more would only slow down disk IO.
+pub fn rpc_pool_size(max_connections: NonZeroUsize) -> NonZeroU32 {
+    let max_connections = u32::try_from(max_connections.get()).unwrap_or(u32::MAX);
+    NonZeroU32::new(std::cmp::max(10, max_connections / 8)).expect("A non-zero minimum is set")
+}
+
+/// One connection per CPU core, which is how many transactions can be executed
+/// in parallel.
+pub fn execution_pool_size() -> anyhow::Result<NonZeroU32> {
+    let available_parallelism = std::thread::available_parallelism()?;
+    Ok(NonZeroU32::new(available_parallelism.get() as u32)
+        .expect("The number of CPU cores should be non-zero"))
+}
+
+const GATEWAY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The configuration used by the `pathfinder` binary by default.
+fn default_rpc_config() -> RpcConfig {
+    RpcConfig {
+        batch_concurrency_limit: NonZeroUsize::new(1).unwrap(),
+        max_batch_size: NonZeroUsize::new(1000).unwrap(),
+        max_response_size: None,
+        get_events_max_blocks_to_scan: NonZeroUsize::new(500).unwrap(),
+        get_events_max_uncached_bloom_filters_to_load: NonZeroUsize::new(100_000).unwrap(),
+        custom_versioned_constants: None,
+        health_max_block_age: Duration::from_secs(300),
+        trace_cache_size: NonZeroUsize::new(128).unwrap(),
+        trie_node_cache_size: pathfinder_merkle_tree::TrieNodeCache::DEFAULT_MEMORY_BUDGET,
+        execution_limits: Default::default(),
+        execution_timeout: None,
+        validate_transactions: true,
+        request_log: Default::default(),
+        sync_mode: Default::default(),
+    }
+}
+
+/// A running node, which keeps running until [NodeHandle::shutdown] is called
+/// or the handle is dropped.
+pub struct NodeHandle {
+    rpc_context: RpcContext,
+    rpc_address: Option<SocketAddr>,
+    chain_events: ChainEvents,
+    rpc_handle: Option<JoinHandle<anyhow::Result<()>>>,
+    sync_handle: Option<JoinHandle<anyhow::Result<()>>>,
+    services: Vec<Service>,
+    tasks: BackgroundTasks,
+    stop_sync: tokio::sync::watch::Sender<bool>,
+    /// Its write-ahead log is flushed on shutdown, unless read-only.
+    wal_storage: Option<Storage>,
+    shutdown_timeout: Duration,
+    sync_shutdown_timeout: Duration,
+}
+
+impl NodeHandle {
+    /// The context the RPC methods are served with, which gives access to the
+    /// database and pending data.
+    pub fn rpc_context(&self) -> &RpcContext {
+        &self.rpc_context
+    }
+
+    /// The address the RPC server is listening on, if it was enabled.
+    pub fn rpc_address(&self) -> Option<SocketAddr> {
+        self.rpc_address
+    }
+
+    /// The events emitted by sync. None are emitted if sync is disabled.
+    pub fn chain_events(&self) -> &ChainEvents {
+        &self.chain_events
+    }
+
+    /// Resolves once the RPC server, sync or a service stop unexpectedly,
+    /// never if none are running.
+    pub async fn stopped(&mut self) -> anyhow::Error {
+        async fn join(handle: &mut Option<JoinHandle<anyhow::Result<()>>>) -> anyhow::Error {
+            let Some(task) = handle else {
+                return std::future::pending().await;
+            };
+            let error = match task.await {
+                Ok(Ok(())) => anyhow::anyhow!("Exited without an error"),
+                Ok(Err(error)) => error,
+                Err(error) => error.into(),
+            };
+            // A finished task must not be polled again.
+            *handle = None;
+            error
+        }
+
+        tokio::select! {
+            error = join(&mut self.rpc_handle) => error.context("RPC server ended unexpectedly"),
+            error = join(&mut self.sync_handle) => error.context("Sync ended unexpectedly"),
+            error = Service::any_stopped(&mut self.services) => error,
+        }
+    }
+
+    /// Stops the RPC server once in-flight requests have completed, and sync
+    /// once it has stored the current block. Sync is aborted if it doesn't
+    /// stop within the sync shutdown timeout. The write-ahead log is flushed
+    /// once everything else has stopped.
+    pub async fn shutdown(mut self) -> anyhow::Result<()> {
+        // Fails if sync isn't running, or doesn't support being stopped.
+        let stopping_sync = self.stop_sync.send(true).is_ok();
+
+        let drained = self
+            .rpc_context
+            .shutdown
+            .shutdown(self.shutdown_timeout)
+            .await
+            .context("Draining in-flight RPC requests");
+
+        if let Some(mut handle) = self.sync_handle.take() {
+            let stopped = if stopping_sync {
+                tokio::time::timeout(self.sync_shutdown_timeout, &mut handle).await
+            } else {
+                Ok((&mut handle).await)
+            };
+            match stopped {
+                Ok(Ok(Ok(()))) => tracing::debug!("Sync stopped"),
+                Ok(Ok(Err(error))) => {
+                    tracing::warn!(?error, "Sync ended with an error while stopping")
+                }
+                Ok(Err(error)) => tracing::warn!(%error, "Sync task failed while stopping"),
+                Err(_) => {
+                    // The block being stored is rolled back with its database transaction.
+                    tracing::warn!("Sync did not stop in time, aborting it");
+                    handle.abort();
+                    _ = handle.await;
+                }
+            }
+        }
+
+        // Stopping the servers drops their contexts, which closes the database
+        // connections they hold.
+        if let Some(handle) = self.rpc_handle.take() {
+            handle.abort();
+            _ = handle.await;
+        }
+        for service in &mut self.services {
+            service.stop().await;
+        }
+        self.tasks.stop().await;
+
+        let flushed = match self.wal_storage.take() {
+            Some(storage) => flush_wal(storage).await,
+            None => Ok(()),
+        };
+
+        drained.and(flushed)
+    }
+}
+
+impl Drop for NodeHandle {
+    fn drop(&mut self) {
+        if let Some(handle) = &self.rpc_handle {
+            handle.abort();
+        }
+        if let Some(handle) = &self.sync_handle {
+            handle.abort();
+        }
+    }
+}
+
+/// A task started alongside the node, see [OpenedNode::with_service]. It is
+/// aborted once dropped.
+struct Service {
+    name: &'static str,
+    task: tokio::task::AbortHandle,
+    /// Resolves to the reason the task ended.
+    exited: Option<JoinHandle<anyhow::Error>>,
+}
+
+impl Service {
+    fn new<T>(name: &'static str, handle: JoinHandle<T>) -> Self
+    where
+        T: std::fmt::Debug + Send + 'static,
+    {
+        let task = handle.abort_handle();
+        let exited = tokio::spawn(async move {
+            match handle.await {
+                Ok(result) => anyhow::anyhow!("Exited with: {result:?}"),
+                Err(error) => error.into(),
+            }
+        });
+
+        Self {
+            name,
+            task,
+            exited: Some(exited),
+        }
+    }
+
+    /// Resolves once any of `services` ends, never if none are running.
+    async fn any_stopped(services: &mut [Self]) -> anyhow::Error {
+        let running = services
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(index, service)| {
+                let exited = service.exited.as_mut()?;
+                Some(Box::pin(async move { (index, exited.await) }))
+            })
+            .collect::<Vec<_>>();
+        if running.is_empty() {
+            return std::future::pending().await;
+        }
+
+        let ((index, result), ..) = futures::future::select_all(running).await;
+        // A finished task must not be polled again.
+        services[index].exited = None;
+        result
+            .unwrap_or_else(Into::into)
+            .context(format!("{} ended unexpectedly", services[index].name))
+    }
+
+    async fn stop(&mut self) {
+        self.task.abort();
+        if let Some(exited) = self.exited.take() {
+            _ = exited.await;
+        }
+    }
+}
+
+impl Drop for Service {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Maintenance tasks running in the background, which are aborted once
+/// dropped.
+#[derive(Default)]
+struct BackgroundTasks(Vec<JoinHandle<()>>);
+
+impl BackgroundTasks {
+    fn spawn<F>(&mut self, future: F)
+    where
+        F: std::future::Future<Output = ()> + Send + 'static,
+    {
+        self.0.push(tokio::spawn(future));
+    }
+
+    async fn stop(&mut self) {
+        for handle in self.0.drain(..) {
+            handle.abort();
+            _ = handle.await;
+        }
+    }
+}
+
+impl Drop for BackgroundTasks {
+    fn drop(&mut self) {
+        for handle in &self.0 {
+            handle.abort();
+        }
+    }
+}
+
+/// Leaves a complete database file behind, in case the write-ahead log is lost
+/// with the container.
+async fn flush_wal(storage: Storage) -> anyhow::Result<()> {
+    tokio::task::spawn_blocking(move || {
+        let mut connection = storage
+            .connection()
+            .context("Creating database connection")?;
+        if !connection.flush_wal()? {
+            tracing::warn!("Database is busy, the write-ahead log was only partially flushed");
+        }
+        anyhow::Ok(())
+    })
+    .await
+    .context("Joining write-ahead log flush task")?
+    .context("Flushing write-ahead log")
+}
+
+/// Returns up to `budget` MiB of free database pages per minute to the file
+/// system, in steps spread over the minute.
+async fn compact_online(storage: Storage, budget: NonZeroU64) {
+    const STEPS_PER_MINUTE: u64 = 6;
+
+    let max_bytes = budget.get() * 1024 * 1024 / STEPS_PER_MINUTE;
+    let mut interval = tokio::time::interval(Duration::from_secs(60 / STEPS_PER_MINUTE));
+    loop {
+        interval.tick().await;
+
+        let storage = storage.clone();
+        let result = tokio::task::spawn_blocking(move || {
+            pathfinder_storage::compaction::compact_incrementally(&storage, max_bytes)
+        })
+        .await
+        .context("Compaction task panicked")
+        .and_then(|result| result);
+
+        match result {
+            Ok(0) => {}
+            Ok(freed) => tracing::debug!(%freed, "Compacted database"),
+            Err(error) => {
+                tracing::warn!(%error, "Online compaction failed, disabling it");
+                return;
+            }
+        }
+    }
+}
+
+/// Runs background migrations at up to `rate` blocks per second until they are
+/// done.
+pub async fn migrate_in_background(storage: Storage, rate: NonZeroU64) {
+    let mut interval = tokio::time::interval(Duration::from_secs(1));
+    loop {
+        interval.tick().await;
+
+        let storage = storage.clone();
+        let result = tokio::task::spawn_blocking(move || {
+            pathfinder_storage::background_migration::migrate_incrementally(&storage, rate.get())
+        })
+        .await
+        .context("Background migration task panicked")
+        .and_then(|result| result);
+
+        match result {
+            Ok(true) => {}
+            Ok(false) => return,
+            Err(error) => {
+                tracing::warn!(%error, "Background migration failed, resuming on the next start");
+                return;
+            }
+        }
+    }
+}
+
+/// Errors if there is a mismatch between the starknet and ethereum networks.
+pub fn verify_networks(starknet: Chain, ethereum: EthereumChain) -> anyhow::Result<()> {
+    if starknet != Chain::Custom {
+        let expected = match starknet {
+            Chain::Mainnet => EthereumChain::Mainnet,
+            Chain::SepoliaTestnet | Chain::SepoliaIntegration => EthereumChain::Sepolia,
+            Chain::Custom => unreachable!("Already checked against"),
+        };
+
+        anyhow::ensure!(
+            ethereum == expected,
+            "Incorrect Ethereum network detected. Found {ethereum:?} but expected {expected:?} \
+             for {} Starknet",
+            starknet
+        );
+    }
+
+    Ok(())
+}
+
+/// Checks that the database belongs to `network`. The genesis block of a
+/// custom network can only be verified against its gateway, and is skipped if
+/// there is none.
+pub async fn verify_database(
+    storage: &Storage,
+    network: Chain,
+    gateway_client: Option<&GatewayClient>,
+) -> anyhow::Result<()> {
+    let storage = storage.clone();
+    let db_genesis = tokio::task::spawn_blocking(move || {
+        let mut conn = storage.connection().context("Create database connection")?;
+        let tx = conn.transaction().context("Create database transaction")?;
+
+        tx.block_id(BlockNumber::GENESIS.into())
+    })
+    .await
+    .context("Joining database task")?
+    .context("Fetching genesis hash from database")?
+    .map(|x| x.1);
+
+    if let Some(database_genesis) = db_genesis {
+        use pathfinder_common::consts::{
+            MAINNET_GENESIS_HASH,
+            SEPOLIA_INTEGRATION_GENESIS_HASH,
+            SEPOLIA_TESTNET_GENESIS_HASH,
+        };
+
+        let db_network = match database_genesis {
+            MAINNET_GENESIS_HASH => Chain::Mainnet,
+            SEPOLIA_TESTNET_GENESIS_HASH => Chain::SepoliaTestnet,
+            SEPOLIA_INTEGRATION_GENESIS_HASH => Chain::SepoliaIntegration,
+            _ => Chain::Custom,
+        };
+
+        match (network, db_network) {
+            (Chain::Custom, _) => {
+                let Some(gateway_client) = gateway_client else {
+                    return Ok(());
+                };
+
+                // Verify against gateway.
+                let (_, gateway_hash) = gateway_client
+                    .block_header(BlockNumber::GENESIS.into())
+                    .await
+                    .context("Downloading genesis block from gateway for database verification")?;
+
+                anyhow::ensure!(
+                    database_genesis == gateway_hash,
+                    "Database genesis block does not match gateway. {} != {}",
+                    database_genesis,
+                    gateway_hash
+                );
+            }
+            (network, db_network) => anyhow::ensure!(
+                network == db_network,
+                "Database ({}) does not match the expected network ({})",
+                db_network,
+                network
+            ),
+        }
+    }
+
+    Ok(())
+}
+
+/// The path prefix a network is served under when running more than one.
+pub fn network_path_prefix(chain: Chain) -> &'static str {
+    match chain {
+        Chain::Mainnet => "mainnet",
+        Chain::SepoliaTestnet => "sepolia-testnet",
+        Chain::SepoliaIntegration => "sepolia-integration",
+        Chain::Custom => "custom",
+    }
+}
+
+/// Imports the snapshot of `checkpoint` into a new database at `database`,
+/// returning the checkpoint's block hash to verify once the database is open.
+///
+/// Returns `None` if the database already exists.
+async fn restore_checkpoint(
+    checkpoint: &SyncCheckpoint,
+    database: &Path,
+) -> anyhow::Result<Option<BlockHash>> {
+    if database.exists() {
+        tracing::warn!(
+            database=%database.display(),
+            "Database already exists, ignoring sync checkpoint"
+        );
+        return Ok(None);
+    }
+
+    // Keep the download alive until it has been imported.
+    let (snapshot, _download) = match &checkpoint.snapshot {
+        SnapshotSource::Path(path) => (path.clone(), None),
+        SnapshotSource::Url(url) => {
+            tracing::info!(%url, "Downloading checkpoint snapshot");
+            let directory = database.parent().unwrap_or(Path::new("."));
+            let download = download_snapshot(url, directory)
+                .await
+                .context("Downloading checkpoint snapshot")?;
+            (download.path().to_path_buf(), Some(download))
+        }
+    };
+
+    tracing::info!(block_hash=%checkpoint.block_hash, "Importing checkpoint snapshot");
+    let database = database.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        pathfinder_storage::snapshot::import_snapshot(&snapshot, &database)
+    })
+    .await
+    .context("Snapshot task panicked")?
+    .context("Importing checkpoint snapshot")?;
+
+    Ok(Some(checkpoint.block_hash))
+}
+
+/// Downloads the snapshot at `url` into a temporary file in `directory`.
+async fn download_snapshot(url: &Url, directory: &Path) -> anyhow::Result<tempfile::NamedTempFile> {
+    use tokio::io::AsyncWriteExt;
+
+    let mut response = reqwest::get(url.clone())
+        .await
+        .context("Requesting snapshot")?
+        .error_for_status()
+        .context("Requesting snapshot")?;
+
+    let directory = directory.to_path_buf();
+    let download = tokio::task::spawn_blocking(move || tempfile::NamedTempFile::new_in(directory))
+        .await
+        .context("Snapshot task panicked")?
+        .context("Creating snapshot download file")?;
+
+    let mut file = tokio::fs::File::from_std(
+        download
+            .as_file()
+            .try_clone()
+            .context("Opening snapshot download file")?,
+    );
+    while let Some(chunk) = response.chunk().await.context("Reading snapshot")? {
+        file.write_all(&chunk).await.context("Writing snapshot")?;
+    }
+    file.flush().await.context("Writing snapshot")?;
+
+    Ok(download)
+}
+
+/// Checks that the restored database contains the checkpoint, and that its
+/// hash matches the one recomputed from its header and commitments.
+async fn verify_checkpoint(
+    storage: &Storage,
+    chain: Chain,
+    chain_id: ChainId,
+    block_hash: BlockHash,
+) -> anyhow::Result<BlockNumber> {
+    let storage = storage.clone();
+    tokio::task::spawn_blocking(move || {
+        let block_number = pathfinder_storage::snapshot::verify_checkpoint(&storage, block_hash)?;
+
+        let report = state::verify_chain::verify_chain(
+            &storage,
+            chain,
+            chain_id,
+            block_number,
+            block_number,
+        )?;
+        if let Some((_, mismatch)) = report.mismatches.first() {
+            anyhow::bail!("Checkpoint {block_number} failed verification: {mismatch}");
+        }
+
+        Ok(block_number)
+    })
+    .await
+    .context("Checkpoint verification task panicked")?
+}
+
+/// Best-effort removal of the database file and its WAL files.
+fn remove_database(database: &Path) {
+    for suffix in ["", "-wal", "-shm"] {
+        let mut path = database.as_os_str().to_owned();
+        path.push(suffix);
+        let path = PathBuf::from(path);
+        if let Err(error) = std::fs::remove_file(&path) {
+            if error.kind() != std::io::ErrorKind::NotFound {
+                tracing::warn!(path=%path.display(), %error, "Failed to remove database file");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn serves_rpc_until_shutdown() {
+        let dir = tempfile::tempdir().unwrap();
+        let node = NodeBuilder::sepolia_testnet(dir.path())
+            .with_rpc_server(RpcServerConfig {
+                address: ([127, 0, 0, 1], 0).into(),
+                default_version: RpcVersion::V07,
+                max_connections: NonZeroUsize::new(8).unwrap(),
+                cors: None,
+                tls: None,
+                method_filter: Default::default(),
+                unrestricted_address: None,
+                ipc_path: None,
+            })
+            .start()
+            .await
+            .unwrap();
+
+        assert!(dir.path().join("testnet-sepolia.sqlite").exists());
+        let address = node.rpc_address().unwrap();
+        tokio::net::TcpStream::connect(address).await.unwrap();
+
+        node.shutdown().await.unwrap();
+
+        tokio::net::TcpStream::connect(address).await.unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn finishes_background_migrations() {
+        use pathfinder_common::consts::SEPOLIA_TESTNET_GENESIS_HASH;
+        use pathfinder_common::macro_prelude::*;
+        use pathfinder_common::BlockHeader;
+
+        let dir = tempfile::tempdir().unwrap();
+        let builder = NodeBuilder::sepolia_testnet(dir.path()).without_rpc_server();
+
+        // A database upgraded from before the background migrations were added.
+        let storage = builder
+            .config()
+            .storage
+            .builder(builder.config().database.clone())
+            .migrate()
+            .unwrap()
+            .create_pool(NonZeroU32::new(1).unwrap())
+            .unwrap();
+        let mut connection = storage.connection().unwrap();
+        let tx = connection.transaction().unwrap();
+        for i in 0..5u8 {
+            let hash = match i {
+                0 => SEPOLIA_TESTNET_GENESIS_HASH,
+                _ => block_hash_bytes!(&[i]),
+            };
+            let header = BlockHeader::builder()
+                .number(BlockNumber::new_or_panic(i.into()))
+                .finalize_with_hash(hash);
+            tx.insert_block_header(&header).unwrap();
+        }
+        tx.commit().unwrap();
+        drop(connection);
+        pathfinder_storage::test_utils::register_background_migrations(&storage);
+        drop(storage);
+
+        let node = builder.start().await.unwrap();
+
+        let pending = || {
+            let mut connection = node.rpc_context().storage.connection().unwrap();
+            let tx = connection.transaction().unwrap();
+            let status = tx.background_migration_status().unwrap();
+            assert!(!status.is_empty());
+            status.iter().filter(|status| !status.is_done()).count()
+        };
+        tokio::time::timeout(Duration::from_secs(30), async {
+            while pending() > 0 {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+        })
+        .await
+        .expect("Background migrations should finish");
+
+        node.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn rejects_pruning_below_reorg_depth() {
+        let dir = tempfile::tempdir().unwrap();
+        let builder = NodeBuilder::sepolia_testnet(dir.path());
+        let storage = StorageConfig {
+            history_prune_mode: HistoryPruneMode::Prune {
+                num_blocks_kept: 10,
+            },
+            ..builder.config().storage
+        };
+        let ethereum = EthereumClient::new("http://127.0.0.1:1").unwrap();
+
+        let error = builder
+            .with_storage(storage)
+            .with_sync(SyncConfig::new(ethereum))
+            .start()
+            .await
+            .unwrap_err();
+
+        assert!(error.to_string().contains("maximum reorg depth"));
+        assert!(!dir.path().join("testnet-sepolia.sqlite").exists());
+    }
+}
@@ -221,6 +221,9 @@ fn get_state_diff(
     block_number: BlockNumber,
     tx: &mpsc::Sender<StateDiffsResponse>,
 ) -> anyhow::Result<bool> {
+    if !db_tx.block_state_exists(block_number.into())? {
+        return Ok(false);
+    }
     let Some(state_diff) = db_tx.state_update(block_number.into())? else {
         return Ok(false);
     };
@@ -1,5 +1,8 @@
 pub mod block_hash;
+pub mod chain_events;
+pub mod state_diff;
 mod sync;
+pub mod verify_chain;
 
 pub use sync::{
     l1,
@@ -7,6 +10,7 @@ pub use sync::{
     revert,
     sync,
     update_starknet_state,
+    ExecutionVerification,
     Gossiper,
     StarknetStateUpdate,
     SyncContext,
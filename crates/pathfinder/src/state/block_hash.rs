@@ -1,11 +1,10 @@
 use std::io::Write;
-use std::sync::LazyLock;
 
-use anyhow::{Context, Result};
+use anyhow::Result;
 use pathfinder_common::event::Event;
-use pathfinder_common::hash::{FeltHash, PedersenHash, PoseidonHash};
-use pathfinder_common::receipt::{ExecutionStatus, Receipt};
-use pathfinder_common::transaction::{Transaction, TransactionVariant};
+use pathfinder_common::hash::{PedersenHash, PoseidonHash};
+use pathfinder_common::receipt::Receipt;
+use pathfinder_common::transaction::Transaction;
 use pathfinder_common::{
     felt_bytes,
     BlockHash,
@@ -24,16 +23,16 @@ use pathfinder_common::{
     StateDiffCommitment,
     TransactionCommitment,
     TransactionHash,
-    TransactionSignatureElem,
 };
-use pathfinder_crypto::hash::{pedersen_hash, poseidon_hash_many, HashChain, PoseidonHasher};
+use pathfinder_crypto::hash::{HashChain, PoseidonHasher};
 use pathfinder_crypto::{Felt, MontFelt};
-use pathfinder_merkle_tree::TransactionOrEventTree;
-use sha3::Digest;
+use pathfinder_merkle_tree::commitment::{
+    commitment_root,
+    receipt_hash,
+    transaction_hash_with_signature,
+};
 use starknet_gateway_types::reply::Block;
 
-const V_0_11_1: StarknetVersion = StarknetVersion::new(0, 11, 1, 0);
-
 #[derive(Debug, PartialEq, Eq)]
 pub enum VerifyResult {
     Match,
@@ -133,6 +132,8 @@ pub struct BlockHeaderData {
     pub strk_l1_gas_price: GasPrice,
     pub eth_l1_data_gas_price: GasPrice,
     pub strk_l1_data_gas_price: GasPrice,
+    pub eth_l2_gas_price: GasPrice,
+    pub strk_l2_gas_price: GasPrice,
     pub receipt_commitment: ReceiptCommitment,
     pub l1_da_mode: L1DataAvailabilityMode,
 }
@@ -160,6 +161,8 @@ impl BlockHeaderData {
             strk_l1_gas_price: header.strk_l1_gas_price,
             eth_l1_data_gas_price: header.eth_l1_data_gas_price,
             strk_l1_data_gas_price: header.strk_l1_data_gas_price,
+            eth_l2_gas_price: header.eth_l2_gas_price,
+            strk_l2_gas_price: header.strk_l2_gas_price,
             receipt_commitment: header.receipt_commitment,
             l1_da_mode: header.l1_da_mode,
             state_diff_commitment: header.state_diff_commitment,
@@ -202,12 +205,69 @@ impl BlockHeaderData {
             strk_l1_gas_price: block.l1_gas_price.price_in_fri,
             eth_l1_data_gas_price: block.l1_data_gas_price.price_in_wei,
             strk_l1_data_gas_price: block.l1_data_gas_price.price_in_fri,
+            eth_l2_gas_price: block
+                .l2_gas_price
+                .map(|p| p.price_in_wei)
+                .unwrap_or(GasPrice::ZERO),
+            strk_l2_gas_price: block
+                .l2_gas_price
+                .map(|p| p.price_in_fri)
+                .unwrap_or(GasPrice::ZERO),
             receipt_commitment: block.receipt_commitment.unwrap_or_default(),
             l1_da_mode: block.l1_da_mode.into(),
         })
     }
 }
 
+/// The block hash definitions used by Starknet over time.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BlockHashVersion {
+    /// Pedersen hash chain which also includes the chain ID. Selected by block
+    /// number instead of Starknet version, see [meta::BlockHashMetaInfo].
+    Pre0_7,
+    /// Pedersen hash chain over the header fields.
+    Pre0_13_2,
+    /// Poseidon hash prefixed with `STARKNET_BLOCK_HASH0`, committing to the
+    /// state diff, receipts and L1 gas prices.
+    V0,
+    /// Poseidon hash prefixed with `STARKNET_BLOCK_HASH1`, which replaces the
+    /// individual gas prices with a hash that also covers the L2 gas prices.
+    V1,
+}
+
+/// Block hash definitions keyed by the first Starknet version using them,
+/// ordered by version.
+const BLOCK_HASH_VERSIONS: &[(StarknetVersion, BlockHashVersion)] = &[
+    (
+        StarknetVersion::new(0, 0, 0, 0),
+        BlockHashVersion::Pre0_13_2,
+    ),
+    (StarknetVersion::V_0_13_2, BlockHashVersion::V0),
+    (StarknetVersion::V_0_13_4, BlockHashVersion::V1),
+];
+
+impl BlockHashVersion {
+    /// Returns the block hash definition for blocks of the given Starknet
+    /// version. Pre-0.7 blocks don't carry a version and have to be detected
+    /// by block number instead.
+    pub fn for_starknet_version(version: StarknetVersion) -> Self {
+        BLOCK_HASH_VERSIONS
+            .iter()
+            .rev()
+            .find(|(first, _)| version >= *first)
+            .map(|(_, hash_version)| *hash_version)
+            .unwrap_or(BlockHashVersion::Pre0_13_2)
+    }
+
+    fn for_header(header: &BlockHeaderData, meta_info: &meta::BlockHashMetaInfo) -> Self {
+        if meta_info.uses_pre_0_7_hash_algorithm(header.number) {
+            BlockHashVersion::Pre0_7
+        } else {
+            Self::for_starknet_version(header.starknet_version)
+        }
+    }
+}
+
 /// Verify the block hash value.
 ///
 /// The method to compute the block hash is documented
@@ -224,31 +284,35 @@ pub fn verify_block_hash(
 ) -> Result<VerifyResult> {
     let meta_info = meta::for_chain(chain);
 
-    let verified = if meta_info.uses_pre_0_7_hash_algorithm(header.number) {
-        anyhow::ensure!(
-            chain != Chain::Custom,
-            "Chain::Custom should not have any pre 0.7 block hashes"
-        );
+    let verified = match BlockHashVersion::for_header(&header, meta_info) {
+        BlockHashVersion::Pre0_7 => {
+            anyhow::ensure!(
+                chain != Chain::Custom,
+                "Chain::Custom should not have any pre 0.7 block hashes"
+            );
 
-        let computed_hash = compute_final_hash_pre_0_7(&header, chain_id);
-        computed_hash == header.hash
-    } else if header.starknet_version < StarknetVersion::V_0_13_2 {
-        let computed_hash = compute_final_hash_pre_0_13_2(&header);
-        if computed_hash == header.hash {
-            true
-        } else if let Some(fallback_sequencer_address) = meta_info.fallback_sequencer_address {
-            // Try with the fallback sequencer address.
-            let computed_hash = compute_final_hash_pre_0_13_2(&BlockHeaderData {
-                sequencer_address: fallback_sequencer_address,
-                ..header
-            });
+            let computed_hash = compute_final_hash_pre_0_7(&header, chain_id);
+            computed_hash == header.hash
+        }
+        BlockHashVersion::Pre0_13_2 => {
+            let computed_hash = compute_final_hash_pre_0_13_2(&header);
+            if computed_hash == header.hash {
+                true
+            } else if let Some(fallback_sequencer_address) = meta_info.fallback_sequencer_address {
+                // Try with the fallback sequencer address.
+                let computed_hash = compute_final_hash_pre_0_13_2(&BlockHeaderData {
+                    sequencer_address: fallback_sequencer_address,
+                    ..header
+                });
+                computed_hash == header.hash
+            } else {
+                false
+            }
+        }
+        BlockHashVersion::V0 | BlockHashVersion::V1 => {
+            let computed_hash = compute_final_hash(&header)?;
             computed_hash == header.hash
-        } else {
-            false
         }
-    } else {
-        let computed_hash = compute_final_hash(&header)?;
-        computed_hash == header.hash
     };
 
     Ok(match verified {
@@ -399,7 +463,13 @@ fn compute_final_hash_pre_0_13_2(header: &BlockHeaderData) -> BlockHash {
     BlockHash(chain.finalize())
 }
 
+/// Computes the Poseidon based block hash used since Starknet 0.13.2.
+///
+/// The exact definition depends on the [BlockHashVersion] of the header's
+/// Starknet version.
 pub fn compute_final_hash(header: &BlockHeaderData) -> Result<BlockHash> {
+    let block_hash_version = BlockHashVersion::for_starknet_version(header.starknet_version);
+
     // Concatenate the transaction count, event count, state diff length, and L1
     // data availability mode into a single felt.
     let mut concat_counts = [0u8; 32];
@@ -420,7 +490,10 @@ pub fn compute_final_hash(header: &BlockHeaderData) -> Result<BlockHash> {
     let concat_counts = MontFelt::from_be_bytes(concat_counts);
     // Hash the block header.
     let mut hasher = PoseidonHasher::new();
-    hasher.write(felt_bytes!(b"STARKNET_BLOCK_HASH0").into());
+    match block_hash_version {
+        BlockHashVersion::V1 => hasher.write(felt_bytes!(b"STARKNET_BLOCK_HASH1").into()),
+        _ => hasher.write(felt_bytes!(b"STARKNET_BLOCK_HASH0").into()),
+    }
     hasher.write(header.number.get().into());
     hasher.write(header.state_commitment.0.into());
     hasher.write(header.sequencer_address.0.into());
@@ -430,10 +503,15 @@ pub fn compute_final_hash(header: &BlockHeaderData) -> Result<BlockHash> {
     hasher.write(header.transaction_commitment.0.into());
     hasher.write(header.event_commitment.0.into());
     hasher.write(header.receipt_commitment.0.into());
-    hasher.write(header.eth_l1_gas_price.0.into());
-    hasher.write(header.strk_l1_gas_price.0.into());
-    hasher.write(header.eth_l1_data_gas_price.0.into());
-    hasher.write(header.strk_l1_data_gas_price.0.into());
+    match block_hash_version {
+        BlockHashVersion::V1 => hasher.write(gas_prices_hash(header)),
+        _ => {
+            hasher.write(header.eth_l1_gas_price.0.into());
+            hasher.write(header.strk_l1_gas_price.0.into());
+            hasher.write(header.eth_l1_data_gas_price.0.into());
+            hasher.write(header.strk_l1_data_gas_price.0.into());
+        }
+    }
     hasher.write(
         Felt::from_be_slice(header.starknet_version_str.as_bytes())
             .expect("Starknet version should fit into a felt")
@@ -444,6 +522,20 @@ pub fn compute_final_hash(header: &BlockHeaderData) -> Result<BlockHash> {
     Ok(BlockHash(hasher.finish().into()))
 }
 
+/// Hashes all gas prices of the header into a single value, as done by the
+/// [BlockHashVersion::V1] block hash.
+fn gas_prices_hash(header: &BlockHeaderData) -> MontFelt {
+    let mut hasher = PoseidonHasher::new();
+    hasher.write(felt_bytes!(b"STARKNET_GAS_PRICES0").into());
+    hasher.write(header.eth_l1_gas_price.0.into());
+    hasher.write(header.strk_l1_gas_price.0.into());
+    hasher.write(header.eth_l1_data_gas_price.0.into());
+    hasher.write(header.strk_l1_data_gas_price.0.into());
+    hasher.write(header.eth_l2_gas_price.0.into());
+    hasher.write(header.strk_l2_gas_price.0.into());
+    hasher.finish()
+}
+
 /// Calculate transaction commitment hash value.
 ///
 /// The transaction commitment is the root of the Patricia Merkle tree with
@@ -458,194 +550,22 @@ pub fn calculate_transaction_commitment(
 
     let final_hashes = transactions
         .par_iter()
-        .map(|tx| {
-            if version < V_0_11_1 {
-                calculate_transaction_hash_with_signature_pre_0_11_1(tx)
-            } else if version < StarknetVersion::V_0_13_2 {
-                calculate_transaction_hash_with_signature_pre_0_13_2(tx)
-            } else {
-                calculate_transaction_hash_with_signature(tx)
-            }
-        })
+        .map(|tx| transaction_hash_with_signature(tx, version))
         .collect();
 
     if version < StarknetVersion::V_0_13_2 {
-        calculate_commitment_root::<PedersenHash>(final_hashes).map(TransactionCommitment)
+        commitment_root::<PedersenHash>(final_hashes).map(TransactionCommitment)
     } else {
-        calculate_commitment_root::<PoseidonHash>(final_hashes).map(TransactionCommitment)
+        commitment_root::<PoseidonHash>(final_hashes).map(TransactionCommitment)
     }
 }
 
 pub fn calculate_receipt_commitment(receipts: &[Receipt]) -> Result<ReceiptCommitment> {
     use rayon::prelude::*;
 
-    let hashes = receipts
-        .par_iter()
-        .map(|receipt| {
-            poseidon_hash_many(&[
-                receipt.transaction_hash.0.into(),
-                receipt.actual_fee.0.into(),
-                // Calculate hash of messages sent.
-                {
-                    let mut hasher = PoseidonHasher::new();
-                    hasher.write((receipt.l2_to_l1_messages.len() as u64).into());
-                    for msg in &receipt.l2_to_l1_messages {
-                        hasher.write(msg.from_address.0.into());
-                        hasher.write(msg.to_address.0.into());
-                        hasher.write((msg.payload.len() as u64).into());
-                        for payload in &msg.payload {
-                            hasher.write(payload.0.into());
-                        }
-                    }
-                    hasher.finish()
-                },
-                // Revert reason.
-                match &receipt.execution_status {
-                    ExecutionStatus::Succeeded => MontFelt::ZERO,
-                    ExecutionStatus::Reverted { reason } => {
-                        let mut keccak = sha3::Keccak256::default();
-                        keccak.update(reason.as_bytes());
-                        let mut hashed_bytes: [u8; 32] = keccak.finalize().into();
-                        hashed_bytes[0] &= 0b00000011_u8; // Discard the six MSBs.
-                        MontFelt::from_be_bytes(hashed_bytes)
-                    }
-                },
-                // Execution resources:
-                // L2 gas
-                MontFelt::ZERO,
-                // L1 gas consumed
-                receipt.execution_resources.total_gas_consumed.l1_gas.into(),
-                // L1 data gas consumed
-                receipt
-                    .execution_resources
-                    .total_gas_consumed
-                    .l1_data_gas
-                    .into(),
-            ])
-            .into()
-        })
-        .collect();
-
-    calculate_commitment_root::<PoseidonHash>(hashes).map(ReceiptCommitment)
-}
-
-fn calculate_commitment_root<H: FeltHash>(hashes: Vec<Felt>) -> Result<Felt> {
-    let mut tree: TransactionOrEventTree<H> = Default::default();
-
-    hashes
-        .into_iter()
-        .enumerate()
-        .try_for_each(|(idx, final_hash)| {
-            let idx: u64 = idx
-                .try_into()
-                .expect("too many transactions while calculating commitment");
-            tree.set(idx, final_hash)
-        })
-        .context("Building transaction commitment tree")?;
-
-    tree.commit()
-}
-
-/// Compute the combined hash of the transaction hash and the signature.
-///
-/// Since the transaction hash doesn't take the signature values as its input
-/// computing the transaction commitent uses a hash value that combines
-/// the transaction hash with the array of signature values.
-///
-/// Note that for non-invoke transactions we don't actually have signatures. The
-/// cairo-lang uses an empty list (whose hash is not the ZERO value!) in that
-/// case.
-fn calculate_transaction_hash_with_signature_pre_0_11_1(tx: &Transaction) -> Felt {
-    static HASH_OF_EMPTY_LIST: LazyLock<Felt> = LazyLock::new(|| HashChain::default().finalize());
-
-    let signature_hash = match &tx.variant {
-        TransactionVariant::InvokeV0(tx) => calculate_signature_hash(&tx.signature),
-        TransactionVariant::InvokeV1(tx) => calculate_signature_hash(&tx.signature),
-        TransactionVariant::InvokeV3(tx) => calculate_signature_hash(&tx.signature),
-        TransactionVariant::DeclareV0(_)
-        | TransactionVariant::DeclareV1(_)
-        | TransactionVariant::DeclareV2(_)
-        | TransactionVariant::DeclareV3(_)
-        | TransactionVariant::DeployV0(_)
-        | TransactionVariant::DeployV1(_)
-        | TransactionVariant::DeployAccountV1(_)
-        | TransactionVariant::DeployAccountV3(_)
-        | TransactionVariant::L1Handler(_) => *HASH_OF_EMPTY_LIST,
-    };
-
-    pedersen_hash(tx.hash.0, signature_hash)
-}
-
-/// Compute the combined hash of the transaction hash and the signature for
-/// block before v0.13.2.
-///
-/// Since the transaction hash doesn't take the signature values as its input
-/// computing the transaction commitment uses a hash value that combines
-/// the transaction hash with the array of signature values.
-///
-/// Note that for non-invoke transactions we don't actually have signatures. The
-/// cairo-lang uses an empty list (whose hash is not the ZERO value!) in that
-/// case.
-fn calculate_transaction_hash_with_signature_pre_0_13_2(tx: &Transaction) -> Felt {
-    static HASH_OF_EMPTY_LIST: LazyLock<Felt> = LazyLock::new(|| HashChain::default().finalize());
-
-    let signature_hash = match &tx.variant {
-        TransactionVariant::InvokeV0(tx) => calculate_signature_hash(&tx.signature),
-        TransactionVariant::DeclareV0(tx) => calculate_signature_hash(&tx.signature),
-        TransactionVariant::DeclareV1(tx) => calculate_signature_hash(&tx.signature),
-        TransactionVariant::DeclareV2(tx) => calculate_signature_hash(&tx.signature),
-        TransactionVariant::DeclareV3(tx) => calculate_signature_hash(&tx.signature),
-        TransactionVariant::DeployAccountV1(tx) => calculate_signature_hash(&tx.signature),
-        TransactionVariant::DeployAccountV3(tx) => calculate_signature_hash(&tx.signature),
-        TransactionVariant::InvokeV1(tx) => calculate_signature_hash(&tx.signature),
-        TransactionVariant::InvokeV3(tx) => calculate_signature_hash(&tx.signature),
-        TransactionVariant::DeployV0(_)
-        | TransactionVariant::DeployV1(_)
-        | TransactionVariant::L1Handler(_) => *HASH_OF_EMPTY_LIST,
-    };
-
-    pedersen_hash(tx.hash.0, signature_hash)
-}
-
-/// Compute the combined hash of the transaction hash and the signature.
-///
-/// [Reference code from StarkWare](https://github.com/starkware-libs/starknet-api/blob/5565e5282f5fead364a41e49c173940fd83dee00/src/block_hash/block_hash_calculator.rs#L95-L98).
-fn calculate_transaction_hash_with_signature(tx: &Transaction) -> Felt {
-    let signature = match &tx.variant {
-        TransactionVariant::InvokeV0(tx) => tx.signature.as_slice(),
-        TransactionVariant::DeclareV0(tx) => tx.signature.as_slice(),
-        TransactionVariant::DeclareV1(tx) => tx.signature.as_slice(),
-        TransactionVariant::DeclareV2(tx) => tx.signature.as_slice(),
-        TransactionVariant::DeclareV3(tx) => tx.signature.as_slice(),
-        TransactionVariant::DeployAccountV1(tx) => tx.signature.as_slice(),
-        TransactionVariant::DeployAccountV3(tx) => tx.signature.as_slice(),
-        TransactionVariant::InvokeV1(tx) => tx.signature.as_slice(),
-        TransactionVariant::InvokeV3(tx) => tx.signature.as_slice(),
-        TransactionVariant::DeployV0(_)
-        | TransactionVariant::DeployV1(_)
-        | TransactionVariant::L1Handler(_) => &[],
-    };
-
-    let signature = if signature.is_empty() {
-        &[TransactionSignatureElem::ZERO]
-    } else {
-        signature
-    };
-
-    let mut hasher = PoseidonHasher::new();
-    hasher.write(tx.hash.0.into());
-    for elem in signature {
-        hasher.write(elem.0.into());
-    }
-    hasher.finish().into()
-}
+    let hashes = receipts.par_iter().map(receipt_hash).collect();
 
-fn calculate_signature_hash(signature: &[TransactionSignatureElem]) -> Felt {
-    let mut hash = HashChain::default();
-    for s in signature {
-        hash.update(s.0);
-    }
-    hash.finalize()
+    commitment_root::<PoseidonHash>(hashes).map(ReceiptCommitment)
 }
 
 /// Calculate event commitment hash value.
@@ -672,9 +592,9 @@ pub fn calculate_event_commitment(
         .collect();
 
     if version < StarknetVersion::V_0_13_2 {
-        calculate_commitment_root::<PedersenHash>(event_hashes).map(EventCommitment)
+        commitment_root::<PedersenHash>(event_hashes).map(EventCommitment)
     } else {
-        calculate_commitment_root::<PoseidonHash>(event_hashes).map(EventCommitment)
+        commitment_root::<PoseidonHash>(event_hashes).map(EventCommitment)
     }
 }
 
@@ -724,11 +644,12 @@ fn calculate_event_hash(event: &Event, transaction_hash: TransactionHash) -> Fel
 mod tests {
     use assert_matches::assert_matches;
     use pathfinder_common::macro_prelude::*;
-    use pathfinder_common::receipt::{ExecutionResources, L1Gas, L2ToL1Message};
+    use pathfinder_common::receipt::{ExecutionResources, ExecutionStatus, L1Gas, L2ToL1Message};
     use pathfinder_common::transaction::{
         EntryPointType,
         InvokeTransactionV0,
         InvokeTransactionV3,
+        TransactionVariant,
     };
     use pathfinder_common::{
         felt,
@@ -738,6 +659,7 @@ mod tests {
         Fee,
         L2ToL1MessagePayloadElem,
         TransactionHash,
+        TransactionSignatureElem,
     };
     use pathfinder_crypto::Felt;
     use starknet_gateway_test_fixtures::v0_13_2;
@@ -795,7 +717,7 @@ mod tests {
             Felt::from_hex_str("0x259c3bd5a1951eafb2f41e0b783eab92cfe4e108b2b1f071e3736f06b909431")
                 .unwrap();
         let calculated_final_hash =
-            calculate_transaction_hash_with_signature_pre_0_13_2(&transaction);
+            transaction_hash_with_signature(&transaction, StarknetVersion::new(0, 13, 1, 0));
         assert_eq!(expected_final_hash, calculated_final_hash);
     }
 
@@ -914,7 +836,7 @@ mod tests {
         };
         let expected = felt!("0x2f0d8840bcf3bc629598d8a6cc80cb7c0d9e52d93dab244bbf9cd0dca0ad082");
         assert_eq!(
-            calculate_transaction_hash_with_signature(&transaction),
+            transaction_hash_with_signature(&transaction, StarknetVersion::V_0_13_2),
             expected
         );
 
@@ -924,7 +846,7 @@ mod tests {
         };
         let expected = felt!("0x00a93bf5e58b9378d093aa86ddc2f61a3295a1d1e665bd0ef3384dd07b30e033");
         assert_eq!(
-            calculate_transaction_hash_with_signature(&transaction),
+            transaction_hash_with_signature(&transaction, StarknetVersion::V_0_13_2),
             expected
         );
     }
@@ -1053,6 +975,8 @@ mod tests {
             eth_l1_gas_price: GasPrice(7),
             strk_l1_data_gas_price: GasPrice(10),
             eth_l1_data_gas_price: GasPrice(9),
+            strk_l2_gas_price: GasPrice::ZERO,
+            eth_l2_gas_price: GasPrice::ZERO,
             starknet_version: StarknetVersion::V_0_13_2,
             starknet_version_str: "10".to_string(),
             parent_hash: BlockHash(11u64.into()),
@@ -1076,6 +1000,71 @@ mod tests {
         assert_eq!(compute_final_hash(&header).unwrap(), expected_hash);
     }
 
+    #[test]
+    fn block_hash_version_selection() {
+        assert_eq!(
+            BlockHashVersion::for_starknet_version(StarknetVersion::default()),
+            BlockHashVersion::Pre0_13_2
+        );
+        assert_eq!(
+            BlockHashVersion::for_starknet_version(StarknetVersion::new(0, 13, 1, 1)),
+            BlockHashVersion::Pre0_13_2
+        );
+        assert_eq!(
+            BlockHashVersion::for_starknet_version(StarknetVersion::V_0_13_2),
+            BlockHashVersion::V0
+        );
+        assert_eq!(
+            BlockHashVersion::for_starknet_version(StarknetVersion::new(0, 13, 3, 0)),
+            BlockHashVersion::V0
+        );
+        assert_eq!(
+            BlockHashVersion::for_starknet_version(StarknetVersion::V_0_13_4),
+            BlockHashVersion::V1
+        );
+        assert_eq!(
+            BlockHashVersion::for_starknet_version(StarknetVersion::new(0, 14, 0, 0)),
+            BlockHashVersion::V1
+        );
+    }
+
+    #[test]
+    fn block_hash_v1_commits_to_l2_gas_prices() {
+        let header = BlockHeaderData {
+            number: BlockNumber::new_or_panic(1),
+            starknet_version: StarknetVersion::V_0_13_4,
+            starknet_version_str: "0.13.4".to_string(),
+            eth_l1_gas_price: GasPrice(7),
+            strk_l1_gas_price: GasPrice(6),
+            ..Default::default()
+        };
+        let hash = compute_final_hash(&header).unwrap();
+
+        // The same header hashed with the previous definition must differ.
+        let v0_hash = compute_final_hash(&BlockHeaderData {
+            starknet_version: StarknetVersion::V_0_13_2,
+            ..header.clone()
+        })
+        .unwrap();
+        assert_ne!(hash, v0_hash);
+
+        let with_l2_gas_price = compute_final_hash(&BlockHeaderData {
+            strk_l2_gas_price: GasPrice(1),
+            ..header.clone()
+        })
+        .unwrap();
+        assert_ne!(hash, with_l2_gas_price);
+
+        // Verification accepts the V1 hash.
+        let result = verify_block_hash(
+            BlockHeaderData { hash, ..header },
+            Chain::SepoliaIntegration,
+            ChainId::SEPOLIA_INTEGRATION,
+        )
+        .unwrap();
+        assert_eq!(result, VerifyResult::Match);
+    }
+
     // Source
     // https://integration-sepolia.starknet.io/feeder_gateway/get_block?blockNumber=35748
     #[test]
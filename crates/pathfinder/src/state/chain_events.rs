@@ -0,0 +1,187 @@
+//! Chain events emitted by sync, for programs embedding pathfinder.
+//!
+//! This is a view of the [Notifications] sync sends to the RPC subscriptions.
+//! Events are only emitted once the corresponding changes have been committed
+//! to the database, so they can be followed up by database queries.
+
+use std::sync::Arc;
+
+use anyhow::Context;
+use pathfinder_common::{BlockHash, BlockHeader, BlockNumber};
+use pathfinder_rpc::{Notifications, Reorg};
+use pathfinder_storage::Storage;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{broadcast, watch};
+
+#[derive(Debug, Clone)]
+pub enum ChainEvent {
+    /// A new block was added to the chain.
+    NewHead(Arc<BlockHeader>),
+    /// The blocks from `first_block_number` up to and including
+    /// `last_block_number` were rolled back.
+    Reorg(Arc<Reorg>),
+    /// The blocks up to and including this one have been accepted on L1.
+    L1Accepted(BlockNumber),
+}
+
+/// Receives the [ChainEvent]s sent through a set of [Notifications].
+///
+/// Cloning is cheap and all clones share the same head.
+#[derive(Clone)]
+pub struct ChainEvents {
+    notifications: Notifications,
+    head: watch::Receiver<Option<(BlockNumber, BlockHash)>>,
+}
+
+impl ChainEvents {
+    /// Follows the events sent through `notifications`, starting with the
+    /// latest block in `storage` as the head.
+    ///
+    /// Must be called from within a tokio runtime, since the head is kept up
+    /// to date by a background task.
+    pub fn new(notifications: Notifications, storage: Storage) -> anyhow::Result<Self> {
+        let (head_tx, head) = watch::channel(latest_block(&storage)?);
+
+        let mut headers = notifications.block_headers.subscribe();
+        let mut reorgs = notifications.reorgs.subscribe();
+        tokio::spawn(async move {
+            loop {
+                // The database is the source of truth, notifications only tell us when to
+                // read it again. This way a lagging receiver cannot leave a stale head.
+                let closed = tokio::select! {
+                    header = headers.recv() => matches!(header, Err(RecvError::Closed)),
+                    reorg = reorgs.recv() => matches!(reorg, Err(RecvError::Closed)),
+                    _ = head_tx.closed() => true,
+                };
+                if closed {
+                    break;
+                }
+
+                let storage = storage.clone();
+                match tokio::task::spawn_blocking(move || latest_block(&storage)).await {
+                    Ok(Ok(latest)) => {
+                        head_tx.send_replace(latest);
+                    }
+                    Ok(Err(error)) => tracing::warn!(?error, "Failed to update chain head"),
+                    Err(error) => tracing::warn!(?error, "Failed to update chain head"),
+                }
+            }
+        });
+
+        Ok(Self {
+            notifications,
+            head,
+        })
+    }
+
+    /// Receives all events emitted after subscribing.
+    ///
+    /// Events of the same kind are received in order, but events of different
+    /// kinds which are pending at the same time may be received in any order.
+    /// The block hashes of a [Reorg] identify the headers it rolled back.
+    ///
+    /// Subscribers which fall more than 1024 events of a kind behind miss the
+    /// oldest ones, and are notified of this by
+    /// [RecvError::Lagged](broadcast::error::RecvError::Lagged).
+    pub fn subscribe(&self) -> ChainEventReceiver {
+        ChainEventReceiver {
+            block_headers: self.notifications.block_headers.subscribe(),
+            reorgs: self.notifications.reorgs.subscribe(),
+            l1_updates: self.notifications.l1_updates.subscribe(),
+        }
+    }
+
+    /// Watches the number and hash of the latest block, `None` while the
+    /// database has no blocks.
+    pub fn head(&self) -> watch::Receiver<Option<(BlockNumber, BlockHash)>> {
+        self.head.clone()
+    }
+}
+
+/// Returned by [ChainEvents::subscribe].
+pub struct ChainEventReceiver {
+    block_headers: broadcast::Receiver<Arc<BlockHeader>>,
+    reorgs: broadcast::Receiver<Arc<Reorg>>,
+    l1_updates: broadcast::Receiver<BlockNumber>,
+}
+
+impl ChainEventReceiver {
+    /// Receives the next event. Cancel safe.
+    pub async fn recv(&mut self) -> Result<ChainEvent, RecvError> {
+        tokio::select! {
+            header = self.block_headers.recv() => header.map(ChainEvent::NewHead),
+            reorg = self.reorgs.recv() => reorg.map(ChainEvent::Reorg),
+            block_number = self.l1_updates.recv() => block_number.map(ChainEvent::L1Accepted),
+        }
+    }
+}
+
+fn latest_block(storage: &Storage) -> anyhow::Result<Option<(BlockNumber, BlockHash)>> {
+    let mut db = storage
+        .connection()
+        .context("Creating database connection")?;
+    let db = db.transaction().context("Creating database transaction")?;
+    db.block_id(pathfinder_storage::BlockId::Latest)
+        .context("Querying latest block")
+}
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::macro_prelude::*;
+    use pathfinder_storage::StorageBuilder;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn head_is_initialized_from_the_database() {
+        let storage = StorageBuilder::in_memory().unwrap();
+        let events = ChainEvents::new(Notifications::default(), storage.clone()).unwrap();
+        assert_eq!(*events.head().borrow(), None);
+
+        let header = BlockHeader::builder()
+            .number(BlockNumber::GENESIS)
+            .finalize_with_hash(block_hash!("0x1"));
+        let mut db = storage.connection().unwrap();
+        let tx = db.transaction().unwrap();
+        tx.insert_block_header(&header).unwrap();
+        tx.commit().unwrap();
+
+        let events = ChainEvents::new(Notifications::default(), storage).unwrap();
+        assert_eq!(
+            *events.head().borrow(),
+            Some((BlockNumber::GENESIS, block_hash!("0x1")))
+        );
+    }
+
+    #[tokio::test]
+    async fn events_of_a_kind_are_received_in_order() {
+        let notifications = Notifications::default();
+        let events =
+            ChainEvents::new(notifications.clone(), StorageBuilder::in_memory().unwrap()).unwrap();
+        let mut rx = events.subscribe();
+
+        for number in [0, 1] {
+            notifications
+                .block_headers
+                .send(Arc::new(BlockHeader {
+                    number: BlockNumber::new_or_panic(number),
+                    ..Default::default()
+                }))
+                .unwrap();
+        }
+        notifications.l1_updates.send(BlockNumber::GENESIS).unwrap();
+
+        let mut headers = Vec::new();
+        for _ in 0..3 {
+            match rx.recv().await.unwrap() {
+                ChainEvent::NewHead(header) => headers.push(header.number),
+                ChainEvent::L1Accepted(number) => assert_eq!(number, BlockNumber::GENESIS),
+                ChainEvent::Reorg(_) => panic!("Unexpected reorg"),
+            }
+        }
+        assert_eq!(
+            headers,
+            vec![BlockNumber::GENESIS, BlockNumber::new_or_panic(1)]
+        );
+    }
+}
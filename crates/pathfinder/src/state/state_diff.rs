@@ -0,0 +1,887 @@
+//! Localizes state divergence by listing the leaves which differ between the
+//! global tries of two states: two blocks of the same database, or a block and
+//! the same block on another node.
+//!
+//! The tries are walked from their roots, skipping the subtrees whose hashes
+//! are equal, so the work done is proportional to the number of differing
+//! leaves rather than to the size of the state. The storage tries are then
+//! compared for every contract whose state hash differs.
+
+use std::collections::HashMap;
+
+use anyhow::Context;
+use bitvec::prelude::*;
+use pathfinder_common::hash::{PedersenHash, PoseidonHash};
+use pathfinder_common::trie::TrieNode;
+use pathfinder_common::{
+    BlockNumber,
+    ClassHash,
+    ContractAddress,
+    ContractNonce,
+    ContractRoot,
+    StorageAddress,
+};
+use pathfinder_crypto::Felt;
+use pathfinder_storage::{StoredNode, Transaction};
+
+/// The height of the Starknet tries, at which their leaves are.
+const HEIGHT: usize = 251;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Trie {
+    /// The global trie of contract state hashes.
+    Storage,
+    /// The global trie of class commitment leaves, keyed by Sierra class hash.
+    Class,
+    /// The storage trie of a contract.
+    Contract(ContractAddress),
+}
+
+impl Trie {
+    fn hash(self, node: &TrieNode) -> Felt {
+        match self {
+            Trie::Class => node.hash::<PoseidonHash>(),
+            Trie::Storage | Trie::Contract(_) => node.hash::<PedersenHash>(),
+        }
+    }
+}
+
+/// One of the two states being compared.
+pub trait State {
+    /// The hash of the root of `trie`, `None` if the trie is empty.
+    fn root(&mut self, trie: Trie) -> anyhow::Result<Option<Felt>>;
+
+    /// The node of `trie` with `hash`, found at `path`.
+    ///
+    /// The children of the nodes just above the leaves are the values of the
+    /// leaves.
+    fn node(
+        &mut self,
+        trie: Trie,
+        hash: Felt,
+        path: &BitSlice<u8, Msb0>,
+    ) -> anyhow::Result<TrieNode>;
+
+    /// The class hash and nonce of a contract which exists in this state.
+    fn contract(&mut self, address: ContractAddress) -> anyhow::Result<(ClassHash, ContractNonce)>;
+}
+
+/// A leaf which is only in one of the tries, or whose value differs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LeafDiff {
+    pub key: Felt,
+    pub a: Option<Felt>,
+    pub b: Option<Felt>,
+}
+
+/// The parts of a contract's state committed to by its state hash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContractState {
+    pub class_hash: ClassHash,
+    pub nonce: ContractNonce,
+    pub root: ContractRoot,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContractDiff {
+    pub address: ContractAddress,
+    /// `None` if the contract doesn't exist in that state.
+    pub a: Option<ContractState>,
+    pub b: Option<ContractState>,
+    /// The storage slots whose values differ.
+    pub storage: Vec<LeafDiff>,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Report {
+    /// The contracts whose state hash differs, in address order.
+    pub contracts: Vec<ContractDiff>,
+    /// The classes whose class commitment leaf differs, in class hash order.
+    pub classes: Vec<LeafDiff>,
+    /// Set if more than the limit of differences were found in one of the
+    /// tries, in which case only the first ones are reported.
+    pub truncated: bool,
+}
+
+impl Report {
+    pub fn is_empty(&self) -> bool {
+        self.contracts.is_empty() && self.classes.is_empty()
+    }
+}
+
+/// Compares the global tries of `a` and `b`, reporting at most `limit`
+/// differing leaves per trie.
+pub fn state_diff(a: &mut impl State, b: &mut impl State, limit: usize) -> anyhow::Result<Report> {
+    let mut report = Report::default();
+
+    let (contracts, truncated) = diff_trie(a, b, Trie::Storage, limit).context("Storage trie")?;
+    report.truncated |= truncated;
+
+    for leaf in contracts {
+        let address = ContractAddress(leaf.key);
+        let trie = Trie::Contract(address);
+
+        let (storage, truncated) =
+            diff_trie(a, b, trie, limit).with_context(|| format!("Storage of {address}"))?;
+        report.truncated |= truncated;
+
+        let a_state = leaf.a.map(|_| contract_state(a, address)).transpose()?;
+        let b_state = leaf.b.map(|_| contract_state(b, address)).transpose()?;
+
+        report.contracts.push(ContractDiff {
+            address,
+            a: a_state,
+            b: b_state,
+            storage,
+        });
+    }
+
+    let (classes, truncated) = diff_trie(a, b, Trie::Class, limit).context("Class trie")?;
+    report.truncated |= truncated;
+    report.classes = classes;
+
+    Ok(report)
+}
+
+fn contract_state(
+    state: &mut impl State,
+    address: ContractAddress,
+) -> anyhow::Result<ContractState> {
+    let (class_hash, nonce) = state
+        .contract(address)
+        .with_context(|| format!("Querying contract {address}"))?;
+    let root = state.root(Trie::Contract(address))?.unwrap_or_default();
+
+    Ok(ContractState {
+        class_hash,
+        nonce,
+        root: ContractRoot(root),
+    })
+}
+
+/// A subtree of one of the tries being compared.
+#[derive(Debug, Clone, PartialEq)]
+enum Subtree {
+    Node(Felt),
+    /// The part of an edge below the current path.
+    Edge {
+        child: Felt,
+        path: BitVec<u8, Msb0>,
+    },
+}
+
+impl Subtree {
+    /// The subtrees below `path`, which this subtree is at, by direction.
+    fn children(
+        self,
+        state: &mut impl State,
+        trie: Trie,
+        path: &BitSlice<u8, Msb0>,
+    ) -> anyhow::Result<[Option<Subtree>; 2]> {
+        let (child, edge) = match self {
+            Subtree::Node(hash) => match state.node(trie, hash, path)? {
+                TrieNode::Binary { left, right } => {
+                    return Ok([Some(Subtree::Node(left)), Some(Subtree::Node(right))])
+                }
+                TrieNode::Edge { child, path } => (child, path),
+            },
+            Subtree::Edge { child, path } => (child, path),
+        };
+
+        let (direction, rest) = edge.split_first().context("Empty edge path")?;
+        let next = if rest.is_empty() {
+            Subtree::Node(child)
+        } else {
+            Subtree::Edge {
+                child,
+                path: rest.to_bitvec(),
+            }
+        };
+
+        let mut children = [None, None];
+        children[usize::from(*direction)] = Some(next);
+        Ok(children)
+    }
+
+    fn leaf_value(self) -> anyhow::Result<Felt> {
+        match self {
+            Subtree::Node(value) => Ok(value),
+            Subtree::Edge { .. } => anyhow::bail!("Edge extends below the leaves"),
+        }
+    }
+}
+
+/// Lists the leaves of `trie` which differ between `a` and `b`, in key order.
+/// The second value is set if there are more than `limit` of them.
+fn diff_trie(
+    a: &mut impl State,
+    b: &mut impl State,
+    trie: Trie,
+    limit: usize,
+) -> anyhow::Result<(Vec<LeafDiff>, bool)> {
+    let a_root = a.root(trie).context("Querying root of a")?;
+    let b_root = b.root(trie).context("Querying root of b")?;
+
+    let mut differences = Vec::new();
+    let mut stack = vec![(
+        BitVec::<u8, Msb0>::new(),
+        a_root.map(Subtree::Node),
+        b_root.map(Subtree::Node),
+    )];
+
+    while let Some((path, a_subtree, b_subtree)) = stack.pop() {
+        if a_subtree == b_subtree {
+            continue;
+        }
+
+        if path.len() == HEIGHT {
+            if differences.len() == limit {
+                return Ok((differences, true));
+            }
+            differences.push(LeafDiff {
+                key: Felt::from_bits(&path).context("Mapping leaf path to key")?,
+                a: a_subtree.map(Subtree::leaf_value).transpose()?,
+                b: b_subtree.map(Subtree::leaf_value).transpose()?,
+            });
+            continue;
+        }
+
+        let a_children = match a_subtree {
+            Some(subtree) => subtree.children(a, trie, &path)?,
+            None => [None, None],
+        };
+        let b_children = match b_subtree {
+            Some(subtree) => subtree.children(b, trie, &path)?,
+            None => [None, None],
+        };
+
+        // Right is pushed first so that the leaves are visited in key order.
+        for (direction, (a_child, b_child)) in
+            a_children.into_iter().zip(b_children).enumerate().rev()
+        {
+            let mut path = path.clone();
+            path.push(direction == 1);
+            stack.push((path, a_child, b_child));
+        }
+    }
+
+    Ok((differences, false))
+}
+
+/// The state at a block of the database.
+pub struct LocalState<'tx> {
+    tx: &'tx Transaction<'tx>,
+    block: BlockNumber,
+    /// The storage indices of the nodes reached so far, since the nodes
+    /// reference their children by index rather than by hash.
+    indices: HashMap<(Trie, Felt), u64>,
+}
+
+impl<'tx> LocalState<'tx> {
+    pub fn new(tx: &'tx Transaction<'tx>, block: BlockNumber) -> Self {
+        Self {
+            tx,
+            block,
+            indices: Default::default(),
+        }
+    }
+
+    /// The hash of the node at `index`, which is remembered for looking the
+    /// node up later.
+    fn hash(&mut self, trie: Trie, index: u64) -> anyhow::Result<Felt> {
+        let hash = match trie {
+            Trie::Storage => self.tx.storage_trie_node_hash(index),
+            Trie::Class => self.tx.class_trie_node_hash(index),
+            Trie::Contract(_) => self.tx.contract_trie_node_hash(index),
+        }
+        .context("Querying trie node hash")?
+        .with_context(|| format!("Trie node {index} is missing, the trie may have been pruned"))?;
+
+        self.indices.insert((trie, hash), index);
+        Ok(hash)
+    }
+
+    fn leaf(&self, trie: Trie, path: &BitSlice<u8, Msb0>) -> anyhow::Result<Felt> {
+        let key = Felt::from_bits(path).context("Mapping leaf path to key")?;
+        let block = self.block.into();
+
+        let value = match trie {
+            Trie::Storage => self
+                .tx
+                .contract_state_hash(self.block, ContractAddress(key))
+                .context("Querying contract state hash")?
+                .map(|x| x.0),
+            Trie::Class => match self
+                .tx
+                .casm_hash_at(block, ClassHash(key))
+                .context("Querying CASM hash")?
+            {
+                Some(casm) => self
+                    .tx
+                    .class_commitment_leaf(self.block, &casm)
+                    .context("Querying class commitment leaf")?
+                    .map(|x| x.0),
+                None => None,
+            },
+            Trie::Contract(address) => self
+                .tx
+                .storage_value(block, address, StorageAddress(key))
+                .context("Querying storage value")?
+                .map(|x| x.0),
+        };
+
+        value.with_context(|| format!("Leaf {key} is missing"))
+    }
+}
+
+impl State for LocalState<'_> {
+    fn root(&mut self, trie: Trie) -> anyhow::Result<Option<Felt>> {
+        let index = match trie {
+            Trie::Storage => self.tx.storage_root_index(self.block),
+            Trie::Class => self.tx.class_root_index(self.block),
+            Trie::Contract(address) => self.tx.contract_root_index(self.block, address),
+        }
+        .context("Querying root index")?;
+
+        index.map(|index| self.hash(trie, index)).transpose()
+    }
+
+    fn node(
+        &mut self,
+        trie: Trie,
+        hash: Felt,
+        path: &BitSlice<u8, Msb0>,
+    ) -> anyhow::Result<TrieNode> {
+        let index = *self
+            .indices
+            .get(&(trie, hash))
+            .with_context(|| format!("Node {hash} was not reached from the root"))?;
+
+        let node = match trie {
+            Trie::Storage => self.tx.storage_trie_node(index),
+            Trie::Class => self.tx.class_trie_node(index),
+            Trie::Contract(_) => self.tx.contract_trie_node(index),
+        }
+        .context("Querying trie node")?
+        .with_context(|| format!("Trie node {index} is missing, the trie may have been pruned"))?;
+
+        let node = match node {
+            StoredNode::Binary { left, right } => TrieNode::Binary {
+                left: self.hash(trie, left)?,
+                right: self.hash(trie, right)?,
+            },
+            StoredNode::Edge { child, path } => TrieNode::Edge {
+                child: self.hash(trie, child)?,
+                path,
+            },
+            StoredNode::LeafBinary => {
+                let mut left = path.to_bitvec();
+                left.push(false);
+                let mut right = path.to_bitvec();
+                right.push(true);
+
+                TrieNode::Binary {
+                    left: self.leaf(trie, &left)?,
+                    right: self.leaf(trie, &right)?,
+                }
+            }
+            StoredNode::LeafEdge { path: edge } => {
+                let mut leaf = path.to_bitvec();
+                leaf.extend_from_bitslice(&edge);
+
+                TrieNode::Edge {
+                    child: self.leaf(trie, &leaf)?,
+                    path: edge,
+                }
+            }
+        };
+
+        Ok(node)
+    }
+
+    fn contract(&mut self, address: ContractAddress) -> anyhow::Result<(ClassHash, ContractNonce)> {
+        let block = self.block.into();
+        // System contracts have neither.
+        let class_hash = self
+            .tx
+            .contract_class_hash(block, address)
+            .context("Querying class hash")?
+            .unwrap_or_default();
+        let nonce = self
+            .tx
+            .contract_nonce(address, block)
+            .context("Querying nonce")?
+            .unwrap_or_default();
+
+        Ok((class_hash, nonce))
+    }
+}
+
+/// The state at a block of another node, whose trie nodes are fetched from
+/// its `pathfinder_getProof` and `pathfinder_getClassProof` methods.
+///
+/// The requests are made by blocking on `runtime`, so this must not be used
+/// from within an async context.
+pub struct RemoteState {
+    client: reqwest::Client,
+    url: reqwest::Url,
+    block: BlockNumber,
+    runtime: tokio::runtime::Handle,
+    /// The nodes of all proofs received so far, by hash.
+    nodes: HashMap<Felt, TrieNode>,
+    /// The contracts queried so far, `None` if they don't exist.
+    contracts: HashMap<ContractAddress, Option<remote::ContractData>>,
+}
+
+impl RemoteState {
+    /// `url` is the pathfinder JSON-RPC API of the node, e.g.
+    /// `http://localhost:9545/rpc/pathfinder/v0.1`.
+    pub fn new(url: reqwest::Url, block: BlockNumber, runtime: tokio::runtime::Handle) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url,
+            block,
+            runtime,
+            nodes: Default::default(),
+            contracts: Default::default(),
+        }
+    }
+
+    fn request<T: serde::de::DeserializeOwned>(
+        &self,
+        method: &str,
+        params: serde_json::Value,
+    ) -> anyhow::Result<T> {
+        let body = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 0,
+            "method": method,
+            "params": params,
+        });
+
+        let response = self.runtime.block_on(async {
+            let response = self
+                .client
+                .post(self.url.clone())
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(body.to_string())
+                .send()
+                .await?
+                .error_for_status()?;
+            response.bytes().await
+        });
+        let response = response.with_context(|| format!("Requesting {method}"))?;
+
+        let response: remote::Response<T> = serde_json::from_slice(&response)
+            .with_context(|| format!("Parsing {method} response"))?;
+        match response {
+            remote::Response::Result { result } => Ok(result),
+            remote::Response::Error { error } => {
+                anyhow::bail!("{method} failed: {}", error)
+            }
+        }
+    }
+
+    fn proof(
+        &mut self,
+        address: ContractAddress,
+        keys: &[StorageAddress],
+    ) -> anyhow::Result<remote::ProofOutput> {
+        let output: remote::ProofOutput = self.request(
+            "pathfinder_getProof",
+            serde_json::json!({
+                "block_id": { "block_number": self.block.get() },
+                "contract_address": address,
+                "keys": keys,
+            }),
+        )?;
+
+        self.insert(Trie::Storage, &output.contract_proof)?;
+        self.contracts.insert(address, output.contract_data.clone());
+        Ok(output)
+    }
+
+    fn class_proof(&mut self, class_hash: ClassHash) -> anyhow::Result<Vec<remote::ProofNode>> {
+        let output: remote::ClassProofOutput = self.request(
+            "pathfinder_getClassProof",
+            serde_json::json!({
+                "block_id": { "block_number": self.block.get() },
+                "class_hash": class_hash,
+            }),
+        )?;
+
+        self.insert(Trie::Class, &output.class_proof)?;
+        Ok(output.class_proof)
+    }
+
+    fn contract_data(
+        &mut self,
+        address: ContractAddress,
+    ) -> anyhow::Result<Option<remote::ContractData>> {
+        match self.contracts.get(&address) {
+            Some(data) => Ok(data.clone()),
+            None => Ok(self.proof(address, &[])?.contract_data),
+        }
+    }
+
+    fn insert(&mut self, trie: Trie, proof: &[remote::ProofNode]) -> anyhow::Result<()> {
+        for node in proof {
+            let node = TrieNode::try_from(node)?;
+            self.nodes.insert(trie.hash(&node), node);
+        }
+        Ok(())
+    }
+}
+
+impl State for RemoteState {
+    fn root(&mut self, trie: Trie) -> anyhow::Result<Option<Felt>> {
+        let proof = match trie {
+            Trie::Storage => self.proof(ContractAddress::ZERO, &[])?.contract_proof,
+            Trie::Class => self.class_proof(ClassHash::ZERO)?,
+            Trie::Contract(address) => {
+                let root = self
+                    .contract_data(address)?
+                    .map(|data| data.root)
+                    .unwrap_or_default();
+                return Ok((root != ContractRoot::ZERO).then_some(root.0));
+            }
+        };
+
+        proof
+            .first()
+            .map(|node| TrieNode::try_from(node).map(|node| trie.hash(&node)))
+            .transpose()
+    }
+
+    fn node(
+        &mut self,
+        trie: Trie,
+        hash: Felt,
+        path: &BitSlice<u8, Msb0>,
+    ) -> anyhow::Result<TrieNode> {
+        if let Some(node) = self.nodes.get(&hash) {
+            return Ok(node.clone());
+        }
+
+        // The proof of any key below the node contains it.
+        let mut key = path.to_bitvec();
+        key.resize(HEIGHT, false);
+        let key = Felt::from_bits(&key).context("Mapping path to key")?;
+
+        match trie {
+            Trie::Storage => {
+                self.proof(ContractAddress(key), &[])?;
+            }
+            Trie::Class => {
+                self.class_proof(ClassHash(key))?;
+            }
+            Trie::Contract(address) => {
+                let storage_proofs = self
+                    .proof(address, &[StorageAddress(key)])?
+                    .contract_data
+                    .with_context(|| format!("Contract {address} is missing"))?
+                    .storage_proofs;
+                for proof in storage_proofs {
+                    self.insert(trie, &proof)?;
+                }
+            }
+        }
+
+        self.nodes.get(&hash).cloned().with_context(|| {
+            format!(
+                "Node {hash} at height {} is not part of the proof",
+                path.len()
+            )
+        })
+    }
+
+    fn contract(&mut self, address: ContractAddress) -> anyhow::Result<(ClassHash, ContractNonce)> {
+        let data = self
+            .contract_data(address)?
+            .with_context(|| format!("Contract {address} is missing"))?;
+
+        Ok((data.class_hash, data.nonce))
+    }
+}
+
+/// The responses of the remote node's JSON-RPC API.
+mod remote {
+    use bitvec::prelude::*;
+    use pathfinder_common::trie::TrieNode;
+    use pathfinder_common::{ClassHash, ContractNonce, ContractRoot};
+    use pathfinder_crypto::Felt;
+    use serde::Deserialize;
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    pub enum Response<T> {
+        Result { result: T },
+        Error { error: serde_json::Value },
+    }
+
+    #[derive(Debug, Clone, Deserialize)]
+    pub struct ProofOutput {
+        pub contract_proof: Vec<ProofNode>,
+        pub contract_data: Option<ContractData>,
+    }
+
+    #[derive(Debug, Clone, Deserialize)]
+    pub struct ContractData {
+        pub class_hash: ClassHash,
+        pub nonce: ContractNonce,
+        pub root: ContractRoot,
+        pub storage_proofs: Vec<Vec<ProofNode>>,
+    }
+
+    #[derive(Debug, Clone, Deserialize)]
+    pub struct ClassProofOutput {
+        pub class_proof: Vec<ProofNode>,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    pub enum ProofNode {
+        Binary { left: Felt, right: Felt },
+        Edge { path: EdgePath, child: Felt },
+    }
+
+    #[derive(Debug, Clone, PartialEq, Deserialize)]
+    pub struct EdgePath {
+        pub value: Felt,
+        pub len: usize,
+    }
+
+    impl TryFrom<&ProofNode> for TrieNode {
+        type Error = anyhow::Error;
+
+        fn try_from(node: &ProofNode) -> Result<Self, Self::Error> {
+            Ok(match node {
+                ProofNode::Binary { left, right } => TrieNode::Binary {
+                    left: *left,
+                    right: *right,
+                },
+                ProofNode::Edge { path, child } => {
+                    let bits = path.value.view_bits();
+                    anyhow::ensure!(
+                        (1..=super::HEIGHT).contains(&path.len),
+                        "Invalid edge path length {}",
+                        path.len
+                    );
+                    let path = bits[bits.len() - path.len..].to_bitvec();
+
+                    TrieNode::Edge {
+                        child: *child,
+                        path,
+                    }
+                }
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::macro_prelude::*;
+    use pathfinder_common::{BlockHeader, StateUpdate};
+    use pathfinder_storage::{Storage, StorageBuilder};
+
+    use super::*;
+    use crate::state::sync::{update_starknet_state, StarknetStateUpdate};
+
+    /// Stores `state_updates` as consecutive blocks, along with their tries.
+    fn setup(state_updates: &[StateUpdate]) -> Storage {
+        let storage = StorageBuilder::in_memory().unwrap();
+
+        for (number, state_update) in state_updates.iter().enumerate() {
+            let number = BlockNumber::new_or_panic(number as u64);
+            let hash = pathfinder_common::BlockHash(Felt::from_u64(number.get() + 1));
+            let header = BlockHeader::builder()
+                .number(number)
+                .finalize_with_hash(hash);
+
+            let mut db = storage.connection().unwrap();
+            let tx = db.transaction().unwrap();
+            tx.insert_block_header(&header).unwrap();
+            tx.insert_state_update(number, state_update).unwrap();
+            update_starknet_state(
+                &tx,
+                StarknetStateUpdate {
+                    contract_updates: &state_update.contract_updates,
+                    system_contract_updates: &state_update.system_contract_updates,
+                    declared_sierra_classes: &state_update.declared_sierra_classes,
+                },
+                false,
+                number,
+                hash,
+                storage.clone(),
+            )
+            .unwrap();
+            tx.commit().unwrap();
+        }
+
+        storage
+    }
+
+    fn genesis() -> StateUpdate {
+        StateUpdate::default()
+            .with_deployed_contract(contract_address!("0x100"), class_hash!("0xc1"))
+            .with_storage_update(
+                contract_address!("0x100"),
+                storage_address!("0x1"),
+                storage_value!("0x11"),
+            )
+            .with_storage_update(
+                contract_address!("0x100"),
+                storage_address!("0x2"),
+                storage_value!("0x22"),
+            )
+            .with_deployed_contract(contract_address!("0x200"), class_hash!("0xc1"))
+            .with_storage_update(
+                contract_address!("0x200"),
+                storage_address!("0x1"),
+                storage_value!("0x11"),
+            )
+    }
+
+    fn diff(storage: &Storage, a: u64, b: u64, limit: usize) -> Report {
+        let mut db = storage.connection().unwrap();
+        let tx = db.transaction().unwrap();
+        let mut a = LocalState::new(&tx, BlockNumber::new_or_panic(a));
+        let mut b = LocalState::new(&tx, BlockNumber::new_or_panic(b));
+
+        state_diff(&mut a, &mut b, limit).unwrap()
+    }
+
+    #[test]
+    fn identical_states() {
+        let storage = setup(&[genesis(), StateUpdate::default()]);
+
+        let report = diff(&storage, 0, 1, 100);
+
+        assert_eq!(report, Report::default());
+    }
+
+    #[test]
+    fn differing_leaves() {
+        let storage = setup(&[
+            genesis(),
+            StateUpdate::default()
+                .with_storage_update(
+                    contract_address!("0x100"),
+                    storage_address!("0x2"),
+                    storage_value!("0x33"),
+                )
+                .with_deployed_contract(contract_address!("0x300"), class_hash!("0xc2"))
+                .with_storage_update(
+                    contract_address!("0x300"),
+                    storage_address!("0x5"),
+                    storage_value!("0x55"),
+                )
+                .with_declared_sierra_class(sierra_hash!("0x5e"), casm_hash!("0xca")),
+        ]);
+
+        let report = diff(&storage, 0, 1, 100);
+
+        let contracts = report
+            .contracts
+            .iter()
+            .map(|contract| {
+                (
+                    contract.address,
+                    contract.a.map(|state| state.class_hash),
+                    contract.b.map(|state| state.class_hash),
+                    contract.storage.clone(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            contracts,
+            vec![
+                (
+                    contract_address!("0x100"),
+                    Some(class_hash!("0xc1")),
+                    Some(class_hash!("0xc1")),
+                    vec![LeafDiff {
+                        key: felt!("0x2"),
+                        a: Some(felt!("0x22")),
+                        b: Some(felt!("0x33")),
+                    }],
+                ),
+                (
+                    contract_address!("0x300"),
+                    None,
+                    Some(class_hash!("0xc2")),
+                    vec![LeafDiff {
+                        key: felt!("0x5"),
+                        a: None,
+                        b: Some(felt!("0x55")),
+                    }],
+                ),
+            ]
+        );
+        assert_ne!(report.contracts[0].a, report.contracts[0].b);
+
+        assert_eq!(report.classes.len(), 1);
+        assert_eq!(report.classes[0].key, felt!("0x5e"));
+        assert_eq!(report.classes[0].a, None);
+        assert!(!report.truncated);
+
+        // Swapping the states swaps the sides.
+        let reversed = diff(&storage, 1, 0, 100);
+        assert_eq!(reversed.contracts[1].a, report.contracts[1].b);
+        assert_eq!(reversed.contracts[1].b, None);
+    }
+
+    #[test]
+    fn limit() {
+        let storage = setup(&[
+            genesis(),
+            StateUpdate::default()
+                .with_storage_update(
+                    contract_address!("0x100"),
+                    storage_address!("0x1"),
+                    storage_value!("0x33"),
+                )
+                .with_storage_update(
+                    contract_address!("0x200"),
+                    storage_address!("0x1"),
+                    storage_value!("0x33"),
+                ),
+        ]);
+
+        let report = diff(&storage, 0, 1, 1);
+
+        assert_eq!(report.contracts.len(), 1);
+        assert_eq!(report.contracts[0].address, contract_address!("0x100"));
+        assert!(report.truncated);
+    }
+
+    #[test]
+    fn proof_nodes() {
+        let nodes: Vec<remote::ProofNode> = serde_json::from_value(serde_json::json!([
+            {"binary": {"left": "0x1", "right": "0x2"}},
+            {"edge": {"path": {"value": "0x5", "len": 4}, "child": "0x3"}},
+        ]))
+        .unwrap();
+
+        let nodes = nodes
+            .iter()
+            .map(TrieNode::try_from)
+            .collect::<anyhow::Result<Vec<_>>>()
+            .unwrap();
+
+        assert_eq!(
+            nodes,
+            vec![
+                TrieNode::Binary {
+                    left: felt!("0x1"),
+                    right: felt!("0x2"),
+                },
+                TrieNode::Edge {
+                    child: felt!("0x3"),
+                    path: bitvec![u8, Msb0; 0, 1, 0, 1],
+                },
+            ]
+        );
+    }
+}
@@ -1,11 +1,15 @@
 mod class;
+mod execution;
 pub mod l1;
 pub mod l2;
 mod pending;
 pub mod revert;
 
+pub use execution::ExecutionVerification;
+
 use std::collections::HashMap;
 use std::future::Future;
+use std::num::NonZeroU64;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
@@ -26,7 +30,14 @@ use pathfinder_merkle_tree::contract_state::update_contract_state;
 use pathfinder_merkle_tree::{ClassCommitmentTree, StorageCommitmentTree};
 use pathfinder_rpc::v02::types::syncing::{self, NumberedBlock, Syncing};
 use pathfinder_rpc::{Notifications, PendingData, Reorg, SyncState, TopicBroadcasters};
-use pathfinder_storage::{Connection, Storage, Transaction, TransactionBehavior};
+use pathfinder_storage::{
+    Connection,
+    ReorgLogEntry,
+    Storage,
+    SyncMode,
+    Transaction,
+    TransactionBehavior,
+};
 use primitive_types::H160;
 use starknet_gateway_client::GatewayApi;
 use starknet_gateway_types::reply::{Block, PendingBlock};
@@ -46,6 +57,8 @@ pub const RESET_DELAY_ON_FAILURE: std::time::Duration = std::time::Duration::ZER
 #[derive(Debug)]
 pub enum SyncEvent {
     L1Update(EthereumStateUpdate),
+    /// All L1 state updates down to the backfill target have been emitted.
+    L1BackfillCompleted,
     /// New L2 [block update](StateUpdate) found.
     Block(
         (
@@ -75,8 +88,11 @@ pub enum SyncEvent {
     },
     /// A new L2 pending update was polled.
     Pending((Arc<PendingBlock>, Arc<StateUpdate>)),
-    /// A new L1 to L2 message was finalized.
+    /// An L1 to L2 message was sent, or its log was removed by an L1 reorg.
     L1ToL2Message(L1ToL2MessageLog),
+    /// An L1 to L2 message was cancelled, or its log was removed by an L1
+    /// reorg.
+    L1ToL2MessageCancelled(L1ToL2MessageLog),
 }
 
 pub struct SyncContext<G, E> {
@@ -89,6 +105,8 @@ pub struct SyncContext<G, E> {
     pub state: Arc<SyncState>,
     pub head_poll_interval: Duration,
     pub l1_poll_interval: Duration,
+    /// How often the pending block is polled while at the chain tip.
+    pub pending_poll_interval: Duration,
     pub pending_data: WatchSender<PendingData>,
     pub block_validation_mode: l2::BlockValidationMode,
     pub websocket_txs: Option<TopicBroadcasters>,
@@ -96,10 +114,54 @@ pub struct SyncContext<G, E> {
     pub block_cache_size: usize,
     pub restart_delay: Duration,
     pub verify_tree_hashes: bool,
+    /// Whether to re-execute synced blocks and compare the results with the
+    /// gateway's data before storing them.
+    pub verify_execution: ExecutionVerification,
     pub gossiper: Gossiper,
     pub sequencer_public_key: PublicKey,
-    pub fetch_concurrency: std::num::NonZeroUsize,
+    pub pipeline: l2::PipelineConfig,
     pub fetch_casm_from_fgw: bool,
+    /// The maximum number of blocks a single reorg may roll back. Deeper reorgs
+    /// halt sync instead, as they require operator intervention.
+    pub max_reorg_depth: NonZeroU64,
+    /// Must match the [SyncMode] of the database. In [SyncMode::Light] state
+    /// diffs are neither applied to the Merkle tries nor stored.
+    pub sync_mode: SyncMode,
+    /// Sync stops after the block being stored once this is set.
+    pub shutdown: tokio::sync::watch::Receiver<bool>,
+}
+
+impl<G, E> SyncContext<G, E> {
+    /// The same context, syncing L2 from `sequencer` instead.
+    pub fn with_sequencer<S>(self, sequencer: S) -> SyncContext<S, E> {
+        SyncContext {
+            storage: self.storage,
+            ethereum: self.ethereum,
+            chain: self.chain,
+            chain_id: self.chain_id,
+            core_address: self.core_address,
+            sequencer,
+            state: self.state,
+            head_poll_interval: self.head_poll_interval,
+            l1_poll_interval: self.l1_poll_interval,
+            pending_poll_interval: self.pending_poll_interval,
+            pending_data: self.pending_data,
+            block_validation_mode: self.block_validation_mode,
+            websocket_txs: self.websocket_txs,
+            notifications: self.notifications,
+            block_cache_size: self.block_cache_size,
+            restart_delay: self.restart_delay,
+            verify_tree_hashes: self.verify_tree_hashes,
+            verify_execution: self.verify_execution,
+            gossiper: self.gossiper,
+            sequencer_public_key: self.sequencer_public_key,
+            pipeline: self.pipeline,
+            fetch_casm_from_fgw: self.fetch_casm_from_fgw,
+            max_reorg_depth: self.max_reorg_depth,
+            sync_mode: self.sync_mode,
+            shutdown: self.shutdown,
+        }
+    }
 }
 
 impl<G, E> From<&SyncContext<G, E>> for L1SyncContext<E>
@@ -112,6 +174,7 @@ where
             chain: value.chain,
             core_address: value.core_address,
             poll_interval: value.l1_poll_interval,
+            storage: value.storage.clone(),
         }
     }
 }
@@ -128,7 +191,7 @@ where
             block_validation_mode: value.block_validation_mode,
             storage: value.storage.clone(),
             sequencer_public_key: value.sequencer_public_key,
-            fetch_concurrency: value.fetch_concurrency,
+            pipeline: value.pipeline,
             fetch_casm_from_fgw: value.fetch_casm_from_fgw,
         }
     }
@@ -194,29 +257,38 @@ where
         storage,
         ethereum: _,
         chain: _,
-        chain_id: _,
+        chain_id,
         core_address: _,
         sequencer,
         state,
         head_poll_interval,
         l1_poll_interval: _,
+        pending_poll_interval,
         pending_data,
         block_validation_mode: _,
         websocket_txs,
-        notifications,
+        mut notifications,
         block_cache_size,
         restart_delay,
         verify_tree_hashes: _,
+        verify_execution,
         gossiper,
         sequencer_public_key: _,
-        fetch_concurrency: _,
+        pipeline: _,
         fetch_casm_from_fgw,
+        max_reorg_depth,
+        sync_mode,
+        shutdown,
     } = context;
 
     let mut db_conn = storage
         .connection()
         .context("Creating database connection")?;
 
+    resume_from_checkpoint(&mut db_conn, max_reorg_depth, sync_mode, &mut notifications)
+        .await
+        .context("Resuming from sync checkpoint")?;
+
     let (event_sender, event_receiver) = mpsc::channel(8);
 
     let l2_head = tokio::task::block_in_place(|| -> anyhow::Result<_> {
@@ -234,8 +306,12 @@ where
         .await
         .context("Fetching latest block from gateway")?;
 
+    // Stops the spawned tasks if this future is dropped, e.g. when the task
+    // running sync is aborted.
+    let mut tasks = SyncTasks::default();
+
     let (tx_latest, rx_latest) = tokio::sync::watch::channel(gateway_latest);
-    let mut latest_handle = tokio::spawn(l2::poll_latest(
+    let mut latest_handle = tasks.spawn(l2::poll_latest(
         sequencer.clone(),
         head_poll_interval,
         tx_latest,
@@ -248,17 +324,18 @@ where
         BlockHash(Felt::ZERO),
         StateCommitment(Felt::ZERO),
     ));
-    let _status_sync = tokio::spawn(update_sync_status_latest(
+    let _status_sync = tasks.spawn(update_sync_status_latest(
         Arc::clone(&state),
         starting_block_hash,
         starting_block_num,
         rx_latest.clone(),
         gossiper,
+        chain_label(chain_id),
     ));
 
     // Start L1 producer task. Clone the event sender so that the channel remains
     // open even if the producer task fails.
-    let mut l1_handle = tokio::spawn(l1_sync(event_sender.clone(), l1_context.clone()));
+    let mut l1_handle = tasks.spawn(l1_sync(event_sender.clone(), l1_context.clone()));
 
     let latest_blocks = latest_n_blocks(&mut db_conn, block_cache_size)
         .await
@@ -267,7 +344,7 @@ where
 
     // Start L2 producer task. Clone the event sender so that the channel remains
     // open even if the producer task fails.
-    let mut l2_handle = tokio::spawn(l2_sync(
+    let mut l2_handle = tasks.spawn(l2_sync(
         event_sender.clone(),
         l2_context.clone(),
         l2_head,
@@ -282,19 +359,25 @@ where
         state,
         pending_data,
         verify_tree_hashes: context.verify_tree_hashes,
+        verify_execution,
+        chain_id,
         websocket_txs,
         notifications,
+        max_reorg_depth,
+        sync_mode,
+        shutdown: shutdown.clone(),
     };
-    let mut consumer_handle = tokio::spawn(consumer(event_receiver, consumer_context, tx_current));
+    let mut consumer_handle = tasks.spawn(consumer(event_receiver, consumer_context, tx_current));
 
-    let mut pending_handle = tokio::spawn(pending::poll_pending(
+    let mut pending_handle = tasks.spawn(pending::poll_pending(
         event_sender.clone(),
         sequencer.clone(),
-        Duration::from_secs(2),
+        pending_poll_interval,
         storage.clone(),
         rx_latest.clone(),
         rx_current.clone(),
         fetch_casm_from_fgw,
+        chain_label(chain_id),
     ));
 
     loop {
@@ -302,14 +385,15 @@ where
             _ = &mut pending_handle => {
                 tracing::error!("Pending tracking task ended unexpectedly");
 
-                pending_handle = tokio::spawn(pending::poll_pending(
+                pending_handle = tasks.spawn(pending::poll_pending(
                     event_sender.clone(),
                     sequencer.clone(),
-                    Duration::from_secs(2),
+                    pending_poll_interval,
                     storage.clone(),
                     rx_latest.clone(),
                     rx_current.clone(),
                     fetch_casm_from_fgw,
+                    chain_label(chain_id),
                 ));
             },
             _ = &mut latest_handle => {
@@ -339,7 +423,7 @@ where
                 }
 
                 let fut = l1_sync(event_sender.clone(), l1_context.clone());
-                l1_handle = tokio::spawn(async move {
+                l1_handle = tasks.spawn(async move {
                     tokio::time::sleep(RESET_DELAY_ON_FAILURE).await;
                     fut.await
                 });
@@ -366,7 +450,7 @@ where
                 let block_chain = BlockChain::with_capacity(1_000, latest_blocks);
                 let fut = l2_sync(event_sender.clone(), l2_context.clone(), l2_head, block_chain, rx_latest.clone());
 
-                l2_handle = tokio::spawn(async move {
+                l2_handle = tasks.spawn(async move {
                     tokio::time::sleep(restart_delay).await;
                     fut.await
                 });
@@ -439,19 +523,56 @@ where
 
                 _ = pending_handle.await;
 
+                if *shutdown.borrow() {
+                    tracing::info!("Sync stopped");
+                    return Ok(());
+                }
+
                 anyhow::bail!("Sync process terminated");
             }
         }
     }
 }
 
+/// Aborts the tracked tasks once dropped.
+#[derive(Default)]
+struct SyncTasks(Vec<tokio::task::AbortHandle>);
+
+impl SyncTasks {
+    fn spawn<F>(&mut self, future: F) -> tokio::task::JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        // Tasks are respawned on failure, forget the ones which already ended.
+        self.0.retain(|task| !task.is_finished());
+
+        let handle = tokio::spawn(future);
+        self.0.push(handle.abort_handle());
+        handle
+    }
+}
+
+impl Drop for SyncTasks {
+    fn drop(&mut self) {
+        for task in &self.0 {
+            task.abort();
+        }
+    }
+}
+
 struct ConsumerContext {
     pub storage: Storage,
     pub state: Arc<SyncState>,
     pub pending_data: WatchSender<PendingData>,
     pub verify_tree_hashes: bool,
+    pub verify_execution: ExecutionVerification,
+    pub chain_id: ChainId,
     pub websocket_txs: Option<TopicBroadcasters>,
     pub notifications: Notifications,
+    pub max_reorg_depth: NonZeroU64,
+    pub sync_mode: SyncMode,
+    pub shutdown: tokio::sync::watch::Receiver<bool>,
 }
 
 async fn consumer(
@@ -464,9 +585,15 @@ async fn consumer(
         state,
         pending_data,
         verify_tree_hashes,
+        verify_execution,
+        chain_id,
         mut websocket_txs,
         mut notifications,
+        max_reorg_depth,
+        sync_mode,
+        mut shutdown,
     } = context;
+    let chain = chain_label(chain_id);
 
     let mut last_block_start = std::time::Instant::now();
     let mut block_time_avg = std::time::Duration::ZERO;
@@ -490,13 +617,45 @@ async fn consumer(
     })
     .context("Fetching latest block time")?;
 
-    while let Some(event) = events.recv().await {
+    loop {
+        // Events are only processed outside of the select, so that sync stops
+        // between blocks rather than in the middle of storing one.
+        let event = tokio::select! {
+            biased;
+            _ = stopped(&mut shutdown) => {
+                tracing::info!("Stopping sync");
+                break;
+            }
+            event = events.recv() => match event {
+                Some(event) => event,
+                None => break,
+            },
+        };
+
         use SyncEvent::*;
         match event {
             L1Update(update) => {
                 tracing::trace!("Updating L1 sync to block {}", update.block_number);
                 l1_update(&mut db_conn, &update).await?;
                 tracing::info!("L1 sync updated to block {}", update.block_number);
+                notifications
+                    .l1_updates
+                    .send(update.block_number)
+                    // Ignore errors in case nobody is listening. New listeners may subscribe in the
+                    // future.
+                    .ok();
+            }
+            L1BackfillCompleted => {
+                tokio::task::block_in_place(|| {
+                    let transaction = db_conn
+                        .transaction_with_behavior(TransactionBehavior::Immediate)
+                        .context("Create database transaction")?;
+                    transaction
+                        .update_l1_backfill_target(None)
+                        .context("Clearing L1 backfill target")?;
+                    transaction.commit().context("Commit database transaction")
+                })?;
+                tracing::debug!("L1 state update backfill completed");
             }
             Block(
                 (block, (tx_comm, ev_comm, rc_comm)),
@@ -530,6 +689,9 @@ async fn consumer(
                     *signature,
                     *state_diff_commitment,
                     verify_tree_hashes,
+                    verify_execution,
+                    sync_mode,
+                    chain_id,
                     storage.clone(),
                     &mut websocket_txs,
                     &mut notifications,
@@ -549,11 +711,11 @@ async fn consumer(
                     Syncing::Status(status) => {
                         status.current = NumberedBlock::from((block_hash, block_number));
 
-                        metrics::gauge!("current_block", block_number.get() as f64);
+                        metrics::gauge!("current_block", block_number.get() as f64, "chain" => chain);
 
                         if status.highest.number <= block_number {
                             status.highest = status.current;
-                            metrics::gauge!("highest_block", block_number.get() as f64);
+                            metrics::gauge!("highest_block", block_number.get() as f64, "chain" => chain);
                         }
                     }
                 }
@@ -568,13 +730,18 @@ async fn consumer(
                     + timings.signature_download)
                     .as_secs_f64();
 
-                metrics::gauge!("block_download", download_time);
-                metrics::gauge!("block_processing", update_t.as_secs_f64());
+                metrics::gauge!("block_download", download_time, "chain" => chain);
+                metrics::gauge!("block_processing", update_t.as_secs_f64(), "chain" => chain);
                 metrics::histogram!("block_processing_duration_seconds", update_t);
-                metrics::gauge!("block_latency", latency as f64);
+                metrics::histogram!("sync_stage_duration_seconds", timings.block_download, "stage" => "block_download");
+                metrics::histogram!("sync_stage_duration_seconds", timings.class_declaration, "stage" => "class_declaration");
+                metrics::histogram!("sync_stage_duration_seconds", timings.signature_download, "stage" => "signature_download");
+                metrics::histogram!("sync_stage_duration_seconds", update_t, "stage" => "state_update");
+                metrics::gauge!("block_latency", latency as f64, "chain" => chain);
                 metrics::gauge!(
                     "block_time",
-                    (block_timestamp.get() - latest_timestamp.get()) as f64
+                    (block_timestamp.get() - latest_timestamp.get()) as f64,
+                    "chain" => chain
                 );
                 latest_timestamp = block_timestamp;
                 next_number += 1;
@@ -608,9 +775,15 @@ async fn consumer(
             }
             Reorg(reorg_tail) => {
                 tracing::trace!("Reorg L2 state to block {}", reorg_tail);
-                l2_reorg(&mut db_conn, reorg_tail, &mut notifications)
-                    .await
-                    .with_context(|| format!("Reorg L2 state to {reorg_tail:?}"))?;
+                l2_reorg(
+                    &mut db_conn,
+                    reorg_tail,
+                    max_reorg_depth,
+                    sync_mode,
+                    &mut notifications,
+                )
+                .await
+                .with_context(|| format!("Reorg L2 state to {reorg_tail:?}"))?;
 
                 next_number = reorg_tail;
 
@@ -692,7 +865,11 @@ async fn consumer(
             }
             L1ToL2Message(msg) => {
                 tracing::trace!("Got a new L1 to L2 message log: {:?}", msg);
-                // todo!()
+                l1_to_l2_message_update(&mut db_conn, &msg, false).await?;
+            }
+            L1ToL2MessageCancelled(msg) => {
+                tracing::trace!("Got a new L1 to L2 message cancellation log: {:?}", msg);
+                l1_to_l2_message_update(&mut db_conn, &msg, true).await?;
             }
         }
     }
@@ -700,6 +877,93 @@ async fn consumer(
     Ok(())
 }
 
+/// The `chain` label of the sync gauges, which tells the networks apart when
+/// an additional network is synced by the same process.
+fn chain_label(chain_id: ChainId) -> &'static str {
+    match chain_id {
+        ChainId::MAINNET => "mainnet",
+        ChainId::SEPOLIA_TESTNET => "testnet-sepolia",
+        ChainId::SEPOLIA_INTEGRATION => "integration-sepolia",
+        _ => "custom",
+    }
+}
+
+/// Resolves once `shutdown` is set, or never if its sender is dropped.
+async fn stopped(shutdown: &mut tokio::sync::watch::Receiver<bool>) {
+    if shutdown.wait_for(|stop| *stop).await.is_err() {
+        std::future::pending::<()>().await;
+    }
+}
+
+/// Rolls back blocks stored after the sync checkpoint.
+///
+/// The checkpoint is written together with each block stored by this sync, so
+/// any blocks above it were not stored by it, e.g. by an older version or
+/// another sync mode, and are synced again instead of being trusted.
+async fn resume_from_checkpoint(
+    connection: &mut Connection,
+    max_reorg_depth: NonZeroU64,
+    sync_mode: SyncMode,
+    notifications: &mut Notifications,
+) -> anyhow::Result<()> {
+    let (latest, checkpoint) = tokio::task::block_in_place(|| {
+        let tx = connection
+            .transaction()
+            .context("Creating database transaction")?;
+        let latest = tx
+            .block_id(pathfinder_storage::BlockId::Latest)
+            .context("Fetching latest block")?;
+        let checkpoint = tx.sync_checkpoint().context("Fetching sync checkpoint")?;
+        // The checkpointed block may have been rolled back since.
+        let checkpoint = match checkpoint {
+            Some((number, hash)) => {
+                let stored = tx
+                    .block_hash(number.into())
+                    .context("Fetching checkpoint block hash")?;
+                (stored == Some(hash)).then_some((number, hash))
+            }
+            None => None,
+        };
+
+        anyhow::Ok((latest, checkpoint))
+    })?;
+
+    match (latest, checkpoint) {
+        (Some((latest, _)), Some((checkpoint, _)))
+            if latest > checkpoint && latest.get() - checkpoint.get() <= max_reorg_depth.get() =>
+        {
+            tracing::warn!(
+                %latest, %checkpoint,
+                "Blocks after the sync checkpoint were not stored by sync, rolling them back"
+            );
+            l2_reorg(
+                connection,
+                checkpoint + 1,
+                max_reorg_depth,
+                sync_mode,
+                notifications,
+            )
+            .await
+        }
+        (latest, checkpoint) if latest != checkpoint => {
+            tracing::debug!(
+                ?latest,
+                ?checkpoint,
+                "Moving sync checkpoint to the latest block"
+            );
+            tokio::task::block_in_place(|| {
+                let tx = connection
+                    .transaction_with_behavior(TransactionBehavior::Immediate)
+                    .context("Creating database transaction")?;
+                tx.update_sync_checkpoint(latest)
+                    .context("Updating sync checkpoint")?;
+                tx.commit().context("Committing database transaction")
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
 async fn latest_n_blocks(
     connection: &mut Connection,
     n: usize,
@@ -745,6 +1009,7 @@ async fn update_sync_status_latest(
     starting_block_num: BlockNumber,
     mut latest: tokio::sync::watch::Receiver<(BlockNumber, BlockHash)>,
     gossiper: Gossiper,
+    chain: &'static str,
 ) {
     let starting = NumberedBlock::from((starting_block_hash, starting_block_num));
 
@@ -771,8 +1036,8 @@ async fn update_sync_status_latest(
                     highest: latest,
                 });
 
-                metrics::gauge!("current_block", starting.number.get() as f64);
-                metrics::gauge!("highest_block", latest.number.get() as f64);
+                metrics::gauge!("current_block", starting.number.get() as f64, "chain" => chain);
+                metrics::gauge!("highest_block", latest.number.get() as f64, "chain" => chain);
 
                 propagate_head(&gossiper, &mut last_propagated, latest).await;
 
@@ -785,7 +1050,7 @@ async fn update_sync_status_latest(
                 if status.highest.hash != latest.hash {
                     status.highest = latest;
 
-                    metrics::gauge!("highest_block", latest.number.get() as f64);
+                    metrics::gauge!("highest_block", latest.number.get() as f64, "chain" => chain);
 
                     propagate_head(&gossiper, &mut last_propagated, latest).await;
 
@@ -830,10 +1095,14 @@ async fn l1_update(
 
         if let Some(l2_hash) = l2_hash {
             if l2_hash == update.block_hash {
-                transaction
-                    .update_l1_l2_pointer(Some(update.block_number))
-                    .context("Updating L1-L2 pointer")?;
-                tracing::info!(block=?update.block_number, "Updated L1/L2 match");
+                // Backfilled updates can be older than the current match.
+                let current = transaction.l1_l2_pointer().context("Query L1-L2 pointer")?;
+                if current.map_or(true, |current| current < update.block_number) {
+                    transaction
+                        .update_l1_l2_pointer(Some(update.block_number))
+                        .context("Updating L1-L2 pointer")?;
+                    tracing::info!(block=?update.block_number, "Updated L1/L2 match");
+                }
             } else {
                 tracing::warn!(block_number=?update.block_number, L1=?update.block_hash, L2=?l2_hash, "L1/L2 block hash mismatch");
                 if let Some(matching_block_number) = transaction.l1_l2_pointer()? {
@@ -850,6 +1119,32 @@ async fn l1_update(
     })
 }
 
+/// Records the L1 transaction which sent or cancelled an L1 to L2 message.
+async fn l1_to_l2_message_update(
+    connection: &mut Connection,
+    log: &L1ToL2MessageLog,
+    cancelled: bool,
+) -> anyhow::Result<()> {
+    tokio::task::block_in_place(move || {
+        let transaction = connection
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .context("Create database transaction")?;
+
+        if cancelled {
+            transaction.upsert_l1_to_l2_message_cancelled(log)
+        } else {
+            transaction.upsert_l1_to_l2_message_sent(log)
+        }
+        .context("Insert L1 to L2 message")?;
+
+        transaction
+            .commit()
+            .context("Commit database transaction")?;
+
+        Ok(())
+    })
+}
+
 /// Returns the new [StateCommitment] after the update.
 #[allow(clippy::too_many_arguments)]
 async fn l2_update(
@@ -862,6 +1157,9 @@ async fn l2_update(
     signature: BlockCommitmentSignature,
     state_diff_commitment: StateDiffCommitment,
     verify_tree_hashes: bool,
+    verify_execution: ExecutionVerification,
+    sync_mode: SyncMode,
+    chain_id: ChainId,
     // we need this so that we can create extra read-only transactions for
     // parallel contract state updates
     storage: Storage,
@@ -872,26 +1170,41 @@ async fn l2_update(
         let transaction = connection
             .transaction_with_behavior(TransactionBehavior::Immediate)
             .context("Create database transaction")?;
-        let (storage_commitment, class_commitment) = update_starknet_state(
-            &transaction,
-            StarknetStateUpdate {
-                contract_updates: &state_update.contract_updates,
-                system_contract_updates: &state_update.system_contract_updates,
-                declared_sierra_classes: &state_update.declared_sierra_classes,
-            },
-            verify_tree_hashes,
-            block.block_number,
-            storage,
-        )
-        .context("Updating Starknet state")?;
-        let state_commitment = StateCommitment::calculate(storage_commitment, class_commitment);
+        let (storage_commitment, class_commitment, state_commitment) = match sync_mode {
+            SyncMode::Full => {
+                let (storage_commitment, class_commitment) = update_starknet_state(
+                    &transaction,
+                    StarknetStateUpdate {
+                        contract_updates: &state_update.contract_updates,
+                        system_contract_updates: &state_update.system_contract_updates,
+                        declared_sierra_classes: &state_update.declared_sierra_classes,
+                    },
+                    verify_tree_hashes,
+                    block.block_number,
+                    block.block_hash,
+                    storage,
+                )
+                .context("Updating Starknet state")?;
+                let state_commitment =
+                    StateCommitment::calculate(storage_commitment, class_commitment);
+
+                // Ensure that roots match.. what should we do if it doesn't? For now the whole
+                // sync process ends..
+                anyhow::ensure!(
+                    state_commitment == block.state_commitment,
+                    "State root mismatch"
+                );
 
-        // Ensure that roots match.. what should we do if it doesn't? For now the whole
-        // sync process ends..
-        anyhow::ensure!(
-            state_commitment == block.state_commitment,
-            "State root mismatch"
-        );
+                (storage_commitment, class_commitment, state_commitment)
+            }
+            // Without tries the state commitment can't be verified, and is only bound to
+            // the block by its hash. The tries' commitments are left unset.
+            SyncMode::Light => (
+                StorageCommitment::ZERO,
+                ClassCommitment::ZERO,
+                block.state_commitment,
+            ),
+        };
 
         let transaction_count = block.transactions.len();
         let event_count = block
@@ -916,6 +1229,9 @@ async fn l2_update(
             eth_l1_data_gas_price: block.l1_data_gas_price.price_in_wei,
             // Default value for Starknet <0.13.1 is zero
             strk_l1_data_gas_price: block.l1_data_gas_price.price_in_fri,
+            // Default value for Starknet <0.13.4 is zero
+            eth_l2_gas_price: block.l2_gas_price.unwrap_or_default().price_in_wei,
+            strk_l2_gas_price: block.l2_gas_price.unwrap_or_default().price_in_fri,
             sequencer_address: block
                 .sequencer_address
                 .unwrap_or(SequencerAddress(Felt::ZERO)),
@@ -933,9 +1249,24 @@ async fn l2_update(
             state_diff_length: state_update.state_diff_length(),
         };
 
+        // Re-execution needs the state of the parent block.
+        if sync_mode == SyncMode::Full {
+            execution::verify(
+                &transaction,
+                verify_execution,
+                chain_id,
+                &header,
+                &block,
+                &state_update,
+            )?;
+        }
+
         transaction
             .insert_block_header(&header)
             .context("Inserting block header into database")?;
+        transaction
+            .update_sync_checkpoint(Some((header.number, header.hash)))
+            .context("Updating sync checkpoint")?;
 
         // Insert the transactions.
         anyhow::ensure!(
@@ -957,22 +1288,21 @@ async fn l2_update(
             .context("Insert transaction data into database")?;
 
         // Insert state updates
-        transaction
-            .insert_state_update(block.block_number, &state_update)
-            .context("Insert state update into database")?;
+        if sync_mode == SyncMode::Full {
+            transaction
+                .insert_state_update(block.block_number, &state_update)
+                .context("Insert state update into database")?;
+        }
 
         // Insert signature
         transaction
             .insert_signature(block.block_number, &signature)
             .context("Insert signature into database")?;
 
-        // Track combined L1 and L2 state.
+        // Track combined L1 and L2 state. L1 state updates may already be known for
+        // blocks we have not synced yet, e.g. when they were backfilled from L1.
         let l1_l2_head = transaction.l1_l2_pointer().context("Query L1-L2 head")?;
-        let expected_next = l1_l2_head
-            .map(|head| head + 1)
-            .unwrap_or(BlockNumber::GENESIS);
-
-        if expected_next == header.number {
+        if l1_l2_head.map_or(true, |head| head < header.number) {
             if let Some(l1_state) = transaction
                 .l1_state_at_number(header.number)
                 .context("Query L1 state")?
@@ -1029,6 +1359,8 @@ async fn l2_update(
 async fn l2_reorg(
     connection: &mut Connection,
     reorg_tail: BlockNumber,
+    max_reorg_depth: NonZeroU64,
+    sync_mode: SyncMode,
     notifications: &mut Notifications,
 ) -> anyhow::Result<()> {
     tokio::task::block_in_place(move || {
@@ -1042,6 +1374,22 @@ async fn l2_reorg(
             .context("Latest block number is none during reorg")?
             .0;
 
+        // Refuse to discard an unexpectedly large amount of verified state. This error
+        // terminates the sync consumer and therefore halts sync.
+        let reorg_depth = head.get().saturating_sub(reorg_tail.get()) + 1;
+        if reorg_depth > max_reorg_depth.get() {
+            tracing::error!(
+                %head, %reorg_tail, %reorg_depth, %max_reorg_depth,
+                "Reorg exceeds the maximum allowed depth, halting sync. Operator intervention is \
+                 required: verify the L1 and L2 sources, and raise `--sync.max-reorg-depth` if \
+                 the reorg is legitimate."
+            );
+            anyhow::bail!(
+                "Reorg of {reorg_depth} blocks (from head {head} to {reorg_tail}) exceeds the \
+                 maximum reorg depth of {max_reorg_depth}"
+            );
+        }
+
         let reorg_tail_hash = transaction
             .block_hash(reorg_tail.into())
             .context("Fetching first block hash")?
@@ -1055,11 +1403,34 @@ async fn l2_reorg(
             .increment_reorg_counter()
             .context("Incrementing reorg counter")?;
 
+        let common_ancestor = reorg_tail
+            .parent()
+            .map(|parent| -> anyhow::Result<_> {
+                let hash = transaction
+                    .block_hash(parent.into())
+                    .context("Fetching common ancestor hash")?
+                    .context("Expected common ancestor hash to exist")?;
+                Ok((parent, hash))
+            })
+            .transpose()?;
+        let detected_at = std::time::SystemTime::now()
+            .duration_since(std::time::SystemTime::UNIX_EPOCH)
+            .context("Current time is before the unix epoch")?
+            .as_secs();
+        transaction
+            .insert_reorg(&ReorgLogEntry {
+                old_head_number: head,
+                old_head_hash: head_hash,
+                common_ancestor,
+                detected_at,
+            })
+            .context("Recording reorg")?;
+
         // Roll back Merkle trie updates.
         //
         // If we're rolling back genesis then there will be no blocks left so state will
         // be empty.
-        if let Some(target_block) = reorg_tail.parent() {
+        if let Some(target_block) = reorg_tail.parent().filter(|_| sync_mode == SyncMode::Full) {
             let target_header = transaction
                 .block_header(target_block.into())
                 .context("Fetching target block header")?
@@ -1104,6 +1475,10 @@ async fn l2_reorg(
             }
         }
 
+        transaction
+            .update_sync_checkpoint(common_ancestor)
+            .context("Updating sync checkpoint")?;
+
         transaction
             .commit()
             .context("Commit database transaction")?;
@@ -1133,59 +1508,166 @@ pub struct StarknetStateUpdate<'a> {
     pub declared_sierra_classes: &'a HashMap<SierraHash, CasmHash>,
 }
 
+/// Applies the state update of `block` to the storage and class tries, and
+/// returns the resulting commitments.
+///
+/// The contract storage tries and the class commitment tree are computed in
+/// parallel on the rayon thread pool, the storage commitment tree is then
+/// committed once for the whole block.
+///
+/// Re-applying the state update of a block which has already been committed
+/// with the same `block_hash` is a no-op which returns the committed
+/// commitments. Re-applying it for a different `block_hash` is an error.
 pub fn update_starknet_state(
     transaction: &Transaction<'_>,
     state_update: StarknetStateUpdate<'_>,
     verify_hashes: bool,
     block: BlockNumber,
+    block_hash: BlockHash,
     // we need this so that we can create extra read-only transactions for
     // parallel contract state updates
     storage: Storage,
 ) -> anyhow::Result<(StorageCommitment, ClassCommitment)> {
     use rayon::prelude::*;
 
-    let mut storage_commitment_tree = match block.parent() {
-        Some(parent) => StorageCommitmentTree::load(transaction, parent)
-            .context("Loading storage commitment tree")?,
-        None => StorageCommitmentTree::empty(transaction),
+    // Trie roots are only stored for blocks which changed the tries, in which
+    // case applying the state update again would duplicate the trie nodes.
+    let already_applied = transaction
+        .storage_root_exists(block)
+        .context("Querying storage root existence")?
+        || transaction
+            .class_root_exists(block)
+            .context("Querying class root existence")?;
+    if already_applied {
+        let header = transaction
+            .block_header(block.into())
+            .context("Querying block header")?
+            .with_context(|| {
+                format!("State update of block {block} has been applied, but its header is missing")
+            })?;
+
+        anyhow::ensure!(
+            header.hash == block_hash,
+            "State update of block {block} has already been applied for block hash {}, refusing \
+             to apply it for block hash {}",
+            header.hash,
+            block_hash
+        );
+
+        tracing::debug!(%block, "State update has already been applied, skipping");
+        return Ok((header.storage_commitment, header.class_commitment));
     }
-    .with_verify_hashes(verify_hashes);
 
+    // The contract storage tries and the class commitment tree are independent
+    // of each other, so they are all computed in parallel using read-only
+    // transactions. The results are then persisted and the storage commitment
+    // tree is committed once, using the write transaction.
+    let trie_update_t = std::time::Instant::now();
     let (send, recv) = std::sync::mpsc::channel();
 
     rayon::scope(|s| {
         s.spawn(|_| {
-            let result: Result<Vec<_>, _> = state_update
-                .contract_updates
-                .par_iter()
-                .map_init(
-                    || storage.clone().connection(),
-                    |connection, (contract_address, update)| {
-                        let connection = match connection {
-                            Ok(connection) => connection,
-                            Err(e) => anyhow::bail!(
-                                "Failed to create database connection in rayon thread: {}",
-                                e
-                            ),
-                        };
-                        let transaction = connection.transaction()?;
-                        update_contract_state(
-                            *contract_address,
-                            &update.storage,
-                            update.nonce,
-                            update.class.as_ref().map(|x| x.class_hash()),
-                            &transaction,
-                            verify_hashes,
-                            block,
+            let result = rayon::join(
+                || -> anyhow::Result<Vec<_>> {
+                    let contract_updates = state_update.contract_updates.par_iter().map(
+                        |(contract_address, update)| {
+                            (
+                                *contract_address,
+                                &update.storage,
+                                update.nonce,
+                                update.class.as_ref().map(|x| x.class_hash()),
+                            )
+                        },
+                    );
+                    let system_contract_updates = state_update
+                        .system_contract_updates
+                        .par_iter()
+                        .map(|(contract_address, update)| {
+                            (*contract_address, &update.storage, None, None)
+                        });
+
+                    contract_updates
+                        .chain(system_contract_updates)
+                        .map_init(
+                            || storage.clone().connection(),
+                            |connection, (contract_address, updates, nonce, class_hash)| {
+                                let connection = match connection {
+                                    Ok(connection) => connection,
+                                    Err(e) => anyhow::bail!(
+                                        "Failed to create database connection in rayon thread: {}",
+                                        e
+                                    ),
+                                };
+                                let transaction = connection.transaction()?;
+                                update_contract_state(
+                                    contract_address,
+                                    updates,
+                                    nonce,
+                                    class_hash,
+                                    &transaction,
+                                    verify_hashes,
+                                    block,
+                                )
+                                .with_context(|| {
+                                    format!("Updating state of contract {contract_address}")
+                                })
+                            },
                         )
-                    },
-                )
-                .collect();
+                        .collect()
+                },
+                || -> anyhow::Result<_> {
+                    let mut connection = storage
+                        .connection()
+                        .context("Creating database connection in rayon thread")?;
+                    let transaction = connection
+                        .transaction()
+                        .context("Creating database transaction")?;
+
+                    let mut class_commitment_tree = match block.parent() {
+                        Some(parent) => ClassCommitmentTree::load(&transaction, parent)
+                            .context("Loading class commitment tree")?,
+                        None => ClassCommitmentTree::empty(&transaction),
+                    }
+                    .with_verify_hashes(verify_hashes);
+
+                    let mut leaves = Vec::with_capacity(state_update.declared_sierra_classes.len());
+                    for (sierra, casm) in state_update.declared_sierra_classes {
+                        let leaf_hash =
+                            pathfinder_common::calculate_class_commitment_leaf_hash(*casm);
+
+                        class_commitment_tree
+                            .set(*sierra, leaf_hash)
+                            .context("Update class commitment tree")?;
+
+                        leaves.push((leaf_hash, casm));
+                    }
+
+                    // Apply all class commitment tree changes.
+                    let (class_commitment, trie_update) = class_commitment_tree
+                        .commit()
+                        .context("Apply class commitment tree updates")?;
+
+                    Ok((class_commitment, trie_update, leaves))
+                },
+            );
             let _ = send.send(result);
         })
     });
 
-    let contract_update_results = recv.recv().context("Panic on rayon thread")??;
+    let (contract_update_results, class_commitment_update) =
+        recv.recv().context("Panic on rayon thread")?;
+    let contract_update_results = contract_update_results?;
+    let (class_commitment, class_trie_update, class_leaves) = class_commitment_update?;
+    metrics::histogram!("sync_stage_duration_seconds", trie_update_t.elapsed(), "stage" => "trie_update");
+
+    let trie_commit_t = std::time::Instant::now();
+
+    let mut storage_commitment_tree = match block.parent() {
+        Some(parent) => StorageCommitmentTree::load(transaction, parent)
+            .context("Loading storage commitment tree")?,
+        None => StorageCommitmentTree::empty(transaction),
+    }
+    .with_verify_hashes(verify_hashes);
 
     for contract_update_result in contract_update_results.into_iter() {
         storage_commitment_tree
@@ -1199,27 +1681,6 @@ pub fn update_starknet_state(
             .context("Inserting contract update result")?;
     }
 
-    for (contract, update) in state_update.system_contract_updates {
-        let update_result = update_contract_state(
-            *contract,
-            &update.storage,
-            None,
-            None,
-            transaction,
-            verify_hashes,
-            block,
-        )
-        .context("Update system contract state")?;
-
-        storage_commitment_tree
-            .set(*contract, update_result.state_hash)
-            .context("Updating system contract storage commitment tree")?;
-
-        update_result
-            .insert(block, transaction)
-            .context("Persisting system contract trie updates")?;
-    }
-
     // Apply storage commitment tree changes.
     let (storage_commitment, trie_update) = storage_commitment_tree
         .commit()
@@ -1233,44 +1694,27 @@ pub fn update_starknet_state(
         .insert_storage_root(block, root_idx)
         .context("Inserting storage root index")?;
 
-    // Add new Sierra classes to class commitment tree.
-    let mut class_commitment_tree = match block.parent() {
-        Some(parent) => ClassCommitmentTree::load(transaction, parent)
-            .context("Loading class commitment tree")?,
-        None => ClassCommitmentTree::empty(transaction),
-    }
-    .with_verify_hashes(verify_hashes);
-
-    for (sierra, casm) in state_update.declared_sierra_classes {
-        let leaf_hash = pathfinder_common::calculate_class_commitment_leaf_hash(*casm);
-
+    for (leaf_hash, casm) in class_leaves {
         transaction
             .insert_class_commitment_leaf(block, &leaf_hash, casm)
             .context("Adding class commitment leaf")?;
-
-        class_commitment_tree
-            .set(*sierra, leaf_hash)
-            .context("Update class commitment tree")?;
     }
 
-    // Apply all class commitment tree changes.
-    let (class_commitment, trie_update) = class_commitment_tree
-        .commit()
-        .context("Apply class commitment tree updates")?;
-
     let class_root_idx = transaction
-        .insert_class_trie(&trie_update, block)
+        .insert_class_trie(&class_trie_update, block)
         .context("Persisting class trie")?;
 
     transaction
         .insert_class_root(block, class_root_idx)
         .context("Inserting class root index")?;
+    metrics::histogram!("sync_stage_duration_seconds", trie_commit_t.elapsed(), "stage" => "trie_commit");
 
     Ok((storage_commitment, class_commitment))
 }
 
 #[cfg(test)]
 mod tests {
+    use std::num::NonZeroU64;
     use std::sync::Arc;
 
     use pathfinder_common::macro_prelude::*;
@@ -1281,6 +1725,7 @@ mod tests {
         BlockHash,
         BlockHeader,
         BlockNumber,
+        ChainId,
         ClassHash,
         EventCommitment,
         ReceiptCommitment,
@@ -1288,15 +1733,17 @@ mod tests {
         StateCommitment,
         StateDiffCommitment,
         StateUpdate,
+        StorageCommitment,
         TransactionCommitment,
     };
     use pathfinder_crypto::Felt;
     use pathfinder_rpc::SyncState;
-    use pathfinder_storage::StorageBuilder;
+    use pathfinder_storage::{Storage, StorageBuilder, SyncMode};
     use starknet_gateway_types::reply::{self, Block, GasPrices};
 
     use super::l2;
-    use crate::state::sync::{consumer, ConsumerContext, SyncEvent};
+    use crate::state::chain_events::{ChainEvent, ChainEvents};
+    use crate::state::sync::{consumer, ConsumerContext, ExecutionVerification, SyncEvent};
 
     /// Generate some arbitrary block chain data from genesis onwards.
     ///
@@ -1366,6 +1813,7 @@ mod tests {
                     Felt::from_hex_str(&format!("0x200{}", header.number)).unwrap(),
                 )),
                 state_diff_length: Some(header.number.get()),
+                l2_gas_price: None,
             });
 
             let signature = Box::new(BlockCommitmentSignature {
@@ -1426,8 +1874,13 @@ mod tests {
             state: Arc::new(SyncState::default()),
             pending_data: tx,
             verify_tree_hashes: false,
+            verify_execution: ExecutionVerification::Disabled,
+            chain_id: ChainId::SEPOLIA_TESTNET,
             websocket_txs: None,
             notifications: Default::default(),
+            max_reorg_depth: NonZeroU64::MAX,
+            sync_mode: SyncMode::Full,
+            shutdown: tokio::sync::watch::channel(false).1,
         };
 
         let (tx, _rx) = tokio::sync::watch::channel(Default::default());
@@ -1449,6 +1902,205 @@ mod tests {
         assert!(!should_not_exist);
     }
 
+    #[tokio::test(flavor = "multi_thread")]
+    async fn light_mode_skips_state() {
+        let storage = StorageBuilder::in_memory().unwrap();
+        let mut connection = storage.connection().unwrap();
+
+        let (event_tx, event_rx) = tokio::sync::mpsc::channel(100);
+
+        let block_data = generate_block_data();
+        let num_blocks = block_data.len();
+        for (mut a, b, c, d, e) in block_data {
+            // Neither would make it past the tries in full mode.
+            a.0.state_commitment = state_commitment_bytes!(b"not verified");
+            let b = Box::new(b.with_contract_nonce(
+                contract_address_bytes!(b"contract"),
+                contract_nonce_bytes!(b"nonce"),
+            ));
+            event_tx
+                .send(SyncEvent::Block(a, b, c, d, e))
+                .await
+                .unwrap();
+        }
+        drop(event_tx);
+
+        let (tx, _rx) = tokio::sync::watch::channel(Default::default());
+        let context = ConsumerContext {
+            storage,
+            state: Arc::new(SyncState::default()),
+            pending_data: tx,
+            verify_tree_hashes: false,
+            verify_execution: ExecutionVerification::Disabled,
+            chain_id: ChainId::SEPOLIA_TESTNET,
+            websocket_txs: None,
+            notifications: Default::default(),
+            max_reorg_depth: NonZeroU64::MAX,
+            sync_mode: SyncMode::Light,
+            shutdown: tokio::sync::watch::channel(false).1,
+        };
+
+        let (tx, _rx) = tokio::sync::watch::channel(Default::default());
+        consumer(event_rx, context, tx).await.unwrap();
+
+        let tx = connection.transaction().unwrap();
+        for i in 0..num_blocks {
+            let header = tx
+                .block_header(BlockNumber::new_or_panic(i as u64).into())
+                .unwrap()
+                .unwrap();
+            assert_eq!(
+                header.state_commitment,
+                state_commitment_bytes!(b"not verified")
+            );
+            assert_eq!(header.storage_commitment, StorageCommitment::ZERO);
+        }
+        let nonce = tx
+            .contract_nonce(
+                contract_address_bytes!(b"contract"),
+                pathfinder_storage::BlockId::Latest,
+            )
+            .unwrap();
+        assert_eq!(nonce, None);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn block_updates_move_sync_checkpoint() {
+        let storage = StorageBuilder::in_memory().unwrap();
+        let mut connection = storage.connection().unwrap();
+
+        let (event_tx, event_rx) = tokio::sync::mpsc::channel(100);
+
+        let block_data = generate_block_data();
+        let head = block_data
+            .last()
+            .map(|(block, ..)| block.0.block_hash)
+            .unwrap();
+        let num_blocks = block_data.len();
+        for (a, b, c, d, e) in block_data {
+            event_tx
+                .send(SyncEvent::Block(a, b, c, d, e))
+                .await
+                .unwrap();
+        }
+        // Close the event channel which allows the consumer task to exit.
+        drop(event_tx);
+
+        let (tx, _rx) = tokio::sync::watch::channel(Default::default());
+        let context = ConsumerContext {
+            storage,
+            state: Arc::new(SyncState::default()),
+            pending_data: tx,
+            verify_tree_hashes: false,
+            verify_execution: ExecutionVerification::Disabled,
+            chain_id: ChainId::SEPOLIA_TESTNET,
+            websocket_txs: None,
+            notifications: Default::default(),
+            max_reorg_depth: NonZeroU64::MAX,
+            sync_mode: SyncMode::Full,
+            shutdown: tokio::sync::watch::channel(false).1,
+        };
+
+        let (tx, _rx) = tokio::sync::watch::channel(Default::default());
+        consumer(event_rx, context, tx).await.unwrap();
+
+        let tx = connection.transaction().unwrap();
+        assert_eq!(
+            tx.sync_checkpoint().unwrap(),
+            Some((BlockNumber::new_or_panic(num_blocks as u64 - 1), head))
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn consumer_stops_on_shutdown() {
+        let storage = StorageBuilder::in_memory().unwrap();
+        let mut connection = storage.connection().unwrap();
+
+        let (event_tx, event_rx) = tokio::sync::mpsc::channel(100);
+
+        for (a, b, c, d, e) in generate_block_data() {
+            event_tx
+                .send(SyncEvent::Block(a, b, c, d, e))
+                .await
+                .unwrap();
+        }
+
+        let (stop, shutdown) = tokio::sync::watch::channel(false);
+        let (tx, _rx) = tokio::sync::watch::channel(Default::default());
+        let context = ConsumerContext {
+            storage,
+            state: Arc::new(SyncState::default()),
+            pending_data: tx,
+            verify_tree_hashes: false,
+            verify_execution: ExecutionVerification::Disabled,
+            chain_id: ChainId::SEPOLIA_TESTNET,
+            websocket_txs: None,
+            notifications: Default::default(),
+            max_reorg_depth: NonZeroU64::MAX,
+            sync_mode: SyncMode::Full,
+            shutdown,
+        };
+        stop.send(true).unwrap();
+
+        // The event channel remains open, so the consumer only exits because
+        // of the shutdown.
+        let (tx, _rx) = tokio::sync::watch::channel(Default::default());
+        consumer(event_rx, context, tx).await.unwrap();
+        drop(event_tx);
+
+        let tx = connection.transaction().unwrap();
+        assert!(!tx.block_exists(BlockNumber::GENESIS.into()).unwrap());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn l1_l2_pointer_follows_backfilled_l1_state() {
+        let storage = StorageBuilder::in_memory().unwrap();
+        let mut connection = storage.connection().unwrap();
+
+        let (event_tx, event_rx) = tokio::sync::mpsc::channel(100);
+
+        let block_data = generate_block_data();
+        let l1_state = pathfinder_ethereum::EthereumStateUpdate {
+            state_root: block_data[1].0 .0.state_commitment,
+            block_number: block_data[1].0 .0.block_number,
+            block_hash: block_data[1].0 .0.block_hash,
+        };
+
+        // The L1 state update arrives before the block it refers to, as it does
+        // when backfilled from L1 during a fresh sync.
+        event_tx.send(SyncEvent::L1Update(l1_state)).await.unwrap();
+        for (a, b, c, d, e) in block_data {
+            event_tx
+                .send(SyncEvent::Block(a, b, c, d, e))
+                .await
+                .unwrap();
+        }
+        // Close the event channel which allows the consumer task to exit.
+        drop(event_tx);
+
+        let (tx, _rx) = tokio::sync::watch::channel(Default::default());
+        let context = ConsumerContext {
+            storage,
+            state: Arc::new(SyncState::default()),
+            pending_data: tx,
+            verify_tree_hashes: false,
+            verify_execution: ExecutionVerification::Disabled,
+            chain_id: ChainId::SEPOLIA_TESTNET,
+            websocket_txs: None,
+            notifications: Default::default(),
+            max_reorg_depth: NonZeroU64::MAX,
+            sync_mode: SyncMode::Full,
+            shutdown: tokio::sync::watch::channel(false).1,
+        };
+
+        let (tx, _rx) = tokio::sync::watch::channel(Default::default());
+        consumer(event_rx, context, tx).await.unwrap();
+
+        let tx = connection.transaction().unwrap();
+        let l1_l2_head = tx.l1_l2_pointer().unwrap();
+        assert_eq!(l1_l2_head, Some(l1_state.block_number));
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn reorg() {
         let storage = StorageBuilder::in_memory().unwrap();
@@ -1476,8 +2128,13 @@ mod tests {
             state: Arc::new(SyncState::default()),
             pending_data: tx,
             verify_tree_hashes: false,
+            verify_execution: ExecutionVerification::Disabled,
+            chain_id: ChainId::SEPOLIA_TESTNET,
             websocket_txs: None,
             notifications: Default::default(),
+            max_reorg_depth: NonZeroU64::MAX,
+            sync_mode: SyncMode::Full,
+            shutdown: tokio::sync::watch::channel(false).1,
         };
 
         let (tx, _rx) = tokio::sync::watch::channel(Default::default());
@@ -1496,6 +2153,82 @@ mod tests {
             .block_exists(BlockNumber::new_or_panic(2).into())
             .unwrap();
         assert!(!block_2_exists);
+
+        let reorgs = tx.reorgs(BlockNumber::GENESIS, BlockNumber::MAX).unwrap();
+        assert_eq!(reorgs.len(), 1);
+        assert_eq!(reorgs[0].old_head_number, BlockNumber::new_or_panic(2));
+        assert_eq!(
+            reorgs[0].common_ancestor,
+            Some((
+                BlockNumber::new_or_panic(1),
+                block_hash_bytes!(b"1 block hash")
+            ))
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn chain_events() {
+        let storage = StorageBuilder::in_memory().unwrap();
+
+        let (event_tx, event_rx) = tokio::sync::mpsc::channel(100);
+
+        for (a, b, c, d, e) in generate_block_data() {
+            event_tx
+                .send(SyncEvent::Block(a, b, c, d, e))
+                .await
+                .unwrap();
+        }
+        event_tx
+            .send(SyncEvent::Reorg(BlockNumber::new_or_panic(2)))
+            .await
+            .unwrap();
+        drop(event_tx);
+
+        let notifications = pathfinder_rpc::Notifications::default();
+        let chain_events = ChainEvents::new(notifications.clone(), storage.clone()).unwrap();
+        let mut events = chain_events.subscribe();
+        let mut head = chain_events.head();
+
+        let (tx, _rx) = tokio::sync::watch::channel(Default::default());
+        let context = ConsumerContext {
+            storage,
+            state: Arc::new(SyncState::default()),
+            pending_data: tx,
+            verify_tree_hashes: false,
+            verify_execution: ExecutionVerification::Disabled,
+            chain_id: ChainId::SEPOLIA_TESTNET,
+            websocket_txs: None,
+            notifications,
+            max_reorg_depth: NonZeroU64::MAX,
+            sync_mode: SyncMode::Full,
+            shutdown: tokio::sync::watch::channel(false).1,
+        };
+
+        let (tx, _rx) = tokio::sync::watch::channel(Default::default());
+        consumer(event_rx, context, tx).await.unwrap();
+
+        let new_head = Some((
+            BlockNumber::new_or_panic(1),
+            block_hash_bytes!(b"1 block hash"),
+        ));
+
+        // Headers and reorgs are sent on separate channels, so only the order of
+        // events of the same kind is guaranteed.
+        let mut headers = Vec::new();
+        let mut reorgs = Vec::new();
+        for _ in 0..4 {
+            match events.recv().await.unwrap() {
+                ChainEvent::NewHead(header) => headers.push(header.number.get()),
+                ChainEvent::Reorg(reorg) => reorgs.push(reorg),
+                ChainEvent::L1Accepted(_) => panic!("Unexpected L1 update"),
+            }
+        }
+        assert_eq!(headers, vec![0, 1, 2]);
+        assert_eq!(reorgs.len(), 1);
+        assert_eq!(reorgs[0].first_block_number, BlockNumber::new_or_panic(2));
+        assert_eq!(reorgs[0].last_block_number, BlockNumber::new_or_panic(2));
+
+        head.wait_for(|head| *head == new_head).await.unwrap();
     }
 
     #[tokio::test(flavor = "multi_thread")]
@@ -1540,8 +2273,13 @@ mod tests {
             state: Arc::new(SyncState::default()),
             pending_data: tx,
             verify_tree_hashes: false,
+            verify_execution: ExecutionVerification::Disabled,
+            chain_id: ChainId::SEPOLIA_TESTNET,
             websocket_txs: None,
             notifications: Default::default(),
+            max_reorg_depth: NonZeroU64::MAX,
+            sync_mode: SyncMode::Full,
+            shutdown: tokio::sync::watch::channel(false).1,
         };
 
         let (tx, _rx) = tokio::sync::watch::channel(Default::default());
@@ -1562,6 +2300,77 @@ mod tests {
         assert!(block_2_exists);
     }
 
+    /// Syncs blocks 0 to 2 followed by a reorg of blocks 1 and 2, i.e. a reorg
+    /// depth of 2.
+    async fn reorg_with_max_depth(max_reorg_depth: u64) -> (anyhow::Result<()>, Storage) {
+        let storage = StorageBuilder::in_memory().unwrap();
+
+        let (event_tx, event_rx) = tokio::sync::mpsc::channel(100);
+
+        for (a, b, c, d, e) in generate_block_data() {
+            event_tx
+                .send(SyncEvent::Block(a, b, c, d, e))
+                .await
+                .unwrap();
+        }
+        event_tx
+            .send(SyncEvent::Reorg(BlockNumber::new_or_panic(1)))
+            .await
+            .unwrap();
+        // Close the event channel which allows the consumer task to exit.
+        drop(event_tx);
+
+        let (tx, _rx) = tokio::sync::watch::channel(Default::default());
+        let context = ConsumerContext {
+            storage: storage.clone(),
+            state: Arc::new(SyncState::default()),
+            pending_data: tx,
+            verify_tree_hashes: false,
+            verify_execution: ExecutionVerification::Disabled,
+            chain_id: ChainId::SEPOLIA_TESTNET,
+            websocket_txs: None,
+            notifications: Default::default(),
+            max_reorg_depth: NonZeroU64::new(max_reorg_depth).unwrap(),
+            sync_mode: SyncMode::Full,
+            shutdown: tokio::sync::watch::channel(false).1,
+        };
+
+        let (tx, _rx) = tokio::sync::watch::channel(Default::default());
+        let result = consumer(event_rx, context, tx).await;
+
+        (result, storage)
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn reorg_at_max_depth() {
+        let (result, storage) = reorg_with_max_depth(2).await;
+        result.unwrap();
+
+        let mut connection = storage.connection().unwrap();
+        let tx = connection.transaction().unwrap();
+        let latest = tx.block_id(pathfinder_storage::BlockId::Latest).unwrap();
+        assert_eq!(latest.map(|(number, _)| number), Some(BlockNumber::GENESIS));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn reorg_past_max_depth_halts() {
+        let (result, storage) = reorg_with_max_depth(1).await;
+        let error = result.unwrap_err();
+        assert!(
+            format!("{error:#}").contains("exceeds the maximum reorg depth of 1"),
+            "{error:#}"
+        );
+
+        // No blocks were rolled back.
+        let mut connection = storage.connection().unwrap();
+        let tx = connection.transaction().unwrap();
+        let latest = tx.block_id(pathfinder_storage::BlockId::Latest).unwrap();
+        assert_eq!(
+            latest.map(|(number, _)| number),
+            Some(BlockNumber::new_or_panic(2))
+        );
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn reorg_to_genesis() {
         let storage = StorageBuilder::in_memory().unwrap();
@@ -1589,8 +2398,13 @@ mod tests {
             state: Arc::new(SyncState::default()),
             pending_data: tx,
             verify_tree_hashes: false,
+            verify_execution: ExecutionVerification::Disabled,
+            chain_id: ChainId::SEPOLIA_TESTNET,
             websocket_txs: None,
             notifications: Default::default(),
+            max_reorg_depth: NonZeroU64::MAX,
+            sync_mode: SyncMode::Full,
+            shutdown: tokio::sync::watch::channel(false).1,
         };
 
         let (tx, _rx) = tokio::sync::watch::channel(Default::default());
@@ -1627,8 +2441,13 @@ mod tests {
             state: Arc::new(SyncState::default()),
             pending_data: tx,
             verify_tree_hashes: false,
+            verify_execution: ExecutionVerification::Disabled,
+            chain_id: ChainId::SEPOLIA_TESTNET,
             websocket_txs: None,
             notifications: Default::default(),
+            max_reorg_depth: NonZeroU64::MAX,
+            sync_mode: SyncMode::Full,
+            shutdown: tokio::sync::watch::channel(false).1,
         };
 
         let (tx, _rx) = tokio::sync::watch::channel(Default::default());
@@ -1668,8 +2487,13 @@ mod tests {
             state: Arc::new(SyncState::default()),
             pending_data: tx,
             verify_tree_hashes: false,
+            verify_execution: ExecutionVerification::Disabled,
+            chain_id: ChainId::SEPOLIA_TESTNET,
             websocket_txs: None,
             notifications: Default::default(),
+            max_reorg_depth: NonZeroU64::MAX,
+            sync_mode: SyncMode::Full,
+            shutdown: tokio::sync::watch::channel(false).1,
         };
 
         let (tx, _rx) = tokio::sync::watch::channel(Default::default());
@@ -1712,11 +2536,191 @@ mod tests {
             state: Arc::new(SyncState::default()),
             pending_data: tx,
             verify_tree_hashes: false,
+            verify_execution: ExecutionVerification::Disabled,
+            chain_id: ChainId::SEPOLIA_TESTNET,
             websocket_txs: None,
             notifications: Default::default(),
+            max_reorg_depth: NonZeroU64::MAX,
+            sync_mode: SyncMode::Full,
+            shutdown: tokio::sync::watch::channel(false).1,
         };
 
         let (tx, _rx) = tokio::sync::watch::channel(Default::default());
         consumer(event_rx, context, tx).await.unwrap();
     }
+
+    mod update_starknet_state {
+        use pathfinder_common::{ClassCommitment, ContractAddress, StorageCommitment};
+
+        use super::*;
+        use crate::state::sync::{update_starknet_state, StarknetStateUpdate};
+
+        fn apply(
+            storage: &Storage,
+            state_update: &StateUpdate,
+            block_hash: BlockHash,
+        ) -> anyhow::Result<(StorageCommitment, ClassCommitment)> {
+            let mut connection = storage.connection().unwrap();
+            let tx = connection.transaction().unwrap();
+            let result = update_starknet_state(
+                &tx,
+                StarknetStateUpdate {
+                    contract_updates: &state_update.contract_updates,
+                    system_contract_updates: &state_update.system_contract_updates,
+                    declared_sierra_classes: &state_update.declared_sierra_classes,
+                },
+                false,
+                BlockNumber::GENESIS,
+                block_hash,
+                storage.clone(),
+            )?;
+            tx.commit().unwrap();
+            Ok(result)
+        }
+
+        /// Applies the genesis state update and commits the matching header.
+        fn setup() -> (Storage, StateUpdate, BlockHeader) {
+            let storage = StorageBuilder::in_memory().unwrap();
+            let state_update = StateUpdate::default()
+                .with_deployed_contract(
+                    contract_address_bytes!(b"contract"),
+                    class_hash_bytes!(b"class"),
+                )
+                .with_storage_update(
+                    contract_address_bytes!(b"contract"),
+                    storage_address_bytes!(b"key"),
+                    storage_value_bytes!(b"value"),
+                )
+                .with_declared_sierra_class(
+                    sierra_hash_bytes!(b"sierra"),
+                    casm_hash_bytes!(b"casm"),
+                );
+
+            let block_hash = block_hash_bytes!(b"genesis");
+            let (storage_commitment, class_commitment) =
+                apply(&storage, &state_update, block_hash).unwrap();
+
+            let header = BlockHeader::builder()
+                .storage_commitment(storage_commitment)
+                .class_commitment(class_commitment)
+                .finalize_with_hash(block_hash);
+            let mut connection = storage.connection().unwrap();
+            let tx = connection.transaction().unwrap();
+            tx.insert_block_header(&header).unwrap();
+            tx.commit().unwrap();
+
+            (storage, state_update, header)
+        }
+
+        fn root_indices(storage: &Storage) -> (Option<u64>, Option<u64>) {
+            let mut connection = storage.connection().unwrap();
+            let tx = connection.transaction().unwrap();
+            (
+                tx.storage_root_index(BlockNumber::GENESIS).unwrap(),
+                tx.class_root_index(BlockNumber::GENESIS).unwrap(),
+            )
+        }
+
+        #[test]
+        fn all_contract_and_class_updates_are_persisted() {
+            let storage = StorageBuilder::in_memory().unwrap();
+            let contracts = (0..10u8)
+                .map(|i| ContractAddress::new_or_panic(Felt::from_u64(0x100 + u64::from(i))))
+                .collect::<Vec<_>>();
+            let state_update = contracts
+                .iter()
+                .fold(StateUpdate::default(), |state_update, contract| {
+                    state_update
+                        .with_deployed_contract(*contract, class_hash_bytes!(b"class"))
+                        .with_storage_update(
+                            *contract,
+                            storage_address_bytes!(b"key"),
+                            storage_value_bytes!(b"value"),
+                        )
+                })
+                .with_system_storage_update(
+                    ContractAddress::ONE,
+                    storage_address_bytes!(b"key"),
+                    storage_value_bytes!(b"value"),
+                )
+                .with_declared_sierra_class(
+                    sierra_hash_bytes!(b"sierra"),
+                    casm_hash_bytes!(b"casm"),
+                );
+
+            let block_hash = block_hash_bytes!(b"genesis");
+            let (storage_commitment, class_commitment) =
+                apply(&storage, &state_update, block_hash).unwrap();
+
+            let header = BlockHeader::builder()
+                .storage_commitment(storage_commitment)
+                .class_commitment(class_commitment)
+                .state_commitment(StateCommitment::calculate(
+                    storage_commitment,
+                    class_commitment,
+                ))
+                .finalize_with_hash(block_hash);
+            let mut connection = storage.connection().unwrap();
+            let tx = connection.transaction().unwrap();
+            tx.insert_block_header(&header).unwrap();
+            // Maps the declared class to its CASM hash, needed to look up its
+            // class trie leaf.
+            tx.insert_state_update(BlockNumber::GENESIS, &state_update).unwrap();
+
+            for contract in contracts.iter().chain([&ContractAddress::ONE]) {
+                assert!(
+                    tx.contract_root(BlockNumber::GENESIS, *contract)
+                        .unwrap()
+                        .is_some(),
+                    "{contract}"
+                );
+            }
+            assert!(tx
+                .class_commitment_leaf(BlockNumber::GENESIS, &casm_hash_bytes!(b"casm"))
+                .unwrap()
+                .is_some());
+            tx.commit().unwrap();
+
+            // The commitments match the ones derived from the persisted trie roots.
+            let mismatch = pathfinder_storage::verify_state_commitments(
+                &storage,
+                BlockNumber::GENESIS,
+                BlockNumber::GENESIS,
+            )
+            .unwrap();
+            assert_eq!(mismatch, None);
+        }
+
+        #[test]
+        fn reapplying_identical_block_is_a_noop() {
+            let (storage, state_update, header) = setup();
+            let roots = root_indices(&storage);
+
+            let result = apply(&storage, &state_update, header.hash).unwrap();
+
+            assert_eq!(result, (header.storage_commitment, header.class_commitment));
+            // No new trie nodes were persisted.
+            assert_eq!(root_indices(&storage), roots);
+        }
+
+        #[test]
+        fn reapplying_conflicting_block_errors() {
+            let (storage, state_update, header) = setup();
+            let roots = root_indices(&storage);
+
+            let error = apply(&storage, &state_update, block_hash_bytes!(b"other")).unwrap_err();
+
+            assert!(
+                error.to_string().contains("has already been applied"),
+                "{error}"
+            );
+            assert_eq!(root_indices(&storage), roots);
+            let mut connection = storage.connection().unwrap();
+            let tx = connection.transaction().unwrap();
+            assert_eq!(
+                tx.block_hash(BlockNumber::GENESIS.into()).unwrap(),
+                Some(header.hash)
+            );
+        }
+    }
 }
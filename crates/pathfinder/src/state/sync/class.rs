@@ -1,5 +1,6 @@
 use anyhow::Context;
 use pathfinder_common::{ClassHash, SierraHash};
+use pathfinder_storage::Storage;
 use starknet_gateway_client::GatewayApi;
 
 pub enum DownloadedClass {
@@ -14,8 +15,23 @@ pub enum DownloadedClass {
     },
 }
 
+/// Downloads the class definition for `class_hash` and verifies it against the
+/// declared hash before it is handed on for storage.
+///
+/// Definitions larger than
+/// [MAX_CLASS_DEFINITION_SIZE](starknet_gateway_client::MAX_CLASS_DEFINITION_SIZE)
+/// are rejected while downloading. The definition is held in memory until its
+/// hash has been verified, so nothing is written for a truncated or mismatching
+/// download. A Sierra hash mismatch fails the download, which in turn rejects
+/// the block so that it gets retried.
+///
+/// Sierra classes compiled by sync are added to the compiled class cache, and
+/// taken from it instead of being compiled again. This happens as soon as a
+/// class is declared in the pending block, so retried blocks, reorgs and the
+/// RPC API can reuse the compilation.
 pub async fn download_class<SequencerClient: GatewayApi>(
     sequencer: &SequencerClient,
+    storage: Storage,
     class_hash: ClassHash,
     fetch_casm_from_fgw: bool,
 ) -> Result<DownloadedClass, anyhow::Error> {
@@ -71,6 +87,11 @@ pub async fn download_class<SequencerClient: GatewayApi>(
                         .with_context(|| format!("Downloading CASM {}", class_hash.0))?
                         .to_vec(),
                 )
+            } else if let Some(casm_definition) =
+                cached_compiled_class(storage.clone(), SierraHash(hash.0)).await?
+            {
+                tracing::trace!(class_hash=%hash, "Compiled class cache hit");
+                (definition, casm_definition)
             } else {
                 let (send, recv) = tokio::sync::oneshot::channel();
                 rayon::spawn(move || {
@@ -84,7 +105,10 @@ pub async fn download_class<SequencerClient: GatewayApi>(
                     recv.await.expect("Panic on rayon thread");
 
                 let casm_definition = match casm_definition {
-                    Ok(casm_definition) => casm_definition,
+                    Ok(casm_definition) => {
+                        cache_compiled_class(storage, SierraHash(hash.0), &casm_definition).await;
+                        casm_definition
+                    }
                     Err(error) => {
                         tracing::info!(class_hash=%hash, ?error, "CASM compilation failed, falling back to fetching from gateway");
                         sequencer
@@ -105,3 +129,39 @@ pub async fn download_class<SequencerClient: GatewayApi>(
         }
     }
 }
+
+async fn cached_compiled_class(
+    storage: Storage,
+    sierra_hash: SierraHash,
+) -> anyhow::Result<Option<Vec<u8>>> {
+    tokio::task::spawn_blocking(move || {
+        let mut db = storage
+            .connection()
+            .context("Creating database connection")?;
+        let tx = db.transaction().context("Creating database transaction")?;
+        tx.compiled_class(sierra_hash)
+            .context("Querying compiled class cache")
+    })
+    .await
+    .context("Joining database task")?
+}
+
+/// Failures are only logged, since the class is compiled again if needed.
+async fn cache_compiled_class(storage: Storage, sierra_hash: SierraHash, casm_definition: &[u8]) {
+    let casm_definition = casm_definition.to_vec();
+    let result = tokio::task::spawn_blocking(move || {
+        let mut db = storage
+            .connection()
+            .context("Creating database connection")?;
+        let tx = db.transaction().context("Creating database transaction")?;
+        tx.insert_compiled_class(sierra_hash, &casm_definition)
+            .context("Inserting compiled class")?;
+        tx.commit().context("Committing database transaction")
+    })
+    .await
+    .context("Joining database task");
+
+    if let Err(error) = result.and_then(|result| result) {
+        tracing::debug!(%sierra_hash, ?error, "Failed to cache compiled class");
+    }
+}
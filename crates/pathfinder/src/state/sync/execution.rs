@@ -0,0 +1,223 @@
+//! Re-execution of synced blocks, enabled using `--sync.verify-execution`.
+//!
+//! Hash and commitment checks only prove that the gateway's data is
+//! self-consistent. Re-executing every block with the local executor
+//! additionally verifies that the receipts, events and state diff are the
+//! result of actually executing the block's transactions.
+
+use std::collections::HashSet;
+use std::fmt::{Debug, Display};
+use std::time::Instant;
+
+use anyhow::Context;
+use pathfinder_common::{BlockHeader, ChainId, StateUpdate, TransactionIndex};
+use pathfinder_executor::{ExecutionState, TransactionExecutionError};
+use pathfinder_rpc::{
+    apply_executed_state_diff,
+    compose_executor_transaction,
+    executed_receipt,
+    VERSIONS_LOWER_THAN_THIS_SHOULD_FALL_BACK_TO_FETCHING_TRACE_FROM_GATEWAY,
+};
+use pathfinder_storage::Transaction;
+use starknet_gateway_types::reply::Block;
+
+/// What to do when re-executing a synced block disagrees with the gateway.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ExecutionVerification {
+    /// Blocks are not re-executed.
+    #[default]
+    Disabled,
+    /// Mismatches are logged and counted, and the block is stored regardless.
+    Warn,
+    /// Sync halts instead of storing a mismatching block.
+    Halt,
+}
+
+/// Re-executes `block` on top of its parent state and compares the resulting
+/// receipts, events and state diff with the ones provided by the gateway.
+///
+/// Blocks older than Starknet 0.13.1.1 cannot be re-executed and are accepted
+/// as is.
+pub(super) fn verify(
+    db: &Transaction<'_>,
+    mode: ExecutionVerification,
+    chain_id: ChainId,
+    header: &BlockHeader,
+    block: &Block,
+    state_update: &StateUpdate,
+) -> anyhow::Result<()> {
+    if mode == ExecutionVerification::Disabled
+        || header.starknet_version
+            < VERSIONS_LOWER_THAN_THIS_SHOULD_FALL_BACK_TO_FETCHING_TRACE_FROM_GATEWAY
+    {
+        return Ok(());
+    }
+
+    let started = Instant::now();
+    let mismatches = mismatches(db, chain_id, header, block, state_update)
+        .with_context(|| format!("Re-executing block {}", header.number))?;
+    metrics::histogram!("sync_stage_duration_seconds", started.elapsed(), "stage" => "execution_verification");
+
+    if mismatches.is_empty() {
+        return Ok(());
+    }
+
+    metrics::increment_counter!("sync_execution_mismatches_total");
+    for mismatch in &mismatches {
+        tracing::warn!(
+            block_number=%header.number, %mismatch,
+            "Re-executed block differs from the gateway"
+        );
+    }
+
+    if mode == ExecutionVerification::Halt {
+        tracing::error!(
+            block_number=%header.number,
+            "Re-executed block differs from the gateway, halting sync. Operator intervention is \
+             required: verify the gateway, or restart with `--sync.verify-execution warn` to \
+             accept the gateway's data."
+        );
+        anyhow::bail!(
+            "Re-executing block {} differs from the gateway in {} places",
+            header.number,
+            mismatches.len()
+        );
+    }
+
+    Ok(())
+}
+
+#[derive(Default)]
+struct Mismatches(Vec<String>);
+
+impl Mismatches {
+    fn check<T: PartialEq + Debug + ?Sized>(
+        &mut self,
+        field: impl Display,
+        gateway: &T,
+        executed: &T,
+    ) {
+        if gateway != executed {
+            self.0.push(format!(
+                "{field}: gateway {gateway:?}, executed {executed:?}"
+            ));
+        }
+    }
+}
+
+fn mismatches(
+    db: &Transaction<'_>,
+    chain_id: ChainId,
+    header: &BlockHeader,
+    block: &Block,
+    state_update: &StateUpdate,
+) -> anyhow::Result<Vec<String>> {
+    let transactions = block
+        .transactions
+        .iter()
+        .map(|transaction| compose_executor_transaction(transaction, db))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let state = ExecutionState::trace(db, chain_id, header.clone(), None, None);
+    let simulations = match pathfinder_executor::simulate(state, transactions, false, false) {
+        Ok(simulations) => simulations,
+        Err(TransactionExecutionError::Internal(e)) => return Err(e),
+        Err(other) => return Ok(vec![format!("re-execution failed: {other}")]),
+    };
+
+    let mut mismatches = Mismatches::default();
+    let mut executed_state = StateUpdate::default();
+    for (index, ((transaction, (receipt, events)), simulation)) in block
+        .transactions
+        .iter()
+        .zip(&block.transaction_receipts)
+        .zip(&simulations)
+        .enumerate()
+    {
+        let transaction_index = TransactionIndex::new_or_panic(index as u64);
+        let (executed, executed_events) =
+            executed_receipt(simulation, transaction.hash, transaction_index);
+        let hash = transaction.hash;
+
+        mismatches.check(
+            format_args!("transaction {hash} reverted"),
+            &receipt.is_reverted(),
+            &executed.is_reverted(),
+        );
+        mismatches.check(
+            format_args!("transaction {hash} actual_fee"),
+            &receipt.actual_fee,
+            &executed.actual_fee,
+        );
+        mismatches.check(
+            format_args!("transaction {hash} events"),
+            events,
+            &executed_events,
+        );
+        mismatches.check(
+            format_args!("transaction {hash} messages"),
+            &receipt.l2_to_l1_messages,
+            &executed.l2_to_l1_messages,
+        );
+
+        executed_state = apply_executed_state_diff(&executed_state, &simulation.trace);
+    }
+
+    let contracts = state_update
+        .contract_updates
+        .keys()
+        .chain(executed_state.contract_updates.keys())
+        .collect::<HashSet<_>>();
+    for contract in contracts {
+        mismatches.check(
+            format_args!("state diff of contract {contract}"),
+            &state_update.contract_updates.get(contract),
+            &executed_state.contract_updates.get(contract),
+        );
+    }
+    let system_contracts = state_update
+        .system_contract_updates
+        .keys()
+        .chain(executed_state.system_contract_updates.keys())
+        .collect::<HashSet<_>>();
+    for contract in system_contracts {
+        mismatches.check(
+            format_args!("state diff of system contract {contract}"),
+            &state_update.system_contract_updates.get(contract),
+            &executed_state.system_contract_updates.get(contract),
+        );
+    }
+    mismatches.check(
+        "declared Cairo classes",
+        &state_update.declared_cairo_classes,
+        &executed_state.declared_cairo_classes,
+    );
+    mismatches.check(
+        "declared Sierra classes",
+        &state_update.declared_sierra_classes,
+        &executed_state.declared_sierra_classes,
+    );
+
+    Ok(mismatches.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_differences_are_recorded() {
+        let mut mismatches = Mismatches::default();
+        mismatches.check("same", &1, &1);
+        mismatches.check(format_args!("transaction {} reverted", 2), &false, &true);
+        mismatches.check("declared Cairo classes", &[1, 2][..], &[1][..]);
+
+        assert_eq!(
+            mismatches.0,
+            vec![
+                "transaction 2 reverted: gateway false, executed true".to_owned(),
+                "declared Cairo classes: gateway [1, 2], executed [1]".to_owned(),
+            ]
+        );
+    }
+}
@@ -1,7 +1,9 @@
 use std::time::Duration;
 
-use pathfinder_common::Chain;
+use anyhow::Context;
+use pathfinder_common::{BlockNumber, Chain};
 use pathfinder_ethereum::{EthereumApi, EthereumEvent};
+use pathfinder_storage::Storage;
 use primitive_types::H160;
 use tokio::sync::mpsc;
 
@@ -16,11 +18,13 @@ pub struct L1SyncContext<EthereumClient> {
     pub core_address: H160,
     /// The interval at which to poll for updates on finalized blocks
     pub poll_interval: Duration,
+    pub storage: Storage,
 }
 
 /// Syncs L1 state update logs. Emits [Ethereum state
 /// update](pathfinder_ethereum::EthereumStateUpdate) which should be handled to
-/// update storage and respond to queries.
+/// update storage and respond to queries, as well as the logs of L1 to L2
+/// messages being sent and cancelled.
 pub async fn sync<T>(
     tx_event: mpsc::Sender<SyncEvent>,
     context: L1SyncContext<T>,
@@ -33,8 +37,62 @@ where
         chain: _,
         core_address,
         poll_interval,
+        storage,
     } = context;
 
+    // Backfill the state updates since the latest one we know of, so that
+    // historical blocks can be verified against L1 and not just the most recent
+    // ones. The target is persisted until the backfill has completed so that an
+    // interrupted backfill is resumed on the next start instead of stopping at
+    // the newest update it already stored.
+    let stop_at = tokio::task::spawn_blocking(move || {
+        let mut db = storage
+            .connection()
+            .context("Creating database connection")?;
+        let tx = db.transaction().context("Creating database transaction")?;
+        if let Some(target) = tx
+            .l1_backfill_target()
+            .context("Querying L1 backfill target")?
+        {
+            return anyhow::Ok(target);
+        }
+
+        let target = tx
+            .latest_l1_state()
+            .context("Querying latest L1 state")?
+            .map(|state_update| state_update.block_number)
+            .unwrap_or(BlockNumber::GENESIS);
+        tx.update_l1_backfill_target(Some(target))
+            .context("Updating L1 backfill target")?;
+        tx.commit().context("Committing database transaction")?;
+        Ok(target)
+    })
+    .await
+    .context("Joining database task")??;
+
+    // Each chunk is forwarded as soon as it is fetched so that nothing is lost
+    // if the backfill is interrupted.
+    let backfill_tx = tx_event.clone();
+    let backfill = ethereum
+        .backfill_state_updates(&core_address, stop_at, move |state_updates| {
+            let tx_event = backfill_tx.clone();
+            async move {
+                for state_update in state_updates {
+                    let _ = tx_event.send(SyncEvent::L1Update(state_update)).await;
+                }
+            }
+        })
+        .await;
+    match backfill {
+        Ok(()) => {
+            tracing::info!(%stop_at, "Backfilled L1 state updates");
+            let _ = tx_event.send(SyncEvent::L1BackfillCompleted).await;
+        }
+        Err(error) => {
+            tracing::warn!(%error, "Backfilling L1 state updates failed");
+        }
+    }
+
     // Fetch the current Starknet state from Ethereum
     let state_update = ethereum.get_starknet_state(&core_address).await?;
     let _ = tx_event.send(SyncEvent::L1Update(state_update)).await;
@@ -52,6 +110,9 @@ where
                     EthereumEvent::MessageLog(log) => {
                         let _ = tx_event.send(SyncEvent::L1ToL2Message(log)).await;
                     }
+                    EthereumEvent::MessageCancelled(log) => {
+                        let _ = tx_event.send(SyncEvent::L1ToL2MessageCancelled(log)).await;
+                    }
                 }
             }
         })
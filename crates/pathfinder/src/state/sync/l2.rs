@@ -1,7 +1,10 @@
-use std::collections::{BTreeMap, HashMap, HashSet};
+use std::collections::{HashMap, HashSet};
+use std::num::NonZeroUsize;
+use std::ops::RangeInclusive;
 use std::time::Duration;
 
 use anyhow::{anyhow, Context};
+use futures::stream::BoxStream;
 use futures::{StreamExt, TryStreamExt};
 use pathfinder_common::state_update::{ContractClassUpdate, StateUpdateData};
 use pathfinder_common::{
@@ -39,6 +42,10 @@ use crate::state::block_hash::{
 use crate::state::sync::class::{download_class, DownloadedClass};
 use crate::state::sync::SyncEvent;
 
+pub mod failover;
+#[cfg(feature = "p2p")]
+pub mod source;
+
 #[derive(Default, Debug, Clone, Copy)]
 pub struct Timings {
     pub block_download: Duration,
@@ -107,7 +114,7 @@ pub struct L2SyncContext<GatewayClient> {
     pub block_validation_mode: BlockValidationMode,
     pub storage: Storage,
     pub sequencer_public_key: PublicKey,
-    pub fetch_concurrency: std::num::NonZeroUsize,
+    pub pipeline: PipelineConfig,
     pub fetch_casm_from_fgw: bool,
 }
 
@@ -139,7 +146,7 @@ where
         block_validation_mode,
         storage,
         sequencer_public_key,
-        fetch_concurrency: _,
+        pipeline: _,
         fetch_casm_from_fgw,
     } = context;
 
@@ -413,8 +420,9 @@ pub async fn download_new_classes(
         return Ok(vec![]);
     }
 
+    let db_storage = storage.clone();
     let require_downloading = tokio::task::spawn_blocking(move || {
-        let mut db_conn = storage
+        let mut db_conn = db_storage
             .connection()
             .context("Creating database connection")?;
         let tx = db_conn
@@ -438,8 +446,9 @@ pub async fn download_new_classes(
     .context("Querying database for missing classes")?;
 
     let futures = require_downloading.into_iter().map(|class_hash| {
+        let storage = storage.clone();
         async move {
-            download_class(sequencer, class_hash, fetch_casm_from_fgw)
+            download_class(sequencer, storage, class_hash, fetch_casm_from_fgw)
                 .await
                 .with_context(|| format!("Downloading class {}", class_hash.0))
         }
@@ -611,11 +620,11 @@ where
         block_validation_mode,
         storage,
         sequencer_public_key,
-        fetch_concurrency,
+        pipeline,
         fetch_casm_from_fgw,
     } = context;
 
-    let mut start = match head {
+    let start = match head {
         Some(head) => head.0.get() + 1,
         None => BlockNumber::GENESIS.get(),
     };
@@ -626,18 +635,207 @@ where
 
     tracing::trace!(%start, %end, "Catching up to the latest block");
 
-    let mut futures = (start..=end)
-        .map(|block_number| {
-            let block_number = BlockNumber::new_or_panic(block_number);
+    let chain_label = super::chain_label(chain_id);
+    let queue_capacity = pipeline.queue_capacity.get();
+    let (tx_downloaded, rx_downloaded) = mpsc::channel(queue_capacity);
+    let (tx_verified, rx_verified) = mpsc::channel(queue_capacity);
+    let (tx_complete, mut rx_complete) = mpsc::channel(queue_capacity);
+
+    let download = download_blocks(
+        sequencer.clone(),
+        start..=end,
+        pipeline.download_concurrency,
+        tx_downloaded,
+        chain_label,
+    );
+    let verify = verify_blocks(
+        rx_downloaded,
+        VerificationContext {
+            chain,
+            chain_id,
+            block_validation_mode,
+            sequencer_public_key,
+        },
+        pipeline.verify_concurrency,
+        tx_verified,
+    );
+    let class_fetch = fetch_classes(
+        rx_verified,
+        sequencer,
+        storage,
+        fetch_casm_from_fgw,
+        pipeline.class_fetch_concurrency,
+        tx_complete,
+        chain_label,
+    );
+    // Takes ownership of the queue, so that the previous stages stop once this
+    // one fails.
+    let commit = async move {
+        while let Some((verified, downloaded_classes)) = rx_complete.recv().await {
+            report_queue_depth(chain_label, "commit", rx_complete.len());
+            let VerifiedBlock {
+                downloaded:
+                    DownloadedBlock {
+                        block,
+                        state_update,
+                        signature,
+                        timings,
+                    },
+                commitments,
+                state_diff_commitment,
+            } = verified;
+
+            *head = Some((
+                block.block_number,
+                block.block_hash,
+                state_update.state_commitment,
+            ));
+            blocks.push(
+                block.block_number,
+                block.block_hash,
+                state_update.state_commitment,
+            );
+
+            emit_events_for_downloaded_classes(
+                &tx_event,
+                downloaded_classes,
+                &state_update.declared_sierra_classes,
+            )
+            .await?;
+
+            tx_event
+                .send(SyncEvent::Block(
+                    (Box::new(block), commitments),
+                    Box::new(state_update),
+                    Box::new(signature.signature()),
+                    Box::new(state_diff_commitment),
+                    timings,
+                ))
+                .await
+                .context("Event channel closed")?;
+        }
+
+        anyhow::Ok(())
+    };
+
+    // A failing stage closes its output queue, after which the following stages
+    // finish processing the blocks queued so far.
+    let (download, verify, class_fetch, commit) =
+        tokio::join!(download, verify, class_fetch, commit);
+    // The queues have been dropped, including any blocks left in them.
+    for stage in ["verify", "class_fetch", "commit"] {
+        report_queue_depth(chain_label, stage, 0);
+    }
+    commit?;
 
-            let _span =
-                tracing::debug_span!("download_and_verify_block_data", %block_number).entered();
-            tracing::trace!("Downloading block");
+    if let Err(error) = download.and(verify).and(class_fetch) {
+        // `head` has been updated to the last synced block so our "tracking" sync
+        // will just continue from there.
+        tracing::info!("Error during bulk syncing blocks, falling back to normal sync: {error}");
+    }
+
+    Ok(())
+}
+
+/// Tunes the bulk sync pipeline. Its stages download blocks, verify them and
+/// download their new classes before the blocks are committed in order.
+#[derive(Clone, Copy, Debug)]
+pub struct PipelineConfig {
+    /// The number of blocks downloaded concurrently.
+    pub download_concurrency: NonZeroUsize,
+    /// The number of blocks verified concurrently.
+    pub verify_concurrency: NonZeroUsize,
+    /// The number of blocks whose new classes are downloaded concurrently.
+    pub class_fetch_concurrency: NonZeroUsize,
+    /// The number of blocks queued in front of each stage. Stages pause while
+    /// the queue of the next stage is full.
+    pub queue_capacity: NonZeroUsize,
+}
+
+impl Default for PipelineConfig {
+    fn default() -> Self {
+        Self {
+            download_concurrency: NonZeroUsize::new(8).unwrap(),
+            verify_concurrency: NonZeroUsize::new(8).unwrap(),
+            class_fetch_concurrency: NonZeroUsize::new(8).unwrap(),
+            queue_capacity: NonZeroUsize::new(256).unwrap(),
+        }
+    }
+}
+
+struct DownloadedBlock {
+    block: Block,
+    state_update: StateUpdate,
+    signature: BlockSignature,
+    timings: Timings,
+}
+
+struct VerifiedBlock {
+    downloaded: DownloadedBlock,
+    commitments: (TransactionCommitment, EventCommitment, ReceiptCommitment),
+    state_diff_commitment: StateDiffCommitment,
+}
+
+#[derive(Clone, Copy)]
+struct VerificationContext {
+    chain: Chain,
+    chain_id: ChainId,
+    block_validation_mode: BlockValidationMode,
+    sequencer_public_key: PublicKey,
+}
+
+/// Queues a block for the next stage of the pipeline and reports the queue's
+/// depth. Returns false if the next stage has stopped.
+async fn enqueue<T>(
+    queue: &mpsc::Sender<T>,
+    chain: &'static str,
+    stage: &'static str,
+    block: T,
+) -> bool {
+    if queue.send(block).await.is_err() {
+        return false;
+    }
+
+    report_queue_depth(chain, stage, queue.max_capacity() - queue.capacity());
+    true
+}
 
+/// Takes blocks off a queue of the pipeline and reports the queue's depth.
+fn dequeue<T: Send + 'static>(
+    queue: mpsc::Receiver<T>,
+    chain: &'static str,
+    stage: &'static str,
+) -> BoxStream<'static, T> {
+    futures::stream::unfold(queue, move |mut queue| async move {
+        let block = queue.recv().await;
+        report_queue_depth(chain, stage, queue.len());
+        block.map(|block| (block, queue))
+    })
+    .boxed()
+}
+
+fn report_queue_depth(chain: &'static str, stage: &'static str, depth: usize) {
+    metrics::gauge!("sync_queue_depth", depth as f64, "chain" => chain, "stage" => stage);
+}
+
+async fn download_blocks<GatewayClient>(
+    sequencer: GatewayClient,
+    block_numbers: RangeInclusive<u64>,
+    concurrency: NonZeroUsize,
+    output: mpsc::Sender<DownloadedBlock>,
+    chain: &'static str,
+) -> anyhow::Result<()>
+where
+    GatewayClient: GatewayApi + Clone + Send + 'static,
+{
+    let mut downloads = futures::stream::iter(block_numbers)
+        .map(|block_number| {
+            let block_number = BlockNumber::new_or_panic(block_number);
             let sequencer = sequencer.clone();
-            let storage = storage.clone();
 
             async move {
+                tracing::trace!("Downloading block");
+
                 let t_block = std::time::Instant::now();
                 let (block, state_update) = sequencer.state_update_with_block(block_number).await?;
                 let t_block = t_block.elapsed();
@@ -646,199 +844,147 @@ where
                 let signature = sequencer.signature(block_number.into()).await?;
                 let t_signature = t_signature.elapsed();
 
-                let span = tracing::Span::current();
-
-                let (tx, rx) = tokio::sync::oneshot::channel();
-
-                rayon::spawn(move || {
-                    let _span = span.entered();
-
-                    let t_verification = std::time::Instant::now();
-
-                    let result = verify_block_and_state_update(
-                        &block,
-                        &state_update,
-                        chain,
-                        chain_id,
-                        block_validation_mode,
-                    )
-                    .and_then(
-                        |(
-                            transaction_commitment,
-                            event_commitment,
-                            receipt_commitment,
-                            state_diff_commitment,
-                        )| {
-                            verify_signature(
-                                block.block_hash,
-                                &signature,
-                                sequencer_public_key,
-                                BlockValidationMode::AllowMismatch,
-                            )
-                            .map_err(|err| err.into())
-                            .map(|_| {
-                                (
-                                    block,
-                                    state_update,
-                                    signature,
-                                    transaction_commitment,
-                                    event_commitment,
-                                    receipt_commitment,
-                                    state_diff_commitment,
-                                )
-                            })
-                        },
-                    );
-
-                    let t_verification = t_verification.elapsed();
-                    tracing::trace!(elapsed=?t_verification, "Block verification done");
-
-                    let _ = tx.send(result);
-                });
-
-                let (
+                Ok::<_, anyhow::Error>(DownloadedBlock {
                     block,
                     state_update,
                     signature,
-                    transaction_commitment,
-                    event_commitment,
-                    receipt_commitment,
-                    state_diff_commitment,
-                ) = rx
-                    .await
-                    .expect("Panic on rayon thread while verifying block")
-                    .context("Verifying block contents")?;
-
-                let t_declare = std::time::Instant::now();
-                let downloaded_classes =
-                    download_new_classes(&state_update, &sequencer, storage, fetch_casm_from_fgw)
-                        .await
-                        .with_context(|| {
-                            format!("Handling newly declared classes for block {block_number:?}")
-                        })?;
-                let t_declare = t_declare.elapsed();
-
-                let timings = Timings {
-                    block_download: t_block,
-                    class_declaration: t_declare,
-                    signature_download: t_signature,
-                };
-
-                Ok::<_, anyhow::Error>((
-                    block,
-                    state_update,
-                    signature,
-                    transaction_commitment,
-                    event_commitment,
-                    receipt_commitment,
-                    state_diff_commitment,
-                    downloaded_classes,
-                    timings,
-                ))
+                    timings: Timings {
+                        block_download: t_block,
+                        class_declaration: Duration::ZERO,
+                        signature_download: t_signature,
+                    },
+                })
             }
-            .in_current_span()
+            .instrument(tracing::debug_span!("download_block", %block_number))
         })
-        .peekable();
-
-    // We want to download blocks in an unordered fashion, but still have a limit on
-    // the size of the cache that is used to then sort the downloaded blocks before
-    // emitting them. (Tries need to be updated in order, hence the sorting.)
-    //
-    // The limit is needed because if we encounter problems downloading a block, at
-    // some point we need to wait for it, otherwise the cache would balloon being
-    // filled with endless newer and newer blocks that we cannot emit and this would
-    // lead to oom.
-    const UNORDERED_CACHE_CAPACITY_FACTOR: usize = 32;
-
-    while futures.peek().is_some() {
-        let futures_chunk = futures
-            .by_ref()
-            .take(fetch_concurrency.get() * UNORDERED_CACHE_CAPACITY_FACTOR);
-
-        let mut stream =
-            futures::stream::iter(futures_chunk).buffer_unordered(fetch_concurrency.get());
-
-        let mut ordered_blocks = BTreeMap::new();
-
-        while let Some(result) = stream.next().await {
-            let Ok(ok) = result else {
-                // We've hit an error, so we stop the loop and return. `head` has been updated
-                // to the last synced block so our "tracking" sync will just
-                // continue from there.
-                tracing::info!(
-                    "Error during bulk syncing blocks, falling back to normal sync: {}",
-                    result.err().unwrap()
-                );
-                return Ok(());
-            };
+        .buffered(concurrency.get());
 
-            ordered_blocks.insert(ok.0.block_number.get(), ok);
+    while let Some(block) = downloads.next().await {
+        if !enqueue(&output, chain, "verify", block?).await {
+            break;
+        }
+    }
+
+    Ok(())
+}
 
-            let keys = ordered_blocks.keys().copied().collect::<Vec<_>>();
+async fn verify_blocks(
+    input: mpsc::Receiver<DownloadedBlock>,
+    context: VerificationContext,
+    concurrency: NonZeroUsize,
+    output: mpsc::Sender<VerifiedBlock>,
+) -> anyhow::Result<()> {
+    let chain = super::chain_label(context.chain_id);
+    let mut verifications = dequeue(input, chain, "verify")
+        .map(|downloaded| {
+            let span = tracing::debug_span!(
+                "verify_block",
+                block_number = %downloaded.block.block_number
+            );
+            let (tx, rx) = tokio::sync::oneshot::channel();
 
-            tracing::trace!(start, len = ordered_blocks.len(), ?keys, "Cached blocks");
+            rayon::spawn(move || {
+                let _span = span.entered();
 
-            // Find number of elems till the first gap that we can emit right now
-            let num_to_emit = ordered_blocks
-                .keys()
-                .take_while(|block_number| {
-                    if **block_number == start {
-                        start += 1;
-                        true
-                    } else {
-                        false
-                    }
-                })
-                .count();
+                let t_verification = std::time::Instant::now();
 
-            for _ in 0..num_to_emit {
-                let (
-                    _,
-                    (
-                        block,
-                        state_update,
-                        signature,
+                let result = verify_block_and_state_update(
+                    &downloaded.block,
+                    &downloaded.state_update,
+                    context.chain,
+                    context.chain_id,
+                    context.block_validation_mode,
+                )
+                .and_then(
+                    |(
                         transaction_commitment,
                         event_commitment,
                         receipt_commitment,
                         state_diff_commitment,
-                        downloaded_classes,
-                        timings,
-                    ),
-                ) = ordered_blocks.pop_first().expect("num_to_emit > 0");
-
-                *head = Some((
-                    block.block_number,
-                    block.block_hash,
-                    state_update.state_commitment,
-                ));
-                blocks.push(
-                    block.block_number,
-                    block.block_hash,
-                    state_update.state_commitment,
+                    )| {
+                        verify_signature(
+                            downloaded.block.block_hash,
+                            &downloaded.signature,
+                            context.sequencer_public_key,
+                            BlockValidationMode::AllowMismatch,
+                        )?;
+
+                        Ok(VerifiedBlock {
+                            downloaded,
+                            commitments: (
+                                transaction_commitment,
+                                event_commitment,
+                                receipt_commitment,
+                            ),
+                            state_diff_commitment,
+                        })
+                    },
                 );
 
-                emit_events_for_downloaded_classes(
-                    &tx_event,
-                    downloaded_classes,
-                    &state_update.declared_sierra_classes,
+                let t_verification = t_verification.elapsed();
+                tracing::trace!(elapsed=?t_verification, "Block verification done");
+
+                let _ = tx.send(result);
+            });
+
+            async move {
+                rx.await
+                    .expect("Panic on rayon thread while verifying block")
+                    .context("Verifying block contents")
+            }
+        })
+        .buffered(concurrency.get());
+
+    while let Some(block) = verifications.next().await {
+        if !enqueue(&output, chain, "class_fetch", block?).await {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+async fn fetch_classes<GatewayClient>(
+    input: mpsc::Receiver<VerifiedBlock>,
+    sequencer: GatewayClient,
+    storage: Storage,
+    fetch_casm_from_fgw: bool,
+    concurrency: NonZeroUsize,
+    output: mpsc::Sender<(VerifiedBlock, Vec<DownloadedClass>)>,
+    chain: &'static str,
+) -> anyhow::Result<()>
+where
+    GatewayClient: GatewayApi + Clone + Send + 'static,
+{
+    let mut downloads = dequeue(input, chain, "class_fetch")
+        .map(|mut verified| {
+            let sequencer = sequencer.clone();
+            let storage = storage.clone();
+            let block_number = verified.downloaded.block.block_number;
+
+            async move {
+                let t_declare = std::time::Instant::now();
+                let downloaded_classes = download_new_classes(
+                    &verified.downloaded.state_update,
+                    &sequencer,
+                    storage,
+                    fetch_casm_from_fgw,
                 )
-                .await?;
+                .await
+                .with_context(|| {
+                    format!("Handling newly declared classes for block {block_number:?}")
+                })?;
+                verified.downloaded.timings.class_declaration = t_declare.elapsed();
 
-                tx_event
-                    .send(SyncEvent::Block(
-                        (
-                            Box::new(block),
-                            (transaction_commitment, event_commitment, receipt_commitment),
-                        ),
-                        Box::new(state_update),
-                        Box::new(signature.signature()),
-                        Box::new(state_diff_commitment),
-                        timings,
-                    ))
-                    .await
-                    .context("Event channel closed")?;
+                anyhow::Ok((verified, downloaded_classes))
             }
+            .instrument(tracing::debug_span!("download_block_classes", %block_number))
+        })
+        .buffered(concurrency.get());
+
+    while let Some(block) = downloads.next().await {
+        if !enqueue(&output, chain, "commit", block?).await {
+            break;
         }
     }
 
@@ -1339,7 +1485,7 @@ mod tests {
                 block_validation_mode: MODE,
                 storage,
                 sequencer_public_key: PublicKey::ZERO,
-                fetch_concurrency: std::num::NonZeroUsize::new(1).unwrap(),
+                pipeline: Default::default(),
                 fetch_casm_from_fgw: false,
             };
 
@@ -1367,7 +1513,10 @@ mod tests {
                 block_validation_mode: MODE,
                 storage,
                 sequencer_public_key: PublicKey::ZERO,
-                fetch_concurrency: std::num::NonZeroUsize::new(2).unwrap(),
+                pipeline: PipelineConfig {
+                    download_concurrency: std::num::NonZeroUsize::new(2).unwrap(),
+                    ..Default::default()
+                },
                 fetch_casm_from_fgw: false,
             };
 
@@ -1413,6 +1562,7 @@ mod tests {
             receipt_commitment: Default::default(),
             state_diff_commitment: Default::default(),
             state_diff_length: Default::default(),
+            l2_gas_price: Default::default(),
         });
         static BLOCK0_V2: LazyLock<reply::Block> = LazyLock::new(|| reply::Block {
             block_hash: BLOCK0_HASH_V2,
@@ -1441,6 +1591,7 @@ mod tests {
             receipt_commitment: Default::default(),
             state_diff_commitment: Default::default(),
             state_diff_length: Default::default(),
+            l2_gas_price: Default::default(),
         });
         static BLOCK1: LazyLock<reply::Block> = LazyLock::new(|| reply::Block {
             block_hash: BLOCK1_HASH,
@@ -1469,6 +1620,7 @@ mod tests {
             receipt_commitment: Default::default(),
             state_diff_commitment: Default::default(),
             state_diff_length: Default::default(),
+            l2_gas_price: Default::default(),
         });
         static BLOCK2: LazyLock<reply::Block> = LazyLock::new(|| reply::Block {
             block_hash: BLOCK2_HASH,
@@ -1497,6 +1649,7 @@ mod tests {
             receipt_commitment: Default::default(),
             state_diff_commitment: Default::default(),
             state_diff_length: Default::default(),
+            l2_gas_price: Default::default(),
         });
 
         static STATE_UPDATE0: LazyLock<StateUpdate> = LazyLock::new(|| {
@@ -1578,19 +1731,6 @@ mod tests {
                 .return_once(move |_| returned_result);
         }
 
-        fn expect_state_update_with_block_no_sequence_at_most_once(
-            mock: &mut MockGatewayApi,
-            block: BlockNumber,
-            returned_result: Result<(reply::Block, StateUpdate), SequencerError>,
-        ) {
-            use mockall::predicate::eq;
-
-            mock.expect_state_update_with_block()
-                .with(eq(block))
-                .times(..=1)
-                .return_once(move |_| returned_result);
-        }
-
         /// Convenience wrapper
         fn expect_block_header(
             mock: &mut MockGatewayApi,
@@ -1637,19 +1777,6 @@ mod tests {
                 .return_once(|_| returned_result);
         }
 
-        fn expect_signature_no_sequence_at_most_once(
-            mock: &mut MockGatewayApi,
-            block: BlockId,
-            returned_result: Result<reply::BlockSignature, SequencerError>,
-        ) {
-            use mockall::predicate::eq;
-
-            mock.expect_signature()
-                .with(eq(block))
-                .times(..=1)
-                .return_once(|_| returned_result);
-        }
-
         /// Convenience wrapper
         fn expect_class_by_hash(
             mock: &mut MockGatewayApi,
@@ -1676,17 +1803,6 @@ mod tests {
                 .return_once(|_| returned_result);
         }
 
-        fn expect_class_by_hash_no_sequence_at_most_once(
-            mock: &mut MockGatewayApi,
-            class_hash: ClassHash,
-            returned_result: Result<bytes::Bytes, SequencerError>,
-        ) {
-            mock.expect_pending_class_by_hash()
-                .withf(move |x| x == &class_hash)
-                .times(..=1)
-                .return_once(|_| returned_result);
-        }
-
         /// Convenience wrapper
         fn block_not_found() -> SequencerError {
             SequencerError::StarknetError(StarknetError {
@@ -1846,7 +1962,7 @@ mod tests {
                     block_validation_mode: MODE,
                     storage: StorageBuilder::in_memory().unwrap(),
                     sequencer_public_key: PublicKey::ZERO,
-                    fetch_concurrency: std::num::NonZeroUsize::new(1).unwrap(),
+                    pipeline: Default::default(),
                     fetch_casm_from_fgw: false,
                 };
                 let latest_track = tokio::sync::watch::channel(Default::default());
@@ -2086,6 +2202,7 @@ mod tests {
                     receipt_commitment: Default::default(),
                     state_diff_commitment: Default::default(),
                     state_diff_length: Default::default(),
+                    l2_gas_price: Default::default(),
                 };
 
                 // Fetch the genesis block with respective state update and contracts
@@ -2320,6 +2437,7 @@ mod tests {
                     receipt_commitment: Default::default(),
                     state_diff_commitment: Default::default(),
                     state_diff_length: Default::default(),
+                    l2_gas_price: Default::default(),
                 };
                 let block2_v2 = reply::Block {
                     block_hash: BLOCK2_HASH_V2,
@@ -2348,6 +2466,7 @@ mod tests {
                     receipt_commitment: Default::default(),
                     state_diff_commitment: Default::default(),
                     state_diff_length: Default::default(),
+                    l2_gas_price: Default::default(),
                 };
                 let block3 = reply::Block {
                     block_hash: BLOCK3_HASH,
@@ -2376,6 +2495,7 @@ mod tests {
                     receipt_commitment: Default::default(),
                     state_diff_commitment: Default::default(),
                     state_diff_length: Default::default(),
+                    l2_gas_price: Default::default(),
                 };
 
                 // Fetch the genesis block with respective state update and contracts
@@ -2619,6 +2739,7 @@ mod tests {
                     receipt_commitment: Default::default(),
                     state_diff_commitment: Default::default(),
                     state_diff_length: Default::default(),
+                    l2_gas_price: Default::default(),
                 };
 
                 // Fetch the genesis block with respective state update and contracts
@@ -2815,6 +2936,7 @@ mod tests {
                     receipt_commitment: Default::default(),
                     state_diff_commitment: Default::default(),
                     state_diff_length: Default::default(),
+                    l2_gas_price: Default::default(),
                 };
                 let block2 = reply::Block {
                     block_hash: BLOCK2_HASH,
@@ -2843,6 +2965,7 @@ mod tests {
                     receipt_commitment: Default::default(),
                     state_diff_commitment: Default::default(),
                     state_diff_length: Default::default(),
+                    l2_gas_price: Default::default(),
                 };
 
                 // Fetch the genesis block with respective state update and contracts
@@ -3108,19 +3231,18 @@ mod tests {
                 let (tx_event, mut rx_event) = tokio::sync::mpsc::channel(1);
                 let mut mock = MockGatewayApi::new();
 
-                // Downloading the genesis block data is racing against the failure of block 1,
-                // hence "at most once"
-                expect_state_update_with_block_no_sequence_at_most_once(
+                // Download the genesis block with respective state update and contracts
+                expect_state_update_with_block_no_sequence(
                     &mut mock,
                     BLOCK0_NUMBER,
                     Ok((BLOCK0.clone(), STATE_UPDATE0.clone())),
                 );
-                expect_class_by_hash_no_sequence_at_most_once(
+                expect_class_by_hash_no_sequence(
                     &mut mock,
                     CONTRACT0_HASH,
                     Ok(CONTRACT0_DEF.clone()),
                 );
-                expect_signature_no_sequence_at_most_once(
+                expect_signature_no_sequence(
                     &mut mock,
                     BLOCK0_NUMBER.into(),
                     Ok(BLOCK0_SIGNATURE.clone()),
@@ -3135,12 +3257,19 @@ mod tests {
                 // Let's run the UUT
                 let jh = spawn_bulk_sync(tx_event, mock);
 
-                // The entire unemitted, yet cached batch is rejected
+                // Blocks downloaded before the failure are still committed
+                assert_matches!(rx_event.recv().await.unwrap(),
+                    SyncEvent::CairoClass { hash, .. } => {
+                        assert_eq!(hash, CONTRACT0_HASH);
+                });
+                assert_matches!(rx_event.recv().await.unwrap(), SyncEvent::Block((block, _), _, _, _, _) => {
+                    assert_eq!(*block, *BLOCK0);
+                });
                 assert!(rx_event.recv().await.is_none());
 
                 // Bulk sync should _not_ fail if the block is not found
                 let result = jh.await.unwrap();
-                assert_matches!(result, Ok(None));
+                assert_matches!(result, Ok(Some((BLOCK0_NUMBER, BLOCK0_HASH, _))));
             }
         }
     }
@@ -0,0 +1,425 @@
+//! Spreading L2 sync requests over several feeder gateways.
+//!
+//! [FailoverSource] slots in wherever the L2 sync expects a [GatewayApi]. Each
+//! request is sent to the healthiest gateway first, and to the next one if it
+//! fails, so sync keeps going as long as any of the gateways is reachable.
+//! Demoted gateways are probed periodically so that they can recover their
+//! score.
+
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use pathfinder_common::{
+    BlockHash,
+    BlockId,
+    BlockNumber,
+    ClassHash,
+    PublicKey,
+    StateUpdate,
+    TransactionHash,
+};
+use starknet_gateway_client::GatewayApi;
+use starknet_gateway_types::error::SequencerError;
+use starknet_gateway_types::reply::{BlockSignature, PendingBlock};
+use starknet_gateway_types::trace::{BlockTrace, TransactionTrace};
+use starknet_gateway_types::{reply, request};
+
+/// Weight of the latest request in the moving averages of the health score.
+const SMOOTHING: f64 = 0.2;
+
+/// How much a gateway failing every request is penalized compared to a
+/// gateway which never fails, in terms of latency.
+const ERROR_PENALTY: Duration = Duration::from_secs(10);
+
+/// Gateways whose scores are within the same multiple of this are considered
+/// equally healthy, so that small latency differences don't cause switching
+/// between gateways.
+const SCORE_RESOLUTION: Duration = Duration::from_millis(50);
+
+/// How often a gateway which is not the healthiest is sent a request anyway,
+/// since its score would otherwise never improve once it has been demoted.
+const REPROBE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// A [GatewayApi] which sends requests to the healthiest of several gateways,
+/// and fails over to the next one if a request fails.
+///
+/// Health is scored using moving averages of the latency and error rate of
+/// each gateway. Starknet errors, such as a block not being found, are valid
+/// replies and are returned as is. Transactions are only submitted to the
+/// first gateway.
+///
+/// A demoted gateway which has not been sent a request for a while is tried
+/// first by the next request, falling over to the healthiest gateway if it
+/// still fails.
+#[derive(Clone)]
+pub struct FailoverSource<G> {
+    endpoints: Arc<[Endpoint<G>]>,
+    reprobe_interval: Duration,
+}
+
+struct Endpoint<G> {
+    /// Used for logging.
+    name: String,
+    gateway: G,
+    health: Mutex<Health>,
+}
+
+#[derive(Debug, Default)]
+struct Health {
+    latency_secs: f64,
+    error_rate: f64,
+    /// When the gateway was last sent a request.
+    last_request: Option<Instant>,
+}
+
+impl Health {
+    fn record(&mut self, latency: Duration, failed: bool) {
+        self.last_request = Some(Instant::now());
+        let failed = if failed { 1.0 } else { 0.0 };
+        self.latency_secs += SMOOTHING * (latency.as_secs_f64() - self.latency_secs);
+        self.error_rate += SMOOTHING * (failed - self.error_rate);
+    }
+
+    /// Lower is better.
+    fn score(&self) -> u64 {
+        let score = self.latency_secs + self.error_rate * ERROR_PENALTY.as_secs_f64();
+        (score / SCORE_RESOLUTION.as_secs_f64()) as u64
+    }
+}
+
+impl<G> FailoverSource<G>
+where
+    G: GatewayApi + Send,
+{
+    /// Creates a [FailoverSource] over the named gateways. The first one is
+    /// preferred while the gateways are equally healthy.
+    ///
+    /// # Panics
+    ///
+    /// If `gateways` is empty.
+    pub fn new(gateways: impl IntoIterator<Item = (String, G)>) -> Self {
+        let endpoints: Arc<[_]> = gateways
+            .into_iter()
+            .map(|(name, gateway)| Endpoint {
+                name,
+                gateway,
+                health: Default::default(),
+            })
+            .collect();
+        assert!(!endpoints.is_empty(), "At least one gateway is required");
+
+        Self {
+            endpoints,
+            reprobe_interval: REPROBE_INTERVAL,
+        }
+    }
+
+    #[cfg(test)]
+    fn with_reprobe_interval(self, reprobe_interval: Duration) -> Self {
+        Self {
+            reprobe_interval,
+            ..self
+        }
+    }
+
+    fn primary(&self) -> &G {
+        &self.endpoints[0].gateway
+    }
+
+    /// Endpoint indices, healthiest first, except for a demoted gateway due
+    /// to be probed which is moved to the front.
+    fn ranked(&self) -> Vec<usize> {
+        let health = self
+            .endpoints
+            .iter()
+            .map(|endpoint| {
+                let health = endpoint.health.lock().unwrap();
+                (health.score(), health.last_request)
+            })
+            .collect::<Vec<_>>();
+
+        let mut ranked = (0..self.endpoints.len()).collect::<Vec<_>>();
+        // Stable sort keeps the configured order for equal scores.
+        ranked.sort_by_key(|index| health[*index].0);
+
+        let best_score = health[ranked[0]].0;
+        let probe = ranked.iter().position(|index| {
+            let (score, last_request) = health[*index];
+            score > best_score
+                && last_request.map_or(true, |t| t.elapsed() >= self.reprobe_interval)
+        });
+        if let Some(position) = probe {
+            let index = ranked.remove(position);
+            tracing::trace!(gateway=%self.endpoints[index].name, "Probing demoted gateway");
+            ranked.insert(0, index);
+        }
+
+        ranked
+    }
+
+    /// Runs `request` against the gateways in order of health until one of
+    /// them doesn't fail, returning the last failure otherwise.
+    async fn call<'a, T, Fut>(&'a self, request: impl Fn(&'a G) -> Fut) -> Result<T, SequencerError>
+    where
+        Fut: Future<Output = Result<T, SequencerError>>,
+    {
+        let mut result = None;
+
+        for index in self.ranked() {
+            let endpoint = &self.endpoints[index];
+
+            let started = Instant::now();
+            let reply = request(&endpoint.gateway).await;
+            let failed = matches!(&reply, Err(e) if is_gateway_failure(e));
+            endpoint
+                .health
+                .lock()
+                .unwrap()
+                .record(started.elapsed(), failed);
+
+            if !failed {
+                return reply;
+            }
+
+            if self.endpoints.len() > 1 {
+                if let Err(error) = &reply {
+                    tracing::debug!(gateway=%endpoint.name, %error, "Gateway request failed, trying the next gateway");
+                }
+            }
+            result = Some(reply);
+        }
+
+        result.expect("At least one gateway is required")
+    }
+}
+
+/// Whether the error means the gateway could not answer, as opposed to a reply
+/// which any other gateway would have also returned.
+fn is_gateway_failure(e: &SequencerError) -> bool {
+    match e {
+        SequencerError::StarknetError(_) | SequencerError::ResponseTooLarge { .. } => false,
+        SequencerError::ReqwestError(_) | SequencerError::InvalidStarknetErrorVariant => true,
+    }
+}
+
+#[async_trait::async_trait]
+impl<G> GatewayApi for FailoverSource<G>
+where
+    G: GatewayApi + Send,
+{
+    async fn pending_block(&self) -> Result<(PendingBlock, StateUpdate), SequencerError> {
+        self.call(|gateway| gateway.pending_block()).await
+    }
+
+    async fn block_header(
+        &self,
+        block: BlockId,
+    ) -> Result<(BlockNumber, BlockHash), SequencerError> {
+        self.call(|gateway| gateway.block_header(block)).await
+    }
+
+    async fn pending_class_by_hash(
+        &self,
+        class_hash: ClassHash,
+    ) -> Result<bytes::Bytes, SequencerError> {
+        self.call(|gateway| gateway.pending_class_by_hash(class_hash))
+            .await
+    }
+
+    async fn pending_casm_by_hash(
+        &self,
+        class_hash: ClassHash,
+    ) -> Result<bytes::Bytes, SequencerError> {
+        self.call(|gateway| gateway.pending_casm_by_hash(class_hash))
+            .await
+    }
+
+    async fn transaction_status(
+        &self,
+        transaction_hash: TransactionHash,
+    ) -> Result<reply::TransactionStatus, SequencerError> {
+        self.call(|gateway| gateway.transaction_status(transaction_hash))
+            .await
+    }
+
+    async fn state_update_with_block(
+        &self,
+        block: BlockNumber,
+    ) -> Result<(reply::Block, StateUpdate), SequencerError> {
+        self.call(|gateway| gateway.state_update_with_block(block))
+            .await
+    }
+
+    async fn eth_contract_addresses(&self) -> Result<reply::EthContractAddresses, SequencerError> {
+        self.call(|gateway| gateway.eth_contract_addresses()).await
+    }
+
+    async fn add_invoke_transaction(
+        &self,
+        invoke: request::add_transaction::InvokeFunction,
+    ) -> Result<reply::add_transaction::InvokeResponse, SequencerError> {
+        self.primary().add_invoke_transaction(invoke).await
+    }
+
+    async fn add_declare_transaction(
+        &self,
+        declare: request::add_transaction::Declare,
+        token: Option<String>,
+    ) -> Result<reply::add_transaction::DeclareResponse, SequencerError> {
+        self.primary().add_declare_transaction(declare, token).await
+    }
+
+    async fn add_deploy_account(
+        &self,
+        deploy: request::add_transaction::DeployAccount,
+    ) -> Result<reply::add_transaction::DeployAccountResponse, SequencerError> {
+        self.primary().add_deploy_account(deploy).await
+    }
+
+    async fn head(&self) -> Result<(BlockNumber, BlockHash), SequencerError> {
+        self.call(|gateway| gateway.head()).await
+    }
+
+    async fn block_traces(&self, block: BlockId) -> Result<BlockTrace, SequencerError> {
+        self.call(|gateway| gateway.block_traces(block)).await
+    }
+
+    async fn transaction_trace(
+        &self,
+        transaction: TransactionHash,
+    ) -> Result<TransactionTrace, SequencerError> {
+        self.call(|gateway| gateway.transaction_trace(transaction))
+            .await
+    }
+
+    async fn signature(&self, block: BlockId) -> Result<BlockSignature, SequencerError> {
+        self.call(|gateway| gateway.signature(block)).await
+    }
+
+    async fn public_key(&self) -> Result<PublicKey, SequencerError> {
+        self.call(|gateway| gateway.public_key()).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::macro_prelude::*;
+    use starknet_gateway_client::MockGatewayApi;
+    use starknet_gateway_types::error::{KnownStarknetErrorCode, StarknetError};
+
+    use super::*;
+
+    fn failing_gateway(times: usize) -> MockGatewayApi {
+        let mut gateway = MockGatewayApi::new();
+        gateway
+            .expect_block_header()
+            .times(times)
+            .returning(|_| Err(SequencerError::InvalidStarknetErrorVariant));
+        gateway
+    }
+
+    fn healthy_gateway(times: usize) -> MockGatewayApi {
+        let mut gateway = MockGatewayApi::new();
+        gateway
+            .expect_block_header()
+            .times(times)
+            .returning(|_| Ok((BlockNumber::new_or_panic(1), block_hash!("0x1"))));
+        gateway
+    }
+
+    #[tokio::test]
+    async fn fails_over_to_healthy_gateway() {
+        // The failing gateway is only tried once, after that the healthy one
+        // has the better score.
+        let source = FailoverSource::new([
+            ("failing".to_owned(), failing_gateway(1)),
+            ("healthy".to_owned(), healthy_gateway(3)),
+        ]);
+
+        for _ in 0..3 {
+            let (number, _) = source.block_header(BlockId::Latest).await.unwrap();
+            assert_eq!(number, BlockNumber::new_or_panic(1));
+        }
+    }
+
+    #[tokio::test]
+    async fn prefers_first_gateway_while_healthy() {
+        let source = FailoverSource::new([
+            ("first".to_owned(), healthy_gateway(2)),
+            ("second".to_owned(), healthy_gateway(0)),
+        ]);
+
+        source.block_header(BlockId::Latest).await.unwrap();
+        source.block_header(BlockId::Latest).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn demoted_gateway_is_probed_again() {
+        let calls = std::sync::atomic::AtomicUsize::new(0);
+        let mut recovering = MockGatewayApi::new();
+        recovering
+            .expect_block_header()
+            .times(2)
+            .returning(
+                move |_| match calls.fetch_add(1, std::sync::atomic::Ordering::Relaxed) {
+                    0 => Err(SequencerError::InvalidStarknetErrorVariant),
+                    _ => Ok((BlockNumber::new_or_panic(2), block_hash!("0x2"))),
+                },
+            );
+        let source = FailoverSource::new([
+            ("recovering".to_owned(), recovering),
+            ("healthy".to_owned(), healthy_gateway(1)),
+        ])
+        .with_reprobe_interval(Duration::ZERO);
+
+        let (number, _) = source.block_header(BlockId::Latest).await.unwrap();
+        assert_eq!(number, BlockNumber::new_or_panic(1));
+
+        // Demoted, but probed first since the interval has passed.
+        let (number, _) = source.block_header(BlockId::Latest).await.unwrap();
+        assert_eq!(number, BlockNumber::new_or_panic(2));
+    }
+
+    #[tokio::test]
+    async fn demoted_gateway_is_not_probed_before_the_interval() {
+        let source = FailoverSource::new([
+            ("failing".to_owned(), failing_gateway(1)),
+            ("healthy".to_owned(), healthy_gateway(3)),
+        ])
+        .with_reprobe_interval(Duration::from_secs(3600));
+
+        for _ in 0..3 {
+            source.block_header(BlockId::Latest).await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn returns_last_failure_if_all_gateways_fail() {
+        let source = FailoverSource::new([
+            ("first".to_owned(), failing_gateway(1)),
+            ("second".to_owned(), failing_gateway(1)),
+        ]);
+
+        let error = source.block_header(BlockId::Latest).await.unwrap_err();
+        assert!(matches!(error, SequencerError::InvalidStarknetErrorVariant));
+    }
+
+    #[tokio::test]
+    async fn starknet_errors_are_not_failed_over() {
+        let mut gateway = MockGatewayApi::new();
+        gateway.expect_block_header().times(1).returning(|_| {
+            Err(SequencerError::StarknetError(StarknetError {
+                code: KnownStarknetErrorCode::BlockNotFound.into(),
+                message: String::new(),
+            }))
+        });
+        let source = FailoverSource::new([
+            ("first".to_owned(), gateway),
+            ("second".to_owned(), healthy_gateway(0)),
+        ]);
+
+        let error = source.block_header(BlockId::Latest).await.unwrap_err();
+        assert!(matches!(error, SequencerError::StarknetError(_)));
+    }
+}
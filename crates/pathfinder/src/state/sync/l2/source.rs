@@ -0,0 +1,591 @@
+//! Downloading L2 blocks from other pathfinder nodes.
+//!
+//! [PeerSource] slots in wherever the L2 sync expects a [GatewayApi]. Headers,
+//! transactions, receipts, events and state diffs are requested from peers and
+//! assembled into the same [Block] and [StateUpdate] the feeder gateway would
+//! have returned, so the rest of the sync is unaware of where a block came
+//! from. Everything else, including class definitions and polling the head of
+//! the chain, is still served by the gateway.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use futures::{pin_mut, StreamExt};
+use p2p::client::peer_agnostic::traits::BlockClient;
+use pathfinder_common::receipt::Receipt;
+use pathfinder_common::state_update::StateUpdateData;
+use pathfinder_common::transaction::Transaction;
+use pathfinder_common::{
+    BlockHash,
+    BlockId,
+    BlockNumber,
+    Chain,
+    ChainId,
+    ClassHash,
+    PublicKey,
+    SignedBlockHeader,
+    StateCommitment,
+    StateUpdate,
+    TransactionHash,
+};
+use starknet_gateway_client::GatewayApi;
+use starknet_gateway_types::error::SequencerError;
+use starknet_gateway_types::reply::{Block, BlockSignature, GasPrices, PendingBlock, Status};
+use starknet_gateway_types::trace::{BlockTrace, TransactionTrace};
+use starknet_gateway_types::{reply, request};
+
+use crate::state::block_hash::{verify_gateway_block_commitments_and_hash, VerifyResult};
+
+/// How long to wait for peers to provide a block before falling back to the
+/// gateway.
+const PEER_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long to stick to the gateway after peers failed to provide a block.
+const PEER_BACKOFF: Duration = Duration::from_secs(60);
+
+/// A [GatewayApi] which downloads blocks from pathfinder peers, and falls back
+/// to `gateway` if peers are unavailable or serve an invalid block.
+///
+/// Blocks from peers are only accepted if their hash matches their contents
+/// and the hash is signed by the sequencer. Contract class updates in state
+/// diffs from peers are always reported as deployments, since peers don't
+/// distinguish these from class replacements.
+#[derive(Clone)]
+pub struct PeerSource<P, G> {
+    p2p: P,
+    gateway: G,
+    chain: Chain,
+    chain_id: ChainId,
+    public_key: PublicKey,
+    /// Peers are skipped until this instant after they failed to provide a
+    /// block.
+    backoff_until: Arc<Mutex<Option<Instant>>>,
+}
+
+impl<P, G> PeerSource<P, G>
+where
+    P: BlockClient + Clone + Send + Sync + 'static,
+    G: GatewayApi + Send,
+{
+    pub fn new(p2p: P, gateway: G, chain: Chain, chain_id: ChainId, public_key: PublicKey) -> Self {
+        Self {
+            p2p,
+            gateway,
+            chain,
+            chain_id,
+            public_key,
+            backoff_until: Default::default(),
+        }
+    }
+
+    fn peers_available(&self) -> bool {
+        let mut backoff_until = self.backoff_until.lock().unwrap();
+        match *backoff_until {
+            Some(until) if Instant::now() < until => false,
+            Some(_) => {
+                *backoff_until = None;
+                true
+            }
+            None => true,
+        }
+    }
+
+    fn back_off(&self) {
+        *self.backoff_until.lock().unwrap() = Some(Instant::now() + PEER_BACKOFF);
+    }
+
+    /// Runs `download` against peers unless they are backing off, and backs
+    /// off if it fails.
+    async fn from_peers<T>(
+        &self,
+        block: BlockNumber,
+        download: impl std::future::Future<Output = anyhow::Result<Option<T>>>,
+    ) -> Option<T> {
+        if !self.peers_available() {
+            return None;
+        }
+
+        let error = match tokio::time::timeout(PEER_TIMEOUT, download).await {
+            Ok(Ok(Some(data))) => return Some(data),
+            Ok(Ok(None)) => anyhow::anyhow!("No peer could provide the block"),
+            Ok(Err(error)) => error,
+            Err(_) => anyhow::anyhow!("Timed out"),
+        };
+
+        tracing::debug!(%block, error=%format!("{error:#}"), "Downloading block from peers failed, falling back to the gateway");
+        self.back_off();
+        None
+    }
+
+    /// The signed header of `block`, along with the state commitment of its
+    /// parent.
+    async fn signed_header(
+        &self,
+        block: BlockNumber,
+    ) -> anyhow::Result<Option<(SignedBlockHeader, StateCommitment)>> {
+        let start = block.parent().unwrap_or(block);
+        let Some((peer, mut headers)) = self.p2p.clone().headers_for_blocks(start, block).await
+        else {
+            return Ok(None);
+        };
+
+        let signed_header = headers.pop().context("Missing header")?;
+        let parent_state_commitment = match headers.pop() {
+            Some(parent) => {
+                anyhow::ensure!(
+                    parent.header.hash == signed_header.header.parent_hash,
+                    "Parent hash mismatch from peer {peer}"
+                );
+                parent.header.state_commitment
+            }
+            None => StateCommitment::ZERO,
+        };
+
+        anyhow::ensure!(
+            signed_header.header.number == block,
+            "Unexpected block number {} from peer {peer}",
+            signed_header.header.number
+        );
+        signed_header
+            .signature
+            .verify(self.public_key, signed_header.header.hash)
+            .with_context(|| format!("Invalid signature from peer {peer}"))?;
+
+        Ok(Some((signed_header, parent_state_commitment)))
+    }
+
+    async fn block_from_peers(
+        &self,
+        block: BlockNumber,
+    ) -> anyhow::Result<Option<(Block, StateUpdate)>> {
+        let Some((signed_header, parent_state_commitment)) = self.signed_header(block).await?
+        else {
+            return Ok(None);
+        };
+        let header = signed_header.header;
+
+        let Some((peer, transactions)) = self.p2p.clone().transactions_for_block(block).await
+        else {
+            return Ok(None);
+        };
+        pin_mut!(transactions);
+        let mut receipts = Vec::with_capacity(header.transaction_count);
+        let mut block_transactions = Vec::with_capacity(header.transaction_count);
+        while let Some(transaction) = transactions.next().await {
+            let (variant, receipt) =
+                transaction.with_context(|| format!("Transactions from peer {peer}"))?;
+            anyhow::ensure!(
+                block_transactions.len() < header.transaction_count,
+                "Too many transactions from peer {peer}"
+            );
+            let hash = variant.calculate_hash(self.chain_id, false);
+            block_transactions.push(Transaction { hash, variant });
+            receipts.push(Receipt {
+                actual_fee: receipt.actual_fee,
+                execution_resources: receipt.execution_resources,
+                l2_to_l1_messages: receipt.l2_to_l1_messages,
+                execution_status: receipt.execution_status,
+                transaction_hash: hash,
+                transaction_index: receipt.transaction_index,
+            });
+        }
+        anyhow::ensure!(
+            block_transactions.len() == header.transaction_count,
+            "Too few transactions from peer {peer}"
+        );
+
+        let Some((peer, events)) = self.p2p.clone().events_for_block(block).await else {
+            return Ok(None);
+        };
+        pin_mut!(events);
+        let mut event_count = 0;
+        let mut block_events: HashMap<TransactionHash, Vec<_>> = HashMap::new();
+        while let Some(event) = events.next().await {
+            let (transaction_hash, event) = event.map_err(|error| anyhow::anyhow!("{error}"))?;
+            event_count += 1;
+            anyhow::ensure!(
+                event_count <= header.event_count,
+                "Too many events from peer {peer}"
+            );
+            block_events
+                .entry(transaction_hash)
+                .or_default()
+                .push(event);
+        }
+        anyhow::ensure!(
+            event_count == header.event_count,
+            "Too few events from peer {peer}"
+        );
+
+        let transaction_receipts = receipts
+            .into_iter()
+            .map(|receipt| {
+                let events = block_events
+                    .remove(&receipt.transaction_hash)
+                    .unwrap_or_default();
+                (receipt, events)
+            })
+            .collect();
+        anyhow::ensure!(
+            block_events.is_empty(),
+            "Events for unknown transactions from peer {peer}"
+        );
+
+        let Some((peer, state_diff)) = self
+            .p2p
+            .clone()
+            .state_diff_for_block(block, header.state_diff_length)
+            .await
+            .map_err(|error| anyhow::anyhow!("{error}"))?
+        else {
+            return Ok(None);
+        };
+        let state_diff_commitment = state_diff.compute_state_diff_commitment();
+        anyhow::ensure!(
+            state_diff_commitment == header.state_diff_commitment,
+            "State diff commitment mismatch from peer {peer}"
+        );
+
+        let StateUpdateData {
+            contract_updates,
+            system_contract_updates,
+            declared_cairo_classes,
+            declared_sierra_classes,
+        } = state_diff;
+        let state_update = StateUpdate {
+            block_hash: header.hash,
+            parent_state_commitment,
+            state_commitment: header.state_commitment,
+            contract_updates,
+            system_contract_updates,
+            declared_cairo_classes,
+            declared_sierra_classes,
+        };
+
+        let block = Block {
+            block_hash: header.hash,
+            block_number: header.number,
+            l1_gas_price: GasPrices {
+                price_in_wei: header.eth_l1_gas_price,
+                price_in_fri: header.strk_l1_gas_price,
+            },
+            l1_data_gas_price: GasPrices {
+                price_in_wei: header.eth_l1_data_gas_price,
+                price_in_fri: header.strk_l1_data_gas_price,
+            },
+            parent_block_hash: header.parent_hash,
+            sequencer_address: Some(header.sequencer_address),
+            state_commitment: header.state_commitment,
+            // Whether the block is accepted on L1 is up to L1 sync.
+            status: Status::AcceptedOnL2,
+            timestamp: header.timestamp,
+            transaction_receipts,
+            transactions: block_transactions,
+            starknet_version: header.starknet_version,
+            transaction_commitment: header.transaction_commitment,
+            event_commitment: header.event_commitment,
+            l1_da_mode: header.l1_da_mode.into(),
+            receipt_commitment: Some(header.receipt_commitment),
+            state_diff_commitment: Some(header.state_diff_commitment),
+            state_diff_length: Some(header.state_diff_length),
+            l2_gas_price: Some(GasPrices {
+                price_in_wei: header.eth_l2_gas_price,
+                price_in_fri: header.strk_l2_gas_price,
+            }),
+        };
+
+        let chain = self.chain;
+        let chain_id = self.chain_id;
+        let block = tokio::task::spawn_blocking(move || -> anyhow::Result<_> {
+            let verify_result = verify_gateway_block_commitments_and_hash(
+                &block,
+                state_diff_commitment,
+                header.state_diff_length,
+                chain,
+                chain_id,
+            )?;
+            anyhow::ensure!(
+                verify_result == VerifyResult::Match,
+                "Block hash mismatch from peers"
+            );
+            Ok(block)
+        })
+        .await
+        .context("Verifying block hash")??;
+
+        Ok(Some((block, state_update)))
+    }
+}
+
+#[async_trait::async_trait]
+impl<P, G> GatewayApi for PeerSource<P, G>
+where
+    P: BlockClient + Clone + Send + Sync + 'static,
+    G: GatewayApi + Send,
+{
+    async fn pending_block(&self) -> Result<(PendingBlock, StateUpdate), SequencerError> {
+        self.gateway.pending_block().await
+    }
+
+    async fn block_header(
+        &self,
+        block: BlockId,
+    ) -> Result<(BlockNumber, BlockHash), SequencerError> {
+        self.gateway.block_header(block).await
+    }
+
+    async fn pending_class_by_hash(
+        &self,
+        class_hash: ClassHash,
+    ) -> Result<bytes::Bytes, SequencerError> {
+        self.gateway.pending_class_by_hash(class_hash).await
+    }
+
+    async fn pending_casm_by_hash(
+        &self,
+        class_hash: ClassHash,
+    ) -> Result<bytes::Bytes, SequencerError> {
+        self.gateway.pending_casm_by_hash(class_hash).await
+    }
+
+    async fn transaction_status(
+        &self,
+        transaction_hash: TransactionHash,
+    ) -> Result<reply::TransactionStatus, SequencerError> {
+        self.gateway.transaction_status(transaction_hash).await
+    }
+
+    async fn state_update_with_block(
+        &self,
+        block: BlockNumber,
+    ) -> Result<(reply::Block, StateUpdate), SequencerError> {
+        match self.from_peers(block, self.block_from_peers(block)).await {
+            Some(block) => Ok(block),
+            None => self.gateway.state_update_with_block(block).await,
+        }
+    }
+
+    async fn eth_contract_addresses(&self) -> Result<reply::EthContractAddresses, SequencerError> {
+        self.gateway.eth_contract_addresses().await
+    }
+
+    async fn add_invoke_transaction(
+        &self,
+        invoke: request::add_transaction::InvokeFunction,
+    ) -> Result<reply::add_transaction::InvokeResponse, SequencerError> {
+        self.gateway.add_invoke_transaction(invoke).await
+    }
+
+    async fn add_declare_transaction(
+        &self,
+        declare: request::add_transaction::Declare,
+        token: Option<String>,
+    ) -> Result<reply::add_transaction::DeclareResponse, SequencerError> {
+        self.gateway.add_declare_transaction(declare, token).await
+    }
+
+    async fn add_deploy_account(
+        &self,
+        deploy: request::add_transaction::DeployAccount,
+    ) -> Result<reply::add_transaction::DeployAccountResponse, SequencerError> {
+        self.gateway.add_deploy_account(deploy).await
+    }
+
+    async fn head(&self) -> Result<(BlockNumber, BlockHash), SequencerError> {
+        self.gateway.head().await
+    }
+
+    async fn block_traces(&self, block: BlockId) -> Result<BlockTrace, SequencerError> {
+        self.gateway.block_traces(block).await
+    }
+
+    async fn transaction_trace(
+        &self,
+        transaction: TransactionHash,
+    ) -> Result<TransactionTrace, SequencerError> {
+        self.gateway.transaction_trace(transaction).await
+    }
+
+    async fn signature(&self, block: BlockId) -> Result<BlockSignature, SequencerError> {
+        if let BlockId::Number(number) = block {
+            let header = self
+                .from_peers(number, async {
+                    Ok(self.signed_header(number).await?.map(|(header, _)| header))
+                })
+                .await;
+            if let Some(SignedBlockHeader { header, signature }) = header {
+                return Ok(BlockSignature {
+                    block_hash: header.hash,
+                    signature: [signature.r, signature.s],
+                });
+            }
+        }
+
+        self.gateway.signature(block).await
+    }
+
+    async fn public_key(&self) -> Result<PublicKey, SequencerError> {
+        self.gateway.public_key().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use futures::{stream, Stream};
+    use p2p::client::types::{
+        ClassDefinition,
+        ClassDefinitionsError,
+        EventsResponseStreamFailure,
+        Receipt as P2PReceipt,
+        StateDiffsError,
+    };
+    use p2p::libp2p::PeerId;
+    use pathfinder_common::event::Event;
+    use pathfinder_common::macro_prelude::*;
+    use pathfinder_common::transaction::TransactionVariant;
+    use pathfinder_common::BlockHeader;
+    use starknet_gateway_client::MockGatewayApi;
+
+    use super::*;
+
+    /// Peers which serve headers with invalid signatures and nothing else.
+    #[derive(Clone, Default)]
+    struct FakePeers {
+        header_requests: Arc<AtomicUsize>,
+    }
+
+    impl BlockClient for FakePeers {
+        async fn headers_for_blocks(
+            self,
+            start: BlockNumber,
+            stop: BlockNumber,
+        ) -> Option<(PeerId, Vec<SignedBlockHeader>)> {
+            self.header_requests.fetch_add(1, Ordering::Relaxed);
+
+            let headers = (start.get()..=stop.get())
+                .map(|number| SignedBlockHeader {
+                    header: BlockHeader {
+                        number: BlockNumber::new_or_panic(number),
+                        ..Default::default()
+                    },
+                    signature: Default::default(),
+                })
+                .collect();
+
+            Some((PeerId::random(), headers))
+        }
+
+        async fn transactions_for_block(
+            self,
+            _: BlockNumber,
+        ) -> Option<(
+            PeerId,
+            impl Stream<Item = anyhow::Result<(TransactionVariant, P2PReceipt)>> + Send,
+        )> {
+            None::<(
+                PeerId,
+                stream::Empty<anyhow::Result<(TransactionVariant, P2PReceipt)>>,
+            )>
+        }
+
+        async fn state_diff_for_block(
+            self,
+            _: BlockNumber,
+            _: u64,
+        ) -> Result<Option<(PeerId, StateUpdateData)>, StateDiffsError> {
+            Ok(None)
+        }
+
+        async fn class_definitions_for_block(
+            self,
+            _: BlockNumber,
+            _: u64,
+        ) -> Result<Option<(PeerId, Vec<ClassDefinition>)>, ClassDefinitionsError> {
+            Ok(None)
+        }
+
+        async fn events_for_block(
+            self,
+            _: BlockNumber,
+        ) -> Option<(
+            PeerId,
+            impl Stream<Item = Result<(TransactionHash, Event), EventsResponseStreamFailure>> + Send,
+        )> {
+            None::<(
+                PeerId,
+                stream::Empty<Result<(TransactionHash, Event), EventsResponseStreamFailure>>,
+            )>
+        }
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_gateway_and_backs_off() {
+        let peers = FakePeers::default();
+        let mut gateway = MockGatewayApi::new();
+        gateway
+            .expect_state_update_with_block()
+            .times(2)
+            .returning(|number| {
+                Ok((
+                    Block {
+                        block_number: number,
+                        block_hash: block_hash!("0x1"),
+                        ..Default::default()
+                    },
+                    StateUpdate::default(),
+                ))
+            });
+        let source = PeerSource::new(
+            peers.clone(),
+            gateway,
+            Chain::SepoliaTestnet,
+            ChainId::SEPOLIA_TESTNET,
+            PublicKey::default(),
+        );
+
+        let (block, _) = source
+            .state_update_with_block(BlockNumber::new_or_panic(5))
+            .await
+            .unwrap();
+        assert_eq!(block.block_hash, block_hash!("0x1"));
+        assert_eq!(peers.header_requests.load(Ordering::Relaxed), 1);
+
+        // Peers are skipped while backing off.
+        source
+            .state_update_with_block(BlockNumber::new_or_panic(6))
+            .await
+            .unwrap();
+        assert_eq!(peers.header_requests.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn rejects_unsigned_header() {
+        let peers = FakePeers::default();
+        let mut gateway = MockGatewayApi::new();
+        gateway.expect_signature().times(1).returning(|_| {
+            Ok(BlockSignature {
+                block_hash: block_hash!("0x1"),
+                signature: Default::default(),
+            })
+        });
+        let source = PeerSource::new(
+            peers.clone(),
+            gateway,
+            Chain::SepoliaTestnet,
+            ChainId::SEPOLIA_TESTNET,
+            PublicKey::default(),
+        );
+
+        let signature = source
+            .signature(BlockNumber::new_or_panic(5).into())
+            .await
+            .unwrap();
+
+        assert_eq!(signature.block_hash, block_hash!("0x1"));
+        assert_eq!(peers.header_requests.load(Ordering::Relaxed), 1);
+    }
+}
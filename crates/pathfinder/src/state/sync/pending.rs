@@ -1,6 +1,6 @@
 use std::sync::Arc;
 
-use pathfinder_common::{BlockHash, BlockNumber};
+use pathfinder_common::{BlockHash, BlockNumber, BlockTimestamp};
 use pathfinder_storage::Storage;
 use starknet_gateway_client::GatewayApi;
 use tokio::sync::watch;
@@ -10,6 +10,9 @@ use crate::state::sync::SyncEvent;
 
 /// Emits new pending data events while the current block is close to the latest
 /// block.
+///
+/// The age of the pending block, measured from its timestamp, is reported by
+/// the `pending_age_seconds` metric, labelled with `chain`.
 pub async fn poll_pending<S: GatewayApi + Clone + Send + 'static>(
     tx_event: tokio::sync::mpsc::Sender<SyncEvent>,
     sequencer: S,
@@ -18,12 +21,17 @@ pub async fn poll_pending<S: GatewayApi + Clone + Send + 'static>(
     latest: watch::Receiver<(BlockNumber, BlockHash)>,
     current: watch::Receiver<(BlockNumber, BlockHash)>,
     fetch_casm_from_fgw: bool,
+    chain: &'static str,
 ) {
     let mut prev_tx_count = 0;
     let mut prev_hash = BlockHash::default();
+    let mut pending_timestamp = None;
 
     loop {
         let t_fetch = Instant::now();
+        if let Some(timestamp) = pending_timestamp {
+            metrics::gauge!("pending_age_seconds", pending_age_seconds(timestamp), "chain" => chain);
+        }
 
         let latest = latest.borrow().0.get();
         let current = current.borrow().0.get();
@@ -80,6 +88,7 @@ pub async fn poll_pending<S: GatewayApi + Clone + Send + 'static>(
 
                 prev_tx_count = block.transactions.len();
                 prev_hash = block.parent_hash;
+                pending_timestamp = Some(block.timestamp);
                 tracing::trace!("Emitting a pending update");
                 let block = Arc::new(block);
                 let state_update = Arc::new(state_update);
@@ -97,6 +106,16 @@ pub async fn poll_pending<S: GatewayApi + Clone + Send + 'static>(
     }
 }
 
+/// Seconds elapsed since the given block timestamp, clamped to zero for
+/// timestamps in the future.
+fn pending_age_seconds(timestamp: BlockTimestamp) -> f64 {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    now.saturating_sub(timestamp.get()) as f64
+}
+
 #[cfg(test)]
 mod tests {
     use std::sync::{Arc, LazyLock};
@@ -149,6 +168,7 @@ mod tests {
         receipt_commitment: Default::default(),
         state_diff_commitment: Default::default(),
         state_diff_length: Default::default(),
+        l2_gas_price: Default::default(),
     });
 
     pub static PENDING_UPDATE: LazyLock<StateUpdate> =
@@ -206,6 +226,7 @@ mod tests {
                 latest,
                 current,
                 false,
+                "custom",
             )
             .await
         });
@@ -279,6 +300,7 @@ mod tests {
                 rx_latest,
                 rx_current,
                 false,
+                "custom",
             )
             .await
         });
@@ -297,4 +319,18 @@ mod tests {
 
         assert_matches!(result2, SyncEvent::Pending(x) if *x.0 == b1 && *x.1 == *PENDING_UPDATE);
     }
+
+    #[test]
+    fn pending_age_is_measured_from_block_timestamp() {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        let age = super::pending_age_seconds(BlockTimestamp::new_or_panic(now - 30));
+        assert!((30.0..35.0).contains(&age), "{age}");
+
+        let age = super::pending_age_seconds(BlockTimestamp::new_or_panic(now + 30));
+        assert_eq!(age, 0.0);
+    }
 }
@@ -0,0 +1,450 @@
+//! Re-verification of the blocks stored in the database, for auditing
+//! databases which were not synced by this node, e.g. restored from a
+//! snapshot.
+
+use anyhow::Context;
+use pathfinder_common::{
+    BlockHash,
+    BlockHeader,
+    BlockNumber,
+    Chain,
+    ChainId,
+    EventCommitment,
+    ReceiptCommitment,
+    StarknetVersion,
+    StateCommitment,
+    TransactionCommitment,
+};
+use pathfinder_crypto::Felt;
+use pathfinder_storage::{BlockId, Storage, Transaction, TrieHasher};
+
+use crate::state::block_hash::{
+    calculate_event_commitment,
+    calculate_receipt_commitment,
+    calculate_transaction_commitment,
+    verify_block_hash,
+    BlockHeaderData,
+};
+
+/// Number of blocks checked per database transaction, so that the read
+/// snapshot isn't held open for the whole range.
+const BATCH_SIZE: u64 = 1_000;
+
+/// A value stored in the database which does not match the one re-computed
+/// from the block's data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mismatch {
+    BlockHash {
+        stored: BlockHash,
+    },
+    ParentHash {
+        stored: BlockHash,
+        expected: BlockHash,
+    },
+    TransactionCommitment {
+        stored: TransactionCommitment,
+        computed: TransactionCommitment,
+    },
+    EventCommitment {
+        stored: EventCommitment,
+        computed: EventCommitment,
+    },
+    ReceiptCommitment {
+        stored: ReceiptCommitment,
+        computed: ReceiptCommitment,
+    },
+    StateCommitment {
+        stored: StateCommitment,
+        derived: StateCommitment,
+    },
+}
+
+impl std::fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Mismatch::BlockHash { stored } => {
+                write!(f, "block hash {stored} does not match the block header")
+            }
+            Mismatch::ParentHash { stored, expected } => {
+                write!(
+                    f,
+                    "parent hash {stored} does not match the previous block's hash {expected}"
+                )
+            }
+            Mismatch::TransactionCommitment { stored, computed } => {
+                write!(
+                    f,
+                    "transaction commitment: stored {stored}, computed {computed}"
+                )
+            }
+            Mismatch::EventCommitment { stored, computed } => {
+                write!(f, "event commitment: stored {stored}, computed {computed}")
+            }
+            Mismatch::ReceiptCommitment { stored, computed } => {
+                write!(
+                    f,
+                    "receipt commitment: stored {stored}, computed {computed}"
+                )
+            }
+            Mismatch::StateCommitment { stored, derived } => {
+                write!(
+                    f,
+                    "state commitment: stored {stored}, recomputed from tries {derived}"
+                )
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Report {
+    /// The number of blocks verified.
+    pub blocks: u64,
+    /// The number of blocks whose state commitment could not be verified
+    /// because their tries have been pruned.
+    pub state_commitments_unavailable: u64,
+    pub mismatches: Vec<(BlockNumber, Mismatch)>,
+}
+
+/// Re-computes the block hash, the transaction, event and receipt commitments
+/// and the state commitment of every block in `from..=to`, and reports those
+/// which do not match the stored values. Each block's parent hash is also
+/// checked against the hash of the previous block.
+///
+/// Fails if a block in the range, or its transaction data, is missing.
+pub fn verify_chain(
+    storage: &Storage,
+    chain: Chain,
+    chain_id: ChainId,
+    from: BlockNumber,
+    to: BlockNumber,
+) -> anyhow::Result<Report> {
+    let mut db = storage
+        .connection()
+        .context("Opening database connection")?;
+
+    let mut report = Report::default();
+    let mut parent_hash = match from.parent() {
+        Some(parent) => {
+            let tx = db.transaction().context("Creating database transaction")?;
+            tx.block_hash(parent.into())
+                .context("Querying parent block hash")?
+        }
+        None => Some(BlockHash::ZERO),
+    };
+
+    let mut hasher = TrieHasher::default();
+    let mut next = from;
+    while next <= to {
+        let batch_end = std::cmp::min(to, next + (BATCH_SIZE - 1));
+        let tx = db.transaction().context("Creating database transaction")?;
+
+        for block_number in next.get()..=batch_end.get() {
+            let block_number = BlockNumber::new_or_panic(block_number);
+
+            let header = tx
+                .block_header(block_number.into())
+                .context("Fetching block header")?
+                .with_context(|| format!("Block {block_number} is missing"))?;
+
+            let mut mismatches = Vec::new();
+
+            if let Some(expected) = parent_hash {
+                if header.parent_hash != expected {
+                    mismatches.push(Mismatch::ParentHash {
+                        stored: header.parent_hash,
+                        expected,
+                    });
+                }
+            }
+            parent_hash = Some(header.hash);
+
+            verify_block(&tx, &header, chain, chain_id, &mut mismatches)
+                .with_context(|| format!("Verifying block {block_number}"))?;
+
+            match hasher
+                .state_commitment(&tx, block_number)
+                .context("Recomputing state commitment from tries")?
+            {
+                Some(derived) if derived != header.state_commitment => {
+                    mismatches.push(Mismatch::StateCommitment {
+                        stored: header.state_commitment,
+                        derived,
+                    });
+                }
+                Some(_) => {}
+                None => report.state_commitments_unavailable += 1,
+            }
+
+            for mismatch in mismatches {
+                tracing::warn!(%block_number, %mismatch, "Block verification failed");
+                report.mismatches.push((block_number, mismatch));
+            }
+            report.blocks += 1;
+        }
+
+        tracing::info!(from=%next, to=%batch_end, "Blocks verified");
+        next = batch_end + 1;
+    }
+
+    Ok(report)
+}
+
+/// Verifies the block's commitments against its transaction data, and its
+/// hash against its header.
+fn verify_block(
+    tx: &Transaction<'_>,
+    header: &BlockHeader,
+    chain: Chain,
+    chain_id: ChainId,
+    mismatches: &mut Vec<Mismatch>,
+) -> anyhow::Result<()> {
+    let data = tx
+        .transaction_data_for_block(header.number.into())
+        .context("Fetching transaction data")?
+        .context("Transaction data is missing")?;
+
+    let mut transactions = Vec::with_capacity(data.len());
+    let mut receipts = Vec::with_capacity(data.len());
+    let mut events = Vec::with_capacity(data.len());
+    for (transaction, receipt, transaction_events) in data {
+        events.push((transaction.hash, transaction_events));
+        transactions.push(transaction);
+        receipts.push(receipt);
+    }
+    let events = events
+        .iter()
+        .map(|(hash, events)| (*hash, events.as_slice()))
+        .collect::<Vec<_>>();
+
+    // Headers of blocks older than Starknet 0.13.2 are stored with the 0.13.2
+    // variants of their commitments.
+    let version = header.starknet_version;
+    let stored_version = version.max(StarknetVersion::V_0_13_2);
+
+    let computed = calculate_transaction_commitment(&transactions, stored_version)?;
+    if computed != header.transaction_commitment {
+        mismatches.push(Mismatch::TransactionCommitment {
+            stored: header.transaction_commitment,
+            computed,
+        });
+    }
+
+    let computed = calculate_event_commitment(&events, stored_version)?;
+    if computed != header.event_commitment {
+        mismatches.push(Mismatch::EventCommitment {
+            stored: header.event_commitment,
+            computed,
+        });
+    }
+
+    let computed = calculate_receipt_commitment(&receipts)?;
+    if computed != header.receipt_commitment {
+        mismatches.push(Mismatch::ReceiptCommitment {
+            stored: header.receipt_commitment,
+            computed,
+        });
+    }
+
+    // The hashes of older blocks were calculated over the commitments of their
+    // own version.
+    let mut header_data = BlockHeaderData::from_header(header);
+    if version < StarknetVersion::V_0_13_2 {
+        header_data.transaction_commitment =
+            calculate_transaction_commitment(&transactions, version)?;
+        header_data.event_commitment = calculate_event_commitment(&events, version)?;
+    }
+
+    if !verify_block_hash(header_data, chain, chain_id)?.is_match() {
+        mismatches.push(Mismatch::BlockHash {
+            stored: header.hash,
+        });
+    }
+
+    Ok(())
+}
+
+/// Determines the chain of the database from its genesis block hash.
+pub fn detect_chain(storage: &Storage) -> anyhow::Result<(Chain, ChainId)> {
+    use pathfinder_common::consts::{
+        MAINNET_GENESIS_HASH,
+        SEPOLIA_INTEGRATION_GENESIS_HASH,
+        SEPOLIA_TESTNET_GENESIS_HASH,
+    };
+
+    let mut db = storage
+        .connection()
+        .context("Opening database connection")?;
+    let tx = db.transaction().context("Creating database transaction")?;
+
+    let genesis = tx
+        .block_hash(BlockId::Number(BlockNumber::GENESIS))
+        .context("Querying genesis block hash")?
+        .context("Database is empty")?;
+
+    // The chain ID is only used to hash blocks from before Starknet 0.7, which
+    // custom networks don't have.
+    Ok(match genesis {
+        MAINNET_GENESIS_HASH => (Chain::Mainnet, ChainId::MAINNET),
+        SEPOLIA_TESTNET_GENESIS_HASH => (Chain::SepoliaTestnet, ChainId::SEPOLIA_TESTNET),
+        SEPOLIA_INTEGRATION_GENESIS_HASH => {
+            (Chain::SepoliaIntegration, ChainId::SEPOLIA_INTEGRATION)
+        }
+        _ => (Chain::Custom, ChainId(Felt::ZERO)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::event::Event;
+    use pathfinder_common::macro_prelude::*;
+    use pathfinder_common::receipt::Receipt;
+    use pathfinder_common::transaction::Transaction;
+    use pathfinder_common::TransactionHash;
+    use pathfinder_storage::StorageBuilder;
+
+    use super::*;
+    use crate::state::block_hash::compute_final_hash;
+
+    /// Creates blocks `0..count` with a single transaction each, whose headers
+    /// commit to their data. Block `corrupt`, if any, commits to a bogus
+    /// transaction commitment but is otherwise correctly hashed.
+    fn setup(count: u64, corrupt: Option<u64>) -> Storage {
+        let storage = StorageBuilder::in_memory().unwrap();
+        let mut db = storage.connection().unwrap();
+        let tx = db.transaction().unwrap();
+
+        let mut parent_hash = BlockHash::ZERO;
+        for i in 0..count {
+            let transaction = Transaction {
+                hash: TransactionHash(Felt::from_u64(i + 1)),
+                variant: Default::default(),
+            };
+            let receipt = Receipt {
+                transaction_hash: transaction.hash,
+                ..Default::default()
+            };
+            let events = vec![Event {
+                from_address: contract_address!("0x1"),
+                keys: vec![event_key!("0x2")],
+                data: vec![event_data!("0x3")],
+            }];
+
+            let version = StarknetVersion::V_0_13_2;
+            let transaction_commitment = if corrupt == Some(i) {
+                transaction_commitment!("0xbad")
+            } else {
+                calculate_transaction_commitment(std::slice::from_ref(&transaction), version)
+                    .unwrap()
+            };
+            let event_commitment =
+                calculate_event_commitment(&[(transaction.hash, events.as_slice())], version)
+                    .unwrap();
+            let receipt_commitment =
+                calculate_receipt_commitment(std::slice::from_ref(&receipt)).unwrap();
+
+            let mut header = BlockHeader::builder()
+                .number(BlockNumber::new_or_panic(i))
+                .parent_hash(parent_hash)
+                .starknet_version(version)
+                .transaction_commitment(transaction_commitment)
+                .event_commitment(event_commitment)
+                .receipt_commitment(receipt_commitment)
+                .transaction_count(1)
+                .event_count(events.len())
+                .finalize_with_hash(BlockHash::ZERO);
+            header.hash = compute_final_hash(&BlockHeaderData::from_header(&header)).unwrap();
+            parent_hash = header.hash;
+
+            tx.insert_block_header(&header).unwrap();
+            tx.insert_transaction_data(
+                header.number,
+                &[(transaction, receipt)],
+                Some(std::slice::from_ref(&events)),
+            )
+            .unwrap();
+        }
+
+        tx.commit().unwrap();
+        storage
+    }
+
+    #[test]
+    fn valid_chain() {
+        let storage = setup(3, None);
+
+        let report = verify_chain(
+            &storage,
+            Chain::Custom,
+            ChainId(Felt::ZERO),
+            BlockNumber::GENESIS,
+            BlockNumber::new_or_panic(2),
+        )
+        .unwrap();
+
+        assert_eq!(
+            report,
+            Report {
+                blocks: 3,
+                // No tries are stored, so the empty state commits to zero.
+                state_commitments_unavailable: 0,
+                mismatches: vec![],
+            }
+        );
+    }
+
+    #[test]
+    fn range_is_linked_to_its_parent() {
+        let storage = setup(3, None);
+
+        let report = verify_chain(
+            &storage,
+            Chain::Custom,
+            ChainId(Felt::ZERO),
+            BlockNumber::new_or_panic(1),
+            BlockNumber::new_or_panic(2),
+        )
+        .unwrap();
+
+        assert_eq!(report.blocks, 2);
+        assert!(report.mismatches.is_empty());
+    }
+
+    #[test]
+    fn mismatching_commitment() {
+        let storage = setup(3, Some(1));
+
+        let report = verify_chain(
+            &storage,
+            Chain::Custom,
+            ChainId(Felt::ZERO),
+            BlockNumber::GENESIS,
+            BlockNumber::new_or_panic(2),
+        )
+        .unwrap();
+
+        assert_eq!(report.mismatches.len(), 1);
+        let (block_number, mismatch) = report.mismatches[0];
+        assert_eq!(block_number, BlockNumber::new_or_panic(1));
+        assert_matches::assert_matches!(
+            mismatch,
+            Mismatch::TransactionCommitment { stored, .. } => {
+                assert_eq!(stored, transaction_commitment!("0xbad"))
+            }
+        );
+    }
+
+    #[test]
+    fn missing_block() {
+        let storage = setup(1, None);
+
+        verify_chain(
+            &storage,
+            Chain::Custom,
+            ChainId(Felt::ZERO),
+            BlockNumber::GENESIS,
+            BlockNumber::new_or_panic(1),
+        )
+        .unwrap_err();
+    }
+}
@@ -141,6 +141,10 @@ impl Sync {
         let local_state = LocalState::from_db(self.storage.clone(), checkpoint)
             .await
             .context("Querying local state after checkpoint sync")?;
+        // Only now is all data of the synced blocks stored.
+        persist_sync_checkpoint(self.storage.clone(), local_state.latest_header)
+            .await
+            .context("Persisting sync checkpoint")?;
         let (next_block_number, last_block_hash) = local_state
             .latest_header
             .map(|(number, hash)| (number + 1, hash))
@@ -684,6 +688,24 @@ async fn rollback_to_anchor(
     .context("Joining blocking task")?
 }
 
+async fn persist_sync_checkpoint(
+    storage: Storage,
+    latest: Option<(BlockNumber, BlockHash)>,
+) -> anyhow::Result<()> {
+    spawn_blocking(move || {
+        let mut db = storage
+            .connection()
+            .context("Creating database connection")?;
+        let db = db.transaction().context("Creating database transaction")?;
+        db.update_sync_checkpoint(latest)
+            .context("Updating sync checkpoint")?;
+        db.commit().context("Committing database transaction")?;
+        Ok(())
+    })
+    .await
+    .context("Joining blocking task")?
+}
+
 async fn persist_anchor(storage: Storage, anchor: EthereumStateUpdate) -> anyhow::Result<()> {
     spawn_blocking(move || {
         let mut db = storage
@@ -791,6 +813,8 @@ mod tests {
                         eth_l1_data_gas_price: GasPrice(1),
                         strk_l1_gas_price: GasPrice(0),
                         strk_l1_data_gas_price: GasPrice(1),
+                        eth_l2_gas_price: GasPrice(0),
+                        strk_l2_gas_price: GasPrice(0),
                         l1_da_mode: L1DataAvailabilityMode::Calldata,
                         class_commitment: ClassCommitment::ZERO,
                         storage_commitment: StorageCommitment::ZERO,
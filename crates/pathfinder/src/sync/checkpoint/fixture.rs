@@ -69,6 +69,8 @@ pub fn blocks() -> [Block; 2] {
                     strk_l1_gas_price: GasPrice(0),
                     eth_l1_data_gas_price: GasPrice(1),
                     strk_l1_data_gas_price: GasPrice(1),
+                    eth_l2_gas_price: GasPrice(0),
+                    strk_l2_gas_price: GasPrice(0),
                     sequencer_address: Default::default(),
                     starknet_version: StarknetVersion::new(0, 0, 0, 0),
                     class_commitment: class_commitment!("0x0"),
@@ -1189,6 +1191,8 @@ pub fn blocks() -> [Block; 2] {
                     strk_l1_gas_price: GasPrice(0),
                     eth_l1_data_gas_price: GasPrice(1),
                     strk_l1_data_gas_price: GasPrice(1),
+                    eth_l2_gas_price: GasPrice(0),
+                    strk_l2_gas_price: GasPrice(0),
                     sequencer_address: Default::default(),
                     starknet_version: StarknetVersion::default(),
                     class_commitment: class_commitment!("0x0"),
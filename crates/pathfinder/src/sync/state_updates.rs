@@ -145,6 +145,11 @@ impl ProcessStage for UpdateStarknetState {
 
         let tail = self.current_block;
 
+        let block_hash = db
+            .block_hash(self.current_block.into())
+            .context("Querying block hash")?
+            .context("Block header not found")?;
+
         let (storage_commitment, class_commitment) = update_starknet_state(
             &db,
             StarknetStateUpdate {
@@ -154,6 +159,7 @@ impl ProcessStage for UpdateStarknetState {
             },
             self.verify_tree_hashes,
             self.current_block,
+            block_hash,
             self.storage.clone(),
         )
         .context("Updating Starknet state")?;
@@ -781,6 +781,8 @@ impl ProcessStage for StoreBlock {
             strk_l1_gas_price: header.strk_l1_gas_price,
             eth_l1_data_gas_price: header.eth_l1_data_gas_price,
             strk_l1_data_gas_price: header.strk_l1_data_gas_price,
+            eth_l2_gas_price: header.eth_l2_gas_price,
+            strk_l2_gas_price: header.strk_l2_gas_price,
             sequencer_address: header.sequencer_address,
             starknet_version: header.starknet_version,
             // Class commitment is updated after the class tries are updated.
@@ -822,6 +824,7 @@ impl ProcessStage for StoreBlock {
             },
             self.verify_tree_hashes,
             block_number,
+            header.hash,
             self.storage.clone(),
         )
         .context("Updating Starknet state")?;
@@ -874,6 +877,9 @@ impl ProcessStage for StoreBlock {
             },
         )?;
 
+        db.update_sync_checkpoint(Some((block_number, header.hash)))
+            .context("Updating sync checkpoint")?;
+
         let result = db
             .commit()
             .context("Committing transaction")
@@ -954,6 +960,7 @@ mod tests {
 
         let mut db = storage.connection().unwrap();
         let db = db.transaction().unwrap();
+        assert_eq!(db.sync_checkpoint().unwrap(), Some(latest));
         for mut expected in blocks {
             // TODO p2p sync does not update class and storage tries yet
             expected.header.header.class_commitment = ClassCommitment::ZERO;
@@ -1037,6 +1044,21 @@ mod tests {
     }
 
     impl BlockClient for FakeP2PClient {
+        async fn headers_for_blocks(
+            self,
+            start: BlockNumber,
+            stop: BlockNumber,
+        ) -> Option<(PeerId, Vec<SignedBlockHeader>)> {
+            let headers = self
+                .blocks
+                .into_iter()
+                .map(|block| block.header)
+                .filter(|header| (start..=stop).contains(&header.header.number))
+                .collect();
+
+            Some((PeerId::random(), headers))
+        }
+
         async fn transactions_for_block(
             self,
             block: BlockNumber,
@@ -0,0 +1,292 @@
+//! Typed client for the pathfinder JSON-RPC API.
+//!
+//! Methods are called with the same input and output types the server uses,
+//! see [pathfinder_rpc::client], over either HTTP or a WebSocket connection.
+//! Methods without typed inputs and outputs yet take their parameters and
+//! return their result as JSON. Subscriptions are made using
+//! [Client::call_raw], after which [Client::next_notification] returns their
+//! notifications.
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use futures::{SinkExt, StreamExt};
+pub use pathfinder_rpc::client::*;
+pub use pathfinder_rpc::RpcVersion;
+use reqwest::Url;
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("HTTP transport failed: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("WebSocket transport failed: {0}")]
+    WebSocket(#[from] tokio_tungstenite::tungstenite::Error),
+    #[error("WebSocket connection closed")]
+    ConnectionClosed,
+    #[error("Invalid JSON-RPC response: {0}")]
+    InvalidResponse(#[from] serde_json::Error),
+    #[error("JSON-RPC response has neither a result nor an error")]
+    MissingResult,
+    #[error("Notifications are only received over WebSocket connections")]
+    NotificationsUnsupported,
+    #[error("JSON-RPC error {code}: {message}")]
+    Rpc {
+        code: i64,
+        message: String,
+        data: Option<serde_json::Value>,
+    },
+}
+
+enum Transport {
+    Http {
+        client: reqwest::Client,
+        url: Url,
+    },
+    /// Requests are sent one at a time.
+    WebSocket(tokio::sync::Mutex<WebSocket>),
+}
+
+/// The number of notifications kept for [Client::next_notification] while
+/// waiting for responses. The oldest ones are dropped beyond this.
+const MAX_BUFFERED_NOTIFICATIONS: usize = 1024;
+
+struct WebSocket {
+    stream: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    /// Notifications received while waiting for a response.
+    notifications: VecDeque<serde_json::Value>,
+}
+
+impl WebSocket {
+    /// Receives the next JSON-RPC message.
+    async fn receive(&mut self) -> Result<serde_json::Value, Error> {
+        loop {
+            let message = match self.stream.next().await.ok_or(Error::ConnectionClosed)?? {
+                Message::Text(text) => text,
+                Message::Close(_) => return Err(Error::ConnectionClosed),
+                _ => continue,
+            };
+            return Ok(serde_json::from_str(&message)?);
+        }
+    }
+
+    fn buffer_notification(&mut self, notification: serde_json::Value) {
+        if self.notifications.len() == MAX_BUFFERED_NOTIFICATIONS {
+            self.notifications.pop_front();
+        }
+        self.notifications.push_back(notification);
+    }
+}
+
+pub struct Client {
+    transport: Transport,
+    version: RpcVersion,
+    next_id: AtomicU64,
+}
+
+impl Client {
+    /// A client sending requests to `url`, e.g.
+    /// `http://127.0.0.1:9545/rpc/v0_7`, whose path must select the same API
+    /// version as `version`.
+    pub fn http(url: Url, version: RpcVersion) -> Self {
+        Self {
+            transport: Transport::Http {
+                client: reqwest::Client::new(),
+                url,
+            },
+            version,
+            next_id: AtomicU64::new(0),
+        }
+    }
+
+    /// A client connected to `url`, e.g. `ws://127.0.0.1:9545/ws/rpc/v0_7`,
+    /// whose path must select the same API version as `version`.
+    pub async fn websocket(url: &str, version: RpcVersion) -> Result<Self, Error> {
+        let (stream, _) = tokio_tungstenite::connect_async(url).await?;
+
+        Ok(Self {
+            transport: Transport::WebSocket(tokio::sync::Mutex::new(WebSocket {
+                stream,
+                notifications: VecDeque::new(),
+            })),
+            version,
+            next_id: AtomicU64::new(0),
+        })
+    }
+
+    /// Calls the method `M`.
+    pub async fn call<M: Method>(&self, input: &M::Input) -> Result<M::Output, Error> {
+        let params = M::params(input, self.version)?;
+        let result = self.call_raw(M::NAME, params).await?;
+
+        Ok(M::output(result, self.version)?)
+    }
+
+    /// Calls `method` with untyped parameters, returning its untyped result.
+    pub async fn call_raw(
+        &self,
+        method: &str,
+        params: Option<serde_json::Value>,
+    ) -> Result<serde_json::Value, Error> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let mut request = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": method,
+        });
+        if let Some(params) = params {
+            request["params"] = params;
+        }
+
+        let response = match &self.transport {
+            Transport::Http { client, url } => {
+                let response = client
+                    .post(url.clone())
+                    .header(reqwest::header::CONTENT_TYPE, "application/json")
+                    .body(request.to_string())
+                    .send()
+                    .await?
+                    .bytes()
+                    .await?;
+                serde_json::from_slice(&response)?
+            }
+            Transport::WebSocket(websocket) => {
+                let mut websocket = websocket.lock().await;
+                websocket
+                    .stream
+                    .send(Message::Text(request.to_string()))
+                    .await?;
+                loop {
+                    let message = websocket.receive().await?;
+                    if message["id"] == id {
+                        break message;
+                    }
+                    if message.get("id").is_none() {
+                        websocket.buffer_notification(message);
+                    }
+                }
+            }
+        };
+
+        parse_response(response)
+    }
+
+    /// Returns the next subscription notification, waiting for one if none
+    /// were received yet. Requests are held up while waiting.
+    pub async fn next_notification(&self) -> Result<serde_json::Value, Error> {
+        let Transport::WebSocket(websocket) = &self.transport else {
+            return Err(Error::NotificationsUnsupported);
+        };
+
+        let mut websocket = websocket.lock().await;
+        if let Some(notification) = websocket.notifications.pop_front() {
+            return Ok(notification);
+        }
+        loop {
+            let message = websocket.receive().await?;
+            // Responses to requests whose callers stopped waiting are skipped.
+            if message.get("id").is_none() {
+                return Ok(message);
+            }
+        }
+    }
+}
+
+fn parse_response(mut response: serde_json::Value) -> Result<serde_json::Value, Error> {
+    if let Some(error) = response.get_mut("error") {
+        return Err(Error::Rpc {
+            code: error["code"].as_i64().unwrap_or_default(),
+            message: error["message"].as_str().unwrap_or_default().to_owned(),
+            data: error.get_mut("data").map(serde_json::Value::take),
+        });
+    }
+
+    response
+        .get_mut("result")
+        .map(serde_json::Value::take)
+        .ok_or(Error::MissingResult)
+}
+
+macro_rules! method {
+    ($method:ident: $fn:ident()) => {
+        impl Client {
+            #[doc = concat!("Calls [", stringify!($method), "].")]
+            pub async fn $fn(&self) -> Result<<$method as Method>::Output, Error> {
+                self.call::<$method>(&()).await
+            }
+        }
+    };
+    ($method:ident: $fn:ident) => {
+        impl Client {
+            #[doc = concat!("Calls [", stringify!($method), "].")]
+            pub async fn $fn(
+                &self,
+                input: &<$method as Method>::Input,
+            ) -> Result<<$method as Method>::Output, Error> {
+                self.call::<$method>(input).await
+            }
+        }
+    };
+}
+
+method!(ChainId: chain_id());
+method!(BlockNumber: block_number());
+method!(BlockHashAndNumber: block_hash_and_number());
+method!(GetBlockTransactionCount: get_block_transaction_count);
+method!(GetClassHashAt: get_class_hash_at);
+method!(GetNonce: get_nonce);
+method!(GetStorageAt: get_storage_at);
+method!(AddDeclareTransaction: add_declare_transaction);
+method!(AddDeployAccountTransaction: add_deploy_account_transaction);
+method!(AddInvokeTransaction: add_invoke_transaction);
+method!(Call: call_contract);
+method!(EstimateFee: estimate_fee);
+method!(EstimateMessageFee: estimate_message_fee);
+method!(GetBlockWithReceipts: get_block_with_receipts);
+method!(GetBlockWithTxHashes: get_block_with_tx_hashes);
+method!(GetBlockWithTxs: get_block_with_txs);
+method!(GetClass: get_class);
+method!(GetClassAt: get_class_at);
+method!(GetCompiledCasm: get_compiled_casm);
+method!(GetEvents: get_events);
+method!(GetStateUpdate: get_state_update);
+method!(GetStorageProof: get_storage_proof);
+method!(GetTransactionByBlockIdAndIndex: get_transaction_by_block_id_and_index);
+method!(GetTransactionByHash: get_transaction_by_hash);
+method!(GetTransactionReceipt: get_transaction_receipt);
+method!(GetTransactionStatus: get_transaction_status);
+method!(SimulateTransactions: simulate_transactions);
+method!(TraceBlockTransactions: trace_block_transactions);
+method!(TraceTransaction: trace_transaction);
+method!(SpecVersion: spec_version());
+method!(Syncing: syncing());
+method!(GetProof: get_proof);
+method!(GetClassProof: get_class_proof);
+method!(GetReceiptProof: get_receipt_proof);
+method!(GetStorageAtBlocks: get_storage_at_blocks);
+method!(GetStorageBatch: get_storage_batch);
+method!(GetStorageHistory: get_storage_history);
+method!(GetNonceAt: get_nonce_at);
+method!(GetStateUpdateRange: get_state_update_range);
+method!(GetFeeHistory: get_fee_history);
+method!(GetBlockStats: get_block_stats);
+method!(GetClassDefinitions: get_class_definitions);
+method!(GetClassInfo: get_class_info);
+method!(GetContractStateHash: get_contract_state_hash);
+method!(GetContractStorageKeys: get_contract_storage_keys);
+method!(PathfinderGetTransactionStatus: pathfinder_get_transaction_status);
+method!(GetL1ToL2MessageStatus: get_l1_to_l2_message_status);
+method!(GetMessageToL1Proof: get_message_to_l1_proof);
+method!(GetTransactionsByAccount: get_transactions_by_account);
+method!(GetReorgs: get_reorgs);
+method!(CompareTrace: compare_trace);
+method!(TraceTransactionFlame: trace_transaction_flame);
+method!(GetSubmittedTransactions: get_submitted_transactions);
+method!(GetBalances: get_balances);
+method!(RegisterAbi: register_abi);
+method!(Version: version());
+method!(GetSyncLag: get_sync_lag());
+method!(GetMigrationStatus: get_migration_status());
+method!(GetBackupStatus: get_backup_status());
+method!(Health: health());
@@ -0,0 +1,136 @@
+use futures::{SinkExt, StreamExt};
+use httpmock::prelude::*;
+use pathfinder_common::macro_prelude::*;
+use pathfinder_common::BlockId;
+use pathfinder_rpc_client::{Client, Error, GetNonceInput, GetNonceOutput, RpcVersion};
+use serde_json::json;
+use tokio_tungstenite::tungstenite::Message;
+
+#[tokio::test]
+async fn http() {
+    let server = MockServer::start_async().await;
+    let nonce = server
+        .mock_async(|when, then| {
+            when.method(POST).path("/rpc/v0_7").json_body(json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "method": "starknet_getNonce",
+                "params": {"block_id": "latest", "contract_address": "0x1"},
+            }));
+            then.status(200)
+                .json_body(json!({"jsonrpc": "2.0", "id": 0, "result": "0x2"}));
+        })
+        .await;
+    let no_blocks = server
+        .mock_async(|when, then| {
+            when.method(POST)
+                .path("/rpc/v0_7")
+                .json_body_partial(r#"{"method": "starknet_blockNumber"}"#);
+            then.status(200).json_body(json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "error": {"code": 32, "message": "There are no blocks"},
+            }));
+        })
+        .await;
+
+    let client = Client::http(server.url("/rpc/v0_7").parse().unwrap(), RpcVersion::V07);
+
+    let output = client
+        .get_nonce(&GetNonceInput {
+            block_id: BlockId::Latest,
+            contract_address: contract_address!("0x1"),
+        })
+        .await
+        .unwrap();
+    assert_eq!(output, GetNonceOutput(contract_nonce!("0x2")));
+
+    let error = client.block_number().await.unwrap_err();
+    assert!(matches!(error, Error::Rpc { code: 32, .. }), "{error:?}");
+
+    nonce.assert_async().await;
+    no_blocks.assert_async().await;
+}
+
+#[tokio::test]
+async fn websocket() {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut stream = tokio_tungstenite::accept_async(stream).await.unwrap();
+        while let Some(Ok(Message::Text(request))) = stream.next().await {
+            let request: serde_json::Value = serde_json::from_str(&request).unwrap();
+            assert_eq!(request["method"], "starknet_chainId");
+            assert_eq!(request.get("params"), None);
+
+            // Notifications received while waiting for the response are kept.
+            let notification = json!({
+                "jsonrpc": "2.0",
+                "method": "pathfinder_subscription",
+                "params": {"result": request["id"]},
+            });
+            stream
+                .send(Message::Text(notification.to_string()))
+                .await
+                .unwrap();
+            let response = json!({
+                "jsonrpc": "2.0",
+                "id": request["id"],
+                "result": "0x534e5f5345504f4c4941",
+            });
+            stream
+                .send(Message::Text(response.to_string()))
+                .await
+                .unwrap();
+        }
+    });
+
+    let client = Client::websocket(&format!("ws://{addr}/ws/rpc/v0_7"), RpcVersion::V07)
+        .await
+        .unwrap();
+
+    for _ in 0..2 {
+        let output = client.chain_id().await.unwrap();
+        assert_eq!(output.0, pathfinder_common::ChainId::SEPOLIA_TESTNET);
+    }
+
+    for id in 0..2 {
+        let notification = client.next_notification().await.unwrap();
+        assert_eq!(notification["params"]["result"], id);
+    }
+}
+
+#[tokio::test]
+async fn json_methods() {
+    let server = MockServer::start_async().await;
+    let state_update = server
+        .mock_async(|when, then| {
+            when.method(POST).path("/rpc/v0_7").json_body(json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "method": "starknet_getStateUpdate",
+                "params": {"block_id": "latest"},
+            }));
+            then.status(200)
+                .json_body(json!({"jsonrpc": "2.0", "id": 0, "result": {"block_hash": "0x1"}}));
+        })
+        .await;
+
+    let client = Client::http(server.url("/rpc/v0_7").parse().unwrap(), RpcVersion::V07);
+
+    let output = client
+        .get_state_update(&json!({"block_id": "latest"}))
+        .await
+        .unwrap();
+    assert_eq!(output, json!({"block_hash": "0x1"}));
+
+    let error = client.next_notification().await.unwrap_err();
+    assert!(
+        matches!(error, Error::NotificationsUnsupported),
+        "{error:?}"
+    );
+
+    state_update.assert_async().await;
+}
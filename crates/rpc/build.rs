@@ -0,0 +1,6 @@
+fn main() -> std::io::Result<()> {
+    #[cfg(feature = "grpc")]
+    tonic_build::compile_protos("proto/starknet.proto")?;
+
+    Ok(())
+}
@@ -0,0 +1,817 @@
+//! Contract ABIs used to decode events into their names and fields.
+//!
+//! ABIs are registered per class hash or contract address, either using
+//! `pathfinder_registerAbi` or by placing them in the directory given by
+//! `--rpc.abi-directory` as `classes/<class hash>.json` or
+//! `contracts/<contract address>.json`. ABIs registered using the RPC method
+//! are written to this directory as well, so that they persist across
+//! restarts.
+//!
+//! Both Cairo 0 and Cairo 1 ABIs are supported. Events which don't match
+//! their ABI are returned without decoded fields.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+
+use anyhow::Context;
+use pathfinder_common::{ClassHash, ContractAddress, EntryPoint};
+use pathfinder_crypto::Felt;
+use primitive_types::U256;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+const CLASSES: &str = "classes";
+const CONTRACTS: &str = "contracts";
+
+/// The largest ABI accepted, in bytes of JSON.
+const MAX_ABI_SIZE: usize = 1024 * 1024;
+/// The largest number of ABIs kept, for classes and contracts combined.
+const MAX_ABIS: usize = 10_000;
+
+/// What an ABI is registered for. ABIs registered for a contract address take
+/// precedence over the ABI of its class.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AbiTarget {
+    Class(ClassHash),
+    Contract(ContractAddress),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RegisterAbiError {
+    #[error("Invalid ABI: {0:#}")]
+    InvalidAbi(anyhow::Error),
+    #[error("ABI exceeds the maximum size of {MAX_ABI_SIZE} bytes")]
+    TooLarge,
+    #[error("The maximum of {MAX_ABIS} ABIs are registered already")]
+    TooMany,
+    #[error(transparent)]
+    Internal(#[from] anyhow::Error),
+}
+
+#[derive(Clone, Default)]
+pub struct AbiRegistry {
+    inner: Arc<RwLock<Inner>>,
+    directory: Option<PathBuf>,
+}
+
+impl std::fmt::Debug for AbiRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AbiRegistry")
+            .field("directory", &self.directory)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Default)]
+struct Inner {
+    classes: HashMap<ClassHash, Arc<Abi>>,
+    contracts: HashMap<ContractAddress, Arc<Abi>>,
+}
+
+impl Inner {
+    fn len(&self) -> usize {
+        self.classes.len() + self.contracts.len()
+    }
+
+    fn contains(&self, target: AbiTarget) -> bool {
+        match target {
+            AbiTarget::Class(class_hash) => self.classes.contains_key(&class_hash),
+            AbiTarget::Contract(address) => self.contracts.contains_key(&address),
+        }
+    }
+}
+
+impl AbiRegistry {
+    /// Loads the ABIs stored in `directory`, creating it if it doesn't exist.
+    /// Fails if it holds more than [MAX_ABIS] ABIs, or any larger than
+    /// [MAX_ABI_SIZE].
+    pub fn load(directory: PathBuf) -> anyhow::Result<Self> {
+        let mut inner = Inner::default();
+
+        for subdirectory in [CLASSES, CONTRACTS] {
+            let path = directory.join(subdirectory);
+            std::fs::create_dir_all(&path)
+                .with_context(|| format!("Creating ABI directory {}", path.display()))?;
+
+            for entry in std::fs::read_dir(&path)
+                .with_context(|| format!("Reading ABI directory {}", path.display()))?
+            {
+                let path = entry.context("Reading ABI directory entry")?.path();
+                if path
+                    .extension()
+                    .map_or(true, |extension| extension != "json")
+                {
+                    continue;
+                }
+
+                anyhow::ensure!(
+                    inner.len() < MAX_ABIS,
+                    "ABI directory {} holds more than {MAX_ABIS} ABIs",
+                    directory.display()
+                );
+                let size = std::fs::metadata(&path)
+                    .with_context(|| format!("Reading ABI file metadata {}", path.display()))?
+                    .len();
+                anyhow::ensure!(
+                    size <= MAX_ABI_SIZE as u64,
+                    "ABI file {} exceeds the maximum size of {MAX_ABI_SIZE} bytes",
+                    path.display()
+                );
+
+                let key = path
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .and_then(|stem| Felt::from_hex_str(stem).ok())
+                    .with_context(|| format!("Parsing ABI file name {}", path.display()))?;
+                let abi = std::fs::read_to_string(&path)
+                    .with_context(|| format!("Reading ABI file {}", path.display()))?;
+                let abi = Abi::parse(&abi)
+                    .with_context(|| format!("Parsing ABI file {}", path.display()))?;
+
+                match subdirectory {
+                    CLASSES => inner.classes.insert(ClassHash(key), Arc::new(abi)),
+                    _ => inner.contracts.insert(ContractAddress(key), Arc::new(abi)),
+                };
+            }
+        }
+
+        tracing::info!(
+            classes=%inner.classes.len(), contracts=%inner.contracts.len(),
+            "Loaded ABIs for event decoding"
+        );
+
+        Ok(Self {
+            inner: Arc::new(RwLock::new(inner)),
+            directory: Some(directory),
+        })
+    }
+
+    /// Registers `abi`, replacing any ABI previously registered for `target`.
+    /// At most [MAX_ABIS] ABIs of up to [MAX_ABI_SIZE] bytes are kept.
+    pub fn register(&self, target: AbiTarget, abi: &str) -> Result<(), RegisterAbiError> {
+        if abi.len() > MAX_ABI_SIZE {
+            return Err(RegisterAbiError::TooLarge);
+        }
+        let parsed = Arc::new(Abi::parse(abi).map_err(RegisterAbiError::InvalidAbi)?);
+
+        // Held while writing the file, so that concurrent registrations can't exceed
+        // the limit.
+        let mut inner = self.inner.write().unwrap();
+        if !inner.contains(target) && inner.len() >= MAX_ABIS {
+            return Err(RegisterAbiError::TooMany);
+        }
+
+        if let Some(directory) = &self.directory {
+            let (subdirectory, key) = match target {
+                AbiTarget::Class(class_hash) => (CLASSES, class_hash.0),
+                AbiTarget::Contract(address) => (CONTRACTS, address.0),
+            };
+            let path = directory.join(subdirectory).join(format!("{key}.json"));
+            std::fs::write(&path, abi)
+                .with_context(|| format!("Writing ABI file {}", path.display()))?;
+        }
+
+        match target {
+            AbiTarget::Class(class_hash) => inner.classes.insert(class_hash, parsed),
+            AbiTarget::Contract(address) => inner.contracts.insert(address, parsed),
+        };
+
+        Ok(())
+    }
+
+    /// Whether any ABIs are registered for class hashes, in which case
+    /// decoding requires the class hash of the emitting contract.
+    pub(crate) fn has_class_abis(&self) -> bool {
+        !self.inner.read().unwrap().classes.is_empty()
+    }
+
+    /// Decodes an event emitted by `contract`, whose class is `class_hash` if
+    /// known.
+    pub(crate) fn decode(
+        &self,
+        contract: ContractAddress,
+        class_hash: Option<ClassHash>,
+        keys: &[Felt],
+        data: &[Felt],
+    ) -> Option<DecodedEvent> {
+        let abi = {
+            let inner = self.inner.read().unwrap();
+            inner
+                .contracts
+                .get(&contract)
+                .or_else(|| class_hash.and_then(|class_hash| inner.classes.get(&class_hash)))
+                .cloned()?
+        };
+
+        abi.decode(keys, data)
+    }
+}
+
+/// An event's name and fields, decoded using its contract's ABI.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize)]
+pub struct DecodedEvent {
+    pub name: String,
+    pub fields: Map<String, Value>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Item {
+    Struct {
+        name: String,
+        members: Vec<Member>,
+    },
+    Enum {
+        name: String,
+        variants: Vec<Member>,
+    },
+    Event(Event),
+    #[serde(other)]
+    Other,
+}
+
+/// Cairo 1 events have a `kind` and either `members` or `variants`, while
+/// Cairo 0 events list their `keys` and `data`.
+#[derive(Deserialize)]
+struct Event {
+    name: String,
+    kind: Option<EventKind>,
+    #[serde(default)]
+    members: Vec<Member>,
+    #[serde(default)]
+    variants: Vec<Member>,
+    #[serde(default)]
+    data: Vec<Member>,
+}
+
+#[derive(Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum EventKind {
+    Struct,
+    Enum,
+}
+
+#[derive(Deserialize)]
+struct Member {
+    name: String,
+    #[serde(rename = "type")]
+    ty: String,
+    kind: Option<MemberKind>,
+}
+
+#[derive(Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum MemberKind {
+    Key,
+    Data,
+    Nested,
+    Flat,
+}
+
+struct Abi {
+    structs: HashMap<String, Vec<Member>>,
+    enums: HashMap<String, Vec<Member>>,
+    events: HashMap<String, Event>,
+    /// Cairo 1 event enums which aren't a variant of another event, i.e. the
+    /// contract's `Event` enum.
+    roots: Vec<String>,
+    /// Cairo 0 event names by selector.
+    legacy_events: HashMap<Felt, String>,
+}
+
+impl Abi {
+    fn parse(abi: &str) -> anyhow::Result<Self> {
+        let items: Vec<Item> = serde_json::from_str(abi).context("Parsing ABI")?;
+
+        let mut structs = HashMap::new();
+        let mut enums = HashMap::new();
+        let mut events = HashMap::new();
+        let mut legacy_events = HashMap::new();
+        for item in items {
+            match item {
+                Item::Struct { name, members } => {
+                    structs.insert(name, members);
+                }
+                Item::Enum { name, variants } => {
+                    enums.insert(name, variants);
+                }
+                Item::Event(event) if event.kind.is_none() => {
+                    legacy_events.insert(selector(&event.name), event.name.clone());
+                    events.insert(event.name.clone(), event);
+                }
+                Item::Event(event) => {
+                    events.insert(event.name.clone(), event);
+                }
+                Item::Other => {}
+            }
+        }
+
+        let nested = events
+            .values()
+            .flat_map(|event| event.variants.iter().map(|variant| variant.ty.as_str()))
+            .collect::<std::collections::HashSet<_>>();
+        let roots = events
+            .values()
+            .filter(|event| event.kind == Some(EventKind::Enum) && !nested.contains(&*event.name))
+            .map(|event| event.name.clone())
+            .collect();
+
+        Ok(Self {
+            structs,
+            enums,
+            events,
+            roots,
+            legacy_events,
+        })
+    }
+
+    fn decode(&self, keys: &[Felt], data: &[Felt]) -> Option<DecodedEvent> {
+        if let Some(name) = keys.first().and_then(|s| self.legacy_events.get(s)) {
+            let mut data = data;
+            let decoded = self.decode_legacy_event(name, &mut data)?;
+            return data.is_empty().then_some(decoded);
+        }
+
+        self.roots.iter().find_map(|root| {
+            let (mut keys, mut data) = (keys, data);
+            let decoded = self.decode_event(root, &mut keys, &mut data)?;
+            (keys.is_empty() && data.is_empty()).then_some(decoded)
+        })
+    }
+
+    fn decode_event(
+        &self,
+        name: &str,
+        keys: &mut &[Felt],
+        data: &mut &[Felt],
+    ) -> Option<DecodedEvent> {
+        let event = self.events.get(name)?;
+
+        if event.kind == Some(EventKind::Enum) {
+            let (all_keys, all_data) = (*keys, *data);
+            let first_key = *all_keys.first()?;
+            for variant in &event.variants {
+                // Flat variants don't add their own selector to the keys.
+                let (mut variant_keys, mut variant_data) = match variant.kind {
+                    Some(MemberKind::Flat) => (all_keys, all_data),
+                    _ if selector(&variant.name) == first_key => (&all_keys[1..], all_data),
+                    _ => continue,
+                };
+                if let Some(decoded) =
+                    self.decode_event(&variant.ty, &mut variant_keys, &mut variant_data)
+                {
+                    (*keys, *data) = (variant_keys, variant_data);
+                    return Some(decoded);
+                }
+            }
+            return None;
+        }
+
+        let mut fields = Map::new();
+        for member in &event.members {
+            let felts = match member.kind {
+                Some(MemberKind::Key) => &mut *keys,
+                _ => &mut *data,
+            };
+            fields.insert(member.name.clone(), self.decode_value(&member.ty, felts)?);
+        }
+
+        Some(DecodedEvent {
+            name: name.to_owned(),
+            fields,
+        })
+    }
+
+    fn decode_legacy_event(&self, name: &str, data: &mut &[Felt]) -> Option<DecodedEvent> {
+        let event = self.events.get(name)?;
+
+        let mut fields = Map::new();
+        let mut previous = None;
+        for member in &event.data {
+            // Arrays are preceded by their length, e.g. `calldata_len` and
+            // `calldata`.
+            let value = match member.ty.strip_suffix('*') {
+                Some(element) => {
+                    let len = felt_to_usize(previous?)?;
+                    self.decode_array(element, len, data)?
+                }
+                None => {
+                    previous = data.first().copied();
+                    self.decode_value(&member.ty, data)?
+                }
+            };
+            fields.insert(member.name.clone(), value);
+        }
+
+        Some(DecodedEvent {
+            name: name.to_owned(),
+            fields,
+        })
+    }
+
+    fn decode_value(&self, ty: &str, felts: &mut &[Felt]) -> Option<Value> {
+        if ty == "()" {
+            return Some(Value::Null);
+        }
+        if let Some(elements) = tuple_elements(ty) {
+            return elements
+                .into_iter()
+                .map(|element| self.decode_value(element, felts))
+                .collect::<Option<Vec<_>>>()
+                .map(Value::Array);
+        }
+        if let Some(element) = array_element(ty) {
+            let len = felt_to_usize(next(felts)?)?;
+            return self.decode_array(element, len, felts);
+        }
+
+        match ty {
+            "core::bool" => {
+                let value = next(felts)?;
+                return match value {
+                    Felt::ZERO => Some(Value::Bool(false)),
+                    Felt::ONE => Some(Value::Bool(true)),
+                    _ => None,
+                };
+            }
+            "core::integer::u256" => {
+                let low = felt_to_u128(next(felts)?)?;
+                let high = felt_to_u128(next(felts)?)?;
+                let value = (U256::from(high) << 128) | U256::from(low);
+                return Some(Value::String(format!("{value:#x}")));
+            }
+            "core::byte_array::ByteArray" => return decode_byte_array(felts).map(Value::String),
+            _ => {}
+        }
+
+        if let Some(members) = self.structs.get(ty) {
+            let mut fields = Map::new();
+            for member in members {
+                fields.insert(member.name.clone(), self.decode_value(&member.ty, felts)?);
+            }
+            return Some(Value::Object(fields));
+        }
+        if let Some(variants) = self.enums.get(ty) {
+            let variant = variants.get(felt_to_usize(next(felts)?)?)?;
+            let value = self.decode_value(&variant.ty, felts)?;
+            return Some(Value::Object(Map::from_iter([(
+                variant.name.clone(),
+                value,
+            )])));
+        }
+
+        // Everything else, e.g. felt252, integers and addresses, is a single felt.
+        next(felts).map(|felt| Value::String(felt.to_hex_str().into_owned()))
+    }
+
+    fn decode_array(&self, element: &str, len: usize, felts: &mut &[Felt]) -> Option<Value> {
+        // Guards against bogus lengths, every element takes up at least one felt.
+        if len > felts.len() {
+            return None;
+        }
+
+        (0..len)
+            .map(|_| self.decode_value(element, felts))
+            .collect::<Option<Vec<_>>>()
+            .map(Value::Array)
+    }
+}
+
+fn selector(name: &str) -> Felt {
+    EntryPoint::hashed(name.as_bytes()).0
+}
+
+fn next(felts: &mut &[Felt]) -> Option<Felt> {
+    let (first, rest) = felts.split_first()?;
+    *felts = rest;
+    Some(*first)
+}
+
+fn felt_to_u128(felt: Felt) -> Option<u128> {
+    let bytes = felt.to_be_bytes();
+    if bytes[..16].iter().any(|b| *b != 0) {
+        return None;
+    }
+    Some(u128::from_be_bytes(bytes[16..].try_into().unwrap()))
+}
+
+fn felt_to_usize(felt: Felt) -> Option<usize> {
+    felt_to_u128(felt)?.try_into().ok()
+}
+
+/// Returns the element type of `core::array::Array::<T>` and
+/// `core::array::Span::<T>`.
+fn array_element(ty: &str) -> Option<&str> {
+    ty.strip_prefix("core::array::Array::<")
+        .or_else(|| ty.strip_prefix("core::array::Span::<"))?
+        .strip_suffix('>')
+}
+
+/// Splits a tuple type such as `(core::felt252, core::array::Array::<(u8,
+/// u8)>)` into its element types.
+fn tuple_elements(ty: &str) -> Option<Vec<&str>> {
+    let inner = ty.strip_prefix('(')?.strip_suffix(')')?;
+
+    let mut elements = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in inner.char_indices() {
+        match c {
+            '(' | '<' => depth += 1,
+            ')' | '>' => depth -= 1,
+            ',' if depth == 0 => {
+                elements.push(inner[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    elements.push(inner[start..].trim());
+
+    Some(elements)
+}
+
+/// Byte arrays consist of full 31 byte words followed by a partial one and its
+/// length in bytes.
+fn decode_byte_array(felts: &mut &[Felt]) -> Option<String> {
+    let len = felt_to_usize(next(felts)?)?;
+    if len > felts.len() {
+        return None;
+    }
+
+    let mut bytes = Vec::new();
+    for _ in 0..len {
+        bytes.extend_from_slice(&next(felts)?.to_be_bytes()[1..]);
+    }
+    let pending_word = next(felts)?.to_be_bytes();
+    let pending_len = felt_to_usize(next(felts)?)?;
+    if pending_len > 31 {
+        return None;
+    }
+    bytes.extend_from_slice(&pending_word[32 - pending_len..]);
+
+    Some(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::macro_prelude::*;
+    use serde_json::json;
+
+    use super::*;
+
+    const CAIRO_1_ABI: &str = r#"[
+        {"type": "function", "name": "transfer", "inputs": [], "outputs": []},
+        {"type": "struct", "name": "core::integer::u256", "members": [
+            {"name": "low", "type": "core::integer::u128"},
+            {"name": "high", "type": "core::integer::u128"}
+        ]},
+        {"type": "event", "name": "token::Transfer", "kind": "struct", "members": [
+            {"name": "from", "type": "core::starknet::contract_address::ContractAddress", "kind": "key"},
+            {"name": "to", "type": "core::starknet::contract_address::ContractAddress", "kind": "key"},
+            {"name": "value", "type": "core::integer::u256", "kind": "data"},
+            {"name": "memo", "type": "core::array::Array::<core::felt252>", "kind": "data"}
+        ]},
+        {"type": "event", "name": "ownable::OwnershipTransferred", "kind": "struct", "members": [
+            {"name": "new_owner", "type": "core::starknet::contract_address::ContractAddress", "kind": "data"}
+        ]},
+        {"type": "event", "name": "ownable::Event", "kind": "enum", "variants": [
+            {"name": "OwnershipTransferred", "type": "ownable::OwnershipTransferred", "kind": "nested"}
+        ]},
+        {"type": "event", "name": "token::Event", "kind": "enum", "variants": [
+            {"name": "Transfer", "type": "token::Transfer", "kind": "nested"},
+            {"name": "OwnableEvent", "type": "ownable::Event", "kind": "flat"}
+        ]}
+    ]"#;
+
+    const CAIRO_0_ABI: &str = r#"[
+        {"type": "struct", "name": "Uint256", "size": 2, "members": [
+            {"name": "low", "type": "felt", "offset": 0},
+            {"name": "high", "type": "felt", "offset": 1}
+        ]},
+        {"type": "event", "name": "Transfer", "keys": [], "data": [
+            {"name": "from_", "type": "felt"},
+            {"name": "value", "type": "Uint256"},
+            {"name": "memo_len", "type": "felt"},
+            {"name": "memo", "type": "felt*"}
+        ]}
+    ]"#;
+
+    fn registry(abi: &str) -> AbiRegistry {
+        let registry = AbiRegistry::default();
+        registry
+            .register(AbiTarget::Contract(contract_address!("0x1")), abi)
+            .unwrap();
+        registry
+    }
+
+    #[test]
+    fn cairo_1_struct_event() {
+        let decoded = registry(CAIRO_1_ABI)
+            .decode(
+                contract_address!("0x1"),
+                None,
+                &[selector("Transfer"), felt!("0xa"), felt!("0xb")],
+                &[felt!("0x5"), felt!("0x1"), felt!("0x1"), felt!("0x123")],
+            )
+            .unwrap();
+
+        assert_eq!(decoded.name, "token::Transfer");
+        assert_eq!(
+            Value::Object(decoded.fields),
+            json!({
+                "from": "0xa",
+                "to": "0xb",
+                "value": "0x100000000000000000000000000000005",
+                "memo": ["0x123"],
+            })
+        );
+    }
+
+    #[test]
+    fn cairo_1_flat_event() {
+        let decoded = registry(CAIRO_1_ABI)
+            .decode(
+                contract_address!("0x1"),
+                None,
+                &[selector("OwnershipTransferred")],
+                &[felt!("0xc")],
+            )
+            .unwrap();
+
+        assert_eq!(decoded.name, "ownable::OwnershipTransferred");
+        assert_eq!(Value::Object(decoded.fields), json!({"new_owner": "0xc"}));
+    }
+
+    #[test]
+    fn cairo_0_event() {
+        let decoded = registry(CAIRO_0_ABI)
+            .decode(
+                contract_address!("0x1"),
+                None,
+                &[selector("Transfer")],
+                &[
+                    felt!("0xa"),
+                    felt!("0x5"),
+                    felt!("0x0"),
+                    felt!("0x2"),
+                    felt!("0x3"),
+                    felt!("0x4"),
+                ],
+            )
+            .unwrap();
+
+        assert_eq!(decoded.name, "Transfer");
+        assert_eq!(
+            Value::Object(decoded.fields),
+            json!({
+                "from_": "0xa",
+                "value": {"low": "0x5", "high": "0x0"},
+                "memo_len": "0x2",
+                "memo": ["0x3", "0x4"],
+            })
+        );
+    }
+
+    #[test]
+    fn mismatching_events_are_not_decoded() {
+        let registry = registry(CAIRO_1_ABI);
+
+        // Unknown selector.
+        assert_eq!(
+            registry.decode(contract_address!("0x1"), None, &[felt!("0x1")], &[]),
+            None
+        );
+        // Missing data.
+        assert_eq!(
+            registry.decode(
+                contract_address!("0x1"),
+                None,
+                &[selector("Transfer"), felt!("0xa"), felt!("0xb")],
+                &[felt!("0x5")],
+            ),
+            None
+        );
+        // Unregistered contract.
+        assert_eq!(
+            registry.decode(
+                contract_address!("0x2"),
+                None,
+                &[selector("OwnershipTransferred")],
+                &[felt!("0xc")],
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn class_abis() {
+        let registry = AbiRegistry::default();
+        registry
+            .register(AbiTarget::Class(class_hash!("0x10")), CAIRO_1_ABI)
+            .unwrap();
+
+        assert!(registry.has_class_abis());
+        assert!(registry
+            .decode(
+                contract_address!("0x2"),
+                Some(class_hash!("0x10")),
+                &[selector("OwnershipTransferred")],
+                &[felt!("0xc")],
+            )
+            .is_some());
+    }
+
+    #[test]
+    fn byte_array() {
+        let mut felts: &[Felt] = &[
+            felt!("0x0"),
+            Felt::from_be_slice(b"hello").unwrap(),
+            felt!("0x5"),
+        ];
+        assert_eq!(decode_byte_array(&mut felts).unwrap(), "hello");
+        assert!(felts.is_empty());
+    }
+
+    #[test]
+    fn tuples() {
+        assert_eq!(
+            tuple_elements("(core::felt252, core::array::Array::<(u8, u8)>)").unwrap(),
+            vec!["core::felt252", "core::array::Array::<(u8, u8)>"]
+        );
+        assert_eq!(tuple_elements("core::felt252"), None);
+    }
+
+    #[test]
+    fn directory_persists_registrations() {
+        let directory = tempfile::tempdir().unwrap();
+
+        let registry = AbiRegistry::load(directory.path().to_owned()).unwrap();
+        registry
+            .register(AbiTarget::Contract(contract_address!("0x1")), CAIRO_0_ABI)
+            .unwrap();
+
+        let registry = AbiRegistry::load(directory.path().to_owned()).unwrap();
+        assert!(registry
+            .decode(
+                contract_address!("0x1"),
+                None,
+                &[selector("Transfer")],
+                &[felt!("0xa"), felt!("0x5"), felt!("0x0"), felt!("0x0")],
+            )
+            .is_some());
+    }
+
+    #[test]
+    fn invalid_abi_is_rejected() {
+        let directory = tempfile::tempdir().unwrap();
+
+        let registry = AbiRegistry::load(directory.path().to_owned()).unwrap();
+        let error = registry
+            .register(
+                AbiTarget::Class(class_hash!("0x10")),
+                r#"{"not": "an abi"}"#,
+            )
+            .unwrap_err();
+        assert_matches::assert_matches!(error, RegisterAbiError::InvalidAbi(_));
+
+        let registry = AbiRegistry::load(directory.path().to_owned()).unwrap();
+        assert!(!registry.has_class_abis());
+    }
+
+    #[test]
+    fn oversized_abi_is_rejected() {
+        let registry = AbiRegistry::default();
+        let abi = format!("[{}]", " ".repeat(MAX_ABI_SIZE));
+
+        let error = registry
+            .register(AbiTarget::Class(class_hash!("0x10")), &abi)
+            .unwrap_err();
+        assert_matches::assert_matches!(error, RegisterAbiError::TooLarge);
+    }
+
+    #[test]
+    fn number_of_abis_is_limited() {
+        let registry = AbiRegistry::default();
+        {
+            let mut inner = registry.inner.write().unwrap();
+            let abi = Arc::new(Abi::parse(CAIRO_0_ABI).unwrap());
+            for i in 0..MAX_ABIS as u64 {
+                inner
+                    .contracts
+                    .insert(ContractAddress(Felt::from_u64(i)), abi.clone());
+            }
+        }
+
+        let error = registry
+            .register(AbiTarget::Class(class_hash!("0x10")), CAIRO_0_ABI)
+            .unwrap_err();
+        assert_matches::assert_matches!(error, RegisterAbiError::TooMany);
+
+        // Replacing a registered ABI is still possible.
+        registry
+            .register(AbiTarget::Contract(contract_address!("0x1")), CAIRO_0_ABI)
+            .unwrap();
+    }
+}
@@ -0,0 +1,39 @@
+//! The status of the node's scheduled database backups, reported by
+//! `pathfinder_getBackupStatus`.
+//!
+//! Backups are made by the node outside of the RPC server, which publishes
+//! their status using a [watch](tokio::sync::watch) channel passed to
+//! [RpcContext::with_backup_status](crate::context::RpcContext::with_backup_status).
+
+use pathfinder_common::{BlockHash, BlockNumber};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BackupStatus {
+    /// Whether a backup is being made right now.
+    pub in_progress: bool,
+    /// The latest backup which was uploaded successfully.
+    pub last_backup: Option<Backup>,
+    /// The latest attempt which failed, cleared once a backup succeeds.
+    pub last_failure: Option<BackupFailure>,
+    /// Unix timestamp at which the next backup starts.
+    pub next_backup_at: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Backup {
+    /// The object key of the backup in the bucket.
+    pub key: String,
+    /// The latest block in the backup, `None` if the database had no blocks.
+    pub block: Option<(BlockNumber, BlockHash)>,
+    /// The size of the compressed backup, in bytes.
+    pub size: u64,
+    /// Unix timestamp at which the upload completed.
+    pub finished_at: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackupFailure {
+    pub error: String,
+    /// Unix timestamp at which the attempt failed.
+    pub failed_at: u64,
+}
@@ -0,0 +1,507 @@
+//! Descriptions of the JSON-RPC methods for clients, see the
+//! `pathfinder-rpc-client` crate.
+//!
+//! Each method is described by a [Method] implementation, which reuses the
+//! input and output types of the server. Its parameters are encoded and its
+//! result decoded exactly like the server does the reverse, so that the client
+//! cannot drift from the server.
+//!
+//! Methods whose input and output types can't be encoded and decoded by
+//! clients yet are described with their parameters and result as plain JSON,
+//! so that every method of the API has a description. Subscriptions are not
+//! described, as they don't return a single result.
+
+use crate::dto::serialize::{SerializeForVersion, Serializer};
+use crate::dto::DeserializeForVersion;
+pub use crate::method::block_hash_and_number::Output as BlockHashAndNumberOutput;
+pub use crate::method::block_number::Output as BlockNumberOutput;
+pub use crate::method::chain_id::Output as ChainIdOutput;
+pub use crate::method::get_block_transaction_count::{
+    Input as GetBlockTransactionCountInput,
+    Output as GetBlockTransactionCountOutput,
+};
+pub use crate::method::get_class_hash_at::{
+    Input as GetClassHashAtInput,
+    Output as GetClassHashAtOutput,
+};
+pub use crate::method::get_nonce::{Input as GetNonceInput, Output as GetNonceOutput};
+pub use crate::method::get_storage_at::{Input as GetStorageAtInput, Output as GetStorageAtOutput};
+use crate::RpcVersion;
+
+/// A JSON-RPC method along with its input and output types.
+pub trait Method {
+    /// The name of the method, e.g. `starknet_getStorageAt`.
+    const NAME: &'static str;
+
+    type Input;
+    type Output;
+
+    /// Encodes `input` as the method's parameters, which are `None` for
+    /// methods without parameters.
+    fn params(
+        input: &Self::Input,
+        version: RpcVersion,
+    ) -> Result<Option<serde_json::Value>, serde_json::Error>;
+
+    /// Decodes the method's result.
+    fn output(
+        result: serde_json::Value,
+        version: RpcVersion,
+    ) -> Result<Self::Output, serde_json::Error>;
+}
+
+macro_rules! method {
+    ($(#[$attr:meta])* $method:ident, $name:literal, (), json) => {
+        $(#[$attr])*
+        pub struct $method;
+
+        impl Method for $method {
+            const NAME: &'static str = $name;
+
+            type Input = ();
+            type Output = serde_json::Value;
+
+            fn params(
+                _: &Self::Input,
+                _: RpcVersion,
+            ) -> Result<Option<serde_json::Value>, serde_json::Error> {
+                Ok(None)
+            }
+
+            fn output(
+                result: serde_json::Value,
+                _: RpcVersion,
+            ) -> Result<Self::Output, serde_json::Error> {
+                Ok(result)
+            }
+        }
+    };
+    ($(#[$attr:meta])* $method:ident, $name:literal, json, json) => {
+        $(#[$attr])*
+        pub struct $method;
+
+        impl Method for $method {
+            const NAME: &'static str = $name;
+
+            type Input = serde_json::Value;
+            type Output = serde_json::Value;
+
+            fn params(
+                input: &Self::Input,
+                _: RpcVersion,
+            ) -> Result<Option<serde_json::Value>, serde_json::Error> {
+                Ok(Some(input.clone()))
+            }
+
+            fn output(
+                result: serde_json::Value,
+                _: RpcVersion,
+            ) -> Result<Self::Output, serde_json::Error> {
+                Ok(result)
+            }
+        }
+    };
+    ($(#[$attr:meta])* $method:ident, $name:literal, (), $output:ty) => {
+        $(#[$attr])*
+        pub struct $method;
+
+        impl Method for $method {
+            const NAME: &'static str = $name;
+
+            type Input = ();
+            type Output = $output;
+
+            fn params(
+                _: &Self::Input,
+                _: RpcVersion,
+            ) -> Result<Option<serde_json::Value>, serde_json::Error> {
+                Ok(None)
+            }
+
+            fn output(
+                result: serde_json::Value,
+                version: RpcVersion,
+            ) -> Result<Self::Output, serde_json::Error> {
+                crate::dto::Value::new(result, version).deserialize()
+            }
+        }
+    };
+    ($(#[$attr:meta])* $method:ident, $name:literal, $input:ty, $output:ty) => {
+        $(#[$attr])*
+        pub struct $method;
+
+        impl Method for $method {
+            const NAME: &'static str = $name;
+
+            type Input = $input;
+            type Output = $output;
+
+            fn params(
+                input: &Self::Input,
+                version: RpcVersion,
+            ) -> Result<Option<serde_json::Value>, serde_json::Error> {
+                input.serialize(Serializer::new(version)).map(Some)
+            }
+
+            fn output(
+                result: serde_json::Value,
+                version: RpcVersion,
+            ) -> Result<Self::Output, serde_json::Error> {
+                crate::dto::Value::new(result, version).deserialize()
+            }
+        }
+    };
+}
+
+method!(
+    /// `starknet_chainId`
+    ChainId, "starknet_chainId", (), ChainIdOutput
+);
+method!(
+    /// `starknet_blockNumber`
+    BlockNumber, "starknet_blockNumber", (), BlockNumberOutput
+);
+method!(
+    /// `starknet_blockHashAndNumber`
+    BlockHashAndNumber, "starknet_blockHashAndNumber", (), BlockHashAndNumberOutput
+);
+method!(
+    /// `starknet_getBlockTransactionCount`
+    GetBlockTransactionCount,
+    "starknet_getBlockTransactionCount",
+    GetBlockTransactionCountInput,
+    GetBlockTransactionCountOutput
+);
+method!(
+    /// `starknet_getClassHashAt`
+    GetClassHashAt, "starknet_getClassHashAt", GetClassHashAtInput, GetClassHashAtOutput
+);
+method!(
+    /// `starknet_getNonce`
+    GetNonce, "starknet_getNonce", GetNonceInput, GetNonceOutput
+);
+method!(
+    /// `starknet_getStorageAt`
+    GetStorageAt, "starknet_getStorageAt", GetStorageAtInput, GetStorageAtOutput
+);
+
+// Methods described with plain JSON, see the module documentation.
+method!(
+    /// `starknet_specVersion`
+    SpecVersion, "starknet_specVersion", (), json
+);
+method!(
+    /// `starknet_syncing`
+    Syncing, "starknet_syncing", (), json
+);
+method!(
+    /// `pathfinder_version`
+    Version, "pathfinder_version", (), json
+);
+method!(
+    /// `pathfinder_getSyncLag`
+    GetSyncLag, "pathfinder_getSyncLag", (), json
+);
+method!(
+    /// `pathfinder_getMigrationStatus`
+    GetMigrationStatus, "pathfinder_getMigrationStatus", (), json
+);
+method!(
+    /// `pathfinder_getBackupStatus`
+    GetBackupStatus, "pathfinder_getBackupStatus", (), json
+);
+method!(
+    /// `pathfinder_health`
+    Health, "pathfinder_health", (), json
+);
+method!(
+    /// `starknet_addDeclareTransaction`
+    AddDeclareTransaction, "starknet_addDeclareTransaction", json, json
+);
+method!(
+    /// `starknet_addDeployAccountTransaction`
+    AddDeployAccountTransaction, "starknet_addDeployAccountTransaction", json, json
+);
+method!(
+    /// `starknet_addInvokeTransaction`
+    AddInvokeTransaction, "starknet_addInvokeTransaction", json, json
+);
+method!(
+    /// `starknet_call`
+    Call, "starknet_call", json, json
+);
+method!(
+    /// `starknet_estimateFee`
+    EstimateFee, "starknet_estimateFee", json, json
+);
+method!(
+    /// `starknet_estimateMessageFee`
+    EstimateMessageFee, "starknet_estimateMessageFee", json, json
+);
+method!(
+    /// `starknet_getBlockWithReceipts`
+    GetBlockWithReceipts, "starknet_getBlockWithReceipts", json, json
+);
+method!(
+    /// `starknet_getBlockWithTxHashes`
+    GetBlockWithTxHashes, "starknet_getBlockWithTxHashes", json, json
+);
+method!(
+    /// `starknet_getBlockWithTxs`
+    GetBlockWithTxs, "starknet_getBlockWithTxs", json, json
+);
+method!(
+    /// `starknet_getClass`
+    GetClass, "starknet_getClass", json, json
+);
+method!(
+    /// `starknet_getClassAt`
+    GetClassAt, "starknet_getClassAt", json, json
+);
+method!(
+    /// `starknet_getCompiledCasm`
+    GetCompiledCasm, "starknet_getCompiledCasm", json, json
+);
+method!(
+    /// `starknet_getEvents`
+    GetEvents, "starknet_getEvents", json, json
+);
+method!(
+    /// `starknet_getStateUpdate`
+    GetStateUpdate, "starknet_getStateUpdate", json, json
+);
+method!(
+    /// `starknet_getStorageProof`
+    GetStorageProof, "starknet_getStorageProof", json, json
+);
+method!(
+    /// `starknet_getTransactionByBlockIdAndIndex`
+    GetTransactionByBlockIdAndIndex, "starknet_getTransactionByBlockIdAndIndex", json, json
+);
+method!(
+    /// `starknet_getTransactionByHash`
+    GetTransactionByHash, "starknet_getTransactionByHash", json, json
+);
+method!(
+    /// `starknet_getTransactionReceipt`
+    GetTransactionReceipt, "starknet_getTransactionReceipt", json, json
+);
+method!(
+    /// `starknet_getTransactionStatus`
+    GetTransactionStatus, "starknet_getTransactionStatus", json, json
+);
+method!(
+    /// `starknet_simulateTransactions`
+    SimulateTransactions, "starknet_simulateTransactions", json, json
+);
+method!(
+    /// `starknet_traceBlockTransactions`
+    TraceBlockTransactions, "starknet_traceBlockTransactions", json, json
+);
+method!(
+    /// `starknet_traceTransaction`
+    TraceTransaction, "starknet_traceTransaction", json, json
+);
+method!(
+    /// `pathfinder_getProof`
+    GetProof, "pathfinder_getProof", json, json
+);
+method!(
+    /// `pathfinder_getClassProof`
+    GetClassProof, "pathfinder_getClassProof", json, json
+);
+method!(
+    /// `pathfinder_getReceiptProof`
+    GetReceiptProof, "pathfinder_getReceiptProof", json, json
+);
+method!(
+    /// `pathfinder_getStorageAtBlocks`
+    GetStorageAtBlocks, "pathfinder_getStorageAtBlocks", json, json
+);
+method!(
+    /// `pathfinder_getStorageBatch`
+    GetStorageBatch, "pathfinder_getStorageBatch", json, json
+);
+method!(
+    /// `pathfinder_getStorageHistory`
+    GetStorageHistory, "pathfinder_getStorageHistory", json, json
+);
+method!(
+    /// `pathfinder_getNonceAt`
+    GetNonceAt, "pathfinder_getNonceAt", json, json
+);
+method!(
+    /// `pathfinder_getStateUpdateRange`
+    GetStateUpdateRange, "pathfinder_getStateUpdateRange", json, json
+);
+method!(
+    /// `pathfinder_getFeeHistory`
+    GetFeeHistory, "pathfinder_getFeeHistory", json, json
+);
+method!(
+    /// `pathfinder_getBlockStats`
+    GetBlockStats, "pathfinder_getBlockStats", json, json
+);
+method!(
+    /// `pathfinder_getClassDefinitions`
+    GetClassDefinitions, "pathfinder_getClassDefinitions", json, json
+);
+method!(
+    /// `pathfinder_getClassInfo`
+    GetClassInfo, "pathfinder_getClassInfo", json, json
+);
+method!(
+    /// `pathfinder_getContractStateHash`
+    GetContractStateHash, "pathfinder_getContractStateHash", json, json
+);
+method!(
+    /// `pathfinder_getContractStorageKeys`
+    GetContractStorageKeys, "pathfinder_getContractStorageKeys", json, json
+);
+method!(
+    /// `pathfinder_getTransactionStatus`
+    PathfinderGetTransactionStatus, "pathfinder_getTransactionStatus", json, json
+);
+method!(
+    /// `pathfinder_getL1ToL2MessageStatus`
+    GetL1ToL2MessageStatus, "pathfinder_getL1ToL2MessageStatus", json, json
+);
+method!(
+    /// `pathfinder_getMessageToL1Proof`
+    GetMessageToL1Proof, "pathfinder_getMessageToL1Proof", json, json
+);
+method!(
+    /// `pathfinder_getTransactionsByAccount`
+    GetTransactionsByAccount, "pathfinder_getTransactionsByAccount", json, json
+);
+method!(
+    /// `pathfinder_getReorgs`
+    GetReorgs, "pathfinder_getReorgs", json, json
+);
+method!(
+    /// `pathfinder_compareTrace`
+    CompareTrace, "pathfinder_compareTrace", json, json
+);
+method!(
+    /// `pathfinder_traceTransactionFlame`
+    TraceTransactionFlame, "pathfinder_traceTransactionFlame", json, json
+);
+method!(
+    /// `pathfinder_getSubmittedTransactions`
+    GetSubmittedTransactions, "pathfinder_getSubmittedTransactions", json, json
+);
+method!(
+    /// `pathfinder_getBalances`
+    GetBalances, "pathfinder_getBalances", json, json
+);
+method!(
+    /// `pathfinder_registerAbi`
+    RegisterAbi, "pathfinder_registerAbi", json, json
+);
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::macro_prelude::*;
+    use pathfinder_common::BlockId;
+
+    use super::*;
+
+    /// Checks that the server decodes the parameters encoded for `input` back
+    /// into `input`, and that the client decodes the result encoded by the
+    /// server for `output` back into `output`.
+    fn round_trip<M>(input: M::Input, output: M::Output)
+    where
+        M: Method,
+        M::Input: DeserializeForVersion + PartialEq + std::fmt::Debug,
+        M::Output: SerializeForVersion + PartialEq + std::fmt::Debug,
+    {
+        for version in [RpcVersion::V06, RpcVersion::V07, RpcVersion::V08] {
+            let params = M::params(&input, version).unwrap().unwrap();
+            let decoded: M::Input = crate::dto::Value::new(params, version)
+                .deserialize()
+                .unwrap();
+            assert_eq!(decoded, input, "{} input for {version:?}", M::NAME);
+
+            let result = output.serialize(Serializer::new(version)).unwrap();
+            let decoded = M::output(result, version).unwrap();
+            assert_eq!(decoded, output, "{} output for {version:?}", M::NAME);
+        }
+    }
+
+    #[test]
+    fn methods_without_params() {
+        for version in [RpcVersion::V06, RpcVersion::V07, RpcVersion::V08] {
+            assert_eq!(ChainId::params(&(), version).unwrap(), None);
+
+            let output = ChainIdOutput(pathfinder_common::ChainId::SEPOLIA_TESTNET);
+            let result = output.serialize(Serializer::new(version)).unwrap();
+            assert_eq!(ChainId::output(result, version).unwrap(), output);
+
+            let output = BlockNumberOutput(pathfinder_common::BlockNumber::new_or_panic(5));
+            let result = output.serialize(Serializer::new(version)).unwrap();
+            assert_eq!(BlockNumber::output(result, version).unwrap(), output);
+
+            let output = BlockHashAndNumberOutput {
+                number: pathfinder_common::BlockNumber::new_or_panic(5),
+                hash: block_hash!("0x5"),
+            };
+            let result = output.serialize(Serializer::new(version)).unwrap();
+            assert_eq!(BlockHashAndNumber::output(result, version).unwrap(), output);
+        }
+    }
+
+    #[test]
+    fn json_methods() {
+        let params = serde_json::json!({"block_id": "latest"});
+        let result = serde_json::json!({"status": "ok"});
+
+        assert_eq!(
+            GetStateUpdate::params(&params, RpcVersion::V07).unwrap(),
+            Some(params)
+        );
+        assert_eq!(
+            GetStateUpdate::output(result.clone(), RpcVersion::V07).unwrap(),
+            result
+        );
+        assert_eq!(Syncing::params(&(), RpcVersion::V07).unwrap(), None);
+    }
+
+    #[test]
+    fn methods_with_params() {
+        let block_ids = [
+            BlockId::Latest,
+            BlockId::Pending,
+            BlockId::Number(pathfinder_common::BlockNumber::new_or_panic(3)),
+            BlockId::Hash(block_hash!("0x3")),
+        ];
+
+        for block_id in block_ids {
+            round_trip::<GetBlockTransactionCount>(
+                GetBlockTransactionCountInput { block_id },
+                GetBlockTransactionCountOutput(4),
+            );
+            round_trip::<GetClassHashAt>(
+                GetClassHashAtInput {
+                    block_id,
+                    contract_address: contract_address!("0x1"),
+                },
+                GetClassHashAtOutput(class_hash!("0x2")),
+            );
+            round_trip::<GetNonce>(
+                GetNonceInput {
+                    block_id,
+                    contract_address: contract_address!("0x1"),
+                },
+                GetNonceOutput(contract_nonce!("0x2")),
+            );
+            round_trip::<GetStorageAt>(
+                GetStorageAtInput {
+                    contract_address: contract_address!("0x1"),
+                    key: storage_address!("0x2"),
+                    block_id,
+                },
+                GetStorageAtOutput(storage_value!("0x3")),
+            );
+        }
+    }
+}
@@ -1,13 +1,26 @@
 use std::num::NonZeroUsize;
 use std::sync::Arc;
+use std::time::Duration;
 
 use pathfinder_common::ChainId;
-use pathfinder_executor::{TraceCache, VersionedConstants};
-use pathfinder_storage::Storage;
+use pathfinder_executor::{ClassCache, ExecutionLimits, TraceCache, VersionedConstants};
+use pathfinder_merkle_tree::TrieNodeCache;
+use pathfinder_storage::{Storage, SyncMode};
 
+use crate::abi_registry::AbiRegistry;
+use crate::backup::BackupStatus;
+use crate::fork::Fork;
+use crate::health::HealthStatus;
 pub use crate::jsonrpc::websocket::WebsocketContext;
 use crate::jsonrpc::Notifications;
+use crate::mempool::Mempool;
+use crate::middleware::rate_limit::{RateLimitConfig, RateLimiter};
+use crate::middleware::request_log::RequestLogConfig;
 use crate::pending::{PendingData, PendingWatcher};
+use crate::record::Recorder;
+use crate::shutdown::ShutdownCoordinator;
+use crate::storage_root_cache::StorageRootCache;
+use crate::submissions::SubmissionTracker;
 use crate::SyncState;
 
 type SequencerClient = starknet_gateway_client::Client;
@@ -16,9 +29,33 @@ use tokio::sync::watch as tokio_watch;
 #[derive(Clone)]
 pub struct RpcConfig {
     pub batch_concurrency_limit: NonZeroUsize,
+    /// The maximum number of requests in a single batch.
+    pub max_batch_size: NonZeroUsize,
+    /// The serialized size in bytes after which paged methods cut their page
+    /// short and return a continuation token.
+    pub max_response_size: Option<NonZeroUsize>,
     pub get_events_max_blocks_to_scan: NonZeroUsize,
     pub get_events_max_uncached_bloom_filters_to_load: NonZeroUsize,
     pub custom_versioned_constants: Option<VersionedConstants>,
+    /// The maximum age of the latest block for sync to be considered healthy.
+    pub health_max_block_age: Duration,
+    /// The number of blocks whose traces are kept in the [TraceCache].
+    pub trace_cache_size: NonZeroUsize,
+    /// The memory budget of the [TrieNodeCache], in bytes.
+    pub trie_node_cache_size: usize,
+    /// Limits on the resources used by `starknet_call` and
+    /// `starknet_estimateFee`.
+    pub execution_limits: ExecutionLimits,
+    /// The maximum time `starknet_call` and `starknet_estimateFee` may take.
+    pub execution_timeout: Option<Duration>,
+    /// Whether submitted transactions are validated locally before being
+    /// forwarded to the gateway.
+    pub validate_transactions: bool,
+    /// Controls which method calls are logged.
+    pub request_log: RequestLogConfig,
+    /// In [SyncMode::Light] methods which read state fail, since the database
+    /// has none.
+    pub sync_mode: SyncMode,
 }
 
 #[derive(Clone)]
@@ -29,10 +66,30 @@ pub struct RpcContext {
     pub pending_data: PendingWatcher,
     pub sync_status: Arc<SyncState>,
     pub chain_id: ChainId,
-    pub sequencer: SequencerClient,
+    /// [None] in offline mode, where nothing is fetched from or forwarded to
+    /// the gateway.
+    pub sequencer: Option<SequencerClient>,
     pub websocket: Option<WebsocketContext>,
     pub notifications: Notifications,
     pub config: RpcConfig,
+    pub shutdown: ShutdownCoordinator,
+    pub(crate) storage_root_cache: Arc<StorageRootCache>,
+    pub(crate) trie_node_cache: TrieNodeCache,
+    /// Classes loaded by `starknet_call`, shared by calls on top of the same
+    /// block.
+    pub(crate) class_cache: ClassCache,
+    pub(crate) mempool: Mempool,
+    /// Records submitted transactions in the database, if enabled.
+    pub(crate) submissions: Option<SubmissionTracker>,
+    /// Set in fork mode, where submitted transactions are executed locally.
+    pub(crate) fork: Option<Fork>,
+    pub(crate) rate_limiter: Option<Arc<RateLimiter>>,
+    /// Records every method call and its response, if enabled.
+    pub(crate) recorder: Option<Arc<Recorder>>,
+    /// ABIs used to decode events when requested.
+    pub(crate) abis: AbiRegistry,
+    /// Set if the node makes scheduled backups.
+    pub(crate) backup_status: Option<tokio_watch::Receiver<BackupStatus>>,
 }
 
 impl RpcContext {
@@ -42,14 +99,16 @@ impl RpcContext {
         execution_storage: Storage,
         sync_status: Arc<SyncState>,
         chain_id: ChainId,
-        sequencer: SequencerClient,
+        sequencer: Option<SequencerClient>,
         pending_data: tokio_watch::Receiver<PendingData>,
         notifications: Notifications,
         config: RpcConfig,
     ) -> Self {
         let pending_data = PendingWatcher::new(pending_data);
         Self {
-            cache: Default::default(),
+            cache: TraceCache::with_size(config.trace_cache_size),
+            trie_node_cache: TrieNodeCache::with_memory_budget(config.trie_node_cache_size),
+            class_cache: Default::default(),
             storage,
             execution_storage,
             sync_status,
@@ -59,6 +118,15 @@ impl RpcContext {
             websocket: None,
             notifications,
             config,
+            shutdown: Default::default(),
+            storage_root_cache: Default::default(),
+            mempool: Default::default(),
+            submissions: None,
+            fork: None,
+            rate_limiter: None,
+            recorder: None,
+            abis: Default::default(),
+            backup_status: None,
         }
     }
 
@@ -102,25 +170,25 @@ impl RpcContext {
         let sync_state = Arc::new(SyncState::default());
         let (_, rx) = tokio_watch::channel(Default::default());
 
-        let config = RpcConfig {
-            batch_concurrency_limit: NonZeroUsize::new(8).unwrap(),
-            get_events_max_blocks_to_scan: NonZeroUsize::new(1000).unwrap(),
-            get_events_max_uncached_bloom_filters_to_load: NonZeroUsize::new(1000).unwrap(),
-            custom_versioned_constants: None,
-        };
-
         Self::new(
             storage.clone(),
             storage,
             sync_state,
             chain_id,
-            sequencer.disable_retry_for_tests(),
+            Some(sequencer.disable_retry_for_tests()),
             rx,
             Notifications::default(),
-            config,
+            crate::fixture::config(),
         )
     }
 
+    /// The gateway client, or an error in offline mode.
+    pub(crate) fn gateway(&self) -> anyhow::Result<&SequencerClient> {
+        self.sequencer
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("The gateway is not available in offline mode"))
+    }
+
     pub fn with_storage(self, storage: Storage) -> Self {
         Self {
             storage: storage.clone(),
@@ -156,4 +224,79 @@ impl RpcContext {
             ..self
         }
     }
+
+    pub fn with_rate_limits(self, config: RateLimitConfig) -> Self {
+        self.with_rate_limiter(Arc::new(RateLimiter::new(config)))
+    }
+
+    /// Applies the limits of `rate_limiter`, which may be reconfigured while in
+    /// use.
+    pub fn with_rate_limiter(self, rate_limiter: Arc<RateLimiter>) -> Self {
+        Self {
+            rate_limiter: Some(rate_limiter),
+            ..self
+        }
+    }
+
+    /// Records every method call and its response using `recorder`, see
+    /// [crate::record].
+    pub fn with_recorder(self, recorder: Recorder) -> Self {
+        Self {
+            recorder: Some(Arc::new(recorder)),
+            ..self
+        }
+    }
+
+    /// Records transactions submitted to the gateway in the database, where
+    /// they are listed by `pathfinder_getSubmittedTransactions`.
+    pub fn with_submission_tracking(self, tracker: SubmissionTracker) -> Self {
+        Self {
+            submissions: Some(tracker),
+            ..self
+        }
+    }
+
+    /// Executes submitted transactions locally instead of forwarding them to
+    /// the gateway. `pending_data` must be the sender of the pending data this
+    /// context was created with, as the results are published through it.
+    pub fn with_fork(self, pending_data: tokio_watch::Sender<PendingData>) -> Self {
+        Self {
+            fork: Some(Fork::new(pending_data)),
+            ..self
+        }
+    }
+
+    /// Decodes events using the ABIs of `registry`, which also keeps the ABIs
+    /// registered using `pathfinder_registerAbi`.
+    pub fn with_abi_registry(self, registry: AbiRegistry) -> Self {
+        Self {
+            abis: registry,
+            ..self
+        }
+    }
+
+    /// Reports the status of the node's scheduled backups in
+    /// `pathfinder_getBackupStatus`.
+    pub fn with_backup_status(self, status: tokio_watch::Receiver<BackupStatus>) -> Self {
+        Self {
+            backup_status: Some(status),
+            ..self
+        }
+    }
+
+    /// Whether the database has the state `method` reads, which it doesn't in
+    /// [SyncMode::Light].
+    pub(crate) fn has_state_for(&self, method: &str) -> bool {
+        self.config.sync_mode == SyncMode::Full
+            || !crate::middleware::light_mode::requires_state(method)
+    }
+
+    /// Reports whether the database is reachable, sync has not stalled and the
+    /// pending data is fresh.
+    ///
+    /// This only reads the latest block header, so it is cheap enough to be
+    /// polled frequently.
+    pub async fn health(&self) -> HealthStatus {
+        crate::health::check(self, self.config.health_max_block_age, crate::health::now()).await
+    }
 }
@@ -10,6 +10,9 @@ pub struct BlockHeader<'a>(pub &'a pathfinder_common::BlockHeader);
 #[derive(Debug)]
 pub struct PendingBlockHeader<'a>(pub &'a starknet_gateway_types::reply::PendingBlock);
 
+#[derive(Debug)]
+pub struct BlockId<'a>(pub &'a pathfinder_common::BlockId);
+
 impl crate::dto::DeserializeForVersion for pathfinder_common::BlockId {
     fn deserialize(value: super::Value) -> Result<Self, serde_json::Error> {
         if value.is_string() {
@@ -22,11 +25,9 @@ impl crate::dto::DeserializeForVersion for pathfinder_common::BlockId {
         } else {
             value.deserialize_map(|value| {
                 if value.contains_key("block_number") {
+                    let number: serde_json::Value = value.deserialize_serde("block_number")?;
                     Ok(Self::Number(
-                        pathfinder_common::BlockNumber::new(
-                            value.deserialize_serde("block_number")?,
-                        )
-                        .ok_or_else(|| serde_json::Error::custom("Invalid block number"))?,
+                        pathfinder_common::BlockNumber::deserialize_lenient(number)?,
                     ))
                 } else if value.contains_key("block_hash") {
                     Ok(Self::Hash(pathfinder_common::BlockHash(
@@ -40,6 +41,28 @@ impl crate::dto::DeserializeForVersion for pathfinder_common::BlockId {
     }
 }
 
+impl crate::dto::serialize::SerializeForVersion for BlockId<'_> {
+    fn serialize(
+        &self,
+        serializer: super::serialize::Serializer,
+    ) -> Result<super::serialize::Ok, super::serialize::Error> {
+        match self.0 {
+            pathfinder_common::BlockId::Latest => serializer.serialize_str("latest"),
+            pathfinder_common::BlockId::Pending => serializer.serialize_str("pending"),
+            pathfinder_common::BlockId::Number(number) => {
+                let mut serializer = serializer.serialize_struct()?;
+                serializer.serialize_field("block_number", &number.get())?;
+                serializer.end()
+            }
+            pathfinder_common::BlockId::Hash(hash) => {
+                let mut serializer = serializer.serialize_struct()?;
+                serializer.serialize_field("block_hash", &crate::dto::BlockHash(hash))?;
+                serializer.end()
+            }
+        }
+    }
+}
+
 impl crate::dto::serialize::SerializeForVersion for BlockHeader<'_> {
     fn serialize(
         &self,
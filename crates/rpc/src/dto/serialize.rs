@@ -129,6 +129,48 @@ impl SerializeStruct {
     }
 }
 
+/// Measures the serialized size of the items of a paged response against
+/// [RpcConfig::max_response_size](crate::context::RpcConfig::max_response_size),
+/// so that a page can be cut short with a continuation token before it grows
+/// too large.
+pub struct ResponseBudget {
+    version: RpcVersion,
+    remaining: Option<usize>,
+    items: usize,
+}
+
+impl ResponseBudget {
+    pub fn new(version: RpcVersion, limit: Option<std::num::NonZeroUsize>) -> Self {
+        Self {
+            version,
+            remaining: limit.map(|limit| limit.get()),
+            items: 0,
+        }
+    }
+
+    /// Charges the serialized size of `item` against the budget, returning
+    /// false if it does not fit. The first item always fits so that paging
+    /// makes progress.
+    pub fn try_add(&mut self, item: &dyn SerializeForVersion) -> Result<bool, Error> {
+        let Some(remaining) = self.remaining else {
+            self.items += 1;
+            return Ok(true);
+        };
+
+        let item = item.serialize(Serializer::new(self.version))?;
+        // Account for the separating comma.
+        let size = serde_json::to_vec(&item)?.len() + 1;
+
+        if size > remaining && self.items > 0 {
+            return Ok(false);
+        }
+
+        self.remaining = Some(remaining.saturating_sub(size));
+        self.items += 1;
+        Ok(true)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use serde_json::json;
@@ -232,4 +274,36 @@ mod tests {
             assert_eq!(encoded, expected);
         }
     }
+
+    mod response_budget {
+        use super::*;
+
+        #[test]
+        fn unlimited() {
+            let mut uut = ResponseBudget::new(RpcVersion::default(), None);
+            for _ in 0..100 {
+                assert!(uut.try_add(&"value".repeat(1000)).unwrap());
+            }
+        }
+
+        #[test]
+        fn first_item_always_fits() {
+            let mut uut =
+                ResponseBudget::new(RpcVersion::default(), std::num::NonZeroUsize::new(1));
+            assert!(uut.try_add(&"value").unwrap());
+            assert!(!uut.try_add(&"value").unwrap());
+        }
+
+        #[test]
+        fn stops_at_limit() {
+            // Each item is 7 bytes plus a comma.
+            let mut uut =
+                ResponseBudget::new(RpcVersion::default(), std::num::NonZeroUsize::new(20));
+            assert!(uut.try_add(&"value").unwrap());
+            assert!(uut.try_add(&"value").unwrap());
+            assert!(!uut.try_add(&"value").unwrap());
+            // Smaller items may still fit.
+            assert!(uut.try_add(&1u64).unwrap());
+        }
+    }
 }
@@ -1,13 +1,18 @@
 use anyhow::anyhow;
-use pathfinder_common::{ContractAddress, ContractNonce};
+use pathfinder_common::{ClassHash, ContractAddress, ContractNonce};
 use serde::ser::Error;
 
 use super::serialize::SerializeStruct;
+use crate::abi_registry::{AbiRegistry, DecodedEvent};
 
 #[derive(Debug)]
 pub struct TransactionTrace<'a> {
     pub trace: &'a pathfinder_executor::types::TransactionTrace,
     pub include_state_diff: bool,
+    /// Events are decoded using these ABIs, if set.
+    pub abis: Option<&'a AbiRegistry>,
+    /// Whether every call also reports the resources used by itself alone.
+    pub call_resources: bool,
 }
 
 impl crate::dto::serialize::SerializeForVersion for TransactionTrace<'_> {
@@ -22,13 +27,17 @@ impl crate::dto::serialize::SerializeForVersion for TransactionTrace<'_> {
                 if let Some(fee_transfer_invocation) = &trace.fee_transfer_invocation {
                     serializer.serialize_field(
                         "fee_transfer_invocation",
-                        &FunctionInvocation(fee_transfer_invocation),
+                        &FunctionInvocation(
+                            fee_transfer_invocation,
+                            self.abis,
+                            self.call_resources,
+                        ),
                     )?;
                 }
                 if let Some(validate_invocation) = &trace.validate_invocation {
                     serializer.serialize_field(
                         "validate_invocation",
-                        &FunctionInvocation(validate_invocation),
+                        &FunctionInvocation(validate_invocation, self.abis, self.call_resources),
                     )?;
                 }
                 if self.include_state_diff {
@@ -43,24 +52,30 @@ impl crate::dto::serialize::SerializeForVersion for TransactionTrace<'_> {
                 serializer.serialize_field("type", &"DEPLOY_ACCOUNT")?;
                 serializer.serialize_field(
                     "constructor_invocation",
-                    &FunctionInvocation(trace.constructor_invocation.as_ref().ok_or_else(
-                        || {
+                    &FunctionInvocation(
+                        trace.constructor_invocation.as_ref().ok_or_else(|| {
                             serde_json::error::Error::custom(
                                 "Missing constructor_invocation in trace",
                             )
-                        },
-                    )?),
+                        })?,
+                        self.abis,
+                        self.call_resources,
+                    ),
                 )?;
                 if let Some(fee_transfer_invocation) = &trace.fee_transfer_invocation {
                     serializer.serialize_field(
                         "fee_transfer_invocation",
-                        &FunctionInvocation(fee_transfer_invocation),
+                        &FunctionInvocation(
+                            fee_transfer_invocation,
+                            self.abis,
+                            self.call_resources,
+                        ),
                     )?;
                 }
                 if let Some(validate_invocation) = &trace.validate_invocation {
                     serializer.serialize_field(
                         "validate_invocation",
-                        &FunctionInvocation(validate_invocation),
+                        &FunctionInvocation(validate_invocation, self.abis, self.call_resources),
                     )?;
                 }
                 if self.include_state_diff {
@@ -75,18 +90,22 @@ impl crate::dto::serialize::SerializeForVersion for TransactionTrace<'_> {
                 serializer.serialize_field("type", &"INVOKE")?;
                 serializer.serialize_field(
                     "execute_invocation",
-                    &ExecuteInvocation(&trace.execute_invocation),
+                    &ExecuteInvocation(&trace.execute_invocation, self.abis, self.call_resources),
                 )?;
                 if let Some(fee_transfer_invocation) = &trace.fee_transfer_invocation {
                     serializer.serialize_field(
                         "fee_transfer_invocation",
-                        &FunctionInvocation(fee_transfer_invocation),
+                        &FunctionInvocation(
+                            fee_transfer_invocation,
+                            self.abis,
+                            self.call_resources,
+                        ),
                     )?;
                 }
                 if let Some(validate_invocation) = &trace.validate_invocation {
                     serializer.serialize_field(
                         "validate_invocation",
-                        &FunctionInvocation(validate_invocation),
+                        &FunctionInvocation(validate_invocation, self.abis, self.call_resources),
                     )?;
                 }
                 if self.include_state_diff {
@@ -101,9 +120,13 @@ impl crate::dto::serialize::SerializeForVersion for TransactionTrace<'_> {
                 serializer.serialize_field("type", &"L1_HANDLER")?;
                 serializer.serialize_field(
                     "function_invocation",
-                    &FunctionInvocation(trace.function_invocation.as_ref().ok_or_else(|| {
-                        serde_json::error::Error::custom("Missing function_invocation in trace")
-                    })?),
+                    &FunctionInvocation(
+                        trace.function_invocation.as_ref().ok_or_else(|| {
+                            serde_json::error::Error::custom("Missing function_invocation in trace")
+                        })?,
+                        self.abis,
+                        self.call_resources,
+                    ),
                 )?;
                 if self.include_state_diff {
                     serializer.serialize_field("state_diff", &StateDiff(&trace.state_diff))?;
@@ -119,7 +142,11 @@ impl crate::dto::serialize::SerializeForVersion for TransactionTrace<'_> {
 }
 
 #[derive(Debug)]
-struct FunctionInvocation<'a>(&'a pathfinder_executor::types::FunctionInvocation);
+struct FunctionInvocation<'a>(
+    &'a pathfinder_executor::types::FunctionInvocation,
+    Option<&'a AbiRegistry>,
+    bool,
+);
 
 impl crate::dto::serialize::SerializeForVersion for FunctionInvocation<'_> {
     fn serialize(
@@ -138,7 +165,11 @@ impl crate::dto::serialize::SerializeForVersion for FunctionInvocation<'_> {
         serializer.serialize_iter(
             "calls",
             self.0.internal_calls.len(),
-            &mut self.0.internal_calls.iter().map(FunctionInvocation),
+            &mut self
+                .0
+                .internal_calls
+                .iter()
+                .map(|call| FunctionInvocation(call, self.1, self.2)),
         )?;
         if let Some(class_hash) = &self.0.class_hash {
             serializer.serialize_field("class_hash", &crate::dto::Felt(class_hash))?;
@@ -154,7 +185,17 @@ impl crate::dto::serialize::SerializeForVersion for FunctionInvocation<'_> {
         serializer.serialize_iter(
             "events",
             self.0.events.len(),
-            &mut self.0.events.iter().map(Event),
+            &mut self.0.events.iter().map(|event| {
+                let decoded = self.1.and_then(|abis| {
+                    abis.decode(
+                        self.0.contract_address,
+                        self.0.class_hash.map(ClassHash),
+                        &event.keys,
+                        &event.data,
+                    )
+                });
+                Event(event, decoded)
+            }),
         )?;
         serializer.serialize_field(
             "contract_address",
@@ -180,11 +221,33 @@ impl crate::dto::serialize::SerializeForVersion for FunctionInvocation<'_> {
             "execution_resources",
             &ComputationResources(&self.0.computation_resources),
         )?;
+        if self.2 {
+            serializer.serialize_field("call_resources", &CallResources(self.0))?;
+        }
+        serializer.end()
+    }
+}
+
+/// The resources used by a call alone, excluding its internal calls.
+///
+/// L1 gas is not attributed to calls, as it is only charged for the
+/// transaction as a whole.
+struct CallResources<'a>(&'a pathfinder_executor::types::FunctionInvocation);
+
+impl crate::dto::serialize::SerializeForVersion for CallResources<'_> {
+    fn serialize(
+        &self,
+        serializer: super::serialize::Serializer,
+    ) -> Result<super::serialize::Ok, super::serialize::Error> {
+        let mut serializer = serializer.serialize_struct()?;
+        serializer.flatten(&ComputationResources(&self.0.own_computation_resources()))?;
+        serializer.serialize_optional("l2_gas", self.0.own_gas_consumed())?;
+        serializer.serialize_optional("total_l2_gas", self.0.gas_consumed)?;
         serializer.end()
     }
 }
 
-struct Event<'a>(&'a pathfinder_executor::types::Event);
+struct Event<'a>(&'a pathfinder_executor::types::Event, Option<DecodedEvent>);
 
 impl crate::dto::serialize::SerializeForVersion for Event<'_> {
     fn serialize(
@@ -203,6 +266,7 @@ impl crate::dto::serialize::SerializeForVersion for Event<'_> {
             self.0.keys.len(),
             &mut self.0.keys.iter().map(crate::dto::Felt),
         )?;
+        serializer.serialize_optional("decoded", self.1.as_ref())?;
         serializer.end()
     }
 }
@@ -464,7 +528,11 @@ impl crate::dto::serialize::SerializeForVersion for DataAvailabilityResources<'_
     }
 }
 
-struct ExecuteInvocation<'a>(&'a pathfinder_executor::types::ExecuteInvocation);
+struct ExecuteInvocation<'a>(
+    &'a pathfinder_executor::types::ExecuteInvocation,
+    Option<&'a AbiRegistry>,
+    bool,
+);
 
 impl crate::dto::serialize::SerializeForVersion for ExecuteInvocation<'_> {
     fn serialize(
@@ -473,7 +541,7 @@ impl crate::dto::serialize::SerializeForVersion for ExecuteInvocation<'_> {
     ) -> Result<super::serialize::Ok, super::serialize::Error> {
         match self.0 {
             pathfinder_executor::types::ExecuteInvocation::FunctionInvocation(Some(invocation)) => {
-                FunctionInvocation(invocation).serialize(serializer)
+                FunctionInvocation(invocation, self.1, self.2).serialize(serializer)
             }
             pathfinder_executor::types::ExecuteInvocation::FunctionInvocation(None) => {
                 let mut serializer = serializer.serialize_struct()?;
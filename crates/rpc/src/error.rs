@@ -5,7 +5,7 @@
 //! be used by each JSON-RPC method to trivially create its subset of
 //! [ApplicationError] along with the boilerplate involved.
 #![macro_use]
-use pathfinder_common::TransactionHash;
+use pathfinder_common::{BlockHash, TransactionHash};
 use serde_json::json;
 
 #[derive(serde::Serialize, Clone, Copy, Debug)]
@@ -47,6 +47,7 @@ pub enum ApplicationError {
     ContractError {
         revert_error: Option<String>,
         revert_error_stack: pathfinder_executor::ErrorStack,
+        reason: pathfinder_executor::FailureReason,
     },
     #[error("Invalid contract class")]
     InvalidContractClass,
@@ -87,6 +88,7 @@ pub enum ApplicationError {
         transaction_index: usize,
         error: String,
         error_stack: pathfinder_executor::ErrorStack,
+        reason: pathfinder_executor::FailureReason,
     },
     #[error("Transaction hash not found in websocket subscription")]
     SubscriptionTransactionHashNotFound {
@@ -99,6 +101,16 @@ pub enum ApplicationError {
     StorageProofNotSupported,
     #[error("Proof is missing")]
     ProofMissing,
+    #[error("Execution exceeded the node's resource limits")]
+    ExecutionResourcesExceeded,
+    #[error("The block the query is pinned to is no longer part of the chain")]
+    ChainReorganized { at_block_hash: BlockHash },
+    #[error("The node syncs in light mode and doesn't keep the state this method reads")]
+    StateUnavailable,
+    #[error("The node is still indexing the blocks this query needs")]
+    MigrationPending { migration: &'static str },
+    #[error("Invalid block range")]
+    InvalidBlockRange { limit: u64 },
     /// Internal errors are errors whose details we don't want to show to the
     /// end user. These are logged, and a simple "internal error" message is
     /// shown to the end user.
@@ -149,6 +161,11 @@ impl ApplicationError {
             // doc/rpc/pathfinder_rpc_api.json
             ApplicationError::ProofLimitExceeded { .. } => 10000,
             ApplicationError::ProofMissing => 10001,
+            ApplicationError::ExecutionResourcesExceeded => 10002,
+            ApplicationError::ChainReorganized { .. } => 10003,
+            ApplicationError::StateUnavailable => 10004,
+            ApplicationError::MigrationPending { .. } => 10005,
+            ApplicationError::InvalidBlockRange { .. } => 10006,
             ApplicationError::SubscriptionTransactionHashNotFound { .. } => 10029,
             ApplicationError::SubscriptionGatewayDown { .. } => 10030,
             // https://www.jsonrpc.org/specification#error_object
@@ -194,19 +211,21 @@ impl ApplicationError {
                 transaction_index,
                 error,
                 error_stack,
-            } => match version {
-                RpcVersion::V08 => {
-                    let error_stack = error_stack_frames_to_json(&error_stack.0);
-                    Some(json!({
-                        "transaction_index": transaction_index,
-                        "execution_error": error_stack,
-                    }))
-                }
-                _ => Some(json!({
+                reason,
+            } => {
+                let execution_error = match version {
+                    RpcVersion::V08 => error_stack_frames_to_json(&error_stack.0),
+                    _ => json!(error),
+                };
+                let mut data = json!({
                     "transaction_index": transaction_index,
-                    "execution_error": error,
-                })),
-            },
+                    "execution_error": execution_error,
+                });
+                if has_execution_failure(version) {
+                    data["execution_failure"] = execution_failure_to_json(*reason, &error_stack.0);
+                }
+                Some(data)
+            }
             ApplicationError::Internal(_) => None,
             ApplicationError::Custom(cause) => {
                 let cause = cause.to_string();
@@ -224,17 +243,21 @@ impl ApplicationError {
             ApplicationError::ContractError {
                 revert_error,
                 revert_error_stack,
-            } => match version {
-                RpcVersion::V08 => {
-                    let revert_error_stack = error_stack_frames_to_json(&revert_error_stack.0);
-                    Some(json!({
-                        "revert_error": revert_error_stack
-                    }))
+                reason,
+            } => {
+                let revert_error = match version {
+                    RpcVersion::V08 => error_stack_frames_to_json(&revert_error_stack.0),
+                    _ => json!(revert_error),
+                };
+                let mut data = json!({
+                    "revert_error": revert_error,
+                });
+                if has_execution_failure(version) {
+                    data["execution_failure"] =
+                        execution_failure_to_json(*reason, &revert_error_stack.0);
                 }
-                _ => Some(json!({
-                    "revert_error": revert_error
-                })),
-            },
+                Some(data)
+            }
             ApplicationError::TooManyKeysInFilter { limit, requested } => Some(json!({
                 "limit": limit,
                 "requested": requested,
@@ -246,6 +269,17 @@ impl ApplicationError {
             })),
             ApplicationError::StorageProofNotSupported => None,
             ApplicationError::ProofMissing => None,
+            ApplicationError::ExecutionResourcesExceeded => None,
+            ApplicationError::ChainReorganized { at_block_hash } => Some(json!({
+                "at_block_hash": at_block_hash,
+            })),
+            ApplicationError::StateUnavailable => None,
+            ApplicationError::MigrationPending { migration } => Some(json!({
+                "migration": migration,
+            })),
+            ApplicationError::InvalidBlockRange { limit } => Some(json!({
+                "limit": limit,
+            })),
             ApplicationError::SubscriptionTransactionHashNotFound {
                 subscription_id,
                 transaction_hash,
@@ -262,22 +296,11 @@ impl ApplicationError {
 }
 
 fn error_stack_frames_to_json(frames: &[pathfinder_executor::Frame]) -> serde_json::Value {
-    let last_string_frame_contents = frames
-        .iter()
-        .rev()
-        .filter_map(|frame| match frame {
-            pathfinder_executor::Frame::StringFrame(string) => Some(string),
-            _ => None,
-        })
-        .next()
+    let last_string_frame_contents = innermost_error(frames)
         .cloned()
         .unwrap_or_else(|| "Unknown error, no string frame available.".to_string());
 
-    let call_frames = frames.iter().filter_map(|frame| match frame {
-        pathfinder_executor::Frame::CallFrame(call_frame) => Some(call_frame),
-        _ => None,
-    });
-    call_frames
+    call_frames(frames)
         .rev()
         .fold(json!(last_string_frame_contents), |child, frame| {
             json!({
@@ -289,6 +312,59 @@ fn error_stack_frames_to_json(frames: &[pathfinder_executor::Frame]) -> serde_js
         })
 }
 
+/// Whether the data of execution errors includes [execution_failure_to_json].
+/// The specifications before v0.8 don't allow additional members.
+fn has_execution_failure(version: RpcVersion) -> bool {
+    !matches!(version, RpcVersion::V06 | RpcVersion::V07)
+}
+
+/// Pathfinder's addition to the data of execution errors: what made execution
+/// fail, the calls it failed in, outermost first, and the innermost error
+/// message.
+fn execution_failure_to_json(
+    reason: pathfinder_executor::FailureReason,
+    frames: &[pathfinder_executor::Frame],
+) -> serde_json::Value {
+    use pathfinder_executor::FailureReason;
+
+    let reason = match reason {
+        FailureReason::Revert => "REVERT",
+        FailureReason::EntryPointNotFound => "ENTRY_POINT_NOT_FOUND",
+        FailureReason::ValidationFailure => "VALIDATION_FAILURE",
+    };
+    let calls = call_frames(frames)
+        .map(|frame| {
+            json!({
+                "contract_address": frame.storage_address,
+                "class_hash": frame.class_hash,
+                "selector": frame.selector,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    json!({
+        "reason": reason,
+        "frames": calls,
+        "error": innermost_error(frames),
+    })
+}
+
+fn call_frames(
+    frames: &[pathfinder_executor::Frame],
+) -> impl DoubleEndedIterator<Item = &pathfinder_executor::CallFrame> {
+    frames.iter().filter_map(|frame| match frame {
+        pathfinder_executor::Frame::CallFrame(call_frame) => Some(call_frame),
+        _ => None,
+    })
+}
+
+fn innermost_error(frames: &[pathfinder_executor::Frame]) -> Option<&String> {
+    frames.iter().rev().find_map(|frame| match frame {
+        pathfinder_executor::Frame::StringFrame(string) => Some(string),
+        _ => None,
+    })
+}
+
 /// Generates an enum subset of [ApplicationError] along with boilerplate for
 /// mapping the variants back to [ApplicationError].
 ///
@@ -490,10 +566,12 @@ mod tests {
 
     mod error_stack {
         use pathfinder_common::{class_hash, contract_address, entry_point};
-        use pathfinder_executor::{CallFrame, Frame};
+        use pathfinder_executor::{CallFrame, FailureReason, Frame};
         use serde_json::json;
 
-        use super::super::error_stack_frames_to_json;
+        use super::super::{error_stack_frames_to_json, execution_failure_to_json};
+        use crate::error::ApplicationError;
+        use crate::RpcVersion;
 
         #[test]
         fn json_representation() {
@@ -543,5 +621,78 @@ mod tests {
                 })
             );
         }
+
+        #[test]
+        fn execution_failure() {
+            let frames = vec![
+                Frame::CallFrame(CallFrame {
+                    storage_address: contract_address!("0xdeadbeef"),
+                    class_hash: class_hash!("0xcaadd"),
+                    selector: Some(entry_point!("0xeeeee")),
+                }),
+                Frame::StringFrame("Error at pc=0:4273".to_string()),
+                Frame::CallFrame(CallFrame {
+                    storage_address: contract_address!("0x2222deadbeef"),
+                    class_hash: class_hash!("0x2222caadd"),
+                    selector: None,
+                }),
+                Frame::StringFrame("Entry point not found".to_string()),
+            ];
+
+            assert_eq!(
+                execution_failure_to_json(FailureReason::EntryPointNotFound, &frames),
+                json!({
+                    "reason": "ENTRY_POINT_NOT_FOUND",
+                    "frames": [
+                        {
+                            "contract_address": "0xdeadbeef",
+                            "class_hash": "0xcaadd",
+                            "selector": "0xeeeee",
+                        },
+                        {
+                            "contract_address": "0x2222deadbeef",
+                            "class_hash": "0x2222caadd",
+                            "selector": null,
+                        },
+                    ],
+                    "error": "Entry point not found",
+                })
+            );
+            assert_eq!(
+                execution_failure_to_json(FailureReason::Revert, &[]),
+                json!({
+                    "reason": "REVERT",
+                    "frames": [],
+                    "error": null,
+                })
+            );
+        }
+
+        #[test]
+        fn execution_failure_only_from_v08() {
+            let error = ApplicationError::ContractError {
+                revert_error: Some("test".to_owned()),
+                revert_error_stack: Default::default(),
+                reason: FailureReason::Revert,
+            };
+
+            for version in [RpcVersion::V06, RpcVersion::V07] {
+                assert_eq!(
+                    error.data(version).unwrap().get("execution_failure"),
+                    None,
+                    "{version:?}"
+                );
+            }
+            for version in [RpcVersion::V08, RpcVersion::PathfinderV01] {
+                assert!(
+                    error
+                        .data(version)
+                        .unwrap()
+                        .get("execution_failure")
+                        .is_some(),
+                    "{version:?}"
+                );
+            }
+        }
     }
 }
@@ -1,8 +1,42 @@
+use std::time::Duration;
+
 use anyhow::Context;
+use pathfinder_common::event::Event;
+use pathfinder_common::receipt::{
+    BuiltinCounters,
+    ExecutionResources,
+    ExecutionStatus,
+    L1Gas,
+    L2ToL1Message,
+    Receipt,
+};
 use pathfinder_common::transaction::TransactionVariant;
-use pathfinder_common::{ChainId, StarknetVersion};
+use pathfinder_common::{
+    ChainId,
+    ClassHash,
+    ContractAddress,
+    EventData,
+    EventKey,
+    Fee,
+    L2ToL1MessagePayloadElem,
+    SierraHash,
+    StarknetVersion,
+    StateUpdate,
+    TransactionHash,
+    TransactionIndex,
+};
+use pathfinder_crypto::Felt;
+use pathfinder_executor::types::{
+    ExecuteInvocation,
+    FunctionInvocation,
+    TransactionSimulation,
+    TransactionTrace,
+};
 use pathfinder_executor::{ClassInfo, IntoStarkFelt};
+use pathfinder_storage::Storage;
 use starknet_api::core::PatriciaKey;
+use starknet_gateway_types::class_hash::ComputedClassHash;
+use tokio::task::{JoinError, JoinHandle};
 
 use crate::v02::types::request::{
     BroadcastedDeployAccountTransaction,
@@ -22,12 +56,30 @@ impl From<anyhow::Error> for ExecutionStateError {
     }
 }
 
+/// Awaits a blocking execution task, or returns [None] once `timeout` has
+/// elapsed.
+///
+/// The Cairo VM cannot be interrupted, so a timed out execution still runs to
+/// completion in the background; only its result is discarded.
+pub(crate) async fn with_timeout<T>(
+    timeout: Option<Duration>,
+    execution: JoinHandle<T>,
+) -> Option<Result<T, JoinError>> {
+    match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, execution).await.ok(),
+        None => Some(execution.await),
+    }
+}
+
 pub const VERSIONS_LOWER_THAN_THIS_SHOULD_FALL_BACK_TO_FETCHING_TRACE_FROM_GATEWAY:
     StarknetVersion = StarknetVersion::new(0, 13, 1, 1);
 
+/// Sierra classes of declare transactions are compiled through the compiled
+/// class cache in `storage`.
 pub(crate) fn map_broadcasted_transaction(
     transaction: &BroadcastedTransaction,
     chain_id: ChainId,
+    storage: &Storage,
 ) -> anyhow::Result<pathfinder_executor::Transaction> {
     use crate::v02::types::request::BroadcastedDeclareTransaction;
 
@@ -55,12 +107,7 @@ pub(crate) fn map_broadcasted_transaction(
             Some(ClassInfo::new(&contract_class, 0, 0)?)
         }
         BroadcastedTransaction::Declare(BroadcastedDeclareTransaction::V2(tx)) => {
-            let casm_contract_definition = pathfinder_compiler::compile_to_casm(
-                &tx.contract_class
-                    .serialize_to_json()
-                    .context("Serializing Sierra class definition")?,
-            )
-            .context("Compiling Sierra class definition to CASM")?;
+            let casm_contract_definition = compile_to_casm(storage, &tx.contract_class)?;
 
             let casm_contract_definition =
                 pathfinder_executor::parse_casm_definition(casm_contract_definition)
@@ -72,12 +119,7 @@ pub(crate) fn map_broadcasted_transaction(
             )?)
         }
         BroadcastedTransaction::Declare(BroadcastedDeclareTransaction::V3(tx)) => {
-            let casm_contract_definition = pathfinder_compiler::compile_to_casm(
-                &tx.contract_class
-                    .serialize_to_json()
-                    .context("Serializing Sierra class definition")?,
-            )
-            .context("Compiling Sierra class definition to CASM")?;
+            let casm_contract_definition = compile_to_casm(storage, &tx.contract_class)?;
 
             let casm_contract_definition =
                 pathfinder_executor::parse_casm_definition(casm_contract_definition)
@@ -153,6 +195,47 @@ pub(crate) fn map_broadcasted_transaction(
     Ok(tx)
 }
 
+/// Compiles a Sierra class to CASM, reusing the CASM of a known class or an
+/// earlier compilation of the same class from the compiled class cache.
+///
+/// The RPC API only reads the compiled class cache. It's filled by sync, so
+/// that requests cannot grow the database.
+pub(crate) fn compile_to_casm(
+    storage: &Storage,
+    class: &SierraContractClass,
+) -> anyhow::Result<Vec<u8>> {
+    let sierra_hash = match class.class_hash().context("Computing class hash")? {
+        ComputedClassHash::Sierra(hash) => SierraHash(hash.0),
+        ComputedClassHash::Cairo(_) => anyhow::bail!("Expected a Sierra class"),
+    };
+
+    let cached = {
+        let mut db = storage
+            .connection()
+            .context("Creating database connection")?;
+        let db = db.transaction().context("Creating database transaction")?;
+        match db
+            .casm_definition(ClassHash(sierra_hash.0))
+            .context("Querying CASM definition")?
+        {
+            Some(casm_definition) => Some(casm_definition),
+            None => db
+                .compiled_class(sierra_hash)
+                .context("Querying compiled class cache")?,
+        }
+    };
+    if let Some(casm_definition) = cached {
+        return Ok(casm_definition);
+    }
+
+    pathfinder_compiler::compile_to_casm(
+        &class
+            .serialize_to_json()
+            .context("Serializing Sierra class definition")?,
+    )
+    .context("Compiling Sierra class definition to CASM")
+}
+
 fn map_transaction_variant(
     variant: TransactionVariant,
 ) -> anyhow::Result<starknet_api::transaction::Transaction> {
@@ -566,6 +649,185 @@ pub fn compose_executor_transaction(
     Ok(tx)
 }
 
+/// Builds the receipt and events of an executed transaction.
+pub fn executed_receipt(
+    simulation: &TransactionSimulation,
+    transaction_hash: TransactionHash,
+    transaction_index: TransactionIndex,
+) -> (Receipt, Vec<Event>) {
+    let (invocations, resources) = match &simulation.trace {
+        TransactionTrace::Declare(trace) => (
+            vec![&trace.validate_invocation, &trace.fee_transfer_invocation],
+            &trace.execution_resources,
+        ),
+        TransactionTrace::DeployAccount(trace) => (
+            vec![
+                &trace.validate_invocation,
+                &trace.constructor_invocation,
+                &trace.fee_transfer_invocation,
+            ],
+            &trace.execution_resources,
+        ),
+        TransactionTrace::Invoke(trace) => {
+            let execute = match &trace.execute_invocation {
+                ExecuteInvocation::FunctionInvocation(invocation) => invocation,
+                ExecuteInvocation::RevertedReason(_) => &None,
+            };
+            (
+                vec![
+                    &trace.validate_invocation,
+                    execute,
+                    &trace.fee_transfer_invocation,
+                ],
+                &trace.execution_resources,
+            )
+        }
+        TransactionTrace::L1Handler(trace) => {
+            (vec![&trace.function_invocation], &trace.execution_resources)
+        }
+    };
+
+    fn collect(
+        invocation: &FunctionInvocation,
+        events: &mut Vec<(i64, Event)>,
+        messages: &mut Vec<(usize, L2ToL1Message)>,
+    ) {
+        events.extend(invocation.events.iter().map(|event| {
+            (
+                event.order,
+                Event {
+                    data: event.data.iter().copied().map(EventData).collect(),
+                    from_address: invocation.contract_address,
+                    keys: event.keys.iter().copied().map(EventKey).collect(),
+                },
+            )
+        }));
+        messages.extend(invocation.messages.iter().map(|message| {
+            (
+                message.order,
+                L2ToL1Message {
+                    from_address: ContractAddress(message.from_address),
+                    payload: message
+                        .payload
+                        .iter()
+                        .copied()
+                        .map(L2ToL1MessagePayloadElem)
+                        .collect(),
+                    to_address: ContractAddress(message.to_address),
+                },
+            )
+        }));
+        for call in &invocation.internal_calls {
+            collect(call, events, messages);
+        }
+    }
+
+    // Events and messages are ordered by top-level invocation first and by
+    // order of emission within each of them.
+    let mut all_events = Vec::new();
+    let mut all_messages = Vec::new();
+    for invocation in invocations.into_iter().flatten() {
+        let mut events = Vec::new();
+        let mut messages = Vec::new();
+        collect(invocation, &mut events, &mut messages);
+
+        events.sort_by_key(|(order, _)| *order);
+        messages.sort_by_key(|(order, _)| *order);
+
+        all_events.extend(events.into_iter().map(|(_, event)| event));
+        all_messages.extend(messages.into_iter().map(|(_, message)| message));
+    }
+
+    let computation = &resources.computation_resources;
+    let data_availability = L1Gas {
+        l1_gas: resources.data_availability.l1_gas,
+        l1_data_gas: resources.data_availability.l1_data_gas,
+    };
+
+    let mut fee = [0u8; 32];
+    simulation
+        .fee_estimation
+        .overall_fee
+        .to_big_endian(&mut fee);
+
+    let receipt = Receipt {
+        actual_fee: Fee(Felt::from_be_bytes(fee).unwrap_or_default()),
+        execution_resources: ExecutionResources {
+            builtins: BuiltinCounters {
+                pedersen: computation.pedersen_builtin_applications as u64,
+                range_check: computation.range_check_builtin_applications as u64,
+                ecdsa: computation.ecdsa_builtin_applications as u64,
+                bitwise: computation.bitwise_builtin_applications as u64,
+                ec_op: computation.ec_op_builtin_applications as u64,
+                keccak: computation.keccak_builtin_applications as u64,
+                poseidon: computation.poseidon_builtin_applications as u64,
+                segment_arena: computation.segment_arena_builtin as u64,
+                ..Default::default()
+            },
+            n_steps: computation.steps as u64,
+            n_memory_holes: computation.memory_holes as u64,
+            data_availability,
+            total_gas_consumed: L1Gas {
+                l1_gas: simulation.fee_estimation.gas_consumed.low_u128(),
+                l1_data_gas: simulation.fee_estimation.data_gas_consumed.low_u128(),
+            },
+        },
+        l2_to_l1_messages: all_messages,
+        execution_status: match simulation.revert_reason() {
+            Some(reason) => ExecutionStatus::Reverted {
+                reason: reason.to_owned(),
+            },
+            None => ExecutionStatus::Succeeded,
+        },
+        transaction_hash,
+        transaction_index,
+    };
+
+    (receipt, all_events)
+}
+
+/// Merges the state changes of an executed transaction into `state_update`.
+pub fn apply_executed_state_diff(
+    state_update: &StateUpdate,
+    trace: &TransactionTrace,
+) -> StateUpdate {
+    let diff = match trace {
+        TransactionTrace::Declare(trace) => &trace.state_diff,
+        TransactionTrace::DeployAccount(trace) => &trace.state_diff,
+        TransactionTrace::Invoke(trace) => &trace.state_diff,
+        TransactionTrace::L1Handler(trace) => &trace.state_diff,
+    };
+
+    let mut state_update = state_update.clone();
+    for (contract, updates) in &diff.storage_diffs {
+        for update in updates {
+            state_update = if contract.is_system_contract() {
+                state_update.with_system_storage_update(*contract, update.key, update.value)
+            } else {
+                state_update.with_storage_update(*contract, update.key, update.value)
+            };
+        }
+    }
+    for contract in &diff.deployed_contracts {
+        state_update = state_update.with_deployed_contract(contract.address, contract.class_hash);
+    }
+    for class in &diff.replaced_classes {
+        state_update = state_update.with_replaced_class(class.contract_address, class.class_hash);
+    }
+    for (contract, nonce) in &diff.nonces {
+        state_update = state_update.with_contract_nonce(*contract, *nonce);
+    }
+    for class in &diff.deprecated_declared_classes {
+        state_update = state_update.with_declared_cairo_class(*class);
+    }
+    for class in &diff.declared_classes {
+        state_update =
+            state_update.with_declared_sierra_class(class.class_hash, class.compiled_class_hash);
+    }
+
+    state_update
+}
+
 fn map_resource_bounds(
     r: pathfinder_common::transaction::ResourceBounds,
 ) -> Result<starknet_api::transaction::ResourceBoundsMapping, starknet_api::StarknetApiError> {
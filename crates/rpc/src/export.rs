@@ -0,0 +1,345 @@
+//! Bulk export of events as newline-delimited JSON over HTTP.
+//!
+//! `GET /export/events?from=<block>&to=<block>&address=<contract>` streams all
+//! matching events in a single response, one `starknet_getEvents` event object
+//! per line, instead of a client paging through them with continuation tokens.
+//! All parameters are optional, and `to` defaults to the latest block.
+//!
+//! Events are read from the database in pages, each using a database
+//! connection of its own, and written as the client reads them. Reading pauses
+//! while the client falls behind, so a slow client doesn't buffer the export in
+//! memory, and the export is aborted if the client stops reading altogether.
+//!
+//! At most [MAX_CONCURRENT_EXPORTS] exports run at the same time. Each page
+//! counts as a `starknet_getEvents` call for rate limiting, and an export is
+//! aborted if the exported blocks are reorged while it runs. Aborted exports
+//! end with an error instead of the end of the response, so that clients can
+//! tell they are incomplete.
+//!
+//! Only committed blocks are exported, i.e. not the pending block.
+
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Context;
+use axum::body::Body;
+use axum::extract::{ConnectInfo, Query, State};
+use axum::response::{IntoResponse, Response};
+use pathfinder_common::{BlockNumber, ContractAddress};
+use pathfinder_crypto::Felt;
+use pathfinder_storage::{EventFilter, EVENT_PAGE_SIZE_LIMIT};
+use tokio::sync::{mpsc, Semaphore};
+use tracing::Instrument;
+
+use crate::context::RpcContext;
+use crate::dto::serialize::{SerializeForVersion, Serializer};
+use crate::method::get_events::EmittedEvent;
+use crate::RpcVersion;
+
+/// The number of pages read ahead of the client.
+const PAGES_BUFFERED: usize = 4;
+
+/// The maximum number of exports running at the same time.
+const MAX_CONCURRENT_EXPORTS: usize = 4;
+
+/// How long the client may stop reading before the export is aborted.
+const SEND_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The method an export counts as for rate limiting.
+const RATE_LIMITED_AS: &str = "starknet_getEvents";
+
+#[derive(Clone)]
+struct ExportState {
+    context: RpcContext,
+    exports: Arc<Semaphore>,
+}
+
+pub(crate) fn router(context: RpcContext) -> axum::Router {
+    axum::Router::new()
+        .route("/export/events", axum::routing::get(export_events))
+        .with_state(ExportState {
+            context,
+            exports: Arc::new(Semaphore::new(MAX_CONCURRENT_EXPORTS)),
+        })
+}
+
+#[derive(Debug, Default, serde::Deserialize)]
+struct Params {
+    from: Option<u64>,
+    to: Option<u64>,
+    address: Option<String>,
+}
+
+impl Params {
+    fn filter(
+        &self,
+    ) -> Result<(BlockNumber, Option<BlockNumber>, Option<ContractAddress>), String> {
+        let block = |number: Option<u64>, name: &str| {
+            number
+                .map(|number| BlockNumber::new(number).ok_or_else(|| format!("Invalid {name}")))
+                .transpose()
+        };
+        let from = block(self.from, "from")?.unwrap_or(BlockNumber::GENESIS);
+        let to = block(self.to, "to")?;
+        let address = self
+            .address
+            .as_ref()
+            .map(|address| {
+                Felt::from_hex_str(address)
+                    .ok()
+                    .and_then(ContractAddress::new)
+                    .ok_or_else(|| "Invalid address".to_owned())
+            })
+            .transpose()?;
+
+        Ok((from, to, address))
+    }
+}
+
+async fn export_events(
+    State(state): State<ExportState>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    Query(params): Query<Params>,
+) -> Response {
+    let (from, to, address) = match params.filter() {
+        Ok(filter) => filter,
+        Err(message) => return (http::StatusCode::BAD_REQUEST, message).into_response(),
+    };
+
+    let client = connect_info.map(|ConnectInfo(addr)| addr.ip());
+    if let Some(limiter) = &state.context.rate_limiter {
+        if let Err(retry_after) = limiter.check(client, RATE_LIMITED_AS) {
+            let retry_after = retry_after.as_secs_f64().ceil() as u64;
+            return (
+                http::StatusCode::TOO_MANY_REQUESTS,
+                [(http::header::RETRY_AFTER, retry_after.to_string())],
+                "Rate limit exceeded",
+            )
+                .into_response();
+        }
+    }
+
+    let Ok(permit) = state.exports.clone().try_acquire_owned() else {
+        return (
+            http::StatusCode::SERVICE_UNAVAILABLE,
+            "Too many exports in progress",
+        )
+            .into_response();
+    };
+
+    let (sender, mut receiver) = mpsc::channel(PAGES_BUFFERED);
+    let export = async move {
+        let _permit = permit;
+        let export = Export {
+            context: state.context,
+            client,
+            sender,
+        };
+        if let Err(error) = export.run(from, to, address).await {
+            tracing::warn!(?error, "Event export failed");
+            // Aborts the response, so that the client can tell it is incomplete.
+            _ = export
+                .send(Err(io::Error::other("Event export failed")))
+                .await;
+        }
+    };
+    tokio::spawn(export.in_current_span());
+
+    let body = Body::from_stream(futures::stream::poll_fn(move |cx| receiver.poll_recv(cx)));
+    ([(http::header::CONTENT_TYPE, "application/x-ndjson")], body).into_response()
+}
+
+struct Export {
+    context: RpcContext,
+    client: Option<std::net::IpAddr>,
+    sender: mpsc::Sender<Result<String, io::Error>>,
+}
+
+impl Export {
+    /// Sends the events of blocks `from..=to` emitted by `address`, a page at a
+    /// time. Stops early if the client disconnected.
+    async fn run(
+        &self,
+        from: BlockNumber,
+        to: Option<BlockNumber>,
+        address: Option<ContractAddress>,
+    ) -> anyhow::Result<()> {
+        // Blocks added during the export are not included.
+        let last = self
+            .read(move |tx| {
+                let Some((latest, _)) = tx
+                    .block_id(pathfinder_storage::BlockId::Latest)
+                    .context("Querying latest block")?
+                else {
+                    return Ok(None);
+                };
+                let to = to.map_or(latest, |to| to.min(latest));
+                let hash = tx
+                    .block_hash(to.into())
+                    .context("Querying block hash")?
+                    .context("Block hash missing")?;
+                Ok(Some((to, hash)))
+            })
+            .await?;
+        let Some((to, to_hash)) = last else {
+            return Ok(());
+        };
+
+        let max_blocks_to_scan = self.context.config.get_events_max_blocks_to_scan;
+        let max_bloom_filters = self
+            .context
+            .config
+            .get_events_max_uncached_bloom_filters_to_load;
+        let (mut block, mut offset) = (from, 0);
+        while block <= to {
+            if let Some(limiter) = &self.context.rate_limiter {
+                while let Err(retry_after) = limiter.check(self.client, RATE_LIMITED_AS) {
+                    tokio::time::sleep(retry_after).await;
+                }
+            }
+
+            let filter = EventFilter {
+                from_block: Some(block),
+                to_block: Some(to),
+                contract_address: address,
+                keys: vec![],
+                page_size: EVENT_PAGE_SIZE_LIMIT,
+                offset,
+            };
+            let (lines, continuation_token) = self
+                .read(move |tx| {
+                    // Otherwise the events of reorged blocks would be followed by those of
+                    // the blocks replacing them.
+                    let hash = tx.block_hash(to.into()).context("Querying block hash")?;
+                    anyhow::ensure!(hash == Some(to_hash), "Exported blocks were reorged");
+
+                    let page = tx
+                        .events(&filter, max_blocks_to_scan, max_bloom_filters)
+                        .context("Querying events")?;
+
+                    let mut lines = String::new();
+                    for event in page.events {
+                        let event = EmittedEvent::from(event)
+                            .serialize(Serializer::new(RpcVersion::V07))
+                            .context("Serializing event")?;
+                        lines.push_str(&event.to_string());
+                        lines.push('\n');
+                    }
+                    Ok((lines, page.continuation_token))
+                })
+                .await?;
+
+            if !lines.is_empty() && !self.send(Ok(lines)).await? {
+                return Ok(());
+            }
+
+            match continuation_token {
+                Some(token) => (block, offset) = (token.block_number, token.offset),
+                None => break,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Runs `f` in a database transaction of its own, so that a connection is
+    /// only held while a page is read rather than while the client reads it.
+    async fn read<T: Send + 'static>(
+        &self,
+        f: impl FnOnce(&pathfinder_storage::Transaction<'_>) -> anyhow::Result<T> + Send + 'static,
+    ) -> anyhow::Result<T> {
+        let storage = self.context.storage.clone();
+        let span = tracing::Span::current();
+        tokio::task::spawn_blocking(move || {
+            let _g = span.enter();
+            let mut db = storage
+                .connection()
+                .context("Opening database connection")?;
+            let tx = db.transaction().context("Creating database transaction")?;
+            f(&tx)
+        })
+        .await
+        .context("Joining database task")?
+    }
+
+    /// Sends `lines` to the client, returning false if it disconnected.
+    async fn send(&self, lines: Result<String, io::Error>) -> anyhow::Result<bool> {
+        match tokio::time::timeout(SEND_TIMEOUT, self.sender.send(lines)).await {
+            Ok(sent) => Ok(sent.is_ok()),
+            Err(_) => anyhow::bail!("Client stopped reading"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_storage::test_utils;
+
+    use super::*;
+    use crate::RpcServer;
+
+    #[tokio::test]
+    async fn events() {
+        let (storage, test_data) = test_utils::setup_test_storage();
+        let context = RpcContext::for_tests().with_storage(storage);
+        let (_jh, addr) = RpcServer::new("127.0.0.1:0".parse().unwrap(), context, RpcVersion::V07)
+            .spawn()
+            .await
+            .unwrap();
+
+        let export = |query: String| async move {
+            let response = reqwest::get(format!("http://{addr}/export/events{query}"))
+                .await
+                .unwrap();
+            let status = response.status();
+            (status, response.text().await.unwrap())
+        };
+        let parse = |body: String| {
+            body.lines()
+                .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        let (status, body) = export(String::new()).await;
+        assert_eq!(status, http::StatusCode::OK);
+        let events = parse(body);
+        assert_eq!(events.len(), test_utils::NUM_EVENTS);
+        let expected = EmittedEvent::from(test_data.events[0].clone())
+            .serialize(Serializer::new(RpcVersion::V07))
+            .unwrap();
+        assert_eq!(events[0], expected);
+
+        let event = &test_data.events[test_utils::EVENTS_PER_BLOCK + 1];
+        let (_, body) = export(format!(
+            "?from=1&to=1&address={}",
+            event.from_address.0.to_hex_str()
+        ))
+        .await;
+        let events = parse(body);
+        let expected = test_data
+            .events
+            .iter()
+            .filter(|e| e.block_number.get() == 1 && e.from_address == event.from_address)
+            .count();
+        assert_eq!(events.len(), expected);
+        assert!(events
+            .iter()
+            .all(|e| e["block_number"] == serde_json::json!(1)));
+
+        let (status, _) = export("?address=zzz".to_owned()).await;
+        assert_eq!(status, http::StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn too_many_exports() {
+        let state = ExportState {
+            context: RpcContext::for_tests(),
+            exports: Arc::new(Semaphore::new(0)),
+        };
+
+        let response = export_events(State(state), None, Query(Params::default())).await;
+        assert_eq!(response.status(), http::StatusCode::SERVICE_UNAVAILABLE);
+    }
+}
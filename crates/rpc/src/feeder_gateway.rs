@@ -0,0 +1,653 @@
+//! A subset of the feeder gateway's REST API, served from the database.
+//!
+//! This lets tooling written against the Starknet feeder gateway point at a
+//! local node instead. Only `get_block`, `get_state_update` and
+//! `get_class_by_hash` are available, with replies in the same format as the
+//! feeder gateway's.
+//!
+//! Only data which has been committed to the database is served, i.e. the
+//! pending block is not available. Neither are the state updates of blocks
+//! whose state has been pruned, nor state updates and classes on a node synced
+//! in light mode.
+//!
+//! An endpoint is only served if the [MethodFilter] allows the JSON-RPC method
+//! returning the same data.
+
+use std::net::SocketAddr;
+
+use anyhow::Context;
+use axum::extract::{Query, State};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use pathfinder_common::{BlockHash, BlockNumber, ClassHash};
+use pathfinder_crypto::Felt;
+use pathfinder_storage::BlockId;
+use starknet_gateway_types::error::{KnownStarknetErrorCode, StarknetError};
+use starknet_gateway_types::reply::{self, GasPrices};
+use tokio::task::JoinHandle;
+
+use crate::context::RpcContext;
+use crate::error::ApplicationError;
+use crate::middleware::method_filter::MethodFilter;
+
+/// Starts the feeder gateway API server on `addr`, returning its handle and
+/// the address it is actually listening on. Endpoints are served under
+/// `/feeder_gateway`.
+pub async fn spawn(
+    addr: SocketAddr,
+    context: RpcContext,
+    method_filter: MethodFilter,
+    max_connections: usize,
+) -> anyhow::Result<(JoinHandle<anyhow::Result<()>>, SocketAddr)> {
+    let listener = tokio::net::TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("Binding feeder gateway API address {addr}"))?;
+    let addr = listener
+        .local_addr()
+        .context("Getting local address from listener")?;
+
+    let shutdown = context.shutdown.clone();
+    let router =
+        crate::with_http_middleware(router(context, &method_filter), max_connections, None);
+    let server = axum::serve(listener, router.into_make_service())
+        .with_graceful_shutdown(async move { shutdown.wait_for_shutdown().await });
+
+    let handle =
+        tokio::spawn(async move { server.await.context("Feeder gateway API server error") });
+
+    Ok((handle, addr))
+}
+
+fn router(context: RpcContext, method_filter: &MethodFilter) -> axum::Router {
+    use axum::routing::get;
+
+    let mut router = axum::Router::new();
+    if method_filter.allows("starknet_getBlockWithReceipts") {
+        router = router.route("/feeder_gateway/get_block", get(get_block));
+    }
+    if method_filter.allows("starknet_getStateUpdate") {
+        router = router.route("/feeder_gateway/get_state_update", get(get_state_update));
+    }
+    if method_filter.allows("starknet_getClass") {
+        router = router.route("/feeder_gateway/get_class_by_hash", get(get_class_by_hash));
+    }
+    router.with_state(context)
+}
+
+/// The query parameters of all endpoints, named as in the feeder gateway API.
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Params {
+    block_number: Option<String>,
+    block_hash: Option<String>,
+    class_hash: Option<String>,
+    #[serde(default)]
+    header_only: bool,
+    #[serde(default)]
+    include_block: bool,
+}
+
+impl Params {
+    /// The requested block, which is the latest one if none is given.
+    fn block_id(&self) -> Result<BlockId, Error> {
+        match (&self.block_number, &self.block_hash) {
+            (None, None) => Ok(BlockId::Latest),
+            (Some(_), Some(_)) => Err(Error::malformed(
+                "Only one of blockNumber and blockHash may be given",
+            )),
+            (None, Some(hash)) => Felt::from_hex_str(hash)
+                .map(|hash| BlockId::Hash(BlockHash(hash)))
+                .map_err(|_| Error::malformed("Invalid blockHash")),
+            (Some(number), None) => match number.as_str() {
+                "latest" => Ok(BlockId::Latest),
+                "pending" => Err(Error::block_not_found(
+                    "The pending block is not served by this node",
+                )),
+                number => number
+                    .parse()
+                    .ok()
+                    .and_then(BlockNumber::new)
+                    .map(BlockId::Number)
+                    .ok_or_else(|| Error::malformed("Invalid blockNumber")),
+            },
+        }
+    }
+
+    fn class_hash(&self) -> Result<ClassHash, Error> {
+        let class_hash = self
+            .class_hash
+            .as_ref()
+            .ok_or_else(|| Error::malformed("Missing classHash"))?;
+        Felt::from_hex_str(class_hash)
+            .map(ClassHash)
+            .map_err(|_| Error::malformed("Invalid classHash"))
+    }
+}
+
+#[derive(Debug)]
+enum Error {
+    /// Returned to the client in the same format as the feeder gateway's
+    /// errors.
+    Starknet(StarknetError),
+    Internal(anyhow::Error),
+}
+
+impl Error {
+    fn starknet(code: KnownStarknetErrorCode, message: impl Into<String>) -> Self {
+        Self::Starknet(StarknetError {
+            code: code.into(),
+            message: message.into(),
+        })
+    }
+
+    fn malformed(message: impl Into<String>) -> Self {
+        Self::starknet(KnownStarknetErrorCode::MalformedRequest, message)
+    }
+
+    fn block_not_found(message: impl Into<String>) -> Self {
+        Self::starknet(KnownStarknetErrorCode::BlockNotFound, message)
+    }
+
+    /// Checks that the database has the state the JSON-RPC `method` reads. Its
+    /// absence on a node synced in light mode is reported like pruned state.
+    fn require_state(context: &RpcContext, method: &str) -> Result<(), Self> {
+        if context.has_state_for(method) {
+            Ok(())
+        } else {
+            Err(Self::block_not_found(
+                ApplicationError::StateUnavailable.to_string(),
+            ))
+        }
+    }
+}
+
+impl From<anyhow::Error> for Error {
+    /// Reads of pruned state are reported like missing blocks, as by the
+    /// JSON-RPC API.
+    fn from(e: anyhow::Error) -> Self {
+        match e.downcast_ref::<pathfinder_storage::StatePruned>() {
+            Some(pruned) => Self::block_not_found(pruned.to_string()),
+            None => Self::Internal(e),
+        }
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> Response {
+        match self {
+            Error::Starknet(error) => (http::StatusCode::BAD_REQUEST, Json(error)).into_response(),
+            Error::Internal(error) => {
+                tracing::warn!(error=?error, "Feeder gateway API request failed");
+                (http::StatusCode::INTERNAL_SERVER_ERROR, "Internal error").into_response()
+            }
+        }
+    }
+}
+
+/// Runs `f` against a database transaction on the blocking thread pool.
+async fn read<T, F>(context: &RpcContext, f: F) -> Result<T, Error>
+where
+    T: Send + 'static,
+    F: FnOnce(&pathfinder_storage::Transaction<'_>) -> Result<T, Error> + Send + 'static,
+{
+    let storage = context.storage.clone();
+    let span = tracing::Span::current();
+
+    tokio::task::spawn_blocking(move || {
+        let _g = span.enter();
+        let mut db = storage
+            .connection()
+            .context("Opening database connection")?;
+        let tx = db.transaction().context("Creating database transaction")?;
+        f(&tx)
+    })
+    .await
+    .context("Database read panic or shutting down")?
+}
+
+async fn get_block(
+    State(context): State<RpcContext>,
+    Query(params): Query<Params>,
+) -> Result<Response, Error> {
+    #[derive(serde::Serialize)]
+    struct BlockHeader {
+        block_hash: BlockHash,
+        block_number: BlockNumber,
+    }
+
+    let block = params.block_id()?;
+
+    read(&context, move |tx| {
+        if params.header_only {
+            let (block_number, block_hash) = tx
+                .block_id(block)
+                .context("Querying block id")?
+                .ok_or_else(|| Error::block_not_found("Block not found"))?;
+            return Ok(Json(BlockHeader {
+                block_hash,
+                block_number,
+            })
+            .into_response());
+        }
+
+        let block =
+            block_reply(tx, block)?.ok_or_else(|| Error::block_not_found("Block not found"))?;
+        Ok(Json(block).into_response())
+    })
+    .await
+}
+
+async fn get_state_update(
+    State(context): State<RpcContext>,
+    Query(params): Query<Params>,
+) -> Result<Response, Error> {
+    #[derive(serde::Serialize)]
+    struct StateUpdateWithBlock {
+        block: reply::Block,
+        state_update: reply::StateUpdate,
+    }
+
+    Error::require_state(&context, "starknet_getStateUpdate")?;
+    let block = params.block_id()?;
+
+    read(&context, move |tx| {
+        let state_update = tx
+            .state_update(block)
+            .context("Querying state update")?
+            .ok_or_else(|| Error::block_not_found("Block not found"))?;
+        let state_update = reply::StateUpdate::from(state_update);
+
+        if !params.include_block {
+            return Ok(Json(state_update).into_response());
+        }
+
+        let block = block_reply(tx, BlockId::Hash(state_update.block_hash))?
+            .context("Block of state update is missing")?;
+        Ok(Json(StateUpdateWithBlock {
+            block,
+            state_update,
+        })
+        .into_response())
+    })
+    .await
+}
+
+async fn get_class_by_hash(
+    State(context): State<RpcContext>,
+    Query(params): Query<Params>,
+) -> Result<Response, Error> {
+    Error::require_state(&context, "starknet_getClass")?;
+    let block = params.block_id()?;
+    let class_hash = params.class_hash()?;
+
+    read(&context, move |tx| {
+        let definition = tx
+            .class_definition_at(block, class_hash)
+            .context("Querying class definition")?
+            .ok_or_else(|| {
+                Error::starknet(
+                    KnownStarknetErrorCode::UndeclaredClass,
+                    format!("Class with hash {} is not declared.", class_hash.0),
+                )
+            })?;
+
+        // Class definitions are stored as returned by the feeder gateway.
+        Ok((
+            [(http::header::CONTENT_TYPE, "application/json")],
+            definition,
+        )
+            .into_response())
+    })
+    .await
+}
+
+/// Builds the feeder gateway's representation of a stored block.
+fn block_reply(
+    tx: &pathfinder_storage::Transaction<'_>,
+    block: BlockId,
+) -> anyhow::Result<Option<reply::Block>> {
+    let Some(header) = tx.block_header(block).context("Querying block header")? else {
+        return Ok(None);
+    };
+    let transactions = tx
+        .transaction_data_for_block(header.number.into())
+        .context("Querying transactions")?
+        .context("Transactions of block are missing")?;
+    let status = if tx
+        .block_is_l1_accepted(header.number.into())
+        .context("Querying L1 status")?
+    {
+        reply::Status::AcceptedOnL1
+    } else {
+        reply::Status::AcceptedOnL2
+    };
+
+    let (transactions, transaction_receipts) = transactions
+        .into_iter()
+        .map(|(transaction, receipt, events)| (transaction, (receipt, events)))
+        .unzip();
+
+    // These commitments are only part of the block since Starknet 0.13.2.
+    let v0_13_2 = header.starknet_version >= pathfinder_common::StarknetVersion::V_0_13_2;
+    // And L2 gas prices since Starknet 0.13.4.
+    let v0_13_4 = header.starknet_version >= pathfinder_common::StarknetVersion::V_0_13_4;
+
+    Ok(Some(reply::Block {
+        block_hash: header.hash,
+        block_number: header.number,
+        l1_gas_price: GasPrices {
+            price_in_wei: header.eth_l1_gas_price,
+            price_in_fri: header.strk_l1_gas_price,
+        },
+        l1_data_gas_price: GasPrices {
+            price_in_wei: header.eth_l1_data_gas_price,
+            price_in_fri: header.strk_l1_data_gas_price,
+        },
+        parent_block_hash: header.parent_hash,
+        sequencer_address: Some(header.sequencer_address),
+        state_commitment: header.state_commitment,
+        status,
+        timestamp: header.timestamp,
+        transaction_receipts,
+        transactions,
+        starknet_version: header.starknet_version,
+        transaction_commitment: header.transaction_commitment,
+        event_commitment: header.event_commitment,
+        l1_da_mode: header.l1_da_mode.into(),
+        receipt_commitment: v0_13_2.then_some(header.receipt_commitment),
+        state_diff_commitment: v0_13_2.then_some(header.state_diff_commitment),
+        state_diff_length: v0_13_2.then_some(header.state_diff_length),
+        l2_gas_price: v0_13_4.then_some(GasPrices {
+            price_in_wei: header.eth_l2_gas_price,
+            price_in_fri: header.strk_l2_gas_price,
+        }),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::macro_prelude::*;
+    use pathfinder_common::GasPrice;
+
+    use super::*;
+
+    /// Starts the server on an ephemeral port and returns its base URL.
+    async fn serve() -> (RpcContext, String) {
+        let context = RpcContext::for_tests();
+        let (_handle, addr) = spawn(
+            ([127, 0, 0, 1], 0).into(),
+            context.clone(),
+            MethodFilter::default(),
+            10,
+        )
+        .await
+        .unwrap();
+        (context, format!("http://{addr}/feeder_gateway"))
+    }
+
+    async fn get(url: String) -> reqwest::Response {
+        reqwest::get(url).await.unwrap()
+    }
+
+    async fn error_code(response: reqwest::Response) -> KnownStarknetErrorCode {
+        assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+        let error: StarknetError = response.json().await.unwrap();
+        match error.code {
+            starknet_gateway_types::error::StarknetErrorCode::Known(code) => code,
+            other => panic!("Unexpected error code {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn block() {
+        let (_context, url) = serve().await;
+
+        let block: reply::Block = get(format!("{url}/get_block?blockNumber=1"))
+            .await
+            .json()
+            .await
+            .unwrap();
+
+        assert_eq!(block.block_number, BlockNumber::new_or_panic(1));
+        assert_eq!(block.block_hash, block_hash_bytes!(b"block 1"));
+        assert_eq!(block.parent_block_hash, block_hash_bytes!(b"genesis"));
+        assert_eq!(block.status, reply::Status::AcceptedOnL2);
+        assert_eq!(
+            block
+                .transactions
+                .iter()
+                .map(|tx| tx.hash)
+                .collect::<Vec<_>>(),
+            vec![
+                transaction_hash_bytes!(b"txn 1"),
+                transaction_hash_bytes!(b"txn 2")
+            ]
+        );
+        assert_eq!(block.transaction_receipts.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn disabled_endpoint() {
+        let method_filter = MethodFilter {
+            disabled_methods: ["starknet_getStateUpdate".to_owned()].into(),
+            ..Default::default()
+        };
+        let (_handle, addr) = spawn(
+            ([127, 0, 0, 1], 0).into(),
+            RpcContext::for_tests(),
+            method_filter,
+            10,
+        )
+        .await
+        .unwrap();
+        let url = format!("http://{addr}/feeder_gateway");
+
+        let response = get(format!("{url}/get_state_update?blockNumber=1")).await;
+        assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+
+        let response = get(format!("{url}/get_block?blockNumber=1")).await;
+        assert_eq!(response.status(), http::StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn light_mode() {
+        let mut context = RpcContext::for_tests();
+        context.config.sync_mode = pathfinder_storage::SyncMode::Light;
+        let (_handle, addr) = spawn(
+            ([127, 0, 0, 1], 0).into(),
+            context,
+            MethodFilter::default(),
+            10,
+        )
+        .await
+        .unwrap();
+        let url = format!("http://{addr}/feeder_gateway");
+
+        let response = get(format!("{url}/get_state_update?blockNumber=1")).await;
+        assert_eq!(
+            error_code(response).await,
+            KnownStarknetErrorCode::BlockNotFound
+        );
+
+        let response = get(format!("{url}/get_block?blockNumber=1")).await;
+        assert_eq!(response.status(), http::StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn block_header_by_hash() {
+        let (_context, url) = serve().await;
+
+        let header: serde_json::Value = get(format!(
+            "{url}/get_block?blockHash={}&headerOnly=true",
+            block_hash_bytes!(b"genesis").0.to_hex_str()
+        ))
+        .await
+        .json()
+        .await
+        .unwrap();
+
+        assert_eq!(
+            header,
+            serde_json::json!({
+                "block_hash": block_hash_bytes!(b"genesis"),
+                "block_number": 0,
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn latest_state_update_with_block() {
+        #[derive(serde::Deserialize)]
+        struct Reply {
+            block: reply::Block,
+            state_update: reply::StateUpdate,
+        }
+
+        let (context, url) = serve().await;
+
+        let reply: Reply = get(format!(
+            "{url}/get_state_update?blockNumber=latest&includeBlock=true"
+        ))
+        .await
+        .json()
+        .await
+        .unwrap();
+
+        let expected = {
+            let mut db = context.storage.connection().unwrap();
+            let tx = db.transaction().unwrap();
+            tx.state_update(BlockId::Latest).unwrap().unwrap()
+        };
+        assert_eq!(reply.block.block_hash, block_hash_bytes!(b"latest"));
+        assert_eq!(
+            pathfinder_common::StateUpdate::from(reply.state_update),
+            expected
+        );
+    }
+
+    #[tokio::test]
+    async fn class_by_hash() {
+        let (_context, url) = serve().await;
+
+        let definition = get(format!(
+            "{url}/get_class_by_hash?classHash={}",
+            class_hash_bytes!(b"class 0 hash").0.to_hex_str()
+        ))
+        .await
+        .bytes()
+        .await
+        .unwrap();
+
+        assert_eq!(
+            definition.as_ref(),
+            starknet_gateway_test_fixtures::class_definitions::CONTRACT_DEFINITION
+        );
+    }
+
+    /// Serves three blocks of Starknet 0.13.4, of which only the state of the
+    /// latest two is kept.
+    async fn serve_pruned() -> String {
+        let storage =
+            pathfinder_storage::StorageBuilder::in_memory_with_history_pruning(2).unwrap();
+        let mut db = storage.connection().unwrap();
+        let tx = db.transaction().unwrap();
+        let mut header = pathfinder_common::BlockHeader::builder()
+            .starknet_version(pathfinder_common::StarknetVersion::V_0_13_4)
+            .eth_l2_gas_price(GasPrice(1))
+            .strk_l2_gas_price(GasPrice(2))
+            .finalize_with_hash(block_hash!("0x0"));
+        for number in 0..3 {
+            if number > 0 {
+                header = header
+                    .child_builder()
+                    .starknet_version(pathfinder_common::StarknetVersion::V_0_13_4)
+                    .eth_l2_gas_price(GasPrice(1))
+                    .strk_l2_gas_price(GasPrice(2))
+                    .finalize_with_hash(BlockHash(Felt::from_u64(number)));
+            }
+            tx.insert_block_header(&header).unwrap();
+        }
+        tx.commit().unwrap();
+
+        let context = RpcContext::for_tests().with_storage(storage);
+        let (_handle, addr) = spawn(
+            ([127, 0, 0, 1], 0).into(),
+            context,
+            MethodFilter::default(),
+            10,
+        )
+        .await
+        .unwrap();
+        format!("http://{addr}/feeder_gateway")
+    }
+
+    #[tokio::test]
+    async fn l2_gas_price() {
+        let url = serve_pruned().await;
+
+        let block: reply::Block = get(format!("{url}/get_block?blockNumber=latest"))
+            .await
+            .json()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            block.l2_gas_price,
+            Some(GasPrices {
+                price_in_wei: GasPrice(1),
+                price_in_fri: GasPrice(2),
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn pruned_state_update() {
+        let url = serve_pruned().await;
+
+        let response = get(format!("{url}/get_state_update?blockNumber=0")).await;
+        assert_eq!(
+            error_code(response).await,
+            KnownStarknetErrorCode::BlockNotFound
+        );
+
+        // The block itself is still available.
+        let response = get(format!("{url}/get_block?blockNumber=0")).await;
+        assert_eq!(response.status(), http::StatusCode::OK);
+
+        let response = get(format!("{url}/get_state_update?blockNumber=1")).await;
+        assert_eq!(response.status(), http::StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn errors() {
+        let (_context, url) = serve().await;
+
+        let response = get(format!("{url}/get_block?blockNumber=9999")).await;
+        assert_eq!(
+            error_code(response).await,
+            KnownStarknetErrorCode::BlockNotFound
+        );
+
+        let response = get(format!("{url}/get_state_update?blockNumber=pending")).await;
+        assert_eq!(
+            error_code(response).await,
+            KnownStarknetErrorCode::BlockNotFound
+        );
+
+        let response = get(format!("{url}/get_block?blockNumber=0x1")).await;
+        assert_eq!(
+            error_code(response).await,
+            KnownStarknetErrorCode::MalformedRequest
+        );
+
+        let response = get(format!(
+            "{url}/get_class_by_hash?classHash={}",
+            class_hash_bytes!(b"class pending hash").0.to_hex_str()
+        ))
+        .await;
+        assert_eq!(
+            error_code(response).await,
+            KnownStarknetErrorCode::UndeclaredClass
+        );
+    }
+}
@@ -0,0 +1,460 @@
+//! Deterministic in-memory chains for testing code built on top of the RPC
+//! context and the storage APIs.
+//!
+//! ```ignore
+//! let context = TestChain::new(ChainId::SEPOLIA_TESTNET)
+//!     .block(
+//!         TestBlock::default()
+//!             .with_cairo_class(class_hash, definition)
+//!             .with_deployed_contract(contract, class_hash)
+//!             .with_storage(contract, key, value),
+//!     )
+//!     .block(TestBlock::default().with_transaction(transaction, receipt, events))
+//!     .pending(TestBlock::default().with_nonce(contract, nonce))
+//!     .build()?;
+//! ```
+//!
+//! Block `N` is given the hash [block_hash]`(N)` and the timestamp `N`, unless
+//! set otherwise. The storage and class tries are computed from the state
+//! updates like sync does, so that state commitments and proofs are
+//! consistent. The context's gateway client points at an unreachable address,
+//! so methods which need the gateway fail instead of reaching a real network.
+
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Context;
+use pathfinder_common::event::Event;
+use pathfinder_common::receipt::Receipt;
+use pathfinder_common::transaction::Transaction;
+use pathfinder_common::{
+    felt_bytes,
+    BlockHash,
+    BlockHeader,
+    BlockNumber,
+    BlockTimestamp,
+    CasmHash,
+    ChainId,
+    ClassHash,
+    ContractAddress,
+    ContractNonce,
+    SierraHash,
+    StateUpdate,
+    StorageAddress,
+    StorageValue,
+    TransactionIndex,
+};
+use pathfinder_executor::TraceCache;
+use pathfinder_merkle_tree::contract_state::update_contract_state;
+use pathfinder_merkle_tree::{ClassCommitmentTree, StorageCommitmentTree, TrieNodeCache};
+use pathfinder_storage::StorageBuilder;
+use starknet_gateway_types::reply::{GasPrices, PendingBlock, Status};
+
+use crate::context::{RpcConfig, RpcContext};
+use crate::jsonrpc::Notifications;
+use crate::pending::PendingData;
+use crate::SyncState;
+
+/// The hash given to block `number` by [TestChain].
+pub fn block_hash(number: BlockNumber) -> BlockHash {
+    BlockHash(felt_bytes!(format!("block {number}").as_bytes()))
+}
+
+/// A chain of blocks, built into an [RpcContext] backed by in-memory storage.
+pub struct TestChain {
+    chain_id: ChainId,
+    blocks: Vec<TestBlock>,
+    pending: Option<TestBlock>,
+    trie_prune_mode: pathfinder_storage::TriePruneMode,
+}
+
+/// The contents of a block of a [TestChain].
+#[derive(Default)]
+pub struct TestBlock {
+    timestamp: Option<BlockTimestamp>,
+    state_update: StateUpdate,
+    transactions: Vec<(Transaction, Receipt, Vec<Event>)>,
+    cairo_classes: Vec<(ClassHash, Vec<u8>)>,
+    sierra_classes: Vec<(SierraHash, Vec<u8>, CasmHash, Vec<u8>)>,
+}
+
+impl TestChain {
+    pub fn new(chain_id: ChainId) -> Self {
+        Self {
+            chain_id,
+            blocks: Vec::new(),
+            pending: None,
+            trie_prune_mode: pathfinder_storage::TriePruneMode::Archive,
+        }
+    }
+
+    /// Appends `block` to the chain, starting at the genesis block.
+    pub fn block(mut self, block: TestBlock) -> Self {
+        self.blocks.push(block);
+        self
+    }
+
+    /// Sets the pending block on top of the latest block. Classes of the
+    /// pending block are stored as if they had been downloaded by sync.
+    pub fn pending(mut self, block: TestBlock) -> Self {
+        self.pending = Some(block);
+        self
+    }
+
+    pub fn with_trie_pruning(mut self, trie_prune_mode: pathfinder_storage::TriePruneMode) -> Self {
+        self.trie_prune_mode = trie_prune_mode;
+        self
+    }
+
+    /// Stores the chain and returns a context serving it.
+    pub fn build(self) -> anyhow::Result<RpcContext> {
+        let storage = StorageBuilder::in_memory_with_trie_pruning(self.trie_prune_mode)
+            .context("Creating in-memory storage")?;
+        let mut connection = storage.connection().context("Opening connection")?;
+        let tx = connection.transaction().context("Creating transaction")?;
+
+        let mut parent: Option<BlockHeader> = None;
+        for (number, block) in self.blocks.into_iter().enumerate() {
+            let number = BlockNumber::new_or_panic(number as u64);
+            let header = block
+                .insert(&tx, number, parent.as_ref())
+                .with_context(|| format!("Inserting block {number}"))?;
+            parent = Some(header);
+        }
+
+        let pending = match self.pending {
+            Some(block) => {
+                block.insert_classes(&tx)?;
+                block.into_pending(parent.as_ref())
+            }
+            None => PendingData::default(),
+        };
+
+        tx.commit().context("Committing chain")?;
+
+        let (_, pending_data) = tokio::sync::watch::channel(pending);
+        let sequencer = starknet_gateway_client::Client::with_base_url(
+            "http://127.0.0.1:1".parse().expect("Valid URL"),
+            Duration::from_secs(1),
+        )?
+        .disable_retry_for_tests();
+
+        Ok(RpcContext::new(
+            storage.clone(),
+            storage,
+            Arc::new(SyncState::default()),
+            self.chain_id,
+            Some(sequencer),
+            pending_data,
+            Notifications::default(),
+            config(),
+        ))
+    }
+}
+
+impl TestBlock {
+    pub fn with_timestamp(mut self, timestamp: BlockTimestamp) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    pub fn with_deployed_contract(mut self, contract: ContractAddress, class: ClassHash) -> Self {
+        self.state_update = self.state_update.with_deployed_contract(contract, class);
+        self
+    }
+
+    pub fn with_replaced_class(mut self, contract: ContractAddress, class: ClassHash) -> Self {
+        self.state_update = self.state_update.with_replaced_class(contract, class);
+        self
+    }
+
+    pub fn with_nonce(mut self, contract: ContractAddress, nonce: ContractNonce) -> Self {
+        self.state_update = self.state_update.with_contract_nonce(contract, nonce);
+        self
+    }
+
+    pub fn with_storage(
+        mut self,
+        contract: ContractAddress,
+        key: StorageAddress,
+        value: StorageValue,
+    ) -> Self {
+        self.state_update = self.state_update.with_storage_update(contract, key, value);
+        self
+    }
+
+    /// Declares a Cairo 0 class with the JSON `definition`.
+    pub fn with_cairo_class(mut self, hash: ClassHash, definition: Vec<u8>) -> Self {
+        self.state_update = self.state_update.with_declared_cairo_class(hash);
+        self.cairo_classes.push((hash, definition));
+        self
+    }
+
+    /// Declares a Sierra class with the JSON `definition`, compiled to the CASM
+    /// `casm_definition`.
+    pub fn with_sierra_class(
+        mut self,
+        hash: SierraHash,
+        definition: Vec<u8>,
+        casm_hash: CasmHash,
+        casm_definition: Vec<u8>,
+    ) -> Self {
+        self.state_update = self
+            .state_update
+            .with_declared_sierra_class(hash, casm_hash);
+        self.sierra_classes
+            .push((hash, definition, casm_hash, casm_definition));
+        self
+    }
+
+    /// Appends a transaction, whose receipt is given the transaction's hash and
+    /// index.
+    pub fn with_transaction(
+        mut self,
+        transaction: Transaction,
+        receipt: Receipt,
+        events: Vec<Event>,
+    ) -> Self {
+        let receipt = Receipt {
+            transaction_hash: transaction.hash,
+            transaction_index: TransactionIndex::new_or_panic(self.transactions.len() as u64),
+            ..receipt
+        };
+        self.transactions.push((transaction, receipt, events));
+        self
+    }
+
+    fn insert_classes(&self, tx: &pathfinder_storage::Transaction<'_>) -> anyhow::Result<()> {
+        for (hash, definition) in &self.cairo_classes {
+            tx.insert_cairo_class(*hash, definition)
+                .with_context(|| format!("Inserting class {hash}"))?;
+        }
+        for (hash, definition, casm_hash, casm_definition) in &self.sierra_classes {
+            tx.insert_sierra_class(hash, definition, casm_hash, casm_definition)
+                .with_context(|| format!("Inserting class {hash}"))?;
+        }
+
+        Ok(())
+    }
+
+    /// Inserts the block and applies its state update to the tries, returning
+    /// its header.
+    fn insert(
+        self,
+        tx: &pathfinder_storage::Transaction<'_>,
+        number: BlockNumber,
+        parent: Option<&BlockHeader>,
+    ) -> anyhow::Result<BlockHeader> {
+        self.insert_classes(tx)?;
+
+        let mut storage_commitment_tree = match number.parent() {
+            Some(parent) => StorageCommitmentTree::load(tx, parent)?,
+            None => StorageCommitmentTree::empty(tx),
+        };
+        let contract_updates =
+            self.state_update
+                .contract_updates
+                .iter()
+                .map(|(contract, update)| {
+                    let class = update.class.as_ref().map(|class| class.class_hash());
+                    (*contract, &update.storage, update.nonce, class)
+                });
+        let system_contract_updates = self
+            .state_update
+            .system_contract_updates
+            .iter()
+            .map(|(contract, update)| (*contract, &update.storage, None, None));
+        for (contract, storage, nonce, class) in contract_updates.chain(system_contract_updates) {
+            let result = update_contract_state(contract, storage, nonce, class, tx, false, number)
+                .with_context(|| format!("Updating state of contract {contract}"))?;
+            storage_commitment_tree.set(contract, result.state_hash)?;
+            result.insert(number, tx)?;
+        }
+        let (storage_commitment, trie_update) = storage_commitment_tree.commit()?;
+        let root = tx.insert_storage_trie(&trie_update, number)?;
+        tx.insert_storage_root(number, root)?;
+
+        let mut class_commitment_tree = match number.parent() {
+            Some(parent) => ClassCommitmentTree::load(tx, parent)?,
+            None => ClassCommitmentTree::empty(tx),
+        };
+        for (sierra, casm) in &self.state_update.declared_sierra_classes {
+            let leaf = pathfinder_common::calculate_class_commitment_leaf_hash(*casm);
+            class_commitment_tree.set(*sierra, leaf)?;
+            tx.insert_class_commitment_leaf(number, &leaf, casm)?;
+        }
+        let (class_commitment, trie_update) = class_commitment_tree.commit()?;
+        let root = tx.insert_class_trie(&trie_update, number)?;
+        tx.insert_class_root(number, root)?;
+
+        let builder = match parent {
+            Some(parent) => parent.child_builder(),
+            None => BlockHeader::builder(),
+        };
+        let header = builder
+            .number(number)
+            .timestamp(
+                self.timestamp
+                    .unwrap_or(BlockTimestamp::new_or_panic(number.get())),
+            )
+            .storage_commitment(storage_commitment)
+            .class_commitment(class_commitment)
+            .calculated_state_commitment()
+            .transaction_count(self.transactions.len())
+            .event_count(self.transactions.iter().map(|(.., e)| e.len()).sum())
+            .finalize_with_hash(block_hash(number));
+
+        let state_update = self
+            .state_update
+            .with_block_hash(header.hash)
+            .with_state_commitment(header.state_commitment)
+            .with_parent_state_commitment(
+                parent
+                    .map(|parent| parent.state_commitment)
+                    .unwrap_or_default(),
+            );
+        let (transactions, events): (Vec<_>, Vec<_>) = self
+            .transactions
+            .into_iter()
+            .map(|(transaction, receipt, events)| ((transaction, receipt), events))
+            .unzip();
+
+        tx.insert_block_header(&header)?;
+        tx.insert_state_update(number, &state_update)?;
+        tx.insert_transaction_data(number, &transactions, Some(&events))?;
+
+        Ok(header)
+    }
+
+    fn into_pending(self, latest: Option<&BlockHeader>) -> PendingData {
+        let number = latest.map(|latest| latest.number + 1).unwrap_or_default();
+        let (transactions, transaction_receipts) = self
+            .transactions
+            .into_iter()
+            .map(|(transaction, receipt, events)| (transaction, (receipt, events)))
+            .unzip();
+
+        let block = PendingBlock {
+            l1_gas_price: GasPrices::default(),
+            l1_data_gas_price: GasPrices::default(),
+            parent_hash: latest.map(|latest| latest.hash).unwrap_or_default(),
+            sequencer_address: Default::default(),
+            status: Status::Pending,
+            timestamp: self
+                .timestamp
+                .unwrap_or(BlockTimestamp::new_or_panic(number.get())),
+            transaction_receipts,
+            transactions,
+            starknet_version: Default::default(),
+            l1_da_mode: Default::default(),
+            l2_gas_price: Default::default(),
+        };
+
+        PendingData {
+            block: Arc::new(block),
+            state_update: Arc::new(self.state_update),
+            number,
+        }
+    }
+}
+
+/// The configuration of contexts built for tests.
+pub(crate) fn config() -> RpcConfig {
+    RpcConfig {
+        batch_concurrency_limit: NonZeroUsize::new(8).unwrap(),
+        max_batch_size: NonZeroUsize::new(1000).unwrap(),
+        max_response_size: None,
+        get_events_max_blocks_to_scan: NonZeroUsize::new(1000).unwrap(),
+        get_events_max_uncached_bloom_filters_to_load: NonZeroUsize::new(1000).unwrap(),
+        custom_versioned_constants: None,
+        health_max_block_age: Duration::from_secs(300),
+        trace_cache_size: TraceCache::DEFAULT_SIZE,
+        trie_node_cache_size: TrieNodeCache::DEFAULT_MEMORY_BUDGET,
+        execution_limits: Default::default(),
+        execution_timeout: None,
+        validate_transactions: false,
+        request_log: Default::default(),
+        sync_mode: Default::default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::macro_prelude::*;
+    use pathfinder_storage::BlockId;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn storage_and_pending_data() {
+        let contract = contract_address!("0x1");
+        let key = storage_address!("0x2");
+        let class_hash =
+            starknet_gateway_test_fixtures::class_definitions::CONTRACT_DEFINITION_CLASS_HASH;
+        let definition =
+            starknet_gateway_test_fixtures::class_definitions::CONTRACT_DEFINITION.to_vec();
+
+        let context = TestChain::new(ChainId::SEPOLIA_TESTNET)
+            .block(
+                TestBlock::default()
+                    .with_cairo_class(class_hash, definition)
+                    .with_deployed_contract(contract, class_hash)
+                    .with_storage(contract, key, storage_value!("0x3")),
+            )
+            .block(
+                TestBlock::default()
+                    .with_storage(contract, key, storage_value!("0x4"))
+                    .with_transaction(
+                        Transaction {
+                            hash: transaction_hash!("0x5"),
+                            variant: Default::default(),
+                        },
+                        Receipt::default(),
+                        vec![],
+                    ),
+            )
+            .pending(TestBlock::default().with_nonce(contract, contract_nonce!("0x6")))
+            .build()
+            .unwrap();
+
+        let mut connection = context.storage.connection().unwrap();
+        let tx = connection.transaction().unwrap();
+
+        let latest = tx.block_header(BlockId::Latest).unwrap().unwrap();
+        assert_eq!(latest.number, BlockNumber::new_or_panic(1));
+        assert_eq!(latest.hash, block_hash(latest.number));
+        assert_eq!(latest.parent_hash, block_hash(BlockNumber::GENESIS));
+        assert_eq!(latest.transaction_count, 1);
+        assert_ne!(latest.storage_commitment, Default::default());
+
+        assert_eq!(
+            tx.storage_value(BlockId::Number(BlockNumber::GENESIS), contract, key)
+                .unwrap(),
+            Some(storage_value!("0x3"))
+        );
+        assert_eq!(
+            tx.storage_value(BlockId::Latest, contract, key).unwrap(),
+            Some(storage_value!("0x4"))
+        );
+        assert_eq!(
+            tx.contract_class_hash(BlockId::Latest, contract).unwrap(),
+            Some(class_hash)
+        );
+        assert!(tx.class_definition(class_hash).unwrap().is_some());
+
+        let (transaction, receipt, ..) = tx
+            .transaction_with_receipt(transaction_hash!("0x5"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(transaction.hash, receipt.transaction_hash);
+
+        let pending = context.pending_data.get(&tx).unwrap();
+        assert_eq!(pending.number, BlockNumber::new_or_panic(2));
+        assert_eq!(pending.block.parent_hash, latest.hash);
+        assert_eq!(
+            pending.state_update.contract_nonce(contract),
+            Some(contract_nonce!("0x6"))
+        );
+    }
+}
@@ -0,0 +1,298 @@
+//! Local execution of submitted transactions for `--fork` mode.
+//!
+//! Instead of being forwarded to the gateway, submitted invoke and deploy
+//! account transactions are executed right away on top of the latest block in
+//! the database and all transactions executed locally so far. No blocks are
+//! mined: each transaction and its receipt is appended to the pending block,
+//! which is what the RPC methods serve for the `pending` block id.
+//!
+//! Nothing is written to the database. This keeps it identical to the network
+//! so that it can continue syncing later, and means local transactions are
+//! discarded on restart. For the same reason declaring classes, whose
+//! definitions would have to be stored, is not supported.
+
+use std::sync::{Arc, Mutex};
+
+use anyhow::Context;
+use pathfinder_common::TransactionIndex;
+use pathfinder_executor::{ExecutionState, L1BlobDataAvailability, TransactionExecutionError};
+use starknet_gateway_types::error::{KnownStarknetErrorCode, SequencerError, StarknetError};
+use tokio::sync::watch::Sender as WatchSender;
+
+use crate::context::RpcContext;
+use crate::executor::{apply_executed_state_diff, executed_receipt};
+use crate::pending::PendingData;
+use crate::v02::types::request::BroadcastedTransaction;
+
+/// Executes submitted transactions locally and publishes the results as the
+/// pending block.
+#[derive(Clone)]
+pub struct Fork {
+    pending: Arc<WatchSender<PendingData>>,
+    /// Held while executing, so that each transaction builds on the state left
+    /// behind by the previous one.
+    lock: Arc<Mutex<()>>,
+}
+
+impl Fork {
+    /// `pending` must be the sender of the pending data the RPC context
+    /// reads from.
+    pub fn new(pending: WatchSender<PendingData>) -> Self {
+        Self {
+            pending: Arc::new(pending),
+            lock: Default::default(),
+        }
+    }
+
+    /// Executes `transaction` and appends it to the pending block.
+    ///
+    /// Failures are reported as the [SequencerError] the gateway would have
+    /// returned, so that they map onto the same RPC errors.
+    pub(crate) async fn execute(
+        &self,
+        context: &RpcContext,
+        transaction: BroadcastedTransaction,
+    ) -> Result<TransactionHash, SequencerError> {
+        let fork = self.clone();
+        let context = context.clone();
+        let span = tracing::Span::current();
+
+        // Rejections are returned as the inner error, anything else is
+        // unexpected.
+        let jh = tokio::task::spawn_blocking(move || -> anyhow::Result<Result<_, _>> {
+            let _g = span.enter();
+            let _lock = fork.lock.lock().unwrap();
+
+            let mut db = context
+                .execution_storage
+                .connection()
+                .context("Creating database connection")?;
+            let db = db.transaction().context("Creating database transaction")?;
+
+            let pending = context
+                .pending_data
+                .get(&db)
+                .context("Querying pending data")?;
+
+            let executor_transaction = crate::executor::map_broadcasted_transaction(
+                &transaction,
+                context.chain_id,
+                &context.storage,
+            )?;
+            let transaction = transaction.into_common(context.chain_id);
+            if pending
+                .block
+                .transactions
+                .iter()
+                .any(|tx| tx.hash == transaction.hash)
+            {
+                return Ok(Err(starknet_error(
+                    KnownStarknetErrorCode::DuplicatedTransaction,
+                    "Transaction already exists".to_owned(),
+                )));
+            }
+
+            let state = ExecutionState::simulation(
+                &db,
+                context.chain_id,
+                pending.header(),
+                Some(pending.state_update.clone()),
+                L1BlobDataAvailability::Enabled,
+                context.config.custom_versioned_constants,
+            );
+            let simulation = match pathfinder_executor::simulate(
+                state,
+                vec![executor_transaction],
+                false,
+                false,
+            ) {
+                Ok(mut simulations) => simulations.pop().context("Simulation result is missing")?,
+                Err(error) => return Ok(Err(execution_error(error))),
+            };
+
+            let transaction_index =
+                TransactionIndex::new_or_panic(pending.block.transactions.len() as u64);
+            let (receipt, events) =
+                executed_receipt(&simulation, transaction.hash, transaction_index);
+            let state_update = apply_executed_state_diff(&pending.state_update, &simulation.trace);
+
+            let mut block = pending.block.as_ref().clone();
+            block.transactions.push(transaction.clone());
+            block.transaction_receipts.push((receipt, events));
+
+            fork.pending.send_replace(PendingData {
+                block: Arc::new(block),
+                state_update: Arc::new(state_update),
+                number: pending.number,
+            });
+
+            tracing::debug!(transaction_hash=%transaction.hash, block_number=%pending.number, "Appended local transaction to pending block");
+
+            Ok(Ok(transaction.hash))
+        });
+
+        jh.await
+            .context("Local execution panicked")
+            .and_then(|result| result)
+            .unwrap_or_else(|error| {
+                Err(starknet_error(
+                    KnownStarknetErrorCode::TransactionFailed,
+                    error.to_string(),
+                ))
+            })
+    }
+}
+
+fn starknet_error(code: KnownStarknetErrorCode, message: String) -> SequencerError {
+    SequencerError::StarknetError(StarknetError {
+        code: code.into(),
+        message,
+    })
+}
+
+fn execution_error(error: TransactionExecutionError) -> SequencerError {
+    match error {
+        TransactionExecutionError::ExecutionError { error, .. } => {
+            starknet_error(KnownStarknetErrorCode::ValidateFailure, error)
+        }
+        TransactionExecutionError::ExecutionResourcesExceeded { .. } => starknet_error(
+            KnownStarknetErrorCode::TransactionFailed,
+            "Execution resources exceeded".to_owned(),
+        ),
+        TransactionExecutionError::Internal(error) | TransactionExecutionError::Custom(error) => {
+            starknet_error(KnownStarknetErrorCode::TransactionFailed, error.to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::macro_prelude::*;
+    use pathfinder_common::receipt::ExecutionStatus;
+    use pathfinder_common::{
+        BlockNumber,
+        CallParam,
+        ContractAddress,
+        ContractNonce,
+        EntryPoint,
+        TransactionNonce,
+        TransactionVersion,
+    };
+    use pathfinder_crypto::Felt;
+    use starknet_gateway_test_fixtures::class_definitions::DUMMY_ACCOUNT_CLASS_HASH;
+
+    use super::*;
+    use crate::v02::types::request::{
+        BroadcastedInvokeTransaction,
+        BroadcastedInvokeTransactionV1,
+    };
+
+    async fn context() -> (RpcContext, ContractAddress, ContractAddress) {
+        let (context, _, account, universal_deployer) = crate::test_setup::test_context().await;
+        let (tx, rx) = tokio::sync::watch::channel(Default::default());
+        let context = context.with_pending_data(rx).with_fork(tx);
+
+        (context, account, universal_deployer)
+    }
+
+    /// Deploys an instance of the account class through the universal
+    /// deployer.
+    fn deploy(
+        account: ContractAddress,
+        universal_deployer: ContractAddress,
+        nonce: u64,
+    ) -> BroadcastedTransaction {
+        BroadcastedTransaction::Invoke(BroadcastedInvokeTransaction::V1(
+            BroadcastedInvokeTransactionV1 {
+                version: TransactionVersion::ONE,
+                max_fee: fee!("0x10000000000"),
+                signature: vec![],
+                nonce: TransactionNonce(Felt::from_u64(nonce)),
+                sender_address: account,
+                calldata: vec![
+                    CallParam(universal_deployer.0),
+                    CallParam(EntryPoint::hashed(b"deployContract").0),
+                    // calldata_len
+                    call_param!("0x4"),
+                    // classHash
+                    CallParam(DUMMY_ACCOUNT_CLASS_HASH.0),
+                    // salt
+                    CallParam(Felt::from_u64(nonce)),
+                    // unique
+                    call_param!("0x0"),
+                    // calldata_len
+                    call_param!("0x0"),
+                ],
+            },
+        ))
+    }
+
+    #[tokio::test]
+    async fn transactions_build_on_each_other() {
+        let (context, account, universal_deployer) = context().await;
+        let fork = context.fork.clone().unwrap();
+
+        let first = fork
+            .execute(&context, deploy(account, universal_deployer, 0))
+            .await
+            .unwrap();
+        let second = fork
+            .execute(&context, deploy(account, universal_deployer, 1))
+            .await
+            .unwrap();
+
+        let pending = context.pending_data.receiver().borrow().clone();
+        assert_eq!(pending.number, BlockNumber::new_or_panic(2));
+
+        let hashes = pending
+            .block
+            .transactions
+            .iter()
+            .map(|tx| tx.hash)
+            .collect::<Vec<_>>();
+        assert_eq!(hashes, vec![first, second]);
+
+        for (index, (receipt, events)) in pending.block.transaction_receipts.iter().enumerate() {
+            assert_eq!(receipt.transaction_index.get(), index as u64);
+            assert_eq!(receipt.execution_status, ExecutionStatus::Succeeded);
+            assert!(!events.is_empty());
+        }
+
+        assert_eq!(
+            pending.state_update.contract_nonce(account),
+            Some(ContractNonce(Felt::from_u64(2)))
+        );
+    }
+
+    #[tokio::test]
+    async fn duplicate_transaction() {
+        let (context, account, universal_deployer) = context().await;
+        let fork = context.fork.clone().unwrap();
+
+        fork.execute(&context, deploy(account, universal_deployer, 0))
+            .await
+            .unwrap();
+        let error = fork
+            .execute(&context, deploy(account, universal_deployer, 0))
+            .await
+            .unwrap_err();
+
+        assert_matches::assert_matches!(
+            error,
+            SequencerError::StarknetError(e) if e.code == KnownStarknetErrorCode::DuplicatedTransaction.into()
+        );
+    }
+
+    #[tokio::test]
+    async fn invalid_nonce() {
+        let (context, account, universal_deployer) = context().await;
+        let fork = context.fork.clone().unwrap();
+
+        fork.execute(&context, deploy(account, universal_deployer, 1))
+            .await
+            .unwrap_err();
+
+        let pending = context.pending_data.receiver().borrow().clone();
+        assert!(pending.block.transactions.is_empty());
+    }
+}
@@ -0,0 +1,990 @@
+//! A read-only GraphQL endpoint over the chain data in storage.
+//!
+//! Unlike the JSON-RPC API, which mirrors the Starknet specification, the
+//! GraphQL schema exposes the relations between stored objects directly. This
+//! allows queries such as "all events emitted by a contract, together with
+//! the transactions which emitted them" to be answered in a single request.
+//!
+//! Only data which has been committed to the database is visible, i.e. the
+//! pending block is not part of the schema.
+//!
+//! Each top-level field is treated as a call of the JSON-RPC method serving
+//! the same data, e.g. `events` as `starknet_getEvents`, for the purposes of
+//! the [MethodFilter] and rate limits. The fields nested below it are covered
+//! by that call, and are loaded in batches across the whole query. Fields
+//! reading classes fail on a node synced in light mode, like their JSON-RPC
+//! counterparts.
+
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+
+use anyhow::Context as _;
+use async_graphql::dataloader::{DataLoader, Loader};
+use async_graphql::{
+    Context,
+    EmptyMutation,
+    EmptySubscription,
+    Enum,
+    InputValueError,
+    InputValueResult,
+    Object,
+    Scalar,
+    ScalarType,
+    Schema,
+    SimpleObject,
+    Value,
+};
+use async_graphql_axum::{GraphQLRequest, GraphQLResponse};
+use axum::extract::{ConnectInfo, State};
+use axum::response::IntoResponse;
+use http::StatusCode;
+use pathfinder_common::receipt::{ExecutionStatus, Receipt as CommonReceipt};
+use pathfinder_common::transaction::{Transaction as CommonTransaction, TransactionKind};
+use pathfinder_common::{
+    BlockHash,
+    BlockHeader,
+    BlockId,
+    BlockNumber,
+    ClassHash,
+    ContractAddress,
+    EventKey,
+    TransactionHash,
+};
+use pathfinder_crypto::Felt;
+use pathfinder_storage::EventFilter;
+use tokio::task::JoinHandle;
+
+use crate::context::RpcContext;
+use crate::method::get_events::EVENT_PAGE_SIZE_LIMIT;
+use crate::middleware::method_filter::MethodFilter;
+use crate::shutdown::ShutdownCoordinator;
+
+/// The maximum number of blocks returned by a single `blocks` query.
+const BLOCK_RANGE_LIMIT: u64 = 100;
+/// The maximum nesting depth of a query.
+const QUERY_DEPTH_LIMIT: usize = 8;
+/// The maximum complexity of a query. Every field counts once, and the fields
+/// of list elements once per element.
+const QUERY_COMPLEXITY_LIMIT: usize = 10_000;
+/// The number of elements assumed for lists whose length is only known once
+/// they have been loaded, e.g. the transactions of a block.
+const ASSUMED_LIST_LENGTH: usize = 100;
+
+pub type GraphQLSchema = Schema<Query, EmptyMutation, EmptySubscription>;
+
+pub fn schema(context: RpcContext, method_filter: Arc<MethodFilter>) -> GraphQLSchema {
+    let loader = DataLoader::new(StorageLoader(context.storage.clone()), tokio::spawn);
+
+    Schema::build(Query, EmptyMutation, EmptySubscription)
+        .data(context)
+        .data(method_filter)
+        .data(loader)
+        .limit_depth(QUERY_DEPTH_LIMIT)
+        .limit_complexity(QUERY_COMPLEXITY_LIMIT)
+        .finish()
+}
+
+#[derive(Clone)]
+struct ServerState {
+    schema: GraphQLSchema,
+    shutdown: ShutdownCoordinator,
+}
+
+/// Starts the GraphQL server on `addr`, returning its handle and the address
+/// it is actually listening on. Queries are served on `/graphql`, behind the
+/// same middleware and graceful shutdown as the JSON-RPC server.
+pub async fn spawn(
+    addr: SocketAddr,
+    context: RpcContext,
+    method_filter: MethodFilter,
+    max_connections: usize,
+) -> anyhow::Result<(JoinHandle<anyhow::Result<()>>, SocketAddr)> {
+    let listener = tokio::net::TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("Binding GraphQL address {addr}"))?;
+    let addr = listener
+        .local_addr()
+        .context("Getting local address from listener")?;
+
+    let shutdown = context.shutdown.clone();
+    let state = ServerState {
+        schema: schema(context, Arc::new(method_filter)),
+        shutdown: shutdown.clone(),
+    };
+    let router = axum::Router::new()
+        .route("/graphql", axum::routing::post(graphql_handler))
+        .with_state(state);
+    let router = crate::with_http_middleware(router, max_connections, None);
+
+    // Connection info provides the client IP address for per-IP rate limits.
+    let service = router.into_make_service_with_connect_info::<SocketAddr>();
+    let server = axum::serve(listener, service)
+        .with_graceful_shutdown(async move { shutdown.wait_for_shutdown().await });
+
+    let handle = tokio::spawn(async move { server.await.context("GraphQL server error") });
+
+    Ok((handle, addr))
+}
+
+/// The address of the client, for per-IP rate limits.
+struct ClientIp(Option<IpAddr>);
+
+async fn graphql_handler(
+    State(state): State<ServerState>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    request: GraphQLRequest,
+) -> axum::response::Response {
+    // Held until the response is ready, so that shutdown waits for this request.
+    let Some(_in_flight) = state.shutdown.begin_request() else {
+        return StatusCode::SERVICE_UNAVAILABLE.into_response();
+    };
+
+    let client_ip = ClientIp(connect_info.map(|ConnectInfo(addr)| addr.ip()));
+    let request = request.into_inner().data(client_ip);
+
+    GraphQLResponse::from(state.schema.execute(request).await).into_response()
+}
+
+/// Checks that `method` is exposed and has the state it reads, and takes a
+/// token for calling it from the configured rate limits.
+fn authorize(ctx: &Context<'_>, method: &str) -> async_graphql::Result<()> {
+    if !ctx.data::<Arc<MethodFilter>>()?.allows(method) {
+        return Err(format!("This query is disabled, as {method} is").into());
+    }
+
+    require_state(ctx, method)?;
+
+    if let Some(limiter) = &ctx.data::<RpcContext>()?.rate_limiter {
+        let client = ctx.data_opt::<ClientIp>().and_then(|client| client.0);
+        limiter.check(client, method).map_err(|retry_after| {
+            format!(
+                "Rate limit exceeded, retry after {} ms",
+                retry_after.as_millis()
+            )
+        })?;
+    }
+
+    Ok(())
+}
+
+/// Checks that the database has the state `method` reads, which it doesn't on
+/// a node synced in light mode.
+fn require_state(ctx: &Context<'_>, method: &str) -> async_graphql::Result<()> {
+    if !ctx.data::<RpcContext>()?.has_state_for(method) {
+        return Err(crate::error::ApplicationError::StateUnavailable
+            .to_string()
+            .into());
+    }
+
+    Ok(())
+}
+
+/// Logs `error` and replaces it by a generic message, so that database
+/// internals are not leaked to the client.
+fn internal_error(error: &anyhow::Error) -> async_graphql::Error {
+    tracing::warn!(error=?error, "GraphQL query failed");
+    async_graphql::Error::new("Internal error")
+}
+
+/// Runs `f` against a database transaction on the blocking thread pool.
+async fn read<T, F>(ctx: &Context<'_>, f: F) -> async_graphql::Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce(&pathfinder_storage::Transaction<'_>) -> anyhow::Result<T> + Send + 'static,
+{
+    let storage = ctx.data::<RpcContext>()?.storage.clone();
+
+    read_storage(storage, f)
+        .await
+        .map_err(|error| internal_error(&error))
+}
+
+async fn read_storage<T, F>(storage: pathfinder_storage::Storage, f: F) -> anyhow::Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce(&pathfinder_storage::Transaction<'_>) -> anyhow::Result<T> + Send + 'static,
+{
+    let span = tracing::Span::current();
+
+    tokio::task::spawn_blocking(move || {
+        let _g = span.enter();
+        let mut db = storage
+            .connection()
+            .context("Opening database connection")?;
+        let tx = db.transaction().context("Creating database transaction")?;
+        f(&tx)
+    })
+    .await
+    .context("Database read panic or shutting down")?
+}
+
+/// Loads the value of `key` through the query's [StorageLoader], batching it
+/// with the loads of the other fields being resolved.
+async fn load<K, V>(ctx: &Context<'_>, key: K) -> async_graphql::Result<Option<V>>
+where
+    K: Send + Sync + std::hash::Hash + Eq + Clone + 'static,
+    V: Send + Sync + Clone + 'static,
+    StorageLoader: Loader<K, Value = V, Error = Arc<anyhow::Error>>,
+{
+    ctx.data::<DataLoader<StorageLoader>>()?
+        .load_one(key)
+        .await
+        .map_err(|error| internal_error(&error))
+}
+
+/// Reads the values of nested fields, each batch of keys in a single database
+/// transaction.
+pub struct StorageLoader(pathfinder_storage::Storage);
+
+impl StorageLoader {
+    /// Reads the value of each key using `f`, leaving out those which are
+    /// missing.
+    async fn read<K, V>(
+        &self,
+        keys: &[K],
+        f: impl Fn(&pathfinder_storage::Transaction<'_>, K) -> anyhow::Result<Option<V>>
+            + Send
+            + 'static,
+    ) -> Result<HashMap<K, V>, Arc<anyhow::Error>>
+    where
+        K: Send + std::hash::Hash + Eq + Clone + 'static,
+        V: Send + 'static,
+    {
+        let keys = keys.to_vec();
+        read_storage(self.0.clone(), move |tx| {
+            let mut values = HashMap::with_capacity(keys.len());
+            for key in keys {
+                if let Some(value) = f(tx, key.clone())? {
+                    values.insert(key, value);
+                }
+            }
+            Ok(values)
+        })
+        .await
+        .map_err(Arc::new)
+    }
+}
+
+impl Loader<BlockNumber> for StorageLoader {
+    type Value = Block;
+    type Error = Arc<anyhow::Error>;
+
+    async fn load(&self, keys: &[BlockNumber]) -> Result<HashMap<BlockNumber, Block>, Self::Error> {
+        self.read(keys, |tx, number| {
+            Ok(tx.block_header(number.into())?.map(Block))
+        })
+        .await
+    }
+}
+
+impl Loader<TransactionHash> for StorageLoader {
+    type Value = Transaction;
+    type Error = Arc<anyhow::Error>;
+
+    async fn load(
+        &self,
+        keys: &[TransactionHash],
+    ) -> Result<HashMap<TransactionHash, Transaction>, Self::Error> {
+        self.read(keys, |tx, hash| {
+            Ok(tx.transaction_with_receipt(hash)?.map(
+                |(transaction, receipt, events, block_number)| {
+                    Transaction::new(transaction, receipt, events, block_number)
+                },
+            ))
+        })
+        .await
+    }
+}
+
+/// The transactions of a block.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct BlockTransactions(BlockNumber);
+
+impl Loader<BlockTransactions> for StorageLoader {
+    type Value = Vec<Transaction>;
+    type Error = Arc<anyhow::Error>;
+
+    async fn load(
+        &self,
+        keys: &[BlockTransactions],
+    ) -> Result<HashMap<BlockTransactions, Vec<Transaction>>, Self::Error> {
+        self.read(keys, |tx, BlockTransactions(number)| {
+            Ok(tx.transaction_data_for_block(number.into())?.map(|data| {
+                data.into_iter()
+                    .map(|(transaction, receipt, events)| {
+                        Transaction::new(transaction, receipt, events, number)
+                    })
+                    .collect()
+            }))
+        })
+        .await
+    }
+}
+
+/// The classes declared in a block.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct DeclaredClasses(BlockNumber);
+
+impl Loader<DeclaredClasses> for StorageLoader {
+    type Value = Vec<Class>;
+    type Error = Arc<anyhow::Error>;
+
+    async fn load(
+        &self,
+        keys: &[DeclaredClasses],
+    ) -> Result<HashMap<DeclaredClasses, Vec<Class>>, Self::Error> {
+        self.read(keys, |tx, DeclaredClasses(number)| {
+            let (classes, _) = tx.declared_classes_in_range(number, number, usize::MAX)?;
+            Ok(Some(
+                classes
+                    .into_iter()
+                    .map(|class| Class {
+                        hash: class.class_hash,
+                        block_number: Some(class.block_number),
+                    })
+                    .collect(),
+            ))
+        })
+        .await
+    }
+}
+
+/// Whether a class is a Sierra class.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct IsSierra(ClassHash);
+
+impl Loader<IsSierra> for StorageLoader {
+    type Value = bool;
+    type Error = Arc<anyhow::Error>;
+
+    async fn load(&self, keys: &[IsSierra]) -> Result<HashMap<IsSierra, bool>, Self::Error> {
+        self.read(keys, |tx, IsSierra(hash)| tx.is_sierra(hash))
+            .await
+    }
+}
+
+/// The definition of a class, as JSON.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct ClassDefinition(ClassHash);
+
+impl Loader<ClassDefinition> for StorageLoader {
+    type Value = String;
+    type Error = Arc<anyhow::Error>;
+
+    async fn load(
+        &self,
+        keys: &[ClassDefinition],
+    ) -> Result<HashMap<ClassDefinition, String>, Self::Error> {
+        self.read(keys, |tx, ClassDefinition(hash)| {
+            tx.class_definition(hash)?
+                .map(|definition| {
+                    String::from_utf8(definition).context("Class definition is not valid UTF-8")
+                })
+                .transpose()
+        })
+        .await
+    }
+}
+
+/// A field element, as a "0x" prefixed hex string.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Hex(Felt);
+
+#[Scalar(name = "Felt")]
+impl ScalarType for Hex {
+    fn parse(value: Value) -> InputValueResult<Self> {
+        match value {
+            Value::String(s) => Felt::from_hex_str(&s)
+                .map(Hex)
+                .map_err(InputValueError::custom),
+            other => Err(InputValueError::expected_type(other)),
+        }
+    }
+
+    fn to_value(&self) -> Value {
+        Value::String(self.0.to_hex_str().into_owned())
+    }
+}
+
+pub struct Query;
+
+#[Object]
+impl Query {
+    /// A block by number or hash. Defaults to the latest block if neither is
+    /// given.
+    async fn block(
+        &self,
+        ctx: &Context<'_>,
+        number: Option<u64>,
+        hash: Option<Hex>,
+    ) -> async_graphql::Result<Option<Block>> {
+        let block_id = match (number, hash) {
+            (Some(_), Some(_)) => {
+                return Err("Only one of `number` and `hash` may be given".into());
+            }
+            (Some(number), None) => {
+                BlockId::Number(BlockNumber::new(number).ok_or("Block number out of range")?)
+            }
+            (None, Some(hash)) => BlockId::Hash(BlockHash(hash.0)),
+            (None, None) => BlockId::Latest,
+        };
+        authorize(ctx, "starknet_getBlockWithReceipts")?;
+
+        read(ctx, move |tx| Ok(tx.block_header(block_id)?.map(Block))).await
+    }
+
+    /// The blocks in `from..=to`, stopping at the latest block. At most 100
+    /// blocks are returned.
+    #[graphql(
+        complexity = "(to.saturating_sub(from) as usize + 1).saturating_mul(child_complexity)"
+    )]
+    async fn blocks(
+        &self,
+        ctx: &Context<'_>,
+        from: u64,
+        to: u64,
+    ) -> async_graphql::Result<Vec<Block>> {
+        if to < from {
+            return Err("`to` must not be smaller than `from`".into());
+        }
+        if to - from >= BLOCK_RANGE_LIMIT {
+            return Err(format!("At most {BLOCK_RANGE_LIMIT} blocks may be requested").into());
+        }
+        let from = BlockNumber::new(from).ok_or("Block number out of range")?;
+        let to = BlockNumber::new(to).ok_or("Block number out of range")?;
+        authorize(ctx, "starknet_getBlockWithReceipts")?;
+
+        read(ctx, move |tx| {
+            let mut blocks = Vec::new();
+            let mut number = from;
+            while number <= to {
+                let Some(header) = tx.block_header(number.into())? else {
+                    break;
+                };
+                blocks.push(Block(header));
+                number += 1;
+            }
+            Ok(blocks)
+        })
+        .await
+    }
+
+    /// A transaction by hash.
+    async fn transaction(
+        &self,
+        ctx: &Context<'_>,
+        hash: Hex,
+    ) -> async_graphql::Result<Option<Transaction>> {
+        authorize(ctx, "starknet_getTransactionReceipt")?;
+
+        load(ctx, TransactionHash(hash.0)).await
+    }
+
+    /// Events matching the filter, in the order they were emitted.
+    ///
+    /// A page holds at most `first` events. The next page is requested by
+    /// passing the `continuation` of this page as `fromBlock` and `offset`.
+    #[allow(clippy::too_many_arguments)]
+    #[graphql(complexity = "first.saturating_mul(child_complexity)")]
+    async fn events(
+        &self,
+        ctx: &Context<'_>,
+        contract_address: Option<Hex>,
+        #[graphql(default)] keys: Vec<Vec<Hex>>,
+        from_block: Option<u64>,
+        to_block: Option<u64>,
+        #[graphql(default = 100)] first: usize,
+        #[graphql(default)] offset: usize,
+    ) -> async_graphql::Result<EventPage> {
+        if first == 0 || first > EVENT_PAGE_SIZE_LIMIT {
+            return Err(format!("`first` must be between 1 and {EVENT_PAGE_SIZE_LIMIT}").into());
+        }
+        authorize(ctx, "starknet_getEvents")?;
+
+        let config = &ctx.data::<RpcContext>()?.config;
+        let max_blocks_to_scan = config.get_events_max_blocks_to_scan;
+        let max_bloom_filters_to_load = config.get_events_max_uncached_bloom_filters_to_load;
+
+        let filter = EventFilter {
+            from_block: from_block
+                .map(|n| BlockNumber::new(n).ok_or("Block number out of range"))
+                .transpose()?,
+            to_block: to_block
+                .map(|n| BlockNumber::new(n).ok_or("Block number out of range"))
+                .transpose()?,
+            contract_address: contract_address.map(|address| ContractAddress(address.0)),
+            keys: keys
+                .into_iter()
+                .map(|keys| keys.into_iter().map(|key| EventKey(key.0)).collect())
+                .collect(),
+            page_size: first,
+            offset,
+        };
+
+        read(ctx, move |tx| {
+            let page = tx.events(&filter, max_blocks_to_scan, max_bloom_filters_to_load)?;
+
+            Ok(EventPage {
+                events: page
+                    .events
+                    .into_iter()
+                    .map(|event| Event {
+                        from_address: event.from_address,
+                        keys: event.keys,
+                        data: event.data.into_iter().map(|data| data.0).collect(),
+                        block_number: event.block_number,
+                        transaction_hash: event.transaction_hash,
+                    })
+                    .collect(),
+                continuation: page.continuation_token.map(|token| Continuation {
+                    from_block: token.block_number.get(),
+                    offset: token.offset,
+                }),
+            })
+        })
+        .await
+    }
+
+    /// A class by hash.
+    async fn class(&self, ctx: &Context<'_>, hash: Hex) -> async_graphql::Result<Option<Class>> {
+        let hash = ClassHash(hash.0);
+        authorize(ctx, "starknet_getClass")?;
+
+        read(ctx, move |tx| {
+            Ok(tx
+                .class_definition_with_block_number(hash)?
+                .map(|(block_number, _)| Class { hash, block_number }))
+        })
+        .await
+    }
+}
+
+#[derive(Clone)]
+pub struct Block(BlockHeader);
+
+#[Object]
+impl Block {
+    async fn number(&self) -> u64 {
+        self.0.number.get()
+    }
+
+    async fn hash(&self) -> Hex {
+        Hex(self.0.hash.0)
+    }
+
+    async fn parent_hash(&self) -> Hex {
+        Hex(self.0.parent_hash.0)
+    }
+
+    async fn timestamp(&self) -> u64 {
+        self.0.timestamp.get()
+    }
+
+    async fn sequencer_address(&self) -> Hex {
+        Hex(self.0.sequencer_address.0)
+    }
+
+    async fn state_commitment(&self) -> Hex {
+        Hex(self.0.state_commitment.0)
+    }
+
+    async fn starknet_version(&self) -> String {
+        self.0.starknet_version.to_string()
+    }
+
+    async fn transaction_count(&self) -> usize {
+        self.0.transaction_count
+    }
+
+    async fn event_count(&self) -> usize {
+        self.0.event_count
+    }
+
+    async fn parent(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<Block>> {
+        let Some(parent) = self.0.number.parent() else {
+            return Ok(None);
+        };
+        load(ctx, parent).await
+    }
+
+    /// The transactions of this block, in order.
+    #[graphql(complexity = "ASSUMED_LIST_LENGTH.saturating_mul(child_complexity)")]
+    async fn transactions(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<Transaction>> {
+        let transactions = load(ctx, BlockTransactions(self.0.number)).await?;
+        Ok(transactions.unwrap_or_default())
+    }
+
+    /// The classes declared in this block.
+    #[graphql(complexity = "ASSUMED_LIST_LENGTH.saturating_mul(child_complexity)")]
+    async fn declared_classes(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<Class>> {
+        require_state(ctx, "pathfinder_getClassDefinitions")?;
+        let classes = load(ctx, DeclaredClasses(self.0.number)).await?;
+        Ok(classes.unwrap_or_default())
+    }
+}
+
+#[derive(Enum, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TransactionType {
+    Declare,
+    Deploy,
+    DeployAccount,
+    Invoke,
+    L1Handler,
+}
+
+impl From<TransactionKind> for TransactionType {
+    fn from(kind: TransactionKind) -> Self {
+        match kind {
+            TransactionKind::Declare => Self::Declare,
+            TransactionKind::Deploy => Self::Deploy,
+            TransactionKind::DeployAccount => Self::DeployAccount,
+            TransactionKind::Invoke => Self::Invoke,
+            TransactionKind::L1Handler => Self::L1Handler,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Transaction {
+    transaction: CommonTransaction,
+    receipt: CommonReceipt,
+    events: Vec<pathfinder_common::event::Event>,
+    block_number: BlockNumber,
+}
+
+impl Transaction {
+    fn new(
+        transaction: CommonTransaction,
+        receipt: CommonReceipt,
+        events: Vec<pathfinder_common::event::Event>,
+        block_number: BlockNumber,
+    ) -> Self {
+        Self {
+            transaction,
+            receipt,
+            events,
+            block_number,
+        }
+    }
+}
+
+#[Object]
+impl Transaction {
+    async fn hash(&self) -> Hex {
+        Hex(self.transaction.hash.0)
+    }
+
+    /// The position of the transaction within its block.
+    async fn index(&self) -> u64 {
+        self.receipt.transaction_index.get()
+    }
+
+    #[graphql(name = "type")]
+    async fn kind(&self) -> TransactionType {
+        self.transaction.variant.kind().into()
+    }
+
+    async fn block_number(&self) -> u64 {
+        self.block_number.get()
+    }
+
+    async fn block(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<Block>> {
+        load(ctx, self.block_number).await
+    }
+
+    async fn receipt(&self) -> Receipt {
+        Receipt {
+            actual_fee: Hex(self.receipt.actual_fee.0),
+            execution_status: match self.receipt.execution_status {
+                ExecutionStatus::Succeeded => Status::Succeeded,
+                ExecutionStatus::Reverted { .. } => Status::Reverted,
+            },
+            revert_reason: self.receipt.revert_reason().map(ToOwned::to_owned),
+            l2_to_l1_message_count: self.receipt.l2_to_l1_messages.len(),
+        }
+    }
+
+    /// The events emitted by this transaction, in order.
+    #[graphql(complexity = "ASSUMED_LIST_LENGTH.saturating_mul(child_complexity)")]
+    async fn events(&self) -> Vec<Event> {
+        self.events
+            .iter()
+            .map(|event| Event {
+                from_address: event.from_address,
+                keys: event.keys.clone(),
+                data: event.data.iter().map(|data| data.0).collect(),
+                block_number: self.block_number,
+                transaction_hash: self.transaction.hash,
+            })
+            .collect()
+    }
+}
+
+#[derive(Enum, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Status {
+    Succeeded,
+    Reverted,
+}
+
+#[derive(SimpleObject)]
+pub struct Receipt {
+    actual_fee: Hex,
+    execution_status: Status,
+    revert_reason: Option<String>,
+    l2_to_l1_message_count: usize,
+}
+
+pub struct Event {
+    from_address: ContractAddress,
+    keys: Vec<EventKey>,
+    data: Vec<Felt>,
+    block_number: BlockNumber,
+    transaction_hash: TransactionHash,
+}
+
+#[Object]
+impl Event {
+    async fn from_address(&self) -> Hex {
+        Hex(self.from_address.0)
+    }
+
+    async fn keys(&self) -> Vec<Hex> {
+        self.keys.iter().map(|key| Hex(key.0)).collect()
+    }
+
+    async fn data(&self) -> Vec<Hex> {
+        self.data.iter().copied().map(Hex).collect()
+    }
+
+    async fn block_number(&self) -> u64 {
+        self.block_number.get()
+    }
+
+    async fn transaction_hash(&self) -> Hex {
+        Hex(self.transaction_hash.0)
+    }
+
+    /// The transaction which emitted this event.
+    async fn transaction(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<Transaction>> {
+        load(ctx, self.transaction_hash).await
+    }
+
+    async fn block(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<Block>> {
+        load(ctx, self.block_number).await
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct EventPage {
+    events: Vec<Event>,
+    /// Present if there are more events matching the filter.
+    continuation: Option<Continuation>,
+}
+
+#[derive(SimpleObject)]
+pub struct Continuation {
+    from_block: u64,
+    offset: usize,
+}
+
+#[derive(Clone)]
+pub struct Class {
+    hash: ClassHash,
+    block_number: Option<BlockNumber>,
+}
+
+#[Object]
+impl Class {
+    async fn hash(&self) -> Hex {
+        Hex(self.hash.0)
+    }
+
+    /// The block in which the class was declared. Classes which have been
+    /// downloaded but whose declaring block has not yet been synced have
+    /// none.
+    async fn block_number(&self) -> Option<u64> {
+        self.block_number.map(|number| number.get())
+    }
+
+    /// Whether this is a Sierra (Cairo 1) class.
+    async fn sierra(&self, ctx: &Context<'_>) -> async_graphql::Result<bool> {
+        let sierra = load(ctx, IsSierra(self.hash)).await?;
+        Ok(sierra.unwrap_or_default())
+    }
+
+    /// The class definition, as JSON.
+    async fn definition(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<String>> {
+        load(ctx, ClassDefinition(self.hash)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::macro_prelude::*;
+    use serde_json::json;
+
+    use super::*;
+
+    async fn execute(query: &str) -> serde_json::Value {
+        let response = schema(RpcContext::for_tests(), Default::default())
+            .execute(query)
+            .await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        serde_json::to_value(response.data).unwrap()
+    }
+
+    #[tokio::test]
+    async fn block_with_transactions() {
+        let data = execute(
+            r#"{ block(number: 1) { number hash parent { number } transactions { hash type } } }"#,
+        )
+        .await;
+
+        assert_eq!(
+            data,
+            json!({
+                "block": {
+                    "number": 1,
+                    "hash": block_hash_bytes!(b"block 1").0.to_hex_str(),
+                    "parent": { "number": 0 },
+                    "transactions": [
+                        {
+                            "hash": transaction_hash_bytes!(b"txn 1").0.to_hex_str(),
+                            "type": "INVOKE",
+                        },
+                        {
+                            "hash": transaction_hash_bytes!(b"txn 2").0.to_hex_str(),
+                            "type": "INVOKE",
+                        },
+                    ],
+                }
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn missing_block() {
+        let data = execute("{ block(number: 9999) { number } }").await;
+
+        assert_eq!(data, json!({ "block": null }));
+    }
+
+    #[tokio::test]
+    async fn events_joined_to_transactions() {
+        let query = format!(
+            r#"{{ events(contractAddress: "{}") {{ events {{ data transaction {{ hash blockNumber }} }} continuation {{ fromBlock }} }} }}"#,
+            contract_address_bytes!(b"event 0 from addr").0.to_hex_str()
+        );
+
+        let data = execute(&query).await;
+
+        assert_eq!(
+            data,
+            json!({
+                "events": {
+                    "events": [{
+                        "data": [event_data_bytes!(b"event 0 data").0.to_hex_str()],
+                        "transaction": {
+                            "hash": transaction_hash_bytes!(b"txn 0").0.to_hex_str(),
+                            "blockNumber": 0,
+                        },
+                    }],
+                    "continuation": null,
+                }
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn class_declared_in_block() {
+        let query = format!(
+            r#"{{ class(hash: "{}") {{ hash sierra }} }}"#,
+            class_hash_bytes!(b"class 2 hash (sierra)").0.to_hex_str()
+        );
+
+        let data = execute(&query).await;
+
+        assert_eq!(
+            data,
+            json!({
+                "class": {
+                    "hash": class_hash_bytes!(b"class 2 hash (sierra)").0.to_hex_str(),
+                    "sierra": true,
+                }
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn invalid_felt_is_rejected() {
+        let response = schema(RpcContext::for_tests(), Default::default())
+            .execute(r#"{ transaction(hash: "not hex") { hash } }"#)
+            .await;
+
+        assert_eq!(response.errors.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn complex_query_is_rejected() {
+        let response = schema(RpcContext::for_tests(), Default::default())
+            .execute("{ blocks(from: 0, to: 99) { transactions { events { data } } } }")
+            .await;
+
+        assert_eq!(response.errors.len(), 1);
+        assert!(response.errors[0].message.contains("complex"));
+    }
+
+    #[tokio::test]
+    async fn disabled_methods_are_respected() {
+        let method_filter = MethodFilter {
+            disabled_methods: ["starknet_getEvents".to_owned()].into(),
+            ..Default::default()
+        };
+        let schema = schema(RpcContext::for_tests(), Arc::new(method_filter));
+
+        let response = schema.execute("{ events { events { data } } }").await;
+        assert_eq!(response.errors.len(), 1);
+
+        let response = schema.execute("{ block(number: 0) { number } }").await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+    }
+
+    #[tokio::test]
+    async fn light_mode() {
+        let mut context = RpcContext::for_tests();
+        context.config.sync_mode = pathfinder_storage::SyncMode::Light;
+        let schema = schema(context, Default::default());
+
+        let response = schema.execute(r#"{ class(hash: "0x1") { hash } }"#).await;
+        assert_eq!(response.errors.len(), 1);
+
+        let response = schema
+            .execute("{ block(number: 0) { declaredClasses { hash } } }")
+            .await;
+        assert_eq!(response.errors.len(), 1);
+
+        let response = schema.execute("{ block(number: 0) { number } }").await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+    }
+
+    #[tokio::test]
+    async fn rate_limits() {
+        use crate::middleware::rate_limit::{BucketConfig, RateLimitConfig};
+
+        let context = RpcContext::for_tests().with_rate_limits(RateLimitConfig {
+            global: Some(BucketConfig {
+                requests_per_second: 1.try_into().unwrap(),
+                burst: None,
+            }),
+            ..Default::default()
+        });
+        let schema = schema(context, Default::default());
+
+        let response = schema.execute("{ block(number: 0) { number } }").await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+
+        let response = schema.execute("{ block(number: 0) { number } }").await;
+        assert_eq!(response.errors.len(), 1);
+    }
+}
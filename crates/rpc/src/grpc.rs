@@ -0,0 +1,515 @@
+//! A gRPC server exposing a read-only subset of the JSON-RPC API.
+//!
+//! The service is defined in `proto/starknet.proto`. Each method is a thin
+//! wrapper around its JSON-RPC counterpart, so the two always agree. Methods
+//! are subject to the [MethodFilter] and rate limits of their counterpart, and
+//! fail like it on a node synced in light mode.
+
+use std::net::SocketAddr;
+use std::pin::Pin;
+
+use anyhow::Context;
+use pathfinder_common::{
+    BlockHash,
+    BlockId,
+    BlockNumber,
+    CallParam,
+    ContractAddress,
+    EntryPoint,
+    EventKey,
+    GasPrice,
+    L1DataAvailabilityMode,
+    StorageAddress,
+};
+use pathfinder_crypto::Felt;
+use tokio::task::JoinHandle;
+use tokio_stream::Stream;
+use tonic::{Request, Response, Status};
+
+use crate::context::RpcContext;
+use crate::error::ApplicationError;
+use crate::method;
+use crate::middleware::method_filter::MethodFilter;
+
+#[allow(clippy::all)]
+mod proto {
+    tonic::include_proto!("starknet.v1");
+}
+
+use proto::starknet_read_server::{StarknetRead, StarknetReadServer};
+
+/// Starts the gRPC server on `addr`, returning its handle and the address it
+/// is actually listening on.
+pub async fn spawn(
+    addr: SocketAddr,
+    context: RpcContext,
+    method_filter: MethodFilter,
+) -> anyhow::Result<(JoinHandle<anyhow::Result<()>>, SocketAddr)> {
+    let listener = tokio::net::TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("Binding gRPC address {addr}"))?;
+    let addr = listener
+        .local_addr()
+        .context("Getting local address from listener")?;
+
+    let server = tonic::transport::Server::builder()
+        .add_service(StarknetReadServer::new(Service(context, method_filter)))
+        .serve_with_incoming(tokio_stream::wrappers::TcpListenerStream::new(listener));
+
+    let handle = tokio::spawn(async move { server.await.context("gRPC server error") });
+
+    Ok((handle, addr))
+}
+
+struct Service(RpcContext, MethodFilter);
+
+impl Service {
+    /// Checks that the JSON-RPC `method` is exposed and has the state it
+    /// reads, and takes a token for calling it from the configured rate limits.
+    fn authorize<T>(&self, request: &Request<T>, method: &str) -> Result<(), Status> {
+        if !self.1.allows(method) {
+            return Err(Status::unimplemented(format!(
+                "This method is disabled, as {method} is"
+            )));
+        }
+
+        if !self.0.has_state_for(method) {
+            return Err(Status::failed_precondition(
+                ApplicationError::StateUnavailable.to_string(),
+            ));
+        }
+
+        if let Some(limiter) = &self.0.rate_limiter {
+            let client = request.remote_addr().map(|addr| addr.ip());
+            limiter.check(client, method).map_err(|retry_after| {
+                Status::resource_exhausted(format!(
+                    "Rate limit exceeded, retry after {} ms",
+                    retry_after.as_millis()
+                ))
+            })?;
+        }
+
+        Ok(())
+    }
+}
+
+#[tonic::async_trait]
+impl StarknetRead for Service {
+    async fn get_block(
+        &self,
+        request: Request<proto::GetBlockRequest>,
+    ) -> Result<Response<proto::Block>, Status> {
+        self.authorize(&request, "starknet_getBlockWithTxHashes")?;
+        let input = method::get_block_with_tx_hashes::Input {
+            block_id: block_id(request.into_inner().block_id)?,
+        };
+
+        let output = method::get_block_with_tx_hashes(self.0.clone(), input)
+            .await
+            .map_err(status)?;
+
+        Ok(Response::new(output.into()))
+    }
+
+    async fn get_storage_at(
+        &self,
+        request: Request<proto::GetStorageAtRequest>,
+    ) -> Result<Response<proto::GetStorageAtResponse>, Status> {
+        self.authorize(&request, "starknet_getStorageAt")?;
+        let request = request.into_inner();
+        let input = method::get_storage_at::Input {
+            contract_address: ContractAddress(felt(request.contract_address, "contract_address")?),
+            key: StorageAddress(felt(request.key, "key")?),
+            block_id: block_id(request.block_id)?,
+        };
+
+        let output = method::get_storage_at(self.0.clone(), input)
+            .await
+            .map_err(status)?;
+
+        Ok(Response::new(proto::GetStorageAtResponse {
+            value: Some(output.0 .0.into()),
+        }))
+    }
+
+    type GetEventsStream =
+        Pin<Box<dyn Stream<Item = Result<proto::EmittedEvent, Status>> + Send + 'static>>;
+
+    async fn get_events(
+        &self,
+        request: Request<proto::GetEventsRequest>,
+    ) -> Result<Response<Self::GetEventsStream>, Status> {
+        self.authorize(&request, "starknet_getEvents")?;
+        let request = request.into_inner();
+        let mut filter = method::get_events::EventFilter {
+            from_block: request
+                .from_block
+                .map(|id| block_id(Some(id)))
+                .transpose()?,
+            to_block: request.to_block.map(|id| block_id(Some(id))).transpose()?,
+            address: request
+                .address
+                .map(|address| felt(Some(address), "address").map(ContractAddress))
+                .transpose()?,
+            keys: request
+                .keys
+                .into_iter()
+                .map(|keys| {
+                    keys.keys
+                        .into_iter()
+                        .map(|key| felt(Some(key), "keys").map(EventKey))
+                        .collect()
+                })
+                .collect::<Result<_, _>>()?,
+            chunk_size: method::get_events::EVENT_PAGE_SIZE_LIMIT,
+            continuation_token: None,
+        };
+
+        let context = self.0.clone();
+        let stream = make_stream::from_future(move |tx| async move {
+            loop {
+                let input = method::get_events::GetEventsInput {
+                    filter: filter.clone(),
+                    decode: false,
+                    at_block_hash: None,
+                };
+                let page = match method::get_events(context.clone(), input).await {
+                    Ok(page) => page,
+                    Err(error) => {
+                        let _ = tx.send(Err(status(error))).await;
+                        return;
+                    }
+                };
+
+                for event in page.events {
+                    if tx.send(Ok(event.into())).await.is_err() {
+                        // The client has gone away.
+                        return;
+                    }
+                }
+
+                match page.continuation_token {
+                    Some(token) => filter.continuation_token = Some(token),
+                    None => return,
+                }
+            }
+        });
+
+        Ok(Response::new(Box::pin(stream)))
+    }
+
+    async fn call(
+        &self,
+        request: Request<proto::CallRequest>,
+    ) -> Result<Response<proto::CallResponse>, Status> {
+        self.authorize(&request, "starknet_call")?;
+        let request = request.into_inner();
+        let input = method::call::Input {
+            request: method::call::FunctionCall {
+                contract_address: ContractAddress(felt(
+                    request.contract_address,
+                    "contract_address",
+                )?),
+                entry_point_selector: EntryPoint(felt(
+                    request.entry_point_selector,
+                    "entry_point_selector",
+                )?),
+                calldata: request
+                    .calldata
+                    .into_iter()
+                    .map(|param| felt(Some(param), "calldata").map(CallParam))
+                    .collect::<Result<_, _>>()?,
+            },
+            block_id: block_id(request.block_id)?,
+        };
+
+        let output = method::call(self.0.clone(), input).await.map_err(status)?;
+
+        Ok(Response::new(proto::CallResponse {
+            result: output.0.into_iter().map(|value| value.0.into()).collect(),
+        }))
+    }
+}
+
+/// Maps a JSON-RPC method error to the closest gRPC status.
+fn status(error: impl Into<ApplicationError>) -> Status {
+    match error.into() {
+        ApplicationError::Internal(e) => {
+            tracing::warn!(backtrace = ?e, "Internal error");
+            Status::internal("Internal error")
+        }
+        error @ (ApplicationError::BlockNotFound | ApplicationError::ContractNotFound) => {
+            Status::not_found(error.to_string())
+        }
+        ApplicationError::ContractError { revert_error, .. } => {
+            Status::aborted(revert_error.unwrap_or_else(|| "Contract error".to_owned()))
+        }
+        error => Status::invalid_argument(error.to_string()),
+    }
+}
+
+fn felt(value: Option<proto::Felt>, field: &str) -> Result<Felt, Status> {
+    let value = value.ok_or_else(|| Status::invalid_argument(format!("Missing {field}")))?;
+    Felt::from_be_slice(&value.value)
+        .map_err(|_| Status::invalid_argument(format!("Invalid field element in {field}")))
+}
+
+fn block_id(value: Option<proto::BlockId>) -> Result<BlockId, Status> {
+    use proto::block_id::Id;
+
+    let id = value
+        .and_then(|value| value.id)
+        .ok_or_else(|| Status::invalid_argument("Missing block_id"))?;
+
+    match id {
+        Id::Number(number) => BlockNumber::new(number)
+            .map(BlockId::Number)
+            .ok_or_else(|| Status::invalid_argument("Invalid block number")),
+        Id::Hash(hash) => felt(Some(hash), "block_id").map(|hash| BlockId::Hash(BlockHash(hash))),
+        Id::Tag(tag) => match proto::BlockTag::try_from(tag) {
+            Ok(proto::BlockTag::Latest) => Ok(BlockId::Latest),
+            Ok(proto::BlockTag::Pending) => Ok(BlockId::Pending),
+            Err(_) => Err(Status::invalid_argument("Invalid block tag")),
+        },
+    }
+}
+
+impl From<Felt> for proto::Felt {
+    fn from(value: Felt) -> Self {
+        let bytes = value.to_be_bytes();
+        let leading_zeros = bytes.iter().take_while(|byte| **byte == 0).count();
+        Self {
+            value: bytes[leading_zeros..].to_vec(),
+        }
+    }
+}
+
+fn resource_price(price_in_wei: GasPrice, price_in_fri: GasPrice) -> proto::ResourcePrice {
+    proto::ResourcePrice {
+        price_in_wei: Some(Felt::from_u128(price_in_wei.0).into()),
+        price_in_fri: Some(Felt::from_u128(price_in_fri.0).into()),
+    }
+}
+
+impl From<method::get_block_with_tx_hashes::Output> for proto::Block {
+    fn from(output: method::get_block_with_tx_hashes::Output) -> Self {
+        use method::get_block_with_tx_hashes::Output;
+        use starknet_gateway_types::reply::L1DataAvailabilityMode as PendingL1DataAvailabilityMode;
+
+        match output {
+            Output::Pending {
+                header,
+                transactions,
+            } => Self {
+                status: proto::BlockStatus::Pending.into(),
+                block_hash: None,
+                parent_hash: Some(header.parent_hash.0.into()),
+                block_number: None,
+                new_root: None,
+                timestamp: header.timestamp.get(),
+                sequencer_address: Some(header.sequencer_address.0.into()),
+                l1_gas_price: Some(resource_price(
+                    header.l1_gas_price.price_in_wei,
+                    header.l1_gas_price.price_in_fri,
+                )),
+                l1_data_gas_price: Some(resource_price(
+                    header.l1_data_gas_price.price_in_wei,
+                    header.l1_data_gas_price.price_in_fri,
+                )),
+                l1_da_mode: match header.l1_da_mode {
+                    PendingL1DataAvailabilityMode::Calldata => {
+                        proto::L1DataAvailabilityMode::Calldata
+                    }
+                    PendingL1DataAvailabilityMode::Blob => proto::L1DataAvailabilityMode::Blob,
+                }
+                .into(),
+                starknet_version: header.starknet_version.to_string(),
+                transactions: transactions.into_iter().map(|hash| hash.0.into()).collect(),
+            },
+            Output::Full {
+                header,
+                transactions,
+                l1_accepted,
+            } => Self {
+                status: if l1_accepted {
+                    proto::BlockStatus::AcceptedOnL1
+                } else {
+                    proto::BlockStatus::AcceptedOnL2
+                }
+                .into(),
+                block_hash: Some(header.hash.0.into()),
+                parent_hash: Some(header.parent_hash.0.into()),
+                block_number: Some(header.number.get()),
+                new_root: Some(header.state_commitment.0.into()),
+                timestamp: header.timestamp.get(),
+                sequencer_address: Some(header.sequencer_address.0.into()),
+                l1_gas_price: Some(resource_price(
+                    header.eth_l1_gas_price,
+                    header.strk_l1_gas_price,
+                )),
+                l1_data_gas_price: Some(resource_price(
+                    header.eth_l1_data_gas_price,
+                    header.strk_l1_data_gas_price,
+                )),
+                l1_da_mode: match header.l1_da_mode {
+                    L1DataAvailabilityMode::Calldata => proto::L1DataAvailabilityMode::Calldata,
+                    L1DataAvailabilityMode::Blob => proto::L1DataAvailabilityMode::Blob,
+                }
+                .into(),
+                starknet_version: header.starknet_version.to_string(),
+                transactions: transactions.into_iter().map(|hash| hash.0.into()).collect(),
+            },
+        }
+    }
+}
+
+impl From<method::get_events::EmittedEvent> for proto::EmittedEvent {
+    fn from(event: method::get_events::EmittedEvent) -> Self {
+        Self {
+            from_address: Some(event.from_address.0.into()),
+            keys: event.keys.into_iter().map(|key| key.0.into()).collect(),
+            data: event.data.into_iter().map(|data| data.0.into()).collect(),
+            block_hash: event.block_hash.map(|hash| hash.0.into()),
+            block_number: event.block_number.map(|number| number.get()),
+            transaction_hash: Some(event.transaction_hash.0.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::macro_prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn felt_round_trip() {
+        let largest = felt!("0x800000000000011000000000000000000000000000000000000000000000000");
+        for value in [Felt::ZERO, felt!("0x1"), felt!("0x1234"), largest] {
+            let encoded = proto::Felt::from(value);
+            assert_eq!(felt(Some(encoded), "test").unwrap(), value);
+        }
+
+        let encoded = proto::Felt::from(felt!("0x1234"));
+        assert_eq!(encoded.value, vec![0x12, 0x34]);
+    }
+
+    #[test]
+    fn felt_overflow_is_rejected() {
+        let error = felt(
+            Some(proto::Felt {
+                value: vec![0xff; 33],
+            }),
+            "test",
+        )
+        .unwrap_err();
+        assert_eq!(error.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn get_block() {
+        let service = Service(RpcContext::for_tests(), MethodFilter::default());
+
+        let block = service
+            .get_block(Request::new(proto::GetBlockRequest {
+                block_id: Some(proto::BlockId {
+                    id: Some(proto::block_id::Id::Number(1)),
+                }),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+
+        assert_eq!(block.block_number, Some(1));
+        assert_eq!(
+            block.block_hash,
+            Some(block_hash_bytes!(b"block 1").0.into())
+        );
+    }
+
+    #[tokio::test]
+    async fn get_block_not_found() {
+        let service = Service(RpcContext::for_tests(), MethodFilter::default());
+
+        let error = service
+            .get_block(Request::new(proto::GetBlockRequest {
+                block_id: Some(proto::BlockId {
+                    id: Some(proto::block_id::Id::Number(9999)),
+                }),
+            }))
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.code(), tonic::Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn disabled_method() {
+        let method_filter = MethodFilter {
+            disabled_methods: ["starknet_getBlockWithTxHashes".to_owned()].into(),
+            ..Default::default()
+        };
+        let service = Service(RpcContext::for_tests(), method_filter);
+
+        let error = service
+            .get_block(Request::new(proto::GetBlockRequest {
+                block_id: Some(proto::BlockId {
+                    id: Some(proto::block_id::Id::Number(1)),
+                }),
+            }))
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.code(), tonic::Code::Unimplemented);
+    }
+
+    #[tokio::test]
+    async fn light_mode() {
+        let mut context = RpcContext::for_tests();
+        context.config.sync_mode = pathfinder_storage::SyncMode::Light;
+        let service = Service(context, MethodFilter::default());
+
+        let error = service
+            .get_storage_at(Request::new(proto::GetStorageAtRequest {
+                contract_address: Some(contract_address_bytes!(b"contract 1").0.into()),
+                key: Some(storage_address_bytes!(b"storage addr 0").0.into()),
+                block_id: Some(proto::BlockId {
+                    id: Some(proto::block_id::Id::Tag(proto::BlockTag::Latest.into())),
+                }),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(error.code(), tonic::Code::FailedPrecondition);
+
+        service
+            .get_block(Request::new(proto::GetBlockRequest {
+                block_id: Some(proto::BlockId {
+                    id: Some(proto::block_id::Id::Number(1)),
+                }),
+            }))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn get_storage_at() {
+        let service = Service(RpcContext::for_tests(), MethodFilter::default());
+
+        let response = service
+            .get_storage_at(Request::new(proto::GetStorageAtRequest {
+                contract_address: Some(contract_address_bytes!(b"contract 1").0.into()),
+                key: Some(storage_address_bytes!(b"storage addr 0").0.into()),
+                block_id: Some(proto::BlockId {
+                    id: Some(proto::block_id::Id::Tag(proto::BlockTag::Latest.into())),
+                }),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+
+        assert_eq!(
+            response.value,
+            Some(storage_value_bytes!(b"storage value 2").0.into())
+        );
+    }
+}
@@ -0,0 +1,299 @@
+//! Aggregates the health of the node's components, see
+//! [RpcContext::health](crate::context::RpcContext::health).
+
+use std::time::{Duration, SystemTime};
+
+use anyhow::Context;
+use pathfinder_common::{BlockNumber, BlockTimestamp};
+
+use crate::context::RpcContext;
+use crate::dto::serialize::{self, SerializeForVersion, Serializer};
+use crate::v02::types::syncing::Syncing;
+
+/// The number of blocks sync may lag behind the chain tip before it is
+/// considered unhealthy, even if the latest block is still recent enough.
+pub const MAX_BLOCKS_BEHIND: u64 = 10;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HealthStatus {
+    /// Unix timestamp at which the status was determined.
+    pub checked_at: u64,
+    pub database: DatabaseHealth,
+    pub sync: SyncHealth,
+    pub pending: PendingHealth,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DatabaseHealth {
+    /// Whether a database transaction could be opened.
+    pub healthy: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyncHealth {
+    /// Whether the latest block is recent enough, and sync is no more than
+    /// [MAX_BLOCKS_BEHIND] blocks behind the chain tip.
+    pub healthy: bool,
+    pub latest_block_number: Option<BlockNumber>,
+    pub latest_block_timestamp: Option<BlockTimestamp>,
+    /// The number of blocks between the latest block and the chain tip, as
+    /// tracked by sync. `None` if sync has not reported its status yet.
+    pub blocks_behind: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingHealth {
+    /// Whether the pending data builds on top of the latest block.
+    pub healthy: bool,
+    pub pending_block_timestamp: Option<BlockTimestamp>,
+}
+
+impl HealthStatus {
+    /// The overall verdict, `false` if any of the components is degraded.
+    pub fn is_healthy(&self) -> bool {
+        self.database.healthy && self.sync.healthy && self.pending.healthy
+    }
+}
+
+/// Determines the [HealthStatus] as of `now` (a unix timestamp).
+///
+/// The database is only touched once, to read the latest block header.
+pub(crate) async fn check(context: &RpcContext, max_block_age: Duration, now: u64) -> HealthStatus {
+    let blocks_behind = match &*context.sync_status.status.read().await {
+        Syncing::Status(status) => Some(
+            status
+                .highest
+                .number
+                .get()
+                .saturating_sub(status.current.number.get()),
+        ),
+        Syncing::False(_) => None,
+    };
+
+    let storage = context.storage.clone();
+    let span = tracing::Span::current();
+    let latest = tokio::task::spawn_blocking(move || {
+        let _g = span.enter();
+        let mut db = storage
+            .connection()
+            .context("Opening database connection")?;
+        let tx = db.transaction().context("Creating database transaction")?;
+        tx.block_header(pathfinder_storage::BlockId::Latest)
+            .context("Querying latest block header")
+    })
+    .await
+    .context("Database read panic or shutting down")
+    .and_then(|x| x);
+
+    let latest = match latest {
+        Ok(latest) => Some(latest),
+        Err(error) => {
+            tracing::warn!(%error, "Health check failed to read from the database");
+            None
+        }
+    };
+
+    let database = DatabaseHealth {
+        healthy: latest.is_some(),
+    };
+    let latest = latest.flatten();
+
+    let sync = SyncHealth {
+        healthy: latest.as_ref().is_some_and(|latest| {
+            now.saturating_sub(latest.timestamp.get()) <= max_block_age.as_secs()
+        }) && blocks_behind.map_or(true, |behind| behind <= MAX_BLOCKS_BEHIND),
+        latest_block_number: latest.as_ref().map(|latest| latest.number),
+        latest_block_timestamp: latest.as_ref().map(|latest| latest.timestamp),
+        blocks_behind,
+    };
+
+    let pending_block = latest
+        .as_ref()
+        .and_then(|latest| context.pending_data.block_on_top_of(latest.hash));
+    let pending = PendingHealth {
+        healthy: pending_block.is_some(),
+        pending_block_timestamp: pending_block.map(|block| block.timestamp),
+    };
+
+    HealthStatus {
+        checked_at: now,
+        database,
+        sync,
+        pending,
+    }
+}
+
+/// The current time as a unix timestamp.
+pub(crate) fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+impl SerializeForVersion for HealthStatus {
+    fn serialize(&self, serializer: Serializer) -> Result<serialize::Ok, serialize::Error> {
+        let mut serializer = serializer.serialize_struct()?;
+        serializer.serialize_field(
+            "status",
+            &if self.is_healthy() {
+                "healthy"
+            } else {
+                "degraded"
+            },
+        )?;
+        serializer.serialize_field("checked_at", &self.checked_at)?;
+        serializer.serialize_field("database", &self.database)?;
+        serializer.serialize_field("sync", &self.sync)?;
+        serializer.serialize_field("pending", &self.pending)?;
+        serializer.end()
+    }
+}
+
+impl SerializeForVersion for DatabaseHealth {
+    fn serialize(&self, serializer: Serializer) -> Result<serialize::Ok, serialize::Error> {
+        let mut serializer = serializer.serialize_struct()?;
+        serializer.serialize_field("healthy", &self.healthy)?;
+        serializer.end()
+    }
+}
+
+impl SerializeForVersion for SyncHealth {
+    fn serialize(&self, serializer: Serializer) -> Result<serialize::Ok, serialize::Error> {
+        let mut serializer = serializer.serialize_struct()?;
+        serializer.serialize_field("healthy", &self.healthy)?;
+        serializer.serialize_optional(
+            "latest_block_number",
+            self.latest_block_number.map(|x| x.get()),
+        )?;
+        serializer.serialize_optional(
+            "latest_block_timestamp",
+            self.latest_block_timestamp.map(|x| x.get()),
+        )?;
+        serializer.serialize_optional("blocks_behind", self.blocks_behind)?;
+        serializer.end()
+    }
+}
+
+impl SerializeForVersion for PendingHealth {
+    fn serialize(&self, serializer: Serializer) -> Result<serialize::Ok, serialize::Error> {
+        let mut serializer = serializer.serialize_struct()?;
+        serializer.serialize_field("healthy", &self.healthy)?;
+        serializer.serialize_optional(
+            "pending_block_timestamp",
+            self.pending_block_timestamp.map(|x| x.get()),
+        )?;
+        serializer.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::v02::types::syncing::{NumberedBlock, Status};
+    use crate::RpcVersion;
+
+    /// The timestamp of the latest block in the test storage.
+    const LATEST_TIMESTAMP: u64 = 2;
+    const MAX_BLOCK_AGE: Duration = Duration::from_secs(60);
+
+    #[tokio::test]
+    async fn healthy() {
+        let context = RpcContext::for_tests_with_pending().await;
+        *context.sync_status.status.write().await = Syncing::Status(Status {
+            starting: NumberedBlock::from(("0x0", 0)),
+            current: NumberedBlock::from(("0x2", 2)),
+            highest: NumberedBlock::from(("0x5", 5)),
+        });
+
+        let status = check(&context, MAX_BLOCK_AGE, LATEST_TIMESTAMP + 10).await;
+
+        assert!(status.is_healthy());
+        assert_eq!(
+            status.sync.latest_block_number,
+            Some(BlockNumber::GENESIS + 2)
+        );
+        assert_eq!(status.sync.blocks_behind, Some(3));
+        assert!(status.pending.pending_block_timestamp.is_some());
+    }
+
+    #[tokio::test]
+    async fn sync_stalled() {
+        let context = RpcContext::for_tests_with_pending().await;
+
+        let now = LATEST_TIMESTAMP + MAX_BLOCK_AGE.as_secs() + 1;
+        let status = check(&context, MAX_BLOCK_AGE, now).await;
+
+        assert!(status.database.healthy);
+        assert!(!status.sync.healthy);
+        assert!(!status.is_healthy());
+    }
+
+    #[tokio::test]
+    async fn sync_far_behind() {
+        let context = RpcContext::for_tests_with_pending().await;
+        *context.sync_status.status.write().await = Syncing::Status(Status {
+            starting: NumberedBlock::from(("0x0", 0)),
+            current: NumberedBlock::from(("0x2", 2)),
+            highest: NumberedBlock::from(("0x100", 0x100)),
+        });
+
+        // The latest block is recent, but the chain tip is far ahead.
+        let status = check(&context, MAX_BLOCK_AGE, LATEST_TIMESTAMP).await;
+
+        assert!(!status.sync.healthy);
+        assert_eq!(status.sync.blocks_behind, Some(0xfe));
+        assert!(!status.is_healthy());
+    }
+
+    #[tokio::test]
+    async fn pending_stale() {
+        // No pending data is available.
+        let context = RpcContext::for_tests();
+
+        let status = check(&context, MAX_BLOCK_AGE, LATEST_TIMESTAMP).await;
+
+        assert!(status.sync.healthy);
+        assert!(!status.pending.healthy);
+        assert!(!status.is_healthy());
+    }
+
+    #[test]
+    fn serialization() {
+        let status = HealthStatus {
+            checked_at: 100,
+            database: DatabaseHealth { healthy: true },
+            sync: SyncHealth {
+                healthy: false,
+                latest_block_number: Some(BlockNumber::GENESIS),
+                latest_block_timestamp: Some(BlockTimestamp::new_or_panic(10)),
+                blocks_behind: None,
+            },
+            pending: PendingHealth {
+                healthy: false,
+                pending_block_timestamp: None,
+            },
+        };
+
+        let output = status
+            .serialize(Serializer::new(RpcVersion::PathfinderV01))
+            .unwrap();
+
+        assert_eq!(
+            output,
+            json!({
+                "status": "degraded",
+                "checked_at": 100,
+                "database": {"healthy": true},
+                "sync": {
+                    "healthy": false,
+                    "latest_block_number": 0,
+                    "latest_block_timestamp": 10,
+                },
+                "pending": {"healthy": false},
+            })
+        );
+    }
+}
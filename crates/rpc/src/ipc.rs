@@ -0,0 +1,205 @@
+//! JSON-RPC over a Unix domain socket, for clients on the same machine.
+//!
+//! Every request, response and subscription notification is a single JSON
+//! document followed by a newline. Batches and subscriptions work as they do
+//! over WebSocket, using the server's default API version.
+//!
+//! There is no authentication. Instead access is controlled by the permissions
+//! of the socket file, which only its owner and group may connect to.
+
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use axum::extract::ws::Message;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::mpsc;
+
+use crate::dto::serialize::{SerializeForVersion, Serializer};
+use crate::jsonrpc::{handle_json_rpc_socket, RpcResponse, RpcRouter};
+use crate::shutdown::ShutdownCoordinator;
+use crate::tls::{is_connection_error, ACCEPT_ERROR_BACKOFF};
+
+/// The permissions of the socket file.
+const SOCKET_MODE: u32 = 0o660;
+
+/// The maximum length of a request line, excluding the newline, matching the
+/// maximum body size of HTTP requests.
+const MAX_LINE_LENGTH: usize = crate::REQUEST_MAX_SIZE;
+
+/// Binds a socket at `path`. A socket left behind by a node which didn't shut
+/// down cleanly is replaced, but one which is still being served is not.
+pub(crate) fn bind(path: &Path) -> anyhow::Result<UnixListener> {
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => {
+            anyhow::ensure!(
+                std::os::unix::net::UnixStream::connect(path).is_err(),
+                "RPC IPC socket {} is already in use",
+                path.display()
+            );
+            std::fs::remove_file(path)
+                .with_context(|| format!("Removing stale RPC IPC socket {}", path.display()))?;
+        }
+        Ok(_) => anyhow::bail!("{} exists and is not a socket", path.display()),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => {}
+        Err(error) => {
+            return Err(error).with_context(|| format!("Inspecting {}", path.display()));
+        }
+    }
+
+    // The socket is created with its final permissions, rather than having them
+    // set afterwards, so that other users can't connect in between.
+    //
+    // SAFETY: umask only swaps the process' file mode creation mask.
+    let umask = unsafe { libc::umask((!SOCKET_MODE & 0o777) as libc::mode_t) };
+    let listener = UnixListener::bind(path);
+    unsafe { libc::umask(umask) };
+    let listener =
+        listener.with_context(|| format!("Binding RPC IPC socket {}", path.display()))?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(SOCKET_MODE))
+        .with_context(|| format!("Setting permissions of RPC IPC socket {}", path.display()))?;
+
+    Ok(listener)
+}
+
+/// Serves `router` on `listener` until shutdown is initiated, then removes the
+/// socket file at `path`.
+pub(crate) async fn serve(
+    listener: UnixListener,
+    path: PathBuf,
+    router: RpcRouter,
+    shutdown: ShutdownCoordinator,
+) -> anyhow::Result<()> {
+    loop {
+        let stream = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => stream,
+                Err(error) if is_connection_error(&error) => {
+                    tracing::debug!(%error, "Failed to accept RPC IPC connection");
+                    continue;
+                }
+                Err(error) => {
+                    tracing::warn!(%error, "Failed to accept RPC IPC connections, retrying shortly");
+                    tokio::select! {
+                        _ = tokio::time::sleep(ACCEPT_ERROR_BACKOFF) => continue,
+                        _ = shutdown.wait_for_shutdown() => break,
+                    }
+                }
+            },
+            _ = shutdown.wait_for_shutdown() => break,
+        };
+
+        handle_connection(stream, router.clone());
+    }
+
+    drop(listener);
+    std::fs::remove_file(&path)
+        .with_context(|| format!("Removing RPC IPC socket {}", path.display()))
+}
+
+/// Handles the requests of a connection the same way as those of a WebSocket,
+/// with newlines delimiting the messages.
+fn handle_connection(stream: UnixStream, router: RpcRouter) {
+    let version = router.version;
+    let (reader, mut writer) = stream.into_split();
+
+    let (sender_tx, mut sender_rx) = mpsc::channel::<Result<Message, RpcResponse>>(1024);
+    tokio::spawn(async move {
+        while let Some(message) = sender_rx.recv().await {
+            let mut line = match message {
+                Ok(Message::Text(text)) => text,
+                // Control messages only have a meaning for WebSockets.
+                Ok(_) => continue,
+                Err(response) => match response.serialize(Serializer::new(version)) {
+                    Ok(response) => response.to_string(),
+                    Err(error) => {
+                        tracing::warn!(%error, "Failed to serialize RPC IPC error response");
+                        continue;
+                    }
+                },
+            };
+            line.push('\n');
+            if writer.write_all(line.as_bytes()).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let (receiver_tx, receiver_rx) = mpsc::channel::<Result<Message, axum::Error>>(1024);
+    tokio::spawn(async move {
+        let mut reader = BufReader::new(reader);
+        loop {
+            // Reading stops one byte past the longest allowed line, newline
+            // included, so that a client can't exhaust memory with a line
+            // which never ends.
+            let mut line = Vec::new();
+            let limit = MAX_LINE_LENGTH as u64 + 2;
+            let message = match (&mut reader).take(limit).read_until(b'\n', &mut line).await {
+                Ok(0) => break,
+                Ok(_) if line.strip_suffix(b"\n").unwrap_or(&line).len() > MAX_LINE_LENGTH => {
+                    Err(axum::Error::new("Request is too large"))
+                }
+                Ok(_) if line.trim_ascii().is_empty() => continue,
+                Ok(_) => Ok(Message::Binary(line)),
+                Err(error) => Err(axum::Error::new(error)),
+            };
+            let stop = message.is_err();
+            if receiver_tx.send(message).await.is_err() || stop {
+                break;
+            }
+        }
+    });
+
+    handle_json_rpc_socket(router, sender_tx, receiver_rx);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::context::RpcContext;
+    use crate::RpcVersion;
+
+    #[tokio::test]
+    async fn serves_requests_on_a_private_socket() {
+        fn success() -> &'static str {
+            "Success"
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pathfinder.ipc");
+        let listener = bind(&path).unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, SOCKET_MODE);
+
+        let router = RpcRouter::builder(RpcVersion::default())
+            .register("test_method", success)
+            .build(RpcContext::for_tests());
+        let shutdown = ShutdownCoordinator::default();
+        let server = tokio::spawn(serve(listener, path.clone(), router, shutdown.clone()));
+
+        let mut stream = UnixStream::connect(&path).await.unwrap();
+        stream
+            .write_all(b"{\"jsonrpc\": \"2.0\", \"method\": \"test_method\", \"id\": 1}\n")
+            .await
+            .unwrap();
+        let mut line = String::new();
+        BufReader::new(&mut stream)
+            .read_line(&mut line)
+            .await
+            .unwrap();
+        let response: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(
+            response,
+            json!({"jsonrpc": "2.0", "id": 1, "result": "Success"})
+        );
+
+        shutdown.shutdown(Duration::from_secs(1)).await.unwrap();
+        server.await.unwrap().unwrap();
+        assert!(!path.exists());
+    }
+}
@@ -11,8 +11,9 @@ use pathfinder_common::{BlockHash, BlockNumber};
 pub use request::RpcRequest;
 pub use response::RpcResponse;
 #[cfg(test)]
-pub use router::{handle_json_rpc_socket, CATCH_UP_BATCH_SIZE};
+pub use router::CATCH_UP_BATCH_SIZE;
 pub use router::{
+    handle_json_rpc_socket,
     rpc_handler,
     CatchUp,
     RpcRouter,
@@ -44,6 +45,8 @@ pub struct Notifications {
     pub block_headers: broadcast::Sender<Arc<pathfinder_common::BlockHeader>>,
     pub l2_blocks: broadcast::Sender<Arc<Block>>,
     pub reorgs: broadcast::Sender<Arc<Reorg>>,
+    /// The latest L2 block number accepted on L1.
+    pub l1_updates: broadcast::Sender<BlockNumber>,
 }
 
 #[derive(Debug, Clone)]
@@ -59,10 +62,12 @@ impl Default for Notifications {
         let (block_headers, _) = broadcast::channel(1024);
         let (l2_blocks, _) = broadcast::channel(1024);
         let (reorgs, _) = broadcast::channel(1024);
+        let (l1_updates, _) = broadcast::channel(1024);
         Self {
             block_headers,
             l2_blocks,
             reorgs,
+            l1_updates,
         }
     }
 }
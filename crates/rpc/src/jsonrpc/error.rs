@@ -1,4 +1,5 @@
 use std::borrow::Cow;
+use std::time::Duration;
 
 use serde_json::{json, Value};
 
@@ -17,6 +18,9 @@ pub enum RpcError {
         subscription_id: u32,
         reason: String,
     },
+    RateLimited {
+        retry_after: Duration,
+    },
 }
 
 impl PartialEq for RpcError {
@@ -39,6 +43,7 @@ impl RpcError {
             RpcError::InternalError(_) => -32603,
             RpcError::ApplicationError(err) => err.code(),
             RpcError::WebsocketSubscriptionClosed { .. } => -32099,
+            RpcError::RateLimited { .. } => -32097,
         }
     }
 
@@ -51,6 +56,7 @@ impl RpcError {
             RpcError::InternalError(_) => "Internal error".into(),
             RpcError::ApplicationError(e) => e.to_string().into(),
             RpcError::WebsocketSubscriptionClosed { .. } => "Websocket subscription closed".into(),
+            RpcError::RateLimited { .. } => "Rate limit exceeded".into(),
         }
     }
 
@@ -63,6 +69,9 @@ impl RpcError {
                 "id": subscription_id,
                 "reason": reason,
             })),
+            RpcError::RateLimited { retry_after } => Some(json!({
+                "retry_after_ms": u64::try_from(retry_after.as_millis()).unwrap_or(u64::MAX),
+            })),
             RpcError::ApplicationError(e) => e.data(version),
             RpcError::InternalError(_) => None,
             RpcError::MethodNotFound => None,
@@ -97,6 +106,27 @@ where
     E: Into<crate::error::ApplicationError>,
 {
     fn from(value: E) -> Self {
-        Self::ApplicationError(value.into())
+        match value.into() {
+            // Reads of pruned state fail deep within storage and execution, where they
+            // can only be reported as internal errors.
+            crate::error::ApplicationError::Internal(e)
+                if e.is::<pathfinder_storage::StatePruned>() =>
+            {
+                Self::ApplicationError(crate::error::ApplicationError::BlockNotFound)
+            }
+            // Likewise for queries of data which is still being backfilled.
+            crate::error::ApplicationError::Internal(e)
+                if e.is::<pathfinder_storage::MigrationPending>() =>
+            {
+                let migration = e
+                    .downcast_ref::<pathfinder_storage::MigrationPending>()
+                    .expect("checked above")
+                    .0;
+                Self::ApplicationError(crate::error::ApplicationError::MigrationPending {
+                    migration,
+                })
+            }
+            e => Self::ApplicationError(e),
+        }
     }
 }
@@ -1,22 +1,29 @@
 use std::collections::HashMap;
-use std::num::NonZeroUsize;
+use std::net::{IpAddr, SocketAddr};
+use std::num::{NonZeroU64, NonZeroUsize};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
 
-use axum::extract::{State, WebSocketUpgrade};
+use axum::extract::{ConnectInfo, State, WebSocketUpgrade};
 use axum::http::StatusCode;
 use axum::response::IntoResponse;
 use futures::{Future, FutureExt, StreamExt};
 use http::HeaderValue;
 use method::RpcMethodEndpoint;
+use pathfinder_executor::ExecutionLimits;
 #[cfg(test)]
 pub use subscription::CATCH_UP_BATCH_SIZE;
 pub use subscription::{handle_json_rpc_socket, CatchUp, RpcSubscriptionFlow, SubscriptionMessage};
 use subscription::{split_ws, RpcSubscriptionEndpoint};
+use tracing::Instrument;
 
-use crate::context::RpcContext;
+use crate::context::{RpcConfig, RpcContext};
 use crate::dto::serialize;
 use crate::jsonrpc::error::RpcError;
 use crate::jsonrpc::request::RpcRequest;
 use crate::jsonrpc::response::RpcResponse;
+use crate::middleware::method_filter::MethodFilter;
 use crate::RpcVersion;
 
 mod method;
@@ -30,6 +37,11 @@ pub struct RpcRouter {
     method_endpoints: &'static HashMap<&'static str, Box<dyn RpcMethodEndpoint>>,
     subscription_endpoints: &'static HashMap<&'static str, Box<dyn RpcSubscriptionEndpoint>>,
     pub version: RpcVersion,
+    /// The address of the client making the requests, used for per-IP rate
+    /// limits.
+    pub client_ip: Option<IpAddr>,
+    /// The methods exposed by this router, others are reported as not found.
+    pub method_filter: Arc<MethodFilter>,
 }
 
 pub struct RpcRouterBuilder {
@@ -84,6 +96,8 @@ impl RpcRouterBuilder {
             method_endpoints: methods,
             subscription_endpoints: subscriptions,
             version: self.version,
+            client_ip: None,
+            method_filter: Default::default(),
         }
     }
 
@@ -101,8 +115,18 @@ impl RpcRouter {
         RpcRouterBuilder::new(version)
     }
 
+    /// Takes a token for calling `method` from the configured rate limits.
+    pub(crate) fn check_rate_limit(&self, method: &str) -> Result<(), RpcError> {
+        match &self.context.rate_limiter {
+            Some(limiter) => limiter
+                .check(self.client_ip, method)
+                .map_err(|retry_after| RpcError::RateLimited { retry_after }),
+            None => Ok(()),
+        }
+    }
+
     /// Parses and executes a request. Returns [None] if its a notification.
-    async fn run_request(&self, request: &str) -> Option<RpcResponse> {
+    pub(crate) async fn run_request(&self, request: &str) -> Option<RpcResponse> {
         tracing::trace!(%request, "Running request");
 
         let request = match serde_json::from_str::<RpcRequest<'_>>(request) {
@@ -119,36 +143,83 @@ impl RpcRouter {
 
         // Also grab the method_name as it is a static str, which is required by the
         // metrics.
-        let Some((&method_name, method)) =
-            self.method_endpoints.get_key_value(request.method.as_ref())
+        let Some((&method_name, method)) = self
+            .method_endpoints
+            .get_key_value(request.method.as_ref())
+            .filter(|(name, _)| self.method_filter.allows(name))
         else {
             return Some(RpcResponse::method_not_found(request.id, self.version));
         };
 
         metrics::increment_counter!("rpc_method_calls_total", "method" => method_name, "version" => self.version.to_str());
 
-        let method = method.invoke(self.context.clone(), request.params, self.version);
-        let result = std::panic::AssertUnwindSafe(method).catch_unwind().await;
+        let params = request.params.0.map(|params| params.get());
+        let latest_block = match &self.context.recorder {
+            Some(recorder) => recorder.latest_block(&self.context).await,
+            None => None,
+        };
 
-        let output = match result {
-            Ok(output) => output,
-            Err(e) => {
-                tracing::warn!(method=%request.method, backtrace=?e, "RPC method panic'd");
-                Err(RpcError::InternalError(anyhow::anyhow!(
-                    "RPC method panic'd"
-                )))
+        let started = std::time::Instant::now();
+        let output = match self.check_rate_limit(method_name) {
+            Ok(()) if !self.context.has_state_for(method_name) => Err(RpcError::ApplicationError(
+                crate::error::ApplicationError::StateUnavailable,
+            )),
+            Ok(()) => {
+                // The root of the trace linking the call to the database and execution
+                // spans entered by the method.
+                let span = tracing::info_span!(
+                    "rpc_call",
+                    method = method_name,
+                    version = self.version.to_str()
+                );
+                let method = method
+                    .invoke(self.context.clone(), request.params, self.version)
+                    .instrument(span);
+                match std::panic::AssertUnwindSafe(method).catch_unwind().await {
+                    Ok(output) => output,
+                    Err(e) => {
+                        tracing::warn!(method=%request.method, backtrace=?e, "RPC method panic'd");
+                        Err(RpcError::InternalError(anyhow::anyhow!(
+                            "RPC method panic'd"
+                        )))
+                    }
+                }
             }
+            Err(error) => Err(error),
         };
 
-        if output.is_err() {
-            metrics::increment_counter!("rpc_method_calls_failed_total", "method" => method_name, "version" => self.version.to_str());
+        let elapsed = started.elapsed();
+        metrics::histogram!("rpc_method_calls_duration_seconds", elapsed, "method" => method_name, "version" => self.version.to_str());
+        crate::middleware::request_log::log_call(
+            &self.context.config.request_log,
+            method_name,
+            self.version,
+            params,
+            elapsed,
+            &output,
+        );
+
+        if let Err(error) = &output {
+            metrics::increment_counter!("rpc_method_calls_failed_total", "method" => method_name, "version" => self.version.to_str(), "code" => error.code().to_string());
         }
 
-        Some(RpcResponse {
+        let response = RpcResponse {
             output,
             id: request.id,
             version: self.version,
-        })
+        };
+
+        if let Some(recorder) = &self.context.recorder {
+            recorder.record(&crate::record::RecordedCall {
+                version: self.version.to_str().to_owned(),
+                method: method_name.to_owned(),
+                params: params.and_then(|params| serde_json::from_str(params).ok()),
+                latest_block,
+                response: crate::record::response_body(&response),
+            });
+        }
+
+        Some(response)
     }
 }
 
@@ -181,14 +252,60 @@ fn is_utf8_encoded_json(headers: http::HeaderMap) -> bool {
     is_json && valid_charset
 }
 
+/// Request headers which lower the node's execution limits for `starknet_call`
+/// and `starknet_estimateFee`.
+const CALL_MAX_STEPS_HEADER: &str = "x-pathfinder-call-max-steps";
+const CALL_MAX_GAS_HEADER: &str = "x-pathfinder-call-max-gas";
+const CALL_TIMEOUT_MS_HEADER: &str = "x-pathfinder-call-timeout-ms";
+
+/// Applies the execution limits requested by the headers. These can only
+/// tighten the limits configured for the node, never relax them.
+fn apply_execution_limit_headers(
+    config: &mut RpcConfig,
+    headers: &http::HeaderMap,
+) -> Result<(), String> {
+    let requested = ExecutionLimits {
+        max_steps: parse_header(headers, CALL_MAX_STEPS_HEADER)?,
+        max_gas: parse_header(headers, CALL_MAX_GAS_HEADER)?,
+    };
+    config.execution_limits = config.execution_limits.tightened_by(requested);
+
+    if let Some(timeout) = parse_header::<NonZeroU64>(headers, CALL_TIMEOUT_MS_HEADER)? {
+        let timeout = Duration::from_millis(timeout.get());
+        config.execution_timeout = Some(
+            config
+                .execution_timeout
+                .map_or(timeout, |configured| configured.min(timeout)),
+        );
+    }
+
+    Ok(())
+}
+
+fn parse_header<T: FromStr>(headers: &http::HeaderMap, name: &str) -> Result<Option<T>, String> {
+    let Some(value) = headers.get(name) else {
+        return Ok(None);
+    };
+
+    value
+        .to_str()
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .map(Some)
+        .ok_or_else(|| format!("Invalid {name} header"))
+}
+
 #[axum::debug_handler]
 pub async fn rpc_handler(
-    State(state): State<RpcRouter>,
+    State(mut state): State<RpcRouter>,
     headers: http::HeaderMap,
     method: http::Method,
     ws: Option<WebSocketUpgrade>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
     body: axum::body::Bytes,
 ) -> impl axum::response::IntoResponse {
+    state.client_ip = connect_info.map(|ConnectInfo(addr)| addr.ip());
+
     match ws {
         Some(ws) => ws.on_upgrade(|ws| async move {
             let (ws_tx, ws_rx) = split_ws(ws, state.version);
@@ -199,6 +316,15 @@ pub async fn rpc_handler(
                 return StatusCode::METHOD_NOT_ALLOWED.into_response();
             }
 
+            // Held until the response is ready, so that shutdown waits for this request.
+            let Some(_in_flight) = state.context.shutdown.begin_request() else {
+                return StatusCode::SERVICE_UNAVAILABLE.into_response();
+            };
+
+            if let Err(e) = apply_execution_limit_headers(&mut state.context.config, &headers) {
+                return (StatusCode::BAD_REQUEST, e).into_response();
+            }
+
             // Only utf8 json content allowed.
             if !is_utf8_encoded_json(headers) {
                 return StatusCode::UNSUPPORTED_MEDIA_TYPE.into_response();
@@ -393,7 +519,8 @@ mod tests {
             let router = axum::Router::new()
                 .route("/", axum::routing::post(rpc_handler).get(rpc_handler))
                 .with_state(router);
-            axum::serve(listener, router.into_make_service()).await
+            let service = router.into_make_service_with_connect_info::<SocketAddr>();
+            axum::serve(listener, service).await
         });
 
         url
@@ -659,6 +786,122 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn batch_size_limit() {
+        fn router() -> RpcRouter {
+            fn success() -> &'static str {
+                "Success"
+            }
+
+            let mut context = RpcContext::for_tests();
+            context.config.max_batch_size = 2.try_into().unwrap();
+
+            RpcRouter::builder(Default::default())
+                .register("success", success)
+                .build(context)
+        }
+
+        let within_limit = json!([
+            {"jsonrpc": "2.0", "method": "success", "id": 1},
+            {"jsonrpc": "2.0", "method": "success", "id": 2},
+        ]);
+        let expected = json!([
+            {"jsonrpc": "2.0", "result": "Success", "id": 1},
+            {"jsonrpc": "2.0", "result": "Success", "id": 2},
+        ]);
+        assert_eq!(
+            serve_and_query(router(), within_limit.clone()).await,
+            expected
+        );
+        assert_eq!(serve_and_query_ws(router(), within_limit).await, expected);
+
+        let too_large = json!([
+            {"jsonrpc": "2.0", "method": "success", "id": 1},
+            {"jsonrpc": "2.0", "method": "success", "id": 2},
+            {"jsonrpc": "2.0", "method": "success", "id": 3},
+        ]);
+        let expected = json!({"jsonrpc": "2.0", "id": null, "error": {
+            "code": -32600,
+            "message": "Invalid request",
+            "data": {"reason": "A batch request must contain at most 2 requests"}
+        }});
+        assert_eq!(serve_and_query(router(), too_large.clone()).await, expected);
+        assert_eq!(serve_and_query_ws(router(), too_large).await, expected);
+    }
+
+    #[tokio::test]
+    async fn rate_limits() {
+        use crate::middleware::rate_limit::{BucketConfig, RateLimitConfig};
+
+        fn router() -> RpcRouter {
+            fn success() -> &'static str {
+                "Success"
+            }
+
+            let context = RpcContext::for_tests().with_rate_limits(RateLimitConfig {
+                per_ip: Some(BucketConfig {
+                    requests_per_second: 1.try_into().unwrap(),
+                    burst: Some(2.try_into().unwrap()),
+                }),
+                ..Default::default()
+            });
+
+            RpcRouter::builder(Default::default())
+                .register("success", success)
+                .build(context)
+        }
+
+        let request = json!([
+            {"jsonrpc": "2.0", "method": "success", "id": 1},
+            {"jsonrpc": "2.0", "method": "success", "id": 2},
+            {"jsonrpc": "2.0", "method": "success", "id": 3},
+        ]);
+
+        for response in [
+            serve_and_query(router(), request.clone()).await,
+            serve_and_query_ws(router(), request).await,
+        ] {
+            let responses = response.as_array().unwrap();
+            let limited = responses
+                .iter()
+                .filter_map(|response| response.get("error"))
+                .collect::<Vec<_>>();
+
+            assert_eq!(responses.len(), 3);
+            assert_eq!(limited.len(), 1);
+            assert_eq!(limited[0]["code"], -32097);
+            assert_eq!(limited[0]["message"], "Rate limit exceeded");
+            assert!(limited[0]["data"]["retry_after_ms"].as_u64().unwrap() <= 1000);
+        }
+    }
+
+    #[tokio::test]
+    async fn light_mode() {
+        fn success() -> &'static str {
+            "Success"
+        }
+
+        let mut context = RpcContext::for_tests();
+        context.config.sync_mode = pathfinder_storage::SyncMode::Light;
+        let router = RpcRouter::builder(Default::default())
+            .register("starknet_getNonce", success)
+            .register("starknet_blockNumber", success)
+            .build(context);
+
+        let request = json!([
+            {"jsonrpc": "2.0", "method": "starknet_getNonce", "id": 1},
+            {"jsonrpc": "2.0", "method": "starknet_blockNumber", "id": 2},
+        ]);
+        let expected = json!([
+            {"jsonrpc": "2.0", "id": 1, "error": {
+                "code": 10004,
+                "message": "The node syncs in light mode and doesn't keep the state this method reads"
+            }},
+            {"jsonrpc": "2.0", "id": 2, "result": "Success"},
+        ]);
+        assert_eq!(serve_and_query(router, request).await, expected);
+    }
+
     mod panic_handling {
         use super::*;
 
@@ -876,6 +1119,41 @@ mod tests {
         assert_eq!(res, expected);
     }
 
+    #[tokio::test]
+    async fn execution_limit_headers() {
+        async fn max_steps(context: RpcContext) -> RpcResult {
+            Ok(json!(context.config.execution_limits.max_steps))
+        }
+
+        let mut context = RpcContext::for_tests();
+        context.config.execution_limits.max_steps = Some(1000.try_into().unwrap());
+        let router = RpcRouter::builder(Default::default())
+            .register("max_steps", max_steps)
+            .build(context);
+
+        let url = spawn_server(router).await;
+        let client = reqwest::Client::new();
+        let request = json!({"jsonrpc": "2.0", "method": "max_steps", "id": 1});
+
+        let query = |max_steps: &'static str| {
+            client
+                .post(url.clone())
+                .json(&request)
+                .header(CALL_MAX_STEPS_HEADER, max_steps)
+                .send()
+        };
+
+        let res = query("10").await.unwrap().json::<Value>().await.unwrap();
+        assert_eq!(res, json!({"jsonrpc": "2.0", "result": 10, "id": 1}));
+
+        // Requests cannot raise the configured limit.
+        let res = query("5000").await.unwrap().json::<Value>().await.unwrap();
+        assert_eq!(res, json!({"jsonrpc": "2.0", "result": 1000, "id": 1}));
+
+        let status = query("many").await.unwrap().status();
+        assert_eq!(status, reqwest::StatusCode::BAD_REQUEST);
+    }
+
     #[tokio::test]
     async fn response_hash_content_type_json() {
         fn always_success() -> &'static str {
@@ -75,6 +75,13 @@ pub async fn handle_json_rpc_body(
             ));
         }
 
+        let max_batch_size = state.context.config.max_batch_size.get();
+        if requests.len() > max_batch_size {
+            return Err(RpcRequestError::InvalidRequest(format!(
+                "A batch request must contain at most {max_batch_size} requests"
+            )));
+        }
+
         let responses = run_concurrently(
             state.context.config.batch_concurrency_limit,
             requests.into_iter().enumerate(),
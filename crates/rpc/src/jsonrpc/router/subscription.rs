@@ -1,3 +1,4 @@
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 
 use axum::extract::ws::{Message, WebSocket};
@@ -67,6 +68,15 @@ pub trait RpcSubscriptionFlow: Send + Sync {
     /// The notification type to be sent to the client.
     type Notification: crate::dto::serialize::SerializeForVersion + Send + Sync + 'static;
 
+    /// Whether notifications carry a `sequence_number`, counting the
+    /// notifications sent by the subscription from zero. Unlike block numbers,
+    /// it keeps increasing when a reorg is notified, so clients can tell the
+    /// headers of the new chain apart from those they replace.
+    ///
+    /// The field is not part of the Starknet specification, so it is only
+    /// added to subscriptions made on the pathfinder API.
+    const SEQUENCE_NUMBERS: bool = false;
+
     /// Validate the subscription parameters. If the parameters are invalid,
     /// return an error.
     fn validate_params(_params: &Self::Params) -> Result<(), RpcError> {
@@ -158,6 +168,8 @@ where
             subscriptions,
             tx: ws_tx,
             version: router.version,
+            sequence_number: (T::SEQUENCE_NUMBERS && router.version == RpcVersion::PathfinderV01)
+                .then(Default::default),
             _phantom: Default::default(),
         };
 
@@ -383,8 +395,26 @@ pub fn handle_json_rpc_socket(
         Default::default();
     // Read and handle messages from the websocket.
     tokio::spawn(async move {
+        let shutdown = state.context.shutdown.clone();
+        // Held while the connection is open, so that shutdown waits for it to be
+        // closed.
+        let Some(_connection) = shutdown.begin_request() else {
+            let _ = ws_tx.send(Ok(Message::Close(None))).await;
+            return;
+        };
+
         loop {
-            let request = match ws_rx.recv().await {
+            let request = tokio::select! {
+                request = ws_rx.recv() => request,
+                _ = shutdown.wait_for_shutdown() => {
+                    for subscription in subscriptions.iter() {
+                        subscription.value().abort();
+                    }
+                    let _ = ws_tx.send(Ok(Message::Close(None))).await;
+                    return;
+                }
+            };
+            let request = match request {
                 Some(Ok(Message::Text(msg))) => msg,
                 Some(Ok(Message::Binary(bytes))) => match String::from_utf8(bytes) {
                     Ok(msg) => msg,
@@ -504,6 +534,24 @@ pub fn handle_json_rpc_socket(
                     }
                 }
 
+                let max_batch_size = state.context.config.max_batch_size.get();
+                if requests.len() > max_batch_size {
+                    if ws_tx
+                        .send(Err(RpcResponse::invalid_request(
+                            format!(
+                                "A batch request must contain at most {max_batch_size} requests"
+                            ),
+                            state.version,
+                        )))
+                        .await
+                        .is_err()
+                    {
+                        // Connection is closing.
+                        break;
+                    }
+                    continue;
+                }
+
                 let responses = run_concurrently(
                     state.context.config.batch_concurrency_limit,
                     requests.into_iter().enumerate(),
@@ -585,6 +633,13 @@ async fn handle_request(
 
     // Handle starknet_unsubscribe.
     if rpc_request.method == "starknet_unsubscribe" {
+        state
+            .check_rate_limit("starknet_unsubscribe")
+            .map_err(|error| RpcResponse {
+                output: Err(error),
+                id: req_id.clone(),
+                version: state.version,
+            })?;
         // End the subscription.
         let params = rpc_request.params.0.ok_or_else(|| {
             RpcResponse::invalid_params(
@@ -618,9 +673,18 @@ async fn handle_request(
     let (&method_name, endpoint) = state
         .subscription_endpoints
         .get_key_value(rpc_request.method.as_ref())
+        .filter(|(name, _)| state.method_filter.allows(name))
         .ok_or_else(|| RpcResponse::method_not_found(req_id.clone(), state.version))?;
     metrics::increment_counter!("rpc_method_calls_total", "method" => method_name, "version" => state.version.to_str());
 
+    state
+        .check_rate_limit(method_name)
+        .map_err(|error| RpcResponse {
+            output: Err(error),
+            id: req_id.clone(),
+            version: state.version,
+        })?;
+
     let params = serde_json::to_value(rpc_request.params)
         .map_err(|e| RpcResponse::invalid_params(req_id.clone(), e.to_string(), state.version))?;
 
@@ -676,6 +740,9 @@ pub struct SubscriptionSender<T> {
     pub subscriptions: Arc<DashMap<SubscriptionId, tokio::task::JoinHandle<()>>>,
     pub tx: mpsc::Sender<Result<Message, RpcResponse>>,
     pub version: RpcVersion,
+    /// The sequence number of the next notification, if the subscription
+    /// numbers its notifications.
+    pub sequence_number: Option<Arc<AtomicU64>>,
     pub _phantom: std::marker::PhantomData<T>,
 }
 
@@ -686,6 +753,7 @@ impl<T> Clone for SubscriptionSender<T> {
             subscriptions: self.subscriptions.clone(),
             tx: self.tx.clone(),
             version: self.version,
+            sequence_number: self.sequence_number.clone(),
             _phantom: Default::default(),
         }
     }
@@ -701,12 +769,17 @@ impl<T: crate::dto::serialize::SerializeForVersion> SubscriptionSender<T> {
             // Race condition due to the subscription ending.
             return Ok(());
         }
+        let sequence_number = self
+            .sequence_number
+            .as_ref()
+            .map(|next| next.fetch_add(1, Ordering::Relaxed));
         let notification = RpcNotification {
             jsonrpc: "2.0",
             method: subscription_name,
             params: SubscriptionResult {
                 subscription_id: self.subscription_id,
                 result: value,
+                sequence_number,
             },
         }
         .serialize(crate::dto::serialize::Serializer::new(self.version))
@@ -729,6 +802,7 @@ impl<T: crate::dto::serialize::SerializeForVersion> SubscriptionSender<T> {
             params: SubscriptionResult {
                 subscription_id: self.subscription_id,
                 result: err,
+                sequence_number: None,
             },
         }
         .serialize(crate::dto::serialize::Serializer::new(self.version))
@@ -752,6 +826,7 @@ struct RpcNotification<T> {
 pub struct SubscriptionResult<T> {
     subscription_id: SubscriptionId,
     result: T,
+    sequence_number: Option<u64>,
 }
 
 impl<T> crate::dto::serialize::SerializeForVersion for RpcNotification<T>
@@ -781,6 +856,7 @@ where
         let mut serializer = serializer.serialize_struct()?;
         serializer.serialize_field("subscription_id", &self.subscription_id)?;
         serializer.serialize_field("result", &self.result)?;
+        serializer.serialize_optional("sequence_number", self.sequence_number)?;
         serializer.end()
     }
 }
@@ -965,6 +1041,53 @@ mod tests {
         )
     }
 
+    #[tokio::test]
+    async fn shutdown_closes_connection() {
+        struct Idle;
+
+        #[async_trait]
+        impl RpcSubscriptionFlow for Idle {
+            type Params = Params;
+            type Notification = serde_json::Value;
+
+            fn starting_block(_params: &Self::Params) -> BlockId {
+                BlockId::Number(BlockNumber::GENESIS)
+            }
+
+            async fn catch_up(
+                _state: &RpcContext,
+                _params: &Self::Params,
+                _from: BlockNumber,
+                _to: BlockNumber,
+            ) -> Result<CatchUp<Self::Notification>, crate::jsonrpc::RpcError> {
+                Ok(Default::default())
+            }
+
+            async fn subscribe(
+                _state: RpcContext,
+                _params: Self::Params,
+                _tx: tokio::sync::mpsc::Sender<SubscriptionMessage<Self::Notification>>,
+            ) -> Result<(), crate::jsonrpc::RpcError> {
+                std::future::pending().await
+            }
+        }
+
+        let router = setup(1, Idle).await;
+        let (sender_tx, mut sender_rx) = mpsc::channel(1024);
+        let (_receiver_tx, receiver_rx) = mpsc::channel(1024);
+        handle_json_rpc_socket(router.clone(), sender_tx, receiver_rx);
+
+        // Completes only once the connection has been closed.
+        router
+            .context
+            .shutdown
+            .shutdown(Duration::from_secs(5))
+            .await
+            .unwrap();
+
+        assert_matches::assert_matches!(sender_rx.recv().await, Some(Ok(Message::Close(None))));
+    }
+
     #[derive(Debug, Clone)]
     struct Params;
 
@@ -1007,15 +1130,35 @@ mod tests {
             }
             .into(),
             chain_id: ChainId::MAINNET,
-            sequencer: Client::mainnet(Duration::from_secs(10)),
+            sequencer: Some(Client::mainnet(Duration::from_secs(10))),
             websocket: None,
             notifications,
             config: RpcConfig {
                 batch_concurrency_limit: 1.try_into().unwrap(),
+                max_batch_size: 1000.try_into().unwrap(),
+                max_response_size: None,
                 get_events_max_blocks_to_scan: 1.try_into().unwrap(),
                 get_events_max_uncached_bloom_filters_to_load: 1.try_into().unwrap(),
                 custom_versioned_constants: None,
+                health_max_block_age: Duration::from_secs(300),
+                trace_cache_size: pathfinder_executor::TraceCache::DEFAULT_SIZE,
+                trie_node_cache_size: pathfinder_merkle_tree::TrieNodeCache::DEFAULT_MEMORY_BUDGET,
+                execution_limits: Default::default(),
+                execution_timeout: None,
+                validate_transactions: false,
+                request_log: Default::default(),
+                sync_mode: Default::default(),
             },
+            shutdown: Default::default(),
+            storage_root_cache: Default::default(),
+            trie_node_cache: Default::default(),
+            class_cache: Default::default(),
+            mempool: Default::default(),
+            submissions: None,
+            fork: None,
+            rate_limiter: None,
+            abis: Default::default(),
+            backup_status: None,
         };
         RpcRouter::builder(crate::RpcVersion::V08)
             .register("test", endpoint)
@@ -236,6 +236,8 @@ impl serde::Serialize for BlockHeader {
             strk_l1_gas_price,
             eth_l1_data_gas_price,
             strk_l1_data_gas_price,
+            eth_l2_gas_price: _,
+            strk_l2_gas_price: _,
             sequencer_address,
             starknet_version,
             class_commitment,
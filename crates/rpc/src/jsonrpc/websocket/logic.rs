@@ -2,13 +2,14 @@
 
 use std::collections::HashMap;
 use std::marker::PhantomData;
+use std::net::SocketAddr;
 use std::num::NonZeroUsize;
 use std::ops::ControlFlow;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 use axum::extract::ws::{Message, WebSocket};
-use axum::extract::{State, WebSocketUpgrade};
+use axum::extract::{ConnectInfo, State, WebSocketUpgrade};
 use axum::response::IntoResponse;
 use futures::sink::Buffer;
 use futures::stream::{SplitSink, SplitStream};
@@ -27,14 +28,14 @@ use super::{EmittedEvent, Params, TransactionStatusUpdate};
 use crate::dto::serialize::{self, SerializeForVersion};
 use crate::error::ApplicationError;
 use crate::jsonrpc::request::RawParams;
-use crate::jsonrpc::router::RpcRequestError;
+use crate::jsonrpc::router::{RpcRequestError, RpcResponses};
 use crate::jsonrpc::websocket::data::{
     EventFilterParams,
     ResponseEvent,
     SubscriptionId,
     SubscriptionItem,
 };
-use crate::jsonrpc::{RequestId, RpcError, RpcRequest, RpcRouter};
+use crate::jsonrpc::{RequestId, RpcError, RpcRequest, RpcResponse, RpcRouter};
 use crate::{BlockHeader, PendingData, RpcVersion};
 
 const SUBSCRIBE_METHOD: &str = "pathfinder_subscribe";
@@ -61,8 +62,11 @@ impl WebsocketContext {
 
 pub async fn websocket_handler(
     ws: WebSocketUpgrade,
-    State(router): State<RpcRouter>,
+    State(mut router): State<RpcRouter>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
 ) -> impl IntoResponse {
+    router.client_ip = connect_info.map(|ConnectInfo(addr)| addr.ip());
+
     let mut upgrade_response = ws
         .max_message_size(crate::REQUEST_MAX_SIZE)
         .on_failed_upgrade(|error| tracing::debug!(%error, "Websocket upgrade failed"))
@@ -185,6 +189,25 @@ async fn read(
             }
         };
 
+        // Subscription requests don't go through the router, so are rate limited here.
+        let method = parsed_request.method.as_ref();
+        if matches!(method, SUBSCRIBE_METHOD | UNSUBSCRIBE_METHOD) {
+            if let Err(error) = router.check_rate_limit(method) {
+                let response = ResponseEvent::Responses(RpcResponses::Single(RpcResponse {
+                    output: Err(error),
+                    id: parsed_request.id,
+                    version: router.version,
+                }));
+                match response_sender.try_send(response) {
+                    Ok(_) => continue,
+                    Err(e) => {
+                        tracing::debug!(reason=%e, "Failed to send rate limited response");
+                        break;
+                    }
+                }
+            }
+        }
+
         // Handle request.
         let response = match parsed_request.method.as_ref() {
             SUBSCRIBE_METHOD => match subscription_manager.subscribe(
@@ -273,7 +296,7 @@ impl SubscriptionManager {
         request_params: RawParams<'_>,
         response_sender: mpsc::Sender<ResponseEvent>,
         websocket_source: TopicBroadcasters,
-        gateway: impl GatewayApi + Send + 'static,
+        gateway: Option<impl GatewayApi + Send + 'static>,
     ) -> anyhow::Result<ResponseEvent> {
         let params = match request_params.deserialize::<Params>() {
             Ok(x) => x,
@@ -310,12 +333,19 @@ impl SubscriptionManager {
                     filter,
                 ))
             }
-            Params::TransactionStatus(params) => tokio::spawn(transaction_status_subscription(
-                response_sender,
-                subscription_id,
-                params.transaction_hash,
-                gateway,
-            )),
+            Params::TransactionStatus(params) => {
+                let gateway = gateway.ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "Transaction status subscriptions are not available in offline mode"
+                    )
+                })?;
+                tokio::spawn(transaction_status_subscription(
+                    response_sender,
+                    subscription_id,
+                    params.transaction_hash,
+                    gateway,
+                ))
+            }
         };
 
         self.subscriptions.insert(subscription_id, handle);
@@ -996,6 +1026,7 @@ mod tests {
                 transactions: block.transactions.clone(),
                 starknet_version: block.starknet_version,
                 l1_da_mode: block.l1_da_mode,
+                l2_gas_price: Default::default(),
             }
             .into(),
             number: BlockNumber::new_or_panic(block.block_number.get() + 1),
@@ -1047,6 +1078,7 @@ mod tests {
                 transactions: block.transactions.clone(),
                 starknet_version: block.starknet_version,
                 l1_da_mode: block.l1_da_mode,
+                l2_gas_price: Default::default(),
             }
             .into(),
             number: BlockNumber::new_or_panic(block.block_number.get() + 1),
@@ -1099,6 +1131,7 @@ mod tests {
                 transactions: block.transactions.clone(),
                 starknet_version: block.starknet_version,
                 l1_da_mode: block.l1_da_mode,
+                l2_gas_price: Default::default(),
             }
             .into(),
             number: BlockNumber::new_or_panic(block.block_number.get() + 3),
@@ -1147,6 +1180,7 @@ mod tests {
                 transactions: block.transactions.clone(),
                 starknet_version: block.starknet_version,
                 l1_da_mode: block.l1_da_mode,
+                l2_gas_price: Default::default(),
             }
             .into(),
             number: BlockNumber::new_or_panic(block.block_number.get() + 2),
@@ -1616,6 +1650,7 @@ mod tests {
             receipt_commitment: Some(receipt_commitment!("0x6")),
             state_diff_commitment: Some(state_diff_commitment!("0x7")),
             state_diff_length: Some(8),
+            l2_gas_price: None,
         }
     }
 
@@ -1,16 +1,37 @@
 //! Starknet node JSON-RPC related modules.
+pub mod abi_registry;
+pub mod backup;
+pub mod client;
 pub mod context;
 mod dto;
 mod error;
 mod executor;
+mod export;
+pub mod feeder_gateway;
 mod felt;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod fixture;
+mod fork;
+#[cfg(feature = "graphql")]
+pub mod graphql;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod health;
+#[cfg(unix)]
+mod ipc;
 mod jsonrpc;
+mod mempool;
 pub(crate) mod method;
 pub mod middleware;
 mod pathfinder;
 mod pending;
+pub mod record;
+pub mod shutdown;
+mod storage_root_cache;
+pub mod submissions;
 #[cfg(test)]
 mod test_setup;
+pub mod tls;
 pub mod v02;
 pub mod v03;
 pub mod v06;
@@ -18,15 +39,22 @@ pub mod v07;
 pub mod v08;
 
 use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::result::Result;
 use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
 
 use anyhow::Context;
 use axum::error_handling::HandleErrorLayer;
 use axum::extract::DefaultBodyLimit;
 use axum::response::IntoResponse;
 use context::RpcContext;
-pub use executor::compose_executor_transaction;
+pub use executor::{
+    apply_executed_state_diff,
+    compose_executor_transaction,
+    executed_receipt,
+    VERSIONS_LOWER_THAN_THIS_SHOULD_FALL_BACK_TO_FETCHING_TRACE_FROM_GATEWAY,
+};
 use http_body::Body;
 pub use jsonrpc::{Notifications, Reorg};
 use pathfinder_common::AllowedOrigins;
@@ -39,6 +67,7 @@ use tower_http::ServiceBuilderExt;
 use crate::jsonrpc::rpc_handler;
 use crate::jsonrpc::websocket::websocket_handler;
 pub use crate::jsonrpc::websocket::{BlockHeader, TopicBroadcasters};
+use crate::middleware::method_filter::MethodFilter;
 use crate::v02::types::syncing::Syncing;
 
 const DEFAULT_MAX_CONNECTIONS: usize = 1024;
@@ -68,12 +97,19 @@ const REQUEST_MAX_SIZE: usize = 10 * 1024 * 1024;
 // TODO: make this configurable
 const REQUEST_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(120);
 
+#[derive(Clone)]
 pub struct RpcServer {
     addr: SocketAddr,
     context: RpcContext,
     max_connections: usize,
     cors: Option<CorsLayer>,
+    tls: Option<tls::TlsConfig>,
+    /// Also serves the default version on a Unix domain socket at this path.
+    ipc_path: Option<PathBuf>,
     default_version: RpcVersion,
+    /// Networks served under a path prefix, see [RpcServer::with_network].
+    networks: Vec<(String, RpcContext)>,
+    method_filter: Arc<MethodFilter>,
 }
 
 impl RpcServer {
@@ -83,7 +119,25 @@ impl RpcServer {
             context,
             max_connections: DEFAULT_MAX_CONNECTIONS,
             cors: None,
+            tls: None,
+            ipc_path: None,
             default_version,
+            networks: Vec::new(),
+            method_filter: Default::default(),
+        }
+    }
+
+    /// Listens on `addr` instead. Together with [Clone], this allows serving
+    /// the same API on several addresses, e.g. with different method filters.
+    pub fn with_address(self, addr: SocketAddr) -> Self {
+        Self { addr, ..self }
+    }
+
+    /// Only exposes the methods allowed by `method_filter`, on all networks.
+    pub fn with_method_filter(self, method_filter: MethodFilter) -> Self {
+        Self {
+            method_filter: Arc::new(method_filter),
+            ..self
         }
     }
 
@@ -99,12 +153,39 @@ impl RpcServer {
         }
     }
 
+    /// Serves HTTPS instead of HTTP using the given certificate and key.
+    pub fn with_tls(self, config: tls::TlsConfig) -> Self {
+        Self {
+            tls: Some(config),
+            ..self
+        }
+    }
+
+    /// Also serves the default API version over a Unix domain socket at
+    /// `path`, see [ipc](crate::ipc). Only supported on unix.
+    pub fn with_ipc(self, path: PathBuf) -> Self {
+        Self {
+            ipc_path: Some(path),
+            ..self
+        }
+    }
+
+    /// Also serves the API of the network of `context` under `/{name}`, e.g.
+    /// `/{name}/rpc/v0_7`, while the network of the server's own context
+    /// remains available without a prefix.
+    ///
+    /// The server stops accepting connections once shutdown of the server's
+    /// own context starts, so the other contexts should be shut down along
+    /// with it.
+    pub fn with_network(mut self, name: impl Into<String>, context: RpcContext) -> Self {
+        self.networks.push((name.into(), context));
+        self
+    }
+
     /// Starts the HTTP-RPC server.
     pub async fn spawn(
         self,
     ) -> Result<(JoinHandle<anyhow::Result<()>>, SocketAddr), anyhow::Error> {
-        use axum::routing::{get, post};
-
         let listener = match tokio::net::TcpListener::bind(self.addr).await {
             Ok(listener) => listener,
             Err(e) => {
@@ -123,96 +204,64 @@ impl RpcServer {
             .local_addr()
             .context("Getting local address from listener")?;
 
-        async fn handle_middleware_errors(err: axum::BoxError) -> (http::StatusCode, String) {
-            use http::StatusCode;
-            if err.is::<tower::timeout::error::Elapsed>() {
-                (
-                    StatusCode::REQUEST_TIMEOUT,
-                    "Request took too long".to_string(),
-                )
-            } else {
-                // TODO: confirm this isn't too verbose.
-                tracing::warn!(error = err, "Unhandled middleware error");
-
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    "Internal error".to_string(),
-                )
-            }
+        let mut router = routes(
+            self.context.clone(),
+            self.default_version,
+            &self.method_filter,
+        )?;
+        for (name, context) in self.networks {
+            router = router.nest(
+                &format!("/{name}"),
+                routes(context, self.default_version, &self.method_filter)?,
+            );
         }
 
-        let middleware = tower::ServiceBuilder::new()
-            // Convert errors created by middleware layers into responses.
-            // This is required by axum -- axum doesn't deal with Result, errors
-            // must be responses as well.
-            .layer(HandleErrorLayer::new(handle_middleware_errors))
-            // make sure to set request ids before the request reaches `TraceLayer`
-            .set_x_request_id(middleware::request_id::RequestIdSource::default())
-            .concurrency_limit(self.max_connections)
-            .layer(DefaultBodyLimit::max(REQUEST_MAX_SIZE))
-            .timeout(REQUEST_TIMEOUT)
-            .layer(middleware::tracing::trace_layer())
-            .option_layer(self.cors)
-            .propagate_x_request_id();
-
-        /// Returns success for requests with an empty body without reading
-        /// the entire body.
-        async fn empty_body(request: axum::extract::Request) -> impl IntoResponse {
-            if request.body().is_end_stream() {
-                axum::http::StatusCode::OK
-            } else {
-                axum::http::StatusCode::METHOD_NOT_ALLOWED
+        let router = with_http_middleware(router, self.max_connections, self.cors);
+
+        let shutdown = self.context.shutdown.clone();
+
+        if let Some(path) = self.ipc_path {
+            #[cfg(unix)]
+            {
+                let listener = ipc::bind(&path)?;
+                let builder = match self.default_version {
+                    RpcVersion::V06 => v06::register_routes(),
+                    RpcVersion::V07 => v07::register_routes(),
+                    RpcVersion::V08 => v08::register_routes(),
+                    RpcVersion::PathfinderV01 => pathfinder::register_routes(),
+                };
+                let ipc_router = jsonrpc::RpcRouter {
+                    method_filter: self.method_filter.clone(),
+                    ..builder.build(self.context.clone())
+                };
+                tracing::info!(path=%path.display(), "RPC IPC socket listening");
+                tokio::spawn(ipc::serve(listener, path, ipc_router, shutdown.clone()));
             }
+            #[cfg(not(unix))]
+            anyhow::bail!(
+                "Serving RPC on {} requires Unix domain sockets, which this platform lacks",
+                path.display()
+            );
         }
 
-        let v06_routes = v06::register_routes().build(self.context.clone());
-        let v07_routes = v07::register_routes().build(self.context.clone());
-        let v08_routes = v08::register_routes().build(self.context.clone());
-        let pathfinder_routes = pathfinder::register_routes().build(self.context.clone());
-
-        let default_router = match self.default_version {
-            RpcVersion::V06 => v06_routes.clone(),
-            RpcVersion::V07 => v07_routes.clone(),
-            RpcVersion::V08 => v08_routes.clone(),
-            RpcVersion::PathfinderV01 => {
-                anyhow::bail!("Did not expect default RPC version to be Pathfinder v0.1")
-            }
-        };
+        if let Some(config) = self.tls {
+            let acceptor =
+                tls::ReloadableAcceptor::new(config).context("Loading RPC TLS certificate")?;
+            #[cfg(unix)]
+            acceptor.reload_on_sighup()?;
 
-        let router = axum::Router::new()
-            // Also return success for get's with an empty body. These are often
-            // used by monitoring bots to check service health.
-            .route("/", get(empty_body).post(rpc_handler))
-            .with_state(default_router.clone())
-            .route("/rpc/v0_6", post(rpc_handler))
-            .with_state(v06_routes.clone())
-            .route("/rpc/v0_7", post(rpc_handler))
-            .with_state(v07_routes.clone())
-            // TODO Uncomment once RPC 0.8 is ready.
-            .route("/rpc/v0_8", post(rpc_handler).get(rpc_handler))
-            .with_state(v08_routes.clone())
-            .route("/rpc/pathfinder/v0.1", post(rpc_handler))
-            .route("/rpc/pathfinder/v0_1", post(rpc_handler))
-            .with_state(pathfinder_routes.clone());
-
-        let router = if self.context.websocket.is_some() {
-            router
-                .route("/ws", get(websocket_handler))
-                .with_state(default_router)
-                .route("/ws/rpc/v0_6", get(websocket_handler))
-                .with_state(v06_routes)
-                .route("/ws/rpc/v0_7", get(websocket_handler))
-                .with_state(v07_routes)
-                .route("/ws/rpc/pathfinder/v0_1", get(websocket_handler))
-                .with_state(pathfinder_routes)
-        } else {
-            router.with_state(default_router)
-        };
+            let server_handle = tokio::spawn(tls::serve(listener, router, acceptor, shutdown));
+            return Ok((server_handle, addr));
+        }
 
-        let router = router.layer(middleware);
+        // Connection info provides the client IP address for per-IP rate limits.
+        let service = router.into_make_service_with_connect_info::<SocketAddr>();
 
         let server_handle = tokio::spawn(async move {
-            axum::serve(listener, router.into_make_service())
+            axum::serve(listener, service)
+                // Stop accepting new connections once shutdown starts, while letting
+                // in-flight requests complete.
+                .with_graceful_shutdown(async move { shutdown.wait_for_shutdown().await })
                 .await
                 .map_err(Into::into)
         });
@@ -228,6 +277,128 @@ impl RpcServer {
     }
 }
 
+/// Wraps `router` in the middleware shared by the HTTP listeners: request ids,
+/// tracing, and limits on concurrent connections, body size and duration.
+fn with_http_middleware(
+    router: axum::Router,
+    max_connections: usize,
+    cors: Option<CorsLayer>,
+) -> axum::Router {
+    async fn handle_middleware_errors(err: axum::BoxError) -> (http::StatusCode, String) {
+        use http::StatusCode;
+        if err.is::<tower::timeout::error::Elapsed>() {
+            (
+                StatusCode::REQUEST_TIMEOUT,
+                "Request took too long".to_string(),
+            )
+        } else {
+            // TODO: confirm this isn't too verbose.
+            tracing::warn!(error = err, "Unhandled middleware error");
+
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Internal error".to_string(),
+            )
+        }
+    }
+
+    let middleware = tower::ServiceBuilder::new()
+        // Convert errors created by middleware layers into responses.
+        // This is required by axum -- axum doesn't deal with Result, errors
+        // must be responses as well.
+        .layer(HandleErrorLayer::new(handle_middleware_errors))
+        // make sure to set request ids before the request reaches `TraceLayer`
+        .set_x_request_id(middleware::request_id::RequestIdSource::default())
+        .concurrency_limit(max_connections)
+        .layer(DefaultBodyLimit::max(REQUEST_MAX_SIZE))
+        .timeout(REQUEST_TIMEOUT)
+        .layer(middleware::tracing::trace_layer())
+        .option_layer(cors)
+        .propagate_x_request_id();
+
+    router.layer(middleware)
+}
+
+/// The routes serving the methods of the API of `context` allowed by
+/// `method_filter`, with `default_version` at the root path.
+fn routes(
+    context: RpcContext,
+    default_version: RpcVersion,
+    method_filter: &Arc<MethodFilter>,
+) -> anyhow::Result<axum::Router> {
+    use axum::routing::{get, post};
+
+    /// Returns success for requests with an empty body without reading
+    /// the entire body.
+    async fn empty_body(request: axum::extract::Request) -> impl IntoResponse {
+        if request.body().is_end_stream() {
+            axum::http::StatusCode::OK
+        } else {
+            axum::http::StatusCode::METHOD_NOT_ALLOWED
+        }
+    }
+
+    let build = |builder: jsonrpc::RpcRouterBuilder| jsonrpc::RpcRouter {
+        method_filter: method_filter.clone(),
+        ..builder.build(context.clone())
+    };
+    let v06_routes = build(v06::register_routes());
+    let v07_routes = build(v07::register_routes());
+    let v08_routes = build(v08::register_routes());
+    let pathfinder_routes = build(pathfinder::register_routes());
+
+    let default_router = match default_version {
+        RpcVersion::V06 => v06_routes.clone(),
+        RpcVersion::V07 => v07_routes.clone(),
+        RpcVersion::V08 => v08_routes.clone(),
+        RpcVersion::PathfinderV01 => {
+            anyhow::bail!("Did not expect default RPC version to be Pathfinder v0.1")
+        }
+    };
+
+    let router = axum::Router::new()
+        // Also return success for get's with an empty body. These are often
+        // used by monitoring bots to check service health.
+        .route("/", get(empty_body).post(rpc_handler))
+        .with_state(default_router.clone())
+        .route("/rpc/v0_6", post(rpc_handler))
+        .with_state(v06_routes.clone())
+        .route("/rpc/v0_7", post(rpc_handler))
+        .with_state(v07_routes.clone())
+        // TODO Uncomment once RPC 0.8 is ready.
+        .route("/rpc/v0_8", post(rpc_handler).get(rpc_handler))
+        .with_state(v08_routes.clone())
+        .route("/rpc/pathfinder/v0.1", post(rpc_handler).get(rpc_handler))
+        .route("/rpc/pathfinder/v0_1", post(rpc_handler).get(rpc_handler))
+        .with_state(pathfinder_routes.clone());
+
+    let router = if context.websocket.is_some() {
+        router
+            .route("/ws", get(websocket_handler))
+            .with_state(default_router)
+            .route("/ws/rpc/v0_6", get(websocket_handler))
+            .with_state(v06_routes)
+            .route("/ws/rpc/v0_7", get(websocket_handler))
+            .with_state(v07_routes)
+            .route("/ws/rpc/v0_8", get(websocket_handler))
+            .with_state(v08_routes)
+            .route("/ws/rpc/pathfinder/v0_1", get(websocket_handler))
+            .with_state(pathfinder_routes)
+    } else {
+        router.with_state(default_router)
+    };
+
+    // The export is a faster alternative to paging through `starknet_getEvents`,
+    // so it is only available if that is.
+    let router = if method_filter.allows("starknet_getEvents") {
+        router.merge(export::router(context))
+    } else {
+        router
+    };
+
+    Ok(router)
+}
+
 pub struct SyncState {
     pub status: RwLock<Syncing>,
 }
@@ -788,6 +959,7 @@ pub mod test_utils {
             transactions,
             starknet_version: StarknetVersion::new(0, 11, 0, 0),
             l1_da_mode: starknet_gateway_types::reply::L1DataAvailabilityMode::Calldata,
+            l2_gas_price: Default::default(),
         };
 
         // The class definitions must be inserted into the database.
@@ -891,13 +1063,146 @@ mod tests {
         assert!(!status.is_success());
     }
 
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn ipc() {
+        use std::os::unix::fs::PermissionsExt;
+
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pathfinder.ipc");
+        let addr: SocketAddr = "127.0.0.1:0".parse().unwrap();
+        let context = RpcContext::for_tests();
+        let (_jh, _addr) = RpcServer::new(addr, context, RpcVersion::V07)
+            .with_ipc(path.clone())
+            .spawn()
+            .await
+            .unwrap();
+
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o660);
+
+        let stream = tokio::net::UnixStream::connect(&path).await.unwrap();
+        let (reader, mut writer) = stream.into_split();
+        writer
+            .write_all(b"{\"jsonrpc\":\"2.0\",\"method\":\"starknet_chainId\",\"id\":1}\n")
+            .await
+            .unwrap();
+
+        let mut line = String::new();
+        BufReader::new(reader).read_line(&mut line).await.unwrap();
+        let response: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(
+            response,
+            json!({"jsonrpc": "2.0", "result": "0x534e5f5345504f4c4941", "id": 1})
+        );
+    }
+
+    #[tokio::test]
+    async fn networks_are_served_under_their_prefix() {
+        let addr: SocketAddr = "127.0.0.1:0".parse().unwrap();
+        let sepolia = RpcContext::for_tests_on(pathfinder_common::Chain::SepoliaTestnet);
+        let mainnet = RpcContext::for_tests_on(pathfinder_common::Chain::Mainnet);
+        let (_jh, addr) = RpcServer::new(addr, sepolia.clone(), RpcVersion::V07)
+            .with_network("sepolia-testnet", sepolia)
+            .with_network("mainnet", mainnet)
+            .spawn()
+            .await
+            .unwrap();
+
+        let client = reqwest::Client::new();
+        let chain_id = |route: &'static str| {
+            let request = client
+                .post(format!("http://{addr}{route}"))
+                .json(&json!({"jsonrpc": "2.0", "method": "starknet_chainId", "id": 0}))
+                .send();
+            async move {
+                let response: serde_json::Value = request.await.unwrap().json().await.unwrap();
+                response["result"].clone()
+            }
+        };
+
+        let sepolia_id = json!(pathfinder_common::ChainId::SEPOLIA_TESTNET.to_hex_str());
+        let mainnet_id = json!(pathfinder_common::ChainId::MAINNET.to_hex_str());
+        assert_eq!(chain_id("/rpc/v0_7").await, sepolia_id);
+        assert_eq!(chain_id("/sepolia-testnet/rpc/v0_7").await, sepolia_id);
+        assert_eq!(chain_id("/mainnet/rpc/v0_7").await, mainnet_id);
+        assert_eq!(chain_id("/mainnet").await, mainnet_id);
+    }
+
+    #[tokio::test]
+    async fn disabled_methods_are_not_found() {
+        use crate::middleware::method_filter::{MethodFilter, MethodSet};
+
+        let addr: SocketAddr = "127.0.0.1:0".parse().unwrap();
+        let context = RpcContext::for_tests();
+        let (_jh, addr) = RpcServer::new(addr, context, RpcVersion::V07)
+            .with_method_filter(MethodFilter {
+                disabled_sets: [MethodSet::Pathfinder].into(),
+                disabled_methods: ["starknet_blockNumber".to_owned()].into(),
+                ..Default::default()
+            })
+            .spawn()
+            .await
+            .unwrap();
+
+        let client = reqwest::Client::new();
+        let call = |route: &'static str, method: &'static str| {
+            let request = client
+                .post(format!("http://{addr}{route}"))
+                .json(&json!({"jsonrpc": "2.0", "method": method, "id": 0}))
+                .send();
+            async move {
+                let response: serde_json::Value = request.await.unwrap().json().await.unwrap();
+                response
+            }
+        };
+
+        let response = call("/rpc/v0_7", "starknet_chainId").await;
+        assert!(response.get("result").is_some(), "{response}");
+        let response = call("/rpc/v0_7", "starknet_blockNumber").await;
+        assert_eq!(response["error"]["code"], json!(-32601));
+        let response = call("/rpc/pathfinder/v0_1", "pathfinder_version").await;
+        assert_eq!(response["error"]["code"], json!(-32601));
+    }
+
     #[rustfmt::skip]
     #[rstest::rstest]
     #[case::root_api  ("/", "v06/starknet_api_openrpc.json",       &[])]
     #[case::root_trace("/", "v06/starknet_trace_api_openrpc.json", &[])]
     #[case::root_write("/", "v06/starknet_write_api.json",         &[])]
     // get_transaction_status is now part of the official spec, so we are phasing it out.
-    #[case::root_pathfinder("/", "pathfinder_rpc_api.json", &["pathfinder_version", "pathfinder_getTransactionStatus"])]
+    // The remaining excluded methods are only served on the pathfinder routes.
+    #[case::root_pathfinder("/", "pathfinder_rpc_api.json", &[
+        "pathfinder_version",
+        "pathfinder_getTransactionStatus",
+        "pathfinder_getL1ToL2MessageStatus",
+        "pathfinder_getMessageToL1Proof",
+        "pathfinder_getMigrationStatus",
+        "pathfinder_getBackupStatus",
+        "pathfinder_getBalances",
+        "pathfinder_getStorageAtBlocks",
+        "pathfinder_getStorageBatch",
+        "pathfinder_getStorageHistory",
+        "pathfinder_getNonceAt",
+        "pathfinder_getFeeHistory",
+        "pathfinder_getBlockStats",
+        "pathfinder_getSubmittedTransactions",
+        "pathfinder_getStateUpdateRange",
+        "pathfinder_getContractStateHash",
+        "pathfinder_getContractStorageKeys",
+        "pathfinder_getReceiptProof",
+        "pathfinder_compareTrace",
+        "pathfinder_traceTransactionFlame",
+        "pathfinder_getClassDefinitions",
+        "pathfinder_getClassInfo",
+        "pathfinder_getTransactionsByAccount",
+        "pathfinder_getReorgs",
+        "pathfinder_getSyncLag",
+        "pathfinder_registerAbi",
+        "pathfinder_health",
+    ])]
 
     #[case::v0_8_api  ("/rpc/v0_8", "v08/starknet_api_openrpc.json", &[
         "starknet_getBlockWithReceipts",
@@ -911,19 +1216,106 @@ mod tests {
         "starknet_addDeployAccountTransaction"
     ])]
     // get_transaction_status is now part of the official spec, so we are phasing it out.
-    #[case::v0_8_pathfinder("/rpc/v0_8", "pathfinder_rpc_api.json", &["pathfinder_version", "pathfinder_getTransactionStatus"])]
+    // The remaining excluded methods are only served on the pathfinder routes.
+    #[case::v0_8_pathfinder("/rpc/v0_8", "pathfinder_rpc_api.json", &[
+        "pathfinder_version",
+        "pathfinder_getTransactionStatus",
+        "pathfinder_getL1ToL2MessageStatus",
+        "pathfinder_getMessageToL1Proof",
+        "pathfinder_getMigrationStatus",
+        "pathfinder_getBackupStatus",
+        "pathfinder_getBalances",
+        "pathfinder_getStorageAtBlocks",
+        "pathfinder_getStorageBatch",
+        "pathfinder_getStorageHistory",
+        "pathfinder_getNonceAt",
+        "pathfinder_getFeeHistory",
+        "pathfinder_getBlockStats",
+        "pathfinder_getSubmittedTransactions",
+        "pathfinder_getStateUpdateRange",
+        "pathfinder_getContractStateHash",
+        "pathfinder_getContractStorageKeys",
+        "pathfinder_getReceiptProof",
+        "pathfinder_compareTrace",
+        "pathfinder_traceTransactionFlame",
+        "pathfinder_getClassDefinitions",
+        "pathfinder_getClassInfo",
+        "pathfinder_getTransactionsByAccount",
+        "pathfinder_getReorgs",
+        "pathfinder_getSyncLag",
+        "pathfinder_registerAbi",
+        "pathfinder_health",
+    ])]
 
     #[case::v0_7_api  ("/rpc/v0_7", "v07/starknet_api_openrpc.json", &[])]
     #[case::v0_7_trace("/rpc/v0_7", "v07/starknet_trace_api_openrpc.json", &[])]
     #[case::v0_7_write("/rpc/v0_7", "v07/starknet_write_api.json", &[])]
     // get_transaction_status is now part of the official spec, so we are phasing it out.
-    #[case::v0_7_pathfinder("/rpc/v0_7", "pathfinder_rpc_api.json", &["pathfinder_version", "pathfinder_getTransactionStatus"])]
+    // The remaining excluded methods are only served on the pathfinder routes.
+    #[case::v0_7_pathfinder("/rpc/v0_7", "pathfinder_rpc_api.json", &[
+        "pathfinder_version",
+        "pathfinder_getTransactionStatus",
+        "pathfinder_getL1ToL2MessageStatus",
+        "pathfinder_getMessageToL1Proof",
+        "pathfinder_getMigrationStatus",
+        "pathfinder_getBackupStatus",
+        "pathfinder_getBalances",
+        "pathfinder_getStorageAtBlocks",
+        "pathfinder_getStorageBatch",
+        "pathfinder_getStorageHistory",
+        "pathfinder_getNonceAt",
+        "pathfinder_getFeeHistory",
+        "pathfinder_getBlockStats",
+        "pathfinder_getSubmittedTransactions",
+        "pathfinder_getStateUpdateRange",
+        "pathfinder_getContractStateHash",
+        "pathfinder_getContractStorageKeys",
+        "pathfinder_getReceiptProof",
+        "pathfinder_compareTrace",
+        "pathfinder_traceTransactionFlame",
+        "pathfinder_getClassDefinitions",
+        "pathfinder_getClassInfo",
+        "pathfinder_getTransactionsByAccount",
+        "pathfinder_getReorgs",
+        "pathfinder_getSyncLag",
+        "pathfinder_registerAbi",
+        "pathfinder_health",
+    ])]
 
     #[case::v0_6_api  ("/rpc/v0_6", "v06/starknet_api_openrpc.json", &[])]
     #[case::v0_6_trace("/rpc/v0_6", "v06/starknet_trace_api_openrpc.json", &[])]
     #[case::v0_6_write("/rpc/v0_6", "v06/starknet_write_api.json", &[])]
     // get_transaction_status is now part of the official spec, so we are phasing it out.
-    #[case::v0_6_pathfinder("/rpc/v0_6", "pathfinder_rpc_api.json", &["pathfinder_version", "pathfinder_getTransactionStatus"])]
+    // The remaining excluded methods are only served on the pathfinder routes.
+    #[case::v0_6_pathfinder("/rpc/v0_6", "pathfinder_rpc_api.json", &[
+        "pathfinder_version",
+        "pathfinder_getTransactionStatus",
+        "pathfinder_getL1ToL2MessageStatus",
+        "pathfinder_getMessageToL1Proof",
+        "pathfinder_getMigrationStatus",
+        "pathfinder_getBackupStatus",
+        "pathfinder_getBalances",
+        "pathfinder_getStorageAtBlocks",
+        "pathfinder_getStorageBatch",
+        "pathfinder_getStorageHistory",
+        "pathfinder_getNonceAt",
+        "pathfinder_getFeeHistory",
+        "pathfinder_getBlockStats",
+        "pathfinder_getSubmittedTransactions",
+        "pathfinder_getStateUpdateRange",
+        "pathfinder_getContractStateHash",
+        "pathfinder_getContractStorageKeys",
+        "pathfinder_getReceiptProof",
+        "pathfinder_compareTrace",
+        "pathfinder_traceTransactionFlame",
+        "pathfinder_getClassDefinitions",
+        "pathfinder_getClassInfo",
+        "pathfinder_getTransactionsByAccount",
+        "pathfinder_getReorgs",
+        "pathfinder_getSyncLag",
+        "pathfinder_registerAbi",
+        "pathfinder_health",
+    ])]
 
     #[case::pathfinder("/rpc/pathfinder/v0.1", "pathfinder_rpc_api.json", &[])]
     #[case::pathfinder("/rpc/pathfinder/v0_1", "pathfinder_rpc_api.json", &[])]
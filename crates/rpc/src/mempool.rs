@@ -0,0 +1,337 @@
+//! Local pre-validation and tracking of transactions submitted through this
+//! node.
+//!
+//! Transactions sent to `starknet_addInvokeTransaction` and friends are
+//! validated against the pending state before being forwarded to the gateway,
+//! so that obviously invalid transactions are rejected immediately instead of
+//! only once the sequencer gets to them. Accepted submissions are remembered
+//! for a while, which lets the transaction status subscriptions report them as
+//! `RECEIVED` before they show up in the pending block.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use pathfinder_common::{ContractAddress, TransactionHash};
+use pathfinder_executor::{ExecutionState, L1BlobDataAvailability, ValidationError};
+use starknet_gateway_types::error::{KnownStarknetErrorCode, SequencerError, StarknetError};
+
+use crate::context::RpcContext;
+use crate::v02::types::request::{
+    BroadcastedDeclareTransaction,
+    BroadcastedInvokeTransaction,
+    BroadcastedTransaction,
+};
+
+/// How long a submitted transaction is tracked. This comfortably covers the
+/// time it takes the sequencer to include a transaction in the pending block.
+const SUBMISSION_TTL: Duration = Duration::from_secs(300);
+
+/// The maximum number of tracked submissions.
+const MAX_SUBMISSIONS: usize = 10_000;
+
+/// Transactions which were successfully submitted through this node.
+#[derive(Clone, Default)]
+pub struct Mempool(Arc<Mutex<Inner>>);
+
+#[derive(Default)]
+struct Inner {
+    submissions: HashMap<TransactionHash, Submission>,
+    /// The number of submissions per sender which have been validated but not
+    /// yet answered by the gateway, and are therefore not in `submissions`.
+    submitting: HashMap<ContractAddress, usize>,
+}
+
+struct Submission {
+    sender: ContractAddress,
+    submitted_at: Instant,
+}
+
+/// Marks a submission of `sender` as in flight until dropped, see
+/// [Mempool::submitting].
+pub(crate) struct Submitting {
+    mempool: Mempool,
+    sender: ContractAddress,
+}
+
+impl Drop for Submitting {
+    fn drop(&mut self) {
+        let mut inner = self.mempool.0.lock().unwrap();
+        if let Some(count) = inner.submitting.get_mut(&self.sender) {
+            *count -= 1;
+            if *count == 0 {
+                inner.submitting.remove(&self.sender);
+            }
+        }
+    }
+}
+
+impl Mempool {
+    /// Tracks a transaction which the gateway has accepted.
+    pub(crate) fn insert(&self, transaction_hash: TransactionHash, sender: ContractAddress) {
+        let mut inner = self.0.lock().unwrap();
+        let submissions = &mut inner.submissions;
+        submissions.retain(|_, submission| submission.submitted_at.elapsed() < SUBMISSION_TTL);
+        if submissions.len() >= MAX_SUBMISSIONS {
+            tracing::debug!(%transaction_hash, "Mempool full, not tracking transaction");
+            return;
+        }
+        submissions.insert(
+            transaction_hash,
+            Submission {
+                sender,
+                submitted_at: Instant::now(),
+            },
+        );
+    }
+
+    /// Marks a submission of `sender` as in flight while it is being forwarded
+    /// to the gateway, before its hash is known to be accepted.
+    pub(crate) fn submitting(&self, sender: ContractAddress) -> Submitting {
+        *self.0.lock().unwrap().submitting.entry(sender).or_default() += 1;
+        Submitting {
+            mempool: self.clone(),
+            sender,
+        }
+    }
+
+    /// Whether the transaction was recently submitted through this node.
+    pub fn contains(&self, transaction_hash: &TransactionHash) -> bool {
+        self.0
+            .lock()
+            .unwrap()
+            .submissions
+            .get(transaction_hash)
+            .is_some_and(|submission| submission.submitted_at.elapsed() < SUBMISSION_TTL)
+    }
+
+    /// Whether `sender` is submitting or has recently submitted a transaction
+    /// which is not yet part of `pending`. The nonce of such a sender is ahead
+    /// of its nonce in the pending state.
+    fn has_in_flight(&self, sender: ContractAddress, pending: &[TransactionHash]) -> bool {
+        let inner = self.0.lock().unwrap();
+        inner.submitting.contains_key(&sender)
+            || inner.submissions.iter().any(|(hash, submission)| {
+                submission.sender == sender
+                    && submission.submitted_at.elapsed() < SUBMISSION_TTL
+                    && !pending.contains(hash)
+            })
+    }
+}
+
+/// The account submitting the transaction.
+pub(crate) fn sender_address(transaction: &BroadcastedTransaction) -> ContractAddress {
+    match transaction {
+        BroadcastedTransaction::Invoke(BroadcastedInvokeTransaction::V0(tx)) => tx.contract_address,
+        BroadcastedTransaction::Invoke(BroadcastedInvokeTransaction::V1(tx)) => tx.sender_address,
+        BroadcastedTransaction::Invoke(BroadcastedInvokeTransaction::V3(tx)) => tx.sender_address,
+        BroadcastedTransaction::Declare(BroadcastedDeclareTransaction::V0(tx)) => tx.sender_address,
+        BroadcastedTransaction::Declare(BroadcastedDeclareTransaction::V1(tx)) => tx.sender_address,
+        BroadcastedTransaction::Declare(BroadcastedDeclareTransaction::V2(tx)) => tx.sender_address,
+        BroadcastedTransaction::Declare(BroadcastedDeclareTransaction::V3(tx)) => tx.sender_address,
+        BroadcastedTransaction::DeployAccount(tx) => tx.deployed_contract_address(),
+    }
+}
+
+/// Validates `transaction` against the pending state, unless disabled by
+/// [RpcConfig::validate_transactions](crate::context::RpcConfig) or the node
+/// is synced in light mode and has no state to validate against.
+///
+/// Validation is subject to the configured execution limits and timeout.
+/// Rejections are reported as the [SequencerError] the gateway would have
+/// returned, so that they map onto the same RPC errors. Transactions which
+/// cannot be validated locally, for example because the sender already has a
+/// transaction in flight, are let through for the gateway to decide.
+///
+/// The returned guard marks the sender as having a transaction in flight and
+/// should be held until the gateway has answered.
+pub(crate) async fn validate(
+    context: &RpcContext,
+    transaction: &BroadcastedTransaction,
+) -> Result<Option<Submitting>, SequencerError> {
+    if !context.config.validate_transactions
+        || context.config.sync_mode == pathfinder_storage::SyncMode::Light
+    {
+        return Ok(None);
+    }
+
+    let sender = sender_address(transaction);
+    let timeout = context.config.execution_timeout;
+    let mempool = context.mempool.clone();
+    let context = context.clone();
+    let transaction = transaction.clone();
+    let span = tracing::Span::current();
+
+    let jh = tokio::task::spawn_blocking(move || -> Result<Option<Submitting>, ValidationError> {
+        let _g = span.enter();
+        let mut db = context
+            .execution_storage
+            .connection()
+            .context("Creating database connection")?;
+        let db = db.transaction().context("Creating database transaction")?;
+
+        let pending = context
+            .pending_data
+            .get(&db)
+            .context("Querying pending data")?;
+
+        let pending_hashes = pending
+            .block
+            .transactions
+            .iter()
+            .map(|tx| tx.hash)
+            .collect::<Vec<_>>();
+        if context.mempool.has_in_flight(sender, &pending_hashes) {
+            tracing::debug!(%sender, "Sender has a transaction in flight, skipping validation");
+            return Ok(Some(context.mempool.submitting(sender)));
+        }
+        // Claim the sender before validating so that a concurrent submission
+        // of its next transaction is not validated against a stale nonce.
+        let submitting = context.mempool.submitting(sender);
+
+        let transaction = crate::executor::map_broadcasted_transaction(
+            &transaction,
+            context.chain_id,
+            &context.storage,
+        )?;
+
+        let state = ExecutionState::simulation(
+            &db,
+            context.chain_id,
+            pending.header(),
+            Some(pending.state_update.clone()),
+            L1BlobDataAvailability::Enabled,
+            context.config.custom_versioned_constants,
+        )
+        .with_limits(context.config.execution_limits);
+
+        pathfinder_executor::validate(state, transaction)?;
+
+        Ok(Some(submitting))
+    });
+
+    let result = match crate::executor::with_timeout(timeout, jh).await {
+        Some(Ok(result)) => result,
+        Some(Err(error)) => Err(ValidationError::Internal(error.into())),
+        None => Err(ValidationError::Internal(anyhow::anyhow!(
+            "Validation timed out"
+        ))),
+    };
+
+    let (code, message) = match result {
+        Ok(submitting) => return Ok(submitting),
+        Err(ValidationError::Internal(error)) => {
+            // Leave it to the gateway rather than rejecting a possibly valid
+            // transaction.
+            tracing::warn!(%error, "Failed to validate transaction locally");
+            return Ok(Some(mempool.submitting(sender)));
+        }
+        Err(ValidationError::InvalidNonce) => (
+            KnownStarknetErrorCode::InvalidTransactionNonce,
+            "Invalid transaction nonce".to_owned(),
+        ),
+        Err(ValidationError::InsufficientMaxFee) => (
+            KnownStarknetErrorCode::InsufficientMaxFee,
+            "Max fee is smaller than the minimal transaction cost".to_owned(),
+        ),
+        Err(ValidationError::InsufficientAccountBalance) => (
+            KnownStarknetErrorCode::InsufficientAccountBalance,
+            "Account balance is smaller than the transaction's max fee".to_owned(),
+        ),
+        Err(ValidationError::ValidationFailure(message)) => {
+            (KnownStarknetErrorCode::ValidateFailure, message)
+        }
+    };
+
+    Err(SequencerError::StarknetError(StarknetError {
+        code: code.into(),
+        message,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::macro_prelude::*;
+    use pathfinder_common::TransactionVersion;
+
+    use super::*;
+    use crate::v02::types::request::BroadcastedInvokeTransactionV1;
+
+    #[test]
+    fn in_flight_until_pending() {
+        let mempool = Mempool::default();
+        let sender = contract_address!("0x1");
+        let hash = transaction_hash!("0x2");
+
+        assert!(!mempool.contains(&hash));
+        assert!(!mempool.has_in_flight(sender, &[]));
+
+        mempool.insert(hash, sender);
+
+        assert!(mempool.contains(&hash));
+        assert!(mempool.has_in_flight(sender, &[]));
+        assert!(!mempool.has_in_flight(contract_address!("0x3"), &[]));
+        assert!(!mempool.has_in_flight(sender, &[hash]));
+    }
+
+    #[test]
+    fn in_flight_while_submitting() {
+        let mempool = Mempool::default();
+        let sender = contract_address!("0x1");
+
+        let submitting = mempool.submitting(sender);
+        assert!(mempool.has_in_flight(sender, &[]));
+        assert!(!mempool.has_in_flight(contract_address!("0x3"), &[]));
+
+        drop(submitting);
+        assert!(!mempool.has_in_flight(sender, &[]));
+    }
+
+    fn invoke(sender_address: ContractAddress) -> BroadcastedTransaction {
+        BroadcastedTransaction::Invoke(BroadcastedInvokeTransaction::V1(
+            BroadcastedInvokeTransactionV1 {
+                version: TransactionVersion::ONE,
+                max_fee: fee!("0x1000"),
+                signature: vec![],
+                nonce: transaction_nonce!("0x0"),
+                sender_address,
+                calldata: vec![],
+            },
+        ))
+    }
+
+    #[tokio::test]
+    async fn disabled() {
+        let context = RpcContext::for_tests();
+
+        validate(&context, &invoke(contract_address!("0xdeadbeef")))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn rejects_unknown_account() {
+        let mut context = RpcContext::for_tests();
+        context.config.validate_transactions = true;
+
+        let error = validate(&context, &invoke(contract_address!("0xdeadbeef")))
+            .await
+            .unwrap_err();
+
+        assert_matches::assert_matches!(error, SequencerError::StarknetError(_));
+    }
+
+    #[tokio::test]
+    async fn skips_sender_with_transaction_in_flight() {
+        let mut context = RpcContext::for_tests();
+        context.config.validate_transactions = true;
+        context
+            .mempool
+            .insert(transaction_hash!("0x1"), contract_address!("0xdeadbeef"));
+
+        validate(&context, &invoke(contract_address!("0xdeadbeef")))
+            .await
+            .unwrap();
+    }
+}
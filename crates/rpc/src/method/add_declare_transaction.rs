@@ -8,7 +8,7 @@ use starknet_gateway_types::request::add_transaction::{
 };
 
 use crate::context::RpcContext;
-use crate::v02::types::request::BroadcastedDeclareTransaction;
+use crate::v02::types::request::{BroadcastedDeclareTransaction, BroadcastedTransaction};
 
 #[derive(Debug)]
 pub enum AddDeclareTransactionError {
@@ -177,7 +177,24 @@ pub async fn add_declare_transaction(
 ) -> Result<Output, AddDeclareTransactionError> {
     use starknet_gateway_types::request::add_transaction;
 
-    match input.declare_transaction {
+    // Executing a declaration locally would require storing the class
+    // definition, which fork mode never writes to the database.
+    if context.fork.is_some() {
+        return Err(AddDeclareTransactionError::UnexpectedError(
+            "Declaring classes is not supported in fork mode".to_owned(),
+        ));
+    }
+
+    let Transaction::Declare(declare) = &input.declare_transaction;
+    let transaction = BroadcastedTransaction::Declare(declare.clone());
+    // Version 0 declares are rejected below without involving the gateway.
+    let _submitting = if !matches!(declare, BroadcastedDeclareTransaction::V0(_)) {
+        crate::mempool::validate(&context, &transaction).await?
+    } else {
+        None
+    };
+
+    let output = match input.declare_transaction {
         Transaction::Declare(BroadcastedDeclareTransaction::V0(_)) => {
             Err(AddDeclareTransactionError::UnsupportedTransactionVersion)
         }
@@ -187,21 +204,21 @@ pub async fn add_declare_transaction(
                 .try_into()
                 .map_err(|e| anyhow::anyhow!("Failed to convert contract definition: {}", e))?;
 
-            let response = context
-                .sequencer
-                .add_declare_transaction(
-                    add_transaction::Declare::V1(add_transaction::DeclareV0V1V2 {
-                        version: tx.version,
-                        max_fee: tx.max_fee,
-                        signature: tx.signature,
-                        contract_class: ContractDefinition::Cairo(contract_definition),
-                        sender_address: tx.sender_address,
-                        nonce: tx.nonce,
-                        compiled_class_hash: None,
-                    }),
-                    input.token,
-                )
-                .await?;
+            let response = crate::submissions::add_declare_transaction(
+                &context,
+                &transaction,
+                add_transaction::Declare::V1(add_transaction::DeclareV0V1V2 {
+                    version: tx.version,
+                    max_fee: tx.max_fee,
+                    signature: tx.signature,
+                    contract_class: ContractDefinition::Cairo(contract_definition),
+                    sender_address: tx.sender_address,
+                    nonce: tx.nonce,
+                    compiled_class_hash: None,
+                }),
+                input.token,
+            )
+            .await?;
 
             Ok(Output {
                 transaction_hash: response.transaction_hash,
@@ -214,21 +231,21 @@ pub async fn add_declare_transaction(
                 .try_into()
                 .map_err(|e| anyhow::anyhow!("Failed to convert contract definition: {}", e))?;
 
-            let response = context
-                .sequencer
-                .add_declare_transaction(
-                    add_transaction::Declare::V2(add_transaction::DeclareV0V1V2 {
-                        version: tx.version,
-                        max_fee: tx.max_fee,
-                        signature: tx.signature,
-                        contract_class: ContractDefinition::Sierra(contract_definition),
-                        sender_address: tx.sender_address,
-                        nonce: tx.nonce,
-                        compiled_class_hash: Some(tx.compiled_class_hash),
-                    }),
-                    input.token,
-                )
-                .await?;
+            let response = crate::submissions::add_declare_transaction(
+                &context,
+                &transaction,
+                add_transaction::Declare::V2(add_transaction::DeclareV0V1V2 {
+                    version: tx.version,
+                    max_fee: tx.max_fee,
+                    signature: tx.signature,
+                    contract_class: ContractDefinition::Sierra(contract_definition),
+                    sender_address: tx.sender_address,
+                    nonce: tx.nonce,
+                    compiled_class_hash: Some(tx.compiled_class_hash),
+                }),
+                input.token,
+            )
+            .await?;
 
             Ok(Output {
                 transaction_hash: response.transaction_hash,
@@ -241,43 +258,50 @@ pub async fn add_declare_transaction(
                 .try_into()
                 .map_err(|e| anyhow::anyhow!("Failed to convert contract definition: {}", e))?;
 
-            let response = context
-                .sequencer
-                .add_declare_transaction(
-                    add_transaction::Declare::V3(add_transaction::DeclareV3 {
-                        signature: tx.signature,
-                        nonce: tx.nonce,
-                        nonce_data_availability_mode:
-                            pathfinder_common::transaction::DataAvailabilityMode::from(
-                                tx.nonce_data_availability_mode,
-                            )
-                            .into(),
-                        fee_data_availability_mode:
-                            pathfinder_common::transaction::DataAvailabilityMode::from(
-                                tx.fee_data_availability_mode,
-                            )
-                            .into(),
-                        resource_bounds: pathfinder_common::transaction::ResourceBounds::from(
-                            tx.resource_bounds,
+            let response = crate::submissions::add_declare_transaction(
+                &context,
+                &transaction,
+                add_transaction::Declare::V3(add_transaction::DeclareV3 {
+                    signature: tx.signature,
+                    nonce: tx.nonce,
+                    nonce_data_availability_mode:
+                        pathfinder_common::transaction::DataAvailabilityMode::from(
+                            tx.nonce_data_availability_mode,
                         )
                         .into(),
-                        tip: tx.tip,
-                        paymaster_data: tx.paymaster_data,
-                        contract_class: contract_definition,
-                        compiled_class_hash: tx.compiled_class_hash,
-                        sender_address: tx.sender_address,
-                        account_deployment_data: tx.account_deployment_data,
-                    }),
-                    input.token,
-                )
-                .await?;
+                    fee_data_availability_mode:
+                        pathfinder_common::transaction::DataAvailabilityMode::from(
+                            tx.fee_data_availability_mode,
+                        )
+                        .into(),
+                    resource_bounds: pathfinder_common::transaction::ResourceBounds::from(
+                        tx.resource_bounds,
+                    )
+                    .into(),
+                    tip: tx.tip,
+                    paymaster_data: tx.paymaster_data,
+                    contract_class: contract_definition,
+                    compiled_class_hash: tx.compiled_class_hash,
+                    sender_address: tx.sender_address,
+                    account_deployment_data: tx.account_deployment_data,
+                }),
+                input.token,
+            )
+            .await?;
 
             Ok(Output {
                 transaction_hash: response.transaction_hash,
                 class_hash: response.class_hash,
             })
         }
-    }
+    }?;
+
+    context.mempool.insert(
+        output.transaction_hash,
+        crate::mempool::sender_address(&transaction),
+    );
+
+    Ok(output)
 }
 
 impl crate::dto::serialize::SerializeForVersion for Output {
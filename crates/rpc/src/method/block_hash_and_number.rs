@@ -3,9 +3,10 @@ use pathfinder_storage::BlockId;
 
 use crate::context::RpcContext;
 
+#[derive(Debug, PartialEq, Eq)]
 pub struct Output {
-    number: pathfinder_common::BlockNumber,
-    hash: pathfinder_common::BlockHash,
+    pub number: pathfinder_common::BlockNumber,
+    pub hash: pathfinder_common::BlockHash,
 }
 
 crate::error::generate_rpc_error_subset!(Error: NoBlocks);
@@ -41,3 +42,14 @@ impl crate::dto::serialize::SerializeForVersion for Output {
         serializer.end()
     }
 }
+
+impl crate::dto::DeserializeForVersion for Output {
+    fn deserialize(value: crate::dto::Value) -> Result<Self, serde_json::Error> {
+        value.deserialize_map(|value| {
+            Ok(Self {
+                hash: pathfinder_common::BlockHash(value.deserialize("block_hash")?),
+                number: value.deserialize_serde("block_number")?,
+            })
+        })
+    }
+}
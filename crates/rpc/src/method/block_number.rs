@@ -3,7 +3,8 @@ use pathfinder_storage::BlockId;
 
 use crate::context::RpcContext;
 
-pub struct Output(pathfinder_common::BlockNumber);
+#[derive(Debug, PartialEq, Eq)]
+pub struct Output(pub pathfinder_common::BlockNumber);
 
 crate::error::generate_rpc_error_subset!(Error: NoBlocks);
 
@@ -35,3 +36,9 @@ impl crate::dto::serialize::SerializeForVersion for Output {
         serializer.serialize(&crate::dto::BlockNumber(self.0))
     }
 }
+
+impl crate::dto::DeserializeForVersion for Output {
+    fn deserialize(value: crate::dto::Value) -> Result<Self, serde_json::Error> {
+        value.deserialize_serde().map(Self)
+    }
+}
@@ -14,7 +14,9 @@ pub enum CallError {
     ContractError {
         revert_error: Option<String>,
         revert_error_stack: pathfinder_executor::ErrorStack,
+        reason: pathfinder_executor::FailureReason,
     },
+    ExecutionResourcesExceeded,
 }
 
 impl From<anyhow::Error> for CallError {
@@ -28,11 +30,12 @@ impl From<pathfinder_executor::CallError> for CallError {
         use pathfinder_executor::CallError::*;
         match value {
             ContractNotFound => Self::ContractNotFound,
-            InvalidMessageSelector => Self::Custom(anyhow::anyhow!("Invalid message selector")),
-            ContractError(error, error_stack) => Self::ContractError {
+            ContractError(error, error_stack, reason) => Self::ContractError {
                 revert_error: Some(format!("Execution error: {}", error)),
                 revert_error_stack: error_stack,
+                reason,
             },
+            ExecutionResourcesExceeded => Self::ExecutionResourcesExceeded,
             Internal(e) => Self::Internal(e),
             Custom(e) => Self::Custom(e),
         }
@@ -57,10 +60,13 @@ impl From<CallError> for ApplicationError {
             CallError::ContractError {
                 revert_error,
                 revert_error_stack,
+                reason,
             } => ApplicationError::ContractError {
                 revert_error,
                 revert_error_stack,
+                reason,
             },
+            CallError::ExecutionResourcesExceeded => ApplicationError::ExecutionResourcesExceeded,
             CallError::Internal(e) => ApplicationError::Internal(e),
             CallError::Custom(e) => ApplicationError::Custom(e),
         }
@@ -106,7 +112,8 @@ pub struct Output(pub Vec<CallResultValue>);
 
 pub async fn call(context: RpcContext, input: Input) -> Result<Output, CallError> {
     let span = tracing::Span::current();
-    let result = tokio::task::spawn_blocking(move || {
+    let timeout = context.config.execution_timeout;
+    let execution = tokio::task::spawn_blocking(move || {
         let _g = span.enter();
 
         let mut db = context
@@ -142,7 +149,10 @@ pub async fn call(context: RpcContext, input: Input) -> Result<Output, CallError
             pending,
             L1BlobDataAvailability::Disabled,
             context.config.custom_versioned_constants,
-        );
+        )
+        .with_limits(context.config.execution_limits)
+        .with_class_cache(context.class_cache.clone())
+        .with_stored_block_hashes();
 
         let result = pathfinder_executor::call(
             state,
@@ -152,9 +162,11 @@ pub async fn call(context: RpcContext, input: Input) -> Result<Output, CallError
         )?;
 
         Ok(result)
-    })
-    .await
-    .context("Executing call")?;
+    });
+    let result = crate::executor::with_timeout(timeout, execution)
+        .await
+        .ok_or(CallError::ExecutionResourcesExceeded)?
+        .context("Executing call")?;
 
     result.map(Output)
 }
@@ -455,6 +467,7 @@ mod tests {
                     transactions: vec![],
                     starknet_version: last_block_header.starknet_version,
                     l1_da_mode: L1DataAvailabilityMode::Calldata,
+                    l2_gas_price: Default::default(),
                 }
                 .into(),
                 state_update: state_update.into(),
@@ -600,7 +613,13 @@ mod tests {
                 block_id: BLOCK_5,
             };
             let error = call(context, input).await;
-            assert_matches::assert_matches!(error, Err(CallError::Custom(_)));
+            assert_matches::assert_matches!(
+                error,
+                Err(CallError::ContractError {
+                    reason: pathfinder_executor::FailureReason::EntryPointNotFound,
+                    ..
+                })
+            );
         }
 
         #[tokio::test]
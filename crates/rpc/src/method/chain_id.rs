@@ -2,7 +2,8 @@ use crate::context::RpcContext;
 
 crate::error::generate_rpc_error_subset!(Error);
 
-pub struct Output(pathfinder_common::ChainId);
+#[derive(Debug, PartialEq, Eq)]
+pub struct Output(pub pathfinder_common::ChainId);
 
 pub async fn chain_id(context: RpcContext) -> Result<Output, Error> {
     Ok(Output(context.chain_id))
@@ -16,3 +17,9 @@ impl crate::dto::serialize::SerializeForVersion for Output {
         serializer.serialize(&crate::dto::ChainId(&self.0))
     }
 }
+
+impl crate::dto::DeserializeForVersion for Output {
+    fn deserialize(value: crate::dto::Value) -> Result<Self, serde_json::Error> {
+        Ok(Self(pathfinder_common::ChainId(value.deserialize()?)))
+    }
+}
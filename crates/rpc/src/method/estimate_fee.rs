@@ -47,8 +47,9 @@ pub struct Output(Vec<pathfinder_executor::types::FeeEstimate>);
 
 pub async fn estimate_fee(context: RpcContext, input: Input) -> Result<Output, EstimateFeeError> {
     let span = tracing::Span::current();
+    let timeout = context.config.execution_timeout;
 
-    let result = tokio::task::spawn_blocking(move || {
+    let execution = tokio::task::spawn_blocking(move || {
         let _g = span.enter();
         let mut db = context
             .execution_storage
@@ -83,7 +84,9 @@ pub async fn estimate_fee(context: RpcContext, input: Input) -> Result<Output, E
             pending,
             L1BlobDataAvailability::Enabled,
             context.config.custom_versioned_constants,
-        );
+        )
+        .with_limits(context.config.execution_limits)
+        .with_stored_block_hashes();
 
         let skip_validate = input
             .simulation_flags
@@ -93,15 +96,23 @@ pub async fn estimate_fee(context: RpcContext, input: Input) -> Result<Output, E
         let transactions = input
             .request
             .into_iter()
-            .map(|tx| crate::executor::map_broadcasted_transaction(&tx, context.chain_id))
+            .map(|tx| {
+                crate::executor::map_broadcasted_transaction(
+                    &tx,
+                    context.chain_id,
+                    &context.storage,
+                )
+            })
             .collect::<Result<Vec<_>, _>>()?;
 
         let result = pathfinder_executor::estimate(state, transactions, skip_validate)?;
 
         Ok::<_, EstimateFeeError>(result)
-    })
-    .await
-    .context("Executing transaction")??;
+    });
+    let result = crate::executor::with_timeout(timeout, execution)
+        .await
+        .ok_or(EstimateFeeError::ExecutionResourcesExceeded)?
+        .context("Executing transaction")??;
 
     Ok(Output(result.into_iter().map(Into::into).collect()))
 }
@@ -115,7 +126,9 @@ pub enum EstimateFeeError {
         transaction_index: usize,
         error: String,
         error_stack: pathfinder_executor::ErrorStack,
+        reason: pathfinder_executor::FailureReason,
     },
+    ExecutionResourcesExceeded,
 }
 
 impl From<anyhow::Error> for EstimateFeeError {
@@ -132,11 +145,14 @@ impl From<pathfinder_executor::TransactionExecutionError> for EstimateFeeError {
                 transaction_index,
                 error,
                 error_stack,
+                reason,
             } => Self::TransactionExecutionError {
                 transaction_index,
                 error,
                 error_stack,
+                reason,
             },
+            ExecutionResourcesExceeded { .. } => Self::ExecutionResourcesExceeded,
             Internal(e) => Self::Internal(e),
             Custom(e) => Self::Custom(e),
         }
@@ -161,11 +177,16 @@ impl From<EstimateFeeError> for ApplicationError {
                 transaction_index,
                 error,
                 error_stack,
+                reason,
             } => ApplicationError::TransactionExecutionError {
                 transaction_index,
                 error,
                 error_stack,
+                reason,
             },
+            EstimateFeeError::ExecutionResourcesExceeded => {
+                ApplicationError::ExecutionResourcesExceeded
+            }
             EstimateFeeError::Internal(e) => ApplicationError::Internal(e),
             EstimateFeeError::Custom(e) => ApplicationError::Custom(e),
         }
@@ -686,4 +707,73 @@ mod tests {
             ])
         );
     }
+
+    #[tokio::test]
+    async fn later_transactions_see_state_of_earlier_ones() {
+        use starknet_gateway_test_fixtures::class_definitions::DUMMY_ACCOUNT_CLASS_HASH;
+
+        use crate::v02::types::request::{
+            BroadcastedDeployAccountTransaction,
+            BroadcastedDeployAccountTransactionV1,
+        };
+
+        let (context, last_block_header, _, _) = crate::test_setup::test_context().await;
+
+        // The address of the account deployed by `deploy_account`.
+        let new_account_address =
+            contract_address!("0x00798C1BFDAF2077F4900E37C8815AFFA8D217D46DB8A84C3FBA1838C8BD4A65");
+
+        let deploy_account = BroadcastedTransaction::DeployAccount(
+            BroadcastedDeployAccountTransaction::V1(BroadcastedDeployAccountTransactionV1 {
+                version: TransactionVersion::ONE,
+                max_fee: Fee::default(),
+                signature: vec![],
+                nonce: TransactionNonce::ZERO,
+                contract_address_salt: contract_address_salt!(
+                    "0x46c0d4abf0192a788aca261e58d7031576f7d8ea5229f452b0f23e691dd5971"
+                ),
+                constructor_calldata: vec![],
+                class_hash: DUMMY_ACCOUNT_CLASS_HASH,
+            }),
+        );
+        let invoke = BroadcastedTransaction::Invoke(BroadcastedInvokeTransaction::V1(
+            BroadcastedInvokeTransactionV1 {
+                nonce: transaction_nonce!("0x1"),
+                version: TransactionVersion::ONE,
+                max_fee: Fee::default(),
+                signature: vec![],
+                sender_address: new_account_address,
+                calldata: vec![
+                    CallParam(*pathfinder_executor::ETH_FEE_TOKEN_ADDRESS.get()),
+                    CallParam(EntryPoint::hashed(b"balanceOf").0),
+                    call_param!("1"),
+                    CallParam(*new_account_address.get()),
+                ],
+            },
+        ));
+
+        // The invoke is sent from the account deployed by the first transaction.
+        let input = Input {
+            request: vec![deploy_account, invoke.clone()],
+            simulation_flags: vec![],
+            block_id: BlockId::Number(last_block_header.number),
+        };
+        let result = estimate_fee(context.clone(), input).await.unwrap();
+        assert_eq!(result.0.len(), 2);
+
+        // On its own, the invoke fails because the account does not exist yet.
+        let input = Input {
+            request: vec![invoke],
+            simulation_flags: vec![],
+            block_id: BlockId::Number(last_block_header.number),
+        };
+        let error = estimate_fee(context, input).await.unwrap_err();
+        assert_matches::assert_matches!(
+            error,
+            EstimateFeeError::TransactionExecutionError {
+                transaction_index: 0,
+                ..
+            }
+        );
+    }
 }
@@ -58,7 +58,8 @@ pub async fn estimate_message_fee(
             pending,
             L1BlobDataAvailability::Enabled,
             context.config.custom_versioned_constants,
-        );
+        )
+        .with_stored_block_hashes();
 
         let transaction = create_executor_transaction(input, context.chain_id)?;
 
@@ -152,7 +153,9 @@ pub enum EstimateMessageFeeError {
     ContractError {
         revert_error: String,
         revert_error_stack: pathfinder_executor::ErrorStack,
+        reason: pathfinder_executor::FailureReason,
     },
+    ExecutionResourcesExceeded,
     Custom(anyhow::Error),
 }
 
@@ -167,11 +170,16 @@ impl From<pathfinder_executor::TransactionExecutionError> for EstimateMessageFee
         use pathfinder_executor::TransactionExecutionError::*;
         match c {
             ExecutionError {
-                error, error_stack, ..
+                error,
+                error_stack,
+                reason,
+                ..
             } => Self::ContractError {
                 revert_error: format!("Execution error: {}", error),
                 revert_error_stack: error_stack,
+                reason,
             },
+            ExecutionResourcesExceeded { .. } => Self::ExecutionResourcesExceeded,
             Internal(e) => Self::Internal(e),
             Custom(e) => Self::Custom(e),
         }
@@ -196,10 +204,15 @@ impl From<EstimateMessageFeeError> for ApplicationError {
             EstimateMessageFeeError::ContractError {
                 revert_error,
                 revert_error_stack,
+                reason,
             } => ApplicationError::ContractError {
                 revert_error: Some(revert_error),
                 revert_error_stack,
+                reason,
             },
+            EstimateMessageFeeError::ExecutionResourcesExceeded => {
+                ApplicationError::ExecutionResourcesExceeded
+            }
             EstimateMessageFeeError::Internal(e) => ApplicationError::Internal(e),
             EstimateMessageFeeError::Custom(e) => ApplicationError::Custom(e),
         }
@@ -5,7 +5,7 @@ use crate::context::RpcContext;
 
 #[derive(Debug, PartialEq, Eq)]
 pub struct Input {
-    block_id: BlockId,
+    pub block_id: BlockId,
 }
 
 impl crate::dto::DeserializeForVersion for Input {
@@ -18,10 +18,21 @@ impl crate::dto::DeserializeForVersion for Input {
     }
 }
 
+impl crate::dto::serialize::SerializeForVersion for Input {
+    fn serialize(
+        &self,
+        serializer: crate::dto::serialize::Serializer,
+    ) -> Result<crate::dto::serialize::Ok, crate::dto::serialize::Error> {
+        let mut serializer = serializer.serialize_struct()?;
+        serializer.serialize_field("block_id", &crate::dto::BlockId(&self.block_id))?;
+        serializer.end()
+    }
+}
+
 crate::error::generate_rpc_error_subset!(Error: BlockNotFound);
 
-#[derive(Debug)]
-pub struct Output(u64);
+#[derive(Debug, PartialEq, Eq)]
+pub struct Output(pub u64);
 
 pub async fn get_block_transaction_count(
     context: RpcContext,
@@ -78,6 +89,12 @@ impl crate::dto::serialize::SerializeForVersion for Output {
     }
 }
 
+impl crate::dto::DeserializeForVersion for Output {
+    fn deserialize(value: crate::dto::Value) -> Result<Self, serde_json::Error> {
+        value.deserialize_serde().map(Self)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use pathfinder_common::macro_prelude::*;
@@ -65,6 +65,10 @@ pub async fn get_block_with_receipts(context: RpcContext, input: Input) -> Resul
             .context("Fetching block header")?
             .ok_or(Error::BlockNotFound)?;
 
+        // Query the remaining data by number so that the block id is only resolved
+        // once, and every query is guaranteed to refer to the same block.
+        let block_id = header.number.into();
+
         let body = db
             .transaction_data_for_block(block_id)
             .context("Fetching transaction data")?
@@ -546,4 +550,49 @@ mod tests {
         });
         assert_eq!(output, expected);
     }
+
+    #[tokio::test]
+    async fn by_hash_matches_by_number() {
+        let context = RpcContext::for_tests();
+
+        let by_hash = get_block_with_receipts(
+            context.clone(),
+            Input {
+                block_id: BlockId::Hash(pathfinder_common::block_hash_bytes!(b"latest")),
+            },
+        )
+        .await
+        .unwrap()
+        .serialize(Serializer {
+            version: RpcVersion::V07,
+        })
+        .unwrap();
+
+        let by_number = get_block_with_receipts(
+            context,
+            Input {
+                block_id: BlockId::Number(pathfinder_common::BlockNumber::new_or_panic(2)),
+            },
+        )
+        .await
+        .unwrap()
+        .serialize(Serializer {
+            version: RpcVersion::V07,
+        })
+        .unwrap();
+
+        assert_eq!(by_hash, by_number);
+    }
+
+    #[tokio::test]
+    async fn block_not_found() {
+        let context = RpcContext::for_tests();
+        let input = Input {
+            block_id: BlockId::Number(pathfinder_common::BlockNumber::MAX),
+        };
+
+        let error = get_block_with_receipts(context, input).await;
+
+        assert_matches::assert_matches!(error, Err(Error::BlockNotFound));
+    }
 }
@@ -134,3 +134,27 @@ impl crate::dto::serialize::SerializeForVersion for Output {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::BlockNumber;
+    use serde_json::json;
+
+    use super::*;
+    use crate::dto::DeserializeForVersion;
+    use crate::RpcVersion;
+
+    #[rstest::rstest]
+    #[case::integer(json!([{"block_number": 123}]))]
+    #[case::decimal_string(json!([{"block_number": "123"}]))]
+    #[case::hex_string(json!([{"block_number": "0x7b"}]))]
+    #[case::named(json!({"block_id": {"block_number": "0x7b"}}))]
+    fn parsing_block_number(#[case] input: serde_json::Value) {
+        let input = Input::deserialize(crate::dto::Value::new(input, RpcVersion::V07)).unwrap();
+
+        assert_eq!(
+            input.block_id,
+            BlockId::Number(BlockNumber::new_or_panic(123))
+        );
+    }
+}
@@ -7,8 +7,8 @@ crate::error::generate_rpc_error_subset!(Error: BlockNotFound, ContractNotFound)
 
 #[derive(Debug, PartialEq, Eq)]
 pub struct Input {
-    block_id: BlockId,
-    contract_address: ContractAddress,
+    pub block_id: BlockId,
+    pub contract_address: ContractAddress,
 }
 
 impl crate::dto::DeserializeForVersion for Input {
@@ -22,8 +22,23 @@ impl crate::dto::DeserializeForVersion for Input {
     }
 }
 
-#[derive(Debug)]
-pub struct Output(ClassHash);
+impl crate::dto::serialize::SerializeForVersion for Input {
+    fn serialize(
+        &self,
+        serializer: crate::dto::serialize::Serializer,
+    ) -> Result<crate::dto::serialize::Ok, crate::dto::serialize::Error> {
+        let mut serializer = serializer.serialize_struct()?;
+        serializer.serialize_field("block_id", &crate::dto::BlockId(&self.block_id))?;
+        serializer.serialize_field(
+            "contract_address",
+            &crate::dto::Felt(&self.contract_address.0),
+        )?;
+        serializer.end()
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Output(pub ClassHash);
 
 pub async fn get_class_hash_at(context: RpcContext, input: Input) -> Result<Output, Error> {
     let span = tracing::Span::current();
@@ -78,6 +93,12 @@ impl crate::dto::serialize::SerializeForVersion for Output {
     }
 }
 
+impl crate::dto::DeserializeForVersion for Output {
+    fn deserialize(value: crate::dto::Value) -> Result<Self, serde_json::Error> {
+        Ok(Self(ClassHash(value.deserialize()?)))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use assert_matches::assert_matches;
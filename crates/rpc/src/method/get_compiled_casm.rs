@@ -0,0 +1,207 @@
+use anyhow::Context;
+use pathfinder_common::ClassHash;
+
+use crate::context::RpcContext;
+use crate::dto;
+use crate::dto::serialize::SerializeForVersion;
+use crate::v02::types::ContractClass;
+
+crate::error::generate_rpc_error_subset!(Error: ClassHashNotFound, CompilationFailed);
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Input {
+    class_hash: ClassHash,
+}
+
+impl crate::dto::DeserializeForVersion for Input {
+    fn deserialize(value: crate::dto::Value) -> Result<Self, serde_json::Error> {
+        value.deserialize_map(|value| {
+            Ok(Self {
+                class_hash: ClassHash(value.deserialize("class_hash")?),
+            })
+        })
+    }
+}
+
+/// The CASM as produced by the compiler.
+#[derive(Debug, PartialEq)]
+pub struct Output(serde_json::Value);
+
+/// Get the CASM compiled from the Sierra class with the given hash.
+///
+/// The CASM stored for declared classes is returned as is. Sierra classes
+/// stored without their CASM are compiled by this node, and the result is kept
+/// in the compiled class cache. Cairo 0 classes have no CASM, and are reported
+/// as not found.
+pub async fn get_compiled_casm(context: RpcContext, input: Input) -> Result<Output, Error> {
+    let span = tracing::Span::current();
+    let jh = tokio::task::spawn_blocking(move || -> Result<Output, Error> {
+        let _g = span.enter();
+        let mut db = context
+            .storage
+            .connection()
+            .context("Opening database connection")?;
+        let tx = db.transaction().context("Creating database transaction")?;
+
+        let stored = tx
+            .casm_definition(input.class_hash)
+            .context("Fetching compiled class definition")?;
+        let casm_definition = match stored {
+            Some(casm_definition) => casm_definition,
+            None => {
+                let definition = tx
+                    .class_definition(input.class_hash)
+                    .context("Fetching class definition")?
+                    .ok_or(Error::ClassHashNotFound)?;
+                drop(tx);
+
+                let class = match ContractClass::from_definition_bytes(&definition)
+                    .context("Parsing class definition")?
+                {
+                    ContractClass::Sierra(class) => class,
+                    ContractClass::Cairo(_) => return Err(Error::ClassHashNotFound),
+                };
+
+                crate::executor::compile_to_casm(&context.storage, &class).map_err(|error| {
+                    tracing::debug!(class_hash=%input.class_hash, ?error, "Compiling class failed");
+                    Error::CompilationFailed
+                })?
+            }
+        };
+
+        let casm = serde_json::from_slice(&casm_definition)
+            .context("Parsing compiled class definition")?;
+
+        Ok(Output(casm))
+    });
+
+    jh.await.context("Reading compiled class from database")?
+}
+
+impl SerializeForVersion for Output {
+    fn serialize(
+        &self,
+        serializer: dto::serialize::Serializer,
+    ) -> Result<dto::serialize::Ok, dto::serialize::Error> {
+        serializer.serialize(&self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+    use pathfinder_common::macro_prelude::*;
+    use pathfinder_common::SierraHash;
+    use pathfinder_storage::StorageBuilder;
+    use serde_json::json;
+    use starknet_gateway_test_fixtures::class_definitions::{
+        CAIRO_1_1_0_BALANCE_CASM_JSON,
+        CAIRO_1_1_0_BALANCE_SIERRA_JSON,
+        CONTRACT_DEFINITION,
+        CONTRACT_DEFINITION_CLASS_HASH,
+    };
+
+    use super::*;
+    use crate::dto::DeserializeForVersion;
+    use crate::RpcVersion;
+
+    #[rstest::rstest]
+    #[case::positional(json!(["0xabcd"]))]
+    #[case::named(json!({"class_hash": "0xabcd"}))]
+    fn parsing(#[case] input: serde_json::Value) {
+        let expected = Input {
+            class_hash: class_hash!("0xabcd"),
+        };
+
+        let input = Input::deserialize(crate::dto::Value::new(input, RpcVersion::V08)).unwrap();
+
+        assert_eq!(input, expected);
+    }
+
+    fn context_with_class(insert: impl FnOnce(&pathfinder_storage::Transaction<'_>)) -> RpcContext {
+        let storage = StorageBuilder::in_memory().unwrap();
+        let mut db = storage.connection().unwrap();
+        let tx = db.transaction().unwrap();
+        insert(&tx);
+        tx.commit().unwrap();
+
+        RpcContext::for_tests().with_storage(storage)
+    }
+
+    #[tokio::test]
+    async fn stored() {
+        let class_hash = class_hash_bytes!(b"balance");
+        let context = context_with_class(|tx| {
+            tx.insert_sierra_class(
+                &SierraHash(class_hash.0),
+                CAIRO_1_1_0_BALANCE_SIERRA_JSON,
+                &casm_hash_bytes!(b"balance casm"),
+                CAIRO_1_1_0_BALANCE_CASM_JSON,
+            )
+            .unwrap()
+        });
+
+        let output = get_compiled_casm(context, Input { class_hash })
+            .await
+            .unwrap();
+
+        let expected = serde_json::from_slice(CAIRO_1_1_0_BALANCE_CASM_JSON).unwrap();
+        assert_eq!(output, Output(expected));
+    }
+
+    #[tokio::test]
+    async fn compiled_on_demand() {
+        let class_hash = ContractClass::from_definition_bytes(CAIRO_1_1_0_BALANCE_SIERRA_JSON)
+            .unwrap()
+            .class_hash()
+            .unwrap()
+            .hash();
+        // Stores the definition without its CASM.
+        let context = context_with_class(|tx| {
+            tx.insert_cairo_class(class_hash, CAIRO_1_1_0_BALANCE_SIERRA_JSON)
+                .unwrap()
+        });
+
+        let output = get_compiled_casm(context.clone(), Input { class_hash })
+            .await
+            .unwrap();
+
+        assert!(output.0["bytecode"].is_array());
+        // RPC requests don't fill the compiled class cache.
+        let mut db = context.storage.connection().unwrap();
+        let tx = db.transaction().unwrap();
+        let cached = tx.compiled_class(SierraHash(class_hash.0)).unwrap();
+        assert!(cached.is_none());
+    }
+
+    #[tokio::test]
+    async fn cairo_class_has_no_casm() {
+        let context = context_with_class(|tx| {
+            tx.insert_cairo_class(CONTRACT_DEFINITION_CLASS_HASH, CONTRACT_DEFINITION)
+                .unwrap()
+        });
+
+        let error = get_compiled_casm(
+            context,
+            Input {
+                class_hash: CONTRACT_DEFINITION_CLASS_HASH,
+            },
+        )
+        .await
+        .unwrap_err();
+
+        assert_matches!(error, Error::ClassHashNotFound);
+    }
+
+    #[tokio::test]
+    async fn missing_class() {
+        let context = RpcContext::for_tests();
+        let input = Input {
+            class_hash: class_hash_bytes!(b"missing"),
+        };
+
+        let error = get_compiled_casm(context, input).await.unwrap_err();
+
+        assert_matches!(error, Error::ClassHashNotFound);
+    }
+}
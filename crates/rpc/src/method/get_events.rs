@@ -1,3 +1,5 @@
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
 use std::str::FromStr;
 
 use anyhow::Context;
@@ -10,14 +12,17 @@ use pathfinder_common::{
     EventKey,
     TransactionHash,
 };
-use pathfinder_storage::{EventFilterError, EVENT_KEY_FILTER_LIMIT};
+use pathfinder_crypto::Felt;
+use pathfinder_storage::{EventFilterError, Storage, EVENT_KEY_FILTER_LIMIT};
 use starknet_gateway_types::reply::PendingBlock;
 use tokio::task::JoinHandle;
 
+use crate::abi_registry::{AbiRegistry, DecodedEvent};
 use crate::context::RpcContext;
-use crate::dto::serialize::{self, SerializeForVersion, Serializer};
+use crate::dto::serialize::{self, ResponseBudget, SerializeForVersion, Serializer};
 use crate::dto::{self};
 use crate::pending::PendingData;
+use crate::RpcVersion;
 
 pub const EVENT_PAGE_SIZE_LIMIT: usize = 1024;
 
@@ -29,6 +34,7 @@ pub enum GetEventsError {
     PageSizeTooBig,
     InvalidContinuationToken,
     TooManyKeysInFilter { limit: usize, requested: usize },
+    ChainReorganized { at_block_hash: BlockHash },
 }
 
 impl From<anyhow::Error> for GetEventsError {
@@ -48,13 +54,22 @@ impl From<GetEventsError> for crate::error::ApplicationError {
             GetEventsError::TooManyKeysInFilter { limit, requested } => {
                 Self::TooManyKeysInFilter { limit, requested }
             }
+            GetEventsError::ChainReorganized { at_block_hash } => {
+                Self::ChainReorganized { at_block_hash }
+            }
         }
     }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct GetEventsInput {
-    filter: EventFilter,
+    pub filter: EventFilter,
+    /// Whether to decode events using the registered ABIs.
+    pub decode: bool,
+    /// Pins the query to the chain containing this block: only events up to
+    /// this block are returned, and the query fails if it is no longer
+    /// canonical.
+    pub at_block_hash: Option<BlockHash>,
 }
 
 impl crate::dto::DeserializeForVersion for GetEventsInput {
@@ -62,6 +77,10 @@ impl crate::dto::DeserializeForVersion for GetEventsInput {
         value.deserialize_map(|value| {
             Ok(Self {
                 filter: value.deserialize("filter")?,
+                decode: value
+                    .deserialize_optional_serde("decode")?
+                    .unwrap_or_default(),
+                at_block_hash: value.deserialize_optional("at_block_hash")?.map(BlockHash),
             })
         })
     }
@@ -126,7 +145,16 @@ pub async fn get_events(
 
     use BlockId::*;
 
-    let request = input.filter;
+    let mut request = input.filter;
+    let at_block_hash = input.at_block_hash;
+    if at_block_hash.is_some() && matches!(request.to_block, Some(Pending)) {
+        // The pending block is newer than any block a query can be pinned to.
+        request.to_block = Some(Latest);
+    }
+    let decoding = input
+        .decode
+        .then(|| (context.abis.clone(), context.storage.clone()));
+    let max_response_size = context.config.max_response_size;
 
     let continuation_token = match &request.continuation_token {
         Some(s) => Some(
@@ -156,6 +184,7 @@ pub async fn get_events(
 
     // blocking task to perform database event query
     let span = tracing::Span::current();
+    // Also returns the pending block's number if pending events were queried.
     let db_events: JoinHandle<Result<_, GetEventsError>> = tokio::task::spawn_blocking(move || {
         let _g = span.enter();
         let mut connection = storage
@@ -166,20 +195,33 @@ pub async fn get_events(
             .transaction()
             .context("Creating database transaction")?;
 
-        // Handle the trivial (1), (2) and (4a) cases.
+        // Checked first, so that a token from a block which was reorged out
+        // together with the anchor is reported as a reorg.
+        let anchor = at_block_hash
+            .map(|hash| anchor_block_number(&transaction, hash))
+            .transpose()?;
+
+        if let Some(token) = &continuation_token {
+            token.check_not_reorged(&transaction)?;
+        }
+
+        // Handle the trivial (1), (2) and (4a) cases. A pinned query never
+        // reaches the pending block, so querying from it yields nothing.
         match (&request.from_block, &request.to_block) {
-            (Some(Pending), id) if !matches!(id, Some(Pending) | None) => {
-                return Ok(GetEventsResult {
+            (Some(Pending), id) if anchor.is_some() || !matches!(id, Some(Pending) | None) => {
+                let events = GetEventsResult {
                     events: Vec::new(),
                     continuation_token: None,
-                });
+                };
+                return Ok((events, None));
             }
             (Some(Pending), Some(Pending) | None) => {
                 let pending = context
                     .pending_data
                     .get(&transaction)
                     .context("Querying pending data")?;
-                return get_pending_events(&request, &pending, continuation_token);
+                let events = get_pending_events(&request, &pending, continuation_token)?;
+                return Ok((events, Some(pending.number)));
             }
             (Some(BlockId::Number(from_block)), Some(BlockId::Pending)) => {
                 let pending = context
@@ -189,10 +231,11 @@ pub async fn get_events(
 
                 // `from_block` is larger than or equal to pending block's number
                 if from_block >= &pending.number {
-                    return Ok(GetEventsResult {
+                    let events = GetEventsResult {
                         events: Vec::new(),
                         continuation_token: None,
-                    });
+                    };
+                    return Ok((events, None));
                 }
             }
             _ => {}
@@ -200,6 +243,10 @@ pub async fn get_events(
 
         let from_block = map_from_block_to_number(&transaction, request.from_block)?;
         let to_block = map_to_block_to_number(&transaction, request.to_block)?;
+        let to_block = match anchor {
+            Some(anchor) => Some(to_block.map_or(anchor, |to_block| to_block.min(anchor))),
+            None => to_block,
+        };
 
         // Handle cases (3) and (4) where `from_block` is non-pending.
 
@@ -228,23 +275,37 @@ pub async fn get_events(
                 EventFilterError::PageSizeTooSmall => GetEventsError::Custom(e.into()),
             })?;
 
+        let continuation_token_after_page = match page.continuation_token {
+            Some(token) => {
+                let block_hash = transaction
+                    .block_id(token.block_number.into())
+                    .context("Querying continuation token block")?
+                    .map(|(_, hash)| hash);
+                Some(
+                    ContinuationToken {
+                        block_number: token.block_number,
+                        offset: token.offset,
+                        block_hash,
+                    }
+                    .to_string(),
+                )
+            }
+            None => None,
+        };
+
         let mut events = GetEventsResult {
             events: page.events.into_iter().map(|e| e.into()).collect(),
-            continuation_token: page.continuation_token.map(|token| {
-                ContinuationToken {
-                    block_number: token.block_number,
-                    offset: token.offset,
-                }
-                .to_string()
-            }),
+            continuation_token: continuation_token_after_page,
         };
 
         // Append pending data if required.
+        let mut pending_number = None;
         if events.continuation_token.is_none() && matches!(request.to_block, Some(Pending)) {
             let pending = context
                 .pending_data
                 .get(&transaction)
                 .context("Querying pending data")?;
+            pending_number = Some(pending.number);
 
             if events.events.len() < request.chunk_size {
                 let amount = request.chunk_size - events.events.len();
@@ -277,6 +338,7 @@ pub async fn get_events(
                     let continuation_token = ContinuationToken {
                         block_number: pending.number,
                         offset: current_offset + amount,
+                        block_hash: None,
                     };
                     Some(continuation_token.to_string())
                 };
@@ -287,18 +349,127 @@ pub async fn get_events(
                     ContinuationToken {
                         block_number: pending.number,
                         offset: 0,
+                        block_hash: None,
                     }
                     .to_string(),
                 );
             }
         }
 
-        Ok(events)
+        Ok((events, pending_number))
     });
 
-    db_events
+    let (mut result, pending_number) = db_events
         .await
-        .context("Database read panic or shutting down")?
+        .context("Database read panic or shutting down")??;
+
+    if let Some((abis, storage)) = decoding {
+        result.events = decode_events(abis, storage, result.events).await?;
+    }
+
+    // Events are serialized the same way by all versions.
+    let budget = ResponseBudget::new(RpcVersion::V07, max_response_size);
+    apply_response_budget(&mut result, budget, continuation_token, pending_number)?;
+
+    Ok(result)
+}
+
+/// Cuts the page short once the serialized size of its events exceeds the
+/// budget, pointing the continuation token at the first event left out.
+fn apply_response_budget(
+    result: &mut GetEventsResult,
+    mut budget: ResponseBudget,
+    start: Option<ContinuationToken>,
+    pending_number: Option<BlockNumber>,
+) -> anyhow::Result<()> {
+    let mut cut = None;
+    for (i, event) in result.events.iter().enumerate() {
+        if !budget.try_add(event).context("Serializing event")? {
+            cut = Some(i);
+            break;
+        }
+    }
+    let Some(cut) = cut else {
+        return Ok(());
+    };
+
+    let event = &result.events[cut];
+    let block_number = event
+        .block_number
+        .or(pending_number)
+        .context("Pending event without a pending block")?;
+    // The token counts the matching events of the block, starting from where
+    // this page started if it is the same block.
+    let preceding = result.events[..cut]
+        .iter()
+        .filter(|preceding| preceding.block_number == event.block_number)
+        .count();
+    let start_offset = match start {
+        Some(start) if start.block_number == block_number => start.offset,
+        _ => 0,
+    };
+
+    result.continuation_token = Some(
+        ContinuationToken {
+            block_number,
+            offset: start_offset + preceding,
+            block_hash: event.block_hash,
+        }
+        .to_string(),
+    );
+    result.events.truncate(cut);
+
+    Ok(())
+}
+
+/// Decodes the events emitted by contracts with a registered ABI.
+async fn decode_events(
+    abis: AbiRegistry,
+    storage: Storage,
+    mut events: Vec<EmittedEvent>,
+) -> anyhow::Result<Vec<EmittedEvent>> {
+    let span = tracing::Span::current();
+    tokio::task::spawn_blocking(move || {
+        let _g = span.enter();
+        let mut connection = storage
+            .connection()
+            .context("Opening database connection")?;
+        let transaction = connection
+            .transaction()
+            .context("Creating database transaction")?;
+
+        // Class hashes are only needed if ABIs are registered by class hash.
+        let class_abis = abis.has_class_abis();
+        let mut class_hashes = HashMap::new();
+
+        for event in &mut events {
+            let class_hash = if class_abis {
+                // Pending events are looked up at the latest block, which misses
+                // contracts deployed in the pending block.
+                match class_hashes.entry((event.from_address, event.block_number)) {
+                    Entry::Occupied(entry) => *entry.get(),
+                    Entry::Vacant(entry) => {
+                        let block = event.block_number.map_or(BlockId::Latest, BlockId::Number);
+                        *entry.insert(
+                            transaction
+                                .contract_class_hash(block, event.from_address)
+                                .context("Querying contract class hash")?,
+                        )
+                    }
+                }
+            } else {
+                None
+            };
+
+            let keys = event.keys.iter().map(|key| key.0).collect::<Vec<_>>();
+            let data = event.data.iter().map(|data| data.0).collect::<Vec<_>>();
+            event.decoded = abis.decode(event.from_address, class_hash, &keys, &data);
+        }
+
+        Ok(events)
+    })
+    .await
+    .context("Database read panic or shutting down")?
 }
 
 // Handle the case when we're querying events exclusively from the pending
@@ -337,6 +508,7 @@ fn get_pending_events(
             ContinuationToken {
                 block_number: pending.number,
                 offset: current_offset + request.chunk_size,
+                block_hash: None,
             }
             .to_string(),
         )
@@ -374,6 +546,19 @@ fn map_to_block_to_number(
     }
 }
 
+/// The number of the block a query is pinned to, as long as the block is part
+/// of the canonical chain. Pages of the query are read from the same chain as
+/// long as this succeeds, since the query doesn't go past this block.
+fn anchor_block_number(
+    tx: &pathfinder_storage::Transaction<'_>,
+    at_block_hash: BlockHash,
+) -> Result<BlockNumber, GetEventsError> {
+    tx.block_id(at_block_hash.into())
+        .context("Querying anchor block")?
+        .map(|(number, _)| number)
+        .ok_or(GetEventsError::ChainReorganized { at_block_hash })
+}
+
 // Maps `from_block` BlockId to a block number which can be used by the events
 // query.
 //
@@ -459,6 +644,7 @@ fn append_pending_events(
             block_hash: None,
             block_number: None,
             transaction_hash: tx_hash,
+            decoded: None,
         });
 
     dst.extend(pending_events);
@@ -470,41 +656,89 @@ fn append_pending_events(
     is_last_page
 }
 
+/// Points to the `offset`-th matching event of a block.
+///
+/// Tokens only depend on the block and the filter, so they remain valid across
+/// node restarts. Tokens pointing into a block in the database also carry its
+/// hash, which is used to reject the token once that block has been reorged
+/// out. Tokens for the pending block, and tokens issued before the hash was
+/// added, omit it.
 #[derive(Clone, Copy, Debug, PartialEq)]
 struct ContinuationToken {
     block_number: BlockNumber,
     offset: usize,
+    block_hash: Option<BlockHash>,
 }
 
 impl FromStr for ContinuationToken {
     type Err = ParseContinuationTokenError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if let Some((block_number, offset)) = s.split_once('-') {
-            let block_number = block_number
-                .parse::<u64>()
-                .map_err(|_| ParseContinuationTokenError)?;
-            let offset = offset.parse().map_err(|_| ParseContinuationTokenError)?;
+        let mut parts = s.split('-');
 
-            let block_number = BlockNumber::new(block_number).ok_or(ParseContinuationTokenError)?;
+        let (Some(block_number), Some(offset)) = (parts.next(), parts.next()) else {
+            return Err(ParseContinuationTokenError);
+        };
+        let block_number = block_number
+            .parse::<u64>()
+            .map_err(|_| ParseContinuationTokenError)?;
+        let block_number = BlockNumber::new(block_number).ok_or(ParseContinuationTokenError)?;
+        let offset = offset.parse().map_err(|_| ParseContinuationTokenError)?;
+
+        let block_hash = match parts.next() {
+            Some(hash) if hash.starts_with("0x") => Felt::from_hex_str(hash)
+                .map(BlockHash)
+                .map(Some)
+                .map_err(|_| ParseContinuationTokenError)?,
+            Some(_) => return Err(ParseContinuationTokenError),
+            None => None,
+        };
 
-            Ok(ContinuationToken {
-                block_number,
-                offset,
-            })
-        } else {
-            Err(ParseContinuationTokenError)
+        if parts.next().is_some() {
+            return Err(ParseContinuationTokenError);
         }
+
+        Ok(ContinuationToken {
+            block_number,
+            offset,
+            block_hash,
+        })
     }
 }
 
 impl std::fmt::Display for ContinuationToken {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}-{}", self.block_number.get(), self.offset)
+        write!(f, "{}-{}", self.block_number.get(), self.offset)?;
+        if let Some(block_hash) = self.block_hash {
+            write!(f, "-{}", block_hash.0.to_hex_str())?;
+        }
+        Ok(())
     }
 }
 
 impl ContinuationToken {
+    /// Fails if the token carries a block hash which no longer matches the
+    /// canonical block at its height, i.e. the block has been reorged out.
+    fn check_not_reorged(
+        &self,
+        tx: &pathfinder_storage::Transaction<'_>,
+    ) -> Result<(), GetEventsError> {
+        let Some(block_hash) = self.block_hash else {
+            return Ok(());
+        };
+
+        let canonical = tx
+            .block_id(self.block_number.into())
+            .context("Querying continuation token block")?
+            .map(|(_, hash)| hash);
+
+        if canonical == Some(block_hash) {
+            Ok(())
+        } else {
+            Err(GetEventsError::InvalidContinuationToken)
+        }
+    }
+
     fn offset_in_block(&self, block_number: BlockNumber) -> Result<usize, GetEventsError> {
         use std::cmp::Ordering;
         match Ord::cmp(&self.block_number, &block_number) {
@@ -548,6 +782,8 @@ pub struct EmittedEvent {
     /// [`None`] for pending events.
     pub block_number: Option<BlockNumber>,
     pub transaction_hash: TransactionHash,
+    /// Set if decoding was requested and the event matches its contract's ABI.
+    pub decoded: Option<DecodedEvent>,
 }
 
 impl From<pathfinder_storage::EmittedEvent> for EmittedEvent {
@@ -559,6 +795,7 @@ impl From<pathfinder_storage::EmittedEvent> for EmittedEvent {
             block_hash: Some(event.block_hash),
             block_number: Some(event.block_number),
             transaction_hash: event.transaction_hash,
+            decoded: None,
         }
     }
 }
@@ -583,6 +820,7 @@ impl SerializeForVersion for EmittedEvent {
             .serialize_optional("block_hash", self.block_hash.as_ref().map(dto::BlockHash))?;
         serializer.serialize_optional("block_number", self.block_number.map(dto::BlockNumber))?;
         serializer.serialize_field("transaction_hash", &dto::TxnHash(&self.transaction_hash))?;
+        serializer.serialize_optional("decoded", self.decoded.as_ref())?;
 
         serializer.end()
     }
@@ -623,14 +861,14 @@ mod tests {
         "address":"0x1",
         "keys":[["0x2"],[]],
         "chunk_size":3,
-        "continuation_token":"4"}]), true
+        "continuation_token":"4"}, true, "0x5"]), true
     )]
     #[case::named_with_optionals(json!({"filter":{
         "from_block":{"block_number":0},
         "to_block":"latest",
         "address":"0x1","keys":[["0x2"],[]],
         "chunk_size":3,
-        "continuation_token":"4"}}), true
+        "continuation_token":"4"}, "decode":true, "at_block_hash":"0x5"}), true
     )]
     #[case::positional_without_optionals(json!([{"chunk_size":5}]), false)]
     #[case::named_without_optionals(json!({"filter":{"chunk_size":5}}), false)]
@@ -650,7 +888,11 @@ mod tests {
                 ..Default::default()
             }
         };
-        let expected = GetEventsInput { filter };
+        let expected = GetEventsInput {
+            filter,
+            decode: with_optionals,
+            at_block_hash: with_optionals.then_some(block_hash!("0x5")),
+        };
 
         let input =
             GetEventsInput::deserialize(crate::dto::Value::new(input, RpcVersion::V07)).unwrap();
@@ -678,13 +920,30 @@ mod tests {
             Err(ParseContinuationTokenError)
         );
 
+        assert_matches!(
+            "1234-5678-0x12-0x34".parse::<ContinuationToken>(),
+            Err(ParseContinuationTokenError)
+        );
+
         assert_eq!(
             "1234-4567".parse::<ContinuationToken>().unwrap(),
             ContinuationToken {
                 block_number: BlockNumber::new_or_panic(1234),
-                offset: 4567
+                offset: 4567,
+                block_hash: None,
             }
         );
+
+        let token = ContinuationToken {
+            block_number: BlockNumber::new_or_panic(1234),
+            offset: 4567,
+            block_hash: Some(block_hash!("0xabc")),
+        };
+        assert_eq!(token.to_string(), "1234-4567-0xabc");
+        assert_eq!(
+            "1234-4567-0xabc".parse::<ContinuationToken>().unwrap(),
+            token
+        );
     }
 
     fn setup() -> (RpcContext, Vec<EmittedEvent>) {
@@ -717,6 +976,8 @@ mod tests {
                 chunk_size: test_utils::NUM_EVENTS,
                 ..Default::default()
             },
+            decode: false,
+            at_block_hash: None,
         };
         let result = get_events(context, input).await.unwrap();
 
@@ -729,6 +990,45 @@ mod tests {
         );
     }
 
+    /// Pages through all events matching `input`, returning them along with
+    /// the number of pages.
+    async fn all_pages(
+        context: RpcContext,
+        mut input: GetEventsInput,
+    ) -> (Vec<EmittedEvent>, usize) {
+        let mut events = Vec::new();
+        let mut pages = 0;
+        loop {
+            let result = get_events(context.clone(), input.clone()).await.unwrap();
+            events.extend(result.events);
+            pages += 1;
+            match result.continuation_token {
+                Some(token) => input.filter.continuation_token = Some(token),
+                None => return (events, pages),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn response_size_limit() {
+        let (mut context, events) = setup();
+        // Only a single event fits.
+        context.config.max_response_size = Some(1.try_into().unwrap());
+
+        let input = GetEventsInput {
+            filter: EventFilter {
+                chunk_size: test_utils::NUM_EVENTS,
+                ..Default::default()
+            },
+            decode: false,
+            at_block_hash: None,
+        };
+        let (paged, pages) = all_pages(context, input).await;
+
+        assert_eq!(paged, events);
+        assert_eq!(pages, test_utils::NUM_EVENTS);
+    }
+
     #[tokio::test]
     async fn get_events_with_fully_specified_filter() {
         let (context, events) = setup();
@@ -748,11 +1048,57 @@ mod tests {
                 chunk_size: test_utils::NUM_EVENTS,
                 continuation_token: None,
             },
+            decode: false,
+            at_block_hash: None,
         };
         let result = get_events(context.clone(), input.clone()).await.unwrap();
         assert_eq!(result, expected_result);
     }
 
+    #[tokio::test]
+    async fn decoding() {
+        use crate::abi_registry::AbiTarget;
+
+        let abis = AbiRegistry::default();
+        abis.register(
+            AbiTarget::Contract(contract_address!("0x1")),
+            r#"[{"type": "event", "name": "Transfer", "keys": [], "data": [
+                {"name": "amount", "type": "felt"}
+            ]}]"#,
+        )
+        .unwrap();
+
+        let event = EmittedEvent {
+            data: vec![event_data!("0x5")],
+            keys: vec![EventKey(
+                pathfinder_common::EntryPoint::hashed(b"Transfer").0,
+            )],
+            from_address: contract_address!("0x1"),
+            block_hash: None,
+            block_number: None,
+            transaction_hash: transaction_hash!("0x1"),
+            decoded: None,
+        };
+        let unregistered = EmittedEvent {
+            from_address: contract_address!("0x2"),
+            ..event.clone()
+        };
+        let storage = pathfinder_storage::StorageBuilder::in_memory().unwrap();
+
+        let events = decode_events(abis, storage, vec![event, unregistered])
+            .await
+            .unwrap();
+
+        assert_eq!(
+            events[0].decoded,
+            Some(DecodedEvent {
+                name: "Transfer".to_owned(),
+                fields: serde_json::Map::from_iter([("amount".to_owned(), json!("0x5"))]),
+            })
+        );
+        assert_eq!(events[1].decoded, None);
+    }
+
     #[tokio::test]
     async fn get_events_by_block() {
         let (context, events) = setup();
@@ -765,6 +1111,8 @@ mod tests {
                 chunk_size: test_utils::NUM_EVENTS,
                 ..Default::default()
             },
+            decode: false,
+            at_block_hash: None,
         };
 
         let result = get_events(context, input).await.unwrap();
@@ -792,6 +1140,8 @@ mod tests {
                 chunk_size: test_utils::NUM_EVENTS,
                 ..Default::default()
             },
+            decode: false,
+            at_block_hash: None,
         };
 
         let result = get_events(context, input).await.unwrap();
@@ -816,6 +1166,8 @@ mod tests {
                 chunk_size: pathfinder_storage::EVENT_PAGE_SIZE_LIMIT + 1,
                 ..Default::default()
             },
+            decode: false,
+            at_block_hash: None,
         };
         let error = get_events(context, input).await.unwrap_err();
 
@@ -841,6 +1193,8 @@ mod tests {
                 chunk_size: 10,
                 ..Default::default()
             },
+            decode: false,
+            at_block_hash: None,
         };
         let error = get_events(context, input).await.unwrap_err();
 
@@ -867,13 +1221,15 @@ mod tests {
                 chunk_size: 1,
                 ..Default::default()
             },
+            decode: false,
+            at_block_hash: None,
         };
         let result = get_events(context.clone(), input).await.unwrap();
         assert_eq!(
             result,
             GetEventsResult {
                 events: expected_events[..1].to_vec(),
-                continuation_token: Some("0-1".to_string()),
+                continuation_token: Some("0-1-0xaaa".to_string()),
             }
         );
 
@@ -881,16 +1237,18 @@ mod tests {
             filter: EventFilter {
                 keys: keys_for_expected_events.clone(),
                 chunk_size: 2,
-                continuation_token: Some("0-1".to_string()),
+                continuation_token: Some("0-1-0xaaa".to_string()),
                 ..Default::default()
             },
+            decode: false,
+            at_block_hash: None,
         };
         let result = get_events(context.clone(), input).await.unwrap();
         assert_eq!(
             result,
             GetEventsResult {
                 events: expected_events[1..3].to_vec(),
-                continuation_token: Some("3-0".to_string()),
+                continuation_token: Some("3-0-0xaaaaaa".to_string()),
             }
         );
 
@@ -898,9 +1256,11 @@ mod tests {
             filter: EventFilter {
                 keys: keys_for_expected_events.clone(),
                 chunk_size: 3,
-                continuation_token: Some("3-0".to_string()),
+                continuation_token: Some("3-0-0xaaaaaa".to_string()),
                 ..Default::default()
             },
+            decode: false,
+            at_block_hash: None,
         };
         let result = get_events(context.clone(), input).await.unwrap();
         assert_eq!(
@@ -920,10 +1280,118 @@ mod tests {
                 continuation_token: Some("2-6".to_string()),
                 ..Default::default()
             },
+            decode: false,
+            at_block_hash: None,
         };
-        let result = get_events(context, input).await.unwrap();
+        let result = get_events(context.clone(), input).await.unwrap();
         assert_eq!(result.events, &[]);
         assert_eq!(result.continuation_token, None);
+
+        // Tokens issued without a block hash remain valid
+        let input = GetEventsInput {
+            filter: EventFilter {
+                keys: keys_for_expected_events.clone(),
+                chunk_size: 3,
+                continuation_token: Some("3-0".to_string()),
+                ..Default::default()
+            },
+            decode: false,
+            at_block_hash: None,
+        };
+        let result = get_events(context.clone(), input).await.unwrap();
+        assert_eq!(result.events, &expected_events[3..]);
+
+        // The block the token points into has been reorged out
+        let input = GetEventsInput {
+            filter: EventFilter {
+                keys: keys_for_expected_events,
+                chunk_size: 3,
+                continuation_token: Some("3-0-0xbbbbbb".to_string()),
+                ..Default::default()
+            },
+            decode: false,
+            at_block_hash: None,
+        };
+        let error = get_events(context, input).await.unwrap_err();
+        assert_eq!(error, GetEventsError::InvalidContinuationToken);
+    }
+
+    #[tokio::test]
+    async fn pinned_to_block() {
+        let (context, events) = setup();
+        let at_block_hash = events[test_utils::EVENTS_PER_BLOCK].block_hash;
+
+        let input = GetEventsInput {
+            filter: EventFilter {
+                to_block: Some(BlockId::Pending),
+                chunk_size: test_utils::NUM_EVENTS,
+                ..Default::default()
+            },
+            decode: false,
+            at_block_hash,
+        };
+        let result = get_events(context.clone(), input).await.unwrap();
+        assert_eq!(
+            result,
+            GetEventsResult {
+                events: events[..test_utils::EVENTS_PER_BLOCK * 2].to_vec(),
+                continuation_token: None,
+            }
+        );
+
+        // The pending block is past the pinned block.
+        let input = GetEventsInput {
+            filter: EventFilter {
+                from_block: Some(BlockId::Pending),
+                chunk_size: test_utils::NUM_EVENTS,
+                ..Default::default()
+            },
+            decode: false,
+            at_block_hash,
+        };
+        let result = get_events(context.clone(), input).await.unwrap();
+        assert_eq!(
+            result,
+            GetEventsResult {
+                events: Vec::new(),
+                continuation_token: None,
+            }
+        );
+
+        // The pinned block is reorged out between pages.
+        let input = GetEventsInput {
+            filter: EventFilter {
+                chunk_size: 1,
+                ..Default::default()
+            },
+            decode: false,
+            at_block_hash,
+        };
+        let result = get_events(context.clone(), input.clone()).await.unwrap();
+        let continuation_token = result.continuation_token;
+        assert!(continuation_token.is_some());
+
+        let mut db = context.storage.connection().unwrap();
+        let tx = db.transaction().unwrap();
+        for block in (1..test_utils::NUM_BLOCKS as u64).rev() {
+            tx.purge_block(BlockNumber::new_or_panic(block)).unwrap();
+        }
+        tx.commit().unwrap();
+
+        let input = GetEventsInput {
+            filter: EventFilter {
+                continuation_token,
+                ..input.filter
+            },
+            ..input
+        };
+        let error = get_events(context, input).await.unwrap_err();
+        assert_eq!(
+            error,
+            GetEventsError::ChainReorganized {
+                at_block_hash: at_block_hash.unwrap()
+            }
+        );
     }
 
     mod pending {
@@ -942,6 +1410,8 @@ mod tests {
                     chunk_size: 100,
                     ..Default::default()
                 },
+                decode: false,
+                at_block_hash: None,
             };
             let result = get_events(context, input).await.unwrap();
             assert!(result.events.is_empty());
@@ -957,6 +1427,8 @@ mod tests {
                     chunk_size: 1024,
                     ..Default::default()
                 },
+                decode: false,
+                at_block_hash: None,
             };
 
             let events = get_events(context.clone(), input.clone()).await.unwrap();
@@ -980,6 +1452,32 @@ mod tests {
             assert!(all_events.continuation_token.is_none());
         }
 
+        #[tokio::test]
+        async fn response_size_limit() {
+            let mut context = RpcContext::for_tests_with_pending().await;
+
+            let input = GetEventsInput {
+                filter: EventFilter {
+                    to_block: Some(BlockId::Pending),
+                    chunk_size: 1024,
+                    ..Default::default()
+                },
+                decode: false,
+                at_block_hash: None,
+            };
+            let all = get_events(context.clone(), input.clone())
+                .await
+                .unwrap()
+                .events;
+
+            // Only a single event fits.
+            context.config.max_response_size = Some(1.try_into().unwrap());
+            let (paged, pages) = all_pages(context, input).await;
+
+            assert_eq!(paged, all);
+            assert_eq!(pages, all.len());
+        }
+
         #[tokio::test]
         async fn paging() {
             let context = RpcContext::for_tests_with_pending().await;
@@ -990,6 +1488,8 @@ mod tests {
                     chunk_size: 1024,
                     ..Default::default()
                 },
+                decode: false,
+                at_block_hash: None,
             };
 
             let all = get_events(context.clone(), input.clone())
@@ -1064,6 +1564,8 @@ mod tests {
                     chunk_size: 1024,
                     continuation_token: None,
                 },
+                decode: false,
+                at_block_hash: None,
             };
 
             let all = get_events(context.clone(), input.clone())
@@ -1092,6 +1594,8 @@ mod tests {
                     chunk_size: 1024,
                     continuation_token: None,
                 },
+                decode: false,
+                at_block_hash: None,
             };
 
             let all = get_events(context.clone(), input.clone())
@@ -1126,6 +1630,8 @@ mod tests {
                     chunk_size: 100,
                     ..Default::default()
                 },
+                decode: false,
+                at_block_hash: None,
             };
             let result = get_events(context, input).await.unwrap();
             assert!(result.events.is_empty());
@@ -1142,6 +1648,8 @@ mod tests {
                     chunk_size: 100,
                     ..Default::default()
                 },
+                decode: false,
+                at_block_hash: None,
             };
             let result = get_events(context, input).await.unwrap();
             assert!(!result.events.is_empty());
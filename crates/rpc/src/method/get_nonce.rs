@@ -5,8 +5,8 @@ use crate::context::RpcContext;
 
 #[derive(Debug, PartialEq, Eq)]
 pub struct Input {
-    block_id: BlockId,
-    contract_address: ContractAddress,
+    pub block_id: BlockId,
+    pub contract_address: ContractAddress,
 }
 
 impl crate::dto::DeserializeForVersion for Input {
@@ -20,8 +20,23 @@ impl crate::dto::DeserializeForVersion for Input {
     }
 }
 
-#[derive(Debug)]
-pub struct Output(ContractNonce);
+impl crate::dto::serialize::SerializeForVersion for Input {
+    fn serialize(
+        &self,
+        serializer: crate::dto::serialize::Serializer,
+    ) -> Result<crate::dto::serialize::Ok, crate::dto::serialize::Error> {
+        let mut serializer = serializer.serialize_struct()?;
+        serializer.serialize_field("block_id", &crate::dto::BlockId(&self.block_id))?;
+        serializer.serialize_field(
+            "contract_address",
+            &crate::dto::Felt(&self.contract_address.0),
+        )?;
+        serializer.end()
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Output(pub ContractNonce);
 
 crate::error::generate_rpc_error_subset!(Error: BlockNotFound, ContractNotFound);
 
@@ -93,6 +108,12 @@ impl crate::dto::serialize::SerializeForVersion for Output {
     }
 }
 
+impl crate::dto::DeserializeForVersion for Output {
+    fn deserialize(value: crate::dto::Value) -> Result<Self, serde_json::Error> {
+        Ok(Self(ContractNonce(value.deserialize()?)))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use assert_matches::assert_matches;
@@ -2,6 +2,7 @@ use anyhow::Context;
 use pathfinder_common::{BlockId, ContractAddress, StorageAddress, StorageValue};
 
 use crate::context::RpcContext;
+use crate::pending::PendingData;
 
 #[derive(Debug, PartialEq, Eq)]
 pub struct Input {
@@ -22,8 +23,24 @@ impl crate::dto::DeserializeForVersion for Input {
     }
 }
 
-#[derive(Debug)]
-pub struct Output(StorageValue);
+impl crate::dto::serialize::SerializeForVersion for Input {
+    fn serialize(
+        &self,
+        serializer: crate::dto::serialize::Serializer,
+    ) -> Result<crate::dto::serialize::Ok, crate::dto::serialize::Error> {
+        let mut serializer = serializer.serialize_struct()?;
+        serializer.serialize_field(
+            "contract_address",
+            &crate::dto::Felt(&self.contract_address.0),
+        )?;
+        serializer.serialize_field("key", &crate::dto::Felt(&self.key.0))?;
+        serializer.serialize_field("block_id", &crate::dto::BlockId(&self.block_id))?;
+        serializer.end()
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Output(pub StorageValue);
 
 crate::error::generate_rpc_error_subset!(Error: ContractNotFound, BlockNotFound);
 
@@ -40,47 +57,92 @@ pub async fn get_storage_at(context: RpcContext, input: Input) -> Result<Output,
 
         let tx = db.transaction().context("Creating database transaction")?;
 
-        if input.block_id.is_pending() {
-            if let Some(value) = context
-                .pending_data
-                .get(&tx)
-                .context("Querying pending data")?
-                .state_update
-                .storage_value(input.contract_address, input.key)
-            {
-                return Ok(Output(value));
-            }
-        }
-
-        let block_id = match input.block_id {
-            BlockId::Pending => pathfinder_storage::BlockId::Latest,
-            other => other.try_into().expect("Only pending cast should fail"),
+        let pending = if input.block_id.is_pending() {
+            Some(
+                context
+                    .pending_data
+                    .get(&tx)
+                    .context("Querying pending data")?,
+            )
+        } else {
+            None
         };
 
-        // Check for block existence.
-        if !tx.block_exists(block_id)? {
-            return Err(Error::BlockNotFound);
-        }
-
-        let value = tx
-            .storage_value(block_id, input.contract_address, input.key)
-            .context("Querying storage value")?;
-
-        match value {
-            Some(value) => Ok(Output(value)),
-            None => {
-                if tx.contract_exists(input.contract_address, block_id)? {
-                    Ok(Output(StorageValue::ZERO))
-                } else {
-                    Err(Error::ContractNotFound)
-                }
-            }
-        }
+        storage_value(
+            &tx,
+            pending.as_ref(),
+            input.contract_address,
+            input.key,
+            input.block_id,
+        )
+        .map(Output)
     });
 
     jh.await.context("Database read panic or shutting down")?
 }
 
+/// Resolves the storage value at `block_id` within an existing database
+/// transaction.
+///
+/// `pending` is only consulted for [BlockId::Pending], in which case values
+/// missing from the pending state update fall back to the latest block.
+pub(crate) fn storage_value(
+    tx: &pathfinder_storage::Transaction<'_>,
+    pending: Option<&PendingData>,
+    contract_address: ContractAddress,
+    key: StorageAddress,
+    block_id: BlockId,
+) -> Result<StorageValue, Error> {
+    let pending = pending.filter(|_| block_id.is_pending());
+
+    let block_id = match block_id {
+        BlockId::Pending => pathfinder_storage::BlockId::Latest,
+        other => other.try_into().expect("Only pending cast should fail"),
+    };
+
+    // Check for block existence.
+    if !tx.block_exists(block_id)? {
+        return Err(Error::BlockNotFound);
+    }
+
+    resolve_storage_value(tx, pending, block_id, contract_address, key)?
+        .ok_or(Error::ContractNotFound)
+}
+
+/// Reads a single storage slot from a block whose state is known to exist.
+///
+/// Values in `pending` take precedence over the stored ones. A slot that was
+/// never written resolves to zero if the contract is deployed, and to `None`
+/// otherwise.
+pub(crate) fn resolve_storage_value(
+    tx: &pathfinder_storage::Transaction<'_>,
+    pending: Option<&PendingData>,
+    block_id: pathfinder_storage::BlockId,
+    contract_address: ContractAddress,
+    key: StorageAddress,
+) -> anyhow::Result<Option<StorageValue>> {
+    if let Some(value) =
+        pending.and_then(|pending| pending.state_update.storage_value(contract_address, key))
+    {
+        return Ok(Some(value));
+    }
+
+    let value = tx
+        .storage_value(block_id, contract_address, key)
+        .context("Querying storage value")?;
+
+    match value {
+        Some(value) => Ok(Some(value)),
+        None if tx
+            .contract_exists(contract_address, block_id)
+            .context("Querying contract existence")? =>
+        {
+            Ok(Some(StorageValue::ZERO))
+        }
+        None => Ok(None),
+    }
+}
+
 impl crate::dto::serialize::SerializeForVersion for Output {
     fn serialize(
         &self,
@@ -90,6 +152,12 @@ impl crate::dto::serialize::SerializeForVersion for Output {
     }
 }
 
+impl crate::dto::DeserializeForVersion for Output {
+    fn deserialize(value: crate::dto::Value) -> Result<Self, serde_json::Error> {
+        Ok(Self(StorageValue(value.deserialize()?)))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use assert_matches::assert_matches;
@@ -324,10 +324,15 @@ pub async fn get_storage_proof(context: RpcContext, input: Input) -> Result<Outp
         let classes_proof = if let Some(class_hashes) = input.class_hashes {
             let mut proofs = vec![];
             for class_hash in class_hashes {
-                let proof =
-                    ClassCommitmentTree::get_proof(&tx, header.number, class_hash, class_root_idx)
-                        .context("Get proof from class tree")?
-                        .ok_or(Error::ProofMissing)?;
+                let proof = ClassCommitmentTree::get_proof(
+                    &tx,
+                    header.number,
+                    class_hash,
+                    class_root_idx,
+                    &context.trie_node_cache,
+                )
+                .context("Get proof from class tree")?
+                .ok_or(Error::ProofMissing)?;
                 proofs.push(proof);
             }
 
@@ -370,6 +375,7 @@ pub async fn get_storage_proof(context: RpcContext, input: Input) -> Result<Outp
                         header.number,
                         &address,
                         storage_root_idx,
+                        &context.trie_node_cache,
                     )
                     .context("Get proof from storage tree")?
                     .ok_or(Error::ProofMissing)?;
@@ -406,8 +412,9 @@ pub async fn get_storage_proof(context: RpcContext, input: Input) -> Result<Outp
             Some(contracts_storage_keys) => {
                 let mut proofs = vec![];
                 for csk in contracts_storage_keys {
-                    let root = tx
-                        .contract_root_index(header.number, csk.contract_address)
+                    let root = context
+                        .storage_root_cache
+                        .contract_root_index(&tx, header.number, header.hash, csk.contract_address)
                         .context("Querying contract root index")?;
 
                     if let Some(root) = root {
@@ -419,6 +426,7 @@ pub async fn get_storage_proof(context: RpcContext, input: Input) -> Result<Outp
                                 header.number,
                                 key.view_bits(),
                                 root,
+                                &context.trie_node_cache,
                             )
                             .context("Get proof from contract storage tree")?
                             .ok_or_else(|| {
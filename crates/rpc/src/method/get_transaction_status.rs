@@ -84,10 +84,12 @@ pub async fn get_transaction_status(context: RpcContext, input: Input) -> Result
         return Ok(db_status);
     }
 
-    // Check gateway for rejected transactions.
+    // Check gateway for rejected transactions, unless offline.
     use starknet_gateway_client::GatewayApi;
-    context
-        .sequencer
+    let Some(sequencer) = &context.sequencer else {
+        return Err(Error::TxnHashNotFound);
+    };
+    sequencer
         .transaction_status(input.transaction_hash)
         .await
         .context("Fetching transaction from gateway")
@@ -310,4 +312,18 @@ mod tests {
 
         assert_matches!(err, Error::TxnHashNotFound);
     }
+
+    #[tokio::test]
+    async fn txn_hash_not_found_offline() {
+        let context = RpcContext {
+            sequencer: None,
+            ..RpcContext::for_tests_with_pending().await
+        };
+        let input = Input {
+            transaction_hash: transaction_hash_bytes!(b"non-existent"),
+        };
+        let err = get_transaction_status(context, input).await.unwrap_err();
+
+        assert_matches!(err, Error::TxnHashNotFound);
+    }
 }
@@ -1,16 +1,118 @@
 use anyhow::Context;
-use pathfinder_common::BlockId;
+use pathfinder_common::{
+    BlockId,
+    ClassHash,
+    ContractAddress,
+    ContractNonce,
+    StateUpdate,
+    StorageAddress,
+    StorageValue,
+};
+use pathfinder_crypto::Felt;
 use pathfinder_executor::TransactionExecutionError;
+use serde::Deserialize;
 
 use crate::context::RpcContext;
 use crate::executor::ExecutionStateError;
+use crate::v02::types::request::BroadcastedTransaction;
 use crate::v06::method::simulate_transactions as v06;
 
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Input {
+    pub block_id: BlockId,
+    pub transactions: Vec<BroadcastedTransaction>,
+    pub simulation_flags: v06::dto::SimulationFlags,
+    /// State to override for the duration of the simulation.
+    #[serde(default)]
+    pub state_overrides: Vec<StateOverride>,
+}
+
+impl crate::dto::DeserializeForVersion for Input {
+    fn deserialize(value: crate::dto::Value) -> Result<Self, serde_json::Error> {
+        value.deserialize_serde()
+    }
+}
+
+/// Overrides the state of a single contract, on top of the state of the
+/// requested block.
+#[derive(Deserialize, Debug, Default, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct StateOverride {
+    pub contract_address: ContractAddress,
+    #[serde(default)]
+    pub nonce: Option<ContractNonce>,
+    /// Replaces the contract's class, which must have been declared.
+    #[serde(default)]
+    pub class_hash: Option<ClassHash>,
+    #[serde(default)]
+    pub storage: Vec<StorageOverride>,
+    /// The contract's balance of the ETH fee token.
+    #[serde(default)]
+    pub eth_balance: Option<Felt>,
+    /// The contract's balance of the STRK fee token.
+    #[serde(default)]
+    pub strk_balance: Option<Felt>,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct StorageOverride {
+    pub key: StorageAddress,
+    pub value: StorageValue,
+}
+
+/// Collects the overrides into the state update layered on top of the state
+/// used for execution.
+fn state_overrides(overrides: Vec<StateOverride>) -> anyhow::Result<StateUpdate> {
+    overrides
+        .into_iter()
+        .try_fold(StateUpdate::default(), |mut state_update, o| {
+            if let Some(nonce) = o.nonce {
+                state_update = state_update.with_contract_nonce(o.contract_address, nonce);
+            }
+            if let Some(class_hash) = o.class_hash {
+                state_update = state_update.with_replaced_class(o.contract_address, class_hash);
+            }
+            for StorageOverride { key, value } in o.storage {
+                state_update = state_update.with_storage_update(o.contract_address, key, value);
+            }
+
+            let balances = [
+                (pathfinder_executor::ETH_FEE_TOKEN_ADDRESS, o.eth_balance),
+                (pathfinder_executor::STRK_FEE_TOKEN_ADDRESS, o.strk_balance),
+            ];
+            for (fee_token, balance) in balances {
+                let Some(balance) = balance else {
+                    continue;
+                };
+
+                // Balances are stored as a u256, split into its low and high 128 bits
+                // in two consecutive storage slots.
+                let low_key = StorageAddress::from_map_name_and_key(
+                    b"ERC20_balances",
+                    *o.contract_address.get(),
+                );
+                let high_key = StorageAddress::new(low_key.0 + Felt::ONE)
+                    .context("Balance storage address overflow")?;
+                let balance = balance.to_be_bytes();
+                let high = Felt::from_be_slice(&balance[..16]).expect("16 bytes fit in a felt");
+                let low = Felt::from_be_slice(&balance[16..]).expect("16 bytes fit in a felt");
+
+                state_update = state_update
+                    .with_storage_update(fee_token, low_key, StorageValue(low))
+                    .with_storage_update(fee_token, high_key, StorageValue(high));
+            }
+
+            Ok(state_update)
+        })
+}
+
 pub struct Output(Vec<pathfinder_executor::types::TransactionSimulation>);
 
 pub async fn simulate_transactions(
     context: RpcContext,
-    input: v06::SimulateTransactionInput,
+    input: Input,
 ) -> Result<Output, SimulateTransactionError> {
     let span = tracing::Span::current();
     tokio::task::spawn_blocking(move || {
@@ -55,19 +157,31 @@ pub async fn simulate_transactions(
             }
         };
 
-        let state = pathfinder_executor::ExecutionState::simulation(
+        let mut state = pathfinder_executor::ExecutionState::simulation(
             &db,
             context.chain_id,
             header,
             pending,
             pathfinder_executor::L1BlobDataAvailability::Enabled,
             context.config.custom_versioned_constants,
-        );
+        )
+        .with_stored_block_hashes();
+        if !input.state_overrides.is_empty() {
+            let overrides =
+                state_overrides(input.state_overrides).map_err(SimulateTransactionError::Custom)?;
+            state = state.with_state_overrides(overrides);
+        }
 
         let transactions = input
             .transactions
             .into_iter()
-            .map(|tx| crate::executor::map_broadcasted_transaction(&tx, context.chain_id))
+            .map(|tx| {
+                crate::executor::map_broadcasted_transaction(
+                    &tx,
+                    context.chain_id,
+                    &context.storage,
+                )
+            })
             .collect::<Result<Vec<_>, _>>()?;
 
         let txs =
@@ -104,6 +218,8 @@ impl crate::dto::serialize::SerializeForVersion for TransactionSimulation<'_> {
             &crate::dto::TransactionTrace {
                 trace: &self.0.trace,
                 include_state_diff: true,
+                abis: None,
+                call_resources: false,
             },
         )?;
         serializer.end()
@@ -119,7 +235,9 @@ pub enum SimulateTransactionError {
         transaction_index: usize,
         error: String,
         error_stack: pathfinder_executor::ErrorStack,
+        reason: pathfinder_executor::FailureReason,
     },
+    ExecutionResourcesExceeded,
 }
 
 impl From<anyhow::Error> for SimulateTransactionError {
@@ -134,14 +252,19 @@ impl From<SimulateTransactionError> for crate::error::ApplicationError {
             SimulateTransactionError::Internal(internal) => Self::Internal(internal),
             SimulateTransactionError::Custom(internal) => Self::Custom(internal),
             SimulateTransactionError::BlockNotFound => Self::BlockNotFound,
+            SimulateTransactionError::ExecutionResourcesExceeded => {
+                Self::ExecutionResourcesExceeded
+            }
             SimulateTransactionError::TransactionExecutionError {
                 transaction_index,
                 error,
                 error_stack,
+                reason,
             } => Self::TransactionExecutionError {
                 transaction_index,
                 error,
                 error_stack,
+                reason,
             },
         }
     }
@@ -155,11 +278,14 @@ impl From<TransactionExecutionError> for SimulateTransactionError {
                 transaction_index,
                 error,
                 error_stack,
+                reason,
             } => Self::TransactionExecutionError {
                 transaction_index,
                 error,
                 error_stack,
+                reason,
             },
+            ExecutionResourcesExceeded { .. } => Self::ExecutionResourcesExceeded,
             Internal(e) => Self::Internal(e),
             Custom(e) => Self::Custom(e),
         }
@@ -185,6 +311,7 @@ pub(crate) mod tests {
         ClassHash,
         EntryPoint,
         StarknetVersion,
+        StorageAddress,
         StorageValue,
         TransactionVersion,
     };
@@ -195,7 +322,7 @@ pub(crate) mod tests {
         ERC20_CONTRACT_DEFINITION_CLASS_HASH,
     };
 
-    use super::simulate_transactions;
+    use super::{simulate_transactions, Input, StateOverride, StorageOverride};
     use crate::context::RpcContext;
     use crate::dto::serialize::{SerializeForVersion, Serializer};
     use crate::v02::types::request::{
@@ -206,8 +333,8 @@ pub(crate) mod tests {
     use crate::v02::types::ContractClass;
     use crate::v03::method::get_state_update::types::{DeployedContract, Nonce, StateDiff};
     use crate::v06::method::call::FunctionCall;
+    use crate::v06::method::simulate_transactions::dto;
     use crate::v06::method::simulate_transactions::tests::setup_storage_with_starknet_version;
-    use crate::v06::method::simulate_transactions::{dto, SimulateTransactionInput};
     use crate::v06::types::PriceUnit;
     use crate::RpcVersion;
 
@@ -231,7 +358,7 @@ pub(crate) mod tests {
             ],
             "simulation_flags": ["SKIP_FEE_CHARGE"]
         });
-        let input = SimulateTransactionInput::deserialize(&input_json).unwrap();
+        let input = Input::deserialize(&input_json).unwrap();
 
         let expected: Vec<dto::SimulatedTransaction> = {
             use dto::*;
@@ -362,10 +489,11 @@ pub(crate) mod tests {
             },
         ));
 
-        let input = SimulateTransactionInput {
+        let input = Input {
             block_id: last_block_header.number.into(),
             transactions: vec![declare],
             simulation_flags: dto::SimulationFlags(vec![]),
+            state_overrides: vec![],
         };
 
         let result = simulate_transactions(context, input).await.unwrap();
@@ -1590,7 +1718,7 @@ pub(crate) mod tests {
         ) = setup_storage_with_starknet_version(StarknetVersion::new(0, 13, 1, 1)).await;
         let context = RpcContext::for_tests().with_storage(storage);
 
-        let input = SimulateTransactionInput {
+        let input = Input {
             transactions: vec![
                 fixtures::input::declare(account_contract_address),
                 fixtures::input::universal_deployer(
@@ -1602,6 +1730,7 @@ pub(crate) mod tests {
             ],
             block_id: BlockId::Number(last_block_header.number),
             simulation_flags: dto::SimulationFlags(vec![]),
+            state_overrides: vec![],
         };
         let result = simulate_transactions(context, input).await.unwrap();
 
@@ -1647,7 +1776,7 @@ pub(crate) mod tests {
         ) = setup_storage_with_starknet_version(StarknetVersion::new(0, 13, 1, 1)).await;
         let context = RpcContext::for_tests().with_storage(storage);
 
-        let input = SimulateTransactionInput {
+        let input = Input {
             transactions: vec![
                 fixtures::input::declare(account_contract_address),
                 fixtures::input::universal_deployer(
@@ -1659,6 +1788,7 @@ pub(crate) mod tests {
             ],
             block_id: BlockId::Number(last_block_header.number),
             simulation_flags: dto::SimulationFlags(vec![dto::SimulationFlag::SkipFeeCharge]),
+            state_overrides: vec![],
         };
         let result = simulate_transactions(context, input).await.unwrap();
 
@@ -1700,7 +1830,7 @@ pub(crate) mod tests {
         ) = setup_storage_with_starknet_version(StarknetVersion::new(0, 13, 1, 1)).await;
         let context = RpcContext::for_tests().with_storage(storage);
 
-        let input = SimulateTransactionInput {
+        let input = Input {
             transactions: vec![
                 fixtures::input::declare(account_contract_address),
                 fixtures::input::universal_deployer(
@@ -1712,6 +1842,7 @@ pub(crate) mod tests {
             ],
             block_id: BlockId::Number(last_block_header.number),
             simulation_flags: dto::SimulationFlags(vec![dto::SimulationFlag::SkipValidate]),
+            state_overrides: vec![],
         };
         let result = simulate_transactions(context, input).await.unwrap();
 
@@ -1745,4 +1876,100 @@ pub(crate) mod tests {
             .unwrap()
         );
     }
+
+    #[test]
+    fn parse_state_overrides() {
+        let input_json = serde_json::json!({
+            "block_id": "latest",
+            "transactions": [],
+            "simulation_flags": [],
+            "state_overrides": [
+                {
+                    "contract_address": "0x1",
+                    "nonce": "0x5",
+                    "storage": [{"key": "0x10", "value": "0x20"}],
+                    "eth_balance": "0x100"
+                }
+            ]
+        });
+
+        let input = Input::deserialize(&input_json).unwrap();
+
+        assert_eq!(
+            input.state_overrides,
+            vec![StateOverride {
+                contract_address: contract_address!("0x1"),
+                nonce: Some(contract_nonce!("0x5")),
+                storage: vec![StorageOverride {
+                    key: storage_address!("0x10"),
+                    value: storage_value!("0x20"),
+                }],
+                eth_balance: Some(felt!("0x100")),
+                ..Default::default()
+            }]
+        );
+    }
+
+    #[test]
+    fn state_overrides_split_balance_into_low_and_high() {
+        let account = contract_address!("0x1");
+        let balance = felt!("0x200000000000000000000000000000003");
+
+        let state_update = super::state_overrides(vec![StateOverride {
+            contract_address: account,
+            strk_balance: Some(balance),
+            ..Default::default()
+        }])
+        .unwrap();
+
+        let low_key = StorageAddress::from_map_name_and_key(b"ERC20_balances", *account.get());
+        let high_key = StorageAddress::new_or_panic(low_key.0 + Felt::ONE);
+        let storage =
+            &state_update.contract_updates[&pathfinder_executor::STRK_FEE_TOKEN_ADDRESS].storage;
+        assert_eq!(storage[&low_key], storage_value!("0x3"));
+        assert_eq!(storage[&high_key], storage_value!("0x2"));
+        assert!(!state_update
+            .contract_updates
+            .contains_key(&pathfinder_executor::ETH_FEE_TOKEN_ADDRESS));
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn nonce_override() {
+        let (storage, last_block_header, account_contract_address, universal_deployer_address, _) =
+            setup_storage_with_starknet_version(StarknetVersion::new(0, 13, 1, 1)).await;
+        let context = RpcContext::for_tests().with_storage(storage);
+
+        // The deployment is the account's second transaction, so it can only be
+        // simulated on its own if the account's nonce is overridden.
+        let input = |state_overrides| Input {
+            transactions: vec![fixtures::input::universal_deployer(
+                account_contract_address,
+                universal_deployer_address,
+            )],
+            block_id: BlockId::Number(last_block_header.number),
+            simulation_flags: dto::SimulationFlags(vec![]),
+            state_overrides,
+        };
+
+        let result = simulate_transactions(context.clone(), input(vec![])).await;
+        assert!(matches!(
+            result,
+            Err(super::SimulateTransactionError::TransactionExecutionError {
+                transaction_index: 0,
+                ..
+            })
+        ));
+
+        let result = simulate_transactions(
+            context,
+            input(vec![StateOverride {
+                contract_address: account_contract_address,
+                nonce: Some(contract_nonce!("0x1")),
+                ..Default::default()
+            }]),
+        )
+        .await
+        .unwrap();
+        assert_eq!(result.0.len(), 1);
+    }
 }
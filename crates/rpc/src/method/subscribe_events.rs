@@ -1,7 +1,9 @@
+use std::collections::HashSet;
 use std::sync::Arc;
 
 use axum::async_trait;
-use pathfinder_common::{BlockId, BlockNumber, ContractAddress, EventKey};
+use pathfinder_common::event::Event;
+use pathfinder_common::{BlockId, BlockNumber, ContractAddress, EventKey, TransactionHash};
 use pathfinder_storage::EVENT_KEY_FILTER_LIMIT;
 use tokio::sync::mpsc;
 
@@ -10,7 +12,7 @@ use crate::context::RpcContext;
 use crate::error::ApplicationError;
 use crate::jsonrpc::{CatchUp, RpcError, RpcSubscriptionFlow, SubscriptionMessage};
 use crate::method::get_events::EmittedEvent;
-use crate::Reorg;
+use crate::{PendingData, Reorg};
 
 pub struct SubscribeEvents;
 
@@ -134,9 +136,23 @@ impl RpcSubscriptionFlow for SubscribeEvents {
     ) -> Result<(), RpcError> {
         let mut blocks = state.notifications.l2_blocks.subscribe();
         let mut reorgs = state.notifications.reorgs.subscribe();
+        let mut pending_data = state.pending_data.receiver();
         let params = params.unwrap_or_default();
-        let keys = params.keys.unwrap_or_default();
-        let key_filter_is_empty = keys.iter().flatten().count() == 0;
+        let filter = EventFilter {
+            from_address: params.from_address,
+            keys: params.keys.unwrap_or_default(),
+        };
+        let mut sent_pending = SentPendingTransactions::default();
+        let pending = pending_data.borrow_and_update().clone();
+        if send_pending_events(&tx, &pending, &filter, &mut sent_pending)
+            .await
+            .is_err()
+        {
+            return Ok(());
+        }
+        // Whether the pending data is still being updated. If not, only blocks and
+        // reorgs are streamed.
+        let mut pending_open = true;
         loop {
             tokio::select! {
                 reorg = reorgs.recv() => {
@@ -168,24 +184,7 @@ impl RpcSubscriptionFlow for SubscribeEvents {
                             let block_hash = block.block_hash;
                             for (receipt, events) in block.transaction_receipts.iter() {
                                 for event in events {
-                                    // Check if the event matches the filter.
-                                    if let Some(from_address) = params.from_address {
-                                        if event.from_address != from_address {
-                                            continue;
-                                        }
-                                    }
-                                    let matches_keys = if key_filter_is_empty {
-                                        true
-                                    } else if event.keys.len() < keys.len() {
-                                        false
-                                    } else {
-                                        event
-                                            .keys
-                                            .iter()
-                                            .zip(keys.iter())
-                                            .all(|(key, filter)| filter.is_empty() || filter.contains(key))
-                                    };
-                                    if !matches_keys {
+                                    if !filter.matches(event) {
                                         continue;
                                     }
                                     if tx.send(SubscriptionMessage {
@@ -196,6 +195,7 @@ impl RpcSubscriptionFlow for SubscribeEvents {
                                             block_hash: Some(block_hash),
                                             block_number: Some(block_number),
                                             transaction_hash: receipt.transaction_hash,
+                                            decoded: None,
                                         }),
                                         block_number,
                                         subscription_name: SUBSCRIPTION_NAME,
@@ -215,12 +215,103 @@ impl RpcSubscriptionFlow for SubscribeEvents {
                         }
                     }
                 }
+                changed = pending_data.changed(), if pending_open => {
+                    if changed.is_err() {
+                        tracing::debug!(
+                            "Pending data channel closed, no longer streaming pending events"
+                        );
+                        pending_open = false;
+                        continue;
+                    }
+                    let pending = pending_data.borrow_and_update().clone();
+                    if send_pending_events(&tx, &pending, &filter, &mut sent_pending)
+                        .await
+                        .is_err()
+                    {
+                        break;
+                    }
+                }
             }
         }
         Ok(())
     }
 }
 
+/// The `from_address` and `keys` filter of the subscription.
+struct EventFilter {
+    from_address: Option<ContractAddress>,
+    keys: Vec<Vec<EventKey>>,
+}
+
+impl EventFilter {
+    fn matches(&self, event: &Event) -> bool {
+        if let Some(from_address) = self.from_address {
+            if event.from_address != from_address {
+                return false;
+            }
+        }
+        if self.keys.iter().flatten().count() == 0 {
+            return true;
+        }
+        if event.keys.len() < self.keys.len() {
+            return false;
+        }
+        event
+            .keys
+            .iter()
+            .zip(self.keys.iter())
+            .all(|(key, filter)| filter.is_empty() || filter.contains(key))
+    }
+}
+
+/// Transactions of the pending block whose events have already been sent to
+/// the subscriber. Pending data updates for the same block number include the
+/// previously seen transactions, which must not be sent again.
+#[derive(Default)]
+struct SentPendingTransactions {
+    block_number: BlockNumber,
+    transactions: HashSet<TransactionHash>,
+}
+
+/// Sends the matching events of pending transactions which have not been sent
+/// yet. Pending events have neither a block hash nor a block number.
+///
+/// Returns an error if the subscription has been closed.
+async fn send_pending_events(
+    tx: &mpsc::Sender<SubscriptionMessage<Notification>>,
+    pending: &PendingData,
+    filter: &EventFilter,
+    sent: &mut SentPendingTransactions,
+) -> Result<(), ()> {
+    if pending.number != sent.block_number {
+        sent.block_number = pending.number;
+        sent.transactions.clear();
+    }
+    for (receipt, events) in pending.block.transaction_receipts.iter() {
+        if !sent.transactions.insert(receipt.transaction_hash) {
+            continue;
+        }
+        for event in events.iter().filter(|event| filter.matches(event)) {
+            tx.send(SubscriptionMessage {
+                notification: Notification::EmittedEvent(EmittedEvent {
+                    data: event.data.clone(),
+                    keys: event.keys.clone(),
+                    from_address: event.from_address,
+                    block_hash: None,
+                    block_number: None,
+                    transaction_hash: receipt.transaction_hash,
+                    decoded: None,
+                }),
+                block_number: pending.number,
+                subscription_name: SUBSCRIPTION_NAME,
+            })
+            .await
+            .map_err(|_| ())?;
+        }
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use std::time::Duration;
@@ -244,14 +335,14 @@ mod tests {
     use pathfinder_crypto::Felt;
     use pathfinder_storage::StorageBuilder;
     use starknet_gateway_client::Client;
-    use starknet_gateway_types::reply::Block;
-    use tokio::sync::mpsc;
+    use starknet_gateway_types::reply::{Block, PendingBlock};
+    use tokio::sync::{mpsc, watch};
 
     use crate::context::{RpcConfig, RpcContext};
-    use crate::jsonrpc::{handle_json_rpc_socket, RpcRouter};
+    use crate::jsonrpc::{handle_json_rpc_socket, RpcResponse, RpcRouter};
     use crate::pending::PendingWatcher;
     use crate::v02::types::syncing::Syncing;
-    use crate::{v08, Notifications, Reorg, SyncState};
+    use crate::{v08, Notifications, PendingData, Reorg, SyncState};
 
     #[tokio::test]
     async fn no_filtering() {
@@ -641,7 +732,65 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn pending_events() {
+        let (router, pending_data_tx) = setup_with_pending(0).await;
+        let (sender_tx, mut sender_rx) = mpsc::channel(1024);
+        let (receiver_tx, receiver_rx) = mpsc::channel(1024);
+        handle_json_rpc_socket(router.clone(), sender_tx, receiver_rx);
+        receiver_tx
+            .send(Ok(Message::Text(
+                serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "id": 1,
+                    "method": "starknet_subscribeEvents",
+                })
+                .to_string(),
+            )))
+            .await
+            .unwrap();
+        let res = sender_rx.recv().await.unwrap().unwrap();
+        let subscription_id = match res {
+            Message::Text(json) => {
+                let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+                assert_eq!(json["jsonrpc"], "2.0");
+                assert_eq!(json["id"], 1);
+                json["result"]["subscription_id"].as_u64().unwrap()
+            }
+            _ => panic!("Expected text message"),
+        };
+
+        pending_data_tx.send(sample_pending(1, 0..2)).unwrap();
+        for i in 0..2 {
+            let expected = sample_pending_event_message(i, subscription_id);
+            assert_eq!(recv(&mut sender_rx).await, expected);
+        }
+        // Events of already sent pending transactions are not sent again.
+        pending_data_tx.send(sample_pending(1, 0..3)).unwrap();
+        let expected = sample_pending_event_message(2, subscription_id);
+        assert_eq!(recv(&mut sender_rx).await, expected);
+
+        // Once the block is synced, its events are sent including the block.
+        retry(|| {
+            router
+                .context
+                .notifications
+                .l2_blocks
+                .send(sample_block(1).into())
+        })
+        .await
+        .unwrap();
+        let expected = sample_event_message(1, subscription_id);
+        assert_eq!(recv(&mut sender_rx).await, expected);
+        assert!(sender_rx.is_empty());
+    }
+
     async fn setup(num_blocks: u64) -> RpcRouter {
+        // Dropping the sender closes the pending data channel.
+        setup_with_pending(num_blocks).await.0
+    }
+
+    async fn setup_with_pending(num_blocks: u64) -> (RpcRouter, watch::Sender<PendingData>) {
         let storage = StorageBuilder::in_memory().unwrap();
         tokio::task::spawn_blocking({
             let storage = storage.clone();
@@ -666,7 +815,7 @@ mod tests {
         })
         .await
         .unwrap();
-        let (_, pending_data) = tokio::sync::watch::channel(Default::default());
+        let (pending_data_tx, pending_data) = watch::channel(Default::default());
         let notifications = Notifications::default();
         let ctx = RpcContext {
             cache: Default::default(),
@@ -678,17 +827,37 @@ mod tests {
             }
             .into(),
             chain_id: ChainId::MAINNET,
-            sequencer: Client::mainnet(Duration::from_secs(10)),
+            sequencer: Some(Client::mainnet(Duration::from_secs(10))),
             websocket: None,
             notifications,
             config: RpcConfig {
                 batch_concurrency_limit: 64.try_into().unwrap(),
+                max_batch_size: 1000.try_into().unwrap(),
+                max_response_size: None,
                 get_events_max_blocks_to_scan: 1024.try_into().unwrap(),
                 get_events_max_uncached_bloom_filters_to_load: 1024.try_into().unwrap(),
                 custom_versioned_constants: None,
+                health_max_block_age: Duration::from_secs(300),
+                trace_cache_size: pathfinder_executor::TraceCache::DEFAULT_SIZE,
+                trie_node_cache_size: pathfinder_merkle_tree::TrieNodeCache::DEFAULT_MEMORY_BUDGET,
+                execution_limits: Default::default(),
+                execution_timeout: None,
+                validate_transactions: false,
+                request_log: Default::default(),
+                sync_mode: Default::default(),
             },
+            shutdown: Default::default(),
+            storage_root_cache: Default::default(),
+            trie_node_cache: Default::default(),
+            class_cache: Default::default(),
+            mempool: Default::default(),
+            submissions: None,
+            fork: None,
+            rate_limiter: None,
+            abis: Default::default(),
+            backup_status: None,
         };
-        v08::register_routes().build(ctx)
+        (v08::register_routes().build(ctx), pending_data_tx)
     }
 
     fn sample_header(block_number: u64) -> BlockHeader {
@@ -769,6 +938,39 @@ mod tests {
         })
     }
 
+    /// A pending block with a single event for each of the transactions in
+    /// `txs`.
+    fn sample_pending(block_number: u64, txs: std::ops::Range<u64>) -> PendingData {
+        PendingData {
+            block: PendingBlock {
+                transaction_receipts: txs
+                    .clone()
+                    .map(|i| (sample_receipt(i), vec![sample_event(i)]))
+                    .collect(),
+                transactions: txs.map(sample_transaction).collect(),
+                ..Default::default()
+            }
+            .into(),
+            number: BlockNumber::new_or_panic(block_number),
+            ..Default::default()
+        }
+    }
+
+    fn sample_pending_event_message(tx: u64, subscription_id: u64) -> serde_json::Value {
+        let mut message = sample_event_message(tx, subscription_id);
+        let result = message["params"]["result"].as_object_mut().unwrap();
+        result.remove("block_hash");
+        result.remove("block_number");
+        message
+    }
+
+    async fn recv(rx: &mut mpsc::Receiver<Result<Message, RpcResponse>>) -> serde_json::Value {
+        match rx.recv().await.unwrap().unwrap() {
+            Message::Text(json) => serde_json::from_str(&json).unwrap(),
+            _ => panic!("Expected text message"),
+        }
+    }
+
     // Retry to let other tasks make progress.
     async fn retry<T, E>(cb: impl Fn() -> Result<T, E>) -> Result<T, E>
     where
@@ -55,6 +55,8 @@ impl RpcSubscriptionFlow for SubscribeNewHeads {
     type Params = Option<Params>;
     type Notification = Notification;
 
+    const SEQUENCE_NUMBERS: bool = true;
+
     fn starting_block(params: &Self::Params) -> BlockId {
         params
             .as_ref()
@@ -165,10 +167,16 @@ mod tests {
     use tokio::sync::mpsc;
 
     use crate::context::{RpcConfig, RpcContext};
-    use crate::jsonrpc::{handle_json_rpc_socket, RpcResponse, RpcRouter, CATCH_UP_BATCH_SIZE};
+    use crate::jsonrpc::{
+        handle_json_rpc_socket,
+        RpcResponse,
+        RpcRouter,
+        RpcRouterBuilder,
+        CATCH_UP_BATCH_SIZE,
+    };
     use crate::pending::PendingWatcher;
     use crate::v02::types::syncing::Syncing;
-    use crate::{v08, Notifications, Reorg, SubscriptionId, SyncState};
+    use crate::{pathfinder, v08, Notifications, Reorg, SubscriptionId, SyncState};
 
     #[tokio::test]
     async fn happy_path_with_historic_blocks() {
@@ -232,6 +240,88 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn sequence_numbers_on_pathfinder_api() {
+        let router = setup_with_routes(0, pathfinder::register_routes()).await;
+        let (sender_tx, mut sender_rx) = mpsc::channel(1024);
+        let (receiver_tx, receiver_rx) = mpsc::channel(1024);
+        handle_json_rpc_socket(router.clone(), sender_tx, receiver_rx);
+        receiver_tx
+            .send(Ok(Message::Text(
+                serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "id": 1,
+                    "method": "pathfinder_subscribeNewHeads",
+                    "params": {"block": "latest"}
+                })
+                .to_string(),
+            )))
+            .await
+            .unwrap();
+        let subscription_id = match sender_rx.recv().await.unwrap().unwrap() {
+            Message::Text(json) => {
+                let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+                json["result"]["subscription_id"].as_u64().unwrap()
+            }
+            _ => panic!("Expected text message"),
+        };
+
+        let notifications = &router.context.notifications;
+        retry(|| notifications.block_headers.send(sample_header(0).into()))
+            .await
+            .unwrap();
+        notifications
+            .block_headers
+            .send(sample_header(1).into())
+            .unwrap();
+        notifications
+            .reorgs
+            .send(
+                Reorg {
+                    first_block_number: BlockNumber::new_or_panic(1),
+                    first_block_hash: BlockHash(felt!("0x1")),
+                    last_block_number: BlockNumber::new_or_panic(1),
+                    last_block_hash: BlockHash(felt!("0x1")),
+                }
+                .into(),
+            )
+            .unwrap();
+
+        let mut received = Vec::new();
+        for _ in 0..3 {
+            let json: serde_json::Value = match sender_rx.recv().await.unwrap().unwrap() {
+                Message::Text(json) => serde_json::from_str(&json).unwrap(),
+                _ => panic!("Expected text message"),
+            };
+            assert_eq!(json["params"]["subscription_id"], subscription_id);
+            received.push((
+                json["method"].as_str().unwrap().to_owned(),
+                json["params"]["sequence_number"].as_u64().unwrap(),
+            ));
+        }
+        // Pending headers and reorgs may be forwarded in any order, but the sequence
+        // numbers are assigned as they are sent.
+        received.sort_by_key(|(_, sequence_number)| *sequence_number);
+        assert_eq!(
+            received.iter().map(|(_, n)| *n).collect::<Vec<_>>(),
+            vec![0, 1, 2]
+        );
+        assert!(received
+            .iter()
+            .any(|(method, _)| method == "starknet_subscriptionReorg"));
+
+        // Headers of the new chain keep counting up from the reorg.
+        notifications
+            .block_headers
+            .send(sample_header(1).into())
+            .unwrap();
+        let json: serde_json::Value = match sender_rx.recv().await.unwrap().unwrap() {
+            Message::Text(json) => serde_json::from_str(&json).unwrap(),
+            _ => panic!("Expected text message"),
+        };
+        assert_eq!(json["params"]["sequence_number"], 3);
+    }
+
     #[tokio::test]
     async fn race_condition_with_historic_blocks() {
         let num_blocks = 1000;
@@ -458,6 +548,10 @@ mod tests {
     }
 
     async fn setup(num_blocks: u64) -> RpcRouter {
+        setup_with_routes(num_blocks, v08::register_routes()).await
+    }
+
+    async fn setup_with_routes(num_blocks: u64, routes: RpcRouterBuilder) -> RpcRouter {
         let storage = StorageBuilder::in_memory().unwrap();
         tokio::task::spawn_blocking({
             let storage = storage.clone();
@@ -485,17 +579,37 @@ mod tests {
             }
             .into(),
             chain_id: ChainId::MAINNET,
-            sequencer: Client::mainnet(Duration::from_secs(10)),
+            sequencer: Some(Client::mainnet(Duration::from_secs(10))),
             websocket: None,
             notifications,
             config: RpcConfig {
                 batch_concurrency_limit: 1.try_into().unwrap(),
+                max_batch_size: 1000.try_into().unwrap(),
+                max_response_size: None,
                 get_events_max_blocks_to_scan: 1.try_into().unwrap(),
                 get_events_max_uncached_bloom_filters_to_load: 1.try_into().unwrap(),
                 custom_versioned_constants: None,
+                health_max_block_age: Duration::from_secs(300),
+                trace_cache_size: pathfinder_executor::TraceCache::DEFAULT_SIZE,
+                trie_node_cache_size: pathfinder_merkle_tree::TrieNodeCache::DEFAULT_MEMORY_BUDGET,
+                execution_limits: Default::default(),
+                execution_timeout: None,
+                validate_transactions: false,
+                request_log: Default::default(),
+                sync_mode: Default::default(),
             },
+            shutdown: Default::default(),
+            storage_root_cache: Default::default(),
+            trie_node_cache: Default::default(),
+            class_cache: Default::default(),
+            mempool: Default::default(),
+            submissions: None,
+            fork: None,
+            rate_limiter: None,
+            abis: Default::default(),
+            backup_status: None,
         };
-        v08::register_routes().build(ctx)
+        routes.build(ctx)
     }
 
     async fn happy_path_test(
@@ -12,8 +12,8 @@ pub struct SubscribePendingTransactions;
 
 #[derive(Debug, Clone, Default)]
 pub struct Params {
-    transaction_details: Option<bool>,
-    sender_address: Option<HashSet<ContractAddress>>,
+    pub(crate) transaction_details: Option<bool>,
+    pub(crate) sender_address: Option<HashSet<ContractAddress>>,
 }
 
 impl crate::dto::DeserializeForVersion for Option<Params> {
@@ -68,69 +68,82 @@ impl RpcSubscriptionFlow for SubscribePendingTransactions {
         params: Self::Params,
         tx: mpsc::Sender<SubscriptionMessage<Self::Notification>>,
     ) -> Result<(), RpcError> {
-        let params = params.unwrap_or_default();
-        let mut pending_data = state.pending_data.0.clone();
-        // Last block sent to the subscriber. Initial value doesn't really matter.
-        let mut last_block = BlockNumber::GENESIS;
-        // Hashes of transactions that have already been sent to the subscriber, as part
-        // of `last_block` block. It is necessary to keep track of this because the
-        // pending data updates might include new transactions for the same
-        // block number.
-        let mut sent_txs = HashSet::new();
-        loop {
-            let pending = pending_data.borrow_and_update().clone();
-            if pending.number != last_block {
-                last_block = pending.number;
-                sent_txs.clear();
+        stream_pending_transactions(state, params.unwrap_or_default(), tx, SUBSCRIPTION_NAME).await
+    }
+}
+
+/// Streams the transactions of the pending block as they appear, until either
+/// the subscription or the pending data channel is closed.
+///
+/// Shared between the `starknet` and `pathfinder` flavours of the subscription,
+/// which only differ in their parameters and `subscription_name`.
+pub(crate) async fn stream_pending_transactions(
+    state: RpcContext,
+    params: Params,
+    tx: mpsc::Sender<SubscriptionMessage<Notification>>,
+    subscription_name: &'static str,
+) -> Result<(), RpcError> {
+    let mut pending_data = state.pending_data.receiver();
+    // Last block sent to the subscriber. Initial value doesn't really matter.
+    let mut last_block = BlockNumber::GENESIS;
+    // Hashes of transactions that have already been sent to the subscriber, as part
+    // of `last_block` block. It is necessary to keep track of this because the
+    // pending data updates might include new transactions for the same
+    // block number.
+    let mut sent_txs = HashSet::new();
+    loop {
+        let pending = pending_data.borrow_and_update().clone();
+        if pending.number != last_block {
+            last_block = pending.number;
+            sent_txs.clear();
+        }
+        for transaction in pending.block.transactions.iter() {
+            if sent_txs.contains(&transaction.hash) {
+                continue;
             }
-            for transaction in pending.block.transactions.iter() {
-                if sent_txs.contains(&transaction.hash) {
-                    continue;
-                }
-                // Filter the transactions by sender address.
-                if let Some(sender_address) = &params.sender_address {
-                    use pathfinder_common::transaction::TransactionVariant::*;
-                    let address = match &transaction.variant {
-                        DeclareV0(tx) => tx.sender_address,
-                        DeclareV1(tx) => tx.sender_address,
-                        DeclareV2(tx) => tx.sender_address,
-                        DeclareV3(tx) => tx.sender_address,
-                        DeployV0(tx) => tx.contract_address,
-                        DeployV1(tx) => tx.contract_address,
-                        DeployAccountV1(tx) => tx.contract_address,
-                        DeployAccountV3(tx) => tx.contract_address,
-                        InvokeV0(tx) => tx.sender_address,
-                        InvokeV1(tx) => tx.sender_address,
-                        InvokeV3(tx) => tx.sender_address,
-                        L1Handler(tx) => tx.contract_address,
-                    };
-                    if !sender_address.contains(&address) {
-                        continue;
-                    }
-                }
-                let notification = match params.transaction_details {
-                    Some(true) => Notification::Transaction(transaction.clone().into()),
-                    Some(false) | None => Notification::TransactionHash(transaction.hash),
+            // Filter the transactions by sender address.
+            if let Some(sender_address) = &params.sender_address {
+                use pathfinder_common::transaction::TransactionVariant::*;
+                let address = match &transaction.variant {
+                    DeclareV0(tx) => tx.sender_address,
+                    DeclareV1(tx) => tx.sender_address,
+                    DeclareV2(tx) => tx.sender_address,
+                    DeclareV3(tx) => tx.sender_address,
+                    DeployV0(tx) => tx.contract_address,
+                    DeployV1(tx) => tx.contract_address,
+                    DeployAccountV1(tx) => tx.contract_address,
+                    DeployAccountV3(tx) => tx.contract_address,
+                    InvokeV0(tx) => tx.sender_address,
+                    InvokeV1(tx) => tx.sender_address,
+                    InvokeV3(tx) => tx.sender_address,
+                    L1Handler(tx) => tx.contract_address,
                 };
-                sent_txs.insert(transaction.hash);
-                if tx
-                    .send(SubscriptionMessage {
-                        notification,
-                        block_number: pending.number,
-                        subscription_name: SUBSCRIPTION_NAME,
-                    })
-                    .await
-                    .is_err()
-                {
-                    // Subscription has been closed.
-                    return Ok(());
+                if !sender_address.contains(&address) {
+                    continue;
                 }
             }
-            if pending_data.changed().await.is_err() {
-                tracing::debug!("Pending data channel closed, stopping subscription");
+            let notification = match params.transaction_details {
+                Some(true) => Notification::Transaction(transaction.clone().into()),
+                Some(false) | None => Notification::TransactionHash(transaction.hash),
+            };
+            sent_txs.insert(transaction.hash);
+            if tx
+                .send(SubscriptionMessage {
+                    notification,
+                    block_number: pending.number,
+                    subscription_name,
+                })
+                .await
+                .is_err()
+            {
+                // Subscription has been closed.
                 return Ok(());
             }
         }
+        if pending_data.changed().await.is_err() {
+            tracing::debug!("Pending data channel closed, stopping subscription");
+            return Ok(());
+        }
     }
 }
 
@@ -154,10 +167,10 @@ mod tests {
     use tokio::sync::{mpsc, watch};
 
     use crate::context::{RpcConfig, RpcContext};
-    use crate::jsonrpc::{handle_json_rpc_socket, RpcResponse};
+    use crate::jsonrpc::{handle_json_rpc_socket, RpcResponse, RpcRouterBuilder};
     use crate::pending::PendingWatcher;
     use crate::v02::types::syncing::Syncing;
-    use crate::{v08, Notifications, PendingData, SyncState};
+    use crate::{pathfinder, v08, Notifications, PendingData, SyncState};
 
     #[tokio::test]
     async fn no_filtering_no_details() {
@@ -401,6 +414,50 @@ mod tests {
         assert!(rx.is_empty());
     }
 
+    #[tokio::test]
+    async fn pathfinder_with_details() {
+        let Setup {
+            tx,
+            mut rx,
+            pending_data_tx,
+        } = setup_with_routes(pathfinder::register_routes());
+        tx.send(Ok(Message::Text(
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "pathfinder_subscribePendingTransactions",
+                "params": {
+                    "with_details": true
+                }
+            })
+            .to_string(),
+        )))
+        .await
+        .unwrap();
+        let response = rx.recv().await.unwrap().unwrap();
+        let subscription_id = match response {
+            Message::Text(json) => {
+                let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+                assert_eq!(json["jsonrpc"], "2.0");
+                assert_eq!(json["id"], 1);
+                json["result"]["subscription_id"].as_u64().unwrap()
+            }
+            _ => {
+                panic!("Expected text message");
+            }
+        };
+        pending_data_tx
+            .send(sample_block(
+                BlockNumber::GENESIS,
+                vec![(contract_address!("0x1"), transaction_hash!("0x1"))],
+            ))
+            .unwrap();
+        let mut expected = sample_message_with_details("0x1", "0x1", subscription_id);
+        expected["method"] = "pathfinder_subscriptionPendingTransactions".into();
+        assert_eq!(recv(&mut rx).await, expected);
+        assert!(rx.is_empty());
+    }
+
     async fn recv(rx: &mut mpsc::Receiver<Result<Message, RpcResponse>>) -> serde_json::Value {
         let res = rx.recv().await.unwrap().unwrap();
         match res {
@@ -468,6 +525,10 @@ mod tests {
     }
 
     fn setup() -> Setup {
+        setup_with_routes(v08::register_routes())
+    }
+
+    fn setup_with_routes(routes: RpcRouterBuilder) -> Setup {
         let storage = StorageBuilder::in_memory().unwrap();
         let (pending_data_tx, pending_data) = tokio::sync::watch::channel(Default::default());
         let notifications = Notifications::default();
@@ -481,17 +542,37 @@ mod tests {
             }
             .into(),
             chain_id: ChainId::MAINNET,
-            sequencer: Client::mainnet(Duration::from_secs(10)),
+            sequencer: Some(Client::mainnet(Duration::from_secs(10))),
             websocket: None,
             notifications,
             config: RpcConfig {
                 batch_concurrency_limit: 1.try_into().unwrap(),
+                max_batch_size: 1000.try_into().unwrap(),
+                max_response_size: None,
                 get_events_max_blocks_to_scan: 1.try_into().unwrap(),
                 get_events_max_uncached_bloom_filters_to_load: 1.try_into().unwrap(),
                 custom_versioned_constants: None,
+                health_max_block_age: Duration::from_secs(300),
+                trace_cache_size: pathfinder_executor::TraceCache::DEFAULT_SIZE,
+                trie_node_cache_size: pathfinder_merkle_tree::TrieNodeCache::DEFAULT_MEMORY_BUDGET,
+                execution_limits: Default::default(),
+                execution_timeout: None,
+                validate_transactions: false,
+                request_log: Default::default(),
+                sync_mode: Default::default(),
             },
+            shutdown: Default::default(),
+            storage_root_cache: Default::default(),
+            trie_node_cache: Default::default(),
+            class_cache: Default::default(),
+            mempool: Default::default(),
+            submissions: None,
+            fork: None,
+            rate_limiter: None,
+            abis: Default::default(),
+            backup_status: None,
         };
-        let router = v08::register_routes().build(ctx);
+        let router = routes.build(ctx);
         let (sender_tx, sender_rx) = mpsc::channel(1024);
         let (receiver_tx, receiver_rx) = mpsc::channel(1024);
         handle_json_rpc_socket(router.clone(), sender_tx, receiver_rx);
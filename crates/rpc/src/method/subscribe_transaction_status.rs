@@ -1,9 +1,10 @@
 use std::sync::Arc;
 use std::time::Duration;
 
+use anyhow::Context;
 use axum::async_trait;
 use pathfinder_common::receipt::ExecutionStatus;
-use pathfinder_common::{BlockId, BlockNumber, TransactionHash};
+use pathfinder_common::{BlockHash, BlockId, BlockNumber, TransactionHash};
 use reply::transaction_status as status;
 use starknet_gateway_client::GatewayApi;
 use starknet_gateway_types::reply;
@@ -16,12 +17,12 @@ use crate::error::ApplicationError;
 use crate::jsonrpc::{RpcError, RpcSubscriptionFlow, SubscriptionMessage};
 use crate::Reorg;
 
-pub struct SubscribeEvents;
+pub struct SubscribeTransactionStatus;
 
 #[derive(Debug, Clone, Default)]
 pub struct Params {
-    transaction_hash: TransactionHash,
-    block: Option<BlockId>,
+    pub transaction_hash: TransactionHash,
+    pub block: Option<BlockId>,
 }
 
 impl crate::dto::DeserializeForVersion for Params {
@@ -37,7 +38,14 @@ impl crate::dto::DeserializeForVersion for Params {
 
 #[derive(Debug)]
 pub enum Notification {
-    TransactionStatus(TransactionHash, FinalityStatus, Option<ExecutionStatus>),
+    /// The block is the one including the transaction, and only set for the
+    /// pathfinder subscription once the transaction is accepted.
+    TransactionStatus(
+        TransactionHash,
+        FinalityStatus,
+        Option<ExecutionStatus>,
+        Option<(BlockNumber, BlockHash)>,
+    ),
     Reorg(Arc<Reorg>),
 }
 
@@ -55,9 +63,13 @@ impl crate::dto::serialize::SerializeForVersion for Notification {
         serializer: crate::dto::serialize::Serializer,
     ) -> Result<crate::dto::serialize::Ok, crate::dto::serialize::Error> {
         return match self {
-            Notification::TransactionStatus(tx_hash, finality_status, execution_status) => {
+            Notification::TransactionStatus(tx_hash, finality_status, execution_status, block) => {
                 let mut serializer = serializer.serialize_struct()?;
                 serializer.serialize_field("transaction_hash", &tx_hash)?;
+                if let Some((block_number, block_hash)) = block {
+                    serializer.serialize_field("block_hash", &crate::dto::BlockHash(block_hash))?;
+                    serializer.serialize_field("block_number", block_number)?;
+                }
                 serializer.serialize_field(
                     "status",
                     &TransactionStatus {
@@ -120,72 +132,114 @@ impl crate::dto::serialize::SerializeForVersion for Notification {
 const SUBSCRIPTION_NAME: &str = "starknet_subscriptionTransactionsStatus";
 
 #[async_trait]
-impl RpcSubscriptionFlow for SubscribeEvents {
+impl RpcSubscriptionFlow for SubscribeTransactionStatus {
     type Params = Params;
     type Notification = Notification;
 
-    #[allow(clippy::collapsible_if)]
     async fn subscribe(
         state: RpcContext,
         params: Self::Params,
         tx: mpsc::Sender<SubscriptionMessage<Self::Notification>>,
     ) -> Result<(), RpcError> {
-        'reorg: loop {
-            let tx_hash = params.transaction_hash;
-            let mut sender = Sender {
-                tx: &tx,
-                tx_hash,
-                last_finality_status: None,
-                last_execution_status: None,
-                last_block_number: BlockNumber::GENESIS, // Initial value not important.
-            };
-            let mut pending_data = state.pending_data.0.clone();
-            let mut l2_blocks = state.notifications.l2_blocks.subscribe();
-            let mut reorgs = state.notifications.reorgs.subscribe();
-            let storage = state.storage.clone();
-            if let Some(first_block) = params.block {
-                // Check if we have the transaction in our database, and if so, send the
-                // relevant transaction status updates.
-                let (first_block, l1_state, tx_with_receipt) =
-                    tokio::task::spawn_blocking(move || -> Result<_, RpcError> {
-                        let mut conn = storage.connection().map_err(RpcError::InternalError)?;
-                        let db = conn.transaction().map_err(RpcError::InternalError)?;
-                        let first_block = db
-                            .block_number(first_block.try_into().map_err(|_| {
-                                RpcError::InvalidParams("block cannot be pending".to_string())
-                            })?)
-                            .map_err(RpcError::InternalError)?;
-                        let l1_block_number =
-                            db.latest_l1_state().map_err(RpcError::InternalError)?;
-                        let tx_with_receipt = db
-                            .transaction_with_receipt(tx_hash)
-                            .map_err(RpcError::InternalError)?;
-                        Ok((first_block, l1_block_number, tx_with_receipt))
-                    })
-                    .await
-                    .map_err(|e| RpcError::InternalError(e.into()))??;
-                let first_block = first_block
-                    .ok_or_else(|| RpcError::ApplicationError(ApplicationError::BlockNotFound))?;
-                if let Some((_, receipt, _, block_number)) = tx_with_receipt {
-                    // We already have the transaction in the database.
-                    if let Some(parent) = block_number.parent() {
-                        // This transaction was pending in the parent block.
-                        if first_block <= parent {
-                            if sender
-                                .send(parent, FinalityStatus::Received, None)
-                                .await
-                                .is_err()
-                            {
-                                // Subscription closing.
-                                break;
-                            }
+        stream_transaction_status(state, params, tx, SUBSCRIPTION_NAME, false).await
+    }
+}
+
+/// Streams the status transitions of a transaction. Shared by the Starknet and
+/// pathfinder subscription methods, which only differ in their parameters,
+/// `subscription_name` and whether the including block is part of accepted
+/// notifications (`with_block`).
+#[allow(clippy::collapsible_if)]
+pub(crate) async fn stream_transaction_status(
+    state: RpcContext,
+    params: Params,
+    tx: mpsc::Sender<SubscriptionMessage<Notification>>,
+    subscription_name: &'static str,
+    with_block: bool,
+) -> Result<(), RpcError> {
+    'reorg: loop {
+        let tx_hash = params.transaction_hash;
+        let mut sender = Sender {
+            tx: &tx,
+            tx_hash,
+            last_finality_status: None,
+            last_execution_status: None,
+            last_block_number: BlockNumber::GENESIS, // Initial value not important.
+            subscription_name,
+            with_block,
+            block: None,
+        };
+        let mut pending_data = state.pending_data.receiver();
+        let mut l2_blocks = state.notifications.l2_blocks.subscribe();
+        let mut l1_updates = state.notifications.l1_updates.subscribe();
+        let mut reorgs = state.notifications.reorgs.subscribe();
+        let storage = state.storage.clone();
+        if let Some(first_block) = params.block {
+            // Check if we have the transaction in our database, and if so, send the
+            // relevant transaction status updates.
+            let (first_block, l1_state, tx_with_receipt) =
+                tokio::task::spawn_blocking(move || -> Result<_, RpcError> {
+                    let mut conn = storage.connection().map_err(RpcError::InternalError)?;
+                    let db = conn.transaction().map_err(RpcError::InternalError)?;
+                    let first_block = db
+                        .block_number(first_block.try_into().map_err(|_| {
+                            RpcError::InvalidParams("block cannot be pending".to_string())
+                        })?)
+                        .map_err(RpcError::InternalError)?;
+                    let l1_block_number = db.latest_l1_state().map_err(RpcError::InternalError)?;
+                    let tx_with_receipt = db
+                        .transaction_with_receipt(tx_hash)
+                        .map_err(RpcError::InternalError)?
+                        .map(|(_, receipt, _, block_number)| {
+                            let block_hash = db
+                                .block_hash(block_number.into())?
+                                .context("Block hash missing for stored transaction")?;
+                            anyhow::Ok((receipt, block_number, block_hash))
+                        })
+                        .transpose()
+                        .map_err(RpcError::InternalError)?;
+                    Ok((first_block, l1_block_number, tx_with_receipt))
+                })
+                .await
+                .map_err(|e| RpcError::InternalError(e.into()))??;
+            let first_block = first_block
+                .ok_or_else(|| RpcError::ApplicationError(ApplicationError::BlockNotFound))?;
+            if let Some((receipt, block_number, block_hash)) = tx_with_receipt {
+                // We already have the transaction in the database.
+                sender.block = Some((block_number, block_hash));
+                if let Some(parent) = block_number.parent() {
+                    // This transaction was pending in the parent block.
+                    if first_block <= parent {
+                        if sender
+                            .send(parent, FinalityStatus::Received, None)
+                            .await
+                            .is_err()
+                        {
+                            // Subscription closing.
+                            break;
                         }
                     }
-                    if first_block <= block_number {
+                }
+                if first_block <= block_number {
+                    if sender
+                        .send(
+                            block_number,
+                            FinalityStatus::AcceptedOnL2,
+                            Some(receipt.execution_status.clone()),
+                        )
+                        .await
+                        .is_err()
+                    {
+                        // Subscription closing.
+                        break;
+                    }
+                }
+                if let Some(l1_state) = l1_state {
+                    if l1_state.block_number >= block_number {
                         if sender
                             .send(
-                                block_number,
-                                FinalityStatus::AcceptedOnL2,
+                                l1_state.block_number,
+                                FinalityStatus::AcceptedOnL1,
                                 Some(receipt.execution_status.clone()),
                             )
                             .await
@@ -195,139 +249,157 @@ impl RpcSubscriptionFlow for SubscribeEvents {
                             break;
                         }
                     }
-                    if let Some(l1_state) = l1_state {
-                        if l1_state.block_number >= block_number {
-                            if sender
-                                .send(
-                                    l1_state.block_number,
-                                    FinalityStatus::AcceptedOnL1,
-                                    Some(receipt.execution_status.clone()),
-                                )
-                                .await
-                                .is_err()
-                            {
-                                // Subscription closing.
-                                break;
-                            }
-                        }
-                    }
                 }
             }
-            let pending = pending_data.borrow_and_update().clone();
-            if pending
+        }
+        let pending = pending_data.borrow_and_update().clone();
+        // Transactions submitted through this node have been received by the
+        // gateway, even if they are not part of the pending block yet.
+        if state.mempool.contains(&tx_hash)
+            || pending
                 .block
                 .transactions
                 .iter()
                 .any(|tx| tx.hash == tx_hash)
+        {
+            if sender
+                .send(pending.number, FinalityStatus::Received, None)
+                .await
+                .is_err()
             {
-                if sender
-                    .send(pending.number, FinalityStatus::Received, None)
-                    .await
-                    .is_err()
-                {
-                    // Subscription closing.
-                    break;
-                }
+                // Subscription closing.
+                break;
             }
-            // Stream transaction status updates.
-            let mut interval = tokio::time::interval(if cfg!(test) {
-                Duration::from_secs(5)
-            } else {
-                Duration::from_secs(60)
-            });
-            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
-            loop {
-                tokio::select! {
-                    _ = interval.tick() => {
-                        match state.sequencer.transaction_status(params.transaction_hash).await {
-                            Ok(status) => {
-                                if matches!(status.execution_status, Some(status::ExecutionStatus::Rejected)) {
-                                    // Transaction has been rejected.
-                                    sender
-                                        .send(BlockNumber::GENESIS, FinalityStatus::Rejected {
-                                            reason: status.tx_failure_reason.map(|reason| reason.error_message)
-                                        }, None)
-                                        .await
-                                        .ok();
-                                    // No more updates needed. Even in case of reorg, the transaction will
-                                    // always be rejected.
-                                    break 'reorg;
-                                }
-                            }
-                            Err(e) => {
-                                tracing::warn!(
-                                    "Failed to get transaction status for subscription: {:?}",
-                                    e
-                                );
+        }
+        // Stream transaction status updates.
+        let mut interval = tokio::time::interval(if cfg!(test) {
+            Duration::from_secs(5)
+        } else {
+            Duration::from_secs(60)
+        });
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            tokio::select! {
+                // Rejections are only known to the gateway, which isn't polled offline.
+                _ = interval.tick(), if state.sequencer.is_some() => {
+                    let Some(sequencer) = &state.sequencer else {
+                        continue;
+                    };
+                    match sequencer.transaction_status(params.transaction_hash).await {
+                        Ok(status) => {
+                            if matches!(status.execution_status, Some(status::ExecutionStatus::Rejected)) {
+                                // Transaction has been rejected.
+                                sender
+                                    .send(BlockNumber::GENESIS, FinalityStatus::Rejected {
+                                        reason: status.tx_failure_reason.map(|reason| reason.error_message)
+                                    }, None)
+                                    .await
+                                    .ok();
+                                // No more updates needed. Even in case of reorg, the transaction will
+                                // always be rejected.
+                                break 'reorg;
                             }
                         }
+                        Err(e) => {
+                            tracing::warn!(
+                                "Failed to get transaction status for subscription: {:?}",
+                                e
+                            );
+                        }
                     }
-                    reorg = reorgs.recv() => {
-                        match reorg {
-                            Ok(reorg) => {
-                                let block_number = sender.last_block_number;
-                                if tx.send(SubscriptionMessage {
-                                    notification: Notification::Reorg(reorg),
-                                    block_number,
-                                    subscription_name: REORG_SUBSCRIPTION_NAME,
-                                }).await.is_err() {
-                                    // Subscription closing.
-                                    break;
-                                }
-                                continue 'reorg;
-                            }
-                            Err(broadcast::error::RecvError::Closed) => {
-                                tracing::debug!("Reorg channel closed, stopping subscription");
-                                break 'reorg;
-                            }
-                            Err(broadcast::error::RecvError::Lagged(_)) => {
-                                tracing::warn!("Reorg channel lagged");
+                }
+                reorg = reorgs.recv() => {
+                    match reorg {
+                        Ok(reorg) => {
+                            let block_number = sender.last_block_number;
+                            if tx.send(SubscriptionMessage {
+                                notification: Notification::Reorg(reorg),
+                                block_number,
+                                subscription_name: REORG_SUBSCRIPTION_NAME,
+                            }).await.is_err() {
+                                // Subscription closing.
+                                break;
                             }
+                            continue 'reorg;
                         }
-                    }
-                    r = pending_data.changed() => {
-                        if r.is_err() {
-                            tracing::debug!("Pending data channel closed, stopping subscription");
+                        Err(broadcast::error::RecvError::Closed) => {
+                            tracing::debug!("Reorg channel closed, stopping subscription");
                             break 'reorg;
                         }
-                        let pending = pending_data.borrow_and_update().clone();
-                        if pending
-                            .block
-                            .transactions
-                            .iter()
-                            .any(|tx| tx.hash == tx_hash)
+                        Err(broadcast::error::RecvError::Lagged(_)) => {
+                            tracing::warn!("Reorg channel lagged");
+                        }
+                    }
+                }
+                r = pending_data.changed() => {
+                    if r.is_err() {
+                        tracing::debug!("Pending data channel closed, stopping subscription");
+                        break 'reorg;
+                    }
+                    let pending = pending_data.borrow_and_update().clone();
+                    if pending
+                        .block
+                        .transactions
+                        .iter()
+                        .any(|tx| tx.hash == tx_hash)
+                    {
+                        if sender
+                            .send(pending.number, FinalityStatus::Received, None)
+                            .await
+                            .is_err()
                         {
-                            if sender
-                                .send(pending.number, FinalityStatus::Received, None)
-                                .await
-                                .is_err()
-                            {
-                                // Subscription closing.
-                                break;
-                            }
+                            // Subscription closing.
+                            break;
                         }
                     }
-                    l2_block = l2_blocks.recv() => {
-                        match l2_block {
-                            Ok(l2_block) => {
-                                let receipt = l2_block.transaction_receipts.iter().find(|(receipt, _)| {
-                                    receipt.transaction_hash == tx_hash
-                                });
-                                if let Some((receipt, _)) = receipt {
-                                    // Send both received and accepted updates.
-                                    if sender
-                                        .send(l2_block.block_number, FinalityStatus::Received, None)
-                                        .await
-                                        .is_err()
-                                    {
-                                        // Subscription closing.
-                                        break;
-                                    }
+                }
+                l2_block = l2_blocks.recv() => {
+                    match l2_block {
+                        Ok(l2_block) => {
+                            let receipt = l2_block.transaction_receipts.iter().find(|(receipt, _)| {
+                                receipt.transaction_hash == tx_hash
+                            });
+                            if let Some((receipt, _)) = receipt {
+                                sender.block = Some((l2_block.block_number, l2_block.block_hash));
+                                // Send both received and accepted updates.
+                                if sender
+                                    .send(l2_block.block_number, FinalityStatus::Received, None)
+                                    .await
+                                    .is_err()
+                                {
+                                    // Subscription closing.
+                                    break;
+                                }
+                                if sender
+                                    .send(
+                                        l2_block.block_number,
+                                        FinalityStatus::AcceptedOnL2,
+                                        Some(receipt.execution_status.clone())
+                                    )
+                                    .await
+                                    .is_err()
+                                {
+                                    // Subscription closing.
+                                    break;
+                                }
+                            }
+                            // Check if our transaction has been confirmed on L1. This is done
+                            // here because it guarantees that the ACCEPTED_ON_L2 update will be
+                            // sent before the ACCEPTED_ON_L1 update.
+                            let storage = state.storage.clone();
+                            let l1_state = tokio::task::spawn_blocking(move || -> Result<_, RpcError> {
+                                let mut conn = storage.connection().map_err(RpcError::InternalError)?;
+                                let db = conn.transaction().map_err(RpcError::InternalError)?;
+                                let l1_state = db.latest_l1_state().map_err(RpcError::InternalError)?;
+                                Ok(l1_state)
+                            }).await.map_err(|e| RpcError::InternalError(e.into()))??;
+                            if let Some(l1_state) = l1_state {
+                                if l1_state.block_number >= l2_block.block_number {
                                     if sender
                                         .send(
-                                            l2_block.block_number,
-                                            FinalityStatus::AcceptedOnL2,
-                                            Some(receipt.execution_status.clone())
+                                            l1_state.block_number,
+                                            FinalityStatus::AcceptedOnL1,
+                                            sender.last_execution_status.clone(),
                                         )
                                         .await
                                         .is_err()
@@ -336,47 +408,52 @@ impl RpcSubscriptionFlow for SubscribeEvents {
                                         break;
                                     }
                                 }
-                                // Check if our transaction has been confirmed on L1. This is done
-                                // here because it guarantees that the ACCEPTED_ON_L2 update will be
-                                // sent before the ACCEPTED_ON_L1 update.
-                                let storage = state.storage.clone();
-                                let l1_state = tokio::task::spawn_blocking(move || -> Result<_, RpcError> {
-                                    let mut conn = storage.connection().map_err(RpcError::InternalError)?;
-                                    let db = conn.transaction().map_err(RpcError::InternalError)?;
-                                    let l1_state = db.latest_l1_state().map_err(RpcError::InternalError)?;
-                                    Ok(l1_state)
-                                }).await.map_err(|e| RpcError::InternalError(e.into()))??;
-                                if let Some(l1_state) = l1_state {
-                                    if l1_state.block_number >= l2_block.block_number {
-                                        if sender
-                                            .send(
-                                                l1_state.block_number,
-                                                FinalityStatus::AcceptedOnL1,
-                                                sender.last_execution_status.clone(),
-                                            )
-                                            .await
-                                            .is_err()
-                                        {
-                                            // Subscription closing.
-                                            break;
-                                        }
-                                    }
-                                }
-                            }
-                            Err(broadcast::error::RecvError::Closed) => {
-                                tracing::debug!("L2 block channel closed, stopping subscription");
-                                break 'reorg;
                             }
-                            Err(broadcast::error::RecvError::Lagged(_)) => {
-                                tracing::warn!("L2 block channel lagged");
+                        }
+                        Err(broadcast::error::RecvError::Closed) => {
+                            tracing::debug!("L2 block channel closed, stopping subscription");
+                            break 'reorg;
+                        }
+                        Err(broadcast::error::RecvError::Lagged(_)) => {
+                            tracing::warn!("L2 block channel lagged");
+                        }
+                    }
+                }
+                l1_update = l1_updates.recv() => {
+                    match l1_update {
+                        Ok(l1_block_number) => {
+                            // The transaction is accepted on L1 once the block it was
+                            // included in is.
+                            if sender.last_finality_status == Some(FinalityStatus::AcceptedOnL2)
+                                && l1_block_number >= sender.last_block_number
+                            {
+                                if sender
+                                    .send(
+                                        l1_block_number,
+                                        FinalityStatus::AcceptedOnL1,
+                                        sender.last_execution_status.clone(),
+                                    )
+                                    .await
+                                    .is_err()
+                                {
+                                    // Subscription closing.
+                                    break;
+                                }
                             }
                         }
+                        Err(broadcast::error::RecvError::Closed) => {
+                            tracing::debug!("L1 update channel closed, stopping subscription");
+                            break 'reorg;
+                        }
+                        Err(broadcast::error::RecvError::Lagged(_)) => {
+                            tracing::warn!("L1 update channel lagged");
+                        }
                     }
                 }
             }
         }
-        Ok(())
     }
+    Ok(())
 }
 
 struct Sender<'a> {
@@ -385,6 +462,10 @@ struct Sender<'a> {
     last_finality_status: Option<FinalityStatus>,
     last_execution_status: Option<ExecutionStatus>,
     last_block_number: BlockNumber,
+    subscription_name: &'static str,
+    with_block: bool,
+    /// The block including the transaction, once known.
+    block: Option<(BlockNumber, BlockHash)>,
 }
 
 impl Sender<'_> {
@@ -403,15 +484,22 @@ impl Sender<'_> {
         self.last_finality_status = Some(finality_status.clone());
         self.last_execution_status = execution_status.clone();
         self.last_block_number = block_number;
+        let block = match finality_status {
+            FinalityStatus::AcceptedOnL2 | FinalityStatus::AcceptedOnL1 if self.with_block => {
+                self.block
+            }
+            _ => None,
+        };
         self.tx
             .send(SubscriptionMessage {
                 notification: Notification::TransactionStatus(
                     self.tx_hash,
                     finality_status,
                     execution_status,
+                    block,
                 ),
                 block_number,
-                subscription_name: SUBSCRIPTION_NAME,
+                subscription_name: self.subscription_name,
             })
             .await
             .map_err(|_| mpsc::error::SendError(()))?;
@@ -3,13 +3,38 @@ use pathfinder_common::BlockId;
 use pathfinder_executor::TransactionExecutionError;
 use starknet_gateway_client::GatewayApi;
 
+use crate::abi_registry::AbiRegistry;
 use crate::compose_executor_transaction;
 use crate::context::RpcContext;
 use crate::executor::{
     ExecutionStateError,
     VERSIONS_LOWER_THAN_THIS_SHOULD_FALL_BACK_TO_FETCHING_TRACE_FROM_GATEWAY,
 };
-use crate::v06::method::trace_block_transactions as v06;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Input {
+    pub block_id: BlockId,
+    /// Whether to decode the traces' events using the registered ABIs.
+    pub decode: bool,
+    /// Whether every call also reports the resources used by itself alone.
+    pub call_resources: bool,
+}
+
+impl crate::dto::DeserializeForVersion for Input {
+    fn deserialize(value: crate::dto::Value) -> Result<Self, serde_json::Error> {
+        value.deserialize_map(|value| {
+            Ok(Self {
+                block_id: value.deserialize_serde("block_id")?,
+                decode: value
+                    .deserialize_optional_serde("decode")?
+                    .unwrap_or_default(),
+                call_resources: value
+                    .deserialize_optional_serde("call_resources")?
+                    .unwrap_or_default(),
+            })
+        })
+    }
+}
 
 pub struct Output {
     traces: Vec<(
@@ -17,17 +42,22 @@ pub struct Output {
         pathfinder_executor::types::TransactionTrace,
     )>,
     include_state_diffs: bool,
+    abis: Option<AbiRegistry>,
+    call_resources: bool,
 }
 
 pub async fn trace_block_transactions(
     context: RpcContext,
-    input: v06::TraceBlockTransactionsInput,
+    input: Input,
 ) -> Result<Output, TraceBlockTransactionsError> {
     enum LocalExecution {
         Success(Output),
         Unsupported(Vec<pathfinder_common::transaction::Transaction>),
     }
 
+    let abis = input.decode.then(|| context.abis.clone());
+    let call_resources = input.call_resources;
+
     let span = tracing::Span::current();
 
     let storage = context.execution_storage.clone();
@@ -117,18 +147,26 @@ pub async fn trace_block_transactions(
         Ok(LocalExecution::Success(Output {
             traces,
             include_state_diffs: true,
+            abis: None,
+            call_resources: false,
         }))
     })
     .await
     .context("trace_block_transactions: fetch block & transactions")??;
 
     let transactions = match traces {
-        LocalExecution::Success(output) => return Ok(output),
+        LocalExecution::Success(output) => {
+            return Ok(Output {
+                abis,
+                call_resources,
+                ..output
+            })
+        }
         LocalExecution::Unsupported(transactions) => transactions,
     };
 
     context
-        .sequencer
+        .gateway()?
         .block_traces(input.block_id)
         .await
         .context("Forwarding to feeder gateway")
@@ -148,6 +186,8 @@ pub async fn trace_block_transactions(
                     .collect::<Result<Vec<_>, TraceBlockTransactionsError>>()?,
                 // State diffs are not available for traces fetched from the gateway.
                 include_state_diffs: false,
+                abis,
+                call_resources,
             })
         })?
 }
@@ -426,6 +466,8 @@ fn map_gateway_function_invocation(
             .collect(),
         result: invocation.result,
         computation_resources: map_gateway_computation_resources(invocation.execution_resources),
+        // Gateway traces do not report Sierra gas.
+        gas_consumed: None,
     })
 }
 
@@ -489,6 +531,8 @@ impl crate::dto::serialize::SerializeForVersion for Output {
                 transaction_hash: hash,
                 transaction_trace: trace,
                 include_state_diff: self.include_state_diffs,
+                abis: self.abis.as_ref(),
+                call_resources: self.call_resources,
             }),
         )
     }
@@ -498,6 +542,8 @@ struct Trace<'a> {
     transaction_hash: &'a pathfinder_common::TransactionHash,
     transaction_trace: &'a pathfinder_executor::types::TransactionTrace,
     include_state_diff: bool,
+    abis: Option<&'a AbiRegistry>,
+    call_resources: bool,
 }
 
 impl crate::dto::serialize::SerializeForVersion for Trace<'_> {
@@ -515,6 +561,8 @@ impl crate::dto::serialize::SerializeForVersion for Trace<'_> {
             &crate::dto::TransactionTrace {
                 trace: self.transaction_trace,
                 include_state_diff: self.include_state_diff,
+                abis: self.abis,
+                call_resources: self.call_resources,
             },
         )?;
         serializer.end()
@@ -561,11 +609,16 @@ impl From<TransactionExecutionError> for TraceBlockTransactionsError {
                 transaction_index,
                 error,
                 error_stack: _,
+                reason: _,
             } => Self::Custom(anyhow::anyhow!(
                 "Transaction execution failed at index {}: {}",
                 transaction_index,
                 error
             )),
+            ExecutionResourcesExceeded { transaction_index } => Self::Custom(anyhow::anyhow!(
+                "Transaction execution exceeded resource limits at index {}",
+                transaction_index
+            )),
             Internal(e) => Self::Internal(e),
             Custom(e) => Self::Custom(e),
         }
@@ -589,10 +642,10 @@ pub(crate) mod tests {
     use starknet_gateway_types::reply::GasPrices;
     use tokio::task::JoinSet;
 
-    use super::v06::{Trace, TraceBlockTransactionsInput, TraceBlockTransactionsOutput};
-    use super::{trace_block_transactions, RpcContext};
+    use super::{trace_block_transactions, Input, RpcContext};
     use crate::dto::serialize::{SerializeForVersion, Serializer};
     use crate::v06::method::simulate_transactions::tests::setup_storage_with_starknet_version;
+    use crate::v06::method::trace_block_transactions::{Trace, TraceBlockTransactionsOutput};
     use crate::RpcVersion;
 
     pub(crate) async fn setup_multi_tx_trace_test(
@@ -703,8 +756,10 @@ pub(crate) mod tests {
     async fn test_multiple_transactions() -> anyhow::Result<()> {
         let (context, next_block_header, traces) = setup_multi_tx_trace_test().await?;
 
-        let input = TraceBlockTransactionsInput {
+        let input = Input {
             block_id: next_block_header.hash.into(),
+            decode: false,
+            call_resources: false,
         };
         let output = trace_block_transactions(context, input).await.unwrap();
         let expected = TraceBlockTransactionsOutput(traces);
@@ -729,8 +784,10 @@ pub(crate) mod tests {
 
         let (context, next_block_header, traces) = setup_multi_tx_trace_test().await?;
 
-        let input = TraceBlockTransactionsInput {
+        let input = Input {
             block_id: next_block_header.hash.into(),
+            decode: false,
+            call_resources: false,
         };
         let mut joins = JoinSet::new();
         for _ in 0..NUM_REQUESTS {
@@ -838,6 +895,7 @@ pub(crate) mod tests {
                 transactions: transactions.iter().cloned().map(Into::into).collect(),
                 starknet_version: last_block_header.starknet_version,
                 l1_da_mode: starknet_gateway_types::reply::L1DataAvailabilityMode::Blob,
+                l2_gas_price: Default::default(),
             };
 
             tx.commit()?;
@@ -878,8 +936,10 @@ pub(crate) mod tests {
     async fn test_multiple_pending_transactions() -> anyhow::Result<()> {
         let (context, traces) = setup_multi_tx_trace_pending_test().await?;
 
-        let input = TraceBlockTransactionsInput {
+        let input = Input {
             block_id: BlockId::Pending,
+            decode: false,
+            call_resources: false,
         };
         let output = trace_block_transactions(context, input).await.unwrap();
         let expected = TraceBlockTransactionsOutput(traces);
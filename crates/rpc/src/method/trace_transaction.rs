@@ -1,7 +1,9 @@
 use anyhow::Context;
+use pathfinder_common::TransactionHash;
 use pathfinder_executor::TransactionExecutionError;
 use starknet_gateway_client::GatewayApi;
 
+use crate::abi_registry::AbiRegistry;
 use crate::compose_executor_transaction;
 use crate::context::RpcContext;
 use crate::error::{ApplicationError, TraceError};
@@ -10,17 +12,43 @@ use crate::executor::{
     VERSIONS_LOWER_THAN_THIS_SHOULD_FALL_BACK_TO_FETCHING_TRACE_FROM_GATEWAY,
 };
 use crate::method::trace_block_transactions::map_gateway_trace;
-use crate::v06::method::trace_transaction as v06;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Input {
+    pub transaction_hash: TransactionHash,
+    /// Whether to decode the trace's events using the registered ABIs.
+    pub decode: bool,
+    /// Whether every call also reports the resources used by itself alone.
+    pub call_resources: bool,
+}
+
+impl crate::dto::DeserializeForVersion for Input {
+    fn deserialize(value: crate::dto::Value) -> Result<Self, serde_json::Error> {
+        value.deserialize_map(|value| {
+            Ok(Self {
+                transaction_hash: value.deserialize_serde("transaction_hash")?,
+                decode: value
+                    .deserialize_optional_serde("decode")?
+                    .unwrap_or_default(),
+                call_resources: value
+                    .deserialize_optional_serde("call_resources")?
+                    .unwrap_or_default(),
+            })
+        })
+    }
+}
 
 #[derive(Debug)]
 pub struct Output {
     trace: pathfinder_executor::types::TransactionTrace,
     include_state_diff: bool,
+    abis: Option<AbiRegistry>,
+    call_resources: bool,
 }
 
 pub async fn trace_transaction(
     context: RpcContext,
-    input: v06::TraceTransactionInput,
+    input: Input,
 ) -> Result<Output, TraceTransactionError> {
     #[allow(clippy::large_enum_variant)]
     enum LocalExecution {
@@ -28,6 +56,9 @@ pub async fn trace_transaction(
         Unsupported(pathfinder_common::transaction::Transaction),
     }
 
+    let abis = input.decode.then(|| context.abis.clone());
+    let call_resources = input.call_resources;
+
     let span = tracing::Span::current();
     let local =
         tokio::task::spawn_blocking(move || -> Result<LocalExecution, TraceTransactionError> {
@@ -150,13 +181,15 @@ pub async fn trace_transaction(
             return Ok(Output {
                 trace,
                 include_state_diff: true,
+                abis,
+                call_resources,
             })
         }
         LocalExecution::Unsupported(tx) => tx,
     };
 
     let trace = context
-        .sequencer
+        .gateway()?
         .transaction_trace(input.transaction_hash)
         .await
         .context("Proxying call to feeder gateway")?;
@@ -167,9 +200,17 @@ pub async fn trace_transaction(
         trace,
         // State diffs are not available for traces fetched from the gateway.
         include_state_diff: false,
+        abis,
+        call_resources,
     })
 }
 
+impl Output {
+    pub(crate) fn into_trace(self) -> pathfinder_executor::types::TransactionTrace {
+        self.trace
+    }
+}
+
 impl crate::dto::serialize::SerializeForVersion for Output {
     fn serialize(
         &self,
@@ -178,6 +219,8 @@ impl crate::dto::serialize::SerializeForVersion for Output {
         crate::dto::TransactionTrace {
             trace: &self.trace,
             include_state_diff: self.include_state_diff,
+            abis: self.abis.as_ref(),
+            call_resources: self.call_resources,
         }
         .serialize(serializer)
     }
@@ -208,11 +251,16 @@ impl From<TransactionExecutionError> for TraceTransactionError {
                 transaction_index,
                 error,
                 error_stack: _,
+                reason: _,
             } => Self::Custom(anyhow::anyhow!(
                 "Transaction execution failed at index {}: {}",
                 transaction_index,
                 error
             )),
+            ExecutionResourcesExceeded { transaction_index } => Self::Custom(anyhow::anyhow!(
+                "Transaction execution exceeded resource limits at index {}",
+                transaction_index
+            )),
             Internal(e) => Self::Internal(e),
             Custom(e) => Self::Custom(e),
         }
@@ -255,9 +303,9 @@ pub mod tests {
         setup_multi_tx_trace_pending_test,
         setup_multi_tx_trace_test,
     };
-    use super::v06::{TraceTransactionInput, TraceTransactionOutput};
     use super::*;
     use crate::dto::serialize::{SerializeForVersion, Serializer};
+    use crate::v06::method::trace_transaction::TraceTransactionOutput;
     use crate::RpcVersion;
 
     #[tokio::test]
@@ -265,8 +313,10 @@ pub mod tests {
         let (context, _, traces) = setup_multi_tx_trace_test().await?;
 
         for trace in traces {
-            let input = TraceTransactionInput {
+            let input = Input {
                 transaction_hash: trace.transaction_hash,
+                decode: false,
+                call_resources: false,
             };
             let output = trace_transaction(context.clone(), input).await.unwrap();
             let expected = TraceTransactionOutput(trace.trace_root);
@@ -288,8 +338,10 @@ pub mod tests {
         let (context, traces) = setup_multi_tx_trace_pending_test().await?;
 
         for trace in traces {
-            let input = TraceTransactionInput {
+            let input = Input {
                 transaction_hash: trace.transaction_hash,
+                decode: false,
+                call_resources: false,
             };
             let output = trace_transaction(context.clone(), input).await.unwrap();
             let expected = TraceTransactionOutput(trace.trace_root);
@@ -303,6 +355,70 @@ pub mod tests {
             );
         }
 
+        Ok(())
+    }
+    #[tokio::test]
+    async fn call_resources_add_up_to_totals() -> anyhow::Result<()> {
+        /// Checks that the resources of a call are its own plus those of its
+        /// internal calls, returning the number of calls checked.
+        fn check(call: &serde_json::Value) -> usize {
+            let steps = |resources: &serde_json::Value| resources["steps"].as_u64().unwrap();
+            let calls = call["calls"].as_array().unwrap();
+
+            let inner_steps: u64 = calls
+                .iter()
+                .map(|call| steps(&call["execution_resources"]))
+                .sum();
+            assert_eq!(
+                steps(&call["call_resources"]) + inner_steps,
+                steps(&call["execution_resources"])
+            );
+
+            let inner_gas: u64 = calls
+                .iter()
+                .map(|call| call["call_resources"]["total_l2_gas"].as_u64().unwrap())
+                .sum();
+            assert_eq!(
+                call["call_resources"]["l2_gas"].as_u64().unwrap() + inner_gas,
+                call["call_resources"]["total_l2_gas"].as_u64().unwrap()
+            );
+
+            1 + calls.iter().map(check).sum::<usize>()
+        }
+
+        let (context, _, traces) = setup_multi_tx_trace_test().await?;
+
+        let mut checked = 0;
+        for trace in traces {
+            let input = Input {
+                transaction_hash: trace.transaction_hash,
+                decode: false,
+                call_resources: true,
+            };
+            let output = trace_transaction(context.clone(), input)
+                .await
+                .unwrap()
+                .serialize(Serializer {
+                    version: RpcVersion::V07,
+                })
+                .unwrap();
+
+            for invocation in [
+                "validate_invocation",
+                "execute_invocation",
+                "fee_transfer_invocation",
+                "constructor_invocation",
+                "function_invocation",
+            ] {
+                if let Some(call) = output.get(invocation) {
+                    if call.get("calls").is_some() {
+                        checked += check(call);
+                    }
+                }
+            }
+        }
+        assert!(checked > 0);
+
         Ok(())
     }
 }
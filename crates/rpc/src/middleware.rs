@@ -1,3 +1,7 @@
 pub mod cors;
+pub(crate) mod light_mode;
+pub mod method_filter;
+pub mod rate_limit;
+pub mod request_log;
 pub(crate) mod request_id;
 pub(crate) mod tracing;
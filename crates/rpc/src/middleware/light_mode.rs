@@ -0,0 +1,41 @@
+//! Methods unavailable on a node synced in light mode.
+//!
+//! A light node stores headers, transactions, receipts and events, but no
+//! contract state, class declarations or Merkle tries. Methods which read any
+//! of these, or execute transactions on top of them, fail with
+//! [StateUnavailable](crate::error::ApplicationError::StateUnavailable) instead
+//! of returning misleading results.
+
+/// Whether `method` reads state which a light node doesn't keep.
+pub fn requires_state(method: &str) -> bool {
+    matches!(
+        method,
+        "starknet_call"
+            | "starknet_estimateFee"
+            | "starknet_estimateMessageFee"
+            | "starknet_simulateTransactions"
+            | "starknet_traceTransaction"
+            | "starknet_traceBlockTransactions"
+            | "starknet_getStorageAt"
+            | "starknet_getNonce"
+            | "starknet_getClassHashAt"
+            | "starknet_getClass"
+            | "starknet_getClassAt"
+            | "starknet_getStateUpdate"
+            | "starknet_getStorageProof"
+            | "pathfinder_compareTrace"
+            | "pathfinder_traceTransactionFlame"
+            | "pathfinder_getProof"
+            | "pathfinder_getClassProof"
+            | "pathfinder_getBalances"
+            | "pathfinder_getClassInfo"
+            | "pathfinder_getClassDefinitions"
+            | "pathfinder_getContractStateHash"
+            | "pathfinder_getContractStorageKeys"
+            | "pathfinder_getNonceAt"
+            | "pathfinder_getStateUpdateRange"
+            | "pathfinder_getStorageAtBlocks"
+            | "pathfinder_getStorageBatch"
+            | "pathfinder_getStorageHistory"
+    )
+}
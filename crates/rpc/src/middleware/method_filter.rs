@@ -0,0 +1,152 @@
+//! Restricts which RPC methods a server exposes.
+//!
+//! Disabled methods are reported as not found, exactly as if they didn't
+//! exist, for both plain calls and subscriptions.
+
+use std::collections::HashSet;
+use std::str::FromStr;
+
+use super::rate_limit::MethodGroup;
+
+/// A set of methods which can be disabled as a whole.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MethodSet {
+    /// Methods which submit transactions to the gateway, or otherwise change
+    /// the node's state.
+    Write,
+    /// Methods which execute transactions: tracing, simulation, fee estimation
+    /// and calls, including the calls made by `pathfinder_getBalances`.
+    Trace,
+    /// The `pathfinder_*` extension methods.
+    Pathfinder,
+    /// Methods revealing how the node is used, such as the transactions
+    /// submitted through it. Not selectable on the command line, the binary
+    /// only serves these on its unrestricted address.
+    Admin,
+}
+
+/// The methods in [MethodSet::Admin].
+const ADMIN_METHODS: &[&str] = &[
+    "pathfinder_getSubmittedTransactions",
+    "pathfinder_registerAbi",
+];
+
+impl MethodSet {
+    pub fn contains(&self, method: &str) -> bool {
+        match self {
+            MethodSet::Write => MethodGroup::of(method) == MethodGroup::Write,
+            MethodSet::Trace => MethodGroup::of(method) == MethodGroup::Trace,
+            MethodSet::Pathfinder => method.starts_with("pathfinder_"),
+            MethodSet::Admin => ADMIN_METHODS.contains(&method),
+        }
+    }
+}
+
+impl FromStr for MethodSet {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "write" => Ok(Self::Write),
+            "trace" => Ok(Self::Trace),
+            "pathfinder" => Ok(Self::Pathfinder),
+            other => Err(format!(
+                "Unknown method group '{other}', expected one of 'write', 'trace' or 'pathfinder'"
+            )),
+        }
+    }
+}
+
+/// The methods exposed by a server. The default exposes every method.
+///
+/// A method is exposed only if it is in `enabled_methods`, when that is set,
+/// and is neither in `disabled_methods` nor in any of `disabled_sets`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MethodFilter {
+    pub disabled_sets: HashSet<MethodSet>,
+    pub disabled_methods: HashSet<String>,
+    /// If set, the only methods which may be exposed.
+    pub enabled_methods: Option<HashSet<String>>,
+}
+
+impl MethodFilter {
+    pub fn allows(&self, method: &str) -> bool {
+        if let Some(enabled) = &self.enabled_methods {
+            if !enabled.contains(method) {
+                return false;
+            }
+        }
+
+        !self.disabled_methods.contains(method)
+            && !self.disabled_sets.iter().any(|set| set.contains(method))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_allows_everything() {
+        let filter = MethodFilter::default();
+
+        assert!(filter.allows("starknet_getNonce"));
+        assert!(filter.allows("starknet_addInvokeTransaction"));
+        assert!(filter.allows("pathfinder_version"));
+    }
+
+    #[test]
+    fn disabled_sets() {
+        let filter = MethodFilter {
+            disabled_sets: [MethodSet::Trace, MethodSet::Pathfinder].into(),
+            ..Default::default()
+        };
+
+        assert!(filter.allows("starknet_getNonce"));
+        assert!(filter.allows("starknet_addInvokeTransaction"));
+        assert!(!filter.allows("starknet_traceTransaction"));
+        assert!(!filter.allows("starknet_call"));
+        assert!(!filter.allows("pathfinder_version"));
+    }
+
+    #[test]
+    fn admin_methods() {
+        let filter = MethodFilter {
+            disabled_sets: [MethodSet::Admin].into(),
+            ..Default::default()
+        };
+
+        assert!(!filter.allows("pathfinder_getSubmittedTransactions"));
+        assert!(!filter.allows("pathfinder_registerAbi"));
+        assert!(filter.allows("pathfinder_version"));
+        assert!(filter.allows("starknet_addInvokeTransaction"));
+    }
+
+    #[test]
+    fn allow_and_deny_lists() {
+        let filter = MethodFilter {
+            disabled_methods: ["starknet_getNonce".to_owned()].into(),
+            enabled_methods: Some(
+                [
+                    "starknet_getNonce".to_owned(),
+                    "starknet_chainId".to_owned(),
+                ]
+                .into(),
+            ),
+            ..Default::default()
+        };
+
+        assert!(filter.allows("starknet_chainId"));
+        assert!(!filter.allows("starknet_getNonce"));
+        assert!(!filter.allows("starknet_blockNumber"));
+    }
+
+    #[test]
+    fn parse_method_set() {
+        assert_eq!("write".parse(), Ok(MethodSet::Write));
+        assert_eq!("trace".parse(), Ok(MethodSet::Trace));
+        assert_eq!("pathfinder".parse(), Ok(MethodSet::Pathfinder));
+        assert!("read".parse::<MethodSet>().is_err());
+        assert!("admin".parse::<MethodSet>().is_err());
+    }
+}
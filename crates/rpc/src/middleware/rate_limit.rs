@@ -0,0 +1,364 @@
+//! Token bucket rate limits for RPC method calls.
+//!
+//! Limits can be configured globally, per client IP address and per
+//! [method group](MethodGroup). A call must be allowed by every configured
+//! limit, and only consumes a token from each of them if it is.
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::num::NonZeroU32;
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use cached::{Cached, SizedCache};
+
+/// The maximum number of client IP addresses tracked at once. Once reached,
+/// the bucket of the least recently seen client is dropped.
+const MAX_TRACKED_CLIENTS: usize = 100_000;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RateLimitConfig {
+    /// Shared by all clients.
+    pub global: Option<BucketConfig>,
+    /// Applied to each client IP address separately.
+    pub per_ip: Option<BucketConfig>,
+    /// Shared by all clients calling methods of the group.
+    #[serde(default)]
+    pub groups: HashMap<MethodGroup, BucketConfig>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BucketConfig {
+    pub requests_per_second: NonZeroU32,
+    /// The number of requests which may be made at once after being idle.
+    /// Defaults to `requests_per_second`.
+    pub burst: Option<NonZeroU32>,
+}
+
+/// Groups methods by the load they put on the node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MethodGroup {
+    /// Methods which only read from the database.
+    Read,
+    /// Methods which execute transactions: tracing, simulation, fee estimation
+    /// and calls.
+    Trace,
+    /// Methods which submit transactions to the gateway, or otherwise change
+    /// the node's state.
+    Write,
+}
+
+impl MethodGroup {
+    pub fn of(method: &str) -> Self {
+        match method {
+            "starknet_addInvokeTransaction"
+            | "starknet_addDeclareTransaction"
+            | "starknet_addDeployAccountTransaction"
+            | "pathfinder_registerAbi" => Self::Write,
+            "starknet_call"
+            | "starknet_estimateFee"
+            | "starknet_estimateMessageFee"
+            | "starknet_simulateTransactions"
+            | "starknet_traceTransaction"
+            | "starknet_traceBlockTransactions"
+            | "pathfinder_compareTrace"
+            | "pathfinder_traceTransactionFlame"
+            | "pathfinder_getBalances" => Self::Trace,
+            _ => Self::Read,
+        }
+    }
+}
+
+impl BucketConfig {
+    fn capacity(&self) -> f64 {
+        self.burst.unwrap_or(self.requests_per_second).get().into()
+    }
+
+    fn rate(&self) -> f64 {
+        self.requests_per_second.get().into()
+    }
+
+    fn full_bucket(&self, now: Instant) -> TokenBucket {
+        TokenBucket {
+            tokens: self.capacity(),
+            updated: now,
+        }
+    }
+
+    /// Refills the bucket up to `now` and returns how long until it holds a
+    /// whole token, which is zero if it already does.
+    fn refill(&self, bucket: &mut TokenBucket, now: Instant) -> Duration {
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.rate()).min(self.capacity());
+        bucket.updated = now;
+
+        let missing = 1.0 - bucket.tokens;
+        if missing > 0.0 {
+            Duration::from_secs_f64(missing / self.rate())
+        } else {
+            Duration::ZERO
+        }
+    }
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+pub struct RateLimiter {
+    config: RwLock<RateLimitConfig>,
+    buckets: Mutex<Buckets>,
+}
+
+struct Buckets {
+    global: Option<TokenBucket>,
+    per_ip: SizedCache<IpAddr, TokenBucket>,
+    groups: HashMap<MethodGroup, TokenBucket>,
+}
+
+impl Default for Buckets {
+    fn default() -> Self {
+        Self {
+            global: None,
+            per_ip: SizedCache::with_size(MAX_TRACKED_CLIENTS),
+            groups: HashMap::new(),
+        }
+    }
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            config: RwLock::new(config),
+            buckets: Default::default(),
+        }
+    }
+
+    /// Replaces the limits, refilling all buckets.
+    pub fn reconfigure(&self, config: RateLimitConfig) {
+        // Locked in the same order as by `check_at`.
+        let mut buckets = self.buckets.lock().unwrap();
+        *self.config.write().unwrap() = config;
+        *buckets = Default::default();
+    }
+
+    /// Takes a token for calling `method` from every applicable bucket.
+    ///
+    /// Fails with the time until the call would be allowed if any of the
+    /// buckets is empty, in which case no tokens are taken.
+    pub fn check(&self, client: Option<IpAddr>, method: &str) -> Result<(), Duration> {
+        self.check_at(client, MethodGroup::of(method), Instant::now())
+    }
+
+    fn check_at(
+        &self,
+        client: Option<IpAddr>,
+        group: MethodGroup,
+        now: Instant,
+    ) -> Result<(), Duration> {
+        let mut buckets = self.buckets.lock().unwrap();
+        let config = self.config.read().unwrap();
+        let Buckets {
+            global,
+            per_ip,
+            groups,
+        } = &mut *buckets;
+
+        let mut applicable = Vec::with_capacity(3);
+        if let Some(config) = &config.global {
+            applicable.push((
+                config,
+                global.get_or_insert_with(|| config.full_bucket(now)),
+            ));
+        }
+        if let (Some(config), Some(client)) = (&config.per_ip, client) {
+            let bucket = per_ip.cache_get_or_set_with(client, || config.full_bucket(now));
+            applicable.push((config, bucket));
+        }
+        if let Some(config) = config.groups.get(&group) {
+            let bucket = groups
+                .entry(group)
+                .or_insert_with(|| config.full_bucket(now));
+            applicable.push((config, bucket));
+        }
+
+        let retry_after = applicable
+            .iter_mut()
+            .map(|(config, bucket)| config.refill(bucket, now))
+            .max()
+            .unwrap_or_default();
+        if !retry_after.is_zero() {
+            return Err(retry_after);
+        }
+
+        for (_, bucket) in applicable {
+            bucket.tokens -= 1.0;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bucket(requests_per_second: u32, burst: Option<u32>) -> BucketConfig {
+        BucketConfig {
+            requests_per_second: NonZeroU32::new(requests_per_second).unwrap(),
+            burst: burst.map(|burst| NonZeroU32::new(burst).unwrap()),
+        }
+    }
+
+    #[test]
+    fn burst_then_refill() {
+        let limiter = RateLimiter::new(RateLimitConfig {
+            global: Some(bucket(2, Some(3))),
+            ..Default::default()
+        });
+        let start = Instant::now();
+
+        for _ in 0..3 {
+            limiter.check_at(None, MethodGroup::Read, start).unwrap();
+        }
+        let retry_after = limiter
+            .check_at(None, MethodGroup::Read, start)
+            .unwrap_err();
+        assert_eq!(retry_after, Duration::from_millis(500));
+
+        let later = start + Duration::from_millis(500);
+        limiter.check_at(None, MethodGroup::Read, later).unwrap();
+        limiter
+            .check_at(None, MethodGroup::Read, later)
+            .unwrap_err();
+    }
+
+    #[test]
+    fn per_ip() {
+        let limiter = RateLimiter::new(RateLimitConfig {
+            per_ip: Some(bucket(1, None)),
+            ..Default::default()
+        });
+        let now = Instant::now();
+        let alice = IpAddr::from([10, 0, 0, 1]);
+        let bob = IpAddr::from([10, 0, 0, 2]);
+
+        limiter
+            .check_at(Some(alice), MethodGroup::Read, now)
+            .unwrap();
+        limiter
+            .check_at(Some(alice), MethodGroup::Read, now)
+            .unwrap_err();
+        limiter.check_at(Some(bob), MethodGroup::Read, now).unwrap();
+    }
+
+    #[test]
+    fn evicts_least_recently_seen_client() {
+        let limiter = RateLimiter::new(RateLimitConfig {
+            per_ip: Some(bucket(1, None)),
+            ..Default::default()
+        });
+        let now = Instant::now();
+        let client = |i: usize| Some(IpAddr::from((i as u32).to_be_bytes()));
+
+        for i in 0..=MAX_TRACKED_CLIENTS {
+            limiter.check_at(client(i), MethodGroup::Read, now).unwrap();
+        }
+        assert_eq!(
+            limiter.buckets.lock().unwrap().per_ip.cache_size(),
+            MAX_TRACKED_CLIENTS
+        );
+
+        // The first client was evicted and starts with a full bucket again, while the
+        // last one is still limited.
+        limiter.check_at(client(0), MethodGroup::Read, now).unwrap();
+        limiter
+            .check_at(client(MAX_TRACKED_CLIENTS), MethodGroup::Read, now)
+            .unwrap_err();
+    }
+
+    #[test]
+    fn rejected_calls_take_no_tokens() {
+        let limiter = RateLimiter::new(RateLimitConfig {
+            global: Some(bucket(2, None)),
+            groups: HashMap::from([(MethodGroup::Trace, bucket(1, None))]),
+            ..Default::default()
+        });
+        let now = Instant::now();
+
+        limiter.check_at(None, MethodGroup::Trace, now).unwrap();
+        // Rejected by the group limit, which must leave the global token.
+        limiter.check_at(None, MethodGroup::Trace, now).unwrap_err();
+        limiter.check_at(None, MethodGroup::Read, now).unwrap();
+        limiter.check_at(None, MethodGroup::Read, now).unwrap_err();
+    }
+
+    #[test]
+    fn reconfigure() {
+        let limiter = RateLimiter::new(RateLimitConfig::default());
+        let now = Instant::now();
+
+        limiter.check_at(None, MethodGroup::Read, now).unwrap();
+        limiter.reconfigure(RateLimitConfig {
+            global: Some(bucket(1, None)),
+            ..Default::default()
+        });
+        limiter.check_at(None, MethodGroup::Read, now).unwrap();
+        limiter.check_at(None, MethodGroup::Read, now).unwrap_err();
+
+        limiter.reconfigure(RateLimitConfig::default());
+        limiter.check_at(None, MethodGroup::Read, now).unwrap();
+    }
+
+    #[test]
+    fn method_groups() {
+        assert_eq!(
+            MethodGroup::of("starknet_addInvokeTransaction"),
+            MethodGroup::Write
+        );
+        assert_eq!(
+            MethodGroup::of("pathfinder_registerAbi"),
+            MethodGroup::Write
+        );
+        assert_eq!(
+            MethodGroup::of("starknet_traceTransaction"),
+            MethodGroup::Trace
+        );
+        assert_eq!(
+            MethodGroup::of("pathfinder_compareTrace"),
+            MethodGroup::Trace
+        );
+        assert_eq!(
+            MethodGroup::of("pathfinder_traceTransactionFlame"),
+            MethodGroup::Trace
+        );
+        assert_eq!(
+            MethodGroup::of("pathfinder_getBalances"),
+            MethodGroup::Trace
+        );
+        assert_eq!(MethodGroup::of("starknet_getStorageAt"), MethodGroup::Read);
+    }
+
+    #[test]
+    fn parse_config() {
+        let config = serde_json::from_value::<RateLimitConfig>(serde_json::json!({
+            "per_ip": {"requests_per_second": 10, "burst": 20},
+            "groups": {"trace": {"requests_per_second": 1}},
+        }))
+        .unwrap();
+
+        assert_eq!(
+            config,
+            RateLimitConfig {
+                global: None,
+                per_ip: Some(bucket(10, Some(20))),
+                groups: HashMap::from([(MethodGroup::Trace, bucket(1, None))]),
+            }
+        );
+    }
+}
@@ -0,0 +1,121 @@
+//! Structured logging of RPC method calls.
+//!
+//! Calls slower than the configured threshold are always logged along with
+//! their full params. A percentage of the remaining calls is sampled and
+//! logged with only a digest of their params, which is enough to tell
+//! identical requests apart without logging large payloads.
+
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use crate::jsonrpc::RpcError;
+use crate::RpcVersion;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RequestLogConfig {
+    /// Calls taking at least this long are logged with their full params.
+    pub slow_request_threshold: Option<Duration>,
+    /// The percentage of the remaining calls which are logged.
+    pub sample_percent: u8,
+}
+
+impl RequestLogConfig {
+    fn is_slow(&self, elapsed: Duration) -> bool {
+        self.slow_request_threshold
+            .is_some_and(|threshold| elapsed >= threshold)
+    }
+
+    /// Samples calls evenly by counting them, so that exactly
+    /// `sample_percent` out of every hundred calls are logged.
+    fn is_sampled(&self) -> bool {
+        static CALLS: AtomicU64 = AtomicU64::new(0);
+
+        if self.sample_percent == 0 {
+            return false;
+        }
+
+        let call = CALLS.fetch_add(1, Ordering::Relaxed) % 100;
+        call < u64::from(self.sample_percent)
+    }
+}
+
+pub(crate) fn log_call<T>(
+    config: &RequestLogConfig,
+    method: &str,
+    version: RpcVersion,
+    params: Option<&str>,
+    elapsed: Duration,
+    output: &Result<T, RpcError>,
+) {
+    let result = match output {
+        Ok(_) => "ok".to_owned(),
+        Err(error) => error.code().to_string(),
+    };
+    let params = params.unwrap_or_default();
+    let elapsed_ms = u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX);
+
+    if config.is_slow(elapsed) {
+        tracing::warn!(
+            method,
+            version = version.to_str(),
+            params_digest = %digest(params),
+            params,
+            elapsed_ms,
+            %result,
+            "Slow RPC request"
+        );
+    } else if config.is_sampled() {
+        tracing::info!(
+            method,
+            version = version.to_str(),
+            params_digest = %digest(params),
+            elapsed_ms,
+            %result,
+            "RPC request"
+        );
+    }
+}
+
+/// A short, non-cryptographic digest of the raw params.
+fn digest(params: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    params.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slow_threshold() {
+        let config = RequestLogConfig {
+            slow_request_threshold: Some(Duration::from_millis(100)),
+            sample_percent: 0,
+        };
+
+        assert!(!config.is_slow(Duration::from_millis(99)));
+        assert!(config.is_slow(Duration::from_millis(100)));
+        assert!(!RequestLogConfig::default().is_slow(Duration::MAX));
+    }
+
+    #[test]
+    fn sampling() {
+        let never = RequestLogConfig::default();
+        assert!((0..100).all(|_| !never.is_sampled()));
+
+        let always = RequestLogConfig {
+            sample_percent: 100,
+            ..Default::default()
+        };
+        assert!((0..100).all(|_| always.is_sampled()));
+    }
+
+    #[test]
+    fn digest_identifies_params() {
+        assert_eq!(digest(r#"["0x1"]"#), digest(r#"["0x1"]"#));
+        assert_ne!(digest(r#"["0x1"]"#), digest(r#"["0x2"]"#));
+        assert_eq!(digest("").len(), 16);
+    }
+}
@@ -1,12 +1,41 @@
 use crate::jsonrpc::{RpcRouter, RpcRouterBuilder};
+use crate::method::subscribe_new_heads::SubscribeNewHeads;
 
 pub(crate) mod methods;
 
 #[rustfmt::skip]
 pub fn register_routes() -> RpcRouterBuilder {
     RpcRouter::builder(crate::RpcVersion::PathfinderV01)
-        .register("pathfinder_version",              || { pathfinder_common::consts::VERGEN_GIT_DESCRIBE })
-        .register("pathfinder_getProof",             methods::get_proof)
-        .register("pathfinder_getClassProof",        methods::get_proof_class)
-        .register("pathfinder_getTransactionStatus", methods::get_transaction_status)
+        .register("pathfinder_version",                      || { pathfinder_common::consts::VERGEN_GIT_DESCRIBE })
+        .register("pathfinder_getProof",                     methods::get_proof)
+        .register("pathfinder_getClassProof",                methods::get_proof_class)
+        .register("pathfinder_getReceiptProof",              methods::get_receipt_proof)
+        .register("pathfinder_getStorageAtBlocks",           methods::get_storage_at_blocks)
+        .register("pathfinder_getStorageBatch",              methods::get_storage_batch)
+        .register("pathfinder_getStorageHistory",            methods::get_storage_history)
+        .register("pathfinder_getNonceAt",                   methods::get_nonce_at)
+        .register("pathfinder_getStateUpdateRange",          methods::get_state_update_range)
+        .register("pathfinder_getFeeHistory",                methods::get_fee_history)
+        .register("pathfinder_getBlockStats",                methods::get_block_stats)
+        .register("pathfinder_getClassDefinitions",          methods::get_class_definitions)
+        .register("pathfinder_getClassInfo",                 methods::get_class_info)
+        .register("pathfinder_getContractStateHash",         methods::get_contract_state_hash)
+        .register("pathfinder_getContractStorageKeys",       methods::get_contract_storage_keys)
+        .register("pathfinder_getTransactionStatus",         methods::get_transaction_status)
+        .register("pathfinder_getL1ToL2MessageStatus",       methods::get_l1_to_l2_message_status)
+        .register("pathfinder_getMessageToL1Proof",          methods::get_message_to_l1_proof)
+        .register("pathfinder_getTransactionsByAccount",     methods::get_transactions_by_account)
+        .register("pathfinder_getReorgs",                    methods::get_reorgs)
+        .register("pathfinder_compareTrace",                 methods::compare_trace)
+        .register("pathfinder_traceTransactionFlame",        methods::trace_transaction_flame)
+        .register("pathfinder_getSubmittedTransactions",     methods::get_submitted_transactions)
+        .register("pathfinder_getSyncLag",                   methods::get_sync_lag)
+        .register("pathfinder_getMigrationStatus",           methods::get_migration_status)
+        .register("pathfinder_getBackupStatus",              methods::get_backup_status)
+        .register("pathfinder_getBalances",                  methods::get_balances)
+        .register("pathfinder_registerAbi",                  methods::register_abi)
+        .register("pathfinder_health",                       methods::health)
+        .register("pathfinder_subscribeNewHeads",            SubscribeNewHeads)
+        .register("pathfinder_subscribePendingTransactions", methods::SubscribePendingTransactions)
+        .register("pathfinder_subscribeTransactionStatus",   methods::SubscribeTransactionStatus)
 }
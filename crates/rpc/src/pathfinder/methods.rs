@@ -1,5 +1,59 @@
+mod compare_trace;
+mod get_backup_status;
+mod get_balances;
+mod get_block_stats;
+mod get_class_definitions;
+mod get_class_info;
+mod get_contract_state_hash;
+mod get_contract_storage_keys;
+mod get_fee_history;
+mod get_l1_to_l2_message_status;
+mod get_message_to_l1_proof;
+mod get_migration_status;
+mod get_nonce_at;
 mod get_proof;
+mod get_receipt_proof;
+mod get_reorgs;
+mod get_state_update_range;
+mod get_storage_at_blocks;
+mod get_storage_batch;
+mod get_storage_history;
+mod get_submitted_transactions;
+mod get_sync_lag;
 mod get_transaction_status;
+mod get_transactions_by_account;
+mod health;
+mod register_abi;
+mod subscribe_pending_transactions;
+mod subscribe_transaction_status;
+mod trace_transaction_flame;
 
+pub(crate) use compare_trace::compare_trace;
+pub(crate) use get_backup_status::get_backup_status;
+pub(crate) use get_balances::get_balances;
+pub(crate) use get_block_stats::get_block_stats;
+pub(crate) use get_class_definitions::get_class_definitions;
+pub(crate) use get_class_info::get_class_info;
+pub(crate) use get_contract_state_hash::get_contract_state_hash;
+pub(crate) use get_contract_storage_keys::get_contract_storage_keys;
+pub(crate) use get_fee_history::get_fee_history;
+pub(crate) use get_l1_to_l2_message_status::get_l1_to_l2_message_status;
+pub(crate) use get_message_to_l1_proof::get_message_to_l1_proof;
+pub(crate) use get_migration_status::get_migration_status;
+pub(crate) use get_nonce_at::get_nonce_at;
 pub(crate) use get_proof::{get_proof, get_proof_class};
+pub(crate) use get_receipt_proof::get_receipt_proof;
+pub(crate) use get_reorgs::get_reorgs;
+pub(crate) use get_state_update_range::get_state_update_range;
+pub(crate) use get_storage_at_blocks::get_storage_at_blocks;
+pub(crate) use get_storage_batch::get_storage_batch;
+pub(crate) use get_storage_history::get_storage_history;
+pub(crate) use get_submitted_transactions::get_submitted_transactions;
+pub(crate) use get_sync_lag::get_sync_lag;
 pub(crate) use get_transaction_status::get_transaction_status;
+pub(crate) use get_transactions_by_account::get_transactions_by_account;
+pub(crate) use health::health;
+pub(crate) use register_abi::register_abi;
+pub(crate) use subscribe_pending_transactions::SubscribePendingTransactions;
+pub(crate) use subscribe_transaction_status::SubscribeTransactionStatus;
+pub(crate) use trace_transaction_flame::trace_transaction_flame;
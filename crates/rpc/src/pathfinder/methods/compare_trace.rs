@@ -0,0 +1,544 @@
+use anyhow::Context;
+use pathfinder_common::event::Event;
+use pathfinder_common::receipt::{L1Gas, Receipt};
+use pathfinder_common::{BlockHash, BlockNumber, TransactionHash};
+use pathfinder_crypto::Felt;
+use pathfinder_executor::types::{
+    ExecuteInvocation,
+    ExecutionResources,
+    FunctionInvocation,
+    TransactionSimulation,
+    TransactionTrace,
+};
+use pathfinder_executor::TransactionExecutionError;
+use primitive_types::U256;
+use serde::Serialize;
+
+use crate::compose_executor_transaction;
+use crate::context::RpcContext;
+use crate::executor::VERSIONS_LOWER_THAN_THIS_SHOULD_FALL_BACK_TO_FETCHING_TRACE_FROM_GATEWAY;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Input {
+    pub transaction_hash: TransactionHash,
+}
+
+impl crate::dto::DeserializeForVersion for Input {
+    fn deserialize(value: crate::dto::Value) -> Result<Self, serde_json::Error> {
+        value.deserialize_map(|value| {
+            Ok(Self {
+                transaction_hash: TransactionHash(value.deserialize("transaction_hash")?),
+            })
+        })
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Output {
+    block_hash: BlockHash,
+    block_number: BlockNumber,
+    transaction_index: u64,
+    /// The fields in which the re-execution differs from the recorded
+    /// receipt. Empty if the two agree.
+    differences: Vec<Difference>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Difference {
+    /// The path of the differing field, e.g. `actual_fee` or `events[2]`.
+    field: String,
+    recorded: serde_json::Value,
+    executed: serde_json::Value,
+}
+
+crate::error::generate_rpc_error_subset!(Error: TxnHashNotFound);
+
+/// Re-executes a transaction on top of the state it was originally executed
+/// against and compares the result with its recorded receipt.
+///
+/// The transactions preceding it in its block are executed first, so any
+/// difference is due to the executor diverging from the sequencer. Only
+/// transactions of blocks we can execute locally are supported, pending
+/// transactions are not found.
+pub async fn compare_trace(context: RpcContext, input: Input) -> Result<Output, Error> {
+    let span = tracing::Span::current();
+
+    let jh = tokio::task::spawn_blocking(move || {
+        let _g = span.enter();
+        let mut db = context
+            .execution_storage
+            .connection()
+            .context("Creating database connection")?;
+        let db = db.transaction().context("Creating database transaction")?;
+
+        let (_, receipt, events, block_number) = db
+            .transaction_with_receipt(input.transaction_hash)
+            .context("Fetching transaction from database")?
+            .ok_or(Error::TxnHashNotFound)?;
+
+        let header = db
+            .block_header(block_number.into())
+            .context("Fetching block header")?
+            .context("Block header is missing")?;
+
+        if header.starknet_version
+            < VERSIONS_LOWER_THAN_THIS_SHOULD_FALL_BACK_TO_FETCHING_TRACE_FROM_GATEWAY
+        {
+            return Err(Error::Custom(anyhow::anyhow!(
+                "Re-execution is not supported for blocks older than Starknet {}",
+                VERSIONS_LOWER_THAN_THIS_SHOULD_FALL_BACK_TO_FETCHING_TRACE_FROM_GATEWAY
+            )));
+        }
+
+        let transactions = db
+            .transactions_for_block(block_number.into())
+            .context("Fetching block transactions")?
+            .context("Block transactions are missing")?;
+
+        let index = transactions
+            .iter()
+            .position(|t| t.hash == input.transaction_hash)
+            .context("Transaction is missing from its block")?;
+
+        // Transactions after the one we are interested in cannot influence it.
+        let executor_transactions = transactions[..=index]
+            .iter()
+            .map(|transaction| compose_executor_transaction(transaction, &db))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let block_hash = header.hash;
+        let state = pathfinder_executor::ExecutionState::trace(
+            &db,
+            context.chain_id,
+            header,
+            None,
+            context.config.custom_versioned_constants,
+        );
+
+        let simulation = pathfinder_executor::simulate(state, executor_transactions, false, false)
+            .map_err(|error| match error {
+                TransactionExecutionError::Internal(e) => Error::Internal(e),
+                other => Error::Custom(anyhow::anyhow!("Re-execution failed: {other}")),
+            })?
+            .pop()
+            .context("Transaction is missing from re-execution")?;
+
+        let differences =
+            compare(&receipt, &events, &simulation).context("Comparing re-execution")?;
+
+        Ok(Output {
+            block_hash,
+            block_number,
+            transaction_index: index as u64,
+            differences,
+        })
+    });
+
+    jh.await.context("Database read panic or shutting down")?
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+struct EmittedEvent {
+    from_address: Felt,
+    keys: Vec<Felt>,
+    data: Vec<Felt>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+struct MessageToL1 {
+    from_address: Felt,
+    to_address: Felt,
+    payload: Vec<Felt>,
+}
+
+#[derive(Default)]
+struct Differences(Vec<Difference>);
+
+impl Differences {
+    fn check<T: PartialEq + Serialize>(
+        &mut self,
+        field: impl Into<String>,
+        recorded: T,
+        executed: T,
+    ) -> serde_json::Result<()> {
+        if recorded != executed {
+            self.0.push(Difference {
+                field: field.into(),
+                recorded: serde_json::to_value(recorded)?,
+                executed: serde_json::to_value(executed)?,
+            });
+        }
+        Ok(())
+    }
+
+    /// Compares two lists element by element, reporting a length mismatch
+    /// separately.
+    fn check_all<T: PartialEq + Serialize>(
+        &mut self,
+        field: &str,
+        recorded: Vec<T>,
+        executed: Vec<T>,
+    ) -> serde_json::Result<()> {
+        self.check(format!("{field}.length"), recorded.len(), executed.len())?;
+        for (i, (recorded, executed)) in recorded.into_iter().zip(executed).enumerate() {
+            self.check(format!("{field}[{i}]"), recorded, executed)?;
+        }
+        Ok(())
+    }
+}
+
+fn compare(
+    receipt: &Receipt,
+    events: &[Event],
+    simulation: &TransactionSimulation,
+) -> serde_json::Result<Vec<Difference>> {
+    let mut differences = Differences::default();
+
+    let status = |reverted: bool| if reverted { "REVERTED" } else { "SUCCEEDED" };
+    let executed_revert_reason = simulation.revert_reason();
+    differences.check(
+        "execution_status",
+        status(receipt.is_reverted()),
+        status(executed_revert_reason.is_some()),
+    )?;
+    if receipt.is_reverted() && executed_revert_reason.is_some() {
+        differences.check(
+            "revert_reason",
+            receipt.revert_reason(),
+            executed_revert_reason,
+        )?;
+    }
+
+    let fee = &simulation.fee_estimation;
+    differences.check(
+        "actual_fee",
+        U256::from_big_endian(receipt.actual_fee.0.as_be_bytes()),
+        fee.overall_fee,
+    )?;
+
+    // Receipts from before Starknet 0.13.2 don't record the total gas consumed.
+    let total = &receipt.execution_resources.total_gas_consumed;
+    if *total != L1Gas::default() {
+        differences.check(
+            "execution_resources.total_gas_consumed.l1_gas",
+            U256::from(total.l1_gas),
+            fee.gas_consumed,
+        )?;
+        differences.check(
+            "execution_resources.total_gas_consumed.l1_data_gas",
+            U256::from(total.l1_data_gas),
+            fee.data_gas_consumed,
+        )?;
+    }
+
+    let recorded = &receipt.execution_resources.data_availability;
+    let executed = &execution_resources(&simulation.trace).data_availability;
+    differences.check(
+        "execution_resources.data_availability.l1_gas",
+        recorded.l1_gas,
+        executed.l1_gas,
+    )?;
+    differences.check(
+        "execution_resources.data_availability.l1_data_gas",
+        recorded.l1_data_gas,
+        executed.l1_data_gas,
+    )?;
+
+    let (executed_events, executed_messages) = emitted(&simulation.trace);
+    let recorded_events = events
+        .iter()
+        .map(|event| EmittedEvent {
+            from_address: event.from_address.0,
+            keys: event.keys.iter().map(|key| key.0).collect(),
+            data: event.data.iter().map(|data| data.0).collect(),
+        })
+        .collect();
+    differences.check_all("events", recorded_events, executed_events)?;
+
+    let recorded_messages = receipt
+        .l2_to_l1_messages
+        .iter()
+        .map(|message| MessageToL1 {
+            from_address: message.from_address.0,
+            to_address: message.to_address.0,
+            payload: message.payload.iter().map(|elem| elem.0).collect(),
+        })
+        .collect();
+    differences.check_all("l2_to_l1_messages", recorded_messages, executed_messages)?;
+
+    Ok(differences.0)
+}
+
+fn execution_resources(trace: &TransactionTrace) -> &ExecutionResources {
+    match trace {
+        TransactionTrace::Declare(trace) => &trace.execution_resources,
+        TransactionTrace::DeployAccount(trace) => &trace.execution_resources,
+        TransactionTrace::Invoke(trace) => &trace.execution_resources,
+        TransactionTrace::L1Handler(trace) => &trace.execution_resources,
+    }
+}
+
+/// Returns the events and messages emitted during execution, in the order in
+/// which they appear in a receipt.
+///
+/// This is the order of the top-level invocations (validation, execution, fee
+/// transfer) and within each of them the order of emission.
+fn emitted(trace: &TransactionTrace) -> (Vec<EmittedEvent>, Vec<MessageToL1>) {
+    let invocations = match trace {
+        TransactionTrace::Declare(trace) => {
+            vec![&trace.validate_invocation, &trace.fee_transfer_invocation]
+        }
+        TransactionTrace::DeployAccount(trace) => vec![
+            &trace.validate_invocation,
+            &trace.constructor_invocation,
+            &trace.fee_transfer_invocation,
+        ],
+        TransactionTrace::Invoke(trace) => {
+            let execute = match &trace.execute_invocation {
+                ExecuteInvocation::FunctionInvocation(invocation) => invocation,
+                ExecuteInvocation::RevertedReason(_) => &None,
+            };
+            vec![
+                &trace.validate_invocation,
+                execute,
+                &trace.fee_transfer_invocation,
+            ]
+        }
+        TransactionTrace::L1Handler(trace) => vec![&trace.function_invocation],
+    };
+
+    fn collect(
+        invocation: &FunctionInvocation,
+        events: &mut Vec<(i64, EmittedEvent)>,
+        messages: &mut Vec<(usize, MessageToL1)>,
+    ) {
+        events.extend(invocation.events.iter().map(|event| {
+            (
+                event.order,
+                EmittedEvent {
+                    from_address: invocation.contract_address.0,
+                    keys: event.keys.clone(),
+                    data: event.data.clone(),
+                },
+            )
+        }));
+        messages.extend(invocation.messages.iter().map(|message| {
+            (
+                message.order,
+                MessageToL1 {
+                    from_address: message.from_address,
+                    to_address: message.to_address,
+                    payload: message.payload.clone(),
+                },
+            )
+        }));
+        for call in &invocation.internal_calls {
+            collect(call, events, messages);
+        }
+    }
+
+    let mut all_events = Vec::new();
+    let mut all_messages = Vec::new();
+    for invocation in invocations.into_iter().flatten() {
+        let mut events = Vec::new();
+        let mut messages = Vec::new();
+        collect(invocation, &mut events, &mut messages);
+
+        events.sort_by_key(|(order, _)| *order);
+        messages.sort_by_key(|(order, _)| *order);
+
+        all_events.extend(events.into_iter().map(|(_, event)| event));
+        all_messages.extend(messages.into_iter().map(|(_, message)| message));
+    }
+
+    (all_events, all_messages)
+}
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::macro_prelude::*;
+    use pathfinder_common::receipt::ExecutionStatus;
+    use pathfinder_common::Fee;
+    use pathfinder_executor::types::{
+        CallType,
+        ComputationResources,
+        DataAvailabilityResources,
+        EntryPointType,
+        FeeEstimate,
+        InvokeTransactionTrace,
+        PriceUnit,
+    };
+    use serde_json::json;
+
+    use super::*;
+    use crate::dto::DeserializeForVersion;
+    use crate::RpcVersion;
+
+    #[rstest::rstest]
+    #[case::positional(json!(["0x1"]))]
+    #[case::named(json!({"transaction_hash": "0x1"}))]
+    fn parsing(#[case] input: serde_json::Value) {
+        let expected = Input {
+            transaction_hash: transaction_hash!("0x1"),
+        };
+
+        let input =
+            Input::deserialize(crate::dto::Value::new(input, RpcVersion::PathfinderV01)).unwrap();
+
+        assert_eq!(input, expected);
+    }
+
+    fn invocation(events: Vec<pathfinder_executor::types::Event>) -> FunctionInvocation {
+        FunctionInvocation {
+            calldata: vec![],
+            contract_address: contract_address!("0xc0ffee"),
+            selector: felt!("0x1"),
+            call_type: CallType::Call,
+            caller_address: felt!("0x2"),
+            internal_calls: vec![],
+            class_hash: None,
+            entry_point_type: EntryPointType::External,
+            events,
+            messages: vec![],
+            result: vec![],
+            computation_resources: ComputationResources::default(),
+            gas_consumed: None,
+        }
+    }
+
+    fn simulation(execute_invocation: ExecuteInvocation) -> TransactionSimulation {
+        TransactionSimulation {
+            trace: TransactionTrace::Invoke(InvokeTransactionTrace {
+                validate_invocation: None,
+                execute_invocation,
+                fee_transfer_invocation: None,
+                state_diff: Default::default(),
+                execution_resources: ExecutionResources {
+                    computation_resources: Default::default(),
+                    data_availability: DataAvailabilityResources {
+                        l1_gas: 0,
+                        l1_data_gas: 128,
+                    },
+                },
+            }),
+            fee_estimation: FeeEstimate {
+                gas_consumed: 10.into(),
+                gas_price: 1.into(),
+                data_gas_consumed: 128.into(),
+                data_gas_price: 1.into(),
+                overall_fee: 138.into(),
+                unit: PriceUnit::Fri,
+            },
+        }
+    }
+
+    fn receipt() -> Receipt {
+        let mut receipt = Receipt {
+            actual_fee: Fee(felt!("0x8a")),
+            ..Default::default()
+        };
+        receipt.execution_resources.data_availability.l1_data_gas = 128;
+        receipt.execution_resources.total_gas_consumed = L1Gas {
+            l1_gas: 10,
+            l1_data_gas: 128,
+        };
+        receipt
+    }
+
+    fn event(data: &[u8]) -> Event {
+        Event {
+            data: vec![event_data_bytes!(data)],
+            from_address: contract_address!("0xc0ffee"),
+            keys: vec![event_key!("0x99")],
+        }
+    }
+
+    #[test]
+    fn identical_execution() {
+        let simulation = simulation(ExecuteInvocation::FunctionInvocation(Some(invocation(
+            vec![
+                // Events are emitted in order, regardless of how they are stored in the trace.
+                pathfinder_executor::types::Event {
+                    order: 1,
+                    data: vec![event_data_bytes!(b"second").0],
+                    keys: vec![felt!("0x99")],
+                },
+                pathfinder_executor::types::Event {
+                    order: 0,
+                    data: vec![event_data_bytes!(b"first").0],
+                    keys: vec![felt!("0x99")],
+                },
+            ],
+        ))));
+
+        let differences = compare(
+            &receipt(),
+            &[event(b"first"), event(b"second")],
+            &simulation,
+        )
+        .unwrap();
+
+        assert_eq!(differences, vec![]);
+    }
+
+    #[test]
+    fn divergent_execution() {
+        let simulation = simulation(ExecuteInvocation::RevertedReason("Out of gas".to_owned()));
+
+        let differences = compare(&receipt(), &[event(b"first")], &simulation).unwrap();
+
+        let fields = differences
+            .iter()
+            .map(|difference| difference.field.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(fields, vec!["execution_status", "events.length"]);
+        assert_eq!(differences[0].recorded, json!("SUCCEEDED"));
+        assert_eq!(differences[0].executed, json!("REVERTED"));
+    }
+
+    #[test]
+    fn revert_reasons_are_compared() {
+        let simulation = simulation(ExecuteInvocation::RevertedReason("Out of gas".to_owned()));
+        let receipt = Receipt {
+            execution_status: ExecutionStatus::Reverted {
+                reason: "Assertion failed".to_owned(),
+            },
+            ..receipt()
+        };
+
+        let differences = compare(&receipt, &[], &simulation).unwrap();
+
+        assert_eq!(
+            differences,
+            vec![Difference {
+                field: "revert_reason".to_owned(),
+                recorded: json!("Assertion failed"),
+                executed: json!("Out of gas"),
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn transaction_not_found() {
+        let context = RpcContext::for_tests();
+        let input = Input {
+            transaction_hash: transaction_hash_bytes!(b"non-existent"),
+        };
+
+        let error = compare_trace(context, input).await.unwrap_err();
+
+        assert_matches::assert_matches!(error, Error::TxnHashNotFound);
+    }
+
+    #[tokio::test]
+    async fn old_blocks_are_not_supported() {
+        let context = RpcContext::for_tests();
+        let input = Input {
+            transaction_hash: transaction_hash_bytes!(b"txn 0"),
+        };
+
+        let error = compare_trace(context, input).await.unwrap_err();
+
+        assert_matches::assert_matches!(error, Error::Custom(_));
+    }
+}
@@ -0,0 +1,148 @@
+use crate::backup::{Backup, BackupFailure, BackupStatus};
+use crate::context::RpcContext;
+use crate::dto::serialize::{self, SerializeForVersion, Serializer};
+
+crate::error::generate_rpc_error_subset!(Error);
+
+/// `None` if the node doesn't make backups.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Output(Option<BackupStatus>);
+
+/// Reports the status of the node's scheduled database backups.
+pub async fn get_backup_status(context: RpcContext) -> Result<Output, Error> {
+    let status = context
+        .backup_status
+        .as_ref()
+        .map(|status| status.borrow().clone());
+
+    Ok(Output(status))
+}
+
+impl SerializeForVersion for Output {
+    fn serialize(&self, serializer: Serializer) -> Result<serialize::Ok, serialize::Error> {
+        let mut serializer = serializer.serialize_struct()?;
+        serializer.serialize_field("enabled", &self.0.is_some())?;
+        if let Some(status) = &self.0 {
+            serializer.serialize_field("in_progress", &status.in_progress)?;
+            serializer.serialize_optional("last_backup", status.last_backup.as_ref().map(Last))?;
+            serializer
+                .serialize_optional("last_failure", status.last_failure.as_ref().map(Failure))?;
+            serializer.serialize_optional("next_backup_at", status.next_backup_at)?;
+        }
+        serializer.end()
+    }
+}
+
+struct Last<'a>(&'a Backup);
+
+impl SerializeForVersion for Last<'_> {
+    fn serialize(&self, serializer: Serializer) -> Result<serialize::Ok, serialize::Error> {
+        let mut serializer = serializer.serialize_struct()?;
+        serializer.serialize_field("key", &self.0.key)?;
+        if let Some((number, hash)) = &self.0.block {
+            serializer.serialize_field("block_number", number)?;
+            serializer.serialize_field("block_hash", &crate::dto::BlockHash(hash))?;
+        }
+        serializer.serialize_field("size", &self.0.size)?;
+        serializer.serialize_field("finished_at", &self.0.finished_at)?;
+        serializer.end()
+    }
+}
+
+struct Failure<'a>(&'a BackupFailure);
+
+impl SerializeForVersion for Failure<'_> {
+    fn serialize(&self, serializer: Serializer) -> Result<serialize::Ok, serialize::Error> {
+        let mut serializer = serializer.serialize_struct()?;
+        serializer.serialize_field("error", &self.0.error)?;
+        serializer.serialize_field("failed_at", &self.0.failed_at)?;
+        serializer.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::macro_prelude::*;
+    use pathfinder_common::BlockNumber;
+    use serde_json::json;
+
+    use super::*;
+    use crate::RpcVersion;
+
+    #[tokio::test]
+    async fn disabled() {
+        let context = RpcContext::for_tests();
+
+        let output = get_backup_status(context).await.unwrap();
+
+        assert_eq!(output, Output(None));
+    }
+
+    #[tokio::test]
+    async fn reports_latest_status() {
+        let (tx, rx) = tokio::sync::watch::channel(BackupStatus::default());
+        let context = RpcContext::for_tests().with_backup_status(rx);
+
+        let status = BackupStatus {
+            in_progress: true,
+            next_backup_at: Some(1000),
+            ..Default::default()
+        };
+        tx.send(status.clone()).unwrap();
+
+        let output = get_backup_status(context).await.unwrap();
+
+        assert_eq!(output, Output(Some(status)));
+    }
+
+    #[test]
+    fn serialization() {
+        let output = Output(Some(BackupStatus {
+            in_progress: false,
+            last_backup: Some(Backup {
+                key: "pathfinder/20261015T120000Z.sqlite.zst".to_owned(),
+                block: Some((BlockNumber::new_or_panic(5), block_hash!("0xabc"))),
+                size: 1234,
+                finished_at: 1760529600,
+            }),
+            last_failure: Some(BackupFailure {
+                error: "Uploading backup".to_owned(),
+                failed_at: 1760533200,
+            }),
+            next_backup_at: Some(1760536800),
+        }));
+
+        let output = output
+            .serialize(Serializer::new(RpcVersion::PathfinderV01))
+            .unwrap();
+
+        assert_eq!(
+            output,
+            json!({
+                "enabled": true,
+                "in_progress": false,
+                "last_backup": {
+                    "key": "pathfinder/20261015T120000Z.sqlite.zst",
+                    "block_number": 5,
+                    "block_hash": "0xabc",
+                    "size": 1234,
+                    "finished_at": 1760529600,
+                },
+                "last_failure": {
+                    "error": "Uploading backup",
+                    "failed_at": 1760533200,
+                },
+                "next_backup_at": 1760536800,
+            })
+        );
+    }
+
+    #[test]
+    fn serialization_disabled() {
+        let output = Output(None)
+            .serialize(Serializer::new(RpcVersion::PathfinderV01))
+            .unwrap();
+
+        assert_eq!(output, json!({"enabled": false}));
+    }
+}
@@ -0,0 +1,475 @@
+use anyhow::Context;
+use pathfinder_common::{
+    BlockId,
+    CallParam,
+    CallResultValue,
+    ContractAddress,
+    EntryPoint,
+    StorageAddress,
+    StorageValue,
+};
+use pathfinder_crypto::Felt;
+use pathfinder_executor::{ExecutionState, FailureReason, L1BlobDataAvailability};
+use primitive_types::U256;
+
+use crate::context::RpcContext;
+use crate::dto::serialize::{self, SerializeForVersion, Serializer};
+use crate::error::ApplicationError;
+
+/// Limits the number of tokens a single request may query, since all their
+/// calls are executed by one blocking task.
+const MAX_TOKENS: usize = 100;
+
+/// Tokens whose balances are read from storage instead of calling
+/// `balanceOf`. They store the balance of an account as a u256 split over
+/// two consecutive slots of the `ERC20_balances` map.
+const KNOWN_LAYOUT_TOKENS: [ContractAddress; 2] = [
+    pathfinder_executor::ETH_FEE_TOKEN_ADDRESS,
+    pathfinder_executor::STRK_FEE_TOKEN_ADDRESS,
+];
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Input {
+    pub account: ContractAddress,
+    pub tokens: Vec<ContractAddress>,
+    pub block_id: BlockId,
+}
+
+impl crate::dto::DeserializeForVersion for Input {
+    fn deserialize(value: crate::dto::Value) -> Result<Self, serde_json::Error> {
+        value.deserialize_map(|value| {
+            Ok(Self {
+                account: value.deserialize("account").map(ContractAddress)?,
+                tokens: value.deserialize_array("tokens", |value| {
+                    value.deserialize().map(ContractAddress)
+                })?,
+                block_id: value.deserialize("block_id")?,
+            })
+        })
+    }
+}
+
+/// The balance of the account in each of the requested tokens, in request
+/// order.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Output(Vec<TokenBalance>);
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct TokenBalance {
+    token: ContractAddress,
+    /// The error message if the balance couldn't be determined, e.g. because
+    /// the token doesn't exist or isn't an ERC20 contract.
+    balance: Result<U256, String>,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Internal(anyhow::Error),
+    BlockNotFound,
+    TooManyTokens { limit: usize, requested: usize },
+}
+
+impl From<anyhow::Error> for Error {
+    fn from(e: anyhow::Error) -> Self {
+        Self::Internal(e)
+    }
+}
+
+impl From<crate::executor::ExecutionStateError> for Error {
+    fn from(error: crate::executor::ExecutionStateError) -> Self {
+        use crate::executor::ExecutionStateError::*;
+        match error {
+            BlockNotFound => Self::BlockNotFound,
+            Internal(e) => Self::Internal(e),
+        }
+    }
+}
+
+impl From<Error> for ApplicationError {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::Internal(internal) => Self::Internal(internal),
+            Error::BlockNotFound => Self::BlockNotFound,
+            Error::TooManyTokens { limit, requested } => Self::Custom(anyhow::anyhow!(
+                "Too many tokens requested: {requested} exceeds the limit of {limit}"
+            )),
+        }
+    }
+}
+
+/// Get the balances of an account in multiple ERC20 tokens at a single block.
+///
+/// The balances of the fee tokens are read directly from storage. Those of
+/// other tokens are queried by calling their `balanceOf` entry point, or
+/// `balance_of` for tokens which only have the snake case one. All calls are
+/// executed on top of the same state.
+pub async fn get_balances(context: RpcContext, input: Input) -> Result<Output, Error> {
+    if input.tokens.len() > MAX_TOKENS {
+        return Err(Error::TooManyTokens {
+            limit: MAX_TOKENS,
+            requested: input.tokens.len(),
+        });
+    }
+
+    let span = tracing::Span::current();
+    let timeout = context.config.execution_timeout;
+    let execution = tokio::task::spawn_blocking(move || {
+        let _g = span.enter();
+
+        let mut db = context
+            .storage
+            .connection()
+            .context("Creating database connection")?;
+        let db = db.transaction().context("Creating database transaction")?;
+
+        let (header, pending) = match input.block_id {
+            BlockId::Pending => {
+                let pending = context
+                    .pending_data
+                    .get(&db)
+                    .context("Querying pending data")?;
+
+                (pending.header(), Some(pending.state_update.clone()))
+            }
+            other => {
+                let block_id = other.try_into().expect("Only pending cast should fail");
+                let header = db
+                    .block_header(block_id)
+                    .context("Querying block header")?
+                    .ok_or(Error::BlockNotFound)?;
+
+                (header, None)
+            }
+        };
+        // The pending block is on top of the latest stored block.
+        let state_block = match pending {
+            Some(_) => pathfinder_storage::BlockId::Latest,
+            None => pathfinder_storage::BlockId::Number(header.number),
+        };
+        // Storage values which are missing are zero balances, which they are not
+        // if the state of the block has been pruned.
+        if !db
+            .block_state_exists(state_block)
+            .context("Querying block state existence")?
+        {
+            return Err(Error::BlockNotFound);
+        }
+
+        let mut balances = input
+            .tokens
+            .iter()
+            .map(|&token| {
+                if !KNOWN_LAYOUT_TOKENS.contains(&token) {
+                    return Ok(None);
+                }
+
+                let low_key =
+                    StorageAddress::from_map_name_and_key(b"ERC20_balances", *input.account.get());
+                let high_key = StorageAddress::new(low_key.0 + Felt::ONE)
+                    .context("Balance storage address overflow")?;
+                let read = |key| -> anyhow::Result<StorageValue> {
+                    if let Some(value) = pending
+                        .as_ref()
+                        .and_then(|pending| pending.storage_value(token, key))
+                    {
+                        return Ok(value);
+                    }
+                    let value = db
+                        .storage_value(state_block, token, key)
+                        .context("Querying storage value")?;
+                    Ok(value.unwrap_or_default())
+                };
+
+                let low = read(low_key)?;
+                let high = read(high_key)?;
+                Ok(Some(u256(&[
+                    CallResultValue(low.0),
+                    CallResultValue(high.0),
+                ])))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let execution_state = || {
+            ExecutionState::simulation(
+                &db,
+                context.chain_id,
+                header.clone(),
+                pending.clone(),
+                L1BlobDataAvailability::Disabled,
+                context.config.custom_versioned_constants.clone(),
+            )
+            .with_limits(context.config.execution_limits)
+            .with_class_cache(context.class_cache.clone())
+        };
+        let mut results = input
+            .tokens
+            .iter()
+            .zip(&balances)
+            .filter(|(_, balance)| balance.is_none())
+            .map(|(&token, _)| (token, None))
+            .collect::<Vec<_>>();
+
+        // Tokens following the older naming convention only have `balance_of`, so
+        // those are called again once all `balanceOf` calls are done.
+        for entry_point in [&b"balanceOf"[..], b"balance_of"] {
+            let retry = results
+                .iter_mut()
+                .filter(|(_, result)| {
+                    matches!(
+                        result,
+                        None | Some(Err(pathfinder_executor::CallError::ContractError(
+                            _,
+                            _,
+                            FailureReason::EntryPointNotFound
+                        )))
+                    )
+                })
+                .collect::<Vec<_>>();
+            if retry.is_empty() {
+                break;
+            }
+
+            let calls = retry
+                .iter()
+                .map(|(token, _)| {
+                    (
+                        *token,
+                        EntryPoint::hashed(entry_point),
+                        vec![CallParam(*input.account.get())],
+                    )
+                })
+                .collect();
+            let outcomes =
+                pathfinder_executor::call_many(execution_state(), calls).map_err(|error| {
+                    match error {
+                        pathfinder_executor::CallError::Internal(e) => e,
+                        other => anyhow::anyhow!("Setting up execution state: {other:?}"),
+                    }
+                })?;
+            for ((_, result), outcome) in retry.into_iter().zip(outcomes) {
+                *result = Some(outcome);
+            }
+        }
+
+        let mut results = results.into_iter();
+        for balance in balances.iter_mut().filter(|balance| balance.is_none()) {
+            let (_, result) = results.next().expect("One result per called token");
+            *balance = Some(match result.expect("Every token is called") {
+                Ok(retdata) => u256(&retdata),
+                Err(pathfinder_executor::CallError::ContractNotFound) => {
+                    Err("Contract not found".to_owned())
+                }
+                Err(pathfinder_executor::CallError::ContractError(error, ..)) => {
+                    Err(format!("Execution error: {error}"))
+                }
+                Err(pathfinder_executor::CallError::ExecutionResourcesExceeded) => {
+                    Err("Execution resources exceeded".to_owned())
+                }
+                Err(pathfinder_executor::CallError::Custom(error)) => Err(error.to_string()),
+                Err(pathfinder_executor::CallError::Internal(error)) => {
+                    return Err(Error::Internal(error))
+                }
+            });
+        }
+
+        let balances = input
+            .tokens
+            .into_iter()
+            .zip(balances)
+            .map(|(token, balance)| TokenBalance {
+                token,
+                balance: balance.expect("All balances determined"),
+            })
+            .collect();
+
+        Ok(Output(balances))
+    });
+
+    crate::executor::with_timeout(timeout, execution)
+        .await
+        .ok_or_else(|| anyhow::anyhow!("Querying balances timed out"))?
+        .context("Querying balances")?
+}
+
+/// Parses the u256 returned by `balanceOf`, which is split into its low and
+/// high 128 bits. Some older tokens return a single felt instead.
+fn u256(retdata: &[CallResultValue]) -> Result<U256, String> {
+    let half = |value: &CallResultValue| -> Result<U256, String> {
+        let bytes = value.0.as_be_bytes();
+        if bytes[..16] != [0u8; 16] {
+            return Err(format!("Balance half {} exceeds 128 bits", value.0));
+        }
+        Ok(U256::from_big_endian(&bytes[16..]))
+    };
+
+    match retdata {
+        [value] => Ok(U256::from_big_endian(value.0.as_be_bytes())),
+        [low, high] => Ok((half(high)? << 128) | half(low)?),
+        other => Err(format!(
+            "Expected a u256 balance, got {} return values",
+            other.len()
+        )),
+    }
+}
+
+impl SerializeForVersion for Output {
+    fn serialize(&self, serializer: Serializer) -> Result<serialize::Ok, serialize::Error> {
+        serializer.serialize_iter(self.0.len(), &mut self.0.iter())
+    }
+}
+
+impl SerializeForVersion for &TokenBalance {
+    fn serialize(&self, serializer: Serializer) -> Result<serialize::Ok, serialize::Error> {
+        let mut serializer = serializer.serialize_struct()?;
+        serializer.serialize_field("token", &crate::dto::Address(&self.token))?;
+        match &self.balance {
+            Ok(balance) => serializer.serialize_field("balance", &crate::dto::U256Hex(*balance))?,
+            Err(error) => serializer.serialize_field("error", error)?,
+        }
+        serializer.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::macro_prelude::*;
+    use serde_json::json;
+    use starknet_gateway_test_fixtures::class_definitions::ERC20_CONTRACT_DEFINITION_CLASS_HASH;
+
+    use super::*;
+    use crate::dto::DeserializeForVersion;
+    use crate::RpcVersion;
+
+    #[rstest::rstest]
+    #[case::positional(json!(["0x1", ["0x2", "0x3"], "latest"]))]
+    #[case::named(json!({"account": "0x1", "tokens": ["0x2", "0x3"], "block_id": "latest"}))]
+    fn parsing(#[case] input: serde_json::Value) {
+        let expected = Input {
+            account: contract_address!("0x1"),
+            tokens: vec![contract_address!("0x2"), contract_address!("0x3")],
+            block_id: BlockId::Latest,
+        };
+
+        let input =
+            Input::deserialize(crate::dto::Value::new(input, RpcVersion::PathfinderV01)).unwrap();
+
+        assert_eq!(input, expected);
+    }
+
+    #[test]
+    fn u256_parsing() {
+        assert_eq!(
+            u256(&[call_result_value!("0x5"), call_result_value!("0x1")]),
+            Ok((U256::one() << 128) + 5)
+        );
+        assert_eq!(u256(&[call_result_value!("0x7")]), Ok(U256::from(7)));
+        u256(&[]).unwrap_err();
+        u256(&[
+            call_result_value!("0x100000000000000000000000000000000"),
+            call_result_value!("0x0"),
+        ])
+        .unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn balances() {
+        // An ERC20 token which isn't a fee token, so its balance is queried by
+        // calling `balanceOf`.
+        let token = contract_address!("0x7070");
+        let account = contract_address!("0xc01");
+        let (storage, ..) = crate::test_setup::test_storage(
+            pathfinder_common::StarknetVersion::new(0, 13, 1, 0),
+            |state_update| {
+                state_update
+                    .with_deployed_contract(token, ERC20_CONTRACT_DEFINITION_CLASS_HASH)
+                    .with_storage_update(
+                        token,
+                        StorageAddress::from_map_name_and_key(b"ERC20_balances", account.0),
+                        storage_value!("0x2a"),
+                    )
+            },
+        )
+        .await;
+        let context = RpcContext::for_tests().with_storage(storage);
+        let missing = contract_address!("0xdead");
+        let input = Input {
+            account,
+            tokens: vec![
+                pathfinder_executor::ETH_FEE_TOKEN_ADDRESS,
+                missing,
+                token,
+                pathfinder_executor::STRK_FEE_TOKEN_ADDRESS,
+            ],
+            block_id: BlockId::Latest,
+        };
+
+        let output = get_balances(context, input).await.unwrap();
+
+        let funded = U256::from_big_endian(
+            storage_value!("0x10000000000000000000000000000")
+                .0
+                .as_be_bytes(),
+        );
+        assert_eq!(
+            output,
+            Output(vec![
+                TokenBalance {
+                    token: pathfinder_executor::ETH_FEE_TOKEN_ADDRESS,
+                    balance: Ok(funded),
+                },
+                TokenBalance {
+                    token: missing,
+                    balance: Err("Contract not found".to_owned()),
+                },
+                TokenBalance {
+                    token,
+                    balance: Ok(U256::from(0x2a)),
+                },
+                TokenBalance {
+                    token: pathfinder_executor::STRK_FEE_TOKEN_ADDRESS,
+                    balance: Ok(funded),
+                },
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn too_many_tokens() {
+        let context = RpcContext::for_tests();
+        let input = Input {
+            account: contract_address!("0x1"),
+            tokens: vec![contract_address!("0x2"); MAX_TOKENS + 1],
+            block_id: BlockId::Latest,
+        };
+
+        let error = get_balances(context, input).await.unwrap_err();
+
+        assert!(matches!(error, Error::TooManyTokens { .. }));
+    }
+
+    #[test]
+    fn serialization() {
+        let output = Output(vec![
+            TokenBalance {
+                token: contract_address!("0x2"),
+                balance: Ok(U256::from(0x10)),
+            },
+            TokenBalance {
+                token: contract_address!("0x3"),
+                balance: Err("Contract not found".to_owned()),
+            },
+        ]);
+
+        let output = output
+            .serialize(Serializer::new(RpcVersion::PathfinderV01))
+            .unwrap();
+
+        assert_eq!(
+            output,
+            json!([
+                {"token": "0x2", "balance": "0x10"},
+                {"token": "0x3", "error": "Contract not found"},
+            ])
+        );
+    }
+}
@@ -0,0 +1,188 @@
+use anyhow::Context;
+use pathfinder_common::BlockNumber;
+use pathfinder_storage::BlockStats;
+
+use crate::context::RpcContext;
+use crate::dto::serialize::{self, SerializeForVersion, Serializer};
+use crate::error::ApplicationError;
+
+/// The largest number of blocks a single request may cover.
+const MAX_BLOCK_RANGE: u64 = 1024;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Input {
+    pub from_block: BlockNumber,
+    pub to_block: BlockNumber,
+}
+
+impl crate::dto::DeserializeForVersion for Input {
+    fn deserialize(value: crate::dto::Value) -> Result<Self, serde_json::Error> {
+        value.deserialize_map(|value| {
+            Ok(Self {
+                from_block: value.deserialize_serde("from_block")?,
+                to_block: value.deserialize_serde("to_block")?,
+            })
+        })
+    }
+}
+
+/// The aggregates of the requested blocks, oldest first.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Output(Vec<BlockStats>);
+
+#[derive(Debug)]
+pub enum Error {
+    Internal(anyhow::Error),
+    InvalidRange,
+}
+
+impl From<anyhow::Error> for Error {
+    fn from(e: anyhow::Error) -> Self {
+        Self::Internal(e)
+    }
+}
+
+impl From<Error> for ApplicationError {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::Internal(internal) => Self::Internal(internal),
+            Error::InvalidRange => Self::InvalidBlockRange {
+                limit: MAX_BLOCK_RANGE,
+            },
+        }
+    }
+}
+
+/// Get the transaction counts by type, event count, total fees and state diff
+/// sizes of the blocks in `from_block..=to_block`.
+///
+/// The aggregates are recorded when a block is stored, and filled in by a
+/// background migration for blocks stored by older versions. Blocks which
+/// don't exist are left out, while ranges including blocks which haven't been
+/// filled in yet fail until the migration gets to them.
+pub async fn get_block_stats(context: RpcContext, input: Input) -> Result<Output, Error> {
+    if input.to_block < input.from_block
+        || input.to_block.get() - input.from_block.get() >= MAX_BLOCK_RANGE
+    {
+        return Err(Error::InvalidRange);
+    }
+
+    let span = tracing::Span::current();
+
+    let jh = tokio::task::spawn_blocking(move || {
+        let _g = span.enter();
+        let mut db = context
+            .storage
+            .connection()
+            .context("Opening database connection")?;
+
+        let tx = db.transaction().context("Creating database transaction")?;
+
+        let stats = tx
+            .block_stats(input.from_block, input.to_block)
+            .context("Querying block stats")?;
+
+        Ok(Output(stats))
+    });
+
+    jh.await.context("Database read panic or shutting down")?
+}
+
+impl SerializeForVersion for Output {
+    fn serialize(&self, serializer: Serializer) -> Result<serialize::Ok, serialize::Error> {
+        serializer.serialize_iter(self.0.len(), &mut self.0.iter().map(Stats))
+    }
+}
+
+struct Stats<'a>(&'a BlockStats);
+
+impl SerializeForVersion for Stats<'_> {
+    fn serialize(&self, serializer: Serializer) -> Result<serialize::Ok, serialize::Error> {
+        let transactions = &self.0.transactions;
+        let state_diff = &self.0.state_diff;
+
+        let mut serializer = serializer.serialize_struct()?;
+        serializer.serialize_field("block_number", &self.0.block_number)?;
+        serializer.serialize_field("declare_transactions", &transactions.declare)?;
+        serializer.serialize_field("deploy_transactions", &transactions.deploy)?;
+        serializer.serialize_field("deploy_account_transactions", &transactions.deploy_account)?;
+        serializer.serialize_field("invoke_transactions", &transactions.invoke)?;
+        serializer.serialize_field("l1_handler_transactions", &transactions.l1_handler)?;
+        serializer.serialize_field("events", &self.0.events)?;
+        serializer.serialize_field("total_fee_wei", &crate::dto::Felt(&self.0.total_fee_wei.0))?;
+        serializer.serialize_field("total_fee_fri", &crate::dto::Felt(&self.0.total_fee_fri.0))?;
+        serializer.serialize_field("storage_diffs", &state_diff.storage_diffs)?;
+        serializer.serialize_field("nonce_updates", &state_diff.nonce_updates)?;
+        serializer.serialize_field("declared_classes", &state_diff.declared_classes)?;
+        serializer.serialize_field("deployed_contracts", &state_diff.deployed_contracts)?;
+        serializer.serialize_field("replaced_classes", &state_diff.replaced_classes)?;
+        serializer.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::dto::DeserializeForVersion;
+    use crate::RpcVersion;
+
+    #[rstest::rstest]
+    #[case::positional(json!([1, 5]))]
+    #[case::named(json!({"from_block": 1, "to_block": 5}))]
+    fn parsing(#[case] input: serde_json::Value) {
+        let expected = Input {
+            from_block: BlockNumber::new_or_panic(1),
+            to_block: BlockNumber::new_or_panic(5),
+        };
+
+        let input =
+            Input::deserialize(crate::dto::Value::new(input, RpcVersion::PathfinderV01)).unwrap();
+
+        assert_eq!(input, expected);
+    }
+
+    #[tokio::test]
+    async fn stored_stats() {
+        let ctx = RpcContext::for_tests();
+        let input = Input {
+            from_block: BlockNumber::GENESIS,
+            to_block: BlockNumber::new_or_panic(100),
+        };
+
+        let output = get_block_stats(ctx.clone(), input).await.unwrap();
+
+        let mut db = ctx.storage.connection().unwrap();
+        let tx = db.transaction().unwrap();
+        let expected = tx
+            .block_stats(BlockNumber::GENESIS, BlockNumber::new_or_panic(100))
+            .unwrap();
+        assert_eq!(output, Output(expected));
+        assert_eq!(
+            output.0.iter().map(|s| s.block_number).collect::<Vec<_>>(),
+            vec![
+                BlockNumber::GENESIS,
+                BlockNumber::new_or_panic(1),
+                BlockNumber::new_or_panic(2)
+            ]
+        );
+        assert_eq!(output.0[0].transactions.invoke, 1);
+    }
+
+    #[rstest::rstest]
+    #[case::reversed(5, 4)]
+    #[case::too_long(0, MAX_BLOCK_RANGE)]
+    #[tokio::test]
+    async fn invalid_range(#[case] from_block: u64, #[case] to_block: u64) {
+        let ctx = RpcContext::for_tests();
+        let input = Input {
+            from_block: BlockNumber::new_or_panic(from_block),
+            to_block: BlockNumber::new_or_panic(to_block),
+        };
+
+        let error = get_block_stats(ctx, input).await.unwrap_err();
+
+        assert_matches::assert_matches!(error, Error::InvalidRange);
+    }
+}
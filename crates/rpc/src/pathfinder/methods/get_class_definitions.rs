@@ -0,0 +1,374 @@
+use anyhow::Context;
+use pathfinder_common::BlockNumber;
+use pathfinder_storage::DeclaredClass;
+
+use crate::context::RpcContext;
+use crate::dto::serialize::{self, ResponseBudget, SerializeForVersion, Serializer};
+use crate::RpcVersion;
+
+crate::error::generate_rpc_error_subset!(Error);
+
+/// The number of classes after which a page is cut at the next block boundary.
+const PAGE_SIZE: usize = 1000;
+/// The page size used when the full definitions are requested, since these can
+/// be several megabytes each.
+const PAGE_SIZE_WITH_DEFINITIONS: usize = 20;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Input {
+    pub from_block: BlockNumber,
+    pub to_block: BlockNumber,
+    pub include_definitions: bool,
+}
+
+impl crate::dto::DeserializeForVersion for Input {
+    fn deserialize(value: crate::dto::Value) -> Result<Self, serde_json::Error> {
+        value.deserialize_map(|value| {
+            Ok(Self {
+                from_block: value.deserialize_serde("from_block")?,
+                to_block: value.deserialize_serde("to_block")?,
+                include_definitions: value
+                    .deserialize_optional_serde("include_definitions")?
+                    .unwrap_or_default(),
+            })
+        })
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Output {
+    classes: Vec<Class>,
+    /// The block to continue from if the range did not fit into a single page.
+    next_block: Option<BlockNumber>,
+}
+
+#[derive(Debug, PartialEq)]
+struct Class {
+    declared: DeclaredClass,
+    definitions: Option<Definitions>,
+}
+
+#[derive(Debug, PartialEq)]
+struct Definitions {
+    definition: Option<serde_json::Value>,
+    casm_definition: Option<serde_json::Value>,
+}
+
+/// Get the classes declared within `from_block..=to_block`, along with their
+/// compiled class hashes and optionally their Sierra and CASM definitions.
+///
+/// Results are paged on block boundaries, `next_block` is set to the
+/// `from_block` of the next request if the range did not fit into one page.
+/// Pages are also cut short once they exceed
+/// [RpcConfig::max_response_size](crate::context::RpcConfig::max_response_size).
+pub async fn get_class_definitions(context: RpcContext, input: Input) -> Result<Output, Error> {
+    let span = tracing::Span::current();
+
+    let jh = tokio::task::spawn_blocking(move || {
+        let _g = span.enter();
+        let mut db = context
+            .storage
+            .connection()
+            .context("Opening database connection")?;
+
+        let tx = db.transaction().context("Creating database transaction")?;
+
+        let page_size = if input.include_definitions {
+            PAGE_SIZE_WITH_DEFINITIONS
+        } else {
+            PAGE_SIZE
+        };
+
+        let (declared, next_block) = tx
+            .declared_classes_in_range(input.from_block, input.to_block, page_size)
+            .context("Querying declared classes")?;
+
+        let mut budget =
+            ResponseBudget::new(RpcVersion::PathfinderV01, context.config.max_response_size);
+        let mut classes: Vec<Class> = Vec::new();
+        let mut next_block = next_block;
+        // Set if the first block of the page exceeds the budget, in which case
+        // it is still returned in full.
+        let mut completing_first_block = false;
+
+        for declared in declared {
+            if completing_first_block && declared.block_number != classes[0].declared.block_number {
+                next_block = Some(declared.block_number);
+                break;
+            }
+
+            let definitions = if input.include_definitions {
+                let definition = tx
+                    .class_definition(declared.class_hash)
+                    .context("Querying class definition")?
+                    .map(|definition| parse_definition(&definition))
+                    .transpose()?;
+                let casm_definition = match declared.casm_hash {
+                    Some(_) => tx
+                        .casm_definition(declared.class_hash)
+                        .context("Querying compiled class definition")?
+                        .map(|definition| parse_definition(&definition))
+                        .transpose()?,
+                    None => None,
+                };
+
+                Some(Definitions {
+                    definition,
+                    casm_definition,
+                })
+            } else {
+                None
+            };
+
+            let class = Class {
+                declared,
+                definitions,
+            };
+
+            // Pages end on block boundaries, so the block which exceeds the
+            // budget is left out entirely.
+            if !completing_first_block && !budget.try_add(&&class).context("Serializing class")? {
+                let block_number = class.declared.block_number;
+                let block_start = classes
+                    .iter()
+                    .position(|class| class.declared.block_number == block_number)
+                    .unwrap_or(classes.len());
+                if block_start > 0 {
+                    classes.truncate(block_start);
+                    next_block = Some(block_number);
+                    break;
+                }
+                completing_first_block = true;
+            }
+
+            classes.push(class);
+        }
+
+        Ok(Output {
+            classes,
+            next_block,
+        })
+    });
+
+    jh.await.context("Database read panic or shutting down")?
+}
+
+fn parse_definition(definition: &[u8]) -> anyhow::Result<serde_json::Value> {
+    serde_json::from_slice(definition).context("Parsing class definition")
+}
+
+impl SerializeForVersion for Output {
+    fn serialize(&self, serializer: Serializer) -> Result<serialize::Ok, serialize::Error> {
+        let mut serializer = serializer.serialize_struct()?;
+        serializer.serialize_iter("classes", self.classes.len(), &mut self.classes.iter())?;
+        serializer.serialize_optional("next_block", self.next_block)?;
+        serializer.end()
+    }
+}
+
+impl SerializeForVersion for &Class {
+    fn serialize(&self, serializer: Serializer) -> Result<serialize::Ok, serialize::Error> {
+        let mut serializer = serializer.serialize_struct()?;
+        serializer.serialize_field("block_number", &self.declared.block_number)?;
+        serializer.serialize_field("class_hash", &crate::dto::Felt(&self.declared.class_hash.0))?;
+        serializer.serialize_optional(
+            "compiled_class_hash",
+            self.declared
+                .casm_hash
+                .as_ref()
+                .map(|casm_hash| crate::dto::Felt(&casm_hash.0)),
+        )?;
+        if let Some(definitions) = &self.definitions {
+            serializer.serialize_optional("definition", definitions.definition.as_ref())?;
+            serializer
+                .serialize_optional("casm_definition", definitions.casm_definition.as_ref())?;
+        }
+        serializer.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::macro_prelude::*;
+    use pathfinder_common::ClassHash;
+    use serde_json::json;
+
+    use super::*;
+    use crate::dto::DeserializeForVersion;
+
+    #[rstest::rstest]
+    #[case::positional(json!([1, 5, true]))]
+    #[case::named(json!({"from_block": 1, "to_block": 5, "include_definitions": true}))]
+    fn parsing(#[case] input: serde_json::Value) {
+        let expected = Input {
+            from_block: BlockNumber::new_or_panic(1),
+            to_block: BlockNumber::new_or_panic(5),
+            include_definitions: true,
+        };
+
+        let input =
+            Input::deserialize(crate::dto::Value::new(input, RpcVersion::PathfinderV01)).unwrap();
+
+        assert_eq!(input, expected);
+    }
+
+    #[test]
+    fn parsing_defaults_to_hashes_only() {
+        let input = json!({"from_block": 1, "to_block": 5});
+
+        let input =
+            Input::deserialize(crate::dto::Value::new(input, RpcVersion::PathfinderV01)).unwrap();
+
+        assert!(!input.include_definitions);
+    }
+
+    #[tokio::test]
+    async fn hashes_only() {
+        let ctx = RpcContext::for_tests();
+        let input = Input {
+            from_block: BlockNumber::GENESIS,
+            to_block: BlockNumber::MAX,
+            include_definitions: false,
+        };
+
+        let output = get_class_definitions(ctx, input).await.unwrap();
+
+        assert_eq!(output.next_block, None);
+        assert_eq!(output.classes.len(), 3);
+        let sierra = output
+            .classes
+            .iter()
+            .find(|class| class.declared.class_hash == class_hash_bytes!(b"class 2 hash (sierra)"))
+            .unwrap();
+        assert_eq!(
+            sierra.declared.casm_hash,
+            Some(casm_hash_bytes!(b"non-existent"))
+        );
+        assert!(output
+            .classes
+            .iter()
+            .all(|class| class.definitions.is_none()));
+    }
+
+    #[tokio::test]
+    async fn with_definitions() {
+        let ctx = RpcContext::for_tests();
+        {
+            // The test storage stores an empty CASM definition.
+            let mut db = ctx.storage.connection().unwrap();
+            let tx = db.transaction().unwrap();
+            tx.update_sierra_class(
+                &sierra_hash_bytes!(b"class 2 hash (sierra)"),
+                starknet_gateway_test_fixtures::class_definitions::CAIRO_0_11_SIERRA,
+                &casm_hash_bytes!(b"non-existent"),
+                br#"{"bytecode":[]}"#,
+            )
+            .unwrap();
+            tx.commit().unwrap();
+        }
+        let input = Input {
+            from_block: BlockNumber::GENESIS,
+            to_block: BlockNumber::MAX,
+            include_definitions: true,
+        };
+
+        let output = get_class_definitions(ctx, input).await.unwrap();
+
+        assert_eq!(output.classes.len(), 3);
+        for class in &output.classes {
+            let definitions = class.definitions.as_ref().unwrap();
+            assert!(definitions.definition.is_some());
+            assert_eq!(
+                definitions.casm_definition.is_some(),
+                class.declared.casm_hash.is_some()
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn response_size_limit() {
+        let mut ctx = RpcContext::for_tests();
+        let input = Input {
+            from_block: BlockNumber::GENESIS,
+            to_block: BlockNumber::MAX,
+            include_definitions: true,
+        };
+        let all = get_class_definitions(ctx.clone(), input).await.unwrap();
+
+        // Only the first block of each page fits.
+        ctx.config.max_response_size = Some(1.try_into().unwrap());
+        let mut classes = Vec::new();
+        let mut from_block = BlockNumber::GENESIS;
+        loop {
+            let input = Input {
+                from_block,
+                to_block: BlockNumber::MAX,
+                include_definitions: true,
+            };
+            let output = get_class_definitions(ctx.clone(), input).await.unwrap();
+
+            let first_block = output.classes[0].declared.block_number;
+            assert!(output
+                .classes
+                .iter()
+                .all(|class| class.declared.block_number == first_block));
+
+            classes.extend(output.classes);
+            match output.next_block {
+                Some(next_block) => from_block = next_block,
+                None => break,
+            }
+        }
+
+        assert_eq!(classes, all.classes);
+    }
+
+    #[test]
+    fn serialization() {
+        let output = Output {
+            classes: vec![
+                Class {
+                    declared: DeclaredClass {
+                        block_number: BlockNumber::new_or_panic(3),
+                        class_hash: class_hash!("0x1"),
+                        casm_hash: None,
+                    },
+                    definitions: None,
+                },
+                Class {
+                    declared: DeclaredClass {
+                        block_number: BlockNumber::new_or_panic(4),
+                        class_hash: ClassHash(sierra_hash!("0x2").0),
+                        casm_hash: Some(casm_hash!("0x3")),
+                    },
+                    definitions: Some(Definitions {
+                        definition: Some(json!({"sierra_program": []})),
+                        casm_definition: Some(json!({"bytecode": []})),
+                    }),
+                },
+            ],
+            next_block: Some(BlockNumber::new_or_panic(9)),
+        };
+
+        let output = output
+            .serialize(Serializer::new(RpcVersion::PathfinderV01))
+            .unwrap();
+
+        assert_eq!(
+            output,
+            json!({
+                "classes": [
+                    {"block_number": 3, "class_hash": "0x1"},
+                    {
+                        "block_number": 4,
+                        "class_hash": "0x2",
+                        "compiled_class_hash": "0x3",
+                        "definition": {"sierra_program": []},
+                        "casm_definition": {"bytecode": []},
+                    },
+                ],
+                "next_block": 9,
+            })
+        );
+    }
+}
@@ -0,0 +1,439 @@
+use std::collections::HashMap;
+
+use anyhow::Context;
+use pathfinder_common::{BlockId, BlockNumber, CasmHash, ClassHash};
+use pathfinder_crypto::Felt;
+
+use crate::context::RpcContext;
+use crate::dto::serialize::{self, SerializeForVersion, Serializer};
+use crate::v02::types::OffsetSerde;
+
+crate::error::generate_rpc_error_subset!(Error: BlockNotFound, ClassHashNotFound);
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Input {
+    pub class_hash: ClassHash,
+    pub block_id: BlockId,
+}
+
+impl crate::dto::DeserializeForVersion for Input {
+    fn deserialize(value: crate::dto::Value) -> Result<Self, serde_json::Error> {
+        value.deserialize_map(|value| {
+            Ok(Self {
+                class_hash: ClassHash(value.deserialize("class_hash")?),
+                block_id: value
+                    .deserialize_optional("block_id")?
+                    .unwrap_or(BlockId::Latest),
+            })
+        })
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Output {
+    class_hash: ClassHash,
+    /// None if the class has only been declared in the pending block.
+    declared_at: Option<BlockNumber>,
+    sierra: Option<SierraInfo>,
+    entry_points: EntryPoints,
+    abi: Option<serde_json::Value>,
+}
+
+#[derive(Debug, PartialEq)]
+struct SierraInfo {
+    sierra_version: String,
+    contract_class_version: String,
+    compiled_class_hash: Option<CasmHash>,
+}
+
+/// The parts of a stored class definition described by this method. Everything
+/// else, most notably the program, is skipped when parsing.
+#[derive(serde::Deserialize)]
+struct Definition {
+    /// Only present in Sierra classes.
+    #[serde(default)]
+    contract_class_version: Option<String>,
+    entry_points_by_type: EntryPoints,
+    /// A JSON array in Cairo 0 classes, and its string encoding in Sierra
+    /// classes.
+    #[serde(default)]
+    abi: Option<serde_json::Value>,
+}
+
+/// The parts of a stored compiled class definition described by this method.
+#[derive(serde::Deserialize)]
+struct CasmDefinition {
+    entry_points_by_type: EntryPoints,
+}
+
+#[derive(Debug, Default, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+struct EntryPoints {
+    #[serde(default)]
+    constructor: Vec<EntryPoint>,
+    #[serde(default)]
+    external: Vec<EntryPoint>,
+    #[serde(default)]
+    l1_handler: Vec<EntryPoint>,
+}
+
+#[serde_with::serde_as]
+#[derive(Debug, PartialEq, serde::Deserialize)]
+struct EntryPoint {
+    #[serde_as(as = "crate::felt::RpcFelt")]
+    selector: Felt,
+    /// The bytecode offset of Cairo 0 entry points, and of the compiled entry
+    /// points of Sierra classes.
+    #[serde_as(as = "Option<OffsetSerde>")]
+    #[serde(default)]
+    offset: Option<u64>,
+    /// Only present in Sierra classes.
+    #[serde(default)]
+    function_idx: Option<u64>,
+}
+
+/// Get the metadata of a class, i.e. its entry points, ABI, Sierra version and
+/// compiled class hash, and the block it was declared in, without its program.
+pub async fn get_class_info(context: RpcContext, input: Input) -> Result<Output, Error> {
+    let span = tracing::Span::current();
+
+    let jh = tokio::task::spawn_blocking(move || {
+        let _g = span.enter();
+        let mut db = context
+            .storage
+            .connection()
+            .context("Opening database connection")?;
+
+        let tx = db.transaction().context("Creating database transaction")?;
+
+        let is_pending = if input.block_id.is_pending() {
+            context
+                .pending_data
+                .get(&tx)
+                .context("Querying pending data")?
+                .state_update
+                .class_is_declared(input.class_hash)
+        } else {
+            false
+        };
+
+        let block_id = match input.block_id {
+            BlockId::Pending => pathfinder_storage::BlockId::Latest,
+            other => other.try_into().expect("Only pending cast should fail"),
+        };
+
+        if !tx
+            .block_exists(block_id)
+            .context("Querying block existence")?
+        {
+            return Err(Error::BlockNotFound);
+        }
+
+        // A class declared in the pending block has no declaration block yet.
+        let definition = if is_pending {
+            tx.class_definition_with_block_number(input.class_hash)
+        } else {
+            tx.class_definition_at_with_block_number(block_id, input.class_hash)
+                .map(|definition| {
+                    definition.map(|(block_number, definition)| (Some(block_number), definition))
+                })
+        }
+        .context("Fetching class definition")?;
+
+        let Some((declared_at, definition)) = definition else {
+            return Err(Error::ClassHashNotFound);
+        };
+
+        let Definition {
+            contract_class_version,
+            mut entry_points_by_type,
+            abi,
+        } = serde_json::from_slice(&definition).context("Parsing class definition")?;
+
+        let sierra = match contract_class_version {
+            Some(contract_class_version) => {
+                let sierra_version = pathfinder_compiler::sierra_version(&definition)?;
+
+                let compiled_class_hash = tx
+                    .casm_hash(input.class_hash)
+                    .context("Querying compiled class hash")?;
+                let casm_definition = tx
+                    .casm_definition(input.class_hash)
+                    .context("Querying compiled class definition")?;
+                if let Some(casm_definition) = casm_definition {
+                    let casm_definition: CasmDefinition = serde_json::from_slice(&casm_definition)
+                        .context("Parsing compiled class definition")?;
+                    entry_points_by_type
+                        .add_compiled_offsets(&casm_definition.entry_points_by_type);
+                }
+
+                Some(SierraInfo {
+                    sierra_version,
+                    contract_class_version,
+                    compiled_class_hash,
+                })
+            }
+            None => None,
+        };
+
+        Ok(Output {
+            class_hash: input.class_hash,
+            declared_at,
+            sierra,
+            entry_points: entry_points_by_type,
+            abi: abi.and_then(parse_abi),
+        })
+    });
+
+    jh.await.context("Database read panic or shutting down")?
+}
+
+impl EntryPoints {
+    /// Sets the offsets of Sierra entry points to those of the compiled entry
+    /// points with the same selector.
+    fn add_compiled_offsets(&mut self, compiled: &EntryPoints) {
+        fn add(entry_points: &mut [EntryPoint], compiled: &[EntryPoint]) {
+            let offsets = compiled
+                .iter()
+                .filter_map(|entry_point| Some((entry_point.selector, entry_point.offset?)))
+                .collect::<HashMap<_, _>>();
+            for entry_point in entry_points {
+                entry_point.offset = offsets.get(&entry_point.selector).copied();
+            }
+        }
+
+        add(&mut self.constructor, &compiled.constructor);
+        add(&mut self.external, &compiled.external);
+        add(&mut self.l1_handler, &compiled.l1_handler);
+    }
+}
+
+/// Sierra classes embed their ABI as a string, which is returned as JSON if it
+/// can be parsed as such. Empty ABIs are omitted.
+fn parse_abi(abi: serde_json::Value) -> Option<serde_json::Value> {
+    match abi {
+        serde_json::Value::Null => None,
+        serde_json::Value::String(abi) if abi.trim().is_empty() => None,
+        serde_json::Value::String(abi) => {
+            Some(serde_json::from_str(&abi).unwrap_or(serde_json::Value::String(abi)))
+        }
+        abi => Some(abi),
+    }
+}
+
+impl SerializeForVersion for Output {
+    fn serialize(&self, serializer: Serializer) -> Result<serialize::Ok, serialize::Error> {
+        let mut serializer = serializer.serialize_struct()?;
+        serializer.serialize_field("class_hash", &crate::dto::Felt(&self.class_hash.0))?;
+        serializer.serialize_optional("declared_at", self.declared_at)?;
+        if let Some(sierra) = &self.sierra {
+            serializer.serialize_field("sierra_version", &sierra.sierra_version)?;
+            serializer.serialize_field("contract_class_version", &sierra.contract_class_version)?;
+            serializer.serialize_optional(
+                "compiled_class_hash",
+                sierra
+                    .compiled_class_hash
+                    .as_ref()
+                    .map(|casm_hash| crate::dto::Felt(&casm_hash.0)),
+            )?;
+        }
+        serializer.serialize_field("entry_points_by_type", &&self.entry_points)?;
+        serializer.serialize_optional("abi", self.abi.as_ref())?;
+        serializer.end()
+    }
+}
+
+impl SerializeForVersion for &EntryPoints {
+    fn serialize(&self, serializer: Serializer) -> Result<serialize::Ok, serialize::Error> {
+        let mut serializer = serializer.serialize_struct()?;
+        serializer.serialize_iter(
+            "CONSTRUCTOR",
+            self.constructor.len(),
+            &mut self.constructor.iter(),
+        )?;
+        serializer.serialize_iter("EXTERNAL", self.external.len(), &mut self.external.iter())?;
+        serializer.serialize_iter(
+            "L1_HANDLER",
+            self.l1_handler.len(),
+            &mut self.l1_handler.iter(),
+        )?;
+        serializer.end()
+    }
+}
+
+impl SerializeForVersion for &EntryPoint {
+    fn serialize(&self, serializer: Serializer) -> Result<serialize::Ok, serialize::Error> {
+        let mut serializer = serializer.serialize_struct()?;
+        serializer.serialize_field("selector", &crate::dto::Felt(&self.selector))?;
+        serializer.serialize_optional("offset", self.offset)?;
+        serializer.serialize_optional("function_idx", self.function_idx)?;
+        serializer.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+    use pathfinder_common::macro_prelude::*;
+    use serde_json::json;
+
+    use super::*;
+    use crate::dto::DeserializeForVersion;
+    use crate::RpcVersion;
+
+    #[rstest::rstest]
+    #[case::positional(json!(["0x1", "latest"]))]
+    #[case::named(json!({"class_hash": "0x1", "block_id": "latest"}))]
+    #[case::default_block(json!({"class_hash": "0x1"}))]
+    fn parsing(#[case] input: serde_json::Value) {
+        let expected = Input {
+            class_hash: class_hash!("0x1"),
+            block_id: BlockId::Latest,
+        };
+
+        let input =
+            Input::deserialize(crate::dto::Value::new(input, RpcVersion::PathfinderV01)).unwrap();
+
+        assert_eq!(input, expected);
+    }
+
+    #[tokio::test]
+    async fn cairo_class() {
+        let context = RpcContext::for_tests();
+        let input = Input {
+            class_hash: class_hash_bytes!(b"class 0 hash"),
+            block_id: BlockId::Latest,
+        };
+
+        let output = get_class_info(context, input).await.unwrap();
+
+        assert_eq!(output.declared_at, Some(BlockNumber::GENESIS));
+        assert_eq!(output.sierra, None);
+        assert!(!output.entry_points.external.is_empty());
+        assert!(output
+            .entry_points
+            .external
+            .iter()
+            .all(|entry_point| entry_point.offset.is_some() && entry_point.function_idx.is_none()));
+        assert_matches!(output.abi, Some(serde_json::Value::Array(_)));
+    }
+
+    #[tokio::test]
+    async fn sierra_class() {
+        let context = RpcContext::for_tests();
+        const SELECTOR: &str = "0x22ff5f21f0b81b113e63f7db6da94fedef11b2119b4088b89664fb9a3cb658";
+        {
+            // The test storage stores an empty CASM definition.
+            let mut db = context.storage.connection().unwrap();
+            let tx = db.transaction().unwrap();
+            let casm_definition = json!({
+                "bytecode": [],
+                "entry_points_by_type": {
+                    "CONSTRUCTOR": [],
+                    "EXTERNAL": [{"selector": SELECTOR, "offset": 17, "builtins": []}],
+                    "L1_HANDLER": [],
+                },
+            });
+            tx.update_sierra_class(
+                &sierra_hash_bytes!(b"class 2 hash (sierra)"),
+                starknet_gateway_test_fixtures::class_definitions::CAIRO_0_11_SIERRA,
+                &casm_hash_bytes!(b"non-existent"),
+                casm_definition.to_string().as_bytes(),
+            )
+            .unwrap();
+            tx.commit().unwrap();
+        }
+        let input = Input {
+            class_hash: class_hash_bytes!(b"class 2 hash (sierra)"),
+            block_id: BlockId::Latest,
+        };
+
+        let output = get_class_info(context, input).await.unwrap();
+
+        assert_eq!(
+            output.sierra,
+            Some(SierraInfo {
+                sierra_version: "0.1.0".to_owned(),
+                contract_class_version: "0.1.0".to_owned(),
+                compiled_class_hash: Some(casm_hash_bytes!(b"non-existent")),
+            })
+        );
+        assert_eq!(output.entry_points.external.len(), 3);
+        for entry_point in &output.entry_points.external {
+            let offset =
+                (entry_point.selector == Felt::from_hex_str(SELECTOR).unwrap()).then_some(17);
+            assert_eq!(entry_point.offset, offset);
+            assert!(entry_point.function_idx.is_some());
+        }
+        assert_matches!(output.abi, Some(serde_json::Value::Array(_)));
+    }
+
+    #[tokio::test]
+    async fn class_not_declared_at_block() {
+        let context = RpcContext::for_tests();
+        let input = Input {
+            class_hash: class_hash_bytes!(b"class 2 hash (sierra)"),
+            block_id: BlockId::Number(BlockNumber::GENESIS),
+        };
+
+        let error = get_class_info(context, input).await.unwrap_err();
+
+        assert_matches!(error, Error::ClassHashNotFound);
+    }
+
+    #[tokio::test]
+    async fn block_not_found() {
+        let context = RpcContext::for_tests();
+        let input = Input {
+            class_hash: class_hash_bytes!(b"class 0 hash"),
+            block_id: BlockId::Number(BlockNumber::MAX),
+        };
+
+        let error = get_class_info(context, input).await.unwrap_err();
+
+        assert_matches!(error, Error::BlockNotFound);
+    }
+
+    #[test]
+    fn serialization() {
+        let output = Output {
+            class_hash: class_hash!("0x1"),
+            declared_at: Some(BlockNumber::new_or_panic(3)),
+            sierra: Some(SierraInfo {
+                sierra_version: "1.5.0".to_owned(),
+                contract_class_version: "0.1.0".to_owned(),
+                compiled_class_hash: Some(casm_hash!("0x2")),
+            }),
+            entry_points: EntryPoints {
+                external: vec![EntryPoint {
+                    selector: felt!("0x3"),
+                    offset: Some(4),
+                    function_idx: Some(0),
+                }],
+                ..Default::default()
+            },
+            abi: Some(json!([{"type": "function", "name": "foo"}])),
+        };
+
+        let output = output
+            .serialize(Serializer::new(RpcVersion::PathfinderV01))
+            .unwrap();
+
+        assert_eq!(
+            output,
+            json!({
+                "class_hash": "0x1",
+                "declared_at": 3,
+                "sierra_version": "1.5.0",
+                "contract_class_version": "0.1.0",
+                "compiled_class_hash": "0x2",
+                "entry_points_by_type": {
+                    "CONSTRUCTOR": [],
+                    "EXTERNAL": [{"selector": "0x3", "offset": 4, "function_idx": 0}],
+                    "L1_HANDLER": [],
+                },
+                "abi": [{"type": "function", "name": "foo"}],
+            })
+        );
+    }
+}
@@ -0,0 +1,290 @@
+use anyhow::Context;
+use pathfinder_common::{BlockId, ContractAddress, ContractStateHash};
+use pathfinder_merkle_tree::contract_state::update_contract_state;
+use pathfinder_storage::Transaction;
+
+use crate::context::RpcContext;
+use crate::pending::PendingData;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Input {
+    pub contract_address: ContractAddress,
+    pub block_id: BlockId,
+}
+
+impl crate::dto::DeserializeForVersion for Input {
+    fn deserialize(value: crate::dto::Value) -> Result<Self, serde_json::Error> {
+        value.deserialize_map(|value| {
+            Ok(Self {
+                contract_address: value.deserialize("contract_address").map(ContractAddress)?,
+                block_id: value.deserialize("block_id")?,
+            })
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct Output(ContractStateHash);
+
+crate::error::generate_rpc_error_subset!(Error: ContractNotFound, BlockNotFound);
+
+/// Get the contract state hash, i.e. the contract's leaf in the global storage
+/// trie, at the given block.
+///
+/// The hash commits to the contract's class hash, storage root and nonce.
+pub async fn get_contract_state_hash(context: RpcContext, input: Input) -> Result<Output, Error> {
+    let span = tracing::Span::current();
+
+    let jh = tokio::task::spawn_blocking(move || {
+        let _g = span.enter();
+        let mut db = context
+            .storage
+            .connection()
+            .context("Opening database connection")?;
+
+        let tx = db.transaction().context("Creating database transaction")?;
+
+        if input.block_id.is_pending() {
+            let pending = context
+                .pending_data
+                .get(&tx)
+                .context("Querying pending data")?;
+
+            if let Some(state_hash) = pending_state_hash(&tx, &pending, input.contract_address)? {
+                return Ok(Output(state_hash));
+            }
+        }
+
+        let block_id = match input.block_id {
+            BlockId::Pending => pathfinder_storage::BlockId::Latest,
+            other => other.try_into().expect("Only pending cast should fail"),
+        };
+
+        let block_number = tx
+            .block_id(block_id)
+            .context("Querying block number")?
+            .ok_or(Error::BlockNotFound)?
+            .0;
+
+        tx.contract_state_hash(block_number, input.contract_address)
+            .context("Querying contract state hash")?
+            .map(Output)
+            .ok_or(Error::ContractNotFound)
+    });
+
+    jh.await.context("Database read panic or shutting down")?
+}
+
+/// Computes the contract's state hash with the pending updates applied on top
+/// of the latest block.
+///
+/// Returns `None` if the contract is not touched by the pending state update,
+/// in which case the state hash is the same as in the latest block.
+fn pending_state_hash(
+    tx: &Transaction<'_>,
+    pending: &PendingData,
+    contract_address: ContractAddress,
+) -> anyhow::Result<Option<ContractStateHash>> {
+    let state_update = &pending.state_update;
+
+    let (storage, nonce, class_hash) =
+        if let Some(update) = state_update.contract_updates.get(&contract_address) {
+            (
+                &update.storage,
+                update.nonce,
+                update.class.as_ref().map(|class| class.class_hash()),
+            )
+        } else if let Some(update) = state_update.system_contract_updates.get(&contract_address) {
+            (&update.storage, None, None)
+        } else {
+            return Ok(None);
+        };
+
+    // The storage trie updates are computed in memory only and never committed.
+    let result = update_contract_state(
+        contract_address,
+        storage,
+        nonce,
+        class_hash,
+        tx,
+        false,
+        pending.number,
+    )
+    .context("Applying pending contract state")?;
+
+    Ok(Some(result.state_hash))
+}
+
+impl crate::dto::serialize::SerializeForVersion for Output {
+    fn serialize(
+        &self,
+        serializer: crate::dto::serialize::Serializer,
+    ) -> Result<crate::dto::serialize::Ok, crate::dto::serialize::Error> {
+        serializer.serialize(&crate::dto::Felt(&self.0 .0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+    use pathfinder_common::macro_prelude::*;
+    use pathfinder_common::{BlockNumber, ContractNonce, ContractRoot};
+    use pathfinder_merkle_tree::contract_state::calculate_contract_state_hash;
+    use serde_json::json;
+
+    use super::*;
+    use crate::dto::DeserializeForVersion;
+    use crate::RpcVersion;
+
+    #[rstest::rstest]
+    #[case::positional(json!(["0x1", "latest"]))]
+    #[case::named(json!({"contract_address": "0x1", "block_id": "latest"}))]
+    fn parsing(#[case] input: serde_json::Value) {
+        let expected = Input {
+            contract_address: contract_address!("0x1"),
+            block_id: BlockId::Latest,
+        };
+
+        let input =
+            Input::deserialize(crate::dto::Value::new(input, RpcVersion::PathfinderV01)).unwrap();
+
+        assert_eq!(input, expected);
+    }
+
+    fn stored_state_hash(
+        context: &RpcContext,
+        block: BlockNumber,
+        contract_address: ContractAddress,
+    ) -> ContractStateHash {
+        let mut db = context.storage.connection().unwrap();
+        let tx = db.transaction().unwrap();
+        tx.contract_state_hash(block, contract_address)
+            .unwrap()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn latest() {
+        let context = RpcContext::for_tests();
+        let contract_address = contract_address_bytes!(b"contract 1");
+        let input = Input {
+            contract_address,
+            block_id: BlockId::Latest,
+        };
+
+        let result = get_contract_state_hash(context.clone(), input)
+            .await
+            .unwrap();
+
+        let expected = stored_state_hash(&context, BlockNumber::GENESIS + 2, contract_address);
+        assert_eq!(result.0, expected);
+    }
+
+    #[tokio::test]
+    async fn by_hash() {
+        let context = RpcContext::for_tests();
+        let contract_address = contract_address_bytes!(b"contract 1");
+        let input = Input {
+            contract_address,
+            block_id: BlockId::Hash(block_hash_bytes!(b"block 1")),
+        };
+
+        let result = get_contract_state_hash(context.clone(), input)
+            .await
+            .unwrap();
+
+        let expected = stored_state_hash(&context, BlockNumber::GENESIS + 1, contract_address);
+        assert_eq!(result.0, expected);
+    }
+
+    #[tokio::test]
+    async fn pending_applies_pending_updates() {
+        let context = RpcContext::for_tests_with_pending().await;
+        let contract_address = contract_address_bytes!(b"contract 1");
+        let input = Input {
+            contract_address,
+            block_id: BlockId::Pending,
+        };
+
+        let result = get_contract_state_hash(context.clone(), input)
+            .await
+            .unwrap();
+
+        // Only the nonce is updated in the pending block.
+        let latest = BlockNumber::GENESIS + 2;
+        let mut db = context.storage.connection().unwrap();
+        let tx = db.transaction().unwrap();
+        let class_hash = tx
+            .contract_class_hash(latest.into(), contract_address)
+            .unwrap()
+            .unwrap();
+        let root = tx.contract_root(latest, contract_address).unwrap().unwrap();
+        let expected = calculate_contract_state_hash(
+            class_hash,
+            root,
+            contract_nonce_bytes!(b"pending nonce"),
+        );
+        assert_eq!(result.0, expected);
+    }
+
+    #[tokio::test]
+    async fn pending_deployed() {
+        let context = RpcContext::for_tests_with_pending().await;
+        let input = Input {
+            contract_address: contract_address_bytes!(b"pending contract 0 address"),
+            block_id: BlockId::Pending,
+        };
+
+        let result = get_contract_state_hash(context, input).await.unwrap();
+
+        let expected = calculate_contract_state_hash(
+            class_hash_bytes!(b"pending class 0 hash"),
+            ContractRoot::ZERO,
+            ContractNonce::ZERO,
+        );
+        assert_eq!(result.0, expected);
+    }
+
+    #[tokio::test]
+    async fn pending_falls_back_to_latest() {
+        let context = RpcContext::for_tests_with_pending().await;
+        let contract_address = contract_address_bytes!(b"contract 2 (sierra)");
+        let input = Input {
+            contract_address,
+            block_id: BlockId::Pending,
+        };
+
+        let result = get_contract_state_hash(context.clone(), input)
+            .await
+            .unwrap();
+
+        let expected = stored_state_hash(&context, BlockNumber::GENESIS + 2, contract_address);
+        assert_eq!(result.0, expected);
+    }
+
+    #[tokio::test]
+    async fn contract_not_found() {
+        let context = RpcContext::for_tests();
+        let input = Input {
+            contract_address: contract_address_bytes!(b"contract 1"),
+            block_id: BlockId::Number(BlockNumber::GENESIS),
+        };
+
+        let result = get_contract_state_hash(context, input).await;
+
+        assert_matches!(result, Err(Error::ContractNotFound));
+    }
+
+    #[tokio::test]
+    async fn block_not_found() {
+        let context = RpcContext::for_tests();
+        let input = Input {
+            contract_address: contract_address_bytes!(b"contract 1"),
+            block_id: BlockId::Number(BlockNumber::MAX),
+        };
+
+        let result = get_contract_state_hash(context, input).await;
+
+        assert_matches!(result, Err(Error::BlockNotFound));
+    }
+}
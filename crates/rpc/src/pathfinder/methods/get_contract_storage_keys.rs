@@ -0,0 +1,323 @@
+use std::cmp::Ordering;
+use std::ops::ControlFlow;
+
+use anyhow::Context;
+use pathfinder_common::{BlockId, ContractAddress, StorageAddress};
+use pathfinder_crypto::Felt;
+use pathfinder_merkle_tree::merkle_node::InternalNode;
+use pathfinder_merkle_tree::tree::Visit;
+use pathfinder_merkle_tree::ContractsStorageTree;
+
+use crate::context::RpcContext;
+use crate::dto::serialize::{self, SerializeForVersion, Serializer};
+
+/// The maximum, and default, number of keys returned per page.
+const MAX_CHUNK_SIZE: usize = 1000;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Input {
+    pub contract_address: ContractAddress,
+    pub block_id: BlockId,
+    pub chunk_size: Option<usize>,
+    /// The last key of the previous page.
+    pub continuation_token: Option<String>,
+}
+
+impl crate::dto::DeserializeForVersion for Input {
+    fn deserialize(value: crate::dto::Value) -> Result<Self, serde_json::Error> {
+        value.deserialize_map(|value| {
+            Ok(Self {
+                contract_address: value.deserialize("contract_address").map(ContractAddress)?,
+                block_id: value.deserialize("block_id")?,
+                chunk_size: value.deserialize_optional_serde("chunk_size")?,
+                continuation_token: value.deserialize_optional_serde("continuation_token")?,
+            })
+        })
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Output {
+    /// The contract's storage keys in ascending order.
+    keys: Vec<StorageAddress>,
+    /// Set if there are more keys after this page.
+    continuation_token: Option<String>,
+}
+
+crate::error::generate_rpc_error_subset!(
+    Error: BlockNotFound,
+    ContractNotFound,
+    PageSizeTooBig,
+    InvalidContinuationToken
+);
+
+/// Enumerate the keys of a contract's storage at the given block, by walking
+/// the leaves of the contract's storage trie.
+///
+/// Only keys with a non-zero value are part of the trie, so keys which have
+/// since been reset to zero are not included.
+pub async fn get_contract_storage_keys(context: RpcContext, input: Input) -> Result<Output, Error> {
+    let chunk_size = input.chunk_size.unwrap_or(MAX_CHUNK_SIZE);
+    if chunk_size > MAX_CHUNK_SIZE {
+        return Err(Error::PageSizeTooBig);
+    }
+    if chunk_size == 0 {
+        return Err(Error::Custom(anyhow::anyhow!(
+            "chunk_size must be greater than zero"
+        )));
+    }
+
+    let after = input
+        .continuation_token
+        .map(|token| {
+            Felt::from_hex_str(&token)
+                .ok()
+                .and_then(StorageAddress::new)
+                .ok_or(Error::InvalidContinuationToken)
+        })
+        .transpose()?;
+
+    let block_id = match input.block_id {
+        BlockId::Pending => {
+            return Err(Error::Custom(anyhow::anyhow!(
+                "'pending' is not currently supported by this method!"
+            )))
+        }
+        other => other.try_into().expect("Only pending cast should fail"),
+    };
+
+    let span = tracing::Span::current();
+
+    let jh = tokio::task::spawn_blocking(move || {
+        let _g = span.enter();
+        let mut db = context
+            .storage
+            .connection()
+            .context("Opening database connection")?;
+
+        let tx = db.transaction().context("Creating database transaction")?;
+
+        let block_number = tx
+            .block_id(block_id)
+            .context("Querying block number")?
+            .ok_or(Error::BlockNotFound)?
+            .0;
+
+        if !tx
+            .contract_exists(input.contract_address, block_number.into())
+            .context("Querying contract existence")?
+        {
+            return Err(Error::ContractNotFound);
+        }
+
+        if tx
+            .storage_root_index(block_number)
+            .context("Querying storage root index")?
+            .is_none()
+        {
+            return Err(Error::Custom(anyhow::anyhow!(
+                "Storage trie is not available for block {block_number}"
+            )));
+        }
+
+        let mut tree = ContractsStorageTree::load(&tx, input.contract_address, block_number)
+            .context("Loading contract storage tree")?
+            .with_node_cache(&context.trie_node_cache)
+            .context("Reading through the trie node cache")?;
+
+        let (keys, has_more) = storage_keys(&mut tree, after, chunk_size)?;
+        let continuation_token = has_more
+            .then(|| keys.last().map(|key| key.0.to_hex_str().into_owned()))
+            .flatten();
+
+        Ok(Output {
+            keys,
+            continuation_token,
+        })
+    });
+
+    jh.await.context("Database read panic or shutting down")?
+}
+
+/// Collects up to `limit` keys of the tree in ascending order, starting after
+/// `after`. Also returns whether there are more keys remaining.
+fn storage_keys(
+    tree: &mut ContractsStorageTree<'_>,
+    after: Option<StorageAddress>,
+    limit: usize,
+) -> anyhow::Result<(Vec<StorageAddress>, bool)> {
+    let mut keys = Vec::new();
+
+    // The tree is visited in pre-order with left children first, so leaves are
+    // visited in ascending key order and any subtree whose path sorts before
+    // `after` can be skipped entirely.
+    let has_more = tree
+        .dfs(&mut |node, path| {
+            if let Some(after) = &after {
+                match path.cmp(&after.view_bits()[..path.len()]) {
+                    Ordering::Less => return ControlFlow::Continue(Visit::StopSubtree),
+                    Ordering::Equal if matches!(node, InternalNode::Leaf) => {
+                        return ControlFlow::Continue(Visit::StopSubtree)
+                    }
+                    _ => {}
+                }
+            }
+
+            if let InternalNode::Leaf = node {
+                if keys.len() == limit {
+                    return ControlFlow::Break(());
+                }
+                let key = Felt::from_bits(path).expect("Leaf path is 251 bits");
+                keys.push(StorageAddress(key));
+            }
+
+            ControlFlow::Continue(Visit::ContinueDeeper)
+        })
+        .context("Visiting contract storage tree")?
+        .is_some();
+
+    Ok((keys, has_more))
+}
+
+impl SerializeForVersion for Output {
+    fn serialize(&self, serializer: Serializer) -> Result<serialize::Ok, serialize::Error> {
+        let mut serializer = serializer.serialize_struct()?;
+        serializer.serialize_iter(
+            "keys",
+            self.keys.len(),
+            &mut self.keys.iter().map(|key| crate::dto::Felt(&key.0)),
+        )?;
+        serializer.serialize_optional("continuation_token", self.continuation_token.as_ref())?;
+        serializer.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::macro_prelude::*;
+    use pathfinder_common::StorageValue;
+    use serde_json::json;
+
+    use super::*;
+    use crate::dto::DeserializeForVersion;
+    use crate::RpcVersion;
+
+    #[rstest::rstest]
+    #[case::positional(json!(["0x1", "latest", 10, "0x2"]))]
+    #[case::named(json!({"contract_address": "0x1", "block_id": "latest", "chunk_size": 10, "continuation_token": "0x2"}))]
+    fn parsing(#[case] input: serde_json::Value) {
+        let expected = Input {
+            contract_address: contract_address!("0x1"),
+            block_id: BlockId::Latest,
+            chunk_size: Some(10),
+            continuation_token: Some("0x2".to_owned()),
+        };
+
+        let input =
+            Input::deserialize(crate::dto::Value::new(input, RpcVersion::PathfinderV01)).unwrap();
+
+        assert_eq!(input, expected);
+    }
+
+    #[tokio::test]
+    async fn keys() {
+        let ctx = RpcContext::for_tests();
+        let input = Input {
+            contract_address: contract_address_bytes!(b"contract 1"),
+            block_id: BlockId::Latest,
+            chunk_size: None,
+            continuation_token: None,
+        };
+
+        let output = get_contract_storage_keys(ctx, input).await.unwrap();
+
+        assert_eq!(
+            output,
+            Output {
+                keys: vec![storage_address_bytes!(b"storage addr 0")],
+                continuation_token: None,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn contract_not_found() {
+        let ctx = RpcContext::for_tests();
+        let input = Input {
+            contract_address: contract_address_bytes!(b"non-existent"),
+            block_id: BlockId::Latest,
+            chunk_size: None,
+            continuation_token: None,
+        };
+
+        let error = get_contract_storage_keys(ctx, input).await.unwrap_err();
+
+        assert_matches::assert_matches!(error, Error::ContractNotFound);
+    }
+
+    #[tokio::test]
+    async fn invalid_continuation_token() {
+        let ctx = RpcContext::for_tests();
+        let input = Input {
+            contract_address: contract_address_bytes!(b"contract 1"),
+            block_id: BlockId::Latest,
+            chunk_size: None,
+            continuation_token: Some("not a key".to_owned()),
+        };
+
+        let error = get_contract_storage_keys(ctx, input).await.unwrap_err();
+
+        assert_matches::assert_matches!(error, Error::InvalidContinuationToken);
+    }
+
+    #[test]
+    fn pagination() {
+        let storage = pathfinder_storage::StorageBuilder::in_memory().unwrap();
+        let mut db = storage.connection().unwrap();
+        let tx = db.transaction().unwrap();
+
+        let mut tree = ContractsStorageTree::empty(&tx, contract_address!("0x1"));
+        for key in [0x30u64, 0x1, 0x20, 0x4, 0x500] {
+            tree.set(
+                StorageAddress(Felt::from_u64(key)),
+                StorageValue(Felt::from_u64(key)),
+            )
+            .unwrap();
+        }
+
+        let (keys, has_more) = storage_keys(&mut tree, None, 2).unwrap();
+        assert_eq!(keys, vec![storage_address!("0x1"), storage_address!("0x4")]);
+        assert!(has_more);
+
+        let (keys, has_more) = storage_keys(&mut tree, keys.last().copied(), 2).unwrap();
+        assert_eq!(
+            keys,
+            vec![storage_address!("0x20"), storage_address!("0x30")]
+        );
+        assert!(has_more);
+
+        let (keys, has_more) = storage_keys(&mut tree, keys.last().copied(), 2).unwrap();
+        assert_eq!(keys, vec![storage_address!("0x500")]);
+        assert!(!has_more);
+    }
+
+    #[test]
+    fn serialization() {
+        let output = Output {
+            keys: vec![storage_address!("0x1"), storage_address!("0x2")],
+            continuation_token: Some("0x2".to_owned()),
+        };
+
+        let output = output
+            .serialize(Serializer::new(RpcVersion::PathfinderV01))
+            .unwrap();
+
+        assert_eq!(
+            output,
+            json!({
+                "keys": ["0x1", "0x2"],
+                "continuation_token": "0x2",
+            })
+        );
+    }
+}
@@ -0,0 +1,342 @@
+use anyhow::Context;
+use pathfinder_common::{BlockId, BlockNumber, GasPrice};
+use pathfinder_storage::{BlockFeeStats, FEE_PERCENTILE_STEP};
+
+use crate::context::RpcContext;
+use crate::dto::serialize::{self, SerializeForVersion, Serializer};
+use crate::error::ApplicationError;
+
+/// The largest number of blocks a single request may cover. Fee statistics
+/// are filled in for this many blocks when upgrading an existing database.
+const MAX_BLOCK_COUNT: u64 = 1024;
+
+#[derive(Debug, PartialEq)]
+pub struct Input {
+    pub block_count: u64,
+    /// Percentiles of the actual fees to return for each block, in ascending
+    /// order.
+    pub percentiles: Option<Vec<f64>>,
+}
+
+impl crate::dto::DeserializeForVersion for Input {
+    fn deserialize(value: crate::dto::Value) -> Result<Self, serde_json::Error> {
+        value.deserialize_map(|value| {
+            Ok(Self {
+                block_count: value.deserialize_serde("block_count")?,
+                percentiles: value.deserialize_optional_serde("percentiles")?,
+            })
+        })
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Output {
+    oldest_block: BlockNumber,
+    l1_gas_price: Vec<ResourcePrice>,
+    l1_data_gas_price: Vec<ResourcePrice>,
+    /// For each block, the actual fees of the transactions paying in WEI at
+    /// the requested percentiles.
+    actual_fee_wei: Option<Vec<Vec<u128>>>,
+    /// For each block, the actual fees of the transactions paying in FRI at
+    /// the requested percentiles.
+    actual_fee_fri: Option<Vec<Vec<u128>>>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct ResourcePrice {
+    price_in_wei: GasPrice,
+    price_in_fri: GasPrice,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Internal(anyhow::Error),
+    NoBlocks,
+    InvalidBlockCount,
+    InvalidPercentiles,
+}
+
+impl From<anyhow::Error> for Error {
+    fn from(e: anyhow::Error) -> Self {
+        Self::Internal(e)
+    }
+}
+
+impl From<Error> for ApplicationError {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::Internal(internal) => Self::Internal(internal),
+            Error::NoBlocks => Self::NoBlocks,
+            Error::InvalidBlockCount => Self::Custom(anyhow::anyhow!(
+                "block_count must be between 1 and {MAX_BLOCK_COUNT}"
+            )),
+            Error::InvalidPercentiles => Self::Custom(anyhow::anyhow!(
+                "percentiles must be between 0 and 100 and in ascending order"
+            )),
+        }
+    }
+}
+
+/// Get the gas prices of the latest `block_count` blocks and, if requested,
+/// percentiles of the actual fees paid by their transactions, analogous to
+/// `eth_feeHistory`.
+///
+/// Fees are reported separately for transactions paying in WEI and in FRI.
+/// Percentiles are interpolated from the stored statistics, which have a
+/// resolution of [FEE_PERCENTILE_STEP] percent. Blocks without transactions
+/// paying in a unit report zero fees for it.
+pub async fn get_fee_history(context: RpcContext, input: Input) -> Result<Output, Error> {
+    if input.block_count == 0 || input.block_count > MAX_BLOCK_COUNT {
+        return Err(Error::InvalidBlockCount);
+    }
+    if let Some(percentiles) = &input.percentiles {
+        let in_range = percentiles.iter().all(|p| (0.0..=100.0).contains(p));
+        let ascending = percentiles.windows(2).all(|w| w[0] <= w[1]);
+        if !in_range || !ascending {
+            return Err(Error::InvalidPercentiles);
+        }
+    }
+
+    let span = tracing::Span::current();
+
+    let jh = tokio::task::spawn_blocking(move || {
+        let _g = span.enter();
+        let mut db = context
+            .storage
+            .connection()
+            .context("Opening database connection")?;
+
+        let tx = db.transaction().context("Creating database transaction")?;
+
+        let (latest, _) = tx
+            .block_id(BlockId::Latest)
+            .context("Fetching latest block")?
+            .ok_or(Error::NoBlocks)?;
+        let oldest = latest.get().saturating_sub(input.block_count - 1);
+        let oldest = BlockNumber::new_or_panic(oldest);
+
+        let stats = tx
+            .block_fee_stats(oldest, latest)
+            .context("Fetching fee stats")?;
+
+        // Blocks older than the fee stats are left out.
+        let oldest_block = stats.first().map_or(latest, |s| s.block_number);
+
+        Ok(output(oldest_block, &stats, input.percentiles.as_deref()))
+    });
+
+    jh.await.context("Database read panic or shutting down")?
+}
+
+fn output(
+    oldest_block: BlockNumber,
+    stats: &[BlockFeeStats],
+    percentiles: Option<&[f64]>,
+) -> Output {
+    let l1_gas_price = stats
+        .iter()
+        .map(|s| ResourcePrice {
+            price_in_wei: s.eth_l1_gas_price,
+            price_in_fri: s.strk_l1_gas_price,
+        })
+        .collect();
+    let l1_data_gas_price = stats
+        .iter()
+        .map(|s| ResourcePrice {
+            price_in_wei: s.eth_l1_data_gas_price,
+            price_in_fri: s.strk_l1_data_gas_price,
+        })
+        .collect();
+    let actual_fees = |fees: fn(&BlockFeeStats) -> &[u128]| {
+        percentiles.map(|percentiles| {
+            stats
+                .iter()
+                .map(|s| {
+                    percentiles
+                        .iter()
+                        .map(|p| interpolate(fees(s), *p))
+                        .collect()
+                })
+                .collect()
+        })
+    };
+
+    Output {
+        oldest_block,
+        l1_gas_price,
+        l1_data_gas_price,
+        actual_fee_wei: actual_fees(|s| &s.fee_percentiles_wei),
+        actual_fee_fri: actual_fees(|s| &s.fee_percentiles_fri),
+    }
+}
+
+/// Interpolates linearly between the stored percentiles, which are
+/// [FEE_PERCENTILE_STEP] percent apart.
+fn interpolate(stored: &[u128], percentile: f64) -> u128 {
+    if stored.is_empty() {
+        return 0;
+    }
+
+    let position = percentile / FEE_PERCENTILE_STEP as f64;
+    let lower = (position.floor() as usize).min(stored.len() - 1);
+    let upper = (position.ceil() as usize).min(stored.len() - 1);
+    let fraction = position - position.floor();
+
+    // The stored percentiles are sorted, so this cannot underflow.
+    stored[lower] + ((stored[upper] - stored[lower]) as f64 * fraction) as u128
+}
+
+impl SerializeForVersion for Output {
+    fn serialize(&self, serializer: Serializer) -> Result<serialize::Ok, serialize::Error> {
+        let mut serializer = serializer.serialize_struct()?;
+        serializer.serialize_field("oldest_block", &self.oldest_block)?;
+        serializer.serialize_iter(
+            "l1_gas_price",
+            self.l1_gas_price.len(),
+            &mut self.l1_gas_price.iter(),
+        )?;
+        serializer.serialize_iter(
+            "l1_data_gas_price",
+            self.l1_data_gas_price.len(),
+            &mut self.l1_data_gas_price.iter(),
+        )?;
+        if let Some(fees) = &self.actual_fee_wei {
+            serializer.serialize_iter(
+                "actual_fee_wei",
+                fees.len(),
+                &mut fees.iter().map(|f| Fees(f)),
+            )?;
+        }
+        if let Some(fees) = &self.actual_fee_fri {
+            serializer.serialize_iter(
+                "actual_fee_fri",
+                fees.len(),
+                &mut fees.iter().map(|f| Fees(f)),
+            )?;
+        }
+        serializer.end()
+    }
+}
+
+impl SerializeForVersion for &ResourcePrice {
+    fn serialize(&self, serializer: Serializer) -> Result<serialize::Ok, serialize::Error> {
+        let mut serializer = serializer.serialize_struct()?;
+        serializer.serialize_field("price_in_wei", &crate::dto::U128Hex(self.price_in_wei.0))?;
+        serializer.serialize_field("price_in_fri", &crate::dto::U128Hex(self.price_in_fri.0))?;
+        serializer.end()
+    }
+}
+
+struct Fees<'a>(&'a [u128]);
+
+impl SerializeForVersion for Fees<'_> {
+    fn serialize(&self, serializer: Serializer) -> Result<serialize::Ok, serialize::Error> {
+        serializer.serialize_iter(
+            self.0.len(),
+            &mut self.0.iter().map(|fee| crate::dto::U128Hex(*fee)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::dto::DeserializeForVersion;
+    use crate::RpcVersion;
+
+    #[rstest::rstest]
+    #[case::positional(json!([2, [10.0, 50.5]]))]
+    #[case::named(json!({"block_count": 2, "percentiles": [10.0, 50.5]}))]
+    fn parsing(#[case] input: serde_json::Value) {
+        let expected = Input {
+            block_count: 2,
+            percentiles: Some(vec![10.0, 50.5]),
+        };
+
+        let input =
+            Input::deserialize(crate::dto::Value::new(input, RpcVersion::PathfinderV01)).unwrap();
+
+        assert_eq!(input, expected);
+    }
+
+    #[test]
+    fn percentiles_are_interpolated() {
+        let stored = (0..=20).map(|i| i * 10).collect::<Vec<u128>>();
+
+        assert_eq!(interpolate(&stored, 0.0), 0);
+        assert_eq!(interpolate(&stored, 5.0), 10);
+        assert_eq!(interpolate(&stored, 7.5), 15);
+        assert_eq!(interpolate(&stored, 100.0), 200);
+        assert_eq!(interpolate(&[], 50.0), 0);
+    }
+
+    #[tokio::test]
+    async fn latest_blocks() {
+        let ctx = RpcContext::for_tests();
+        let input = Input {
+            block_count: 2,
+            percentiles: Some(vec![50.0]),
+        };
+
+        let output = get_fee_history(ctx.clone(), input).await.unwrap();
+
+        let mut db = ctx.storage.connection().unwrap();
+        let tx = db.transaction().unwrap();
+        let (latest, _) = tx.block_id(BlockId::Latest).unwrap().unwrap();
+        assert_eq!(output.oldest_block, latest - 1);
+        assert_eq!(output.l1_gas_price.len(), 2);
+        assert_eq!(output.l1_data_gas_price.len(), 2);
+        assert_eq!(output.actual_fee_wei.unwrap().len(), 2);
+        assert_eq!(output.actual_fee_fri.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn without_percentiles() {
+        let ctx = RpcContext::for_tests();
+        let input = Input {
+            block_count: 1,
+            percentiles: None,
+        };
+
+        let output = get_fee_history(ctx, input).await.unwrap();
+
+        assert_eq!(output.l1_gas_price.len(), 1);
+        assert_eq!(output.actual_fee_wei, None);
+        assert_eq!(output.actual_fee_fri, None);
+    }
+
+    #[rstest::rstest]
+    #[case::zero(0)]
+    #[case::too_many(MAX_BLOCK_COUNT + 1)]
+    #[tokio::test]
+    async fn invalid_block_count(#[case] block_count: u64) {
+        let ctx = RpcContext::for_tests();
+        let input = Input {
+            block_count,
+            percentiles: None,
+        };
+
+        let error = get_fee_history(ctx, input).await.unwrap_err();
+
+        assert_matches::assert_matches!(error, Error::InvalidBlockCount);
+    }
+
+    #[rstest::rstest]
+    #[case::out_of_range(vec![50.0, 101.0])]
+    #[case::negative(vec![-1.0])]
+    #[case::descending(vec![50.0, 10.0])]
+    #[tokio::test]
+    async fn invalid_percentiles(#[case] percentiles: Vec<f64>) {
+        let ctx = RpcContext::for_tests();
+        let input = Input {
+            block_count: 1,
+            percentiles: Some(percentiles),
+        };
+
+        let error = get_fee_history(ctx, input).await.unwrap_err();
+
+        assert_matches::assert_matches!(error, Error::InvalidPercentiles);
+    }
+}
@@ -0,0 +1,328 @@
+use anyhow::Context;
+use pathfinder_common::{BlockNumber, TransactionHash};
+use primitive_types::H256;
+
+use crate::context::RpcContext;
+use crate::dto::serialize::{self, SerializeForVersion, Serializer};
+use crate::dto::H256Hex;
+
+crate::error::generate_rpc_error_subset!(Error:);
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Input {
+    message_hash: H256,
+}
+
+impl crate::dto::DeserializeForVersion for Input {
+    fn deserialize(value: crate::dto::Value) -> Result<Self, serde_json::Error> {
+        value.deserialize_map(|value| {
+            Ok(Self {
+                message_hash: value.deserialize::<H256Hex>("message_hash")?.0,
+            })
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Status {
+    /// Neither the message nor its L1 handler transaction have been seen.
+    Unknown,
+    /// The message was sent on L1, but not yet consumed on L2.
+    Sent,
+    /// The message was cancelled on L1 before being consumed on L2.
+    Cancelled,
+    /// An L1 handler transaction consuming the message succeeded on L2.
+    Consumed,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Output {
+    status: Status,
+    sent: Option<(H256, u64)>,
+    cancelled: Option<(H256, u64)>,
+    consumed: Option<(TransactionHash, BlockNumber)>,
+}
+
+/// Get the status of an L1 -> L2 message, along with the L1 transactions which
+/// sent or cancelled it and the L2 transaction which consumed it.
+///
+/// L1 transactions are only known for messages seen while this node was
+/// following L1, whereas consumption is known for all messages handled in the
+/// synced L2 blocks. Messages consumed in the pending block are reported as
+/// sent.
+pub async fn get_l1_to_l2_message_status(
+    context: RpcContext,
+    input: Input,
+) -> Result<Output, Error> {
+    let span = tracing::Span::current();
+
+    let jh = tokio::task::spawn_blocking(move || {
+        let _g = span.enter();
+        let mut db = context
+            .storage
+            .connection()
+            .context("Opening database connection")?;
+
+        let tx = db.transaction().context("Creating database transaction")?;
+
+        let message = tx
+            .l1_to_l2_message(input.message_hash)
+            .context("Querying L1 to L2 message")?
+            .unwrap_or_default();
+
+        // A reverted L1 handler does not consume the message, which can then
+        // be handled again.
+        let mut consumed = None;
+        for (block_number, transaction_hash) in tx
+            .l1_handler_transactions(input.message_hash)
+            .context("Querying L1 handler transactions")?
+        {
+            let (_, receipt, ..) = tx
+                .transaction_with_receipt(transaction_hash)
+                .context("Querying L1 handler receipt")?
+                .context("L1 handler receipt missing from database")?;
+            if !receipt.is_reverted() {
+                consumed = Some((transaction_hash, block_number));
+                break;
+            }
+        }
+
+        let status = if consumed.is_some() {
+            Status::Consumed
+        } else if message.cancelled.is_some() {
+            Status::Cancelled
+        } else if message.sent.is_some() {
+            Status::Sent
+        } else {
+            Status::Unknown
+        };
+
+        Ok(Output {
+            status,
+            sent: message.sent,
+            cancelled: message.cancelled,
+            consumed,
+        })
+    });
+
+    jh.await.context("Database read panic or shutting down")?
+}
+
+impl SerializeForVersion for Status {
+    fn serialize(&self, serializer: Serializer) -> Result<serialize::Ok, serialize::Error> {
+        serializer.serialize_str(match self {
+            Status::Unknown => "UNKNOWN",
+            Status::Sent => "SENT",
+            Status::Cancelled => "CANCELLED",
+            Status::Consumed => "CONSUMED",
+        })
+    }
+}
+
+impl SerializeForVersion for Output {
+    fn serialize(&self, serializer: Serializer) -> Result<serialize::Ok, serialize::Error> {
+        let mut serializer = serializer.serialize_struct()?;
+        serializer.serialize_field("status", &self.status)?;
+        if let Some((tx_hash, block_number)) = self.sent {
+            serializer.serialize_field("l1_transaction_hash", &H256Hex(tx_hash))?;
+            serializer.serialize_field("l1_block_number", &block_number)?;
+        }
+        if let Some((tx_hash, block_number)) = self.cancelled {
+            serializer.serialize_field("cancellation_l1_transaction_hash", &H256Hex(tx_hash))?;
+            serializer.serialize_field("cancellation_l1_block_number", &block_number)?;
+        }
+        if let Some((transaction_hash, block_number)) = self.consumed {
+            serializer
+                .serialize_field("transaction_hash", &crate::dto::Felt(&transaction_hash.0))?;
+            serializer.serialize_field("block_number", &block_number)?;
+        }
+        serializer.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::macro_prelude::*;
+    use pathfinder_common::receipt::{ExecutionStatus, Receipt};
+    use pathfinder_common::transaction::{L1HandlerTransaction, Transaction, TransactionVariant};
+    use pathfinder_common::{BlockHeader, L1ToL2MessageLog, TransactionIndex};
+    use pathfinder_storage::StorageBuilder;
+    use serde_json::json;
+
+    use super::*;
+    use crate::dto::DeserializeForVersion;
+    use crate::RpcVersion;
+
+    #[rstest::rstest]
+    #[case::positional(json!(["0x1"]))]
+    #[case::named(json!({"message_hash": "0x1"}))]
+    fn parsing(#[case] input: serde_json::Value) {
+        let expected = Input {
+            message_hash: H256::from_low_u64_be(1),
+        };
+
+        let input =
+            Input::deserialize(crate::dto::Value::new(input, RpcVersion::PathfinderV01)).unwrap();
+
+        assert_eq!(input, expected);
+    }
+
+    fn l1_handler() -> L1HandlerTransaction {
+        L1HandlerTransaction {
+            contract_address: contract_address_bytes!(b"l1 handler contract"),
+            entry_point_selector: entry_point_bytes!(b"l1 handler selector"),
+            nonce: transaction_nonce!("0x1"),
+            calldata: vec![call_param_bytes!(b"l1 sender")],
+        }
+    }
+
+    /// Creates a context with a single block handling the message of
+    /// [l1_handler] twice, first reverting and then succeeding.
+    fn context() -> RpcContext {
+        let storage = StorageBuilder::in_memory().unwrap();
+        let mut db = storage.connection().unwrap();
+        let db_tx = db.transaction().unwrap();
+
+        let transactions = [
+            (transaction_hash_bytes!(b"reverted"), true),
+            (transaction_hash_bytes!(b"consumed"), false),
+        ]
+        .into_iter()
+        .enumerate()
+        .map(|(i, (hash, reverted))| {
+            let transaction = Transaction {
+                hash,
+                variant: TransactionVariant::L1Handler(l1_handler()),
+            };
+            let execution_status = if reverted {
+                ExecutionStatus::Reverted {
+                    reason: "reverted".to_owned(),
+                }
+            } else {
+                ExecutionStatus::Succeeded
+            };
+            let receipt = Receipt {
+                transaction_hash: transaction.hash,
+                transaction_index: TransactionIndex::new_or_panic(i as u64),
+                execution_status,
+                ..Default::default()
+            };
+            (transaction, receipt)
+        })
+        .collect::<Vec<_>>();
+
+        let header = BlockHeader::builder().finalize_with_hash(block_hash_bytes!(b"block 0"));
+        db_tx.insert_block_header(&header).unwrap();
+        db_tx
+            .insert_transaction_data(header.number, &transactions, None)
+            .unwrap();
+        db_tx.commit().unwrap();
+
+        RpcContext::for_tests().with_storage(storage)
+    }
+
+    fn insert_log(context: &RpcContext, message_hash: H256, tx: u8, cancelled: bool) {
+        let mut db = context.storage.connection().unwrap();
+        let db_tx = db.transaction().unwrap();
+        let log = L1ToL2MessageLog {
+            message_hash,
+            l1_tx_hash: H256::repeat_byte(tx),
+            l1_block_number: tx.into(),
+            removed: false,
+        };
+        if cancelled {
+            db_tx.upsert_l1_to_l2_message_cancelled(&log).unwrap();
+        } else {
+            db_tx.upsert_l1_to_l2_message_sent(&log).unwrap();
+        }
+        db_tx.commit().unwrap();
+    }
+
+    #[tokio::test]
+    async fn unknown() {
+        let context = context();
+        let input = Input {
+            message_hash: H256::repeat_byte(1),
+        };
+
+        let output = get_l1_to_l2_message_status(context, input).await.unwrap();
+
+        assert_eq!(
+            output,
+            Output {
+                status: Status::Unknown,
+                sent: None,
+                cancelled: None,
+                consumed: None,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn sent_then_cancelled() {
+        let context = context();
+        let message_hash = H256::repeat_byte(1);
+
+        insert_log(&context, message_hash, 10, false);
+        let output = get_l1_to_l2_message_status(context.clone(), Input { message_hash })
+            .await
+            .unwrap();
+        assert_eq!(output.status, Status::Sent);
+        assert_eq!(output.sent, Some((H256::repeat_byte(10), 10)));
+
+        insert_log(&context, message_hash, 20, true);
+        let output = get_l1_to_l2_message_status(context, Input { message_hash })
+            .await
+            .unwrap();
+        assert_eq!(output.status, Status::Cancelled);
+        assert_eq!(output.sent, Some((H256::repeat_byte(10), 10)));
+        assert_eq!(output.cancelled, Some((H256::repeat_byte(20), 20)));
+        assert_eq!(output.consumed, None);
+    }
+
+    #[tokio::test]
+    async fn consumed_skips_reverted_handlers() {
+        let context = context();
+        let message_hash = l1_handler().calculate_message_hash();
+        insert_log(&context, message_hash, 10, false);
+
+        let output = get_l1_to_l2_message_status(context, Input { message_hash })
+            .await
+            .unwrap();
+
+        assert_eq!(
+            output,
+            Output {
+                status: Status::Consumed,
+                sent: Some((H256::repeat_byte(10), 10)),
+                cancelled: None,
+                consumed: Some((transaction_hash_bytes!(b"consumed"), BlockNumber::GENESIS)),
+            }
+        );
+    }
+
+    #[test]
+    fn serialization() {
+        let output = Output {
+            status: Status::Consumed,
+            sent: Some((H256::repeat_byte(0x10), 10)),
+            cancelled: None,
+            consumed: Some((transaction_hash!("0x1"), BlockNumber::new_or_panic(2))),
+        };
+
+        let output = output
+            .serialize(Serializer::new(RpcVersion::PathfinderV01))
+            .unwrap();
+
+        assert_eq!(
+            output,
+            json!({
+                "status": "CONSUMED",
+                "l1_transaction_hash": format!("0x{}", "10".repeat(32)),
+                "l1_block_number": 10,
+                "transaction_hash": "0x1",
+                "block_number": 2,
+            })
+        );
+    }
+}
@@ -0,0 +1,342 @@
+use anyhow::Context;
+use pathfinder_common::receipt::L2ToL1Message;
+use pathfinder_common::{BlockHash, BlockNumber, TransactionHash};
+use pathfinder_ethereum::EthereumStateUpdate;
+
+use crate::context::RpcContext;
+use crate::dto::serialize::{self, SerializeForVersion, Serializer};
+use crate::dto::H256Hex;
+use crate::error::ApplicationError;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Input {
+    transaction_hash: TransactionHash,
+    message_index: usize,
+}
+
+impl crate::dto::DeserializeForVersion for Input {
+    fn deserialize(value: crate::dto::Value) -> Result<Self, serde_json::Error> {
+        value.deserialize_map(|value| {
+            Ok(Self {
+                transaction_hash: TransactionHash(value.deserialize("transaction_hash")?),
+                message_index: value.deserialize_serde("message_index")?,
+            })
+        })
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Output {
+    message: L2ToL1Message,
+    block_hash: BlockHash,
+    block_number: BlockNumber,
+    /// The latest state update accepted on L1, if it includes the block of the
+    /// message.
+    l1_state_update: Option<EthereumStateUpdate>,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Internal(anyhow::Error),
+    TxnHashNotFound,
+    InvalidMessageIndex { message_count: usize },
+}
+
+impl From<anyhow::Error> for Error {
+    fn from(e: anyhow::Error) -> Self {
+        Self::Internal(e)
+    }
+}
+
+impl From<Error> for ApplicationError {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::Internal(internal) => Self::Internal(internal),
+            Error::TxnHashNotFound => Self::TxnHashNotFound,
+            Error::InvalidMessageIndex { message_count } => Self::Custom(anyhow::anyhow!(
+                "message_index must be less than the transaction's {message_count} messages"
+            )),
+        }
+    }
+}
+
+/// Get an L2 -> L1 message sent by a transaction, along with the data needed
+/// to consume it on L1 once the state update containing its block has been
+/// accepted there.
+///
+/// Messages are indexed in the order they appear in the transaction's receipt.
+/// Transactions in the pending block are not considered.
+pub async fn get_message_to_l1_proof(context: RpcContext, input: Input) -> Result<Output, Error> {
+    let span = tracing::Span::current();
+
+    let jh = tokio::task::spawn_blocking(move || {
+        let _g = span.enter();
+        let mut db = context
+            .storage
+            .connection()
+            .context("Opening database connection")?;
+
+        let tx = db.transaction().context("Creating database transaction")?;
+
+        let Some((_, receipt, _, block_number)) = tx
+            .transaction_with_receipt(input.transaction_hash)
+            .context("Fetching receipt from database")?
+        else {
+            return Err(Error::TxnHashNotFound);
+        };
+
+        let message_count = receipt.l2_to_l1_messages.len();
+        let message = receipt
+            .l2_to_l1_messages
+            .into_iter()
+            .nth(input.message_index)
+            .ok_or(Error::InvalidMessageIndex { message_count })?;
+
+        let block_hash = tx
+            .block_hash(block_number.into())
+            .context("Fetching block hash")?
+            .context("Block hash missing from database")?;
+
+        let l1_state_update = tx
+            .latest_l1_state()
+            .context("Querying latest L1 state")?
+            .filter(|l1_state| l1_state.block_number >= block_number);
+
+        Ok(Output {
+            message,
+            block_hash,
+            block_number,
+            l1_state_update,
+        })
+    });
+
+    jh.await.context("Database read panic or shutting down")?
+}
+
+impl SerializeForVersion for Output {
+    fn serialize(&self, serializer: Serializer) -> Result<serialize::Ok, serialize::Error> {
+        let mut serializer = serializer.serialize_struct()?;
+        serializer.serialize_field(
+            "message_hash",
+            &H256Hex(self.message.calculate_message_hash()),
+        )?;
+        serializer.serialize_field(
+            "from_address",
+            &crate::dto::Felt(&self.message.from_address.0),
+        )?;
+        serializer.serialize_field("to_address", &crate::dto::Felt(&self.message.to_address.0))?;
+        serializer.serialize_iter(
+            "payload",
+            self.message.payload.len(),
+            &mut self
+                .message
+                .payload
+                .iter()
+                .map(|elem| crate::dto::Felt(&elem.0)),
+        )?;
+        serializer.serialize_field("block_hash", &crate::dto::BlockHash(&self.block_hash))?;
+        serializer.serialize_field("block_number", &self.block_number)?;
+        serializer.serialize_field("accepted_on_l1", &self.l1_state_update.is_some())?;
+        serializer.serialize_optional("l1_state_update", self.l1_state_update.as_ref())?;
+        serializer.end()
+    }
+}
+
+impl SerializeForVersion for &EthereumStateUpdate {
+    fn serialize(&self, serializer: Serializer) -> Result<serialize::Ok, serialize::Error> {
+        let mut serializer = serializer.serialize_struct()?;
+        serializer.serialize_field("block_number", &self.block_number)?;
+        serializer.serialize_field("block_hash", &crate::dto::BlockHash(&self.block_hash))?;
+        serializer.serialize_field("state_root", &crate::dto::Felt(&self.state_root.0))?;
+        serializer.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+    use pathfinder_common::macro_prelude::*;
+    use pathfinder_common::receipt::Receipt;
+    use pathfinder_common::transaction::Transaction;
+    use pathfinder_common::{BlockHeader, ContractAddress, TransactionIndex};
+    use pathfinder_storage::StorageBuilder;
+    use serde_json::json;
+
+    use super::*;
+    use crate::dto::DeserializeForVersion;
+    use crate::RpcVersion;
+
+    #[rstest::rstest]
+    #[case::positional(json!(["0x1", 2]))]
+    #[case::named(json!({"transaction_hash": "0x1", "message_index": 2}))]
+    fn parsing(#[case] input: serde_json::Value) {
+        let expected = Input {
+            transaction_hash: transaction_hash!("0x1"),
+            message_index: 2,
+        };
+
+        let input =
+            Input::deserialize(crate::dto::Value::new(input, RpcVersion::PathfinderV01)).unwrap();
+
+        assert_eq!(input, expected);
+    }
+
+    fn message(to_address: ContractAddress) -> L2ToL1Message {
+        L2ToL1Message {
+            from_address: contract_address_bytes!(b"bridge"),
+            payload: vec![
+                l2_to_l1_message_payload_elem!("0x0"),
+                l2_to_l1_message_payload_elem!("0x1234"),
+            ],
+            to_address,
+        }
+    }
+
+    /// Creates a context with a single block containing a transaction which
+    /// sent two messages.
+    fn context() -> RpcContext {
+        let storage = StorageBuilder::in_memory().unwrap();
+        let mut db = storage.connection().unwrap();
+        let db_tx = db.transaction().unwrap();
+
+        let transaction = Transaction {
+            hash: transaction_hash_bytes!(b"withdrawal"),
+            variant: Default::default(),
+        };
+        let receipt = Receipt {
+            transaction_hash: transaction.hash,
+            transaction_index: TransactionIndex::new_or_panic(0),
+            l2_to_l1_messages: vec![
+                message(contract_address!("0x1")),
+                message(contract_address!("0x2")),
+            ],
+            ..Default::default()
+        };
+
+        let header = BlockHeader::builder().finalize_with_hash(block_hash_bytes!(b"block 0"));
+        db_tx.insert_block_header(&header).unwrap();
+        db_tx
+            .insert_transaction_data(header.number, &[(transaction, receipt)], None)
+            .unwrap();
+        db_tx.commit().unwrap();
+
+        RpcContext::for_tests().with_storage(storage)
+    }
+
+    #[tokio::test]
+    async fn accepted_on_l1() {
+        let context = context();
+        let l1_state_update = EthereumStateUpdate {
+            state_root: state_commitment_bytes!(b"state root"),
+            block_number: BlockNumber::GENESIS,
+            block_hash: block_hash_bytes!(b"block 0"),
+        };
+        {
+            let mut db = context.storage.connection().unwrap();
+            let db_tx = db.transaction().unwrap();
+            db_tx.upsert_l1_state(&l1_state_update).unwrap();
+            db_tx.commit().unwrap();
+        }
+        let input = Input {
+            transaction_hash: transaction_hash_bytes!(b"withdrawal"),
+            message_index: 1,
+        };
+
+        let output = get_message_to_l1_proof(context, input).await.unwrap();
+
+        assert_eq!(
+            output,
+            Output {
+                message: message(contract_address!("0x2")),
+                block_hash: block_hash_bytes!(b"block 0"),
+                block_number: BlockNumber::GENESIS,
+                l1_state_update: Some(l1_state_update),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn not_yet_accepted_on_l1() {
+        let context = context();
+        let input = Input {
+            transaction_hash: transaction_hash_bytes!(b"withdrawal"),
+            message_index: 0,
+        };
+
+        let output = get_message_to_l1_proof(context, input).await.unwrap();
+
+        assert_eq!(output.message, message(contract_address!("0x1")));
+        assert_eq!(output.l1_state_update, None);
+    }
+
+    #[tokio::test]
+    async fn invalid_message_index() {
+        let context = context();
+        let input = Input {
+            transaction_hash: transaction_hash_bytes!(b"withdrawal"),
+            message_index: 2,
+        };
+
+        let error = get_message_to_l1_proof(context, input).await.unwrap_err();
+
+        assert_matches!(error, Error::InvalidMessageIndex { message_count: 2 });
+    }
+
+    #[tokio::test]
+    async fn transaction_not_found() {
+        let context = context();
+        let input = Input {
+            transaction_hash: transaction_hash_bytes!(b"unknown"),
+            message_index: 0,
+        };
+
+        let error = get_message_to_l1_proof(context, input).await.unwrap_err();
+
+        assert_matches!(error, Error::TxnHashNotFound);
+    }
+
+    #[test]
+    fn serialization() {
+        let output = Output {
+            message: L2ToL1Message {
+                from_address: contract_address!("0x123"),
+                payload: vec![
+                    l2_to_l1_message_payload_elem!("0x0"),
+                    l2_to_l1_message_payload_elem!("0xae0ee0a63a2ce6baeeffe56e7714fb4efe48d419"),
+                    l2_to_l1_message_payload_elem!("0x1"),
+                    l2_to_l1_message_payload_elem!("0x0"),
+                ],
+                to_address: contract_address!("0xae0ee0a63a2ce6baeeffe56e7714fb4efe48d419"),
+            },
+            block_hash: block_hash!("0x1"),
+            block_number: BlockNumber::new_or_panic(2),
+            l1_state_update: Some(EthereumStateUpdate {
+                state_root: state_commitment!("0x3"),
+                block_number: BlockNumber::new_or_panic(4),
+                block_hash: block_hash!("0x5"),
+            }),
+        };
+
+        let output = output
+            .serialize(Serializer::new(RpcVersion::PathfinderV01))
+            .unwrap();
+
+        assert_eq!(
+            output,
+            json!({
+                "message_hash": "0xe9596e8e00da1bb362ba5fe87113a5067913c0ba804d0d327b872d89a7fd00a",
+                "from_address": "0x123",
+                "to_address": "0xae0ee0a63a2ce6baeeffe56e7714fb4efe48d419",
+                "payload": ["0x0", "0xae0ee0a63a2ce6baeeffe56e7714fb4efe48d419", "0x1", "0x0"],
+                "block_hash": "0x1",
+                "block_number": 2,
+                "accepted_on_l1": true,
+                "l1_state_update": {
+                    "block_number": 4,
+                    "block_hash": "0x5",
+                    "state_root": "0x3",
+                },
+            })
+        );
+    }
+}
@@ -0,0 +1,94 @@
+use anyhow::Context;
+use pathfinder_storage::BackgroundMigrationStatus;
+
+use crate::context::RpcContext;
+use crate::dto::serialize::{self, SerializeForVersion, Serializer};
+
+crate::error::generate_rpc_error_subset!(Error);
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Output(Vec<BackgroundMigrationStatus>);
+
+/// Reports the progress of the background migrations backfilling data for the
+/// blocks stored before a database upgrade. Data covered by an unfinished
+/// migration may be missing for older blocks.
+pub async fn get_migration_status(context: RpcContext) -> Result<Output, Error> {
+    let span = tracing::Span::current();
+
+    let jh = tokio::task::spawn_blocking(move || {
+        let _g = span.enter();
+        let mut db = context
+            .storage
+            .connection()
+            .context("Opening database connection")?;
+
+        let tx = db.transaction().context("Creating database transaction")?;
+
+        let status = tx
+            .background_migration_status()
+            .context("Querying background migrations")?;
+
+        Ok(Output(status))
+    });
+
+    jh.await.context("Database read panic or shutting down")?
+}
+
+impl SerializeForVersion for Output {
+    fn serialize(&self, serializer: Serializer) -> Result<serialize::Ok, serialize::Error> {
+        serializer.serialize_iter(self.0.len(), &mut self.0.iter().map(Migration))
+    }
+}
+
+struct Migration<'a>(&'a BackgroundMigrationStatus);
+
+impl SerializeForVersion for Migration<'_> {
+    fn serialize(&self, serializer: Serializer) -> Result<serialize::Ok, serialize::Error> {
+        let mut serializer = serializer.serialize_struct()?;
+        serializer.serialize_field("name", &self.0.name)?;
+        serializer.serialize_field("migrated_blocks", &self.0.migrated_blocks)?;
+        serializer.serialize_field("total_blocks", &self.0.total_blocks)?;
+        serializer.serialize_field("done", &self.0.is_done())?;
+        serializer.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::RpcVersion;
+
+    #[tokio::test]
+    async fn new_database_has_no_migrations() {
+        let context = RpcContext::for_tests();
+
+        let output = get_migration_status(context).await.unwrap();
+
+        assert_eq!(output, Output(vec![]));
+    }
+
+    #[test]
+    fn serialization() {
+        let output = Output(vec![BackgroundMigrationStatus {
+            name: "l1_handler_messages".to_owned(),
+            migrated_blocks: 10,
+            total_blocks: 25,
+        }]);
+
+        let output = output
+            .serialize(Serializer::new(RpcVersion::PathfinderV01))
+            .unwrap();
+
+        assert_eq!(
+            output,
+            json!([{
+                "name": "l1_handler_messages",
+                "migrated_blocks": 10,
+                "total_blocks": 25,
+                "done": false,
+            }])
+        );
+    }
+}
@@ -0,0 +1,333 @@
+use anyhow::Context;
+use pathfinder_common::{BlockId, BlockNumber, ContractAddress, ContractNonce};
+
+use crate::context::RpcContext;
+use crate::dto::serialize::{self, SerializeForVersion, Serializer};
+use crate::error::ApplicationError;
+
+/// Limits the number of changes a single request may return. Clients should
+/// narrow the block range and query again if this is exceeded.
+const MAX_CHANGES: usize = 1000;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Input {
+    pub contract_address: ContractAddress,
+    pub block_id: BlockId,
+    /// The first block of the nonce change history, defaults to genesis.
+    pub from_block: Option<BlockNumber>,
+}
+
+impl crate::dto::DeserializeForVersion for Input {
+    fn deserialize(value: crate::dto::Value) -> Result<Self, serde_json::Error> {
+        value.deserialize_map(|value| {
+            Ok(Self {
+                contract_address: value.deserialize("contract_address").map(ContractAddress)?,
+                block_id: value.deserialize("block_id")?,
+                from_block: value.deserialize_optional_serde("from_block")?,
+            })
+        })
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Output {
+    /// The nonce at the requested block.
+    nonce: ContractNonce,
+    /// The changes to the nonce from `from_block` up to and including the
+    /// requested block, oldest first.
+    changes: Vec<(BlockNumber, ContractNonce)>,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Internal(anyhow::Error),
+    BlockNotFound,
+    ContractNotFound,
+    TooManyChanges { limit: usize },
+}
+
+impl From<anyhow::Error> for Error {
+    fn from(e: anyhow::Error) -> Self {
+        Self::Internal(e)
+    }
+}
+
+impl From<Error> for ApplicationError {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::Internal(internal) => Self::Internal(internal),
+            Error::BlockNotFound => Self::BlockNotFound,
+            Error::ContractNotFound => Self::ContractNotFound,
+            Error::TooManyChanges { limit } => Self::Custom(anyhow::anyhow!(
+                "The nonce changed more than {limit} times in the requested range, please \
+                 request a later from_block"
+            )),
+        }
+    }
+}
+
+/// Get a contract's nonce at a block, along with every block since
+/// `from_block` in which the nonce changed.
+///
+/// For the pending block the nonce includes pending changes, while the
+/// changes only cover the blocks stored so far.
+pub async fn get_nonce_at(context: RpcContext, input: Input) -> Result<Output, Error> {
+    let span = tracing::Span::current();
+
+    let jh = tokio::task::spawn_blocking(move || {
+        let _g = span.enter();
+        let mut db = context
+            .storage
+            .connection()
+            .context("Opening database connection")?;
+
+        let tx = db.transaction().context("Creating database transaction")?;
+
+        let pending_nonce = if input.block_id.is_pending() {
+            context
+                .pending_data
+                .get(&tx)
+                .context("Querying pending data")?
+                .state_update
+                .contract_nonce(input.contract_address)
+        } else {
+            None
+        };
+
+        let block_id = match input.block_id {
+            BlockId::Pending => pathfinder_storage::BlockId::Latest,
+            other => other.try_into().expect("Only pending cast should fail"),
+        };
+
+        let block_number = tx
+            .block_id(block_id)
+            .context("Querying block number")?
+            .ok_or(Error::BlockNotFound)?
+            .0;
+        // Reads of pruned state fail, which is reported as the block not being found.
+        let block_id = pathfinder_storage::BlockId::Number(block_number);
+
+        let nonce = match pending_nonce {
+            Some(nonce) => nonce,
+            None => match tx
+                .contract_nonce(input.contract_address, block_id)
+                .context("Querying contract nonce")?
+            {
+                Some(nonce) => nonce,
+                None if tx.contract_exists(input.contract_address, block_id)? => {
+                    ContractNonce::ZERO
+                }
+                None => return Err(Error::ContractNotFound),
+            },
+        };
+
+        let changes = tx
+            .nonce_history(
+                input.contract_address,
+                input.from_block.unwrap_or(BlockNumber::GENESIS),
+                block_number,
+                MAX_CHANGES + 1,
+            )
+            .context("Querying nonce history")?;
+
+        if changes.len() > MAX_CHANGES {
+            return Err(Error::TooManyChanges { limit: MAX_CHANGES });
+        }
+
+        Ok(Output { nonce, changes })
+    });
+
+    jh.await.context("Database read panic or shutting down")?
+}
+
+impl SerializeForVersion for Output {
+    fn serialize(&self, serializer: Serializer) -> Result<serialize::Ok, serialize::Error> {
+        let mut serializer = serializer.serialize_struct()?;
+        serializer.serialize_field("nonce", &crate::dto::Felt(&self.nonce.0))?;
+        serializer.serialize_iter(
+            "changes",
+            self.changes.len(),
+            &mut self.changes.iter().map(Change),
+        )?;
+        serializer.end()
+    }
+}
+
+struct Change<'a>(&'a (BlockNumber, ContractNonce));
+
+impl SerializeForVersion for Change<'_> {
+    fn serialize(&self, serializer: Serializer) -> Result<serialize::Ok, serialize::Error> {
+        let mut serializer = serializer.serialize_struct()?;
+        serializer.serialize_field("block_number", &self.0 .0)?;
+        serializer.serialize_field("nonce", &crate::dto::Felt(&self.0 .1 .0))?;
+        serializer.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::macro_prelude::*;
+    use serde_json::json;
+
+    use super::*;
+    use crate::dto::DeserializeForVersion;
+    use crate::RpcVersion;
+
+    #[rstest::rstest]
+    #[case::positional(json!(["0x1", {"block_number": 5}, 3]))]
+    #[case::named(json!({"contract_address": "0x1", "block_id": {"block_number": 5}, "from_block": 3}))]
+    fn parsing(#[case] input: serde_json::Value) {
+        let expected = Input {
+            contract_address: contract_address!("0x1"),
+            block_id: BlockId::Number(BlockNumber::new_or_panic(5)),
+            from_block: Some(BlockNumber::new_or_panic(3)),
+        };
+
+        let input =
+            Input::deserialize(crate::dto::Value::new(input, RpcVersion::PathfinderV01)).unwrap();
+
+        assert_eq!(input, expected);
+    }
+
+    #[test]
+    fn parsing_defaults_to_full_history() {
+        let input = json!({"contract_address": "0x1", "block_id": "latest"});
+
+        let input =
+            Input::deserialize(crate::dto::Value::new(input, RpcVersion::PathfinderV01)).unwrap();
+
+        assert_eq!(input.from_block, None);
+    }
+
+    #[tokio::test]
+    async fn historic_nonce() {
+        let ctx = RpcContext::for_tests();
+        let input = Input {
+            contract_address: contract_address_bytes!(b"contract 0"),
+            block_id: BlockId::Number(BlockNumber::new_or_panic(1)),
+            from_block: None,
+        };
+
+        let output = get_nonce_at(ctx, input).await.unwrap();
+
+        assert_eq!(
+            output,
+            Output {
+                nonce: contract_nonce!("0x1"),
+                changes: vec![(BlockNumber::GENESIS, contract_nonce!("0x1"))],
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn changes_are_limited_to_range() {
+        let ctx = RpcContext::for_tests();
+        let input = Input {
+            contract_address: contract_address_bytes!(b"contract 1"),
+            block_id: BlockId::Latest,
+            from_block: Some(BlockNumber::new_or_panic(2)),
+        };
+
+        let output = get_nonce_at(ctx, input).await.unwrap();
+
+        assert_eq!(
+            output,
+            Output {
+                nonce: contract_nonce!("0x10"),
+                changes: vec![(BlockNumber::new_or_panic(2), contract_nonce!("0x10"))],
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn deployed_contract_without_nonce_update() {
+        let ctx = RpcContext::for_tests();
+        let input = Input {
+            contract_address: contract_address_bytes!(b"contract 1"),
+            block_id: BlockId::Number(BlockNumber::new_or_panic(1)),
+            from_block: None,
+        };
+
+        let output = get_nonce_at(ctx, input).await.unwrap();
+
+        assert_eq!(
+            output,
+            Output {
+                nonce: ContractNonce::ZERO,
+                changes: vec![],
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn pending() {
+        let ctx = RpcContext::for_tests_with_pending().await;
+        let input = Input {
+            contract_address: contract_address_bytes!(b"contract 1"),
+            block_id: BlockId::Pending,
+            from_block: None,
+        };
+
+        let output = get_nonce_at(ctx, input).await.unwrap();
+
+        assert_eq!(output.nonce, contract_nonce_bytes!(b"pending nonce"));
+        assert_eq!(
+            output.changes,
+            vec![(BlockNumber::new_or_panic(2), contract_nonce!("0x10"))]
+        );
+    }
+
+    #[tokio::test]
+    async fn contract_not_found() {
+        let ctx = RpcContext::for_tests();
+        let input = Input {
+            contract_address: contract_address_bytes!(b"unknown contract"),
+            block_id: BlockId::Latest,
+            from_block: None,
+        };
+
+        let error = get_nonce_at(ctx, input).await.unwrap_err();
+
+        assert_matches::assert_matches!(error, Error::ContractNotFound);
+    }
+
+    #[tokio::test]
+    async fn block_not_found() {
+        let ctx = RpcContext::for_tests();
+        let input = Input {
+            contract_address: contract_address_bytes!(b"contract 1"),
+            block_id: BlockId::Number(BlockNumber::MAX),
+            from_block: None,
+        };
+
+        let error = get_nonce_at(ctx, input).await.unwrap_err();
+
+        assert_matches::assert_matches!(error, Error::BlockNotFound);
+    }
+
+    #[test]
+    fn serialization() {
+        let output = Output {
+            nonce: contract_nonce!("0x2"),
+            changes: vec![
+                (BlockNumber::new_or_panic(3), contract_nonce!("0x1")),
+                (BlockNumber::new_or_panic(7), contract_nonce!("0x2")),
+            ],
+        };
+
+        let output = output
+            .serialize(Serializer::new(RpcVersion::PathfinderV01))
+            .unwrap();
+
+        assert_eq!(
+            output,
+            json!({
+                "nonce": "0x2",
+                "changes": [
+                    {"block_number": 3, "nonce": "0x1"},
+                    {"block_number": 7, "nonce": "0x2"},
+                ],
+            })
+        );
+    }
+}
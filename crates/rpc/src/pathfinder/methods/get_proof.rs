@@ -85,7 +85,7 @@ struct PathWrapper {
 /// Wrapper around [`Vec<TrieNode>`] as we don't control [TrieNode] in this
 /// crate.
 #[derive(Debug)]
-pub struct ProofNodes(Vec<TrieNode>);
+pub struct ProofNodes(pub(crate) Vec<TrieNode>);
 
 impl Serialize for ProofNodes {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
@@ -217,6 +217,8 @@ pub async fn get_proof(
     };
 
     let storage = context.storage.clone();
+    let storage_root_cache = context.storage_root_cache.clone();
+    let trie_node_cache = context.trie_node_cache.clone();
     let span = tracing::Span::current();
 
     let jh = tokio::task::spawn_blocking(move || {
@@ -256,6 +258,7 @@ pub async fn get_proof(
             header.number,
             &input.contract_address,
             storage_root_idx,
+            &trie_node_cache,
         )
         .context("Creating contract proof")?
         .ok_or(GetProofError::ProofMissing)?;
@@ -289,8 +292,8 @@ pub async fn get_proof(
             .context("Querying contract's nonce")?
             .unwrap_or_default();
 
-        let root = tx
-            .contract_root_index(header.number, input.contract_address)
+        let root = storage_root_cache
+            .contract_root_index(&tx, header.number, header.hash, input.contract_address)
             .context("Querying contract root index")?;
 
         let mut storage_proofs = Vec::new();
@@ -302,6 +305,7 @@ pub async fn get_proof(
                     header.number,
                     k.view_bits(),
                     root,
+                    &trie_node_cache,
                 )
                 .context("Get proof from contract state tree")?
                 .ok_or_else(|| {
@@ -354,6 +358,7 @@ pub async fn get_proof_class(
     };
 
     let storage = context.storage.clone();
+    let trie_node_cache = context.trie_node_cache.clone();
     let span = tracing::Span::current();
 
     let jh = tokio::task::spawn_blocking(move || {
@@ -384,10 +389,15 @@ pub async fn get_proof_class(
 
         // Generate a proof for this class. If the class does not exist, this will
         // be a "non membership" proof.
-        let class_proof =
-            ClassCommitmentTree::get_proof(&tx, header.number, input.class_hash, class_root_idx)
-                .context("Creating class proof")?
-                .ok_or(GetProofError::ProofMissing)?;
+        let class_proof = ClassCommitmentTree::get_proof(
+            &tx,
+            header.number,
+            input.class_hash,
+            class_root_idx,
+            &trie_node_cache,
+        )
+        .context("Creating class proof")?
+        .ok_or(GetProofError::ProofMissing)?;
         let class_proof = ProofNodes(class_proof);
 
         Ok(GetClassProofOutput {
@@ -401,10 +411,67 @@ pub async fn get_proof_class(
 
 #[cfg(test)]
 mod tests {
+    use pathfinder_common::hash::PedersenHash;
     use pathfinder_common::macro_prelude::*;
 
     use super::*;
 
+    /// The header of the latest block in the test storage.
+    fn latest_header(context: &RpcContext) -> BlockHeader {
+        let mut conn = context.storage.connection().unwrap();
+        let tx = conn.transaction().unwrap();
+        tx.block_header(pathfinder_storage::BlockId::Latest)
+            .unwrap()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn membership_proof() {
+        let context = RpcContext::for_tests();
+        let header = latest_header(&context);
+        let input = GetProofInput {
+            block_id: BlockId::Latest,
+            contract_address: contract_address_bytes!(b"contract 1"),
+            keys: vec![
+                storage_address_bytes!(b"storage addr 0"),
+                storage_address_bytes!(b"unset storage addr"),
+            ],
+        };
+
+        let output = get_proof(context, input).await.unwrap();
+
+        assert_eq!(output.state_commitment, Some(header.state_commitment));
+        assert_eq!(
+            output.contract_proof.0[0].hash::<PedersenHash>(),
+            header.storage_commitment.0
+        );
+
+        let contract_data = output.contract_data.unwrap();
+        assert_eq!(contract_data.storage_proofs.len(), 2);
+        for proof in &contract_data.storage_proofs {
+            assert_eq!(proof.0[0].hash::<PedersenHash>(), contract_data.root.0);
+        }
+    }
+
+    #[tokio::test]
+    async fn non_membership_proof() {
+        let context = RpcContext::for_tests();
+        let header = latest_header(&context);
+        let input = GetProofInput {
+            block_id: BlockId::Latest,
+            contract_address: contract_address_bytes!(b"unknown contract"),
+            keys: vec![storage_address_bytes!(b"storage addr 0")],
+        };
+
+        let output = get_proof(context, input).await.unwrap();
+
+        assert!(output.contract_data.is_none());
+        assert_eq!(
+            output.contract_proof.0[0].hash::<PedersenHash>(),
+            header.storage_commitment.0
+        );
+    }
+
     #[tokio::test]
     async fn limit_exceeded() {
         let context = RpcContext::for_tests();
@@ -0,0 +1,311 @@
+use anyhow::Context;
+use pathfinder_common::{BlockHash, BlockNumber, StarknetVersion, TransactionHash};
+use pathfinder_crypto::Felt;
+use pathfinder_merkle_tree::commitment::{
+    receipt_commitment_proof,
+    transaction_commitment_proof,
+    CommitmentProof,
+};
+use serde::Serialize;
+use serde_with::skip_serializing_none;
+
+use super::get_proof::ProofNodes;
+use crate::context::RpcContext;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Input {
+    pub transaction_hash: TransactionHash,
+}
+
+impl crate::dto::DeserializeForVersion for Input {
+    fn deserialize(value: crate::dto::Value) -> Result<Self, serde_json::Error> {
+        value.deserialize_map(|value| {
+            Ok(Self {
+                transaction_hash: TransactionHash(value.deserialize("transaction_hash")?),
+            })
+        })
+    }
+}
+
+/// Proves that a leaf is part of one of the commitments in a block header.
+#[derive(Debug, Serialize)]
+pub struct Proof {
+    /// The commitment as found in the block header.
+    commitment: Felt,
+    /// The leaf of the transaction, keyed by its index within the block.
+    leaf: Felt,
+    /// The nodes from the commitment down to the leaf.
+    proof: ProofNodes,
+}
+
+impl From<CommitmentProof> for Proof {
+    fn from(proof: CommitmentProof) -> Self {
+        Self {
+            commitment: proof.root,
+            leaf: proof.leaf,
+            proof: ProofNodes(proof.nodes),
+        }
+    }
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Serialize)]
+pub struct Output {
+    block_hash: BlockHash,
+    block_number: BlockNumber,
+    transaction_index: u64,
+    /// Membership proof of the transaction in the transaction commitment.
+    transaction_proof: Proof,
+    /// Membership proof of the receipt in the receipt commitment. Only present
+    /// for Starknet 0.13.2 blocks onwards, as older blocks don't commit to
+    /// their receipts.
+    receipt_proof: Option<Proof>,
+}
+
+crate::error::generate_rpc_error_subset!(Error: TxnHashNotFound);
+
+/// Returns the Merkle paths proving a transaction and its receipt against the
+/// commitments in the header of the block containing them.
+///
+/// Together with a trusted block hash this lets a receipt be verified without
+/// trusting the node. Pending transactions have no commitment yet and are
+/// therefore not found.
+pub async fn get_receipt_proof(context: RpcContext, input: Input) -> Result<Output, Error> {
+    let span = tracing::Span::current();
+
+    let jh = tokio::task::spawn_blocking(move || {
+        let _g = span.enter();
+        let mut db = context
+            .storage
+            .connection()
+            .context("Opening database connection")?;
+
+        let tx = db.transaction().context("Creating database transaction")?;
+
+        let (.., block_number) = tx
+            .transaction_with_receipt(input.transaction_hash)
+            .context("Fetching transaction from database")?
+            .ok_or(Error::TxnHashNotFound)?;
+
+        let header = tx
+            .block_header(block_number.into())
+            .context("Fetching block header")?
+            .context("Block header is missing")?;
+
+        let (transactions, receipts): (Vec<_>, Vec<_>) = tx
+            .transactions_with_receipts_for_block(block_number.into())
+            .context("Fetching block transactions")?
+            .context("Block transactions are missing")?
+            .into_iter()
+            .unzip();
+
+        let index = transactions
+            .iter()
+            .position(|t| t.hash == input.transaction_hash)
+            .context("Transaction is missing from its block")?;
+
+        let transaction_proof =
+            transaction_commitment_proof(&transactions, header.starknet_version, index)
+                .context("Calculating transaction commitment proof")?;
+        if transaction_proof.root != header.transaction_commitment.0 {
+            return Err(Error::Internal(anyhow::anyhow!(
+                "Transaction commitment does not match block header"
+            )));
+        }
+
+        let receipt_proof = if header.starknet_version >= StarknetVersion::V_0_13_2 {
+            let proof = receipt_commitment_proof(&receipts, index)
+                .context("Calculating receipt commitment proof")?;
+            if proof.root != header.receipt_commitment.0 {
+                return Err(Error::Internal(anyhow::anyhow!(
+                    "Receipt commitment does not match block header"
+                )));
+            }
+            Some(proof.into())
+        } else {
+            None
+        };
+
+        Ok(Output {
+            block_hash: header.hash,
+            block_number: header.number,
+            transaction_index: index as u64,
+            transaction_proof: transaction_proof.into(),
+            receipt_proof,
+        })
+    });
+
+    jh.await.context("Database read panic or shutting down")?
+}
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::hash::{PedersenHash, PoseidonHash};
+    use pathfinder_common::macro_prelude::*;
+    use pathfinder_common::receipt::Receipt;
+    use pathfinder_common::transaction::Transaction;
+    use pathfinder_common::{
+        BlockHeader,
+        ReceiptCommitment,
+        TransactionCommitment,
+        TransactionIndex,
+    };
+    use pathfinder_merkle_tree::commitment::{
+        commitment_root,
+        receipt_hash,
+        transaction_hash_with_signature,
+    };
+    use pathfinder_storage::StorageBuilder;
+    use serde_json::json;
+
+    use super::*;
+    use crate::dto::DeserializeForVersion;
+    use crate::RpcVersion;
+
+    #[rstest::rstest]
+    #[case::positional(json!(["0x1"]))]
+    #[case::named(json!({"transaction_hash": "0x1"}))]
+    fn parsing(#[case] input: serde_json::Value) {
+        let expected = Input {
+            transaction_hash: transaction_hash!("0x1"),
+        };
+
+        let input =
+            Input::deserialize(crate::dto::Value::new(input, RpcVersion::PathfinderV01)).unwrap();
+
+        assert_eq!(input, expected);
+    }
+
+    /// Creates a context with a single block of three transactions, whose
+    /// header contains the correct commitments.
+    fn context(version: StarknetVersion) -> RpcContext {
+        let storage = StorageBuilder::in_memory().unwrap();
+        let mut db = storage.connection().unwrap();
+        let db_tx = db.transaction().unwrap();
+
+        let transactions = [
+            transaction_hash_bytes!(b"txn 0"),
+            transaction_hash_bytes!(b"txn 1"),
+            transaction_hash_bytes!(b"txn 2"),
+        ]
+        .into_iter()
+        .enumerate()
+        .map(|(i, hash)| {
+            let transaction = Transaction {
+                hash,
+                variant: Default::default(),
+            };
+            let receipt = Receipt {
+                transaction_hash: transaction.hash,
+                transaction_index: TransactionIndex::new_or_panic(i as u64),
+                ..Default::default()
+            };
+            (transaction, receipt)
+        })
+        .collect::<Vec<_>>();
+
+        let transaction_commitment = transactions
+            .iter()
+            .map(|(t, _)| transaction_hash_with_signature(t, version))
+            .collect::<Vec<_>>();
+        let receipt_commitment = transactions
+            .iter()
+            .map(|(_, r)| receipt_hash(r))
+            .collect::<Vec<_>>();
+
+        let header = BlockHeader::builder()
+            .starknet_version(version)
+            .transaction_commitment(TransactionCommitment(
+                if version < StarknetVersion::V_0_13_2 {
+                    commitment_root::<PedersenHash>(transaction_commitment)
+                } else {
+                    commitment_root::<PoseidonHash>(transaction_commitment)
+                }
+                .unwrap(),
+            ))
+            .receipt_commitment(ReceiptCommitment(
+                commitment_root::<PoseidonHash>(receipt_commitment).unwrap(),
+            ))
+            .finalize_with_hash(block_hash_bytes!(b"block 0"));
+
+        db_tx.insert_block_header(&header).unwrap();
+        db_tx
+            .insert_transaction_data(header.number, &transactions, None)
+            .unwrap();
+        db_tx.commit().unwrap();
+
+        RpcContext::for_tests().with_storage(storage)
+    }
+
+    #[tokio::test]
+    async fn receipt_proof() {
+        let context = context(StarknetVersion::V_0_13_2);
+        let input = Input {
+            transaction_hash: transaction_hash_bytes!(b"txn 1"),
+        };
+
+        let output = get_receipt_proof(context, input).await.unwrap();
+
+        assert_eq!(output.block_hash, block_hash_bytes!(b"block 0"));
+        assert_eq!(output.transaction_index, 1);
+        assert!(!output.transaction_proof.proof.0.is_empty());
+        let receipt_proof = output.receipt_proof.unwrap();
+        assert!(!receipt_proof.proof.0.is_empty());
+    }
+
+    #[tokio::test]
+    async fn no_receipt_commitment_before_0_13_2() {
+        let context = context(StarknetVersion::new(0, 13, 1, 0));
+        let input = Input {
+            transaction_hash: transaction_hash_bytes!(b"txn 2"),
+        };
+
+        let output = get_receipt_proof(context, input).await.unwrap();
+
+        assert_eq!(output.transaction_index, 2);
+        assert!(output.receipt_proof.is_none());
+    }
+
+    #[tokio::test]
+    async fn transaction_not_found() {
+        let context = RpcContext::for_tests();
+        let input = Input {
+            transaction_hash: transaction_hash_bytes!(b"non-existent"),
+        };
+
+        let error = get_receipt_proof(context, input).await.unwrap_err();
+
+        assert_matches::assert_matches!(error, Error::TxnHashNotFound);
+    }
+
+    #[test]
+    fn serialization() {
+        let output = Output {
+            block_hash: block_hash!("0x1"),
+            block_number: BlockNumber::new_or_panic(2),
+            transaction_index: 3,
+            transaction_proof: Proof {
+                commitment: felt!("0x4"),
+                leaf: felt!("0x5"),
+                proof: ProofNodes(vec![]),
+            },
+            receipt_proof: None,
+        };
+
+        let output = serde_json::to_value(output).unwrap();
+
+        assert_eq!(
+            output,
+            json!({
+                "block_hash": "0x1",
+                "block_number": 2,
+                "transaction_index": 3,
+                "transaction_proof": {
+                    "commitment": "0x4",
+                    "leaf": "0x5",
+                    "proof": [],
+                },
+            })
+        );
+    }
+}
@@ -0,0 +1,184 @@
+use anyhow::Context;
+use pathfinder_common::{BlockHash, BlockNumber};
+use pathfinder_storage::ReorgLogEntry;
+
+use crate::context::RpcContext;
+use crate::dto::serialize::{self, SerializeForVersion, Serializer};
+
+crate::error::generate_rpc_error_subset!(Error);
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Input {
+    pub from_block: BlockNumber,
+    pub to_block: BlockNumber,
+}
+
+impl crate::dto::DeserializeForVersion for Input {
+    fn deserialize(value: crate::dto::Value) -> Result<Self, serde_json::Error> {
+        value.deserialize_map(|value| {
+            Ok(Self {
+                from_block: value.deserialize_serde("from_block")?,
+                to_block: value.deserialize_serde("to_block")?,
+            })
+        })
+    }
+}
+
+/// The reorgs which affected the requested range, oldest first.
+#[derive(Debug)]
+pub struct Output(Vec<ReorgLogEntry>);
+
+/// Get the L2 reorgs this node has observed which rolled back any block within
+/// `from_block..=to_block`.
+pub async fn get_reorgs(context: RpcContext, input: Input) -> Result<Output, Error> {
+    let span = tracing::Span::current();
+
+    let jh = tokio::task::spawn_blocking(move || {
+        let _g = span.enter();
+        let mut db = context
+            .storage
+            .connection()
+            .context("Opening database connection")?;
+
+        let tx = db.transaction().context("Creating database transaction")?;
+
+        let reorgs = tx
+            .reorgs(input.from_block, input.to_block)
+            .context("Querying reorgs")?;
+
+        Ok(Output(reorgs))
+    });
+
+    jh.await.context("Database read panic or shutting down")?
+}
+
+impl SerializeForVersion for Output {
+    fn serialize(&self, serializer: Serializer) -> Result<serialize::Ok, serialize::Error> {
+        serializer.serialize_iter(self.0.len(), &mut self.0.iter().map(Reorg))
+    }
+}
+
+struct Reorg<'a>(&'a ReorgLogEntry);
+
+impl SerializeForVersion for Reorg<'_> {
+    fn serialize(&self, serializer: Serializer) -> Result<serialize::Ok, serialize::Error> {
+        let mut serializer = serializer.serialize_struct()?;
+        serializer.serialize_field(
+            "old_head",
+            &BlockRef(self.0.old_head_number, &self.0.old_head_hash),
+        )?;
+        serializer.serialize_optional(
+            "common_ancestor",
+            self.0
+                .common_ancestor
+                .as_ref()
+                .map(|(number, hash)| BlockRef(*number, hash)),
+        )?;
+        serializer.serialize_field("detected_at", &self.0.detected_at)?;
+        serializer.end()
+    }
+}
+
+pub(crate) struct BlockRef<'a>(pub BlockNumber, pub &'a BlockHash);
+
+impl SerializeForVersion for BlockRef<'_> {
+    fn serialize(&self, serializer: Serializer) -> Result<serialize::Ok, serialize::Error> {
+        let mut serializer = serializer.serialize_struct()?;
+        serializer.serialize_field("block_number", &self.0)?;
+        serializer.serialize_field("block_hash", &crate::dto::BlockHash(self.1))?;
+        serializer.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::macro_prelude::*;
+    use serde_json::json;
+
+    use super::*;
+    use crate::dto::DeserializeForVersion;
+    use crate::RpcVersion;
+
+    #[rstest::rstest]
+    #[case::positional(json!([1, 5]))]
+    #[case::named(json!({"from_block": 1, "to_block": 5}))]
+    fn parsing(#[case] input: serde_json::Value) {
+        let expected = Input {
+            from_block: BlockNumber::new_or_panic(1),
+            to_block: BlockNumber::new_or_panic(5),
+        };
+
+        let input =
+            Input::deserialize(crate::dto::Value::new(input, RpcVersion::PathfinderV01)).unwrap();
+
+        assert_eq!(input, expected);
+    }
+
+    #[tokio::test]
+    async fn stored_reorgs() {
+        let ctx = RpcContext::for_tests();
+        let reorg = ReorgLogEntry {
+            old_head_number: BlockNumber::new_or_panic(10),
+            old_head_hash: block_hash!("0xa"),
+            common_ancestor: Some((BlockNumber::new_or_panic(7), block_hash!("0x7"))),
+            detected_at: 1234,
+        };
+        {
+            let mut db = ctx.storage.connection().unwrap();
+            let tx = db.transaction().unwrap();
+            tx.insert_reorg(&reorg).unwrap();
+            tx.commit().unwrap();
+        }
+
+        let input = Input {
+            from_block: BlockNumber::new_or_panic(9),
+            to_block: BlockNumber::new_or_panic(20),
+        };
+        let output = get_reorgs(ctx.clone(), input).await.unwrap();
+        assert_eq!(output.0, vec![reorg]);
+
+        let input = Input {
+            from_block: BlockNumber::new_or_panic(0),
+            to_block: BlockNumber::new_or_panic(7),
+        };
+        let output = get_reorgs(ctx, input).await.unwrap();
+        assert!(output.0.is_empty());
+    }
+
+    #[test]
+    fn serialization() {
+        let output = Output(vec![
+            ReorgLogEntry {
+                old_head_number: BlockNumber::new_or_panic(10),
+                old_head_hash: block_hash!("0xa"),
+                common_ancestor: Some((BlockNumber::new_or_panic(7), block_hash!("0x7"))),
+                detected_at: 1234,
+            },
+            ReorgLogEntry {
+                old_head_number: BlockNumber::new_or_panic(2),
+                old_head_hash: block_hash!("0x2"),
+                common_ancestor: None,
+                detected_at: 5678,
+            },
+        ]);
+
+        let output = output
+            .serialize(Serializer::new(RpcVersion::PathfinderV01))
+            .unwrap();
+
+        assert_eq!(
+            output,
+            json!([
+                {
+                    "old_head": {"block_number": 10, "block_hash": "0xa"},
+                    "common_ancestor": {"block_number": 7, "block_hash": "0x7"},
+                    "detected_at": 1234,
+                },
+                {
+                    "old_head": {"block_number": 2, "block_hash": "0x2"},
+                    "detected_at": 5678,
+                },
+            ])
+        );
+    }
+}
@@ -0,0 +1,250 @@
+use anyhow::Context;
+use pathfinder_common::{BlockNumber, StateUpdate};
+
+use crate::context::RpcContext;
+use crate::dto::serialize::{self, SerializeForVersion, Serializer};
+use crate::error::ApplicationError;
+
+/// Limits the number of blocks squashed by a single request. Clients should
+/// split larger ranges and squash the results themselves.
+const MAX_BLOCKS: u64 = 1000;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Input {
+    pub from_block: BlockNumber,
+    pub to_block: BlockNumber,
+}
+
+impl crate::dto::DeserializeForVersion for Input {
+    fn deserialize(value: crate::dto::Value) -> Result<Self, serde_json::Error> {
+        value.deserialize_map(|value| {
+            Ok(Self {
+                from_block: value.deserialize_serde("from_block")?,
+                to_block: value.deserialize_serde("to_block")?,
+            })
+        })
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Output {
+    from_block: BlockNumber,
+    to_block: BlockNumber,
+    state_update: StateUpdate,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Internal(anyhow::Error),
+    BlockNotFound,
+    InvalidRange,
+    TooManyBlocks { limit: u64 },
+}
+
+impl From<anyhow::Error> for Error {
+    fn from(e: anyhow::Error) -> Self {
+        Self::Internal(e)
+    }
+}
+
+impl From<Error> for ApplicationError {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::Internal(internal) => Self::Internal(internal),
+            Error::BlockNotFound => Self::BlockNotFound,
+            Error::InvalidRange => {
+                Self::Custom(anyhow::anyhow!("from_block must not be after to_block"))
+            }
+            Error::TooManyBlocks { limit } => Self::Custom(anyhow::anyhow!(
+                "The range may contain at most {limit} blocks, please request a smaller range"
+            )),
+        }
+    }
+}
+
+/// Get the state diff of `from_block..=to_block` as a single state update,
+/// which takes the state from before `from_block` to the state of `to_block`.
+///
+/// Storage values, nonces and classes changed more than once in the range
+/// only appear with their final value.
+pub async fn get_state_update_range(context: RpcContext, input: Input) -> Result<Output, Error> {
+    if input.from_block > input.to_block {
+        return Err(Error::InvalidRange);
+    }
+    if input.to_block.get() - input.from_block.get() >= MAX_BLOCKS {
+        return Err(Error::TooManyBlocks { limit: MAX_BLOCKS });
+    }
+
+    let span = tracing::Span::current();
+
+    let jh = tokio::task::spawn_blocking(move || {
+        let _g = span.enter();
+        let mut db = context
+            .storage
+            .connection()
+            .context("Opening database connection")?;
+
+        let tx = db.transaction().context("Creating database transaction")?;
+
+        // Fail before squashing anything, the state of every later block is retained if
+        // that of the first one is.
+        tx.ensure_state_not_pruned(input.from_block.into())?;
+
+        let mut state_update: Option<StateUpdate> = None;
+        for number in input.from_block.get()..=input.to_block.get() {
+            let block = BlockNumber::new_or_panic(number);
+            let next = tx
+                .state_update(block.into())
+                .context("Fetching state update")?
+                .ok_or(Error::BlockNotFound)?;
+
+            state_update = Some(match state_update {
+                Some(state_update) => state_update.squash(next),
+                None => next,
+            });
+        }
+
+        Ok(Output {
+            from_block: input.from_block,
+            to_block: input.to_block,
+            state_update: state_update.expect("The range is not empty"),
+        })
+    });
+
+    jh.await.context("Database read panic or shutting down")?
+}
+
+impl SerializeForVersion for Output {
+    fn serialize(&self, serializer: Serializer) -> Result<serialize::Ok, serialize::Error> {
+        let mut serializer = serializer.serialize_struct()?;
+        serializer.serialize_field("from_block", &self.from_block)?;
+        serializer.serialize_field("to_block", &self.to_block)?;
+        serializer.serialize_field(
+            "block_hash",
+            &crate::dto::BlockHash(&self.state_update.block_hash),
+        )?;
+        serializer.serialize_field(
+            "old_root",
+            &crate::dto::Felt(&self.state_update.parent_state_commitment.0),
+        )?;
+        serializer.serialize_field(
+            "new_root",
+            &crate::dto::Felt(&self.state_update.state_commitment.0),
+        )?;
+        serializer.serialize_field("state_diff", &crate::dto::StateDiff(&self.state_update))?;
+        serializer.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::macro_prelude::*;
+    use serde_json::json;
+
+    use super::*;
+    use crate::dto::DeserializeForVersion;
+    use crate::RpcVersion;
+
+    #[rstest::rstest]
+    #[case::positional(json!([1, 2]))]
+    #[case::named(json!({"from_block": 1, "to_block": 2}))]
+    fn parsing(#[case] input: serde_json::Value) {
+        let expected = Input {
+            from_block: BlockNumber::new_or_panic(1),
+            to_block: BlockNumber::new_or_panic(2),
+        };
+
+        let input =
+            Input::deserialize(crate::dto::Value::new(input, RpcVersion::PathfinderV01)).unwrap();
+
+        assert_eq!(input, expected);
+    }
+
+    #[tokio::test]
+    async fn squashes_range() {
+        let ctx = RpcContext::for_tests();
+        let input = Input {
+            from_block: BlockNumber::GENESIS,
+            to_block: BlockNumber::new_or_panic(2),
+        };
+
+        let output = get_state_update_range(ctx.clone(), input).await.unwrap();
+
+        let mut db = ctx.storage.connection().unwrap();
+        let tx = db.transaction().unwrap();
+        let expected = (0..=2)
+            .map(|n| {
+                tx.state_update(BlockNumber::new_or_panic(n).into())
+                    .unwrap()
+                    .unwrap()
+            })
+            .reduce(StateUpdate::squash)
+            .unwrap();
+
+        assert_eq!(output.state_update, expected);
+        assert_eq!(
+            output
+                .state_update
+                .contract_nonce(contract_address_bytes!(b"contract 1")),
+            Some(contract_nonce!("0x10"))
+        );
+    }
+
+    #[tokio::test]
+    async fn single_block() {
+        let ctx = RpcContext::for_tests();
+        let input = Input {
+            from_block: BlockNumber::new_or_panic(1),
+            to_block: BlockNumber::new_or_panic(1),
+        };
+
+        let output = get_state_update_range(ctx.clone(), input).await.unwrap();
+
+        let mut db = ctx.storage.connection().unwrap();
+        let tx = db.transaction().unwrap();
+        let expected = tx
+            .state_update(BlockNumber::new_or_panic(1).into())
+            .unwrap()
+            .unwrap();
+        assert_eq!(output.state_update, expected);
+    }
+
+    #[tokio::test]
+    async fn block_not_found() {
+        let ctx = RpcContext::for_tests();
+        let input = Input {
+            from_block: BlockNumber::new_or_panic(1),
+            to_block: BlockNumber::new_or_panic(10),
+        };
+
+        let error = get_state_update_range(ctx, input).await.unwrap_err();
+
+        assert_matches::assert_matches!(error, Error::BlockNotFound);
+    }
+
+    #[tokio::test]
+    async fn invalid_range() {
+        let ctx = RpcContext::for_tests();
+        let input = Input {
+            from_block: BlockNumber::new_or_panic(2),
+            to_block: BlockNumber::new_or_panic(1),
+        };
+
+        let error = get_state_update_range(ctx, input).await.unwrap_err();
+
+        assert_matches::assert_matches!(error, Error::InvalidRange);
+    }
+
+    #[tokio::test]
+    async fn too_many_blocks() {
+        let ctx = RpcContext::for_tests();
+        let input = Input {
+            from_block: BlockNumber::GENESIS,
+            to_block: BlockNumber::new_or_panic(MAX_BLOCKS),
+        };
+
+        let error = get_state_update_range(ctx, input).await.unwrap_err();
+
+        assert_matches::assert_matches!(error, Error::TooManyBlocks { limit: MAX_BLOCKS });
+    }
+}
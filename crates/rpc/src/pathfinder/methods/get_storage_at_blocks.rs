@@ -0,0 +1,252 @@
+use anyhow::Context;
+use pathfinder_common::{BlockId, ContractAddress, StorageAddress, StorageValue};
+
+use crate::context::RpcContext;
+use crate::dto::serialize::{self, SerializeForVersion, Serializer};
+use crate::error::ApplicationError;
+use crate::method::get_storage_at;
+
+/// Limits the number of block ids a single request may resolve, since they
+/// are all resolved within one database transaction.
+const MAX_BLOCK_IDS: usize = 100;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Input {
+    pub contract_address: ContractAddress,
+    pub key: StorageAddress,
+    pub block_ids: Vec<BlockId>,
+}
+
+impl crate::dto::DeserializeForVersion for Input {
+    fn deserialize(value: crate::dto::Value) -> Result<Self, serde_json::Error> {
+        value.deserialize_map(|value| {
+            Ok(Self {
+                contract_address: value.deserialize("contract_address").map(ContractAddress)?,
+                key: value.deserialize("key").map(StorageAddress)?,
+                block_ids: value.deserialize_array("block_ids", |value| value.deserialize())?,
+            })
+        })
+    }
+}
+
+/// The storage value at each of the requested blocks, in request order.
+#[derive(Debug)]
+pub struct Output(Vec<Result<StorageValue, BlockError>>);
+
+/// Errors which only affect a single requested block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockError {
+    ContractNotFound,
+    BlockNotFound,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Internal(anyhow::Error),
+    TooManyBlockIds { limit: usize, requested: usize },
+}
+
+impl From<anyhow::Error> for Error {
+    fn from(e: anyhow::Error) -> Self {
+        Self::Internal(e)
+    }
+}
+
+impl From<Error> for ApplicationError {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::Internal(internal) => Self::Internal(internal),
+            Error::TooManyBlockIds { limit, requested } => Self::Custom(anyhow::anyhow!(
+                "Too many block ids requested: {requested} exceeds the limit of {limit}"
+            )),
+        }
+    }
+}
+
+/// Get the value of a storage slot at multiple blocks.
+///
+/// All block ids are resolved against a single database snapshot, so the
+/// results are consistent with each other even if the chain advances while
+/// the request is being processed.
+pub async fn get_storage_at_blocks(context: RpcContext, input: Input) -> Result<Output, Error> {
+    if input.block_ids.len() > MAX_BLOCK_IDS {
+        return Err(Error::TooManyBlockIds {
+            limit: MAX_BLOCK_IDS,
+            requested: input.block_ids.len(),
+        });
+    }
+
+    let span = tracing::Span::current();
+
+    let jh = tokio::task::spawn_blocking(move || {
+        let _g = span.enter();
+        let mut db = context
+            .storage
+            .connection()
+            .context("Opening database connection")?;
+
+        let tx = db.transaction().context("Creating database transaction")?;
+
+        let pending = if input.block_ids.iter().any(BlockId::is_pending) {
+            Some(
+                context
+                    .pending_data
+                    .get(&tx)
+                    .context("Querying pending data")?,
+            )
+        } else {
+            None
+        };
+
+        input
+            .block_ids
+            .into_iter()
+            .map(|block_id| {
+                match get_storage_at::storage_value(
+                    &tx,
+                    pending.as_ref(),
+                    input.contract_address,
+                    input.key,
+                    block_id,
+                ) {
+                    Ok(value) => Ok(Ok(value)),
+                    Err(get_storage_at::Error::ContractNotFound) => {
+                        Ok(Err(BlockError::ContractNotFound))
+                    }
+                    Err(get_storage_at::Error::BlockNotFound) => Ok(Err(BlockError::BlockNotFound)),
+                    Err(get_storage_at::Error::Internal(e) | get_storage_at::Error::Custom(e)) => {
+                        Err(Error::Internal(e))
+                    }
+                }
+            })
+            .collect::<Result<Vec<_>, Error>>()
+            .map(Output)
+    });
+
+    jh.await.context("Database read panic or shutting down")?
+}
+
+impl SerializeForVersion for Output {
+    fn serialize(&self, serializer: Serializer) -> Result<serialize::Ok, serialize::Error> {
+        serializer.serialize_iter(self.0.len(), &mut self.0.iter().map(BlockResult))
+    }
+}
+
+struct BlockResult<'a>(&'a Result<StorageValue, BlockError>);
+
+impl SerializeForVersion for BlockResult<'_> {
+    fn serialize(&self, serializer: Serializer) -> Result<serialize::Ok, serialize::Error> {
+        let mut serializer = serializer.serialize_struct()?;
+        match self.0 {
+            Ok(value) => serializer.serialize_field("value", &crate::dto::Felt(&value.0))?,
+            Err(e) => serializer.serialize_field("error", e)?,
+        }
+        serializer.end()
+    }
+}
+
+impl SerializeForVersion for BlockError {
+    fn serialize(&self, serializer: Serializer) -> Result<serialize::Ok, serialize::Error> {
+        let error = match self {
+            BlockError::ContractNotFound => ApplicationError::ContractNotFound,
+            BlockError::BlockNotFound => ApplicationError::BlockNotFound,
+        };
+
+        let mut serializer = serializer.serialize_struct()?;
+        serializer.serialize_field("code", &error.code())?;
+        serializer.serialize_field("message", &error.to_string())?;
+        serializer.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::macro_prelude::*;
+    use pathfinder_common::BlockNumber;
+    use serde_json::json;
+
+    use super::*;
+    use crate::dto::DeserializeForVersion;
+    use crate::RpcVersion;
+
+    #[rstest::rstest]
+    #[case::positional(json!(["1", "2", ["latest", "pending"]]))]
+    #[case::named(json!({"contract_address": "0x1", "key": "0x2", "block_ids": ["latest", "pending"]}))]
+    fn parsing(#[case] input: serde_json::Value) {
+        let expected = Input {
+            contract_address: contract_address!("0x1"),
+            key: storage_address!("0x2"),
+            block_ids: vec![BlockId::Latest, BlockId::Pending],
+        };
+
+        let input =
+            Input::deserialize(crate::dto::Value::new(input, RpcVersion::PathfinderV01)).unwrap();
+
+        assert_eq!(input, expected);
+    }
+
+    #[tokio::test]
+    async fn mixed_results() {
+        let ctx = RpcContext::for_tests_with_pending().await;
+        let input = Input {
+            contract_address: contract_address_bytes!(b"contract 1"),
+            key: storage_address_bytes!(b"storage addr 0"),
+            block_ids: vec![
+                BlockId::Latest,
+                BlockId::Pending,
+                BlockId::Hash(block_hash_bytes!(b"block 1")),
+                BlockId::Number(BlockNumber::GENESIS),
+                BlockId::Number(BlockNumber::MAX),
+            ],
+        };
+
+        let output = get_storage_at_blocks(ctx, input).await.unwrap();
+
+        assert_eq!(
+            output.0,
+            vec![
+                Ok(storage_value_bytes!(b"storage value 2")),
+                Ok(storage_value_bytes!(b"storage value 2")),
+                Ok(storage_value_bytes!(b"storage value 1")),
+                Err(BlockError::ContractNotFound),
+                Err(BlockError::BlockNotFound),
+            ]
+        );
+    }
+
+    #[test]
+    fn serialization() {
+        let output = Output(vec![
+            Ok(storage_value!("0x123")),
+            Err(BlockError::ContractNotFound),
+            Err(BlockError::BlockNotFound),
+        ]);
+
+        let output = output
+            .serialize(Serializer::new(RpcVersion::PathfinderV01))
+            .unwrap();
+
+        assert_eq!(
+            output,
+            json!([
+                {"value": "0x123"},
+                {"error": {"code": 20, "message": "Contract not found"}},
+                {"error": {"code": 24, "message": "Block not found"}},
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn too_many_block_ids() {
+        let ctx = RpcContext::for_tests();
+        let input = Input {
+            contract_address: contract_address_bytes!(b"contract 1"),
+            key: storage_address_bytes!(b"storage addr 0"),
+            block_ids: vec![BlockId::Latest; MAX_BLOCK_IDS + 1],
+        };
+
+        let error = get_storage_at_blocks(ctx, input).await.unwrap_err();
+
+        assert_matches::assert_matches!(error, Error::TooManyBlockIds { .. });
+    }
+}
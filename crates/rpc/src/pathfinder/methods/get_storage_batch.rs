@@ -0,0 +1,318 @@
+use anyhow::Context;
+use pathfinder_common::{BlockId, ContractAddress, StorageAddress, StorageValue};
+
+use crate::context::RpcContext;
+use crate::dto::serialize::{self, SerializeForVersion, Serializer};
+use crate::error::ApplicationError;
+use crate::method::get_storage_at::resolve_storage_value;
+
+/// Limits the number of storage slots a single request may resolve, since
+/// they are all resolved within one database transaction.
+const MAX_KEYS: usize = 1000;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Input {
+    pub keys: Vec<StorageKey>,
+    pub block_id: BlockId,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct StorageKey {
+    pub contract_address: ContractAddress,
+    pub key: StorageAddress,
+}
+
+impl crate::dto::DeserializeForVersion for Input {
+    fn deserialize(value: crate::dto::Value) -> Result<Self, serde_json::Error> {
+        value.deserialize_map(|value| {
+            Ok(Self {
+                keys: value.deserialize_array("keys", |value| {
+                    value.deserialize_map(|value| {
+                        Ok(StorageKey {
+                            contract_address: value
+                                .deserialize("contract_address")
+                                .map(ContractAddress)?,
+                            key: value.deserialize("key").map(StorageAddress)?,
+                        })
+                    })
+                })?,
+                block_id: value.deserialize("block_id")?,
+            })
+        })
+    }
+}
+
+/// The storage value of each of the requested slots, in request order. `None`
+/// if the contract was not found.
+#[derive(Debug)]
+pub struct Output(Vec<Option<StorageValue>>);
+
+#[derive(Debug)]
+pub enum Error {
+    Internal(anyhow::Error),
+    BlockNotFound,
+    TooManyKeys { limit: usize, requested: usize },
+}
+
+impl From<anyhow::Error> for Error {
+    fn from(e: anyhow::Error) -> Self {
+        Self::Internal(e)
+    }
+}
+
+impl From<Error> for ApplicationError {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::Internal(internal) => Self::Internal(internal),
+            Error::BlockNotFound => Self::BlockNotFound,
+            Error::TooManyKeys { limit, requested } => Self::Custom(anyhow::anyhow!(
+                "Too many storage keys requested: {requested} exceeds the limit of {limit}"
+            )),
+        }
+    }
+}
+
+/// Get the values of multiple storage slots at a single block.
+///
+/// The block is resolved once, and all values are read within a single
+/// database transaction.
+pub async fn get_storage_batch(context: RpcContext, input: Input) -> Result<Output, Error> {
+    if input.keys.len() > MAX_KEYS {
+        return Err(Error::TooManyKeys {
+            limit: MAX_KEYS,
+            requested: input.keys.len(),
+        });
+    }
+
+    let span = tracing::Span::current();
+
+    let jh = tokio::task::spawn_blocking(move || {
+        let _g = span.enter();
+        let mut db = context
+            .storage
+            .connection()
+            .context("Opening database connection")?;
+
+        let tx = db.transaction().context("Creating database transaction")?;
+
+        let pending = if input.block_id.is_pending() {
+            Some(
+                context
+                    .pending_data
+                    .get(&tx)
+                    .context("Querying pending data")?,
+            )
+        } else {
+            None
+        };
+
+        let block_id = match input.block_id {
+            BlockId::Pending => pathfinder_storage::BlockId::Latest,
+            other => other.try_into().expect("Only pending cast should fail"),
+        };
+
+        let block_number = tx
+            .block_id(block_id)
+            .context("Querying block number")?
+            .ok_or(Error::BlockNotFound)?
+            .0;
+        // Reads of pruned state fail, which is reported as the block not being found.
+        let block_id = pathfinder_storage::BlockId::Number(block_number);
+
+        input
+            .keys
+            .into_iter()
+            .map(|slot| {
+                resolve_storage_value(
+                    &tx,
+                    pending.as_ref(),
+                    block_id,
+                    slot.contract_address,
+                    slot.key,
+                )
+                .map_err(Error::from)
+            })
+            .collect::<Result<Vec<_>, Error>>()
+            .map(Output)
+    });
+
+    jh.await.context("Database read panic or shutting down")?
+}
+
+impl SerializeForVersion for Output {
+    fn serialize(&self, serializer: Serializer) -> Result<serialize::Ok, serialize::Error> {
+        serializer.serialize_iter(self.0.len(), &mut self.0.iter().map(KeyResult))
+    }
+}
+
+struct KeyResult<'a>(&'a Option<StorageValue>);
+
+impl SerializeForVersion for KeyResult<'_> {
+    fn serialize(&self, serializer: Serializer) -> Result<serialize::Ok, serialize::Error> {
+        let mut serializer = serializer.serialize_struct()?;
+        match self.0 {
+            Some(value) => serializer.serialize_field("value", &crate::dto::Felt(&value.0))?,
+            None => serializer.serialize_field("error", &ContractNotFound)?,
+        }
+        serializer.end()
+    }
+}
+
+struct ContractNotFound;
+
+impl SerializeForVersion for ContractNotFound {
+    fn serialize(&self, serializer: Serializer) -> Result<serialize::Ok, serialize::Error> {
+        let error = ApplicationError::ContractNotFound;
+
+        let mut serializer = serializer.serialize_struct()?;
+        serializer.serialize_field("code", &error.code())?;
+        serializer.serialize_field("message", &error.to_string())?;
+        serializer.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::macro_prelude::*;
+    use pathfinder_common::BlockNumber;
+    use serde_json::json;
+
+    use super::*;
+    use crate::dto::DeserializeForVersion;
+    use crate::RpcVersion;
+
+    #[rstest::rstest]
+    #[case::positional(json!([[{"contract_address": "0x1", "key": "0x2"}], "latest"]))]
+    #[case::named(json!({"keys": [{"contract_address": "0x1", "key": "0x2"}], "block_id": "latest"}))]
+    fn parsing(#[case] input: serde_json::Value) {
+        let expected = Input {
+            keys: vec![StorageKey {
+                contract_address: contract_address!("0x1"),
+                key: storage_address!("0x2"),
+            }],
+            block_id: BlockId::Latest,
+        };
+
+        let input =
+            Input::deserialize(crate::dto::Value::new(input, RpcVersion::PathfinderV01)).unwrap();
+
+        assert_eq!(input, expected);
+    }
+
+    #[tokio::test]
+    async fn mixed_results() {
+        let ctx = RpcContext::for_tests_with_pending().await;
+        let input = Input {
+            keys: vec![
+                StorageKey {
+                    contract_address: contract_address_bytes!(b"contract 1"),
+                    key: storage_address_bytes!(b"storage addr 0"),
+                },
+                StorageKey {
+                    contract_address: contract_address_bytes!(b"contract 1"),
+                    key: storage_address_bytes!(b"unset storage addr"),
+                },
+                StorageKey {
+                    contract_address: contract_address_bytes!(b"unknown contract"),
+                    key: storage_address_bytes!(b"storage addr 0"),
+                },
+            ],
+            block_id: BlockId::Hash(block_hash_bytes!(b"block 1")),
+        };
+
+        let output = get_storage_batch(ctx, input).await.unwrap();
+
+        assert_eq!(
+            output.0,
+            vec![
+                Some(storage_value_bytes!(b"storage value 1")),
+                Some(StorageValue::ZERO),
+                None,
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn pending() {
+        let ctx = RpcContext::for_tests_with_pending().await;
+        let input = Input {
+            keys: vec![
+                StorageKey {
+                    contract_address: contract_address_bytes!(b"contract 1"),
+                    key: storage_address_bytes!(b"storage addr 0"),
+                },
+                StorageKey {
+                    contract_address: contract_address_bytes!(b"pending contract 1 address"),
+                    key: storage_address_bytes!(b"pending storage key 0"),
+                },
+                StorageKey {
+                    contract_address: contract_address_bytes!(b"pending contract 0 address"),
+                    key: storage_address_bytes!(b"unset storage addr"),
+                },
+            ],
+            block_id: BlockId::Pending,
+        };
+
+        let output = get_storage_batch(ctx, input).await.unwrap();
+
+        assert_eq!(
+            output.0,
+            vec![
+                Some(storage_value_bytes!(b"storage value 2")),
+                Some(storage_value_bytes!(b"pending storage value 0")),
+                Some(StorageValue::ZERO),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn block_not_found() {
+        let ctx = RpcContext::for_tests();
+        let input = Input {
+            keys: vec![StorageKey {
+                contract_address: contract_address_bytes!(b"contract 1"),
+                key: storage_address_bytes!(b"storage addr 0"),
+            }],
+            block_id: BlockId::Number(BlockNumber::MAX),
+        };
+
+        let error = get_storage_batch(ctx, input).await.unwrap_err();
+
+        assert_matches::assert_matches!(error, Error::BlockNotFound);
+    }
+
+    #[test]
+    fn serialization() {
+        let output = Output(vec![Some(storage_value!("0x123")), None]);
+
+        let output = output
+            .serialize(Serializer::new(RpcVersion::PathfinderV01))
+            .unwrap();
+
+        assert_eq!(
+            output,
+            json!([
+                {"value": "0x123"},
+                {"error": {"code": 20, "message": "Contract not found"}},
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn too_many_keys() {
+        let ctx = RpcContext::for_tests();
+        let input = Input {
+            keys: (0..=MAX_KEYS)
+                .map(|_| StorageKey {
+                    contract_address: contract_address_bytes!(b"contract 1"),
+                    key: storage_address_bytes!(b"storage addr 0"),
+                })
+                .collect(),
+            block_id: BlockId::Latest,
+        };
+
+        let error = get_storage_batch(ctx, input).await.unwrap_err();
+
+        assert_matches::assert_matches!(error, Error::TooManyKeys { .. });
+    }
+}
@@ -0,0 +1,201 @@
+use anyhow::Context;
+use pathfinder_common::{BlockNumber, ContractAddress, StorageAddress, StorageValue};
+
+use crate::context::RpcContext;
+use crate::dto::serialize::{self, SerializeForVersion, Serializer};
+use crate::error::ApplicationError;
+
+/// Limits the number of changes a single request may return. Clients should
+/// narrow the block range and query again if this is exceeded.
+const MAX_CHANGES: usize = 1000;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Input {
+    pub contract_address: ContractAddress,
+    pub key: StorageAddress,
+    pub from_block: BlockNumber,
+    pub to_block: BlockNumber,
+}
+
+impl crate::dto::DeserializeForVersion for Input {
+    fn deserialize(value: crate::dto::Value) -> Result<Self, serde_json::Error> {
+        value.deserialize_map(|value| {
+            Ok(Self {
+                contract_address: value.deserialize("contract_address").map(ContractAddress)?,
+                key: value.deserialize("key").map(StorageAddress)?,
+                from_block: value.deserialize_serde("from_block")?,
+                to_block: value.deserialize_serde("to_block")?,
+            })
+        })
+    }
+}
+
+/// The changes to the storage slot within the requested range, oldest first.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Output(Vec<(BlockNumber, StorageValue)>);
+
+#[derive(Debug)]
+pub enum Error {
+    Internal(anyhow::Error),
+    TooManyChanges { limit: usize },
+}
+
+impl From<anyhow::Error> for Error {
+    fn from(e: anyhow::Error) -> Self {
+        Self::Internal(e)
+    }
+}
+
+impl From<Error> for ApplicationError {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::Internal(internal) => Self::Internal(internal),
+            Error::TooManyChanges { limit } => Self::Custom(anyhow::anyhow!(
+                "The storage slot changed more than {limit} times in the requested range, please \
+                 request a smaller range"
+            )),
+        }
+    }
+}
+
+/// Get every change to a storage slot within `from_block..=to_block`.
+///
+/// Blocks in which the slot was not written are omitted, so the value at any
+/// block in the range is that of the latest change at or before it.
+pub async fn get_storage_history(context: RpcContext, input: Input) -> Result<Output, Error> {
+    let span = tracing::Span::current();
+
+    let jh = tokio::task::spawn_blocking(move || {
+        let _g = span.enter();
+        let mut db = context
+            .storage
+            .connection()
+            .context("Opening database connection")?;
+
+        let tx = db.transaction().context("Creating database transaction")?;
+
+        let history = tx
+            .storage_history(
+                input.contract_address,
+                input.key,
+                input.from_block,
+                input.to_block,
+                MAX_CHANGES + 1,
+            )
+            .context("Querying storage history")?;
+
+        if history.len() > MAX_CHANGES {
+            return Err(Error::TooManyChanges { limit: MAX_CHANGES });
+        }
+
+        Ok(Output(history))
+    });
+
+    jh.await.context("Database read panic or shutting down")?
+}
+
+impl SerializeForVersion for Output {
+    fn serialize(&self, serializer: Serializer) -> Result<serialize::Ok, serialize::Error> {
+        serializer.serialize_iter(self.0.len(), &mut self.0.iter().map(Change))
+    }
+}
+
+struct Change<'a>(&'a (BlockNumber, StorageValue));
+
+impl SerializeForVersion for Change<'_> {
+    fn serialize(&self, serializer: Serializer) -> Result<serialize::Ok, serialize::Error> {
+        let mut serializer = serializer.serialize_struct()?;
+        serializer.serialize_field("block_number", &self.0 .0)?;
+        serializer.serialize_field("value", &crate::dto::Felt(&self.0 .1 .0))?;
+        serializer.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::macro_prelude::*;
+    use serde_json::json;
+
+    use super::*;
+    use crate::dto::DeserializeForVersion;
+    use crate::RpcVersion;
+
+    #[rstest::rstest]
+    #[case::positional(json!(["0x1", "0x2", 3, 5]))]
+    #[case::named(json!({"contract_address": "0x1", "key": "0x2", "from_block": 3, "to_block": 5}))]
+    fn parsing(#[case] input: serde_json::Value) {
+        let expected = Input {
+            contract_address: contract_address!("0x1"),
+            key: storage_address!("0x2"),
+            from_block: BlockNumber::new_or_panic(3),
+            to_block: BlockNumber::new_or_panic(5),
+        };
+
+        let input =
+            Input::deserialize(crate::dto::Value::new(input, RpcVersion::PathfinderV01)).unwrap();
+
+        assert_eq!(input, expected);
+    }
+
+    #[tokio::test]
+    async fn history() {
+        let ctx = RpcContext::for_tests();
+        let input = Input {
+            contract_address: contract_address_bytes!(b"contract 1"),
+            key: storage_address_bytes!(b"storage addr 0"),
+            from_block: BlockNumber::GENESIS,
+            to_block: BlockNumber::MAX,
+        };
+
+        let output = get_storage_history(ctx, input).await.unwrap();
+
+        assert_eq!(
+            output,
+            Output(vec![
+                (
+                    BlockNumber::new_or_panic(1),
+                    storage_value_bytes!(b"storage value 1")
+                ),
+                (
+                    BlockNumber::new_or_panic(2),
+                    storage_value_bytes!(b"storage value 2")
+                ),
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn empty_range() {
+        let ctx = RpcContext::for_tests();
+        let input = Input {
+            contract_address: contract_address_bytes!(b"contract 1"),
+            key: storage_address_bytes!(b"storage addr 0"),
+            from_block: BlockNumber::new_or_panic(2),
+            to_block: BlockNumber::new_or_panic(1),
+        };
+
+        let output = get_storage_history(ctx, input).await.unwrap();
+
+        assert_eq!(output, Output(vec![]));
+    }
+
+    #[test]
+    fn serialization() {
+        let output = Output(vec![
+            (BlockNumber::new_or_panic(3), storage_value!("0x123")),
+            (BlockNumber::new_or_panic(7), storage_value!("0x0")),
+        ]);
+
+        let output = output
+            .serialize(Serializer::new(RpcVersion::PathfinderV01))
+            .unwrap();
+
+        assert_eq!(
+            output,
+            json!([
+                {"block_number": 3, "value": "0x123"},
+                {"block_number": 7, "value": "0x0"},
+            ])
+        );
+    }
+}
@@ -0,0 +1,319 @@
+use std::collections::HashSet;
+
+use anyhow::Context;
+use pathfinder_common::{BlockNumber, ContractAddress};
+use pathfinder_storage::SubmittedTransaction;
+
+use crate::context::RpcContext;
+use crate::dto::serialize::{self, SerializeForVersion, Serializer};
+
+crate::error::generate_rpc_error_subset!(Error);
+
+/// The maximum number of transactions returned.
+const LIMIT: usize = 1000;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Input {
+    pub sender_address: Option<ContractAddress>,
+}
+
+impl crate::dto::DeserializeForVersion for Input {
+    fn deserialize(value: crate::dto::Value) -> Result<Self, serde_json::Error> {
+        value.deserialize_map(|value| {
+            Ok(Self {
+                sender_address: value
+                    .deserialize_optional("sender_address")?
+                    .map(ContractAddress),
+            })
+        })
+    }
+}
+
+/// The tracked transactions, newest first.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Output(Vec<(SubmittedTransaction, Status)>);
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Status {
+    /// Neither in the pending block nor in any stored block yet.
+    Received,
+    Pending,
+    AcceptedOnL2(BlockNumber),
+    /// The gateway rejected a re-broadcast of the transaction.
+    Rejected,
+}
+
+/// Get the transactions submitted through this node within the last day,
+/// optionally only those of `sender_address`, along with whether they have
+/// made it into a block.
+///
+/// Transactions which haven't appeared in a block within the re-broadcast
+/// window are sent to the gateway again.
+pub async fn get_submitted_transactions(
+    context: RpcContext,
+    input: Input,
+) -> Result<Output, Error> {
+    let span = tracing::Span::current();
+
+    let jh = tokio::task::spawn_blocking(move || {
+        let _g = span.enter();
+        let mut db = context
+            .storage
+            .connection()
+            .context("Opening database connection")?;
+
+        let tx = db.transaction().context("Creating database transaction")?;
+
+        let pending = context
+            .pending_data
+            .get(&tx)
+            .context("Querying pending data")?;
+        let pending = pending
+            .block
+            .transactions
+            .iter()
+            .map(|tx| tx.hash)
+            .collect::<HashSet<_>>();
+
+        let submitted = tx
+            .submitted_transactions(input.sender_address, LIMIT)
+            .context("Querying submitted transactions")?;
+
+        let transactions = submitted
+            .into_iter()
+            .map(|submitted| {
+                let block_number = match tx
+                    .transaction_block_hash(submitted.hash)
+                    .context("Querying transaction's block")?
+                {
+                    Some(block_hash) => tx
+                        .block_number(block_hash.into())
+                        .context("Querying block number")?,
+                    None => None,
+                };
+
+                let status = if let Some(block_number) = block_number {
+                    Status::AcceptedOnL2(block_number)
+                } else if pending.contains(&submitted.hash) {
+                    Status::Pending
+                } else if submitted.rejection.is_some() {
+                    Status::Rejected
+                } else {
+                    Status::Received
+                };
+
+                anyhow::Ok((submitted, status))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Output(transactions))
+    });
+
+    jh.await.context("Database read panic or shutting down")?
+}
+
+impl SerializeForVersion for Output {
+    fn serialize(&self, serializer: Serializer) -> Result<serialize::Ok, serialize::Error> {
+        serializer.serialize_iter(
+            self.0.len(),
+            &mut self
+                .0
+                .iter()
+                .map(|(submitted, status)| Transaction(submitted, *status)),
+        )
+    }
+}
+
+struct Transaction<'a>(&'a SubmittedTransaction, Status);
+
+impl SerializeForVersion for Transaction<'_> {
+    fn serialize(&self, serializer: Serializer) -> Result<serialize::Ok, serialize::Error> {
+        let Self(submitted, status) = self;
+
+        let mut serializer = serializer.serialize_struct()?;
+        serializer.serialize_field("transaction_hash", &crate::dto::TxnHash(&submitted.hash))?;
+        serializer.serialize_field(
+            "sender_address",
+            &crate::dto::Address(&submitted.sender_address),
+        )?;
+        serializer.serialize_field("submitted_at", &submitted.submitted_at)?;
+        serializer.serialize_field("last_broadcast_at", &submitted.last_broadcast_at)?;
+        serializer.serialize_field("broadcast_count", &submitted.broadcast_count)?;
+        serializer.serialize_field(
+            "status",
+            &match status {
+                Status::Received => "RECEIVED",
+                Status::Pending => "PENDING",
+                Status::AcceptedOnL2(_) => "ACCEPTED_ON_L2",
+                Status::Rejected => "REJECTED",
+            },
+        )?;
+        if let Status::AcceptedOnL2(block_number) = status {
+            serializer.serialize_field("block_number", block_number)?;
+        }
+        if let Status::Rejected = status {
+            serializer.serialize_optional("rejection_reason", submitted.rejection.as_deref())?;
+        }
+        serializer.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::macro_prelude::*;
+    use pathfinder_common::TransactionHash;
+    use serde_json::json;
+
+    use super::*;
+    use crate::dto::DeserializeForVersion;
+    use crate::RpcVersion;
+
+    #[rstest::rstest]
+    #[case::positional(json!(["0x1"]))]
+    #[case::named(json!({"sender_address": "0x1"}))]
+    fn parsing(#[case] input: serde_json::Value) {
+        let expected = Input {
+            sender_address: Some(contract_address!("0x1")),
+        };
+
+        let input =
+            Input::deserialize(crate::dto::Value::new(input, RpcVersion::PathfinderV01)).unwrap();
+
+        assert_eq!(input, expected);
+    }
+
+    #[test]
+    fn parsing_without_sender() {
+        let input =
+            Input::deserialize(crate::dto::Value::new(json!({}), RpcVersion::PathfinderV01))
+                .unwrap();
+
+        assert_eq!(
+            input,
+            Input {
+                sender_address: None
+            }
+        );
+    }
+
+    fn submitted(hash: TransactionHash, submitted_at: u64) -> SubmittedTransaction {
+        SubmittedTransaction {
+            hash,
+            sender_address: contract_address!("0x123"),
+            submitted_at,
+            last_broadcast_at: submitted_at,
+            broadcast_count: 1,
+            rejection: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn statuses() {
+        let context = RpcContext::for_tests_with_pending().await;
+
+        let (included, pending) = {
+            let mut db = context.storage.connection().unwrap();
+            let tx = db.transaction().unwrap();
+
+            let included = tx
+                .transaction_hashes_for_block(BlockNumber::GENESIS.into())
+                .unwrap()
+                .unwrap()[0];
+            let pending = context.pending_data.get(&tx).unwrap().block.transactions[0].hash;
+
+            let rejected = SubmittedTransaction {
+                rejection: Some("Invalid transaction nonce".to_owned()),
+                ..submitted(transaction_hash!("0xdead"), 1)
+            };
+            for transaction in [
+                submitted(included, 4),
+                submitted(pending, 3),
+                submitted(transaction_hash!("0xbeef"), 2),
+                rejected,
+            ] {
+                tx.insert_submitted_transaction(&transaction, b"{}")
+                    .unwrap();
+            }
+            tx.commit().unwrap();
+
+            (included, pending)
+        };
+
+        let output = get_submitted_transactions(
+            context.clone(),
+            Input {
+                sender_address: None,
+            },
+        )
+        .await
+        .unwrap();
+
+        let statuses = output
+            .0
+            .iter()
+            .map(|(submitted, status)| (submitted.hash, *status))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            statuses,
+            vec![
+                (included, Status::AcceptedOnL2(BlockNumber::GENESIS)),
+                (pending, Status::Pending),
+                (transaction_hash!("0xbeef"), Status::Received),
+                (transaction_hash!("0xdead"), Status::Rejected),
+            ]
+        );
+
+        let output = get_submitted_transactions(
+            context,
+            Input {
+                sender_address: Some(contract_address!("0x456")),
+            },
+        )
+        .await
+        .unwrap();
+        assert!(output.0.is_empty());
+    }
+
+    #[test]
+    fn serialization() {
+        let rejected = SubmittedTransaction {
+            rejection: Some("Invalid transaction nonce".to_owned()),
+            ..submitted(transaction_hash!("0x2"), 100)
+        };
+        let output = Output(vec![
+            (
+                submitted(transaction_hash!("0x1"), 200),
+                Status::AcceptedOnL2(BlockNumber::new_or_panic(5)),
+            ),
+            (rejected, Status::Rejected),
+        ]);
+
+        let output = output
+            .serialize(Serializer::new(RpcVersion::PathfinderV01))
+            .unwrap();
+
+        assert_eq!(
+            output,
+            json!([
+                {
+                    "transaction_hash": "0x1",
+                    "sender_address": "0x123",
+                    "submitted_at": 200,
+                    "last_broadcast_at": 200,
+                    "broadcast_count": 1,
+                    "status": "ACCEPTED_ON_L2",
+                    "block_number": 5,
+                },
+                {
+                    "transaction_hash": "0x2",
+                    "sender_address": "0x123",
+                    "submitted_at": 100,
+                    "last_broadcast_at": 100,
+                    "broadcast_count": 1,
+                    "status": "REJECTED",
+                    "rejection_reason": "Invalid transaction nonce",
+                },
+            ])
+        );
+    }
+}
@@ -0,0 +1,148 @@
+use anyhow::Context;
+use pathfinder_common::{BlockHash, BlockNumber};
+
+use super::get_reorgs::BlockRef;
+use crate::context::RpcContext;
+use crate::dto::serialize::{self, SerializeForVersion, Serializer};
+use crate::v02::types::syncing::Syncing;
+
+crate::error::generate_rpc_error_subset!(Error);
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Output {
+    /// The latest block stored by this node.
+    local_head: Option<(BlockNumber, BlockHash)>,
+    /// The chain tip as last seen by sync, `None` if sync has not reported its
+    /// status yet.
+    gateway_head: Option<(BlockNumber, BlockHash)>,
+    /// The latest block whose state update has been accepted on L1.
+    l1_head: Option<(BlockNumber, BlockHash)>,
+}
+
+/// Reports how far behind the chain tip and L1 this node's latest block is.
+pub async fn get_sync_lag(context: RpcContext) -> Result<Output, Error> {
+    let gateway_head = match &*context.sync_status.status.read().await {
+        Syncing::Status(status) => Some((status.highest.number, status.highest.hash)),
+        Syncing::False(_) => None,
+    };
+
+    let span = tracing::Span::current();
+
+    let jh = tokio::task::spawn_blocking(move || {
+        let _g = span.enter();
+        let mut db = context
+            .storage
+            .connection()
+            .context("Opening database connection")?;
+
+        let tx = db.transaction().context("Creating database transaction")?;
+
+        let local_head = tx
+            .block_id(pathfinder_storage::BlockId::Latest)
+            .context("Querying latest block")?;
+        let l1_head = tx
+            .latest_l1_state()
+            .context("Querying latest L1 state")?
+            .map(|l1| (l1.block_number, l1.block_hash));
+
+        Ok(Output {
+            local_head,
+            gateway_head,
+            l1_head,
+        })
+    });
+
+    jh.await.context("Database read panic or shutting down")?
+}
+
+impl SerializeForVersion for Output {
+    fn serialize(&self, serializer: Serializer) -> Result<serialize::Ok, serialize::Error> {
+        let local_number = self.local_head.map(|(number, _)| number.get());
+        let blocks_behind_gateway = self.gateway_head.map(|(number, _)| {
+            number
+                .get()
+                .saturating_sub(local_number.unwrap_or_default())
+        });
+
+        let mut serializer = serializer.serialize_struct()?;
+        serializer.serialize_optional("local_head", self.local_head.as_ref().map(block_ref))?;
+        serializer.serialize_optional("gateway_head", self.gateway_head.as_ref().map(block_ref))?;
+        serializer.serialize_optional("l1_head", self.l1_head.as_ref().map(block_ref))?;
+        serializer.serialize_optional("blocks_behind_gateway", blocks_behind_gateway)?;
+        serializer.end()
+    }
+}
+
+fn block_ref((number, hash): &(BlockNumber, BlockHash)) -> BlockRef<'_> {
+    BlockRef(*number, hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::macro_prelude::*;
+    use pathfinder_ethereum::EthereumStateUpdate;
+    use serde_json::json;
+
+    use super::*;
+    use crate::v02::types::syncing::{NumberedBlock, Status};
+    use crate::RpcVersion;
+
+    #[tokio::test]
+    async fn heads() {
+        let ctx = RpcContext::for_tests();
+        {
+            let mut db = ctx.storage.connection().unwrap();
+            let tx = db.transaction().unwrap();
+            tx.upsert_l1_state(&EthereumStateUpdate {
+                state_root: state_commitment!("0x1"),
+                block_number: BlockNumber::GENESIS,
+                block_hash: block_hash_bytes!(b"genesis"),
+            })
+            .unwrap();
+            tx.commit().unwrap();
+        }
+        *ctx.sync_status.status.write().await = Syncing::Status(Status {
+            starting: NumberedBlock::from(("0x0", 0)),
+            current: NumberedBlock::from(("0x2", 2)),
+            highest: NumberedBlock::from(("0xa", 10)),
+        });
+
+        let output = get_sync_lag(ctx.clone()).await.unwrap();
+
+        let local_head = {
+            let mut db = ctx.storage.connection().unwrap();
+            let tx = db.transaction().unwrap();
+            tx.block_id(pathfinder_storage::BlockId::Latest).unwrap()
+        };
+        assert_eq!(
+            output,
+            Output {
+                local_head,
+                gateway_head: Some((BlockNumber::new_or_panic(10), block_hash!("0xa"))),
+                l1_head: Some((BlockNumber::GENESIS, block_hash_bytes!(b"genesis"))),
+            }
+        );
+    }
+
+    #[test]
+    fn serialization() {
+        let output = Output {
+            local_head: Some((BlockNumber::new_or_panic(7), block_hash!("0x7"))),
+            gateway_head: Some((BlockNumber::new_or_panic(10), block_hash!("0xa"))),
+            l1_head: None,
+        };
+
+        let output = output
+            .serialize(Serializer::new(RpcVersion::PathfinderV01))
+            .unwrap();
+
+        assert_eq!(
+            output,
+            json!({
+                "local_head": {"block_number": 7, "block_hash": "0x7"},
+                "gateway_head": {"block_number": 10, "block_hash": "0xa"},
+                "blocks_behind_gateway": 3,
+            })
+        );
+    }
+}
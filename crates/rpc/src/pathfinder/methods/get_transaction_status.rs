@@ -73,10 +73,12 @@ pub async fn get_transaction_status(
         return Ok(db_status);
     }
 
-    // Check gateway for rejected transactions.
+    // Check gateway for rejected transactions, unless offline.
     use starknet_gateway_client::GatewayApi;
-    context
-        .sequencer
+    let Some(sequencer) = &context.sequencer else {
+        return Ok(TransactionStatus::NotReceived);
+    };
+    sequencer
         .transaction_status(input.transaction_hash)
         .await
         .context("Fetching transaction status from gateway")
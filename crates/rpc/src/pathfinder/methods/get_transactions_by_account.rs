@@ -0,0 +1,323 @@
+use std::str::FromStr;
+
+use anyhow::Context;
+use pathfinder_common::{BlockNumber, ContractAddress};
+use pathfinder_storage::SentTransaction;
+
+use crate::context::RpcContext;
+use crate::dto::serialize::{self, SerializeForVersion, Serializer};
+
+crate::error::generate_rpc_error_subset!(Error: InvalidContinuationToken);
+
+/// The number of transactions returned per page.
+const PAGE_SIZE: usize = 1000;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Input {
+    pub address: ContractAddress,
+    pub from_block: BlockNumber,
+    pub to_block: BlockNumber,
+    pub continuation_token: Option<String>,
+}
+
+impl crate::dto::DeserializeForVersion for Input {
+    fn deserialize(value: crate::dto::Value) -> Result<Self, serde_json::Error> {
+        value.deserialize_map(|value| {
+            Ok(Self {
+                address: value.deserialize("address").map(ContractAddress)?,
+                from_block: value.deserialize_serde("from_block")?,
+                to_block: value.deserialize_serde("to_block")?,
+                continuation_token: value.deserialize_optional_serde("continuation_token")?,
+            })
+        })
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Output {
+    transactions: Vec<SentTransaction>,
+    continuation_token: Option<String>,
+}
+
+/// Points at the first transaction of the next page.
+///
+/// Formatted as `<block number>-<transaction index>`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct ContinuationToken {
+    block_number: BlockNumber,
+    index: usize,
+}
+
+impl FromStr for ContinuationToken {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (block_number, index) = s.split_once('-').ok_or(())?;
+        let block_number = block_number.parse::<u64>().map_err(|_| ())?;
+        let block_number = BlockNumber::new(block_number).ok_or(())?;
+        let index = index.parse().map_err(|_| ())?;
+
+        Ok(Self {
+            block_number,
+            index,
+        })
+    }
+}
+
+impl std::fmt::Display for ContinuationToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{}", self.block_number.get(), self.index)
+    }
+}
+
+/// Get the hashes of the transactions sent by an account within
+/// `from_block..=to_block`, oldest first.
+///
+/// Invoke and declare transactions are attributed to their sender, deploy
+/// account transactions to the deployed account. `v0` declare transactions
+/// have no sender account and are not included. If the range does not fit
+/// into a single page, `continuation_token` is set and should be passed to the
+/// next request along with the same range.
+pub async fn get_transactions_by_account(
+    context: RpcContext,
+    input: Input,
+) -> Result<Output, Error> {
+    transactions_by_account(context, input, PAGE_SIZE).await
+}
+
+async fn transactions_by_account(
+    context: RpcContext,
+    input: Input,
+    page_size: usize,
+) -> Result<Output, Error> {
+    let from = match input.continuation_token.as_deref() {
+        Some(token) => {
+            let token = token
+                .parse::<ContinuationToken>()
+                .map_err(|_| Error::InvalidContinuationToken)?;
+            if token.block_number < input.from_block {
+                return Err(Error::InvalidContinuationToken);
+            }
+            (token.block_number, token.index)
+        }
+        None => (input.from_block, 0),
+    };
+
+    let span = tracing::Span::current();
+
+    let jh = tokio::task::spawn_blocking(move || {
+        let _g = span.enter();
+        let mut db = context
+            .storage
+            .connection()
+            .context("Opening database connection")?;
+
+        let tx = db.transaction().context("Creating database transaction")?;
+
+        let mut transactions = tx
+            .transactions_by_sender(input.address, from, input.to_block, page_size + 1)
+            .context("Querying transactions by sender")?;
+
+        let continuation_token = if transactions.len() > page_size {
+            let next = transactions.pop().expect("Page is not empty");
+            Some(
+                ContinuationToken {
+                    block_number: next.block_number,
+                    index: next.index,
+                }
+                .to_string(),
+            )
+        } else {
+            None
+        };
+
+        Ok(Output {
+            transactions,
+            continuation_token,
+        })
+    });
+
+    jh.await.context("Database read panic or shutting down")?
+}
+
+impl SerializeForVersion for Output {
+    fn serialize(&self, serializer: Serializer) -> Result<serialize::Ok, serialize::Error> {
+        let mut serializer = serializer.serialize_struct()?;
+        serializer.serialize_iter(
+            "transactions",
+            self.transactions.len(),
+            &mut self.transactions.iter().map(Transaction),
+        )?;
+        serializer.serialize_optional("continuation_token", self.continuation_token.as_ref())?;
+        serializer.end()
+    }
+}
+
+struct Transaction<'a>(&'a SentTransaction);
+
+impl SerializeForVersion for Transaction<'_> {
+    fn serialize(&self, serializer: Serializer) -> Result<serialize::Ok, serialize::Error> {
+        let mut serializer = serializer.serialize_struct()?;
+        serializer.serialize_field("block_number", &self.0.block_number)?;
+        serializer.serialize_field("transaction_hash", &crate::dto::Felt(&self.0.hash.0))?;
+        serializer.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::macro_prelude::*;
+    use serde_json::json;
+
+    use super::*;
+    use crate::dto::DeserializeForVersion;
+    use crate::RpcVersion;
+
+    #[rstest::rstest]
+    #[case::positional(json!(["0x1", 3, 5, "3-1"]))]
+    #[case::named(json!({"address": "0x1", "from_block": 3, "to_block": 5, "continuation_token": "3-1"}))]
+    fn parsing(#[case] input: serde_json::Value) {
+        let expected = Input {
+            address: contract_address!("0x1"),
+            from_block: BlockNumber::new_or_panic(3),
+            to_block: BlockNumber::new_or_panic(5),
+            continuation_token: Some("3-1".to_owned()),
+        };
+
+        let input =
+            Input::deserialize(crate::dto::Value::new(input, RpcVersion::PathfinderV01)).unwrap();
+
+        assert_eq!(input, expected);
+    }
+
+    fn hashes(output: &Output) -> Vec<pathfinder_common::TransactionHash> {
+        output.transactions.iter().map(|tx| tx.hash).collect()
+    }
+
+    #[tokio::test]
+    async fn all_transactions() {
+        let ctx = RpcContext::for_tests();
+        let input = Input {
+            address: contract_address_bytes!(b"contract 1"),
+            from_block: BlockNumber::GENESIS,
+            to_block: BlockNumber::MAX,
+            continuation_token: None,
+        };
+
+        let output = get_transactions_by_account(ctx, input).await.unwrap();
+
+        assert_eq!(output.continuation_token, None);
+        assert_eq!(
+            hashes(&output),
+            vec![
+                transaction_hash_bytes!(b"txn 1"),
+                transaction_hash_bytes!(b"txn 2"),
+                transaction_hash_bytes!(b"txn 3"),
+                transaction_hash_bytes!(b"txn 5"),
+                transaction_hash_bytes!(b"txn 6"),
+                transaction_hash_bytes!(b"txn reverted"),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn block_range() {
+        let ctx = RpcContext::for_tests();
+        let input = Input {
+            address: contract_address_bytes!(b"contract 1"),
+            from_block: BlockNumber::new_or_panic(1),
+            to_block: BlockNumber::new_or_panic(1),
+            continuation_token: None,
+        };
+
+        let output = get_transactions_by_account(ctx, input).await.unwrap();
+
+        assert_eq!(
+            hashes(&output),
+            vec![
+                transaction_hash_bytes!(b"txn 1"),
+                transaction_hash_bytes!(b"txn 2"),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn paging() {
+        let ctx = RpcContext::for_tests();
+        let mut input = Input {
+            address: contract_address_bytes!(b"contract 1"),
+            from_block: BlockNumber::GENESIS,
+            to_block: BlockNumber::MAX,
+            continuation_token: None,
+        };
+
+        let first = transactions_by_account(ctx.clone(), input.clone(), 3)
+            .await
+            .unwrap();
+        assert_eq!(
+            hashes(&first),
+            vec![
+                transaction_hash_bytes!(b"txn 1"),
+                transaction_hash_bytes!(b"txn 2"),
+                transaction_hash_bytes!(b"txn 3"),
+            ]
+        );
+        // `txn 4` at index 1 of block 2 is sent by another account.
+        assert_eq!(first.continuation_token.as_deref(), Some("2-2"));
+
+        input.continuation_token = first.continuation_token;
+        let second = transactions_by_account(ctx, input, 3).await.unwrap();
+        assert_eq!(
+            hashes(&second),
+            vec![
+                transaction_hash_bytes!(b"txn 5"),
+                transaction_hash_bytes!(b"txn 6"),
+                transaction_hash_bytes!(b"txn reverted"),
+            ]
+        );
+        assert_eq!(second.continuation_token, None);
+    }
+
+    #[rstest::rstest]
+    #[case::malformed("abc")]
+    #[case::missing_index("1")]
+    #[case::before_range("0-0")]
+    #[tokio::test]
+    async fn invalid_continuation_token(#[case] token: &str) {
+        let ctx = RpcContext::for_tests();
+        let input = Input {
+            address: contract_address_bytes!(b"contract 1"),
+            from_block: BlockNumber::new_or_panic(1),
+            to_block: BlockNumber::MAX,
+            continuation_token: Some(token.to_owned()),
+        };
+
+        let error = get_transactions_by_account(ctx, input).await.unwrap_err();
+
+        assert_matches::assert_matches!(error, Error::InvalidContinuationToken);
+    }
+
+    #[test]
+    fn serialization() {
+        let output = Output {
+            transactions: vec![SentTransaction {
+                block_number: BlockNumber::new_or_panic(3),
+                index: 1,
+                hash: transaction_hash!("0x123"),
+            }],
+            continuation_token: Some("4-0".to_owned()),
+        };
+
+        let output = output
+            .serialize(Serializer::new(RpcVersion::PathfinderV01))
+            .unwrap();
+
+        assert_eq!(
+            output,
+            json!({
+                "transactions": [{"block_number": 3, "transaction_hash": "0x123"}],
+                "continuation_token": "4-0",
+            })
+        );
+    }
+}
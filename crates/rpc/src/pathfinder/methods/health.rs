@@ -0,0 +1,12 @@
+use crate::context::RpcContext;
+use crate::health::HealthStatus;
+
+crate::error::generate_rpc_error_subset!(Error);
+
+/// Reports the health of the node's database, sync and pending data.
+///
+/// A degraded node is still reported successfully, the verdict is part of the
+/// output.
+pub async fn health(context: RpcContext) -> Result<HealthStatus, Error> {
+    Ok(context.health().await)
+}
@@ -0,0 +1,164 @@
+use anyhow::Context;
+use pathfinder_common::{ClassHash, ContractAddress};
+
+use crate::abi_registry::{AbiTarget, RegisterAbiError};
+use crate::context::RpcContext;
+use crate::error::ApplicationError;
+
+#[derive(Debug, PartialEq)]
+pub struct Input {
+    target: AbiTarget,
+    /// The ABI as found in the contract class, either as a JSON array or as
+    /// the string encoding of one.
+    abi: String,
+}
+
+impl crate::dto::DeserializeForVersion for Input {
+    fn deserialize(value: crate::dto::Value) -> Result<Self, serde_json::Error> {
+        value.deserialize_map(|value| {
+            let class_hash: Option<ClassHash> = value.deserialize_optional_serde("class_hash")?;
+            let contract_address: Option<ContractAddress> =
+                value.deserialize_optional_serde("contract_address")?;
+            let target = match (class_hash, contract_address) {
+                (Some(class_hash), None) => AbiTarget::Class(class_hash),
+                (None, Some(contract_address)) => AbiTarget::Contract(contract_address),
+                _ => {
+                    return Err(serde::de::Error::custom(
+                        "exactly one of class_hash and contract_address is required",
+                    ))
+                }
+            };
+
+            let abi: serde_json::Value = value.deserialize_serde("abi")?;
+            let abi = match abi {
+                serde_json::Value::String(abi) => abi,
+                abi @ serde_json::Value::Array(_) => abi.to_string(),
+                _ => return Err(serde::de::Error::custom("abi must be an array or a string")),
+            };
+
+            Ok(Self { target, abi })
+        })
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Internal(anyhow::Error),
+    InvalidAbi(anyhow::Error),
+    Limit(RegisterAbiError),
+}
+
+impl From<RegisterAbiError> for Error {
+    fn from(e: RegisterAbiError) -> Self {
+        match e {
+            RegisterAbiError::InvalidAbi(e) => Self::InvalidAbi(e),
+            e @ (RegisterAbiError::TooLarge | RegisterAbiError::TooMany) => Self::Limit(e),
+            RegisterAbiError::Internal(e) => Self::Internal(e),
+        }
+    }
+}
+
+impl From<anyhow::Error> for Error {
+    fn from(e: anyhow::Error) -> Self {
+        Self::Internal(e)
+    }
+}
+
+impl From<Error> for ApplicationError {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::Internal(internal) => Self::Internal(internal),
+            Error::InvalidAbi(e) => Self::Custom(anyhow::anyhow!("Invalid ABI: {e:#}")),
+            Error::Limit(e) => Self::Custom(e.into()),
+        }
+    }
+}
+
+/// Registers the ABI used to decode the events of a class or contract when
+/// `decode` is requested.
+pub async fn register_abi(context: RpcContext, input: Input) -> Result<(), Error> {
+    let span = tracing::Span::current();
+
+    let jh = tokio::task::spawn_blocking(move || {
+        let _g = span.enter();
+        context.abis.register(input.target, &input.abi)?;
+        Ok(())
+    });
+
+    jh.await.context("Registering ABI")?
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+    use pathfinder_common::macro_prelude::*;
+    use serde_json::json;
+
+    use super::*;
+    use crate::dto::DeserializeForVersion;
+    use crate::RpcVersion;
+
+    const ABI: &str = r#"[{"type": "event", "name": "Ping", "keys": [], "data": []}]"#;
+
+    #[rstest::rstest]
+    #[case::class_hash(
+        json!({"class_hash": "0x10", "abi": ABI}),
+        AbiTarget::Class(class_hash!("0x10"))
+    )]
+    #[case::contract_address(
+        json!({"contract_address": "0x1", "abi": serde_json::from_str::<serde_json::Value>(ABI).unwrap()}),
+        AbiTarget::Contract(contract_address!("0x1"))
+    )]
+    fn parsing(#[case] input: serde_json::Value, #[case] target: AbiTarget) {
+        let input =
+            Input::deserialize(crate::dto::Value::new(input, RpcVersion::PathfinderV01)).unwrap();
+
+        assert_eq!(input.target, target);
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&input.abi).unwrap(),
+            serde_json::from_str::<serde_json::Value>(ABI).unwrap()
+        );
+    }
+
+    #[rstest::rstest]
+    #[case::no_target(json!({"abi": ABI}))]
+    #[case::both_targets(json!({"class_hash": "0x10", "contract_address": "0x1", "abi": ABI}))]
+    #[case::not_an_abi(json!({"class_hash": "0x10", "abi": 1}))]
+    fn parsing_rejects(#[case] input: serde_json::Value) {
+        Input::deserialize(crate::dto::Value::new(input, RpcVersion::PathfinderV01)).unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn invalid_abi() {
+        let context = RpcContext::for_tests();
+        let input = Input {
+            target: AbiTarget::Class(class_hash!("0x10")),
+            abi: "[{}]".to_owned(),
+        };
+
+        let error = register_abi(context, input).await.unwrap_err();
+        assert_matches!(error, Error::InvalidAbi(_));
+    }
+
+    #[tokio::test]
+    async fn registered_abi_is_used() {
+        let context = RpcContext::for_tests();
+        let input = Input {
+            target: AbiTarget::Contract(contract_address!("0x1")),
+            abi: ABI.to_owned(),
+        };
+
+        register_abi(context.clone(), input).await.unwrap();
+
+        let decoded = context
+            .abis
+            .decode(
+                contract_address!("0x1"),
+                None,
+                &[pathfinder_common::EntryPoint::hashed(b"Ping").0],
+                &[],
+            )
+            .unwrap();
+        assert_eq!(decoded.name, "Ping");
+    }
+}
@@ -0,0 +1,74 @@
+use axum::async_trait;
+use tokio::sync::mpsc;
+
+use crate::context::RpcContext;
+use crate::jsonrpc::{RpcError, RpcSubscriptionFlow, SubscriptionMessage};
+use crate::method::subscribe_pending_transactions::{
+    self as starknet,
+    stream_pending_transactions,
+    Notification,
+};
+
+/// Streams the hashes, or optionally the full transactions, of pending
+/// transactions as they appear in the pending data.
+pub struct SubscribePendingTransactions;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Params {
+    with_details: Option<bool>,
+}
+
+impl crate::dto::DeserializeForVersion for Option<Params> {
+    fn deserialize(value: crate::dto::Value) -> Result<Self, serde_json::Error> {
+        if value.is_null() {
+            return Ok(None);
+        }
+        value.deserialize_map(|value| {
+            Ok(Some(Params {
+                with_details: value.deserialize_optional_serde("with_details")?,
+            }))
+        })
+    }
+}
+
+const SUBSCRIPTION_NAME: &str = "pathfinder_subscriptionPendingTransactions";
+
+#[async_trait]
+impl RpcSubscriptionFlow for SubscribePendingTransactions {
+    type Params = Option<Params>;
+    type Notification = Notification;
+
+    async fn subscribe(
+        state: RpcContext,
+        params: Self::Params,
+        tx: mpsc::Sender<SubscriptionMessage<Self::Notification>>,
+    ) -> Result<(), RpcError> {
+        let params = starknet::Params {
+            transaction_details: params.unwrap_or_default().with_details,
+            sender_address: None,
+        };
+        stream_pending_transactions(state, params, tx, SUBSCRIPTION_NAME).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::dto::DeserializeForVersion;
+    use crate::RpcVersion;
+
+    #[rstest::rstest]
+    #[case::missing(json!(null), None)]
+    #[case::positional(json!([true]), Some(Params { with_details: Some(true) }))]
+    #[case::named(json!({"with_details": false}), Some(Params { with_details: Some(false) }))]
+    #[case::empty(json!({}), Some(Params { with_details: None }))]
+    fn parsing(#[case] input: serde_json::Value, #[case] expected: Option<Params>) {
+        let input =
+            Option::<Params>::deserialize(crate::dto::Value::new(input, RpcVersion::PathfinderV01))
+                .unwrap();
+
+        assert_eq!(input, expected);
+    }
+}
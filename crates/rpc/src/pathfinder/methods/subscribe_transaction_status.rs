@@ -0,0 +1,268 @@
+use axum::async_trait;
+use pathfinder_common::{BlockId, BlockNumber, TransactionHash};
+use tokio::sync::mpsc;
+
+use crate::context::RpcContext;
+use crate::jsonrpc::{RpcError, RpcSubscriptionFlow, SubscriptionMessage};
+use crate::method::subscribe_transaction_status::{
+    self as starknet,
+    stream_transaction_status,
+    Notification,
+};
+
+/// Streams the status transitions of a transaction, from `RECEIVED` through
+/// `ACCEPTED_ON_L2` to `ACCEPTED_ON_L1`, or `REJECTED`.
+pub struct SubscribeTransactionStatus;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Params {
+    transaction_hash: TransactionHash,
+}
+
+impl crate::dto::DeserializeForVersion for Params {
+    fn deserialize(value: crate::dto::Value) -> Result<Self, serde_json::Error> {
+        value.deserialize_map(|value| {
+            Ok(Self {
+                transaction_hash: value.deserialize("transaction_hash").map(TransactionHash)?,
+            })
+        })
+    }
+}
+
+const SUBSCRIPTION_NAME: &str = "pathfinder_subscriptionTransactionStatus";
+
+#[async_trait]
+impl RpcSubscriptionFlow for SubscribeTransactionStatus {
+    type Params = Params;
+    type Notification = Notification;
+
+    async fn subscribe(
+        state: RpcContext,
+        params: Self::Params,
+        tx: mpsc::Sender<SubscriptionMessage<Self::Notification>>,
+    ) -> Result<(), RpcError> {
+        // Replaying from genesis reports the current status of a transaction that is
+        // already part of the chain right away.
+        let params = starknet::Params {
+            transaction_hash: params.transaction_hash,
+            block: Some(BlockId::Number(BlockNumber::GENESIS)),
+        };
+        stream_transaction_status(state, params, tx, SUBSCRIPTION_NAME, true).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use axum::extract::ws::Message;
+    use pathfinder_common::macro_prelude::*;
+    use pathfinder_common::receipt::Receipt;
+    use pathfinder_common::BlockNumber;
+    use serde_json::json;
+    use starknet_gateway_types::reply::Block;
+
+    use super::*;
+    use crate::dto::DeserializeForVersion;
+    use crate::jsonrpc::{handle_json_rpc_socket, RpcResponse};
+    use crate::RpcVersion;
+
+    #[rstest::rstest]
+    #[case::positional(json!(["0x1"]))]
+    #[case::named(json!({"transaction_hash": "0x1"}))]
+    fn parsing(#[case] input: serde_json::Value) {
+        let expected = Params {
+            transaction_hash: transaction_hash!("0x1"),
+        };
+
+        let input =
+            Params::deserialize(crate::dto::Value::new(input, RpcVersion::PathfinderV01)).unwrap();
+
+        assert_eq!(input, expected);
+    }
+
+    #[tokio::test]
+    async fn accepted_on_l1_after_l1_update() {
+        let router = crate::pathfinder::register_routes().build(RpcContext::for_tests());
+        let (sender_tx, mut sender_rx) = mpsc::channel(1024);
+        let (receiver_tx, receiver_rx) = mpsc::channel(1024);
+        handle_json_rpc_socket(router.clone(), sender_tx, receiver_rx);
+        receiver_tx
+            .send(Ok(Message::Text(
+                json!({
+                    "jsonrpc": "2.0",
+                    "id": 1,
+                    "method": "pathfinder_subscribeTransactionStatus",
+                    "params": {"transaction_hash": "0x1"}
+                })
+                .to_string(),
+            )))
+            .await
+            .unwrap();
+        let response = recv(&mut sender_rx).await;
+        let subscription_id = response["result"]["subscription_id"].as_u64().unwrap();
+
+        let block = Block {
+            block_hash: block_hash!("0x5"),
+            block_number: BlockNumber::new_or_panic(5),
+            transaction_receipts: vec![(
+                Receipt {
+                    transaction_hash: transaction_hash!("0x1"),
+                    ..Default::default()
+                },
+                vec![],
+            )],
+            ..Default::default()
+        };
+        retry(|| {
+            router
+                .context
+                .notifications
+                .l2_blocks
+                .send(block.clone().into())
+        })
+        .await
+        .unwrap();
+        assert_eq!(
+            recv(&mut sender_rx).await,
+            status_message("RECEIVED", None, None, subscription_id)
+        );
+        assert_eq!(
+            recv(&mut sender_rx).await,
+            status_message(
+                "ACCEPTED_ON_L2",
+                Some("SUCCEEDED"),
+                Some(5),
+                subscription_id
+            )
+        );
+
+        let l1_updates = &router.context.notifications.l1_updates;
+        // The transaction's block is not yet accepted on L1.
+        l1_updates.send(BlockNumber::new_or_panic(4)).unwrap();
+        l1_updates.send(BlockNumber::new_or_panic(5)).unwrap();
+        assert_eq!(
+            recv(&mut sender_rx).await,
+            status_message(
+                "ACCEPTED_ON_L1",
+                Some("SUCCEEDED"),
+                Some(5),
+                subscription_id
+            )
+        );
+        assert!(sender_rx.is_empty());
+    }
+
+    #[tokio::test]
+    async fn reports_transaction_already_in_the_chain() {
+        let router = crate::pathfinder::register_routes().build(RpcContext::for_tests());
+        let (sender_tx, mut sender_rx) = mpsc::channel(1024);
+        let (receiver_tx, receiver_rx) = mpsc::channel(1024);
+        handle_json_rpc_socket(router.clone(), sender_tx, receiver_rx);
+        receiver_tx
+            .send(Ok(Message::Text(
+                json!({
+                    "jsonrpc": "2.0",
+                    "id": 1,
+                    "method": "pathfinder_subscribeTransactionStatus",
+                    "params": {"transaction_hash": transaction_hash_bytes!(b"txn 0")}
+                })
+                .to_string(),
+            )))
+            .await
+            .unwrap();
+        recv(&mut sender_rx).await;
+
+        let notification = recv(&mut sender_rx).await;
+        let result = &notification["params"]["result"];
+        assert_eq!(result["status"]["finality_status"], "ACCEPTED_ON_L2");
+        assert_eq!(result["block_number"], 0);
+        assert_eq!(result["block_hash"], json!(block_hash_bytes!(b"genesis")));
+    }
+
+    #[tokio::test]
+    async fn received_when_submitted_through_node() {
+        let context = RpcContext::for_tests();
+        context
+            .mempool
+            .insert(transaction_hash!("0x1"), contract_address!("0x2"));
+        let router = crate::pathfinder::register_routes().build(context);
+        let (sender_tx, mut sender_rx) = mpsc::channel(1024);
+        let (receiver_tx, receiver_rx) = mpsc::channel(1024);
+        handle_json_rpc_socket(router.clone(), sender_tx, receiver_rx);
+        receiver_tx
+            .send(Ok(Message::Text(
+                json!({
+                    "jsonrpc": "2.0",
+                    "id": 1,
+                    "method": "pathfinder_subscribeTransactionStatus",
+                    "params": {"transaction_hash": "0x1"}
+                })
+                .to_string(),
+            )))
+            .await
+            .unwrap();
+        let response = recv(&mut sender_rx).await;
+        let subscription_id = response["result"]["subscription_id"].as_u64().unwrap();
+
+        assert_eq!(
+            recv(&mut sender_rx).await,
+            status_message("RECEIVED", None, None, subscription_id)
+        );
+    }
+
+    fn status_message(
+        finality_status: &str,
+        execution_status: Option<&str>,
+        block_number: Option<u64>,
+        subscription_id: u64,
+    ) -> serde_json::Value {
+        let mut status = json!({ "finality_status": finality_status });
+        if let Some(execution_status) = execution_status {
+            status["execution_status"] = execution_status.into();
+        }
+        let mut result = json!({
+            "transaction_hash": "0x1",
+            "status": status,
+        });
+        if let Some(block_number) = block_number {
+            result["block_hash"] = format!("{block_number:#x}").into();
+            result["block_number"] = block_number.into();
+        }
+        json!({
+            "jsonrpc": "2.0",
+            "method": "pathfinder_subscriptionTransactionStatus",
+            "params": {
+                "result": result,
+                "subscription_id": subscription_id
+            }
+        })
+    }
+
+    async fn recv(rx: &mut mpsc::Receiver<Result<Message, RpcResponse>>) -> serde_json::Value {
+        let res = rx.recv().await.unwrap().unwrap();
+        match res {
+            Message::Text(json) => serde_json::from_str(&json).unwrap(),
+            _ => panic!("Expected text message"),
+        }
+    }
+
+    async fn retry<T, E>(cb: impl Fn() -> Result<T, E>) -> Result<T, E>
+    where
+        E: std::fmt::Debug,
+    {
+        const RETRIES: u64 = 25;
+        for i in 0..RETRIES {
+            match cb() {
+                Ok(result) => return Ok(result),
+                Err(e) => {
+                    if i == RETRIES - 1 {
+                        return Err(e);
+                    }
+                    tokio::time::sleep(Duration::from_secs(i)).await;
+                }
+            }
+        }
+        unreachable!()
+    }
+}
@@ -0,0 +1,290 @@
+use pathfinder_common::TransactionHash;
+use pathfinder_executor::types::{
+    DeclareTransactionTrace,
+    DeployAccountTransactionTrace,
+    ExecuteInvocation,
+    FunctionInvocation,
+    InvokeTransactionTrace,
+    L1HandlerTransactionTrace,
+    TransactionTrace,
+};
+use serde::Serialize;
+
+use crate::context::RpcContext;
+use crate::method::trace_transaction::{self, TraceTransactionError};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Input {
+    transaction_hash: TransactionHash,
+    format: Format,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    /// One line per call stack, as read by `flamegraph.pl` and speedscope.
+    #[default]
+    Folded,
+    /// The Chrome trace event format, as read by Perfetto and `chrome://tracing`.
+    Perfetto,
+}
+
+impl crate::dto::DeserializeForVersion for Input {
+    fn deserialize(value: crate::dto::Value) -> Result<Self, serde_json::Error> {
+        value.deserialize_map(|value| {
+            Ok(Self {
+                transaction_hash: value.deserialize_serde("transaction_hash")?,
+                format: value
+                    .deserialize_optional_serde("format")?
+                    .unwrap_or_default(),
+            })
+        })
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum Output {
+    Folded(String),
+    Perfetto(PerfettoTrace),
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PerfettoTrace {
+    trace_events: Vec<PerfettoEvent>,
+}
+
+/// A complete event, whose time axis counts Cairo steps instead of
+/// microseconds.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct PerfettoEvent {
+    name: String,
+    /// The phase of the transaction the call belongs to.
+    cat: &'static str,
+    ph: &'static str,
+    ts: u64,
+    dur: u64,
+    pid: u32,
+    tid: u32,
+    args: PerfettoArgs,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct PerfettoArgs {
+    contract_address: String,
+    selector: String,
+    class_hash: Option<String>,
+    /// Steps used by the call alone, excluding its internal calls.
+    steps: u64,
+    /// Unknown for traces fetched from the gateway.
+    l2_gas: Option<u64>,
+    total_l2_gas: Option<u64>,
+}
+
+/// Traces a transaction like `starknet_traceTransaction` and converts its call
+/// tree into a format understood by flame graph and trace visualization tools.
+///
+/// Calls are weighted by the Cairo steps they used. Calls are named after their
+/// contract address and entry point selector, and grouped under the phase of
+/// the transaction they belong to, e.g. `validate` or `execute`.
+pub async fn trace_transaction_flame(
+    context: RpcContext,
+    input: Input,
+) -> Result<Output, TraceTransactionError> {
+    let trace = trace_transaction::trace_transaction(
+        context,
+        trace_transaction::Input {
+            transaction_hash: input.transaction_hash,
+            decode: false,
+            call_resources: false,
+        },
+    )
+    .await?
+    .into_trace();
+
+    let phases = phases(&trace);
+
+    let output = match input.format {
+        Format::Folded => {
+            let mut folded = String::new();
+            for (phase, invocation) in phases {
+                fold(phase, invocation, &mut folded);
+            }
+            Output::Folded(folded)
+        }
+        Format::Perfetto => {
+            let mut trace_events = Vec::new();
+            let mut ts = 0;
+            for (phase, invocation) in phases {
+                perfetto_events(phase, invocation, ts, &mut trace_events);
+                ts += invocation.computation_resources.steps as u64;
+            }
+            Output::Perfetto(PerfettoTrace { trace_events })
+        }
+    };
+
+    Ok(output)
+}
+
+/// The top-level calls of the transaction in execution order, named after the
+/// phase of the transaction they belong to.
+fn phases(trace: &TransactionTrace) -> Vec<(&'static str, &FunctionInvocation)> {
+    let phases = match trace {
+        TransactionTrace::Declare(DeclareTransactionTrace {
+            validate_invocation,
+            fee_transfer_invocation,
+            ..
+        }) => vec![
+            ("validate", validate_invocation.as_ref()),
+            ("fee_transfer", fee_transfer_invocation.as_ref()),
+        ],
+        TransactionTrace::DeployAccount(DeployAccountTransactionTrace {
+            validate_invocation,
+            constructor_invocation,
+            fee_transfer_invocation,
+            ..
+        }) => vec![
+            ("validate", validate_invocation.as_ref()),
+            ("constructor", constructor_invocation.as_ref()),
+            ("fee_transfer", fee_transfer_invocation.as_ref()),
+        ],
+        TransactionTrace::Invoke(InvokeTransactionTrace {
+            validate_invocation,
+            execute_invocation,
+            fee_transfer_invocation,
+            ..
+        }) => {
+            // Reverted executions have no call tree.
+            let execute_invocation = match execute_invocation {
+                ExecuteInvocation::FunctionInvocation(invocation) => invocation.as_ref(),
+                ExecuteInvocation::RevertedReason(_) => None,
+            };
+            vec![
+                ("validate", validate_invocation.as_ref()),
+                ("execute", execute_invocation),
+                ("fee_transfer", fee_transfer_invocation.as_ref()),
+            ]
+        }
+        TransactionTrace::L1Handler(L1HandlerTransactionTrace {
+            function_invocation,
+            ..
+        }) => vec![("l1_handler", function_invocation.as_ref())],
+    };
+
+    phases
+        .into_iter()
+        .filter_map(|(phase, invocation)| Some((phase, invocation?)))
+        .collect()
+}
+
+fn frame_name(invocation: &FunctionInvocation) -> String {
+    format!(
+        "{}:{}",
+        invocation.contract_address.0.to_hex_str(),
+        invocation.selector.to_hex_str()
+    )
+}
+
+/// Appends a `frame;frame;... weight` line for `invocation` and each of its
+/// internal calls which used any steps itself.
+fn fold(stack: &str, invocation: &FunctionInvocation, folded: &mut String) {
+    use std::fmt::Write;
+
+    let stack = format!("{stack};{}", frame_name(invocation));
+    let steps = invocation.own_computation_resources().steps;
+    if steps > 0 {
+        writeln!(folded, "{stack} {steps}").expect("Writing to a string cannot fail");
+    }
+
+    for call in &invocation.internal_calls {
+        fold(&stack, call, folded);
+    }
+}
+
+/// Appends an event for `invocation` starting at `ts`, followed by those of its
+/// internal calls, which are laid out one after the other from the start of
+/// their caller.
+fn perfetto_events(
+    phase: &'static str,
+    invocation: &FunctionInvocation,
+    ts: u64,
+    events: &mut Vec<PerfettoEvent>,
+) {
+    events.push(PerfettoEvent {
+        name: frame_name(invocation),
+        cat: phase,
+        ph: "X",
+        ts,
+        dur: invocation.computation_resources.steps as u64,
+        pid: 1,
+        tid: 1,
+        args: PerfettoArgs {
+            contract_address: invocation.contract_address.0.to_hex_str().into_owned(),
+            selector: invocation.selector.to_hex_str().into_owned(),
+            class_hash: invocation
+                .class_hash
+                .map(|class_hash| class_hash.to_hex_str().into_owned()),
+            steps: invocation.own_computation_resources().steps as u64,
+            l2_gas: invocation.own_gas_consumed(),
+            total_l2_gas: invocation.gas_consumed,
+        },
+    });
+
+    let mut ts = ts;
+    for call in &invocation.internal_calls {
+        perfetto_events(phase, call, ts, events);
+        ts += call.computation_resources.steps as u64;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::method::trace_block_transactions::tests::setup_multi_tx_trace_test;
+
+    #[tokio::test]
+    async fn folded_steps_add_up_to_totals() {
+        let (context, _, traces) = setup_multi_tx_trace_test().await.unwrap();
+
+        for trace in traces {
+            let input = Input {
+                transaction_hash: trace.transaction_hash,
+                format: Format::Folded,
+            };
+            let Output::Folded(folded) = trace_transaction_flame(context.clone(), input.clone())
+                .await
+                .unwrap()
+            else {
+                panic!("Expected folded output");
+            };
+
+            let input = Input {
+                format: Format::Perfetto,
+                ..input
+            };
+            let Output::Perfetto(perfetto) = trace_transaction_flame(context.clone(), input)
+                .await
+                .unwrap()
+            else {
+                panic!("Expected Perfetto output");
+            };
+
+            let mut folded_steps = 0;
+            for line in folded.lines() {
+                let (stack, steps) = line.rsplit_once(' ').unwrap();
+                let (phase, _) = stack.split_once(';').unwrap();
+                assert!(["validate", "execute", "fee_transfer"].contains(&phase));
+                folded_steps += steps.parse::<u64>().unwrap();
+            }
+            assert!(folded_steps > 0);
+
+            let own_steps: u64 = perfetto
+                .trace_events
+                .iter()
+                .map(|event| event.args.steps)
+                .sum();
+            assert_eq!(own_steps, folded_steps);
+        }
+    }
+}
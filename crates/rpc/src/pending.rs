@@ -1,15 +1,24 @@
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 use anyhow::Context;
-use pathfinder_common::{BlockHeader, BlockNumber, StateUpdate};
+use pathfinder_common::{BlockHash, BlockHeader, BlockNumber, StateUpdate};
 use pathfinder_storage::Transaction;
 use starknet_gateway_types::reply::{GasPrices, PendingBlock, Status};
 use tokio::sync::watch::Receiver as WatchReceiver;
 
 /// Provides the latest [PendingData] which is consistent with a given
 /// view of storage.
+///
+/// Clones share the same underlying channel and cache, so the handle can be
+/// passed around freely (e.g. as part of the RPC context).
 #[derive(Clone)]
-pub struct PendingWatcher(pub WatchReceiver<PendingData>);
+pub struct PendingWatcher {
+    receiver: WatchReceiver<PendingData>,
+    /// Placeholder [PendingData] built on top of the latest block, used while
+    /// no valid pending data is available. Rebuilt once the latest block
+    /// changes.
+    fallback: Arc<Mutex<Option<PendingData>>>,
+}
 
 #[derive(Clone, Default, Debug, PartialEq)]
 pub struct PendingData {
@@ -30,6 +39,8 @@ impl PendingData {
             strk_l1_gas_price: self.block.l1_gas_price.price_in_fri,
             eth_l1_data_gas_price: self.block.l1_data_gas_price.price_in_wei,
             strk_l1_data_gas_price: self.block.l1_data_gas_price.price_in_fri,
+            eth_l2_gas_price: self.block.l2_gas_price.unwrap_or_default().price_in_wei,
+            strk_l2_gas_price: self.block.l2_gas_price.unwrap_or_default().price_in_fri,
             sequencer_address: self.block.sequencer_address,
             starknet_version: self.block.starknet_version,
             // Pending block does not know what these are yet.
@@ -51,7 +62,15 @@ impl PendingData {
 
 impl PendingWatcher {
     pub fn new(receiver: WatchReceiver<PendingData>) -> Self {
-        Self(receiver)
+        Self {
+            receiver,
+            fallback: Default::default(),
+        }
+    }
+
+    /// A new receiver for the raw, unvalidated pending data updates.
+    pub fn receiver(&self) -> WatchReceiver<PendingData> {
+        self.receiver.clone()
     }
 
     /// Returns [PendingData] which has been validated against the latest block
@@ -61,48 +80,76 @@ impl PendingWatcher {
     /// latest block if no valid pending data is available. The block number
     /// is also incremented.
     pub fn get(&self, tx: &Transaction<'_>) -> anyhow::Result<PendingData> {
+        let latest_hash = tx
+            .block_hash(pathfinder_storage::BlockId::Latest)
+            .context("Querying latest block hash")?
+            .unwrap_or_default();
+
+        // The pending data is cloned exactly once so that callers only ever see
+        // a single version, even if a new one arrives while they hold it.
+        let data = self.receiver.borrow().clone();
+        if data.block.parent_hash == latest_hash {
+            return Ok(data);
+        }
+
+        let mut fallback = self.fallback.lock().unwrap();
+        if let Some(data) = fallback
+            .as_ref()
+            .filter(|data| data.block.parent_hash == latest_hash)
+        {
+            return Ok(data.clone());
+        }
+
         let latest = tx
             .block_header(pathfinder_storage::BlockId::Latest)
             .context("Querying latest block header")?
             .unwrap_or_default();
 
-        let data = self.0.borrow().clone();
-        if data.block.parent_hash == latest.hash {
-            Ok(data)
-        } else {
-            let data = PendingData {
-                block: PendingBlock {
-                    l1_gas_price: GasPrices {
-                        price_in_wei: latest.eth_l1_gas_price,
-                        price_in_fri: latest.strk_l1_gas_price,
-                    },
-                    l1_data_gas_price: GasPrices {
-                        price_in_wei: latest.eth_l1_data_gas_price,
-                        price_in_fri: latest.strk_l1_data_gas_price,
-                    },
-                    timestamp: latest.timestamp,
-                    parent_hash: latest.hash,
-                    starknet_version: latest.starknet_version,
-                    l1_da_mode: latest.l1_da_mode.into(),
-                    // This shouldn't have an impact anywhere as the RPC methods should
-                    // know this is a pending block. But rather safe than sorry.
-                    status: Status::Pending,
-                    sequencer_address: latest.sequencer_address,
-                    transaction_receipts: vec![],
-                    transactions: vec![],
-                }
-                .into(),
-                state_update: Default::default(),
-                number: latest.number + 1,
-            };
+        let data = PendingData {
+            block: PendingBlock {
+                l1_gas_price: GasPrices {
+                    price_in_wei: latest.eth_l1_gas_price,
+                    price_in_fri: latest.strk_l1_gas_price,
+                },
+                l1_data_gas_price: GasPrices {
+                    price_in_wei: latest.eth_l1_data_gas_price,
+                    price_in_fri: latest.strk_l1_data_gas_price,
+                },
+                timestamp: latest.timestamp,
+                parent_hash: latest.hash,
+                starknet_version: latest.starknet_version,
+                l1_da_mode: latest.l1_da_mode.into(),
+                // This shouldn't have an impact anywhere as the RPC methods should
+                // know this is a pending block. But rather safe than sorry.
+                status: Status::Pending,
+                sequencer_address: latest.sequencer_address,
+                transaction_receipts: vec![],
+                transactions: vec![],
+                l2_gas_price: Some(GasPrices {
+                    price_in_wei: latest.eth_l2_gas_price,
+                    price_in_fri: latest.strk_l2_gas_price,
+                }),
+            }
+            .into(),
+            state_update: Default::default(),
+            number: latest.number + 1,
+        };
+        *fallback = Some(data.clone());
 
-            Ok(data)
-        }
+        Ok(data)
+    }
+
+    /// Returns the raw pending block if it builds on top of the block with
+    /// `latest_hash`, i.e. if [get](Self::get) would not fall back to an empty
+    /// block.
+    pub fn block_on_top_of(&self, latest_hash: BlockHash) -> Option<Arc<PendingBlock>> {
+        let data = self.receiver.borrow();
+        (data.block.parent_hash == latest_hash).then(|| data.block.clone())
     }
 
     #[cfg(test)]
     pub fn get_unchecked(&self) -> PendingData {
-        self.0.borrow().clone()
+        self.receiver.borrow().clone()
     }
 }
 
@@ -219,4 +266,48 @@ mod tests {
 
         pretty_assertions_sorted::assert_eq_sorted!(result, expected);
     }
+
+    #[test]
+    fn fallback_is_reused_until_pending_or_latest_changes() {
+        let (sender, receiver) = tokio::sync::watch::channel(Default::default());
+        let uut = PendingWatcher::new(receiver);
+
+        let mut storage = pathfinder_storage::StorageBuilder::in_memory()
+            .unwrap()
+            .connection()
+            .unwrap();
+
+        let latest = BlockHeader::builder().finalize_with_hash(block_hash_bytes!(b"latest hash"));
+        let tx = storage.transaction().unwrap();
+        tx.insert_block_header(&latest).unwrap();
+
+        let first = uut.get(&tx).unwrap();
+        // Clones share the same cached fallback.
+        let second = uut.clone().get(&tx).unwrap();
+        assert!(Arc::ptr_eq(&first.block, &second.block));
+
+        // Valid pending data takes precedence over the cached fallback.
+        let pending = PendingData {
+            block: PendingBlock {
+                parent_hash: latest.hash,
+                ..Default::default()
+            }
+            .into(),
+            number: latest.number + 1,
+            ..Default::default()
+        };
+        sender.send(pending.clone()).unwrap();
+        assert_eq!(uut.get(&tx).unwrap(), pending);
+
+        // A new latest block invalidates both the pending data and the fallback.
+        let next = latest
+            .child_builder()
+            .finalize_with_hash(block_hash_bytes!(b"next hash"));
+        tx.insert_block_header(&next).unwrap();
+
+        let result = uut.get(&tx).unwrap();
+        assert_eq!(result.block.parent_hash, next.hash);
+        assert_eq!(result.number, next.number + 1);
+        assert!(!Arc::ptr_eq(&result.block, &first.block));
+    }
 }
@@ -0,0 +1,689 @@
+//! Recording of RPC method calls, and their replay against a database.
+//!
+//! Every call answered by a context with a [Recorder] is appended as a
+//! [RecordedCall] to a JSON lines file, along with the latest block at the
+//! time. [replay] runs the recorded calls again and reports the differences
+//! between the recorded and the new responses, e.g. to reproduce a reported
+//! discrepancy with another version of pathfinder.
+//!
+//! Calls are written to files of at most [MAX_FILE_SIZE] bytes. If the disk
+//! can't keep up, calls are dropped rather than delaying their responses.
+//!
+//! To keep the replay deterministic, `latest` block ids, whether explicit or
+//! implied by a missing parameter, are replaced by the block which was the
+//! latest when the call was recorded. Calls which depend on the pending block
+//! or on the node's state rather than its blocks, and calls which change the
+//! node's state, are skipped.
+
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use anyhow::Context;
+use pathfinder_common::{BlockHash, BlockHeader, BlockNumber};
+use serde_json::Value;
+use tokio::sync::broadcast;
+
+use crate::context::RpcContext;
+use crate::dto::serialize::{SerializeForVersion, Serializer};
+use crate::jsonrpc::{Reorg, RpcResponse, RpcRouter};
+use crate::middleware::method_filter::MethodSet;
+use crate::middleware::rate_limit::MethodGroup;
+
+/// The size after which calls are written to a new file.
+pub const MAX_FILE_SIZE: u64 = 256 * 1024 * 1024;
+
+/// The number of calls waiting to be written, beyond which further calls are
+/// dropped.
+const MAX_QUEUED_CALLS: usize = 10_000;
+
+/// Methods whose responses depend on the node's state rather than on its
+/// blocks, so replaying them reports meaningless differences.
+const NODE_STATE_METHODS: &[&str] = &[
+    "starknet_blockNumber",
+    "starknet_blockHashAndNumber",
+    "starknet_syncing",
+    "starknet_getTransactionStatus",
+    "pathfinder_version",
+    "pathfinder_getTransactionStatus",
+    "pathfinder_getFeeHistory",
+    "pathfinder_getSyncLag",
+    "pathfinder_getMigrationStatus",
+    "pathfinder_getBackupStatus",
+    "pathfinder_health",
+];
+
+/// A method call and its response.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct RecordedCall {
+    /// The API version the call was made to, e.g. `v0.7`.
+    pub version: String,
+    pub method: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub params: Option<Value>,
+    /// The latest block just before the call was answered, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latest_block: Option<(BlockNumber, BlockHash)>,
+    /// The `result` or `error` of the response.
+    pub response: Value,
+}
+
+/// Appends recorded calls to files in a directory, written to by a background
+/// thread so that calls don't wait for the disk.
+pub struct Recorder {
+    sender: std::sync::mpsc::SyncSender<String>,
+    /// Calls dropped since the writer last caught up.
+    dropped: Arc<AtomicU64>,
+    latest: Mutex<LatestBlock>,
+}
+
+/// The latest block, followed using the block notifications so that recording
+/// a call doesn't need a database query.
+struct LatestBlock {
+    block: Option<(BlockNumber, BlockHash)>,
+    /// Set if `block` has to be read from the database, i.e. initially and
+    /// after a reorg.
+    stale: bool,
+    notifications: Option<(
+        broadcast::Receiver<Arc<BlockHeader>>,
+        broadcast::Receiver<Arc<Reorg>>,
+    )>,
+}
+
+impl Recorder {
+    /// Records calls to new files in `directory`, which is created if
+    /// missing.
+    pub fn create(directory: &Path) -> anyhow::Result<Self> {
+        Self::with_max_file_size(directory, MAX_FILE_SIZE)
+    }
+
+    fn with_max_file_size(directory: &Path, max_file_size: u64) -> anyhow::Result<Self> {
+        std::fs::create_dir_all(directory)
+            .with_context(|| format!("Creating {}", directory.display()))?;
+
+        let started = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default();
+        // Zero padded so that the files sort in the order they were written.
+        let prefix = format!("calls-{}-{}", started.as_millis(), std::process::id());
+        let directory = directory.to_owned();
+        let path = move |index: usize| directory.join(format!("{prefix}-{index:06}.jsonl"));
+        let create = |path: PathBuf| {
+            std::fs::File::create(&path)
+                .map(std::io::BufWriter::new)
+                .with_context(|| format!("Creating {}", path.display()))
+        };
+
+        let mut file = create(path(0))?;
+        let (sender, receiver) = std::sync::mpsc::sync_channel::<String>(MAX_QUEUED_CALLS);
+        let dropped = Arc::new(AtomicU64::new(0));
+        let dropped_by_writer = dropped.clone();
+        std::thread::Builder::new()
+            .name("rpc-recorder".to_owned())
+            .spawn(move || {
+                let mut index = 0;
+                let mut size = 0;
+                // Flushed once the backlog has been written, so that the file is
+                // complete whenever the node is idle.
+                while let Ok(line) = receiver.recv() {
+                    let written = std::iter::once(line)
+                        .chain(receiver.try_iter())
+                        .try_for_each(|line| -> anyhow::Result<()> {
+                            if size > 0 && size + line.len() as u64 > max_file_size {
+                                file.flush()?;
+                                index += 1;
+                                size = 0;
+                                file = create(path(index))?;
+                            }
+                            writeln!(file, "{line}")?;
+                            size += line.len() as u64 + 1;
+                            Ok(())
+                        })
+                        .and_then(|_| Ok(file.flush()?));
+                    if let Err(error) = written {
+                        tracing::warn!(%error, "Failed to record RPC calls, stopping");
+                        return;
+                    }
+
+                    let dropped = dropped_by_writer.swap(0, Ordering::Relaxed);
+                    if dropped > 0 {
+                        tracing::warn!(%dropped, "Dropped RPC calls, recording can't keep up");
+                    }
+                }
+            })
+            .context("Spawning recorder thread")?;
+
+        Ok(Self {
+            sender,
+            dropped,
+            latest: Mutex::new(LatestBlock {
+                block: None,
+                stale: true,
+                notifications: None,
+            }),
+        })
+    }
+
+    pub(crate) fn record(&self, call: &RecordedCall) {
+        let line = match serde_json::to_string(call) {
+            Ok(line) => line,
+            Err(error) => {
+                tracing::warn!(%error, "Failed to serialize RPC call");
+                return;
+            }
+        };
+
+        match self.sender.try_send(line) {
+            Ok(()) => {}
+            Err(std::sync::mpsc::TrySendError::Full(_)) => {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+            }
+            // The writer has stopped, which it has already logged.
+            Err(std::sync::mpsc::TrySendError::Disconnected(_)) => {}
+        }
+    }
+
+    /// The latest block, for recording calls.
+    ///
+    /// Only read from the database initially and after reorgs, otherwise
+    /// followed using the new block notifications.
+    pub(crate) async fn latest_block(
+        &self,
+        context: &RpcContext,
+    ) -> Option<(BlockNumber, BlockHash)> {
+        use broadcast::error::TryRecvError;
+
+        {
+            let mut latest = self.latest.lock().unwrap();
+            let LatestBlock {
+                block,
+                stale,
+                notifications,
+            } = &mut *latest;
+            let (headers, reorgs) = notifications.get_or_insert_with(|| {
+                (
+                    context.notifications.block_headers.subscribe(),
+                    context.notifications.reorgs.subscribe(),
+                )
+            });
+
+            loop {
+                match headers.try_recv() {
+                    Ok(header) => *block = Some((header.number, header.hash)),
+                    Err(TryRecvError::Lagged(_)) => *stale = true,
+                    Err(TryRecvError::Empty | TryRecvError::Closed) => break,
+                }
+            }
+            loop {
+                match reorgs.try_recv() {
+                    Ok(_) | Err(TryRecvError::Lagged(_)) => *stale = true,
+                    Err(TryRecvError::Empty | TryRecvError::Closed) => break,
+                }
+            }
+
+            if !*stale {
+                return *block;
+            }
+        }
+
+        let storage = context.storage.clone();
+        let latest = tokio::task::spawn_blocking(move || -> anyhow::Result<_> {
+            let mut db = storage.connection()?;
+            let tx = db.transaction()?;
+            tx.block_id(pathfinder_storage::BlockId::Latest)
+        })
+        .await;
+
+        match latest {
+            Ok(Ok(block)) => {
+                let mut latest = self.latest.lock().unwrap();
+                latest.block = block;
+                latest.stale = false;
+                block
+            }
+            Ok(Err(error)) => {
+                tracing::debug!(%error, "Failed to fetch latest block for recording");
+                None
+            }
+            Err(_) => None,
+        }
+    }
+}
+
+/// A response without its `jsonrpc` and `id` members, which differ between
+/// the recorded and replayed calls.
+pub(crate) fn response_body(response: &RpcResponse) -> Value {
+    let mut body = response
+        .serialize(Serializer::new(response.version))
+        .unwrap_or_default();
+    if let Value::Object(members) = &mut body {
+        members.remove("jsonrpc");
+        members.remove("id");
+    }
+    body
+}
+
+/// The outcome of replaying a single call.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReplayOutcome {
+    Matched,
+    /// The differences between the recorded and replayed responses, as
+    /// `<path>: <recorded> != <replayed>`.
+    Mismatched(Vec<String>),
+    Skipped(&'static str),
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReplayReport {
+    pub matched: usize,
+    pub mismatched: usize,
+    pub skipped: usize,
+}
+
+/// Replays the calls recorded in the files of `directory`, in the order of
+/// their file names, calling `on_call` with the outcome of each.
+pub async fn replay(
+    context: RpcContext,
+    directory: &Path,
+    mut on_call: impl FnMut(&RecordedCall, &ReplayOutcome),
+) -> anyhow::Result<ReplayReport> {
+    let routers = [
+        crate::v06::register_routes().build(context.clone()),
+        crate::v07::register_routes().build(context.clone()),
+        crate::v08::register_routes().build(context.clone()),
+        crate::pathfinder::register_routes().build(context),
+    ];
+
+    let mut files = std::fs::read_dir(directory)
+        .with_context(|| format!("Reading {}", directory.display()))?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("Reading {}", directory.display()))?;
+    files.retain(|path| {
+        path.extension()
+            .is_some_and(|extension| extension == "jsonl")
+    });
+    files.sort();
+
+    let mut report = ReplayReport::default();
+    for path in files {
+        let file =
+            std::fs::File::open(&path).with_context(|| format!("Opening {}", path.display()))?;
+        for (line_number, line) in std::io::BufReader::new(file).lines().enumerate() {
+            let line = line.with_context(|| format!("Reading {}", path.display()))?;
+            let call: RecordedCall = serde_json::from_str(&line).with_context(|| {
+                format!("Parsing line {} of {}", line_number + 1, path.display())
+            })?;
+
+            let router = routers
+                .iter()
+                .find(|router| router.version.to_str() == call.version);
+            let outcome = match router {
+                Some(router) => replay_call(router, &call).await,
+                None => ReplayOutcome::Skipped("unknown API version"),
+            };
+
+            match outcome {
+                ReplayOutcome::Matched => report.matched += 1,
+                ReplayOutcome::Mismatched(_) => report.mismatched += 1,
+                ReplayOutcome::Skipped(_) => report.skipped += 1,
+            }
+            on_call(&call, &outcome);
+        }
+    }
+
+    Ok(report)
+}
+
+async fn replay_call(router: &RpcRouter, call: &RecordedCall) -> ReplayOutcome {
+    if let Some(reason) = skip_reason(&call.method) {
+        return ReplayOutcome::Skipped(reason);
+    }
+    if call.params.as_ref().is_some_and(mentions_pending) {
+        return ReplayOutcome::Skipped("depends on the pending block");
+    }
+
+    let mut params = call.params.clone();
+    if let (Some(params), Some((latest, _))) = (&mut params, call.latest_block) {
+        pin_implicit_latest(&call.method, params);
+        pin_latest(params, latest);
+    }
+
+    let mut request = serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": 0,
+        "method": call.method,
+    });
+    if let Some(params) = params {
+        request["params"] = params;
+    }
+
+    let Some(response) = router.run_request(&request.to_string()).await else {
+        return ReplayOutcome::Skipped("no response");
+    };
+
+    let mut differences = Vec::new();
+    diff(
+        &mut String::new(),
+        &call.response,
+        &response_body(&response),
+        &mut differences,
+    );
+    if differences.is_empty() {
+        ReplayOutcome::Matched
+    } else {
+        ReplayOutcome::Mismatched(differences)
+    }
+}
+
+/// Why calls of `method` aren't replayed, if they aren't.
+fn skip_reason(method: &str) -> Option<&'static str> {
+    if MethodGroup::of(method) == MethodGroup::Write {
+        Some("changes the node's state")
+    } else if method.contains("subscribe") {
+        Some("is a subscription")
+    } else if MethodSet::Admin.contains(method) || NODE_STATE_METHODS.contains(&method) {
+        Some("depends on the node's state")
+    } else {
+        None
+    }
+}
+
+fn mentions_pending(value: &Value) -> bool {
+    match value {
+        Value::String(s) => s == "pending",
+        Value::Array(values) => values.iter().any(mentions_pending),
+        Value::Object(values) => values.values().any(mentions_pending),
+        _ => false,
+    }
+}
+
+/// Replaces `latest` block ids in `params` by `latest`.
+fn pin_latest(params: &mut Value, latest: BlockNumber) {
+    match params {
+        Value::String(s) if s == "latest" => {
+            *params = serde_json::json!({ "block_number": latest.get() });
+        }
+        Value::Array(values) => values.iter_mut().for_each(|v| pin_latest(v, latest)),
+        Value::Object(values) => values.values_mut().for_each(|v| pin_latest(v, latest)),
+        _ => {}
+    }
+}
+
+/// Adds the `latest` block ids implied by missing optional parameters, so that
+/// [pin_latest] can replace them.
+fn pin_implicit_latest(method: &str, params: &mut Value) {
+    let (params, key) = match method {
+        "starknet_getEvents" => match params {
+            Value::Object(params) => (params.get_mut("filter"), "to_block"),
+            Value::Array(params) => (params.first_mut(), "to_block"),
+            _ => return,
+        },
+        "pathfinder_getClassInfo" => match params {
+            Value::Array(params) if params.len() == 1 => {
+                params.push(Value::from("latest"));
+                return;
+            }
+            params => (Some(params), "block_id"),
+        },
+        _ => return,
+    };
+
+    if let Some(Value::Object(params)) = params {
+        let block_id = params.entry(key).or_insert(Value::Null);
+        if block_id.is_null() {
+            *block_id = Value::from("latest");
+        }
+    }
+}
+
+/// Appends the paths at which `recorded` and `replayed` differ to
+/// `differences`.
+fn diff(path: &mut String, recorded: &Value, replayed: &Value, differences: &mut Vec<String>) {
+    let len = path.len();
+    match (recorded, replayed) {
+        (Value::Object(recorded), Value::Object(replayed)) => {
+            let keys = recorded
+                .keys()
+                .chain(replayed.keys().filter(|key| !recorded.contains_key(*key)));
+            for key in keys {
+                path.push('/');
+                path.push_str(key);
+                diff(
+                    path,
+                    recorded.get(key).unwrap_or(&Value::Null),
+                    replayed.get(key).unwrap_or(&Value::Null),
+                    differences,
+                );
+                path.truncate(len);
+            }
+        }
+        (Value::Array(recorded), Value::Array(replayed)) if recorded.len() == replayed.len() => {
+            for (i, (recorded, replayed)) in recorded.iter().zip(replayed).enumerate() {
+                path.push_str(&format!("/{i}"));
+                diff(path, recorded, replayed, differences);
+                path.truncate(len);
+            }
+        }
+        (recorded, replayed) if recorded != replayed => {
+            let path = if path.is_empty() { "/" } else { path.as_str() };
+            differences.push(format!("{path}: {recorded} != {replayed}"));
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn pin_latest_block_ids() {
+        let mut params = json!({
+            "block_id": "latest",
+            "request": {"contract_address": "0x1"},
+            "other": ["latest", {"block_hash": "0x2"}],
+        });
+        pin_latest(&mut params, BlockNumber::new_or_panic(7));
+
+        assert_eq!(
+            params,
+            json!({
+                "block_id": {"block_number": 7},
+                "request": {"contract_address": "0x1"},
+                "other": [{"block_number": 7}, {"block_hash": "0x2"}],
+            })
+        );
+        assert!(mentions_pending(&json!([{"block_id": "pending"}])));
+        assert!(!mentions_pending(&params));
+    }
+
+    #[test]
+    fn implicit_latest_block_ids() {
+        let mut params = json!({"filter": {"from_block": {"block_number": 1}}});
+        pin_implicit_latest("starknet_getEvents", &mut params);
+        assert_eq!(
+            params,
+            json!({"filter": {"from_block": {"block_number": 1}, "to_block": "latest"}})
+        );
+
+        let mut params = json!([{"to_block": {"block_hash": "0x2"}}]);
+        pin_implicit_latest("starknet_getEvents", &mut params);
+        assert_eq!(params, json!([{"to_block": {"block_hash": "0x2"}}]));
+
+        let mut params = json!(["0x1"]);
+        pin_implicit_latest("pathfinder_getClassInfo", &mut params);
+        assert_eq!(params, json!(["0x1", "latest"]));
+
+        let mut params = json!({"class_hash": "0x1", "block_id": null});
+        pin_implicit_latest("pathfinder_getClassInfo", &mut params);
+        assert_eq!(params, json!({"class_hash": "0x1", "block_id": "latest"}));
+    }
+
+    #[test]
+    fn skipped_methods() {
+        assert!(skip_reason("starknet_addInvokeTransaction").is_some());
+        assert!(skip_reason("pathfinder_registerAbi").is_some());
+        assert!(skip_reason("pathfinder_getSubmittedTransactions").is_some());
+        assert!(skip_reason("starknet_subscribeNewHeads").is_some());
+        assert!(skip_reason("starknet_blockNumber").is_some());
+        assert!(skip_reason("starknet_getStorageAt").is_none());
+        assert!(skip_reason("starknet_call").is_none());
+    }
+
+    #[test]
+    fn differences() {
+        let recorded = json!({"a": 1, "b": [1, 2], "c": {"d": "x"}});
+        let replayed = json!({"a": 1, "b": [1, 3], "c": {"d": "y"}, "e": true});
+
+        let mut differences = Vec::new();
+        diff(&mut String::new(), &recorded, &replayed, &mut differences);
+
+        assert_eq!(
+            differences,
+            vec![
+                "/b/1: 2 != 3".to_owned(),
+                r#"/c/d: "x" != "y""#.to_owned(),
+                "/e: null != true".to_owned(),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn record_and_replay() {
+        let directory = tempfile::tempdir().unwrap();
+        let context =
+            RpcContext::for_tests().with_recorder(Recorder::create(directory.path()).unwrap());
+        let router = crate::v07::register_routes().build(context.clone());
+
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 0,
+            "method": "starknet_getStorageAt",
+            "params": {
+                "contract_address": "0x1",
+                "key": "0x1",
+                "block_id": "latest",
+            },
+        });
+        router.run_request(&request.to_string()).await.unwrap();
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "starknet_getStateUpdate",
+            "params": ["pending"],
+        });
+        router.run_request(&request.to_string()).await.unwrap();
+        // Give the recorder thread time to write the calls.
+        drop(router);
+        drop(context);
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+        let mut outcomes = Vec::new();
+        let report = replay(
+            RpcContext::for_tests(),
+            directory.path(),
+            |call, outcome| outcomes.push((call.clone(), outcome.clone())),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            report,
+            ReplayReport {
+                matched: 1,
+                mismatched: 0,
+                skipped: 1,
+            }
+        );
+        let (call, outcome) = &outcomes[0];
+        assert_eq!(call.method, "starknet_getStorageAt");
+        assert_eq!(
+            call.latest_block.map(|(number, _)| number),
+            Some(BlockNumber::new_or_panic(2))
+        );
+        assert_eq!(outcome, &ReplayOutcome::Matched);
+        assert_eq!(
+            outcomes[1].1,
+            ReplayOutcome::Skipped("depends on the pending block")
+        );
+    }
+
+    #[tokio::test]
+    async fn files_are_rotated() {
+        let directory = tempfile::tempdir().unwrap();
+        let recorder = Recorder::with_max_file_size(directory.path(), 1).unwrap();
+        for method in ["a", "b", "c"] {
+            recorder.record(&RecordedCall {
+                version: "v0.7".to_owned(),
+                method: method.to_owned(),
+                params: None,
+                latest_block: None,
+                response: json!({"result": 0}),
+            });
+        }
+        drop(recorder);
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+        let mut files = std::fs::read_dir(directory.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect::<Vec<_>>();
+        files.sort();
+        let methods = files
+            .iter()
+            .map(|path| {
+                let line = std::fs::read_to_string(path).unwrap();
+                serde_json::from_str::<RecordedCall>(&line).unwrap().method
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(methods, vec!["a", "b", "c"]);
+    }
+
+    #[tokio::test]
+    async fn latest_block_follows_notifications() {
+        let directory = tempfile::tempdir().unwrap();
+        let recorder = Recorder::create(directory.path()).unwrap();
+        let context = RpcContext::for_tests();
+
+        let (latest, _) = recorder.latest_block(&context).await.unwrap();
+        assert_eq!(latest, BlockNumber::new_or_panic(2));
+
+        // Not in the database, so only known from the notification.
+        let header = BlockHeader::builder()
+            .number(BlockNumber::new_or_panic(3))
+            .finalize_with_hash(BlockHash(pathfinder_crypto::Felt::from_u64(3)));
+        context
+            .notifications
+            .block_headers
+            .send(header.into())
+            .unwrap();
+        assert_eq!(
+            recorder.latest_block(&context).await,
+            Some((
+                BlockNumber::new_or_panic(3),
+                BlockHash(pathfinder_crypto::Felt::from_u64(3))
+            ))
+        );
+
+        // A reorg makes it read the database again.
+        context
+            .notifications
+            .reorgs
+            .send(
+                Reorg {
+                    first_block_number: BlockNumber::new_or_panic(3),
+                    first_block_hash: BlockHash(pathfinder_crypto::Felt::from_u64(3)),
+                    last_block_number: BlockNumber::new_or_panic(3),
+                    last_block_hash: BlockHash(pathfinder_crypto::Felt::from_u64(3)),
+                }
+                .into(),
+            )
+            .unwrap();
+        let (latest, _) = recorder.latest_block(&context).await.unwrap();
+        assert_eq!(latest, BlockNumber::new_or_panic(2));
+    }
+}
@@ -0,0 +1,139 @@
+//! Coordinates a graceful shutdown of the RPC server.
+//!
+//! Every HTTP request holds a [RequestGuard] for as long as it is being
+//! processed, including any blocking database work it waits on. Once shutdown
+//! has been initiated no new guards are handed out, and
+//! [ShutdownCoordinator::shutdown] waits for the outstanding ones to be
+//! released so that in-flight requests still receive their responses.
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::Notify;
+
+#[derive(Clone, Default)]
+pub struct ShutdownCoordinator(Arc<Inner>);
+
+#[derive(Default)]
+struct Inner {
+    shutting_down: AtomicBool,
+    in_flight: AtomicUsize,
+    /// Notified once shutdown is initiated.
+    shutdown_started: Notify,
+    /// Notified whenever the last in-flight request completes.
+    idle: Notify,
+}
+
+/// Marks a request as in-flight until dropped.
+pub struct RequestGuard(Arc<Inner>);
+
+impl Drop for RequestGuard {
+    fn drop(&mut self) {
+        if self.0.in_flight.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.0.idle.notify_waiters();
+        }
+    }
+}
+
+/// Returned by [ShutdownCoordinator::shutdown] if in-flight requests did not
+/// complete within the timeout.
+#[derive(Debug, thiserror::Error)]
+#[error("Timed out after {timeout:?} waiting for {in_flight} in-flight RPC requests to complete")]
+pub struct ShutdownTimeout {
+    pub timeout: Duration,
+    pub in_flight: usize,
+}
+
+impl ShutdownCoordinator {
+    /// Registers a new in-flight request, or returns `None` if shutdown has
+    /// already been initiated and the request should be rejected.
+    pub fn begin_request(&self) -> Option<RequestGuard> {
+        // Increment first so that a concurrent shutdown cannot miss this request.
+        self.0.in_flight.fetch_add(1, Ordering::AcqRel);
+        let guard = RequestGuard(self.0.clone());
+
+        if self.is_shutting_down() {
+            return None;
+        }
+
+        Some(guard)
+    }
+
+    pub fn is_shutting_down(&self) -> bool {
+        self.0.shutting_down.load(Ordering::Acquire)
+    }
+
+    /// Resolves once shutdown has been initiated. Used to stop the HTTP server
+    /// from accepting new connections.
+    pub async fn wait_for_shutdown(&self) {
+        let notified = self.0.shutdown_started.notified();
+        if self.is_shutting_down() {
+            return;
+        }
+        notified.await;
+    }
+
+    /// Stops accepting new requests and waits for in-flight requests to
+    /// complete, for at most `timeout`.
+    pub async fn shutdown(&self, timeout: Duration) -> Result<(), ShutdownTimeout> {
+        self.0.shutting_down.store(true, Ordering::Release);
+        self.0.shutdown_started.notify_waiters();
+
+        let drained = async {
+            loop {
+                let idle = self.0.idle.notified();
+                if self.0.in_flight.load(Ordering::Acquire) == 0 {
+                    return;
+                }
+                idle.await;
+            }
+        };
+
+        tokio::time::timeout(timeout, drained)
+            .await
+            .map_err(|_| ShutdownTimeout {
+                timeout,
+                in_flight: self.0.in_flight.load(Ordering::Acquire),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn waits_for_in_flight_requests() {
+        let uut = ShutdownCoordinator::default();
+        let guard = uut.begin_request().unwrap();
+
+        let release = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            drop(guard);
+        });
+
+        uut.shutdown(Duration::from_secs(5)).await.unwrap();
+        release.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn rejects_new_requests_once_shutting_down() {
+        let uut = ShutdownCoordinator::default();
+
+        uut.shutdown(Duration::from_secs(1)).await.unwrap();
+
+        assert!(uut.begin_request().is_none());
+        // The rejected request must not count as in-flight.
+        uut.shutdown(Duration::ZERO).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn times_out() {
+        let uut = ShutdownCoordinator::default();
+        let _guard = uut.begin_request().unwrap();
+
+        let error = uut.shutdown(Duration::from_millis(10)).await.unwrap_err();
+        assert_eq!(error.in_flight, 1);
+    }
+}
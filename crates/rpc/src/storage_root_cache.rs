@@ -0,0 +1,124 @@
+use std::sync::{Mutex, MutexGuard};
+
+use anyhow::Context;
+use cached::{Cached, SizedCache};
+use pathfinder_common::{BlockHash, BlockNumber, ContractAddress};
+use pathfinder_storage::Transaction;
+
+/// The default number of (contract, block) entries kept in the
+/// [StorageRootCache].
+pub const DEFAULT_SIZE: usize = 16_384;
+
+type CacheKey = (ContractAddress, BlockHash);
+
+/// Caches the index of a contract's storage trie root at a block.
+///
+/// Entries are keyed by block hash rather than number, so that the entries of
+/// reverted blocks are never returned for the blocks replacing them. A cache
+/// miss simply falls back to querying the database.
+pub struct StorageRootCache(Mutex<SizedCache<CacheKey, Option<u64>>>);
+
+impl StorageRootCache {
+    pub fn with_size(size: usize) -> Self {
+        Self(Mutex::new(SizedCache::with_size(size)))
+    }
+
+    fn locked_cache(&self) -> MutexGuard<'_, SizedCache<CacheKey, Option<u64>>> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Returns the index of the contract's storage trie root at `block`, or
+    /// `None` if the contract has no storage at that block. `block_hash` must
+    /// be the hash of `block`.
+    ///
+    /// Equivalent to [Transaction::contract_root_index].
+    pub fn contract_root_index(
+        &self,
+        tx: &Transaction<'_>,
+        block: BlockNumber,
+        block_hash: BlockHash,
+        contract: ContractAddress,
+    ) -> anyhow::Result<Option<u64>> {
+        let key = (contract, block_hash);
+
+        if let Some(root) = self.locked_cache().cache_get(&key) {
+            return Ok(*root);
+        }
+
+        let root = tx
+            .contract_root_index(block, contract)
+            .context("Querying contract root index")?;
+        self.locked_cache().cache_set(key, root);
+
+        Ok(root)
+    }
+}
+
+impl Default for StorageRootCache {
+    fn default() -> Self {
+        Self::with_size(DEFAULT_SIZE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::macro_prelude::*;
+    use pathfinder_storage::RootIndexUpdate;
+
+    use super::*;
+    use crate::context::RpcContext;
+
+    #[test]
+    fn matches_uncached_lookup() {
+        let context = RpcContext::for_tests();
+        let mut db = context.storage.connection().unwrap();
+        let tx = db.transaction().unwrap();
+        let uut = StorageRootCache::with_size(10);
+
+        for contract in [
+            contract_address_bytes!(b"contract 1"),
+            contract_address_bytes!(b"contract 2 (sierra)"),
+            contract_address_bytes!(b"unknown contract"),
+        ] {
+            for block in [BlockNumber::GENESIS, BlockNumber::GENESIS + 2] {
+                let expected = tx.contract_root_index(block, contract).unwrap();
+                let hash = tx.block_hash(block.into()).unwrap().unwrap();
+
+                // Both the miss and the subsequent hit must match the database.
+                assert_eq!(
+                    uut.contract_root_index(&tx, block, hash, contract).unwrap(),
+                    expected
+                );
+                assert_eq!(
+                    uut.contract_root_index(&tx, block, hash, contract).unwrap(),
+                    expected
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn invalidated_by_reorg() {
+        let context = RpcContext::for_tests();
+        let mut db = context.storage.connection().unwrap();
+        let tx = db.transaction().unwrap();
+        let uut = StorageRootCache::with_size(10);
+
+        let contract = contract_address_bytes!(b"contract 1");
+        let block = BlockNumber::GENESIS + 2;
+        let hash = tx.block_hash(block.into()).unwrap().unwrap();
+        let root = uut.contract_root_index(&tx, block, hash, contract).unwrap();
+        assert!(root.is_some());
+
+        // Simulate the block being reverted and replaced by one in which the
+        // contract has no storage.
+        tx.insert_contract_root(block, contract, RootIndexUpdate::TrieEmpty)
+            .unwrap();
+
+        assert_eq!(
+            uut.contract_root_index(&tx, block, block_hash!("0xabcd"), contract)
+                .unwrap(),
+            None
+        );
+    }
+}
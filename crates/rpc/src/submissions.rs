@@ -0,0 +1,407 @@
+//! Persistent tracking and re-broadcasting of transactions submitted through
+//! this node.
+//!
+//! The gateway occasionally drops accepted transactions without them ever
+//! making it into a block. Transactions forwarded to the gateway are therefore
+//! recorded in the database along with the exact request they were submitted
+//! with, and sent again if they haven't appeared in a block within the
+//! re-broadcast window.
+
+use std::time::Duration;
+
+use anyhow::Context;
+use pathfinder_common::{ContractAddress, TransactionHash};
+use pathfinder_storage::{Storage, SubmittedTransaction};
+use starknet_gateway_client::GatewayApi;
+use starknet_gateway_types::error::{
+    KnownStarknetErrorCode,
+    SequencerError,
+    StarknetError,
+    StarknetErrorCode,
+};
+use starknet_gateway_types::reply::add_transaction::{
+    DeclareResponse,
+    DeployAccountResponse,
+    InvokeResponse,
+};
+use starknet_gateway_types::request::add_transaction::{
+    AddTransaction,
+    Declare,
+    DeployAccount,
+    InvokeFunction,
+};
+
+use crate::context::RpcContext;
+use crate::v02::types::request::BroadcastedTransaction;
+
+/// The maximum number of times a transaction is sent to the gateway,
+/// including the original submission.
+const MAX_BROADCASTS: u64 = 5;
+
+/// How long submitted transactions are tracked.
+const RETENTION: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Upper bound on how often the tracked transactions are checked.
+const MAX_POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Records the transactions submitted through this node in the database.
+///
+/// The RPC connection pools are read-only, so the tracker has its own
+/// writable [Storage].
+#[derive(Clone)]
+pub struct SubmissionTracker {
+    storage: Storage,
+}
+
+impl SubmissionTracker {
+    pub fn new(storage: Storage) -> Self {
+        Self { storage }
+    }
+
+    /// Tracks a transaction which the gateway has accepted. Failures are only
+    /// logged, since the transaction was submitted regardless.
+    fn record(
+        &self,
+        transaction_hash: TransactionHash,
+        sender_address: ContractAddress,
+        request: AddTransaction,
+    ) {
+        let storage = self.storage.clone();
+        let span = tracing::Span::current();
+        tokio::task::spawn_blocking(move || {
+            let _g = span.enter();
+            let result = (|| -> anyhow::Result<()> {
+                let request =
+                    serde_json::to_vec(&request).context("Serializing submitted transaction")?;
+                let now = crate::health::now();
+
+                let mut db = storage
+                    .connection()
+                    .context("Creating database connection")?;
+                let db = db.transaction().context("Creating database transaction")?;
+                db.insert_submitted_transaction(
+                    &SubmittedTransaction {
+                        hash: transaction_hash,
+                        sender_address,
+                        submitted_at: now,
+                        last_broadcast_at: now,
+                        broadcast_count: 1,
+                        rejection: None,
+                    },
+                    &request,
+                )?;
+                // Also pruned here as re-broadcasting, which prunes as well, may be disabled.
+                db.prune_submitted_transactions(now.saturating_sub(RETENTION.as_secs()))?;
+                db.commit().context("Committing database transaction")
+            })();
+
+            if let Err(error) = result {
+                tracing::warn!(
+                    %transaction_hash,
+                    ?error,
+                    "Failed to track submitted transaction"
+                );
+            }
+        });
+    }
+
+    /// Re-broadcasts tracked transactions which haven't appeared in a block
+    /// within `window` of being sent to the gateway, until they are included,
+    /// rejected by the gateway or have been sent [MAX_BROADCASTS] times.
+    pub async fn rebroadcast<G: GatewayApi + Send>(self, gateway: G, window: Duration) {
+        let mut interval = tokio::time::interval(window.min(MAX_POLL_INTERVAL));
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        loop {
+            interval.tick().await;
+
+            if let Err(error) = self.rebroadcast_once(&gateway, window).await {
+                tracing::warn!(?error, "Failed to re-broadcast submitted transactions");
+            }
+        }
+    }
+
+    async fn rebroadcast_once<G: GatewayApi>(
+        &self,
+        gateway: &G,
+        window: Duration,
+    ) -> anyhow::Result<()> {
+        let now = crate::health::now();
+
+        let storage = self.storage.clone();
+        let stale = tokio::task::spawn_blocking(move || -> anyhow::Result<_> {
+            let mut db = storage
+                .connection()
+                .context("Creating database connection")?;
+            let db = db.transaction().context("Creating database transaction")?;
+
+            db.prune_submitted_transactions(now.saturating_sub(RETENTION.as_secs()))?;
+            let stale = db
+                .submitted_transactions_to_rebroadcast(
+                    now.saturating_sub(window.as_secs()),
+                    MAX_BROADCASTS,
+                )?
+                .into_iter()
+                .filter_map(|hash| {
+                    db.submitted_transaction_request(hash)
+                        .transpose()
+                        .map(|request| request.map(|request| (hash, request)))
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?;
+
+            db.commit().context("Committing database transaction")?;
+            Ok(stale)
+        })
+        .await
+        .context("Joining database task")??;
+
+        for (hash, request) in stale {
+            let request = String::from_utf8(request).context("Parsing submitted transaction")?;
+            let request = serde_json::value::RawValue::from_string(request)
+                .context("Parsing submitted transaction")?;
+
+            match gateway.resubmit_transaction(request).await {
+                Ok(_) => {
+                    tracing::info!(transaction_hash=%hash, "Re-broadcast submitted transaction");
+                    self.update(move |db| db.update_submitted_transaction_broadcast(hash, now))
+                        .await?;
+                }
+                // The gateway still knows about the transaction, so it was not dropped.
+                Err(SequencerError::StarknetError(error))
+                    if error.code == KnownStarknetErrorCode::DuplicatedTransaction.into() =>
+                {
+                    self.update(move |db| db.update_submitted_transaction_broadcast(hash, now))
+                        .await?;
+                }
+                Err(SequencerError::StarknetError(error)) => {
+                    tracing::info!(
+                        transaction_hash=%hash,
+                        reason=%error.message,
+                        "Gateway rejected re-broadcast transaction"
+                    );
+                    self.update(move |db| db.reject_submitted_transaction(hash, &error.message))
+                        .await?;
+                }
+                Err(error) => {
+                    // Try again once the gateway is reachable.
+                    return Err(error).context("Re-broadcasting submitted transaction");
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn update(
+        &self,
+        f: impl FnOnce(&pathfinder_storage::Transaction<'_>) -> anyhow::Result<()> + Send + 'static,
+    ) -> anyhow::Result<()> {
+        let storage = self.storage.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut db = storage
+                .connection()
+                .context("Creating database connection")?;
+            let db = db.transaction().context("Creating database transaction")?;
+            f(&db)?;
+            db.commit().context("Committing database transaction")
+        })
+        .await
+        .context("Joining database task")?
+    }
+}
+
+/// Submits `invoke` to the gateway, tracking it if accepted.
+pub(crate) async fn add_invoke_transaction(
+    context: &RpcContext,
+    transaction: &BroadcastedTransaction,
+    invoke: InvokeFunction,
+) -> Result<InvokeResponse, SequencerError> {
+    let request = context
+        .submissions
+        .as_ref()
+        .map(|_| AddTransaction::Invoke(invoke.clone()));
+    let response = gateway(context)?.add_invoke_transaction(invoke).await?;
+    track(context, response.transaction_hash, transaction, request);
+
+    Ok(response)
+}
+
+/// Submits `declare` to the gateway, tracking it if accepted.
+pub(crate) async fn add_declare_transaction(
+    context: &RpcContext,
+    transaction: &BroadcastedTransaction,
+    declare: Declare,
+    token: Option<String>,
+) -> Result<DeclareResponse, SequencerError> {
+    let request = context
+        .submissions
+        .as_ref()
+        .map(|_| AddTransaction::Declare(declare.clone()));
+    let response = gateway(context)?
+        .add_declare_transaction(declare, token)
+        .await?;
+    track(context, response.transaction_hash, transaction, request);
+
+    Ok(response)
+}
+
+/// Submits `deploy` to the gateway, tracking it if accepted.
+pub(crate) async fn add_deploy_account(
+    context: &RpcContext,
+    transaction: &BroadcastedTransaction,
+    deploy: DeployAccount,
+) -> Result<DeployAccountResponse, SequencerError> {
+    let request = context
+        .submissions
+        .as_ref()
+        .map(|_| AddTransaction::DeployAccount(deploy.clone()));
+    let response = gateway(context)?.add_deploy_account(deploy).await?;
+    track(context, response.transaction_hash, transaction, request);
+
+    Ok(response)
+}
+
+/// The gateway transactions are submitted to, of which there is none in
+/// offline mode.
+fn gateway(context: &RpcContext) -> Result<&starknet_gateway_client::Client, SequencerError> {
+    context.sequencer.as_ref().ok_or_else(|| {
+        SequencerError::StarknetError(StarknetError {
+            code: StarknetErrorCode::Unknown("OFFLINE".to_owned()),
+            message: "Transactions cannot be submitted in offline mode".to_owned(),
+        })
+    })
+}
+
+fn track(
+    context: &RpcContext,
+    transaction_hash: TransactionHash,
+    transaction: &BroadcastedTransaction,
+    request: Option<AddTransaction>,
+) {
+    if let (Some(submissions), Some(request)) = (&context.submissions, request) {
+        submissions.record(
+            transaction_hash,
+            crate::mempool::sender_address(transaction),
+            request,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::macro_prelude::*;
+    use starknet_gateway_client::MockGatewayApi;
+    use starknet_gateway_types::error::StarknetError;
+    use starknet_gateway_types::reply::add_transaction::AddTransactionResponse;
+
+    use super::*;
+
+    const HOUR: u64 = 60 * 60;
+
+    fn setup() -> (SubmissionTracker, Vec<TransactionHash>) {
+        let storage = pathfinder_storage::StorageBuilder::in_memory().unwrap();
+        let mut db = storage.connection().unwrap();
+        let db = db.transaction().unwrap();
+
+        let now = crate::health::now();
+        let hashes = vec![transaction_hash!("0x1"), transaction_hash!("0x2")];
+        for (i, hash) in hashes.iter().enumerate() {
+            db.insert_submitted_transaction(
+                &SubmittedTransaction {
+                    hash: *hash,
+                    sender_address: contract_address!("0x123"),
+                    submitted_at: now - HOUR,
+                    last_broadcast_at: now - HOUR,
+                    broadcast_count: 1,
+                    rejection: None,
+                },
+                format!(r#"{{"type":"INVOKE_FUNCTION","index":{i}}}"#).as_bytes(),
+            )
+            .unwrap();
+        }
+        // Too old to still be tracked.
+        db.insert_submitted_transaction(
+            &SubmittedTransaction {
+                hash: transaction_hash!("0x3"),
+                sender_address: contract_address!("0x123"),
+                submitted_at: now - 48 * HOUR,
+                last_broadcast_at: now - 48 * HOUR,
+                broadcast_count: 1,
+                rejection: None,
+            },
+            b"{}",
+        )
+        .unwrap();
+        db.commit().unwrap();
+
+        (SubmissionTracker::new(storage), hashes)
+    }
+
+    fn tracked(tracker: &SubmissionTracker) -> Vec<SubmittedTransaction> {
+        let mut db = tracker.storage.connection().unwrap();
+        let db = db.transaction().unwrap();
+        db.submitted_transactions(None, 10).unwrap()
+    }
+
+    #[tokio::test]
+    async fn stale_transactions_are_rebroadcast() {
+        let (tracker, hashes) = setup();
+
+        let mut gateway = MockGatewayApi::new();
+        gateway
+            .expect_resubmit_transaction()
+            .withf(|request| request.get() == r#"{"type":"INVOKE_FUNCTION","index":0}"#)
+            .times(1)
+            .returning(|_| {
+                Ok(AddTransactionResponse {
+                    code: "TRANSACTION_RECEIVED".to_owned(),
+                    transaction_hash: transaction_hash!("0x1"),
+                })
+            });
+        gateway
+            .expect_resubmit_transaction()
+            .withf(|request| request.get() == r#"{"type":"INVOKE_FUNCTION","index":1}"#)
+            .times(1)
+            .returning(|_| {
+                Err(SequencerError::StarknetError(StarknetError {
+                    code: KnownStarknetErrorCode::InvalidTransactionNonce.into(),
+                    message: "Invalid transaction nonce".to_owned(),
+                }))
+            });
+
+        tracker
+            .rebroadcast_once(&gateway, Duration::from_secs(600))
+            .await
+            .unwrap();
+
+        let tracked = tracked(&tracker);
+        assert_eq!(tracked.len(), 2);
+        let first = tracked.iter().find(|tx| tx.hash == hashes[0]).unwrap();
+        assert_eq!(first.broadcast_count, 2);
+        assert_eq!(first.rejection, None);
+        let second = tracked.iter().find(|tx| tx.hash == hashes[1]).unwrap();
+        assert_eq!(second.broadcast_count, 1);
+        assert_eq!(
+            second.rejection.as_deref(),
+            Some("Invalid transaction nonce")
+        );
+
+        // Neither is due for another re-broadcast.
+        tracker
+            .rebroadcast_once(&MockGatewayApi::new(), Duration::from_secs(600))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn recent_transactions_are_not_rebroadcast() {
+        let (tracker, _) = setup();
+
+        tracker
+            .rebroadcast_once(&MockGatewayApi::new(), Duration::from_secs(2 * HOUR))
+            .await
+            .unwrap();
+
+        assert!(tracked(&tracker).iter().all(|tx| tx.broadcast_count == 1));
+    }
+}
@@ -0,0 +1,304 @@
+//! TLS termination for the RPC server.
+//!
+//! The certificate chain and private key are read from PEM files. On unix they
+//! are reloaded on `SIGHUP`, so that renewed certificates are picked up by new
+//! connections without restarting the node.
+
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use anyhow::Context;
+use hyper::body::Incoming;
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use hyper_util::server::conn::auto::Builder;
+use tokio::net::TcpListener;
+use tokio_rustls::TlsAcceptor;
+use tower::Service;
+
+use crate::shutdown::ShutdownCoordinator;
+
+/// Clients which don't complete the TLS handshake within this time are
+/// disconnected.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long to wait before accepting connections again after an error which
+/// is not specific to a single connection, such as running out of file
+/// descriptors. Retrying right away would spin without making progress.
+pub(crate) const ACCEPT_ERROR_BACKOFF: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TlsConfig {
+    /// PEM encoded certificate chain, starting with the server's certificate.
+    pub cert_path: PathBuf,
+    /// PEM encoded private key.
+    pub key_path: PathBuf,
+}
+
+/// Hands out a [TlsAcceptor] for the most recently loaded certificate.
+#[derive(Clone)]
+pub(crate) struct ReloadableAcceptor {
+    config: Arc<TlsConfig>,
+    acceptor: Arc<RwLock<TlsAcceptor>>,
+}
+
+impl ReloadableAcceptor {
+    pub fn new(config: TlsConfig) -> anyhow::Result<Self> {
+        let acceptor = load(&config)?;
+
+        Ok(Self {
+            config: Arc::new(config),
+            acceptor: Arc::new(RwLock::new(acceptor)),
+        })
+    }
+
+    fn acceptor(&self) -> TlsAcceptor {
+        self.acceptor.read().unwrap().clone()
+    }
+
+    /// Reloads the certificate and key. The current ones are kept if loading
+    /// fails.
+    fn reload(&self) -> anyhow::Result<()> {
+        let acceptor = load(&self.config)?;
+        *self.acceptor.write().unwrap() = acceptor;
+        Ok(())
+    }
+
+    /// Reloads the certificate and key whenever `SIGHUP` is received.
+    #[cfg(unix)]
+    pub fn reload_on_sighup(&self) -> anyhow::Result<()> {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut hangup = signal(SignalKind::hangup()).context("Registering SIGHUP handler")?;
+        let acceptor = self.clone();
+        tokio::spawn(async move {
+            while hangup.recv().await.is_some() {
+                match acceptor.reload() {
+                    Ok(()) => tracing::info!("Reloaded RPC TLS certificate"),
+                    Err(error) => tracing::warn!(
+                        error=?error,
+                        "Failed to reload RPC TLS certificate, keeping the current one"
+                    ),
+                }
+            }
+        });
+
+        Ok(())
+    }
+}
+
+fn load(config: &TlsConfig) -> anyhow::Result<TlsAcceptor> {
+    let certs = std::fs::read(&config.cert_path).with_context(|| {
+        format!(
+            "Reading TLS certificate from {}",
+            config.cert_path.display()
+        )
+    })?;
+    let certs = rustls_pemfile::certs(&mut certs.as_slice())
+        .collect::<Result<Vec<_>, _>>()
+        .context("Parsing TLS certificate")?;
+    anyhow::ensure!(
+        !certs.is_empty(),
+        "No certificates found in {}",
+        config.cert_path.display()
+    );
+
+    let key = std::fs::read(&config.key_path)
+        .with_context(|| format!("Reading TLS private key from {}", config.key_path.display()))?;
+    let key = rustls_pemfile::private_key(&mut key.as_slice())
+        .context("Parsing TLS private key")?
+        .with_context(|| format!("No private key found in {}", config.key_path.display()))?;
+
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let mut server_config = rustls::ServerConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .context("Configuring TLS protocol versions")?
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .context("TLS certificate does not match the private key")?;
+    server_config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+
+    Ok(TlsAcceptor::from(Arc::new(server_config)))
+}
+
+/// Serves `router` over TLS until shutdown is initiated, after which open
+/// connections are closed once their in-flight requests complete.
+pub(crate) async fn serve(
+    listener: TcpListener,
+    router: axum::Router,
+    acceptor: ReloadableAcceptor,
+    shutdown: ShutdownCoordinator,
+) -> anyhow::Result<()> {
+    let mut make_service = router.into_make_service_with_connect_info::<SocketAddr>();
+    // Every connection holds a receiver, so that we can wait for all of them to
+    // close.
+    let (close_tx, close_rx) = tokio::sync::watch::channel(());
+
+    loop {
+        let (stream, remote_addr) = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok(accepted) => accepted,
+                Err(error) if is_connection_error(&error) => {
+                    tracing::debug!(%error, "Failed to accept RPC connection");
+                    continue;
+                }
+                Err(error) => {
+                    tracing::warn!(%error, "Failed to accept RPC connections, retrying shortly");
+                    tokio::select! {
+                        _ = tokio::time::sleep(ACCEPT_ERROR_BACKOFF) => continue,
+                        _ = shutdown.wait_for_shutdown() => break,
+                    }
+                }
+            },
+            _ = shutdown.wait_for_shutdown() => break,
+        };
+
+        let tower_service = match make_service.call(remote_addr).await {
+            Ok(service) => service,
+            Err(never) => match never {},
+        };
+        let acceptor = acceptor.acceptor();
+        let shutdown = shutdown.clone();
+        let close_rx = close_rx.clone();
+
+        tokio::spawn(async move {
+            let handshake = tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream));
+            let stream = match handshake.await {
+                Ok(Ok(stream)) => stream,
+                Ok(Err(error)) => {
+                    tracing::debug!(%remote_addr, %error, "RPC TLS handshake failed");
+                    return;
+                }
+                Err(_) => {
+                    tracing::debug!(%remote_addr, "RPC TLS handshake timed out");
+                    return;
+                }
+            };
+
+            let hyper_service =
+                hyper::service::service_fn(move |request: http::Request<Incoming>| {
+                    tower_service.clone().call(request)
+                });
+
+            let builder = Builder::new(TokioExecutor::new());
+            let connection =
+                builder.serve_connection_with_upgrades(TokioIo::new(stream), hyper_service);
+            tokio::pin!(connection);
+
+            let result = tokio::select! {
+                result = connection.as_mut() => result,
+                _ = shutdown.wait_for_shutdown() => {
+                    connection.as_mut().graceful_shutdown();
+                    connection.await
+                }
+            };
+            if let Err(error) = result {
+                tracing::debug!(%remote_addr, %error, "RPC connection failed");
+            }
+
+            drop(close_rx);
+        });
+    }
+
+    drop(close_rx);
+    drop(listener);
+    close_tx.closed().await;
+
+    Ok(())
+}
+
+/// Errors which only affect the connection being accepted, as opposed to the
+/// listener.
+pub(crate) fn is_connection_error(error: &std::io::Error) -> bool {
+    use std::io::ErrorKind;
+
+    matches!(
+        error.kind(),
+        ErrorKind::ConnectionRefused | ErrorKind::ConnectionAborted | ErrorKind::ConnectionReset
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn serves_requests_over_tls() {
+        let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_owned()]).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let config = TlsConfig {
+            cert_path: dir.path().join("cert.pem"),
+            key_path: dir.path().join("key.pem"),
+        };
+        std::fs::write(&config.cert_path, certified.cert.pem()).unwrap();
+        std::fs::write(&config.key_path, certified.key_pair.serialize_pem()).unwrap();
+
+        let acceptor = ReloadableAcceptor::new(config).unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let router = axum::Router::new().route("/", axum::routing::get(|| async { "hello" }));
+        let shutdown = ShutdownCoordinator::default();
+        let server = tokio::spawn(serve(listener, router, acceptor, shutdown.clone()));
+
+        let mut roots = rustls::RootCertStore::empty();
+        roots.add(certified.cert.der().clone()).unwrap();
+        let client_config = rustls::ClientConfig::builder_with_provider(Arc::new(
+            rustls::crypto::ring::default_provider(),
+        ))
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+        let connector = tokio_rustls::TlsConnector::from(Arc::new(client_config));
+
+        let stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let server_name = rustls::pki_types::ServerName::try_from("localhost").unwrap();
+        let mut stream = connector.connect(server_name, stream).await.unwrap();
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 200 OK"), "{response}");
+        assert!(response.ends_with("hello"), "{response}");
+
+        shutdown.shutdown(Duration::from_secs(1)).await.unwrap();
+        server.await.unwrap().unwrap();
+    }
+
+    #[test]
+    fn missing_files_are_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = TlsConfig {
+            cert_path: dir.path().join("cert.pem"),
+            key_path: dir.path().join("key.pem"),
+        };
+
+        let error = ReloadableAcceptor::new(config).err().unwrap();
+
+        assert!(error.to_string().contains("cert.pem"), "{error:#}");
+    }
+
+    #[test]
+    fn files_without_pem_blocks_are_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = TlsConfig {
+            cert_path: dir.path().join("cert.pem"),
+            key_path: dir.path().join("key.pem"),
+        };
+        std::fs::write(&config.cert_path, "not a certificate").unwrap();
+        std::fs::write(&config.key_path, "not a key").unwrap();
+
+        let error = ReloadableAcceptor::new(config).err().unwrap();
+
+        assert!(
+            error.to_string().contains("No certificates found"),
+            "{error:#}"
+        );
+    }
+}
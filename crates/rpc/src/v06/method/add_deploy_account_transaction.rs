@@ -7,6 +7,7 @@ use crate::felt::{RpcFelt, RpcFelt251};
 use crate::v02::types::request::{
     BroadcastedDeployAccountTransaction,
     BroadcastedDeployAccountTransactionV1,
+    BroadcastedTransaction,
 };
 
 #[derive(serde::Deserialize, Debug, PartialEq, Eq)]
@@ -131,40 +132,43 @@ pub(crate) async fn add_deploy_account_transaction_impl(
 ) -> Result<starknet_gateway_types::reply::add_transaction::DeployAccountResponse, SequencerError> {
     use starknet_gateway_types::request::add_transaction;
 
-    match tx {
+    let transaction = BroadcastedTransaction::DeployAccount(tx.clone());
+    let _submitting = crate::mempool::validate(context, &transaction).await?;
+
+    let response = match tx {
         BroadcastedDeployAccountTransaction::V1(
             tx @ BroadcastedDeployAccountTransactionV1 { version, .. },
         ) if version.without_query_version() == 0 => {
-            context
-                .sequencer
-                .add_deploy_account(add_transaction::DeployAccount::V0(
-                    add_transaction::DeployAccountV0V1 {
-                        max_fee: tx.max_fee,
-                        signature: tx.signature,
-                        nonce: tx.nonce,
-                        class_hash: tx.class_hash,
-                        contract_address_salt: tx.contract_address_salt,
-                        constructor_calldata: tx.constructor_calldata,
-                    },
-                ))
-                .await
+            crate::submissions::add_deploy_account(
+                context,
+                &transaction,
+                add_transaction::DeployAccount::V0(add_transaction::DeployAccountV0V1 {
+                    max_fee: tx.max_fee,
+                    signature: tx.signature,
+                    nonce: tx.nonce,
+                    class_hash: tx.class_hash,
+                    contract_address_salt: tx.contract_address_salt,
+                    constructor_calldata: tx.constructor_calldata,
+                }),
+            )
+            .await
         }
         BroadcastedDeployAccountTransaction::V1(
             tx @ BroadcastedDeployAccountTransactionV1 { version, .. },
         ) if version.without_query_version() == 1 => {
-            context
-                .sequencer
-                .add_deploy_account(add_transaction::DeployAccount::V1(
-                    add_transaction::DeployAccountV0V1 {
-                        max_fee: tx.max_fee,
-                        signature: tx.signature,
-                        nonce: tx.nonce,
-                        class_hash: tx.class_hash,
-                        contract_address_salt: tx.contract_address_salt,
-                        constructor_calldata: tx.constructor_calldata,
-                    },
-                ))
-                .await
+            crate::submissions::add_deploy_account(
+                context,
+                &transaction,
+                add_transaction::DeployAccount::V1(add_transaction::DeployAccountV0V1 {
+                    max_fee: tx.max_fee,
+                    signature: tx.signature,
+                    nonce: tx.nonce,
+                    class_hash: tx.class_hash,
+                    contract_address_salt: tx.contract_address_salt,
+                    constructor_calldata: tx.constructor_calldata,
+                }),
+            )
+            .await
         }
         BroadcastedDeployAccountTransaction::V1(_) => Err(SequencerError::StarknetError(
             starknet_gateway_types::error::StarknetError {
@@ -173,36 +177,43 @@ pub(crate) async fn add_deploy_account_transaction_impl(
             },
         )),
         BroadcastedDeployAccountTransaction::V3(tx) => {
-            context
-                .sequencer
-                .add_deploy_account(add_transaction::DeployAccount::V3(
-                    add_transaction::DeployAccountV3 {
-                        signature: tx.signature,
-                        nonce: tx.nonce,
-                        nonce_data_availability_mode:
-                            pathfinder_common::transaction::DataAvailabilityMode::from(
-                                tx.nonce_data_availability_mode,
-                            )
-                            .into(),
-                        fee_data_availability_mode:
-                            pathfinder_common::transaction::DataAvailabilityMode::from(
-                                tx.fee_data_availability_mode,
-                            )
-                            .into(),
-                        resource_bounds: pathfinder_common::transaction::ResourceBounds::from(
-                            tx.resource_bounds,
+            crate::submissions::add_deploy_account(
+                context,
+                &transaction,
+                add_transaction::DeployAccount::V3(add_transaction::DeployAccountV3 {
+                    signature: tx.signature,
+                    nonce: tx.nonce,
+                    nonce_data_availability_mode:
+                        pathfinder_common::transaction::DataAvailabilityMode::from(
+                            tx.nonce_data_availability_mode,
+                        )
+                        .into(),
+                    fee_data_availability_mode:
+                        pathfinder_common::transaction::DataAvailabilityMode::from(
+                            tx.fee_data_availability_mode,
                         )
                         .into(),
-                        tip: tx.tip,
-                        paymaster_data: tx.paymaster_data,
-                        class_hash: tx.class_hash,
-                        contract_address_salt: tx.contract_address_salt,
-                        constructor_calldata: tx.constructor_calldata,
-                    },
-                ))
-                .await
+                    resource_bounds: pathfinder_common::transaction::ResourceBounds::from(
+                        tx.resource_bounds,
+                    )
+                    .into(),
+                    tip: tx.tip,
+                    paymaster_data: tx.paymaster_data,
+                    class_hash: tx.class_hash,
+                    contract_address_salt: tx.contract_address_salt,
+                    constructor_calldata: tx.constructor_calldata,
+                }),
+            )
+            .await
         }
-    }
+    }?;
+
+    context.mempool.insert(
+        response.transaction_hash,
+        crate::mempool::sender_address(&transaction),
+    );
+
+    Ok(response)
 }
 
 #[cfg(test)]
@@ -4,7 +4,7 @@ use starknet_gateway_types::error::SequencerError;
 
 use crate::context::RpcContext;
 use crate::felt::RpcFelt;
-use crate::v02::types::request::BroadcastedInvokeTransaction;
+use crate::v02::types::request::{BroadcastedInvokeTransaction, BroadcastedTransaction};
 
 #[derive(serde::Deserialize, Debug, PartialEq, Eq)]
 #[serde(tag = "type")]
@@ -119,68 +119,78 @@ pub(crate) async fn add_invoke_transaction_impl(
 ) -> Result<starknet_gateway_types::reply::add_transaction::InvokeResponse, SequencerError> {
     use starknet_gateway_types::request::add_transaction;
 
-    match tx {
+    let transaction = BroadcastedTransaction::Invoke(tx.clone());
+    let _submitting = crate::mempool::validate(context, &transaction).await?;
+
+    let response = match tx {
         BroadcastedInvokeTransaction::V0(tx) => {
-            context
-                .sequencer
-                .add_invoke_transaction(add_transaction::InvokeFunction::V0(
-                    add_transaction::InvokeFunctionV0V1 {
-                        max_fee: tx.max_fee,
-                        signature: tx.signature,
-                        nonce: None,
-                        sender_address: tx.contract_address,
-                        entry_point_selector: Some(tx.entry_point_selector),
-                        calldata: tx.calldata,
-                    },
-                ))
-                .await
+            crate::submissions::add_invoke_transaction(
+                context,
+                &transaction,
+                add_transaction::InvokeFunction::V0(add_transaction::InvokeFunctionV0V1 {
+                    max_fee: tx.max_fee,
+                    signature: tx.signature,
+                    nonce: None,
+                    sender_address: tx.contract_address,
+                    entry_point_selector: Some(tx.entry_point_selector),
+                    calldata: tx.calldata,
+                }),
+            )
+            .await
         }
         BroadcastedInvokeTransaction::V1(tx) => {
-            context
-                .sequencer
-                .add_invoke_transaction(add_transaction::InvokeFunction::V1(
-                    add_transaction::InvokeFunctionV0V1 {
-                        max_fee: tx.max_fee,
-                        signature: tx.signature,
-                        nonce: Some(tx.nonce),
-                        sender_address: tx.sender_address,
-                        entry_point_selector: None,
-                        calldata: tx.calldata,
-                    },
-                ))
-                .await
+            crate::submissions::add_invoke_transaction(
+                context,
+                &transaction,
+                add_transaction::InvokeFunction::V1(add_transaction::InvokeFunctionV0V1 {
+                    max_fee: tx.max_fee,
+                    signature: tx.signature,
+                    nonce: Some(tx.nonce),
+                    sender_address: tx.sender_address,
+                    entry_point_selector: None,
+                    calldata: tx.calldata,
+                }),
+            )
+            .await
         }
         BroadcastedInvokeTransaction::V3(tx) => {
-            context
-                .sequencer
-                .add_invoke_transaction(add_transaction::InvokeFunction::V3(
-                    add_transaction::InvokeFunctionV3 {
-                        signature: tx.signature,
-                        nonce: tx.nonce,
-                        nonce_data_availability_mode:
-                            pathfinder_common::transaction::DataAvailabilityMode::from(
-                                tx.nonce_data_availability_mode,
-                            )
-                            .into(),
-                        fee_data_availability_mode:
-                            pathfinder_common::transaction::DataAvailabilityMode::from(
-                                tx.fee_data_availability_mode,
-                            )
-                            .into(),
-                        resource_bounds: pathfinder_common::transaction::ResourceBounds::from(
-                            tx.resource_bounds,
+            crate::submissions::add_invoke_transaction(
+                context,
+                &transaction,
+                add_transaction::InvokeFunction::V3(add_transaction::InvokeFunctionV3 {
+                    signature: tx.signature,
+                    nonce: tx.nonce,
+                    nonce_data_availability_mode:
+                        pathfinder_common::transaction::DataAvailabilityMode::from(
+                            tx.nonce_data_availability_mode,
+                        )
+                        .into(),
+                    fee_data_availability_mode:
+                        pathfinder_common::transaction::DataAvailabilityMode::from(
+                            tx.fee_data_availability_mode,
                         )
                         .into(),
-                        tip: tx.tip,
-                        paymaster_data: tx.paymaster_data,
-                        sender_address: tx.sender_address,
-                        calldata: tx.calldata,
-                        account_deployment_data: tx.account_deployment_data,
-                    },
-                ))
-                .await
+                    resource_bounds: pathfinder_common::transaction::ResourceBounds::from(
+                        tx.resource_bounds,
+                    )
+                    .into(),
+                    tip: tx.tip,
+                    paymaster_data: tx.paymaster_data,
+                    sender_address: tx.sender_address,
+                    calldata: tx.calldata,
+                    account_deployment_data: tx.account_deployment_data,
+                }),
+            )
+            .await
         }
-    }
+    }?;
+
+    context.mempool.insert(
+        response.transaction_hash,
+        crate::mempool::sender_address(&transaction),
+    );
+
+    Ok(response)
 }
 
 #[cfg(test)]
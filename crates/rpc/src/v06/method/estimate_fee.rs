@@ -39,7 +39,10 @@ pub enum EstimateFeeError {
     TransactionExecutionError {
         transaction_index: usize,
         error: String,
+        error_stack: pathfinder_executor::ErrorStack,
+        reason: pathfinder_executor::FailureReason,
     },
+    ExecutionResourcesExceeded,
 }
 
 impl From<anyhow::Error> for EstimateFeeError {
@@ -55,11 +58,15 @@ impl From<pathfinder_executor::TransactionExecutionError> for EstimateFeeError {
             ExecutionError {
                 transaction_index,
                 error,
-                error_stack: _,
+                error_stack,
+                reason,
             } => Self::TransactionExecutionError {
                 transaction_index,
                 error,
+                error_stack,
+                reason,
             },
+            ExecutionResourcesExceeded { .. } => Self::ExecutionResourcesExceeded,
             Internal(e) => Self::Internal(e),
             Custom(e) => Self::Custom(e),
         }
@@ -83,11 +90,17 @@ impl From<EstimateFeeError> for ApplicationError {
             EstimateFeeError::TransactionExecutionError {
                 transaction_index,
                 error,
+                error_stack,
+                reason,
             } => ApplicationError::TransactionExecutionError {
                 transaction_index,
                 error,
-                error_stack: Default::default(),
+                error_stack,
+                reason,
             },
+            EstimateFeeError::ExecutionResourcesExceeded => {
+                ApplicationError::ExecutionResourcesExceeded
+            }
             EstimateFeeError::Internal(e) => ApplicationError::Internal(e),
             EstimateFeeError::Custom(e) => ApplicationError::Custom(e),
         }
@@ -150,8 +163,9 @@ pub async fn estimate_fee_impl(
     l1_blob_data_availability: L1BlobDataAvailability,
 ) -> Result<Vec<FeeEstimate>, EstimateFeeError> {
     let span = tracing::Span::current();
+    let timeout = context.config.execution_timeout;
 
-    let result = tokio::task::spawn_blocking(move || {
+    let execution = tokio::task::spawn_blocking(move || {
         let _g = span.enter();
         let mut db = context
             .execution_storage
@@ -186,7 +200,9 @@ pub async fn estimate_fee_impl(
             pending,
             l1_blob_data_availability,
             context.config.custom_versioned_constants,
-        );
+        )
+        .with_limits(context.config.execution_limits)
+        .with_stored_block_hashes();
 
         let skip_validate = input
             .simulation_flags
@@ -197,15 +213,23 @@ pub async fn estimate_fee_impl(
         let transactions = input
             .request
             .into_iter()
-            .map(|tx| crate::executor::map_broadcasted_transaction(&tx, context.chain_id))
+            .map(|tx| {
+                crate::executor::map_broadcasted_transaction(
+                    &tx,
+                    context.chain_id,
+                    &context.storage,
+                )
+            })
             .collect::<Result<Vec<_>, _>>()?;
 
         let result = pathfinder_executor::estimate(state, transactions, skip_validate)?;
 
         Ok::<_, EstimateFeeError>(result)
-    })
-    .await
-    .context("Executing transaction")??;
+    });
+    let result = crate::executor::with_timeout(timeout, execution)
+        .await
+        .ok_or(EstimateFeeError::ExecutionResourcesExceeded)?
+        .context("Executing transaction")??;
 
     Ok(result.into_iter().map(Into::into).collect())
 }
@@ -538,6 +562,7 @@ pub(crate) mod tests {
                     transactions: vec![],
                     starknet_version: last_block_header.starknet_version,
                     l1_da_mode: L1DataAvailabilityMode::Calldata,
+                    l2_gas_price: Default::default(),
                 }
                 .into(),
                 state_update: state_update.into(),
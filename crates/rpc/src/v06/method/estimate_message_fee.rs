@@ -26,7 +26,9 @@ pub enum EstimateMessageFeeError {
     ContractError {
         revert_error: String,
         revert_error_stack: pathfinder_executor::ErrorStack,
+        reason: pathfinder_executor::FailureReason,
     },
+    ExecutionResourcesExceeded,
     Custom(anyhow::Error),
 }
 
@@ -41,11 +43,16 @@ impl From<pathfinder_executor::TransactionExecutionError> for EstimateMessageFee
         use pathfinder_executor::TransactionExecutionError::*;
         match c {
             ExecutionError {
-                error, error_stack, ..
+                error,
+                error_stack,
+                reason,
+                ..
             } => Self::ContractError {
                 revert_error: format!("Execution error: {}", error),
                 revert_error_stack: error_stack,
+                reason,
             },
+            ExecutionResourcesExceeded { .. } => Self::ExecutionResourcesExceeded,
             Internal(e) => Self::Internal(e),
             Custom(e) => Self::Custom(e),
         }
@@ -70,10 +77,15 @@ impl From<EstimateMessageFeeError> for ApplicationError {
             EstimateMessageFeeError::ContractError {
                 revert_error,
                 revert_error_stack,
+                reason,
             } => ApplicationError::ContractError {
                 revert_error: Some(revert_error),
                 revert_error_stack,
+                reason,
             },
+            EstimateMessageFeeError::ExecutionResourcesExceeded => {
+                ApplicationError::ExecutionResourcesExceeded
+            }
             EstimateMessageFeeError::Internal(e) => ApplicationError::Internal(e),
             EstimateMessageFeeError::Custom(e) => ApplicationError::Custom(e),
         }
@@ -164,7 +176,8 @@ pub(crate) async fn estimate_message_fee_impl(
             pending,
             l1_blob_data_availability,
             context.config.custom_versioned_constants,
-        );
+        )
+        .with_stored_block_hashes();
 
         let transaction = create_executor_transaction(input, context.chain_id)?;
 
@@ -132,6 +132,8 @@ mod tests {
     #[case::latest_by_name(json!({"block_id": "latest"}), BlockId::Latest)]
     #[case::number_by_position(json!([{"block_number":123}]), BlockNumber::new_or_panic(123).into())]
     #[case::number_by_name(json!({"block_id": {"block_number":123}}), BlockNumber::new_or_panic(123).into())]
+    #[case::number_decimal_string(json!([{"block_number":"123"}]), BlockNumber::new_or_panic(123).into())]
+    #[case::number_hex_string(json!([{"block_number":"0x7b"}]), BlockNumber::new_or_panic(123).into())]
     #[case::hash_by_position(json!([{"block_hash": "0xbeef"}]), block_hash!("0xbeef").into())]
     #[case::hash_by_name(json!({"block_id": {"block_hash": "0xbeef"}}), block_hash!("0xbeef").into())]
     fn input_parsing(#[case] input: serde_json::Value, #[case] block_id: BlockId) {
@@ -142,6 +144,15 @@ mod tests {
         assert_eq!(input, expected);
     }
 
+    #[rstest::rstest]
+    #[case::negative(json!([{"block_number":-1}]))]
+    #[case::invalid_decimal(json!([{"block_number":"12a"}]))]
+    #[case::invalid_hex(json!([{"block_number":"0xzz"}]))]
+    #[case::out_of_range(json!([{"block_number":"0xffffffffffffffff"}]))]
+    fn input_parsing_invalid_number(#[case] input: serde_json::Value) {
+        serde_json::from_value::<GetBlockInput>(input).unwrap_err();
+    }
+
     #[tokio::test]
     async fn pending() {
         let context = RpcContext::for_tests_with_pending().await;
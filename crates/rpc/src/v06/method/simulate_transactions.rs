@@ -35,7 +35,10 @@ pub enum SimulateTransactionError {
     TransactionExecutionError {
         transaction_index: usize,
         error: String,
+        error_stack: pathfinder_executor::ErrorStack,
+        reason: pathfinder_executor::FailureReason,
     },
+    ExecutionResourcesExceeded,
 }
 
 impl From<anyhow::Error> for SimulateTransactionError {
@@ -50,13 +53,19 @@ impl From<SimulateTransactionError> for crate::error::ApplicationError {
             SimulateTransactionError::Internal(internal) => Self::Internal(internal),
             SimulateTransactionError::Custom(internal) => Self::Custom(internal),
             SimulateTransactionError::BlockNotFound => Self::BlockNotFound,
+            SimulateTransactionError::ExecutionResourcesExceeded => {
+                Self::ExecutionResourcesExceeded
+            }
             SimulateTransactionError::TransactionExecutionError {
                 transaction_index,
                 error,
+                error_stack,
+                reason,
             } => Self::TransactionExecutionError {
                 transaction_index,
                 error,
-                error_stack: Default::default(),
+                error_stack,
+                reason,
             },
         }
     }
@@ -69,11 +78,15 @@ impl From<TransactionExecutionError> for SimulateTransactionError {
             ExecutionError {
                 transaction_index,
                 error,
-                error_stack: _,
+                error_stack,
+                reason,
             } => Self::TransactionExecutionError {
                 transaction_index,
                 error,
+                error_stack,
+                reason,
             },
+            ExecutionResourcesExceeded { .. } => Self::ExecutionResourcesExceeded,
             Internal(e) => Self::Internal(e),
             Custom(e) => Self::Custom(e),
         }
@@ -156,12 +169,19 @@ pub async fn simulate_transactions_impl(
             pending,
             l1_blob_data_availability,
             context.config.custom_versioned_constants,
-        );
+        )
+        .with_stored_block_hashes();
 
         let transactions = input
             .transactions
             .into_iter()
-            .map(|tx| crate::executor::map_broadcasted_transaction(&tx, context.chain_id))
+            .map(|tx| {
+                crate::executor::map_broadcasted_transaction(
+                    &tx,
+                    context.chain_id,
+                    &context.storage,
+                )
+            })
             .collect::<Result<Vec<_>, _>>()?;
 
         let txs =
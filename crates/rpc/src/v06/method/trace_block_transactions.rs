@@ -84,11 +84,16 @@ impl From<TransactionExecutionError> for TraceBlockTransactionsError {
                 transaction_index,
                 error,
                 error_stack: _,
+                reason: _,
             } => Self::Custom(anyhow::anyhow!(
                 "Transaction execution failed at index {}: {}",
                 transaction_index,
                 error
             )),
+            ExecutionResourcesExceeded { transaction_index } => Self::Custom(anyhow::anyhow!(
+                "Transaction execution exceeded resource limits at index {}",
+                transaction_index
+            )),
             Internal(e) => Self::Internal(e),
             Custom(e) => Self::Custom(e),
         }
@@ -304,7 +309,7 @@ pub async fn trace_block_transactions_impl(
     };
 
     context
-        .sequencer
+        .gateway()?
         .block_traces(input.block_id)
         .await
         .context("Forwarding to feeder gateway")
@@ -578,6 +583,7 @@ pub(crate) mod tests {
                 transactions: transactions.iter().cloned().map(Into::into).collect(),
                 starknet_version: last_block_header.starknet_version,
                 l1_da_mode: L1DataAvailabilityMode::Calldata,
+                l2_gas_price: Default::default(),
             };
 
             tx.commit()?;
@@ -652,6 +658,8 @@ pub(crate) mod tests {
             strk_l1_gas_price: block.l1_gas_price.price_in_fri,
             eth_l1_data_gas_price: block.l1_data_gas_price.price_in_wei,
             strk_l1_data_gas_price: block.l1_data_gas_price.price_in_fri,
+            eth_l2_gas_price: block.l2_gas_price.unwrap_or_default().price_in_wei,
+            strk_l2_gas_price: block.l2_gas_price.unwrap_or_default().price_in_fri,
             sequencer_address: block
                 .sequencer_address
                 .unwrap_or(SequencerAddress(Felt::ZERO)),
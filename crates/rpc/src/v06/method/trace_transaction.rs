@@ -55,11 +55,16 @@ impl From<TransactionExecutionError> for TraceTransactionError {
                 transaction_index,
                 error,
                 error_stack: _,
+                reason: _,
             } => Self::Custom(anyhow::anyhow!(
                 "Transaction execution failed at index {}: {}",
                 transaction_index,
                 error
             )),
+            ExecutionResourcesExceeded { transaction_index } => Self::Custom(anyhow::anyhow!(
+                "Transaction execution exceeded resource limits at index {}",
+                transaction_index
+            )),
             Internal(e) => Self::Internal(e),
             Custom(e) => Self::Custom(e),
         }
@@ -245,7 +250,7 @@ pub async fn trace_transaction_impl(
     };
 
     let trace = context
-        .sequencer
+        .gateway()?
         .transaction_trace(input.transaction_hash)
         .await
         .context("Proxying call to feeder gateway")?;
@@ -322,6 +327,8 @@ pub mod tests {
             strk_l1_gas_price: block.l1_gas_price.price_in_fri,
             eth_l1_data_gas_price: block.l1_data_gas_price.price_in_wei,
             strk_l1_data_gas_price: block.l1_data_gas_price.price_in_fri,
+            eth_l2_gas_price: block.l2_gas_price.unwrap_or_default().price_in_wei,
+            strk_l2_gas_price: block.l2_gas_price.unwrap_or_default().price_in_fri,
             sequencer_address: block
                 .sequencer_address
                 .unwrap_or(SequencerAddress(Felt::ZERO)),
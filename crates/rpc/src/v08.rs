@@ -18,6 +18,7 @@ pub fn register_routes() -> RpcRouterBuilder {
         .register("starknet_getClass",                            crate::method::get_class)
         .register("starknet_getClassAt",                          crate::method::get_class_at)
         .register("starknet_getClassHashAt",                      crate::method::get_class_hash_at)
+        .register("starknet_getCompiledCasm",                     crate::method::get_compiled_casm)
         .register("starknet_getEvents",                           crate::method::get_events)
         .register("starknet_getNonce",                            crate::method::get_nonce)
         .register("starknet_getStateUpdate",                      crate::method::get_state_update)
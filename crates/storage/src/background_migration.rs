@@ -0,0 +1,195 @@
+//! Backfills which run while the node is running instead of during startup.
+//!
+//! Schema migrations which would need to process every stored block, e.g. to
+//! fill a new index, instead register a background migration covering the
+//! blocks stored at the time. New blocks are handled by the regular insert
+//! path, so only those blocks need backfilling. Progress is persisted after
+//! every step, so a restarted node continues where it left off.
+
+use anyhow::Context;
+use pathfinder_common::BlockNumber;
+
+use crate::prelude::*;
+use crate::schema::background_migrations;
+use crate::Storage;
+
+/// Registers the background migration `name` to run over all blocks stored at
+/// the time. Does nothing if there are none, e.g. for a new database.
+pub(crate) fn register(tx: &rusqlite::Transaction<'_>, name: &str) -> anyhow::Result<()> {
+    debug_assert!(
+        background_migrations().iter().any(|(n, _)| *n == name),
+        "Unknown background migration {name}"
+    );
+
+    tx.execute(
+        r"INSERT INTO background_migrations (name, next_block, last_block)
+        SELECT ?, 0, MAX(number) FROM block_headers HAVING COUNT(*) > 0",
+        params![&name],
+    )
+    .with_context(|| format!("Registering background migration {name}"))?;
+
+    Ok(())
+}
+
+/// Runs the next step of at most `max_blocks` blocks of the first unfinished
+/// background migration, returning false once there is nothing left to do.
+///
+/// This is meant to be called periodically while the node is running, which
+/// limits the I/O spent on backfilling and how long other writers are blocked.
+pub fn migrate_incrementally(storage: &Storage, max_blocks: u64) -> anyhow::Result<bool> {
+    let mut connection = storage
+        .connection()
+        .context("Opening database connection")?;
+    let tx = connection
+        .transaction()
+        .context("Creating database transaction")?;
+
+    for (name, migrate) in background_migrations() {
+        let progress = tx
+            .inner()
+            .query_row(
+                r"SELECT next_block, last_block FROM background_migrations
+                WHERE name = ? AND next_block <= last_block",
+                params![name],
+                |row| Ok((row.get_block_number(0)?, row.get_block_number(1)?)),
+            )
+            .optional()
+            .with_context(|| format!("Querying progress of background migration {name}"))?;
+        let Some((next_block, last_block)) = progress else {
+            continue;
+        };
+
+        let remaining = last_block.get() - next_block.get();
+        let end = next_block + max_blocks.saturating_sub(1).min(remaining);
+        migrate(tx.inner(), next_block..=end)
+            .with_context(|| format!("Running background migration {name}"))?;
+
+        tx.inner()
+            .execute(
+                "UPDATE background_migrations SET next_block = ? WHERE name = ?",
+                params![&(end + 1), name],
+            )
+            .with_context(|| format!("Updating progress of background migration {name}"))?;
+        tx.commit()
+            .with_context(|| format!("Committing background migration {name}"))?;
+
+        tracing::debug!(%name, %end, %last_block, "Background migration progressed");
+
+        return Ok(true);
+    }
+
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::macro_prelude::*;
+    use pathfinder_common::receipt::Receipt;
+    use pathfinder_common::transaction::{L1HandlerTransaction, Transaction, TransactionVariant};
+    use pathfinder_common::{BlockHeader, CallParam, TransactionIndex};
+    use pathfinder_crypto::Felt;
+
+    use super::*;
+    use crate::BackgroundMigrationStatus;
+
+    fn l1_handler(nonce: u8) -> L1HandlerTransaction {
+        L1HandlerTransaction {
+            contract_address: contract_address_bytes!(b"l1 handler contract"),
+            entry_point_selector: entry_point_bytes!(b"l1 handler selector"),
+            nonce: transaction_nonce!("0x1"),
+            calldata: vec![CallParam(Felt::from_u64(nonce.into()))],
+        }
+    }
+
+    #[test]
+    fn l1_handler_messages() {
+        let storage = crate::StorageBuilder::in_memory().unwrap();
+        let mut connection = storage.connection().unwrap();
+        let tx = connection.transaction().unwrap();
+
+        for i in 0..5u8 {
+            let header = BlockHeader::builder()
+                .number(BlockNumber::new_or_panic(i.into()))
+                .finalize_with_hash(block_hash_bytes!(&[i]));
+            let transaction = Transaction {
+                hash: transaction_hash_bytes!(&[i]),
+                variant: TransactionVariant::L1Handler(l1_handler(i)),
+            };
+            let receipt = Receipt {
+                transaction_hash: transaction.hash,
+                transaction_index: TransactionIndex::new_or_panic(0),
+                ..Default::default()
+            };
+            tx.insert_block_header(&header).unwrap();
+            tx.insert_transaction_data(header.number, &[(transaction, receipt)], None)
+                .unwrap();
+        }
+
+        // Simulate a database which existed before the index.
+        tx.inner()
+            .execute_batch("DELETE FROM l1_handler_messages; DELETE FROM background_migrations")
+            .unwrap();
+        register(tx.inner(), "l1_handler_messages").unwrap();
+        tx.commit().unwrap();
+
+        let status = |storage: &Storage| {
+            let mut connection = storage.connection().unwrap();
+            let tx = connection.transaction().unwrap();
+            tx.background_migration_status().unwrap()
+        };
+        let indexed = |storage: &Storage, i: u8| {
+            let mut connection = storage.connection().unwrap();
+            let tx = connection.transaction().unwrap();
+            tx.inner()
+                .query_row(
+                    "SELECT EXISTS (SELECT 1 FROM l1_handler_messages WHERE block_number = ?)",
+                    [i],
+                    |row| row.get::<_, bool>(0),
+                )
+                .unwrap()
+        };
+        let lookup = |storage: &Storage, i: u8| {
+            let mut connection = storage.connection().unwrap();
+            let tx = connection.transaction().unwrap();
+            tx.l1_handler_transactions(l1_handler(i).calculate_message_hash())
+        };
+
+        assert_eq!(
+            status(&storage),
+            vec![BackgroundMigrationStatus {
+                name: "l1_handler_messages".to_owned(),
+                migrated_blocks: 0,
+                total_blocks: 5,
+            }]
+        );
+
+        assert!(migrate_incrementally(&storage, 2).unwrap());
+        assert_eq!(status(&storage)[0].migrated_blocks, 2);
+        assert!(indexed(&storage, 1));
+        assert!(!indexed(&storage, 2));
+
+        // Lookups fail instead of missing unindexed messages.
+        let error = lookup(&storage, 1).unwrap_err();
+        assert!(error.downcast_ref::<crate::MigrationPending>().is_some());
+
+        assert!(migrate_incrementally(&storage, 2).unwrap());
+        assert!(migrate_incrementally(&storage, 2).unwrap());
+        assert!(status(&storage)[0].is_done());
+        assert!((0..5).all(|i| indexed(&storage, i)));
+        assert_eq!(lookup(&storage, 1).unwrap().len(), 1);
+
+        // Nothing left to do.
+        assert!(!migrate_incrementally(&storage, 2).unwrap());
+    }
+
+    #[test]
+    fn nothing_registered_for_new_databases() {
+        let storage = crate::StorageBuilder::in_memory().unwrap();
+        let mut connection = storage.connection().unwrap();
+        let tx = connection.transaction().unwrap();
+
+        assert_eq!(tx.background_migration_status().unwrap(), vec![]);
+        drop(tx);
+        assert!(!migrate_incrementally(&storage, 10).unwrap());
+    }
+}
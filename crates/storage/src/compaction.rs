@@ -0,0 +1,112 @@
+//! Returning unused space in the database to the file system.
+//!
+//! SQLite reuses the pages of deleted rows, but never shrinks the database
+//! file by itself. Trie and state history pruning delete a lot of rows, so
+//! long-running nodes can accumulate tens of gigabytes of free pages.
+
+use anyhow::Context;
+
+use crate::Storage;
+
+/// The number of pages freed per database transaction by [compact].
+const PAGES_PER_STEP: u64 = 10_000;
+
+/// Deletes orphaned trie nodes and returns all free pages to the file system.
+/// Fails if the database is in use, the node must be stopped while this runs.
+///
+/// Databases created before incremental vacuum was enabled by default are
+/// instead rebuilt once using `VACUUM`, which requires as much free disk space
+/// as the size of the database.
+pub fn compact(storage: &Storage) -> anyhow::Result<()> {
+    let mut connection = storage
+        .connection()
+        .context("Opening database connection")?;
+    connection.lock_exclusively()?;
+
+    tracing::info!("Deleting orphaned trie nodes");
+    let tx = connection
+        .transaction()
+        .context("Creating database transaction")?;
+    let deleted = tx.delete_orphaned_trie_nodes()?;
+    tx.commit()
+        .context("Committing orphaned trie node deletion")?;
+
+    let tx = connection
+        .transaction()
+        .context("Creating database transaction")?;
+    let incremental_vacuum_enabled = tx.incremental_vacuum_enabled()?;
+    let page_size = tx.page_size()?;
+    let free_pages = tx.free_page_count()?;
+    drop(tx);
+    tracing::info!(
+        orphans_deleted=%deleted, free_mib=%mib(free_pages, page_size),
+        "Compacting database"
+    );
+
+    if !incremental_vacuum_enabled {
+        tracing::info!(
+            "Rebuilding database to enable incremental vacuum, this requires as much free disk \
+             space as the size of the database"
+        );
+        connection.enable_incremental_vacuum()?;
+        tracing::info!("Database compacted");
+        return Ok(());
+    }
+
+    let mut remaining = free_pages;
+    while remaining > 0 {
+        let tx = connection
+            .transaction()
+            .context("Creating database transaction")?;
+        tx.incremental_vacuum(PAGES_PER_STEP)?;
+        let left = tx.free_page_count()?;
+        tx.commit().context("Committing incremental vacuum")?;
+
+        anyhow::ensure!(left < remaining, "Incremental vacuum made no progress");
+        remaining = left;
+        tracing::info!(
+            freed_mib=%mib(free_pages - remaining, page_size),
+            total_mib=%mib(free_pages, page_size),
+            "Compacting database"
+        );
+    }
+
+    tracing::info!("Database compacted");
+    Ok(())
+}
+
+/// Returns at most `max_bytes` worth of free pages to the file system,
+/// returning the number of bytes freed.
+///
+/// This is meant to be called periodically while the node is running, which
+/// limits the I/O spent on compaction and how long other writers are blocked.
+/// Fails if incremental vacuum is not enabled for the database, see [compact].
+pub fn compact_incrementally(storage: &Storage, max_bytes: u64) -> anyhow::Result<u64> {
+    let mut connection = storage
+        .connection()
+        .context("Opening database connection")?;
+    let tx = connection
+        .transaction()
+        .context("Creating database transaction")?;
+
+    anyhow::ensure!(
+        tx.incremental_vacuum_enabled()?,
+        "Incremental vacuum is not enabled for this database. Run `pathfinder database compact` \
+         once while the node is stopped to enable it."
+    );
+
+    let page_size = tx.page_size()?;
+    let before = tx.free_page_count()?;
+    if before == 0 {
+        return Ok(0);
+    }
+    tx.incremental_vacuum((max_bytes / page_size).max(1))?;
+    let after = tx.free_page_count()?;
+    tx.commit().context("Committing incremental vacuum")?;
+
+    Ok(before.saturating_sub(after) * page_size)
+}
+
+fn mib(pages: u64, page_size: u64) -> u64 {
+    pages * page_size / (1024 * 1024)
+}
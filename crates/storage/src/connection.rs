@@ -1,16 +1,26 @@
 use std::sync::Arc;
 
+mod background_migration;
 mod block;
+pub(crate) mod block_stats;
 mod class;
 mod ethereum;
 mod event;
+pub(crate) mod fee_stats;
+mod message;
 mod reference;
 mod reorg_counter;
+mod reorg_log;
 mod signature;
-mod state_update;
+pub(crate) mod state_update;
+mod submitted_transaction;
 pub(crate) mod transaction;
 mod trie;
+mod vacuum;
 
+pub use background_migration::{BackgroundMigrationStatus, MigrationPending};
+pub use block_stats::{BlockStats, StateDiffCounts, TransactionCounts};
+pub use class::DeclaredClass;
 pub use event::{
     EmittedEvent,
     EventFilter,
@@ -18,33 +28,46 @@ pub use event::{
     PageOfEvents,
     PAGE_SIZE_LIMIT as EVENT_PAGE_SIZE_LIMIT,
 };
+pub use fee_stats::{BlockFeeStats, FEE_PERCENTILE_STEP};
+pub use message::L1ToL2Message;
 use pathfinder_common::event::Event;
 use pathfinder_common::receipt::Receipt;
 use pathfinder_common::transaction::Transaction as StarknetTransaction;
 use pathfinder_common::{BlockNumber, TransactionHash};
-pub(crate) use reorg_counter::ReorgCounter;
+pub use reorg_counter::ReorgCounter;
+pub use reorg_log::ReorgLogEntry;
 // Re-export this so users don't require rusqlite as a direct dep.
 pub use rusqlite::TransactionBehavior;
+pub use submitted_transaction::SubmittedTransaction;
+pub use transaction::SentTransaction;
 pub use trie::{Node, NodeRef, RootIndexUpdate, StoredNode, TrieUpdate};
 
+use crate::trie_backend::TrieNodes;
+
 type PooledConnection = r2d2::PooledConnection<r2d2_sqlite::SqliteConnectionManager>;
 
 pub struct Connection {
     connection: PooledConnection,
     bloom_filter_cache: Arc<crate::bloom::Cache>,
+    trie_nodes: Arc<dyn TrieNodes>,
     trie_prune_mode: TriePruneMode,
+    history_prune_mode: HistoryPruneMode,
 }
 
 impl Connection {
     pub(crate) fn new(
         connection: PooledConnection,
         bloom_filter_cache: Arc<crate::bloom::Cache>,
+        trie_nodes: Arc<dyn TrieNodes>,
         trie_prune_mode: TriePruneMode,
+        history_prune_mode: HistoryPruneMode,
     ) -> Self {
         Self {
             connection,
             bloom_filter_cache,
+            trie_nodes,
             trie_prune_mode,
+            history_prune_mode,
         }
     }
 
@@ -53,7 +76,9 @@ impl Connection {
         Ok(Transaction {
             transaction: tx,
             bloom_filter_cache: self.bloom_filter_cache.clone(),
+            trie_nodes: self.trie_nodes.clone(),
             trie_prune_mode: self.trie_prune_mode,
+            history_prune_mode: self.history_prune_mode,
         })
     }
 
@@ -65,7 +90,9 @@ impl Connection {
         Ok(Transaction {
             transaction: tx,
             bloom_filter_cache: self.bloom_filter_cache.clone(),
+            trie_nodes: self.trie_nodes.clone(),
             trie_prune_mode: self.trie_prune_mode,
+            history_prune_mode: self.history_prune_mode,
         })
     }
 }
@@ -73,7 +100,9 @@ impl Connection {
 pub struct Transaction<'inner> {
     transaction: rusqlite::Transaction<'inner>,
     bloom_filter_cache: Arc<crate::bloom::Cache>,
+    trie_nodes: Arc<dyn TrieNodes>,
     trie_prune_mode: TriePruneMode,
+    history_prune_mode: HistoryPruneMode,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -85,6 +114,22 @@ pub enum TriePruneMode {
     Prune { num_blocks_kept: u64 },
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistoryPruneMode {
+    /// Keep the state diffs of all blocks.
+    Archive,
+    /// Delete state diff entries once they have been superseded for more than
+    /// `num_blocks_kept` blocks. Historical state is only available for the
+    /// last `num_blocks_kept` blocks, while headers and transactions are kept.
+    Prune { num_blocks_kept: u64 },
+}
+
+/// The state of a block was requested after [HistoryPruneMode::Prune] deleted
+/// its history.
+#[derive(Debug, thiserror::Error)]
+#[error("The state of block {0} has been pruned")]
+pub struct StatePruned(pub BlockNumber);
+
 type TransactionWithReceipt = (StarknetTransaction, Receipt, Vec<Event>, BlockNumber);
 
 type TransactionDataForBlock = (StarknetTransaction, Receipt, Vec<Event>);
@@ -96,7 +141,7 @@ impl<'inner> Transaction<'inner> {
     // the real implementations be kept in separate files with more reasonable
     // LOC counts and easier test oversight.
 
-    fn inner(&self) -> &rusqlite::Transaction<'_> {
+    pub(crate) fn inner(&self) -> &rusqlite::Transaction<'_> {
         &self.transaction
     }
 
@@ -0,0 +1,82 @@
+use std::ops::RangeInclusive;
+
+use anyhow::Context;
+use pathfinder_common::BlockNumber;
+
+use crate::prelude::*;
+
+/// The progress of a [background migration](crate::background_migration).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackgroundMigrationStatus {
+    pub name: String,
+    pub migrated_blocks: u64,
+    /// The number of blocks stored when the migration was registered.
+    pub total_blocks: u64,
+}
+
+impl BackgroundMigrationStatus {
+    pub fn is_done(&self) -> bool {
+        self.migrated_blocks >= self.total_blocks
+    }
+}
+
+/// A query needed blocks which the [background
+/// migration](crate::background_migration) named here hasn't processed yet.
+#[derive(Debug, thiserror::Error)]
+#[error("Background migration {0} hasn't finished yet")]
+pub struct MigrationPending(pub &'static str);
+
+impl Transaction<'_> {
+    /// Returns the progress of all background migrations registered for this
+    /// database, including finished ones.
+    pub fn background_migration_status(&self) -> anyhow::Result<Vec<BackgroundMigrationStatus>> {
+        let mut stmt = self.inner().prepare_cached(
+            r"SELECT name, next_block, last_block FROM background_migrations ORDER BY rowid",
+        )?;
+
+        let status = stmt
+            .query_map([], |row| {
+                let name: String = row.get(0)?;
+                let migrated_blocks = row.get_i64(1)? as u64;
+                let total_blocks = row.get_i64(2)? as u64 + 1;
+                Ok(BackgroundMigrationStatus {
+                    name,
+                    migrated_blocks,
+                    total_blocks,
+                })
+            })
+            .context("Querying background migrations")?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(status)
+    }
+
+    /// Fails with [MigrationPending] if the background migration `name` still
+    /// has to process any of `blocks`.
+    ///
+    /// Queries of the data a background migration fills go through this, so
+    /// that missing entries aren't reported as incomplete results.
+    pub(crate) fn ensure_migrated(
+        &self,
+        name: &'static str,
+        blocks: RangeInclusive<BlockNumber>,
+    ) -> anyhow::Result<()> {
+        let mut stmt = self.inner().prepare_cached(
+            r"SELECT EXISTS (
+                SELECT 1 FROM background_migrations
+                WHERE name = ? AND next_block <= last_block AND next_block <= ? AND last_block >= ?
+            )",
+        )?;
+        let pending = stmt
+            .query_row(params![&name, blocks.end(), blocks.start()], |row| {
+                row.get::<_, bool>(0)
+            })
+            .with_context(|| format!("Querying progress of background migration {name}"))?;
+
+        if pending {
+            Err(MigrationPending(name).into())
+        } else {
+            Ok(())
+        }
+    }
+}
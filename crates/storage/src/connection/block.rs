@@ -16,15 +16,15 @@ use pathfinder_common::{
 };
 
 use crate::prelude::*;
-use crate::BlockId;
+use crate::{BlockId, HistoryPruneMode, StatePruned};
 
 impl Transaction<'_> {
     pub fn insert_block_header(&self, header: &BlockHeader) -> anyhow::Result<()> {
         // Insert the header
         self.inner().execute(
         r"INSERT INTO block_headers 
-                   ( number,  hash,  parent_hash,  storage_commitment,  timestamp,  eth_l1_gas_price,  strk_l1_gas_price,  eth_l1_data_gas_price,  strk_l1_data_gas_price,  sequencer_address,  version,  transaction_commitment,  event_commitment,  state_commitment,  class_commitment,  transaction_count,  event_count,  l1_da_mode,  receipt_commitment,  state_diff_commitment,  state_diff_length)
-            VALUES (:number, :hash, :parent_hash, :storage_commitment, :timestamp, :eth_l1_gas_price, :strk_l1_gas_price, :eth_l1_data_gas_price, :strk_l1_data_gas_price, :sequencer_address, :version, :transaction_commitment, :event_commitment, :state_commitment, :class_commitment, :transaction_count, :event_count, :l1_da_mode, :receipt_commitment, :state_diff_commitment, :state_diff_length)",
+                   ( number,  hash,  parent_hash,  storage_commitment,  timestamp,  eth_l1_gas_price,  strk_l1_gas_price,  eth_l1_data_gas_price,  strk_l1_data_gas_price,  eth_l2_gas_price,  strk_l2_gas_price,  sequencer_address,  version,  transaction_commitment,  event_commitment,  state_commitment,  class_commitment,  transaction_count,  event_count,  l1_da_mode,  receipt_commitment,  state_diff_commitment,  state_diff_length)
+            VALUES (:number, :hash, :parent_hash, :storage_commitment, :timestamp, :eth_l1_gas_price, :strk_l1_gas_price, :eth_l1_data_gas_price, :strk_l1_data_gas_price, :eth_l2_gas_price, :strk_l2_gas_price, :sequencer_address, :version, :transaction_commitment, :event_commitment, :state_commitment, :class_commitment, :transaction_count, :event_count, :l1_da_mode, :receipt_commitment, :state_diff_commitment, :state_diff_length)",
         named_params! {
             ":number": &header.number,
             ":hash": &header.hash,
@@ -35,6 +35,8 @@ impl Transaction<'_> {
             ":strk_l1_gas_price": &header.strk_l1_gas_price.to_be_bytes().as_slice(),
             ":eth_l1_data_gas_price": &header.eth_l1_data_gas_price.to_be_bytes().as_slice(),
             ":strk_l1_data_gas_price": &header.strk_l1_data_gas_price.to_be_bytes().as_slice(),
+            ":eth_l2_gas_price": &header.eth_l2_gas_price.to_be_bytes().as_slice(),
+            ":strk_l2_gas_price": &header.strk_l2_gas_price.to_be_bytes().as_slice(),
             ":sequencer_address": &header.sequencer_address,
             ":version": &header.starknet_version.as_u32(),
             ":transaction_commitment": &header.transaction_commitment,
@@ -121,6 +123,27 @@ impl Transaction<'_> {
             )
             .context("Deleting bloom filter")?;
 
+        self.inner()
+            .execute(
+                "DELETE FROM contract_event_blocks WHERE block_number = ?",
+                params![&block],
+            )
+            .context("Deleting contract event blocks")?;
+
+        self.inner()
+            .execute(
+                "DELETE FROM transaction_senders WHERE block_number = ?",
+                params![&block],
+            )
+            .context("Deleting transaction senders")?;
+
+        self.inner()
+            .execute(
+                "DELETE FROM l1_handler_messages WHERE block_number = ?",
+                params![&block],
+            )
+            .context("Deleting L1 handler messages")?;
+
         self.inner()
             .execute(
                 "DELETE FROM transactions WHERE block_number = ?",
@@ -128,6 +151,20 @@ impl Transaction<'_> {
             )
             .context("Deleting transactions")?;
 
+        self.inner()
+            .execute(
+                "DELETE FROM block_fee_stats WHERE block_number = ?",
+                params![&block],
+            )
+            .context("Deleting fee stats")?;
+
+        self.inner()
+            .execute(
+                "DELETE FROM block_stats WHERE block_number = ?",
+                params![&block],
+            )
+            .context("Deleting block stats")?;
+
         self.inner()
             .execute(
                 "DELETE FROM canonical_blocks WHERE number = ?",
@@ -326,6 +363,47 @@ impl Transaction<'_> {
         .map_err(|e| e.into())
     }
 
+    /// Like [Self::block_exists], but `false` for blocks whose state has been
+    /// pruned, see [HistoryPruneMode].
+    pub fn block_state_exists(&self, block: BlockId) -> anyhow::Result<bool> {
+        if self.pruned_block(block)?.is_some() {
+            return Ok(false);
+        }
+
+        self.block_exists(block)
+    }
+
+    /// Fails with [StatePruned] if the state of `block` has been pruned, see
+    /// [HistoryPruneMode].
+    ///
+    /// All state reads for a specific block go through this, so that pruned
+    /// history is reported as such instead of as missing values.
+    pub fn ensure_state_not_pruned(&self, block: BlockId) -> anyhow::Result<()> {
+        match self.pruned_block(block)? {
+            Some(number) => Err(StatePruned(number).into()),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the number of `block` if its state has been pruned.
+    fn pruned_block(&self, block: BlockId) -> anyhow::Result<Option<BlockNumber>> {
+        let HistoryPruneMode::Prune { num_blocks_kept } = self.history_prune_mode else {
+            return Ok(None);
+        };
+        if block == BlockId::Latest {
+            return Ok(None);
+        }
+
+        let Some((number, _)) = self.block_id(block)? else {
+            return Ok(None);
+        };
+        let Some((latest, _)) = self.block_id(BlockId::Latest)? else {
+            return Ok(None);
+        };
+
+        Ok((number.get() + num_blocks_kept <= latest.get()).then_some(number))
+    }
+
     pub fn block_version(&self, block: BlockNumber) -> anyhow::Result<Option<StarknetVersion>> {
         let mut stmt = self
             .inner()
@@ -613,6 +691,12 @@ fn parse_row_as_header(row: &rusqlite::Row<'_>) -> rusqlite::Result<BlockHeader>
     let strk_l1_data_gas_price = row
         .get_optional_gas_price("strk_l1_data_gas_price")?
         .unwrap_or(GasPrice::ZERO);
+    let eth_l2_gas_price = row
+        .get_optional_gas_price("eth_l2_gas_price")?
+        .unwrap_or(GasPrice::ZERO);
+    let strk_l2_gas_price = row
+        .get_optional_gas_price("strk_l2_gas_price")?
+        .unwrap_or(GasPrice::ZERO);
     let sequencer_address = row.get_sequencer_address("sequencer_address")?;
     let transaction_commitment = row.get_transaction_commitment("transaction_commitment")?;
     let event_commitment = row.get_event_commitment("event_commitment")?;
@@ -637,6 +721,8 @@ fn parse_row_as_header(row: &rusqlite::Row<'_>) -> rusqlite::Result<BlockHeader>
         strk_l1_gas_price,
         eth_l1_data_gas_price,
         strk_l1_data_gas_price,
+        eth_l2_gas_price,
+        strk_l2_gas_price,
         sequencer_address,
         class_commitment,
         event_commitment,
@@ -688,6 +774,8 @@ mod tests {
             strk_l1_gas_price: GasPrice(33),
             eth_l1_data_gas_price: GasPrice(34),
             strk_l1_data_gas_price: GasPrice(35),
+            eth_l2_gas_price: GasPrice(36),
+            strk_l2_gas_price: GasPrice(37),
             sequencer_address: sequencer_address_bytes!(b"sequencer address genesis"),
             starknet_version: StarknetVersion::default(),
             class_commitment,
@@ -0,0 +1,391 @@
+use anyhow::Context;
+use pathfinder_common::receipt::Receipt;
+use pathfinder_common::transaction::{Transaction as StarknetTransaction, TransactionKind};
+use pathfinder_common::{BlockNumber, Fee};
+
+use crate::connection::fee_stats::account_fees;
+use crate::prelude::*;
+
+/// Aggregates of a block's transactions and state diff, which are recorded
+/// when the block is stored.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BlockStats {
+    pub block_number: BlockNumber,
+    pub transactions: TransactionCounts,
+    /// The number of events, as given by the block's header.
+    pub events: u64,
+    /// The sum of the actual fees paid in WEI, by the same transactions as the
+    /// fee percentiles of [BlockFeeStats](crate::BlockFeeStats).
+    pub total_fee_wei: Fee,
+    /// The sum of the actual fees paid in FRI, by the same transactions as the
+    /// fee percentiles of [BlockFeeStats](crate::BlockFeeStats).
+    pub total_fee_fri: Fee,
+    pub state_diff: StateDiffCounts,
+}
+
+/// The number of transactions of a block by type.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TransactionCounts {
+    pub declare: u64,
+    pub deploy: u64,
+    pub deploy_account: u64,
+    pub invoke: u64,
+    pub l1_handler: u64,
+}
+
+/// The size of a block's state diff.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StateDiffCounts {
+    pub storage_diffs: u64,
+    pub nonce_updates: u64,
+    /// Both Cairo 0 and Sierra classes, including re-declarations.
+    pub declared_classes: u64,
+    pub deployed_contracts: u64,
+    pub replaced_classes: u64,
+}
+
+impl Transaction<'_> {
+    /// Records the transaction aggregates of a block, leaving its state diff
+    /// aggregates as they are.
+    pub(super) fn insert_block_transaction_stats(
+        &self,
+        block_number: BlockNumber,
+        transactions: &[(StarknetTransaction, Receipt)],
+    ) -> anyhow::Result<()> {
+        let (counts, fee_wei, fee_fri) = transaction_stats(transactions);
+        insert_transaction_stats(self.inner(), block_number, &counts, fee_wei, fee_fri)
+    }
+
+    /// Records the state diff aggregates of a block, leaving its transaction
+    /// aggregates as they are.
+    pub(super) fn insert_block_state_diff_stats(
+        &self,
+        block_number: BlockNumber,
+        counts: &StateDiffCounts,
+    ) -> anyhow::Result<()> {
+        insert_state_diff_stats(self.inner(), block_number, counts)
+    }
+
+    /// Returns the aggregates of the blocks in `from..=to`, oldest first.
+    /// Blocks without recorded aggregates are left out.
+    ///
+    /// Fails with [MigrationPending](crate::MigrationPending) while the
+    /// aggregates of existing blocks are still being computed.
+    pub fn block_stats(
+        &self,
+        from: BlockNumber,
+        to: BlockNumber,
+    ) -> anyhow::Result<Vec<BlockStats>> {
+        self.ensure_migrated(crate::schema::BLOCK_STATS, from..=to)?;
+
+        let mut stmt = self
+            .inner()
+            .prepare_cached(
+                r"SELECT
+                    block_stats.block_number,
+                    declare_count,
+                    deploy_count,
+                    deploy_account_count,
+                    invoke_count,
+                    l1_handler_count,
+                    block_headers.event_count,
+                    total_fee_wei,
+                    total_fee_fri,
+                    storage_diff_count,
+                    nonce_update_count,
+                    declared_class_count,
+                    deployed_contract_count,
+                    replaced_class_count
+                FROM block_stats
+                JOIN block_headers ON block_headers.number = block_stats.block_number
+                WHERE block_stats.block_number BETWEEN ? AND ?
+                ORDER BY block_stats.block_number",
+            )
+            .context("Preparing block stats query")?;
+        let mut rows = stmt
+            .query(params![&from, &to])
+            .context("Querying block stats")?;
+
+        let count = |row: &rusqlite::Row<'_>, index: usize| row.get_i64(index).map(|c| c as u64);
+        let fee = |row: &rusqlite::Row<'_>, index: usize| {
+            row.get_optional_felt(index)
+                .map(|fee| fee.map(Fee).unwrap_or_default())
+        };
+
+        let mut stats = Vec::new();
+        while let Some(row) = rows.next().context("Iterating over rows")? {
+            stats.push(BlockStats {
+                block_number: row.get_block_number(0)?,
+                transactions: TransactionCounts {
+                    declare: count(row, 1)?,
+                    deploy: count(row, 2)?,
+                    deploy_account: count(row, 3)?,
+                    invoke: count(row, 4)?,
+                    l1_handler: count(row, 5)?,
+                },
+                events: count(row, 6)?,
+                total_fee_wei: fee(row, 7)?,
+                total_fee_fri: fee(row, 8)?,
+                state_diff: StateDiffCounts {
+                    storage_diffs: count(row, 9)?,
+                    nonce_updates: count(row, 10)?,
+                    declared_classes: count(row, 11)?,
+                    deployed_contracts: count(row, 12)?,
+                    replaced_classes: count(row, 13)?,
+                },
+            });
+        }
+
+        Ok(stats)
+    }
+}
+
+/// Counts the transactions of a block by type and sums their
+/// [account fees](crate::connection::fee_stats::account_fees) by the unit they
+/// are paid in, WEI and FRI.
+pub(crate) fn transaction_stats(
+    transactions: &[(StarknetTransaction, Receipt)],
+) -> (TransactionCounts, Fee, Fee) {
+    let mut counts = TransactionCounts::default();
+    for (transaction, _) in transactions {
+        let count = match transaction.variant.kind() {
+            TransactionKind::Declare => &mut counts.declare,
+            TransactionKind::Deploy => &mut counts.deploy,
+            TransactionKind::DeployAccount => &mut counts.deploy_account,
+            TransactionKind::Invoke => &mut counts.invoke,
+            TransactionKind::L1Handler => &mut counts.l1_handler,
+        };
+        *count += 1;
+    }
+
+    let (fee_wei, fee_fri) = account_fees(transactions.iter().map(|(transaction, receipt)| {
+        (
+            transaction.variant.kind(),
+            transaction.version(),
+            receipt.actual_fee,
+        )
+    }));
+    let total = |fees: Vec<Fee>| {
+        fees.into_iter()
+            .fold(Fee::ZERO, |total, fee| Fee(total.0 + fee.0))
+    };
+
+    (counts, total(fee_wei), total(fee_fri))
+}
+
+pub(crate) fn insert_transaction_stats(
+    tx: &rusqlite::Transaction<'_>,
+    block_number: BlockNumber,
+    counts: &TransactionCounts,
+    fee_wei: Fee,
+    fee_fri: Fee,
+) -> anyhow::Result<()> {
+    let mut stmt = tx
+        .prepare_cached(
+            r"INSERT INTO block_stats (
+                block_number,
+                declare_count,
+                deploy_count,
+                deploy_account_count,
+                invoke_count,
+                l1_handler_count,
+                total_fee_wei,
+                total_fee_fri
+            ) VALUES (
+                :block_number,
+                :declare_count,
+                :deploy_count,
+                :deploy_account_count,
+                :invoke_count,
+                :l1_handler_count,
+                :total_fee_wei,
+                :total_fee_fri
+            )
+            ON CONFLICT (block_number) DO UPDATE SET
+                declare_count = excluded.declare_count,
+                deploy_count = excluded.deploy_count,
+                deploy_account_count = excluded.deploy_account_count,
+                invoke_count = excluded.invoke_count,
+                l1_handler_count = excluded.l1_handler_count,
+                total_fee_wei = excluded.total_fee_wei,
+                total_fee_fri = excluded.total_fee_fri",
+        )
+        .context("Preparing insert transaction stats statement")?;
+    stmt.execute(named_params![
+        ":block_number": &block_number,
+        ":declare_count": &counts.declare.try_into_sql_int()?,
+        ":deploy_count": &counts.deploy.try_into_sql_int()?,
+        ":deploy_account_count": &counts.deploy_account.try_into_sql_int()?,
+        ":invoke_count": &counts.invoke.try_into_sql_int()?,
+        ":l1_handler_count": &counts.l1_handler.try_into_sql_int()?,
+        ":total_fee_wei": &fee_wei,
+        ":total_fee_fri": &fee_fri,
+    ])
+    .context("Inserting transaction stats")?;
+
+    Ok(())
+}
+
+pub(crate) fn insert_state_diff_stats(
+    tx: &rusqlite::Transaction<'_>,
+    block_number: BlockNumber,
+    counts: &StateDiffCounts,
+) -> anyhow::Result<()> {
+    let mut stmt = tx
+        .prepare_cached(
+            r"INSERT INTO block_stats (
+                block_number,
+                storage_diff_count,
+                nonce_update_count,
+                declared_class_count,
+                deployed_contract_count,
+                replaced_class_count
+            ) VALUES (
+                :block_number,
+                :storage_diff_count,
+                :nonce_update_count,
+                :declared_class_count,
+                :deployed_contract_count,
+                :replaced_class_count
+            )
+            ON CONFLICT (block_number) DO UPDATE SET
+                storage_diff_count = excluded.storage_diff_count,
+                nonce_update_count = excluded.nonce_update_count,
+                declared_class_count = excluded.declared_class_count,
+                deployed_contract_count = excluded.deployed_contract_count,
+                replaced_class_count = excluded.replaced_class_count",
+        )
+        .context("Preparing insert state diff stats statement")?;
+    stmt.execute(named_params![
+        ":block_number": &block_number,
+        ":storage_diff_count": &counts.storage_diffs.try_into_sql_int()?,
+        ":nonce_update_count": &counts.nonce_updates.try_into_sql_int()?,
+        ":declared_class_count": &counts.declared_classes.try_into_sql_int()?,
+        ":deployed_contract_count": &counts.deployed_contracts.try_into_sql_int()?,
+        ":replaced_class_count": &counts.replaced_classes.try_into_sql_int()?,
+    ])
+    .context("Inserting state diff stats")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::macro_prelude::*;
+    use pathfinder_common::transaction::{
+        DeclareTransactionV0V1,
+        InvokeTransactionV0,
+        InvokeTransactionV3,
+        L1HandlerTransaction,
+        TransactionVariant,
+    };
+    use pathfinder_common::{BlockHeader, StateUpdate};
+    use pathfinder_crypto::Felt;
+
+    use super::*;
+
+    #[test]
+    fn stats_are_recorded_with_block_data() {
+        let storage = crate::StorageBuilder::in_memory().unwrap();
+        let mut db = storage.connection().unwrap();
+        let db = db.transaction().unwrap();
+
+        let header = BlockHeader::builder()
+            .number(BlockNumber::GENESIS)
+            .event_count(3)
+            .finalize_with_hash(block_hash_bytes!(b"genesis"));
+        db.insert_block_header(&header).unwrap();
+
+        let transaction = |hash, variant, fee| {
+            (
+                StarknetTransaction { hash, variant },
+                Receipt {
+                    actual_fee: Fee(Felt::from_u64(fee)),
+                    transaction_hash: hash,
+                    ..Default::default()
+                },
+            )
+        };
+        let transactions = vec![
+            transaction(
+                transaction_hash_bytes!(b"declare"),
+                TransactionVariant::DeclareV1(DeclareTransactionV0V1::default()),
+                1,
+            ),
+            transaction(
+                transaction_hash_bytes!(b"v0"),
+                TransactionVariant::InvokeV0(InvokeTransactionV0::default()),
+                10,
+            ),
+            transaction(
+                transaction_hash_bytes!(b"v3"),
+                TransactionVariant::InvokeV3(InvokeTransactionV3::default()),
+                100,
+            ),
+            // Not paid for by an account, so left out of the fee totals.
+            transaction(
+                transaction_hash_bytes!(b"l1 handler"),
+                TransactionVariant::L1Handler(L1HandlerTransaction::default()),
+                1000,
+            ),
+        ];
+        db.insert_transaction_data(header.number, &transactions, None)
+            .unwrap();
+
+        let state_update = StateUpdate::default()
+            .with_block_hash(header.hash)
+            .with_deployed_contract(
+                contract_address_bytes!(b"deployed"),
+                class_hash_bytes!(b"a"),
+            )
+            .with_replaced_class(
+                contract_address_bytes!(b"replaced"),
+                class_hash_bytes!(b"b"),
+            )
+            .with_contract_nonce(contract_address_bytes!(b"replaced"), contract_nonce!("0x1"))
+            .with_storage_update(
+                contract_address_bytes!(b"deployed"),
+                storage_address_bytes!(b"key 1"),
+                storage_value_bytes!(b"value"),
+            )
+            .with_storage_update(
+                contract_address_bytes!(b"deployed"),
+                storage_address_bytes!(b"key 2"),
+                storage_value_bytes!(b"value"),
+            )
+            .with_declared_cairo_class(class_hash_bytes!(b"cairo"))
+            .with_declared_sierra_class(sierra_hash_bytes!(b"sierra"), casm_hash_bytes!(b"casm"));
+        db.insert_state_update(header.number, &state_update)
+            .unwrap();
+
+        let expected = BlockStats {
+            block_number: BlockNumber::GENESIS,
+            transactions: TransactionCounts {
+                declare: 1,
+                invoke: 2,
+                l1_handler: 1,
+                ..Default::default()
+            },
+            events: 3,
+            total_fee_wei: Fee(Felt::from_u64(11)),
+            total_fee_fri: Fee(Felt::from_u64(100)),
+            state_diff: StateDiffCounts {
+                storage_diffs: 2,
+                nonce_updates: 1,
+                declared_classes: 2,
+                deployed_contracts: 1,
+                replaced_classes: 1,
+            },
+        };
+        let stats = db
+            .block_stats(BlockNumber::GENESIS, BlockNumber::GENESIS)
+            .unwrap();
+        assert_eq!(stats, vec![expected]);
+
+        db.purge_block(BlockNumber::GENESIS).unwrap();
+        let stats = db
+            .block_stats(BlockNumber::GENESIS, BlockNumber::GENESIS)
+            .unwrap();
+        assert!(stats.is_empty());
+    }
+}
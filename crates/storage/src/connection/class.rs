@@ -4,6 +4,23 @@ use pathfinder_common::{BlockNumber, CasmHash, ClassCommitmentLeafHash, ClassHas
 use crate::prelude::*;
 use crate::BlockId;
 
+const METRIC_COMPILED_CLASS_CACHE_HITS: &str = "compiled_class_cache_hits_total";
+const METRIC_COMPILED_CLASS_CACHE_MISSES: &str = "compiled_class_cache_misses_total";
+
+/// The number of classes kept in the compiled class cache. The oldest entries
+/// are evicted once it's full.
+const MAX_COMPILED_CLASSES: usize = 1024;
+
+/// A class declared within a block range, see
+/// [`Transaction::declared_classes_in_range`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeclaredClass {
+    pub block_number: BlockNumber,
+    pub class_hash: ClassHash,
+    /// The compiled class hash, `None` for Cairo 0 classes.
+    pub casm_hash: Option<CasmHash>,
+}
+
 impl Transaction<'_> {
     pub fn insert_sierra_class(
         &self,
@@ -474,6 +491,135 @@ impl Transaction<'_> {
         Ok(compiled_class_hash)
     }
 
+    /// Returns the classes declared in `from..=to`, ordered by block.
+    ///
+    /// Only whole blocks are returned. Once at least `limit` classes have been
+    /// collected no further blocks are read, and the number of the next block
+    /// which declares classes is returned alongside the page.
+    pub fn declared_classes_in_range(
+        &self,
+        from: BlockNumber,
+        to: BlockNumber,
+        limit: usize,
+    ) -> anyhow::Result<(Vec<DeclaredClass>, Option<BlockNumber>)> {
+        let mut stmt = self
+            .inner()
+            .prepare_cached(
+                r"SELECT
+                class_definitions.block_number,
+                class_definitions.hash,
+                casm_definitions.compiled_class_hash
+            FROM
+                class_definitions
+            LEFT OUTER JOIN
+                casm_definitions ON casm_definitions.hash = class_definitions.hash
+            WHERE
+                class_definitions.block_number BETWEEN ? AND ?
+            ORDER BY
+                class_definitions.block_number, class_definitions.hash",
+            )
+            .context("Preparing declared classes query")?;
+
+        let mut rows = stmt
+            .query_map(params![&from, &to], |row| {
+                Ok(DeclaredClass {
+                    block_number: row.get_block_number(0)?,
+                    class_hash: row.get_class_hash(1)?,
+                    casm_hash: row.get_optional_casm_hash(2)?,
+                })
+            })
+            .context("Querying declared classes")?;
+
+        let mut classes: Vec<DeclaredClass> = Vec::new();
+
+        while let Some(class) = rows
+            .next()
+            .transpose()
+            .context("Iterating over declared classes")?
+        {
+            if classes.len() >= limit
+                && classes
+                    .last()
+                    .is_some_and(|last| last.block_number != class.block_number)
+            {
+                return Ok((classes, Some(class.block_number)));
+            }
+
+            classes.push(class);
+        }
+
+        Ok((classes, None))
+    }
+
+    /// Returns the uncompressed CASM compiled from the Sierra class, if it has
+    /// been cached using [Self::insert_compiled_class].
+    ///
+    /// Lookups are counted by the `compiled_class_cache_hits_total` and
+    /// `compiled_class_cache_misses_total` metrics.
+    pub fn compiled_class(&self, sierra_hash: SierraHash) -> anyhow::Result<Option<Vec<u8>>> {
+        let mut stmt = self
+            .inner()
+            .prepare_cached("SELECT definition FROM compiled_classes WHERE sierra_hash = ?")?;
+        let definition = stmt
+            .query_row(params![&sierra_hash], |row| {
+                row.get_blob(0).map(|x| x.to_vec())
+            })
+            .optional()
+            .context("Querying for cached compiled class")?;
+
+        let Some(definition) = definition else {
+            metrics::increment_counter!(METRIC_COMPILED_CLASS_CACHE_MISSES);
+            return Ok(None);
+        };
+        metrics::increment_counter!(METRIC_COMPILED_CLASS_CACHE_HITS);
+
+        let definition = zstd::decode_all(definition.as_slice())
+            .context("Decompressing cached compiled class")?;
+
+        Ok(Some(definition))
+    }
+
+    /// Caches the zstd compressed CASM compiled from the Sierra class. The
+    /// class need not be declared.
+    ///
+    /// At most [MAX_COMPILED_CLASSES] classes are kept, evicting the ones which
+    /// were inserted first.
+    pub fn insert_compiled_class(
+        &self,
+        sierra_hash: SierraHash,
+        casm_definition: &[u8],
+    ) -> anyhow::Result<()> {
+        let mut compressor = zstd::bulk::Compressor::new(10).context("Creating zstd compressor")?;
+        let casm_definition = compressor
+            .compress(casm_definition)
+            .context("Compressing casm definition")?;
+
+        self.inner()
+            .execute(
+                "INSERT OR IGNORE INTO compiled_classes (sierra_hash, definition) VALUES (?, ?)",
+                params![&sierra_hash, &casm_definition],
+            )
+            .context("Inserting cached compiled class")?;
+
+        self.evict_compiled_classes(MAX_COMPILED_CLASSES)
+    }
+
+    /// Deletes all but the `keep` most recently inserted compiled classes.
+    fn evict_compiled_classes(&self, keep: usize) -> anyhow::Result<()> {
+        let keep = i64::try_from(keep)?;
+        // The rowid increases with every insert, so it orders the entries by age.
+        self.inner()
+            .execute(
+                r"DELETE FROM compiled_classes WHERE rowid <= (
+                    SELECT rowid FROM compiled_classes ORDER BY rowid DESC LIMIT 1 OFFSET ?
+                )",
+                params![&keep],
+            )
+            .context("Evicting cached compiled classes")?;
+
+        Ok(())
+    }
+
     pub fn is_sierra(&self, class_hash: ClassHash) -> anyhow::Result<Option<bool>> {
         let mut stmt = self.inner().prepare_cached(
             "SELECT EXISTS(SELECT 1 FROM casm_definitions WHERE casm_definitions.hash = ?)",
@@ -618,6 +764,58 @@ mod tests {
         assert_eq!(definition, sierra_definition);
     }
 
+    #[test]
+    fn compiled_class_cache() {
+        let mut connection = crate::StorageBuilder::in_memory()
+            .unwrap()
+            .connection()
+            .unwrap();
+        let tx = connection.transaction().unwrap();
+
+        let sierra_hash = sierra_hash_bytes!(b"sierra hash");
+        let casm_definition = b"compiled sierra program";
+
+        assert_eq!(tx.compiled_class(sierra_hash).unwrap(), None);
+
+        tx.insert_compiled_class(sierra_hash, casm_definition)
+            .unwrap();
+        // Inserting the same class again is a no-op.
+        tx.insert_compiled_class(sierra_hash, casm_definition)
+            .unwrap();
+
+        assert_eq!(
+            tx.compiled_class(sierra_hash).unwrap().unwrap(),
+            casm_definition
+        );
+        assert_eq!(
+            tx.compiled_class(sierra_hash_bytes!(b"other")).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn compiled_class_cache_evicts_oldest_entries() {
+        let mut connection = crate::StorageBuilder::in_memory()
+            .unwrap()
+            .connection()
+            .unwrap();
+        let tx = connection.transaction().unwrap();
+
+        let first = sierra_hash_bytes!(b"first");
+        let second = sierra_hash_bytes!(b"second");
+        let third = sierra_hash_bytes!(b"third");
+        for sierra_hash in [first, second, third] {
+            tx.insert_compiled_class(sierra_hash, b"compiled sierra program")
+                .unwrap();
+        }
+
+        tx.evict_compiled_classes(2).unwrap();
+
+        assert_eq!(tx.compiled_class(first).unwrap(), None);
+        assert!(tx.compiled_class(second).unwrap().is_some());
+        assert!(tx.compiled_class(third).unwrap().is_some());
+    }
+
     #[test]
     fn compiled_class_leaves() {
         let mut connection = crate::StorageBuilder::in_memory()
@@ -654,4 +852,77 @@ mod tests {
             .unwrap();
         assert!(result.is_none());
     }
+
+    #[test]
+    fn declared_classes_in_range() {
+        use pathfinder_common::{BlockHash, BlockHeader, StateUpdate};
+
+        let mut connection = crate::StorageBuilder::in_memory()
+            .unwrap()
+            .connection()
+            .unwrap();
+        let tx = connection.transaction().unwrap();
+
+        let cairo0 = class_hash_bytes!(b"cairo 0");
+        let cairo1 = class_hash_bytes!(b"cairo 1");
+        let sierra = sierra_hash_bytes!(b"sierra");
+        let casm = casm_hash_bytes!(b"casm");
+
+        tx.insert_cairo_class(cairo0, b"cairo 0 definition")
+            .unwrap();
+        tx.insert_cairo_class(cairo1, b"cairo 1 definition")
+            .unwrap();
+        tx.insert_sierra_class(&sierra, b"sierra definition", &casm, b"casm definition")
+            .unwrap();
+
+        let state_updates = [
+            StateUpdate::default().with_declared_cairo_class(cairo0),
+            StateUpdate::default(),
+            StateUpdate::default()
+                .with_declared_cairo_class(cairo1)
+                .with_declared_sierra_class(sierra, casm),
+        ];
+        for (i, state_update) in state_updates.iter().enumerate() {
+            let header = BlockHeader::builder()
+                .number(BlockNumber::new_or_panic(i as u64))
+                .finalize_with_hash(BlockHash(Felt::from_u64(i as u64)));
+            tx.insert_block_header(&header).unwrap();
+            tx.insert_state_update(header.number, state_update).unwrap();
+        }
+
+        let block0 = DeclaredClass {
+            block_number: BlockNumber::GENESIS,
+            class_hash: cairo0,
+            casm_hash: None,
+        };
+        let mut block2 = [
+            DeclaredClass {
+                block_number: BlockNumber::GENESIS + 2,
+                class_hash: cairo1,
+                casm_hash: None,
+            },
+            DeclaredClass {
+                block_number: BlockNumber::GENESIS + 2,
+                class_hash: ClassHash(sierra.0),
+                casm_hash: Some(casm),
+            },
+        ];
+        block2.sort_by_key(|class| class.class_hash);
+
+        let result = tx
+            .declared_classes_in_range(BlockNumber::GENESIS, BlockNumber::MAX, 10)
+            .unwrap();
+        assert_eq!(result, ([vec![block0], block2.to_vec()].concat(), None));
+
+        let result = tx
+            .declared_classes_in_range(BlockNumber::GENESIS, BlockNumber::MAX, 1)
+            .unwrap();
+        assert_eq!(result, (vec![block0], Some(BlockNumber::GENESIS + 2)));
+
+        // A block is never split across pages.
+        let result = tx
+            .declared_classes_in_range(BlockNumber::GENESIS + 1, BlockNumber::MAX, 1)
+            .unwrap();
+        assert_eq!(result, (block2.to_vec(), None));
+    }
 }
@@ -1,5 +1,6 @@
 use std::num::NonZeroUsize;
 
+use anyhow::Context;
 use pathfinder_common::event::Event;
 use pathfinder_common::{
     BlockHash,
@@ -73,14 +74,25 @@ impl Transaction<'_> {
              DO UPDATE SET bloom=excluded.bloom",
         )?;
 
+        let mut index_stmt = self.inner().prepare_cached(
+            "INSERT OR IGNORE INTO contract_event_blocks (contract_address, block_number) VALUES \
+             (?, ?)",
+        )?;
+
         let mut bloom = BloomFilter::new();
+        let mut contracts = std::collections::HashSet::new();
         for event in events {
             bloom.set_keys(&event.keys);
             bloom.set_address(&event.from_address);
+            contracts.insert(event.from_address);
         }
 
         stmt.execute(params![&block_number, &bloom.to_compressed_bytes()])?;
 
+        for contract in contracts {
+            index_stmt.execute(params![&contract, &block_number])?;
+        }
+
         Ok(())
     }
 
@@ -170,6 +182,10 @@ impl Transaction<'_> {
         let to_block = filter.to_block.unwrap_or(BlockNumber::MAX);
         let key_filter_is_empty = filter.keys.iter().flatten().count() == 0;
 
+        if filter.contract_address.is_some() {
+            self.ensure_migrated(crate::schema::CONTRACT_EVENT_BLOCKS, from_block..=to_block)?;
+        }
+
         let mut emitted_events = Vec::new();
         let mut bloom_filters_loaded: usize = 0;
         let mut blocks_scanned: usize = 0;
@@ -183,13 +199,22 @@ impl Transaction<'_> {
         }
 
         let result = loop {
+            // Skip straight to the next block the contract emitted events in.
+            if let Some(contract_address) = filter.contract_address {
+                match self.next_block_with_events_from(contract_address, block_number)? {
+                    Some(next) => block_number = next,
+                    None => break ScanResult::Done,
+                }
+            }
+
             // Stop if we're past the last block.
             if block_number > to_block {
                 break ScanResult::Done;
             }
 
-            // Check bloom filter
-            if !key_filter_is_empty || filter.contract_address.is_some() {
+            // Check bloom filter. The contract address was already checked
+            // using the index.
+            if !key_filter_is_empty {
                 let bloom = self.load_bloom(reorg_counter, block_number)?;
                 match bloom {
                     Filter::Missing => {}
@@ -288,6 +313,28 @@ impl Transaction<'_> {
         }
     }
 
+    /// Returns the first block from `from_block` onwards which contains
+    /// events emitted by `contract_address`.
+    fn next_block_with_events_from(
+        &self,
+        contract_address: ContractAddress,
+        from_block: BlockNumber,
+    ) -> anyhow::Result<Option<BlockNumber>> {
+        let mut stmt = self.inner().prepare_cached(
+            r"SELECT block_number
+            FROM contract_event_blocks
+            WHERE contract_address = ? AND block_number >= ?
+            ORDER BY block_number
+            LIMIT 1",
+        )?;
+
+        stmt.query_row(params![&contract_address, &from_block], |row| {
+            row.get_block_number(0)
+        })
+        .optional()
+        .context("Querying next block with contract events")
+    }
+
     fn scan_block_into(
         &self,
         block_number: BlockNumber,
@@ -771,6 +818,38 @@ mod tests {
         );
     }
 
+    #[test]
+    fn contract_filter_only_scans_blocks_with_its_events() {
+        let (storage, test_data) = test_utils::setup_test_storage();
+        let emitted_events = test_data.events;
+        let mut connection = storage.connection().unwrap();
+        let tx = connection.transaction().unwrap();
+
+        let expected_event = &emitted_events[33];
+
+        let filter = EventFilter {
+            from_block: None,
+            to_block: None,
+            contract_address: Some(expected_event.from_address),
+            keys: vec![],
+            page_size: test_utils::NUM_EVENTS,
+            offset: 0,
+        };
+
+        // The event is in block 3, but the blocks before it don't count
+        // against the scan limit as they contain no events from the contract.
+        let events = tx
+            .events(&filter, 1.try_into().unwrap(), *MAX_BLOOM_FILTERS_TO_LOAD)
+            .unwrap();
+        assert_eq!(
+            events,
+            PageOfEvents {
+                events: vec![expected_event.clone()],
+                continuation_token: None,
+            }
+        );
+    }
+
     #[test]
     fn get_events_by_key() {
         let (storage, test_data) = test_utils::setup_test_storage();
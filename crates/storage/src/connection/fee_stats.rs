@@ -0,0 +1,331 @@
+use anyhow::Context;
+use pathfinder_common::receipt::Receipt;
+use pathfinder_common::transaction::{Transaction as StarknetTransaction, TransactionKind};
+use pathfinder_common::{BlockNumber, Fee, GasPrice, TransactionVersion};
+
+use crate::prelude::*;
+
+/// Fee percentiles are stored in steps of this many percent, i.e. for the 0th,
+/// 5th, ..., 100th percentile.
+pub const FEE_PERCENTILE_STEP: usize = 5;
+
+/// The gas prices of a block, and the distribution of the actual fees paid by
+/// its transactions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockFeeStats {
+    pub block_number: BlockNumber,
+    pub eth_l1_gas_price: GasPrice,
+    pub strk_l1_gas_price: GasPrice,
+    pub eth_l1_data_gas_price: GasPrice,
+    pub strk_l1_data_gas_price: GasPrice,
+    /// The actual fees of the transactions paying in WEI at every
+    /// [FEE_PERCENTILE_STEP], or empty if there are none.
+    pub fee_percentiles_wei: Vec<u128>,
+    /// The actual fees of the transactions paying in FRI at every
+    /// [FEE_PERCENTILE_STEP], or empty if there are none.
+    pub fee_percentiles_fri: Vec<u128>,
+}
+
+impl Transaction<'_> {
+    /// Records the fee statistics of a block. Its gas prices are read from its
+    /// header when queried.
+    pub(super) fn insert_block_fee_stats(
+        &self,
+        block_number: BlockNumber,
+        transactions: &[(StarknetTransaction, Receipt)],
+    ) -> anyhow::Result<()> {
+        let (wei, fri) =
+            encoded_fee_percentiles(transactions.iter().map(|(transaction, receipt)| {
+                (
+                    transaction.variant.kind(),
+                    transaction.version(),
+                    receipt.actual_fee,
+                )
+            }))?;
+
+        let mut stmt = self
+            .inner()
+            .prepare_cached(INSERT_BLOCK_FEE_STATS)
+            .context("Preparing insert fee stats statement")?;
+        stmt.execute(named_params![
+            ":block_number": &block_number,
+            ":fee_percentiles_wei": &wei,
+            ":fee_percentiles_fri": &fri,
+        ])
+        .context("Inserting fee stats")?;
+
+        Ok(())
+    }
+
+    /// Returns the fee statistics of the blocks in `from..=to`, oldest first,
+    /// along with the gas prices from their headers.
+    pub fn block_fee_stats(
+        &self,
+        from: BlockNumber,
+        to: BlockNumber,
+    ) -> anyhow::Result<Vec<BlockFeeStats>> {
+        let mut stmt = self
+            .inner()
+            .prepare_cached(
+                r"SELECT
+                    block_fee_stats.block_number,
+                    block_headers.number,
+                    block_headers.eth_l1_gas_price,
+                    block_headers.strk_l1_gas_price,
+                    block_headers.eth_l1_data_gas_price,
+                    block_headers.strk_l1_data_gas_price,
+                    block_fee_stats.fee_percentiles_wei,
+                    block_fee_stats.fee_percentiles_fri
+                FROM block_fee_stats
+                LEFT JOIN block_headers ON block_headers.number = block_fee_stats.block_number
+                WHERE block_fee_stats.block_number BETWEEN ? AND ?
+                ORDER BY block_fee_stats.block_number",
+            )
+            .context("Preparing fee stats query")?;
+        let mut rows = stmt
+            .query(params![&from, &to])
+            .context("Querying fee stats")?;
+
+        let mut stats = Vec::new();
+        while let Some(row) = rows.next().context("Iterating over rows")? {
+            let block_number = row.get_block_number(0)?;
+            anyhow::ensure!(
+                row.get_optional_block_number(1)?.is_some(),
+                "Header of block {block_number} is missing"
+            );
+
+            stats.push(BlockFeeStats {
+                block_number,
+                eth_l1_gas_price: row.get_gas_price(2)?,
+                strk_l1_gas_price: row.get_optional_gas_price(3)?.unwrap_or(GasPrice::ZERO),
+                eth_l1_data_gas_price: row.get_optional_gas_price(4)?.unwrap_or(GasPrice::ZERO),
+                strk_l1_data_gas_price: row.get_optional_gas_price(5)?.unwrap_or(GasPrice::ZERO),
+                fee_percentiles_wei: decode_fee_percentiles(row.get_blob(6)?)?,
+                fee_percentiles_fri: decode_fee_percentiles(row.get_blob(7)?)?,
+            });
+        }
+
+        Ok(stats)
+    }
+}
+
+const INSERT_BLOCK_FEE_STATS: &str = r"INSERT OR REPLACE INTO block_fee_stats (
+        block_number,
+        fee_percentiles_wei,
+        fee_percentiles_fri
+    ) VALUES (
+        :block_number,
+        :fee_percentiles_wei,
+        :fee_percentiles_fri
+    )";
+
+/// Splits the actual fees of a block's transactions by the unit they are paid
+/// in, returning the fees paid in WEI and in FRI. Used for both the fee
+/// statistics and the [block aggregates](crate::BlockStats), so that the two
+/// agree.
+///
+/// L1 handler and deploy transactions are left out, as they are not paid for
+/// by an account.
+pub(crate) fn account_fees(
+    fees: impl IntoIterator<Item = (TransactionKind, TransactionVersion, Fee)>,
+) -> (Vec<Fee>, Vec<Fee>) {
+    let mut wei = Vec::new();
+    let mut fri = Vec::new();
+    for (kind, version, fee) in fees {
+        if matches!(kind, TransactionKind::L1Handler | TransactionKind::Deploy) {
+            continue;
+        }
+
+        // Fees are paid in FRI starting from V3 transactions.
+        match version {
+            TransactionVersion::ZERO | TransactionVersion::ONE | TransactionVersion::TWO => {
+                wei.push(fee)
+            }
+            _ => fri.push(fee),
+        }
+    }
+
+    (wei, fri)
+}
+
+/// Returns the encoded percentiles of the [account_fees] paid in WEI and FRI.
+pub(crate) fn encoded_fee_percentiles(
+    fees: impl Iterator<Item = (TransactionKind, TransactionVersion, Fee)>,
+) -> anyhow::Result<(Vec<u8>, Vec<u8>)> {
+    let (wei, fri) = account_fees(fees);
+    let encode = |fees: Vec<Fee>| {
+        let fees = fees.into_iter().map(fee_to_u128).collect();
+        bincode::encode_to_vec(fee_percentiles(fees), bincode::config::standard())
+            .context("Encoding fee percentiles")
+    };
+
+    Ok((encode(wei)?, encode(fri)?))
+}
+
+fn decode_fee_percentiles(blob: &[u8]) -> anyhow::Result<Vec<u128>> {
+    let (percentiles, _) = bincode::decode_from_slice(blob, bincode::config::standard())
+        .context("Decoding fee percentiles")?;
+    Ok(percentiles)
+}
+
+/// Returns the nearest-rank percentiles of `fees` at every
+/// [FEE_PERCENTILE_STEP].
+fn fee_percentiles(mut fees: Vec<u128>) -> Vec<u128> {
+    if fees.is_empty() {
+        return Vec::new();
+    }
+    fees.sort_unstable();
+
+    (0..=100)
+        .step_by(FEE_PERCENTILE_STEP)
+        .map(|percentile| fees[(percentile * (fees.len() - 1) + 50) / 100])
+        .collect()
+}
+
+/// Fees are far below 2^128 in practice, larger ones saturate.
+fn fee_to_u128(fee: Fee) -> u128 {
+    let bytes = fee.0.to_be_bytes();
+    if bytes[..16].iter().any(|b| *b != 0) {
+        return u128::MAX;
+    }
+    u128::from_be_bytes(bytes[16..].try_into().expect("16 bytes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::macro_prelude::*;
+    use pathfinder_common::transaction::{
+        InvokeTransactionV0,
+        InvokeTransactionV3,
+        TransactionVariant,
+    };
+    use pathfinder_common::BlockHeader;
+    use pathfinder_crypto::Felt;
+
+    use super::*;
+
+    #[test]
+    fn percentiles() {
+        assert_eq!(fee_percentiles(vec![]), Vec::<u128>::new());
+        assert_eq!(fee_percentiles(vec![7]), vec![7; 21]);
+
+        let percentiles = fee_percentiles((1..=101).rev().collect());
+        assert_eq!(percentiles.len(), 21);
+        assert_eq!(percentiles[0], 1);
+        assert_eq!(percentiles[10], 51);
+        assert_eq!(percentiles[20], 101);
+    }
+
+    #[test]
+    fn fees_are_split_by_unit() {
+        let (wei, fri) = encoded_fee_percentiles(
+            [
+                (
+                    TransactionKind::Invoke,
+                    TransactionVersion::ONE,
+                    Fee(Felt::from_u64(0x10)),
+                ),
+                (
+                    TransactionKind::Invoke,
+                    TransactionVersion::THREE,
+                    Fee(Felt::from_u64(0x20)),
+                ),
+                (
+                    TransactionKind::Invoke,
+                    TransactionVersion::THREE,
+                    Fee(Felt::from_u64(0x30)),
+                ),
+                (
+                    TransactionKind::L1Handler,
+                    TransactionVersion::ZERO,
+                    Fee::ZERO,
+                ),
+                (TransactionKind::Deploy, TransactionVersion::ZERO, Fee::ZERO),
+            ]
+            .into_iter(),
+        )
+        .unwrap();
+
+        let wei = decode_fee_percentiles(&wei).unwrap();
+        let fri = decode_fee_percentiles(&fri).unwrap();
+        assert_eq!(wei, vec![0x10; 21]);
+        assert_eq!(fri[0], 0x20);
+        assert_eq!(fri[20], 0x30);
+    }
+
+    #[test]
+    fn stats_are_recorded_with_block_data() {
+        let storage = crate::StorageBuilder::in_memory().unwrap();
+        let mut db = storage.connection().unwrap();
+        let db = db.transaction().unwrap();
+
+        let header = BlockHeader::builder()
+            .number(BlockNumber::GENESIS)
+            .eth_l1_gas_price(GasPrice(10))
+            .strk_l1_data_gas_price(GasPrice(20))
+            .finalize_with_hash(block_hash_bytes!(b"genesis"));
+        db.insert_block_header(&header).unwrap();
+
+        let transactions = vec![
+            (
+                StarknetTransaction {
+                    hash: transaction_hash_bytes!(b"v0"),
+                    variant: TransactionVariant::InvokeV0(InvokeTransactionV0::default()),
+                },
+                Receipt {
+                    actual_fee: Fee(Felt::from_u64(100)),
+                    transaction_hash: transaction_hash_bytes!(b"v0"),
+                    ..Default::default()
+                },
+            ),
+            (
+                StarknetTransaction {
+                    hash: transaction_hash_bytes!(b"v3"),
+                    variant: TransactionVariant::InvokeV3(InvokeTransactionV3::default()),
+                },
+                Receipt {
+                    actual_fee: Fee(Felt::from_u64(200)),
+                    transaction_hash: transaction_hash_bytes!(b"v3"),
+                    ..Default::default()
+                },
+            ),
+        ];
+        db.insert_transaction_data(header.number, &transactions, None)
+            .unwrap();
+
+        let stats = db
+            .block_fee_stats(BlockNumber::GENESIS, BlockNumber::GENESIS)
+            .unwrap();
+        assert_eq!(
+            stats,
+            vec![BlockFeeStats {
+                block_number: BlockNumber::GENESIS,
+                eth_l1_gas_price: GasPrice(10),
+                strk_l1_gas_price: GasPrice::ZERO,
+                eth_l1_data_gas_price: GasPrice::ZERO,
+                strk_l1_data_gas_price: GasPrice(20),
+                fee_percentiles_wei: vec![100; 21],
+                fee_percentiles_fri: vec![200; 21],
+            }]
+        );
+
+        db.purge_block(BlockNumber::GENESIS).unwrap();
+        let stats = db
+            .block_fee_stats(BlockNumber::GENESIS, BlockNumber::GENESIS)
+            .unwrap();
+        assert!(stats.is_empty());
+    }
+
+    #[test]
+    fn missing_header_is_an_error() {
+        let storage = crate::StorageBuilder::in_memory().unwrap();
+        let mut db = storage.connection().unwrap();
+        let db = db.transaction().unwrap();
+
+        db.insert_transaction_data(BlockNumber::GENESIS, &[], None)
+            .unwrap();
+
+        db.block_fee_stats(BlockNumber::GENESIS, BlockNumber::GENESIS)
+            .unwrap_err();
+    }
+}
@@ -0,0 +1,174 @@
+use anyhow::Context;
+use pathfinder_common::L1ToL2MessageLog;
+use primitive_types::H256;
+
+use crate::prelude::*;
+
+/// The L1 transactions which sent and cancelled an L1 -> L2 message, as seen
+/// in the logs of the Starknet core contract.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct L1ToL2Message {
+    /// The L1 transaction hash and block number which sent the message.
+    pub sent: Option<(H256, u64)>,
+    /// The L1 transaction hash and block number which cancelled the message.
+    pub cancelled: Option<(H256, u64)>,
+}
+
+impl Transaction<'_> {
+    /// Records the L1 transaction which sent an L1 -> L2 message, or forgets it
+    /// if its log was removed by an L1 reorg.
+    pub fn upsert_l1_to_l2_message_sent(&self, log: &L1ToL2MessageLog) -> anyhow::Result<()> {
+        self.upsert_l1_to_l2_message_log("sent", log)
+    }
+
+    /// Records the L1 transaction which cancelled an L1 -> L2 message, or
+    /// forgets it if its log was removed by an L1 reorg.
+    pub fn upsert_l1_to_l2_message_cancelled(&self, log: &L1ToL2MessageLog) -> anyhow::Result<()> {
+        self.upsert_l1_to_l2_message_log("cancelled", log)
+    }
+
+    fn upsert_l1_to_l2_message_log(
+        &self,
+        column: &str,
+        log: &L1ToL2MessageLog,
+    ) -> anyhow::Result<()> {
+        let block_number = i64::try_from(log.l1_block_number)?;
+
+        if !log.removed {
+            self.inner()
+                .execute(
+                    &format!(
+                        r"INSERT INTO l1_to_l2_messages (message_hash, {column}_tx_hash, {column}_block_number)
+                        VALUES (?, ?, ?)
+                        ON CONFLICT(message_hash) DO UPDATE SET
+                            {column}_tx_hash = excluded.{column}_tx_hash,
+                            {column}_block_number = excluded.{column}_block_number"
+                    ),
+                    params![
+                        &log.message_hash.as_bytes(),
+                        &log.l1_tx_hash.as_bytes(),
+                        &block_number
+                    ],
+                )
+                .context("Inserting L1 to L2 message")?;
+
+            return Ok(());
+        }
+
+        self.inner()
+            .execute(
+                &format!(
+                    r"UPDATE l1_to_l2_messages
+                    SET {column}_tx_hash = NULL, {column}_block_number = NULL
+                    WHERE message_hash = ? AND {column}_tx_hash = ?"
+                ),
+                params![&log.message_hash.as_bytes(), &log.l1_tx_hash.as_bytes()],
+            )
+            .context("Removing L1 to L2 message log")?;
+        self.inner()
+            .execute(
+                r"DELETE FROM l1_to_l2_messages
+                WHERE message_hash = ? AND sent_tx_hash IS NULL AND cancelled_tx_hash IS NULL",
+                params![&log.message_hash.as_bytes()],
+            )
+            .context("Deleting L1 to L2 message")?;
+
+        Ok(())
+    }
+
+    /// Returns the L1 transactions seen for the L1 -> L2 message with
+    /// `message_hash`, or `None` if none have been seen.
+    pub fn l1_to_l2_message(&self, message_hash: H256) -> anyhow::Result<Option<L1ToL2Message>> {
+        self.inner()
+            .query_row(
+                r"SELECT sent_tx_hash, sent_block_number, cancelled_tx_hash, cancelled_block_number
+                FROM l1_to_l2_messages
+                WHERE message_hash = ?",
+                params![&message_hash.as_bytes()],
+                |row| {
+                    let log = |hash: Option<&[u8]>, block_number: Option<i64>| {
+                        hash.zip(block_number).map(|(hash, block_number)| {
+                            (H256::from_slice(hash), block_number as u64)
+                        })
+                    };
+                    let sent = log(row.get_optional_blob(0)?, row.get_optional_i64(1)?);
+                    let cancelled = log(row.get_optional_blob(2)?, row.get_optional_i64(3)?);
+
+                    Ok(L1ToL2Message { sent, cancelled })
+                },
+            )
+            .optional()
+            .map_err(|e| e.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log(message: u8, tx: u8, removed: bool) -> L1ToL2MessageLog {
+        L1ToL2MessageLog {
+            message_hash: H256::repeat_byte(message),
+            l1_tx_hash: H256::repeat_byte(tx),
+            l1_block_number: tx.into(),
+            removed,
+        }
+    }
+
+    #[test]
+    fn sent_and_cancelled() {
+        let storage = crate::StorageBuilder::in_memory().unwrap();
+        let mut connection = storage.connection().unwrap();
+        let tx = connection.transaction().unwrap();
+
+        let message = H256::repeat_byte(1);
+        assert_eq!(tx.l1_to_l2_message(message).unwrap(), None);
+
+        tx.upsert_l1_to_l2_message_sent(&log(1, 10, false)).unwrap();
+        assert_eq!(
+            tx.l1_to_l2_message(message).unwrap(),
+            Some(L1ToL2Message {
+                sent: Some((H256::repeat_byte(10), 10)),
+                cancelled: None,
+            })
+        );
+
+        tx.upsert_l1_to_l2_message_cancelled(&log(1, 20, false))
+            .unwrap();
+        assert_eq!(
+            tx.l1_to_l2_message(message).unwrap(),
+            Some(L1ToL2Message {
+                sent: Some((H256::repeat_byte(10), 10)),
+                cancelled: Some((H256::repeat_byte(20), 20)),
+            })
+        );
+
+        // Other messages are not affected.
+        assert_eq!(tx.l1_to_l2_message(H256::repeat_byte(2)).unwrap(), None);
+    }
+
+    #[test]
+    fn removed_logs_are_forgotten() {
+        let storage = crate::StorageBuilder::in_memory().unwrap();
+        let mut connection = storage.connection().unwrap();
+        let tx = connection.transaction().unwrap();
+
+        let message = H256::repeat_byte(1);
+        tx.upsert_l1_to_l2_message_sent(&log(1, 10, false)).unwrap();
+        tx.upsert_l1_to_l2_message_cancelled(&log(1, 20, false))
+            .unwrap();
+
+        tx.upsert_l1_to_l2_message_cancelled(&log(1, 20, true))
+            .unwrap();
+        assert_eq!(
+            tx.l1_to_l2_message(message).unwrap(),
+            Some(L1ToL2Message {
+                sent: Some((H256::repeat_byte(10), 10)),
+                cancelled: None,
+            })
+        );
+
+        tx.upsert_l1_to_l2_message_sent(&log(1, 10, true)).unwrap();
+        assert_eq!(tx.l1_to_l2_message(message).unwrap(), None);
+    }
+}
@@ -1,4 +1,4 @@
-use pathfinder_common::BlockNumber;
+use pathfinder_common::{BlockHash, BlockNumber};
 
 use crate::prelude::*;
 
@@ -20,10 +20,94 @@ impl Transaction<'_> {
             })
             .map_err(|e| e.into())
     }
+
+    /// Records `head` as the last block stored by sync together with all of
+    /// its data. Must be called in the transaction storing or removing the
+    /// block.
+    pub fn update_sync_checkpoint(
+        &self,
+        head: Option<(BlockNumber, BlockHash)>,
+    ) -> anyhow::Result<()> {
+        self.inner().execute(
+            "UPDATE refs SET sync_checkpoint_number = ?, sync_checkpoint_hash = ? WHERE idx = 1",
+            params![&head.map(|(number, _)| number), &head.map(|(_, hash)| hash)],
+        )?;
+
+        Ok(())
+    }
+
+    /// The last block stored by sync together with all of its data, see
+    /// [Transaction::update_sync_checkpoint].
+    pub fn sync_checkpoint(&self) -> anyhow::Result<Option<(BlockNumber, BlockHash)>> {
+        // This table always contains exactly one row.
+        self.inner()
+            .query_row(
+                "SELECT sync_checkpoint_number, sync_checkpoint_hash FROM refs WHERE idx = 1",
+                [],
+                |row| {
+                    let number = row.get_optional_block_number(0)?;
+                    let hash = row.get_optional_felt(1)?.map(BlockHash);
+                    Ok(number.zip(hash))
+                },
+            )
+            .map_err(|e| e.into())
+    }
+
+    /// Records the oldest block whose L1 state update must be fetched by an
+    /// unfinished L1 state update backfill, or clears it once the backfill
+    /// has completed.
+    pub fn update_l1_backfill_target(&self, target: Option<BlockNumber>) -> anyhow::Result<()> {
+        self.inner().execute(
+            "UPDATE refs SET l1_backfill_target = ? WHERE idx = 1",
+            params![&target],
+        )?;
+
+        Ok(())
+    }
+
+    /// The target of an unfinished L1 state update backfill, see
+    /// [Transaction::update_l1_backfill_target].
+    pub fn l1_backfill_target(&self) -> anyhow::Result<Option<BlockNumber>> {
+        // This table always contains exactly one row.
+        self.inner()
+            .query_row(
+                "SELECT l1_backfill_target FROM refs WHERE idx = 1",
+                [],
+                |row| row.get_optional_block_number(0),
+            )
+            .map_err(|e| e.into())
+    }
+
+    /// Marks trie node indices as possibly reused, see
+    /// [Transaction::trie_generation].
+    pub(crate) fn increment_trie_generation(&self) -> anyhow::Result<()> {
+        self.inner().execute(
+            "UPDATE refs SET trie_generation = trie_generation + 1 WHERE idx = 1",
+            [],
+        )?;
+
+        Ok(())
+    }
+
+    /// Changes whenever deleting trie nodes may cause their indices to be
+    /// assigned to new nodes. Trie nodes read by index can be cached for as
+    /// long as this stays the same.
+    pub fn trie_generation(&self) -> anyhow::Result<u64> {
+        // This table always contains exactly one row.
+        self.inner()
+            .query_row(
+                "SELECT trie_generation FROM refs WHERE idx = 1",
+                [],
+                |row| row.get(0),
+            )
+            .map_err(|e| e.into())
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use pathfinder_common::macro_prelude::*;
+
     use super::*;
 
     #[test]
@@ -56,4 +140,54 @@ mod tests {
         let result = tx.l1_l2_pointer().unwrap();
         assert_eq!(result, None);
     }
+
+    #[test]
+    fn sync_checkpoint() {
+        let storage = crate::StorageBuilder::in_memory().unwrap();
+        let mut connection = storage.connection().unwrap();
+        let tx = connection.transaction().unwrap();
+
+        assert_eq!(tx.sync_checkpoint().unwrap(), None);
+
+        let checkpoint = (
+            BlockNumber::new_or_panic(10),
+            block_hash_bytes!(b"block 10"),
+        );
+        tx.update_sync_checkpoint(Some(checkpoint)).unwrap();
+        assert_eq!(tx.sync_checkpoint().unwrap(), Some(checkpoint));
+
+        tx.update_sync_checkpoint(None).unwrap();
+        assert_eq!(tx.sync_checkpoint().unwrap(), None);
+    }
+
+    #[test]
+    fn l1_backfill_target() {
+        let storage = crate::StorageBuilder::in_memory().unwrap();
+        let mut connection = storage.connection().unwrap();
+        let tx = connection.transaction().unwrap();
+
+        assert_eq!(tx.l1_backfill_target().unwrap(), None);
+
+        tx.update_l1_backfill_target(Some(BlockNumber::new_or_panic(10)))
+            .unwrap();
+        assert_eq!(
+            tx.l1_backfill_target().unwrap(),
+            Some(BlockNumber::new_or_panic(10))
+        );
+
+        tx.update_l1_backfill_target(None).unwrap();
+        assert_eq!(tx.l1_backfill_target().unwrap(), None);
+    }
+
+    #[test]
+    fn trie_generation() {
+        let storage = crate::StorageBuilder::in_memory().unwrap();
+        let mut connection = storage.connection().unwrap();
+        let tx = connection.transaction().unwrap();
+
+        assert_eq!(tx.trie_generation().unwrap(), 0);
+
+        tx.increment_trie_generation().unwrap();
+        assert_eq!(tx.trie_generation().unwrap(), 1);
+    }
 }
@@ -0,0 +1,118 @@
+use pathfinder_common::{BlockHash, BlockNumber};
+
+use crate::prelude::*;
+
+/// A reorg which rolled the chain back from `old_head` to `common_ancestor`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReorgLogEntry {
+    pub old_head_number: BlockNumber,
+    pub old_head_hash: BlockHash,
+    /// The latest block kept by the reorg, or `None` if genesis was rolled
+    /// back as well.
+    pub common_ancestor: Option<(BlockNumber, BlockHash)>,
+    /// Unix timestamp at which the reorg was rolled back.
+    pub detected_at: u64,
+}
+
+impl Transaction<'_> {
+    pub fn insert_reorg(&self, reorg: &ReorgLogEntry) -> anyhow::Result<()> {
+        self.inner().execute(
+            r"INSERT INTO reorgs (
+                    old_head_number,
+                    old_head_hash,
+                    common_ancestor_number,
+                    common_ancestor_hash,
+                    detected_at
+                ) VALUES (
+                    :old_head_number,
+                    :old_head_hash,
+                    :common_ancestor_number,
+                    :common_ancestor_hash,
+                    :detected_at
+                )",
+            named_params! {
+                ":old_head_number": &reorg.old_head_number,
+                ":old_head_hash": &reorg.old_head_hash,
+                ":common_ancestor_number": &reorg.common_ancestor.map(|(number, _)| number),
+                ":common_ancestor_hash": &reorg.common_ancestor.map(|(_, hash)| hash),
+                ":detected_at": &(reorg.detected_at as i64),
+            },
+        )?;
+
+        Ok(())
+    }
+
+    /// Returns the reorgs which rolled back any block in `from..=to`, oldest
+    /// first.
+    pub fn reorgs(&self, from: BlockNumber, to: BlockNumber) -> anyhow::Result<Vec<ReorgLogEntry>> {
+        let mut stmt = self.inner().prepare_cached(
+            r"SELECT old_head_number, old_head_hash, common_ancestor_number, common_ancestor_hash, detected_at
+            FROM reorgs
+            WHERE old_head_number >= ? AND COALESCE(common_ancestor_number + 1, 0) <= ?
+            ORDER BY id",
+        )?;
+
+        let reorgs = stmt
+            .query_map(params![&from, &to], |row| {
+                let old_head_number = row.get_block_number(0)?;
+                let old_head_hash = row.get_block_hash(1)?;
+                let common_ancestor_number = row.get_optional_block_number(2)?;
+                let common_ancestor_hash = row.get_optional_felt(3)?.map(BlockHash);
+                let detected_at = row.get_i64(4)? as u64;
+
+                Ok(ReorgLogEntry {
+                    old_head_number,
+                    old_head_hash,
+                    common_ancestor: common_ancestor_number.zip(common_ancestor_hash),
+                    detected_at,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(reorgs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::macro_prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn overlapping_range() {
+        let storage = crate::StorageBuilder::in_memory().unwrap();
+        let mut connection = storage.connection().unwrap();
+        let tx = connection.transaction().unwrap();
+
+        let genesis = ReorgLogEntry {
+            old_head_number: BlockNumber::new_or_panic(2),
+            old_head_hash: block_hash!("0x2"),
+            common_ancestor: None,
+            detected_at: 100,
+        };
+        let later = ReorgLogEntry {
+            old_head_number: BlockNumber::new_or_panic(20),
+            old_head_hash: block_hash!("0x20"),
+            common_ancestor: Some((BlockNumber::new_or_panic(10), block_hash!("0x10"))),
+            detected_at: 200,
+        };
+        tx.insert_reorg(&genesis).unwrap();
+        tx.insert_reorg(&later).unwrap();
+
+        let reorgs = |from, to| {
+            tx.reorgs(
+                BlockNumber::new_or_panic(from),
+                BlockNumber::new_or_panic(to),
+            )
+            .unwrap()
+        };
+
+        assert_eq!(reorgs(0, 100), vec![genesis, later]);
+        assert_eq!(reorgs(0, 2), vec![genesis]);
+        // The common ancestor itself was not rolled back.
+        assert_eq!(reorgs(3, 10), vec![]);
+        assert_eq!(reorgs(11, 11), vec![later]);
+        assert_eq!(reorgs(21, 100), vec![]);
+    }
+}
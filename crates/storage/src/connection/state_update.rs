@@ -24,8 +24,9 @@ use pathfinder_common::{
     StorageValue,
 };
 
+use crate::connection::StateDiffCounts;
 use crate::prelude::*;
-use crate::BlockId;
+use crate::{BlockId, HistoryPruneMode};
 
 type StorageUpdates = Vec<(StorageAddress, StorageValue)>;
 
@@ -257,9 +258,152 @@ impl Transaction<'_> {
                 .context("Inserting casm hash")?;
         }
 
+        let storage_diffs = contract_updates
+            .values()
+            .map(|update| update.storage.len())
+            .chain(
+                system_contract_updates
+                    .values()
+                    .map(|update| update.storage.len()),
+            )
+            .sum::<usize>();
+        let class_updates = |f: fn(&ContractClassUpdate) -> bool| {
+            contract_updates
+                .values()
+                .filter(|update| update.class.as_ref().is_some_and(f))
+                .count()
+        };
+        let state_diff_counts = StateDiffCounts {
+            storage_diffs: storage_diffs as u64,
+            nonce_updates: contract_updates
+                .values()
+                .filter(|update| update.nonce.is_some())
+                .count() as u64,
+            declared_classes: (declared_cairo_classes.len() + declared_sierra_classes.len()) as u64,
+            deployed_contracts: class_updates(|class| {
+                matches!(class, ContractClassUpdate::Deploy(_))
+            }) as u64,
+            replaced_classes: class_updates(|class| {
+                matches!(class, ContractClassUpdate::Replace(_))
+            }) as u64,
+        };
+        self.insert_block_state_diff_stats(block_number, &state_diff_counts)
+            .context("Inserting block stats")?;
+
+        if let HistoryPruneMode::Prune { num_blocks_kept } = self.history_prune_mode {
+            if let Some(horizon) = block_number.get().checked_sub(num_blocks_kept) {
+                self.prune_state_history(BlockNumber::new_or_panic(horizon))
+                    .context("Pruning state history")?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Deletes the storage, nonce and class hash updates which were superseded
+    /// by an update in block `horizon`.
+    ///
+    /// This keeps the state at `horizon` and all later blocks intact, while the
+    /// state of earlier blocks becomes incomplete. Running this for every new
+    /// horizon deletes the superseded history incrementally.
+    fn prune_state_history(&self, horizon: BlockNumber) -> anyhow::Result<()> {
+        self.inner()
+            .execute(
+                r"DELETE FROM storage_updates WHERE rowid IN (
+                    SELECT old.rowid FROM storage_updates AS new
+                    JOIN storage_updates AS old
+                        ON old.contract_address_id = new.contract_address_id
+                        AND old.storage_address_id = new.storage_address_id
+                        AND old.block_number < new.block_number
+                    WHERE new.block_number = ?
+                )",
+                params![&horizon],
+            )
+            .context("Pruning storage updates")?;
+        self.inner()
+            .execute(
+                r"DELETE FROM nonce_updates WHERE rowid IN (
+                    SELECT old.rowid FROM nonce_updates AS new
+                    JOIN nonce_updates AS old
+                        ON old.contract_address_id = new.contract_address_id
+                        AND old.block_number < new.block_number
+                    WHERE new.block_number = ?
+                )",
+                params![&horizon],
+            )
+            .context("Pruning nonce updates")?;
+        self.inner()
+            .execute(
+                r"DELETE FROM contract_updates WHERE rowid IN (
+                    SELECT old.rowid FROM contract_updates AS new
+                    JOIN contract_updates AS old
+                        ON old.contract_address = new.contract_address
+                        AND old.block_number < new.block_number
+                    WHERE new.block_number = ?
+                )",
+                params![&horizon],
+            )
+            .context("Pruning contract updates")?;
+
         Ok(())
     }
+}
 
+/// Deletes all storage, nonce and class hash updates which
+/// [Transaction::prune_state_history] would have deleted by now, had pruning
+/// been enabled while syncing the existing blocks.
+pub(crate) fn prune_state_history_before_horizon(
+    tx: &rusqlite::Transaction<'_>,
+    num_blocks_kept: u64,
+) -> anyhow::Result<()> {
+    let latest: Option<BlockNumber> = tx
+        .query_row("SELECT MAX(number) FROM canonical_blocks", [], |row| {
+            row.get_optional_block_number(0)
+        })
+        .context("Querying latest block number")?;
+    let Some(horizon) = latest.and_then(|latest| latest.get().checked_sub(num_blocks_kept)) else {
+        return Ok(());
+    };
+    let horizon = BlockNumber::new_or_panic(horizon);
+
+    tracing::info!(%horizon, "Pruning existing state history, this may take a while");
+
+    tx.execute(
+        r"DELETE FROM storage_updates WHERE block_number < ?1 AND EXISTS (
+            SELECT 1 FROM storage_updates AS newer
+            WHERE newer.contract_address_id = storage_updates.contract_address_id
+                AND newer.storage_address_id = storage_updates.storage_address_id
+                AND newer.block_number > storage_updates.block_number
+                AND newer.block_number <= ?1
+        )",
+        params![&horizon],
+    )
+    .context("Pruning storage updates")?;
+    tx.execute(
+        r"DELETE FROM nonce_updates WHERE block_number < ?1 AND EXISTS (
+            SELECT 1 FROM nonce_updates AS newer
+            WHERE newer.contract_address_id = nonce_updates.contract_address_id
+                AND newer.block_number > nonce_updates.block_number
+                AND newer.block_number <= ?1
+        )",
+        params![&horizon],
+    )
+    .context("Pruning nonce updates")?;
+    tx.execute(
+        r"DELETE FROM contract_updates WHERE block_number < ?1 AND EXISTS (
+            SELECT 1 FROM contract_updates AS newer
+            WHERE newer.contract_address = contract_updates.contract_address
+                AND newer.block_number > contract_updates.block_number
+                AND newer.block_number <= ?1
+        )",
+        params![&horizon],
+    )
+    .context("Pruning contract updates")?;
+
+    Ok(())
+}
+
+impl Transaction<'_> {
     fn block_details(
         &self,
         block: BlockId,
@@ -305,6 +449,8 @@ impl Transaction<'_> {
     }
 
     pub fn state_update(&self, block: BlockId) -> anyhow::Result<Option<StateUpdate>> {
+        self.ensure_state_not_pruned(block)?;
+
         let Some((block_number, block_hash, state_commitment, parent_state_commitment)) =
             self.block_details(block).context("Querying block header")?
         else {
@@ -596,6 +742,8 @@ impl Transaction<'_> {
         contract_address: ContractAddress,
         key: StorageAddress,
     ) -> anyhow::Result<Option<StorageValue>> {
+        self.ensure_state_not_pruned(block)?;
+
         match block {
             BlockId::Latest => {
                 let mut stmt = self.inner().prepare_cached(
@@ -649,11 +797,92 @@ impl Transaction<'_> {
         .map_err(|e| e.into())
     }
 
+    /// Returns every change to the storage slot within `from..=to`, oldest
+    /// first and at most `limit` entries.
+    ///
+    /// Fails with [StatePruned](crate::StatePruned) if the state of `from` has
+    /// been pruned, since changes before the horizon are incomplete.
+    pub fn storage_history(
+        &self,
+        contract_address: ContractAddress,
+        key: StorageAddress,
+        from: BlockNumber,
+        to: BlockNumber,
+        limit: usize,
+    ) -> anyhow::Result<Vec<(BlockNumber, StorageValue)>> {
+        self.ensure_state_not_pruned(from.into())?;
+
+        let mut stmt = self.inner().prepare_cached(
+            r"
+            SELECT block_number, storage_value
+            FROM storage_updates
+            JOIN contract_addresses ON contract_addresses.id = storage_updates.contract_address_id
+            JOIN storage_addresses ON storage_addresses.id = storage_updates.storage_address_id
+            WHERE contract_address = ? AND storage_address = ? AND block_number BETWEEN ? AND ?
+            ORDER BY block_number ASC LIMIT ?
+            ",
+        )?;
+
+        let limit = u64::try_from(limit).expect("ptr size is 64 bits");
+        let history = stmt
+            .query_map(
+                params![&contract_address, &key, &from, &to, &limit],
+                |row| {
+                    let block_number = row.get_block_number(0)?;
+                    let value = row.get_storage_value(1)?;
+                    Ok((block_number, value))
+                },
+            )
+            .context("Querying storage history")?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(history)
+    }
+
+    /// Returns every change to the contract's nonce within `from..=to`, oldest
+    /// first and at most `limit` entries.
+    ///
+    /// Fails with [StatePruned](crate::StatePruned) if the state of `from` has
+    /// been pruned, since changes before the horizon are incomplete.
+    pub fn nonce_history(
+        &self,
+        contract_address: ContractAddress,
+        from: BlockNumber,
+        to: BlockNumber,
+        limit: usize,
+    ) -> anyhow::Result<Vec<(BlockNumber, ContractNonce)>> {
+        self.ensure_state_not_pruned(from.into())?;
+
+        let mut stmt = self.inner().prepare_cached(
+            r"
+            SELECT block_number, nonce
+            FROM nonce_updates
+            JOIN contract_addresses ON contract_addresses.id = nonce_updates.contract_address_id
+            WHERE contract_address = ? AND block_number BETWEEN ? AND ?
+            ORDER BY block_number ASC LIMIT ?
+            ",
+        )?;
+
+        let limit = u64::try_from(limit).expect("ptr size is 64 bits");
+        let history = stmt
+            .query_map(params![&contract_address, &from, &to, &limit], |row| {
+                let block_number = row.get_block_number(0)?;
+                let nonce = row.get_contract_nonce(1)?;
+                Ok((block_number, nonce))
+            })
+            .context("Querying nonce history")?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(history)
+    }
+
     pub fn contract_exists(
         &self,
         contract_address: ContractAddress,
         block_id: BlockId,
     ) -> anyhow::Result<bool> {
+        self.ensure_state_not_pruned(block_id)?;
+
         match block_id {
             BlockId::Number(number) => {
                 let mut stmt = self.inner().prepare_cached(
@@ -695,6 +924,8 @@ impl Transaction<'_> {
         contract_address: ContractAddress,
         block_id: BlockId,
     ) -> anyhow::Result<Option<ContractNonce>> {
+        self.ensure_state_not_pruned(block_id)?;
+
         match block_id {
             BlockId::Latest => {
                 let mut stmt = self.inner().prepare_cached(
@@ -745,6 +976,8 @@ impl Transaction<'_> {
         block_id: BlockId,
         contract_address: ContractAddress,
     ) -> anyhow::Result<Option<ClassHash>> {
+        self.ensure_state_not_pruned(block_id)?;
+
         match block_id {
             BlockId::Latest => {
                 let mut stmt = self.inner().prepare_cached(
@@ -1522,5 +1755,240 @@ mod tests {
                 .unwrap();
             assert_eq!(by_number, None);
         }
+
+        #[test]
+        fn storage_history() {
+            let mut db = crate::StorageBuilder::in_memory()
+                .unwrap()
+                .connection()
+                .unwrap();
+            let tx = db.transaction().unwrap();
+
+            let contract = contract_address_bytes!(b"contract address");
+            let key = storage_address_bytes!(b"storage address");
+            let other_key = storage_address_bytes!(b"other storage address");
+
+            let mut header = BlockHeader::builder().finalize_with_hash(block_hash_bytes!(b"0"));
+            for i in 0..5u8 {
+                let mut state_update = StateUpdate::default().with_storage_update(
+                    contract,
+                    other_key,
+                    StorageValue(pathfinder_crypto::Felt::from_u64(i.into())),
+                );
+                // The slot changes only in even blocks.
+                if i % 2 == 0 {
+                    state_update = state_update.with_storage_update(
+                        contract,
+                        key,
+                        StorageValue(pathfinder_crypto::Felt::from_u64(i.into())),
+                    );
+                }
+
+                tx.insert_block_header(&header).unwrap();
+                tx.insert_state_update(header.number, &state_update)
+                    .unwrap();
+                header = header.child_builder().finalize_with_hash(BlockHash(
+                    pathfinder_crypto::Felt::from_u64(u64::from(i) + 1),
+                ));
+            }
+
+            let history = tx
+                .storage_history(
+                    contract,
+                    key,
+                    BlockNumber::new_or_panic(1),
+                    BlockNumber::new_or_panic(4),
+                    10,
+                )
+                .unwrap();
+            assert_eq!(
+                history,
+                vec![
+                    (BlockNumber::new_or_panic(2), storage_value!("0x2")),
+                    (BlockNumber::new_or_panic(4), storage_value!("0x4")),
+                ]
+            );
+
+            let limited = tx
+                .storage_history(contract, key, BlockNumber::GENESIS, BlockNumber::MAX, 2)
+                .unwrap();
+            assert_eq!(
+                limited,
+                vec![
+                    (BlockNumber::GENESIS, storage_value!("0x0")),
+                    (BlockNumber::new_or_panic(2), storage_value!("0x2")),
+                ]
+            );
+        }
+
+        #[test]
+        fn nonce_history() {
+            let mut db = crate::StorageBuilder::in_memory()
+                .unwrap()
+                .connection()
+                .unwrap();
+            let tx = db.transaction().unwrap();
+
+            let contract = contract_address_bytes!(b"contract address");
+            let other_contract = contract_address_bytes!(b"other contract address");
+
+            let mut header = BlockHeader::builder().finalize_with_hash(block_hash_bytes!(b"0"));
+            for i in 0..5u8 {
+                let mut state_update = StateUpdate::default().with_contract_nonce(
+                    other_contract,
+                    ContractNonce(pathfinder_crypto::Felt::from_u64(i.into())),
+                );
+                // The nonce changes only in even blocks.
+                if i % 2 == 0 {
+                    state_update = state_update.with_contract_nonce(
+                        contract,
+                        ContractNonce(pathfinder_crypto::Felt::from_u64(i.into())),
+                    );
+                }
+
+                tx.insert_block_header(&header).unwrap();
+                tx.insert_state_update(header.number, &state_update)
+                    .unwrap();
+                header = header.child_builder().finalize_with_hash(BlockHash(
+                    pathfinder_crypto::Felt::from_u64(u64::from(i) + 1),
+                ));
+            }
+
+            let history = tx
+                .nonce_history(
+                    contract,
+                    BlockNumber::new_or_panic(1),
+                    BlockNumber::new_or_panic(4),
+                    10,
+                )
+                .unwrap();
+            assert_eq!(
+                history,
+                vec![
+                    (BlockNumber::new_or_panic(2), contract_nonce!("0x2")),
+                    (BlockNumber::new_or_panic(4), contract_nonce!("0x4")),
+                ]
+            );
+
+            let limited = tx
+                .nonce_history(contract, BlockNumber::GENESIS, BlockNumber::MAX, 2)
+                .unwrap();
+            assert_eq!(
+                limited,
+                vec![
+                    (BlockNumber::GENESIS, contract_nonce!("0x0")),
+                    (BlockNumber::new_or_panic(2), contract_nonce!("0x2")),
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn history_pruning() {
+        let mut db = crate::StorageBuilder::in_memory_with_history_pruning(2)
+            .unwrap()
+            .connection()
+            .unwrap();
+        let tx = db.transaction().unwrap();
+
+        let contract = contract_address_bytes!(b"contract");
+        let key = storage_address_bytes!(b"key");
+        let untouched_key = storage_address_bytes!(b"untouched key");
+
+        let state_updates = [
+            StateUpdate::default()
+                .with_deployed_contract(contract, class_hash_bytes!(b"class"))
+                .with_storage_update(contract, key, storage_value_bytes!(b"value 0"))
+                .with_storage_update(contract, untouched_key, storage_value_bytes!(b"untouched")),
+            StateUpdate::default().with_contract_nonce(contract, contract_nonce_bytes!(b"nonce 1")),
+            StateUpdate::default().with_storage_update(
+                contract,
+                key,
+                storage_value_bytes!(b"value 2"),
+            ),
+            StateUpdate::default().with_contract_nonce(contract, contract_nonce_bytes!(b"nonce 3")),
+            StateUpdate::default().with_storage_update(
+                contract,
+                key,
+                storage_value_bytes!(b"value 4"),
+            ),
+        ];
+
+        let mut header = BlockHeader::builder().finalize_with_hash(block_hash!("0x0"));
+        for (i, state_update) in state_updates.iter().enumerate() {
+            if i > 0 {
+                header = header
+                    .child_builder()
+                    .finalize_with_hash(BlockHash(pathfinder_crypto::Felt::from_u64(i as u64)));
+            }
+            tx.insert_block_header(&header).unwrap();
+            tx.insert_state_update(header.number, state_update).unwrap();
+        }
+
+        // The update in block 0 was superseded by block 2, which is now the horizon.
+        let storage_updates: u64 = tx
+            .inner()
+            .query_row("SELECT COUNT(*) FROM storage_updates", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(storage_updates, 3);
+
+        // Reading the state of older blocks fails instead of returning partial state.
+        let error = tx
+            .storage_value(BlockNumber::new_or_panic(2).into(), contract, key)
+            .unwrap_err();
+        assert!(error.downcast_ref::<crate::StatePruned>().is_some());
+        let error = tx.state_update(BlockNumber::GENESIS.into()).unwrap_err();
+        assert!(error.downcast_ref::<crate::StatePruned>().is_some());
+        let error = tx
+            .storage_history(contract, key, BlockNumber::GENESIS, BlockNumber::MAX, 10)
+            .unwrap_err();
+        assert!(error.downcast_ref::<crate::StatePruned>().is_some());
+        let error = tx
+            .nonce_history(contract, BlockNumber::new_or_panic(2), BlockNumber::MAX, 10)
+            .unwrap_err();
+        assert!(error.downcast_ref::<crate::StatePruned>().is_some());
+
+        // The state of the retained blocks is intact.
+        let value = tx
+            .storage_value(BlockNumber::new_or_panic(3).into(), contract, key)
+            .unwrap();
+        assert_eq!(value, Some(storage_value_bytes!(b"value 2")));
+        let value = tx.storage_value(BlockId::Latest, contract, key).unwrap();
+        assert_eq!(value, Some(storage_value_bytes!(b"value 4")));
+        let value = tx
+            .storage_value(BlockId::Latest, contract, untouched_key)
+            .unwrap();
+        assert_eq!(value, Some(storage_value_bytes!(b"untouched")));
+        let nonce = tx.contract_nonce(contract, BlockId::Latest).unwrap();
+        assert_eq!(nonce, Some(contract_nonce_bytes!(b"nonce 3")));
+        let class_hash = tx.contract_class_hash(BlockId::Latest, contract).unwrap();
+        assert_eq!(class_hash, Some(class_hash_bytes!(b"class")));
+        let history = tx
+            .storage_history(
+                contract,
+                key,
+                BlockNumber::new_or_panic(3),
+                BlockNumber::MAX,
+                10,
+            )
+            .unwrap();
+        assert_eq!(
+            history,
+            vec![(
+                BlockNumber::new_or_panic(4),
+                storage_value_bytes!(b"value 4")
+            )]
+        );
+
+        // Only the last two blocks are considered to have state.
+        assert!(!tx
+            .block_state_exists(BlockNumber::new_or_panic(2).into())
+            .unwrap());
+        assert!(tx
+            .block_state_exists(BlockNumber::new_or_panic(3).into())
+            .unwrap());
+        assert!(tx.block_state_exists(BlockId::Latest).unwrap());
+        // Block headers are retained.
+        assert!(tx.block_exists(BlockNumber::GENESIS.into()).unwrap());
     }
 }
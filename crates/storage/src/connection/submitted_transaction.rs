@@ -0,0 +1,340 @@
+use anyhow::Context;
+use pathfinder_common::{ContractAddress, TransactionHash};
+
+use crate::prelude::*;
+
+/// A transaction which was submitted to the gateway through this node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubmittedTransaction {
+    pub hash: TransactionHash,
+    pub sender_address: ContractAddress,
+    /// Unix timestamp at which the transaction was first submitted.
+    pub submitted_at: u64,
+    /// Unix timestamp at which the transaction was last sent to the gateway.
+    pub last_broadcast_at: u64,
+    /// The number of times the transaction was sent to the gateway, including
+    /// the original submission.
+    pub broadcast_count: u64,
+    /// Why the gateway rejected a re-broadcast of the transaction. Rejected
+    /// transactions are not re-broadcast again.
+    pub rejection: Option<String>,
+}
+
+impl Transaction<'_> {
+    /// Tracks a submitted transaction, along with the gateway `request` it was
+    /// submitted with. Does nothing if the transaction is already tracked.
+    pub fn insert_submitted_transaction(
+        &self,
+        transaction: &SubmittedTransaction,
+        request: &[u8],
+    ) -> anyhow::Result<()> {
+        let mut compressor = zstd::bulk::Compressor::new(10).context("Creating zstd compressor")?;
+        let request = compressor
+            .compress(request)
+            .context("Compressing submitted transaction")?;
+
+        self.inner()
+            .execute(
+                r"INSERT OR IGNORE INTO submitted_transactions (
+                    hash,
+                    sender_address,
+                    request,
+                    submitted_at,
+                    last_broadcast_at,
+                    broadcast_count,
+                    rejection
+                ) VALUES (
+                    :hash,
+                    :sender_address,
+                    :request,
+                    :submitted_at,
+                    :last_broadcast_at,
+                    :broadcast_count,
+                    :rejection
+                )",
+                named_params! {
+                    ":hash": &transaction.hash,
+                    ":sender_address": &transaction.sender_address,
+                    ":request": &request,
+                    ":submitted_at": &(transaction.submitted_at as i64),
+                    ":last_broadcast_at": &(transaction.last_broadcast_at as i64),
+                    ":broadcast_count": &(transaction.broadcast_count as i64),
+                    ":rejection": &transaction.rejection,
+                },
+            )
+            .context("Inserting submitted transaction")?;
+
+        Ok(())
+    }
+
+    /// Returns the most recently submitted transactions, optionally only those
+    /// of `sender`, newest first and at most `limit` entries.
+    pub fn submitted_transactions(
+        &self,
+        sender: Option<ContractAddress>,
+        limit: usize,
+    ) -> anyhow::Result<Vec<SubmittedTransaction>> {
+        let mut stmt = self.inner().prepare_cached(
+            r"SELECT hash, sender_address, submitted_at, last_broadcast_at, broadcast_count, rejection
+            FROM submitted_transactions
+            WHERE ?1 IS NULL OR sender_address = ?1
+            ORDER BY submitted_at DESC, hash
+            LIMIT ?2",
+        )?;
+
+        let limit = u64::try_from(limit).expect("ptr size is 64 bits");
+        let transactions = stmt
+            .query_map(params![&sender, &limit], |row| {
+                Ok(SubmittedTransaction {
+                    hash: row.get_transaction_hash(0)?,
+                    sender_address: row.get_contract_address(1)?,
+                    submitted_at: row.get_i64(2)? as u64,
+                    last_broadcast_at: row.get_i64(3)? as u64,
+                    broadcast_count: row.get_i64(4)? as u64,
+                    rejection: row.get_optional_str(5)?.map(ToOwned::to_owned),
+                })
+            })
+            .context("Querying submitted transactions")?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(transactions)
+    }
+
+    /// Returns the gateway request the transaction was submitted with.
+    pub fn submitted_transaction_request(
+        &self,
+        hash: TransactionHash,
+    ) -> anyhow::Result<Option<Vec<u8>>> {
+        let mut stmt = self
+            .inner()
+            .prepare_cached("SELECT request FROM submitted_transactions WHERE hash = ?")?;
+        let request = stmt
+            .query_row(params![&hash], |row| row.get_blob(0).map(|x| x.to_vec()))
+            .optional()
+            .context("Querying submitted transaction")?;
+
+        request
+            .map(|request| {
+                zstd::decode_all(request.as_slice()).context("Decompressing submitted transaction")
+            })
+            .transpose()
+    }
+
+    /// Returns the submitted transactions which are not part of any stored
+    /// block, were last sent to the gateway at or before `broadcast_before`,
+    /// have been sent fewer than `max_broadcasts` times and were not rejected.
+    pub fn submitted_transactions_to_rebroadcast(
+        &self,
+        broadcast_before: u64,
+        max_broadcasts: u64,
+    ) -> anyhow::Result<Vec<TransactionHash>> {
+        let mut stmt = self.inner().prepare_cached(
+            r"SELECT hash FROM submitted_transactions
+            WHERE rejection IS NULL
+                AND last_broadcast_at <= ?
+                AND broadcast_count < ?
+                AND NOT EXISTS (
+                    SELECT 1 FROM transaction_hashes
+                    WHERE transaction_hashes.hash = submitted_transactions.hash
+                )
+            ORDER BY submitted_at",
+        )?;
+
+        let hashes = stmt
+            .query_map(
+                params![&(broadcast_before as i64), &(max_broadcasts as i64)],
+                |row| row.get_transaction_hash(0),
+            )
+            .context("Querying submitted transactions to re-broadcast")?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(hashes)
+    }
+
+    /// Records that the transaction was sent to the gateway again at
+    /// `broadcast_at`.
+    pub fn update_submitted_transaction_broadcast(
+        &self,
+        hash: TransactionHash,
+        broadcast_at: u64,
+    ) -> anyhow::Result<()> {
+        self.inner()
+            .execute(
+                r"UPDATE submitted_transactions
+                SET last_broadcast_at = ?, broadcast_count = broadcast_count + 1
+                WHERE hash = ?",
+                params![&(broadcast_at as i64), &hash],
+            )
+            .context("Updating submitted transaction")?;
+
+        Ok(())
+    }
+
+    /// Records why the gateway rejected the transaction, which stops it from
+    /// being re-broadcast.
+    pub fn reject_submitted_transaction(
+        &self,
+        hash: TransactionHash,
+        rejection: &str,
+    ) -> anyhow::Result<()> {
+        self.inner()
+            .execute(
+                "UPDATE submitted_transactions SET rejection = ? WHERE hash = ?",
+                params![&rejection, &hash],
+            )
+            .context("Rejecting submitted transaction")?;
+
+        Ok(())
+    }
+
+    /// Stops tracking the transactions submitted before `submitted_before`,
+    /// returning how many were removed.
+    pub fn prune_submitted_transactions(&self, submitted_before: u64) -> anyhow::Result<usize> {
+        let pruned = self
+            .inner()
+            .execute(
+                "DELETE FROM submitted_transactions WHERE submitted_at < ?",
+                params![&(submitted_before as i64)],
+            )
+            .context("Pruning submitted transactions")?;
+
+        Ok(pruned)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::macro_prelude::*;
+    use pathfinder_common::receipt::Receipt;
+    use pathfinder_common::transaction::{
+        Transaction as StarknetTransaction,
+        TransactionVariant,
+    };
+    use pathfinder_common::{BlockHeader, BlockNumber};
+
+    use super::*;
+
+    fn submitted(hash: TransactionHash, submitted_at: u64) -> SubmittedTransaction {
+        SubmittedTransaction {
+            hash,
+            sender_address: contract_address!("0x1"),
+            submitted_at,
+            last_broadcast_at: submitted_at,
+            broadcast_count: 1,
+            rejection: None,
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let storage = crate::StorageBuilder::in_memory().unwrap();
+        let mut connection = storage.connection().unwrap();
+        let tx = connection.transaction().unwrap();
+
+        let first = submitted(transaction_hash!("0x1"), 100);
+        let second = SubmittedTransaction {
+            sender_address: contract_address!("0x2"),
+            ..submitted(transaction_hash!("0x2"), 200)
+        };
+        tx.insert_submitted_transaction(&first, b"first").unwrap();
+        tx.insert_submitted_transaction(&second, b"second").unwrap();
+        // Submitting the same transaction again keeps the original.
+        tx.insert_submitted_transaction(&submitted(first.hash, 300), b"again")
+            .unwrap();
+
+        assert_eq!(
+            tx.submitted_transactions(None, 10).unwrap(),
+            vec![second.clone(), first.clone()]
+        );
+        assert_eq!(
+            tx.submitted_transactions(Some(contract_address!("0x1")), 10)
+                .unwrap(),
+            vec![first.clone()]
+        );
+        assert_eq!(
+            tx.submitted_transactions(None, 1).unwrap(),
+            vec![second.clone()]
+        );
+        assert_eq!(
+            tx.submitted_transaction_request(first.hash).unwrap(),
+            Some(b"first".to_vec())
+        );
+        assert_eq!(
+            tx.submitted_transaction_request(transaction_hash!("0x3"))
+                .unwrap(),
+            None
+        );
+
+        assert_eq!(tx.prune_submitted_transactions(200).unwrap(), 1);
+        assert_eq!(tx.submitted_transactions(None, 10).unwrap(), vec![second]);
+    }
+
+    #[test]
+    fn rebroadcast_candidates() {
+        let storage = crate::StorageBuilder::in_memory().unwrap();
+        let mut connection = storage.connection().unwrap();
+        let tx = connection.transaction().unwrap();
+
+        let stale = submitted(transaction_hash!("0x1"), 100);
+        let recent = submitted(transaction_hash!("0x2"), 200);
+        let rejected = submitted(transaction_hash!("0x3"), 100);
+        let included = submitted(transaction_hash!("0x4"), 100);
+        for transaction in [&stale, &recent, &rejected, &included] {
+            tx.insert_submitted_transaction(transaction, b"request")
+                .unwrap();
+        }
+        tx.reject_submitted_transaction(rejected.hash, "Invalid transaction nonce")
+            .unwrap();
+
+        let header = BlockHeader::builder()
+            .number(BlockNumber::GENESIS)
+            .finalize_with_hash(block_hash!("0xb0"));
+        tx.insert_block_header(&header).unwrap();
+        tx.insert_transaction_data(
+            header.number,
+            &[(
+                StarknetTransaction {
+                    hash: included.hash,
+                    variant: TransactionVariant::InvokeV0(Default::default()),
+                },
+                Receipt {
+                    transaction_hash: included.hash,
+                    ..Default::default()
+                },
+            )],
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(
+            tx.submitted_transactions_to_rebroadcast(150, 3).unwrap(),
+            vec![stale.hash]
+        );
+
+        tx.update_submitted_transaction_broadcast(stale.hash, 150)
+            .unwrap();
+        tx.update_submitted_transaction_broadcast(stale.hash, 150)
+            .unwrap();
+        assert_eq!(
+            tx.submitted_transactions(None, 10).unwrap()[1],
+            SubmittedTransaction {
+                last_broadcast_at: 150,
+                broadcast_count: 3,
+                ..stale
+            }
+        );
+        // Sent too often already.
+        assert_eq!(
+            tx.submitted_transactions_to_rebroadcast(150, 3).unwrap(),
+            vec![]
+        );
+
+        let rejections = tx
+            .submitted_transactions(None, 10)
+            .unwrap()
+            .into_iter()
+            .filter_map(|transaction| transaction.rejection)
+            .collect::<Vec<_>>();
+        assert_eq!(rejections, vec!["Invalid transaction nonce".to_owned()]);
+    }
+}
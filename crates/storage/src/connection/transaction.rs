@@ -3,8 +3,9 @@
 use anyhow::Context;
 use pathfinder_common::event::Event;
 use pathfinder_common::receipt::Receipt;
-use pathfinder_common::transaction::Transaction as StarknetTransaction;
-use pathfinder_common::{BlockHash, BlockNumber, TransactionHash};
+use pathfinder_common::transaction::{Transaction as StarknetTransaction, TransactionVariant};
+use pathfinder_common::{BlockHash, BlockNumber, ContractAddress, TransactionHash};
+use primitive_types::H256;
 
 use super::{EventsForBlock, TransactionDataForBlock, TransactionWithReceipt};
 use crate::prelude::*;
@@ -83,6 +84,16 @@ pub(crate) mod compression {
     }
 }
 
+/// A transaction sent by an account, see
+/// [`Transaction::transactions_by_sender`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SentTransaction {
+    pub block_number: BlockNumber,
+    /// The index of the transaction within its block.
+    pub index: usize,
+    pub hash: TransactionHash,
+}
+
 type TransactionsAndEventsByBlock = (Vec<(StarknetTransaction, Receipt)>, Vec<Vec<Event>>);
 type TransactionAndEventsByHash = (
     BlockNumber,
@@ -99,6 +110,11 @@ impl Transaction<'_> {
         transactions: &[(StarknetTransaction, Receipt)],
         events: Option<&[Vec<Event>]>,
     ) -> anyhow::Result<()> {
+        self.insert_block_fee_stats(block_number, transactions)
+            .context("Inserting fee stats")?;
+        self.insert_block_transaction_stats(block_number, transactions)
+            .context("Inserting block stats")?;
+
         if transactions.is_empty() && events.map_or(true, |x| x.is_empty()) {
             return Ok(());
         }
@@ -117,6 +133,20 @@ impl Transaction<'_> {
                  :block_number, :idx)",
             )
             .context("Preparing insert transaction hash statement")?;
+        let mut insert_transaction_sender_stmt = self
+            .inner()
+            .prepare_cached(
+                "INSERT INTO transaction_senders (sender_address, block_number, idx) VALUES \
+                 (:sender_address, :block_number, :idx)",
+            )
+            .context("Preparing insert transaction sender statement")?;
+        let mut insert_l1_handler_message_stmt = self
+            .inner()
+            .prepare_cached(
+                "INSERT INTO l1_handler_messages (message_hash, block_number, idx) VALUES \
+                 (:message_hash, :block_number, :idx)",
+            )
+            .context("Preparing insert L1 handler message statement")?;
 
         for (idx, (transaction, ..)) in transactions.iter().enumerate() {
             let idx: i64 = idx.try_into()?;
@@ -125,6 +155,21 @@ impl Transaction<'_> {
                 ":block_number": &block_number,
                 ":idx": &idx,
             ])?;
+            if let Some(sender) = transaction.variant.sender_address() {
+                insert_transaction_sender_stmt.execute(named_params![
+                    ":sender_address": &sender,
+                    ":block_number": &block_number,
+                    ":idx": &idx,
+                ])?;
+            }
+            if let TransactionVariant::L1Handler(l1_handler) = &transaction.variant {
+                let message_hash = l1_handler.calculate_message_hash();
+                insert_l1_handler_message_stmt.execute(named_params![
+                    ":message_hash": &message_hash.as_bytes(),
+                    ":block_number": &block_number,
+                    ":idx": &idx,
+                ])?;
+            }
         }
         let transactions_with_receipts: Vec<_> = transactions
             .iter()
@@ -368,6 +413,91 @@ impl Transaction<'_> {
             .map_err(|e| e.into())
     }
 
+    /// Returns the transactions sent by `sender` from transaction index
+    /// `from.1` of block `from.0` up to and including block `to`, oldest first
+    /// and at most `limit` entries.
+    pub fn transactions_by_sender(
+        &self,
+        sender: ContractAddress,
+        from: (BlockNumber, usize),
+        to: BlockNumber,
+        limit: usize,
+    ) -> anyhow::Result<Vec<SentTransaction>> {
+        self.ensure_migrated(crate::schema::TRANSACTION_SENDERS, from.0..=to)?;
+
+        let mut stmt = self.inner().prepare_cached(
+            r"
+            SELECT transaction_senders.block_number, transaction_senders.idx, transaction_hashes.hash
+            FROM transaction_senders
+            JOIN transaction_hashes ON transaction_hashes.block_number = transaction_senders.block_number
+                AND transaction_hashes.idx = transaction_senders.idx
+            WHERE transaction_senders.sender_address = ?
+                AND (transaction_senders.block_number, transaction_senders.idx) >= (?, ?)
+                AND transaction_senders.block_number <= ?
+            ORDER BY transaction_senders.block_number, transaction_senders.idx
+            LIMIT ?
+            ",
+        )?;
+
+        let (from_block, from_idx) = from;
+        let from_idx = i64::try_from(from_idx)?;
+        let limit = u64::try_from(limit).expect("ptr size is 64 bits");
+        let transactions = stmt
+            .query_map(
+                params![&sender, &from_block, &from_idx, &to, &limit],
+                |row| {
+                    let block_number = row.get_block_number(0)?;
+                    // Always safe since indices are inserted from a usize.
+                    let index = row.get_i64(1)?.try_into().unwrap();
+                    let hash = row.get_transaction_hash(2)?;
+                    Ok(SentTransaction {
+                        block_number,
+                        index,
+                        hash,
+                    })
+                },
+            )
+            .context("Querying transactions by sender")?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(transactions)
+    }
+
+    /// Returns the L1 handler transactions of the L1 -> L2 message with
+    /// `message_hash`, oldest first. There is usually one, unless the message
+    /// was retried after its L1 handler reverted.
+    pub fn l1_handler_transactions(
+        &self,
+        message_hash: H256,
+    ) -> anyhow::Result<Vec<(BlockNumber, TransactionHash)>> {
+        self.ensure_migrated(
+            crate::schema::L1_HANDLER_MESSAGES,
+            BlockNumber::GENESIS..=BlockNumber::MAX,
+        )?;
+
+        let mut stmt = self.inner().prepare_cached(
+            r"
+            SELECT l1_handler_messages.block_number, transaction_hashes.hash
+            FROM l1_handler_messages
+            JOIN transaction_hashes ON transaction_hashes.block_number = l1_handler_messages.block_number
+                AND transaction_hashes.idx = l1_handler_messages.idx
+            WHERE l1_handler_messages.message_hash = ?
+            ORDER BY l1_handler_messages.block_number, l1_handler_messages.idx
+            ",
+        )?;
+
+        let transactions = stmt
+            .query_map(params![&message_hash.as_bytes()], |row| {
+                let block_number = row.get_block_number(0)?;
+                let hash = row.get_transaction_hash(1)?;
+                Ok((block_number, hash))
+            })
+            .context("Querying L1 handler transactions")?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(transactions)
+    }
+
     fn query_transactions_by_block(
         &self,
         block_number: BlockNumber,
@@ -2870,6 +3000,98 @@ mod tests {
         assert_eq!(invalid_block, None);
     }
 
+    #[test]
+    fn transactions_by_sender() {
+        let (mut db, header, body) = setup();
+        let tx = db.transaction().unwrap();
+
+        let sender = contract_address_bytes!(b"invoke v1 contract address");
+        let index = body
+            .iter()
+            .position(|(transaction, _)| {
+                transaction.hash == transaction_hash_bytes!(b"invoke v1 tx hash")
+            })
+            .unwrap();
+
+        let result = tx
+            .transactions_by_sender(sender, (BlockNumber::GENESIS, 0), BlockNumber::MAX, 10)
+            .unwrap();
+        assert_eq!(
+            result,
+            vec![SentTransaction {
+                block_number: header.number,
+                index,
+                hash: transaction_hash_bytes!(b"invoke v1 tx hash"),
+            }]
+        );
+
+        let deploy_account = tx
+            .transactions_by_sender(
+                contract_address_bytes!(b"deploy account contract address"),
+                (BlockNumber::GENESIS, 0),
+                BlockNumber::MAX,
+                10,
+            )
+            .unwrap();
+        assert_eq!(deploy_account.len(), 1);
+
+        let after_index = tx
+            .transactions_by_sender(sender, (header.number, index + 1), BlockNumber::MAX, 10)
+            .unwrap();
+        assert_eq!(after_index, vec![]);
+
+        let deploy = tx
+            .transactions_by_sender(
+                contract_address_bytes!(b"deploy contract address"),
+                (BlockNumber::GENESIS, 0),
+                BlockNumber::MAX,
+                10,
+            )
+            .unwrap();
+        assert_eq!(deploy, vec![]);
+
+        // Declare v0 transactions have a placeholder sender.
+        let declare_v0 = tx
+            .transactions_by_sender(
+                contract_address_bytes!(b"declare v0 contract address"),
+                (BlockNumber::GENESIS, 0),
+                BlockNumber::MAX,
+                10,
+            )
+            .unwrap();
+        assert_eq!(declare_v0, vec![]);
+    }
+
+    #[test]
+    fn l1_handler_transactions() {
+        let (mut db, header, body) = setup();
+        let tx = db.transaction().unwrap();
+
+        let (l1_handler, _) = body
+            .iter()
+            .find(|(transaction, _)| {
+                matches!(transaction.variant, TransactionVariant::L1Handler(_))
+            })
+            .unwrap();
+        let TransactionVariant::L1Handler(variant) = &l1_handler.variant else {
+            unreachable!();
+        };
+
+        let result = tx
+            .l1_handler_transactions(variant.calculate_message_hash())
+            .unwrap();
+        assert_eq!(result, vec![(header.number, l1_handler.hash)]);
+
+        let unknown = tx.l1_handler_transactions(H256::zero()).unwrap();
+        assert_eq!(unknown, vec![]);
+
+        tx.purge_block(header.number).unwrap();
+        let purged = tx
+            .l1_handler_transactions(variant.calculate_message_hash())
+            .unwrap();
+        assert_eq!(purged, vec![]);
+    }
+
     #[test]
     fn transaction_block_hash() {
         let (mut db, header, body) = setup();
@@ -1,7 +1,8 @@
 use std::collections::HashMap;
+use std::time::{Duration, Instant};
 
 use anyhow::Context;
-use bitvec::prelude::Msb0;
+use bitvec::prelude::{Lsb0, Msb0};
 use bitvec::vec::BitVec;
 use pathfinder_common::prelude::*;
 use pathfinder_crypto::Felt;
@@ -78,16 +79,11 @@ impl Transaction<'_> {
         block_number: BlockNumber,
         contract: ContractAddress,
     ) -> anyhow::Result<Option<ContractRoot>> {
-        self.inner()
-        .query_row(
-            r"SELECT hash FROM trie_contracts WHERE idx = (
-                SELECT root_index FROM contract_roots WHERE block_number <= ? AND contract_address = ? ORDER BY block_number DESC LIMIT 1
-            )",
-            params![&block_number, &contract],
-            |row| row.get_contract_root(0),
-        )
-        .optional()
-        .map_err(Into::into)
+        let Some(index) = self.contract_root_index(block_number, contract)? else {
+            return Ok(None);
+        };
+
+        Ok(self.contract_trie_node_hash(index)?.map(ContractRoot))
     }
 
     pub fn insert_class_root(
@@ -366,6 +362,19 @@ impl Transaction<'_> {
         Ok(())
     }
 
+    /// Deletes the trie nodes which are not reachable from any of the stored
+    /// trie roots, returning the number of nodes deleted.
+    ///
+    /// Orphans are left behind by interrupted writes and by pruning, which only
+    /// deletes the nodes it has marked as removed. This visits every reachable
+    /// node, so it should only be run while the node is stopped.
+    pub fn delete_orphaned_trie_nodes(&self) -> anyhow::Result<u64> {
+        let mut deleted = self.delete_orphaned_trie_nodes_in("trie_class", "class_roots")?;
+        deleted += self.delete_orphaned_trie_nodes_in("trie_contracts", "contract_roots")?;
+        deleted += self.delete_orphaned_trie_nodes_in("trie_storage", "storage_roots")?;
+        Ok(deleted)
+    }
+
     pub fn coalesce_trie_removals(&self, target_block: BlockNumber) -> anyhow::Result<()> {
         self.coalesce_removed_trie_nodes(target_block, "trie_contracts")?;
         self.coalesce_removed_trie_nodes(target_block, "trie_storage")?;
@@ -441,19 +450,13 @@ impl Transaction<'_> {
             let mut rows = select_stmt
                 .query(params![&before_block])
                 .context("Fetching nodes to delete")?;
-            let mut delete_stmt = self
-                .inner()
-                .prepare_cached(&format!(r"DELETE FROM {table} WHERE idx = ?"))
-                .context("Creating delete statement")?;
             while let Some(row) = rows.next().context("Iterating over rows")? {
                 let (indices, _) = bincode::decode_from_slice::<Vec<u64>, _>(
                     row.get_blob(0)?,
                     bincode::config::standard(),
                 )
                 .context("Decoding indices")?;
-                for idx in indices.iter() {
-                    delete_stmt.execute(params![idx]).context("Deleting node")?;
-                }
+                self.trie_nodes.delete(self, table, &indices)?;
                 metrics::counter!(METRIC_TRIE_NODES_REMOVED, indices.len() as u64, "table" => table);
             }
 
@@ -472,6 +475,69 @@ impl Transaction<'_> {
         Ok(())
     }
 
+    fn delete_orphaned_trie_nodes_in(
+        &self,
+        table: &'static str,
+        roots_table: &'static str,
+    ) -> anyhow::Result<u64> {
+        let mut stmt = self
+            .inner()
+            .prepare(&format!(
+                "SELECT DISTINCT root_index FROM {roots_table} WHERE root_index IS NOT NULL"
+            ))
+            .context("Creating root index statement")?;
+        let mut to_visit = stmt
+            .query_map([], |row| row.get::<_, u64>(0))
+            .context("Querying root indices")?
+            .collect::<Result<Vec<_>, _>>()
+            .context("Iterating over root indices")?;
+
+        let mut reachable = BitVec::<usize, Lsb0>::new();
+        let mut visited = 0u64;
+        let mut last_report = Instant::now();
+        while let Some(index) = to_visit.pop() {
+            let i = index as usize;
+            if reachable.len() <= i {
+                reachable.resize(i + 1, false);
+            }
+            if reachable.replace(i, true) {
+                continue;
+            }
+
+            // Roots outside of the pruning window may refer to nodes which have already
+            // been pruned.
+            let Some(node) = self.trie_node(index, table)? else {
+                continue;
+            };
+            match node {
+                StoredNode::Binary { left, right } => to_visit.extend([left, right]),
+                StoredNode::Edge { child, .. } => to_visit.push(child),
+                StoredNode::LeafBinary | StoredNode::LeafEdge { .. } => {}
+            }
+
+            visited += 1;
+            if last_report.elapsed() >= PROGRESS_REPORT_INTERVAL {
+                tracing::info!(%table, %visited, "Collecting reachable trie nodes");
+                last_report = Instant::now();
+            }
+        }
+
+        let mut orphans = Vec::new();
+        self.trie_nodes.for_each_index(self, table, &mut |index| {
+            if !reachable.get(index as usize).is_some_and(|bit| *bit) {
+                orphans.push(index);
+            }
+        })?;
+        self.trie_nodes.delete(self, table, &orphans)?;
+        metrics::counter!(METRIC_TRIE_NODES_REMOVED, orphans.len() as u64, "table" => table);
+        tracing::info!(
+            %table, reachable=%visited, deleted=%orphans.len(),
+            "Deleted orphaned trie nodes"
+        );
+
+        Ok(orphans.len() as u64)
+    }
+
     /// Stores the node data for a trie and returns the root index change.
     fn insert_trie(
         &self,
@@ -492,13 +558,6 @@ impl Transaction<'_> {
             }
         }
 
-        let mut stmt = self
-            .inner()
-            .prepare_cached(&format!(
-                "INSERT INTO {table} (hash, data) VALUES(?, ?) RETURNING idx",
-            ))
-            .context("Creating insert statement")?;
-
         let mut to_insert = Vec::new();
         let mut to_process = vec![NodeRef::Index(update.nodes_added.len() - 1)];
 
@@ -529,53 +588,47 @@ impl Transaction<'_> {
             }
         }
 
+        // Insert nodes in reverse to ensure children always have an assigned index for
+        // the parent to use.
+        to_insert.reverse();
+
         let mut indices = HashMap::new();
 
         // Reusable (and oversized) buffer for encoding.
         let mut buffer = [0u8; 256];
 
-        // Insert nodes in reverse to ensure children always have an assigned index for
-        // the parent to use.
-        for idx in to_insert.into_iter().rev() {
-            let (hash, node) = &update.nodes_added.get(idx).context("Node index missing")?;
+        let storage_indices =
+            self.trie_nodes
+                .insert(self, table, to_insert.len(), &mut |inserted: &[u64]| {
+                    // One node is inserted per call, so only the last index is new.
+                    if let Some(storage_idx) = inserted.last() {
+                        indices.insert(to_insert[inserted.len() - 1], *storage_idx);
+                    }
 
-            let node = node.as_stored(&indices)?;
+                    let idx = to_insert[inserted.len()];
+                    let (hash, node) =
+                        &update.nodes_added.get(idx).context("Node index missing")?;
 
-            let length = node.encode(&mut buffer).context("Encoding node")?;
+                    let node = node.as_stored(&indices)?;
 
-            let storage_idx: u64 = stmt
-                .query_row(
-                    params![&hash.as_be_bytes().as_slice(), &&buffer[..length]],
-                    |row| row.get(0),
-                )
-                .context("Inserting node")?;
+                    let length = node.encode(&mut buffer).context("Encoding node")?;
 
-            indices.insert(idx, storage_idx);
+                    Ok((*hash, buffer[..length].to_vec()))
+                })?;
 
-            metrics::increment_counter!(METRIC_TRIE_NODES_ADDED, "table" => table);
-        }
+        metrics::counter!(METRIC_TRIE_NODES_ADDED, storage_indices.len() as u64, "table" => table);
 
+        // The root is the last node to be inserted.
         Ok(RootIndexUpdate::Updated(
-            *indices
-                .get(&(update.nodes_added.len() - 1))
+            *storage_indices
+                .last()
                 .expect("Root index must exist as we just inserted it"),
         ))
     }
 
     /// Returns the node with the given index.
     fn trie_node(&self, index: u64, table: &'static str) -> anyhow::Result<Option<StoredNode>> {
-        // We rely on sqlite caching the statement here. Storing the statement would be
-        // nice, however that leads to &mut requirements or interior mutable
-        // work-arounds.
-        let mut stmt = self
-            .inner()
-            .prepare_cached(&format!("SELECT data FROM {table} WHERE idx = ?"))
-            .context("Creating get statement")?;
-
-        let Some(data): Option<Vec<u8>> = stmt
-            .query_row(params![&index], |row| row.get(0))
-            .optional()?
-        else {
+        let Some(data) = self.trie_nodes.node(self, table, index)? else {
             return Ok(None);
         };
 
@@ -586,20 +639,12 @@ impl Transaction<'_> {
 
     /// Returns the hash of the node with the given index.
     fn trie_node_hash(&self, index: u64, table: &'static str) -> anyhow::Result<Option<Felt>> {
-        // We rely on sqlite caching the statement here. Storing the statement would be
-        // nice, however that leads to &mut requirements or interior mutable
-        // work-arounds.
-        let mut stmt = self
-            .inner()
-            .prepare_cached(&format!("SELECT hash FROM {table} WHERE idx = ?"))
-            .context("Creating get statement")?;
-
-        stmt.query_row(params![&index], |row| row.get_felt(0))
-            .optional()
-            .map_err(Into::into)
+        self.trie_nodes.node_hash(self, table, index)
     }
 }
 
+const PROGRESS_REPORT_INTERVAL: Duration = Duration::from_secs(10);
+
 const METRIC_TRIE_NODES_REMOVED: &str = "pathfinder_storage_trie_nodes_deleted_total";
 const METRIC_TRIE_NODES_ADDED: &str = "pathfinder_storage_trie_nodes_added_total";
 
@@ -1434,6 +1479,70 @@ mod tests {
         assert!(tx.class_trie_node(3).unwrap().is_none());
     }
 
+    #[test]
+    fn orphaned_trie_nodes_are_deleted() {
+        let mut db = crate::StorageBuilder::in_memory()
+            .unwrap()
+            .connection()
+            .unwrap();
+        let tx = db.transaction().unwrap();
+
+        let root = tx
+            .insert_class_trie(
+                &TrieUpdate {
+                    nodes_added: vec![
+                        (felt!("0x1"), Node::LeafBinary),
+                        (felt!("0x2"), Node::LeafBinary),
+                        (
+                            felt!("0x3"),
+                            Node::Binary {
+                                left: NodeRef::Index(0),
+                                right: NodeRef::Index(1),
+                            },
+                        ),
+                    ],
+                    nodes_removed: vec![],
+                    root_commitment: felt!("0x3"),
+                },
+                BlockNumber::GENESIS,
+            )
+            .unwrap();
+        assert_eq!(root, RootIndexUpdate::Updated(3));
+        tx.insert_class_root(BlockNumber::GENESIS, root).unwrap();
+
+        // A trie update whose root was never stored.
+        tx.insert_class_trie(
+            &TrieUpdate {
+                nodes_added: vec![(felt!("0x4"), Node::LeafBinary)],
+                nodes_removed: vec![],
+                root_commitment: felt!("0x4"),
+            },
+            BlockNumber::GENESIS + 1,
+        )
+        .unwrap();
+
+        let generation = tx.trie_generation().unwrap();
+        assert_eq!(tx.delete_orphaned_trie_nodes().unwrap(), 1);
+        assert!(tx.class_trie_node(1).unwrap().is_some());
+        assert!(tx.class_trie_node(2).unwrap().is_some());
+        assert!(tx.class_trie_node(3).unwrap().is_some());
+        assert!(tx.class_trie_node(4).unwrap().is_none());
+
+        // The deleted node had the largest index, which is now reused.
+        assert_eq!(tx.trie_generation().unwrap(), generation + 1);
+        let reused = tx
+            .insert_class_trie(
+                &TrieUpdate {
+                    nodes_added: vec![(felt!("0x5"), Node::LeafBinary)],
+                    nodes_removed: vec![],
+                    root_commitment: felt!("0x5"),
+                },
+                BlockNumber::GENESIS + 2,
+            )
+            .unwrap();
+        assert_eq!(reused, RootIndexUpdate::Updated(4));
+    }
+
     #[test]
     fn class_trie_root_updates() {
         let mut db = crate::StorageBuilder::in_memory_with_trie_pruning(TriePruneMode::Prune {
@@ -0,0 +1,145 @@
+use anyhow::Context;
+
+use super::Connection;
+use crate::prelude::*;
+
+impl Connection {
+    /// Switches the database to incremental auto-vacuum, which requires
+    /// rebuilding the whole database file using `VACUUM`. This needs as much
+    /// free disk space as the size of the database, and blocks all other
+    /// connections until done.
+    pub fn enable_incremental_vacuum(&mut self) -> anyhow::Result<()> {
+        self.connection
+            .execute_batch("PRAGMA auto_vacuum = INCREMENTAL; VACUUM;")
+            .context("Rebuilding database")
+    }
+
+    /// Locks the database file for as long as this connection is open. Fails
+    /// if any other connection has the database open, such as those of a
+    /// running node, and keeps nodes from opening it in the meantime.
+    pub fn lock_exclusively(&mut self) -> anyhow::Result<()> {
+        self.connection
+            .pragma_update(None, "locking_mode", "EXCLUSIVE")
+            .context("Setting locking_mode")?;
+        // The lock is only taken by the first write.
+        self.connection
+            .execute_batch("BEGIN EXCLUSIVE; COMMIT;")
+            .context("Locking database, it must not be in use by a running node")
+    }
+
+    /// Copies all of the write-ahead log into the database file and truncates
+    /// the log, so that the database file alone is complete.
+    ///
+    /// Returns false if other connections prevented the log from being fully
+    /// copied.
+    pub fn flush_wal(&mut self) -> anyhow::Result<bool> {
+        let busy: bool = self
+            .connection
+            .query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |row| row.get(0))
+            .context("Checkpointing write-ahead log")?;
+        Ok(!busy)
+    }
+}
+
+impl Transaction<'_> {
+    /// Returns true if free pages can be reclaimed using
+    /// [Transaction::incremental_vacuum].
+    pub fn incremental_vacuum_enabled(&self) -> anyhow::Result<bool> {
+        // 2 is INCREMENTAL, see https://sqlite.org/pragma.html#pragma_auto_vacuum.
+        let auto_vacuum: u8 = self
+            .inner()
+            .query_row("PRAGMA auto_vacuum", [], |row| row.get(0))
+            .context("Querying auto_vacuum")?;
+        Ok(auto_vacuum == 2)
+    }
+
+    /// Returns the number of unused pages in the database file.
+    pub fn free_page_count(&self) -> anyhow::Result<u64> {
+        self.inner()
+            .query_row("PRAGMA freelist_count", [], |row| row.get(0))
+            .context("Querying freelist_count")
+    }
+
+    /// Returns the size of a database page in bytes.
+    pub fn page_size(&self) -> anyhow::Result<u64> {
+        self.inner()
+            .query_row("PRAGMA page_size", [], |row| row.get(0))
+            .context("Querying page_size")
+    }
+
+    /// Removes up to `pages` free pages from the database file, shrinking it
+    /// once the transaction is committed.
+    pub fn incremental_vacuum(&self, pages: u64) -> anyhow::Result<()> {
+        let mut stmt = self
+            .inner()
+            .prepare(&format!("PRAGMA incremental_vacuum({pages})"))
+            .context("Preparing incremental vacuum")?;
+        // A page is only freed each time a row is stepped through.
+        let mut rows = stmt.query([]).context("Running incremental vacuum")?;
+        while rows.next().context("Running incremental vacuum")?.is_some() {}
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU32;
+
+    use crate::StorageBuilder;
+
+    #[test]
+    fn new_databases_use_incremental_vacuum() {
+        let mut db = StorageBuilder::in_memory().unwrap().connection().unwrap();
+        let tx = db.transaction().unwrap();
+
+        assert!(tx.incremental_vacuum_enabled().unwrap());
+        tx.incremental_vacuum(10).unwrap();
+        assert_eq!(tx.free_page_count().unwrap(), 0);
+    }
+
+    #[test]
+    fn incremental_vacuum_frees_all_requested_pages() {
+        let mut db = StorageBuilder::in_memory().unwrap().connection().unwrap();
+        let tx = db.transaction().unwrap();
+        tx.inner()
+            .execute_batch(
+                r"CREATE TABLE vacuum_test (data BLOB);
+                WITH RECURSIVE n(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM n WHERE x < 100)
+                INSERT INTO vacuum_test SELECT zeroblob(10000) FROM n;
+                DELETE FROM vacuum_test;",
+            )
+            .unwrap();
+        tx.commit().unwrap();
+
+        let tx = db.transaction().unwrap();
+        let free_pages = tx.free_page_count().unwrap();
+        assert!(free_pages > 1);
+
+        tx.incremental_vacuum(free_pages).unwrap();
+        assert_eq!(tx.free_page_count().unwrap(), 0);
+    }
+
+    #[test]
+    fn exclusive_lock_fails_while_database_is_open() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage_manager = StorageBuilder::file(dir.path().join("db.sqlite"))
+            .migrate()
+            .unwrap();
+        let storage = storage_manager
+            .create_pool(NonZeroU32::new(1).unwrap())
+            .unwrap();
+        // Stands in for a running node.
+        let node = storage_manager
+            .create_pool(NonZeroU32::new(1).unwrap())
+            .unwrap();
+        let node_connection = node.connection().unwrap();
+
+        let mut db = storage.connection().unwrap();
+        db.lock_exclusively().unwrap_err();
+
+        drop(node_connection);
+        drop(node);
+        db.lock_exclusively().unwrap();
+    }
+}
@@ -0,0 +1,416 @@
+//! Consistency checks which re-derive data from the database and compare it
+//! against the stored values.
+
+use std::collections::HashMap;
+
+use anyhow::Context;
+use bitvec::prelude::*;
+use pathfinder_common::hash::{PedersenHash, PoseidonHash};
+use pathfinder_common::trie::TrieNode;
+use pathfinder_common::{
+    BlockNumber,
+    ClassCommitment,
+    ClassHash,
+    ContractAddress,
+    StateCommitment,
+    StorageCommitment,
+};
+use pathfinder_crypto::Felt;
+
+use crate::{BlockId, Storage, StoredNode, Transaction};
+
+/// Number of blocks checked per database transaction. Keeping transactions
+/// short prevents the check from holding a read snapshot open for the whole
+/// range, which would block WAL checkpointing on a live node.
+const BATCH_SIZE: u64 = 1_000;
+
+/// Number of recomputed node hashes remembered by [TrieHasher] before they are
+/// forgotten, bounding its memory use to a few hundred MiB.
+const MAX_REMEMBERED_NODES: usize = 4_000_000;
+
+/// A block whose stored state commitment does not match the one derived from
+/// the state tries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StateCommitmentMismatch {
+    pub block_number: BlockNumber,
+    /// The state commitment stored in the block header.
+    pub stored: StateCommitment,
+    /// The state commitment recomputed from the storage and class tries.
+    pub derived: StateCommitment,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Trie {
+    Storage,
+    Class,
+}
+
+impl Trie {
+    fn node(self, tx: &Transaction<'_>, index: u64) -> anyhow::Result<Option<StoredNode>> {
+        match self {
+            Trie::Storage => tx.storage_trie_node(index),
+            Trie::Class => tx.class_trie_node(index),
+        }
+    }
+
+    fn node_hash(self, tx: &Transaction<'_>, index: u64) -> anyhow::Result<Option<Felt>> {
+        match self {
+            Trie::Storage => tx.storage_trie_node_hash(index),
+            Trie::Class => tx.class_trie_node_hash(index),
+        }
+    }
+
+    fn hash(self, node: &TrieNode) -> Felt {
+        match self {
+            Trie::Storage => node.hash::<PedersenHash>(),
+            Trie::Class => node.hash::<PoseidonHash>(),
+        }
+    }
+}
+
+/// Recomputes the roots of the global tries bottom-up, from the leaf values
+/// they commit to, rather than trusting the hashes stored with the nodes.
+///
+/// Consecutive blocks share most of their trie nodes, so the hashes recomputed
+/// so far are remembered and only the nodes which changed are hashed again.
+/// They are keyed by the stored hash as well as the index, since indices are
+/// reused once pruning has removed a node.
+#[derive(Default)]
+pub struct TrieHasher {
+    remembered: HashMap<(Trie, u64), (Felt, Felt)>,
+}
+
+impl TrieHasher {
+    /// The state commitment of `block`, recomputed from its tries. `None` if
+    /// some of its trie nodes are missing, e.g. because they have been pruned.
+    pub fn state_commitment(
+        &mut self,
+        tx: &Transaction<'_>,
+        block: BlockNumber,
+    ) -> anyhow::Result<Option<StateCommitment>> {
+        let Some(storage_commitment) = self
+            .root(tx, Trie::Storage, block, &mut |key| {
+                tx.contract_state_hash(block, ContractAddress(key))
+                    .map(|hash| hash.map(|hash| hash.0))
+            })
+            .context("Recomputing storage commitment")?
+        else {
+            return Ok(None);
+        };
+
+        let Some(class_commitment) = self
+            .root(tx, Trie::Class, block, &mut |key| {
+                let Some(casm_hash) = tx.casm_hash_at(block.into(), ClassHash(key))? else {
+                    return Ok(None);
+                };
+                tx.class_commitment_leaf(block, &casm_hash)
+                    .map(|leaf| leaf.map(|leaf| leaf.0))
+            })
+            .context("Recomputing class commitment")?
+        else {
+            return Ok(None);
+        };
+
+        Ok(Some(StateCommitment::calculate(
+            StorageCommitment(storage_commitment),
+            ClassCommitment(class_commitment),
+        )))
+    }
+
+    /// The root of `trie` at `block`, zero if the trie is empty. The value of
+    /// a leaf is looked up by `leaf` from its key.
+    fn root(
+        &mut self,
+        tx: &Transaction<'_>,
+        trie: Trie,
+        block: BlockNumber,
+        leaf: &mut dyn FnMut(Felt) -> anyhow::Result<Option<Felt>>,
+    ) -> anyhow::Result<Option<Felt>> {
+        let index = match trie {
+            Trie::Storage => tx.storage_root_index(block),
+            Trie::Class => tx.class_root_index(block),
+        }
+        .context("Querying root index")?;
+
+        if self.remembered.len() > MAX_REMEMBERED_NODES {
+            self.remembered.clear();
+        }
+
+        match index {
+            Some(index) => self.hash(tx, trie, index, &mut BitVec::new(), leaf),
+            None => Ok(Some(Felt::ZERO)),
+        }
+    }
+
+    /// The hash of the node at `index`, which is at `path` in the trie.
+    fn hash(
+        &mut self,
+        tx: &Transaction<'_>,
+        trie: Trie,
+        index: u64,
+        path: &mut BitVec<u8, Msb0>,
+        leaf: &mut dyn FnMut(Felt) -> anyhow::Result<Option<Felt>>,
+    ) -> anyhow::Result<Option<Felt>> {
+        let Some(stored_hash) = trie.node_hash(tx, index)? else {
+            return Ok(None);
+        };
+        if let Some((hash, recomputed)) = self.remembered.get(&(trie, index)) {
+            if *hash == stored_hash {
+                return Ok(Some(*recomputed));
+            }
+        }
+
+        let Some(node) = trie.node(tx, index)? else {
+            return Ok(None);
+        };
+
+        let node = match node {
+            StoredNode::Binary { left, right } => {
+                path.push(false);
+                let left = self.hash(tx, trie, left, path, leaf)?;
+                path.pop();
+                path.push(true);
+                let right = self.hash(tx, trie, right, path, leaf)?;
+                path.pop();
+
+                let (Some(left), Some(right)) = (left, right) else {
+                    return Ok(None);
+                };
+                TrieNode::Binary { left, right }
+            }
+            StoredNode::Edge { child, path: edge } => {
+                let height = path.len();
+                path.extend_from_bitslice(&edge);
+                let child = self.hash(tx, trie, child, path, leaf)?;
+                path.truncate(height);
+
+                let Some(child) = child else {
+                    return Ok(None);
+                };
+                TrieNode::Edge { child, path: edge }
+            }
+            StoredNode::LeafBinary => {
+                path.push(false);
+                let left = leaf_value(path, leaf);
+                path.pop();
+                path.push(true);
+                let right = leaf_value(path, leaf);
+                path.pop();
+
+                TrieNode::Binary {
+                    left: left?,
+                    right: right?,
+                }
+            }
+            StoredNode::LeafEdge { path: edge } => {
+                let height = path.len();
+                path.extend_from_bitslice(&edge);
+                let child = leaf_value(path, leaf);
+                path.truncate(height);
+
+                TrieNode::Edge {
+                    child: child?,
+                    path: edge,
+                }
+            }
+        };
+
+        let recomputed = trie.hash(&node);
+        self.remembered
+            .insert((trie, index), (stored_hash, recomputed));
+        Ok(Some(recomputed))
+    }
+}
+
+fn leaf_value(
+    path: &BitSlice<u8, Msb0>,
+    leaf: &mut dyn FnMut(Felt) -> anyhow::Result<Option<Felt>>,
+) -> anyhow::Result<Felt> {
+    let key = Felt::from_bits(path).context("Mapping leaf path to key")?;
+    leaf(key)?.with_context(|| format!("Leaf {key} is missing"))
+}
+
+/// Recomputes the global state commitment from the storage and class tries
+/// for every block in `from..=to` and compares it against the value stored in
+/// the block header.
+///
+/// Blocks are streamed in small batches so memory use is independent of the
+/// range size. Returns the first mismatching block, or `None` if the whole
+/// range is consistent.
+///
+/// Fails if a block in the range is missing, or if its trie data is no longer
+/// available, e.g. because it has been pruned.
+pub fn verify_state_commitments(
+    storage: &Storage,
+    from: BlockNumber,
+    to: BlockNumber,
+) -> anyhow::Result<Option<StateCommitmentMismatch>> {
+    let mut db = storage
+        .connection()
+        .context("Opening database connection")?;
+
+    let mut hasher = TrieHasher::default();
+    let mut next = from;
+    while next <= to {
+        let batch_end = std::cmp::min(to, next + (BATCH_SIZE - 1));
+        let tx = db.transaction().context("Creating database transaction")?;
+
+        for block_number in next.get()..=batch_end.get() {
+            let block_number = BlockNumber::new_or_panic(block_number);
+
+            let stored = tx
+                .state_commitment(BlockId::Number(block_number))
+                .context("Querying stored state commitment")?
+                .with_context(|| format!("Block {block_number} is missing"))?;
+            let derived = hasher
+                .state_commitment(&tx, block_number)
+                .context("Recomputing state commitment from tries")?
+                .with_context(|| format!("Trie data for block {block_number} is not available"))?;
+
+            if stored != derived {
+                return Ok(Some(StateCommitmentMismatch {
+                    block_number,
+                    stored,
+                    derived,
+                }));
+            }
+        }
+
+        tracing::debug!(from=%next, to=%batch_end, "State commitments verified");
+        next = batch_end + 1;
+    }
+
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::macro_prelude::*;
+    use pathfinder_common::{BlockHash, BlockHeader, ContractStateHash};
+
+    use super::*;
+    use crate::{Node, StorageBuilder, TrieUpdate};
+
+    const CONTRACT: ContractAddress = ContractAddress::ONE;
+
+    /// The root of a storage trie whose only leaf is [CONTRACT] with
+    /// `state_hash`.
+    fn storage_root(state_hash: Felt) -> Felt {
+        TrieNode::Edge {
+            child: state_hash,
+            path: CONTRACT.0.view_bits().to_bitvec(),
+        }
+        .hash::<PedersenHash>()
+    }
+
+    /// Creates blocks `0..count` whose storage trie consists of [CONTRACT]
+    /// only, with a different state hash in every block. Headers commit to
+    /// that trie, except for blocks from `corrupt_from` onwards which store a
+    /// bogus state commitment.
+    fn setup(count: u64, corrupt_from: Option<u64>) -> Storage {
+        let storage = StorageBuilder::in_memory().unwrap();
+        let mut db = storage.connection().unwrap();
+        let tx = db.transaction().unwrap();
+
+        for i in 0..count {
+            let block_number = BlockNumber::new_or_panic(i);
+            let state_hash = Felt::from_u64(i + 1);
+            let root = storage_root(state_hash);
+
+            let update = TrieUpdate {
+                nodes_added: vec![(
+                    root,
+                    Node::LeafEdge {
+                        path: CONTRACT.0.view_bits().to_bitvec(),
+                    },
+                )],
+                nodes_removed: vec![],
+                root_commitment: root,
+            };
+            let root_index = tx.insert_storage_trie(&update, block_number).unwrap();
+            tx.insert_storage_root(block_number, root_index).unwrap();
+            tx.insert_contract_state_hash(block_number, CONTRACT, ContractStateHash(state_hash))
+                .unwrap();
+
+            let builder = BlockHeader::builder()
+                .number(block_number)
+                .storage_commitment(StorageCommitment(root))
+                .calculated_state_commitment();
+            let builder = match corrupt_from {
+                Some(corrupt_from) if i >= corrupt_from => {
+                    builder.state_commitment(state_commitment!("0x1234"))
+                }
+                _ => builder,
+            };
+            let header = builder.finalize_with_hash(BlockHash(Felt::from_u64(i)));
+            tx.insert_block_header(&header).unwrap();
+        }
+
+        tx.commit().unwrap();
+        storage
+    }
+
+    #[test]
+    fn consistent() {
+        let storage = setup(3, None);
+
+        let result =
+            verify_state_commitments(&storage, BlockNumber::GENESIS, BlockNumber::new_or_panic(2))
+                .unwrap();
+        assert_eq!(result, None);
+    }
+
+    #[test]
+    fn reports_first_mismatch() {
+        let storage = setup(4, Some(2));
+
+        let result =
+            verify_state_commitments(&storage, BlockNumber::GENESIS, BlockNumber::new_or_panic(3))
+                .unwrap()
+                .unwrap();
+        assert_eq!(result.block_number, BlockNumber::new_or_panic(2));
+        assert_eq!(
+            result.derived,
+            StateCommitment::calculate(
+                StorageCommitment(storage_root(Felt::from_u64(3))),
+                ClassCommitment::ZERO
+            )
+        );
+    }
+
+    #[test]
+    fn tampered_leaf_is_detected() {
+        let storage = setup(2, None);
+        // The stored root hashes still match the headers, but not the leaf.
+        let mut db = storage.connection().unwrap();
+        let tx = db.transaction().unwrap();
+        tx.insert_contract_state_hash(
+            BlockNumber::new_or_panic(1),
+            CONTRACT,
+            contract_state_hash!("0xbad"),
+        )
+        .unwrap();
+        tx.commit().unwrap();
+
+        let result =
+            verify_state_commitments(&storage, BlockNumber::GENESIS, BlockNumber::new_or_panic(1))
+                .unwrap()
+                .unwrap();
+        assert_eq!(result.block_number, BlockNumber::new_or_panic(1));
+        assert_eq!(
+            result.derived,
+            StateCommitment::calculate(
+                StorageCommitment(storage_root(felt!("0xbad"))),
+                ClassCommitment::ZERO
+            )
+        );
+    }
+
+    #[test]
+    fn missing_block_is_an_error() {
+        let storage = setup(2, None);
+
+        verify_state_commitments(&storage, BlockNumber::GENESIS, BlockNumber::new_or_panic(5))
+            .unwrap_err();
+    }
+}
@@ -1,28 +1,47 @@
 //! Local storage.
 //!
 //! Currently this consists of a Sqlite backend implementation.
+//!
+//! Sqlite allows a single writer per database file, so blocks are committed
+//! one at a time: writes made on separate connections are serialized by the
+//! database lock rather than run concurrently. Splitting independent tables
+//! into separate, attached database files would lift that limit, but a
+//! transaction spanning attached databases is [not atomic in WAL
+//! mode](https://sqlite.org/lang_attach.html), so a block could no longer be
+//! made visible with a single atomic write of its header. Only the trie nodes,
+//! which are reachable solely through roots stored in Sqlite, can live
+//! elsewhere, see [TrieBackend].
 
 // This is intended for internal use only -- do not make public.
 mod prelude;
 
+pub mod background_migration;
 mod bloom;
+pub mod compaction;
 mod connection;
+mod consistency;
 pub mod fake;
 mod params;
 mod schema;
+pub mod snapshot;
 pub mod test_utils;
+mod trie_backend;
 
 use std::num::NonZeroU32;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use anyhow::Context;
 pub use bloom::EVENT_KEY_FILTER_LIMIT;
 pub use connection::*;
+pub use consistency::{verify_state_commitments, StateCommitmentMismatch, TrieHasher};
 use pathfinder_common::{BlockHash, BlockNumber};
 use r2d2::Pool;
 use r2d2_sqlite::SqliteConnectionManager;
 use rusqlite::{OpenFlags, OptionalExtension};
+use trie_backend::TrieNodes;
+pub use trie_backend::{rocksdb_path, TrieBackend};
 
 /// Sqlite key used for the PRAGMA user version.
 const VERSION_KEY: &str = "user_version";
@@ -35,6 +54,106 @@ pub enum JournalMode {
     WAL,
 }
 
+/// Set of per-connection [pragmas](https://sqlite.org/pragma.html) applied to
+/// every connection of a [Storage] pool.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PragmaProfile {
+    /// Keeps Sqlite's defaults.
+    #[default]
+    Default,
+    /// Trades memory for read throughput: a large page cache, memory mapped
+    /// I/O and in-memory temporary storage. Intended for read-heavy nodes.
+    ReadOptimized,
+}
+
+impl PragmaProfile {
+    /// The pragmas set by this profile, as `(name, value)` pairs. Values are
+    /// given as Sqlite reports them back, so they can be verified after being
+    /// applied.
+    fn pragmas(self) -> &'static [(&'static str, i64)] {
+        match self {
+            PragmaProfile::Default => &[],
+            PragmaProfile::ReadOptimized => &[
+                // 1 GiB
+                ("mmap_size", 1024 * 1024 * 1024),
+                // Negative values are in KiB, i.e. 256 MiB.
+                ("cache_size", -256 * 1024),
+                // MEMORY
+                ("temp_store", 2),
+            ],
+        }
+    }
+}
+
+/// Per-connection settings which take precedence over those of the
+/// [PragmaProfile]. Unset values are left as they are.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ConnectionSettings {
+    /// How long a connection waits for a lock held by another connection
+    /// before failing with `SQLITE_BUSY`.
+    pub busy_timeout: Option<Duration>,
+    /// The maximum number of bytes of the database file which are memory
+    /// mapped, up to [ConnectionSettings::MAX_MMAP_SIZE].
+    pub mmap_size: Option<u64>,
+    /// The size of each connection's page cache, in KiB.
+    pub cache_size_kib: Option<u64>,
+}
+
+impl ConnectionSettings {
+    /// The largest `mmap_size` Sqlite accepts. Larger values are silently
+    /// capped by Sqlite's compile time `SQLITE_MAX_MMAP_SIZE`.
+    pub const MAX_MMAP_SIZE: u64 = 0x7fff_0000;
+
+    fn validate(&self) -> anyhow::Result<()> {
+        if let Some(mmap_size) = self.mmap_size {
+            anyhow::ensure!(
+                mmap_size <= Self::MAX_MMAP_SIZE,
+                "The memory map size of {mmap_size} bytes exceeds the maximum of {} bytes",
+                Self::MAX_MMAP_SIZE
+            );
+        }
+
+        Ok(())
+    }
+
+    /// The pragmas set by these settings, as `(name, value)` pairs in the form
+    /// Sqlite reports them back.
+    fn pragmas(&self) -> Vec<(&'static str, i64)> {
+        let mut pragmas = Vec::new();
+        if let Some(mmap_size) = self.mmap_size {
+            // Validated to be at most `MAX_MMAP_SIZE`.
+            pragmas.push(("mmap_size", mmap_size as i64));
+        }
+        if let Some(cache_size_kib) = self.cache_size_kib {
+            // Negative values are in KiB.
+            let cache_size_kib: i64 = cache_size_kib.try_into().unwrap_or(i64::MAX);
+            pragmas.push(("cache_size", -cache_size_kib));
+        }
+        if let Some(busy_timeout) = self.busy_timeout {
+            let busy_timeout_ms = busy_timeout.as_millis().try_into().unwrap_or(i64::MAX);
+            pragmas.push(("busy_timeout", busy_timeout_ms));
+        }
+        pragmas
+    }
+}
+
+/// Which data is synced into the database, see [StorageBuilder::sync_mode].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SyncMode {
+    /// Blocks and their state diffs are stored, and the Merkle tries are
+    /// maintained.
+    #[default]
+    Full,
+    /// Only headers, transactions, receipts and events are stored. State diffs
+    /// are not applied, so the database has no contract state or Merkle tries.
+    Light,
+}
+
+impl SyncMode {
+    /// The `storage_flags` entry marking a database synced in light mode.
+    const LIGHT_FLAG: &'static str = "sync_mode_light";
+}
+
 /// Identifies a specific starknet block stored in the database.
 ///
 /// Note that this excludes the `Pending` variant since we never store pending
@@ -89,15 +208,25 @@ struct Inner {
     /// Uses [`Arc`] to allow _shallow_ [Storage] cloning
     database_path: Arc<PathBuf>,
     pool: Pool<SqliteConnectionManager>,
+    /// Labels the pool's metrics.
+    pool_name: &'static str,
     bloom_filter_cache: Arc<bloom::Cache>,
+    trie_nodes: Arc<dyn TrieNodes>,
     trie_prune_mode: TriePruneMode,
+    history_prune_mode: HistoryPruneMode,
 }
 
 pub struct StorageManager {
     database_path: PathBuf,
     journal_mode: JournalMode,
     bloom_filter_cache: Arc<bloom::Cache>,
+    trie_nodes: Arc<dyn TrieNodes>,
+    trie_backend: TrieBackend,
     trie_prune_mode: TriePruneMode,
+    history_prune_mode: HistoryPruneMode,
+    sync_mode: SyncMode,
+    pragma_profile: PragmaProfile,
+    connection_settings: ConnectionSettings,
 }
 
 impl std::fmt::Debug for StorageManager {
@@ -105,21 +234,42 @@ impl std::fmt::Debug for StorageManager {
         f.debug_struct("StorageManager")
             .field("database_path", &self.database_path)
             .field("journal_mode", &self.journal_mode)
+            .field("trie_backend", &self.trie_backend)
             .field("trie_prune_mode", &self.trie_prune_mode)
+            .field("history_prune_mode", &self.history_prune_mode)
+            .field("sync_mode", &self.sync_mode)
+            .field("pragma_profile", &self.pragma_profile)
+            .field("connection_settings", &self.connection_settings)
             .finish()
     }
 }
 
 impl StorageManager {
+    /// The [SyncMode] of the database.
+    pub fn sync_mode(&self) -> SyncMode {
+        self.sync_mode
+    }
+
+    /// The [TrieBackend] storing the database's Merkle trie nodes.
+    pub fn trie_backend(&self) -> TrieBackend {
+        self.trie_backend
+    }
+
     fn create_pool_with_flags(
         &self,
         capacity: NonZeroU32,
         open_flags: OpenFlags,
     ) -> anyhow::Result<Storage> {
         let journal_mode = self.journal_mode;
+        let pragma_profile = self.pragma_profile;
+        let connection_settings = self.connection_settings;
         let pool_manager = SqliteConnectionManager::file(&self.database_path)
             .with_flags(open_flags)
-            .with_init(move |connection| setup_connection(connection, journal_mode));
+            .with_init(move |connection| {
+                setup_connection(connection, journal_mode)?;
+                apply_pragma_profile(connection, pragma_profile)?;
+                apply_connection_settings(connection, connection_settings)
+            });
         let pool = Pool::builder()
             .max_size(capacity.get())
             .build(pool_manager)?;
@@ -127,8 +277,11 @@ impl StorageManager {
         Ok(Storage(Inner {
             database_path: Arc::new(self.database_path.clone()),
             pool,
+            pool_name: "default",
             bloom_filter_cache: self.bloom_filter_cache.clone(),
+            trie_nodes: self.trie_nodes.clone(),
             trie_prune_mode: self.trie_prune_mode,
+            history_prune_mode: self.history_prune_mode,
         }))
     }
 
@@ -149,6 +302,11 @@ pub struct StorageBuilder {
     journal_mode: JournalMode,
     bloom_filter_cache_size: usize,
     trie_prune_mode: Option<TriePruneMode>,
+    history_prune_mode: HistoryPruneMode,
+    trie_backend: Option<TrieBackend>,
+    sync_mode: Option<SyncMode>,
+    pragma_profile: PragmaProfile,
+    connection_settings: ConnectionSettings,
 }
 
 impl StorageBuilder {
@@ -158,6 +316,11 @@ impl StorageBuilder {
             journal_mode: JournalMode::WAL,
             bloom_filter_cache_size: 16,
             trie_prune_mode: None,
+            history_prune_mode: HistoryPruneMode::Archive,
+            trie_backend: None,
+            sync_mode: None,
+            pragma_profile: PragmaProfile::Default,
+            connection_settings: Default::default(),
         }
     }
 
@@ -176,6 +339,40 @@ impl StorageBuilder {
         self
     }
 
+    pub fn history_prune_mode(mut self, history_prune_mode: HistoryPruneMode) -> Self {
+        self.history_prune_mode = history_prune_mode;
+        self
+    }
+
+    /// Sets where trie nodes are stored. This can only be chosen when creating
+    /// a new database, `None` uses the backend of an existing database or
+    /// SQLite for a new one.
+    pub fn trie_backend(mut self, trie_backend: Option<TrieBackend>) -> Self {
+        self.trie_backend = trie_backend;
+        self
+    }
+
+    /// Sets which data is synced into the database. This can only be chosen
+    /// when creating a new database, `None` uses the mode of an existing
+    /// database or [SyncMode::Full] for a new one.
+    pub fn sync_mode(mut self, sync_mode: Option<SyncMode>) -> Self {
+        self.sync_mode = sync_mode;
+        self
+    }
+
+    /// Sets the [PragmaProfile] applied to every pooled connection.
+    pub fn pragma_profile(mut self, pragma_profile: PragmaProfile) -> Self {
+        self.pragma_profile = pragma_profile;
+        self
+    }
+
+    /// Sets the [ConnectionSettings] applied to every pooled connection, on
+    /// top of the [PragmaProfile].
+    pub fn connection_settings(mut self, connection_settings: ConnectionSettings) -> Self {
+        self.connection_settings = connection_settings;
+        self
+    }
+
     /// Convenience function for tests to create an in-memory database.
     pub fn in_memory() -> anyhow::Result<Storage> {
         Self::in_memory_with_trie_pruning(TriePruneMode::Archive)
@@ -184,6 +381,22 @@ impl StorageBuilder {
     /// Convenience function for tests to create an in-memory database with a
     /// specific trie prune mode.
     pub fn in_memory_with_trie_pruning(trie_prune_mode: TriePruneMode) -> anyhow::Result<Storage> {
+        Self::in_memory_with_pruning(trie_prune_mode, HistoryPruneMode::Archive)
+    }
+
+    /// Convenience function for tests to create an in-memory database with
+    /// state history pruning enabled.
+    pub fn in_memory_with_history_pruning(num_blocks_kept: u64) -> anyhow::Result<Storage> {
+        Self::in_memory_with_pruning(
+            TriePruneMode::Archive,
+            HistoryPruneMode::Prune { num_blocks_kept },
+        )
+    }
+
+    fn in_memory_with_pruning(
+        trie_prune_mode: TriePruneMode,
+        history_prune_mode: HistoryPruneMode,
+    ) -> anyhow::Result<Storage> {
         // Create a unique database name so that they are not shared between
         // concurrent tests. i.e. Make every in-mem Storage unique.
         static COUNT: std::sync::Mutex<u64> = std::sync::Mutex::new(0);
@@ -203,6 +416,7 @@ impl StorageBuilder {
 
         let mut storage = Self::file(database_path)
             .journal_mode(JournalMode::Rollback)
+            .history_prune_mode(history_prune_mode)
             .migrate()?;
 
         if let TriePruneMode::Prune { .. } = trie_prune_mode {
@@ -252,6 +466,19 @@ impl StorageBuilder {
         // Set the journal mode to the desired value.
         setup_journal_mode(&mut connection, self.journal_mode).context("Setting journal mode")?;
 
+        // Sqlite silently ignores or clamps pragma values it cannot apply, so verify the
+        // profile once here instead of finding out from degraded performance later.
+        apply_pragma_profile(&mut connection, self.pragma_profile)
+            .context("Applying pragma profile")?;
+        verify_pragma_profile(&connection, self.pragma_profile)?;
+        self.connection_settings
+            .validate()
+            .context("Validating connection settings")?;
+        apply_connection_settings(&mut connection, self.connection_settings)
+            .context("Applying connection settings")?;
+        verify_pragmas(&connection, &self.connection_settings.pragmas())
+            .context("Verifying connection settings")?;
+
         // Validate that configuration matches database flags.
         let trie_prune_mode = self.determine_trie_prune_mode(&mut connection, is_new_database)?;
         if let TriePruneMode::Prune { num_blocks_kept } = trie_prune_mode {
@@ -259,6 +486,10 @@ impl StorageBuilder {
         } else {
             tracing::info!("Merkle trie pruning disabled");
         }
+        let history_prune_mode = self.determine_history_prune_mode(&mut connection)?;
+        let trie_backend = self.determine_trie_backend(&mut connection, is_new_database)?;
+        let trie_nodes = trie_backend.open(&self.database_path)?;
+        let sync_mode = self.determine_sync_mode(&mut connection, is_new_database)?;
 
         connection
             .close()
@@ -269,7 +500,102 @@ impl StorageBuilder {
             database_path: self.database_path,
             journal_mode: self.journal_mode,
             bloom_filter_cache: Arc::new(bloom::Cache::with_size(self.bloom_filter_cache_size)),
+            trie_nodes,
+            trie_backend,
             trie_prune_mode,
+            history_prune_mode,
+            sync_mode,
+            pragma_profile: self.pragma_profile,
+            connection_settings: self.connection_settings,
+        })
+    }
+
+    /// Opens an existing database without migrating or otherwise modifying it,
+    /// returning a [storage manager](StorageManager).
+    ///
+    /// This is meant for serving a database which another pathfinder instance
+    /// is syncing into. Only [StorageManager::create_read_only_pool] should be
+    /// used with the result.
+    pub fn open_read_only(self) -> anyhow::Result<StorageManager> {
+        let connection = rusqlite::Connection::open_with_flags(
+            &self.database_path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )
+        .context("Opening database in read-only mode")?;
+
+        let current_revision = schema_version(&connection)?;
+        let latest_revision = schema::BASE_SCHEMA_REVISION + schema::migrations().len();
+        anyhow::ensure!(
+            current_revision == latest_revision,
+            "Database version {current_revision} does not match the expected version \
+             {latest_revision}. The database must be migrated by a pathfinder instance of the \
+             same version which is not running in read-only mode."
+        );
+
+        let storage_flag_is_set = |flag: &str| -> anyhow::Result<bool> {
+            let is_set = connection
+                .query_row("SELECT 1 FROM storage_flags WHERE flag = ?", [flag], |_| {
+                    Ok(())
+                })
+                .optional()?
+                .is_some();
+            Ok(is_set)
+        };
+
+        let trie_prune_mode = match (self.trie_prune_mode, storage_flag_is_set("prune_tries")?) {
+            (None, true) => TriePruneMode::Prune {
+                num_blocks_kept: 20,
+            },
+            (None, false) => TriePruneMode::Archive,
+            (Some(TriePruneMode::Archive), true) => {
+                anyhow::bail!("The database was created with Merkle trie pruning enabled.")
+            }
+            (Some(TriePruneMode::Prune { .. }), false) => {
+                anyhow::bail!("The database was not created with Merkle trie pruning enabled.")
+            }
+            (Some(mode), _) => mode,
+        };
+
+        let history_prune_mode = self.history_prune_mode;
+        if history_prune_mode == HistoryPruneMode::Archive && storage_flag_is_set("prune_history")?
+        {
+            anyhow::bail!("The database state history has been pruned.");
+        }
+
+        let sync_mode = if storage_flag_is_set(SyncMode::LIGHT_FLAG)? {
+            SyncMode::Light
+        } else {
+            SyncMode::Full
+        };
+        if let Some(requested) = self.sync_mode.filter(|requested| *requested != sync_mode) {
+            anyhow::bail!("The database was synced in {sync_mode:?} mode, not {requested:?}.");
+        }
+
+        // RocksDB only allows a single process to open a database, which is held by the
+        // node syncing into it.
+        if storage_flag_is_set(TrieBackend::ROCKSDB_FLAG)? {
+            anyhow::bail!(
+                "The database stores its Merkle tries in RocksDB, which cannot be opened in \
+                 read-only mode."
+            );
+        }
+
+        connection
+            .close()
+            .map_err(|(_connection, error)| error)
+            .context("Closing DB after opening")?;
+
+        Ok(StorageManager {
+            database_path: self.database_path,
+            journal_mode: self.journal_mode,
+            bloom_filter_cache: Arc::new(bloom::Cache::with_size(self.bloom_filter_cache_size)),
+            trie_nodes: TrieBackend::Sqlite.open(&self.database_path)?,
+            trie_backend: TrieBackend::Sqlite,
+            trie_prune_mode,
+            history_prune_mode,
+            sync_mode,
+            pragma_profile: self.pragma_profile,
+            connection_settings: self.connection_settings,
         })
     }
 
@@ -331,24 +657,183 @@ impl StorageBuilder {
 
         Ok(trie_prune_mode)
     }
+
+    /// State history pruning can be enabled on any database, in which case the
+    /// existing history is pruned right away. It cannot be disabled once
+    /// enabled, as the history of previously pruned blocks is incomplete.
+    fn determine_history_prune_mode(
+        &self,
+        connection: &mut rusqlite::Connection,
+    ) -> anyhow::Result<HistoryPruneMode> {
+        let prune_flag_is_set = connection
+            .query_row(
+                "SELECT 1 FROM storage_flags WHERE flag = 'prune_history'",
+                [],
+                |_| Ok(()),
+            )
+            .optional()
+            .map(|x| x.is_some())?;
+
+        match self.history_prune_mode {
+            HistoryPruneMode::Archive => {
+                if prune_flag_is_set {
+                    anyhow::bail!(
+                        "Cannot disable state history pruning on a database that has been pruned."
+                    )
+                }
+                tracing::info!("State history pruning disabled");
+            }
+            HistoryPruneMode::Prune { num_blocks_kept } => {
+                let tx = connection.transaction()?;
+                if !prune_flag_is_set {
+                    // Syncing only prunes the history it leaves behind, so an existing archive
+                    // catches up in one go.
+                    connection::state_update::prune_state_history_before_horizon(
+                        &tx,
+                        num_blocks_kept,
+                    )
+                    .context("Pruning existing state history")?;
+                }
+                tx.execute(
+                    "INSERT OR IGNORE INTO storage_flags (flag) VALUES ('prune_history')",
+                    [],
+                )?;
+                tx.commit()?;
+                tracing::info!(history_kept=%num_blocks_kept, "State history pruning enabled");
+            }
+        }
+
+        Ok(self.history_prune_mode)
+    }
+
+    /// The trie backend is chosen when creating a database, and cannot be
+    /// changed afterwards since trie nodes are not migrated between backends.
+    /// Existing databases use the backend they were created with unless one
+    /// is explicitly requested.
+    fn determine_trie_backend(
+        &self,
+        connection: &mut rusqlite::Connection,
+        is_new_database: bool,
+    ) -> anyhow::Result<TrieBackend> {
+        if is_new_database {
+            let trie_backend = self.trie_backend.unwrap_or_default();
+            if trie_backend == TrieBackend::RocksDb {
+                connection.execute(
+                    "INSERT OR IGNORE INTO storage_flags (flag) VALUES (?)",
+                    [TrieBackend::ROCKSDB_FLAG],
+                )?;
+                tracing::info!("Created new database with Merkle tries stored in RocksDB.");
+            }
+            return Ok(trie_backend);
+        }
+
+        let rocksdb_flag_is_set = connection
+            .query_row(
+                "SELECT 1 FROM storage_flags WHERE flag = ?",
+                [TrieBackend::ROCKSDB_FLAG],
+                |_| Ok(()),
+            )
+            .optional()
+            .map(|x| x.is_some())?;
+        let database_backend = if rocksdb_flag_is_set {
+            TrieBackend::RocksDb
+        } else {
+            TrieBackend::Sqlite
+        };
+
+        match self.trie_backend {
+            Some(trie_backend) if trie_backend != database_backend => anyhow::bail!(
+                "Cannot change the trie backend of an existing database from \
+                 {database_backend:?} to {trie_backend:?}."
+            ),
+            _ => Ok(database_backend),
+        }
+    }
+
+    /// Like the trie backend, the sync mode is chosen when creating a database.
+    /// A light database has no state to switch to full sync from, and a full
+    /// database would be left with state that stops being updated.
+    fn determine_sync_mode(
+        &self,
+        connection: &mut rusqlite::Connection,
+        is_new_database: bool,
+    ) -> anyhow::Result<SyncMode> {
+        if is_new_database {
+            let sync_mode = self.sync_mode.unwrap_or_default();
+            if sync_mode == SyncMode::Light {
+                connection.execute(
+                    "INSERT OR IGNORE INTO storage_flags (flag) VALUES (?)",
+                    [SyncMode::LIGHT_FLAG],
+                )?;
+                tracing::info!("Created new database for light sync.");
+            }
+            return Ok(sync_mode);
+        }
+
+        let light_flag_is_set = connection
+            .query_row(
+                "SELECT 1 FROM storage_flags WHERE flag = ?",
+                [SyncMode::LIGHT_FLAG],
+                |_| Ok(()),
+            )
+            .optional()
+            .map(|x| x.is_some())?;
+        let database_mode = if light_flag_is_set {
+            SyncMode::Light
+        } else {
+            SyncMode::Full
+        };
+
+        match self.sync_mode {
+            Some(sync_mode) if sync_mode != database_mode => anyhow::bail!(
+                "Cannot change the sync mode of an existing database from {database_mode:?} to \
+                 {sync_mode:?}."
+            ),
+            _ => Ok(database_mode),
+        }
+    }
 }
 
 impl Storage {
     /// Returns a new Sqlite [Connection] to the database.
+    ///
+    /// Waits for a pooled connection to become available if all of them are
+    /// in use.
     pub fn connection(&self) -> anyhow::Result<Connection> {
-        let conn = self.0.pool.get()?;
+        let started = Instant::now();
+        let conn = self.0.pool.get();
+
+        // Also record waits which timed out, as those are the longest.
+        let pool = self.0.pool_name;
+        metrics::histogram!(METRIC_CONNECTION_WAIT, started.elapsed(), "pool" => pool);
+        let conn = conn?;
+        let state = self.0.pool.state();
+        let in_use = state.connections - state.idle_connections;
+        metrics::gauge!(METRIC_CONNECTIONS_IN_USE, f64::from(in_use), "pool" => pool);
+
         Ok(Connection::new(
             conn,
             self.0.bloom_filter_cache.clone(),
+            self.0.trie_nodes.clone(),
             self.0.trie_prune_mode,
+            self.0.history_prune_mode,
         ))
     }
 
     pub fn path(&self) -> &Path {
         &self.0.database_path
     }
+
+    /// Labels the metrics of this connection pool with `name`.
+    pub fn with_pool_name(mut self, name: &'static str) -> Self {
+        self.0.pool_name = name;
+        self
+    }
 }
 
+const METRIC_CONNECTION_WAIT: &str = "storage_connection_wait_seconds";
+const METRIC_CONNECTIONS_IN_USE: &str = "storage_connections_in_use";
+
 fn setup_journal_mode(
     connection: &mut rusqlite::Connection,
     journal_mode: JournalMode,
@@ -396,6 +881,56 @@ fn setup_connection(
     Ok(())
 }
 
+fn apply_pragma_profile(
+    connection: &mut rusqlite::Connection,
+    profile: PragmaProfile,
+) -> Result<(), rusqlite::Error> {
+    for (name, value) in profile.pragmas() {
+        connection.pragma_update(None, name, value)?;
+    }
+
+    Ok(())
+}
+
+fn apply_connection_settings(
+    connection: &mut rusqlite::Connection,
+    settings: ConnectionSettings,
+) -> Result<(), rusqlite::Error> {
+    for (name, value) in settings.pragmas() {
+        connection.pragma_update(None, name, value)?;
+    }
+
+    Ok(())
+}
+
+/// Checks that every pragma of the profile has taken effect.
+fn verify_pragma_profile(
+    connection: &rusqlite::Connection,
+    profile: PragmaProfile,
+) -> anyhow::Result<()> {
+    verify_pragmas(connection, profile.pragmas())
+        .with_context(|| format!("Verifying the {profile:?} pragma profile"))
+}
+
+/// Checks that every pragma has taken the given value.
+fn verify_pragmas(
+    connection: &rusqlite::Connection,
+    pragmas: &[(&str, i64)],
+) -> anyhow::Result<()> {
+    for (name, expected) in pragmas {
+        let actual: i64 = connection
+            .pragma_query_value(None, name, |row| row.get(0))
+            .with_context(|| format!("Querying pragma {name}"))?;
+
+        anyhow::ensure!(
+            actual == *expected,
+            "Sqlite did not accept pragma {name}: requested {expected}, got {actual}"
+        );
+    }
+
+    Ok(())
+}
+
 /// Migrates the database to the latest version. This __MUST__ be called
 /// at the beginning of the application.
 fn migrate_database(connection: &mut rusqlite::Connection) -> anyhow::Result<()> {
@@ -408,6 +943,12 @@ fn migrate_database(connection: &mut rusqlite::Connection) -> anyhow::Result<()>
 
     // Apply the base schema if the database is new.
     if current_revision == 0 {
+        // This can only be changed before any tables are created, and lets free pages
+        // be reclaimed without rebuilding the database.
+        connection
+            .pragma_update(None, "auto_vacuum", "INCREMENTAL")
+            .context("Enabling incremental vacuum")?;
+
         let tx = connection
             .transaction()
             .context("Create database transaction")?;
@@ -611,4 +1152,219 @@ mod tests {
             "Cannot enable Merkle trie pruning on a database that was not created with it enabled."
         );
     }
+
+    #[test]
+    fn changing_trie_backend_fails() {
+        let (_db_dir, db_path) = rpc_test_db_fixture();
+
+        assert_eq!(
+            StorageBuilder::file(db_path)
+                .trie_backend(Some(TrieBackend::RocksDb))
+                .migrate()
+                .unwrap_err()
+                .to_string(),
+            "Cannot change the trie backend of an existing database from Sqlite to RocksDb."
+        );
+    }
+
+    #[test]
+    fn enabling_history_pruning_prunes_existing_history() {
+        use pathfinder_common::macro_prelude::*;
+        use pathfinder_common::{BlockHeader, StateUpdate, StorageValue};
+
+        let db_dir = tempfile::TempDir::new().unwrap();
+        let db_path = db_dir.path().join("archive.sqlite");
+
+        let storage = StorageBuilder::file(db_path.clone())
+            .migrate()
+            .unwrap()
+            .create_pool(NonZeroU32::new(1).unwrap())
+            .unwrap();
+        let mut db = storage.connection().unwrap();
+        let tx = db.transaction().unwrap();
+        let contract = contract_address_bytes!(b"contract");
+        let key = storage_address_bytes!(b"key");
+        let mut header = BlockHeader::builder().finalize_with_hash(block_hash!("0x0"));
+        for i in 0..4u64 {
+            if i > 0 {
+                header = header
+                    .child_builder()
+                    .finalize_with_hash(BlockHash(pathfinder_crypto::Felt::from_u64(i)));
+            }
+            let state_update = StateUpdate::default()
+                .with_deployed_contract(contract, class_hash_bytes!(b"class"))
+                .with_storage_update(
+                    contract,
+                    key,
+                    StorageValue(pathfinder_crypto::Felt::from_u64(i)),
+                );
+            tx.insert_block_header(&header).unwrap();
+            tx.insert_state_update(header.number, &state_update)
+                .unwrap();
+        }
+        tx.commit().unwrap();
+        drop(db);
+        drop(storage);
+
+        let storage = StorageBuilder::file(db_path)
+            .history_prune_mode(HistoryPruneMode::Prune { num_blocks_kept: 2 })
+            .migrate()
+            .unwrap()
+            .create_pool(NonZeroU32::new(1).unwrap())
+            .unwrap();
+        let mut db = storage.connection().unwrap();
+        let tx = db.transaction().unwrap();
+
+        // The horizon is block 1, which supersedes the updates of the genesis block.
+        let storage_updates: u64 = tx
+            .inner()
+            .query_row("SELECT COUNT(*) FROM storage_updates", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(storage_updates, 3);
+        let contract_updates: u64 = tx
+            .inner()
+            .query_row("SELECT COUNT(*) FROM contract_updates", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(contract_updates, 3);
+        assert_eq!(
+            tx.storage_value(BlockId::Latest, contract, key).unwrap(),
+            Some(StorageValue(pathfinder_crypto::Felt::from_u64(3)))
+        );
+    }
+
+    #[test]
+    fn sync_mode_is_kept() {
+        let db_dir = tempfile::TempDir::new().unwrap();
+        let db_path = db_dir.path().join("light.sqlite");
+
+        let manager = StorageBuilder::file(db_path.clone())
+            .sync_mode(Some(SyncMode::Light))
+            .migrate()
+            .unwrap();
+        assert_eq!(manager.sync_mode(), SyncMode::Light);
+        drop(manager);
+
+        let manager = StorageBuilder::file(db_path.clone()).migrate().unwrap();
+        assert_eq!(manager.sync_mode(), SyncMode::Light);
+        let manager = StorageBuilder::file(db_path.clone())
+            .open_read_only()
+            .unwrap();
+        assert_eq!(manager.sync_mode(), SyncMode::Light);
+
+        assert_eq!(
+            StorageBuilder::file(db_path)
+                .sync_mode(Some(SyncMode::Full))
+                .migrate()
+                .unwrap_err()
+                .to_string(),
+            "Cannot change the sync mode of an existing database from Light to Full."
+        );
+    }
+
+    #[test]
+    fn read_optimized_pragma_profile() {
+        let db_dir = tempfile::TempDir::new().unwrap();
+        let db_path = db_dir.path().join("read_optimized.sqlite");
+
+        let storage = StorageBuilder::file(db_path.clone())
+            .pragma_profile(PragmaProfile::ReadOptimized)
+            .migrate()
+            .unwrap()
+            .create_pool(NonZeroU32::new(1).unwrap())
+            .unwrap();
+        storage.connection().unwrap();
+
+        let mut conn = rusqlite::Connection::open(db_path).unwrap();
+        // A connection without the profile does not pass verification..
+        verify_pragma_profile(&conn, PragmaProfile::ReadOptimized).unwrap_err();
+        // ..but does once it has been applied.
+        apply_pragma_profile(&mut conn, PragmaProfile::ReadOptimized).unwrap();
+        verify_pragma_profile(&conn, PragmaProfile::ReadOptimized).unwrap();
+    }
+
+    #[test]
+    fn connection_settings() {
+        let db_dir = tempfile::TempDir::new().unwrap();
+        let db_path = db_dir.path().join("connection_settings.sqlite");
+        let settings = ConnectionSettings {
+            busy_timeout: Some(Duration::from_millis(1500)),
+            mmap_size: Some(64 * 1024 * 1024),
+            cache_size_kib: Some(32 * 1024),
+        };
+
+        let storage = StorageBuilder::file(db_path.clone())
+            .pragma_profile(PragmaProfile::ReadOptimized)
+            .connection_settings(settings)
+            .migrate()
+            .unwrap()
+            .create_pool(NonZeroU32::new(1).unwrap())
+            .unwrap();
+
+        let mut conn = storage.connection().unwrap();
+        let tx = conn.transaction().unwrap();
+        let pragma = |name: &str| -> i64 {
+            tx.inner()
+                .pragma_query_value(None, name, |row| row.get(0))
+                .unwrap()
+        };
+        // The settings take precedence over the profile's pragmas.
+        assert_eq!(pragma("mmap_size"), 64 * 1024 * 1024);
+        assert_eq!(pragma("cache_size"), -32 * 1024);
+        assert_eq!(pragma("busy_timeout"), 1500);
+        // Pragmas the settings leave unset keep the profile's value.
+        assert_eq!(pragma("temp_store"), 2);
+    }
+
+    #[test]
+    fn mmap_size_is_validated() {
+        let db_dir = tempfile::TempDir::new().unwrap();
+        let db_path = db_dir.path().join("mmap_size.sqlite");
+
+        StorageBuilder::file(db_path)
+            .connection_settings(ConnectionSettings {
+                mmap_size: Some(ConnectionSettings::MAX_MMAP_SIZE + 1),
+                ..Default::default()
+            })
+            .migrate()
+            .unwrap_err();
+    }
+
+    #[test]
+    fn read_only_sees_writes_of_other_instance() {
+        use pathfinder_common::macro_prelude::*;
+        use pathfinder_common::BlockHeader;
+
+        let db_dir = tempfile::TempDir::new().unwrap();
+        let db_path = db_dir.path().join("shared.sqlite");
+
+        // The database must already exist and be migrated.
+        StorageBuilder::file(db_path.clone())
+            .open_read_only()
+            .unwrap_err();
+
+        let writer = StorageBuilder::file(db_path.clone())
+            .migrate()
+            .unwrap()
+            .create_pool(NonZeroU32::new(1).unwrap())
+            .unwrap();
+        let reader = StorageBuilder::file(db_path)
+            .open_read_only()
+            .unwrap()
+            .create_read_only_pool(NonZeroU32::new(1).unwrap())
+            .unwrap();
+
+        let header = BlockHeader::builder().finalize_with_hash(block_hash!("0xabc"));
+        let mut db = writer.connection().unwrap();
+        let tx = db.transaction().unwrap();
+        tx.insert_block_header(&header).unwrap();
+        tx.commit().unwrap();
+
+        let mut db = reader.connection().unwrap();
+        let tx = db.transaction().unwrap();
+        let result = tx.block_header(BlockId::Latest).unwrap();
+        assert_eq!(result, Some(header));
+        tx.insert_block_header(&BlockHeader::default()).unwrap_err();
+    }
 }
@@ -24,11 +24,35 @@ mod revision_0061;
 mod revision_0062;
 mod revision_0063;
 mod revision_0064;
+mod revision_0065;
+mod revision_0066;
+mod revision_0067;
+mod revision_0068;
+mod revision_0069;
+mod revision_0070;
+mod revision_0071;
+mod revision_0072;
+mod revision_0073;
+mod revision_0074;
+mod revision_0075;
+mod revision_0076;
+mod revision_0077;
+mod revision_0078;
+
+use std::ops::RangeInclusive;
 
 pub(crate) use base::base_schema;
+use pathfinder_common::BlockNumber;
+pub(crate) use revision_0066::CONTRACT_EVENT_BLOCKS;
+pub(crate) use revision_0067::TRANSACTION_SENDERS;
+pub(crate) use revision_0073::L1_HANDLER_MESSAGES;
+pub(crate) use revision_0074::BLOCK_STATS;
 
 type MigrationFn = fn(&rusqlite::Transaction<'_>) -> anyhow::Result<()>;
 
+pub(crate) type BackgroundMigrationFn =
+    fn(&rusqlite::Transaction<'_>, RangeInclusive<BlockNumber>) -> anyhow::Result<()>;
+
 /// The full list of pathfinder migrations.
 pub fn migrations() -> &'static [MigrationFn] {
     &[
@@ -56,6 +80,43 @@ pub fn migrations() -> &'static [MigrationFn] {
         revision_0062::migrate,
         revision_0063::migrate,
         revision_0064::migrate,
+        revision_0065::migrate,
+        revision_0066::migrate,
+        revision_0067::migrate,
+        revision_0068::migrate,
+        revision_0069::migrate,
+        revision_0070::migrate,
+        revision_0071::migrate,
+        revision_0072::migrate,
+        revision_0073::migrate,
+        revision_0074::migrate,
+        revision_0075::migrate,
+        revision_0076::migrate,
+        revision_0077::migrate,
+        revision_0078::migrate,
+    ]
+}
+
+/// The full list of [background migrations](crate::background_migration) by
+/// name, in the order they are run.
+pub(crate) fn background_migrations() -> &'static [(&'static str, BackgroundMigrationFn)] {
+    &[
+        (
+            revision_0066::CONTRACT_EVENT_BLOCKS,
+            revision_0066::index_contract_event_blocks,
+        ),
+        (
+            revision_0067::TRANSACTION_SENDERS,
+            revision_0067::index_transaction_senders,
+        ),
+        (
+            revision_0073::L1_HANDLER_MESSAGES,
+            revision_0073::index_l1_handler_messages,
+        ),
+        (
+            revision_0074::BLOCK_STATS,
+            revision_0074::compute_block_stats,
+        ),
     ]
 }
 
@@ -0,0 +1,23 @@
+use anyhow::Context;
+
+/// Adds the `reorgs` table, which records every L2 reorg rolled back by sync.
+///
+/// The rolled back blocks are purged from storage, so the table intentionally
+/// does not reference `block_headers`.
+pub(crate) fn migrate(tx: &rusqlite::Transaction<'_>) -> anyhow::Result<()> {
+    tracing::info!("Adding reorgs table");
+
+    tx.execute_batch(
+        r"CREATE TABLE reorgs (
+            id INTEGER PRIMARY KEY,
+            old_head_number INTEGER NOT NULL,
+            old_head_hash BLOB NOT NULL,
+            common_ancestor_number INTEGER,
+            common_ancestor_hash BLOB,
+            detected_at INTEGER NOT NULL
+        );",
+    )
+    .context("Adding reorgs table")?;
+
+    Ok(())
+}
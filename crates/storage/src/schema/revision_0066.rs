@@ -0,0 +1,77 @@
+use std::collections::HashSet;
+use std::ops::RangeInclusive;
+
+use anyhow::Context;
+use pathfinder_common::{BlockNumber, ContractAddress};
+
+use crate::connection::transaction::{compression, dto};
+use crate::params::{params, RowExt};
+
+/// The name of the background migration filling `contract_event_blocks`.
+pub(crate) const CONTRACT_EVENT_BLOCKS: &str = "contract_event_blocks";
+
+/// Adds the `contract_event_blocks` table, which lists the blocks each
+/// contract emitted events in. The existing blocks are indexed by a background
+/// migration, registered once the table tracking those exists in
+/// [revision 73](super::revision_0073).
+///
+/// This lets `getEvents` queries filtering by contract address jump straight
+/// to the relevant blocks instead of checking every block's Bloom filter.
+pub(crate) fn migrate(tx: &rusqlite::Transaction<'_>) -> anyhow::Result<()> {
+    tx.execute_batch(
+        r"
+        CREATE TABLE contract_event_blocks (
+            contract_address BLOB NOT NULL,
+            block_number INTEGER NOT NULL,
+            PRIMARY KEY (contract_address, block_number)
+        ) WITHOUT ROWID;
+        CREATE INDEX contract_event_blocks_block_number ON contract_event_blocks(block_number);
+    ",
+    )
+    .context("Creating contract event blocks table")
+}
+
+/// Indexes the events of `blocks` by contract address.
+pub(crate) fn index_contract_event_blocks(
+    tx: &rusqlite::Transaction<'_>,
+    blocks: RangeInclusive<BlockNumber>,
+) -> anyhow::Result<()> {
+    let mut query_statement = tx.prepare_cached(
+        r"SELECT block_number, events
+        FROM transactions
+        WHERE events IS NOT NULL AND block_number BETWEEN ? AND ?
+        ORDER BY block_number",
+    )?;
+
+    let mut insert_statement = tx.prepare_cached(
+        r"INSERT OR IGNORE INTO contract_event_blocks (contract_address, block_number) VALUES (?, ?)",
+    )?;
+
+    let mut rows = query_statement.query(params![blocks.start(), blocks.end()])?;
+
+    while let Some(row) = rows.next().context("Fetching next block of events")? {
+        let block_number = row.get_block_number(0)?;
+        let events = row.get_blob(1)?;
+
+        let events = compression::decompress_events(events).context("Decompressing events")?;
+        let events: dto::EventsForBlock =
+            bincode::serde::decode_from_slice(&events, bincode::config::standard())
+                .context("Deserializing events")?
+                .0;
+
+        let contracts = events
+            .events()
+            .into_iter()
+            .flatten()
+            .map(|event| ContractAddress(event.from_address.into()))
+            .collect::<HashSet<_>>();
+
+        for contract in contracts {
+            insert_statement
+                .execute(params![&contract, &block_number])
+                .context("Inserting contract event block")?;
+        }
+    }
+
+    Ok(())
+}
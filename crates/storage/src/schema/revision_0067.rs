@@ -0,0 +1,81 @@
+use std::ops::RangeInclusive;
+
+use anyhow::Context;
+use pathfinder_common::transaction::Transaction;
+use pathfinder_common::BlockNumber;
+
+use crate::connection::transaction::{compression, dto};
+use crate::params::{params, RowExt};
+
+/// The name of the background migration filling `transaction_senders`.
+pub(crate) const TRANSACTION_SENDERS: &str = "transaction_senders";
+
+/// Adds the `transaction_senders` table, which lists the transactions sent by
+/// each account. The existing blocks are indexed by a background migration,
+/// registered once the table tracking those exists in
+/// [revision 73](super::revision_0073).
+pub(crate) fn migrate(tx: &rusqlite::Transaction<'_>) -> anyhow::Result<()> {
+    tx.execute_batch(
+        r"
+        CREATE TABLE transaction_senders (
+            sender_address BLOB NOT NULL,
+            block_number INTEGER NOT NULL,
+            idx INTEGER NOT NULL,
+            PRIMARY KEY (sender_address, block_number, idx)
+        ) WITHOUT ROWID;
+        CREATE INDEX transaction_senders_block_number ON transaction_senders(block_number);
+    ",
+    )
+    .context("Creating transaction senders table")
+}
+
+/// Indexes the transactions of `blocks` by sender address.
+pub(crate) fn index_transaction_senders(
+    tx: &rusqlite::Transaction<'_>,
+    blocks: RangeInclusive<BlockNumber>,
+) -> anyhow::Result<()> {
+    let mut query_statement = tx.prepare_cached(
+        r"SELECT block_number, transactions
+        FROM transactions
+        WHERE block_number BETWEEN ? AND ?
+        ORDER BY block_number",
+    )?;
+
+    // Blocks inserted after the schema migration are already indexed.
+    let mut insert_statement = tx.prepare_cached(
+        r"INSERT OR IGNORE INTO transaction_senders (sender_address, block_number, idx)
+        VALUES (?, ?, ?)",
+    )?;
+
+    let mut rows = query_statement.query(params![blocks.start(), blocks.end()])?;
+
+    while let Some(row) = rows.next().context("Fetching next block of transactions")? {
+        let block_number = row.get_block_number(0)?;
+        let transactions = row.get_blob(1)?;
+
+        let transactions = compression::decompress_transactions(transactions)
+            .context("Decompressing transactions")?;
+        let transactions: dto::TransactionsWithReceiptsForBlock =
+            bincode::serde::decode_from_slice(&transactions, bincode::config::standard())
+                .context("Deserializing transactions")?
+                .0;
+
+        for (idx, transaction) in transactions
+            .transactions_with_receipts()
+            .into_iter()
+            .enumerate()
+        {
+            let transaction = Transaction::from(transaction.transaction);
+            let Some(sender) = transaction.variant.sender_address() else {
+                continue;
+            };
+
+            let idx: i64 = idx.try_into()?;
+            insert_statement
+                .execute(params![&sender, &block_number, &idx])
+                .context("Inserting transaction sender")?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,18 @@
+use anyhow::Context;
+
+/// Adds the `compiled_classes` table, caching the CASM compiled from Sierra
+/// classes which are not necessarily declared yet, such as classes of
+/// submitted or simulated declare transactions.
+pub(crate) fn migrate(tx: &rusqlite::Transaction<'_>) -> anyhow::Result<()> {
+    tracing::info!("Adding compiled classes table");
+
+    tx.execute_batch(
+        r"CREATE TABLE compiled_classes (
+            sierra_hash BLOB PRIMARY KEY,
+            definition BLOB NOT NULL
+        );",
+    )
+    .context("Adding compiled classes table")?;
+
+    Ok(())
+}
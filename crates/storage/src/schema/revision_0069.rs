@@ -0,0 +1,24 @@
+use anyhow::Context;
+
+/// Adds the `submitted_transactions` table, tracking the transactions submitted
+/// through this node so that they can be re-broadcast if the gateway drops
+/// them.
+pub(crate) fn migrate(tx: &rusqlite::Transaction<'_>) -> anyhow::Result<()> {
+    tracing::info!("Adding submitted transactions table");
+
+    tx.execute_batch(
+        r"CREATE TABLE submitted_transactions (
+            hash BLOB PRIMARY KEY,
+            sender_address BLOB NOT NULL,
+            request BLOB NOT NULL,
+            submitted_at INTEGER NOT NULL,
+            last_broadcast_at INTEGER NOT NULL,
+            broadcast_count INTEGER NOT NULL,
+            rejection TEXT
+        );
+        CREATE INDEX submitted_transactions_submitted_at ON submitted_transactions(submitted_at);",
+    )
+    .context("Adding submitted transactions table")?;
+
+    Ok(())
+}
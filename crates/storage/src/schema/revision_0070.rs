@@ -0,0 +1,109 @@
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use pathfinder_common::receipt::Receipt;
+use pathfinder_common::transaction::Transaction;
+
+use crate::connection::fee_stats::encoded_fee_percentiles;
+use crate::connection::transaction::{compression, dto};
+use crate::params::{named_params, RowExt};
+
+/// The number of most recent blocks whose fee stats are filled in. This is
+/// the largest range served by `pathfinder_getFeeHistory`.
+const BACKFILLED_BLOCKS: i64 = 1024;
+
+/// Adds the `block_fee_stats` table, recording the gas prices and the
+/// distribution of actual fees of each block, and fills it for the most recent
+/// blocks.
+pub(crate) fn migrate(tx: &rusqlite::Transaction<'_>) -> anyhow::Result<()> {
+    tx.execute_batch(
+        r"CREATE TABLE block_fee_stats (
+            block_number INTEGER PRIMARY KEY,
+            eth_l1_gas_price BLOB NOT NULL,
+            strk_l1_gas_price BLOB,
+            eth_l1_data_gas_price BLOB,
+            strk_l1_data_gas_price BLOB,
+            fee_percentiles_wei BLOB NOT NULL,
+            fee_percentiles_fri BLOB NOT NULL
+        );",
+    )
+    .context("Creating block fee stats table")?;
+
+    tracing::info!("Computing fee statistics of recent blocks");
+
+    let mut query_statement = tx.prepare(
+        r"SELECT block_headers.number, transactions.transactions
+        FROM block_headers
+        LEFT JOIN transactions ON transactions.block_number = block_headers.number
+        ORDER BY block_headers.number DESC
+        LIMIT ?",
+    )?;
+
+    let mut insert_statement = tx.prepare(
+        r"INSERT OR REPLACE INTO block_fee_stats (
+            block_number,
+            eth_l1_gas_price,
+            strk_l1_gas_price,
+            eth_l1_data_gas_price,
+            strk_l1_data_gas_price,
+            fee_percentiles_wei,
+            fee_percentiles_fri
+        )
+        SELECT
+            number,
+            eth_l1_gas_price,
+            strk_l1_gas_price,
+            eth_l1_data_gas_price,
+            strk_l1_data_gas_price,
+            :fee_percentiles_wei,
+            :fee_percentiles_fri
+        FROM block_headers WHERE number = :block_number",
+    )?;
+
+    let mut rows = query_statement.query([BACKFILLED_BLOCKS])?;
+
+    let mut progress_logged = Instant::now();
+    const LOG_RATE: Duration = Duration::from_secs(10);
+
+    while let Some(row) = rows.next().context("Fetching next block of transactions")? {
+        let block_number = row.get_block_number(0)?;
+        let transactions = row.get_optional_blob(1)?;
+
+        if progress_logged.elapsed() > LOG_RATE {
+            tracing::debug!(%block_number, "Computing fee statistics");
+            progress_logged = Instant::now();
+        }
+
+        let transactions = match transactions {
+            Some(transactions) => {
+                let transactions = compression::decompress_transactions(transactions)
+                    .context("Decompressing transactions")?;
+                let transactions: dto::TransactionsWithReceiptsForBlock =
+                    bincode::serde::decode_from_slice(&transactions, bincode::config::standard())
+                        .context("Deserializing transactions")?
+                        .0;
+                transactions.transactions_with_receipts()
+            }
+            None => Vec::new(),
+        };
+
+        let (wei, fri) = encoded_fee_percentiles(transactions.into_iter().map(|t| {
+            let transaction = Transaction::from(t.transaction);
+            (
+                transaction.variant.kind(),
+                transaction.version(),
+                Receipt::from(t.receipt).actual_fee,
+            )
+        }))?;
+
+        insert_statement
+            .execute(named_params![
+                ":block_number": &block_number,
+                ":fee_percentiles_wei": &wei,
+                ":fee_percentiles_fri": &fri,
+            ])
+            .context("Inserting fee stats")?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,21 @@
+use anyhow::Context;
+
+/// Adds the sync checkpoint to `refs`, starting from the latest block.
+pub(crate) fn migrate(tx: &rusqlite::Transaction<'_>) -> anyhow::Result<()> {
+    tx.execute_batch(
+        r"ALTER TABLE refs ADD COLUMN sync_checkpoint_number INTEGER;
+        ALTER TABLE refs ADD COLUMN sync_checkpoint_hash BLOB;",
+    )
+    .context("Adding sync checkpoint columns")?;
+
+    tx.execute(
+        r"UPDATE refs SET
+            sync_checkpoint_number = (SELECT number FROM block_headers ORDER BY number DESC LIMIT 1),
+            sync_checkpoint_hash = (SELECT hash FROM block_headers ORDER BY number DESC LIMIT 1)
+        WHERE idx = 1",
+        [],
+    )
+    .context("Setting sync checkpoint to the latest block")?;
+
+    Ok(())
+}
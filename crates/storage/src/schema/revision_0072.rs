@@ -0,0 +1,30 @@
+use anyhow::Context;
+
+/// Adds the `l1_to_l2_messages` table, which tracks the L1 transactions sending
+/// and cancelling L1 -> L2 messages, and the `l1_handler_messages` table, which
+/// lists the L1 handler transactions consuming each message. The latter is
+/// filled from the stored transactions by a background migration, see
+/// [revision_0073](super::revision_0073).
+pub(crate) fn migrate(tx: &rusqlite::Transaction<'_>) -> anyhow::Result<()> {
+    tx.execute_batch(
+        r"
+        CREATE TABLE l1_to_l2_messages (
+            message_hash BLOB PRIMARY KEY,
+            sent_tx_hash BLOB,
+            sent_block_number INTEGER,
+            cancelled_tx_hash BLOB,
+            cancelled_block_number INTEGER
+        );
+        CREATE TABLE l1_handler_messages (
+            message_hash BLOB NOT NULL,
+            block_number INTEGER NOT NULL,
+            idx INTEGER NOT NULL,
+            PRIMARY KEY (message_hash, block_number, idx)
+        ) WITHOUT ROWID;
+        CREATE INDEX l1_handler_messages_block_number ON l1_handler_messages(block_number);
+    ",
+    )
+    .context("Creating L1 to L2 message tables")?;
+
+    Ok(())
+}
@@ -0,0 +1,84 @@
+use std::ops::RangeInclusive;
+
+use anyhow::Context;
+use pathfinder_common::transaction::{Transaction, TransactionVariant};
+use pathfinder_common::BlockNumber;
+
+use crate::background_migration;
+use crate::connection::transaction::{compression, dto};
+use crate::params::{params, RowExt};
+
+/// The name of the background migration filling `l1_handler_messages`.
+pub(crate) const L1_HANDLER_MESSAGES: &str = "l1_handler_messages";
+
+/// Adds the `background_migrations` table, which tracks the progress of
+/// [background migrations](crate::background_migration), and registers the
+/// first ones, which index the existing blocks' events by contract address,
+/// their transactions by sender and their L1 handler transactions.
+pub(crate) fn migrate(tx: &rusqlite::Transaction<'_>) -> anyhow::Result<()> {
+    tx.execute(
+        r"CREATE TABLE background_migrations (
+            name TEXT PRIMARY KEY,
+            next_block INTEGER NOT NULL,
+            last_block INTEGER NOT NULL
+        )",
+        [],
+    )
+    .context("Creating background_migrations table")?;
+
+    background_migration::register(tx, super::revision_0066::CONTRACT_EVENT_BLOCKS)?;
+    background_migration::register(tx, super::revision_0067::TRANSACTION_SENDERS)?;
+    background_migration::register(tx, L1_HANDLER_MESSAGES)
+}
+
+/// Indexes the L1 handler transactions of `blocks` by message hash.
+pub(crate) fn index_l1_handler_messages(
+    tx: &rusqlite::Transaction<'_>,
+    blocks: RangeInclusive<BlockNumber>,
+) -> anyhow::Result<()> {
+    let mut query_statement = tx.prepare_cached(
+        r"SELECT block_number, transactions
+        FROM transactions
+        WHERE block_number BETWEEN ? AND ?
+        ORDER BY block_number",
+    )?;
+
+    // Blocks inserted after the schema migration are already indexed.
+    let mut insert_statement = tx.prepare_cached(
+        r"INSERT OR IGNORE INTO l1_handler_messages (message_hash, block_number, idx)
+        VALUES (?, ?, ?)",
+    )?;
+
+    let mut rows = query_statement.query(params![blocks.start(), blocks.end()])?;
+
+    while let Some(row) = rows.next().context("Fetching next block of transactions")? {
+        let block_number = row.get_block_number(0)?;
+        let transactions = row.get_blob(1)?;
+
+        let transactions = compression::decompress_transactions(transactions)
+            .context("Decompressing transactions")?;
+        let transactions: dto::TransactionsWithReceiptsForBlock =
+            bincode::serde::decode_from_slice(&transactions, bincode::config::standard())
+                .context("Deserializing transactions")?
+                .0;
+
+        for (idx, transaction) in transactions
+            .transactions_with_receipts()
+            .into_iter()
+            .enumerate()
+        {
+            let transaction = Transaction::from(transaction.transaction);
+            let TransactionVariant::L1Handler(l1_handler) = transaction.variant else {
+                continue;
+            };
+
+            let message_hash = l1_handler.calculate_message_hash();
+            let idx: i64 = idx.try_into()?;
+            insert_statement
+                .execute(params![&message_hash.as_bytes(), &block_number, &idx])
+                .context("Inserting L1 handler message")?;
+        }
+    }
+
+    Ok(())
+}
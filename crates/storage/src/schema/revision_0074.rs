@@ -0,0 +1,140 @@
+use std::collections::{HashMap, HashSet};
+use std::ops::RangeInclusive;
+
+use anyhow::Context;
+use pathfinder_common::receipt::Receipt;
+use pathfinder_common::transaction::{Transaction, TransactionVariant};
+use pathfinder_common::BlockNumber;
+
+use crate::background_migration;
+use crate::connection::block_stats::{
+    insert_state_diff_stats,
+    insert_transaction_stats,
+    transaction_stats,
+    StateDiffCounts,
+};
+use crate::connection::transaction::{compression, dto};
+use crate::params::{named_params, params, RowExt};
+
+/// The name of the background migration filling `block_stats`.
+pub(crate) const BLOCK_STATS: &str = "block_stats";
+
+/// Adds the `block_stats` table, recording aggregates of each block's
+/// transactions and state diff, and registers a background migration filling
+/// it for the existing blocks.
+pub(crate) fn migrate(tx: &rusqlite::Transaction<'_>) -> anyhow::Result<()> {
+    tx.execute(
+        r"CREATE TABLE block_stats (
+            block_number INTEGER PRIMARY KEY,
+            declare_count INTEGER NOT NULL DEFAULT 0,
+            deploy_count INTEGER NOT NULL DEFAULT 0,
+            deploy_account_count INTEGER NOT NULL DEFAULT 0,
+            invoke_count INTEGER NOT NULL DEFAULT 0,
+            l1_handler_count INTEGER NOT NULL DEFAULT 0,
+            total_fee_wei BLOB,
+            total_fee_fri BLOB,
+            storage_diff_count INTEGER NOT NULL DEFAULT 0,
+            nonce_update_count INTEGER NOT NULL DEFAULT 0,
+            declared_class_count INTEGER NOT NULL DEFAULT 0,
+            deployed_contract_count INTEGER NOT NULL DEFAULT 0,
+            replaced_class_count INTEGER NOT NULL DEFAULT 0
+        )",
+        [],
+    )
+    .context("Creating block_stats table")?;
+
+    background_migration::register(tx, BLOCK_STATS)
+}
+
+/// Computes the aggregates of `blocks` from the stored transactions and state
+/// updates.
+///
+/// Declared classes are counted from the block's declare transactions, so that
+/// Cairo 0 classes implicitly declared by their first deployment are left out,
+/// as they are when the aggregates are recorded during sync. The state diff
+/// counts of blocks whose history was pruned using `--storage.prune-history`
+/// are incomplete.
+pub(crate) fn compute_block_stats(
+    tx: &rusqlite::Transaction<'_>,
+    blocks: RangeInclusive<BlockNumber>,
+) -> anyhow::Result<()> {
+    let mut transactions_statement = tx.prepare_cached(
+        r"SELECT block_number, transactions
+        FROM transactions
+        WHERE block_number BETWEEN ? AND ?
+        ORDER BY block_number",
+    )?;
+
+    let mut declared_classes = HashMap::new();
+    let mut rows = transactions_statement.query(params![blocks.start(), blocks.end()])?;
+    while let Some(row) = rows.next().context("Fetching next block of transactions")? {
+        let block_number = row.get_block_number(0)?;
+        let transactions = row.get_blob(1)?;
+
+        let transactions = compression::decompress_transactions(transactions)
+            .context("Decompressing transactions")?;
+        let transactions: dto::TransactionsWithReceiptsForBlock =
+            bincode::serde::decode_from_slice(&transactions, bincode::config::standard())
+                .context("Deserializing transactions")?
+                .0;
+        let transactions = transactions
+            .transactions_with_receipts()
+            .into_iter()
+            .map(|t| (Transaction::from(t.transaction), Receipt::from(t.receipt)))
+            .collect::<Vec<_>>();
+
+        let declared = transactions
+            .iter()
+            .filter(|(_, receipt)| !receipt.is_reverted())
+            .filter_map(|(transaction, _)| match &transaction.variant {
+                TransactionVariant::DeclareV0(t) | TransactionVariant::DeclareV1(t) => {
+                    Some(t.class_hash)
+                }
+                TransactionVariant::DeclareV2(t) => Some(t.class_hash),
+                TransactionVariant::DeclareV3(t) => Some(t.class_hash),
+                _ => None,
+            })
+            .collect::<HashSet<_>>();
+        declared_classes.insert(block_number, declared.len() as u64);
+
+        let (counts, fee_wei, fee_fri) = transaction_stats(&transactions);
+        insert_transaction_stats(tx, block_number, &counts, fee_wei, fee_fri)?;
+    }
+
+    let mut state_diff_statement = tx.prepare_cached(
+        r"SELECT
+            (SELECT COUNT(*) FROM storage_updates WHERE block_number = :block_number),
+            (SELECT COUNT(*) FROM nonce_updates WHERE block_number = :block_number),
+            (SELECT COUNT(*) FROM contract_updates AS new
+                WHERE new.block_number = :block_number AND NOT EXISTS (
+                    SELECT 1 FROM contract_updates AS old
+                    WHERE old.contract_address = new.contract_address
+                        AND old.block_number < new.block_number
+                )),
+            (SELECT COUNT(*) FROM contract_updates WHERE block_number = :block_number)",
+    )?;
+
+    for block_number in blocks.start().get()..=blocks.end().get() {
+        let block_number = BlockNumber::new_or_panic(block_number);
+        let counts = state_diff_statement
+            .query_row(named_params![":block_number": &block_number], |row| {
+                let count = |index: usize| row.get_i64(index).map(|c| c as u64);
+                let deployed = count(2)?;
+                Ok(StateDiffCounts {
+                    storage_diffs: count(0)?,
+                    nonce_updates: count(1)?,
+                    declared_classes: declared_classes
+                        .get(&block_number)
+                        .copied()
+                        .unwrap_or_default(),
+                    deployed_contracts: deployed,
+                    replaced_classes: count(3)? - deployed,
+                })
+            })
+            .context("Querying state diff counts")?;
+
+        insert_state_diff_stats(tx, block_number, &counts)?;
+    }
+
+    Ok(())
+}
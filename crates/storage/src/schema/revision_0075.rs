@@ -0,0 +1,7 @@
+use anyhow::Context;
+
+/// Adds the L1 state update backfill target to `refs`.
+pub(crate) fn migrate(tx: &rusqlite::Transaction<'_>) -> anyhow::Result<()> {
+    tx.execute_batch("ALTER TABLE refs ADD COLUMN l1_backfill_target INTEGER;")
+        .context("Adding L1 backfill target column")
+}
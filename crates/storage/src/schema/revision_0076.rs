@@ -0,0 +1,7 @@
+use anyhow::Context;
+
+/// Adds the trie node generation to `refs`.
+pub(crate) fn migrate(tx: &rusqlite::Transaction<'_>) -> anyhow::Result<()> {
+    tx.execute_batch("ALTER TABLE refs ADD COLUMN trie_generation INTEGER NOT NULL DEFAULT 0;")
+        .context("Adding trie generation column")
+}
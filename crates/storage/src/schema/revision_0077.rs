@@ -0,0 +1,12 @@
+use anyhow::Context;
+
+/// Adds the L2 gas prices introduced in Starknet 0.13.4 to `block_headers`.
+pub(crate) fn migrate(tx: &rusqlite::Transaction<'_>) -> anyhow::Result<()> {
+    tx.execute_batch(
+        r"
+ALTER TABLE block_headers ADD COLUMN eth_l2_gas_price BLOB DEFAULT NULL;
+ALTER TABLE block_headers ADD COLUMN strk_l2_gas_price BLOB DEFAULT NULL;
+",
+    )
+    .context("Adding L2 gas price columns to block_headers")
+}
@@ -0,0 +1,15 @@
+use anyhow::Context;
+
+/// Removes the gas prices from `block_fee_stats`, which are read from
+/// `block_headers` instead.
+pub(crate) fn migrate(tx: &rusqlite::Transaction<'_>) -> anyhow::Result<()> {
+    tx.execute_batch(
+        r"
+ALTER TABLE block_fee_stats DROP COLUMN eth_l1_gas_price;
+ALTER TABLE block_fee_stats DROP COLUMN strk_l1_gas_price;
+ALTER TABLE block_fee_stats DROP COLUMN eth_l1_data_gas_price;
+ALTER TABLE block_fee_stats DROP COLUMN strk_l1_data_gas_price;
+",
+    )
+    .context("Removing gas price columns from block_fee_stats")
+}
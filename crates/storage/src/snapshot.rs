@@ -0,0 +1,346 @@
+//! Export and import of compressed database snapshots.
+//!
+//! A snapshot is a zstd compressed copy of the complete SQLite database file,
+//! and can be used to bootstrap a new node instead of syncing from genesis.
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use pathfinder_common::{BlockHash, BlockNumber};
+use rusqlite::backup::Backup;
+use rusqlite::{OpenFlags, OptionalExtension};
+
+use crate::params::RowExt;
+use crate::{verify_state_commitments, BlockId, Storage, TrieBackend};
+
+/// The zstd compression level used for snapshots.
+const COMPRESSION_LEVEL: i32 = 3;
+
+/// Writes a compressed snapshot of the database at `database` to `snapshot`.
+///
+/// The database is copied using SQLite's online backup API within a single
+/// read transaction, so the snapshot is consistent even if a running node is
+/// writing to the database at the same time. The copy is staged in a temporary
+/// file next to `snapshot` before being compressed, so exporting needs free
+/// space of twice the database size next to `snapshot`: once for the staged
+/// copy, and up to once more for the snapshot itself. This is checked up front.
+///
+/// Returns the latest block of the snapshot, which is [None] if the database
+/// has no blocks yet.
+pub fn export_snapshot(
+    database: &Path,
+    snapshot: &Path,
+) -> anyhow::Result<Option<(BlockNumber, BlockHash)>> {
+    anyhow::ensure!(
+        !snapshot.exists(),
+        "Snapshot file {} already exists",
+        snapshot.display()
+    );
+
+    let source = rusqlite::Connection::open_with_flags(
+        database,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )
+    .context("Opening database")?;
+
+    let tries_in_rocksdb = source
+        .query_row(
+            "SELECT 1 FROM storage_flags WHERE flag = ?",
+            [TrieBackend::ROCKSDB_FLAG],
+            |_| Ok(()),
+        )
+        .optional()
+        .context("Querying trie backend")?
+        .is_some();
+    anyhow::ensure!(
+        !tries_in_rocksdb,
+        "Snapshots of databases storing their Merkle tries in RocksDB are not supported"
+    );
+
+    ensure_free_space(database, snapshot)?;
+
+    let staging = TempFile(with_suffix(snapshot, ".sqlite.tmp"));
+    let mut copy = rusqlite::Connection::open(&staging.0).context("Creating staging database")?;
+
+    tracing::info!(database=%database.display(), "Copying database");
+    {
+        let backup = Backup::new(&source, &mut copy).context("Starting database backup")?;
+        // Copying all pages in one step keeps the source read transaction open for
+        // the whole copy. This gives a consistent copy instead of restarting the
+        // backup every time the node commits a new block.
+        backup.step(-1).context("Copying database")?;
+    }
+    let latest = copy
+        .query_row(
+            "SELECT number, hash FROM block_headers ORDER BY number DESC LIMIT 1",
+            [],
+            |row| Ok((row.get_block_number(0)?, row.get_block_hash(1)?)),
+        )
+        .optional()
+        .context("Querying latest block of snapshot")?;
+    drop(copy);
+    drop(source);
+
+    tracing::info!(snapshot=%snapshot.display(), "Compressing snapshot");
+    let partial = TempFile(with_suffix(snapshot, ".partial"));
+    let mut reader = BufReader::new(File::open(&staging.0).context("Opening staging database")?);
+    let writer = BufWriter::new(File::create(&partial.0).context("Creating snapshot file")?);
+    let mut encoder =
+        zstd::Encoder::new(writer, COMPRESSION_LEVEL).context("Creating zstd encoder")?;
+    std::io::copy(&mut reader, &mut encoder).context("Compressing snapshot")?;
+    encoder
+        .finish()
+        .context("Finishing snapshot compression")?
+        .into_inner()
+        .map_err(|e| e.into_error())
+        .context("Flushing snapshot file")?
+        .sync_all()
+        .context("Syncing snapshot file")?;
+
+    std::fs::rename(&partial.0, snapshot).context("Moving snapshot into place")?;
+
+    Ok(latest)
+}
+
+/// Fails unless the file system holding `snapshot` has room for the staged copy
+/// of `database` and the compressed snapshot, which is at most as large as the
+/// database.
+fn ensure_free_space(database: &Path, snapshot: &Path) -> anyhow::Result<()> {
+    let mut database_size = std::fs::metadata(database)
+        .context("Querying database size")?
+        .len();
+    // Pages in the WAL end up in the copy as well.
+    if let Ok(wal) = std::fs::metadata(with_suffix(database, "-wal")) {
+        database_size += wal.len();
+    }
+    let required = 2 * database_size;
+
+    let directory = snapshot
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let Some(available) = available_space(directory)? else {
+        return Ok(());
+    };
+
+    anyhow::ensure!(
+        available >= required,
+        "Exporting a snapshot needs {required} bytes of free space in {}, but only {available} \
+         bytes are available",
+        directory.display()
+    );
+
+    Ok(())
+}
+
+/// Returns the space available to unprivileged users on the file system holding
+/// `directory`, or [None] where this cannot be queried.
+#[cfg(unix)]
+fn available_space(directory: &Path) -> anyhow::Result<Option<u64>> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = std::ffi::CString::new(directory.as_os_str().as_bytes())
+        .context("Converting directory path")?;
+    let mut stat = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: `path` is NUL terminated and `stat` is valid for writes.
+    if unsafe { libc::statvfs(path.as_ptr(), stat.as_mut_ptr()) } != 0 {
+        return Err(std::io::Error::last_os_error()).context("Querying free disk space");
+    }
+    // SAFETY: statvfs succeeded, so it initialized `stat`.
+    let stat = unsafe { stat.assume_init() };
+
+    // The field types differ between platforms.
+    #[allow(clippy::useless_conversion)]
+    let available = u64::from(stat.f_bavail) * u64::from(stat.f_frsize);
+    Ok(Some(available))
+}
+
+#[cfg(not(unix))]
+fn available_space(_directory: &Path) -> anyhow::Result<Option<u64>> {
+    Ok(None)
+}
+
+/// Restores the snapshot at `snapshot` into a new database at `database`.
+///
+/// The snapshot is decompressed into a temporary file next to `database` and
+/// checked for integrity before it is moved into place. Fails if `database`
+/// already exists.
+pub fn import_snapshot(snapshot: &Path, database: &Path) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        !database.exists(),
+        "Database file {} already exists",
+        database.display()
+    );
+
+    tracing::info!(snapshot=%snapshot.display(), "Decompressing snapshot");
+    let staging = TempFile(with_suffix(database, ".partial"));
+    let reader = BufReader::new(File::open(snapshot).context("Opening snapshot file")?);
+    let mut decoder = zstd::Decoder::new(reader).context("Creating zstd decoder")?;
+    let mut writer = BufWriter::new(File::create(&staging.0).context("Creating database file")?);
+    std::io::copy(&mut decoder, &mut writer).context("Decompressing snapshot")?;
+    writer
+        .into_inner()
+        .map_err(|e| e.into_error())
+        .context("Flushing database file")?
+        .sync_all()
+        .context("Syncing database file")?;
+
+    tracing::info!("Verifying database integrity");
+    {
+        let connection =
+            rusqlite::Connection::open(&staging.0).context("Opening imported database")?;
+        let result: String = connection
+            .query_row("PRAGMA quick_check", [], |row| row.get(0))
+            .context("Checking database integrity")?;
+        anyhow::ensure!(result == "ok", "Snapshot is corrupt: {result}");
+    }
+
+    std::fs::rename(&staging.0, database).context("Moving database into place")?;
+
+    Ok(())
+}
+
+/// Verifies that `storage` was restored from a snapshot of the block
+/// `checkpoint`, returning its number.
+///
+/// The checkpoint must be the latest block in the snapshot, and its state
+/// commitment must match the one derived from the state tries, so that sync
+/// can safely continue from it.
+pub fn verify_checkpoint(storage: &Storage, checkpoint: BlockHash) -> anyhow::Result<BlockNumber> {
+    let (block_number, block_hash) = {
+        let mut db = storage
+            .connection()
+            .context("Opening database connection")?;
+        let tx = db.transaction().context("Creating database transaction")?;
+        tx.block_id(BlockId::Latest)
+            .context("Querying latest block")?
+            .context("Snapshot contains no blocks")?
+    };
+
+    anyhow::ensure!(
+        block_hash == checkpoint,
+        "Latest block {block_number} of the snapshot has hash {block_hash}, expected {checkpoint}"
+    );
+
+    if let Some(mismatch) = verify_state_commitments(storage, block_number, block_number)? {
+        anyhow::bail!(
+            "State commitment mismatch at checkpoint {block_number}: stored {}, derived from \
+             tries {}",
+            mismatch.stored,
+            mismatch.derived
+        );
+    }
+
+    Ok(block_number)
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(suffix);
+    path.into()
+}
+
+/// Removes the file on drop, if it has not been moved into place.
+struct TempFile(PathBuf);
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::macro_prelude::*;
+    use pathfinder_common::BlockHeader;
+
+    use super::*;
+    use crate::StorageBuilder;
+
+    #[test]
+    fn round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let database = dir.path().join("source.sqlite");
+        let snapshot = dir.path().join("snapshot.zst");
+        let restored = dir.path().join("restored.sqlite");
+
+        let header = BlockHeader::builder().finalize_with_hash(block_hash!("0xabc"));
+        let storage = StorageBuilder::file(database.clone())
+            .migrate()
+            .unwrap()
+            .create_pool(std::num::NonZeroU32::new(1).unwrap())
+            .unwrap();
+        let mut db = storage.connection().unwrap();
+        let tx = db.transaction().unwrap();
+        tx.insert_block_header(&header).unwrap();
+        tx.commit().unwrap();
+
+        // The source stays open, as it would for a running node.
+        let latest = export_snapshot(&database, &snapshot).unwrap();
+        assert_eq!(latest, Some((header.number, header.hash)));
+        import_snapshot(&snapshot, &restored).unwrap();
+
+        let storage = StorageBuilder::file(restored)
+            .migrate()
+            .unwrap()
+            .create_pool(std::num::NonZeroU32::new(1).unwrap())
+            .unwrap();
+        let mut db = storage.connection().unwrap();
+        let tx = db.transaction().unwrap();
+        let result = tx.block_header(crate::BlockId::Latest).unwrap();
+        assert_eq!(result, Some(header));
+
+        let leftovers = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .filter(|name| name.ends_with(".tmp") || name.ends_with(".partial"))
+            .collect::<Vec<_>>();
+        assert!(leftovers.is_empty(), "{leftovers:?}");
+    }
+
+    #[test]
+    fn checkpoint() {
+        let storage = StorageBuilder::in_memory().unwrap();
+        let mut db = storage.connection().unwrap();
+        let tx = db.transaction().unwrap();
+        let genesis = BlockHeader::builder().finalize_with_hash(block_hash!("0xabc"));
+        let head = genesis
+            .child_builder()
+            .finalize_with_hash(block_hash!("0xdef"));
+        tx.insert_block_header(&genesis).unwrap();
+        tx.insert_block_header(&head).unwrap();
+        tx.commit().unwrap();
+
+        let block_number = verify_checkpoint(&storage, block_hash!("0xdef")).unwrap();
+        assert_eq!(block_number, head.number);
+
+        // Only the latest block of the snapshot is accepted as checkpoint.
+        verify_checkpoint(&storage, block_hash!("0xabc")).unwrap_err();
+    }
+
+    #[test]
+    fn checkpoint_with_inconsistent_state() {
+        let storage = StorageBuilder::in_memory().unwrap();
+        let mut db = storage.connection().unwrap();
+        let tx = db.transaction().unwrap();
+        let header = BlockHeader::builder()
+            .state_commitment(state_commitment!("0x1234"))
+            .finalize_with_hash(block_hash!("0xabc"));
+        tx.insert_block_header(&header).unwrap();
+        tx.commit().unwrap();
+
+        verify_checkpoint(&storage, block_hash!("0xabc")).unwrap_err();
+    }
+
+    #[test]
+    fn import_refuses_to_overwrite() {
+        let dir = tempfile::tempdir().unwrap();
+        let database = dir.path().join("existing.sqlite");
+        std::fs::write(&database, b"").unwrap();
+
+        let result = import_snapshot(&dir.path().join("snapshot.zst"), &database);
+        assert!(result.is_err());
+    }
+}
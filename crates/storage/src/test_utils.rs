@@ -186,6 +186,22 @@ pub struct TestData {
     pub events: Vec<EmittedEvent>,
 }
 
+/// Registers all background migrations over the blocks currently stored, as
+/// if `storage` was a database upgraded from before they were added.
+pub fn register_background_migrations(storage: &crate::Storage) {
+    let mut connection = storage.connection().unwrap();
+    let tx = connection.transaction().unwrap();
+
+    tx.inner()
+        .execute("DELETE FROM background_migrations", [])
+        .unwrap();
+    for (name, _) in crate::schema::background_migrations() {
+        crate::background_migration::register(tx.inner(), name).unwrap();
+    }
+
+    tx.commit().unwrap();
+}
+
 // Creates a storage instance in memory with a set of expected emitted event
 pub fn setup_test_storage() -> (crate::Storage, TestData) {
     let storage = crate::StorageBuilder::in_memory().unwrap();
@@ -0,0 +1,238 @@
+//! Storage of Merkle trie nodes.
+//!
+//! Trie nodes make up the bulk of the writes during sync, and SQLite page churn
+//! on the trie tables is the main cause of database bloat. The nodes can
+//! therefore be kept in a separate key-value store, while everything else,
+//! including the trie roots and the removal markers used for pruning, remains
+//! in SQLite.
+
+#[cfg(feature = "rocksdb")]
+mod rocksdb;
+
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::Context;
+use pathfinder_crypto::Felt;
+
+use crate::prelude::*;
+
+/// Selects where trie nodes are stored.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TrieBackend {
+    /// In the `trie_class`, `trie_contracts` and `trie_storage` tables of the
+    /// SQLite database.
+    #[default]
+    Sqlite,
+    /// In a RocksDB database next to the SQLite database, see
+    /// [rocksdb_path]. Requires the `rocksdb` feature.
+    RocksDb,
+}
+
+impl TrieBackend {
+    /// The `storage_flags` entry marking a database whose trie nodes are stored
+    /// in RocksDB.
+    pub(crate) const ROCKSDB_FLAG: &'static str = "trie_backend_rocksdb";
+
+    pub(crate) fn open(self, database_path: &Path) -> anyhow::Result<Arc<dyn TrieNodes>> {
+        match self {
+            TrieBackend::Sqlite => Ok(Arc::new(SqliteTrieNodes)),
+            #[cfg(feature = "rocksdb")]
+            TrieBackend::RocksDb => {
+                let path = rocksdb_path(database_path);
+                let nodes = rocksdb::RocksDbTrieNodes::open(&path)
+                    .with_context(|| format!("Opening trie node database at {}", path.display()))?;
+                Ok(Arc::new(nodes))
+            }
+            #[cfg(not(feature = "rocksdb"))]
+            TrieBackend::RocksDb => {
+                let _ = database_path;
+                anyhow::bail!(
+                    "The RocksDB trie backend requires building with the `rocksdb` feature"
+                )
+            }
+        }
+    }
+}
+
+/// The directory of the RocksDB database holding the trie nodes of the SQLite
+/// database at `database_path`.
+pub fn rocksdb_path(database_path: &Path) -> std::path::PathBuf {
+    database_path.with_extension("tries")
+}
+
+/// Reads and writes the nodes of the trie tables.
+///
+/// `table` is one of `trie_class`, `trie_contracts` or `trie_storage`. Nodes
+/// are identified by an index assigned on insertion, which is what the trie
+/// roots and parent nodes refer to.
+pub(crate) trait TrieNodes: Send + Sync {
+    /// Returns the encoded [StoredNode](crate::StoredNode) with the given
+    /// index.
+    fn node(
+        &self,
+        tx: &Transaction<'_>,
+        table: &'static str,
+        index: u64,
+    ) -> anyhow::Result<Option<Vec<u8>>>;
+
+    /// Returns the hash of the node with the given index.
+    fn node_hash(
+        &self,
+        tx: &Transaction<'_>,
+        table: &'static str,
+        index: u64,
+    ) -> anyhow::Result<Option<Felt>>;
+
+    /// Stores `count` nodes, returning their indices in order. `node` returns
+    /// the hash and encoding of each node in turn, given the indices assigned
+    /// to the nodes before it, which it may refer to.
+    ///
+    /// The nodes must be durable once this returns, as the SQLite transaction
+    /// referring to them may be committed right away.
+    fn insert(
+        &self,
+        tx: &Transaction<'_>,
+        table: &'static str,
+        count: usize,
+        node: &mut dyn FnMut(&[u64]) -> anyhow::Result<(Felt, Vec<u8>)>,
+    ) -> anyhow::Result<Vec<u64>>;
+
+    /// Deletes the nodes with the given indices.
+    ///
+    /// If the indices may later be assigned to new nodes, the
+    /// [trie generation](Transaction::trie_generation) must be incremented.
+    fn delete(
+        &self,
+        tx: &Transaction<'_>,
+        table: &'static str,
+        indices: &[u64],
+    ) -> anyhow::Result<()>;
+
+    /// Calls `f` with the index of every stored node, in ascending order.
+    fn for_each_index(
+        &self,
+        tx: &Transaction<'_>,
+        table: &'static str,
+        f: &mut dyn FnMut(u64),
+    ) -> anyhow::Result<()>;
+}
+
+/// Stores trie nodes in the SQLite database itself.
+pub(crate) struct SqliteTrieNodes;
+
+impl TrieNodes for SqliteTrieNodes {
+    fn node(
+        &self,
+        tx: &Transaction<'_>,
+        table: &'static str,
+        index: u64,
+    ) -> anyhow::Result<Option<Vec<u8>>> {
+        // We rely on sqlite caching the statement here. Storing the statement would be
+        // nice, however that leads to &mut requirements or interior mutable
+        // work-arounds.
+        let mut stmt = tx
+            .inner()
+            .prepare_cached(&format!("SELECT data FROM {table} WHERE idx = ?"))
+            .context("Creating get statement")?;
+
+        stmt.query_row(params![&index], |row| row.get(0))
+            .optional()
+            .map_err(Into::into)
+    }
+
+    fn node_hash(
+        &self,
+        tx: &Transaction<'_>,
+        table: &'static str,
+        index: u64,
+    ) -> anyhow::Result<Option<Felt>> {
+        let mut stmt = tx
+            .inner()
+            .prepare_cached(&format!("SELECT hash FROM {table} WHERE idx = ?"))
+            .context("Creating get statement")?;
+
+        stmt.query_row(params![&index], |row| row.get_felt(0))
+            .optional()
+            .map_err(Into::into)
+    }
+
+    fn insert(
+        &self,
+        tx: &Transaction<'_>,
+        table: &'static str,
+        count: usize,
+        node: &mut dyn FnMut(&[u64]) -> anyhow::Result<(Felt, Vec<u8>)>,
+    ) -> anyhow::Result<Vec<u64>> {
+        let mut stmt = tx
+            .inner()
+            .prepare_cached(&format!(
+                "INSERT INTO {table} (hash, data) VALUES(?, ?) RETURNING idx",
+            ))
+            .context("Creating insert statement")?;
+
+        let mut indices = Vec::with_capacity(count);
+        for _ in 0..count {
+            let (hash, data) = node(&indices)?;
+            let index: u64 = stmt
+                .query_row(params![&hash.as_be_bytes().as_slice(), &data], |row| {
+                    row.get(0)
+                })
+                .context("Inserting node")?;
+            indices.push(index);
+        }
+
+        Ok(indices)
+    }
+
+    fn delete(
+        &self,
+        tx: &Transaction<'_>,
+        table: &'static str,
+        indices: &[u64],
+    ) -> anyhow::Result<()> {
+        let mut stmt = tx
+            .inner()
+            .prepare_cached(&format!(r"DELETE FROM {table} WHERE idx = ?"))
+            .context("Creating delete statement")?;
+        for index in indices {
+            stmt.execute(params![index]).context("Deleting node")?;
+        }
+
+        // SQLite assigns new nodes the index after the largest one remaining, so
+        // deleting the largest indices lets them be reused.
+        if let Some(&largest_deleted) = indices.iter().max() {
+            let largest_remaining: Option<u64> = tx
+                .inner()
+                .prepare_cached(&format!("SELECT max(idx) FROM {table}"))
+                .context("Creating max index statement")?
+                .query_row([], |row| row.get(0))
+                .context("Querying max index")?;
+
+            if largest_remaining.map_or(true, |remaining| remaining < largest_deleted) {
+                tx.increment_trie_generation()
+                    .context("Incrementing trie generation")?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn for_each_index(
+        &self,
+        tx: &Transaction<'_>,
+        table: &'static str,
+        f: &mut dyn FnMut(u64),
+    ) -> anyhow::Result<()> {
+        let mut stmt = tx
+            .inner()
+            .prepare(&format!("SELECT idx FROM {table} ORDER BY idx"))
+            .context("Creating select statement")?;
+        let mut rows = stmt.query([]).context("Querying indices")?;
+        while let Some(row) = rows.next().context("Iterating over rows")? {
+            f(row.get(0)?);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,162 @@
+//! Trie nodes stored in RocksDB, with one column family per trie table.
+//!
+//! Nodes are keyed by their big-endian index, and stored as their hash followed
+//! by their encoding. Indices are assigned from a per-table counter instead of
+//! by SQLite, which means nodes written by a transaction which is later rolled
+//! back are left behind unreferenced. These only take up space.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use anyhow::Context;
+use pathfinder_crypto::Felt;
+use rocksdb::{ColumnFamily, IteratorMode, Options, WriteBatch, WriteOptions, DB};
+
+use super::TrieNodes;
+use crate::Transaction;
+
+const TABLES: [&str; 3] = ["trie_class", "trie_contracts", "trie_storage"];
+
+pub(super) struct RocksDbTrieNodes {
+    db: DB,
+    /// The next index to assign in each table.
+    next_index: HashMap<&'static str, AtomicU64>,
+}
+
+impl RocksDbTrieNodes {
+    pub(super) fn open(path: &Path) -> anyhow::Result<Self> {
+        let mut options = Options::default();
+        options.create_if_missing(true);
+        options.create_missing_column_families(true);
+        let db = DB::open_cf(&options, path, TABLES)?;
+
+        let mut next_index = HashMap::new();
+        for table in TABLES {
+            let column = db.cf_handle(table).context("Column family missing")?;
+            let last_index = match db.iterator_cf(column, IteratorMode::End).next() {
+                Some(entry) => decode_index(&entry.context("Reading last node")?.0)?,
+                None => 0,
+            };
+            next_index.insert(table, AtomicU64::new(last_index + 1));
+        }
+
+        Ok(Self { db, next_index })
+    }
+
+    fn column(&self, table: &'static str) -> anyhow::Result<&ColumnFamily> {
+        self.db
+            .cf_handle(table)
+            .with_context(|| format!("Unknown trie table {table}"))
+    }
+
+    fn value(&self, table: &'static str, index: u64) -> anyhow::Result<Option<Vec<u8>>> {
+        self.db
+            .get_cf(self.column(table)?, index.to_be_bytes())
+            .context("Reading node")
+    }
+}
+
+impl TrieNodes for RocksDbTrieNodes {
+    fn node(
+        &self,
+        _: &Transaction<'_>,
+        table: &'static str,
+        index: u64,
+    ) -> anyhow::Result<Option<Vec<u8>>> {
+        let Some(mut value) = self.value(table, index)? else {
+            return Ok(None);
+        };
+        anyhow::ensure!(value.len() >= 32, "Node {index} in {table} is truncated");
+
+        Ok(Some(value.split_off(32)))
+    }
+
+    fn node_hash(
+        &self,
+        _: &Transaction<'_>,
+        table: &'static str,
+        index: u64,
+    ) -> anyhow::Result<Option<Felt>> {
+        let Some(value) = self.value(table, index)? else {
+            return Ok(None);
+        };
+        let hash = value
+            .get(..32)
+            .and_then(|hash| <[u8; 32]>::try_from(hash).ok())
+            .with_context(|| format!("Node {index} in {table} is truncated"))?;
+
+        Felt::from_be_bytes(hash)
+            .map(Some)
+            .context("Decoding node hash")
+    }
+
+    fn insert(
+        &self,
+        _: &Transaction<'_>,
+        table: &'static str,
+        count: usize,
+        node: &mut dyn FnMut(&[u64]) -> anyhow::Result<(Felt, Vec<u8>)>,
+    ) -> anyhow::Result<Vec<u64>> {
+        let column = self.column(table)?;
+        let first_index = self.next_index[table].fetch_add(count as u64, Ordering::Relaxed);
+
+        let indices = (first_index..first_index + count as u64).collect::<Vec<_>>();
+        let mut batch = WriteBatch::default();
+        for (i, index) in indices.iter().enumerate() {
+            let (hash, data) = node(&indices[..i])?;
+            let mut value = Vec::with_capacity(32 + data.len());
+            value.extend_from_slice(hash.as_be_bytes());
+            value.extend_from_slice(&data);
+            batch.put_cf(column, index.to_be_bytes(), value);
+        }
+
+        // The SQLite transaction referring to these nodes may be committed right
+        // after this, so they must not be lost on a crash.
+        let mut options = WriteOptions::default();
+        options.set_sync(true);
+        self.db
+            .write_opt(batch, &options)
+            .context("Inserting nodes")?;
+
+        Ok(indices)
+    }
+
+    fn delete(
+        &self,
+        _: &Transaction<'_>,
+        table: &'static str,
+        indices: &[u64],
+    ) -> anyhow::Result<()> {
+        // Pruned nodes are no longer referenced by any trie root that is kept, so
+        // these can be deleted even if the SQLite transaction is rolled back.
+        let column = self.column(table)?;
+        let mut batch = WriteBatch::default();
+        for index in indices {
+            batch.delete_cf(column, index.to_be_bytes());
+        }
+
+        self.db.write(batch).context("Deleting nodes")
+    }
+
+    fn for_each_index(
+        &self,
+        _: &Transaction<'_>,
+        table: &'static str,
+        f: &mut dyn FnMut(u64),
+    ) -> anyhow::Result<()> {
+        for entry in self
+            .db
+            .iterator_cf(self.column(table)?, IteratorMode::Start)
+        {
+            f(decode_index(&entry.context("Iterating over nodes")?.0)?);
+        }
+
+        Ok(())
+    }
+}
+
+fn decode_index(key: &[u8]) -> anyhow::Result<u64> {
+    let key = <[u8; 8]>::try_from(key).context("Invalid node key")?;
+    Ok(u64::from_be_bytes(key))
+}